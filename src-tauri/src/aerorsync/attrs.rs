@@ -0,0 +1,744 @@
+//! Owner, extended-attribute and POSIX ACL handling for `-o -g -X -A`.
+//!
+//! The wire layer (`real_wire`) only moves the file-list payloads
+//! around. This module is the bridge to the local file system on both
+//! ends:
+//!
+//! * the sender reads xattrs and ACLs into the wire shapes
+//!   (`read_xattrs`, `read_acl`), stripping from the access ACL what the
+//!   mode bits already carry, as `rsync_acl_strip_perms` does;
+//! * the receiver turns an entry into a `FileAttrs` (`IdMapper::attrs_for`)
+//!   and applies it to the temp file before the atomic rename, in the
+//!   order `set_file_attrs` uses: xattrs, owner, ACL. The mode and the
+//!   mtime stay with `StreamingAtomicWriter`, which runs them last.
+//!
+//! ACLs go through the `system.posix_acl_access` / `system.posix_acl_default`
+//! xattrs in the Linux on-disk format, so no libacl is needed. Elsewhere
+//! ACLs are read as empty and applying one is a no-op, like an rsync built
+//! without ACL support.
+//!
+//! Name filtering follows rsync: an unprivileged peer only handles the
+//! `user.*` namespace; root handles everything except the ACL xattrs,
+//! which travel through `-A` instead.
+
+#![cfg(feature = "aerorsync")]
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
+
+use crate::aerorsync::real_wire::{
+    AclIdAccess, EntryAcl, FileListEntry, RsyncAcl, XattrItem, XattrValue,
+};
+
+/// Xattr holding the access ACL on Linux.
+pub const ACL_ACCESS_XATTR: &str = "system.posix_acl_access";
+/// Xattr holding the default ACL of a directory on Linux.
+pub const ACL_DEFAULT_XATTR: &str = "system.posix_acl_default";
+
+/// `POSIX_ACL_XATTR_VERSION` and the entry tags of the xattr format.
+const POSIX_ACL_VERSION: u32 = 2;
+const ACL_USER_OBJ: u16 = 0x01;
+const ACL_USER: u16 = 0x02;
+const ACL_GROUP_OBJ: u16 = 0x04;
+const ACL_GROUP: u16 = 0x08;
+const ACL_MASK: u16 = 0x10;
+const ACL_OTHER: u16 = 0x20;
+const ACL_UNDEFINED_ID: u32 = u32::MAX;
+const POSIX_ACL_ENTRY_LEN: usize = 8;
+
+/// Metadata the receiver applies on top of content, mode and mtime.
+/// `None` fields are left alone.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileAttrs {
+    /// Local owner. Only applied when running as root, like rsync.
+    pub uid: Option<u32>,
+    /// Local group. Failures to change it are ignored (an unprivileged
+    /// receiver can only pick one of its own groups).
+    pub gid: Option<u32>,
+    /// The full xattr set: allowed names missing from it are removed.
+    pub xattrs: Option<BTreeMap<String, Vec<u8>>>,
+    /// Complete access ACL (mode-derived objects filled back in).
+    pub access_acl: Option<RsyncAcl>,
+    /// Default ACL; an empty one removes it. Directories only.
+    pub default_acl: Option<RsyncAcl>,
+}
+
+impl FileAttrs {
+    pub fn is_empty(&self) -> bool {
+        self.uid.is_none()
+            && self.gid.is_none()
+            && self.xattrs.is_none()
+            && self.access_acl.is_none()
+            && self.default_acl.is_none()
+    }
+
+    /// Apply to `path` without following a final symlink. Errors carry
+    /// the stage that failed (`"xattr"`, `"chown"`, `"acl"`).
+    pub fn apply(&self, path: &Path) -> Result<(), (&'static str, io::Error)> {
+        #[cfg(unix)]
+        {
+            let is_link = std::fs::symlink_metadata(path)
+                .map_err(|e| ("xattr", e))?
+                .file_type()
+                .is_symlink();
+            let privileged = is_privileged();
+            if let Some(want) = &self.xattrs {
+                // Linux refuses `user.*` xattrs on symlinks.
+                if !is_link || privileged {
+                    apply_xattrs(path, want, privileged).map_err(|e| ("xattr", e))?;
+                }
+            }
+            let uid = self.uid.filter(|_| privileged);
+            if uid.is_some() || self.gid.is_some() {
+                match std::os::unix::fs::lchown(path, uid, self.gid) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {}
+                    Err(e) => return Err(("chown", e)),
+                }
+            }
+            if !is_link {
+                apply_acls(path, self.access_acl.as_ref(), self.default_acl.as_ref())
+                    .map_err(|e| ("acl", e))?;
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = path;
+        Ok(())
+    }
+}
+
+/// Whether this process runs as root and may set any owner or xattr.
+pub fn is_privileged() -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: geteuid has no preconditions and cannot fail.
+        unsafe { libc::geteuid() == 0 }
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Whether an xattr `name` takes part in `-X`.
+pub fn xattr_name_allowed(name: &str, privileged: bool) -> bool {
+    if privileged {
+        name != ACL_ACCESS_XATTR && name != ACL_DEFAULT_XATTR
+    } else {
+        name.starts_with("user.")
+    }
+}
+
+/// The `-X` set of `path`, sorted by name, values in full. Symlinks
+/// are read without following them.
+pub fn read_xattrs(path: &Path) -> io::Result<Vec<XattrItem>> {
+    #[cfg(unix)]
+    {
+        let privileged = is_privileged();
+        let mut items = Vec::new();
+        let names = match xattr::list(path) {
+            Ok(names) => names,
+            Err(e) if is_unsupported(&e) => return Ok(items),
+            Err(e) => return Err(e),
+        };
+        for name in names {
+            let Some(name) = name.to_str() else { continue };
+            if !xattr_name_allowed(name, privileged) {
+                continue;
+            }
+            if let Some(value) = xattr::get(path, name)? {
+                items.push(XattrItem {
+                    name: name.to_string(),
+                    value: XattrValue::Full(value),
+                });
+            }
+        }
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(items)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(Vec::new())
+    }
+}
+
+/// The `-A` payload of `path`: the access ACL stripped against `mode`,
+/// plus the default ACL when `mode` is a directory.
+pub fn read_acl(path: &Path, mode: u32) -> io::Result<EntryAcl> {
+    let access = read_acl_xattr(path, ACL_ACCESS_XATTR)?.unwrap_or_default();
+    let default = if mode & 0o170000 == 0o040000 {
+        Some(read_acl_xattr(path, ACL_DEFAULT_XATTR)?.unwrap_or_default())
+    } else {
+        None
+    };
+    Ok(EntryAcl {
+        access: strip_access_acl(&access, mode),
+        default,
+    })
+}
+
+fn read_acl_xattr(path: &Path, name: &str) -> io::Result<Option<RsyncAcl>> {
+    #[cfg(target_os = "linux")]
+    {
+        match xattr::get(path, name) {
+            Ok(Some(blob)) => decode_posix_acl(&blob).map(Some).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("malformed {}", name))
+            }),
+            Ok(None) => Ok(None),
+            Err(e) if is_unsupported(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (path, name);
+        Ok(None)
+    }
+}
+
+/// `rsync_acl_strip_perms`: drop the access ACL objects the mode bits
+/// carry. With a mask the group bits are the mask, so the owning group
+/// only survives when it differs from them.
+pub fn strip_access_acl(acl: &RsyncAcl, mode: u32) -> RsyncAcl {
+    let group_bits = ((mode >> 3) & 7) as u8;
+    let mut stripped = acl.clone();
+    stripped.user_obj = None;
+    stripped.other_obj = None;
+    if acl.mask_obj.is_none() {
+        stripped.group_obj = None;
+    } else {
+        if acl.group_obj == Some(group_bits) {
+            stripped.group_obj = None;
+        }
+        if !acl.names.is_empty() && acl.mask_obj == Some(group_bits) {
+            stripped.mask_obj = None;
+        }
+    }
+    stripped
+}
+
+/// Inverse of `strip_access_acl`: fill the objects back in from `mode`.
+pub fn unstrip_access_acl(acl: &RsyncAcl, mode: u32) -> RsyncAcl {
+    let group_bits = ((mode >> 3) & 7) as u8;
+    let mut full = acl.clone();
+    full.user_obj = Some(((mode >> 6) & 7) as u8);
+    full.other_obj = Some((mode & 7) as u8);
+    if acl.mask_obj.is_some() || !acl.names.is_empty() {
+        full.mask_obj.get_or_insert(group_bits);
+        full.group_obj.get_or_insert(group_bits);
+    } else {
+        full.group_obj = Some(group_bits);
+    }
+    full
+}
+
+/// Whether an access ACL says no more than the mode bits.
+fn is_minimal(acl: &RsyncAcl) -> bool {
+    acl.names.is_empty() && acl.mask_obj.is_none()
+}
+
+/// Encode a complete ACL in the `system.posix_acl_*` xattr format.
+/// Entries come out in the order the kernel requires: by tag, then id.
+pub fn encode_posix_acl(acl: &RsyncAcl) -> Vec<u8> {
+    let mut entries: Vec<(u16, u32, u8)> = Vec::new();
+    if let Some(perm) = acl.user_obj {
+        entries.push((ACL_USER_OBJ, ACL_UNDEFINED_ID, perm));
+    }
+    for name in &acl.names {
+        let tag = if name.is_user { ACL_USER } else { ACL_GROUP };
+        entries.push((tag, name.id, name.access));
+    }
+    if let Some(perm) = acl.group_obj {
+        entries.push((ACL_GROUP_OBJ, ACL_UNDEFINED_ID, perm));
+    }
+    if let Some(perm) = acl.mask_obj {
+        entries.push((ACL_MASK, ACL_UNDEFINED_ID, perm));
+    }
+    if let Some(perm) = acl.other_obj {
+        entries.push((ACL_OTHER, ACL_UNDEFINED_ID, perm));
+    }
+    entries.sort_by_key(|&(tag, id, _)| (tag, id));
+
+    let mut blob = Vec::with_capacity(4 + entries.len() * POSIX_ACL_ENTRY_LEN);
+    blob.extend_from_slice(&POSIX_ACL_VERSION.to_le_bytes());
+    for (tag, id, perm) in entries {
+        blob.extend_from_slice(&tag.to_le_bytes());
+        blob.extend_from_slice(&u16::from(perm & 7).to_le_bytes());
+        blob.extend_from_slice(&id.to_le_bytes());
+    }
+    blob
+}
+
+/// Decode a `system.posix_acl_*` xattr. `None` for a wrong version,
+/// a truncated entry or an unknown tag.
+pub fn decode_posix_acl(blob: &[u8]) -> Option<RsyncAcl> {
+    let (version, body) = blob.split_first_chunk::<4>()?;
+    if u32::from_le_bytes(*version) != POSIX_ACL_VERSION || body.len() % POSIX_ACL_ENTRY_LEN != 0 {
+        return None;
+    }
+    let mut acl = RsyncAcl::default();
+    for entry in body.chunks_exact(POSIX_ACL_ENTRY_LEN) {
+        let tag = u16::from_le_bytes([entry[0], entry[1]]);
+        let perm = (u16::from_le_bytes([entry[2], entry[3]]) & 7) as u8;
+        let id = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+        match tag {
+            ACL_USER_OBJ => acl.user_obj = Some(perm),
+            ACL_GROUP_OBJ => acl.group_obj = Some(perm),
+            ACL_MASK => acl.mask_obj = Some(perm),
+            ACL_OTHER => acl.other_obj = Some(perm),
+            ACL_USER | ACL_GROUP => acl.names.push(AclIdAccess {
+                id,
+                is_user: tag == ACL_USER,
+                access: perm,
+                name: None,
+            }),
+            _ => return None,
+        }
+    }
+    Some(acl)
+}
+
+/// Make a default ACL from the wire valid for the kernel: objects it
+/// lacks come from `mode`, and named entries get a mask covering them.
+fn complete_default_acl(acl: &RsyncAcl, mode: u32) -> RsyncAcl {
+    let mut full = acl.clone();
+    full.user_obj.get_or_insert(((mode >> 6) & 7) as u8);
+    full.group_obj.get_or_insert(((mode >> 3) & 7) as u8);
+    full.other_obj.get_or_insert((mode & 7) as u8);
+    if !full.names.is_empty() && full.mask_obj.is_none() {
+        let class = full
+            .names
+            .iter()
+            .fold(full.group_obj.unwrap_or(0), |acc, n| acc | n.access);
+        full.mask_obj = Some(class);
+    }
+    full
+}
+
+#[cfg(unix)]
+fn apply_xattrs(path: &Path, want: &BTreeMap<String, Vec<u8>>, privileged: bool) -> io::Result<()> {
+    let current = match xattr::list(path) {
+        Ok(names) => names,
+        Err(e) if is_unsupported(&e) && want.is_empty() => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for name in current {
+        let Some(name) = name.to_str() else { continue };
+        if xattr_name_allowed(name, privileged) && !want.contains_key(name) {
+            remove_xattr(path, name)?;
+        }
+    }
+    for (name, value) in want {
+        if !xattr_name_allowed(name, privileged) {
+            continue;
+        }
+        if xattr::get(path, name)?.as_deref() != Some(value.as_slice()) {
+            xattr::set(path, name, value)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn apply_acls(
+    path: &Path,
+    access: Option<&RsyncAcl>,
+    default: Option<&RsyncAcl>,
+) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(access) = access {
+            if is_minimal(access) {
+                remove_xattr(path, ACL_ACCESS_XATTR)?;
+            } else {
+                xattr::set(path, ACL_ACCESS_XATTR, &encode_posix_acl(access))?;
+            }
+        }
+        if let Some(default) = default {
+            if *default == RsyncAcl::default() {
+                remove_xattr(path, ACL_DEFAULT_XATTR)?;
+            } else {
+                xattr::set(path, ACL_DEFAULT_XATTR, &encode_posix_acl(default))?;
+            }
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = (path, access, default);
+    Ok(())
+}
+
+/// Remove an xattr; one that is already gone, or a file system without
+/// xattrs, counts as done.
+#[cfg(unix)]
+fn remove_xattr(path: &Path, name: &str) -> io::Result<()> {
+    match xattr::remove(path, name) {
+        Ok(()) => Ok(()),
+        Err(e) if is_unsupported(&e) || is_missing_xattr(&e) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(unix)]
+fn is_missing_xattr(error: &io::Error) -> bool {
+    #[cfg(target_os = "linux")]
+    let missing = libc::ENODATA;
+    #[cfg(not(target_os = "linux"))]
+    let missing = libc::ENOATTR;
+    error.raw_os_error() == Some(missing)
+}
+
+fn is_unsupported(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        error.raw_os_error() == Some(libc::ENOTSUP) || error.kind() == io::ErrorKind::Unsupported
+    }
+    #[cfg(not(unix))]
+    {
+        error.kind() == io::ErrorKind::Unsupported
+    }
+}
+
+/// Maps the sender's uids and gids to local ones by name, as rsync does
+/// without `--numeric-ids`. A name travels with the first entry using
+/// an id, so the mapping is remembered per id. Ids without a name, or
+/// whose name does not exist here, stay numeric; id 0 is never mapped.
+#[derive(Debug, Default)]
+pub struct IdMapper {
+    users: HashMap<u32, u32>,
+    groups: HashMap<u32, u32>,
+}
+
+impl IdMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn map_uid(&mut self, id: u32, name: Option<&str>) -> u32 {
+        map_id(&mut self.users, id, name, lookup_uid_by_name)
+    }
+
+    pub fn map_gid(&mut self, id: u32, name: Option<&str>) -> u32 {
+        map_id(&mut self.groups, id, name, lookup_gid_by_name)
+    }
+
+    /// What the receiver applies for `entry`. Abbreviated xattr values
+    /// must have been fetched (turned into `XattrValue::Full`) first;
+    /// any left over are skipped.
+    pub fn attrs_for(&mut self, entry: &FileListEntry) -> FileAttrs {
+        let uid = entry
+            .uid
+            .and_then(|id| u32::try_from(id).ok())
+            .map(|id| self.map_uid(id, entry.uid_name.as_deref()));
+        let gid = entry
+            .gid
+            .and_then(|id| u32::try_from(id).ok())
+            .map(|id| self.map_gid(id, entry.gid_name.as_deref()));
+        let xattrs = entry.xattrs.as_ref().map(|items| {
+            items
+                .iter()
+                .filter_map(|item| match &item.value {
+                    XattrValue::Full(value) => Some((item.name.clone(), value.clone())),
+                    XattrValue::Abbrev { .. } => None,
+                })
+                .collect()
+        });
+        let (access_acl, default_acl) = match &entry.acl {
+            Some(acl) => {
+                let access = unstrip_access_acl(&self.map_acl_names(&acl.access), entry.mode);
+                let default = acl.default.as_ref().map(|default| {
+                    if *default == RsyncAcl::default() {
+                        RsyncAcl::default()
+                    } else {
+                        complete_default_acl(&self.map_acl_names(default), entry.mode)
+                    }
+                });
+                (Some(access), default)
+            }
+            None => (None, None),
+        };
+        FileAttrs {
+            uid,
+            gid,
+            xattrs,
+            access_acl,
+            default_acl,
+        }
+    }
+
+    fn map_acl_names(&mut self, acl: &RsyncAcl) -> RsyncAcl {
+        let mut mapped = acl.clone();
+        for entry in &mut mapped.names {
+            entry.id = if entry.is_user {
+                self.map_uid(entry.id, entry.name.as_deref())
+            } else {
+                self.map_gid(entry.id, entry.name.as_deref())
+            };
+        }
+        mapped
+    }
+}
+
+fn map_id(
+    cache: &mut HashMap<u32, u32>,
+    id: u32,
+    name: Option<&str>,
+    lookup: fn(&str) -> Option<u32>,
+) -> u32 {
+    if id == 0 {
+        return 0;
+    }
+    if let Some(name) = name {
+        let local = lookup(name).unwrap_or(id);
+        cache.insert(id, local);
+        return local;
+    }
+    cache.get(&id).copied().unwrap_or(id)
+}
+
+/// `getpwnam_r`; `None` when the user does not exist here.
+fn lookup_uid_by_name(name: &str) -> Option<u32> {
+    #[cfg(unix)]
+    unsafe {
+        let name = std::ffi::CString::new(name).ok()?;
+        let mut pwd: libc::passwd = std::mem::zeroed();
+        let mut buf = [0 as libc::c_char; 1024];
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+        let rc = libc::getpwnam_r(
+            name.as_ptr(),
+            &mut pwd,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        );
+        if rc == 0 && !result.is_null() {
+            return Some(pwd.pw_uid);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = name;
+    None
+}
+
+/// `getgrnam_r`; `None` when the group does not exist here.
+fn lookup_gid_by_name(name: &str) -> Option<u32> {
+    #[cfg(unix)]
+    unsafe {
+        let name = std::ffi::CString::new(name).ok()?;
+        let mut grp: libc::group = std::mem::zeroed();
+        let mut buf = [0 as libc::c_char; 4096];
+        let mut result: *mut libc::group = std::ptr::null_mut();
+        let rc = libc::getgrnam_r(
+            name.as_ptr(),
+            &mut grp,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        );
+        if rc == 0 && !result.is_null() {
+            return Some(grp.gr_gid);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = name;
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(id: u32, is_user: bool, access: u8) -> AclIdAccess {
+        AclIdAccess {
+            id,
+            is_user,
+            access,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn minimal_access_acl_strips_to_nothing() {
+        let full = RsyncAcl {
+            user_obj: Some(6),
+            group_obj: Some(4),
+            mask_obj: None,
+            other_obj: Some(4),
+            names: Vec::new(),
+        };
+        let stripped = strip_access_acl(&full, 0o100644);
+        assert_eq!(stripped, RsyncAcl::default());
+        assert_eq!(unstrip_access_acl(&stripped, 0o100644), full);
+    }
+
+    #[test]
+    fn extended_access_acl_keeps_what_the_mode_cannot_say() {
+        // user:1001:rwx, group::r-x, mask::rwx → mode group bits are the mask.
+        let full = RsyncAcl {
+            user_obj: Some(7),
+            group_obj: Some(5),
+            mask_obj: Some(7),
+            other_obj: Some(0),
+            names: vec![named(1001, true, 7)],
+        };
+        let stripped = strip_access_acl(&full, 0o100770);
+        assert_eq!(
+            stripped,
+            RsyncAcl {
+                group_obj: Some(5),
+                names: vec![named(1001, true, 7)],
+                ..RsyncAcl::default()
+            }
+        );
+        assert_eq!(unstrip_access_acl(&stripped, 0o100770), full);
+    }
+
+    #[test]
+    fn posix_acl_blob_round_trips_in_kernel_order() {
+        let acl = RsyncAcl {
+            user_obj: Some(7),
+            group_obj: Some(5),
+            mask_obj: Some(7),
+            other_obj: Some(0),
+            names: vec![
+                named(1002, false, 6),
+                named(1001, true, 7),
+                named(1000, true, 4),
+            ],
+        };
+        let blob = encode_posix_acl(&acl);
+        assert_eq!(blob.len(), 4 + 7 * POSIX_ACL_ENTRY_LEN);
+        assert_eq!(&blob[..4], &2u32.to_le_bytes());
+        let tags: Vec<u16> = blob[4..]
+            .chunks_exact(POSIX_ACL_ENTRY_LEN)
+            .map(|e| u16::from_le_bytes([e[0], e[1]]))
+            .collect();
+        assert_eq!(tags, [1, 2, 2, 4, 8, 0x10, 0x20]);
+
+        let decoded = decode_posix_acl(&blob).unwrap();
+        assert_eq!(decoded.user_obj, Some(7));
+        assert_eq!(decoded.mask_obj, Some(7));
+        assert_eq!(
+            decoded.names,
+            [
+                named(1000, true, 4),
+                named(1001, true, 7),
+                named(1002, false, 6)
+            ]
+        );
+        assert!(decode_posix_acl(&blob[..blob.len() - 1]).is_none());
+        assert!(decode_posix_acl(&[1, 0, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn default_acl_gets_base_objects_and_a_mask() {
+        let partial = RsyncAcl {
+            names: vec![named(1001, false, 6)],
+            ..RsyncAcl::default()
+        };
+        let full = complete_default_acl(&partial, 0o040750);
+        assert_eq!(full.user_obj, Some(7));
+        assert_eq!(full.group_obj, Some(5));
+        assert_eq!(full.other_obj, Some(0));
+        assert_eq!(full.mask_obj, Some(7));
+    }
+
+    #[test]
+    fn xattr_names_follow_the_rsync_namespace_rules() {
+        assert!(xattr_name_allowed("user.comment", false));
+        assert!(!xattr_name_allowed("security.selinux", false));
+        assert!(!xattr_name_allowed("trusted.x", false));
+        assert!(xattr_name_allowed("security.selinux", true));
+        assert!(xattr_name_allowed("trusted.x", true));
+        assert!(!xattr_name_allowed(ACL_ACCESS_XATTR, true));
+        assert!(!xattr_name_allowed(ACL_DEFAULT_XATTR, true));
+    }
+
+    #[test]
+    fn id_mapper_remembers_names_and_never_maps_root() {
+        let mut mapper = IdMapper::new();
+        assert_eq!(mapper.map_uid(0, Some("nobody")), 0);
+        // An unknown name keeps the numeric id.
+        assert_eq!(mapper.map_uid(4242, Some("aerorsync-no-such-user")), 4242);
+        // A name that exists here wins, and sticks to the remote id.
+        let local_root = lookup_uid_by_name("root");
+        if let Some(local) = local_root {
+            assert_eq!(mapper.map_uid(5000, Some("root")), local);
+            assert_eq!(mapper.map_uid(5000, None), local);
+        }
+        assert_eq!(mapper.map_gid(77, None), 77);
+    }
+
+    #[test]
+    fn attrs_for_unstrips_the_access_acl_and_skips_abbreviated_values() {
+        let mut entry = FileListEntry {
+            flags: 0,
+            path: "f".to_string(),
+            size: 0,
+            mtime: 0,
+            mtime_nsec: None,
+            mode: 0o100640,
+            uid: Some(1000),
+            uid_name: None,
+            gid: Some(1000),
+            gid_name: None,
+            checksum: Vec::new(),
+            link_target: None,
+            hardlink: None,
+            acl: Some(EntryAcl::default()),
+            xattrs: Some(vec![
+                XattrItem {
+                    name: "user.a".to_string(),
+                    value: XattrValue::Full(b"1".to_vec()),
+                },
+                XattrItem {
+                    name: "user.b".to_string(),
+                    value: XattrValue::Abbrev {
+                        len: 40,
+                        digest: [0; 16],
+                    },
+                },
+            ]),
+        };
+        let attrs = IdMapper::new().attrs_for(&entry);
+        assert_eq!(attrs.uid, Some(1000));
+        let access = attrs.access_acl.unwrap();
+        assert_eq!(
+            (access.user_obj, access.group_obj, access.other_obj),
+            (Some(6), Some(4), Some(0))
+        );
+        assert!(is_minimal(&access));
+        assert_eq!(attrs.xattrs.unwrap().keys().collect::<Vec<_>>(), ["user.a"]);
+
+        entry.acl = None;
+        entry.xattrs = None;
+        let attrs = IdMapper::new().attrs_for(&entry);
+        assert!(attrs.access_acl.is_none() && attrs.xattrs.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_replaces_the_user_xattr_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("f");
+        std::fs::write(&path, b"x").unwrap();
+        if xattr::set(&path, "user.old", b"1").is_err() {
+            // File system without user xattrs: nothing to check here.
+            return;
+        }
+        let attrs = FileAttrs {
+            xattrs: Some(BTreeMap::from([("user.new".to_string(), b"2".to_vec())])),
+            ..FileAttrs::default()
+        };
+        attrs.apply(&path).unwrap();
+        let items = read_xattrs(&path).unwrap();
+        assert_eq!(
+            items,
+            [XattrItem {
+                name: "user.new".to_string(),
+                value: XattrValue::Full(b"2".to_vec()),
+            }]
+        );
+    }
+}
@@ -0,0 +1,848 @@
+//! rsync batch files (`--write-batch` / `--read-batch`, `batch.c`).
+//!
+//! A batch is the sender's half of one transfer, recorded so the same
+//! update can be replayed later against other copies of the same basis
+//! without talking to the sender again: compute the delta once, carry
+//! the file on removable media, apply it to every identical replica.
+//!
+//! Layout, as stock rsync writes and reads it:
+//!
+//! ```text
+//!   int32    stream flags (`write_stream_flags`: -r -o -g -l -D -H -c ...)
+//!   int32    protocol version        \
+//!   varint   compat flags (30+)       } `start_write_batch`
+//!   int32    checksum seed           /
+//!   ...      the sender's demultiplexed data, file list onwards
+//!   varlong  × 5 stats (`handle_stats`)
+//!   ndx      NDX_DONE goodbye (31+)
+//! ```
+//!
+//! Recording happens in the driver (`TransferOptions::write_batch`): a
+//! client sender keeps what it writes, a client receiver what it reads,
+//! so both directions yield the same sender stream. The negotiated
+//! checksum and compressor are not part of the file; the companion
+//! `FILE.sh` passes them as `--checksum-choice` / `--compress-choice`,
+//! and [`apply_batch`] reads them back from there.
+//!
+//! A replay runs the regular tree receiver with the batch standing in
+//! for the sender, as `rsync --read-batch` points its `f_in` at the
+//! file: the generator still decides what it wants from the local copy,
+//! and a recorded file it does not want is decoded and dropped
+//! ("Skipping batched update"). Reconstructed files are verified
+//! against the recorded whole-file checksum before they are installed.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter, DuplexStream};
+
+use crate::aerorsync::checksum::ChecksumAlgo;
+use crate::aerorsync::compression::CompressionAlgo;
+use crate::aerorsync::daemon::{DaemonUnusedStream, IoRawStream};
+use crate::aerorsync::delta_transport_impl::LocalTreeRoot;
+use crate::aerorsync::engine_adapter::CurrentDeltaSyncBridge;
+use crate::aerorsync::events::{AerorsyncEvent, EventSink};
+use crate::aerorsync::local_transport::check_tree_root;
+use crate::aerorsync::native_driver::{AerorsyncDriver, SessionFlags};
+use crate::aerorsync::negotiation::AlgorithmPreferences;
+use crate::aerorsync::real_wire::{
+    decode_varint, encode_server_preamble, encode_varint, MuxHeader, MuxTag, RealWireError,
+    ServerPreamble,
+};
+use crate::aerorsync::remote_command::{AppendMode, RemoteCommandSpec, TransferOptions};
+use crate::aerorsync::shell_escape::shell_escape_posix;
+use crate::aerorsync::transport::{
+    CancelHandle, RawRemoteShellTransport, RemoteCommandOutput, RemoteExecRequest,
+    RemoteShellTransport, TransportProbe,
+};
+use crate::aerorsync::tree::TreeTransferReport;
+use crate::aerorsync::types::{AerorsyncError, AerorsyncErrorKind, ProtocolVersion};
+
+/// Suffix of the replay script written next to a batch.
+pub const BATCH_SCRIPT_SUFFIX: &str = ".sh";
+
+/// Bytes of batch data handed to the replay driver per `MSG_DATA` frame.
+const REPLAY_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Pipe buffer between the batch reader and the replay driver.
+const REPLAY_DUPLEX_BYTES: usize = 256 * 1024;
+
+/// Enough to hold the longest header: three int32 and a 5-byte varint.
+const HEADER_MAX_LEN: usize = 17;
+
+// `batch.c::flag_ptr` bit positions.
+const FLAG_RECURSE: i32 = 1 << 0;
+const FLAG_PRESERVE_UID: i32 = 1 << 1;
+const FLAG_PRESERVE_GID: i32 = 1 << 2;
+const FLAG_PRESERVE_LINKS: i32 = 1 << 3;
+const FLAG_PRESERVE_DEVICES: i32 = 1 << 4;
+const FLAG_PRESERVE_HARD_LINKS: i32 = 1 << 5;
+const FLAG_ALWAYS_CHECKSUM: i32 = 1 << 6;
+const FLAG_XFER_DIRS: i32 = 1 << 7;
+const FLAG_DO_COMPRESSION: i32 = 1 << 8;
+const FLAG_ICONV: i32 = 1 << 9;
+const FLAG_PRESERVE_ACLS: i32 = 1 << 10;
+const FLAG_PRESERVE_XATTRS: i32 = 1 << 11;
+const FLAG_INPLACE: i32 = 1 << 12;
+const FLAG_APPEND: i32 = 1 << 13;
+const FLAG_APPEND_VERIFY: i32 = 1 << 14;
+
+/// The options a batch records because its stream depends on them
+/// (`batch.c::write_stream_flags`). A replay takes them from the file,
+/// whatever its own command line says.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchStreamFlags {
+    pub recurse: bool,
+    pub preserve_uid: bool,
+    pub preserve_gid: bool,
+    pub preserve_links: bool,
+    pub preserve_devices: bool,
+    pub preserve_hard_links: bool,
+    pub always_checksum: bool,
+    pub xfer_dirs: bool,
+    /// Literals are compressed; which compressor is up to the script.
+    pub do_compression: bool,
+    pub preserve_acls: bool,
+    pub preserve_xattrs: bool,
+    pub inplace: bool,
+    pub append: AppendMode,
+}
+
+impl BatchStreamFlags {
+    /// Flags of a session run by this driver: our command line always
+    /// carries `-logDtprc`, the rest follows the options.
+    pub fn for_session(
+        flags: SessionFlags,
+        options: &TransferOptions,
+        compression: CompressionAlgo,
+    ) -> Self {
+        Self {
+            recurse: true,
+            preserve_uid: flags.preserve_uid,
+            preserve_gid: flags.preserve_gid,
+            preserve_links: flags.preserve_links,
+            preserve_devices: true,
+            preserve_hard_links: options.hard_links,
+            always_checksum: flags.always_checksum,
+            xfer_dirs: true,
+            do_compression: compression != CompressionAlgo::None,
+            preserve_acls: options.acls,
+            preserve_xattrs: options.xattrs,
+            inplace: options.writes_in_place(),
+            append: options.append,
+        }
+    }
+
+    pub fn to_bits(self) -> i32 {
+        let mut bits = 0;
+        for (on, bit) in [
+            (self.recurse, FLAG_RECURSE),
+            (self.preserve_uid, FLAG_PRESERVE_UID),
+            (self.preserve_gid, FLAG_PRESERVE_GID),
+            (self.preserve_links, FLAG_PRESERVE_LINKS),
+            (self.preserve_devices, FLAG_PRESERVE_DEVICES),
+            (self.preserve_hard_links, FLAG_PRESERVE_HARD_LINKS),
+            (self.always_checksum, FLAG_ALWAYS_CHECKSUM),
+            (self.xfer_dirs, FLAG_XFER_DIRS),
+            (self.do_compression, FLAG_DO_COMPRESSION),
+            (self.preserve_acls, FLAG_PRESERVE_ACLS),
+            (self.preserve_xattrs, FLAG_PRESERVE_XATTRS),
+            (self.inplace, FLAG_INPLACE),
+            (self.append == AppendMode::Append, FLAG_APPEND),
+            (self.append == AppendMode::AppendVerify, FLAG_APPEND_VERIFY),
+        ] {
+            if on {
+                bits |= bit;
+            }
+        }
+        bits
+    }
+
+    /// `read_stream_flags`. A batch written with `--iconv` is refused:
+    /// its names would need converting on the way in.
+    pub fn from_bits(bits: i32) -> Result<Self, AerorsyncError> {
+        if bits & FLAG_ICONV != 0 {
+            return Err(AerorsyncError::unsupported_version(
+                "batch was written with --iconv, which aerorsync does not support",
+            ));
+        }
+        let has = |bit: i32| bits & bit != 0;
+        let append = if has(FLAG_APPEND_VERIFY) {
+            AppendMode::AppendVerify
+        } else if has(FLAG_APPEND) {
+            AppendMode::Append
+        } else {
+            AppendMode::Off
+        };
+        Ok(Self {
+            recurse: has(FLAG_RECURSE),
+            preserve_uid: has(FLAG_PRESERVE_UID),
+            preserve_gid: has(FLAG_PRESERVE_GID),
+            preserve_links: has(FLAG_PRESERVE_LINKS),
+            preserve_devices: has(FLAG_PRESERVE_DEVICES),
+            preserve_hard_links: has(FLAG_PRESERVE_HARD_LINKS),
+            always_checksum: has(FLAG_ALWAYS_CHECKSUM),
+            xfer_dirs: has(FLAG_XFER_DIRS),
+            do_compression: has(FLAG_DO_COMPRESSION),
+            preserve_acls: has(FLAG_PRESERVE_ACLS),
+            preserve_xattrs: has(FLAG_PRESERVE_XATTRS),
+            inplace: has(FLAG_INPLACE),
+            append,
+        })
+    }
+}
+
+/// Everything a batch carries before the sender's stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchHeader {
+    pub flags: BatchStreamFlags,
+    pub protocol_version: u32,
+    pub compat_flags: i32,
+    pub checksum_seed: u32,
+}
+
+impl BatchHeader {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_MAX_LEN);
+        out.extend_from_slice(&self.flags.to_bits().to_le_bytes());
+        out.extend_from_slice(&(self.protocol_version as i32).to_le_bytes());
+        if self.protocol_version >= 30 {
+            out.extend_from_slice(&encode_varint(self.compat_flags));
+        }
+        out.extend_from_slice(&self.checksum_seed.to_le_bytes());
+        out
+    }
+
+    /// Decode the header at the start of `buf`, returning it with the
+    /// number of bytes it took.
+    pub fn decode(buf: &[u8]) -> Result<(Self, usize), AerorsyncError> {
+        let truncated = || AerorsyncError::invalid_frame("batch file is shorter than its header");
+        let int = |at: usize| -> Result<[u8; 4], AerorsyncError> {
+            buf.get(at..at + 4)
+                .and_then(|b| b.try_into().ok())
+                .ok_or_else(truncated)
+        };
+        let flags = BatchStreamFlags::from_bits(i32::from_le_bytes(int(0)?))?;
+        let protocol = i32::from_le_bytes(int(4)?);
+        if !(29..=40).contains(&protocol) {
+            return Err(AerorsyncError::unsupported_version(format!(
+                "batch records protocol {protocol}; not an rsync 3.x batch file"
+            )));
+        }
+        let mut cursor = 8;
+        let mut compat_flags = 0;
+        if protocol >= 30 {
+            let (value, consumed) = decode_varint(&buf[cursor..]).map_err(|e| match e {
+                RealWireError::TruncatedBuffer { .. } => truncated(),
+                other => AerorsyncError::invalid_frame(format!("batch compat flags: {other}")),
+            })?;
+            compat_flags = value as i32;
+            cursor += consumed;
+        }
+        let checksum_seed = u32::from_le_bytes(int(cursor)?);
+        Ok((
+            Self {
+                flags,
+                protocol_version: protocol as u32,
+                compat_flags,
+                checksum_seed,
+            },
+            cursor + 4,
+        ))
+    }
+}
+
+/// Path of the replay script of `batch`.
+pub fn batch_script_path(batch: &Path) -> PathBuf {
+    let mut name = batch.as_os_str().to_os_string();
+    name.push(BATCH_SCRIPT_SUFFIX);
+    PathBuf::from(name)
+}
+
+fn batch_io_error(action: &str, path: &Path, err: impl std::fmt::Display) -> AerorsyncError {
+    AerorsyncError::new(
+        AerorsyncErrorKind::Internal,
+        format!("cannot {action} batch file {}: {err}", path.display()),
+    )
+}
+
+// --- recording ------------------------------------------------------------
+
+/// `--write-batch` sink of a driver session: the header, then every
+/// sender-stream byte in order.
+pub(crate) struct BatchRecorder {
+    path: PathBuf,
+    out: BufWriter<fs::File>,
+}
+
+impl BatchRecorder {
+    /// Create (or truncate) the batch at `path` and write its header,
+    /// along with the replay script: `rsync --read-batch` with the
+    /// options of `options` and the negotiated algorithms.
+    pub(crate) async fn create(
+        path: &Path,
+        header: &BatchHeader,
+        options: &TransferOptions,
+        checksum: ChecksumAlgo,
+        compression: CompressionAlgo,
+    ) -> Result<Self, AerorsyncError> {
+        let file = fs::File::create(path)
+            .await
+            .map_err(|e| batch_io_error("create", path, e))?;
+        let mut recorder = Self {
+            path: path.to_path_buf(),
+            out: BufWriter::new(file),
+        };
+        recorder.record(&header.encode()).await?;
+        write_batch_script(path, options, checksum, compression).await?;
+        Ok(recorder)
+    }
+
+    pub(crate) async fn record(&mut self, bytes: &[u8]) -> Result<(), AerorsyncError> {
+        self.out
+            .write_all(bytes)
+            .await
+            .map_err(|e| batch_io_error("write", &self.path, e))
+    }
+
+    pub(crate) async fn finish(mut self) -> Result<(), AerorsyncError> {
+        self.out
+            .flush()
+            .await
+            .map_err(|e| batch_io_error("write", &self.path, e))?;
+        self.out
+            .get_mut()
+            .sync_all()
+            .await
+            .map_err(|e| batch_io_error("sync", &self.path, e))
+    }
+}
+
+/// `batch.c::write_batch_shell_file`. The destination is left to the
+/// caller (`$1`): a batch is meant for other copies than the one it
+/// was recorded against.
+async fn write_batch_script(
+    batch: &Path,
+    options: &TransferOptions,
+    checksum: ChecksumAlgo,
+    compression: CompressionAlgo,
+) -> Result<(), AerorsyncError> {
+    let script = batch_script_path(batch);
+    let mut words = vec![
+        "rsync".to_string(),
+        shell_escape_posix(&format!("--read-batch={}", batch.display())),
+    ];
+    words.extend(
+        options
+            .read_batch_args()
+            .iter()
+            .map(|a| shell_escape_posix(a)),
+    );
+    words.push(format!("--checksum-choice={}", checksum.name()));
+    words.push(format!("--compress-choice={}", compression.name()));
+    words.push("\"${1:?usage: $0 DEST}\"".to_string());
+    let text = format!("{}\n", words.join(" "));
+    fs::write(&script, text)
+        .await
+        .map_err(|e| batch_io_error("write", &script, e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, std::fs::Permissions::from_mode(0o700))
+            .await
+            .map_err(|e| batch_io_error("chmod", &script, e))?;
+    }
+    Ok(())
+}
+
+/// Algorithms named on a replay script's command line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScriptChoices {
+    pub checksum: Option<ChecksumAlgo>,
+    pub compression: Option<CompressionAlgo>,
+}
+
+/// Pick `--checksum-choice` / `--compress-choice` (and their `--cc` /
+/// `--zc` short forms) out of a replay script. With two checksum names
+/// (`xfer,file`) the transfer one is the first.
+pub fn parse_batch_script(text: &str) -> Result<ScriptChoices, AerorsyncError> {
+    let words: Vec<&str> = text
+        .split_ascii_whitespace()
+        .map(|w| w.trim_matches(|c| c == '\'' || c == '"'))
+        .collect();
+    let mut choices = ScriptChoices::default();
+    let mut i = 0;
+    while i < words.len() {
+        let (name, inline) = match words[i].split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (words[i], None),
+        };
+        i += 1;
+        let is_checksum = matches!(name, "--checksum-choice" | "--cc");
+        let is_compress = matches!(name, "--compress-choice" | "--zc");
+        if !is_checksum && !is_compress {
+            continue;
+        }
+        let value = match inline {
+            Some(value) => value,
+            None => {
+                i += 1;
+                words.get(i - 1).copied().unwrap_or_default()
+            }
+        };
+        if is_checksum {
+            let first = value.split(',').next().unwrap_or_default();
+            choices.checksum = Some(ChecksumAlgo::from_name(first).ok_or_else(|| {
+                AerorsyncError::new(
+                    AerorsyncErrorKind::NegotiationFailed,
+                    format!("unknown checksum {first:?} in batch script"),
+                )
+            })?);
+        } else {
+            choices.compression = Some(CompressionAlgo::from_name(value).ok_or_else(|| {
+                AerorsyncError::new(
+                    AerorsyncErrorKind::NegotiationFailed,
+                    format!("unknown compressor {value:?} in batch script"),
+                )
+            })?);
+        }
+    }
+    Ok(choices)
+}
+
+// --- replay ---------------------------------------------------------------
+
+/// How [`apply_batch`] replays a batch.
+#[derive(Debug, Clone, Default)]
+pub struct BatchReplayOptions {
+    /// Checksum the recording session negotiated. `None` takes it from
+    /// the batch's script, then falls back to rsync's default for a
+    /// session without negotiation (md5).
+    pub checksum: Option<ChecksumAlgo>,
+    /// Compressor the recording session negotiated; same fallback, with
+    /// zlib as the default for a compressed batch.
+    pub compression: Option<CompressionAlgo>,
+    /// Receiver options of the replay (`--delete`, `--partial-dir`,
+    /// `--mkpath`, ...). The ones a batch records override these.
+    pub transfer_options: TransferOptions,
+}
+
+/// `rsync --read-batch=BATCH DEST`: apply the transfer recorded in
+/// `batch` to the copy of its basis under `dest`.
+pub async fn apply_batch(
+    batch: &Path,
+    dest: &Path,
+    options: &BatchReplayOptions,
+) -> Result<TreeTransferReport, AerorsyncError> {
+    let mut head = Vec::with_capacity(HEADER_MAX_LEN);
+    fs::File::open(batch)
+        .await
+        .map_err(|e| batch_io_error("open", batch, e))?
+        .take(HEADER_MAX_LEN as u64)
+        .read_to_end(&mut head)
+        .await
+        .map_err(|e| batch_io_error("read", batch, e))?;
+    let (header, header_len) = BatchHeader::decode(&head)?;
+
+    let script = batch_script_path(batch);
+    let recorded = match fs::read_to_string(&script).await {
+        Ok(text) => parse_batch_script(&text)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => ScriptChoices::default(),
+        Err(e) => return Err(batch_io_error("read", &script, e)),
+    };
+    let checksum =
+        options
+            .checksum
+            .or(recorded.checksum)
+            .unwrap_or(if header.protocol_version >= 30 {
+                ChecksumAlgo::Md5
+            } else {
+                ChecksumAlgo::Md4
+            });
+    let compression = if header.flags.do_compression {
+        options
+            .compression
+            .or(recorded.compression)
+            .unwrap_or(CompressionAlgo::Zlib)
+    } else {
+        CompressionAlgo::None
+    };
+
+    let flags = header.flags;
+    let transfer_options = TransferOptions {
+        hard_links: flags.preserve_hard_links,
+        acls: flags.preserve_acls,
+        xattrs: flags.preserve_xattrs,
+        inplace: flags.inplace,
+        append: flags.append,
+        write_batch: None,
+        algorithms: AlgorithmPreferences {
+            prefer_checksums: vec![checksum],
+            prefer_compressions: vec![compression],
+            compress_level: options.transfer_options.algorithms.compress_level,
+            ..AlgorithmPreferences::default()
+        },
+        ..options.transfer_options.clone()
+    };
+    check_tree_root(dest, transfer_options.mkpath)?;
+    fs::create_dir_all(dest)
+        .await
+        .map_err(|e| AerorsyncError::transport(format!("{}: {e}", dest.display())))?;
+
+    let transport = BatchReplayTransport {
+        batch: batch.to_path_buf(),
+        header,
+        body_offset: header_len as u64,
+        checksum,
+        compression,
+        cancel_flag: Arc::new(AtomicBool::new(false)),
+    };
+    let mut driver = AerorsyncDriver::new(transport, CancelHandle::inert());
+    driver.set_session_flags(SessionFlags {
+        always_checksum: flags.always_checksum,
+        preserve_uid: flags.preserve_uid,
+        preserve_gid: flags.preserve_gid,
+        preserve_links: flags.preserve_links,
+    });
+    driver.set_reading_batch(true);
+    let mut sink = LocalTreeRoot::new(dest).with_options(transfer_options.clone());
+    let spec =
+        RemoteCommandSpec::download(batch.display().to_string()).with_options(transfer_options);
+    driver
+        .drive_download_tree(
+            spec,
+            &mut sink,
+            &CurrentDeltaSyncBridge::new(),
+            &mut ReplayLogSink,
+        )
+        .await
+        .map_err(|e| match e.kind {
+            // The only thing that can hang up on a replay is the end
+            // of the file.
+            AerorsyncErrorKind::TransportFailure => AerorsyncError::invalid_frame(format!(
+                "batch file {} ends before the transfer it records ({})",
+                batch.display(),
+                e.detail
+            )),
+            _ => e,
+        })
+}
+
+/// The "remote sender" of a replay: a task that answers the driver's
+/// preamble with the one the batch records, then hands it the batch
+/// body as `MSG_DATA` frames. Whatever the driver writes is dropped.
+struct BatchReplayTransport {
+    batch: PathBuf,
+    header: BatchHeader,
+    body_offset: u64,
+    checksum: ChecksumAlgo,
+    compression: CompressionAlgo,
+    cancel_flag: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl RemoteShellTransport for BatchReplayTransport {
+    type Stream = DaemonUnusedStream;
+
+    async fn probe(&self) -> Result<TransportProbe, AerorsyncError> {
+        Ok(TransportProbe {
+            remote_banner: format!("rsync batch {}", self.batch.display()),
+            protocol: ProtocolVersion(self.header.protocol_version),
+            supports_remote_shell: false,
+        })
+    }
+
+    async fn exec(
+        &self,
+        request: RemoteExecRequest,
+    ) -> Result<RemoteCommandOutput, AerorsyncError> {
+        Err(AerorsyncError::transport(format!(
+            "a batch replay runs no commands (asked for {})",
+            request.program
+        )))
+    }
+
+    async fn open_stream(
+        &self,
+        _request: RemoteExecRequest,
+    ) -> Result<Self::Stream, AerorsyncError> {
+        Err(AerorsyncError::transport(
+            "BatchReplayTransport does not support the legacy RSNP framed stream",
+        ))
+    }
+
+    async fn cancel(&self) -> Result<(), AerorsyncError> {
+        self.cancel_flag.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle::new(self.cancel_flag.clone(), None)
+    }
+}
+
+#[async_trait]
+impl RawRemoteShellTransport for BatchReplayTransport {
+    type RawStream = IoRawStream<DuplexStream>;
+
+    async fn open_raw_stream(
+        &self,
+        _request: RemoteExecRequest,
+    ) -> Result<Self::RawStream, AerorsyncError> {
+        let mut file = fs::File::open(&self.batch)
+            .await
+            .map_err(|e| batch_io_error("open", &self.batch, e))?;
+        tokio::io::AsyncSeekExt::seek(&mut file, std::io::SeekFrom::Start(self.body_offset))
+            .await
+            .map_err(|e| batch_io_error("read", &self.batch, e))?;
+        let preamble = encode_server_preamble(&ServerPreamble {
+            protocol_version: self.header.protocol_version,
+            compat_flags: self.header.compat_flags,
+            checksum_algos: self.checksum.name().to_string(),
+            compression_algos: self.compression.name().to_string(),
+            checksum_seed: self.header.checksum_seed,
+            consumed: 0,
+        });
+        let (client_end, feeder_end) = tokio::io::duplex(REPLAY_DUPLEX_BYTES);
+        tokio::spawn(feed_batch(feeder_end, preamble, file, self.batch.clone()));
+        Ok(IoRawStream::new(client_end, None))
+    }
+}
+
+/// Pump the preamble and the batch body into `io` while draining what
+/// the driver writes. The end of the file closes the stream, which the
+/// driver sees as the sender hanging up; a read error is reported as
+/// an `MSG_ERROR`, as a sender would.
+async fn feed_batch(io: DuplexStream, preamble: Vec<u8>, mut file: fs::File, path: PathBuf) {
+    let (mut from_driver, mut to_driver) = tokio::io::split(io);
+    let drain = async {
+        let _ = tokio::io::copy(&mut from_driver, &mut tokio::io::sink()).await;
+    };
+    let pump = async {
+        to_driver.write_all(&preamble).await?;
+        let mut chunk = vec![0u8; REPLAY_CHUNK_BYTES];
+        loop {
+            let n = match file.read(&mut chunk).await {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) => {
+                    let message = format!("cannot read batch file {}: {e}", path.display());
+                    let header = MuxHeader {
+                        tag: MuxTag::Error,
+                        length: message.len() as u32,
+                    };
+                    to_driver.write_all(&header.encode()).await?;
+                    to_driver.write_all(message.as_bytes()).await?;
+                    break;
+                }
+            };
+            let header = MuxHeader {
+                tag: MuxTag::Data,
+                length: n as u32,
+            };
+            to_driver.write_all(&header.encode()).await?;
+            to_driver.write_all(&chunk[..n]).await?;
+        }
+        to_driver.shutdown().await
+    };
+    let (_, pumped): ((), std::io::Result<()>) = tokio::join!(drain, pump);
+    if let Err(e) = pumped {
+        // The driver closing its end early is how a finished replay
+        // with trailing bytes, or a failed one, looks from here.
+        tracing::trace!("aerorsync batch feeder {}: {e}", path.display());
+    }
+}
+
+/// Replay events only go to the log: the caller reports the outcome.
+struct ReplayLogSink;
+
+impl EventSink for ReplayLogSink {
+    fn on_info(&mut self, event: AerorsyncEvent) {
+        tracing::debug!("aerorsync batch replay: {:?}", event);
+    }
+
+    fn on_warning(&mut self, event: AerorsyncEvent) {
+        tracing::warn!("aerorsync batch replay: {:?}", event);
+    }
+
+    fn on_error(&mut self, event: AerorsyncEvent) {
+        tracing::warn!("aerorsync batch replay: {:?}", event);
+    }
+
+    fn on_terminal(&mut self, event: AerorsyncEvent) {
+        tracing::debug!("aerorsync batch replay: {:?}", event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aerorsync::delta_transport_impl::AerorsyncLocalDeltaTransport;
+    use crate::delta_transport::DeltaTransport;
+
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    async fn mirror(source: &Path, target: &Path, options: TransferOptions) {
+        AerorsyncLocalDeltaTransport::new(0)
+            .with_transfer_options(options)
+            .upload_tree(source, target.to_str().unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn header_round_trips_with_stream_flags() {
+        let flags = BatchStreamFlags::for_session(
+            SessionFlags::default(),
+            &TransferOptions {
+                hard_links: true,
+                xattrs: true,
+                append: AppendMode::AppendVerify,
+                ..TransferOptions::default()
+            },
+            CompressionAlgo::Zstd,
+        );
+        let header = BatchHeader {
+            flags,
+            protocol_version: 31,
+            compat_flags: 0x1FF,
+            checksum_seed: 0xDEAD_BEEF,
+        };
+        let mut bytes = header.encode();
+        let len = bytes.len();
+        bytes.extend_from_slice(b"body");
+        let (decoded, consumed) = BatchHeader::decode(&bytes).unwrap();
+        assert_eq!(consumed, len);
+        assert_eq!(decoded, header);
+        assert!(decoded.flags.do_compression && decoded.flags.preserve_hard_links);
+
+        // A batch needing iconv cannot be replayed faithfully.
+        assert!(BatchStreamFlags::from_bits(flags.to_bits() | FLAG_ICONV).is_err());
+    }
+
+    #[test]
+    fn script_choices_accept_every_spelling() {
+        let text = "rsync --read-batch=/m/b -logDtprcz --cc xxh128,md5 \
+                    '--compress-choice=zstd' \"${1:?usage: $0 DEST}\"\n";
+        let choices = parse_batch_script(text).unwrap();
+        assert_eq!(choices.checksum, Some(ChecksumAlgo::Xxh128));
+        assert_eq!(choices.compression, Some(CompressionAlgo::Zstd));
+        assert_eq!(
+            parse_batch_script("rsync --read-batch=b \"$1\"").unwrap(),
+            ScriptChoices::default()
+        );
+        assert!(parse_batch_script("rsync --zc=brotli").is_err());
+    }
+
+    #[tokio::test]
+    async fn recorded_update_replays_onto_identical_replicas() {
+        let work = tempfile::tempdir().unwrap();
+        let source = work.path().join("source");
+        std::fs::create_dir_all(source.join("vm")).unwrap();
+        std::fs::write(source.join("notes.txt"), b"first edition").unwrap();
+        let mut image = noise(600_000, 11);
+        std::fs::write(source.join("vm/disk.img"), &image).unwrap();
+        let [live, offline, current, diverged] =
+            ["live", "offline", "current", "diverged"].map(|name| work.path().join(name));
+        for replica in [&live, &offline, &diverged] {
+            mirror(&source, replica, TransferOptions::default()).await;
+        }
+
+        image[250_000..250_032].copy_from_slice(&[0xAB; 32]);
+        std::fs::write(source.join("vm/disk.img"), &image).unwrap();
+        std::fs::write(source.join("notes.txt"), b"second edition").unwrap();
+        std::fs::write(source.join("added.txt"), b"new file").unwrap();
+        mirror(&source, &current, TransferOptions::default()).await;
+        let batch = work.path().join("update.batch");
+        mirror(
+            &source,
+            &live,
+            TransferOptions {
+                write_batch: Some(batch.clone()),
+                ..TransferOptions::default()
+            },
+        )
+        .await;
+        let script = std::fs::read_to_string(batch_script_path(&batch)).unwrap();
+        assert!(script.contains("--read-batch="), "{script}");
+        // The delta is small: the batch carries a block, not the image.
+        assert!(std::fs::metadata(&batch).unwrap().len() < 100_000);
+
+        let report = apply_batch(&batch, &offline, &BatchReplayOptions::default())
+            .await
+            .unwrap();
+        for name in ["notes.txt", "added.txt", "vm/disk.img"] {
+            assert_eq!(
+                std::fs::read(offline.join(name)).unwrap(),
+                std::fs::read(source.join(name)).unwrap(),
+                "{name}"
+            );
+        }
+        assert!(report.files_transferred >= 3, "{report:?}");
+
+        // Already up to date: every recorded update is skipped.
+        apply_batch(&batch, &current, &BatchReplayOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(current.join("vm/disk.img")).unwrap(), image);
+
+        // Not the basis the batch was made against: the reconstruction
+        // fails its checksum and the replica keeps its own bytes.
+        let other = noise(600_000, 99);
+        std::fs::write(diverged.join("vm/disk.img"), &other).unwrap();
+        assert!(
+            apply_batch(&batch, &diverged, &BatchReplayOptions::default())
+                .await
+                .is_err()
+        );
+        assert_eq!(std::fs::read(diverged.join("vm/disk.img")).unwrap(), other);
+    }
+
+    #[tokio::test]
+    async fn truncated_batch_is_refused() {
+        let work = tempfile::tempdir().unwrap();
+        let source = work.path().join("source");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("a.bin"), noise(200_000, 5)).unwrap();
+        let batch = work.path().join("full.batch");
+        mirror(
+            &source,
+            &work.path().join("live"),
+            TransferOptions {
+                write_batch: Some(batch.clone()),
+                ..TransferOptions::default()
+            },
+        )
+        .await;
+        let bytes = std::fs::read(&batch).unwrap();
+        std::fs::write(&batch, &bytes[..bytes.len() / 2]).unwrap();
+
+        let err = apply_batch(
+            &batch,
+            &work.path().join("offline"),
+            &BatchReplayOptions {
+                transfer_options: TransferOptions {
+                    mkpath: true,
+                    ..TransferOptions::default()
+                },
+                ..BatchReplayOptions::default()
+            },
+        )
+        .await
+        .unwrap_err();
+        assert!(err.detail.contains("ends before"), "{err}");
+    }
+}
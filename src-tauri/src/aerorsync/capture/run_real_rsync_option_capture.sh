@@ -0,0 +1,163 @@
+#!/usr/bin/env bash
+# Option byte-oracle harness (user-040 / user-043). Builds the real-rsync
+# Docker lane on a chosen base image, then runs one rsync session per
+# transfer option with BOTH ends inside the container, so client and
+# server are the same stock rsync release. Each session is captured by
+# the same `rsync_proxy.py` tee as the S8a lane and frozen under
+#
+#   capture/artifacts_real/frozen-peers/<RSYNC_PEER>/<scenario>/
+#
+# which `fixtures::RealRsyncPeerCapture::try_load` reads back.
+#
+#   RSYNC_PEER=3.4   BASE_IMAGE=debian:trixie-slim   ./run_real_rsync_option_capture.sh
+#   RSYNC_PEER=3.3   BASE_IMAGE=ubuntu:24.10         ./run_real_rsync_option_capture.sh
+#   RSYNC_PEER=3.2.7 BASE_IMAGE=debian:bookworm-slim ./run_real_rsync_option_capture.sh
+#
+# The option scenarios are replayed against `RemoteCommandSpec` only for
+# the 3.4 peer; every peer contributes its baseline preambles to the
+# negotiation matrix.
+
+set -euo pipefail
+
+CAPTURE_DIR="$(cd -- "$(dirname -- "${BASH_SOURCE[0]}")" && pwd)"
+RSYNC_PEER="${RSYNC_PEER:-3.4}"
+BASE_IMAGE="${BASE_IMAGE:-debian:trixie-slim}"
+KEEP_STACK="${KEEP_STACK:-0}"
+REAL_CAPTURE_SRC="$CAPTURE_DIR/workspace/real_capture"
+DEST_ROOT="$CAPTURE_DIR/artifacts_real/frozen-peers/$RSYNC_PEER"
+CONTAINER=aeroftp-rsync-real
+LANE=/workspace/real/options
+
+export BASE_IMAGE
+COMPOSE=(docker compose -f "$CAPTURE_DIR/docker-compose.real-rsync.yml")
+
+cleanup() {
+  if [[ "$KEEP_STACK" != "1" ]]; then
+    "${COMPOSE[@]}" down --remove-orphans >/dev/null 2>&1 || true
+  fi
+}
+trap cleanup EXIT
+
+mkdir -p "$REAL_CAPTURE_SRC" "$CAPTURE_DIR/workspace/real"
+"${COMPOSE[@]}" down --remove-orphans >/dev/null 2>&1 || true
+"${COMPOSE[@]}" up -d --build
+
+for _ in $(seq 1 30); do
+  if docker exec "$CONTAINER" test -f /var/run/sshd.pid 2>/dev/null; then
+    break
+  fi
+  sleep 1
+done
+
+# The client runs as testuser inside the container and reaches sshd on
+# loopback, so the ForceCommand tee sees exactly one session per run.
+docker exec -u testuser "$CONTAINER" bash -c \
+  'install -m 600 /keys/id_ed25519 /tmp/id_ed25519'
+SSH="ssh -i /tmp/id_ed25519 -o StrictHostKeyChecking=no -o UserKnownHostsFile=/dev/null -o BatchMode=yes"
+
+# Fresh inputs for every scenario, created as testuser so the receiver
+# can write to them.
+docker exec -u testuser -i "$CONTAINER" python3 - "$LANE" <<'PY'
+import os
+import shutil
+import sys
+from pathlib import Path
+
+lane = Path(sys.argv[1])
+shutil.rmtree(lane, ignore_errors=True)
+
+def payload(size: int, salt: int = 0) -> bytes:
+    return bytes(((i + salt) % 251 for i in range(size)))
+
+def put(path: Path, data: bytes) -> None:
+    path.parent.mkdir(parents=True, exist_ok=True)
+    path.write_bytes(data)
+
+basis = payload(256 * 1024)
+changed = bytearray(basis)
+changed[8 * 1024:8 * 1024 + 16] = b"real-live-option"
+
+for name in ("baseline_upload", "inplace", "partial_dir"):
+    put(lane / name / "src" / "target.bin", bytes(changed))
+    put(lane / name / "dst" / "target.bin", basis)
+put(lane / "baseline_download" / "src" / "target.bin", bytes(changed))
+put(lane / "baseline_download" / "dst" / "target.bin", basis)
+
+for name in ("append", "append_verify"):
+    put(lane / name / "src" / "target.bin", basis + payload(64 * 1024, 7))
+    put(lane / name / "dst" / "target.bin", basis)
+
+put(lane / "delete" / "src" / "tree" / "keep.bin", basis)
+put(lane / "delete" / "dst" / "tree" / "keep.bin", basis)
+put(lane / "delete" / "dst" / "tree" / "stale.bin", payload(4096, 3))
+
+put(lane / "sparse" / "src" / "target.bin",
+    payload(4096) + bytes(128 * 1024) + payload(4096, 5))
+(lane / "sparse" / "dst").mkdir(parents=True)
+
+put(lane / "mkpath" / "src" / "target.bin", basis)
+(lane / "mkpath" / "dst").mkdir(parents=True)
+
+put(lane / "hard_links" / "src" / "tree" / "a.bin", basis)
+os.link(lane / "hard_links" / "src" / "tree" / "a.bin",
+        lane / "hard_links" / "src" / "tree" / "b.bin")
+(lane / "hard_links" / "dst").mkdir(parents=True)
+PY
+
+# scenario | rsync options (on top of -avz --stats --checksum) | source | destination
+SCENARIOS=(
+  "baseline_upload||src/target.bin|@dst/target.bin"
+  "baseline_download||@src/target.bin|dst/target.bin"
+  "delete|--delete|src/tree/|@dst/tree/"
+  "inplace|--inplace|src/target.bin|@dst/target.bin"
+  "append|--append|src/target.bin|@dst/target.bin"
+  "append_verify|--append-verify|src/target.bin|@dst/target.bin"
+  "partial_dir|--partial-dir=.rsync-partial|src/target.bin|@dst/target.bin"
+  "sparse|--sparse|src/target.bin|@dst/target.bin"
+  "mkpath|--mkpath|src/target.bin|@dst/missing/dir/target.bin"
+  "hard_links|--hard-links|src/tree/|@dst/tree/"
+)
+
+rm -rf "$DEST_ROOT"
+mkdir -p "$DEST_ROOT"
+
+for entry in "${SCENARIOS[@]}"; do
+  IFS='|' read -r scenario flags src dst <<< "$entry"
+  base="$LANE/$scenario"
+  # `@` marks the remote side of the transfer.
+  if [[ "$src" == @* ]]; then src="testuser@127.0.0.1:$base/${src#@}"; else src="$base/$src"; fi
+  if [[ "$dst" == @* ]]; then dst="testuser@127.0.0.1:$base/${dst#@}"; else dst="$base/$dst"; fi
+
+  echo "[harness] $RSYNC_PEER: capturing $scenario"
+  docker exec "$CONTAINER" bash -c 'rm -rf /workspace/real_capture/*'
+  out="$DEST_ROOT/$scenario"
+  mkdir -p "$out"
+  # shellcheck disable=SC2086
+  docker exec -u testuser "$CONTAINER" \
+    rsync -avz --stats --checksum $flags -e "$SSH" "$src" "$dst" \
+    >"$out/client.stdout.txt" 2>"$out/client.stderr.txt" \
+    || {
+      echo "[harness] $scenario failed; stderr:" >&2
+      cat "$out/client.stderr.txt" >&2
+      exit 2
+    }
+
+  session="$(find "$REAL_CAPTURE_SRC" -mindepth 1 -maxdepth 1 -type d | LC_ALL=C sort | tail -1)"
+  if [[ -z "$session" || ! -s "$session/remote_command.txt" ]]; then
+    echo "[harness] FATAL: no capture session for $scenario" >&2
+    exit 3
+  fi
+  cp "$session"/capture_in.bin "$session"/capture_out.bin "$session"/remote_command.txt "$out/"
+done
+
+SERVER_RSYNC="$(docker exec "$CONTAINER" rsync --version | head -1)"
+printf '%s\n' "$SERVER_RSYNC" > "$DEST_ROOT/server_rsync_version.txt"
+
+cat > "$DEST_ROOT/summary.env" <<EOF
+freeze_ts=$(date -u +%Y%m%d_%H%M%S)
+rsync_peer=$RSYNC_PEER
+base_image=$BASE_IMAGE
+server_rsync=$SERVER_RSYNC
+EOF
+
+echo "[harness] artifacts written to $DEST_ROOT"
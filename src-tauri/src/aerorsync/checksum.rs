@@ -0,0 +1,217 @@
+//! File checksums of the rsync 3.2+ algorithm negotiation (`checksum.c`).
+//!
+//! After the preamble both peers settle on one name out of
+//! `xxh128 xxh3 xxh64 md5 md4 sha1` (see `negotiation.rs`). The winner
+//! decides two things on the wire:
+//!
+//! * the whole-file checksum written raw after every delta stream's
+//!   end marker (`match.c::match_sums`);
+//! * the per-file checksum of `-c` file lists (`flist.c::send_file_entry`).
+//!
+//! Both are plain, unseeded digests of the file contents: `sum_init`
+//! only mixes the checksum seed into the pre-protocol-30 MD4 variants,
+//! which a protocol-31 session never selects. Block strong sums are a
+//! separate matter, owned by the delta engine.
+//!
+//! The digest length is not on the wire either; both ends derive it from
+//! the name, as `csum_len_for_type` does.
+
+#![cfg(feature = "aerorsync")]
+
+use md4::Md4;
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use xxhash_rust::xxh3::Xxh3Default;
+use xxhash_rust::xxh64::Xxh64;
+
+/// One negotiable checksum algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumAlgo {
+    Xxh128,
+    Xxh3,
+    Xxh64,
+    Md5,
+    Md4,
+    Sha1,
+}
+
+impl ChecksumAlgo {
+    /// Every supported algorithm, in rsync 3.2.7's default preference
+    /// order (`valid_checksums_items`).
+    pub const ALL: [ChecksumAlgo; 6] = [
+        ChecksumAlgo::Xxh128,
+        ChecksumAlgo::Xxh3,
+        ChecksumAlgo::Xxh64,
+        ChecksumAlgo::Md5,
+        ChecksumAlgo::Md4,
+        ChecksumAlgo::Sha1,
+    ];
+
+    /// Name used in the negotiation strings.
+    pub fn name(self) -> &'static str {
+        match self {
+            ChecksumAlgo::Xxh128 => "xxh128",
+            ChecksumAlgo::Xxh3 => "xxh3",
+            ChecksumAlgo::Xxh64 => "xxh64",
+            ChecksumAlgo::Md5 => "md5",
+            ChecksumAlgo::Md4 => "md4",
+            ChecksumAlgo::Sha1 => "sha1",
+        }
+    }
+
+    /// Parse a negotiation name. `xxhash` is rsync's alias for `xxh64`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "xxh128" => Some(ChecksumAlgo::Xxh128),
+            "xxh3" => Some(ChecksumAlgo::Xxh3),
+            "xxh64" | "xxhash" => Some(ChecksumAlgo::Xxh64),
+            "md5" => Some(ChecksumAlgo::Md5),
+            "md4" => Some(ChecksumAlgo::Md4),
+            "sha1" => Some(ChecksumAlgo::Sha1),
+            _ => None,
+        }
+    }
+
+    /// Digest length on the wire (`csum_len_for_type`).
+    pub fn digest_len(self) -> usize {
+        match self {
+            ChecksumAlgo::Xxh128 | ChecksumAlgo::Md5 | ChecksumAlgo::Md4 => 16,
+            ChecksumAlgo::Xxh3 | ChecksumAlgo::Xxh64 => 8,
+            ChecksumAlgo::Sha1 => 20,
+        }
+    }
+
+    /// Digest of `data` in wire byte order.
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finish()
+    }
+
+    /// Incremental hasher for sources streamed in chunks.
+    pub fn hasher(self) -> FileHasher {
+        match self {
+            ChecksumAlgo::Xxh128 => FileHasher::Xxh128(Box::default()),
+            ChecksumAlgo::Xxh3 => FileHasher::Xxh3(Box::default()),
+            ChecksumAlgo::Xxh64 => FileHasher::Xxh64(Xxh64::new(0)),
+            ChecksumAlgo::Md5 => FileHasher::Md5(Md5::new()),
+            ChecksumAlgo::Md4 => FileHasher::Md4(Md4::new()),
+            ChecksumAlgo::Sha1 => FileHasher::Sha1(Sha1::new()),
+        }
+    }
+}
+
+impl std::fmt::Display for ChecksumAlgo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Streaming state of one file checksum. The xxh3 states are boxed: they
+/// carry a 576-byte buffer the digest states do not need.
+pub enum FileHasher {
+    Xxh128(Box<Xxh3Default>),
+    Xxh3(Box<Xxh3Default>),
+    Xxh64(Xxh64),
+    Md5(Md5),
+    Md4(Md4),
+    Sha1(Sha1),
+}
+
+impl FileHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            FileHasher::Xxh128(h) | FileHasher::Xxh3(h) => h.update(data),
+            FileHasher::Xxh64(h) => h.update(data),
+            FileHasher::Md5(h) => h.update(data),
+            FileHasher::Md4(h) => h.update(data),
+            FileHasher::Sha1(h) => h.update(data),
+        }
+    }
+
+    /// Final digest in wire byte order. The xxhash values are written
+    /// little-endian with `SIVAL64`, xxh128 as low half then high half.
+    pub fn finish(self) -> Vec<u8> {
+        match self {
+            FileHasher::Xxh128(h) => {
+                let hash = h.digest128();
+                let mut out = Vec::with_capacity(16);
+                out.extend_from_slice(&(hash as u64).to_le_bytes());
+                out.extend_from_slice(&((hash >> 64) as u64).to_le_bytes());
+                out
+            }
+            FileHasher::Xxh3(h) => h.digest().to_le_bytes().to_vec(),
+            FileHasher::Xxh64(h) => h.digest().to_le_bytes().to_vec(),
+            FileHasher::Md5(h) => h.finalize().to_vec(),
+            FileHasher::Md4(h) => h.finalize().to_vec(),
+            FileHasher::Sha1(h) => h.finalize().to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn names_round_trip_and_accept_the_xxhash_alias() {
+        for algo in ChecksumAlgo::ALL {
+            assert_eq!(ChecksumAlgo::from_name(algo.name()), Some(algo));
+        }
+        assert_eq!(ChecksumAlgo::from_name("xxhash"), Some(ChecksumAlgo::Xxh64));
+        assert_eq!(ChecksumAlgo::from_name("none"), None);
+        assert_eq!(ChecksumAlgo::from_name("XXH128"), None);
+    }
+
+    #[test]
+    fn digest_lengths_match_csum_len_for_type() {
+        for algo in ChecksumAlgo::ALL {
+            assert_eq!(algo.digest(b"abc").len(), algo.digest_len(), "{algo}");
+        }
+    }
+
+    #[test]
+    fn digests_match_reference_vectors() {
+        assert_eq!(
+            hex(&ChecksumAlgo::Md5.digest(b"abc")),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            hex(&ChecksumAlgo::Md4.digest(b"abc")),
+            "a448017aaf21d8525fc10ae87aa6729d"
+        );
+        assert_eq!(
+            hex(&ChecksumAlgo::Sha1.digest(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        // XXH64("", 0) = 0xef46db3751d8e999, written little-endian.
+        assert_eq!(hex(&ChecksumAlgo::Xxh64.digest(b"")), "99e9d85137db46ef");
+        // XXH3_64bits("") = 0x2d06800538d394c2.
+        assert_eq!(hex(&ChecksumAlgo::Xxh3.digest(b"")), "c294d3380580062d");
+    }
+
+    #[test]
+    fn xxh128_layout_is_low_half_first() {
+        let data = b"aerorsync xxh128 layout";
+        let hash = xxhash_rust::xxh3::xxh3_128(data);
+        let wire = ChecksumAlgo::Xxh128.digest(data);
+        assert_eq!(&wire[..8], &(hash as u64).to_le_bytes());
+        assert_eq!(&wire[8..], &((hash >> 64) as u64).to_le_bytes());
+    }
+
+    #[test]
+    fn streaming_matches_one_shot() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        for algo in ChecksumAlgo::ALL {
+            let mut hasher = algo.hasher();
+            for chunk in data.chunks(4093) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finish(), algo.digest(&data), "{algo}");
+        }
+    }
+}
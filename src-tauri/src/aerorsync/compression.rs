@@ -0,0 +1,708 @@
+//! Literal compressors of the rsync 3.2+ negotiation (`token.c`).
+//!
+//! The compression list is exchanged only under `-z`. Whatever wins
+//! decides how literal bytes travel inside the delta stream:
+//!
+//! * `zstd`: one session-wide context per direction, flushed at the end
+//!   of every literal run and never ended (`send_zstd_token`). The codec itself lives
+//!   in `real_wire` (`ZstdLiteralCompressor` / `ZstdLiteralDecompressor`).
+//! * `lz4`: every DEFLATED_DATA record is an independent raw LZ4 block
+//!   compressed from at most `MAX_DATA_COUNT` input bytes; the sender
+//!   halves the input until the block fits (`send_compressed_token`).
+//! * `zlibx` / `zlib`: raw deflate reset per file. Each literal run ends
+//!   with a sync flush whose `00 00 ff ff` tail is stripped on the wire
+//!   and re-fed by the receiver when the run ends (`send_deflated_token`).
+//!   Plain `zlib` additionally feeds every matched block to the
+//!   receiver's inflater as stored blocks (`see_deflate_token`), so the
+//!   sender may reference matched data; we send with a full flush
+//!   instead, which clears our history and keeps the stream valid for
+//!   either view.
+//! * `none`: raw literals in the simple token framing.
+
+#![cfg(feature = "aerorsync")]
+
+use std::ops::RangeInclusive;
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use crate::aerorsync::real_wire::{
+    RealWireError, TokenFormat, ZstdLiteralCompressor, ZstdLiteralDecompressor,
+    MAX_DELTA_LITERAL_LEN, SIMPLE_TOKEN_CHUNK_LEN,
+};
+
+/// Empty stored block a sync flush ends with; not sent on the wire.
+const SYNC_FLUSH_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Largest stored block `see_deflate_token` feeds in one go.
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+/// One negotiable literal compressor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompressionAlgo {
+    Zstd,
+    Lz4,
+    Zlibx,
+    Zlib,
+    None,
+}
+
+impl CompressionAlgo {
+    /// Every supported name, in rsync 3.2.7's default preference order
+    /// (`valid_compressions_items`).
+    pub const ALL: [CompressionAlgo; 5] = [
+        CompressionAlgo::Zstd,
+        CompressionAlgo::Lz4,
+        CompressionAlgo::Zlibx,
+        CompressionAlgo::Zlib,
+        CompressionAlgo::None,
+    ];
+
+    /// Name used in the negotiation strings.
+    pub fn name(self) -> &'static str {
+        match self {
+            CompressionAlgo::Zstd => "zstd",
+            CompressionAlgo::Lz4 => "lz4",
+            CompressionAlgo::Zlibx => "zlibx",
+            CompressionAlgo::Zlib => "zlib",
+            CompressionAlgo::None => "none",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "zstd" => Some(CompressionAlgo::Zstd),
+            "lz4" => Some(CompressionAlgo::Lz4),
+            "zlibx" => Some(CompressionAlgo::Zlibx),
+            "zlib" => Some(CompressionAlgo::Zlib),
+            "none" => Some(CompressionAlgo::None),
+            _ => None,
+        }
+    }
+
+    /// Delta-stream framing this compressor implies.
+    pub fn token_format(self) -> TokenFormat {
+        match self {
+            CompressionAlgo::None => TokenFormat::Simple,
+            _ => TokenFormat::Compressed,
+        }
+    }
+
+    /// `--compress-level` values accepted for this compressor, `None`
+    /// when it has no level (`lz4`, `none`).
+    pub fn level_range(self) -> Option<RangeInclusive<i32>> {
+        match self {
+            CompressionAlgo::Zstd => Some(-131_072..=22),
+            CompressionAlgo::Zlibx | CompressionAlgo::Zlib => Some(0..=9),
+            CompressionAlgo::Lz4 | CompressionAlgo::None => None,
+        }
+    }
+
+    /// Level used without `--compress-level` (`compat.c`).
+    pub fn default_level(self) -> Option<i32> {
+        match self {
+            CompressionAlgo::Zstd => Some(3),
+            CompressionAlgo::Zlibx | CompressionAlgo::Zlib => Some(6),
+            CompressionAlgo::Lz4 | CompressionAlgo::None => None,
+        }
+    }
+}
+
+impl std::fmt::Display for CompressionAlgo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn codec_error(algo: &'static str, reason: impl std::fmt::Display) -> RealWireError {
+    RealWireError::LiteralCodecFailed {
+        algo,
+        reason: reason.to_string(),
+    }
+}
+
+/// Sender half: turns literal bytes into DEFLATED_DATA payloads (or raw
+/// simple-format chunks). One instance per session and direction.
+pub enum LiteralEncoder {
+    Zstd {
+        ctx: ZstdLiteralCompressor,
+        /// Compressed bytes of the current run not yet cut into records.
+        pending: Vec<u8>,
+        /// Input fed since the last flush.
+        unflushed: bool,
+    },
+    Lz4,
+    Deflate {
+        ctx: Box<Compress>,
+        /// `zlib` rather than `zlibx`: flush with `Full`, see the module
+        /// docs.
+        full_flush: bool,
+        /// Compressed bytes not yet cut into records. The last four
+        /// bytes of a run are the sync tail, so they are held back
+        /// until the run ends.
+        pending: Vec<u8>,
+        /// Input fed since the last flush (`flush_pending`).
+        unflushed: bool,
+    },
+    Plain,
+}
+
+impl LiteralEncoder {
+    /// `level` falls back to the compressor's default; it is ignored by
+    /// compressors without levels.
+    pub fn new(algo: CompressionAlgo, level: Option<i32>) -> Result<Self, RealWireError> {
+        let level = level.or(algo.default_level());
+        Ok(match algo {
+            CompressionAlgo::Zstd => LiteralEncoder::Zstd {
+                ctx: ZstdLiteralCompressor::with_level(level.unwrap_or(3))?,
+                pending: Vec::new(),
+                unflushed: false,
+            },
+            CompressionAlgo::Lz4 => LiteralEncoder::Lz4,
+            CompressionAlgo::Zlibx | CompressionAlgo::Zlib => {
+                let level = level.unwrap_or(6).clamp(0, 9) as u32;
+                LiteralEncoder::Deflate {
+                    ctx: Box::new(Compress::new(Compression::new(level), false)),
+                    full_flush: algo == CompressionAlgo::Zlib,
+                    pending: Vec::new(),
+                    unflushed: false,
+                }
+            }
+            CompressionAlgo::None => LiteralEncoder::Plain,
+        })
+    }
+
+    /// Encode one literal. `run_ends` is true when a matched block or the
+    /// end of the file follows it; consecutive literals form one run.
+    /// Returns the payloads to send as `DeltaOp::Literal`, each within
+    /// the framing's record limit. Inside a run only full records come
+    /// back, so a run split into many literals costs no more than one
+    /// literal and the encoder holds back less than one record of output.
+    pub fn encode_literal(
+        &mut self,
+        raw: &[u8],
+        run_ends: bool,
+    ) -> Result<Vec<Vec<u8>>, RealWireError> {
+        match self {
+            LiteralEncoder::Zstd {
+                ctx,
+                pending,
+                unflushed,
+            } => {
+                let fed = *unflushed || !raw.is_empty();
+                let flush = run_ends && fed;
+                if !raw.is_empty() || flush {
+                    ctx.compress_into(raw, flush, pending)?;
+                }
+                *unflushed = fed && !flush;
+                Ok(take_records(pending, flush))
+            }
+            LiteralEncoder::Lz4 => Ok(lz4_records(raw)),
+            LiteralEncoder::Deflate {
+                ctx,
+                full_flush,
+                pending,
+                unflushed,
+            } => {
+                if !raw.is_empty() {
+                    deflate_into(ctx, raw, FlushCompress::None, pending)?;
+                    *unflushed = true;
+                }
+                let mut records = Vec::new();
+                if run_ends && *unflushed {
+                    let flush = if *full_flush {
+                        FlushCompress::Full
+                    } else {
+                        FlushCompress::Sync
+                    };
+                    deflate_into(ctx, &[], flush, pending)?;
+                    if !pending.ends_with(&SYNC_FLUSH_TAIL) {
+                        return Err(codec_error(
+                            "deflate",
+                            "flush did not end on a stored block",
+                        ));
+                    }
+                    pending.truncate(pending.len() - SYNC_FLUSH_TAIL.len());
+                    *unflushed = false;
+                    records.extend(pending.chunks(MAX_DELTA_LITERAL_LEN).map(<[u8]>::to_vec));
+                    pending.clear();
+                } else {
+                    // Full records can go now; keep the tail candidates.
+                    let ready = pending.len().saturating_sub(SYNC_FLUSH_TAIL.len())
+                        / MAX_DELTA_LITERAL_LEN
+                        * MAX_DELTA_LITERAL_LEN;
+                    records.extend(
+                        pending[..ready]
+                            .chunks(MAX_DELTA_LITERAL_LEN)
+                            .map(<[u8]>::to_vec),
+                    );
+                    pending.drain(..ready);
+                }
+                Ok(records)
+            }
+            LiteralEncoder::Plain => Ok(raw
+                .chunks(SIMPLE_TOKEN_CHUNK_LEN)
+                .map(<[u8]>::to_vec)
+                .collect()),
+        }
+    }
+
+    /// Per-file reset: `deflateReset` at the next file's first token.
+    /// zstd keeps its context across files, lz4 has none.
+    pub fn end_file(&mut self) {
+        if let LiteralEncoder::Deflate {
+            ctx,
+            pending,
+            unflushed,
+            ..
+        } = self
+        {
+            ctx.reset();
+            pending.clear();
+            *unflushed = false;
+        }
+    }
+}
+
+/// Cut `pending` into DEFLATED_DATA records: all of it when the run
+/// ends, otherwise only the full ones.
+fn take_records(pending: &mut Vec<u8>, run_ends: bool) -> Vec<Vec<u8>> {
+    let ready = if run_ends {
+        pending.len()
+    } else {
+        pending.len() / MAX_DELTA_LITERAL_LEN * MAX_DELTA_LITERAL_LEN
+    };
+    let records = pending[..ready]
+        .chunks(MAX_DELTA_LITERAL_LEN)
+        .map(<[u8]>::to_vec)
+        .collect();
+    pending.drain(..ready);
+    records
+}
+
+/// Run `raw` through the deflater with `flush`, appending the output.
+fn deflate_into(
+    ctx: &mut Compress,
+    raw: &[u8],
+    flush: FlushCompress,
+    out: &mut Vec<u8>,
+) -> Result<(), RealWireError> {
+    let start = ctx.total_in();
+    loop {
+        let consumed = (ctx.total_in() - start) as usize;
+        if out.capacity() - out.len() < 1024 {
+            out.reserve((raw.len() - consumed) / 2 + 4096);
+        }
+        ctx.compress_vec(&raw[consumed..], out, flush)
+            .map_err(|e| codec_error("deflate", e))?;
+        let consumed = (ctx.total_in() - start) as usize;
+        if consumed == raw.len() && out.len() < out.capacity() {
+            return Ok(());
+        }
+    }
+}
+
+/// `send_compressed_token`: independent LZ4 blocks from at most
+/// `MAX_DATA_COUNT` input bytes, halving the input until the block does
+/// not outgrow a record.
+fn lz4_records(raw: &[u8]) -> Vec<Vec<u8>> {
+    let mut records = Vec::new();
+    let mut rest = raw;
+    while !rest.is_empty() {
+        let mut take = rest.len().min(MAX_DELTA_LITERAL_LEN);
+        loop {
+            let block = lz4_flex::block::compress(&rest[..take]);
+            if block.len() <= MAX_DELTA_LITERAL_LEN {
+                records.push(block);
+                break;
+            }
+            take /= 2;
+        }
+        rest = &rest[take..];
+    }
+    records
+}
+
+/// Receiver half: turns the DEFLATED_DATA payloads of one literal run
+/// back into bytes. One instance per session and direction.
+pub enum LiteralDecoder {
+    Zstd(ZstdLiteralDecompressor),
+    Lz4(Vec<u8>),
+    Deflate {
+        ctx: Box<Decompress>,
+        /// `zlib`: matched blocks are fed through `see_match`.
+        sees_matches: bool,
+    },
+    Plain,
+}
+
+impl LiteralDecoder {
+    pub fn new(algo: CompressionAlgo) -> Self {
+        match algo {
+            CompressionAlgo::Zstd => LiteralDecoder::Zstd(ZstdLiteralDecompressor::new()),
+            // Every block decodes to at most MAX_DATA_COUNT bytes.
+            CompressionAlgo::Lz4 => LiteralDecoder::Lz4(vec![0u8; 2 * MAX_DELTA_LITERAL_LEN]),
+            CompressionAlgo::Zlibx | CompressionAlgo::Zlib => LiteralDecoder::Deflate {
+                ctx: Box::new(Decompress::new(false)),
+                sees_matches: algo == CompressionAlgo::Zlib,
+            },
+            CompressionAlgo::None => LiteralDecoder::Plain,
+        }
+    }
+
+    /// Decode one run of consecutive literal records (everything between
+    /// two matched blocks, or up to the end of the file).
+    pub fn decode_run(&mut self, records: &[&[u8]]) -> Result<Vec<u8>, RealWireError> {
+        match self {
+            LiteralDecoder::Zstd(ctx) => {
+                let blob = records.concat();
+                if blob.is_empty() {
+                    return Ok(Vec::new());
+                }
+                Ok(ctx.decompress_payloads(&[&blob])?.pop().unwrap_or_default())
+            }
+            LiteralDecoder::Lz4(scratch) => {
+                let mut out = Vec::new();
+                for record in records {
+                    let n = lz4_flex::block::decompress_into(record, scratch)
+                        .map_err(|e| codec_error("lz4", e))?;
+                    out.extend_from_slice(&scratch[..n]);
+                }
+                Ok(out)
+            }
+            LiteralDecoder::Deflate { ctx, .. } => {
+                let mut input = records.concat();
+                if input.is_empty() {
+                    return Ok(Vec::new());
+                }
+                input.extend_from_slice(&SYNC_FLUSH_TAIL);
+                inflate_all(ctx, &input, input.len() * 4)
+            }
+            LiteralDecoder::Plain => Ok(records.concat()),
+        }
+    }
+
+    /// Decode one record of the current run as it arrives, appending the
+    /// bytes it releases to `out`. Feeding every record of a run and then
+    /// calling `finish_run` yields the same bytes as `decode_run`, without
+    /// holding the whole run in memory.
+    pub fn decode_record(&mut self, record: &[u8], out: &mut Vec<u8>) -> Result<(), RealWireError> {
+        match self {
+            LiteralDecoder::Zstd(ctx) => ctx.decompress_into(record, out),
+            LiteralDecoder::Lz4(scratch) => {
+                let n = lz4_flex::block::decompress_into(record, scratch)
+                    .map_err(|e| codec_error("lz4", e))?;
+                out.extend_from_slice(&scratch[..n]);
+                Ok(())
+            }
+            LiteralDecoder::Deflate { ctx, .. } => {
+                if !record.is_empty() {
+                    out.extend_from_slice(&inflate_all(ctx, record, record.len() * 4)?);
+                }
+                Ok(())
+            }
+            LiteralDecoder::Plain => {
+                out.extend_from_slice(record);
+                Ok(())
+            }
+        }
+    }
+
+    /// Close a run fed through `decode_record`: deflate gets the sync
+    /// tail the sender stripped. Call it only for runs with at least one
+    /// record.
+    pub fn finish_run(&mut self, out: &mut Vec<u8>) -> Result<(), RealWireError> {
+        if let LiteralDecoder::Deflate { ctx, .. } = self {
+            out.extend_from_slice(&inflate_all(ctx, &SYNC_FLUSH_TAIL, 1024)?);
+        }
+        Ok(())
+    }
+
+    /// Whether `see_match` must be fed the data of every matched block.
+    pub fn needs_match_data(&self) -> bool {
+        matches!(
+            self,
+            LiteralDecoder::Deflate {
+                sees_matches: true,
+                ..
+            }
+        )
+    }
+
+    /// `see_deflate_token`: add one matched block to the inflater's
+    /// history as stored blocks. A no-op for every other compressor.
+    pub fn see_match(&mut self, data: &[u8]) -> Result<(), RealWireError> {
+        let LiteralDecoder::Deflate {
+            ctx,
+            sees_matches: true,
+        } = self
+        else {
+            return Ok(());
+        };
+        for chunk in data.chunks(MAX_STORED_BLOCK) {
+            let len = chunk.len() as u16;
+            let mut input = Vec::with_capacity(5 + chunk.len());
+            input.push(0);
+            input.extend_from_slice(&len.to_le_bytes());
+            input.extend_from_slice(&(!len).to_le_bytes());
+            input.extend_from_slice(chunk);
+            let replayed = inflate_all(ctx, &input, chunk.len())?;
+            if replayed.len() != chunk.len() {
+                return Err(codec_error("zlib", "stored block replay came back short"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Per-file reset: `inflateReset` when the next file starts.
+    pub fn end_file(&mut self) {
+        if let LiteralDecoder::Deflate { ctx, .. } = self {
+            ctx.reset(false);
+        }
+    }
+}
+
+/// Inflate all of `input` with a sync flush and return the output.
+fn inflate_all(
+    ctx: &mut Decompress,
+    input: &[u8],
+    size_hint: usize,
+) -> Result<Vec<u8>, RealWireError> {
+    let mut out = Vec::with_capacity(size_hint.max(1024));
+    let start = ctx.total_in();
+    loop {
+        let consumed = (ctx.total_in() - start) as usize;
+        if out.len() == out.capacity() {
+            out.reserve(out.capacity());
+        }
+        let before_out = out.len();
+        let status = ctx
+            .decompress_vec(&input[consumed..], &mut out, FlushDecompress::Sync)
+            .map_err(|e| codec_error("zlib", e))?;
+        let consumed_now = (ctx.total_in() - start) as usize;
+        if status == Status::StreamEnd {
+            return Err(codec_error("zlib", "unexpected end of the deflate stream"));
+        }
+        if consumed_now == input.len() && out.len() < out.capacity() {
+            return Ok(out);
+        }
+        if consumed_now == consumed && out.len() == before_out && out.len() < out.capacity() {
+            return Err(codec_error("zlib", "inflate made no progress"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_u32;
+        (0..len)
+            .map(|i| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                // Half noise, half repetition, so every codec has work.
+                if (i / 4096) % 2 == 0 {
+                    state as u8
+                } else {
+                    b"aerorsync"[i % 9]
+                }
+            })
+            .collect()
+    }
+
+    /// One run of literals through encoder and decoder.
+    fn round_trip(algo: CompressionAlgo, literals: &[&[u8]]) -> Vec<u8> {
+        let mut encoder = LiteralEncoder::new(algo, None).unwrap();
+        let mut records = Vec::new();
+        for (i, raw) in literals.iter().enumerate() {
+            let ends = i + 1 == literals.len();
+            for record in encoder.encode_literal(raw, ends).unwrap() {
+                assert!(!record.is_empty());
+                assert!(record.len() <= algo.token_format().max_literal_len());
+                records.push(record);
+            }
+        }
+        let slices: Vec<&[u8]> = records.iter().map(Vec::as_slice).collect();
+        LiteralDecoder::new(algo).decode_run(&slices).unwrap()
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for algo in CompressionAlgo::ALL {
+            assert_eq!(CompressionAlgo::from_name(algo.name()), Some(algo));
+        }
+        assert_eq!(CompressionAlgo::from_name("zlib2"), None);
+        assert_eq!(CompressionAlgo::None.token_format(), TokenFormat::Simple);
+        assert_eq!(CompressionAlgo::Lz4.token_format(), TokenFormat::Compressed);
+    }
+
+    #[test]
+    fn every_codec_round_trips_a_multi_record_run() {
+        let a = sample(70_000);
+        let b = sample(5_000);
+        for algo in CompressionAlgo::ALL {
+            let out = round_trip(algo, &[&a, &b]);
+            assert_eq!(out.len(), a.len() + b.len(), "{algo}");
+            assert!(out[..a.len()] == a[..] && out[a.len()..] == b[..], "{algo}");
+        }
+    }
+
+    #[test]
+    fn records_decode_one_at_a_time_like_a_whole_run() {
+        let a = sample(90_000);
+        let b = sample(20_000);
+        for algo in CompressionAlgo::ALL {
+            let mut encoder = LiteralEncoder::new(algo, None).unwrap();
+            let mut records = encoder.encode_literal(&a, false).unwrap();
+            records.extend(encoder.encode_literal(&b, true).unwrap());
+            let slices: Vec<&[u8]> = records.iter().map(Vec::as_slice).collect();
+            let whole = LiteralDecoder::new(algo).decode_run(&slices).unwrap();
+
+            let mut decoder = LiteralDecoder::new(algo);
+            let mut streamed = Vec::new();
+            for record in &records {
+                decoder.decode_record(record, &mut streamed).unwrap();
+            }
+            decoder.finish_run(&mut streamed).unwrap();
+            assert_eq!(streamed, whole, "{algo}");
+            assert_eq!(streamed.len(), a.len() + b.len(), "{algo}");
+        }
+    }
+
+    #[test]
+    fn zstd_run_records_do_not_depend_on_how_the_run_is_split() {
+        // `send_zstd_token` flushes only when the run ends, so the
+        // records of one run come out the same however the sender chunks
+        // the literal bytes.
+        let data = sample(300_000);
+        let mut whole = LiteralEncoder::new(CompressionAlgo::Zstd, None).unwrap();
+        let expected = whole.encode_literal(&data, true).unwrap();
+        let mut split = LiteralEncoder::new(CompressionAlgo::Zstd, None).unwrap();
+        let mut records = Vec::new();
+        let chunks: Vec<&[u8]> = data.chunks(70_000).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let out = split.encode_literal(chunk, i + 1 == chunks.len()).unwrap();
+            if i + 1 < chunks.len() {
+                assert!(out.iter().all(|r| r.len() == MAX_DELTA_LITERAL_LEN));
+            }
+            records.extend(out);
+        }
+        assert_eq!(records, expected);
+    }
+
+    #[test]
+    fn lz4_records_are_independent_blocks() {
+        let data = sample(40_000);
+        let records = lz4_records(&data);
+        assert!(records.len() >= 3);
+        let mut out = Vec::new();
+        for record in &records {
+            out.extend(lz4_flex::block::decompress(record, MAX_DELTA_LITERAL_LEN).unwrap());
+        }
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn lz4_halves_incompressible_input_until_it_fits() {
+        // Random bytes grow under LZ4, so a full MAX_DATA_COUNT slice
+        // cannot fit one record.
+        let mut state = 7u64;
+        let data: Vec<u8> = (0..MAX_DELTA_LITERAL_LEN)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (state >> 33) as u8
+            })
+            .collect();
+        let records = lz4_records(&data);
+        assert!(records.len() > 1);
+        assert!(records.iter().all(|r| r.len() <= MAX_DELTA_LITERAL_LEN));
+    }
+
+    #[test]
+    fn deflate_strips_the_sync_tail_and_resets_per_file() {
+        for algo in [CompressionAlgo::Zlibx, CompressionAlgo::Zlib] {
+            let mut encoder = LiteralEncoder::new(algo, Some(9)).unwrap();
+            let mut decoder = LiteralDecoder::new(algo);
+            for file in 0..3 {
+                let data = sample(3_000 + file * 1000);
+                let records = encoder.encode_literal(&data, true).unwrap();
+                let joined = records.concat();
+                assert!(!joined.ends_with(&SYNC_FLUSH_TAIL), "{algo}");
+                let slices: Vec<&[u8]> = records.iter().map(Vec::as_slice).collect();
+                assert_eq!(decoder.decode_run(&slices).unwrap(), data, "{algo}");
+                encoder.end_file();
+                decoder.end_file();
+            }
+        }
+    }
+
+    #[test]
+    fn zlib_runs_decode_around_matched_blocks() {
+        // Literal, matched block, literal: the zlib receiver replays the
+        // block as stored data between the runs.
+        let first = sample(9_000);
+        let matched = sample(70_000);
+        let second = first.clone();
+        let mut encoder = LiteralEncoder::new(CompressionAlgo::Zlib, None).unwrap();
+        let mut decoder = LiteralDecoder::new(CompressionAlgo::Zlib);
+        assert!(decoder.needs_match_data());
+
+        let run1 = encoder.encode_literal(&first, true).unwrap();
+        let run2 = encoder.encode_literal(&second, true).unwrap();
+        let s1: Vec<&[u8]> = run1.iter().map(Vec::as_slice).collect();
+        let s2: Vec<&[u8]> = run2.iter().map(Vec::as_slice).collect();
+        assert_eq!(decoder.decode_run(&s1).unwrap(), first);
+        decoder.see_match(&matched).unwrap();
+        assert_eq!(decoder.decode_run(&s2).unwrap(), second);
+    }
+
+    #[test]
+    fn zlibx_ignores_matched_blocks() {
+        let mut decoder = LiteralDecoder::new(CompressionAlgo::Zlibx);
+        assert!(!decoder.needs_match_data());
+        decoder.see_match(b"ignored").unwrap();
+    }
+
+    #[test]
+    fn long_literal_holds_back_only_the_tail_until_the_run_ends() {
+        let data = sample(200_000);
+        let mut encoder = LiteralEncoder::new(CompressionAlgo::Zlibx, Some(1)).unwrap();
+        let early = encoder.encode_literal(&data, false).unwrap();
+        assert!(early.iter().all(|r| r.len() == MAX_DELTA_LITERAL_LEN));
+        let late = encoder.encode_literal(&[], true).unwrap();
+        assert!(!late.is_empty());
+        let all: Vec<&[u8]> = early.iter().chain(&late).map(Vec::as_slice).collect();
+        let out = LiteralDecoder::new(CompressionAlgo::Zlibx)
+            .decode_run(&all)
+            .unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn plain_chunks_at_the_simple_record_limit() {
+        let data = sample(SIMPLE_TOKEN_CHUNK_LEN * 2 + 10);
+        let mut encoder = LiteralEncoder::new(CompressionAlgo::None, None).unwrap();
+        let records = encoder.encode_literal(&data, true).unwrap();
+        assert_eq!(
+            records.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![SIMPLE_TOKEN_CHUNK_LEN, SIMPLE_TOKEN_CHUNK_LEN, 10]
+        );
+    }
+
+    #[test]
+    fn corrupt_payloads_surface_codec_errors() {
+        let err = LiteralDecoder::new(CompressionAlgo::Lz4)
+            .decode_run(&[&[0xff, 0xff, 0xff]])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            RealWireError::LiteralCodecFailed { algo: "lz4", .. }
+        ));
+        let err = LiteralDecoder::new(CompressionAlgo::Zlibx)
+            .decode_run(&[&[0xff; 8]])
+            .unwrap_err();
+        assert!(matches!(err, RealWireError::LiteralCodecFailed { .. }));
+    }
+}
@@ -0,0 +1,977 @@
+//! rsync daemon protocol (`rsync://host/module`, TCP 873): the text
+//! handshake in front of the protocol-31 session, for both ends.
+//!
+//! A daemon connection opens with an `@RSYNCD: <version> <digests>`
+//! greeting from each side. The client then names a module (or asks for
+//! the listing), answers the `@RSYNCD: AUTHREQD` challenge when the
+//! module has `auth users`, and sends the `rsync --server ...` argument
+//! list a remote shell would otherwise have run, NUL-separated. From
+//! there the socket carries the same wire as the SSH path, except that
+//! the 4-byte version exchange is gone: the greetings already agreed
+//! on it, which `IoRawStream::agreed_protocol` passes on to the driver.
+//!
+//! Lines are read one byte at a time on purpose: the binary session
+//! starts right after the last one, and nothing past its newline may
+//! be consumed here.
+//!
+//! The server half (`rsyncd.conf`, accept loop, sessions) lives in
+//! `daemon_server.rs`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine as _;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::aerorsync::transport::{
+    BidirectionalByteStream, CancelHandle, RawByteStream, RawRemoteShellTransport,
+    RemoteCommandOutput, RemoteExecRequest, RemoteShellTransport, TransportProbe,
+};
+use crate::aerorsync::types::{AerorsyncError, AerorsyncErrorKind, ProtocolVersion};
+use crate::aerorsync::CURRENT_PROTOCOL_VERSION;
+
+/// Default rsync daemon port.
+pub const RSYNCD_PORT: u16 = 873;
+
+/// Every daemon control line starts with this.
+pub const GREETING_PREFIX: &str = "@RSYNCD: ";
+/// Module accepted: the argument list comes next.
+pub const DAEMON_OK: &str = "@RSYNCD: OK";
+/// End of a module listing, or a refusal without details.
+pub const DAEMON_EXIT: &str = "@RSYNCD: EXIT";
+/// Followed by the challenge the client must hash with its password.
+pub const AUTHREQD_PREFIX: &str = "@RSYNCD: AUTHREQD ";
+/// Fatal error line, `@ERROR: <message>`.
+pub const ERROR_PREFIX: &str = "@ERROR";
+
+/// Auth digests we can compute, in our preference order. `md4`, which
+/// pre-3.2 daemons still list, has no implementation here.
+pub const SUPPORTED_AUTH_DIGESTS: &str = "sha512 sha256 sha1 md5";
+
+/// Longest handshake line accepted (`rsync` uses `BIGPATHBUFLEN`).
+const MAX_LINE_LEN: usize = 4096;
+
+/// Caps on the server argument list a client may send.
+const MAX_SERVER_ARGS: usize = 512;
+const MAX_SERVER_ARG_LEN: usize = 4096;
+
+/// Connect timeout of `DaemonClientConfig::from_url`.
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 10_000;
+
+/// Raw socket read size for `IoRawStream`.
+const IO_READ_CHUNK: usize = 64 * 1024;
+
+/// `@RSYNCD: 31.0 sha512 sha256 sha1 md5`: protocol version, sub
+/// version and the auth digests the sender of the line supports. Old
+/// daemons send no digest list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonGreeting {
+    pub protocol: u32,
+    pub sub_protocol: u32,
+    pub digests: Vec<String>,
+}
+
+impl DaemonGreeting {
+    /// Our own greeting.
+    pub fn ours() -> Self {
+        Self {
+            protocol: CURRENT_PROTOCOL_VERSION,
+            sub_protocol: 0,
+            digests: SUPPORTED_AUTH_DIGESTS
+                .split_ascii_whitespace()
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<Self, AerorsyncError> {
+        let rest = line.strip_prefix(GREETING_PREFIX).ok_or_else(|| {
+            AerorsyncError::invalid_frame(format!("not an rsync daemon greeting: {line:?}"))
+        })?;
+        let mut words = rest.split_ascii_whitespace();
+        let version = words.next().unwrap_or_default();
+        let (major, minor) = version.split_once('.').unwrap_or((version, "0"));
+        let protocol = major.parse::<u32>().map_err(|_| {
+            AerorsyncError::invalid_frame(format!("bad protocol version in greeting: {line:?}"))
+        })?;
+        let sub_protocol = minor.parse::<u32>().map_err(|_| {
+            AerorsyncError::invalid_frame(format!("bad sub-protocol in greeting: {line:?}"))
+        })?;
+        Ok(Self {
+            protocol,
+            sub_protocol,
+            digests: words.map(str::to_string).collect(),
+        })
+    }
+
+    /// The line as sent, newline included.
+    pub fn to_line(&self) -> String {
+        let mut line = format!("{GREETING_PREFIX}{}.{}", self.protocol, self.sub_protocol);
+        for digest in &self.digests {
+            line.push(' ');
+            line.push_str(digest);
+        }
+        line.push('\n');
+        line
+    }
+}
+
+/// Digest of the `AUTHREQD` response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthDigest {
+    Sha512,
+    Sha256,
+    Sha1,
+    Md5,
+}
+
+impl AuthDigest {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha512" => Some(Self::Sha512),
+            "sha256" => Some(Self::Sha256),
+            "sha1" => Some(Self::Sha1),
+            "md5" => Some(Self::Md5),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sha512 => "sha512",
+            Self::Sha256 => "sha256",
+            Self::Sha1 => "sha1",
+            Self::Md5 => "md5",
+        }
+    }
+
+    fn digest(self, parts: &[&[u8]]) -> Vec<u8> {
+        fn run<D: sha2::Digest>(parts: &[&[u8]]) -> Vec<u8> {
+            let mut hasher = D::new();
+            for part in parts {
+                hasher.update(part);
+            }
+            hasher.finalize().to_vec()
+        }
+        match self {
+            Self::Sha512 => run::<sha2::Sha512>(parts),
+            Self::Sha256 => run::<sha2::Sha256>(parts),
+            Self::Sha1 => run::<sha1::Sha1>(parts),
+            Self::Md5 => run::<md5::Md5>(parts),
+        }
+    }
+}
+
+/// `negotiate_daemon_auth`: the first digest of the client's list that
+/// the server lists too. A greeting without a list means `md5`, the
+/// protocol-30+ default.
+pub fn pick_auth_digest(client: &DaemonGreeting, server: &DaemonGreeting) -> Option<AuthDigest> {
+    let fallback = vec!["md5".to_string()];
+    let client_list = if client.digests.is_empty() {
+        &fallback
+    } else {
+        &client.digests
+    };
+    let server_list = if server.digests.is_empty() {
+        &fallback
+    } else {
+        &server.digests
+    };
+    client_list
+        .iter()
+        .filter(|name| server_list.contains(name))
+        .find_map(|name| AuthDigest::from_name(name))
+}
+
+/// `generate_hash`: base64 (no padding) of `digest(password ||
+/// challenge)`. The server computes the same value from the secrets
+/// file and compares.
+pub fn auth_response(digest: AuthDigest, password: &str, challenge: &str) -> String {
+    let hash = digest.digest(&[password.as_bytes(), challenge.as_bytes()]);
+    base64::engine::general_purpose::STANDARD_NO_PAD.encode(hash)
+}
+
+/// A fresh `AUTHREQD` challenge: 16 random bytes, base64 without
+/// padding like `gen_challenge`.
+pub fn new_challenge() -> String {
+    let bytes: [u8; 16] = rand::random();
+    base64::engine::general_purpose::STANDARD_NO_PAD.encode(bytes)
+}
+
+/// One line of a module listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonModule {
+    pub name: String,
+    pub comment: String,
+}
+
+impl DaemonModule {
+    /// `name<TAB>comment`; the daemon pads the name to 15 columns.
+    pub fn parse_listing_line(line: &str) -> Option<Self> {
+        let (name, comment) = line.split_once('\t')?;
+        let name = name.trim_end();
+        if name.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name: name.to_string(),
+            comment: comment.to_string(),
+        })
+    }
+
+    pub fn to_listing_line(&self) -> String {
+        format!("{:<15}\t{}\n", self.name, self.comment)
+    }
+}
+
+/// `rsync://[user@]host[:port]/module[/path]` or
+/// `[user@]host::module[/path]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonUrl {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: u16,
+    /// Empty when the URL names the daemon only (module listing).
+    pub module: String,
+    /// Path inside the module, without a leading `/`; a trailing `/`
+    /// is kept.
+    pub path: String,
+}
+
+impl DaemonUrl {
+    pub fn parse(url: &str) -> Result<Self, AerorsyncError> {
+        let invalid = |why: &str| {
+            AerorsyncError::new(
+                AerorsyncErrorKind::PlannerRejected,
+                format!("invalid rsync daemon URL {url:?}: {why}"),
+            )
+        };
+        let (authority, rest, default_port) = if let Some(tail) = url.strip_prefix("rsync://") {
+            match tail.split_once('/') {
+                Some((authority, rest)) => (authority, rest, true),
+                None => (tail, "", true),
+            }
+        } else if let Some((authority, rest)) = url.split_once("::") {
+            (authority, rest, false)
+        } else {
+            return Err(invalid("expected rsync://host/module or host::module"));
+        };
+        let (user, host_port) = match authority.rsplit_once('@') {
+            Some((user, host_port)) if !user.is_empty() => (Some(user.to_string()), host_port),
+            Some(_) => return Err(invalid("empty user name")),
+            None => (None, authority),
+        };
+        let (host, port) = if let Some(bracketed) = host_port.strip_prefix('[') {
+            let (host, after) = bracketed
+                .split_once(']')
+                .ok_or_else(|| invalid("unterminated IPv6 address"))?;
+            match after.strip_prefix(':') {
+                Some(port) => (host, Some(port)),
+                None if after.is_empty() => (host, None),
+                None => return Err(invalid("garbage after IPv6 address")),
+            }
+        } else {
+            match host_port.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_port, None),
+            }
+        };
+        if host.is_empty() {
+            return Err(invalid("empty host"));
+        }
+        // `host::module` has no room for a port in its authority.
+        let port = match port {
+            Some(_) if !default_port => return Err(invalid("use rsync:// to give a port")),
+            Some(port) => port.parse::<u16>().map_err(|_| invalid("bad port"))?,
+            None => RSYNCD_PORT,
+        };
+        let rest = rest.trim_start_matches('/');
+        let (module, path) = match rest.split_once('/') {
+            Some((module, path)) => (module, path.trim_start_matches('/')),
+            None => (rest, ""),
+        };
+        Ok(Self {
+            user,
+            host: host.to_string(),
+            port,
+            module: module.to_string(),
+            path: path.to_string(),
+        })
+    }
+}
+
+/// Read one `\n`-terminated line, byte by byte, without the line end.
+/// `None` on EOF before the first byte.
+pub async fn read_line<S>(stream: &mut S) -> Result<Option<String>, AerorsyncError>
+where
+    S: AsyncRead + Unpin + ?Sized,
+{
+    let mut line = Vec::new();
+    loop {
+        let byte = match stream.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                if line.is_empty() {
+                    return Ok(None);
+                }
+                return Err(AerorsyncError::transport(
+                    "rsync daemon connection closed mid line",
+                ));
+            }
+            Err(e) => {
+                return Err(AerorsyncError::transport(format!(
+                    "rsync daemon read failed: {e}"
+                )))
+            }
+        };
+        if byte == b'\n' {
+            break;
+        }
+        if line.len() >= MAX_LINE_LEN {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "rsync daemon line longer than {MAX_LINE_LEN} bytes"
+            )));
+        }
+        line.push(byte);
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| AerorsyncError::invalid_frame("rsync daemon line is not UTF-8"))
+}
+
+/// `read_line` where EOF is an error.
+pub async fn expect_line<S>(stream: &mut S, what: &str) -> Result<String, AerorsyncError>
+where
+    S: AsyncRead + Unpin + ?Sized,
+{
+    read_line(stream).await?.ok_or_else(|| {
+        AerorsyncError::transport(format!("rsync daemon connection closed before {what}"))
+    })
+}
+
+pub async fn write_text<S>(stream: &mut S, text: &str) -> Result<(), AerorsyncError>
+where
+    S: AsyncWrite + Unpin + ?Sized,
+{
+    stream
+        .write_all(text.as_bytes())
+        .await
+        .map_err(|e| AerorsyncError::transport(format!("rsync daemon write failed: {e}")))?;
+    stream
+        .flush()
+        .await
+        .map_err(|e| AerorsyncError::transport(format!("rsync daemon flush failed: {e}")))
+}
+
+/// Client side of the greetings: ours goes out, the server's comes back.
+pub async fn exchange_greetings<S>(stream: &mut S) -> Result<DaemonGreeting, AerorsyncError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + ?Sized,
+{
+    write_text(stream, &DaemonGreeting::ours().to_line()).await?;
+    let line = expect_line(stream, "the daemon greeting").await?;
+    if let Some(message) = error_line_message(&line) {
+        return Err(AerorsyncError::remote(0, message));
+    }
+    DaemonGreeting::parse(&line)
+}
+
+fn error_line_message(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix(ERROR_PREFIX)?;
+    Some(rest.trim_start_matches(':').trim())
+}
+
+/// What a daemon sent back when a module was opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonSession {
+    /// `min(ours, the daemon's)`: the protocol of the binary session.
+    pub protocol: u32,
+    pub greeting: DaemonGreeting,
+    /// Message-of-the-day lines printed before `@RSYNCD: OK`.
+    pub motd: Vec<String>,
+}
+
+/// User name and password for modules with `auth users`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct DaemonCredentials {
+    pub user: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for DaemonCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DaemonCredentials")
+            .field("user", &self.user)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Ask for the module listing (`#list`). Returns the modules and any
+/// other line the daemon printed (its MOTD).
+pub async fn list_modules<S>(
+    stream: &mut S,
+) -> Result<(Vec<DaemonModule>, Vec<String>), AerorsyncError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + ?Sized,
+{
+    exchange_greetings(stream).await?;
+    write_text(stream, "#list\n").await?;
+    let mut modules = Vec::new();
+    let mut motd = Vec::new();
+    loop {
+        let line = expect_line(stream, "the end of the module listing").await?;
+        if line == DAEMON_EXIT {
+            return Ok((modules, motd));
+        }
+        if let Some(message) = error_line_message(&line) {
+            return Err(AerorsyncError::remote(0, message));
+        }
+        match DaemonModule::parse_listing_line(&line) {
+            Some(module) => modules.push(module),
+            None => motd.push(line),
+        }
+    }
+}
+
+/// Open `module`: greetings, module name, challenge when the daemon
+/// asks for one, up to `@RSYNCD: OK`. The caller sends the argument
+/// list next.
+pub async fn open_module<S>(
+    stream: &mut S,
+    module: &str,
+    credentials: Option<&DaemonCredentials>,
+) -> Result<DaemonSession, AerorsyncError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + ?Sized,
+{
+    if module.is_empty() || module.contains(['\n', '\r', '/']) || module.starts_with('#') {
+        return Err(AerorsyncError::new(
+            AerorsyncErrorKind::PlannerRejected,
+            format!("invalid rsync module name {module:?}"),
+        ));
+    }
+    let greeting = exchange_greetings(stream).await?;
+    if greeting.protocol < 30 {
+        return Err(AerorsyncError::unsupported_version(format!(
+            "rsync daemon speaks protocol {}, 30 or newer is required",
+            greeting.protocol
+        )));
+    }
+    write_text(stream, &format!("{module}\n")).await?;
+    let mut motd = Vec::new();
+    loop {
+        let line = expect_line(stream, "the module reply").await?;
+        if line == DAEMON_OK {
+            break;
+        }
+        if let Some(challenge) = line.strip_prefix(AUTHREQD_PREFIX) {
+            let credentials = credentials.ok_or_else(|| {
+                AerorsyncError::new(
+                    AerorsyncErrorKind::NegotiationFailed,
+                    format!("rsync module {module:?} requires a user name and password"),
+                )
+            })?;
+            let digest = pick_auth_digest(&DaemonGreeting::ours(), &greeting).ok_or_else(|| {
+                AerorsyncError::new(
+                    AerorsyncErrorKind::NegotiationFailed,
+                    format!(
+                        "no common auth digest with the daemon (it offers {:?})",
+                        greeting.digests.join(" ")
+                    ),
+                )
+            })?;
+            let response = auth_response(digest, &credentials.password, challenge.trim());
+            write_text(stream, &format!("{} {response}\n", credentials.user)).await?;
+            continue;
+        }
+        if line == DAEMON_EXIT {
+            return Err(AerorsyncError::remote(
+                0,
+                format!("rsync daemon closed module {module:?}"),
+            ));
+        }
+        if let Some(message) = error_line_message(&line) {
+            return Err(AerorsyncError::remote(0, message));
+        }
+        motd.push(line);
+    }
+    Ok(DaemonSession {
+        protocol: greeting.protocol.min(CURRENT_PROTOCOL_VERSION),
+        greeting,
+        motd,
+    })
+}
+
+/// Send the server argument list: each argument NUL-terminated, then
+/// an empty one (`protocol >= 30` framing).
+pub async fn send_server_args<S>(stream: &mut S, args: &[String]) -> Result<(), AerorsyncError>
+where
+    S: AsyncWrite + Unpin + ?Sized,
+{
+    let mut bytes = Vec::new();
+    for arg in args {
+        if arg.contains('\0') {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "server argument {arg:?} contains a NUL byte"
+            )));
+        }
+        bytes.extend_from_slice(arg.as_bytes());
+        bytes.push(0);
+    }
+    bytes.push(0);
+    stream
+        .write_all(&bytes)
+        .await
+        .map_err(|e| AerorsyncError::transport(format!("rsync daemon write failed: {e}")))
+}
+
+/// Server counterpart of `send_server_args`.
+pub async fn read_server_args<S>(stream: &mut S) -> Result<Vec<String>, AerorsyncError>
+where
+    S: AsyncRead + Unpin + ?Sized,
+{
+    let mut args = Vec::new();
+    let mut current = Vec::new();
+    loop {
+        let byte = stream.read_u8().await.map_err(|e| {
+            AerorsyncError::transport(format!("rsync daemon client closed its arguments: {e}"))
+        })?;
+        if byte != 0 {
+            if current.len() >= MAX_SERVER_ARG_LEN {
+                return Err(AerorsyncError::invalid_frame("server argument too long"));
+            }
+            current.push(byte);
+            continue;
+        }
+        if current.is_empty() {
+            return Ok(args);
+        }
+        if args.len() >= MAX_SERVER_ARGS {
+            return Err(AerorsyncError::invalid_frame("too many server arguments"));
+        }
+        let arg = String::from_utf8(std::mem::take(&mut current))
+            .map_err(|_| AerorsyncError::invalid_frame("server argument is not UTF-8"))?;
+        args.push(arg);
+    }
+}
+
+/// `RawByteStream` over any tokio byte stream, carrying the protocol
+/// the daemon handshake agreed on.
+pub struct IoRawStream<S> {
+    io: S,
+    agreed_protocol: Option<u32>,
+}
+
+impl<S> IoRawStream<S> {
+    pub fn new(io: S, agreed_protocol: Option<u32>) -> Self {
+        Self {
+            io,
+            agreed_protocol,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> RawByteStream for IoRawStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn read_bytes(&mut self, max: usize) -> Result<Vec<u8>, AerorsyncError> {
+        let mut buf = vec![0u8; max.clamp(1, IO_READ_CHUNK)];
+        let n = self
+            .io
+            .read(&mut buf)
+            .await
+            .map_err(|e| AerorsyncError::transport(format!("rsync daemon read failed: {e}")))?;
+        buf.truncate(n);
+        Ok(buf)
+    }
+
+    async fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), AerorsyncError> {
+        self.io
+            .write_all(bytes)
+            .await
+            .map_err(|e| AerorsyncError::transport(format!("rsync daemon write failed: {e}")))
+    }
+
+    async fn shutdown(&mut self) -> Result<(), AerorsyncError> {
+        match self.io.shutdown().await {
+            Ok(()) => Ok(()),
+            // The peer hanging up first is a normal end of session.
+            Err(e) if e.kind() == std::io::ErrorKind::NotConnected => Ok(()),
+            Err(e) => Err(AerorsyncError::transport(format!(
+                "rsync daemon shutdown failed: {e}"
+            ))),
+        }
+    }
+
+    fn agreed_protocol(&self) -> Option<u32> {
+        self.agreed_protocol
+    }
+}
+
+/// Where and as whom to reach an rsync daemon module.
+#[derive(Debug, Clone)]
+pub struct DaemonClientConfig {
+    pub host: String,
+    pub port: u16,
+    pub module: String,
+    pub credentials: Option<DaemonCredentials>,
+    pub connect_timeout_ms: u64,
+}
+
+impl DaemonClientConfig {
+    /// `password` applies when the URL names a user.
+    pub fn from_url(url: &DaemonUrl, password: Option<String>) -> Self {
+        let credentials = url.user.clone().map(|user| DaemonCredentials {
+            user,
+            password: password.unwrap_or_default(),
+        });
+        Self {
+            host: url.host.clone(),
+            port: url.port,
+            module: url.module.clone(),
+            credentials,
+            connect_timeout_ms: DEFAULT_CONNECT_TIMEOUT_MS,
+        }
+    }
+}
+
+/// Transport for `rsync://` modules: one TCP connection per session,
+/// handshake included, then the usual raw stream. There is no remote
+/// shell, so `exec` and the RSNP stream are refused.
+pub struct DaemonTransport {
+    config: DaemonClientConfig,
+    cancel_flag: Arc<AtomicBool>,
+}
+
+impl DaemonTransport {
+    pub fn new(config: DaemonClientConfig) -> Self {
+        Self {
+            config,
+            cancel_flag: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn config(&self) -> &DaemonClientConfig {
+        &self.config
+    }
+
+    async fn connect(&self) -> Result<TcpStream, AerorsyncError> {
+        if self.cancel_flag.load(Ordering::SeqCst) {
+            return Err(AerorsyncError::cancelled(
+                "DaemonTransport cancelled before connect",
+            ));
+        }
+        let address = (self.config.host.as_str(), self.config.port);
+        let timeout = Duration::from_millis(self.config.connect_timeout_ms);
+        let stream = tokio::time::timeout(timeout, TcpStream::connect(address))
+            .await
+            .map_err(|_| {
+                AerorsyncError::transport(format!(
+                    "rsync daemon {}:{} did not answer within {} ms",
+                    self.config.host, self.config.port, self.config.connect_timeout_ms
+                ))
+            })?
+            .map_err(|e| {
+                AerorsyncError::transport(format!(
+                    "cannot connect to rsync daemon {}:{}: {e}",
+                    self.config.host, self.config.port
+                ))
+            })?;
+        let _ = stream.set_nodelay(true);
+        Ok(stream)
+    }
+
+    /// The modules the daemon lists (those without `list = no`).
+    pub async fn list_modules(&self) -> Result<Vec<DaemonModule>, AerorsyncError> {
+        let mut stream = self.connect().await?;
+        let (modules, _motd) = list_modules(&mut stream).await?;
+        Ok(modules)
+    }
+
+    /// `module/path` as the daemon resolves it.
+    fn module_path(&self, path: &str) -> String {
+        format!("{}/{}", self.config.module, path.trim_start_matches('/'))
+    }
+}
+
+#[async_trait]
+impl RemoteShellTransport for DaemonTransport {
+    type Stream = DaemonUnusedStream;
+
+    /// Greetings plus a module listing, which every daemon answers
+    /// without authentication.
+    async fn probe(&self) -> Result<TransportProbe, AerorsyncError> {
+        let mut stream = self.connect().await?;
+        let greeting = exchange_greetings(&mut stream).await?;
+        write_text(&mut stream, "#list\n").await?;
+        while let Some(line) = read_line(&mut stream).await? {
+            if line == DAEMON_EXIT {
+                break;
+            }
+        }
+        let banner = greeting.to_line().trim_end().to_string();
+        Ok(TransportProbe {
+            remote_banner: banner,
+            protocol: ProtocolVersion(greeting.protocol.min(CURRENT_PROTOCOL_VERSION)),
+            supports_remote_shell: false,
+        })
+    }
+
+    async fn exec(
+        &self,
+        request: RemoteExecRequest,
+    ) -> Result<RemoteCommandOutput, AerorsyncError> {
+        Err(AerorsyncError::transport(format!(
+            "an rsync daemon runs no commands (asked for {})",
+            request.program
+        )))
+    }
+
+    async fn open_stream(
+        &self,
+        _request: RemoteExecRequest,
+    ) -> Result<Self::Stream, AerorsyncError> {
+        Err(AerorsyncError::transport(
+            "DaemonTransport does not support the legacy RSNP framed stream",
+        ))
+    }
+
+    async fn cancel(&self) -> Result<(), AerorsyncError> {
+        self.cancel_flag.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle::new(self.cancel_flag.clone(), None)
+    }
+}
+
+#[async_trait]
+impl RawRemoteShellTransport for DaemonTransport {
+    type RawStream = IoRawStream<TcpStream>;
+
+    /// Open the module and send `request.args` (the `rsync --server`
+    /// argument list, program name dropped) with the last argument, the
+    /// path, moved under the module.
+    async fn open_raw_stream(
+        &self,
+        request: RemoteExecRequest,
+    ) -> Result<Self::RawStream, AerorsyncError> {
+        let mut args = request.args;
+        let path = args
+            .pop()
+            .ok_or_else(|| AerorsyncError::invalid_frame("server argument list without a path"))?;
+        args.push(self.module_path(&path));
+        let mut stream = self.connect().await?;
+        let session = open_module(
+            &mut stream,
+            &self.config.module,
+            self.config.credentials.as_ref(),
+        )
+        .await?;
+        for line in &session.motd {
+            tracing::info!("rsync daemon {}: {}", self.config.host, line);
+        }
+        send_server_args(&mut stream, &args).await?;
+        Ok(IoRawStream::new(stream, Some(session.protocol)))
+    }
+}
+
+/// `RemoteShellTransport::Stream` placeholder: daemons only speak the
+/// raw wire.
+pub struct DaemonUnusedStream;
+
+#[async_trait]
+impl BidirectionalByteStream for DaemonUnusedStream {
+    async fn write_frame(&mut self, _frame: &[u8]) -> Result<(), AerorsyncError> {
+        Err(AerorsyncError::transport(
+            "DaemonUnusedStream cannot be driven: rsync daemons only speak the raw wire",
+        ))
+    }
+
+    async fn read_frame(&mut self) -> Result<Vec<u8>, AerorsyncError> {
+        Err(AerorsyncError::transport(
+            "DaemonUnusedStream cannot be driven: rsync daemons only speak the raw wire",
+        ))
+    }
+
+    async fn shutdown(&mut self) -> Result<(), AerorsyncError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn greeting_round_trips_and_tolerates_old_daemons() {
+        let line = "@RSYNCD: 31.0 sha512 sha256 sha1 md5 md4";
+        let greeting = DaemonGreeting::parse(line).unwrap();
+        assert_eq!(greeting.protocol, 31);
+        assert_eq!(greeting.sub_protocol, 0);
+        assert_eq!(greeting.digests.len(), 5);
+        assert_eq!(greeting.to_line(), format!("{line}\n"));
+
+        let old = DaemonGreeting::parse("@RSYNCD: 30").unwrap();
+        assert_eq!((old.protocol, old.sub_protocol), (30, 0));
+        assert!(old.digests.is_empty());
+        assert!(DaemonGreeting::parse("SSH-2.0-OpenSSH").is_err());
+    }
+
+    #[test]
+    fn auth_response_hashes_password_then_challenge() {
+        let challenge = "Xj0xQ6cY3m1c9FrJ2bGh0A";
+        assert_eq!(
+            auth_response(AuthDigest::Md5, "hunter2", challenge),
+            "PvI8MrQQIbOl4v7dyCJZqg"
+        );
+        assert_eq!(
+            auth_response(AuthDigest::Sha1, "hunter2", challenge),
+            "H32X+XWROAPqg/03V5XFCa91+y0"
+        );
+        assert_eq!(
+            auth_response(AuthDigest::Sha256, "hunter2", challenge),
+            "VXbo6kVHiX0q33rkAswFvLePnz8Ha3+AbJlHwgCzpI4"
+        );
+        assert_eq!(new_challenge().len(), 22);
+    }
+
+    #[test]
+    fn auth_digest_follows_the_client_preference() {
+        let stock = DaemonGreeting::parse("@RSYNCD: 31.0 sha512 sha256 sha1 md5 md4").unwrap();
+        let md_only = DaemonGreeting::parse("@RSYNCD: 31.0 md5 md4").unwrap();
+        let old = DaemonGreeting::parse("@RSYNCD: 30.0").unwrap();
+        let md4_only = DaemonGreeting::parse("@RSYNCD: 31.0 md4").unwrap();
+        let ours = DaemonGreeting::ours();
+        assert_eq!(pick_auth_digest(&ours, &stock), Some(AuthDigest::Sha512));
+        assert_eq!(pick_auth_digest(&ours, &md_only), Some(AuthDigest::Md5));
+        assert_eq!(pick_auth_digest(&ours, &old), Some(AuthDigest::Md5));
+        assert_eq!(pick_auth_digest(&md_only, &stock), Some(AuthDigest::Md5));
+        assert_eq!(pick_auth_digest(&ours, &md4_only), None);
+    }
+
+    #[test]
+    fn daemon_urls_parse_in_both_spellings() {
+        let url = DaemonUrl::parse("rsync://alice@nas.local:8730/backup/photos/").unwrap();
+        assert_eq!(url.user.as_deref(), Some("alice"));
+        assert_eq!(url.host, "nas.local");
+        assert_eq!(url.port, 8730);
+        assert_eq!(url.module, "backup");
+        assert_eq!(url.path, "photos/");
+
+        let url = DaemonUrl::parse("mirror.example.org::debian").unwrap();
+        assert_eq!(url.user, None);
+        assert_eq!(url.port, RSYNCD_PORT);
+        assert_eq!((url.module.as_str(), url.path.as_str()), ("debian", ""));
+
+        let url = DaemonUrl::parse("rsync://[::1]:8873/m/a/b").unwrap();
+        assert_eq!((url.host.as_str(), url.port), ("::1", 8873));
+        assert_eq!(url.path, "a/b");
+
+        assert_eq!(DaemonUrl::parse("rsync://host").unwrap().module, "");
+        assert!(DaemonUrl::parse("host:/path").is_err());
+        assert!(DaemonUrl::parse("rsync://host:99999/m").is_err());
+        assert!(DaemonUrl::parse("rsync://@host/m").is_err());
+    }
+
+    #[tokio::test]
+    async fn listing_separates_modules_from_the_motd() {
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        let daemon = tokio::spawn(async move {
+            write_text(&mut server, "@RSYNCD: 31.0 sha512 md5\n")
+                .await
+                .unwrap();
+            let greeting = expect_line(&mut server, "greeting").await.unwrap();
+            assert!(greeting.starts_with("@RSYNCD: 31.0 "));
+            assert_eq!(expect_line(&mut server, "list").await.unwrap(), "#list");
+            write_text(
+                &mut server,
+                "Welcome to the NAS\n\nbackup         \tNightly backups\npublic         \t\n@RSYNCD: EXIT\n",
+            )
+            .await
+            .unwrap();
+        });
+        let (modules, motd) = list_modules(&mut client).await.unwrap();
+        daemon.await.unwrap();
+        assert_eq!(
+            modules,
+            vec![
+                DaemonModule {
+                    name: "backup".into(),
+                    comment: "Nightly backups".into()
+                },
+                DaemonModule {
+                    name: "public".into(),
+                    comment: String::new()
+                },
+            ]
+        );
+        assert_eq!(motd, vec!["Welcome to the NAS".to_string(), String::new()]);
+    }
+
+    #[tokio::test]
+    async fn open_module_answers_the_challenge_and_stops_at_ok() {
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        let daemon = tokio::spawn(async move {
+            write_text(&mut server, "@RSYNCD: 32.0 sha256 md5\n")
+                .await
+                .unwrap();
+            expect_line(&mut server, "greeting").await.unwrap();
+            assert_eq!(expect_line(&mut server, "module").await.unwrap(), "backup");
+            write_text(&mut server, "@RSYNCD: AUTHREQD Xj0xQ6cY3m1c9FrJ2bGh0A\n")
+                .await
+                .unwrap();
+            let answer = expect_line(&mut server, "auth").await.unwrap();
+            assert_eq!(
+                answer,
+                format!(
+                    "alice {}",
+                    auth_response(AuthDigest::Sha256, "hunter2", "Xj0xQ6cY3m1c9FrJ2bGh0A")
+                )
+            );
+            write_text(&mut server, "@RSYNCD: OK\n").await.unwrap();
+            read_server_args(&mut server).await.unwrap()
+        });
+        let credentials = DaemonCredentials {
+            user: "alice".into(),
+            password: "hunter2".into(),
+        };
+        let session = open_module(&mut client, "backup", Some(&credentials))
+            .await
+            .unwrap();
+        assert_eq!(session.protocol, 31);
+        let args = vec!["--server".to_string(), ".".into(), "backup/".into()];
+        send_server_args(&mut client, &args).await.unwrap();
+        assert_eq!(daemon.await.unwrap(), args);
+    }
+
+    #[tokio::test]
+    async fn open_module_surfaces_daemon_errors() {
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            write_text(&mut server, "@RSYNCD: 31.0\n").await.unwrap();
+            expect_line(&mut server, "greeting").await.unwrap();
+            expect_line(&mut server, "module").await.unwrap();
+            write_text(&mut server, "@ERROR: Unknown module 'nope'\n")
+                .await
+                .unwrap();
+        });
+        let err = open_module(&mut client, "nope", None).await.unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::RemoteError);
+        assert!(
+            err.detail.contains("Unknown module 'nope'"),
+            "{}",
+            err.detail
+        );
+    }
+}
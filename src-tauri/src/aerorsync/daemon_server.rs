@@ -0,0 +1,1247 @@
+//! Standalone rsync daemon: `rsyncd.conf`-style modules served over
+//! TCP to stock rsync clients and to `DaemonTransport`.
+//!
+//! The text handshake (`daemon.rs`) picks the module and checks the
+//! credentials; the client's argument list then becomes a
+//! `ServerSession` and the connection is handed to `AerorsyncDriver`,
+//! which runs the same tree loops as the client side, with the roles
+//! mirrored.
+//!
+//! Scope, deliberately narrower than `rsyncd`:
+//! - a request names a directory (`module/` or `module/dir/`) and is
+//!   recursive with incremental recursion, as rsync 3.x clients send
+//!   by default;
+//! - no filter rules, `--dry-run`, backups or `--relative`: such
+//!   sessions are refused with a message instead of half-honoured;
+//! - no `uid`/`gid` switch or chroot: paths are confined to the module
+//!   by resolution, and the daemon runs as the user who started it.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+
+use async_trait::async_trait;
+use subtle::ConstantTimeEq;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+
+use crate::aerorsync::daemon::{
+    auth_response, expect_line, new_challenge, pick_auth_digest, read_line, read_server_args,
+    write_text, DaemonGreeting, DaemonModule, DaemonUnusedStream, IoRawStream, AUTHREQD_PREFIX,
+    DAEMON_EXIT, DAEMON_OK, RSYNCD_PORT,
+};
+use crate::aerorsync::delta_transport_impl::{scan_local_tree, LocalTreeRoot};
+use crate::aerorsync::engine_adapter::CurrentDeltaSyncBridge;
+use crate::aerorsync::events::{AerorsyncEvent, EventSink};
+use crate::aerorsync::native_driver::{AerorsyncDriver, ServerSession, SessionFlags};
+use crate::aerorsync::negotiation::AlgorithmPreferences;
+use crate::aerorsync::real_wire::{
+    CF_AVOID_XATTR_OPTIM, CF_CHKSUM_SEED_FIX, CF_ID0_NAMES, CF_INC_RECURSE, CF_INPLACE_PARTIAL_DIR,
+    CF_SAFE_FLIST, CF_SYMLINK_ICONV, CF_SYMLINK_TIMES, CF_VARINT_FLIST_FLAGS,
+};
+use crate::aerorsync::remote_command::{AppendMode, TransferOptions};
+use crate::aerorsync::transport::{
+    CancelHandle, RawRemoteShellTransport, RemoteCommandOutput, RemoteExecRequest,
+    RemoteShellTransport, TransportProbe,
+};
+use crate::aerorsync::types::{AerorsyncError, AerorsyncErrorKind, ProtocolVersion};
+use crate::aerorsync::CURRENT_PROTOCOL_VERSION;
+
+/// Parameters whose `rsyncd` meaning we cannot honour and that would
+/// widen access if ignored: the config is refused instead.
+const REFUSED_PARAMETERS: &[&str] = &[
+    "hostsallow",
+    "hostsdeny",
+    "refuseoptions",
+    "exclude",
+    "include",
+    "filter",
+    "excludefrom",
+    "includefrom",
+    "daemonchroot",
+];
+
+/// The `rsyncd.conf` subset the daemon understands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsyncdConfig {
+    pub port: u16,
+    /// Listen address; all interfaces when `None`.
+    pub address: Option<String>,
+    /// Lines sent before the module listing or the module reply.
+    pub motd_file: Option<PathBuf>,
+    pub modules: Vec<RsyncdModule>,
+    /// Parameters that were parsed but have no effect here.
+    pub warnings: Vec<String>,
+    /// Checksums and compressors every session offers. Not an
+    /// `rsyncd.conf` parameter: `rsyncd` takes them from
+    /// `RSYNC_CHECKSUM_LIST` / `RSYNC_COMPRESS_LIST`, and so does the
+    /// standalone binary.
+    pub algorithms: AlgorithmPreferences,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsyncdModule {
+    pub name: String,
+    pub path: PathBuf,
+    pub comment: String,
+    /// `read only` (default yes): uploads are refused.
+    pub read_only: bool,
+    /// `write only` (default no): downloads are refused.
+    pub write_only: bool,
+    /// `list` (default yes): shown in the module listing.
+    pub list: bool,
+    /// `auth users`: when non-empty, only these users get in.
+    pub auth_users: Vec<String>,
+    /// `secrets file`: `user:password` lines.
+    pub secrets_file: Option<PathBuf>,
+    /// `strict modes` (default yes): refuse a secrets file that other
+    /// users can read.
+    pub strict_modes: bool,
+}
+
+impl RsyncdModule {
+    fn with_defaults(name: &str, defaults: &HashMap<String, String>) -> Self {
+        let mut module = Self {
+            name: name.to_string(),
+            path: PathBuf::new(),
+            comment: String::new(),
+            read_only: true,
+            write_only: false,
+            list: true,
+            auth_users: Vec::new(),
+            secrets_file: None,
+            strict_modes: true,
+        };
+        // Module parameters in the global section are defaults for
+        // every module; they were validated when read.
+        for (key, value) in defaults {
+            let _ = module.set(key, value);
+        }
+        module
+    }
+
+    /// Apply one parameter. `Ok(false)` when the key is not a module
+    /// parameter.
+    fn set(&mut self, key: &str, value: &str) -> Result<bool, String> {
+        match key {
+            "path" => self.path = PathBuf::from(value),
+            "comment" => self.comment = value.to_string(),
+            "readonly" => self.read_only = parse_bool(key, value)?,
+            "writeonly" => self.write_only = parse_bool(key, value)?,
+            "list" => self.list = parse_bool(key, value)?,
+            "authusers" => self.auth_users = parse_auth_users(value)?,
+            "secretsfile" => self.secrets_file = Some(PathBuf::from(value)),
+            "strictmodes" => self.strict_modes = parse_bool(key, value)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// User name to password, from `secrets file`.
+    pub fn load_secrets(&self) -> Result<HashMap<String, String>, String> {
+        let path = self
+            .secrets_file
+            .as_ref()
+            .ok_or_else(|| format!("module {} has auth users but no secrets file", self.name))?;
+        #[cfg(unix)]
+        if self.strict_modes {
+            use std::os::unix::fs::PermissionsExt;
+            let meta = std::fs::metadata(path)
+                .map_err(|e| format!("cannot stat secrets file {}: {e}", path.display()))?;
+            if meta.permissions().mode() & 0o007 != 0 {
+                return Err(format!(
+                    "secrets file {} must not be other-accessible (see strict modes)",
+                    path.display()
+                ));
+            }
+        }
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read secrets file {}: {e}", path.display()))?;
+        Ok(text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(user, password)| (user.to_string(), password.to_string()))
+            .collect())
+    }
+}
+
+impl RsyncdConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        Self::parse(&text)
+    }
+
+    /// Parse `rsyncd.conf` syntax: `[module]` sections, `key = value`
+    /// lines, `#`/`;` comments. Keys ignore case and spaces, so `read
+    /// only` and `readonly` are the same parameter.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self {
+            port: RSYNCD_PORT,
+            address: None,
+            motd_file: None,
+            modules: Vec::new(),
+            warnings: Vec::new(),
+            algorithms: AlgorithmPreferences::default(),
+        };
+        let mut defaults: HashMap<String, String> = HashMap::new();
+        let mut current: Option<RsyncdModule> = None;
+        for (index, raw) in text.lines().enumerate() {
+            let lineno = index + 1;
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[') {
+                let name = section
+                    .strip_suffix(']')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty() && !name.contains('/'))
+                    .ok_or_else(|| format!("line {lineno}: bad section header {line:?}"))?;
+                if let Some(module) = current.take() {
+                    config.push_module(module)?;
+                }
+                current = Some(RsyncdModule::with_defaults(name, &defaults));
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {lineno}: expected `key = value`, got {line:?}"))?;
+            let key: String = key
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .flat_map(char::to_lowercase)
+                .collect();
+            let value = value.trim();
+            if REFUSED_PARAMETERS.contains(&key.as_str()) {
+                return Err(format!(
+                    "line {lineno}: `{}` is not supported by this daemon",
+                    key
+                ));
+            }
+            match current.as_mut() {
+                Some(module) => {
+                    if !module
+                        .set(&key, value)
+                        .map_err(|e| format!("line {lineno}: {e}"))?
+                    {
+                        config
+                            .warnings
+                            .push(format!("line {lineno}: parameter `{key}` ignored"));
+                    }
+                }
+                None => match key.as_str() {
+                    "port" => {
+                        config.port = value
+                            .parse()
+                            .map_err(|_| format!("line {lineno}: bad port {value:?}"))?
+                    }
+                    "address" => config.address = Some(value.to_string()),
+                    "motdfile" => config.motd_file = Some(PathBuf::from(value)),
+                    _ => {
+                        let mut probe = RsyncdModule::with_defaults("", &HashMap::new());
+                        if probe
+                            .set(&key, value)
+                            .map_err(|e| format!("line {lineno}: {e}"))?
+                        {
+                            defaults.insert(key, value.to_string());
+                        } else {
+                            config
+                                .warnings
+                                .push(format!("line {lineno}: parameter `{key}` ignored"));
+                        }
+                    }
+                },
+            }
+        }
+        if let Some(module) = current.take() {
+            config.push_module(module)?;
+        }
+        Ok(config)
+    }
+
+    fn push_module(&mut self, module: RsyncdModule) -> Result<(), String> {
+        if module.path.as_os_str().is_empty() {
+            return Err(format!("module {} has no path", module.name));
+        }
+        if self.module(&module.name).is_some() {
+            return Err(format!("module {} is defined twice", module.name));
+        }
+        if !module.auth_users.is_empty() && module.secrets_file.is_none() {
+            return Err(format!(
+                "module {} has auth users but no secrets file",
+                module.name
+            ));
+        }
+        self.modules.push(module);
+        Ok(())
+    }
+
+    pub fn module(&self, name: &str) -> Option<&RsyncdModule> {
+        self.modules.iter().find(|module| module.name == name)
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" | "true" | "on" | "1" => Ok(true),
+        "no" | "false" | "off" | "0" => Ok(false),
+        _ => Err(format!("`{key}` expects yes or no, got {value:?}")),
+    }
+}
+
+/// Comma- or space-separated user names. Group entries (`@group`) and
+/// per-user access (`user:ro`) are refused rather than read as names.
+fn parse_auth_users(value: &str) -> Result<Vec<String>, String> {
+    value
+        .split([',', ' ', '\t'])
+        .filter(|user| !user.is_empty())
+        .map(|user| {
+            if user.starts_with('@') || user.contains(':') {
+                Err(format!("auth users entry {user:?} is not supported"))
+            } else {
+                Ok(user.to_string())
+            }
+        })
+        .collect()
+}
+
+/// The client's `rsync --server` argument list, resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerRequest {
+    pub session: ServerSession,
+    /// `--sender`: the client downloads.
+    pub sender: bool,
+    pub recursive: bool,
+    /// Path inside the module, `/`-separated, without the module name.
+    pub path: String,
+    /// Why the session must be turned down after the preamble.
+    pub refusal: Option<String>,
+}
+
+impl ServerRequest {
+    /// Parse `args` for `module`. `Err` only when there is no usable
+    /// session at all (not a `--server` list); option-level problems
+    /// land in `refusal` so they reach the client as `MSG_ERROR`.
+    pub fn parse(args: &[String], module: &str) -> Result<Self, String> {
+        let mut request = Self::parse_args(args)?;
+        match module_relative(&request.path, module) {
+            Ok(path) => request.path = path,
+            Err(e) => {
+                request.path.clear();
+                request.refusal.get_or_insert(e);
+            }
+        }
+        if !request.recursive {
+            request
+                .refusal
+                .get_or_insert("this daemon serves directory trees only: use -r".to_string());
+        }
+        Ok(request)
+    }
+
+    /// The module-independent half of [`Self::parse`]: options, flags
+    /// and compat bits, with `path` left exactly as the client sent it.
+    pub(crate) fn parse_args(args: &[String]) -> Result<Self, String> {
+        let mut iter = args.iter();
+        if iter.next().map(String::as_str) != Some("--server") {
+            return Err("argument list does not start with --server".to_string());
+        }
+        let mut options = TransferOptions::default();
+        let mut flags = SessionFlags {
+            always_checksum: false,
+            preserve_uid: false,
+            preserve_gid: false,
+            preserve_links: false,
+        };
+        let mut sender = false;
+        let mut recursive = false;
+        let mut compress = false;
+        let mut client_info = String::new();
+        let mut refusal: Option<String> = None;
+        let mut refuse = |why: String| {
+            refusal.get_or_insert(why);
+        };
+        let mut positional: Vec<&str> = Vec::new();
+        while let Some(arg) = iter.next() {
+            if !positional.is_empty() || arg == "." || !arg.starts_with('-') {
+                positional.push(arg);
+                continue;
+            }
+            if let Some(long) = arg.strip_prefix("--") {
+                let (name, value) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => (long, None),
+                };
+                match name {
+                    "sender" => sender = true,
+                    "stats" | "log-format" | "out-format" | "timeout" => {}
+                    "delete" | "delete-during" => options.delete = true,
+                    "inplace" => options.inplace = true,
+                    "append" => {
+                        options.append = match options.append {
+                            AppendMode::Off => AppendMode::Append,
+                            _ => AppendMode::AppendVerify,
+                        }
+                    }
+                    "partial-dir" => match value.or_else(|| iter.next().map(String::as_str)) {
+                        Some(dir) => options.partial_dir = Some(dir.to_string()),
+                        None => refuse("--partial-dir needs a value".to_string()),
+                    },
+                    "mkpath" => options.mkpath = true,
+                    "compress-level" => match value.map(str::parse::<i32>) {
+                        Some(Ok(level)) => options.algorithms.compress_level = Some(level),
+                        _ => refuse("--compress-level needs an integer value".to_string()),
+                    },
+                    _ => refuse(format!("option --{name} is not supported by this daemon")),
+                }
+                continue;
+            }
+            let letters = &arg[1..];
+            for (at, letter) in letters.char_indices() {
+                match letter {
+                    'l' => flags.preserve_links = true,
+                    'o' => flags.preserve_uid = true,
+                    'g' => flags.preserve_gid = true,
+                    'c' => flags.always_checksum = true,
+                    'r' => recursive = true,
+                    'z' => compress = true,
+                    'H' => options.hard_links = true,
+                    'A' => options.acls = true,
+                    'X' => options.xattrs = true,
+                    'S' => options.sparse = true,
+                    // Devices, times and permissions are always kept;
+                    // verbosity, itemizing, whole-file and
+                    // one-file-system change nothing on a tree served
+                    // from one directory.
+                    'D' | 't' | 'p' | 'v' | 'q' | 'i' | 'W' | 'x' => {}
+                    'e' => {
+                        client_info = letters[at + 1..].to_string();
+                        break;
+                    }
+                    other => refuse(format!("option -{other} is not supported by this daemon")),
+                }
+            }
+        }
+        let path = match positional.as_slice() {
+            [".", path] => path.to_string(),
+            _ => {
+                refuse("expected exactly one path after `.`".to_string());
+                String::new()
+            }
+        };
+
+        let mut compat_flags = 0;
+        for (letter, bit) in [
+            ('L', CF_SYMLINK_TIMES),
+            ('s', CF_SYMLINK_ICONV),
+            ('f', CF_SAFE_FLIST),
+            ('x', CF_AVOID_XATTR_OPTIM),
+            ('C', CF_CHKSUM_SEED_FIX),
+            ('I', CF_INPLACE_PARTIAL_DIR),
+            ('v', CF_VARINT_FLIST_FLAGS),
+            ('u', CF_ID0_NAMES),
+        ] {
+            if client_info.contains(letter) {
+                compat_flags |= bit;
+            }
+        }
+        if recursive && client_info.contains('i') {
+            compat_flags |= CF_INC_RECURSE;
+        } else if recursive {
+            refuse("this daemon needs incremental recursion (rsync 3.0 or newer)".to_string());
+        }
+
+        Ok(Self {
+            session: ServerSession {
+                options,
+                flags,
+                compat_flags,
+                compress,
+                checksum_seed: rand::random(),
+            },
+            sender,
+            recursive,
+            path,
+            refusal,
+        })
+    }
+}
+
+/// `module/rel/` to `rel/`, refusing anything that would leave the
+/// module. An empty result names the module root.
+fn module_relative(arg: &str, module: &str) -> Result<String, String> {
+    let rest = arg
+        .strip_prefix(module)
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        .ok_or_else(|| format!("path {arg:?} is not inside module {module}"))?;
+    let rel = rest.trim_start_matches('/');
+    if Path::new(rel)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!("path {arg:?} leaves module {module}"));
+    }
+    if !rel.is_empty() && !rel.ends_with('/') {
+        return Err(format!(
+            "this daemon serves directory contents only: name {arg:?} with a trailing slash"
+        ));
+    }
+    Ok(rel.to_string())
+}
+
+/// `RawRemoteShellTransport` over a connection the daemon already
+/// accepted and took through the text handshake. The driver's
+/// `open_raw_stream` takes it, once.
+pub struct AcceptedTransport<S> {
+    stream: StdMutex<Option<IoRawStream<S>>>,
+}
+
+impl<S> AcceptedTransport<S> {
+    pub fn new(io: S, agreed_protocol: u32) -> Self {
+        Self {
+            stream: StdMutex::new(Some(IoRawStream::new(io, Some(agreed_protocol)))),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> RemoteShellTransport for AcceptedTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Stream = DaemonUnusedStream;
+
+    async fn probe(&self) -> Result<TransportProbe, AerorsyncError> {
+        Ok(TransportProbe {
+            remote_banner: String::new(),
+            protocol: ProtocolVersion(CURRENT_PROTOCOL_VERSION),
+            supports_remote_shell: false,
+        })
+    }
+
+    async fn exec(
+        &self,
+        request: RemoteExecRequest,
+    ) -> Result<RemoteCommandOutput, AerorsyncError> {
+        Err(AerorsyncError::transport(format!(
+            "an accepted daemon connection runs no commands (asked for {})",
+            request.program
+        )))
+    }
+
+    async fn open_stream(
+        &self,
+        _request: RemoteExecRequest,
+    ) -> Result<Self::Stream, AerorsyncError> {
+        Err(AerorsyncError::transport(
+            "AcceptedTransport does not support the legacy RSNP framed stream",
+        ))
+    }
+
+    async fn cancel(&self) -> Result<(), AerorsyncError> {
+        Ok(())
+    }
+
+    fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle::inert()
+    }
+}
+
+#[async_trait]
+impl<S> RawRemoteShellTransport for AcceptedTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type RawStream = IoRawStream<S>;
+
+    async fn open_raw_stream(
+        &self,
+        _request: RemoteExecRequest,
+    ) -> Result<Self::RawStream, AerorsyncError> {
+        self.stream
+            .lock()
+            .map_err(|_| AerorsyncError::transport("AcceptedTransport lock poisoned"))?
+            .take()
+            .ok_or_else(|| AerorsyncError::transport("daemon connection already handed over"))
+    }
+}
+
+/// Session events go to the daemon log, tagged with the peer.
+struct DaemonLogSink {
+    peer: String,
+}
+
+impl EventSink for DaemonLogSink {
+    fn on_info(&mut self, event: AerorsyncEvent) {
+        tracing::debug!("rsync daemon {}: {:?}", self.peer, event);
+    }
+
+    fn on_warning(&mut self, event: AerorsyncEvent) {
+        tracing::warn!("rsync daemon {}: {:?}", self.peer, event);
+    }
+
+    fn on_error(&mut self, event: AerorsyncEvent) {
+        tracing::warn!("rsync daemon {}: {:?}", self.peer, event);
+    }
+
+    fn on_terminal(&mut self, event: AerorsyncEvent) {
+        tracing::warn!("rsync daemon {}: {:?}", self.peer, event);
+    }
+}
+
+async fn send_error_line<S>(stream: &mut S, message: &str) -> Result<(), AerorsyncError>
+where
+    S: AsyncWrite + Unpin + ?Sized,
+{
+    write_text(stream, &format!("@ERROR: {message}\n")).await
+}
+
+/// `AUTHREQD` exchange for a module with `auth users`. `Ok(None)` when
+/// the client got in, `Ok(Some(reason))` for the log when it did not.
+async fn authenticate<S>(
+    stream: &mut S,
+    module: &RsyncdModule,
+    client: &DaemonGreeting,
+) -> Result<Option<String>, AerorsyncError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + ?Sized,
+{
+    let challenge = new_challenge();
+    write_text(stream, &format!("{AUTHREQD_PREFIX}{challenge}\n")).await?;
+    let line = expect_line(stream, "the auth response").await?;
+    let Some((user, response)) = line.split_once(' ') else {
+        return Ok(Some("malformed auth response".to_string()));
+    };
+    if !module.auth_users.iter().any(|allowed| allowed == user) {
+        return Ok(Some(format!("user {user:?} is not in auth users")));
+    }
+    let secrets = match module.load_secrets() {
+        Ok(secrets) => secrets,
+        Err(e) => return Ok(Some(e)),
+    };
+    let Some(password) = secrets.get(user) else {
+        return Ok(Some(format!("no secret for user {user:?}")));
+    };
+    let Some(digest) = pick_auth_digest(client, &DaemonGreeting::ours()) else {
+        return Ok(Some("no common auth digest".to_string()));
+    };
+    let expected = auth_response(digest, password, &challenge);
+    if bool::from(expected.as_bytes().ct_eq(response.trim().as_bytes())) {
+        Ok(None)
+    } else {
+        Ok(Some(format!("wrong password for user {user:?}")))
+    }
+}
+
+/// Serve one accepted connection to the end of its session.
+pub async fn serve_connection<S>(
+    mut stream: S,
+    config: Arc<RsyncdConfig>,
+    peer: String,
+) -> Result<(), AerorsyncError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    write_text(&mut stream, &DaemonGreeting::ours().to_line()).await?;
+    let client = DaemonGreeting::parse(&expect_line(&mut stream, "the client greeting").await?)?;
+    if client.protocol < 30 {
+        send_error_line(
+            &mut stream,
+            &format!(
+                "protocol version {} is too old, 30 or newer is required",
+                client.protocol
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+    let protocol = client.protocol.min(CURRENT_PROTOCOL_VERSION);
+
+    if let Some(motd) = &config.motd_file {
+        // `rsyncd` skips an unreadable MOTD silently, so do we.
+        if let Ok(text) = tokio::fs::read_to_string(motd).await {
+            let mut text = text.replace("\r\n", "\n");
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            write_text(&mut stream, &text).await?;
+        }
+    }
+
+    let Some(request) = read_line(&mut stream).await? else {
+        return Ok(());
+    };
+    let request = request.trim();
+    if request.is_empty() || request == "#list" {
+        let mut listing = String::new();
+        for module in config.modules.iter().filter(|module| module.list) {
+            listing.push_str(
+                &DaemonModule {
+                    name: module.name.clone(),
+                    comment: module.comment.clone(),
+                }
+                .to_listing_line(),
+            );
+        }
+        listing.push_str(DAEMON_EXIT);
+        listing.push('\n');
+        return write_text(&mut stream, &listing).await;
+    }
+    let Some(module) = config.module(request) else {
+        tracing::info!("rsync daemon {peer}: unknown module {request:?}");
+        return send_error_line(&mut stream, &format!("Unknown module '{request}'")).await;
+    };
+    if !module.auth_users.is_empty() {
+        if let Some(reason) = authenticate(&mut stream, module, &client).await? {
+            tracing::warn!(
+                "rsync daemon {peer}: auth failed on module {}: {reason}",
+                module.name
+            );
+            return send_error_line(
+                &mut stream,
+                &format!("auth failed on module {}", module.name),
+            )
+            .await;
+        }
+    }
+    write_text(&mut stream, &format!("{DAEMON_OK}\n")).await?;
+
+    let args = read_server_args(&mut stream).await?;
+    let mut request = ServerRequest::parse(&args, &module.name)
+        .map_err(|e| AerorsyncError::new(AerorsyncErrorKind::PlannerRejected, e))?;
+    // The lists are the daemon's; the client only picks the level.
+    request.session.options.algorithms = AlgorithmPreferences {
+        compress_level: request.session.options.algorithms.compress_level,
+        ..config.algorithms.clone()
+    };
+    let root = module.path.join(&request.path);
+    let refusal = request
+        .refusal
+        .clone()
+        .or_else(|| check_access(module, &request, &root).err());
+
+    let mut driver = AerorsyncDriver::new(
+        AcceptedTransport::new(stream, protocol),
+        CancelHandle::inert(),
+    );
+    if let Some(message) = refusal {
+        tracing::info!(
+            "rsync daemon {peer}: refused {}/{}: {message}",
+            module.name,
+            request.path
+        );
+        return driver.serve_refusal(&request.session, &message).await;
+    }
+
+    let adapter = CurrentDeltaSyncBridge::new();
+    let mut sink = DaemonLogSink { peer: peer.clone() };
+    let direction = if request.sender { "sent" } else { "received" };
+    let report = if request.sender {
+        let tree = scan_local_tree(&root, &request.session.options)
+            .await
+            .map_err(|e| {
+                AerorsyncError::new(
+                    AerorsyncErrorKind::Internal,
+                    format!("cannot scan {}: {e}", root.display()),
+                )
+            })?;
+        let mut source = LocalTreeRoot::new(&root).with_options(request.session.options.clone());
+        driver
+            .serve_tree_sender(&request.session, &tree, &mut source, &adapter, &mut sink)
+            .await?
+    } else {
+        let mut target = LocalTreeRoot::new(&root).with_options(request.session.options.clone());
+        driver
+            .serve_tree_receiver(&request.session, &mut target, &adapter, &mut sink)
+            .await?
+    };
+    tracing::info!(
+        "rsync daemon {peer}: {}/{}: {}/{} files {direction}",
+        module.name,
+        request.path,
+        report.files_transferred,
+        report.files_total
+    );
+    Ok(())
+}
+
+/// Module-level rules for an otherwise valid request.
+fn check_access(module: &RsyncdModule, request: &ServerRequest, root: &Path) -> Result<(), String> {
+    if request.sender && module.write_only {
+        return Err(format!("module {} is write only", module.name));
+    }
+    if !request.sender && module.read_only {
+        return Err(format!("module {} is read only", module.name));
+    }
+    let base = module
+        .path
+        .canonicalize()
+        .map_err(|e| format!("module {} is unavailable: {e}", module.name))?;
+    // A symlinked directory inside the module must not lead out of it.
+    let mut existing = root.to_path_buf();
+    while !existing.exists() {
+        if !existing.pop() {
+            break;
+        }
+    }
+    match existing.canonicalize() {
+        Ok(resolved) if resolved.starts_with(&base) => {}
+        _ => return Err(format!("{} leaves module {}", request.path, module.name)),
+    }
+    if request.sender && !root.is_dir() {
+        return Err(format!(
+            "{}/{}: no such directory",
+            module.name, request.path
+        ));
+    }
+    if !request.sender && !root.exists() && !request.session.options.mkpath {
+        let parent_ok = root
+            .parent()
+            .is_some_and(|parent| parent.as_os_str().is_empty() || parent.is_dir());
+        if !parent_ok {
+            return Err(format!(
+                "{}/{}: parent directory missing (use --mkpath)",
+                module.name, request.path
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Bind the listener `config` asks for.
+pub async fn bind_daemon(config: &RsyncdConfig) -> std::io::Result<TcpListener> {
+    let address = config.address.as_deref().unwrap_or("0.0.0.0");
+    TcpListener::bind((address, config.port)).await
+}
+
+/// Accept connections until the listener fails, one task per client.
+pub async fn serve_daemon(listener: TcpListener, config: Arc<RsyncdConfig>) -> std::io::Result<()> {
+    loop {
+        let (socket, peer): (_, SocketAddr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            // Per-connection failures (resets, fd pressure) must not
+            // stop the daemon.
+            Err(e) if is_transient_accept_error(&e) => {
+                tracing::warn!("rsync daemon: accept failed: {e}");
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let _ = socket.set_nodelay(true);
+        let config = config.clone();
+        tokio::spawn(async move {
+            let peer = peer.to_string();
+            if let Err(e) = serve_connection(socket, config, peer.clone()).await {
+                tracing::warn!("rsync daemon {peer}: {}", e.detail);
+            }
+        });
+    }
+}
+
+fn is_transient_accept_error(e: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        e.kind(),
+        ErrorKind::ConnectionAborted
+            | ErrorKind::ConnectionReset
+            | ErrorKind::Interrupted
+            | ErrorKind::WouldBlock
+    ) || e.raw_os_error() == Some(24)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aerorsync::daemon::{DaemonClientConfig, DaemonCredentials, DaemonTransport};
+    use crate::aerorsync::remote_command::RemoteCommandSpec;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn config_parses_modules_defaults_and_warnings() {
+        let config = RsyncdConfig::parse(
+            "# global\nport = 8730\nread only = no\nuid = nobody\n\n\
+             [backup]\n  path = /srv/backup\n  comment = Nightly\n  Auth Users = alice, bob\n  \
+             secrets file = /etc/rsyncd.secrets\n\n[public]\npath=/srv/pub\nreadonly=yes\nlist = false\n",
+        )
+        .unwrap();
+        assert_eq!(config.port, 8730);
+        let backup = config.module("backup").unwrap();
+        assert_eq!(backup.path, PathBuf::from("/srv/backup"));
+        assert!(!backup.read_only, "global default applies");
+        assert_eq!(backup.auth_users, vec!["alice", "bob"]);
+        let public = config.module("public").unwrap();
+        assert!(public.read_only && !public.list);
+        assert_eq!(config.warnings, vec!["line 4: parameter `uid` ignored"]);
+
+        assert!(RsyncdConfig::parse("[m]\npath = /x\nhosts allow = 10.0.0.0/8\n").is_err());
+        assert!(RsyncdConfig::parse("[m]\ncomment = no path\n").is_err());
+        assert!(RsyncdConfig::parse("[m]\npath = /x\nauth users = @staff\n").is_err());
+        assert!(RsyncdConfig::parse("[m]\npath = /x\nauth users = alice\n").is_err());
+    }
+
+    #[test]
+    fn stock_client_arguments_become_a_session() {
+        let request = ServerRequest::parse(
+            &args(&["--server", "--sender", "-vlogDtpre.iLsfxCIvu", ".", "data/"]),
+            "data",
+        )
+        .unwrap();
+        assert!(request.sender && request.recursive);
+        assert_eq!(request.refusal, None);
+        assert_eq!(request.path, "");
+        assert!(request.session.flags.preserve_links && !request.session.flags.always_checksum);
+        assert_eq!(request.session.compat_flags, 0x1FF);
+        assert!(!request.session.compress);
+
+        let request = ServerRequest::parse(
+            &args(&[
+                "--server",
+                "-logDtprcze.iLsfxCIvu",
+                "--delete",
+                "--partial-dir",
+                ".part",
+                "--compress-level=9",
+                ".",
+                "data/sub/",
+            ]),
+            "data",
+        )
+        .unwrap();
+        assert!(!request.sender);
+        assert!(request.session.compress && request.session.flags.always_checksum);
+        assert!(request.session.options.delete);
+        assert_eq!(
+            request.session.options.partial_dir.as_deref(),
+            Some(".part")
+        );
+        assert_eq!(request.session.options.algorithms.compress_level, Some(9));
+        assert_eq!(request.path, "sub/");
+    }
+
+    #[test]
+    fn unsupported_requests_are_refused_with_a_reason() {
+        let refusal = |list: &[&str]| {
+            ServerRequest::parse(&args(list), "data")
+                .unwrap()
+                .refusal
+                .unwrap_or_default()
+        };
+        assert!(refusal(&["--server", "-nre.iLsfxCIvu", ".", "data/"]).contains("-n"));
+        assert!(
+            refusal(&["--server", "-re.iLsfxCIvu", "--backup", ".", "data/"]).contains("--backup")
+        );
+        assert!(refusal(&["--server", "-lte.iLsfxCIvu", ".", "data/"]).contains("-r"));
+        assert!(refusal(&["--server", "-re.iLsfxCIvu", ".", "data/../etc/"]).contains("leaves"));
+        assert!(refusal(&["--server", "-re.iLsfxCIvu", ".", "other/"]).contains("not inside"));
+        assert!(refusal(&["--server", "-re.iLsfxCIvu", ".", "data/file.txt"]).contains("trailing"));
+        assert!(ServerRequest::parse(&args(&["-r", ".", "data/"]), "data").is_err());
+    }
+
+    fn write_tree(root: &Path) {
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("a.txt"), b"alpha alpha alpha").unwrap();
+        std::fs::write(root.join("docs/b.txt"), vec![7u8; 40_000]).unwrap();
+    }
+
+    async fn start_daemon(config: RsyncdConfig) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve_daemon(listener, Arc::new(config)));
+        port
+    }
+
+    fn client(port: u16, module: &str, credentials: Option<DaemonCredentials>) -> DaemonTransport {
+        DaemonTransport::new(DaemonClientConfig {
+            host: "127.0.0.1".into(),
+            port,
+            module: module.into(),
+            credentials,
+            connect_timeout_ms: 5_000,
+        })
+    }
+
+    #[tokio::test]
+    async fn daemon_round_trip_over_loopback() {
+        let served = tempfile::tempdir().unwrap();
+        let incoming = tempfile::tempdir().unwrap();
+        write_tree(served.path());
+        let etc = tempfile::tempdir().unwrap();
+        let secrets = etc.path().join("rsyncd.secrets");
+        std::fs::write(&secrets, "alice:hunter2\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&secrets, std::fs::Permissions::from_mode(0o600)).unwrap();
+        }
+        let config = RsyncdConfig::parse(&format!(
+            "[pub]\npath = {}\ncomment = Public files\n\n[drop]\npath = {}\nread only = no\n\
+             auth users = alice\nsecrets file = {}\nlist = no\n",
+            served.path().display(),
+            incoming.path().display(),
+            secrets.display()
+        ))
+        .unwrap();
+        let port = start_daemon(config).await;
+
+        let modules = client(port, "", None).list_modules().await.unwrap();
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules[0].name, "pub");
+        assert_eq!(modules[0].comment, "Public files");
+
+        // Download the public module.
+        let fetched = tempfile::tempdir().unwrap();
+        let mut driver = AerorsyncDriver::new(client(port, "pub", None), CancelHandle::inert());
+        let mut sink = LocalTreeRoot::new(fetched.path());
+        let adapter = CurrentDeltaSyncBridge::new();
+        let mut events = crate::aerorsync::events::CollectingSink::default();
+        let report = driver
+            .drive_download_tree(
+                RemoteCommandSpec::download("/"),
+                &mut sink,
+                &adapter,
+                &mut events,
+            )
+            .await
+            .unwrap();
+        assert!(report.files_transferred >= 2, "{report:?}");
+        assert_eq!(
+            std::fs::read(fetched.path().join("docs/b.txt")).unwrap(),
+            vec![7u8; 40_000]
+        );
+
+        // Uploading to a read-only module is refused over the wire.
+        let options = TransferOptions::default();
+        let tree = scan_local_tree(fetched.path(), &options).await.unwrap();
+        let mut source = LocalTreeRoot::new(fetched.path());
+        let mut driver = AerorsyncDriver::new(client(port, "pub", None), CancelHandle::inert());
+        let err = driver
+            .drive_upload_tree(
+                RemoteCommandSpec::upload("/"),
+                &tree,
+                &mut source,
+                &adapter,
+                &mut events,
+            )
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("read only"), "{err:?}");
+
+        // The writable module wants the right password.
+        let wrong = DaemonCredentials {
+            user: "alice".into(),
+            password: "nope".into(),
+        };
+        let mut driver =
+            AerorsyncDriver::new(client(port, "drop", Some(wrong)), CancelHandle::inert());
+        let err = driver
+            .drive_upload_tree(
+                RemoteCommandSpec::upload("/"),
+                &tree,
+                &mut source,
+                &adapter,
+                &mut events,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.detail.contains("auth failed"), "{}", err.detail);
+
+        let right = DaemonCredentials {
+            user: "alice".into(),
+            password: "hunter2".into(),
+        };
+        let mut driver =
+            AerorsyncDriver::new(client(port, "drop", Some(right)), CancelHandle::inert());
+        let report = driver
+            .drive_upload_tree(
+                RemoteCommandSpec::upload("/"),
+                &tree,
+                &mut source,
+                &adapter,
+                &mut events,
+            )
+            .await
+            .unwrap();
+        assert!(report.files_transferred >= 2, "{report:?}");
+        assert_eq!(
+            std::fs::read(incoming.path().join("a.txt")).unwrap(),
+            b"alpha alpha alpha"
+        );
+    }
+
+    /// Run the stock rsync client named by `RSNP_TEST_STOCK_RSYNC` (default
+    /// `rsync` on `PATH`).
+    async fn stock_rsync(args: &[String], password: Option<&str>) -> std::process::Output {
+        let program = std::env::var("RSNP_TEST_STOCK_RSYNC").unwrap_or_else(|_| "rsync".into());
+        let mut command = tokio::process::Command::new(&program);
+        command.args(args).env_remove("RSYNC_PASSWORD");
+        if let Some(password) = password {
+            command.env("RSYNC_PASSWORD", password);
+        }
+        command
+            .output()
+            .await
+            .unwrap_or_else(|e| panic!("cannot run stock rsync client `{program}`: {e}"))
+    }
+
+    /// Interop with the stock client: module listing, a download, and an
+    /// upload behind `auth users`, all over `rsync://`.
+    #[tokio::test]
+    #[ignore = "requires a stock rsync >= 3.2 client"]
+    async fn stock_rsync_client_talks_to_the_daemon() {
+        let served = tempfile::tempdir().unwrap();
+        let incoming = tempfile::tempdir().unwrap();
+        write_tree(served.path());
+        let etc = tempfile::tempdir().unwrap();
+        let secrets = etc.path().join("rsyncd.secrets");
+        std::fs::write(&secrets, "alice:hunter2\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&secrets, std::fs::Permissions::from_mode(0o600)).unwrap();
+        }
+        let config = RsyncdConfig::parse(&format!(
+            "[pub]\npath = {}\ncomment = Public files\n\n[drop]\npath = {}\nread only = no\n\
+             auth users = alice\nsecrets file = {}\nlist = no\n",
+            served.path().display(),
+            incoming.path().display(),
+            secrets.display()
+        ))
+        .unwrap();
+        let port = start_daemon(config).await;
+        let url = |rest: &str| format!("rsync://127.0.0.1:{port}/{rest}");
+        let dir = |path: &Path| format!("{}/", path.display());
+
+        let listing = stock_rsync(&[url("")], None).await;
+        assert!(listing.status.success(), "{listing:?}");
+        let listing = String::from_utf8_lossy(&listing.stdout);
+        assert!(
+            listing.contains("pub") && listing.contains("Public files"),
+            "{listing}"
+        );
+        assert!(!listing.contains("drop"), "{listing}");
+
+        let fetched = tempfile::tempdir().unwrap();
+        let download = stock_rsync(&args(&["-a", &url("pub/"), &dir(fetched.path())]), None).await;
+        assert!(download.status.success(), "{download:?}");
+        assert_eq!(
+            std::fs::read(fetched.path().join("docs/b.txt")).unwrap(),
+            vec![7u8; 40_000]
+        );
+
+        let upload_args = args(&[
+            "-a",
+            &dir(fetched.path()),
+            &format!("rsync://alice@127.0.0.1:{port}/drop/"),
+        ]);
+        let refused = stock_rsync(&upload_args, Some("nope")).await;
+        assert!(!refused.status.success(), "{refused:?}");
+        assert!(
+            String::from_utf8_lossy(&refused.stderr).contains("auth failed"),
+            "{refused:?}"
+        );
+        let upload = stock_rsync(&upload_args, Some("hunter2")).await;
+        assert!(upload.status.success(), "{upload:?}");
+        assert_eq!(
+            std::fs::read(incoming.path().join("a.txt")).unwrap(),
+            b"alpha alpha alpha"
+        );
+        assert_eq!(
+            std::fs::read(incoming.path().join("docs/b.txt")).unwrap(),
+            vec![7u8; 40_000]
+        );
+    }
+
+    /// A daemon restricted to one checksum and one compressor: both
+    /// directions settle on them and rebuild files against a stale basis,
+    /// so matched blocks (replayed by `zlib`) cross the wire too.
+    #[tokio::test]
+    async fn daemon_sessions_settle_on_the_configured_algorithms() {
+        let mut stale = vec![7u8; 40_000];
+        stale[20_000..20_100].fill(1);
+        for (checksums, compressions) in [
+            ("xxh3", "lz4"),
+            ("md5", "zlibx"),
+            ("sha1", "zlib"),
+            ("md4", "none"),
+        ] {
+            let served = tempfile::tempdir().unwrap();
+            let incoming = tempfile::tempdir().unwrap();
+            write_tree(served.path());
+            std::fs::create_dir_all(incoming.path().join("docs")).unwrap();
+            std::fs::write(incoming.path().join("docs/b.txt"), &stale).unwrap();
+            let mut config = RsyncdConfig::parse(&format!(
+                "[pub]\npath = {}\n\n[drop]\npath = {}\nread only = no\n",
+                served.path().display(),
+                incoming.path().display(),
+            ))
+            .unwrap();
+            config.algorithms = AlgorithmPreferences::default()
+                .with_lists(Some(checksums), Some(compressions))
+                .unwrap();
+            let port = start_daemon(config).await;
+            // A stock client leaves `none` out of its list unless asked.
+            let options = TransferOptions {
+                algorithms: AlgorithmPreferences::default()
+                    .with_lists(None, (compressions == "none").then_some("none"))
+                    .unwrap(),
+                ..TransferOptions::default()
+            };
+
+            let fetched = tempfile::tempdir().unwrap();
+            std::fs::create_dir_all(fetched.path().join("docs")).unwrap();
+            std::fs::write(fetched.path().join("docs/b.txt"), &stale).unwrap();
+            let mut driver = AerorsyncDriver::new(client(port, "pub", None), CancelHandle::inert());
+            let mut sink = LocalTreeRoot::new(fetched.path());
+            let adapter = CurrentDeltaSyncBridge::new();
+            let mut events = crate::aerorsync::events::CollectingSink::default();
+            driver
+                .drive_download_tree(
+                    RemoteCommandSpec::download("/").with_options(options.clone()),
+                    &mut sink,
+                    &adapter,
+                    &mut events,
+                )
+                .await
+                .unwrap();
+            assert_eq!(driver.checksum_algo().name(), checksums);
+            assert_eq!(driver.compression_algo().name(), compressions);
+            assert_eq!(
+                std::fs::read(fetched.path().join("docs/b.txt")).unwrap(),
+                vec![7u8; 40_000],
+                "{checksums} + {compressions}"
+            );
+
+            let tree = scan_local_tree(fetched.path(), &options).await.unwrap();
+            let mut source = LocalTreeRoot::new(fetched.path());
+            let mut driver =
+                AerorsyncDriver::new(client(port, "drop", None), CancelHandle::inert());
+            driver
+                .drive_upload_tree(
+                    RemoteCommandSpec::upload("/").with_options(options),
+                    &tree,
+                    &mut source,
+                    &adapter,
+                    &mut events,
+                )
+                .await
+                .unwrap();
+            assert_eq!(driver.checksum_algo().name(), checksums);
+            assert_eq!(
+                std::fs::read(incoming.path().join("docs/b.txt")).unwrap(),
+                vec![7u8; 40_000],
+                "{checksums} + {compressions}"
+            );
+            assert_eq!(
+                std::fs::read(incoming.path().join("a.txt")).unwrap(),
+                b"alpha alpha alpha"
+            );
+        }
+    }
+}
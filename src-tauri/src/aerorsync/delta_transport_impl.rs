@@ -19,6 +19,14 @@
 //!   silently.
 //! - Atomic disk write of the download result via a temp-file + rename
 //!   helper with kill-9 invariant pin (`write_atomic_chunked`).
+//! - Whole-directory sessions (`upload_tree` / `download_tree`): a
+//!   local walk into a `TreeFileList` on upload, and `LocalTreeRoot` as
+//!   the driver's file source / sink in both directions.
+//! - The rsync transfer options (`TransferOptions`: `--delete`,
+//!   `--inplace`, `--append[-verify]`, `--partial-dir`, `--sparse`,
+//!   `--mkpath`). On upload they travel on the remote command line; on
+//!   download this module applies them to the local side (writer choice,
+//!   partial-dir basis, deletion in tree sessions).
 //!
 //! # Q5 PreCommit / PostCommit semantics (recap)
 //!
@@ -43,14 +51,11 @@
 //!   atomic rename on `finalize`). The download-side
 //!   `AERORSYNC_MAX_IN_MEMORY_BYTES` guard was removed.
 //!
-//!   The signature phase still bulk-reads `local_path` via `tokio::fs::read`
-//!   because `DeltaEngineAdapter::build_signatures` is bulk-only; a
-//!   `build_signatures_streaming` adapter API is the post-P3-T01
-//!   follow-up that brings the resident set to `O(window)` regardless of
-//!   baseline size. Until then, baselines are still read once into RAM
-//!   for signatures, but the reconstructed buffer is gone: RSS scales
-//!   with `O(baseline + writer_buffer)` instead of
-//!   `O(baseline + reconstructed)`.
+//!   The signature phase reads the same `FileBaseline` in strides
+//!   through `DeltaEngineAdapter::build_signatures_streaming`, and both
+//!   delta directions encode, frame and decode incrementally, so the
+//!   resident set stays a few `STREAMING_READ_CHUNK_BYTES` windows
+//!   whatever the file size (pinned by `tests/aerorsync_memory.rs`).
 
 #![cfg(feature = "aerorsync")]
 
@@ -63,13 +68,18 @@ use async_trait::async_trait;
 use tokio::fs::{self, OpenOptions};
 use tokio::io::AsyncWriteExt;
 
+use crate::aerorsync::attrs::{self, IdMapper};
+use crate::aerorsync::compression::CompressionAlgo;
+use crate::aerorsync::daemon::{DaemonClientConfig, DaemonTransport};
 use crate::aerorsync::engine_adapter::{
     BaselineSource, CurrentDeltaSyncBridge, FileBaseline, MemoryBaseline,
 };
 use crate::aerorsync::fallback_policy::{classify_fallback, FallbackVerdict};
+use crate::aerorsync::local_transport::{InProcessTransport, ReceiverTask};
 use crate::aerorsync::native_driver::AerorsyncDriver;
-use crate::aerorsync::real_wire::FileListEntry;
-use crate::aerorsync::remote_command::RemoteCommandSpec;
+use crate::aerorsync::negotiation::AlgorithmPreferences;
+use crate::aerorsync::real_wire::{EntryAcl, FileListEntry, XattrItem};
+use crate::aerorsync::remote_command::{AppendMode, RemoteCommandSpec, TransferOptions};
 use crate::aerorsync::rsync_event_bridge::RsyncEventBridge;
 use crate::aerorsync::russh_session_transport::RusshSessionTransport;
 use crate::aerorsync::ssh_transport::{
@@ -79,14 +89,22 @@ use crate::aerorsync::streaming_writer::StreamingAtomicWriter;
 use crate::aerorsync::transport::{
     CancelHandle, RawRemoteShellTransport, RemoteExecRequest, RemoteShellTransport,
 };
+use crate::aerorsync::tree::{is_symlink_mode, TreeFileList, TreeSink, TreeSource, TREE_ROOT_PATH};
 use crate::aerorsync::types::{AerorsyncError, AerorsyncErrorKind, SessionStats};
 use crate::delta_transport::{BatchStats, DeltaBatch, DeltaTransport};
 use crate::rsync_output::RsyncEvent;
 use crate::rsync_over_ssh::{RsyncCapability, RsyncConfig, RsyncError, RsyncStats};
+use std::collections::{BTreeSet, HashMap};
 
 /// Display name surfaced by `DeltaTransport::name()`.
 const AERORSYNC_TRANSPORT_NAME: &str = "aerorsync-proto-31";
 
+/// `DeltaTransport::name` of the `rsync://` daemon flavour.
+const AERORSYNC_DAEMON_TRANSPORT_NAME: &str = "aerorsync-daemon-31";
+
+/// Display name of the local-to-local transport.
+const AERORSYNC_LOCAL_TRANSPORT_NAME: &str = "aerorsync-local-31";
+
 /// Chunk size used by `write_atomic_chunked` in production. 64 KiB
 /// matches the AeroVault v2 body chunk + keeps syscall count reasonable.
 const ATOMIC_WRITE_CHUNK_SIZE: usize = 64 * 1024;
@@ -108,6 +126,7 @@ static TEMP_SUFFIX_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::At
 pub struct AerorsyncDeltaTransport {
     ssh_config: SshTransportConfig,
     min_file_size: u64,
+    transfer_options: TransferOptions,
 }
 
 impl AerorsyncDeltaTransport {
@@ -117,14 +136,24 @@ impl AerorsyncDeltaTransport {
         Self {
             ssh_config,
             min_file_size,
+            transfer_options: TransferOptions::default(),
         }
     }
 
+    /// Apply rsync transfer options (`--delete`, `--inplace`, ...) to
+    /// every transfer of this transport, batches included.
+    pub fn with_transfer_options(mut self, options: TransferOptions) -> Self {
+        self.transfer_options = options;
+        self
+    }
+
     /// Convenience constructor that maps the production `RsyncConfig`
     /// (used by `providers::sftp::delta_transport`) onto the prototype's
     /// `SshTransportConfig`. `host_key_policy` is provided by the caller
     /// so the factory (Zona B1) can honour whatever pinning the SFTP
-    /// session established during connect.
+    /// session established during connect. `compress = false` asks for
+    /// plain literals, and `RSYNC_CHECKSUM_LIST` / `RSYNC_COMPRESS_LIST`
+    /// override the algorithm lists as they do for stock rsync.
     pub fn from_rsync_config(
         cfg: &RsyncConfig,
         host_key_policy: SshHostKeyPolicy,
@@ -155,7 +184,19 @@ impl AerorsyncDeltaTransport {
                 environment: Vec::new(),
             },
         };
-        Ok(Self::new(ssh_config, cfg.min_file_size))
+        let mut algorithms = AlgorithmPreferences::default();
+        if !cfg.compress {
+            algorithms.prefer_compressions = vec![CompressionAlgo::None];
+        }
+        let algorithms = algorithms
+            .with_env()
+            .map_err(|e| RsyncError::ProbeFailed(e.detail))?;
+        Ok(
+            Self::new(ssh_config, cfg.min_file_size).with_transfer_options(TransferOptions {
+                algorithms,
+                ..TransferOptions::default()
+            }),
+        )
     }
 }
 
@@ -213,6 +254,22 @@ impl DeltaTransport for AerorsyncDeltaTransport {
         self.upload_inner(local_path, remote_path).await
     }
 
+    async fn download_tree(
+        &self,
+        remote_dir: &str,
+        local_dir: &Path,
+    ) -> Result<RsyncStats, RsyncError> {
+        self.download_tree_inner(remote_dir, local_dir).await
+    }
+
+    async fn upload_tree(
+        &self,
+        local_dir: &Path,
+        remote_dir: &str,
+    ) -> Result<RsyncStats, RsyncError> {
+        self.upload_tree_inner(local_dir, remote_dir).await
+    }
+
     /// P3-T01 W3.2(b2): open a session-reuse batch backed by russh.
     ///
     /// Performs one SSH handshake here ([`RusshSessionTransport::connect`])
@@ -224,7 +281,10 @@ impl DeltaTransport for AerorsyncDeltaTransport {
     /// without losing the file.
     async fn begin_batch(&self) -> Result<Box<dyn DeltaBatch>, RsyncError> {
         match RusshSessionTransport::connect(self.ssh_config.clone()).await {
-            Ok(transport) => Ok(Box::new(AerorsyncBatch::new(transport, self.min_file_size))),
+            Ok(transport) => Ok(Box::new(
+                AerorsyncBatch::new(transport, self.min_file_size)
+                    .with_transfer_options(self.transfer_options.clone()),
+            )),
             Err(e) => {
                 tracing::warn!(
                     "AerorsyncDeltaTransport::begin_batch: russh connect failed ({}); \
@@ -259,6 +319,7 @@ impl AerorsyncDeltaTransport {
             local_path,
             remote_path,
             self.min_file_size,
+            &self.transfer_options,
         )
         .await
     }
@@ -283,6 +344,7 @@ async fn do_upload<T>(
     local_path: &Path,
     remote_path: &str,
     min_file_size: u64,
+    options: &TransferOptions,
 ) -> Result<RsyncStats, RsyncError>
 where
     T: RawRemoteShellTransport + 'static,
@@ -333,7 +395,10 @@ where
     // (WrapperParity flavor) instead of the dev helper
     // `aerorsync_serve`. The wrapper command line is byte-pinned
     // against rsync 3.2.7 capture by `upload_remote_command_matches_capture`.
-    let spec = RemoteCommandSpec::upload(remote_path);
+    // Transfer options ride on that command line: the remote receiver
+    // does the deleting, the in-place writing and the partial-dir
+    // bookkeeping, the driver only adapts the delta it sends.
+    let spec = RemoteCommandSpec::upload(remote_path).with_options(options.clone());
     let drive_res = driver
         .drive_upload_through_delta_streaming(
             spec,
@@ -376,13 +441,20 @@ impl AerorsyncDeltaTransport {
     ) -> Result<RsyncStats, RsyncError> {
         let transport = SshRemoteShellTransport::new(self.ssh_config.clone());
         let cancel = CancelHandle::inert();
-        do_download(transport, cancel, remote_path, local_path).await
+        do_download(
+            transport,
+            cancel,
+            remote_path,
+            local_path,
+            &self.transfer_options,
+        )
+        .await
     }
 }
 
 /// Module-private download core extracted from `download_inner` in P3-T01
 /// W3.2(a). Behavior byte-identical to the pre-W3.2 single-shot path:
-/// baseline read + FileBaseline/MemoryBaseline pick + StreamingAtomicWriter
+/// FileBaseline/MemoryBaseline pick + StreamingAtomicWriter
 /// open + driver drive + finish_session + writer.finalize + stats build.
 ///
 /// Like [`do_upload`], the function accepts the remote-shell transport and
@@ -395,92 +467,19 @@ async fn do_download<T>(
     cancel: CancelHandle,
     remote_path: &str,
     local_path: &Path,
+    options: &TransferOptions,
 ) -> Result<RsyncStats, RsyncError>
 where
     T: RawRemoteShellTransport + 'static,
 {
     let start = Instant::now();
-    // P3-T01 W2.5: the bulk read still feeds the signature phase
-    // (`adapter.build_signatures` is bulk-only until the post-P3-T01
-    // streaming variant lands). Reconstruction, however, no longer
-    // materialises a `Vec<u8>`: it streams into a
-    // `StreamingAtomicWriter` opened below.
-    //
-    // U-03: distinguish `NotFound` (legitimate empty baseline) from
-    // every other `io::Error`. Before the fix, `unwrap_or_default()`
-    // silently masked `PermissionDenied`, `EIO`, symlink loops, etc.
-    // into "empty baseline", degrading the delta path to a full
-    // download while hiding the underlying error from the user.
-    let (destination_data, baseline_mode) = match fs::read(local_path).await {
-        Ok(data) => {
-            // U-09: capture the pre-existing mode so we can restore it on
-            // the temp file before the atomic rename, preserving
-            // perms / setuid / readonly across the in-place update.
-            let mode = existing_mode_if_any(local_path).await;
-            (data, mode)
-        }
-        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
-            // Legitimate empty baseline: target file does not exist
-            // yet. Classic full-download semantics via the native
-            // delta pipeline.
-            (Vec::new(), None)
-        }
-        Err(error) => {
-            // Any other read failure must surface, not silently
-            // degrade to full-size delta. Pre-commit classification
-            // routes this through classic fallback with a visible
-            // reason in the stderr string.
-            return Err(RsyncError::TransferFailed {
-                exit: -1,
-                stderr: format!(
-                    "native fallback: cannot read local baseline {}: {}",
-                    local_path.display(),
-                    error
-                ),
-            });
-        }
-    };
-
-    // Random-access baseline for `apply_delta_streaming`'s
-    // `CopyBlock(idx)` dispatch. When the target does not exist yet
-    // we substitute an empty `MemoryBaseline`: the engine never
-    // emits CopyBlocks against an empty signature set, so the
-    // baseline is unused but the trait object is still required by
-    // the streaming entry-point signature.
-    let mut baseline: Box<dyn BaselineSource + Send> = if destination_data.is_empty() {
-        Box::new(MemoryBaseline::new(Vec::new()))
-    } else {
-        match FileBaseline::open(local_path).await {
-            Ok(fb) => Box::new(fb),
-            Err(error) => {
-                return Err(RsyncError::TransferFailed {
-                    exit: -1,
-                    stderr: format!(
-                        "native fallback: cannot open streaming baseline {}: {}",
-                        local_path.display(),
-                        error
-                    ),
-                });
-            }
-        }
-    };
-
-    // Open the `<target>.aerotmp` sink before the SSH session so a
-    // failure here surfaces as a pre-commit error (no wire bytes
-    // exchanged, no `local_committed=true` invariant tripped).
-    let mut writer =
-        StreamingAtomicWriter::new(local_path)
-            .await
-            .map_err(|e| RsyncError::TransferFailed {
-                exit: -1,
-                stderr: format!(
-                    "native fallback: cannot open streaming temp file for {}: {}",
-                    local_path.display(),
-                    e
-                ),
-            })?;
+    // Basis and writer are set up before the SSH session so a failure
+    // here surfaces as a pre-commit error (no wire bytes exchanged, no
+    // `local_committed=true` invariant tripped).
+    let mut target = LocalTarget::open(local_path, options).await?;
 
     let mut driver = AerorsyncDriver::new(transport, cancel);
+    driver.set_partial_basis(target.partial_basis);
     let adapter = CurrentDeltaSyncBridge::new();
     let warnings = new_warnings_sink();
     let mut bridge = build_event_bridge(warnings.clone());
@@ -488,13 +487,12 @@ where
     // B.1: production dispatch now talks to stock `rsync --server --sender`
     // (WrapperParity flavor). Pinned against rsync 3.2.7 capture by
     // `download_remote_command_matches_capture`.
-    let spec = RemoteCommandSpec::download(remote_path);
+    let spec = RemoteCommandSpec::download(remote_path).with_options(options.clone());
     let drive_res = driver
-        .drive_download_through_delta_streaming(
+        .drive_download_reconstructing(
             spec,
-            &destination_data,
-            &mut *baseline,
-            &mut writer,
+            &mut *target.baseline,
+            &mut target.writer,
             &adapter,
             &mut bridge,
         )
@@ -502,52 +500,1139 @@ where
     if let Err(e) = drive_res {
         // The `StreamingAtomicWriter` Drop leaves the temp orphan;
         // the original `local_path` is untouched. Caller-visible
-        // semantics match the pre-W2.5 bulk path.
+        // semantics match the pre-W2.5 bulk path. With `--partial-dir`
+        // the received bytes are kept for the next attempt instead.
+        target.keep_partial().await;
         return Err(map_native_error_to_rsync(e, driver.committed()));
     }
     if let Err(e) = driver.finish_session(&mut bridge).await {
         return Err(map_native_error_to_rsync(e, driver.committed()));
     }
 
-    let file_size = writer.bytes_written();
+    if driver.transfer_skipped() {
+        // `--append` and the local copy is already as long as the remote
+        // one: rsync leaves it alone, metadata included.
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let warnings = drain_warnings(warnings);
+        return Ok(build_stats(
+            driver.session_stats(),
+            0,
+            duration_ms,
+            warnings,
+        ));
+    }
 
     let remote_entry = driver.downloaded_entry().cloned();
-    let preserve_mode = remote_entry
-        .as_ref()
-        .map(|entry| entry.mode)
-        .or(baseline_mode);
-    // `StreamingAtomicWriter::finalize` takes `(i64, u32)` for
-    // (mtime_secs, mtime_nsecs); rsync wire entries carry the
-    // sub-second part as `Option<i32>` (None = NSEC absent / 0).
-    // Cast through `u32` matching the bulk path (`write_atomic_chunked`
-    // does the same internally via `mtime_nsec.unwrap_or(0)`).
-    let preserve_mtime = remote_entry
-        .as_ref()
-        .map(|entry| (entry.mtime, entry.mtime_nsec.unwrap_or(0).max(0) as u32));
     if remote_entry.is_none() {
         tracing::warn!(
             "native rsync download completed without remote file metadata; preserving local baseline mode only"
         );
     }
+    let file_size = target.commit(remote_entry.as_ref()).await?;
 
-    // Atomic commit: flush + sync_all + chmod (Unix) + set_mtime + rename.
-    // Failures here are post-commit-cutover and surface as
-    // `HardRejection` via `map_write_atomic_error`.
-    writer
-        .finalize(preserve_mode, preserve_mtime)
+    let duration_ms = start.elapsed().as_millis() as u64;
+    let warnings = drain_warnings(warnings);
+    Ok(build_stats(
+        driver.session_stats(),
+        file_size,
+        duration_ms,
+        warnings,
+    ))
+}
+
+/// Local file a streamed reconstruction lands in: the basis the
+/// signatures are built from and the writer the delta is applied to,
+/// both chosen by the transfer options. Shared by the download core
+/// and the in-process receiver of the local transport.
+pub(crate) struct LocalTarget {
+    path: PathBuf,
+    /// Read in strides by the signature phase and block by block by
+    /// the delta apply; never whole.
+    pub(crate) baseline: Box<dyn BaselineSource + Send>,
+    /// Mode of the file being replaced, kept when the sender's entry
+    /// is missing.
+    baseline_mode: Option<u32>,
+    pub(crate) writer: StreamingAtomicWriter,
+    partial_path: Option<PathBuf>,
+    /// The basis is the `--partial-dir` file of an interrupted run.
+    pub(crate) partial_basis: bool,
+    in_place: bool,
+}
+
+impl LocalTarget {
+    /// Create missing parents under `--mkpath`, pick the basis (the
+    /// partial-dir file, the target, or nothing) and open the writer:
+    /// `<target>.aerotmp` normally, the target itself under `--inplace`
+    /// and `--append`.
+    pub(crate) async fn open(
+        local_path: &Path,
+        options: &TransferOptions,
+    ) -> Result<Self, RsyncError> {
+        if options.mkpath {
+            if let Some(parent) = local_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| RsyncError::TransferFailed {
+                        exit: -1,
+                        stderr: format!(
+                            "native fallback: cannot create {} (--mkpath): {}",
+                            parent.display(),
+                            e
+                        ),
+                    })?;
+            }
+        }
+        // `--partial-dir`: a file left there by an interrupted run becomes
+        // the basis instead of the target (`FNAMECMP_PARTIAL_DIR`).
+        let partial_path = options
+            .effective_partial_dir()
+            .and_then(|dir| partial_path_for(local_path, dir));
+        let partial_basis = match &partial_path {
+            Some(path) => fs::metadata(path).await.is_ok_and(|m| m.is_file()),
+            None => false,
+        };
+        let basis_path = match &partial_path {
+            Some(path) if partial_basis => path.as_path(),
+            _ => local_path,
+        };
+        // The basis is never read whole: the signature phase streams it
+        // through `build_signatures_streaming` and `apply_delta_streaming`
+        // reads back the blocks the delta references, both through this
+        // random-access `FileBaseline`. When the target does not exist yet
+        // we substitute an empty `MemoryBaseline`: no signatures, so the
+        // sender never emits CopyBlocks against it.
+        //
+        // U-03: distinguish `NotFound` (legitimate empty baseline) from
+        // every other `io::Error`. Before the fix, `unwrap_or_default()`
+        // silently masked `PermissionDenied`, `EIO`, symlink loops, etc.
+        // into "empty baseline", degrading the delta path to a full
+        // download while hiding the underlying error from the user.
+        let opened = match fs::metadata(basis_path).await {
+            Ok(meta) if !meta.is_file() => Err(std::io::Error::other("not a regular file")),
+            _ => FileBaseline::open(basis_path).await,
+        };
+        let (baseline, baseline_mode): (Box<dyn BaselineSource + Send>, _) = match opened {
+            Ok(file_baseline) => {
+                // U-09: capture the pre-existing mode so we can restore it on
+                // the temp file before the atomic rename, preserving
+                // perms / setuid / readonly across the in-place update.
+                let mode = existing_mode_if_any(local_path).await;
+                (Box::new(file_baseline), mode)
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                // Legitimate empty baseline: target file does not exist
+                // yet. Classic full-download semantics via the native
+                // delta pipeline.
+                (Box::new(MemoryBaseline::new(Vec::new())), None)
+            }
+            Err(error) => {
+                // Any other open failure must surface, not silently
+                // degrade to full-size delta. Pre-commit classification
+                // routes this through classic fallback with a visible
+                // reason in the stderr string.
+                return Err(RsyncError::TransferFailed {
+                    exit: -1,
+                    stderr: format!(
+                        "native fallback: cannot read local baseline {}: {}",
+                        basis_path.display(),
+                        error
+                    ),
+                });
+            }
+        };
+
+        // `--inplace` / `--append` write the target itself instead; the
+        // sender never copies from a block we already overwrote.
+        let in_place = options.writes_in_place();
+        let writer = if options.append != AppendMode::Off {
+            StreamingAtomicWriter::in_place(local_path, baseline.len()).await
+        } else if in_place {
+            StreamingAtomicWriter::in_place(local_path, 0).await
+        } else {
+            StreamingAtomicWriter::new(local_path).await
+        };
+        let writer = writer
+            .map_err(|e| RsyncError::TransferFailed {
+                exit: -1,
+                stderr: format!(
+                    "native fallback: cannot open streaming temp file for {}: {}",
+                    local_path.display(),
+                    e
+                ),
+            })?
+            .with_sparse(options.sparse);
+        Ok(Self {
+            path: local_path.to_path_buf(),
+            baseline,
+            baseline_mode,
+            writer,
+            partial_path,
+            partial_basis,
+            in_place,
+        })
+    }
+
+    /// After a failed transfer: with `--partial-dir` the received bytes
+    /// are kept there for the next attempt, otherwise the temp stays an
+    /// orphan and the target is untouched.
+    pub(crate) async fn keep_partial(self) {
+        if let Some(partial) = &self.partial_path {
+            if let Err(keep_err) = self.writer.keep_partial(partial).await {
+                tracing::warn!(
+                    "cannot keep partial file {}: {}",
+                    partial.display(),
+                    keep_err
+                );
+            }
+        }
+    }
+
+    /// Install the reconstruction with the sender's metadata (`entry`)
+    /// and drop a used partial-dir basis. Returns the file size.
+    pub(crate) async fn commit(self, entry: Option<&FileListEntry>) -> Result<u64, RsyncError> {
+        let file_size = self.writer.bytes_written();
+        let preserve_mode = entry.map(|entry| entry.mode).or(self.baseline_mode);
+        // `StreamingAtomicWriter::finalize` takes `(i64, u32)` for
+        // (mtime_secs, mtime_nsecs); rsync wire entries carry the
+        // sub-second part as `Option<i32>` (None = NSEC absent / 0).
+        // Cast through `u32` matching the bulk path (`write_atomic_chunked`
+        // does the same internally via `mtime_nsec.unwrap_or(0)`).
+        let preserve_mtime =
+            entry.map(|entry| (entry.mtime, entry.mtime_nsec.unwrap_or(0).max(0) as u32));
+        let writer = match entry {
+            Some(entry) => self.writer.with_attrs(IdMapper::new().attrs_for(entry)),
+            None => self.writer,
+        };
+
+        // Atomic commit: flush + sync_all + owner / xattrs / ACLs + chmod
+        // (Unix) + set_mtime + rename. Failures here are post-commit-cutover
+        // and surface as `HardRejection` via `map_write_atomic_error`. An
+        // in-place writer has been modifying the target all along, so any
+        // failure is one.
+        let in_place = self.in_place;
+        writer
+            .finalize(preserve_mode, preserve_mtime)
+            .await
+            .map_err(|e| match e {
+                WriteAtomicError::PostOpen { stage, source } if in_place => {
+                    RsyncError::HardRejection(format!("in-place write failed at {stage}: {source}"))
+                }
+                e => map_write_atomic_error(e),
+            })?;
+        if self.partial_basis {
+            if let Some(partial) = &self.partial_path {
+                if let Err(e) = fs::remove_file(partial).await {
+                    tracing::warn!(
+                        "cannot remove used partial file {}: {}",
+                        partial.display(),
+                        e
+                    );
+                }
+            }
+        }
+        tracing::trace!("committed {} ({file_size} bytes)", self.path.display());
+        Ok(file_size)
+    }
+}
+
+// --- tree flow -------------------------------------------------------------
+
+impl AerorsyncDeltaTransport {
+    async fn upload_tree_inner(
+        &self,
+        local_dir: &Path,
+        remote_dir: &str,
+    ) -> Result<RsyncStats, RsyncError> {
+        let transport = SshRemoteShellTransport::new(self.ssh_config.clone());
+        do_upload_tree(
+            transport,
+            CancelHandle::inert(),
+            local_dir,
+            remote_dir,
+            &self.transfer_options,
+        )
+        .await
+    }
+
+    async fn download_tree_inner(
+        &self,
+        remote_dir: &str,
+        local_dir: &Path,
+    ) -> Result<RsyncStats, RsyncError> {
+        let transport = SshRemoteShellTransport::new(self.ssh_config.clone());
+        do_download_tree(
+            transport,
+            CancelHandle::inert(),
+            remote_dir,
+            local_dir,
+            &self.transfer_options,
+        )
+        .await
+    }
+}
+
+/// Tree upload core, generic over the transport like [`do_upload`] so
+/// the SSH and `rsync://` daemon paths share it.
+async fn do_upload_tree<T>(
+    transport: T,
+    cancel: CancelHandle,
+    local_dir: &Path,
+    remote_dir: &str,
+    options: &TransferOptions,
+) -> Result<RsyncStats, RsyncError>
+where
+    T: RawRemoteShellTransport + 'static,
+{
+    let start = Instant::now();
+    let tree = scan_local_tree(local_dir, options).await?;
+    let total_size = tree.total_size();
+    let mut source = LocalTreeRoot::new(local_dir).with_options(options.clone());
+
+    let mut driver = AerorsyncDriver::new(transport, cancel);
+    let adapter = CurrentDeltaSyncBridge::new();
+    let warnings = new_warnings_sink();
+    let mut bridge = build_event_bridge(warnings.clone());
+
+    let spec = RemoteCommandSpec::upload(tree_target(remote_dir)).with_options(options.clone());
+    let report = driver
+        .drive_upload_tree(spec, &tree, &mut source, &adapter, &mut bridge)
         .await
-        .map_err(map_write_atomic_error)?;
+        .map_err(|e| map_native_error_to_rsync(e, driver.committed()))?;
+    tracing::debug!(
+        "aerorsync tree upload {}: {}/{} files sent, {} dirs",
+        local_dir.display(),
+        report.files_transferred,
+        report.files_total,
+        report.dirs_total
+    );
 
     let duration_ms = start.elapsed().as_millis() as u64;
     let warnings = drain_warnings(warnings);
     Ok(build_stats(
         driver.session_stats(),
-        file_size,
+        total_size,
         duration_ms,
         warnings,
     ))
 }
 
+/// Tree download core; see [`do_upload_tree`].
+async fn do_download_tree<T>(
+    transport: T,
+    cancel: CancelHandle,
+    remote_dir: &str,
+    local_dir: &Path,
+    options: &TransferOptions,
+) -> Result<RsyncStats, RsyncError>
+where
+    T: RawRemoteShellTransport + 'static,
+{
+    let start = Instant::now();
+    let mut sink = LocalTreeRoot::new(local_dir).with_options(options.clone());
+
+    let mut driver = AerorsyncDriver::new(transport, cancel);
+    let adapter = CurrentDeltaSyncBridge::new();
+    let warnings = new_warnings_sink();
+    let mut bridge = build_event_bridge(warnings.clone());
+
+    let spec = RemoteCommandSpec::download(tree_target(remote_dir)).with_options(options.clone());
+    let report = driver
+        .drive_download_tree(spec, &mut sink, &adapter, &mut bridge)
+        .await
+        // Files already renamed into place make a classic retry of
+        // the whole tree unsafe to do silently.
+        .map_err(|e| map_native_error_to_rsync(e, sink.files_committed > 0))?;
+    tracing::debug!(
+        "aerorsync tree download {}: {}/{} files received, {} dirs, {} deleted",
+        local_dir.display(),
+        report.files_transferred,
+        report.files_total,
+        report.dirs_total,
+        report.entries_deleted
+    );
+
+    let duration_ms = start.elapsed().as_millis() as u64;
+    let warnings = drain_warnings(warnings);
+    Ok(build_stats(
+        driver.session_stats(),
+        report.bytes_transferred,
+        duration_ms,
+        warnings,
+    ))
+}
+
+// --- rsync daemon ----------------------------------------------------------
+
+/// `DeltaTransport` for `rsync://host/module` endpoints: the same
+/// upload, download and tree flows as [`AerorsyncDeltaTransport`], over
+/// a [`DaemonTransport`] TCP connection instead of an SSH exec channel.
+/// Remote paths are relative to the module.
+///
+/// No batch: every transfer opens its own connection, which on a
+/// daemon costs one TCP handshake and no key exchange.
+pub struct AerorsyncDaemonDeltaTransport {
+    config: DaemonClientConfig,
+    min_file_size: u64,
+    transfer_options: TransferOptions,
+}
+
+impl AerorsyncDaemonDeltaTransport {
+    pub fn new(config: DaemonClientConfig, min_file_size: u64) -> Self {
+        Self {
+            config,
+            min_file_size,
+            transfer_options: TransferOptions::default(),
+        }
+    }
+
+    /// See [`AerorsyncDeltaTransport::with_transfer_options`].
+    pub fn with_transfer_options(mut self, options: TransferOptions) -> Self {
+        self.transfer_options = options;
+        self
+    }
+
+    fn transport(&self) -> DaemonTransport {
+        DaemonTransport::new(self.config.clone())
+    }
+}
+
+#[async_trait]
+impl DeltaTransport for AerorsyncDaemonDeltaTransport {
+    fn name(&self) -> &'static str {
+        AERORSYNC_DAEMON_TRANSPORT_NAME
+    }
+
+    async fn probe_remote(&self) -> Result<RsyncCapability, RsyncError> {
+        let probe = self
+            .transport()
+            .probe()
+            .await
+            .map_err(map_native_probe_error_to_rsync)?;
+        Ok(RsyncCapability {
+            version: probe.remote_banner,
+            protocol: probe.protocol.0,
+        })
+    }
+
+    async fn probe_local(&self) -> Result<(), RsyncError> {
+        Ok(())
+    }
+
+    async fn download(
+        &self,
+        remote_path: &str,
+        local_path: &Path,
+    ) -> Result<RsyncStats, RsyncError> {
+        do_download(
+            self.transport(),
+            CancelHandle::inert(),
+            remote_path,
+            local_path,
+            &self.transfer_options,
+        )
+        .await
+    }
+
+    async fn upload(&self, local_path: &Path, remote_path: &str) -> Result<RsyncStats, RsyncError> {
+        do_upload(
+            self.transport(),
+            CancelHandle::inert(),
+            local_path,
+            remote_path,
+            self.min_file_size,
+            &self.transfer_options,
+        )
+        .await
+    }
+
+    async fn download_tree(
+        &self,
+        remote_dir: &str,
+        local_dir: &Path,
+    ) -> Result<RsyncStats, RsyncError> {
+        do_download_tree(
+            self.transport(),
+            CancelHandle::inert(),
+            remote_dir,
+            local_dir,
+            &self.transfer_options,
+        )
+        .await
+    }
+
+    async fn upload_tree(
+        &self,
+        local_dir: &Path,
+        remote_dir: &str,
+    ) -> Result<RsyncStats, RsyncError> {
+        do_upload_tree(
+            self.transport(),
+            CancelHandle::inert(),
+            local_dir,
+            remote_dir,
+            &self.transfer_options,
+        )
+        .await
+    }
+}
+
+// --- local to local --------------------------------------------------------
+
+/// `DeltaTransport` between two local paths: an external disk, a
+/// mounted NAS share, a FUSE mount of another provider. Sender and
+/// receiver both run in this process over an [`InProcessTransport`],
+/// so "remote" paths are local paths too. Data flows the way the call
+/// names it: `upload` copies `local_path` to `remote_path`, `download`
+/// copies `remote_path` to `local_path`; either way the destination
+/// is the side that is signed and rebuilt.
+///
+/// With `inplace` in the transfer options only the blocks that changed
+/// are written to the destination, which is what refreshing a large
+/// VM image wants. Without it the destination is rebuilt into a temp
+/// file and renamed, as for the other transports.
+pub struct AerorsyncLocalDeltaTransport {
+    min_file_size: u64,
+    transfer_options: TransferOptions,
+}
+
+impl AerorsyncLocalDeltaTransport {
+    pub fn new(min_file_size: u64) -> Self {
+        Self {
+            min_file_size,
+            transfer_options: TransferOptions::default(),
+        }
+    }
+
+    /// See [`AerorsyncDeltaTransport::with_transfer_options`].
+    pub fn with_transfer_options(mut self, options: TransferOptions) -> Self {
+        self.transfer_options = options;
+        self
+    }
+
+    /// The transfer options with compression off unless the caller
+    /// picked an algorithm: compressing a pipe inside one process only
+    /// costs CPU. Both ends get the same lists.
+    fn session_options(&self) -> TransferOptions {
+        let mut options = self.transfer_options.clone();
+        if options.algorithms.prefer_compressions.is_empty() {
+            options.algorithms.prefer_compressions = vec![CompressionAlgo::None];
+        }
+        options
+    }
+
+    async fn copy_file(&self, source: &Path, destination: &Path) -> Result<RsyncStats, RsyncError> {
+        let start = Instant::now();
+        let target = local_arg(destination)?;
+        let mut options = self.session_options();
+        // Nothing to delete next to a single file.
+        options.delete = false;
+        if options.append != AppendMode::Off {
+            // rsync's generator skips a file the local copy already
+            // covers; the receiver cannot tell a client sender so, hence
+            // the check before the session.
+            let source_len = fs::metadata(source).await.map_err(RsyncError::Io)?.len();
+            let covered = fs::metadata(destination)
+                .await
+                .is_ok_and(|meta| meta.is_file() && meta.len() >= source_len);
+            if covered {
+                let duration_ms = start.elapsed().as_millis() as u64;
+                return Ok(build_stats(
+                    &SessionStats::default(),
+                    0,
+                    duration_ms,
+                    Vec::new(),
+                ));
+            }
+        }
+        let transport = InProcessTransport::new(options.algorithms.clone());
+        let receiver = transport.receiver();
+        let sent = do_upload(
+            transport,
+            CancelHandle::inert(),
+            source,
+            target,
+            self.min_file_size,
+            &options,
+        )
+        .await;
+        settle_local_session(sent, receiver).await
+    }
+
+    async fn copy_tree(&self, source: &Path, destination: &Path) -> Result<RsyncStats, RsyncError> {
+        let target = local_arg(destination)?;
+        let options = self.session_options();
+        let transport = InProcessTransport::new(options.algorithms.clone());
+        let receiver = transport.receiver();
+        let sent = do_upload_tree(transport, CancelHandle::inert(), source, target, &options).await;
+        settle_local_session(sent, receiver).await
+    }
+}
+
+#[async_trait]
+impl DeltaTransport for AerorsyncLocalDeltaTransport {
+    fn name(&self) -> &'static str {
+        AERORSYNC_LOCAL_TRANSPORT_NAME
+    }
+
+    async fn probe_remote(&self) -> Result<RsyncCapability, RsyncError> {
+        let probe = InProcessTransport::new(AlgorithmPreferences::default())
+            .probe()
+            .await
+            .map_err(map_native_probe_error_to_rsync)?;
+        Ok(RsyncCapability {
+            version: probe.remote_banner,
+            protocol: probe.protocol.0,
+        })
+    }
+
+    async fn probe_local(&self) -> Result<(), RsyncError> {
+        Ok(())
+    }
+
+    async fn download(
+        &self,
+        remote_path: &str,
+        local_path: &Path,
+    ) -> Result<RsyncStats, RsyncError> {
+        self.copy_file(Path::new(remote_path), local_path).await
+    }
+
+    async fn upload(&self, local_path: &Path, remote_path: &str) -> Result<RsyncStats, RsyncError> {
+        self.copy_file(local_path, Path::new(remote_path)).await
+    }
+
+    async fn download_tree(
+        &self,
+        remote_dir: &str,
+        local_dir: &Path,
+    ) -> Result<RsyncStats, RsyncError> {
+        self.copy_tree(Path::new(remote_dir), local_dir).await
+    }
+
+    async fn upload_tree(
+        &self,
+        local_dir: &Path,
+        remote_dir: &str,
+    ) -> Result<RsyncStats, RsyncError> {
+        self.copy_tree(local_dir, Path::new(remote_dir)).await
+    }
+}
+
+/// A destination travels on the server command line, which is text.
+fn local_arg(path: &Path) -> Result<&str, RsyncError> {
+    path.to_str().ok_or_else(|| RsyncError::TransferFailed {
+        exit: -1,
+        stderr: format!("native fallback: {} is not valid UTF-8", path.display()),
+    })
+}
+
+/// Combine both halves of a local session. A receiver failure wins:
+/// the sender then usually saw nothing but the pipe closing. A sender
+/// that got to the end has had its data written, so a late receiver
+/// failure leaves the destination in an unknown state.
+async fn settle_local_session(
+    sent: Result<RsyncStats, RsyncError>,
+    receiver: ReceiverTask,
+) -> Result<RsyncStats, RsyncError> {
+    match (sent, receiver.join().await) {
+        (sent, Ok(())) => sent,
+        (sent, Err(e)) => {
+            let committed = matches!(sent, Ok(_) | Err(RsyncError::HardRejection(_)));
+            Err(map_native_error_to_rsync(e, committed))
+        }
+    }
+}
+
+/// `dir/`: rsync copies the *contents* of a source spelled with a
+/// trailing slash, so both ends name the same directory.
+fn tree_target(dir: &str) -> String {
+    format!("{}/", dir.trim_end_matches('/'))
+}
+
+/// Where `--partial-dir DIR` keeps `target`'s partial file: `DIR` is
+/// relative to the target's directory unless absolute, like rsync.
+fn partial_path_for(target: &Path, partial_dir: &str) -> Option<PathBuf> {
+    let name = target.file_name()?;
+    let dir = Path::new(partial_dir);
+    let dir = if dir.is_absolute() {
+        dir.to_path_buf()
+    } else {
+        target.parent().unwrap_or(Path::new("")).join(dir)
+    };
+    Some(dir.join(name))
+}
+
+/// Local directory acting as [`TreeSource`] (upload) or [`TreeSink`]
+/// (download). List paths are already sanitized by `TreeFileList`, so
+/// joining them under `root` cannot escape it.
+pub(crate) struct LocalTreeRoot {
+    root: PathBuf,
+    files_committed: u64,
+    options: TransferOptions,
+    /// Remote owner names to local ids, for the receiver side.
+    ids: IdMapper,
+}
+
+impl LocalTreeRoot {
+    pub(crate) fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            files_committed: 0,
+            options: TransferOptions::default(),
+            ids: IdMapper::new(),
+        }
+    }
+
+    pub(crate) fn with_options(mut self, options: TransferOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        if path == TREE_ROOT_PATH {
+            self.root.clone()
+        } else {
+            self.root.join(path)
+        }
+    }
+}
+
+fn tree_io_error(action: &str, path: &Path, err: impl std::fmt::Display) -> AerorsyncError {
+    AerorsyncError::new(
+        AerorsyncErrorKind::Internal,
+        format!("cannot {action} {}: {err}", path.display()),
+    )
+}
+
+#[async_trait]
+impl TreeSource for LocalTreeRoot {
+    async fn read_file(&mut self, path: &str) -> Result<Vec<u8>, AerorsyncError> {
+        let local = self.resolve(path);
+        fs::read(&local)
+            .await
+            .map_err(|e| tree_io_error("read", &local, e))
+    }
+}
+
+#[async_trait]
+impl TreeSink for LocalTreeRoot {
+    async fn create_dir(
+        &mut self,
+        path: &str,
+        entry: &FileListEntry,
+    ) -> Result<(), AerorsyncError> {
+        let local = self.resolve(path);
+        match fs::symlink_metadata(&local).await {
+            Ok(meta) if meta.is_dir() => {}
+            // Never write through a symlink or over a file that sits
+            // where the remote has a directory.
+            Ok(_) => {
+                return Err(tree_io_error(
+                    "create directory",
+                    &local,
+                    "a non-directory is in the way",
+                ))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                fs::create_dir_all(&local)
+                    .await
+                    .map_err(|e| tree_io_error("create directory", &local, e))?;
+            }
+            Err(e) => return Err(tree_io_error("stat", &local, e)),
+        }
+        self.ids
+            .attrs_for(entry)
+            .apply(&local)
+            .map_err(|(stage, e)| tree_io_error(stage, &local, e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let perms = std::fs::Permissions::from_mode(entry.mode & 0o7777);
+            fs::set_permissions(&local, perms)
+                .await
+                .map_err(|e| tree_io_error("chmod", &local, e))?;
+        }
+        #[cfg(not(unix))]
+        let _ = entry;
+        Ok(())
+    }
+
+    async fn baseline_mtime(&mut self, path: &str) -> Result<Option<i64>, AerorsyncError> {
+        let local = self.resolve(path);
+        match fs::symlink_metadata(&local).await {
+            Ok(meta) => Ok(Some(file_mtime_components(&meta).0)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(tree_io_error("stat", &local, e)),
+        }
+    }
+
+    async fn read_baseline(&mut self, path: &str) -> Result<Option<Vec<u8>>, AerorsyncError> {
+        let local = self.resolve(path);
+        match fs::read(&local).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(tree_io_error("read baseline", &local, e)),
+        }
+    }
+
+    async fn commit_file(
+        &mut self,
+        path: &str,
+        entry: &FileListEntry,
+        data: Vec<u8>,
+    ) -> Result<(), AerorsyncError> {
+        let local = self.resolve(path);
+        let attrs = self.ids.attrs_for(entry);
+        if self.options.writes_in_place() || self.options.sparse || !attrs.is_empty() {
+            commit_with_streaming_writer(&local, entry, &data, &self.options, attrs).await?;
+            self.files_committed += 1;
+            return Ok(());
+        }
+        write_atomic_chunked(
+            &local,
+            &data,
+            ATOMIC_WRITE_CHUNK_SIZE,
+            None,
+            Some(entry.mode),
+            Some((entry.mtime, entry.mtime_nsec)),
+        )
+        .await
+        .map_err(|e| match e {
+            WriteAtomicError::PreOpen(io) => tree_io_error("write", &local, io),
+            WriteAtomicError::PostOpen { stage, source } => tree_io_error(stage, &local, source),
+        })?;
+        self.files_committed += 1;
+        Ok(())
+    }
+
+    async fn create_symlink(
+        &mut self,
+        path: &str,
+        entry: &FileListEntry,
+    ) -> Result<(), AerorsyncError> {
+        let local = self.resolve(path);
+        let target = entry.link_target.as_deref().unwrap_or_default();
+        if fs::symlink_metadata(&local)
+            .await
+            .is_ok_and(|meta| meta.is_dir())
+        {
+            return Err(tree_io_error(
+                "create symlink",
+                &local,
+                "a directory is in the way",
+            ));
+        }
+        if fs::read_link(&local)
+            .await
+            .is_ok_and(|current| current == Path::new(target))
+        {
+            return self.apply_attrs(path, entry).await;
+        }
+        let temp = tree_temp_sibling(&local);
+        #[cfg(unix)]
+        let created = fs::symlink(target, &temp).await;
+        #[cfg(not(unix))]
+        let created = Err::<(), _>(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "symlinks need a Unix receiver",
+        ));
+        created.map_err(|e| tree_io_error("create symlink", &local, e))?;
+        fs::rename(&temp, &local).await.map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            tree_io_error("rename", &local, e)
+        })?;
+        self.apply_attrs(path, entry).await
+    }
+
+    async fn hard_link(&mut self, leader: &str, path: &str) -> Result<(), AerorsyncError> {
+        let source = self.resolve(leader);
+        let local = self.resolve(path);
+        let same_inode = match (
+            std::fs::symlink_metadata(&source),
+            std::fs::symlink_metadata(&local),
+        ) {
+            #[cfg(unix)]
+            (Ok(a), Ok(b)) => {
+                use std::os::unix::fs::MetadataExt;
+                a.dev() == b.dev() && a.ino() == b.ino()
+            }
+            _ => false,
+        };
+        if same_inode {
+            return Ok(());
+        }
+        // Link under a temp name first so an existing `path` is only
+        // replaced once the new link exists.
+        let temp = tree_temp_sibling(&local);
+        fs::hard_link(&source, &temp)
+            .await
+            .map_err(|e| tree_io_error("hard-link", &local, e))?;
+        fs::rename(&temp, &local).await.map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            tree_io_error("rename", &local, e)
+        })?;
+        Ok(())
+    }
+
+    async fn apply_attrs(
+        &mut self,
+        path: &str,
+        entry: &FileListEntry,
+    ) -> Result<(), AerorsyncError> {
+        let local = self.resolve(path);
+        self.ids
+            .attrs_for(entry)
+            .apply(&local)
+            .map_err(|(stage, e)| tree_io_error(stage, &local, e))?;
+        let mtime = filetime::FileTime::from_unix_time(
+            entry.mtime,
+            entry.mtime_nsec.unwrap_or(0).max(0) as u32,
+        );
+        if is_symlink_mode(entry.mode) {
+            return filetime::set_symlink_file_times(&local, mtime, mtime)
+                .map_err(|e| tree_io_error("set mtime", &local, e));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let perms = std::fs::Permissions::from_mode(entry.mode & 0o7777);
+            fs::set_permissions(&local, perms)
+                .await
+                .map_err(|e| tree_io_error("chmod", &local, e))?;
+        }
+        filetime::set_file_mtime(&local, mtime).map_err(|e| tree_io_error("set mtime", &local, e))
+    }
+
+    async fn delete_extraneous(
+        &mut self,
+        dir: &str,
+        keep: &BTreeSet<String>,
+    ) -> Result<u64, AerorsyncError> {
+        let local_dir = self.resolve(dir);
+        let mut entries = match fs::read_dir(&local_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(tree_io_error("list", &local_dir, e)),
+        };
+        // A relative partial dir is protected from deletion, as rsync
+        // does with its implicit `--filter=P DIR` rule.
+        let partial_dir = self
+            .options
+            .effective_partial_dir()
+            .filter(|d| !Path::new(d).is_absolute() && !d.contains('/'));
+        let mut deleted = 0u64;
+        while let Some(item) = entries
+            .next_entry()
+            .await
+            .map_err(|e| tree_io_error("list", &local_dir, e))?
+        {
+            let name = item.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            if keep.contains(name) || partial_dir == Some(name) {
+                continue;
+            }
+            let victim = item.path();
+            let is_dir = item.file_type().await.is_ok_and(|t| t.is_dir());
+            let removed = if is_dir {
+                fs::remove_dir_all(&victim).await
+            } else {
+                fs::remove_file(&victim).await
+            };
+            removed.map_err(|e| tree_io_error("delete", &victim, e))?;
+            tracing::debug!("aerorsync --delete: removed {}", victim.display());
+            deleted += 1;
+        }
+        Ok(deleted)
+    }
+}
+
+/// Temp name next to `local` for the symlink / hard-link swaps: a
+/// dot-file in the same directory, so the rename stays atomic.
+fn tree_temp_sibling(local: &Path) -> PathBuf {
+    let name = local
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    local.with_file_name(format!(".{name}.aerorsync-{}", std::process::id()))
+}
+
+/// Tree-download commit when the file needs more than the temp +
+/// rename of `write_atomic_chunked`: in-place and/or sparse writes, or
+/// owner, xattrs and ACLs.
+async fn commit_with_streaming_writer(
+    local: &Path,
+    entry: &FileListEntry,
+    data: &[u8],
+    options: &TransferOptions,
+    attrs: attrs::FileAttrs,
+) -> Result<(), AerorsyncError> {
+    let writer = if options.writes_in_place() {
+        StreamingAtomicWriter::in_place(local, 0).await
+    } else {
+        StreamingAtomicWriter::new(local).await
+    };
+    let mut writer = writer
+        .map_err(|e| tree_io_error("open", local, e))?
+        .with_sparse(options.sparse)
+        .with_attrs(attrs);
+    writer
+        .write_all(data)
+        .await
+        .map_err(|e| tree_io_error("write", local, e))?;
+    let mtime = (entry.mtime, entry.mtime_nsec.unwrap_or(0).max(0) as u32);
+    writer
+        .finalize(Some(entry.mode), Some(mtime))
+        .await
+        .map_err(|e| match e {
+            WriteAtomicError::PreOpen(io) => tree_io_error("write", local, io),
+            WriteAtomicError::PostOpen { stage, source } => tree_io_error(stage, local, source),
+        })
+}
+
+/// What the tree scan keeps of one local entry besides its metadata.
+struct ScannedEntry {
+    path: String,
+    metadata: std::fs::Metadata,
+    link_target: Option<String>,
+    acl: Option<EntryAcl>,
+    xattrs: Option<Vec<XattrItem>>,
+}
+
+/// Walk `local_dir` into the sender-side [`TreeFileList`]: the root as
+/// `.`, then every directory, regular file and symlink below it with
+/// the same metadata `build_source_entry` puts on single-file uploads.
+/// Symlinks are sent as links, never followed; special files are
+/// skipped. `options` decides whether hard-link groups, ACLs and
+/// xattrs are collected.
+pub(crate) async fn scan_local_tree(
+    local_dir: &Path,
+    options: &TransferOptions,
+) -> Result<TreeFileList, RsyncError> {
+    let root = local_dir.to_path_buf();
+    let (want_acls, want_xattrs) = (options.acls, options.xattrs);
+    let walked = tokio::task::spawn_blocking(move || {
+        let mut found: Vec<ScannedEntry> = Vec::new();
+        for item in walkdir::WalkDir::new(&root).follow_links(false) {
+            let item = item.map_err(|e| RsyncError::Io(e.into()))?;
+            let file_type = item.file_type();
+            if !file_type.is_dir() && !file_type.is_file() && !file_type.is_symlink() {
+                tracing::debug!(
+                    "aerorsync tree scan: skipping special file {}",
+                    item.path().display()
+                );
+                continue;
+            }
+            let relative = item.path().strip_prefix(&root).unwrap_or(item.path());
+            let path = if relative.as_os_str().is_empty() {
+                TREE_ROOT_PATH.to_string()
+            } else {
+                let Some(utf8) = relative.to_str() else {
+                    tracing::warn!(
+                        "aerorsync tree scan: skipping non-UTF-8 path {}",
+                        item.path().display()
+                    );
+                    continue;
+                };
+                utf8.replace(std::path::MAIN_SEPARATOR, "/")
+            };
+            let link_target = if file_type.is_symlink() {
+                let target = std::fs::read_link(item.path()).map_err(RsyncError::Io)?;
+                let Some(target) = target.to_str().map(str::to_string) else {
+                    tracing::warn!(
+                        "aerorsync tree scan: skipping symlink with non-UTF-8 target {}",
+                        item.path().display()
+                    );
+                    continue;
+                };
+                Some(target)
+            } else {
+                None
+            };
+            let metadata = item.metadata().map_err(|e| RsyncError::Io(e.into()))?;
+            let acl = if want_acls && !file_type.is_symlink() {
+                Some(
+                    attrs::read_acl(item.path(), file_mode_from_metadata(&metadata))
+                        .map_err(RsyncError::Io)?,
+                )
+            } else {
+                None
+            };
+            let xattrs = if want_xattrs {
+                Some(attrs::read_xattrs(item.path()).map_err(RsyncError::Io)?)
+            } else {
+                None
+            };
+            found.push(ScannedEntry {
+                path,
+                metadata,
+                link_target,
+                acl,
+                xattrs,
+            });
+        }
+        Ok::<_, RsyncError>(found)
+    })
+    .await
+    .map_err(|e| RsyncError::Io(std::io::Error::other(e)))??;
+
+    let mut entries = Vec::with_capacity(walked.len());
+    let mut inodes = HashMap::new();
+    for scanned in walked {
+        let ScannedEntry {
+            path,
+            metadata,
+            link_target,
+            acl,
+            xattrs,
+        } = scanned;
+        let (mtime, mtime_nsec) = file_mtime_components(&metadata);
+        let (uid, gid) = file_owner_components(&metadata);
+        let (size, checksum) = if metadata.is_file() {
+            let checksum = compute_xxh128_file_streaming(&local_dir.join(&path))
+                .await
+                .map_err(RsyncError::Io)?;
+            (metadata.len() as i64, checksum)
+        } else if metadata.is_symlink() {
+            (metadata.len() as i64, Vec::new())
+        } else {
+            (0, Vec::new())
+        };
+        #[cfg(unix)]
+        if options.hard_links && !metadata.is_dir() {
+            use std::os::unix::fs::MetadataExt;
+            if metadata.nlink() > 1 {
+                inodes.insert(path.clone(), (metadata.dev(), metadata.ino()));
+            }
+        }
+        entries.push(FileListEntry {
+            // Recomputed for the send order by `TreeFileList`.
+            flags: 0,
+            path,
+            size,
+            mtime,
+            mtime_nsec: Some(mtime_nsec.unwrap_or(0)),
+            mode: file_mode_from_metadata(&metadata),
+            uid: Some(uid as i64),
+            uid_name: Some(lookup_user_name(uid)),
+            gid: Some(gid as i64),
+            gid_name: Some(lookup_group_name(gid)),
+            checksum,
+            link_target,
+            hardlink: None,
+            acl,
+            xattrs,
+        });
+    }
+    TreeFileList::from_local_entries_linked(entries, &inodes).map_err(|e| {
+        RsyncError::TransferFailed {
+            exit: -1,
+            stderr: format!("native fallback: cannot build tree file list: {}", e.detail),
+        }
+    })
+}
+
 // --- batch (W3.2(b2)) -----------------------------------------------------
 
 /// P3-T01 W3.2(b2): concrete [`DeltaBatch`] impl backed by russh.
@@ -572,6 +1657,7 @@ pub struct AerorsyncBatch {
     ///
     /// [`finalize`]: DeltaBatch::finalize
     cancel_observed: Arc<AtomicBool>,
+    transfer_options: TransferOptions,
 }
 
 impl AerorsyncBatch {
@@ -584,8 +1670,14 @@ impl AerorsyncBatch {
             files_transferred: AtomicU64::new(0),
             bytes_on_wire: AtomicU64::new(0),
             cancel_observed: flag,
+            transfer_options: TransferOptions::default(),
         }
     }
+
+    fn with_transfer_options(mut self, options: TransferOptions) -> Self {
+        self.transfer_options = options;
+        self
+    }
 }
 
 #[async_trait]
@@ -601,6 +1693,7 @@ impl DeltaBatch for AerorsyncBatch {
             local_path,
             remote_path,
             self.min_file_size,
+            &self.transfer_options,
         )
         .await?;
         self.files_transferred.fetch_add(1, Ordering::SeqCst);
@@ -619,6 +1712,7 @@ impl DeltaBatch for AerorsyncBatch {
             self.cancel.clone(),
             remote_path,
             local_path,
+            &self.transfer_options,
         )
         .await?;
         self.files_transferred.fetch_add(1, Ordering::SeqCst);
@@ -704,6 +1798,10 @@ fn build_source_entry(
         gid: Some(gid_value as i64),
         gid_name: Some(gid_name),
         checksum: file_checksum,
+        link_target: None,
+        hardlink: None,
+        acl: None,
+        xattrs: None,
     }
 }
 
@@ -1695,4 +2793,139 @@ mod tests {
 
         assert_eq!(stats.session_count, 2);
     }
+
+    // -- transfer options ----------------------------------------------------
+
+    #[test]
+    fn partial_path_is_relative_to_the_target_directory_unless_absolute() {
+        let target = Path::new("/data/in/big.iso");
+        assert_eq!(
+            partial_path_for(target, ".rsync-partial"),
+            Some(PathBuf::from("/data/in/.rsync-partial/big.iso"))
+        );
+        assert_eq!(
+            partial_path_for(target, "/var/tmp/partials"),
+            Some(PathBuf::from("/var/tmp/partials/big.iso"))
+        );
+        assert_eq!(partial_path_for(Path::new("/"), ".rsync-partial"), None);
+    }
+
+    #[tokio::test]
+    async fn local_tree_delete_extraneous_spares_kept_names_and_partial_dir() {
+        let dir = fresh_tempdir();
+        write_test_file(&dir, "keep.txt", b"k");
+        write_test_file(&dir, "stale.txt", b"s");
+        std::fs::create_dir_all(dir.path().join("old/deep")).unwrap();
+        std::fs::write(dir.path().join("old/deep/x"), b"x").unwrap();
+        std::fs::create_dir(dir.path().join(".rsync-partial")).unwrap();
+
+        let mut root = LocalTreeRoot::new(dir.path()).with_options(TransferOptions {
+            delete: true,
+            partial_dir: Some(".rsync-partial".into()),
+            ..TransferOptions::default()
+        });
+        let keep: BTreeSet<String> = ["keep.txt".to_string()].into();
+        let deleted = root.delete_extraneous(".", &keep).await.expect("delete");
+
+        assert_eq!(deleted, 2);
+        assert!(dir.path().join("keep.txt").exists());
+        assert!(!dir.path().join("stale.txt").exists());
+        assert!(!dir.path().join("old").exists());
+        assert!(dir.path().join(".rsync-partial").exists());
+        assert_eq!(
+            root.delete_extraneous("missing", &keep)
+                .await
+                .expect("gone"),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn local_tree_inplace_commit_rewrites_the_target_without_a_temp() {
+        let dir = fresh_tempdir();
+        let target = write_test_file(&dir, "f.bin", b"old contents, longer");
+        let entry = FileListEntry {
+            flags: 0,
+            path: "f.bin".into(),
+            size: 3,
+            mtime: 1_700_000_000,
+            mtime_nsec: Some(0),
+            mode: 0o100644,
+            uid: None,
+            uid_name: None,
+            gid: None,
+            gid_name: None,
+            checksum: Vec::new(),
+            link_target: None,
+            hardlink: None,
+            acl: None,
+            xattrs: None,
+        };
+        let mut root = LocalTreeRoot::new(dir.path()).with_options(TransferOptions {
+            inplace: true,
+            ..TransferOptions::default()
+        });
+        root.commit_file("f.bin", &entry, b"new".to_vec())
+            .await
+            .expect("commit");
+
+        assert_eq!(std::fs::read(&target).unwrap(), b"new");
+        assert_eq!(root.files_committed, 1);
+        let leftovers: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().contains(TEMP_SUFFIX))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn local_tree_scan_and_sink_keep_symlinks_and_hard_links() {
+        let src = fresh_tempdir();
+        write_test_file(&src, "a.txt", b"alpha");
+        std::fs::hard_link(src.path().join("a.txt"), src.path().join("b.txt")).unwrap();
+        std::os::unix::fs::symlink("a.txt", src.path().join("link")).unwrap();
+
+        let options = TransferOptions {
+            hard_links: true,
+            ..TransferOptions::default()
+        };
+        let tree = scan_local_tree(src.path(), &options).await.expect("scan");
+        let link = tree.segments()[0]
+            .entries
+            .iter()
+            .find(|e| e.path == "link")
+            .expect("symlink listed");
+        assert_eq!(link.link_target.as_deref(), Some("a.txt"));
+        let followers: Vec<_> = tree
+            .hardlink_followers()
+            .map(|(e, ndx)| (e.path.clone(), ndx))
+            .collect();
+        assert_eq!(followers, vec![("b.txt".to_string(), 2)]);
+
+        let dst = fresh_tempdir();
+        let mut root = LocalTreeRoot::new(dst.path()).with_options(options);
+        let a = tree.segments()[0]
+            .entries
+            .iter()
+            .find(|e| e.path == "a.txt")
+            .unwrap();
+        root.commit_file("a.txt", a, b"alpha".to_vec())
+            .await
+            .expect("commit");
+        root.hard_link("a.txt", "b.txt").await.expect("link");
+        // An existing symlink with another target is replaced.
+        std::os::unix::fs::symlink("elsewhere", dst.path().join("link")).unwrap();
+        root.create_symlink("link", link).await.expect("symlink");
+
+        use std::os::unix::fs::MetadataExt;
+        let a_meta = std::fs::metadata(dst.path().join("a.txt")).unwrap();
+        let b_meta = std::fs::metadata(dst.path().join("b.txt")).unwrap();
+        assert_eq!(a_meta.ino(), b_meta.ino());
+        assert_eq!(
+            std::fs::read_link(dst.path().join("link")).unwrap(),
+            PathBuf::from("a.txt")
+        );
+    }
 }
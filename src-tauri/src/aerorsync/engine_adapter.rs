@@ -194,6 +194,15 @@ pub trait DeltaEngineAdapter: Send + Sync {
         block_size: usize,
     ) -> Vec<EngineSignatureBlock>;
 
+    /// Chunk-fed counterpart of `build_signatures`: the returned builder
+    /// takes the destination bytes in arbitrary contiguous chunks and
+    /// emits each signature as soon as its block is complete, so the
+    /// basis never has to sit in memory as a whole. The default builder
+    /// runs the same rolling + strong hash pair as `delta_sync`.
+    fn build_signatures_streaming(&self, block_size: usize) -> Box<dyn SignatureBuilder> {
+        Box::new(RollingSignatureBuilder::new(block_size))
+    }
+
     fn compute_delta(
         &self,
         source_data: &[u8],
@@ -201,6 +210,20 @@ pub trait DeltaEngineAdapter: Send + Sync {
         block_size: usize,
     ) -> EngineDeltaPlan;
 
+    /// `compute_delta` against signatures rebuilt from wire sums, whose
+    /// strong hash holds only its first `strong_len` bytes. The default
+    /// suits engines that keep full-length hashes on the wire.
+    fn compute_delta_truncated(
+        &self,
+        source_data: &[u8],
+        destination_signatures: &[EngineSignatureBlock],
+        block_size: usize,
+        strong_len: usize,
+    ) -> EngineDeltaPlan {
+        let _ = strong_len;
+        self.compute_delta(source_data, destination_signatures, block_size)
+    }
+
     /// Reconstruct a file from the destination data and a delta instruction
     /// stream. Used by the download-with-engine driver path. `block_size` is
     /// the block size the signatures were computed with.
@@ -245,6 +268,16 @@ impl DeltaEngineAdapter for CurrentDeltaSyncBridge {
         source_data: &[u8],
         destination_signatures: &[EngineSignatureBlock],
         block_size: usize,
+    ) -> EngineDeltaPlan {
+        self.compute_delta_truncated(source_data, destination_signatures, block_size, 32)
+    }
+
+    fn compute_delta_truncated(
+        &self,
+        source_data: &[u8],
+        destination_signatures: &[EngineSignatureBlock],
+        block_size: usize,
+        strong_len: usize,
     ) -> EngineDeltaPlan {
         // Reconstruct the engine's SignatureTable from the engine-form input.
         // `file_size` is recovered as the sum of per-block lengths: which is
@@ -263,7 +296,8 @@ impl DeltaEngineAdapter for CurrentDeltaSyncBridge {
             file_size,
             signatures,
         };
-        let (ops, result) = delta_sync::compute_delta(source_data, &table);
+        let (ops, result) =
+            delta_sync::compute_delta_with_strong_len(source_data, &table, strong_len);
         EngineDeltaPlan {
             ops: ops.into_iter().map(Into::into).collect(),
             copy_blocks: result.copy_blocks,
@@ -355,9 +389,9 @@ pub trait DeltaPlanProducer: Send {
 ///   and again at `finalize`.
 ///
 /// Memory footprint: `O(block_size + literal_run_length)`. The literal run
-/// is unbounded for sources that never match: same as the bulk planner;
-/// callers that need a hard ceiling enforce it externally (see
-/// `PLAN_WINDOW_MAX_BYTES` in `delta_transport_impl`).
+/// is unbounded for sources that never match: same as the bulk planner.
+/// `with_max_literal` caps it by cutting the run into consecutive
+/// literals, which the wire encoders join back into one run.
 pub struct RollingDeltaPlanProducer {
     block_size: usize,
     signatures: Vec<EngineSignatureBlock>,
@@ -366,6 +400,10 @@ pub struct RollingDeltaPlanProducer {
     pos: usize,
     rolling: Option<RollingChecksum>,
     literal_buf: Vec<u8>,
+    /// Longest literal emitted in one op (`with_max_literal`).
+    max_literal: usize,
+    /// Strong-hash bytes compared per candidate (`with_strong_len`).
+    strong_len: usize,
     stats: EngineDeltaStats,
     /// Tracks whether the producer has emitted any op yet. Needed to
     /// match the bulk planner's quirk of emitting a single `Literal(empty)`
@@ -392,12 +430,31 @@ impl RollingDeltaPlanProducer {
             pos: 0,
             rolling: None,
             literal_buf: Vec::new(),
+            max_literal: usize::MAX,
+            strong_len: 32,
             stats: EngineDeltaStats::default(),
             has_emitted: false,
             finalized: false,
         }
     }
 
+    /// Emit unmatched bytes as soon as `max_literal` of them pile up,
+    /// instead of one literal per run. The op sequence then differs from
+    /// `compute_delta` only in how runs are split; their concatenation
+    /// is the same.
+    pub fn with_max_literal(mut self, max_literal: usize) -> Self {
+        self.max_literal = max_literal.max(1);
+        self
+    }
+
+    /// Compare only the first `strong_len` bytes of each strong hash:
+    /// signatures rebuilt from wire sums carry the truncated
+    /// `checksum_length` prefix, zero-padded.
+    pub fn with_strong_len(mut self, strong_len: usize) -> Self {
+        self.strong_len = strong_len.clamp(1, 32);
+        self
+    }
+
     fn flush_literal(&mut self, out: &mut Vec<EngineDeltaOp>) {
         if !self.literal_buf.is_empty() {
             self.stats.literal_bytes += self.literal_buf.len() as u64;
@@ -413,9 +470,10 @@ impl RollingDeltaPlanProducer {
         let candidates = self.lookup.get(&rolling_val)?;
         let window = &self.source_buf[self.pos..self.pos + self.block_size];
         let strong = strong_hash(window);
+        let n = self.strong_len;
         candidates
             .iter()
-            .find(|&&idx| self.signatures[idx].strong == strong)
+            .find(|&&idx| self.signatures[idx].strong[..n] == strong[..n])
             .copied()
     }
 }
@@ -467,6 +525,9 @@ impl DeltaPlanProducer for RollingDeltaPlanProducer {
             let old_byte = self.source_buf[self.pos];
             let new_byte = self.source_buf[self.pos + self.block_size];
             self.literal_buf.push(old_byte);
+            if self.literal_buf.len() >= self.max_literal {
+                self.flush_literal(out);
+            }
             self.rolling
                 .as_mut()
                 .expect("rolling initialised above")
@@ -510,6 +571,111 @@ impl DeltaPlanProducer for RollingDeltaPlanProducer {
     }
 }
 
+// --- streaming signature builder -----------------------------------------
+//
+// `SignatureBuilder` is to `build_signatures` what `DeltaPlanProducer` is to
+// `compute_delta`: the receiver feeds its basis file through it chunk by
+// chunk and writes each `sum_block` to the wire as it comes out, so the
+// signature phase of a multi-GB download holds one block of the basis
+// instead of all of it.
+
+/// Chunk-fed signature generator. Chunks must be logically contiguous;
+/// `finalize` emits the short tail block, if any, and must be called once.
+pub trait SignatureBuilder: Send {
+    /// Feed one chunk; appends the signature of every block it completes.
+    fn drive_chunk(&mut self, chunk: &[u8], out: &mut Vec<EngineSignatureBlock>);
+
+    /// Emit the trailing partial block.
+    fn finalize(&mut self, out: &mut Vec<EngineSignatureBlock>);
+}
+
+/// `SignatureBuilder` over the `delta_sync` rolling checksum and strong
+/// hash: block for block identical to `delta_sync::compute_signatures`,
+/// for any chunking. Pinned by `signature_builder_matches_bulk_*`.
+pub struct RollingSignatureBuilder {
+    block_size: usize,
+    /// Bytes of the block being assembled across chunk boundaries.
+    partial: Vec<u8>,
+    next_index: u32,
+}
+
+impl RollingSignatureBuilder {
+    /// `block_size == 0` produces no signatures, like an empty basis.
+    pub fn new(block_size: usize) -> Self {
+        Self {
+            block_size,
+            partial: Vec::new(),
+            next_index: 0,
+        }
+    }
+
+    fn sign(&mut self, block: &[u8]) -> EngineSignatureBlock {
+        let sig = EngineSignatureBlock {
+            index: self.next_index,
+            rolling: RollingChecksum::new(block).value(),
+            strong: strong_hash(block),
+            block_len: block.len() as u32,
+        };
+        self.next_index += 1;
+        sig
+    }
+}
+
+impl SignatureBuilder for RollingSignatureBuilder {
+    fn drive_chunk(&mut self, mut chunk: &[u8], out: &mut Vec<EngineSignatureBlock>) {
+        if self.block_size == 0 {
+            return;
+        }
+        if !self.partial.is_empty() {
+            let take = (self.block_size - self.partial.len()).min(chunk.len());
+            self.partial.extend_from_slice(&chunk[..take]);
+            chunk = &chunk[take..];
+            if self.partial.len() < self.block_size {
+                return;
+            }
+            let block = std::mem::take(&mut self.partial);
+            let sig = self.sign(&block);
+            out.push(sig);
+        }
+        let mut blocks = chunk.chunks_exact(self.block_size);
+        for block in blocks.by_ref() {
+            let sig = self.sign(block);
+            out.push(sig);
+        }
+        self.partial.extend_from_slice(blocks.remainder());
+    }
+
+    fn finalize(&mut self, out: &mut Vec<EngineSignatureBlock>) {
+        if !self.partial.is_empty() {
+            let block = std::mem::take(&mut self.partial);
+            let sig = self.sign(&block);
+            out.push(sig);
+        }
+    }
+}
+
+/// Most blocks a locally built signature set may have before the block
+/// length grows past the engine's choice.
+pub const MAX_SIGNATURE_BLOCKS: u64 = 1 << 17;
+
+/// `MAX_BLOCK_SIZE` of rsync protocol 30+ (`rsync.h`): the largest block
+/// length a stock sender accepts in a `sum_head`.
+pub const MAX_SIGNATURE_BLOCK_LEN: usize = 1 << 17;
+
+/// Block length for the signatures of a `file_size`-byte basis. The
+/// engine's choice stops growing at 8 KiB, which leaves a 50 GB basis
+/// with six million blocks; past `MAX_SIGNATURE_BLOCKS` the length grows
+/// instead (a multiple of 8, like `generator.c::sum_sizes_sqroot`) up
+/// to `MAX_SIGNATURE_BLOCK_LEN`, so the sender's table stays bounded.
+pub fn signature_block_size(adapter: &dyn DeltaEngineAdapter, file_size: u64) -> usize {
+    let engine = adapter.compute_block_size(file_size);
+    let spread = file_size.div_ceil(MAX_SIGNATURE_BLOCKS) as usize;
+    if spread <= engine {
+        return engine;
+    }
+    spread.next_multiple_of(8).min(MAX_SIGNATURE_BLOCK_LEN)
+}
+
 // --- W2.1: streaming download baseline source ----------------------------
 //
 // `BaselineSource` is the random-access read counterpart of the streaming
@@ -780,6 +946,21 @@ where
     Ok(bytes_written)
 }
 
+/// Sink of a streamed reconstruction that may be updating the basis
+/// file itself. When the delta copies a block onto its own offset, an
+/// in-place writer can step over it instead of rewriting identical
+/// bytes, as `receiver.c` seeks past such blocks under `--inplace`.
+pub trait ReconstructionWriter: AsyncWrite + Send + Unpin {
+    /// Advance the write position by `len` bytes without writing them,
+    /// the data already there being kept. Returns `false` when the
+    /// writer does not update the basis in place: the caller then
+    /// writes the block as usual.
+    fn skip_in_place(&mut self, len: u64) -> bool {
+        let _ = len;
+        false
+    }
+}
+
 #[cfg(test)]
 mod apply_delta_streaming_tests {
     use super::*;
@@ -1488,4 +1669,96 @@ mod producer_tests {
         producer.drive_chunk(b"ignored", &mut out);
         assert_eq!(out, after_first);
     }
+
+    /// Join consecutive literals so a capped op sequence can be compared
+    /// with the bulk one.
+    fn merge_literals(ops: Vec<EngineDeltaOp>) -> Vec<EngineDeltaOp> {
+        let mut merged: Vec<EngineDeltaOp> = Vec::new();
+        for op in ops {
+            match (merged.last_mut(), op) {
+                (Some(EngineDeltaOp::Literal(acc)), EngineDeltaOp::Literal(more)) => {
+                    acc.extend_from_slice(&more)
+                }
+                (_, op) => merged.push(op),
+            }
+        }
+        merged
+    }
+
+    #[test]
+    fn producer_max_literal_splits_runs_without_changing_them() {
+        let block_size = 256;
+        let dest: Vec<u8> = (0..16 * block_size)
+            .map(|i: usize| (i.wrapping_mul(2654435761) >> 8) as u8)
+            .collect();
+        let mut source: Vec<u8> = (0..20 * block_size)
+            .map(|i: usize| ((i.wrapping_mul(40503) ^ 0xA5) & 0xFF) as u8)
+            .collect();
+        source[9 * block_size..10 * block_size]
+            .copy_from_slice(&dest[4 * block_size..5 * block_size]);
+        let sigs = engine_sigs_from_dest(&dest, block_size);
+        let bulk = bulk_ops(&source, &dest, block_size);
+
+        let cap = 1000;
+        let mut producer = RollingDeltaPlanProducer::new(block_size, sigs).with_max_literal(cap);
+        let mut ops = Vec::new();
+        for chunk in source.chunks(333) {
+            producer.drive_chunk(chunk, &mut ops);
+        }
+        producer.finalize(&mut ops);
+
+        assert!(ops.iter().all(|op| match op {
+            EngineDeltaOp::Literal(data) => data.len() <= cap,
+            EngineDeltaOp::CopyBlock(_) => true,
+        }));
+        assert!(ops.len() > bulk.len());
+        assert_eq!(merge_literals(ops), bulk);
+    }
+
+    #[test]
+    fn signature_builder_matches_bulk_for_any_chunking() {
+        let block_size = 512;
+        let dest: Vec<u8> = (0..7 * block_size + 333)
+            .map(|i: usize| (i.wrapping_mul(2654435761) >> 16) as u8)
+            .collect();
+        let bulk = engine_sigs_from_dest(&dest, block_size);
+
+        for &chunk_size in &[1usize, 7, 511, 512, 513, 2048, 1 << 20] {
+            let mut builder = RollingSignatureBuilder::new(block_size);
+            let mut sigs = Vec::new();
+            for chunk in dest.chunks(chunk_size) {
+                builder.drive_chunk(chunk, &mut sigs);
+            }
+            builder.finalize(&mut sigs);
+            assert_eq!(sigs, bulk, "chunk_size={chunk_size}");
+        }
+
+        let mut empty = RollingSignatureBuilder::new(block_size);
+        let mut sigs = Vec::new();
+        empty.finalize(&mut sigs);
+        assert!(sigs.is_empty());
+    }
+
+    #[test]
+    fn signature_block_size_caps_the_block_count() {
+        let bridge = CurrentDeltaSyncBridge::new();
+        // Up to MAX_SIGNATURE_BLOCKS engine-sized blocks the engine decides.
+        for size in [0u64, 4096, 100 << 20, 1 << 30] {
+            assert_eq!(
+                signature_block_size(&bridge, size),
+                bridge.compute_block_size(size)
+            );
+        }
+        for size in [(1u64 << 30) + 1, 5_000_000_007] {
+            let block = signature_block_size(&bridge, size);
+            assert_eq!(block % 8, 0);
+            assert!(block > bridge.compute_block_size(size));
+            assert!(size.div_ceil(block as u64) <= MAX_SIGNATURE_BLOCKS);
+        }
+        // A 50 GB image hits rsync's ceiling: 381 470 blocks of 128 KiB.
+        assert_eq!(
+            signature_block_size(&bridge, 50_000_000_000),
+            MAX_SIGNATURE_BLOCK_LEN
+        );
+    }
 }
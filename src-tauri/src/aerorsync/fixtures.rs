@@ -350,3 +350,110 @@ impl RealRsyncBaselineByteTranscript {
         Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
     }
 }
+
+// ----------------------------------------------------------------------------
+// Stock-peer option captures (`run_real_rsync_option_capture.sh`).
+//
+// One directory per stock release, one subdirectory per scenario, both
+// ends of every session being that release:
+//
+//   capture/artifacts_real/frozen-peers/
+//     └── <peer>/                          3.2.7, 3.3, 3.4
+//         ├── summary.env
+//         ├── server_rsync_version.txt
+//         └── <scenario>/                  see REAL_RSYNC_OPTION_SCENARIOS
+//             ├── capture_in.bin           client -> server
+//             ├── capture_out.bin          server -> client
+//             ├── remote_command.txt
+//             ├── client.stdout.txt
+//             └── client.stderr.txt
+
+/// Root of the per-peer captures, relative to the cargo manifest
+/// directory.
+pub const REAL_RSYNC_PEER_CAPTURES_REL: &str = "src/aerorsync/capture/artifacts_real/frozen-peers";
+
+/// Stock releases the option harness is run against, oldest first.
+pub const REAL_RSYNC_PEERS: [&str; 3] = ["3.2.7", "3.3", "3.4"];
+
+/// Scenarios the option harness records for every peer. `baseline_*`
+/// carry no option on top of `-avz --stats --checksum`; the others add
+/// the option they are named after.
+pub const REAL_RSYNC_OPTION_SCENARIOS: [&str; 10] = [
+    "baseline_upload",
+    "baseline_download",
+    "delete",
+    "inplace",
+    "append",
+    "append_verify",
+    "partial_dir",
+    "sparse",
+    "mkpath",
+    "hard_links",
+];
+
+/// One frozen session of a stock peer, loaded at test time.
+#[derive(Debug, Clone)]
+pub struct RealRsyncPeerCapture {
+    pub peer: String,
+    pub scenario: String,
+    /// `SSH_ORIGINAL_COMMAND` as the server saw it.
+    pub remote_command: String,
+    pub client_to_server: Vec<u8>,
+    pub server_to_client: Vec<u8>,
+}
+
+impl RealRsyncPeerCapture {
+    /// Load `<peer>/<scenario>`. The captures are committed fixtures, so a
+    /// missing or empty file panics with the harness run that records it
+    /// instead of letting the replay tests pass vacuously.
+    pub fn load(peer: &str, scenario: &str) -> Self {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join(REAL_RSYNC_PEER_CAPTURES_REL)
+            .join(peer)
+            .join(scenario);
+        let read = |name: &str| {
+            let bytes = std::fs::read(root.join(name)).unwrap_or_default();
+            assert!(
+                !bytes.is_empty(),
+                "missing frozen capture {}: record it with `RSYNC_PEER={peer} \
+                 capture/run_real_rsync_option_capture.sh` (see capture/README.md)",
+                root.join(name).display()
+            );
+            bytes
+        };
+        let remote_command = String::from_utf8(read("remote_command.txt"))
+            .expect("remote_command.txt is UTF-8")
+            .trim()
+            .to_string();
+        Self {
+            peer: peer.to_string(),
+            scenario: scenario.to_string(),
+            remote_command,
+            client_to_server: read("capture_in.bin"),
+            server_to_client: read("capture_out.bin"),
+        }
+    }
+
+    /// Every scenario of every peer, in `REAL_RSYNC_PEERS` then
+    /// `REAL_RSYNC_OPTION_SCENARIOS` order.
+    pub fn load_all() -> Vec<Self> {
+        REAL_RSYNC_PEERS
+            .iter()
+            .flat_map(|peer| {
+                REAL_RSYNC_OPTION_SCENARIOS
+                    .iter()
+                    .map(move |scenario| Self::load(peer, scenario))
+            })
+            .collect()
+    }
+
+    /// Remote argv without the program name. The harness paths carry
+    /// no whitespace, so a plain split is exact.
+    pub fn remote_args(&self) -> Vec<String> {
+        self.remote_command
+            .split_whitespace()
+            .skip(1)
+            .map(str::to_string)
+            .collect()
+    }
+}
@@ -0,0 +1,451 @@
+//! In-process transport for local-to-local delta transfers: the
+//! client's `rsync --server` command line is served by a receiver task
+//! on the other end of a `tokio::io::duplex` pipe instead of a remote
+//! shell.
+//!
+//! Both ends are the regular `AerorsyncDriver`: the client runs the
+//! sender loop against the source, the task runs `serve_file_receiver`
+//! (or `serve_tree_receiver` for a `dir/` target) against the
+//! destination, exactly as `rsyncd` would. Signatures, matching and
+//! reconstruction therefore behave as over the wire; with `--inplace`
+//! the receiver steps over blocks that are already in place and only
+//! the changed ones hit the disk.
+//!
+//! The receiver's outcome is kept apart from the client's: a failure
+//! on the writing side usually reaches the sender only as a closed
+//! pipe, so callers join [`ReceiverTask`] to learn what happened.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+
+use async_trait::async_trait;
+use tokio::io::DuplexStream;
+use tokio::task::JoinHandle;
+
+use crate::aerorsync::daemon::{DaemonUnusedStream, IoRawStream};
+use crate::aerorsync::daemon_server::{AcceptedTransport, ServerRequest};
+use crate::aerorsync::delta_transport_impl::{LocalTarget, LocalTreeRoot};
+use crate::aerorsync::engine_adapter::CurrentDeltaSyncBridge;
+use crate::aerorsync::events::{AerorsyncEvent, EventSink};
+use crate::aerorsync::native_driver::AerorsyncDriver;
+use crate::aerorsync::negotiation::AlgorithmPreferences;
+use crate::aerorsync::transport::{
+    CancelHandle, RawRemoteShellTransport, RemoteCommandOutput, RemoteExecRequest,
+    RemoteShellTransport, TransportProbe,
+};
+use crate::aerorsync::types::{AerorsyncError, AerorsyncErrorKind, ProtocolVersion};
+use crate::aerorsync::CURRENT_PROTOCOL_VERSION;
+
+/// Bytes buffered in each direction of the pipe. Large enough that
+/// the sender keeps a literal run in flight while the receiver writes.
+const DUPLEX_BUFFER_BYTES: usize = 1024 * 1024;
+
+type ReceiverSlot = Arc<StdMutex<Option<JoinHandle<Result<(), AerorsyncError>>>>>;
+
+/// `RawRemoteShellTransport` whose "remote" is a receiver task in this
+/// process, writing to the local path named on the command line. One
+/// transport serves one session.
+pub struct InProcessTransport {
+    algorithms: AlgorithmPreferences,
+    receiver: ReceiverSlot,
+    cancel_flag: Arc<AtomicBool>,
+}
+
+impl InProcessTransport {
+    /// `algorithms` are the receiver's lists; pass the client's own so
+    /// both ends agree on the first choice.
+    pub fn new(algorithms: AlgorithmPreferences) -> Self {
+        Self {
+            algorithms,
+            receiver: Arc::new(StdMutex::new(None)),
+            cancel_flag: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Handle on the receiver task, valid after the transport itself
+    /// has been handed to a driver.
+    pub fn receiver(&self) -> ReceiverTask {
+        ReceiverTask {
+            slot: self.receiver.clone(),
+        }
+    }
+}
+
+/// Outcome of the receiving half of an [`InProcessTransport`] session.
+pub struct ReceiverTask {
+    slot: ReceiverSlot,
+}
+
+impl ReceiverTask {
+    /// Wait for the receiver to finish. `Ok` too when no session was
+    /// ever opened.
+    pub async fn join(self) -> Result<(), AerorsyncError> {
+        let handle = self.slot.lock().ok().and_then(|mut slot| slot.take());
+        match handle {
+            None => Ok(()),
+            Some(handle) => handle.await.unwrap_or_else(|e| {
+                Err(AerorsyncError::new(
+                    AerorsyncErrorKind::Internal,
+                    format!("local receiver task ended abnormally: {e}"),
+                ))
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl RemoteShellTransport for InProcessTransport {
+    type Stream = DaemonUnusedStream;
+
+    async fn probe(&self) -> Result<TransportProbe, AerorsyncError> {
+        Ok(TransportProbe {
+            remote_banner: format!(
+                "aerorsync in-process receiver, protocol {CURRENT_PROTOCOL_VERSION}"
+            ),
+            protocol: ProtocolVersion(CURRENT_PROTOCOL_VERSION),
+            supports_remote_shell: false,
+        })
+    }
+
+    async fn exec(
+        &self,
+        request: RemoteExecRequest,
+    ) -> Result<RemoteCommandOutput, AerorsyncError> {
+        Err(AerorsyncError::transport(format!(
+            "the in-process transport runs no commands (asked for {})",
+            request.program
+        )))
+    }
+
+    async fn open_stream(
+        &self,
+        _request: RemoteExecRequest,
+    ) -> Result<Self::Stream, AerorsyncError> {
+        Err(AerorsyncError::transport(
+            "InProcessTransport does not support the legacy RSNP framed stream",
+        ))
+    }
+
+    async fn cancel(&self) -> Result<(), AerorsyncError> {
+        self.cancel_flag.store(true, Ordering::SeqCst);
+        if let Ok(slot) = self.receiver.lock() {
+            if let Some(handle) = slot.as_ref() {
+                handle.abort();
+            }
+        }
+        Ok(())
+    }
+
+    fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle::new(self.cancel_flag.clone(), None)
+    }
+}
+
+#[async_trait]
+impl RawRemoteShellTransport for InProcessTransport {
+    type RawStream = IoRawStream<DuplexStream>;
+
+    /// Parse `request.args` as `rsync --server` would, open the
+    /// destination and start the receiver on the far end of a fresh
+    /// pipe. Problems found here (refused options, unreadable basis)
+    /// surface before any protocol byte.
+    async fn open_raw_stream(
+        &self,
+        request: RemoteExecRequest,
+    ) -> Result<Self::RawStream, AerorsyncError> {
+        if self.cancel_flag.load(Ordering::SeqCst) {
+            return Err(AerorsyncError::cancelled(
+                "InProcessTransport cancelled before the session",
+            ));
+        }
+        let mut request = ServerRequest::parse_args(&request.args)
+            .map_err(|e| AerorsyncError::new(AerorsyncErrorKind::PlannerRejected, e))?;
+        if let Some(refusal) = request.refusal.take() {
+            return Err(AerorsyncError::new(
+                AerorsyncErrorKind::PlannerRejected,
+                refusal,
+            ));
+        }
+        if request.sender {
+            return Err(AerorsyncError::new(
+                AerorsyncErrorKind::PlannerRejected,
+                "the in-process transport only receives: run the sender on the client",
+            ));
+        }
+        request.session.options.algorithms = AlgorithmPreferences {
+            compress_level: request.session.options.algorithms.compress_level,
+            ..self.algorithms.clone()
+        };
+
+        let receiver = if request.path.ends_with('/') {
+            let root = PathBuf::from(&request.path);
+            check_tree_root(&root, request.session.options.mkpath)?;
+            Receiver::Tree(Box::new(
+                LocalTreeRoot::new(&root).with_options(request.session.options.clone()),
+            ))
+        } else {
+            let target = LocalTarget::open(Path::new(&request.path), &request.session.options)
+                .await
+                .map_err(|e| AerorsyncError::transport(e.to_string()))?;
+            Receiver::File(Box::new(target))
+        };
+
+        let (client_end, server_end) = tokio::io::duplex(DUPLEX_BUFFER_BYTES);
+        let path = request.path.clone();
+        let task = tokio::spawn(async move {
+            let result = receive(server_end, request, receiver).await;
+            if let Err(e) = &result {
+                tracing::debug!("aerorsync local receiver {path}: {e}");
+            }
+            result
+        });
+        let mut slot = self
+            .receiver
+            .lock()
+            .map_err(|_| AerorsyncError::transport("InProcessTransport lock poisoned"))?;
+        if slot.is_some() {
+            task.abort();
+            return Err(AerorsyncError::transport(
+                "InProcessTransport serves a single session",
+            ));
+        }
+        *slot = Some(task);
+        Ok(IoRawStream::new(client_end, Some(CURRENT_PROTOCOL_VERSION)))
+    }
+}
+
+/// The destination, opened before the session starts.
+enum Receiver {
+    File(Box<LocalTarget>),
+    Tree(Box<LocalTreeRoot>),
+}
+
+/// A tree lands in `root`; like rsync, its parent must exist unless
+/// `--mkpath` is given.
+pub(crate) fn check_tree_root(root: &Path, mkpath: bool) -> Result<(), AerorsyncError> {
+    if mkpath || root.exists() {
+        return Ok(());
+    }
+    let parent_ok = root
+        .components()
+        .as_path()
+        .parent()
+        .is_some_and(|parent| parent.as_os_str().is_empty() || parent.is_dir());
+    if parent_ok {
+        Ok(())
+    } else {
+        Err(AerorsyncError::transport(format!(
+            "{}: parent directory missing (use --mkpath)",
+            root.display()
+        )))
+    }
+}
+
+async fn receive(
+    io: DuplexStream,
+    request: ServerRequest,
+    receiver: Receiver,
+) -> Result<(), AerorsyncError> {
+    let mut driver = AerorsyncDriver::new(
+        AcceptedTransport::new(io, CURRENT_PROTOCOL_VERSION),
+        CancelHandle::inert(),
+    );
+    let adapter = CurrentDeltaSyncBridge::new();
+    let mut sink = ReceiverLogSink;
+    match receiver {
+        Receiver::File(mut target) => {
+            driver.set_partial_basis(target.partial_basis);
+            let served = driver
+                .serve_file_receiver(
+                    &request.session,
+                    &mut *target.baseline,
+                    &mut target.writer,
+                    &adapter,
+                    &mut sink,
+                )
+                .await;
+            match served {
+                Ok(entry) => {
+                    target.commit(Some(&entry)).await.map_err(|e| {
+                        AerorsyncError::new(AerorsyncErrorKind::Internal, e.to_string())
+                    })?;
+                }
+                Err(e) => {
+                    target.keep_partial().await;
+                    return Err(e);
+                }
+            }
+        }
+        Receiver::Tree(mut root) => {
+            let report = driver
+                .serve_tree_receiver(&request.session, &mut *root, &adapter, &mut sink)
+                .await?;
+            tracing::debug!(
+                "aerorsync local receiver {}: {}/{} files received",
+                request.path,
+                report.files_transferred,
+                report.files_total
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Receiver-side events only go to the log: the client's bridge
+/// reports the transfer.
+struct ReceiverLogSink;
+
+impl EventSink for ReceiverLogSink {
+    fn on_info(&mut self, event: AerorsyncEvent) {
+        tracing::trace!("aerorsync local receiver: {:?}", event);
+    }
+
+    fn on_warning(&mut self, event: AerorsyncEvent) {
+        tracing::warn!("aerorsync local receiver: {:?}", event);
+    }
+
+    fn on_error(&mut self, event: AerorsyncEvent) {
+        tracing::debug!("aerorsync local receiver: {:?}", event);
+    }
+
+    fn on_terminal(&mut self, event: AerorsyncEvent) {
+        tracing::debug!("aerorsync local receiver: {:?}", event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aerorsync::delta_transport_impl::AerorsyncLocalDeltaTransport;
+    use crate::aerorsync::remote_command::TransferOptions;
+    use crate::delta_transport::DeltaTransport;
+
+    /// Incompressible, so a full send could not hide behind zstd.
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn inplace_refresh_sends_only_changed_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("disk.img");
+        let copy = dir.path().join("usb/disk.img");
+        let mut image = noise(4 * 1024 * 1024, 7);
+        std::fs::write(&source, &image).unwrap();
+
+        let transport =
+            AerorsyncLocalDeltaTransport::new(0).with_transfer_options(TransferOptions {
+                mkpath: true,
+                ..TransferOptions::default()
+            });
+        let first = transport
+            .upload(&source, copy.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&copy).unwrap(), image);
+        assert!(first.bytes_sent >= image.len() as u64);
+
+        image[100_000] ^= 0xFF;
+        image[3_000_000..3_000_016].copy_from_slice(b"guest wrote here");
+        std::fs::write(&source, &image).unwrap();
+        #[cfg(unix)]
+        let inode = {
+            use std::os::unix::fs::MetadataExt;
+            std::fs::metadata(&copy).unwrap().ino()
+        };
+        let transport =
+            AerorsyncLocalDeltaTransport::new(0).with_transfer_options(TransferOptions {
+                inplace: true,
+                ..TransferOptions::default()
+            });
+        let refresh = transport
+            .download(source.to_str().unwrap(), &copy)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&copy).unwrap(), image);
+        assert!(
+            refresh.bytes_sent < image.len() as u64 / 20,
+            "sent {} bytes to refresh two blocks",
+            refresh.bytes_sent
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(std::fs::metadata(&copy).unwrap().ino(), inode);
+        }
+    }
+
+    #[tokio::test]
+    async fn tree_is_mirrored_between_two_directories() {
+        let source = tempfile::tempdir().unwrap();
+        let mirror = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(source.path().join("vm")).unwrap();
+        std::fs::write(source.path().join("notes.txt"), b"local to local").unwrap();
+        std::fs::write(source.path().join("vm/base.qcow2"), noise(300_000, 3)).unwrap();
+
+        let transport = AerorsyncLocalDeltaTransport::new(0);
+        let target = mirror.path().join("copy");
+        transport
+            .upload_tree(source.path(), target.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read(target.join("notes.txt")).unwrap(),
+            b"local to local"
+        );
+        assert_eq!(
+            std::fs::read(target.join("vm/base.qcow2")).unwrap(),
+            noise(300_000, 3)
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_destination_parent_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.bin");
+        std::fs::write(&source, noise(10_000, 1)).unwrap();
+        let transport = AerorsyncLocalDeltaTransport::new(0);
+
+        let missing = dir.path().join("nowhere/deeper/a.bin");
+        let err = transport
+            .upload(&source, missing.to_str().unwrap())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("nowhere"), "{err}");
+        let err = transport
+            .upload_tree(
+                dir.path(),
+                dir.path().join("nowhere/tree").to_str().unwrap(),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("--mkpath"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn receiver_refuses_sender_sessions() {
+        let transport = InProcessTransport::new(AlgorithmPreferences::default());
+        let err = transport
+            .open_raw_stream(RemoteExecRequest {
+                program: "rsync".into(),
+                args: ["--server", "--sender", "-re.iLsfxCIvu", ".", "/tmp/x/"]
+                    .map(String::from)
+                    .to_vec(),
+                environment: Vec::new(),
+            })
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind, AerorsyncErrorKind::PlannerRejected);
+        assert!(transport.receiver().join().await.is_ok());
+    }
+}
@@ -21,6 +21,12 @@
 // tests or live-test lanes, so dead-code warnings here would be noise.
 #![allow(dead_code)]
 
+pub mod attrs;
+pub mod batch;
+pub mod checksum;
+pub mod compression;
+pub mod daemon;
+pub mod daemon_server;
 pub mod delta_transport_impl;
 pub mod driver;
 pub mod engine_adapter;
@@ -29,8 +35,10 @@ pub mod fallback_policy;
 pub mod fixtures;
 pub mod frame_io;
 pub mod live_tests;
+pub mod local_transport;
 pub mod mock;
 pub mod native_driver;
+pub mod negotiation;
 pub mod planner;
 pub mod protocol;
 pub mod real_wire;
@@ -44,6 +52,7 @@ pub mod ssh_transport;
 pub mod streaming_writer;
 pub mod tests;
 pub mod transport;
+pub mod tree;
 pub mod types;
 
 pub const CURRENT_PROTOCOL_VERSION: u32 = 31;
@@ -40,32 +40,54 @@
 //! `committed()` reports `false`, letting the A4 adapter decide to fall
 //! back to the classic-SFTP path.
 //!
-//! # csum_len in A2.1
+//! # Negotiated algorithms
 //!
-//! Hardcoded to 16 (xxh128 / md5 / md4). A2.2 will derive it dynamically
-//! from `negotiated_checksum_algos`. Accepted risk, tracked in the
-//! checkpoint doc.
-
+//! The preamble settles one file checksum and one literal compressor
+//! (`negotiation.rs`). `checksum_algo` sizes the `-c` file-list checksums
+//! and the trailer after every delta stream; `compression_algo` picks the
+//! literal codec and, with `none`, the plain token framing. Until a
+//! preamble runs they hold the frozen-oracle profile, xxh128 and zstd.
+
+use crate::aerorsync::batch::{BatchHeader, BatchRecorder, BatchStreamFlags};
+use crate::aerorsync::checksum::{ChecksumAlgo, FileHasher};
+use crate::aerorsync::compression::{CompressionAlgo, LiteralDecoder, LiteralEncoder};
 use crate::aerorsync::engine_adapter::{
-    apply_delta_streaming, BaselineSource, DeltaEngineAdapter, DeltaPlanProducer, EngineDeltaOp,
-    EngineSignatureBlock, RollingDeltaPlanProducer,
+    apply_delta_streaming, signature_block_size, BaselineSource, DeltaEngineAdapter,
+    DeltaPlanProducer, EngineDeltaOp, EngineDeltaPlan, EngineSignatureBlock, ReconstructionWriter,
+    RollingDeltaPlanProducer,
 };
 use crate::aerorsync::events::EventSink;
 use crate::aerorsync::real_wire::{
-    compress_zstd_literal_stream, decode_delta_stream, decode_file_list_entry, decode_item_flags,
-    decode_ndx, decode_server_preamble, decode_sum_block, decode_sum_head, decode_summary_frame,
-    decompress_zstd_literal_stream_boundaries, encode_client_preamble, encode_delta_stream,
+    decode_delta_op, decode_delta_stream_with, decode_file_checksum, decode_file_list_entry,
+    decode_file_list_entry_after, decode_item_flags, decode_ndx, decode_server_preamble,
+    decode_sum_block, decode_sum_head, decode_summary_frame, decode_varint, decode_vstring,
+    encode_client_preamble, encode_delta_end, encode_delta_op, encode_delta_stream_with,
     encode_file_list_entry, encode_file_list_terminator, encode_item_flags, encode_ndx,
-    encode_sum_block, encode_sum_head, encode_summary_frame, ClientPreamble, DeltaOp,
-    DeltaStreamReport, FileListDecodeOptions, FileListDecodeOutcome, FileListEntry, MuxHeader,
-    MuxPoll, MuxStreamReader, MuxTag, NdxState, RealWireError, SumBlock, SumHead, SummaryFrame,
-    MAX_DELTA_LITERAL_LEN, NDX_DONE, NDX_FLIST_EOF,
+    encode_protocol_version, encode_sum_block, encode_sum_head, encode_summary_frame,
+    encode_varint, encode_vstring, ClientPreamble, DeltaOp, DeltaOpOutcome, DeltaStreamReport,
+    DeltaStreamState, FileListDecodeOptions, FileListDecodeOutcome, FileListEntry, FlistMetaTables,
+    FlistScope, HardlinkRef, MuxHeader, MuxPoll, MuxStreamReader, MuxTag, NdxState, RealWireError,
+    SumBlock, SumHead, SummaryFrame, TokenFormat, XattrValue, CF_INC_RECURSE,
+    CF_VARINT_FLIST_FLAGS, FNAMECMP_PARTIAL_DIR, ITEM_BASIS_TYPE_FOLLOWS, ITEM_REPORT_CHANGE,
+    ITEM_REPORT_XATTR, ITEM_TRANSFER, ITEM_XNAME_FOLLOWS, NDX_DONE, NDX_FLIST_EOF,
+    NDX_FLIST_OFFSET, PROTOCOL_VERSION_LEN,
+};
+use crate::aerorsync::remote_command::{
+    AppendMode, RemoteCommandFlavor, RemoteCommandSpec, TransferOptions,
+};
+use crate::aerorsync::transport::{
+    CancelHandle, RawByteStream, RawRemoteShellTransport, RemoteExecRequest,
+};
+use crate::aerorsync::tree::{
+    is_dir_mode, is_regular_mode, is_symlink_mode, TreeFileList, TreeSink, TreeSource,
+    TreeTransferReport,
 };
-use crate::aerorsync::remote_command::{RemoteCommandFlavor, RemoteCommandSpec};
-use crate::aerorsync::transport::{CancelHandle, RawByteStream, RawRemoteShellTransport};
 use crate::aerorsync::types::{AerorsyncError, AerorsyncErrorKind, SessionRole, SessionStats};
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
-use xxhash_rust::xxh3::{xxh3_128, Xxh3Default};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use xxhash_rust::xxh3::xxh3_128;
 
 /// Compute the 16-byte file-level strong checksum rsync verifies at the
 /// end of the delta stream when `xxh128` is the negotiated algo.
@@ -100,23 +122,36 @@ const RAW_READ_CHUNK: usize = 8192;
 /// default and keeps the per-chunk allocation tax (one `vec![0u8; N]`
 /// per `read()` call) negligible on multi-GiB sources.
 ///
-/// The producer drains its sliding window after each chunk, so
-/// resident memory stays bounded by `block_size + literal_run_length`
-/// regardless of `STREAMING_READ_CHUNK_BYTES`. The constant only
-/// trades I/O syscalls vs. allocation; bigger is fine, smaller is fine,
-/// 4 MiB is the documented default.
+/// The producer drains its sliding window after each chunk and cuts
+/// literal runs at this length too, so resident memory stays bounded by
+/// a small multiple of it. The same figure paces the basis reads of the
+/// streaming signature phase and the literal batches of the streaming
+/// receiver.
 const STREAMING_READ_CHUNK_BYTES: usize = 4 * 1024 * 1024;
 
+/// Largest `MSG_DATA` payload: the 24-bit length of the multiplex header.
+/// Streamed phases cut their output into frames of exactly this size,
+/// so the bulk and streaming senders frame the same bytes identically.
+const MAX_DATA_FRAME_LEN: usize = 0x00FF_FFFF;
+
+/// How much of its output the streaming sender keeps in
+/// `emitted_delta_ops` (op headers plus literal payloads). The bulk
+/// sender records everything; a multi-GB streaming send keeps only the
+/// head of the stream.
+const EMITTED_OPS_RECORD_BYTES: usize = 1024 * 1024;
+
+/// Ops the streaming receiver collects before handing them to
+/// `apply_delta_streaming`, besides the `STREAMING_READ_CHUNK_BYTES`
+/// cap on their literal bytes.
+const STREAMING_APPLY_BATCH_OPS: usize = 4096;
+
 // A2.2 signature phase constants.
 //
-// `ITEM_TRANSFER` is the per-file flag (u16) the server-generator sets
-// when it wants the sender to push actual delta bytes: i.e. every file
-// that we are about to exchange signatures for. `ITEM_REPORT_CHANGE` is
-// the common companion bit telling the client "log this as changed".
-// Neither mapping belongs in a shared module yet; when a second
-// consumer emerges in S8j we will promote to `real_wire.rs`.
-const ITEM_TRANSFER: u16 = 0x8000;
-const ITEM_REPORT_CHANGE: u16 = 0x0002;
+// `ITEM_TRANSFER` (from `real_wire.rs`) is the per-file flag the
+// server-generator sets when it wants the sender to push actual delta
+// bytes: i.e. every file that we are about to exchange signatures for.
+// `ITEM_REPORT_CHANGE` is the common companion bit telling the client
+// "log this as changed".
 /// iflags emitted by the driver in the download path, replicating the
 /// frozen oracle's client→server first-file signature shape.
 const A2_2_DOWNLOAD_IFLAGS: u16 = ITEM_TRANSFER | ITEM_REPORT_CHANGE;
@@ -141,6 +176,63 @@ const A2_3_FILE_CHECKSUM_LEN: usize = 16;
 /// loudly if rsync ever shifts the marker count.
 const PRE_SUMMARY_NDX_DONE_COUNT_DOWNLOAD: usize = 3;
 
+/// Tree sessions: keep sending extra file lists while fewer than this
+/// many entries sit in lists the generator has not freed yet. Same
+/// value and meaning as `flist.c::MIN_FILECNT_LOOKAHEAD`.
+const TREE_FLIST_LOOKAHEAD_ENTRIES: usize = 1000;
+
+/// Tree downloads: cap on generator request bytes (ndx + iflags +
+/// sum_head + blocks) written but not yet answered by the sender. The
+/// remote sender only drains requests between file transmissions, so an
+/// unbounded generator could fill both pipes and stall the session.
+const TREE_REQUEST_BUDGET_BYTES: usize = 256 * 1024;
+
+/// How long and how much `serve_refusal` reads from a refused client
+/// before closing.
+const REFUSAL_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+const REFUSAL_DRAIN_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+/// File-list fields a session carries. Our own command line always asks
+/// for `-c -o -g -l`; a stock client talking to the daemon decides with
+/// its short options, and both ends must decode with the same set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionFlags {
+    /// `-c`: regular files carry their checksum. Without it the quick
+    /// check compares size and mtime.
+    pub always_checksum: bool,
+    /// `-o`: entries carry the owner.
+    pub preserve_uid: bool,
+    /// `-g`: entries carry the group.
+    pub preserve_gid: bool,
+    /// `-l`: symlinks are sent as links.
+    pub preserve_links: bool,
+}
+
+impl Default for SessionFlags {
+    fn default() -> Self {
+        Self {
+            always_checksum: true,
+            preserve_uid: true,
+            preserve_gid: true,
+            preserve_links: true,
+        }
+    }
+}
+
+/// What the server half of a daemon session settled from the client's
+/// argument list, before the binary preamble.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerSession {
+    pub options: TransferOptions,
+    pub flags: SessionFlags,
+    /// `compat_flags` announced to the client, built from its `-e.`
+    /// letters.
+    pub compat_flags: i32,
+    /// `-z`: the compression name list is negotiated too.
+    pub compress: bool,
+    pub checksum_seed: u32,
+}
+
 /// State machine phase for the native driver session.
 ///
 /// Pub because the A4 adapter (`AerorsyncDeltaTransport`) may want to
@@ -209,6 +301,10 @@ pub struct AerorsyncDriver<T: RawRemoteShellTransport> {
     checksum_seed: u32,
     negotiated_checksum_algos: String,
     negotiated_compression_algos: String,
+    /// Checksum both peers settled on.
+    checksum_algo: ChecksumAlgo,
+    /// Literal compressor both peers settled on; `None` without `-z`.
+    compression_algo: CompressionAlgo,
 
     phase: AerorsyncSessionPhase,
     committed: bool,
@@ -241,7 +337,8 @@ pub struct AerorsyncDriver<T: RawRemoteShellTransport> {
     sent_sum_head: Option<SumHead>,
     /// Download path: signature blocks we emitted on the wire. Kept for
     /// test visibility; carries the truncated-to-`s2length` strong halves
-    /// that actually went on the wire.
+    /// that actually went on the wire. Left empty by the streaming
+    /// signature phase, which never holds them all.
     sent_signatures: Vec<SumBlock>,
     /// Last iflags value observed in upload (received) or emitted in
     /// download (sent).
@@ -273,6 +370,8 @@ pub struct AerorsyncDriver<T: RawRemoteShellTransport> {
     received_file_checksum: Option<Vec<u8>>,
     /// Upload path: delta ops emitted on the wire, in emission order.
     /// Kept for test visibility: production callers should ignore this.
+    /// The streaming sender keeps only the first
+    /// `EMITTED_OPS_RECORD_BYTES` worth of them.
     emitted_delta_ops: Vec<DeltaOp>,
     /// Upload path: total MSG_DATA payload bytes written. The numerator
     /// of the progress indicator; A4 exposes it to the UI.
@@ -311,6 +410,29 @@ pub struct AerorsyncDriver<T: RawRemoteShellTransport> {
     /// `#[cfg(all(test, feature = "aerorsync"))]` live lane. Do not wire
     /// it into any product-facing code path.
     remote_command_flavor: RemoteCommandFlavor,
+
+    // Transfer options (`--inplace`, `--append`, `--delete`, ...).
+    /// Options of the current session, copied from the command spec by
+    /// `open_raw_stream_internal` so the command line and the local
+    /// behaviour can never disagree.
+    transfer_options: TransferOptions,
+    /// Download path: the caller built `destination_data` from the
+    /// `--partial-dir` copy, announced as `FNAMECMP_PARTIAL_DIR`.
+    partial_basis: bool,
+    /// Download path: `--append` found the local file at least as long
+    /// as the remote one, so no request was sent and nothing was
+    /// received (`generator.c::recv_generator` skip).
+    transfer_skipped: bool,
+    /// File-list fields of the session; all on unless a daemon client
+    /// asked otherwise.
+    session_flags: SessionFlags,
+    /// `--write-batch`: open from the end of the preamble to the end of
+    /// the session.
+    batch_recorder: Option<BatchRecorder>,
+    /// `--read-batch`: the "sender" is a recorded stream that answers
+    /// no request, so the generator runs unthrottled and recorded files
+    /// it did not ask for are dropped instead of refused.
+    reading_batch: bool,
 }
 
 impl<T: RawRemoteShellTransport> AerorsyncDriver<T> {
@@ -323,6 +445,8 @@ impl<T: RawRemoteShellTransport> AerorsyncDriver<T> {
             checksum_seed: 0,
             negotiated_checksum_algos: String::new(),
             negotiated_compression_algos: String::new(),
+            checksum_algo: ChecksumAlgo::Xxh128,
+            compression_algo: CompressionAlgo::Zstd,
             phase: AerorsyncSessionPhase::PreConnect,
             committed: false,
             stream: None,
@@ -347,6 +471,12 @@ impl<T: RawRemoteShellTransport> AerorsyncDriver<T> {
             received_raw_bytes: 0,
             summary_seed: Vec::new(),
             remote_command_flavor: RemoteCommandFlavor::WrapperParity,
+            transfer_options: TransferOptions::default(),
+            partial_basis: false,
+            transfer_skipped: false,
+            session_flags: SessionFlags::default(),
+            batch_recorder: None,
+            reading_batch: false,
         }
     }
 
@@ -372,6 +502,12 @@ impl<T: RawRemoteShellTransport> AerorsyncDriver<T> {
     pub fn negotiated_compression_algos(&self) -> &str {
         &self.negotiated_compression_algos
     }
+    pub fn checksum_algo(&self) -> ChecksumAlgo {
+        self.checksum_algo
+    }
+    pub fn compression_algo(&self) -> CompressionAlgo {
+        self.compression_algo
+    }
     pub fn committed(&self) -> bool {
         self.committed
     }
@@ -436,6 +572,38 @@ impl<T: RawRemoteShellTransport> AerorsyncDriver<T> {
         &self.session_stats
     }
 
+    pub fn transfer_options(&self) -> &TransferOptions {
+        &self.transfer_options
+    }
+
+    pub fn session_flags(&self) -> SessionFlags {
+        self.session_flags
+    }
+
+    /// Client sessions: the file-list fields to expect, for a peer that
+    /// was not started with our own `-c -o -g -l` (a batch replay).
+    pub fn set_session_flags(&mut self, flags: SessionFlags) {
+        self.session_flags = flags;
+    }
+
+    /// Mark the transport as a recorded sender stream (`--read-batch`).
+    pub fn set_reading_batch(&mut self, reading_batch: bool) {
+        self.reading_batch = reading_batch;
+    }
+
+    /// Download path: mark the upcoming `destination_data` as the
+    /// `--partial-dir` copy of the file rather than the file itself.
+    pub fn set_partial_basis(&mut self, partial_basis: bool) {
+        self.partial_basis = partial_basis;
+    }
+
+    /// Download path: `true` when `--append` skipped the file because the
+    /// local copy is already at least as long as the remote one. The
+    /// delta phase did not run and the caller's writer received nothing.
+    pub fn transfer_skipped(&self) -> bool {
+        self.transfer_skipped
+    }
+
     // --- public drive entry points ---------------------------------------
 
     pub async fn drive_upload(
@@ -603,21 +771,12 @@ impl<T: RawRemoteShellTransport> AerorsyncDriver<T> {
     /// can `finalize` it (commit the temp file via rename) once the
     /// driver returns.
     ///
-    /// `destination_data` is still passed because the signature phase
-    /// runs `adapter.build_signatures(destination_data, block_size)`
-    /// in bulk; making *that* phase streaming requires a separate
-    /// `build_signatures_streaming` adapter API that is out of scope for
-    /// W2.5. The W2.5 caller gives the same `destination_data` slice
-    /// (read once into RAM with `tokio::fs::read`) and a `FileBaseline`
-    /// over the same path; the cap removal in W2.5 targets the
-    /// reconstruction side, where the asymmetry is `O(reconstructed)`
-    /// vs `O(baseline + reconstructed)` for >1 GiB downloads with mostly
-    /// matching baselines.
-    ///
-    /// `baseline` is the random-access source consulted by
-    /// `apply_delta_streaming` for `EngineDeltaOp::CopyBlock(idx)`. It
-    /// must be the byte-for-byte identical content as `destination_data`
-    /// (caller invariant; the streaming + bulk views of the same file).
+    /// `baseline` is the local copy, never loaded whole: the signature
+    /// phase reads it in `STREAMING_READ_CHUNK_BYTES` strides through
+    /// `adapter.build_signatures_streaming`, and `apply_delta_streaming`
+    /// reads back the blocks the delta references. The delta itself is
+    /// decoded and applied a batch at a time as its frames arrive, so
+    /// resident memory does not grow with the file size.
     ///
     /// `writer` is borrowed by `&mut` for the duration of the call -
     /// the caller retains ownership and is responsible for
@@ -626,21 +785,35 @@ impl<T: RawRemoteShellTransport> AerorsyncDriver<T> {
     pub async fn drive_download_through_delta_streaming(
         &mut self,
         command_spec: RemoteCommandSpec,
-        destination_data: &[u8],
         baseline: &mut dyn BaselineSource,
         writer: &mut (dyn AsyncWrite + Send + Unpin),
         adapter: &dyn DeltaEngineAdapter,
         bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        self.drive_download_reconstructing(
+            command_spec,
+            baseline,
+            &mut PlainWriter(writer),
+            adapter,
+            bridge,
+        )
+        .await
+    }
+
+    /// [`drive_download_through_delta_streaming`] into a
+    /// [`ReconstructionWriter`]: under `--inplace` a writer updating the
+    /// basis itself steps over the blocks the delta leaves at their own
+    /// offset, so only changed blocks are rewritten.
+    pub async fn drive_download_reconstructing(
+        &mut self,
+        command_spec: RemoteCommandSpec,
+        baseline: &mut dyn BaselineSource,
+        writer: &mut dyn ReconstructionWriter,
+        adapter: &dyn DeltaEngineAdapter,
+        bridge: &mut dyn EventSink,
     ) -> Result<(), AerorsyncError> {
         match self
-            .drive_download_inner_streaming(
-                command_spec,
-                destination_data,
-                baseline,
-                writer,
-                adapter,
-                bridge,
-            )
+            .drive_download_inner_streaming(command_spec, baseline, writer, adapter, bridge)
             .await
         {
             Ok(()) => Ok(()),
@@ -662,13 +835,8 @@ impl<T: RawRemoteShellTransport> AerorsyncDriver<T> {
         self.session_role = Some(SessionRole::Sender);
         self.remote_command_flavor = command_spec.flavor;
         self.open_raw_stream_internal(&command_spec).await?;
-        // B.2: rsync wire protocol uses SPACE-separated algo lists in
-        // priority-descending order. Using commas causes stock rsync
-        // 3.4.1 to parse the whole list as a single unknown algorithm
-        // and close the stream. Values cribbed from the frozen capture
-        // `capture/artifacts_real/frozen/upload/capture_in.bin` shape.
-        self.perform_preamble_exchange(31, "xxh128 xxh3 xxh64 md5 md4", "zstd lz4 zlibx zlib")
-            .await?;
+        self.perform_preamble_exchange(31).await?;
+        let source_entry = self.with_session_checksum(source_entry, Some(source_data));
         self.send_file_list_single_file(&source_entry).await?;
         self.receive_signature_phase_single_file(bridge).await?;
         self.send_delta_phase_single_file(source_data, adapter)
@@ -696,8 +864,8 @@ impl<T: RawRemoteShellTransport> AerorsyncDriver<T> {
         self.session_role = Some(SessionRole::Sender);
         self.remote_command_flavor = command_spec.flavor;
         self.open_raw_stream_internal(&command_spec).await?;
-        self.perform_preamble_exchange(31, "xxh128 xxh3 xxh64 md5 md4", "zstd lz4 zlibx zlib")
-            .await?;
+        self.perform_preamble_exchange(31).await?;
+        let source_entry = self.with_session_checksum(source_entry, None);
         self.send_file_list_single_file(&source_entry).await?;
         self.receive_signature_phase_single_file(bridge).await?;
         self.send_delta_phase_streaming(source_reader, source_len, adapter)
@@ -720,2140 +888,4591 @@ impl<T: RawRemoteShellTransport> AerorsyncDriver<T> {
         // 3.4.1 to parse the whole list as a single unknown algorithm
         // and close the stream. Values cribbed from the frozen capture
         // `capture/artifacts_real/frozen/upload/capture_in.bin` shape.
-        self.perform_preamble_exchange(31, "xxh128 xxh3 xxh64 md5 md4", "zstd lz4 zlibx zlib")
-            .await?;
+        self.perform_preamble_exchange(31).await?;
         self.receive_file_list_single_file(bridge).await?;
         self.send_signature_phase_single_file(destination_data, adapter)
             .await?;
+        if self.transfer_skipped {
+            self.reconstructed = Some(destination_data.to_vec());
+            return Ok(());
+        }
         self.receive_delta_phase_single_file(destination_data, adapter, bridge)
             .await?;
         Ok(())
     }
 
-    /// P3-T01 W2.4: streaming-sink twin of [`drive_download_inner`]. The
-    /// pre-delta phases run unchanged against the bulk `destination_data`
-    /// slice; the final phase swaps `receive_delta_phase_single_file` for
-    /// `receive_delta_phase_streaming` which writes reconstructed bytes
-    /// to the configured `Streaming(writer)` target via
+    /// P3-T01 W2.4: streaming-sink twin of [`drive_download_inner`].
+    /// Signatures come from `send_signature_phase_streaming` over the
+    /// baseline and the delta goes through `receive_delta_phase_streaming`,
+    /// which writes reconstructed bytes to `writer` via
     /// `apply_delta_streaming(baseline, ops, block_size, writer)`.
     async fn drive_download_inner_streaming(
         &mut self,
         command_spec: RemoteCommandSpec,
-        destination_data: &[u8],
         baseline: &mut dyn BaselineSource,
-        writer: &mut (dyn AsyncWrite + Send + Unpin),
+        writer: &mut dyn ReconstructionWriter,
         adapter: &dyn DeltaEngineAdapter,
         bridge: &mut dyn EventSink,
     ) -> Result<(), AerorsyncError> {
         self.session_role = Some(SessionRole::Receiver);
         self.remote_command_flavor = command_spec.flavor;
         self.open_raw_stream_internal(&command_spec).await?;
-        self.perform_preamble_exchange(31, "xxh128 xxh3 xxh64 md5 md4", "zstd lz4 zlibx zlib")
-            .await?;
+        self.perform_preamble_exchange(31).await?;
         self.receive_file_list_single_file(bridge).await?;
-        self.send_signature_phase_single_file(destination_data, adapter)
+        self.send_signature_phase_streaming(baseline, adapter)
             .await?;
-        self.receive_delta_phase_streaming(baseline, writer, adapter, bridge)
+        if self.transfer_skipped {
+            return Ok(());
+        }
+        self.receive_delta_phase_streaming(baseline, writer, None, bridge)
             .await?;
         Ok(())
     }
 
-    // --- private helpers -------------------------------------------------
-
-    async fn open_raw_stream_internal(
+    // --- recursive tree sessions ------------------------------------------
+    //
+    // Multi-file siblings of the single-file entry points. They require
+    // incremental recursion (`CF_INC_RECURSE`, negotiated by every
+    // rsync >= 3.0 for the `-logDtprcze.iLsfxCIvu` command line) and run
+    // the whole session, finish included: the per-file exchange and the
+    // NDX_DONE phase loop share one inbound stream, so there is no fixed
+    // point where a separate `finish_session` call could take over.
+
+    /// Upload `tree` into the remote directory named by `command_spec`
+    /// (a `RemoteCommandSpec::upload` whose target ends in `/`). File
+    /// bytes come from `source` one file at a time, when the remote
+    /// generator asks for them; unchanged files are never read.
+    ///
+    /// `committed()` flips on the first delta written, exactly as in the
+    /// single-file upload.
+    pub async fn drive_upload_tree(
         &mut self,
-        command_spec: &RemoteCommandSpec,
-    ) -> Result<(), AerorsyncError> {
-        self.check_cancel("open_raw_stream")?;
-        let stream = self
-            .transport
-            .open_raw_stream(command_spec.to_exec_request())
-            .await?;
-        self.stream = Some(stream);
-        self.phase = AerorsyncSessionPhase::RawStreamOpen;
-        Ok(())
+        command_spec: RemoteCommandSpec,
+        tree: &TreeFileList,
+        source: &mut dyn TreeSource,
+        adapter: &dyn DeltaEngineAdapter,
+        bridge: &mut dyn EventSink,
+    ) -> Result<TreeTransferReport, AerorsyncError> {
+        match self
+            .drive_upload_tree_inner(command_spec, tree, source, adapter, bridge)
+            .await
+        {
+            Ok(report) => Ok(report),
+            Err(e) => {
+                self.phase = AerorsyncSessionPhase::Failed;
+                Err(e)
+            }
+        }
     }
 
-    /// B.2 fix: rsync wire protocol places the CLIENT first: the client
-    /// writes its preamble onto the raw stream and only afterwards reads
-    /// the server's response. The captured frozen transcripts confirm it:
-    /// `capture/artifacts_real/frozen/upload/capture_in.bin` (bytes the
-    /// client sends) starts with `1f 00 00 00` (protocol 31 LE u32) + the
-    /// checksum algo list; only after that the server replies with
-    /// `20 00 00 00 81 ff 23 ...` in `capture_out.bin`.
+    /// Download the remote directory named by `command_spec` (a
+    /// `RemoteCommandSpec::download` whose target ends in `/`) into
+    /// `sink`. Files whose local copy already matches the size and
+    /// checksum carried by the file list are skipped; the rest are
+    /// requested with signatures of the local copy as delta baseline.
     ///
-    /// The previous implementation read first and wrote after, which
-    /// deadlocked against stock `rsync --server` because both peers were
-    /// stuck in read. It happened to work against the dev helper
-    /// `aerorsync_serve` only because that path is never exercised via
-    /// the `NativeRsyncDriver` (which speaks the real wire); live lanes
-    /// against the dev helper go through the `SessionDriver` RSNP
-    /// framing instead.
-    async fn perform_preamble_exchange(
+    /// `committed()` stays `false`, as in the single-file download: the
+    /// caller tracks what `sink` already installed.
+    pub async fn drive_download_tree(
         &mut self,
-        protocol_version: u32,
-        checksum_algos: &str,
-        compression_algos: &str,
-    ) -> Result<(), AerorsyncError> {
-        // 1. Write our client preamble first.
-        let outbound = encode_client_preamble(&ClientPreamble {
-            protocol_version,
-            checksum_algos: checksum_algos.to_string(),
-            compression_algos: compression_algos.to_string(),
-            consumed: 0,
-        });
+        command_spec: RemoteCommandSpec,
+        sink: &mut dyn TreeSink,
+        adapter: &dyn DeltaEngineAdapter,
+        bridge: &mut dyn EventSink,
+    ) -> Result<TreeTransferReport, AerorsyncError> {
+        match self
+            .drive_download_tree_inner(command_spec, sink, adapter, bridge)
+            .await
         {
-            self.check_cancel("perform_preamble_exchange send")?;
-            let stream = self.stream.as_mut().ok_or_else(|| {
-                AerorsyncError::transport("perform_preamble_exchange: stream not open (pre-write)")
-            })?;
-            stream.write_bytes(&outbound).await?;
+            Ok(report) => Ok(report),
+            Err(e) => {
+                self.phase = AerorsyncSessionPhase::Failed;
+                Err(e)
+            }
         }
-        // 2. Drain the server preamble from the stream. Any bytes read
-        //    past the server preamble's `consumed` cursor are fed into
-        //    `mux_reader` so the subsequent file list decode sees them.
-        let mut scratch = Vec::with_capacity(128);
-        loop {
-            self.check_cancel("perform_preamble_exchange recv")?;
-            match decode_server_preamble(&scratch) {
-                Ok(preamble) => {
-                    // Mirror `compat.c::setup_protocol` line 605:
-                    //   if (protocol_version > remote_protocol)
-                    //       protocol_version = remote_protocol;
-                    // The negotiated protocol is MIN(client_max, server_max).
-                    // The server's preamble advertises its max; both peers
-                    // then speak min() on the wire. Using the server's raw
-                    // value (e.g. proto 32 from rsync 3.4.x) while our
-                    // encoders target proto 31 produced subtle format drift
-                    // that manifested as receiver-side protocol errors and
-                    // generator EOF on the error pipe.
-                    self.protocol_version = preamble.protocol_version.min(protocol_version);
-                    self.compat_flags = preamble.compat_flags;
-                    self.checksum_seed = preamble.checksum_seed;
-                    self.negotiated_checksum_algos = preamble.checksum_algos;
-                    self.negotiated_compression_algos = preamble.compression_algos;
-                    if preamble.consumed < scratch.len() {
-                        self.mux_reader.feed(&scratch[preamble.consumed..]);
-                    }
-                    break;
-                }
-                Err(RealWireError::TruncatedBuffer { .. }) => {
-                    let stream = self.stream.as_mut().ok_or_else(|| {
-                        AerorsyncError::transport("perform_preamble_exchange: stream not open")
-                    })?;
-                    let chunk = stream.read_bytes(RAW_READ_CHUNK).await?;
-                    if chunk.is_empty() {
-                        return Err(AerorsyncError::transport(
-                            "perform_preamble_exchange: remote closed before server preamble",
-                        ));
-                    }
-                    scratch.extend_from_slice(&chunk);
-                }
-                Err(other) => {
-                    return Err(map_realwire_error(other, "server preamble"));
-                }
+    }
+
+    /// Server half of a daemon session where this end is `--sender`
+    /// (the client downloads `tree`). The transport's raw stream must
+    /// be the accepted connection, past the `@RSYNCD:` handshake and
+    /// the argument list, with the agreed protocol attached.
+    pub async fn serve_tree_sender(
+        &mut self,
+        session: &ServerSession,
+        tree: &TreeFileList,
+        source: &mut dyn TreeSource,
+        adapter: &dyn DeltaEngineAdapter,
+        bridge: &mut dyn EventSink,
+    ) -> Result<TreeTransferReport, AerorsyncError> {
+        match self
+            .serve_tree_sender_inner(session, tree, source, adapter, bridge)
+            .await
+        {
+            Ok(report) => Ok(report),
+            Err(e) => {
+                self.phase = AerorsyncSessionPhase::Failed;
+                Err(e)
             }
         }
-        self.phase = AerorsyncSessionPhase::ClientPreambleRecvd;
-        Ok(())
     }
 
-    /// Compute `FileListDecodeOptions` from the driver's current
-    /// negotiation state. csum_len hardcoded to 16 for A2.1.
-    fn build_flist_options(&self) -> FileListDecodeOptions<'static> {
-        FileListDecodeOptions {
-            protocol: self.protocol_version,
-            // CF_VARINT_FLIST_FLAGS is active from protocol 30+. The
-            // frozen oracle has it on; assert that implicitly by using
-            // the varint path. If a legacy peer disagrees, decode will
-            // surface a `RealWireError` which we translate.
-            xfer_flags_as_varint: true,
-            // B.2: production dispatch invokes the server with `-c`
-            // (always_checksum) and `-o -g` (preserve owner/group).
-            // Mirror the oracle compat: each regular file entry carries
-            // 16-byte xxh128 checksum + uid + gid varints (with names
-            // when XMIT_USER/GROUP_NAME_FOLLOWS gates them).
-            always_checksum: true,
-            csum_len: 16,
-            preserve_uid: true,
-            preserve_gid: true,
-            previous_name: None,
+    /// Server half of a daemon session where this end receives into
+    /// `sink` (the client uploads). Same stream requirements as
+    /// `serve_tree_sender`.
+    pub async fn serve_tree_receiver(
+        &mut self,
+        session: &ServerSession,
+        sink: &mut dyn TreeSink,
+        adapter: &dyn DeltaEngineAdapter,
+        bridge: &mut dyn EventSink,
+    ) -> Result<TreeTransferReport, AerorsyncError> {
+        match self
+            .serve_tree_receiver_inner(session, sink, adapter, bridge)
+            .await
+        {
+            Ok(report) => Ok(report),
+            Err(e) => {
+                self.phase = AerorsyncSessionPhase::Failed;
+                Err(e)
+            }
         }
     }
 
-    async fn send_file_list_single_file(
+    /// Server half of a single-file session where this end receives
+    /// (the client uploads one file): the generator asks for the file
+    /// with signatures of `baseline`, and the reconstruction goes to
+    /// `writer`, which the caller finalizes with the returned list
+    /// entry. Unlike the tree receiver the file is never held whole, and
+    /// the sender's file checksum is verified. Same stream requirements
+    /// as `serve_tree_sender`.
+    pub async fn serve_file_receiver(
         &mut self,
-        entry: &FileListEntry,
+        session: &ServerSession,
+        baseline: &mut dyn BaselineSource,
+        writer: &mut dyn ReconstructionWriter,
+        adapter: &dyn DeltaEngineAdapter,
+        bridge: &mut dyn EventSink,
+    ) -> Result<FileListEntry, AerorsyncError> {
+        match self
+            .serve_file_receiver_inner(session, baseline, writer, adapter, bridge)
+            .await
+        {
+            Ok(entry) => Ok(entry),
+            Err(e) => {
+                self.phase = AerorsyncSessionPhase::Failed;
+                Err(e)
+            }
+        }
+    }
+
+    /// Turn a daemon session down after its argument list (read-only
+    /// module, missing path, unsupported option): rsync reports such
+    /// errors once the multiplexed stream is up, so the preamble runs
+    /// first and the message goes out as `MSG_ERROR`.
+    pub async fn serve_refusal(
+        &mut self,
+        session: &ServerSession,
+        message: &str,
     ) -> Result<(), AerorsyncError> {
-        self.phase = AerorsyncSessionPhase::FileListSending;
-        let opts = self.build_flist_options();
-        // B.2: coalesce entry + terminator + NDX_FLIST_EOF into a single
-        // MSG_DATA frame. The frozen oracle's first MSG_DATA payload is
-        // 67 bytes carrying exactly this layout (entry 47 B + xxh128
-        // 16 B + terminator 2 B + NDX_FLIST_EOF marker 2 B). Split
-        // frames break stock rsync's expectation that the whole flist
-        // arrives before the sender starts waiting on the receiver.
-        let mut payload = encode_file_list_entry(entry, &opts);
-        payload.extend_from_slice(&encode_file_list_terminator(&opts));
-        payload.extend_from_slice(&encode_ndx(NDX_FLIST_EOF, &mut self.outbound_ndx_state));
-        self.write_data_frame(&payload).await?;
-        // S8j: remember the entry on the sender side so
-        // `emit_summary_phase` can populate `total_size`. Parity with the
-        // receiver path, which already pushes decoded entries.
-        self.file_list.push(entry.clone());
-        self.phase = AerorsyncSessionPhase::FileListSent;
+        self.open_served_stream(session).await?;
+        self.perform_server_preamble(session).await?;
+        self.write_error_message(message).await?;
+        self.phase = AerorsyncSessionPhase::Failed;
+        if let Some(mut stream) = self.stream.take() {
+            stream.shutdown().await?;
+            // Closing with the client's list still unread would reset
+            // the connection before it reads MSG_ERROR: wait for it to
+            // hang up, within bounds.
+            let mut drained = 0usize;
+            let _ = tokio::time::timeout(REFUSAL_DRAIN_TIMEOUT, async {
+                while drained < REFUSAL_DRAIN_MAX_BYTES {
+                    match stream.read_bytes(RAW_READ_CHUNK).await {
+                        Ok(chunk) if !chunk.is_empty() => drained += chunk.len(),
+                        _ => break,
+                    }
+                }
+            })
+            .await;
+        }
         Ok(())
     }
 
-    async fn receive_file_list_single_file(
+    /// `main.c::do_server_sender`: the client's filter list, the tree
+    /// sender loop, then `handle_stats` (NDX_DONE + totals) and the
+    /// sender branch of `read_final_goodbye`.
+    async fn serve_tree_sender_inner(
         &mut self,
+        session: &ServerSession,
+        tree: &TreeFileList,
+        source: &mut dyn TreeSource,
+        adapter: &dyn DeltaEngineAdapter,
         bridge: &mut dyn EventSink,
-    ) -> Result<(), AerorsyncError> {
-        self.phase = AerorsyncSessionPhase::FileListReceiving;
-        let opts = self.build_flist_options();
-        let mut flist_buf: Vec<u8> = Vec::new();
-        let mut entry_seen = false;
-        loop {
-            self.check_cancel("receive_file_list")?;
-            // Try to decode as much of the file list as we can from the
-            // currently buffered bytes. Only fall through to another
-            // Data frame when we run out of material.
-            if !flist_buf.is_empty() {
-                match decode_file_list_entry(&flist_buf, &opts) {
-                    Ok((FileListDecodeOutcome::Entry(entry), consumed)) => {
-                        flist_buf.drain(..consumed);
-                        self.file_list.push(entry);
-                        entry_seen = true;
-                        continue;
-                    }
-                    Ok((FileListDecodeOutcome::EndOfList { .. }, consumed)) => {
-                        flist_buf.drain(..consumed);
-                        if !entry_seen {
-                            return Err(AerorsyncError::invalid_frame(
-                                "file list ended without any entry",
-                            ));
-                        }
-                        self.phase = AerorsyncSessionPhase::FileListReceived;
-                        return Ok(());
-                    }
-                    // A partial FileListEntry can surface several
-                    // "need-more-bytes" shapes from `decode_file_list_entry`:
-                    // raw truncation, a declared name length that overshoots
-                    // the current buffer, or a declared algo-list length that
-                    // overshoots. All three are recoverable by pulling
-                    // another MSG_DATA frame off the wire.
-                    Err(RealWireError::TruncatedBuffer { .. })
-                    | Err(RealWireError::InvalidNameLen { .. })
-                    | Err(RealWireError::InvalidAlgoListLen { .. }) => {
-                        // Need more bytes: poll another Data frame below.
-                    }
-                    Err(other) => {
-                        return Err(map_realwire_error(other, "file list entry"));
-                    }
-                }
-            }
-            let payload = self.next_data_frame(bridge).await?;
-            flist_buf.extend_from_slice(&payload);
+    ) -> Result<TreeTransferReport, AerorsyncError> {
+        if tree.is_empty() {
+            return Err(AerorsyncError::invalid_frame(
+                "serve_tree_sender: empty file list",
+            ));
         }
-    }
+        self.session_role = Some(SessionRole::Sender);
+        self.open_served_stream(session).await?;
+        self.perform_server_preamble(session).await?;
+        self.require_inc_recurse()?;
+        let mut inbound = Vec::new();
+        self.read_filter_list(&mut inbound, bridge).await?;
+        let (report, inbound) = self
+            .send_tree_files(tree, source, adapter, bridge, inbound)
+            .await?;
 
-    /// Wrap `payload` in a `MSG_DATA` mux frame and write it to the raw
-    /// stream. Rejects payloads larger than the 24-bit length field.
-    async fn write_data_frame(&mut self, payload: &[u8]) -> Result<(), AerorsyncError> {
-        if payload.len() > 0x00FF_FFFF {
-            return Err(AerorsyncError::invalid_frame(format!(
-                "MSG_DATA payload {} exceeds 24-bit length field",
-                payload.len()
-            )));
-        }
-        self.check_cancel("write_data_frame")?;
-        let header = MuxHeader {
-            tag: MuxTag::Data,
-            length: payload.len() as u32,
+        // sender.c:462 NDX_DONE and the server sender's stats, which the
+        // client reads in `receive_summary_phase`.
+        self.phase = AerorsyncSessionPhase::SummaryReceiving;
+        let frame = SummaryFrame {
+            total_read: self.received_raw_bytes as i64,
+            total_written: self.sent_data_bytes as i64,
+            total_size: tree.total_size() as i64,
+            flist_buildtime: Some(0),
+            flist_xfertime: Some(0),
         };
-        let hdr_bytes = header.encode();
-        let stream = self
-            .stream
-            .as_mut()
-            .ok_or_else(|| AerorsyncError::transport("write_data_frame: stream not open"))?;
-        stream.write_bytes(&hdr_bytes).await?;
-        stream.write_bytes(payload).await?;
-        self.sent_data_bytes += payload.len() as u64;
-        Ok(())
+        let mut payload = vec![0x00];
+        payload.extend_from_slice(&encode_summary_frame(&frame, self.protocol_version));
+        self.write_data_frame(&payload).await?;
+        self.summary_seed = inbound;
+        if self.read_client_goodbye(bridge).await? && self.protocol_version >= 31 {
+            self.emit_ndx_done_marker().await?;
+        }
+        self.session_stats.bytes_sent = self.sent_data_bytes;
+        self.session_stats.bytes_received = self.received_raw_bytes;
+        self.phase = AerorsyncSessionPhase::SummaryReceived;
+        self.shutdown_raw_stream().await?;
+        Ok(report)
     }
 
-    /// Drive the `MuxStreamReader` until a `MSG_DATA` frame pops out.
-    /// Non-terminal OOB frames are routed to `bridge`; the first
-    /// terminal OOB bails with a typed error (and is also forwarded to
-    /// the bridge so it can capture `first_terminal()` for post-mortem).
-    async fn next_data_frame(
+    /// `main.c::do_server_recv`: the filter list when deleting, the
+    /// tree receiver loop, then the generator's goodbye NDX_DONE and
+    /// the proto-31 final one, which a client sender answers in
+    /// `read_final_goodbye`. A server receiver sends no stats.
+    async fn serve_tree_receiver_inner(
         &mut self,
+        session: &ServerSession,
+        sink: &mut dyn TreeSink,
+        adapter: &dyn DeltaEngineAdapter,
         bridge: &mut dyn EventSink,
-    ) -> Result<Vec<u8>, AerorsyncError> {
-        loop {
-            // Poll-first policy: a full frame may already be buffered
-            // from a previous chunk. Without this we would deadlock when
-            // the server's response arrived in a single read.
-            if let Some(res) = self.mux_reader.poll_frame() {
-                let poll = res.map_err(|e| map_realwire_error(e, "mux frame"))?;
-                match poll {
-                    MuxPoll::Data(bytes) => {
-                        // S8j: mirror of `sent_data_bytes`, used by
-                        // `emit_summary_phase` to populate `total_read`
-                        // in upload finishes.
-                        self.received_raw_bytes += bytes.len() as u64;
-                        return Ok(bytes);
-                    }
-                    MuxPoll::Oob(event) => {
-                        bridge.handle(event);
-                        continue;
-                    }
-                    MuxPoll::Terminal(event) => {
-                        // Forward to the bridge before bailing so
-                        // `first_terminal()` captures the full payload.
-                        let event_for_bridge = event.clone();
-                        bridge.handle(event_for_bridge);
-                        return Err(AerorsyncError::from_oob_event(&event));
-                    }
-                }
-            }
-            self.check_cancel("next_data_frame")?;
-            let stream = self
-                .stream
-                .as_mut()
-                .ok_or_else(|| AerorsyncError::transport("next_data_frame: stream not open"))?;
-            let chunk = stream.read_bytes(RAW_READ_CHUNK).await?;
-            if chunk.is_empty() {
-                return Err(AerorsyncError::transport(
-                    "next_data_frame: remote closed mid file list",
-                ));
-            }
-            self.mux_reader.feed(&chunk);
+    ) -> Result<TreeTransferReport, AerorsyncError> {
+        self.session_role = Some(SessionRole::Receiver);
+        self.open_served_stream(session).await?;
+        self.perform_server_preamble(session).await?;
+        self.require_inc_recurse()?;
+        let mut inbound = Vec::new();
+        // `send_filter_list`: a client sender only sends one when the
+        // receiver deletes.
+        if self.transfer_options.delete {
+            self.read_filter_list(&mut inbound, bridge).await?;
         }
-    }
+        let (report, inbound) = self
+            .receive_tree_files(sink, adapter, bridge, inbound)
+            .await?;
 
-    // --- A2.2 signature phase (upload: receive, download: send) ----------
+        self.phase = AerorsyncSessionPhase::SummaryReceiving;
+        self.emit_ndx_done_marker().await?;
+        if self.protocol_version >= 31 {
+            self.emit_ndx_done_marker().await?;
+        }
+        self.summary_seed = inbound;
+        self.read_client_goodbye(bridge).await?;
+        self.session_stats.bytes_sent = self.sent_data_bytes;
+        self.session_stats.bytes_received = self.received_raw_bytes;
+        self.phase = AerorsyncSessionPhase::SummaryReceived;
+        self.shutdown_raw_stream().await?;
+        Ok(report)
+    }
 
-    /// Upload path: drain `ndx + iflags + sum_head + count × sum_block`
-    /// from the server. Populates `received_sum_head`,
-    /// `received_signatures`, `last_iflags`. Phase transitions:
-    /// `FileListSent → SumHeadReceiving → SumBlocksReceiving`.
-    async fn receive_signature_phase_single_file(
+    /// `main.c::do_server_recv` for one file: the generator requests
+    /// ndx 1 with the basis signatures, the receiver reads the echoed
+    /// item header and the delta, then both walk the NDX_DONE phases
+    /// the client sender answers in `sender_phase_loop` and
+    /// `read_final_goodbye`.
+    async fn serve_file_receiver_inner(
         &mut self,
+        session: &ServerSession,
+        baseline: &mut dyn BaselineSource,
+        writer: &mut dyn ReconstructionWriter,
+        adapter: &dyn DeltaEngineAdapter,
         bridge: &mut dyn EventSink,
-    ) -> Result<(), AerorsyncError> {
+    ) -> Result<FileListEntry, AerorsyncError> {
+        if session.options.delete {
+            return Err(AerorsyncError::new(
+                AerorsyncErrorKind::PlannerRejected,
+                "--delete applies to directory sessions only",
+            ));
+        }
+        self.session_role = Some(SessionRole::Receiver);
+        self.open_served_stream(session).await?;
+        self.perform_server_preamble(session).await?;
+        self.require_inc_recurse()?;
+        let inbound = self.receive_file_list_single_file(bridge).await?;
+        self.read_flist_eof(inbound, bridge).await?;
+        let entry = match self.file_list.as_slice() {
+            [entry] if is_regular_mode(entry.mode) => entry.clone(),
+            _ => {
+                return Err(AerorsyncError::invalid_frame(
+                    "single-file session expects exactly one regular file",
+                ))
+            }
+        };
+
+        self.send_signature_phase_streaming(baseline, adapter)
+            .await?;
+        if self.transfer_skipped {
+            // The client sender cannot take NDX_DONE in place of the
+            // request: a local copy that already covers the file is
+            // for the caller to spot before opening the session.
+            return Err(AerorsyncError::invalid_frame(
+                "--append: the local copy already covers the sender's file",
+            ));
+        }
         self.phase = AerorsyncSessionPhase::SumHeadReceiving;
         let (ndx, iflags, head) = self.read_signature_header(bridge).await?;
-        if !(0..=i32::MAX).contains(&ndx) {
+        if ndx != A2_2_FIRST_FILE_NDX
+            || iflags & ITEM_TRANSFER == 0
+            || Some(head) != self.sent_sum_head
+        {
             return Err(AerorsyncError::invalid_frame(format!(
-                "unexpected ndx sentinel before signature phase: {ndx}"
+                "sender echoed ndx {ndx}, iflags 0x{iflags:04X}, {head:?} for the request"
             )));
         }
-        if iflags & ITEM_TRANSFER == 0 {
-            return Err(AerorsyncError::invalid_frame(format!(
-                "server signature message lacks ITEM_TRANSFER bit: iflags=0x{iflags:04X}"
-            )));
+        let mut hasher = self.checksum_algo.hasher();
+        if self.transfer_options.append == AppendMode::AppendVerify {
+            // The trailer covers the kept prefix too.
+            let kept = head.basis_length();
+            let stride = STREAMING_READ_CHUNK_BYTES as u32;
+            let mut hashed = 0u64;
+            let mut idx = 0u32;
+            while hashed < kept {
+                let chunk = baseline.read_block(idx, stride).await.map_err(|e| {
+                    AerorsyncError::transport(format!("--append-verify: basis read failed: {e}"))
+                })?;
+                if chunk.is_empty() {
+                    break;
+                }
+                let take = chunk.len().min((kept - hashed) as usize);
+                hasher.update(&chunk[..take]);
+                hashed += take as u64;
+                idx += 1;
+            }
         }
-        // B.2 Step 4: stash the received NDX so `send_delta_phase_*`
-        // can echo it back at the start of the delta payload (parity
-        // with `sender.c::write_ndx_and_attrs`). Without this echo the
-        // receiver mis-aligns and aborts with rsync exit 22.
-        self.last_received_ndx = ndx;
-        self.last_iflags = iflags;
-        self.received_sum_head = Some(head);
+        self.receive_delta_phase_streaming(baseline, writer, Some(hasher), bridge)
+            .await?;
 
-        if head.count < 0 {
-            return Err(AerorsyncError::invalid_frame(format!(
-                "server sum_head.count is negative: {}",
-                head.count
-            )));
+        // `generator.c` ends each phase with NDX_DONE and `receiver.c`
+        // reads the sender's echo; the last one is the sender's
+        // post-loop marker. Then the goodbye, as for a tree.
+        self.phase = AerorsyncSessionPhase::SummaryReceiving;
+        let max_phase = if self.protocol_version >= 29 { 2 } else { 1 };
+        for _ in 0..=max_phase {
+            self.emit_ndx_done_marker().await?;
+            if self
+                .try_read_ndx_done_marker(bridge, "serve_file_receiver: phase echo")
+                .await?
+                .is_none()
+            {
+                return Err(AerorsyncError::invalid_frame(
+                    "serve_file_receiver: sender closed before the phase echoes",
+                ));
+            }
         }
-        self.phase = AerorsyncSessionPhase::SumBlocksReceiving;
-        let blocks = self
-            .read_signature_blocks(head.count as usize, head.checksum_length as usize, bridge)
-            .await?;
-        self.received_signatures = blocks;
-        Ok(())
+        self.emit_ndx_done_marker().await?;
+        if self.protocol_version >= 31 {
+            self.emit_ndx_done_marker().await?;
+        }
+        self.read_client_goodbye(bridge).await?;
+        self.session_stats.bytes_sent = self.sent_data_bytes;
+        self.session_stats.bytes_received = self.received_raw_bytes;
+        self.phase = AerorsyncSessionPhase::SummaryReceived;
+        self.shutdown_raw_stream().await?;
+        Ok(entry)
     }
 
-    /// Decode `ndx + iflags + sum_head` from the data stream, pulling
-    /// additional `MSG_DATA` frames whenever the decoder reports it
-    /// needs more bytes.
-    async fn read_signature_header(
+    /// The `NDX_FLIST_EOF` a sender writes after its last list, from
+    /// the residual of the file-list frame or the next one. Whatever
+    /// follows it opens the next read.
+    async fn read_flist_eof(
         &mut self,
+        mut buf: Vec<u8>,
         bridge: &mut dyn EventSink,
-    ) -> Result<(i32, u16, SumHead), AerorsyncError> {
-        let mut buf: Vec<u8> = Vec::new();
-        // 1. ndx
-        let ndx = loop {
-            self.check_cancel("read_signature_header ndx")?;
+    ) -> Result<(), AerorsyncError> {
+        loop {
+            self.check_cancel("read_flist_eof")?;
             if !buf.is_empty() {
                 match decode_ndx(&buf, &mut self.inbound_ndx_state) {
-                    Ok((ndx, consumed)) => {
+                    Ok((NDX_FLIST_EOF, consumed)) => {
                         buf.drain(..consumed);
-                        break ndx;
+                        self.sig_residual_after_header = buf;
+                        return Ok(());
                     }
-                    Err(RealWireError::NdxTruncated { .. }) => {
-                        // need more bytes
+                    Ok((ndx, _)) => {
+                        return Err(AerorsyncError::invalid_frame(format!(
+                            "expected NDX_FLIST_EOF after the file list, got ndx {ndx}"
+                        )))
                     }
-                    Err(other) => return Err(map_realwire_error(other, "signature ndx")),
+                    Err(RealWireError::NdxTruncated { .. }) => {}
+                    Err(other) => return Err(map_realwire_error(other, "NDX_FLIST_EOF")),
                 }
             }
             let payload = self.next_data_frame(bridge).await?;
             buf.extend_from_slice(&payload);
-        };
-        if ndx == NDX_DONE || ndx == NDX_FLIST_EOF {
-            return Err(AerorsyncError::invalid_frame(format!(
-                "unexpected ndx sentinel at start of signature phase: {ndx}"
-            )));
         }
-        // 2. iflags (u16 LE: 2 bytes)
-        let iflags = loop {
-            self.check_cancel("read_signature_header iflags")?;
-            if buf.len() >= 2 {
-                match decode_item_flags(&buf) {
-                    Ok((flags, consumed)) => {
-                        buf.drain(..consumed);
-                        break flags;
-                    }
-                    Err(other) => return Err(map_realwire_error(other, "signature iflags")),
-                }
-            }
-            let payload = self.next_data_frame(bridge).await?;
-            buf.extend_from_slice(&payload);
-        };
-        // 3. sum_head (16 bytes)
-        let head = loop {
-            self.check_cancel("read_signature_header sum_head")?;
-            if buf.len() >= 16 {
-                match decode_sum_head(&buf) {
-                    Ok((head, consumed)) => {
-                        buf.drain(..consumed);
-                        break head;
-                    }
-                    Err(other) => return Err(map_realwire_error(other, "signature sum_head")),
-                }
-            }
-            let payload = self.next_data_frame(bridge).await?;
-            buf.extend_from_slice(&payload);
-        };
-        // Stash any residual bytes back into the mux reader for the
-        // subsequent sum_blocks reader. `MuxStreamReader.feed` takes raw
-        // mux frames: but at this point `buf` holds POST-mux payload
-        // bytes, not raw mux. We cannot re-feed it into the reader.
-        // Instead we carry the residual through `read_signature_blocks`
-        // via an explicit argument.
-        //
-        // Implementation choice: pass `buf` to `read_signature_blocks`
-        // as the prefix of its own accumulator. Getters stay clean.
-        self.sig_residual_after_header = std::mem::take(&mut buf);
-        Ok((ndx, iflags, head))
     }
 
-    /// Read exactly `count` sum_blocks from the data stream, using the
-    /// residual bytes left by `read_signature_header` as a prefix to the
-    /// accumulator.
-    async fn read_signature_blocks(
+    /// `sender.c::send_files` for a tree: lists go out lazily (at most
+    /// `TREE_FLIST_LOOKAHEAD_ENTRIES` unfreed entries ahead of the
+    /// generator), every request is answered in arrival order, and the
+    /// generator's NDX_DONEs free lists before they advance the phase.
+    async fn drive_upload_tree_inner(
         &mut self,
-        count: usize,
-        strong_len: usize,
+        command_spec: RemoteCommandSpec,
+        tree: &TreeFileList,
+        source: &mut dyn TreeSource,
+        adapter: &dyn DeltaEngineAdapter,
         bridge: &mut dyn EventSink,
-    ) -> Result<Vec<SumBlock>, AerorsyncError> {
-        let mut buf: Vec<u8> = std::mem::take(&mut self.sig_residual_after_header);
-        let mut out = Vec::with_capacity(count);
-        while out.len() < count {
-            self.check_cancel("read_signature_blocks")?;
-            let block_wire_size = 4 + strong_len;
-            if buf.len() >= block_wire_size {
-                match decode_sum_block(&buf, strong_len) {
-                    Ok((block, consumed)) => {
-                        buf.drain(..consumed);
-                        out.push(block);
-                        continue;
-                    }
-                    Err(other) => return Err(map_realwire_error(other, "sum_block")),
-                }
-            }
-            let payload = self.next_data_frame(bridge).await?;
-            buf.extend_from_slice(&payload);
+    ) -> Result<TreeTransferReport, AerorsyncError> {
+        if tree.is_empty() {
+            return Err(AerorsyncError::invalid_frame(
+                "drive_upload_tree: empty file list",
+            ));
         }
-        Ok(out)
+        self.session_role = Some(SessionRole::Sender);
+        self.remote_command_flavor = command_spec.flavor;
+        self.open_raw_stream_internal(&command_spec).await?;
+        self.perform_preamble_exchange(31).await?;
+        self.require_inc_recurse()?;
+        let (report, inbound) = self
+            .send_tree_files(tree, source, adapter, bridge, Vec::new())
+            .await?;
+
+        // Same tail as `finish_stock_rsync_sender_tail` once its phase
+        // loop has run: sender.c:462 NDX_DONE, then read_final_goodbye.
+        self.phase = AerorsyncSessionPhase::SummaryReceiving;
+        self.emit_ndx_done_marker().await?;
+        let total_size = tree
+            .segments()
+            .iter()
+            .flat_map(|segment| &segment.entries)
+            .filter(|entry| is_regular_mode(entry.mode))
+            .map(|entry| entry.size)
+            .sum();
+        self.record_batch_stats(total_size).await?;
+        self.summary_seed = inbound;
+        self.read_final_goodbye_marker(bridge).await?;
+        self.session_stats.bytes_sent = self.sent_data_bytes;
+        self.session_stats.bytes_received = self.received_raw_bytes;
+        self.phase = AerorsyncSessionPhase::SummaryReceived;
+        self.shutdown_raw_stream().await?;
+        Ok(report)
     }
 
-    /// Download path: compute signatures from `destination_data` via
-    /// `adapter` and emit a single mux-wrapped blob with
-    /// `ndx + iflags + sum_head + count × sum_block`. Phase transitions:
-    /// `FileListReceived → SumHeadSent → SumBlocksSent`.
-    async fn send_signature_phase_single_file(
+    /// Sender loop shared by `drive_upload_tree` and `serve_tree_sender`,
+    /// from the first file list to the generator's last phase NDX_DONE.
+    /// `inbound` holds bytes already read past the previous step; the
+    /// ones left unread are handed back for the tail.
+    async fn send_tree_files(
         &mut self,
-        destination_data: &[u8],
+        tree: &TreeFileList,
+        source: &mut dyn TreeSource,
         adapter: &dyn DeltaEngineAdapter,
-    ) -> Result<(), AerorsyncError> {
-        self.phase = AerorsyncSessionPhase::SumHeadSent;
-        let block_size = adapter.compute_block_size(destination_data.len() as u64);
-        let engine_sigs = adapter.build_signatures(destination_data, block_size);
-
-        // Build truncated wire SumBlocks.
-        let s2length = A2_2_DOWNLOAD_S2LENGTH;
-        let s2length_usize = s2length as usize;
-        let mut sum_blocks: Vec<SumBlock> = Vec::with_capacity(engine_sigs.len());
-        for sig in &engine_sigs {
-            let strong = sig.strong[..s2length_usize.min(sig.strong.len())].to_vec();
-            sum_blocks.push(SumBlock {
-                rolling: sig.rolling,
-                strong,
-            });
-        }
-
-        // Compose sum_head. Block length from the engine's choice;
-        // remainder is (file_size mod block_size): identical to rsync's
-        // own derivation.
-        let file_size = destination_data.len() as i32;
-        let block_length = block_size as i32;
-        let remainder_length = if block_length > 0 {
-            file_size % block_length
-        } else {
-            0
+        bridge: &mut dyn EventSink,
+        mut inbound: Vec<u8>,
+    ) -> Result<(TreeTransferReport, Vec<u8>), AerorsyncError> {
+        let segments = tree.segments();
+        let mut report = TreeTransferReport {
+            files_total: tree.file_count() as u64,
+            dirs_total: tree.entries().filter(|e| is_dir_mode(e.mode)).count() as u64,
+            ..TreeTransferReport::default()
         };
-        let head = SumHead {
-            count: sum_blocks.len() as i32,
-            block_length,
-            checksum_length: s2length,
-            remainder_length,
+        // One encoder for the whole session, like `token.c`'s statics.
+        let mut encoder = self.literal_encoder()?;
+        let append = self.transfer_options.append;
+        let decode_request: TreeMessageDecoder<GeneratorMessage> = if append == AppendMode::Off {
+            decode_generator_message
+        } else {
+            decode_append_generator_message
         };
-        self.sent_sum_head = Some(head);
-
-        // Build a single MSG_DATA payload that concatenates everything.
-        let mut payload: Vec<u8> = Vec::with_capacity(
-            16 /* sum_head worst */ + 4 /* ndx upper bound */ + 2 /* iflags */
-                + sum_blocks.len() * (4 + s2length_usize),
-        );
-        payload.extend_from_slice(&encode_ndx(
-            A2_2_FIRST_FILE_NDX,
-            &mut self.outbound_ndx_state,
-        ));
-        payload.extend_from_slice(&encode_item_flags(A2_2_DOWNLOAD_IFLAGS));
-        payload.extend_from_slice(&encode_sum_head(&head));
-        for block in &sum_blocks {
-            payload.extend_from_slice(&encode_sum_block(block));
-        }
+        let max_phase: i32 = if self.protocol_version >= 29 { 2 } else { 1 };
+        let mut phase: i32 = 0;
+        let mut sent = 0usize;
+        let mut freed = 0usize;
+        let mut live_entries = 0usize;
+        let mut tables = FlistMetaTables::new();
+        self.phase = AerorsyncSessionPhase::FileListSending;
+        loop {
+            while sent < segments.len()
+                && (sent == 0 || live_entries < TREE_FLIST_LOOKAHEAD_ENTRIES)
+            {
+                let last = sent + 1 == segments.len();
+                self.send_tree_segment(sent, tree, source, last, &mut tables)
+                    .await?;
+                live_entries += segments[sent].entries.len();
+                sent += 1;
+                if last {
+                    self.phase = AerorsyncSessionPhase::FileListSent;
+                }
+            }
 
-        self.last_iflags = A2_2_DOWNLOAD_IFLAGS;
-        self.write_data_frame(&payload).await?;
+            let message = self
+                .next_tree_message(
+                    &mut inbound,
+                    bridge,
+                    decode_request,
+                    "tree generator message",
+                )
+                .await?;
+            let (mut header, head, blocks) = match message {
+                GeneratorMessage::Done => {
+                    if freed < sent {
+                        live_entries -= segments[freed].entries.len();
+                        freed += 1;
+                        if freed < sent {
+                            self.emit_ndx_done_marker().await?;
+                            continue;
+                        }
+                    }
+                    phase += 1;
+                    if phase > max_phase {
+                        break;
+                    }
+                    self.emit_ndx_done_marker().await?;
+                    continue;
+                }
+                GeneratorMessage::Request {
+                    mut header,
+                    sums: None,
+                } => {
+                    // Attribute-only item: echo it back, with the values
+                    // of any xattrs it asks for.
+                    if !header.xattrs.is_empty() {
+                        let (_, entry) = tree_entry_for(tree, header.ndx)?;
+                        answer_xattr_request(&mut header, entry)?;
+                    }
+                    let echo = header.encode(&mut self.outbound_ndx_state);
+                    self.write_data_frame(&echo).await?;
+                    continue;
+                }
+                GeneratorMessage::Request {
+                    header,
+                    sums: Some((head, blocks)),
+                } => (header, head, blocks),
+                GeneratorMessage::Unexpected(ndx) => {
+                    return Err(AerorsyncError::invalid_frame(format!(
+                        "unexpected ndx {ndx} from the remote generator"
+                    )));
+                }
+            };
 
-        self.sent_signatures = sum_blocks;
-        // Keep engine_sigs alive until after emit in case the caller
-        // inspects them via a future getter. Dropped here.
-        let _ = engine_sigs;
+            let (_, entry) = tree_entry_for(tree, header.ndx)?;
+            answer_xattr_request(&mut header, entry)?;
+            if !is_regular_mode(entry.mode) {
+                return Err(AerorsyncError::invalid_frame(format!(
+                    "remote generator requested data for non-file {:?}",
+                    entry.path
+                )));
+            }
+            self.check_cancel("tree file read")?;
+            let data = source.read_file(&entry.path).await?;
+            if data.len() as u64 != entry.size.max(0) as u64 {
+                return Err(AerorsyncError::invalid_frame(format!(
+                    "{:?} changed size during the transfer ({} -> {} bytes)",
+                    entry.path,
+                    entry.size,
+                    data.len()
+                )));
+            }
 
-        self.phase = AerorsyncSessionPhase::SumBlocksSent;
-        Ok(())
+            self.phase = AerorsyncSessionPhase::DeltaSending;
+            let block_size = head.block_length as usize;
+            let append_from = if append == AppendMode::Off {
+                None
+            } else {
+                let offset = head.basis_length();
+                if offset > data.len() as u64 {
+                    return Err(AerorsyncError::invalid_frame(format!(
+                        "--append: remote copy of {:?} is longer than the source",
+                        entry.path
+                    )));
+                }
+                Some(offset)
+            };
+            let mut plan = if let Some(offset) = append_from {
+                whole_file_plan(&data[offset as usize..])
+            } else if block_size == 0 {
+                whole_file_plan(&data)
+            } else {
+                adapter.compute_delta_truncated(
+                    &data,
+                    &sum_blocks_to_engine(&head, &blocks),
+                    block_size,
+                    head.checksum_length as usize,
+                )
+            };
+            if self.transfer_options.writes_in_place() && block_size != 0 {
+                let mut guard = InplaceGuard::new(head);
+                guard.apply(&mut plan.ops, &data, 0)?;
+                plan.literal_bytes += guard.converted_bytes;
+            }
+            let wire_ops = engine_ops_to_wire_ops(&plan.ops, &mut encoder)?;
+            encoder.end_file();
+            let hash_from = checksum_start(append, append_from) as usize;
+            let delta = encode_delta_stream_with(
+                &DeltaStreamReport {
+                    ops: wire_ops,
+                    file_checksum: self.checksum_algo.digest(&data[hash_from..]),
+                },
+                self.token_format(),
+            );
+            let mut payload = header.encode(&mut self.outbound_ndx_state);
+            payload.extend_from_slice(&encode_sum_head(&head));
+            payload.extend_from_slice(&delta);
+
+            self.committed = true;
+            self.write_data_frames(&payload).await?;
+            self.session_stats.literal_bytes += plan.literal_bytes;
+            self.session_stats.matched_bytes +=
+                (data.len() as u64).saturating_sub(plan.literal_bytes);
+            report.files_transferred += 1;
+            report.bytes_transferred +=
+                (data.len() as u64).saturating_sub(append_from.unwrap_or(0));
+            self.phase = AerorsyncSessionPhase::DeltaSent;
+        }
+        Ok((report, inbound))
     }
 
-    // --- A2.3 delta phase (upload: send, download: receive) --------------
+    /// Generator and receiver halves of a tree download, interleaved on
+    /// one task. The generator walks the lists received so far and keeps
+    /// at most `TREE_REQUEST_BUDGET_BYTES` of requests unanswered; the
+    /// receiver half reads extra lists, file data and NDX_DONEs in
+    /// stream order. NDX_DONE accounting mirrors `generator.c` /
+    /// `receiver.c`: one per freed list, the last one doubling as the
+    /// phase-0 end, then one per remaining phase.
+    async fn drive_download_tree_inner(
+        &mut self,
+        command_spec: RemoteCommandSpec,
+        sink: &mut dyn TreeSink,
+        adapter: &dyn DeltaEngineAdapter,
+        bridge: &mut dyn EventSink,
+    ) -> Result<TreeTransferReport, AerorsyncError> {
+        self.session_role = Some(SessionRole::Receiver);
+        self.remote_command_flavor = command_spec.flavor;
+        self.open_raw_stream_internal(&command_spec).await?;
+        self.perform_preamble_exchange(31).await?;
+        self.require_inc_recurse()?;
+        // `send_filter_list`: the client receiver always sends one, even
+        // when empty (a single int 0 terminator).
+        self.write_data_frame(&0i32.to_le_bytes()).await?;
+        let (report, inbound) = self
+            .receive_tree_files(sink, adapter, bridge, Vec::new())
+            .await?;
 
-    /// Upload path: compute delta via `adapter.compute_delta`, compress
-    /// literals session-wide, encode the full stream (ops + END_FLAG +
-    /// file_checksum trailer), and emit on the wire in a single MSG_DATA
-    /// frame. Flips `committed = true` immediately before the first
-    /// wire byte: the PreCommit/PostCommit boundary.
-    ///
-    /// Phase transitions: `SumBlocksReceiving → DeltaSending → DeltaSent`.
-    async fn send_delta_phase_single_file(
+        // Same tail as `finish_session_inner` for a receiver, minus the
+        // NDX_DONE drain the loop above already consumed.
+        self.summary_seed = inbound;
+        self.receive_summary_phase(bridge).await?;
+        self.emit_ndx_done_marker().await?;
+        self.read_trailing_ndx_done(bridge).await?;
+        self.shutdown_raw_stream().await?;
+        Ok(report)
+    }
+
+    /// Generator and receiver loop shared by `drive_download_tree` and
+    /// `serve_tree_receiver`, from the first file list to the sender's
+    /// last NDX_DONE, hard links included. `inbound` works as in
+    /// `send_tree_files`.
+    async fn receive_tree_files(
         &mut self,
-        source_data: &[u8],
+        sink: &mut dyn TreeSink,
         adapter: &dyn DeltaEngineAdapter,
-    ) -> Result<(), AerorsyncError> {
-        self.phase = AerorsyncSessionPhase::DeltaSending;
+        bridge: &mut dyn EventSink,
+        mut inbound: Vec<u8>,
+    ) -> Result<(TreeTransferReport, Vec<u8>), AerorsyncError> {
+        self.phase = AerorsyncSessionPhase::FileListReceiving;
+        let mut tree = TreeFileList::new();
+        let mut lists = ReceivedListState::default();
+        let mut io_error = self
+            .receive_tree_segment(&mut inbound, None, &mut tree, &mut lists, bridge)
+            .await?;
+        let append = self.transfer_options.append;
 
-        // Rebuild EngineSignatureBlock vec from received SumBlocks.
-        let engine_sigs = self.wire_sigs_to_engine()?;
-        let block_size = self
-            .received_sum_head
-            .as_ref()
-            .map(|h| h.block_length as usize)
-            .unwrap_or(0);
+        // One decoder for the whole session, like `token.c`'s statics.
+        let mut decoder = self.literal_decoder();
+        let max_phase: i32 = if self.protocol_version >= 29 { 2 } else { 1 };
+        let mut report = TreeTransferReport::default();
+        let mut flist_eof = false;
+        let mut gen_seg = 0usize;
+        let mut gen_pos = 0usize;
+        let mut gen_freed = 0usize;
+        let mut gen_phase: i32 = 0;
+        let mut recv_freed = 0usize;
+        let mut recv_phase: i32 = 0;
+        let mut pending: HashMap<i32, usize> = HashMap::new();
+        let mut in_flight = 0usize;
+        // `-H` followers, linked to their leader once every file is in.
+        let mut hard_links: Vec<(String, String)> = Vec::new();
 
-        // B.2 Step 4: `block_size == 0` is the "whole file" case: the
-        // receiver's local target is absent or zero-byte so it has
-        // nothing to diff against. `generator.c::write_sum_head(f_out,
-        // NULL)` emits four zero int32s in this scenario. The sender
-        // must react by streaming the entire source as a single literal
-        // (no block matches possible). We build a synthetic plan with
-        // one Literal op covering all `source_data`.
-        let plan = if block_size == 0 {
-            use crate::aerorsync::engine_adapter::EngineDeltaPlan;
-            let ops = if source_data.is_empty() {
-                Vec::new()
-            } else {
-                vec![EngineDeltaOp::Literal(source_data.to_vec())]
-            };
-            EngineDeltaPlan {
-                ops,
-                copy_blocks: 0,
-                literal_bytes: source_data.len() as u64,
-                total_delta_bytes: source_data.len() as u64,
-                savings_ratio: 1.0,
-                should_use_delta: true,
+        loop {
+            // --- generator half ---
+            while gen_seg < tree.segments().len()
+                && (self.reading_batch || in_flight < TREE_REQUEST_BUDGET_BYTES)
+            {
+                if gen_pos == 0 && self.transfer_options.delete {
+                    self.delete_for_segment(&tree, gen_seg, io_error, sink, &mut report)
+                        .await?;
+                }
+                let segment = &tree.segments()[gen_seg];
+                let Some(entry) = segment.entries.get(gen_pos) else {
+                    gen_seg += 1;
+                    gen_pos = 0;
+                    continue;
+                };
+                let ndx = segment.ndx_start + gen_pos as i32;
+                gen_pos += 1;
+                if let Some(HardlinkRef::Follower { first_ndx }) = entry.hardlink {
+                    let leader = lists.hlink_leaders.get(&first_ndx).ok_or_else(|| {
+                        map_realwire_error(
+                            RealWireError::HardlinkLeaderMissing { first_ndx },
+                            "tree hard link",
+                        )
+                    })?;
+                    hard_links.push((leader.clone(), entry.path.clone()));
+                    report.files_total += 1;
+                    continue;
+                }
+                if let Some(bytes) = self
+                    .generate_tree_entry(ndx, entry, sink, adapter, &mut report)
+                    .await?
+                {
+                    pending.insert(ndx, bytes);
+                    in_flight += bytes;
+                }
+            }
+            // A list is released once the generator has moved on to the
+            // next one; the last one is released by the phase-0 NDX_DONE.
+            while gen_freed < gen_seg && gen_freed + 1 < tree.segments().len() {
+                self.emit_ndx_done_marker().await?;
+                gen_freed += 1;
+            }
+            if gen_phase == 0 && flist_eof && gen_seg == tree.segments().len() {
+                self.emit_ndx_done_marker().await?;
+                gen_freed += 1;
+                gen_phase = 1;
+            }
+            while gen_phase >= 1 && gen_phase <= max_phase && recv_phase >= gen_phase {
+                self.emit_ndx_done_marker().await?;
+                gen_phase += 1;
             }
-        } else {
-            adapter.compute_delta(source_data, &engine_sigs, block_size)
-        };
-
-        // Extract raw literals in encounter order for session-wide
-        // zstd compression (matches `send_zstd_token`'s shared CCtx).
-        let pending_raw: Vec<&[u8]> = plan
-            .ops
-            .iter()
-            .filter_map(|op| match op {
-                EngineDeltaOp::Literal(raw) => Some(raw.as_slice()),
-                EngineDeltaOp::CopyBlock(_) => None,
-            })
-            .collect();
 
-        let zstd_on = self.zstd_negotiated();
-        let compressed_blobs: Vec<Vec<u8>> = if zstd_on && !pending_raw.is_empty() {
-            compress_zstd_literal_stream(&pending_raw)
-                .map_err(|e| map_realwire_error(e, "zstd compress literal stream"))?
-        } else {
-            // No compression negotiated: emit raw payloads as-is.
-            pending_raw.iter().map(|p| p.to_vec()).collect()
-        };
-        // S8j: multi-chunk DEFLATED_DATA splitting.
-        //
-        // Stock rsync's `send_zstd_token` (token.c:678-776) flushes the
-        // zstd output buffer whenever it reaches `MAX_DATA_COUNT`
-        // (= 16383, the 14-bit length budget of the DEFLATED_DATA
-        // token) and emits a fresh DEFLATED_DATA record with the rest.
-        // A compressed literal larger than 16383 bytes therefore lands
-        // as N consecutive DEFLATED_DATA frames on the wire; the
-        // receiver's single session-wide `ZSTD_DCtx` concatenates the
-        // payloads transparently: the chunk boundaries carry no
-        // logical meaning, they're pure transport fragmentation.
-        //
-        // We mirror that behaviour by chunking every compressed blob
-        // that exceeds `MAX_DELTA_LITERAL_LEN` into 16383-byte slices
-        // and emitting one `DeltaOp::Literal` per slice. The original
-        // `EngineDeltaOp::Literal` → wire literal ordering is
-        // preserved; CopyRun ops stay interleaved at the same logical
-        // positions they occupied in the engine plan. Pre-fix the
-        // driver bailed with `InvalidFrame` as soon as any blob
-        // crossed 16 KiB, capping the native path at ~16 KiB delta
-        // payloads. Post-fix the cap is the 24-bit DEFLATED_DATA
-        // per-token length (unchanged) times an unbounded number of
-        // tokens: in practice governed by the driver's in-memory
-        // cap (`AERORSYNC_MAX_IN_MEMORY_BYTES`).
-
-        // Interleave literals with CopyRun ops in the original engine
-        // order. Each EngineDeltaOp::CopyBlock(idx) becomes a single-
-        // block CopyRun; the engine may already coalesce runs, but we
-        // keep A2.3 simple and emit one CopyRun per CopyBlock. Each
-        // EngineDeltaOp::Literal becomes 1..N DeltaOp::Literal records,
-        // depending on whether the compressed blob fits in a single
-        // DEFLATED_DATA token or needs chunking.
-        let mut wire_ops: Vec<DeltaOp> =
-            Vec::with_capacity(plan.ops.len() + compressed_blobs.len());
-        let mut blob_idx: usize = 0;
-        for op in &plan.ops {
-            match op {
-                EngineDeltaOp::Literal(_) => {
-                    let blob = &compressed_blobs[blob_idx];
-                    blob_idx += 1;
-                    if blob.is_empty() {
-                        // Skip zero-length blobs: `compress_zstd_literal_stream`
-                        // already drops empty inputs, but defensively guard
-                        // the non-zstd branch where empty payloads could
-                        // surface. DEFLATED_DATA length=0 is a protocol
-                        // error (decode_delta_op rejects it).
+            // --- receiver half ---
+            if recv_phase > max_phase {
+                break;
+            }
+            let message = self
+                .next_tree_message(
+                    &mut inbound,
+                    bridge,
+                    decode_sender_message,
+                    "tree sender message",
+                )
+                .await?;
+            match message {
+                SenderMessage::ExtraList { dir_ndx } => {
+                    if flist_eof {
+                        return Err(AerorsyncError::invalid_frame(
+                            "extra file list after NDX_FLIST_EOF",
+                        ));
+                    }
+                    io_error |= self
+                        .receive_tree_segment(
+                            &mut inbound,
+                            Some(dir_ndx),
+                            &mut tree,
+                            &mut lists,
+                            bridge,
+                        )
+                        .await?;
+                }
+                SenderMessage::FlistEof => {
+                    flist_eof = true;
+                    self.phase = AerorsyncSessionPhase::FileListReceived;
+                }
+                SenderMessage::Done => {
+                    if recv_freed < tree.segments().len() {
+                        recv_freed += 1;
+                        if recv_freed < tree.segments().len() {
+                            continue;
+                        }
+                    }
+                    recv_phase += 1;
+                }
+                SenderMessage::Item {
+                    header,
+                    sum_head: None,
+                } => {
+                    tracing::debug!(
+                        "drive_download_tree: attribute-only item ndx={} iflags=0x{:04X}",
+                        header.ndx,
+                        header.iflags
+                    );
+                    // The answer to an xattr request for an up-to-date
+                    // entry: its metadata can be applied now.
+                    if !header.xattrs.is_empty() {
+                        let mut entry = tree_entry_for(&tree, header.ndx)?.1.clone();
+                        store_xattr_answer(&mut entry, &header)?;
+                        sink.apply_attrs(&entry.path, &entry).await?;
+                    }
+                }
+                SenderMessage::Item {
+                    header,
+                    sum_head: Some(head),
+                } => {
+                    let wanted = match pending.remove(&header.ndx) {
+                        Some(bytes) => {
+                            in_flight -= bytes;
+                            true
+                        }
+                        None if self.reading_batch => false,
+                        None => {
+                            return Err(AerorsyncError::invalid_frame(format!(
+                                "remote sender delivered unrequested ndx {}",
+                                header.ndx
+                            )))
+                        }
+                    };
+                    let mut entry = tree_entry_for(&tree, header.ndx)?.1.clone();
+                    store_xattr_answer(&mut entry, &header)?;
+                    self.phase = AerorsyncSessionPhase::DeltaReceiving;
+                    let delta = self.read_tree_delta(&mut inbound, bridge).await?;
+                    // The basis is read first: a `zlib` decoder replays
+                    // matched blocks while it decodes.
+                    let baseline = if append != AppendMode::Off || head.block_length != 0 {
+                        sink.read_baseline(&entry.path).await?.unwrap_or_default()
+                    } else {
+                        Vec::new()
+                    };
+                    let basis = MatchBasis {
+                        data: &baseline,
+                        block_len: head.block_length as usize,
+                    };
+                    let engine_ops =
+                        wire_ops_to_engine_ops(&delta.ops, &mut decoder, head.count, &basis)?;
+                    decoder.end_file();
+                    if !wanted {
+                        // `receiver.c`: "Skipping batched update". The
+                        // literals were decoded to keep the compressor
+                        // in step with the recording.
+                        tracing::info!("skipping batched update for {:?}", entry.path);
                         continue;
                     }
-                    for chunk in blob.chunks(MAX_DELTA_LITERAL_LEN) {
-                        wire_ops.push(DeltaOp::Literal {
-                            compressed_payload: chunk.to_vec(),
-                        });
+                    let mut kept = 0usize;
+                    let mut hash_from = 0usize;
+                    let data = if append != AppendMode::Off {
+                        let mut data = baseline;
+                        data.truncate(head.basis_length() as usize);
+                        kept = data.len();
+                        hash_from = checksum_start(append, Some(kept as u64)) as usize;
+                        data.extend_from_slice(&literal_only_data(engine_ops)?);
+                        data
+                    } else if head.block_length == 0 {
+                        literal_only_data(engine_ops)?
+                    } else {
+                        adapter
+                            .apply_delta(&baseline, &engine_ops, head.block_length as usize)
+                            .map_err(|e| {
+                                AerorsyncError::invalid_frame(format!(
+                                    "apply_delta {:?}: {e}",
+                                    entry.path
+                                ))
+                            })?
+                    };
+                    if self.checksum_algo.digest(&data[hash_from..]) != delta.file_checksum {
+                        return Err(AerorsyncError::invalid_frame(format!(
+                            "file checksum mismatch after reconstructing {:?}",
+                            entry.path
+                        )));
                     }
+                    report.files_transferred += 1;
+                    report.bytes_transferred += (data.len() - kept) as u64;
+                    sink.commit_file(&entry.path, &entry, data).await?;
+                    self.phase = AerorsyncSessionPhase::DeltaReceived;
                 }
-                EngineDeltaOp::CopyBlock(idx) => {
-                    wire_ops.push(DeltaOp::CopyRun {
-                        start_token_index: *idx as i32,
-                        run_length: 1,
-                    });
+                SenderMessage::Unexpected(ndx) => {
+                    return Err(AerorsyncError::invalid_frame(format!(
+                        "unexpected ndx {ndx} from the remote sender"
+                    )));
                 }
             }
         }
 
-        // S8j: real xxh128 (XXH3-128) over `source_data`. Rsync 3.2.7
-        // verifies this trailer server-side when `xxh128` is negotiated
-        // in `checksum_algos` (see `perform_preamble_exchange` call
-        // sites below). Byte layout matches `checksum.c::hash_struct`'s
-        // `SIVAL64(buf, 0, lo); SIVAL64(buf, 8, hi)`: lower 64 bits LE
-        // first, upper 64 bits LE second.
-        let file_checksum = compute_xxh128_wire(source_data);
-
-        let report = DeltaStreamReport {
-            ops: wire_ops.clone(),
-            file_checksum,
-        };
-        let delta_bytes = encode_delta_stream(&report);
-
-        // B.2 Step 4: the sender MUST echo back `write_ndx + write_shortint(iflags) +
-        // write_sum_head` before the delta tokens, mirroring
-        // `sender.c::send_files` (line 411-412). Without this echo the
-        // receiver expects sum_head bytes where it gets delta tokens and
-        // aborts with rsync exit 22 ("Error allocating core memory
-        // buffers": sum.count is read as a huge int from delta bytes).
-        //
-        // Echo values come from the receiver's signature header that
-        // `read_signature_header` stashed in `last_received_ndx`,
-        // `last_iflags`, and `received_sum_head`.
-        let echo_head = *self.received_sum_head.as_ref().ok_or_else(|| {
-            AerorsyncError::invalid_frame(
-                "send_delta_phase: missing received sum_head: signature phase didn't run",
-            )
-        })?;
-        let mut payload = Vec::with_capacity(8 + delta_bytes.len());
-        payload.extend_from_slice(&encode_ndx(
-            self.last_received_ndx,
-            &mut self.outbound_ndx_state,
-        ));
-        payload.extend_from_slice(&encode_item_flags(self.last_iflags));
-        payload.extend_from_slice(&encode_sum_head(&echo_head));
-        payload.extend_from_slice(&delta_bytes);
-
-        // PreCommit → PostCommit boundary: flip BEFORE writing the first
-        // byte of delta material. Once the server starts receiving the
-        // delta stream, we no longer can transparently fall back.
-        self.committed = true;
-        self.emitted_delta_ops = wire_ops;
-        self.write_data_frame(&payload).await?;
+        for (leader, path) in &hard_links {
+            self.check_cancel("tree hard link")?;
+            sink.hard_link(leader, path).await?;
+        }
+        Ok((report, inbound))
+    }
 
-        self.phase = AerorsyncSessionPhase::DeltaSent;
+    fn require_inc_recurse(&self) -> Result<(), AerorsyncError> {
+        if self.compat_flags & CF_INC_RECURSE == 0 {
+            return Err(AerorsyncError::unsupported_version(format!(
+                "remote did not negotiate incremental recursion (compat flags 0x{:X})",
+                self.compat_flags
+            )));
+        }
         Ok(())
     }
 
-    /// P3-T01 W1.2 / W1.3: streaming-source twin of
-    /// [`send_delta_phase_single_file`]. The engine plan is produced
-    /// chunk-by-chunk (`RollingDeltaPlanProducer` for
-    /// `block_size != 0`, fixed-slab chunking for `block_size == 0`)
-    /// and the file-level xxh128 checksum is computed by streaming
-    /// (`Xxh3Default` instead of `xxh3_128(&[u8])`).
-    ///
-    /// ## Wire-byte parity vs. the bulk path
-    ///
-    /// - For `block_size != 0`: byte-identical with
-    ///   [`send_delta_phase_single_file`] for any source length
-    ///   (pinned by `streaming_send_matches_bulk_send_*`).
-    /// - For `block_size == 0` and source `<= STREAMING_READ_CHUNK_BYTES`:
-    ///   byte-identical (single literal in both paths).
-    /// - For `block_size == 0` and source `> STREAMING_READ_CHUNK_BYTES`:
-    ///   wire bytes **diverge** from the bulk path: the streaming
-    ///   path emits `ceil(source_len / STREAMING_READ_CHUNK_BYTES)`
-    ///   engine literals through the session-wide zstd `CCtx`, where
-    ///   the bulk path emits one. The receiver's session-wide
-    ///   `ZSTD_DCtx` concatenates both shapes to the same plaintext
-    ///   per stock rsync's `send_zstd_token` semantics, so the
-    ///   divergence is *protocol-equivalent*. The chunked emission is
-    ///   what allows W1.3 to lift the 256 MiB upload-side cap without
-    ///   requesting a `Vec<u8>` of `source_len` bytes: the
-    ///   contiguous-allocation failure mode that gated the bulk path
-    ///   on multi-GiB uploads with no baseline.
-    ///
-    /// ## Memory bound (W1.3)
-    ///
-    /// Resident memory during the function is bounded by:
-    ///
-    /// - `STREAMING_READ_CHUNK_BYTES` for the read buffer
-    /// - `STREAMING_READ_CHUNK_BYTES` for the in-flight literal slab
-    ///   (`chunk_acc` for `block_size == 0`, the producer's window for
-    ///   `block_size != 0`)
-    /// - the accumulated op vector, whose size is proportional to
-    ///   `source_len` (true multi-frame streaming of zstd + wire is
-    ///   post-P3-T01 scope).
-    ///
-    /// `source_len` MUST equal the byte count drained from
-    /// `source_reader`; mismatches abort the upload with
-    /// `InvalidFrame` (the file changed mid-flight or the caller
-    /// declared the wrong size).
-    async fn send_delta_phase_streaming<R>(
+    /// Write list `index` of `tree`: the `NDX_FLIST_OFFSET - dir_ndx`
+    /// header for extra lists, the entries, the terminator, and
+    /// `NDX_FLIST_EOF` after the last list. Regular files are re-read
+    /// from `source` when the session's checksum is not the xxh128 the
+    /// scan computed.
+    async fn send_tree_segment(
         &mut self,
-        mut source_reader: R,
-        source_len: u64,
-        _adapter: &dyn DeltaEngineAdapter,
-    ) -> Result<(), AerorsyncError>
-    where
-        R: AsyncRead + Unpin + Send,
-    {
-        self.phase = AerorsyncSessionPhase::DeltaSending;
-
-        // Identical sig-derivation as the bulk path. `wire_sigs_to_engine`
-        // depends only on `received_signatures` + `received_sum_head`,
-        // which the preceding signature phase already populated.
-        let engine_sigs = self.wire_sigs_to_engine()?;
-        let block_size = self
-            .received_sum_head
-            .as_ref()
-            .map(|h| h.block_length as usize)
-            .unwrap_or(0);
-
-        // Drive the producer + xxh3 hasher chunk-by-chunk. The producer
-        // owns the rolling window; the hasher accumulates a streaming
-        // xxh3_128 of the source. Both are populated from the same
-        // chunk slice so the wire trailer matches what
-        // `compute_xxh128_wire(source_data)` would have produced bulk.
-        let mut hasher = Xxh3Default::new();
-        let mut ops: Vec<EngineDeltaOp> = Vec::new();
-        let mut total_source_bytes: u64 = 0;
-        let mut buf = vec![0u8; STREAMING_READ_CHUNK_BYTES];
-
-        if block_size == 0 {
-            // Whole-file case: the receiver has no baseline to diff
-            // against (`block_size == 0` is rsync's "send everything as
-            // one literal" sentinel). The producer would silently emit
-            // zero ops here, so we materialise the literal explicitly.
-            //
-            // P3-T01 W1.3: emit one `EngineDeltaOp::Literal` per
-            // `STREAMING_READ_CHUNK_BYTES`-bounded slab instead of one
-            // big literal covering `source_len`. Reasons:
-            //
-            //   1. Avoids a single contiguous `Vec<u8>` allocation of
-            //      `source_len` bytes. On a 4 GiB upload with no
-            //      baseline the bulk path would request a 4 GiB
-            //      contiguous reservation from the allocator, which
-            //      fails on fragmented heaps even when total free RAM
-            //      is plentiful.
-            //   2. Keeps the per-op working set aligned with the read
-            //      chunk size, so the producer-driven (`block_size != 0`)
-            //      and whole-file (`block_size == 0`) branches share
-            //      the same bound on op-level allocation.
-            //   3. Wire-equivalent for sources `<= STREAMING_READ_CHUNK_BYTES`
-            //      (single literal, byte-identical to bulk). Above that
-            //      threshold the wire bytes diverge from bulk because
-            //      the session-wide zstd `CCtx` flushes between literals;
-            //      the receiver's session-wide `ZSTD_DCtx` concatenates
-            //      the payloads transparently per stock rsync's
-            //      `send_zstd_token` semantics, so the divergence is
-            //      *protocol-equivalent* even though it is not
-            //      byte-identical. Pinned by
-            //      `streaming_send_matches_bulk_send_whole_file_no_baseline`
-            //      (small source: byte-identical) and
-            //      `streaming_send_block_size_zero_chunks_large_source`
-            //      (large source: chunked, multiple engine literals).
-            //
-            // Memory bound: O(STREAMING_READ_CHUNK_BYTES) for `chunk_acc`
-            // plus the read buffer plus one in-flight literal in `ops`
-            // until zstd compression. The full op vector still grows
-            // proportionally to `source_len`; lifting that requires
-            // streaming the zstd encoder + wire emission, scoped
-            // post-P3-T01 (see W1.2 docstring).
-            let mut chunk_acc: Vec<u8> = Vec::new();
-            loop {
-                let n = source_reader.read(&mut buf).await.map_err(|e| {
-                    AerorsyncError::transport(format!(
-                        "send_delta_phase_streaming: source read failed: {e}"
-                    ))
-                })?;
-                if n == 0 {
-                    break;
-                }
-                hasher.update(&buf[..n]);
-                total_source_bytes += n as u64;
+        index: usize,
+        tree: &TreeFileList,
+        source: &mut dyn TreeSource,
+        last: bool,
+        tables: &mut FlistMetaTables,
+    ) -> Result<(), AerorsyncError> {
+        let segment = &tree.segments()[index];
+        let opts = self.build_flist_options();
+        let mut payload = Vec::new();
+        if let Some(dir_ndx) = segment.parent_dir_ndx {
+            payload.extend_from_slice(&encode_ndx(
+                NDX_FLIST_OFFSET - dir_ndx,
+                &mut self.outbound_ndx_state,
+            ));
+        }
+        for (pos, entry) in segment.entries.iter().enumerate() {
+            let rehashed;
+            let entry = if self.checksum_algo != ChecksumAlgo::Xxh128
+                && is_regular_mode(entry.mode)
+                && !entry.checksum.is_empty()
+            {
+                self.check_cancel("tree file checksum")?;
+                let data = source.read_file(&entry.path).await?;
+                rehashed = self.with_session_checksum(entry.clone(), Some(&data));
+                &rehashed
+            } else {
+                entry
+            };
+            let entry_opts = FileListDecodeOptions {
+                scope: Some(FlistScope {
+                    ndx_start: segment.ndx_start,
+                    entries: &segment.entries[..pos],
+                    tables,
+                }),
+                ..opts.clone()
+            };
+            payload.extend_from_slice(&encode_file_list_entry(entry, &entry_opts));
+            tables.absorb(entry);
+        }
+        payload.extend_from_slice(&encode_file_list_terminator(&opts));
+        if last {
+            payload.extend_from_slice(&encode_ndx(NDX_FLIST_EOF, &mut self.outbound_ndx_state));
+        }
+        self.write_data_frames(&payload).await
+    }
 
-                let mut to_consume: &[u8] = &buf[..n];
-                while !to_consume.is_empty() {
-                    if chunk_acc.capacity() == 0 {
-                        chunk_acc.reserve_exact(STREAMING_READ_CHUNK_BYTES);
+    /// Decode one file list off `inbound` (pulling frames as needed) and
+    /// append it to `tree`. `lists` carries the state that spans lists.
+    /// Returns whether the sender flagged an I/O error while building
+    /// the list.
+    async fn receive_tree_segment(
+        &mut self,
+        inbound: &mut Vec<u8>,
+        parent_dir_ndx: Option<i32>,
+        tree: &mut TreeFileList,
+        lists: &mut ReceivedListState,
+        bridge: &mut dyn EventSink,
+    ) -> Result<bool, AerorsyncError> {
+        let opts = self.build_flist_options();
+        let ndx_start = tree.next_ndx_start();
+        let mut entries: Vec<FileListEntry> = Vec::new();
+        loop {
+            self.check_cancel("receive_tree_segment")?;
+            if !inbound.is_empty() {
+                let entry_opts = FileListDecodeOptions {
+                    scope: Some(FlistScope {
+                        ndx_start,
+                        entries: &entries,
+                        tables: &lists.tables,
+                    }),
+                    ..opts.clone()
+                };
+                match decode_file_list_entry_after(inbound, &entry_opts, lists.previous.as_ref()) {
+                    Ok((FileListDecodeOutcome::Entry(entry), consumed)) => {
+                        inbound.drain(..consumed);
+                        lists.tables.absorb(&entry);
+                        if entry.hardlink == Some(HardlinkRef::First) {
+                            // `F_HL_GNUM`: the sender numbers a group by
+                            // its first entry's position in wire order.
+                            let gnum = ndx_start + entries.len() as i32;
+                            lists.hlink_leaders.insert(gnum, entry.path.clone());
+                        }
+                        lists.previous = Some(entry.clone());
+                        entries.push(entry);
+                        continue;
                     }
-                    let space_left = STREAMING_READ_CHUNK_BYTES.saturating_sub(chunk_acc.len());
-                    let take = to_consume.len().min(space_left);
-                    chunk_acc.extend_from_slice(&to_consume[..take]);
-                    to_consume = &to_consume[take..];
-                    if chunk_acc.len() >= STREAMING_READ_CHUNK_BYTES {
-                        ops.push(EngineDeltaOp::Literal(std::mem::take(&mut chunk_acc)));
+                    Ok((FileListDecodeOutcome::EndOfList { io_error }, consumed)) => {
+                        inbound.drain(..consumed);
+                        if io_error != 0 {
+                            tracing::warn!(
+                                "remote sender reported io_error {io_error} while listing files"
+                            );
+                        }
+                        tree.push_segment(parent_dir_ndx, entries)?;
+                        return Ok(io_error != 0);
+                    }
+                    Err(e) if needs_more_bytes(&e) => {}
+                    Err(other) => {
+                        return Err(map_realwire_error(other, "tree file list entry"));
                     }
                 }
             }
-            if !chunk_acc.is_empty() {
-                ops.push(EngineDeltaOp::Literal(chunk_acc));
-            }
-        } else {
-            let mut producer = RollingDeltaPlanProducer::new(block_size, engine_sigs);
-            loop {
-                let n = source_reader.read(&mut buf).await.map_err(|e| {
-                    AerorsyncError::transport(format!(
-                        "send_delta_phase_streaming: source read failed: {e}"
-                    ))
-                })?;
-                if n == 0 {
-                    break;
-                }
-                hasher.update(&buf[..n]);
-                producer.drive_chunk(&buf[..n], &mut ops);
-                total_source_bytes += n as u64;
-            }
-            producer.finalize(&mut ops);
+            let payload = self.next_data_frame(bridge).await?;
+            inbound.extend_from_slice(&payload);
         }
+    }
 
-        if total_source_bytes != source_len {
-            return Err(AerorsyncError::invalid_frame(format!(
-                "send_delta_phase_streaming: declared source_len {source_len} != bytes read {total_source_bytes}"
-            )));
+    /// Generator step for one received entry: create directories, skip
+    /// files whose local copy matches the list's size and checksum, and
+    /// request the rest. Returns the request size when one was written.
+    async fn generate_tree_entry(
+        &mut self,
+        ndx: i32,
+        entry: &FileListEntry,
+        sink: &mut dyn TreeSink,
+        adapter: &dyn DeltaEngineAdapter,
+        report: &mut TreeTransferReport,
+    ) -> Result<Option<usize>, AerorsyncError> {
+        self.check_cancel("generate_tree_entry")?;
+        if is_dir_mode(entry.mode) {
+            report.dirs_total += 1;
+            sink.create_dir(&entry.path, entry).await?;
+            self.request_missing_xattrs(ndx, entry).await?;
+            return Ok(None);
         }
-
-        // From here on the encoding/wire-emission path is byte-for-byte
-        // identical to `send_delta_phase_single_file`. Any divergence
-        // would break the `streaming_send_matches_bulk_send_*` parity
-        // pin below: kept in lockstep on purpose.
-        let pending_raw: Vec<&[u8]> = ops
-            .iter()
-            .filter_map(|op| match op {
-                EngineDeltaOp::Literal(raw) => Some(raw.as_slice()),
-                EngineDeltaOp::CopyBlock(_) => None,
-            })
-            .collect();
-
-        let zstd_on = self.zstd_negotiated();
-        let compressed_blobs: Vec<Vec<u8>> = if zstd_on && !pending_raw.is_empty() {
-            compress_zstd_literal_stream(&pending_raw)
-                .map_err(|e| map_realwire_error(e, "zstd compress literal stream"))?
-        } else {
-            pending_raw.iter().map(|p| p.to_vec()).collect()
-        };
-
-        let mut wire_ops: Vec<DeltaOp> = Vec::with_capacity(ops.len() + compressed_blobs.len());
-        let mut blob_idx: usize = 0;
-        for op in &ops {
-            match op {
-                EngineDeltaOp::Literal(_) => {
-                    let blob = &compressed_blobs[blob_idx];
-                    blob_idx += 1;
-                    if blob.is_empty() {
-                        continue;
-                    }
-                    for chunk in blob.chunks(MAX_DELTA_LITERAL_LEN) {
-                        wire_ops.push(DeltaOp::Literal {
-                            compressed_payload: chunk.to_vec(),
-                        });
-                    }
-                }
-                EngineDeltaOp::CopyBlock(idx) => {
-                    wire_ops.push(DeltaOp::CopyRun {
-                        start_token_index: *idx as i32,
-                        run_length: 1,
-                    });
+        if is_symlink_mode(entry.mode) {
+            sink.create_symlink(&entry.path, entry).await?;
+            return Ok(None);
+        }
+        report.files_total += 1;
+        let append = self.transfer_options.append != AppendMode::Off;
+        let baseline = sink.read_baseline(&entry.path).await?;
+        if let Some(local) = &baseline {
+            let up_to_date = if append && local.len() as i64 >= entry.size {
+                true
+            } else if local.len() as i64 != entry.size {
+                false
+            } else if self.session_flags.always_checksum {
+                self.checksum_algo.digest(local) == entry.checksum
+            } else {
+                // `quick_check_ok` without `-c`: size and mtime.
+                sink.baseline_mtime(&entry.path).await? == Some(entry.mtime)
+            };
+            if up_to_date {
+                if !self.request_missing_xattrs(ndx, entry).await? {
+                    sink.apply_attrs(&entry.path, entry).await?;
                 }
+                return Ok(None);
             }
         }
-
-        // Streaming xxh3-128 → 16-byte wire trailer.
-        // Layout pinned by `xxh128_wire_bytes_match_SIVAL64_pair`:
-        // `out[0..8] = lo.to_le_bytes(); out[8..16] = hi.to_le_bytes()`.
-        let file_checksum = {
-            let hash = hasher.digest128();
-            let lo = hash as u64;
-            let hi = (hash >> 64) as u64;
-            let mut out = Vec::with_capacity(16);
-            out.extend_from_slice(&lo.to_le_bytes());
-            out.extend_from_slice(&hi.to_le_bytes());
-            out
+        let (head, mut blocks) = match baseline.as_deref() {
+            Some(local) if !local.is_empty() => build_wire_signatures(local, adapter),
+            // `write_sum_head(f, NULL)`: no baseline, whole-file transfer.
+            _ => (
+                SumHead {
+                    count: 0,
+                    block_length: 0,
+                    checksum_length: 0,
+                    remainder_length: 0,
+                },
+                Vec::new(),
+            ),
         };
-
-        let report = DeltaStreamReport {
-            ops: wire_ops.clone(),
-            file_checksum,
+        if append {
+            // Head only: the sender just needs to know where we end.
+            blocks.clear();
+        }
+        let xattrs = xattr_request(entry);
+        let iflags = if xattrs.is_empty() {
+            A2_2_DOWNLOAD_IFLAGS
+        } else {
+            A2_2_DOWNLOAD_IFLAGS | ITEM_REPORT_XATTR
         };
-        let delta_bytes = encode_delta_stream(&report);
-
-        let echo_head = *self.received_sum_head.as_ref().ok_or_else(|| {
-            AerorsyncError::invalid_frame(
-                "send_delta_phase_streaming: missing received sum_head: signature phase didn't run",
-            )
-        })?;
-        let mut payload = Vec::with_capacity(8 + delta_bytes.len());
-        payload.extend_from_slice(&encode_ndx(
-            self.last_received_ndx,
-            &mut self.outbound_ndx_state,
-        ));
-        payload.extend_from_slice(&encode_item_flags(self.last_iflags));
-        payload.extend_from_slice(&encode_sum_head(&echo_head));
-        payload.extend_from_slice(&delta_bytes);
+        let mut payload = ItemHeader {
+            ndx,
+            iflags,
+            basis_type: None,
+            xname: None,
+            xattrs,
+        }
+        .encode(&mut self.outbound_ndx_state);
+        payload.extend_from_slice(&encode_sum_head(&head));
+        for block in &blocks {
+            payload.extend_from_slice(&encode_sum_block(block));
+        }
+        self.write_data_frames(&payload).await?;
+        Ok(Some(payload.len()))
+    }
 
-        self.committed = true;
-        self.emitted_delta_ops = wire_ops;
-        self.write_data_frame(&payload).await?;
+    /// Ask the sender for the abbreviated xattr values of an entry that
+    /// needs no data, with an attribute-only item; its echo carries
+    /// them. Returns whether a request went out, in which case the
+    /// entry's metadata is applied when the echo arrives.
+    async fn request_missing_xattrs(
+        &mut self,
+        ndx: i32,
+        entry: &FileListEntry,
+    ) -> Result<bool, AerorsyncError> {
+        let xattrs = xattr_request(entry);
+        if xattrs.is_empty() {
+            return Ok(false);
+        }
+        let payload = ItemHeader {
+            ndx,
+            iflags: ITEM_REPORT_XATTR,
+            basis_type: None,
+            xname: None,
+            xattrs,
+        }
+        .encode(&mut self.outbound_ndx_state);
+        self.write_data_frames(&payload).await?;
+        Ok(true)
+    }
 
-        self.phase = AerorsyncSessionPhase::DeltaSent;
+    /// `--delete` for list `index`, run when the generator reaches it
+    /// (delete-during, `generator.c::delete_in_dir`): whatever the local
+    /// directory holds beyond the list's names goes away. Skipped once
+    /// the sender has reported an I/O error, since its list may then be
+    /// incomplete.
+    async fn delete_for_segment(
+        &mut self,
+        tree: &TreeFileList,
+        index: usize,
+        io_error: bool,
+        sink: &mut dyn TreeSink,
+        report: &mut TreeTransferReport,
+    ) -> Result<(), AerorsyncError> {
+        let dir = tree.segment_dir(index).ok_or_else(|| {
+            AerorsyncError::invalid_frame(format!("file list {index} has no directory"))
+        })?;
+        if io_error {
+            tracing::warn!("IO error encountered -- skipping file deletion in {dir:?}");
+            return Ok(());
+        }
+        self.check_cancel("delete_for_segment")?;
+        let keep = tree.segments()[index].child_names();
+        report.entries_deleted += sink.delete_extraneous(dir, &keep).await?;
         Ok(())
     }
 
-    /// Download path: drain delta stream bytes from MSG_DATA frames,
-    /// decode ops + trailer, decompress literals, convert to engine ops,
-    /// and apply via `adapter.apply_delta`. The reconstructed bytes are
-    /// stashed in `self.reconstructed` for the A4 adapter to flush to
-    /// disk via temp+rename.
-    ///
-    /// `committed` stays `false` throughout: A2.3 download never writes
-    /// to disk. A4 will flip committed when it opens the temp file.
-    ///
-    /// Phase transitions: `SumBlocksSent → DeltaReceiving → DeltaReceived`.
-    async fn receive_delta_phase_single_file(
+    /// Read one file's delta tokens and checksum trailer token by token,
+    /// so a multi-frame delta is parsed once instead of re-parsed every
+    /// time another frame arrives.
+    async fn read_tree_delta(
         &mut self,
-        destination_data: &[u8],
-        adapter: &dyn DeltaEngineAdapter,
+        inbound: &mut Vec<u8>,
         bridge: &mut dyn EventSink,
-    ) -> Result<(), AerorsyncError> {
-        self.phase = AerorsyncSessionPhase::DeltaReceiving;
-
-        // Accumulate bytes until `decode_delta_stream` succeeds.
-        let mut buf: Vec<u8> = Vec::new();
-        let sum_head_count = self.sent_sum_head.as_ref().map(|h| h.count);
+    ) -> Result<DeltaStreamReport, AerorsyncError> {
+        let mut state = DeltaStreamState::with_format(self.token_format());
+        let mut ops: Vec<DeltaOp> = Vec::new();
+        let mut ended = false;
+        let checksum_len = self.checksum_algo.digest_len();
         loop {
-            self.check_cancel("receive_delta_phase")?;
-            if !buf.is_empty() {
-                match decode_delta_stream(&buf, A2_3_FILE_CHECKSUM_LEN, sum_head_count) {
-                    Ok((report, consumed)) => {
-                        buf.drain(..consumed);
-                        self.received_file_checksum = Some(report.file_checksum.clone());
-                        self.install_reconstructed_from_wire(
-                            destination_data,
-                            adapter,
-                            report.ops,
-                        )?;
-                        self.phase = AerorsyncSessionPhase::DeltaReceived;
-                        return Ok(());
+            self.check_cancel("read_tree_delta")?;
+            while !inbound.is_empty() {
+                if ended {
+                    match decode_file_checksum(inbound, checksum_len) {
+                        Ok((file_checksum, consumed)) => {
+                            inbound.drain(..consumed);
+                            return Ok(DeltaStreamReport { ops, file_checksum });
+                        }
+                        Err(e) if needs_more_bytes(&e) => break,
+                        Err(other) => return Err(map_realwire_error(other, "tree file checksum")),
                     }
-                    Err(RealWireError::DeltaTokenTruncated { .. }) => {
-                        // need more bytes
+                }
+                match decode_delta_op(inbound, &mut state) {
+                    Ok((DeltaOpOutcome::Op(op), consumed)) => {
+                        inbound.drain(..consumed);
+                        ops.push(op);
                     }
-                    Err(other) => {
-                        return Err(map_realwire_error(other, "delta stream"));
+                    Ok((DeltaOpOutcome::EndFlag, consumed)) => {
+                        inbound.drain(..consumed);
+                        ended = true;
                     }
+                    Err(e) if needs_more_bytes(&e) => break,
+                    Err(other) => return Err(map_realwire_error(other, "tree delta token")),
                 }
             }
             let payload = self.next_data_frame(bridge).await?;
-            buf.extend_from_slice(&payload);
-        }
-    }
-
-    fn install_reconstructed_from_wire(
-        &mut self,
-        destination_data: &[u8],
-        adapter: &dyn DeltaEngineAdapter,
-        wire_ops: Vec<DeltaOp>,
-    ) -> Result<(), AerorsyncError> {
-        let zstd_on = self.zstd_negotiated();
-        let engine_ops = self.delta_wire_to_engine_ops(&wire_ops, zstd_on)?;
-        let block_size = self
-            .sent_sum_head
-            .as_ref()
-            .map(|h| h.block_length as usize)
-            .unwrap_or(0);
-        if block_size == 0 {
-            return Err(AerorsyncError::invalid_frame(
-                "receive_delta_phase: block_size is zero (missing local sum_head)",
-            ));
+            inbound.extend_from_slice(&payload);
         }
-        let reconstructed = adapter
-            .apply_delta(destination_data, &engine_ops, block_size)
-            .map_err(|e| AerorsyncError::invalid_frame(format!("apply_delta: {e}")))?;
-        self.reconstructed = Some(reconstructed);
-        Ok(())
     }
 
-    /// P3-T01 W2.4: streaming sibling of [`receive_delta_phase_single_file`].
-    /// Identical wire-handling loop (drain MSG_DATA frames, decode delta
-    /// stream, decompress literals, convert to engine ops); the only
-    /// difference is the install step calls
-    /// [`install_reconstructed_from_wire_streaming`] to apply the ops via
-    /// `apply_delta_streaming(baseline, ops, block_size, writer)` instead
-    /// of stashing a `Vec<u8>` in `self.reconstructed`.
-    ///
-    /// `committed` stays `false` throughout: the W2.5 caller flips its
-    /// own `local_committed` flag on the first byte successfully written
-    /// to the `StreamingAtomicWriter` temp.
-    async fn receive_delta_phase_streaming(
+    /// Decode the next complete message from `inbound` with `decode`,
+    /// pulling MSG_DATA frames until one fits. The inbound ndx state is
+    /// only committed when a message decodes completely.
+    async fn next_tree_message<M>(
         &mut self,
-        baseline: &mut dyn BaselineSource,
-        writer: &mut (dyn AsyncWrite + Send + Unpin),
-        adapter: &dyn DeltaEngineAdapter,
+        inbound: &mut Vec<u8>,
         bridge: &mut dyn EventSink,
-    ) -> Result<(), AerorsyncError> {
-        self.phase = AerorsyncSessionPhase::DeltaReceiving;
-
-        let mut buf: Vec<u8> = Vec::new();
-        let sum_head_count = self.sent_sum_head.as_ref().map(|h| h.count);
+        decode: TreeMessageDecoder<M>,
+        context: &'static str,
+    ) -> Result<M, AerorsyncError> {
         loop {
-            self.check_cancel("receive_delta_phase")?;
-            if !buf.is_empty() {
-                match decode_delta_stream(&buf, A2_3_FILE_CHECKSUM_LEN, sum_head_count) {
-                    Ok((report, consumed)) => {
-                        buf.drain(..consumed);
-                        self.received_file_checksum = Some(report.file_checksum.clone());
-                        self.install_reconstructed_from_wire_streaming(
-                            baseline, writer, adapter, report.ops,
-                        )
-                        .await?;
-                        self.phase = AerorsyncSessionPhase::DeltaReceived;
-                        return Ok(());
-                    }
-                    Err(RealWireError::DeltaTokenTruncated { .. }) => {
-                        // need more bytes
-                    }
-                    Err(other) => {
-                        return Err(map_realwire_error(other, "delta stream"));
+            self.check_cancel(context)?;
+            if !inbound.is_empty() {
+                let mut state = self.inbound_ndx_state;
+                match decode(inbound, &mut state) {
+                    Ok((message, consumed)) => {
+                        inbound.drain(..consumed);
+                        self.inbound_ndx_state = state;
+                        return Ok(message);
                     }
+                    Err(e) if needs_more_bytes(&e) => {}
+                    Err(other) => return Err(map_realwire_error(other, context)),
                 }
             }
             let payload = self.next_data_frame(bridge).await?;
-            buf.extend_from_slice(&payload);
+            inbound.extend_from_slice(&payload);
         }
     }
 
-    /// P3-T01 W2.4/W2.5: streaming sibling of
-    /// [`install_reconstructed_from_wire`]. Decodes the wire ops to
-    /// engine ops (same conversion as the bulk path) and dispatches to
-    /// [`apply_delta_streaming`] against the caller-supplied baseline +
-    /// caller-supplied writer.
-    ///
-    /// Errors:
-    /// - `InvalidFrame` if `block_size == 0` (no `sent_sum_head` from the
-    ///   signature phase: a wire-level invariant violation, identical
-    ///   to the bulk path).
-    /// - `InvalidFrame` from `apply_delta_streaming` (baseline read errors,
-    ///   writer poll_write errors, oversized block_size).
-    async fn install_reconstructed_from_wire_streaming(
-        &mut self,
-        baseline: &mut dyn BaselineSource,
-        writer: &mut (dyn AsyncWrite + Send + Unpin),
-        adapter: &dyn DeltaEngineAdapter,
-        wire_ops: Vec<DeltaOp>,
-    ) -> Result<(), AerorsyncError> {
-        let zstd_on = self.zstd_negotiated();
-        let engine_ops = self.delta_wire_to_engine_ops(&wire_ops, zstd_on)?;
-        let _ = adapter; // adapter is unused on the streaming path -
-                         // engine ops carry everything apply_delta_streaming needs.
-                         // Kept in the signature for parity with the bulk twin and
-                         // to leave room for future adapter-driven dispatch.
-        let block_size = self
-            .sent_sum_head
-            .as_ref()
-            .map(|h| h.block_length as usize)
-            .unwrap_or(0);
-        if block_size == 0 {
-            return Err(AerorsyncError::invalid_frame(
-                "receive_delta_phase: block_size is zero (missing local sum_head)",
-            ));
+    /// `write_data_frame` for payloads that may exceed the 24-bit frame
+    /// length (large deltas, big directories).
+    async fn write_data_frames(&mut self, payload: &[u8]) -> Result<(), AerorsyncError> {
+        for chunk in payload.chunks(MAX_DATA_FRAME_LEN) {
+            self.write_data_frame(chunk).await?;
         }
-        apply_delta_streaming(baseline, engine_ops, block_size, writer)
-            .await
-            .map_err(|e| AerorsyncError::invalid_frame(format!("apply_delta_streaming: {e}")))?;
-        // self.reconstructed intentionally stays None: the bytes flowed
-        // through the writer and reading them back from RAM would defeat
-        // the purpose of the streaming path. W2.4 acceptance test 4
-        // pins this.
         Ok(())
     }
 
-    /// Rebuild an `EngineSignatureBlock` vec from the driver's received
-    /// `SumBlock` vec + `received_sum_head`. The strong bytes are zero-
-    /// padded to 32 (engine API shape); only the first `checksum_length`
-    /// bytes are ever consulted by the engine for matching.
-    fn wire_sigs_to_engine(&self) -> Result<Vec<EngineSignatureBlock>, AerorsyncError> {
-        let head = self.received_sum_head.as_ref().ok_or_else(|| {
-            AerorsyncError::invalid_frame("wire_sigs_to_engine: no received sum_head")
-        })?;
-        let block_len = head.block_length as u32;
-        let mut out = Vec::with_capacity(self.received_signatures.len());
-        for (idx, wire) in self.received_signatures.iter().enumerate() {
-            let mut strong = [0u8; 32];
-            let take = wire.strong.len().min(32);
-            strong[..take].copy_from_slice(&wire.strong[..take]);
-            out.push(EngineSignatureBlock {
-                index: idx as u32,
-                rolling: wire.rolling,
-                strong,
-                block_len,
-            });
-        }
-        Ok(out)
+    // --- private helpers -------------------------------------------------
+
+    async fn open_raw_stream_internal(
+        &mut self,
+        command_spec: &RemoteCommandSpec,
+    ) -> Result<(), AerorsyncError> {
+        self.check_cancel("open_raw_stream")?;
+        let stream = self
+            .transport
+            .open_raw_stream(command_spec.to_exec_request())
+            .await?;
+        self.transfer_options = match command_spec.flavor {
+            RemoteCommandFlavor::WrapperParity => command_spec.options.clone(),
+            // The preamble is the same for both flavors, and batch
+            // recording never leaves this side.
+            RemoteCommandFlavor::AerorsyncServe => TransferOptions {
+                algorithms: command_spec.options.algorithms.clone(),
+                write_batch: command_spec.options.write_batch.clone(),
+                ..TransferOptions::default()
+            },
+        };
+        self.transfer_skipped = false;
+        self.stream = Some(stream);
+        self.phase = AerorsyncSessionPhase::RawStreamOpen;
+        Ok(())
     }
 
-    /// Convert wire delta ops into engine delta ops, decompressing
-    /// literals session-wide when zstd is negotiated. CopyRuns expand
-    /// 1:1 into `EngineDeltaOp::CopyBlock(index)` per block in the run.
-    ///
-    /// **S8j download-side**: stock rsync's `send_zstd_token`
-    /// (token.c:678-776) flushes the zstd output buffer whenever it
-    /// reaches `MAX_DATA_COUNT` and emits a fresh DEFLATED_DATA frame
-    /// with the rest. A single logical literal can therefore arrive
-    /// as N ≥ 1 consecutive `DeltaOp::Literal` wire records. We group
-    /// those runs (any `DeltaOp::Literal` sequence uninterrupted by a
-    /// `DeltaOp::CopyRun`), concatenate their compressed payloads, and
-    /// feed ONE concatenated blob per run through the session-wide
-    /// DCtx. Pre-S8j this helper assumed 1 wire Literal = 1 logical
-    /// literal, which silently mis-scaled the engine plan whenever the
-    /// server split (for anything > ~16 KiB of compressed payload).
-    fn delta_wire_to_engine_ops(
-        &self,
-        wire_ops: &[DeltaOp],
-        zstd_on: bool,
-    ) -> Result<Vec<EngineDeltaOp>, AerorsyncError> {
-        // Pass 1: coalesce consecutive DeltaOp::Literal chunks into
-        // per-run blobs. Each run represents one logical literal; the
-        // fragmentation across DEFLATED_DATA tokens is pure transport.
-        let mut literal_run_blobs: Vec<Vec<u8>> = Vec::new();
-        let mut current_run: Vec<u8> = Vec::new();
-        for op in wire_ops {
-            match op {
-                DeltaOp::Literal { compressed_payload } => {
-                    current_run.extend_from_slice(compressed_payload);
+    /// Server counterpart of `open_raw_stream_internal`: the transport
+    /// hands over the accepted connection, and the session's options
+    /// come from the client's argument list.
+    async fn open_served_stream(&mut self, session: &ServerSession) -> Result<(), AerorsyncError> {
+        self.check_cancel("open_raw_stream")?;
+        let stream = self
+            .transport
+            .open_raw_stream(RemoteExecRequest {
+                program: String::new(),
+                args: Vec::new(),
+                environment: Vec::new(),
+            })
+            .await?;
+        self.transfer_options = session.options.clone();
+        self.session_flags = session.flags;
+        self.transfer_skipped = false;
+        self.stream = Some(stream);
+        self.phase = AerorsyncSessionPhase::RawStreamOpen;
+        Ok(())
+    }
+
+    /// Server side of the preamble on a daemon stream (`compat.c::
+    /// setup_protocol` with `am_server`): compat flags, our name lists
+    /// and the checksum seed go out in one write, then the client's
+    /// lists come in, the compression one only under `-z`. Both ends
+    /// pick the first name of the client's list that the server offers.
+    async fn perform_server_preamble(
+        &mut self,
+        session: &ServerSession,
+    ) -> Result<(), AerorsyncError> {
+        self.check_cancel("perform_server_preamble send")?;
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| AerorsyncError::transport("perform_server_preamble: stream not open"))?;
+        let agreed = stream.agreed_protocol().ok_or_else(|| {
+            AerorsyncError::unsupported_version(
+                "server preamble needs a daemon stream with an agreed protocol",
+            )
+        })?;
+        if session.compat_flags & CF_VARINT_FLIST_FLAGS == 0 {
+            return Err(AerorsyncError::unsupported_version(
+                "client does not negotiate checksum names (rsync 3.2 or newer required)",
+            ));
+        }
+        self.protocol_version = agreed.min(31);
+        self.compat_flags = session.compat_flags;
+        self.checksum_seed = session.checksum_seed;
+        let algorithms = &session.options.algorithms;
+        let checksum_list = algorithms.checksum_list();
+        let compression_list = algorithms.compression_list(true);
+        let mut outbound = encode_varint(session.compat_flags);
+        outbound.extend_from_slice(&encode_vstring(checksum_list.as_bytes()));
+        if session.compress {
+            outbound.extend_from_slice(&encode_vstring(compression_list.as_bytes()));
+        }
+        outbound.extend_from_slice(&session.checksum_seed.to_le_bytes());
+        stream.write_bytes(&outbound).await?;
+        self.phase = AerorsyncSessionPhase::ServerPreambleSent;
+
+        let wanted = if session.compress { 2 } else { 1 };
+        let mut lists: Vec<String> = Vec::with_capacity(wanted);
+        let mut scratch: Vec<u8> = Vec::with_capacity(64);
+        let mut cursor = 0usize;
+        while lists.len() < wanted {
+            match decode_vstring(&scratch[cursor..]) {
+                Ok((bytes, consumed)) => {
+                    cursor += consumed;
+                    lists.push(String::from_utf8_lossy(&bytes).into_owned());
                 }
-                DeltaOp::CopyRun { .. } => {
-                    if !current_run.is_empty() {
-                        literal_run_blobs.push(std::mem::take(&mut current_run));
+                Err(e) if needs_more_bytes(&e) => {
+                    self.check_cancel("perform_server_preamble recv")?;
+                    let stream = self.stream.as_mut().ok_or_else(|| {
+                        AerorsyncError::transport("perform_server_preamble: stream not open")
+                    })?;
+                    let chunk = stream.read_bytes(RAW_READ_CHUNK).await?;
+                    if chunk.is_empty() {
+                        return Err(AerorsyncError::transport(
+                            "perform_server_preamble: client closed before its name lists",
+                        ));
                     }
+                    scratch.extend_from_slice(&chunk);
                 }
+                Err(other) => return Err(map_realwire_error(other, "client name lists")),
             }
         }
-        if !current_run.is_empty() {
-            literal_run_blobs.push(current_run);
+        if cursor < scratch.len() {
+            self.mux_reader.feed(&scratch[cursor..]);
         }
-
-        // Decompress each run. For non-zstd sessions the raw wire
-        // bytes already carry the raw literal, so the concatenated run
-        // blob is already the logical literal's bytes.
-        let run_slices: Vec<&[u8]> = literal_run_blobs.iter().map(|b| b.as_slice()).collect();
-        let raw_run_literals: Vec<Vec<u8>> = if zstd_on && !run_slices.is_empty() {
-            decompress_zstd_literal_stream_boundaries(&run_slices)
-                .map_err(|e| map_realwire_error(e, "zstd decompress delta literals"))?
-        } else {
-            literal_run_blobs
+        self.checksum_algo = algorithms.accept_checksum(&lists[0])?;
+        self.negotiated_checksum_algos = self.checksum_algo.name().to_string();
+        // Without `-z` literals travel plain, whatever the lists say.
+        self.compression_algo = match lists.get(1) {
+            Some(offered) => algorithms.accept_compression(offered)?,
+            None => CompressionAlgo::None,
+        };
+        self.negotiated_compression_algos = match lists.get(1) {
+            Some(_) => self.compression_algo.name().to_string(),
+            None => String::new(),
         };
+        self.phase = AerorsyncSessionPhase::ClientPreambleRecvd;
+        Ok(())
+    }
 
-        // Pass 2: emit engine ops in wire order. Consecutive wire
-        // literals collapse into a single EngineDeltaOp::Literal
-        // (pushed on the first of the run); subsequent literals in
-        // the same run are folded silently. A CopyRun closes the
-        // current literal run.
-        let mut out = Vec::with_capacity(wire_ops.len());
-        let mut run_idx: usize = 0;
-        let mut in_literal_run = false;
-        for op in wire_ops {
-            match op {
-                DeltaOp::Literal { .. } => {
-                    if !in_literal_run {
-                        out.push(EngineDeltaOp::Literal(raw_run_literals[run_idx].clone()));
-                        run_idx += 1;
-                        in_literal_run = true;
-                    }
+    /// `recv_filter_list`: int32 length-prefixed rules up to a zero
+    /// length. The daemon applies no filter rules, so a non-empty list
+    /// is refused rather than silently ignored.
+    async fn read_filter_list(
+        &mut self,
+        inbound: &mut Vec<u8>,
+        bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        loop {
+            self.check_cancel("read_filter_list")?;
+            if inbound.len() >= 4 {
+                let len = i32::from_le_bytes([inbound[0], inbound[1], inbound[2], inbound[3]]);
+                if len == 0 {
+                    inbound.drain(..4);
+                    return Ok(());
                 }
-                DeltaOp::CopyRun {
-                    start_token_index,
-                    run_length,
-                } => {
-                    in_literal_run = false;
-                    for k in 0..*run_length {
-                        let block_idx = *start_token_index + i32::from(k);
-                        if block_idx < 0 {
-                            return Err(AerorsyncError::invalid_frame(format!(
-                                "negative block index {block_idx} in delta CopyRun"
-                            )));
-                        }
-                        out.push(EngineDeltaOp::CopyBlock(block_idx as u32));
-                    }
+                if len < 0 {
+                    return Err(AerorsyncError::invalid_frame(format!(
+                        "negative filter rule length {len}"
+                    )));
+                }
+                if inbound.len() >= 4 + len as usize {
+                    let rule = String::from_utf8_lossy(&inbound[4..4 + len as usize]).into_owned();
+                    let message = format!("filter rule {rule:?} is not supported by this daemon");
+                    self.write_error_message(&message).await?;
+                    return Err(AerorsyncError::new(
+                        AerorsyncErrorKind::PlannerRejected,
+                        message,
+                    ));
                 }
             }
+            let payload = self.next_data_frame(bridge).await?;
+            inbound.extend_from_slice(&payload);
         }
-        Ok(out)
-    }
-
-    fn zstd_negotiated(&self) -> bool {
-        // Rsync's preamble serialises algo lists as SPACE-separated,
-        // priority-descending tokens (see `perform_preamble_exchange`
-        // above). The historical comma split here silently disabled
-        // zstd against every real rsync peer: the list parses as a
-        // single "zstd lz4 zlibx zlib" literal token that never equals
-        // "zstd". The resulting raw-literal delta stream was still a
-        // protocol-shaped `DEFLATED_DATA` payload, which stock rsync
-        // tries to run through `recv_zstd_token` and then drops the
-        // connection ("error in rsync protocol data stream").
-        self.negotiated_compression_algos
-            .split_ascii_whitespace()
-            .any(|a| a.eq_ignore_ascii_case("zstd"))
     }
 
-    /// A2.4 entry point: drain the server's `SummaryFrame`, populate
-    /// `session_stats`, and shut the raw stream down cleanly. Call
-    /// **after** `drive_upload` / `drive_download` have reached the
-    /// post-delta stub frontier.
-    ///
-    /// Split from the main drive loop intentionally: the A4 adapter may
-    /// want to decide between an eager finish (replicate classic rsync
-    /// client UX) vs a deferred one (honour UI cancel during finish).
-    /// Keeping the split explicit at the driver boundary avoids a hidden
-    /// await that A4 couldn't interrupt cleanly.
-    pub async fn finish_session(
+    /// `read_final_goodbye` on a server: the client's NDX_DONE, or a
+    /// clean EOF from a client that already hung up. Returns whether
+    /// the marker arrived.
+    async fn read_client_goodbye(
         &mut self,
         bridge: &mut dyn EventSink,
-    ) -> Result<(), AerorsyncError> {
-        match self.finish_session_inner(bridge).await {
-            Ok(()) => {
-                self.phase = AerorsyncSessionPhase::Complete;
-                Ok(())
+    ) -> Result<bool, AerorsyncError> {
+        if self.summary_seed.is_empty() {
+            match self.next_data_frame(bridge).await {
+                Ok(bytes) => self.summary_seed.extend_from_slice(&bytes),
+                Err(e) if e.kind == AerorsyncErrorKind::TransportFailure => {
+                    tracing::debug!("client closed before its goodbye marker ({})", e.detail);
+                    return Ok(false);
+                }
+                Err(e) => return Err(e),
             }
-            Err(e) => {
-                self.phase = AerorsyncSessionPhase::Failed;
-                Err(e)
+        }
+        match self.summary_seed.first().copied() {
+            Some(0x00) => {
+                self.summary_seed.drain(..1);
+                Ok(true)
             }
+            Some(b) => Err(AerorsyncError::invalid_frame(format!(
+                "expected the client's goodbye NDX_DONE (0x00), got 0x{b:02X}"
+            ))),
+            None => Ok(false),
         }
     }
 
-    async fn finish_session_inner(
+    /// Send `message` as `MSG_ERROR`, which a client prints and treats
+    /// as fatal.
+    async fn write_error_message(&mut self, message: &str) -> Result<(), AerorsyncError> {
+        let mut payload = message.as_bytes().to_vec();
+        payload.push(b'\n');
+        payload.truncate(MAX_DATA_FRAME_LEN);
+        let header = MuxHeader {
+            tag: MuxTag::Error,
+            length: payload.len() as u32,
+        };
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| AerorsyncError::transport("write_error_message: stream not open"))?;
+        stream.write_bytes(&header.encode()).await?;
+        stream.write_bytes(&payload).await
+    }
+
+    /// B.2 fix: rsync wire protocol places the CLIENT first: the client
+    /// writes its preamble onto the raw stream and only afterwards reads
+    /// the server's response. The captured frozen transcripts confirm it:
+    /// `capture/artifacts_real/frozen/upload/capture_in.bin` (bytes the
+    /// client sends) starts with `1f 00 00 00` (protocol 31 LE u32) + the
+    /// checksum algo list; only after that the server replies with
+    /// `20 00 00 00 81 ff 23 ...` in `capture_out.bin`.
+    ///
+    /// The previous implementation read first and wrote after, which
+    /// deadlocked against stock `rsync --server` because both peers were
+    /// stuck in read. It happened to work against the dev helper
+    /// `aerorsync_serve` only because that path is never exercised via
+    /// the `NativeRsyncDriver` (which speaks the real wire); live lanes
+    /// against the dev helper go through the `SessionDriver` RSNP
+    /// framing instead.
+    async fn perform_preamble_exchange(
         &mut self,
-        bridge: &mut dyn EventSink,
+        protocol_version: u32,
     ) -> Result<(), AerorsyncError> {
-        // S8j dispatch by session role.
-        //
-        // - `Some(Receiver)` → real download against rsync 3.2.7: drain
-        //   exactly `PRE_SUMMARY_NDX_DONE_COUNT_DOWNLOAD` leading
-        //   NDX_DONE markers, then decode the SummaryFrame from the
-        //   residual.
-        // - `Some(Sender)` → real upload (A7 lane 3 scope). Upload-side
-        //   finish is wired in S8j.3+; legacy receive semantics stay
-        //   here for now to keep the A2.4 mock upload tests working.
-        // - `None` → legacy mock test that drove `finish_session`
-        //   directly on a synthesised inbound buffer without entering
-        //   `drive_*_inner`. Skip the drain entirely: the mock inbound
-        //   never contains leading NDX_DONE bytes and the peek-based
-        //   heuristic of the drain would misread a varlong value of 0
-        //   as a marker.
-        match self.session_role {
-            Some(SessionRole::Receiver) => {
-                // Download against real rsync: drain the 3 leading
-                // NDX_DONE markers, decode the summary the server
-                // emitted, send our own NDX_DONE ACK, and consume the
-                // trailing marker rsync echoes back.
-                self.drain_leading_ndx_done_download(bridge).await?;
-                self.receive_summary_phase(bridge).await?;
-                self.emit_ndx_done_marker().await?;
-                self.read_trailing_ndx_done(bridge).await?;
-            }
-            Some(SessionRole::Sender) => {
-                match self.remote_command_flavor {
-                    RemoteCommandFlavor::WrapperParity => {
-                        self.finish_stock_rsync_sender_tail(bridge).await?;
+        // 1. Write our client preamble first. The lists follow the
+        //    session's `AlgorithmPreferences`; the defaults are stock
+        //    rsync's client lists.
+        let algorithms = &self.transfer_options.algorithms;
+        let outbound = encode_client_preamble(&ClientPreamble {
+            protocol_version,
+            checksum_algos: algorithms.checksum_list(),
+            compression_algos: algorithms.compression_list(false),
+            consumed: 0,
+        });
+        let agreed = {
+            self.check_cancel("perform_preamble_exchange send")?;
+            let stream = self.stream.as_mut().ok_or_else(|| {
+                AerorsyncError::transport("perform_preamble_exchange: stream not open (pre-write)")
+            })?;
+            // An rsync daemon already agreed the version in its
+            // `@RSYNCD:` greeting: neither side sends the 4-byte int.
+            let agreed = stream.agreed_protocol();
+            let skip = if agreed.is_some() {
+                PROTOCOL_VERSION_LEN
+            } else {
+                0
+            };
+            stream.write_bytes(&outbound[skip..]).await?;
+            agreed
+        };
+        // 2. Drain the server preamble from the stream. Any bytes read
+        //    past the server preamble's `consumed` cursor are fed into
+        //    `mux_reader` so the subsequent file list decode sees them.
+        //    A daemon stream gets the agreed version spliced in front so
+        //    the same decoder applies.
+        let mut scratch = Vec::with_capacity(128);
+        if let Some(version) = agreed {
+            scratch.extend_from_slice(&encode_protocol_version(version));
+        }
+        loop {
+            self.check_cancel("perform_preamble_exchange recv")?;
+            match decode_server_preamble(&scratch) {
+                Ok(preamble) => {
+                    // Mirror `compat.c::setup_protocol` line 605:
+                    //   if (protocol_version > remote_protocol)
+                    //       protocol_version = remote_protocol;
+                    // The negotiated protocol is MIN(client_max, server_max).
+                    // The server's preamble advertises its max; both peers
+                    // then speak min() on the wire. Using the server's raw
+                    // value (e.g. proto 32 from rsync 3.4.x) while our
+                    // encoders target proto 31 produced subtle format drift
+                    // that manifested as receiver-side protocol errors and
+                    // generator EOF on the error pipe.
+                    self.protocol_version = preamble.protocol_version.min(protocol_version);
+                    self.compat_flags = preamble.compat_flags;
+                    self.checksum_seed = preamble.checksum_seed;
+                    self.negotiated_checksum_algos = preamble.checksum_algos;
+                    self.negotiated_compression_algos = preamble.compression_algos;
+                    if preamble.consumed < scratch.len() {
+                        self.mux_reader.feed(&scratch[preamble.consumed..]);
                     }
-                    RemoteCommandFlavor::AerorsyncServe => {
-                        // Dev helper compatibility: aerorsync_serve
-                        // still consumes the legacy NDX_DONE +
-                        // SummaryFrame tail.
-                        self.emit_summary_phase().await?;
-                        self.read_trailing_ndx_done(bridge).await?;
-                        self.session_stats.bytes_sent = self.sent_data_bytes;
-                        self.session_stats.bytes_received = self.received_raw_bytes;
+                    self.pick_negotiated_algorithms()?;
+                    self.start_batch_recording().await?;
+                    break;
+                }
+                Err(RealWireError::TruncatedBuffer { .. }) => {
+                    let stream = self.stream.as_mut().ok_or_else(|| {
+                        AerorsyncError::transport("perform_preamble_exchange: stream not open")
+                    })?;
+                    let chunk = stream.read_bytes(RAW_READ_CHUNK).await?;
+                    if chunk.is_empty() {
+                        return Err(AerorsyncError::transport(
+                            "perform_preamble_exchange: remote closed before server preamble",
+                        ));
                     }
+                    scratch.extend_from_slice(&chunk);
+                }
+                Err(other) => {
+                    return Err(map_realwire_error(other, "server preamble"));
                 }
-            }
-            None => {
-                // U-10: every public `drive_*` entry point sets
-                // `session_role` before `open_raw_stream_internal`, so
-                // reaching `finish_session` with `session_role = None`
-                // means a caller skipped the drive loop entirely. Refuse
-                // the call with an explicit illegal-state error instead
-                // of silently running receive semantics: that path used
-                // to mask wrong-role bugs in mock tests.
-                return Err(AerorsyncError::new(
-                    AerorsyncErrorKind::IllegalStateTransition,
-                    "finish_session called without a session_role: invoke drive_upload*/drive_download* first",
-                ));
             }
         }
-        self.shutdown_raw_stream().await?;
+        self.phase = AerorsyncSessionPhase::ClientPreambleRecvd;
         Ok(())
     }
 
-    // --- S8j NDX_DONE drain (download direction) -------------------------
+    /// Client side of `negotiate_the_strings`: pick our checksum and
+    /// compressor out of the server's lists, exactly as the server picks
+    /// them out of ours.
+    fn pick_negotiated_algorithms(&mut self) -> Result<(), AerorsyncError> {
+        let algorithms = &self.transfer_options.algorithms;
+        let checksum = algorithms.pick_checksum(&self.negotiated_checksum_algos);
+        let compression = algorithms.pick_compression(&self.negotiated_compression_algos);
+        self.checksum_algo =
+            checksum.inspect_err(|_| self.phase = AerorsyncSessionPhase::Failed)?;
+        self.compression_algo =
+            compression.inspect_err(|_| self.phase = AerorsyncSessionPhase::Failed)?;
+        Ok(())
+    }
 
-    /// Pull MSG_DATA frames until we have accumulated at least
-    /// `PRE_SUMMARY_NDX_DONE_COUNT_DOWNLOAD` bytes, verify that the
-    /// first `N` of them are `0x00` (NDX_DONE markers), drop them, and
-    /// stash the remainder into `summary_seed` for
-    /// `receive_summary_phase` to prepend.
-    ///
-    /// Empty-drain policy: tests that synthesise a clean `SummaryFrame`
-    /// with NO leading markers (all A2.4 mock tests as written) MUST
-    /// keep working. We detect that case by a peek at the first byte
-    ///: if it is not `0x00`, the drain is a no-op and the summary
-    /// decoder sees the data unchanged.
-    async fn drain_leading_ndx_done_download(
-        &mut self,
-        bridge: &mut dyn EventSink,
-    ) -> Result<(), AerorsyncError> {
-        let want = PRE_SUMMARY_NDX_DONE_COUNT_DOWNLOAD;
-        let mut buf: Vec<u8> = Vec::new();
-        // Pull at least one frame to peek.
-        let first = self.next_data_frame(bridge).await?;
-        buf.extend_from_slice(&first);
-
-        // Empty-drain: if the first byte is not NDX_DONE, rsync did not
-        // emit leading markers on this profile (synthesised mocks). Pass
-        // the whole payload through to the summary decoder as-is.
-        if buf.first().copied() != Some(0x00) {
-            self.summary_seed = buf;
+    /// `--write-batch`: the preamble has settled everything the batch
+    /// header and its replay script record; every sender-stream byte
+    /// from here on goes to the file too.
+    async fn start_batch_recording(&mut self) -> Result<(), AerorsyncError> {
+        let Some(path) = self.transfer_options.write_batch.clone() else {
             return Ok(());
-        }
+        };
+        let header = BatchHeader {
+            flags: BatchStreamFlags::for_session(
+                self.session_flags,
+                &self.transfer_options,
+                self.compression_algo,
+            ),
+            protocol_version: self.protocol_version,
+            compat_flags: self.compat_flags,
+            checksum_seed: self.checksum_seed,
+        };
+        let recorder = BatchRecorder::create(
+            &path,
+            &header,
+            &self.transfer_options,
+            self.checksum_algo,
+            self.compression_algo,
+        )
+        .await?;
+        self.batch_recorder = Some(recorder);
+        Ok(())
+    }
 
-        // Pull more frames until we can cover `want` leading bytes AND
-        // verify they are all `0x00`.
-        while buf.len() < want {
-            let more = self.next_data_frame(bridge).await?;
-            buf.extend_from_slice(&more);
-        }
-        for (i, b) in buf.iter().take(want).enumerate() {
-            if *b != 0x00 {
-                return Err(AerorsyncError::invalid_frame(format!(
-                    "expected {want} leading NDX_DONE markers before SummaryFrame, \
-                     found non-zero byte 0x{b:02X} at offset {i}"
-                )));
+    /// `handle_stats` of a client sender under `--write-batch`: the
+    /// replay is a client receiver and expects the sender's totals,
+    /// which never cross the wire in this direction.
+    async fn record_batch_stats(&mut self, total_size: i64) -> Result<(), AerorsyncError> {
+        let frame = SummaryFrame {
+            total_read: self.received_raw_bytes as i64,
+            total_written: self.sent_data_bytes as i64,
+            total_size,
+            flist_buildtime: Some(0),
+            flist_xfertime: Some(0),
+        };
+        let protocol = self.protocol_version;
+        match self.batch_recorder.as_mut() {
+            Some(recorder) => {
+                recorder
+                    .record(&encode_summary_frame(&frame, protocol))
+                    .await
             }
+            None => Ok(()),
         }
-        // Drop the drained markers, keep the residual.
-        buf.drain(..want);
-        self.summary_seed = buf;
-        Ok(())
     }
 
-    // --- A2.4 summary phase + shutdown ----------------------------------
+    /// Compute `FileListDecodeOptions` from the driver's current
+    /// negotiation state.
+    fn build_flist_options(&self) -> FileListDecodeOptions<'static> {
+        FileListDecodeOptions {
+            protocol: self.protocol_version,
+            // CF_VARINT_FLIST_FLAGS is active from protocol 30+. The
+            // frozen oracle has it on; assert that implicitly by using
+            // the varint path. If a legacy peer disagrees, decode will
+            // surface a `RealWireError` which we translate.
+            xfer_flags_as_varint: true,
+            // B.2: production dispatch invokes the server with `-c`
+            // (always_checksum) and `-o -g` (preserve owner/group).
+            // Mirror the oracle compat: each regular file entry carries
+            // the negotiated checksum + uid + gid varints (with names
+            // when XMIT_USER/GROUP_NAME_FOLLOWS gates them).
+            // A daemon client may leave any of them out: the server
+            // side takes them from its `SessionFlags`.
+            always_checksum: self.session_flags.always_checksum,
+            csum_len: self.checksum_algo.digest_len(),
+            preserve_uid: self.session_flags.preserve_uid,
+            preserve_gid: self.session_flags.preserve_gid,
+            previous_name: None,
+            // `-l` is part of the fixed flag bundle; `-H -A -X` follow
+            // the session's `TransferOptions`.
+            preserve_links: self.session_flags.preserve_links,
+            preserve_hard_links: self.transfer_options.hard_links,
+            preserve_acls: self.transfer_options.acls,
+            preserve_xattrs: self.transfer_options.xattrs,
+            scope: None,
+        }
+    }
 
-    /// Read the `SummaryFrame` from the data stream, populate
-    /// `received_summary` + `session_stats`, and advance phase.
-    ///
-    /// S8j: preloads the decode buffer from `summary_seed`, which the
-    /// `drain_leading_ndx_done_download` helper populates after
-    /// dropping the leading `NDX_DONE` markers rsync 3.2.7 interleaves
-    /// between the file-csum and the summary on the server→client
-    /// stream (see `main.c::read_final_goodbye`).
-    async fn receive_summary_phase(
+    async fn send_file_list_single_file(
         &mut self,
-        bridge: &mut dyn EventSink,
+        entry: &FileListEntry,
     ) -> Result<(), AerorsyncError> {
-        self.phase = AerorsyncSessionPhase::SummaryReceiving;
-        let mut buf: Vec<u8> = std::mem::take(&mut self.summary_seed);
-        let protocol = self.protocol_version;
+        self.phase = AerorsyncSessionPhase::FileListSending;
+        let opts = self.build_flist_options();
+        // B.2: coalesce entry + terminator + NDX_FLIST_EOF into a single
+        // MSG_DATA frame. The frozen oracle's first MSG_DATA payload is
+        // 67 bytes carrying exactly this layout (entry 47 B + xxh128
+        // 16 B + terminator 2 B + NDX_FLIST_EOF marker 2 B). Split
+        // frames break stock rsync's expectation that the whole flist
+        // arrives before the sender starts waiting on the receiver.
+        let mut payload = encode_file_list_entry(entry, &opts);
+        payload.extend_from_slice(&encode_file_list_terminator(&opts));
+        payload.extend_from_slice(&encode_ndx(NDX_FLIST_EOF, &mut self.outbound_ndx_state));
+        self.write_data_frame(&payload).await?;
+        // S8j: remember the entry on the sender side so
+        // `emit_summary_phase` can populate `total_size`. Parity with the
+        // receiver path, which already pushes decoded entries.
+        self.file_list.push(entry.clone());
+        self.phase = AerorsyncSessionPhase::FileListSent;
+        Ok(())
+    }
+
+    /// Decode the single-file list up to its terminator. Returns the
+    /// bytes of the frame that followed the terminator (the sender's
+    /// `NDX_FLIST_EOF` under incremental recursion).
+    async fn receive_file_list_single_file(
+        &mut self,
+        bridge: &mut dyn EventSink,
+    ) -> Result<Vec<u8>, AerorsyncError> {
+        self.phase = AerorsyncSessionPhase::FileListReceiving;
+        let opts = self.build_flist_options();
+        let mut flist_buf: Vec<u8> = Vec::new();
+        let mut entry_seen = false;
         loop {
-            self.check_cancel("receive_summary_phase")?;
-            if !buf.is_empty() {
-                match decode_summary_frame(&buf, protocol) {
-                    Ok((frame, consumed)) => {
-                        buf.drain(..consumed);
-                        self.session_stats.bytes_received = frame.total_read as u64;
-                        self.session_stats.bytes_sent = frame.total_written as u64;
-                        self.received_summary = Some(frame);
-                        self.phase = AerorsyncSessionPhase::SummaryReceived;
-                        return Ok(());
+            self.check_cancel("receive_file_list")?;
+            // Try to decode as much of the file list as we can from the
+            // currently buffered bytes. Only fall through to another
+            // Data frame when we run out of material.
+            if !flist_buf.is_empty() {
+                match decode_file_list_entry(&flist_buf, &opts) {
+                    Ok((FileListDecodeOutcome::Entry(entry), consumed)) => {
+                        flist_buf.drain(..consumed);
+                        self.file_list.push(entry);
+                        entry_seen = true;
+                        continue;
+                    }
+                    Ok((FileListDecodeOutcome::EndOfList { .. }, consumed)) => {
+                        flist_buf.drain(..consumed);
+                        if !entry_seen {
+                            return Err(AerorsyncError::invalid_frame(
+                                "file list ended without any entry",
+                            ));
+                        }
+                        self.phase = AerorsyncSessionPhase::FileListReceived;
+                        return Ok(flist_buf);
                     }
+                    // A partial FileListEntry can surface several
+                    // "need-more-bytes" shapes from `decode_file_list_entry`:
+                    // raw truncation, a declared name length that overshoots
+                    // the current buffer, or a declared algo-list length that
+                    // overshoots. All three are recoverable by pulling
+                    // another MSG_DATA frame off the wire.
                     Err(RealWireError::TruncatedBuffer { .. })
-                    | Err(RealWireError::DeltaTokenTruncated { .. }) => {
-                        // need more bytes
+                    | Err(RealWireError::InvalidNameLen { .. })
+                    | Err(RealWireError::InvalidAlgoListLen { .. }) => {
+                        // Need more bytes: poll another Data frame below.
                     }
                     Err(other) => {
-                        return Err(map_realwire_error(other, "summary frame"));
+                        return Err(map_realwire_error(other, "file list entry"));
                     }
                 }
             }
             let payload = self.next_data_frame(bridge).await?;
-            buf.extend_from_slice(&payload);
+            flist_buf.extend_from_slice(&payload);
         }
     }
 
-    /// Tear the raw stream down cleanly. Advances phase to `Complete`.
-    async fn shutdown_raw_stream(&mut self) -> Result<(), AerorsyncError> {
-        if let Some(mut stream) = self.stream.take() {
-            stream.shutdown().await?;
+    /// Wrap `payload` in a `MSG_DATA` mux frame and write it to the raw
+    /// stream. Rejects payloads larger than the 24-bit length field.
+    async fn write_data_frame(&mut self, payload: &[u8]) -> Result<(), AerorsyncError> {
+        if payload.len() > MAX_DATA_FRAME_LEN {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "MSG_DATA payload {} exceeds 24-bit length field",
+                payload.len()
+            )));
+        }
+        self.check_cancel("write_data_frame")?;
+        let header = MuxHeader {
+            tag: MuxTag::Data,
+            length: payload.len() as u32,
+        };
+        let hdr_bytes = header.encode();
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| AerorsyncError::transport("write_data_frame: stream not open"))?;
+        stream.write_bytes(&hdr_bytes).await?;
+        stream.write_bytes(payload).await?;
+        self.sent_data_bytes += payload.len() as u64;
+        if self.session_role == Some(SessionRole::Sender) {
+            if let Some(recorder) = self.batch_recorder.as_mut() {
+                recorder.record(payload).await?;
+            }
         }
-        self.phase = AerorsyncSessionPhase::Complete;
-        Ok(())
-    }
-
-    // --- S8j upload finish helpers ---------------------------------------
-
-    /// Write a single `NDX_DONE` marker (1 byte `0x00`) wrapped in a
-    /// MSG_DATA mux frame. `write_data_frame` enforces the wrapping.
-    async fn emit_ndx_done_marker(&mut self) -> Result<(), AerorsyncError> {
-        self.write_data_frame(&[0x00]).await
-    }
-
-    /// Finish an upload against stock `rsync --server` while this client
-    /// is the sender. Replicates the exact sender-side tail of
-    /// `sender.c::send_files` + `main.c::client_run`:
-    ///
-    /// 1. `sender_phase_loop`: read-then-echo ping-pong with the
-    ///    generator's phase markers. For `max_phase = 2` (proto >= 29)
-    ///    the generator writes three NDX_DONE triggers on the socket
-    ///    (lines 2337, 2366, 2370); the sender echoes the first two and
-    ///    breaks on the third (sender.c:232-258).
-    /// 2. Final `write_ndx(NDX_DONE)` after the loop (sender.c:462).
-    /// 3. `handle_stats(-1)` client-sender branch: no socket writes
-    ///    (main.c:325-358 early-returns when !am_server).
-    /// 4. `read_final_goodbye` (proto >= 31): read the generator's 4th
-    ///    NDX_DONE (generator.c:2376), write the sender-branch ACK
-    ///    (main.c:889), read the parent-generator's final NDX_DONE
-    ///    (main.c:1121).
-    ///
-    /// Outbound total on the app stream: 4 NDX_DONE markers.
-    /// Inbound total on the app stream: 5 NDX_DONE markers.
-    /// Matches the frozen upload capture exactly.
-    async fn finish_stock_rsync_sender_tail(
-        &mut self,
-        bridge: &mut dyn EventSink,
-    ) -> Result<(), AerorsyncError> {
-        self.phase = AerorsyncSessionPhase::SummaryReceiving;
-        // (1) phase loop with ping-pong echoes
-        self.sender_phase_loop(bridge).await?;
-        // (2) sender.c:462: final NDX_DONE once the phase loop breaks
-        self.emit_ndx_done_marker().await?;
-        // (3) handle_stats(-1): intentional no-op for the client sender
-        // (4) read_final_goodbye + proto-31 ACK
-        self.read_final_goodbye_marker(bridge).await?;
-        self.received_summary = None;
-        self.session_stats.bytes_sent = self.sent_data_bytes;
-        self.session_stats.bytes_received = self.received_raw_bytes;
-        self.phase = AerorsyncSessionPhase::SummaryReceived;
         Ok(())
     }
 
-    /// Ping-pong phase loop mirroring `sender.c::send_files` lines
-    /// 225-258. For each generator phase trigger we read from the wire,
-    /// we either echo NDX_DONE (phase advance not yet past max) or
-    /// break out of the loop (phase > max_phase). The final post-loop
-    /// NDX_DONE (sender.c:462) is emitted by the caller.
-    ///
-    /// For proto >= 29 `max_phase = 2`: the loop reads 3 NDX_DONE
-    /// triggers and writes 2 echoes. This ordering matters against
-    /// stock `rsync --server`: the generator coordinates with its
-    /// receiver child via internal `msgdone_cnt` increments that only
-    /// happen after the sender's echo reaches the receiver. A
-    /// fire-and-forget burst of NDX_DONEs (the pre-fix behaviour)
-    /// racewith the generator's phase bookkeeping and left the
-    /// receiver child stuck in `read_final_goodbye` long enough for
-    /// the generator to see EOF on its error pipe (exit 12).
-    async fn sender_phase_loop(
+    /// Drive the `MuxStreamReader` until a `MSG_DATA` frame pops out.
+    /// Non-terminal OOB frames are routed to `bridge`; the first
+    /// terminal OOB bails with a typed error (and is also forwarded to
+    /// the bridge so it can capture `first_terminal()` for post-mortem).
+    async fn next_data_frame(
         &mut self,
         bridge: &mut dyn EventSink,
-    ) -> Result<(), AerorsyncError> {
-        let max_phase: i32 = if self.protocol_version >= 29 { 2 } else { 1 };
-        let mut phase: i32 = 0;
+    ) -> Result<Vec<u8>, AerorsyncError> {
         loop {
-            self.check_cancel("sender_phase_loop")?;
-            let Some(()) = self
-                .try_read_ndx_done_marker(bridge, "sender_phase_loop: phase trigger")
-                .await?
-            else {
-                return Err(AerorsyncError::invalid_frame(
-                    "sender_phase_loop: remote closed before all phase markers",
-                ));
-            };
-            phase += 1;
-            if phase > max_phase {
-                break;
+            // Poll-first policy: a full frame may already be buffered
+            // from a previous chunk. Without this we would deadlock when
+            // the server's response arrived in a single read.
+            if let Some(res) = self.mux_reader.poll_frame() {
+                let poll = res.map_err(|e| map_realwire_error(e, "mux frame"))?;
+                match poll {
+                    MuxPoll::Data(bytes) => {
+                        // S8j: mirror of `sent_data_bytes`, used by
+                        // `emit_summary_phase` to populate `total_read`
+                        // in upload finishes.
+                        self.received_raw_bytes += bytes.len() as u64;
+                        if self.session_role == Some(SessionRole::Receiver) {
+                            if let Some(recorder) = self.batch_recorder.as_mut() {
+                                recorder.record(&bytes).await?;
+                            }
+                        }
+                        return Ok(bytes);
+                    }
+                    MuxPoll::Oob(event) => {
+                        bridge.handle(event);
+                        continue;
+                    }
+                    MuxPoll::Terminal(event) => {
+                        // Forward to the bridge before bailing so
+                        // `first_terminal()` captures the full payload.
+                        let event_for_bridge = event.clone();
+                        bridge.handle(event_for_bridge);
+                        return Err(AerorsyncError::from_oob_event(&event));
+                    }
+                }
             }
-            // sender.c:256: echo NDX_DONE back to advance the receiver's phase loop
-            self.emit_ndx_done_marker().await?;
+            self.check_cancel("next_data_frame")?;
+            let stream = self
+                .stream
+                .as_mut()
+                .ok_or_else(|| AerorsyncError::transport("next_data_frame: stream not open"))?;
+            let chunk = stream.read_bytes(RAW_READ_CHUNK).await?;
+            if chunk.is_empty() {
+                return Err(AerorsyncError::transport(
+                    "next_data_frame: remote closed mid file list",
+                ));
+            }
+            self.mux_reader.feed(&chunk);
         }
-        Ok(())
     }
 
-    async fn read_final_goodbye_marker(
+    // --- A2.2 signature phase (upload: receive, download: send) ----------
+
+    /// Upload path: drain `ndx + iflags + sum_head + count × sum_block`
+    /// from the server. Populates `received_sum_head`,
+    /// `received_signatures`, `last_iflags`. Phase transitions:
+    /// `FileListSent → SumHeadReceiving → SumBlocksReceiving`.
+    async fn receive_signature_phase_single_file(
         &mut self,
         bridge: &mut dyn EventSink,
     ) -> Result<(), AerorsyncError> {
-        let Some(()) = self
-            .try_read_ndx_done_marker(bridge, "read_final_goodbye first marker")
-            .await?
-        else {
-            return Ok(());
-        };
+        self.phase = AerorsyncSessionPhase::SumHeadReceiving;
+        let (ndx, iflags, head) = self.read_signature_header(bridge).await?;
+        if !(0..=i32::MAX).contains(&ndx) {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "unexpected ndx sentinel before signature phase: {ndx}"
+            )));
+        }
+        if iflags & ITEM_TRANSFER == 0 {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "server signature message lacks ITEM_TRANSFER bit: iflags=0x{iflags:04X}"
+            )));
+        }
+        // B.2 Step 4: stash the received NDX so `send_delta_phase_*`
+        // can echo it back at the start of the delta payload (parity
+        // with `sender.c::write_ndx_and_attrs`). Without this echo the
+        // receiver mis-aligns and aborts with rsync exit 22.
+        self.last_received_ndx = ndx;
+        self.last_iflags = iflags;
+        self.received_sum_head = Some(head);
 
-        if self.protocol_version >= 31 {
-            self.emit_ndx_done_marker().await?;
-            let Some(()) = self
-                .try_read_ndx_done_marker(bridge, "read_final_goodbye final marker")
-                .await?
-            else {
-                return Ok(());
-            };
+        if head.count < 0 {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "server sum_head.count is negative: {}",
+                head.count
+            )));
         }
+        self.phase = AerorsyncSessionPhase::SumBlocksReceiving;
+        // `generator.c::generate_and_send_sums` stops after the sum_head
+        // in append mode: the head only tells us where the remote copy ends.
+        let block_count = if self.transfer_options.append == AppendMode::Off {
+            head.count as usize
+        } else {
+            0
+        };
+        let blocks = self
+            .read_signature_blocks(block_count, head.checksum_length as usize, bridge)
+            .await?;
+        self.received_signatures = blocks;
         Ok(())
     }
 
-    async fn try_read_ndx_done_marker(
+    /// Decode `ndx + iflags + sum_head` from the data stream, pulling
+    /// additional `MSG_DATA` frames whenever the decoder reports it
+    /// needs more bytes.
+    async fn read_signature_header(
         &mut self,
         bridge: &mut dyn EventSink,
-        context: &'static str,
-    ) -> Result<Option<()>, AerorsyncError> {
-        loop {
-            if let Some(&b) = self.summary_seed.first() {
-                self.summary_seed.drain(..1);
-                if b != 0x00 {
-                    return Err(AerorsyncError::invalid_frame(format!(
-                        "{context}: expected NDX_DONE (0x00), got 0x{b:02X}"
-                    )));
+    ) -> Result<(i32, u16, SumHead), AerorsyncError> {
+        let mut buf: Vec<u8> = std::mem::take(&mut self.sig_residual_after_header);
+        // 1. ndx
+        let ndx = loop {
+            self.check_cancel("read_signature_header ndx")?;
+            if !buf.is_empty() {
+                match decode_ndx(&buf, &mut self.inbound_ndx_state) {
+                    Ok((ndx, consumed)) => {
+                        buf.drain(..consumed);
+                        break ndx;
+                    }
+                    Err(RealWireError::NdxTruncated { .. }) => {
+                        // need more bytes
+                    }
+                    Err(other) => return Err(map_realwire_error(other, "signature ndx")),
                 }
-                return Ok(Some(()));
             }
-
-            match self.next_data_frame(bridge).await {
-                Ok(bytes) => {
-                    if bytes.is_empty() {
-                        continue;
+            let payload = self.next_data_frame(bridge).await?;
+            buf.extend_from_slice(&payload);
+        };
+        if ndx == NDX_DONE || ndx == NDX_FLIST_EOF {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "unexpected ndx sentinel at start of signature phase: {ndx}"
+            )));
+        }
+        // 2. iflags (u16 LE: 2 bytes)
+        let iflags = loop {
+            self.check_cancel("read_signature_header iflags")?;
+            if buf.len() >= 2 {
+                match decode_item_flags(&buf) {
+                    Ok((flags, consumed)) => {
+                        buf.drain(..consumed);
+                        break flags;
                     }
-                    self.summary_seed.extend_from_slice(&bytes);
+                    Err(other) => return Err(map_realwire_error(other, "signature iflags")),
                 }
-                Err(e) if e.kind == AerorsyncErrorKind::TransportFailure => return Err(e),
-                Err(e) => return Err(e),
             }
-        }
-    }
-
-    /// Emit the end-of-session `NDX_DONE` + `SummaryFrame` pair that
-    /// rsync 3.2.7 expects from the **sender** (client in upload mode)
-    /// after the delta stream and its file-level checksum trailer.
-    ///
-    /// Wire layout (matches `main.c::read_final_goodbye` + `handle_stats`):
-    /// ```text
-    ///   [0x00]                                  // NDX_DONE marker
-    ///   encode_summary_frame(frame, 31)        // 5 × varlong(_, _, 3)
-    /// ```
-    /// Both chunks go out in a single MSG_DATA frame for wire economy;
-    /// rsync's mux layer accepts either bundled or split.
-    ///
-    /// Field population:
-    /// - `total_read` = `self.received_raw_bytes` (bytes consumed from
-    ///   the remote via `next_data_frame`, incl. signatures).
-    /// - `total_written` = `self.sent_data_bytes` (bytes written via
-    ///   `write_data_frame`, incl. file list, signatures echo, delta).
-    /// - `total_size` = size of the first entry in `self.file_list`
-    ///   (single-file prototype scope).
-    /// - `flist_buildtime` / `flist_xfertime` = `Some(0)`. Rsync's
-    ///   `handle_stats` treats these as informational (never validated
-    ///   as `> 0`); a future S8k will wire actual `Instant` measurement
-    ///   if lane 3 telemetry shows the zeros are surprising.
-    async fn emit_summary_phase(&mut self) -> Result<(), AerorsyncError> {
-        self.phase = AerorsyncSessionPhase::SummaryReceiving;
-        let total_size = self.file_list.first().map(|e| e.size).unwrap_or(0);
-        // `SummaryFrame` snapshots the counters as of the moment the
-        // client decided to announce them: matching rsync 3.2.7's
-        // `handle_stats`, which reads `stats.total_written` before
-        // emitting the summary itself (so the reported number does NOT
-        // include the summary bytes being written).
-        let frame = SummaryFrame {
-            total_read: self.received_raw_bytes as i64,
-            total_written: self.sent_data_bytes as i64,
-            total_size,
-            flist_buildtime: Some(0),
-            flist_xfertime: Some(0),
+            let payload = self.next_data_frame(bridge).await?;
+            buf.extend_from_slice(&payload);
         };
-        let mut payload = Vec::with_capacity(1 + 9 * 5);
-        payload.push(0x00); // NDX_DONE
-        payload.extend_from_slice(&encode_summary_frame(&frame, self.protocol_version));
-        self.write_data_frame(&payload).await?;
-        // `session_stats` is a post-emit aggregate: it DOES include the
-        // summary bytes we just wrote, so downstream consumers see the
-        // actual wire-level totals for the session.
-        self.session_stats.bytes_sent = self.sent_data_bytes;
-        self.session_stats.bytes_received = self.received_raw_bytes;
-        self.received_summary = Some(frame);
-        self.phase = AerorsyncSessionPhase::SummaryReceived;
-        Ok(())
+        // 3. sum_head (16 bytes)
+        let head = loop {
+            self.check_cancel("read_signature_header sum_head")?;
+            if buf.len() >= 16 {
+                match decode_sum_head(&buf) {
+                    Ok((head, consumed)) => {
+                        buf.drain(..consumed);
+                        break head;
+                    }
+                    Err(other) => return Err(map_realwire_error(other, "signature sum_head")),
+                }
+            }
+            let payload = self.next_data_frame(bridge).await?;
+            buf.extend_from_slice(&payload);
+        };
+        // Stash any residual bytes back into the mux reader for the
+        // subsequent sum_blocks reader. `MuxStreamReader.feed` takes raw
+        // mux frames: but at this point `buf` holds POST-mux payload
+        // bytes, not raw mux. We cannot re-feed it into the reader.
+        // Instead we carry the residual through `read_signature_blocks`
+        // via an explicit argument.
+        //
+        // Implementation choice: pass `buf` to `read_signature_blocks`
+        // as the prefix of its own accumulator. Getters stay clean.
+        self.sig_residual_after_header = std::mem::take(&mut buf);
+        Ok((ndx, iflags, head))
     }
 
-    /// Read the final `NDX_DONE` (1 byte `0x00`) the rsync receiver
-    /// writes back in `read_final_goodbye` line 887 after consuming
-    /// the sender's `NDX_DONE + SummaryFrame`. Tolerates clean EOF
-    /// (some rsync builds close the channel before the byte flushes).
-    async fn read_trailing_ndx_done(
+    /// Read exactly `count` sum_blocks from the data stream, using the
+    /// residual bytes left by `read_signature_header` as a prefix to the
+    /// accumulator.
+    async fn read_signature_blocks(
         &mut self,
+        count: usize,
+        strong_len: usize,
         bridge: &mut dyn EventSink,
-    ) -> Result<(), AerorsyncError> {
-        // Best-effort read: if the stream is already closed, or the
-        // next frame is empty, treat as clean completion.
-        match self.next_data_frame(bridge).await {
-            Ok(bytes) => {
-                if let Some(&b) = bytes.first() {
-                    if b != 0x00 {
-                        return Err(AerorsyncError::invalid_frame(format!(
-                            "expected trailing NDX_DONE (0x00), got 0x{b:02X}"
-                        )));
-                    }
-                }
-                // bytes.is_empty() is valid too: nothing to check.
-                Ok(())
-            }
-            Err(e) if e.kind == AerorsyncErrorKind::TransportFailure => {
-                // EOF is an acceptable end of a clean rsync session.
-                tracing::debug!(
-                    "read_trailing_ndx_done: remote closed before trailing marker ({})",
-                    e.detail
-                );
-                Ok(())
+    ) -> Result<Vec<SumBlock>, AerorsyncError> {
+        let mut buf: Vec<u8> = std::mem::take(&mut self.sig_residual_after_header);
+        let mut out = Vec::with_capacity(count);
+        while out.len() < count {
+            self.check_cancel("read_signature_blocks")?;
+            let block_wire_size = 4 + strong_len;
+            if buf.len() >= block_wire_size {
+                match decode_sum_block(&buf, strong_len) {
+                    Ok((block, consumed)) => {
+                        buf.drain(..consumed);
+                        out.push(block);
+                        continue;
+                    }
+                    Err(other) => return Err(map_realwire_error(other, "sum_block")),
+                }
             }
-            Err(e) => Err(e),
+            let payload = self.next_data_frame(bridge).await?;
+            buf.extend_from_slice(&payload);
         }
+        Ok(out)
     }
 
-    fn check_cancel(&self, op: &'static str) -> Result<(), AerorsyncError> {
-        if self.cancel_handle.requested() {
-            Err(AerorsyncError::cancelled(format!(
-                "driver cancelled before {op}"
-            )))
-        } else {
-            Ok(())
+    /// Download path: compute signatures from `destination_data` via
+    /// `adapter` and emit a single mux-wrapped blob with
+    /// `ndx + iflags + sum_head + count × sum_block`. Phase transitions:
+    /// `FileListReceived → SumHeadSent → SumBlocksSent`.
+    async fn send_signature_phase_single_file(
+        &mut self,
+        destination_data: &[u8],
+        adapter: &dyn DeltaEngineAdapter,
+    ) -> Result<(), AerorsyncError> {
+        self.phase = AerorsyncSessionPhase::SumHeadSent;
+        let append = self.transfer_options.append != AppendMode::Off;
+        if append && self.basis_covers_remote(destination_data.len() as u64) {
+            self.transfer_skipped = true;
+            self.phase = AerorsyncSessionPhase::SumBlocksSent;
+            return Ok(());
         }
-    }
+        let (head, mut sum_blocks) = build_wire_signatures(destination_data, adapter);
+        if append {
+            sum_blocks.clear();
+        }
+        self.sent_sum_head = Some(head);
 
-    // --- A2.0 preamble helpers preserved for regression pins ---
-    //
-    // These operate on in-memory buffers rather than the transport. They
-    // were the A2.0 surface; A2.1 keeps them as-is so the existing
-    // frozen-oracle pins and round-trip tests do not regress. The
-    // production drive loop uses `perform_preamble_exchange` instead.
+        // Build a single MSG_DATA payload that concatenates everything.
+        let mut payload = self.signature_item_header();
+        payload.reserve(16 + sum_blocks.len() * (4 + A2_2_DOWNLOAD_S2LENGTH as usize));
+        payload.extend_from_slice(&encode_sum_head(&head));
+        for block in &sum_blocks {
+            payload.extend_from_slice(&encode_sum_block(block));
+        }
+        self.write_data_frame(&payload).await?;
 
-    #[allow(clippy::unused_async)] // kept async for API symmetry with A2.0
-    async fn send_client_preamble(
+        self.sent_signatures = sum_blocks;
+
+        self.phase = AerorsyncSessionPhase::SumBlocksSent;
+        Ok(())
+    }
+
+    /// Streaming twin of [`send_signature_phase_single_file`]: the basis
+    /// is read from `baseline` in whole-block strides of about
+    /// `STREAMING_READ_CHUNK_BYTES`, signed by
+    /// `adapter.build_signatures_streaming` and sent in full MSG_DATA
+    /// frames as the sum blocks pile up. Neither the basis nor its
+    /// signature list is ever resident; the block size comes from
+    /// `signature_block_size`, which also bounds the count the sender
+    /// has to index. Same wire bytes as the bulk phase for bases that fit
+    /// one frame.
+    async fn send_signature_phase_streaming(
         &mut self,
-        sink: &mut Vec<u8>,
-        protocol_version: u32,
-        checksum_algos: &str,
-        compression_algos: &str,
+        baseline: &mut dyn BaselineSource,
+        adapter: &dyn DeltaEngineAdapter,
     ) -> Result<(), AerorsyncError> {
-        let preamble = ClientPreamble {
-            protocol_version,
-            checksum_algos: checksum_algos.to_string(),
-            compression_algos: compression_algos.to_string(),
-            consumed: 0,
+        self.phase = AerorsyncSessionPhase::SumHeadSent;
+        let basis_len = baseline.len();
+        let append = self.transfer_options.append != AppendMode::Off;
+        if append && self.basis_covers_remote(basis_len) {
+            self.transfer_skipped = true;
+            self.phase = AerorsyncSessionPhase::SumBlocksSent;
+            return Ok(());
+        }
+        let block_size = signature_block_size(adapter, basis_len);
+        let count = match block_size {
+            0 => 0,
+            size => basis_len.div_ceil(size as u64),
         };
-        let bytes = encode_client_preamble(&preamble);
-        sink.extend_from_slice(&bytes);
-        self.phase = AerorsyncSessionPhase::ServerPreambleSent;
+        let count = usize::try_from(count)
+            .ok()
+            .filter(|&count| i32::try_from(count).is_ok())
+            .ok_or_else(|| {
+                AerorsyncError::invalid_frame(format!(
+                    "send_signature_phase_streaming: {count} blocks overflow sum_head.count"
+                ))
+            })?;
+        let head = wire_sum_head(basis_len, block_size, count);
+        self.sent_sum_head = Some(head);
+
+        let mut payload = self.signature_item_header();
+        payload.extend_from_slice(&encode_sum_head(&head));
+        // `--append` sends the head alone, as `generate_and_send_sums`
+        // returns right after `write_sum_head`.
+        if !append && count > 0 {
+            let stride = block_size * (STREAMING_READ_CHUNK_BYTES / block_size).max(1);
+            let mut builder = adapter.build_signatures_streaming(block_size);
+            let mut sigs: Vec<EngineSignatureBlock> = Vec::new();
+            let mut sent = 0usize;
+            let mut stride_idx: u32 = 0;
+            loop {
+                self.check_cancel("send_signature_phase")?;
+                let chunk = baseline
+                    .read_block(stride_idx, stride as u32)
+                    .await
+                    .map_err(|e| {
+                        AerorsyncError::transport(format!(
+                            "send_signature_phase_streaming: basis read failed: {e}"
+                        ))
+                    })?;
+                builder.drive_chunk(&chunk, &mut sigs);
+                sent += sigs.len();
+                for sig in sigs.drain(..) {
+                    payload.extend_from_slice(&encode_sum_block(&wire_sum_block(&sig)));
+                }
+                self.write_full_data_frames(&mut payload).await?;
+                if chunk.len() < stride {
+                    break;
+                }
+                stride_idx = stride_idx.checked_add(1).ok_or_else(|| {
+                    AerorsyncError::invalid_frame(
+                        "send_signature_phase_streaming: basis too large for u32 read strides",
+                    )
+                })?;
+            }
+            builder.finalize(&mut sigs);
+            sent += sigs.len();
+            for sig in sigs.drain(..) {
+                payload.extend_from_slice(&encode_sum_block(&wire_sum_block(&sig)));
+            }
+            if sent != count {
+                return Err(AerorsyncError::invalid_frame(format!(
+                    "send_signature_phase_streaming: basis changed while signing: \
+                     {sent} blocks built, sum_head announced {count}"
+                )));
+            }
+        }
+        if !payload.is_empty() {
+            self.write_data_frame(&payload).await?;
+        }
+
+        self.phase = AerorsyncSessionPhase::SumBlocksSent;
         Ok(())
     }
 
-    #[allow(clippy::unused_async)]
-    async fn receive_server_preamble(&mut self, source: &[u8]) -> Result<usize, AerorsyncError> {
-        let preamble = decode_server_preamble(source).map_err(|e| {
-            self.phase = AerorsyncSessionPhase::Failed;
-            map_realwire_error(e, "server preamble")
-        })?;
-        self.protocol_version = preamble.protocol_version;
-        self.compat_flags = preamble.compat_flags;
-        self.checksum_seed = preamble.checksum_seed;
-        self.negotiated_checksum_algos = preamble.checksum_algos;
-        self.negotiated_compression_algos = preamble.compression_algos;
-        self.phase = AerorsyncSessionPhase::ClientPreambleRecvd;
-        Ok(preamble.consumed)
+    /// Download path, `--append`: `true` when the local copy is already
+    /// at least as long as the remote file, so there is nothing to ask
+    /// for.
+    fn basis_covers_remote(&self, basis_len: u64) -> bool {
+        let remote_size = self.file_list.first().map_or(0, |e| e.size);
+        basis_len as i64 >= remote_size
     }
-}
 
-fn map_realwire_error(err: RealWireError, context: &'static str) -> AerorsyncError {
-    AerorsyncError::new(
-        AerorsyncErrorKind::InvalidFrame,
-        format!("{context}: {err}"),
-    )
-}
+    /// `ndx + iflags` (plus the basis type for a partial-dir basis) that
+    /// opens the generator's request for the single file. Records the
+    /// iflags in `last_iflags`.
+    fn signature_item_header(&mut self) -> Vec<u8> {
+        let iflags = if self.partial_basis {
+            A2_2_DOWNLOAD_IFLAGS | ITEM_BASIS_TYPE_FOLLOWS
+        } else {
+            A2_2_DOWNLOAD_IFLAGS
+        };
+        let header = ItemHeader {
+            ndx: A2_2_FIRST_FILE_NDX,
+            iflags,
+            basis_type: self.partial_basis.then_some(FNAMECMP_PARTIAL_DIR),
+            xname: None,
+            xattrs: Vec::new(),
+        };
+        self.last_iflags = iflags;
+        header.encode(&mut self.outbound_ndx_state)
+    }
 
-// ============================================================================
-// Tests
-// ============================================================================
+    // --- A2.3 delta phase (upload: send, download: receive) --------------
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::aerorsync::engine_adapter::{
-        DeltaEngineAdapter, EngineDeltaOp, EngineDeltaPlan, EngineSignatureBlock,
-    };
-    use crate::aerorsync::events::{classify_oob_frame, AerorsyncEvent, CollectingSink};
-    use crate::aerorsync::fixtures::RealRsyncBaselineByteTranscript;
-    use crate::aerorsync::mock::{MockRemoteShellTransport, MockTransportConfig};
-    use crate::aerorsync::real_wire::{encode_server_preamble, ServerPreamble};
+    /// Upload path: compute delta via `adapter.compute_delta` and encode
+    /// it (ops + END_FLAG + file_checksum trailer) through a
+    /// [`DeltaEmitter`], literals compressed session-wide. Flips
+    /// `committed = true` immediately before the first wire byte: the
+    /// PreCommit/PostCommit boundary.
+    ///
+    /// Phase transitions: `SumBlocksReceiving → DeltaSending → DeltaSent`.
+    async fn send_delta_phase_single_file(
+        &mut self,
+        source_data: &[u8],
+        adapter: &dyn DeltaEngineAdapter,
+    ) -> Result<(), AerorsyncError> {
+        self.phase = AerorsyncSessionPhase::DeltaSending;
 
-    /// Mock adapter used by A2.2/A2.3 tests. Returns a configurable
-    /// block size, pre-fabricated signatures, and a pre-canned delta
-    /// plan. `apply_delta` returns the destination data with `literal`
-    /// bytes interleaved at the start (simple deterministic output).
-    #[derive(Default)]
-    struct MockSigAdapter {
-        block_size: Option<usize>,
-        signatures: Vec<EngineSignatureBlock>,
-        upload_plan_ops: Vec<EngineDeltaOp>,
-        upload_savings_ratio: f64,
-        upload_should_use_delta: bool,
-    }
+        // Rebuild EngineSignatureBlock vec from received SumBlocks.
+        let engine_sigs = self.wire_sigs_to_engine()?;
+        let block_size = self
+            .received_sum_head
+            .as_ref()
+            .map(|h| h.block_length as usize)
+            .unwrap_or(0);
 
-    impl MockSigAdapter {
-        fn with_fixed_signatures(block_size: usize, signatures: Vec<EngineSignatureBlock>) -> Self {
-            Self {
-                block_size: Some(block_size),
-                signatures,
-                upload_plan_ops: Vec::new(),
-                upload_savings_ratio: 1.0,
-                upload_should_use_delta: false,
-            }
+        // B.2 Step 4: `block_size == 0` is the "whole file" case: the
+        // receiver's local target is absent or zero-byte so it has
+        // nothing to diff against. `generator.c::write_sum_head(f_out,
+        // NULL)` emits four zero int32s in this scenario. The sender
+        // must react by streaming the entire source as a single literal
+        // (no block matches possible). We build a synthetic plan with
+        // one Literal op covering all `source_data`.
+        let append_from = self.append_offset(source_data.len() as u64)?;
+        let mut plan = if let Some(offset) = append_from {
+            whole_file_plan(&source_data[offset as usize..])
+        } else if block_size == 0 {
+            whole_file_plan(source_data)
+        } else {
+            adapter.compute_delta_truncated(
+                source_data,
+                &engine_sigs,
+                block_size,
+                self.received_strong_len(),
+            )
+        };
+        if let Some(mut guard) = self.inplace_guard() {
+            guard.apply(&mut plan.ops, source_data, 0)?;
         }
 
-        fn with_upload_plan(mut self, ops: Vec<EngineDeltaOp>) -> Self {
-            self.upload_plan_ops = ops;
-            self.upload_should_use_delta = true;
-            self.upload_savings_ratio = 0.5;
-            self
-        }
+        // S8j: the whole-file checksum of the negotiated algorithm,
+        // verified by the receiver after reconstruction.
+        let hash_from = checksum_start(self.transfer_options.append, append_from);
+        let file_checksum = self
+            .checksum_algo
+            .digest(&source_data[hash_from as usize..]);
+
+        let mut emitter = self.delta_emitter("send_delta_phase", usize::MAX)?;
+        emitter.push_ops(&mut plan.ops)?;
+        emitter.finish(&file_checksum)?;
+        self.emitted_delta_ops = std::mem::take(&mut emitter.recorded);
+        self.flush_delta_frames(&mut emitter, true).await?;
+
+        self.phase = AerorsyncSessionPhase::DeltaSent;
+        Ok(())
     }
 
-    impl DeltaEngineAdapter for MockSigAdapter {
-        fn compute_block_size(&self, _file_size: u64) -> usize {
-            self.block_size.unwrap_or(1024)
-        }
-        fn build_signatures(
-            &self,
-            _destination_data: &[u8],
-            _block_size: usize,
-        ) -> Vec<EngineSignatureBlock> {
-            self.signatures.clone()
-        }
-        fn compute_delta(
-            &self,
-            _source_data: &[u8],
-            _destination_signatures: &[EngineSignatureBlock],
-            _block_size: usize,
-        ) -> EngineDeltaPlan {
-            let literal_bytes: u64 = self
-                .upload_plan_ops
-                .iter()
-                .map(|op| match op {
-                    EngineDeltaOp::Literal(b) => b.len() as u64,
-                    EngineDeltaOp::CopyBlock(_) => 0,
-                })
-                .sum();
-            let copy_blocks: u32 = self
-                .upload_plan_ops
-                .iter()
-                .filter(|op| matches!(op, EngineDeltaOp::CopyBlock(_)))
-                .count() as u32;
-            EngineDeltaPlan {
-                ops: self.upload_plan_ops.clone(),
-                copy_blocks,
-                literal_bytes,
-                total_delta_bytes: literal_bytes,
-                savings_ratio: self.upload_savings_ratio,
-                should_use_delta: self.upload_should_use_delta,
-            }
-        }
-        fn apply_delta(
-            &self,
-            destination_data: &[u8],
-            ops: &[EngineDeltaOp],
-            block_size: usize,
-        ) -> Result<Vec<u8>, String> {
-            // Simple deterministic reconstructor: literal bytes verbatim;
-            // CopyBlock(idx) → destination_data[idx*bs..(idx+1)*bs].
-            let mut out: Vec<u8> = Vec::new();
-            for op in ops {
-                match op {
-                    EngineDeltaOp::Literal(raw) => out.extend_from_slice(raw),
-                    EngineDeltaOp::CopyBlock(idx) => {
-                        let start = (*idx as usize) * block_size;
-                        let end = (start + block_size).min(destination_data.len());
-                        if start >= destination_data.len() {
-                            return Err(format!(
-                                "CopyBlock idx {idx} out of bounds for destination len {}",
-                                destination_data.len()
-                            ));
-                        }
-                        out.extend_from_slice(&destination_data[start..end]);
+    /// P3-T01 W1.2 / W1.3: streaming-source twin of
+    /// [`send_delta_phase_single_file`]. The engine plan is produced
+    /// chunk-by-chunk (`RollingDeltaPlanProducer` for
+    /// `block_size != 0`, fixed-slab chunking for `block_size == 0`),
+    /// each chunk's ops go straight through the [`DeltaEmitter`] and
+    /// full MSG_DATA frames leave as soon as they fill. The file-level
+    /// checksum is computed by streaming (`ChecksumAlgo::hasher`
+    /// instead of `ChecksumAlgo::digest`).
+    ///
+    /// ## Wire-byte parity vs. the bulk path
+    ///
+    /// Both paths frame the same emitter output the same way, and a
+    /// literal run cut into several engine literals encodes to the same
+    /// records as the whole run with zstd (and uncompressed while the
+    /// cuts fall on record boundaries), so the streaming output is
+    /// byte-identical to [`send_delta_phase_single_file`] for any source
+    /// length (pinned by `streaming_send_matches_bulk_send_*` and
+    /// `streaming_send_block_size_zero_chunks_large_source`).
+    ///
+    /// ## Memory bound
+    ///
+    /// Resident memory is a small multiple of `STREAMING_READ_CHUNK_BYTES`
+    /// whatever `source_len`:
+    ///
+    /// - the read buffer;
+    /// - the in-flight literal slab (`chunk_acc` for `block_size == 0`,
+    ///   the producer's window, capped by `with_max_literal`, otherwise)
+    ///   plus the one literal the emitter holds back;
+    /// - less than one `MAX_DATA_FRAME_LEN` frame of encoded output;
+    /// - the first `EMITTED_OPS_RECORD_BYTES` of `emitted_delta_ops`.
+    ///
+    /// `source_len` MUST equal the byte count drained from
+    /// `source_reader`; mismatches abort the upload with
+    /// `InvalidFrame` (the file changed mid-flight or the caller
+    /// declared the wrong size).
+    async fn send_delta_phase_streaming<R>(
+        &mut self,
+        mut source_reader: R,
+        source_len: u64,
+        _adapter: &dyn DeltaEngineAdapter,
+    ) -> Result<(), AerorsyncError>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        self.phase = AerorsyncSessionPhase::DeltaSending;
+
+        // Identical sig-derivation as the bulk path. `wire_sigs_to_engine`
+        // depends only on `received_signatures` + `received_sum_head`,
+        // which the preceding signature phase already populated.
+        let engine_sigs = self.wire_sigs_to_engine()?;
+        let block_size = self
+            .received_sum_head
+            .as_ref()
+            .map(|h| h.block_length as usize)
+            .unwrap_or(0);
+
+        // Drive the producer + file hasher chunk-by-chunk. The producer
+        // owns the rolling window; the hasher accumulates the negotiated
+        // checksum of the source. Both are populated from the same
+        // chunk slice so the wire trailer matches what the bulk path
+        // computes in one shot.
+        let mut hasher = self.checksum_algo.hasher();
+        let mut ops: Vec<EngineDeltaOp> = Vec::new();
+        let mut total_source_bytes: u64 = 0;
+        let mut buf = vec![0u8; STREAMING_READ_CHUNK_BYTES];
+        let append_from = self.append_offset(source_len)?;
+        let hash_from = checksum_start(self.transfer_options.append, append_from);
+        let mut emitter =
+            self.delta_emitter("send_delta_phase_streaming", EMITTED_OPS_RECORD_BYTES)?;
+
+        if block_size == 0 || append_from.is_some() {
+            // Whole-file case: the receiver has no baseline to diff
+            // against (`block_size == 0` is rsync's "send everything as
+            // one literal" sentinel). The producer would silently emit
+            // zero ops here, so we materialise the literal explicitly,
+            // one `EngineDeltaOp::Literal` per
+            // `STREAMING_READ_CHUNK_BYTES` slab: the emitter encodes
+            // consecutive literals as one run, so the slabs never need
+            // to be joined.
+            //
+            // `--append` takes the same route: the receiver already holds
+            // the first `append_from` bytes, so they are skipped here and
+            // only hashed when `--append-verify` covers them.
+            let literal_from = append_from.unwrap_or(0);
+            let mut chunk_acc: Vec<u8> = Vec::new();
+            loop {
+                let n = source_reader.read(&mut buf).await.map_err(|e| {
+                    AerorsyncError::transport(format!(
+                        "send_delta_phase_streaming: source read failed: {e}"
+                    ))
+                })?;
+                if n == 0 {
+                    break;
+                }
+                let skip_to =
+                    |from: u64| from.saturating_sub(total_source_bytes).min(n as u64) as usize;
+                hasher.update(&buf[skip_to(hash_from)..n]);
+                let mut to_consume: &[u8] = &buf[skip_to(literal_from)..n];
+                total_source_bytes += n as u64;
+
+                while !to_consume.is_empty() {
+                    if chunk_acc.capacity() == 0 {
+                        chunk_acc.reserve_exact(STREAMING_READ_CHUNK_BYTES);
+                    }
+                    let space_left = STREAMING_READ_CHUNK_BYTES.saturating_sub(chunk_acc.len());
+                    let take = to_consume.len().min(space_left);
+                    chunk_acc.extend_from_slice(&to_consume[..take]);
+                    to_consume = &to_consume[take..];
+                    if chunk_acc.len() >= STREAMING_READ_CHUNK_BYTES {
+                        emitter.push_op(EngineDeltaOp::Literal(std::mem::take(&mut chunk_acc)))?;
                     }
                 }
+                self.flush_delta_frames(&mut emitter, false).await?;
             }
-            Ok(out)
+            if !chunk_acc.is_empty() {
+                emitter.push_op(EngineDeltaOp::Literal(chunk_acc))?;
+            }
+        } else {
+            let mut producer = RollingDeltaPlanProducer::new(block_size, engine_sigs)
+                .with_strong_len(self.received_strong_len())
+                .with_max_literal(STREAMING_READ_CHUNK_BYTES);
+            // `--inplace`: a match completed inside this chunk starts at
+            // most one block before it, so the guard only needs the chunk
+            // plus one block of history to turn it into literal bytes.
+            let mut guard = self.inplace_guard();
+            let mut window: Vec<u8> = Vec::new();
+            let mut window_start: u64 = 0;
+            loop {
+                let n = source_reader.read(&mut buf).await.map_err(|e| {
+                    AerorsyncError::transport(format!(
+                        "send_delta_phase_streaming: source read failed: {e}"
+                    ))
+                })?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+                producer.drive_chunk(&buf[..n], &mut ops);
+                total_source_bytes += n as u64;
+                if let Some(guard) = guard.as_mut() {
+                    let keep = window.len().min(block_size);
+                    window_start += (window.len() - keep) as u64;
+                    window.drain(..window.len() - keep);
+                    window.extend_from_slice(&buf[..n]);
+                    guard.apply(&mut ops, &window, window_start)?;
+                }
+                emitter.push_ops(&mut ops)?;
+                self.flush_delta_frames(&mut emitter, false).await?;
+            }
+            producer.finalize(&mut ops);
+            if let Some(guard) = guard.as_mut() {
+                guard.apply(&mut ops, &window, window_start)?;
+            }
+            emitter.push_ops(&mut ops)?;
         }
-    }
 
-    /// Build a synthetic server signature-phase payload (bytes as they
-    /// appear inside one or more `MSG_DATA` frames before mux-wrapping).
-    /// The caller decides the chunking.
-    fn build_sig_phase_payload(
-        ndx: i32,
-        iflags: u16,
-        head: &SumHead,
-        blocks: &[SumBlock],
-    ) -> Vec<u8> {
-        use crate::aerorsync::real_wire::{
-            encode_item_flags, encode_ndx, encode_sum_block, encode_sum_head, NdxState,
-        };
-        let mut st = NdxState::new();
-        let mut out = Vec::new();
-        out.extend_from_slice(&encode_ndx(ndx, &mut st));
-        out.extend_from_slice(&encode_item_flags(iflags));
-        out.extend_from_slice(&encode_sum_head(head));
-        for b in blocks {
-            out.extend_from_slice(&encode_sum_block(b));
+        if total_source_bytes != source_len {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "send_delta_phase_streaming: declared source_len {source_len} != bytes read {total_source_bytes}"
+            )));
         }
-        out
-    }
 
-    fn make_sig_block(rolling: u32, strong_first_byte: u8, s2length: usize) -> SumBlock {
-        SumBlock {
-            rolling,
-            strong: vec![strong_first_byte; s2length],
-        }
+        emitter.finish(&hasher.finish())?;
+        self.emitted_delta_ops = std::mem::take(&mut emitter.recorded);
+        self.flush_delta_frames(&mut emitter, true).await?;
+
+        self.phase = AerorsyncSessionPhase::DeltaSent;
+        Ok(())
     }
 
-    fn make_engine_sig(
-        index: u32,
-        rolling: u32,
-        strong_first_byte: u8,
-        block_len: u32,
-    ) -> EngineSignatureBlock {
-        let mut strong = [0u8; 32];
-        for b in strong.iter_mut().take(32) {
-            *b = strong_first_byte;
+    /// A [`DeltaEmitter`] for the single file, its output primed with
+    /// the echo of the receiver's request.
+    ///
+    /// B.2 Step 4: the sender MUST echo back `write_ndx +
+    /// write_shortint(iflags) + write_sum_head` before the delta tokens,
+    /// mirroring `sender.c::send_files` (line 411-412). Without this echo
+    /// the receiver expects sum_head bytes where it gets delta tokens and
+    /// aborts with rsync exit 22 ("Error allocating core memory buffers":
+    /// sum.count is read as a huge int from delta bytes).
+    ///
+    /// Echo values come from the receiver's signature header that
+    /// `read_signature_header` stashed in `last_received_ndx`,
+    /// `last_iflags`, and `received_sum_head`.
+    fn delta_emitter(
+        &mut self,
+        context: &str,
+        record_budget: usize,
+    ) -> Result<DeltaEmitter, AerorsyncError> {
+        let echo_head = *self.received_sum_head.as_ref().ok_or_else(|| {
+            AerorsyncError::invalid_frame(format!(
+                "{context}: missing received sum_head: signature phase didn't run"
+            ))
+        })?;
+        let mut echo = encode_ndx(self.last_received_ndx, &mut self.outbound_ndx_state);
+        echo.extend_from_slice(&encode_item_flags(self.last_iflags));
+        echo.extend_from_slice(&encode_sum_head(&echo_head));
+        Ok(DeltaEmitter::new(
+            self.literal_encoder()?,
+            self.token_format(),
+            echo,
+            record_budget,
+        ))
+    }
+
+    /// Write what `emitter` has encoded so far: the full frames only, or
+    /// everything once the stream is `finished`.
+    ///
+    /// PreCommit → PostCommit boundary: `committed` flips BEFORE the
+    /// first byte of delta material is written. Once the server starts
+    /// receiving the delta stream, we no longer can transparently fall
+    /// back.
+    async fn flush_delta_frames(
+        &mut self,
+        emitter: &mut DeltaEmitter,
+        finished: bool,
+    ) -> Result<(), AerorsyncError> {
+        if !finished && emitter.out.len() < MAX_DATA_FRAME_LEN {
+            return Ok(());
         }
-        EngineSignatureBlock {
-            index,
-            rolling,
-            strong,
-            block_len,
+        self.committed = true;
+        if finished {
+            let out = std::mem::take(&mut emitter.out);
+            self.write_data_frames(&out).await
+        } else {
+            self.write_full_data_frames(&mut emitter.out).await
         }
     }
-    use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::Arc;
-
-    // ---- helpers ---------------------------------------------------------
-
-    fn mock_transport() -> MockRemoteShellTransport {
-        MockRemoteShellTransport::new(MockTransportConfig::healthy_upload())
-    }
 
-    fn mock_transport_with_raw_inbound(inbound: Vec<u8>) -> MockRemoteShellTransport {
-        let cfg = MockTransportConfig::healthy_upload().with_raw_inbound(inbound);
-        MockRemoteShellTransport::new(cfg)
+    /// Write the whole `MAX_DATA_FRAME_LEN` frames buffered in `payload`
+    /// and keep the tail for later.
+    async fn write_full_data_frames(
+        &mut self,
+        payload: &mut Vec<u8>,
+    ) -> Result<(), AerorsyncError> {
+        let full = payload.len() - payload.len() % MAX_DATA_FRAME_LEN;
+        if full > 0 {
+            self.write_data_frames(&payload[..full]).await?;
+            payload.drain(..full);
+        }
+        Ok(())
     }
 
-    fn make_driver(
-        transport: MockRemoteShellTransport,
-    ) -> AerorsyncDriver<MockRemoteShellTransport> {
-        AerorsyncDriver::new(transport, CancelHandle::inert())
-    }
+    /// Download path: drain delta stream bytes from MSG_DATA frames,
+    /// decode ops + trailer, decompress literals, convert to engine ops,
+    /// and apply via `adapter.apply_delta`. The reconstructed bytes are
+    /// stashed in `self.reconstructed` for the A4 adapter to flush to
+    /// disk via temp+rename.
+    ///
+    /// `committed` stays `false` throughout: A2.3 download never writes
+    /// to disk. A4 will flip committed when it opens the temp file.
+    ///
+    /// Phase transitions: `SumBlocksSent → DeltaReceiving → DeltaReceived`.
+    async fn receive_delta_phase_single_file(
+        &mut self,
+        destination_data: &[u8],
+        adapter: &dyn DeltaEngineAdapter,
+        bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        self.phase = AerorsyncSessionPhase::DeltaReceiving;
 
-    fn canonical_server_preamble_bytes() -> Vec<u8> {
-        // Rsync serialises both lists as SPACE-separated (see
-        // `perform_preamble_exchange` and the frozen oracle capture).
-        // Using commas here hid the `zstd_negotiated` parsing bug that
-        // made live uploads skip zstd compression against stock rsync.
-        encode_server_preamble(&ServerPreamble {
-            protocol_version: 31,
-            compat_flags: 0x07,
-            checksum_algos: "md5 xxh64".to_string(),
-            compression_algos: "none zstd".to_string(),
-            checksum_seed: 0xDEAD_BEEF,
-            consumed: 0,
-        })
+        // Accumulate bytes until `decode_delta_stream_with` succeeds.
+        let mut buf: Vec<u8> = Vec::new();
+        let sum_head_count = self.sent_sum_head.as_ref().map(|h| h.count);
+        loop {
+            self.check_cancel("receive_delta_phase")?;
+            if !buf.is_empty() {
+                match decode_delta_stream_with(
+                    &buf,
+                    self.checksum_algo.digest_len(),
+                    sum_head_count,
+                    self.token_format(),
+                ) {
+                    Ok((report, consumed)) => {
+                        buf.drain(..consumed);
+                        self.received_file_checksum = Some(report.file_checksum.clone());
+                        self.install_reconstructed_from_wire(
+                            destination_data,
+                            adapter,
+                            report.ops,
+                        )?;
+                        self.phase = AerorsyncSessionPhase::DeltaReceived;
+                        return Ok(());
+                    }
+                    Err(RealWireError::DeltaTokenTruncated { .. }) => {
+                        // need more bytes
+                    }
+                    Err(other) => {
+                        return Err(map_realwire_error(other, "delta stream"));
+                    }
+                }
+            }
+            let payload = self.next_data_frame(bridge).await?;
+            buf.extend_from_slice(&payload);
+        }
     }
 
-    fn mux_frame(tag: MuxTag, payload: &[u8]) -> Vec<u8> {
-        let header = MuxHeader {
-            tag,
-            length: payload.len() as u32,
+    fn install_reconstructed_from_wire(
+        &mut self,
+        destination_data: &[u8],
+        adapter: &dyn DeltaEngineAdapter,
+        wire_ops: Vec<DeltaOp>,
+    ) -> Result<(), AerorsyncError> {
+        let basis = MatchBasis {
+            data: destination_data,
+            block_len: self.sent_sum_head.map_or(0, |h| h.block_length as usize),
         };
-        let mut out = Vec::with_capacity(4 + payload.len());
-        out.extend_from_slice(&header.encode());
-        out.extend_from_slice(payload);
-        out
+        let engine_ops =
+            wire_ops_to_engine_ops(&wire_ops, &mut self.literal_decoder(), i32::MAX, &basis)?;
+        if self.transfer_options.append != AppendMode::Off {
+            // `receiver.c::receive_data` in append mode: keep the local
+            // bytes, the sender only ships what comes after them.
+            let mut reconstructed = destination_data.to_vec();
+            reconstructed.extend_from_slice(&literal_only_data(engine_ops)?);
+            self.reconstructed = Some(reconstructed);
+            return Ok(());
+        }
+        let block_size = self
+            .sent_sum_head
+            .as_ref()
+            .map(|h| h.block_length as usize)
+            .unwrap_or(0);
+        if block_size == 0 {
+            return Err(AerorsyncError::invalid_frame(
+                "receive_delta_phase: block_size is zero (missing local sum_head)",
+            ));
+        }
+        let reconstructed = adapter
+            .apply_delta(destination_data, &engine_ops, block_size)
+            .map_err(|e| AerorsyncError::invalid_frame(format!("apply_delta: {e}")))?;
+        self.reconstructed = Some(reconstructed);
+        Ok(())
     }
 
-    /// Build a `FileListEntry` that the encoder/decoder will round-trip
-    /// under `build_flist_options` (varint flags, always_checksum on,
-    /// preserve_uid/gid on with SAME_UID/SAME_GID gating uid/gid out).
-    /// The flags include `XMIT_LONG_NAME` so the suffix length is encoded
-    /// as a varint: which the path length (9 chars) still fits in.
-    fn sample_file_list_entry(path: &str) -> FileListEntry {
-        // Flags: XMIT_LONG_NAME (0x0040) | XMIT_SAME_MODE (0x0002) |
-        //        XMIT_SAME_TIME (0x0080) | XMIT_SAME_UID (0x0008) |
-        //        XMIT_SAME_GID (0x0010)
-        //: the "all same" upload case where only the name and size are
-        // transmitted. Matches a minimum-viable shape; the 16-byte
-        // checksum is required because B.2 turned `always_checksum` on
-        // in `build_flist_options` to mirror the oracle (`-c` always
-        // active in production dispatch).
-        const XMIT_SAME_MODE: u32 = 0x0002;
-        const XMIT_SAME_UID: u32 = 0x0008;
-        const XMIT_SAME_GID: u32 = 0x0010;
-        const XMIT_LONG_NAME: u32 = 0x0040;
-        const XMIT_SAME_TIME: u32 = 0x0080;
-        FileListEntry {
-            flags: XMIT_LONG_NAME | XMIT_SAME_MODE | XMIT_SAME_UID | XMIT_SAME_GID | XMIT_SAME_TIME,
-            path: path.to_string(),
-            size: 4096,
-            mtime: 0,
-            mtime_nsec: None,
-            mode: 0,
-            uid: None,
-            uid_name: None,
-            gid: None,
-            gid_name: None,
-            // 16 bytes filled with a sentinel; xxh128 length, never
-            // validated against file content in unit tests.
-            checksum: vec![0xAA; 16],
-        }
-    }
+    /// P3-T01 W2.4: streaming sibling of [`receive_delta_phase_single_file`].
+    /// Ops are decoded one at a time as MSG_DATA frames arrive, literal
+    /// records decompressed as they come, and the engine ops handed to
+    /// `apply_delta_streaming(baseline, ops, block_size, writer)` in
+    /// batches of at most `STREAMING_APPLY_BATCH_OPS` ops or
+    /// `STREAMING_READ_CHUNK_BYTES` literal bytes. Neither the delta
+    /// stream nor the reconstructed file is ever held whole, and
+    /// `self.reconstructed` stays `None`.
+    ///
+    /// `committed` stays `false` throughout: the W2.5 caller flips its
+    /// own `local_committed` flag on the first byte successfully written
+    /// to the `StreamingAtomicWriter` temp.
+    async fn receive_delta_phase_streaming(
+        &mut self,
+        baseline: &mut dyn BaselineSource,
+        writer: &mut dyn ReconstructionWriter,
+        hasher: Option<FileHasher>,
+        bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        self.phase = AerorsyncSessionPhase::DeltaReceiving;
 
-    // ---- A2.0 regression pins (preserved) -------------------------------
+        let append = self.transfer_options.append != AppendMode::Off;
+        let head = self.sent_sum_head.unwrap_or(SumHead {
+            count: 0,
+            block_length: 0,
+            checksum_length: 0,
+            remainder_length: 0,
+        });
+        let block_size = head.block_length.max(0) as usize;
+        if block_size == 0 && !append {
+            return Err(AerorsyncError::invalid_frame(
+                "receive_delta_phase: block_size is zero (missing local sum_head)",
+            ));
+        }
+        let mut decoder = self.literal_decoder();
+        let mut state = DeltaStreamState::with_format(self.token_format());
+        // A server receiver arrives here straight from the sender's
+        // echoed item header, whose frame may carry the first ops.
+        let mut buf: Vec<u8> = std::mem::take(&mut self.sig_residual_after_header);
+        let mut cursor = 0usize;
+        // Decoded bytes of the literal run in progress, and whether one
+        // is open (Deflate closes it with the sync-flush tail).
+        let mut literal: Vec<u8> = Vec::new();
+        let mut in_run = false;
+        let mut batch = ApplyBatch {
+            in_place: !append && self.transfer_options.writes_in_place(),
+            hasher,
+            ..ApplyBatch::default()
+        };
+        loop {
+            self.check_cancel("receive_delta_phase")?;
+            let outcome = match decode_delta_op(&buf[cursor..], &mut state) {
+                Ok((outcome, consumed)) => {
+                    cursor += consumed;
+                    outcome
+                }
+                Err(RealWireError::DeltaTokenTruncated { .. }) => {
+                    buf.drain(..cursor);
+                    cursor = 0;
+                    let payload = self.next_data_frame(bridge).await?;
+                    buf.extend_from_slice(&payload);
+                    continue;
+                }
+                Err(other) => return Err(map_realwire_error(other, "delta stream")),
+            };
+            let (start_token_index, run_length) = match outcome {
+                DeltaOpOutcome::Op(DeltaOp::Literal { compressed_payload }) => {
+                    decoder
+                        .decode_record(&compressed_payload, &mut literal)
+                        .map_err(|e| map_realwire_error(e, "decode delta literals"))?;
+                    in_run = true;
+                    if literal.len() >= STREAMING_READ_CHUNK_BYTES {
+                        batch.push_literal(&mut literal);
+                    }
+                    batch.flush_if_full(baseline, writer, block_size).await?;
+                    continue;
+                }
+                DeltaOpOutcome::Op(DeltaOp::CopyRun {
+                    start_token_index,
+                    run_length,
+                }) => (start_token_index, run_length),
+                DeltaOpOutcome::EndFlag => {
+                    end_literal_run(&mut decoder, &mut literal, &mut in_run, &mut batch)?;
+                    break;
+                }
+            };
+            end_literal_run(&mut decoder, &mut literal, &mut in_run, &mut batch)?;
+            let out_of_range = || {
+                map_realwire_error(
+                    RealWireError::DeltaTokenOutOfRange {
+                        token_index: start_token_index,
+                        block_count: head.count,
+                    },
+                    "delta stream",
+                )
+            };
+            let end = start_token_index
+                .checked_add(i32::from(run_length))
+                .ok_or_else(out_of_range)?;
+            if start_token_index < 0 || end > head.count {
+                return Err(out_of_range());
+            }
+            if append {
+                // `receiver.c::receive_data` in append mode: the sender
+                // only ships what comes after the local bytes.
+                return Err(AerorsyncError::invalid_frame(format!(
+                    "CopyBlock({start_token_index}) in a whole-file transfer"
+                )));
+            }
+            for idx in start_token_index..end {
+                let idx = idx as u32;
+                if decoder.needs_match_data() {
+                    // `zlib` replays matched blocks while decoding.
+                    let block = baseline
+                        .read_block(idx, block_size as u32)
+                        .await
+                        .map_err(|e| {
+                            AerorsyncError::invalid_frame(format!(
+                                "read matched block {idx} for zlib: {e}"
+                            ))
+                        })?;
+                    decoder
+                        .see_match(&block)
+                        .map_err(|e| map_realwire_error(e, "replay matched block"))?;
+                }
+                batch.ops.push(EngineDeltaOp::CopyBlock(idx));
+            }
+            batch.flush_if_full(baseline, writer, block_size).await?;
+        }
 
-    #[test]
-    fn constructor_initialises_phase_and_defaults() {
-        let d = make_driver(mock_transport());
-        assert_eq!(d.phase(), AerorsyncSessionPhase::PreConnect);
-        assert!(!d.committed());
-        assert_eq!(d.protocol_version(), 0);
-        assert_eq!(d.compat_flags(), 0);
-        assert_eq!(d.checksum_seed(), 0);
-        assert!(d.negotiated_checksum_algos().is_empty());
-        assert!(d.negotiated_compression_algos().is_empty());
-        assert!(d.file_list().is_empty());
-        assert_eq!(d.data_bytes_consumed(), 0);
+        let digest_len = self.checksum_algo.digest_len();
+        loop {
+            match decode_file_checksum(&buf[cursor..], digest_len) {
+                Ok((file_checksum, _)) => {
+                    self.received_file_checksum = Some(file_checksum);
+                    break;
+                }
+                Err(RealWireError::DeltaTokenTruncated { .. }) => {
+                    buf.drain(..cursor);
+                    cursor = 0;
+                    let payload = self.next_data_frame(bridge).await?;
+                    buf.extend_from_slice(&payload);
+                }
+                Err(other) => return Err(map_realwire_error(other, "delta stream")),
+            }
+        }
+        batch.flush(baseline, writer, block_size).await?;
+        if let Some(hasher) = batch.hasher.take() {
+            if Some(hasher.finish()) != self.received_file_checksum {
+                return Err(AerorsyncError::invalid_frame(
+                    "file checksum mismatch after reconstructing the file",
+                ));
+            }
+        }
+        self.phase = AerorsyncSessionPhase::DeltaReceived;
+        Ok(())
     }
 
-    #[tokio::test]
-    async fn send_client_preamble_writes_bytes_that_decode_back() {
-        use crate::aerorsync::real_wire::decode_client_preamble;
-        let mut d = make_driver(mock_transport());
-        let mut sink = Vec::new();
-        d.send_client_preamble(&mut sink, 31, "md5,xxh64", "none,zstd")
-            .await
-            .unwrap();
-        let decoded = decode_client_preamble(&sink).unwrap();
-        assert_eq!(decoded.protocol_version, 31);
-        assert_eq!(decoded.checksum_algos, "md5,xxh64");
-        assert_eq!(decoded.compression_algos, "none,zstd");
-        assert_eq!(d.phase(), AerorsyncSessionPhase::ServerPreambleSent);
+    /// Rebuild an `EngineSignatureBlock` vec from the driver's received
+    /// `SumBlock` vec + `received_sum_head`. The strong bytes are zero-
+    /// padded to 32 (engine API shape); only the first `checksum_length`
+    /// bytes are ever consulted by the engine for matching.
+    fn wire_sigs_to_engine(&self) -> Result<Vec<EngineSignatureBlock>, AerorsyncError> {
+        let head = self.received_sum_head.as_ref().ok_or_else(|| {
+            AerorsyncError::invalid_frame("wire_sigs_to_engine: no received sum_head")
+        })?;
+        Ok(sum_blocks_to_engine(head, &self.received_signatures))
     }
 
-    #[tokio::test]
-    async fn receive_server_preamble_populates_driver_state() {
-        let encoded = canonical_server_preamble_bytes();
-        let mut d = make_driver(mock_transport());
-        let consumed = d.receive_server_preamble(&encoded).await.unwrap();
-        assert_eq!(consumed, encoded.len());
-        assert_eq!(d.protocol_version(), 31);
-        assert_eq!(d.compat_flags(), 0x07);
-        assert_eq!(d.checksum_seed(), 0xDEAD_BEEF);
-        assert_eq!(d.negotiated_checksum_algos(), "md5 xxh64");
-        assert_eq!(d.negotiated_compression_algos(), "none zstd");
-        assert_eq!(d.phase(), AerorsyncSessionPhase::ClientPreambleRecvd);
+    /// Strong-sum bytes the received signatures carry: the sum_head's
+    /// `checksum_length`, the engine's full 32 when none was received.
+    fn received_strong_len(&self) -> usize {
+        self.received_sum_head
+            .as_ref()
+            .map_or(32, |h| h.checksum_length as usize)
     }
 
-    #[tokio::test]
-    async fn receive_server_preamble_on_malformed_bytes_marks_failed() {
-        let mut d = make_driver(mock_transport());
-        let err = d.receive_server_preamble(&[0x01]).await.unwrap_err();
-        assert_eq!(err.kind, AerorsyncErrorKind::InvalidFrame);
-        assert!(err.detail.contains("server preamble"));
-        assert_eq!(d.phase(), AerorsyncSessionPhase::Failed);
+    /// Upload path, `--append`: where the remote copy ends according to
+    /// the received sum_head. `None` outside append mode.
+    fn append_offset(&self, source_len: u64) -> Result<Option<u64>, AerorsyncError> {
+        if self.transfer_options.append == AppendMode::Off {
+            return Ok(None);
+        }
+        let offset = self
+            .received_sum_head
+            .as_ref()
+            .map_or(0, SumHead::basis_length);
+        if offset > source_len {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "--append: remote copy ({offset} bytes) is longer than the source ({source_len} bytes)"
+            )));
+        }
+        Ok(Some(offset))
     }
 
-    #[tokio::test]
-    async fn driver_preamble_exchange_round_trip_matches_frozen_oracle_server_side() {
-        let Some(frozen) = RealRsyncBaselineByteTranscript::try_load_frozen() else {
-            eprintln!("frozen oracle missing: driver preamble pin skipped");
-            return;
-        };
-        let mut d = make_driver(mock_transport());
-        let consumed = d
-            .receive_server_preamble(&frozen.upload_server_to_client)
-            .await
-            .expect("driver must decode frozen server preamble");
-        assert!(consumed > 0);
-        let re_encoded = encode_server_preamble(&ServerPreamble {
-            protocol_version: d.protocol_version(),
-            compat_flags: d.compat_flags(),
-            checksum_algos: d.negotiated_checksum_algos().to_string(),
-            compression_algos: d.negotiated_compression_algos().to_string(),
-            checksum_seed: d.checksum_seed(),
-            consumed: 0,
-        });
-        assert_eq!(
-            re_encoded.as_slice(),
-            &frozen.upload_server_to_client[..consumed],
-            "driver round-trip must be byte-identical to frozen oracle prefix"
-        );
+    /// Upload path, `--inplace`: the guard for the received sum_head, or
+    /// `None` when the receiver writes to a temp file or has no basis.
+    fn inplace_guard(&self) -> Option<InplaceGuard> {
+        let head = self.received_sum_head?;
+        (self.transfer_options.writes_in_place() && head.block_length > 0)
+            .then(|| InplaceGuard::new(head))
     }
 
-    #[test]
-    fn cancel_handle_returns_clone_sharing_flag() {
-        let d = make_driver(mock_transport());
-        let h1 = d.cancel_handle();
-        let h2 = d.cancel_handle();
-        assert!(!h1.requested());
-        h1.cancel();
-        assert!(h2.requested());
-        assert!(d.cancel_handle().requested());
+    /// Literal encoder for the negotiated compressor and level. One per
+    /// session: zstd keeps its context across files.
+    fn literal_encoder(&self) -> Result<LiteralEncoder, AerorsyncError> {
+        let algo = self.compression_algo;
+        LiteralEncoder::new(algo, self.transfer_options.algorithms.level_for(algo))
+            .map_err(|e| map_realwire_error(e, "literal compressor"))
     }
 
-    // ---- A2.1 tests ------------------------------------------------------
+    fn literal_decoder(&self) -> LiteralDecoder {
+        LiteralDecoder::new(self.compression_algo)
+    }
 
-    #[tokio::test]
-    async fn driver_upload_writes_preamble_then_filelist_then_terminator() {
-        // Inbound: server preamble + a minimal synthetic signature
-        // phase (upload path in A2.2 drains ndx+iflags+sum_head+blocks
-        // after the file list: without these bytes the test would
-        // fail with TransportFailure instead of the expected stub
-        // frontier.)
-        let head = SumHead {
-            count: 1,
-            block_length: 1024,
-            checksum_length: 2,
-            remainder_length: 0,
-        };
-        let blocks = vec![make_sig_block(0xAAAAAAAA, 0x11, 2)];
-        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
-        let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
-        let transport = mock_transport_with_raw_inbound(inbound);
-        let last_raw_outbound = transport.last_raw_outbound.clone();
-        let mut d = make_driver(transport);
-        let mut sink = CollectingSink::default();
+    /// Token framing of the session: `none` sends plain int32 records.
+    fn token_format(&self) -> TokenFormat {
+        self.compression_algo.token_format()
+    }
 
-        let err = d
-            .drive_upload(
-                RemoteCommandSpec::upload("/remote/target.bin"),
-                sample_file_list_entry("target.bin"),
-                &[],
-                &MockSigAdapter::default(),
-                &mut sink,
-            )
-            .await
-            .unwrap_err();
-        // Stub frontier: sum_head not yet wired.
-        assert_eq!(err.kind, AerorsyncErrorKind::UnsupportedVersion);
-        assert_eq!(d.phase(), AerorsyncSessionPhase::Stub);
-        // A2.3: drive_upload now crosses the PreCommit/PostCommit boundary
-        // during the delta phase. MockSigAdapter returns an empty plan
-        // which still emits END_FLAG + file_checksum, counting as
-        // "first delta material written" → committed flips true.
-        assert!(d.committed());
+    /// `-c` file-list checksums are computed with xxh128 before the
+    /// preamble. Another pick is recomputed from `data`, or zero-filled
+    /// for a streamed source: the remote generator then never skips the
+    /// file on checksum, and the delta trailer still verifies it.
+    fn with_session_checksum(
+        &self,
+        mut entry: FileListEntry,
+        data: Option<&[u8]>,
+    ) -> FileListEntry {
+        if self.checksum_algo != ChecksumAlgo::Xxh128 && !entry.checksum.is_empty() {
+            entry.checksum = match data {
+                Some(data) => self.checksum_algo.digest(data),
+                None => vec![0; self.checksum_algo.digest_len()],
+            };
+        }
+        entry
+    }
 
-        // Outbound bytes = encode_client_preamble + mux(entry) + mux(terminator)
-        let guard = last_raw_outbound.lock().unwrap();
-        let outbound_arc = guard.as_ref().expect("raw stream must have been opened");
-        let outbound = outbound_arc.lock().unwrap().clone();
+    /// A2.4 entry point: drain the server's `SummaryFrame`, populate
+    /// `session_stats`, and shut the raw stream down cleanly. Call
+    /// **after** `drive_upload` / `drive_download` have reached the
+    /// post-delta stub frontier.
+    ///
+    /// Split from the main drive loop intentionally: the A4 adapter may
+    /// want to decide between an eager finish (replicate classic rsync
+    /// client UX) vs a deferred one (honour UI cancel during finish).
+    /// Keeping the split explicit at the driver boundary avoids a hidden
+    /// await that A4 couldn't interrupt cleanly.
+    pub async fn finish_session(
+        &mut self,
+        bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        match self.finish_session_inner(bridge).await {
+            Ok(()) => {
+                self.phase = AerorsyncSessionPhase::Complete;
+                Ok(())
+            }
+            Err(e) => {
+                self.phase = AerorsyncSessionPhase::Failed;
+                Err(e)
+            }
+        }
+    }
 
-        let expected_client = encode_client_preamble(&ClientPreamble {
-            protocol_version: 31,
-            // B.2: rsync wire protocol uses SPACE-separated algo lists
+    async fn finish_session_inner(
+        &mut self,
+        bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        // S8j dispatch by session role.
+        //
+        // - `Some(Receiver)` → real download against rsync 3.2.7: drain
+        //   exactly `PRE_SUMMARY_NDX_DONE_COUNT_DOWNLOAD` leading
+        //   NDX_DONE markers, then decode the SummaryFrame from the
+        //   residual.
+        // - `Some(Sender)` → real upload (A7 lane 3 scope). Upload-side
+        //   finish is wired in S8j.3+; legacy receive semantics stay
+        //   here for now to keep the A2.4 mock upload tests working.
+        // - `None` → legacy mock test that drove `finish_session`
+        //   directly on a synthesised inbound buffer without entering
+        //   `drive_*_inner`. Skip the drain entirely: the mock inbound
+        //   never contains leading NDX_DONE bytes and the peek-based
+        //   heuristic of the drain would misread a varlong value of 0
+        //   as a marker.
+        match self.session_role {
+            Some(SessionRole::Receiver) => {
+                // Download against real rsync: drain the 3 leading
+                // NDX_DONE markers, decode the summary the server
+                // emitted, send our own NDX_DONE ACK, and consume the
+                // trailing marker rsync echoes back.
+                self.drain_leading_ndx_done_download(bridge).await?;
+                self.receive_summary_phase(bridge).await?;
+                self.emit_ndx_done_marker().await?;
+                self.read_trailing_ndx_done(bridge).await?;
+            }
+            Some(SessionRole::Sender) => {
+                match self.remote_command_flavor {
+                    RemoteCommandFlavor::WrapperParity => {
+                        self.finish_stock_rsync_sender_tail(bridge).await?;
+                    }
+                    RemoteCommandFlavor::AerorsyncServe => {
+                        // Dev helper compatibility: aerorsync_serve
+                        // still consumes the legacy NDX_DONE +
+                        // SummaryFrame tail.
+                        self.emit_summary_phase().await?;
+                        self.read_trailing_ndx_done(bridge).await?;
+                        self.session_stats.bytes_sent = self.sent_data_bytes;
+                        self.session_stats.bytes_received = self.received_raw_bytes;
+                    }
+                }
+            }
+            None => {
+                // U-10: every public `drive_*` entry point sets
+                // `session_role` before `open_raw_stream_internal`, so
+                // reaching `finish_session` with `session_role = None`
+                // means a caller skipped the drive loop entirely. Refuse
+                // the call with an explicit illegal-state error instead
+                // of silently running receive semantics: that path used
+                // to mask wrong-role bugs in mock tests.
+                return Err(AerorsyncError::new(
+                    AerorsyncErrorKind::IllegalStateTransition,
+                    "finish_session called without a session_role: invoke drive_upload*/drive_download* first",
+                ));
+            }
+        }
+        self.shutdown_raw_stream().await?;
+        Ok(())
+    }
+
+    // --- S8j NDX_DONE drain (download direction) -------------------------
+
+    /// Pull MSG_DATA frames until we have accumulated at least
+    /// `PRE_SUMMARY_NDX_DONE_COUNT_DOWNLOAD` bytes, verify that the
+    /// first `N` of them are `0x00` (NDX_DONE markers), drop them, and
+    /// stash the remainder into `summary_seed` for
+    /// `receive_summary_phase` to prepend.
+    ///
+    /// Empty-drain policy: tests that synthesise a clean `SummaryFrame`
+    /// with NO leading markers (all A2.4 mock tests as written) MUST
+    /// keep working. We detect that case by a peek at the first byte
+    ///: if it is not `0x00`, the drain is a no-op and the summary
+    /// decoder sees the data unchanged.
+    async fn drain_leading_ndx_done_download(
+        &mut self,
+        bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        let want = PRE_SUMMARY_NDX_DONE_COUNT_DOWNLOAD;
+        let mut buf: Vec<u8> = Vec::new();
+        // Pull at least one frame to peek.
+        let first = self.next_data_frame(bridge).await?;
+        buf.extend_from_slice(&first);
+
+        // Empty-drain: if the first byte is not NDX_DONE, rsync did not
+        // emit leading markers on this profile (synthesised mocks). Pass
+        // the whole payload through to the summary decoder as-is.
+        if buf.first().copied() != Some(0x00) {
+            self.summary_seed = buf;
+            return Ok(());
+        }
+
+        // Pull more frames until we can cover `want` leading bytes AND
+        // verify they are all `0x00`.
+        while buf.len() < want {
+            let more = self.next_data_frame(bridge).await?;
+            buf.extend_from_slice(&more);
+        }
+        for (i, b) in buf.iter().take(want).enumerate() {
+            if *b != 0x00 {
+                return Err(AerorsyncError::invalid_frame(format!(
+                    "expected {want} leading NDX_DONE markers before SummaryFrame, \
+                     found non-zero byte 0x{b:02X} at offset {i}"
+                )));
+            }
+        }
+        // Drop the drained markers, keep the residual.
+        buf.drain(..want);
+        self.summary_seed = buf;
+        Ok(())
+    }
+
+    // --- A2.4 summary phase + shutdown ----------------------------------
+
+    /// Read the `SummaryFrame` from the data stream, populate
+    /// `received_summary` + `session_stats`, and advance phase.
+    ///
+    /// S8j: preloads the decode buffer from `summary_seed`, which the
+    /// `drain_leading_ndx_done_download` helper populates after
+    /// dropping the leading `NDX_DONE` markers rsync 3.2.7 interleaves
+    /// between the file-csum and the summary on the server→client
+    /// stream (see `main.c::read_final_goodbye`).
+    async fn receive_summary_phase(
+        &mut self,
+        bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        self.phase = AerorsyncSessionPhase::SummaryReceiving;
+        let mut buf: Vec<u8> = std::mem::take(&mut self.summary_seed);
+        let protocol = self.protocol_version;
+        loop {
+            self.check_cancel("receive_summary_phase")?;
+            if !buf.is_empty() {
+                match decode_summary_frame(&buf, protocol) {
+                    Ok((frame, consumed)) => {
+                        buf.drain(..consumed);
+                        // The goodbye NDX_DONE may share the frame.
+                        self.summary_seed = buf;
+                        self.session_stats.bytes_received = frame.total_read as u64;
+                        self.session_stats.bytes_sent = frame.total_written as u64;
+                        self.received_summary = Some(frame);
+                        self.phase = AerorsyncSessionPhase::SummaryReceived;
+                        return Ok(());
+                    }
+                    Err(RealWireError::TruncatedBuffer { .. })
+                    | Err(RealWireError::DeltaTokenTruncated { .. }) => {
+                        // need more bytes
+                    }
+                    Err(other) => {
+                        return Err(map_realwire_error(other, "summary frame"));
+                    }
+                }
+            }
+            let payload = self.next_data_frame(bridge).await?;
+            buf.extend_from_slice(&payload);
+        }
+    }
+
+    /// Tear the raw stream down cleanly. Advances phase to `Complete`.
+    async fn shutdown_raw_stream(&mut self) -> Result<(), AerorsyncError> {
+        if let Some(mut stream) = self.stream.take() {
+            stream.shutdown().await?;
+        }
+        if let Some(recorder) = self.batch_recorder.take() {
+            recorder.finish().await?;
+        }
+        self.phase = AerorsyncSessionPhase::Complete;
+        Ok(())
+    }
+
+    // --- S8j upload finish helpers ---------------------------------------
+
+    /// Write a single `NDX_DONE` marker (1 byte `0x00`) wrapped in a
+    /// MSG_DATA mux frame. `write_data_frame` enforces the wrapping.
+    async fn emit_ndx_done_marker(&mut self) -> Result<(), AerorsyncError> {
+        self.write_data_frame(&[0x00]).await
+    }
+
+    /// Finish an upload against stock `rsync --server` while this client
+    /// is the sender. Replicates the exact sender-side tail of
+    /// `sender.c::send_files` + `main.c::client_run`:
+    ///
+    /// 1. `sender_phase_loop`: read-then-echo ping-pong with the
+    ///    generator's phase markers. For `max_phase = 2` (proto >= 29)
+    ///    the generator writes three NDX_DONE triggers on the socket
+    ///    (lines 2337, 2366, 2370); the sender echoes the first two and
+    ///    breaks on the third (sender.c:232-258).
+    /// 2. Final `write_ndx(NDX_DONE)` after the loop (sender.c:462).
+    /// 3. `handle_stats(-1)` client-sender branch: no socket writes
+    ///    (main.c:325-358 early-returns when !am_server).
+    /// 4. `read_final_goodbye` (proto >= 31): read the generator's 4th
+    ///    NDX_DONE (generator.c:2376), write the sender-branch ACK
+    ///    (main.c:889), read the parent-generator's final NDX_DONE
+    ///    (main.c:1121).
+    ///
+    /// Outbound total on the app stream: 4 NDX_DONE markers.
+    /// Inbound total on the app stream: 5 NDX_DONE markers.
+    /// Matches the frozen upload capture exactly.
+    async fn finish_stock_rsync_sender_tail(
+        &mut self,
+        bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        self.phase = AerorsyncSessionPhase::SummaryReceiving;
+        // (1) phase loop with ping-pong echoes
+        self.sender_phase_loop(bridge).await?;
+        // (2) sender.c:462: final NDX_DONE once the phase loop breaks
+        self.emit_ndx_done_marker().await?;
+        // (3) handle_stats(-1): no socket write for the client sender,
+        // only the batch copy under --write-batch
+        let total_size = self.file_list.iter().map(|e| e.size).sum();
+        self.record_batch_stats(total_size).await?;
+        // (4) read_final_goodbye + proto-31 ACK
+        self.read_final_goodbye_marker(bridge).await?;
+        self.received_summary = None;
+        self.session_stats.bytes_sent = self.sent_data_bytes;
+        self.session_stats.bytes_received = self.received_raw_bytes;
+        self.phase = AerorsyncSessionPhase::SummaryReceived;
+        Ok(())
+    }
+
+    /// Ping-pong phase loop mirroring `sender.c::send_files` lines
+    /// 225-258. For each generator phase trigger we read from the wire,
+    /// we either echo NDX_DONE (phase advance not yet past max) or
+    /// break out of the loop (phase > max_phase). The final post-loop
+    /// NDX_DONE (sender.c:462) is emitted by the caller.
+    ///
+    /// For proto >= 29 `max_phase = 2`: the loop reads 3 NDX_DONE
+    /// triggers and writes 2 echoes. This ordering matters against
+    /// stock `rsync --server`: the generator coordinates with its
+    /// receiver child via internal `msgdone_cnt` increments that only
+    /// happen after the sender's echo reaches the receiver. A
+    /// fire-and-forget burst of NDX_DONEs (the pre-fix behaviour)
+    /// racewith the generator's phase bookkeeping and left the
+    /// receiver child stuck in `read_final_goodbye` long enough for
+    /// the generator to see EOF on its error pipe (exit 12).
+    async fn sender_phase_loop(
+        &mut self,
+        bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        let max_phase: i32 = if self.protocol_version >= 29 { 2 } else { 1 };
+        let mut phase: i32 = 0;
+        loop {
+            self.check_cancel("sender_phase_loop")?;
+            let Some(()) = self
+                .try_read_ndx_done_marker(bridge, "sender_phase_loop: phase trigger")
+                .await?
+            else {
+                return Err(AerorsyncError::invalid_frame(
+                    "sender_phase_loop: remote closed before all phase markers",
+                ));
+            };
+            phase += 1;
+            if phase > max_phase {
+                break;
+            }
+            // sender.c:256: echo NDX_DONE back to advance the receiver's phase loop
+            self.emit_ndx_done_marker().await?;
+        }
+        Ok(())
+    }
+
+    async fn read_final_goodbye_marker(
+        &mut self,
+        bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        let Some(()) = self
+            .try_read_ndx_done_marker(bridge, "read_final_goodbye first marker")
+            .await?
+        else {
+            return Ok(());
+        };
+
+        if self.protocol_version >= 31 {
+            self.emit_ndx_done_marker().await?;
+            let Some(()) = self
+                .try_read_ndx_done_marker(bridge, "read_final_goodbye final marker")
+                .await?
+            else {
+                return Ok(());
+            };
+        }
+        Ok(())
+    }
+
+    async fn try_read_ndx_done_marker(
+        &mut self,
+        bridge: &mut dyn EventSink,
+        context: &'static str,
+    ) -> Result<Option<()>, AerorsyncError> {
+        loop {
+            if let Some(&b) = self.summary_seed.first() {
+                self.summary_seed.drain(..1);
+                if b != 0x00 {
+                    return Err(AerorsyncError::invalid_frame(format!(
+                        "{context}: expected NDX_DONE (0x00), got 0x{b:02X}"
+                    )));
+                }
+                return Ok(Some(()));
+            }
+
+            match self.next_data_frame(bridge).await {
+                Ok(bytes) => {
+                    if bytes.is_empty() {
+                        continue;
+                    }
+                    self.summary_seed.extend_from_slice(&bytes);
+                }
+                Err(e) if e.kind == AerorsyncErrorKind::TransportFailure => return Err(e),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Emit the end-of-session `NDX_DONE` + `SummaryFrame` pair that
+    /// rsync 3.2.7 expects from the **sender** (client in upload mode)
+    /// after the delta stream and its file-level checksum trailer.
+    ///
+    /// Wire layout (matches `main.c::read_final_goodbye` + `handle_stats`):
+    /// ```text
+    ///   [0x00]                                  // NDX_DONE marker
+    ///   encode_summary_frame(frame, 31)        // 5 × varlong(_, _, 3)
+    /// ```
+    /// Both chunks go out in a single MSG_DATA frame for wire economy;
+    /// rsync's mux layer accepts either bundled or split.
+    ///
+    /// Field population:
+    /// - `total_read` = `self.received_raw_bytes` (bytes consumed from
+    ///   the remote via `next_data_frame`, incl. signatures).
+    /// - `total_written` = `self.sent_data_bytes` (bytes written via
+    ///   `write_data_frame`, incl. file list, signatures echo, delta).
+    /// - `total_size` = size of the first entry in `self.file_list`
+    ///   (single-file prototype scope).
+    /// - `flist_buildtime` / `flist_xfertime` = `Some(0)`. Rsync's
+    ///   `handle_stats` treats these as informational (never validated
+    ///   as `> 0`); a future S8k will wire actual `Instant` measurement
+    ///   if lane 3 telemetry shows the zeros are surprising.
+    async fn emit_summary_phase(&mut self) -> Result<(), AerorsyncError> {
+        self.phase = AerorsyncSessionPhase::SummaryReceiving;
+        let total_size = self.file_list.first().map(|e| e.size).unwrap_or(0);
+        // `SummaryFrame` snapshots the counters as of the moment the
+        // client decided to announce them: matching rsync 3.2.7's
+        // `handle_stats`, which reads `stats.total_written` before
+        // emitting the summary itself (so the reported number does NOT
+        // include the summary bytes being written).
+        let frame = SummaryFrame {
+            total_read: self.received_raw_bytes as i64,
+            total_written: self.sent_data_bytes as i64,
+            total_size,
+            flist_buildtime: Some(0),
+            flist_xfertime: Some(0),
+        };
+        let mut payload = Vec::with_capacity(1 + 9 * 5);
+        payload.push(0x00); // NDX_DONE
+        payload.extend_from_slice(&encode_summary_frame(&frame, self.protocol_version));
+        self.write_data_frame(&payload).await?;
+        // `session_stats` is a post-emit aggregate: it DOES include the
+        // summary bytes we just wrote, so downstream consumers see the
+        // actual wire-level totals for the session.
+        self.session_stats.bytes_sent = self.sent_data_bytes;
+        self.session_stats.bytes_received = self.received_raw_bytes;
+        self.received_summary = Some(frame);
+        self.phase = AerorsyncSessionPhase::SummaryReceived;
+        Ok(())
+    }
+
+    /// Read the final `NDX_DONE` (1 byte `0x00`) the rsync receiver
+    /// writes back in `read_final_goodbye` line 887 after consuming
+    /// the sender's `NDX_DONE + SummaryFrame`. Tolerates clean EOF
+    /// (some rsync builds close the channel before the byte flushes).
+    async fn read_trailing_ndx_done(
+        &mut self,
+        bridge: &mut dyn EventSink,
+    ) -> Result<(), AerorsyncError> {
+        if let Some(&b) = self.summary_seed.first() {
+            self.summary_seed.drain(..1);
+            if b != 0x00 {
+                return Err(AerorsyncError::invalid_frame(format!(
+                    "expected trailing NDX_DONE (0x00), got 0x{b:02X}"
+                )));
+            }
+            return Ok(());
+        }
+        // Best-effort read: if the stream is already closed, or the
+        // next frame is empty, treat as clean completion.
+        match self.next_data_frame(bridge).await {
+            Ok(bytes) => {
+                if let Some(&b) = bytes.first() {
+                    if b != 0x00 {
+                        return Err(AerorsyncError::invalid_frame(format!(
+                            "expected trailing NDX_DONE (0x00), got 0x{b:02X}"
+                        )));
+                    }
+                }
+                // bytes.is_empty() is valid too: nothing to check.
+                Ok(())
+            }
+            Err(e) if e.kind == AerorsyncErrorKind::TransportFailure => {
+                // EOF is an acceptable end of a clean rsync session.
+                tracing::debug!(
+                    "read_trailing_ndx_done: remote closed before trailing marker ({})",
+                    e.detail
+                );
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn check_cancel(&self, op: &'static str) -> Result<(), AerorsyncError> {
+        if self.cancel_handle.requested() {
+            Err(AerorsyncError::cancelled(format!(
+                "driver cancelled before {op}"
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    // --- A2.0 preamble helpers preserved for regression pins ---
+    //
+    // These operate on in-memory buffers rather than the transport. They
+    // were the A2.0 surface; A2.1 keeps them as-is so the existing
+    // frozen-oracle pins and round-trip tests do not regress. The
+    // production drive loop uses `perform_preamble_exchange` instead.
+
+    #[allow(clippy::unused_async)] // kept async for API symmetry with A2.0
+    async fn send_client_preamble(
+        &mut self,
+        sink: &mut Vec<u8>,
+        protocol_version: u32,
+        checksum_algos: &str,
+        compression_algos: &str,
+    ) -> Result<(), AerorsyncError> {
+        let preamble = ClientPreamble {
+            protocol_version,
+            checksum_algos: checksum_algos.to_string(),
+            compression_algos: compression_algos.to_string(),
+            consumed: 0,
+        };
+        let bytes = encode_client_preamble(&preamble);
+        sink.extend_from_slice(&bytes);
+        self.phase = AerorsyncSessionPhase::ServerPreambleSent;
+        Ok(())
+    }
+
+    #[allow(clippy::unused_async)]
+    async fn receive_server_preamble(&mut self, source: &[u8]) -> Result<usize, AerorsyncError> {
+        let preamble = decode_server_preamble(source).map_err(|e| {
+            self.phase = AerorsyncSessionPhase::Failed;
+            map_realwire_error(e, "server preamble")
+        })?;
+        self.protocol_version = preamble.protocol_version;
+        self.compat_flags = preamble.compat_flags;
+        self.checksum_seed = preamble.checksum_seed;
+        self.negotiated_checksum_algos = preamble.checksum_algos;
+        self.negotiated_compression_algos = preamble.compression_algos;
+        self.pick_negotiated_algorithms()?;
+        self.phase = AerorsyncSessionPhase::ClientPreambleRecvd;
+        Ok(preamble.consumed)
+    }
+}
+
+fn map_realwire_error(err: RealWireError, context: &'static str) -> AerorsyncError {
+    AerorsyncError::new(
+        AerorsyncErrorKind::InvalidFrame,
+        format!("{context}: {err}"),
+    )
+}
+
+// --- tree session wire helpers ----------------------------------------------
+
+/// Decoder signature accepted by `next_tree_message`.
+type TreeMessageDecoder<M> = fn(&[u8], &mut NdxState) -> Result<(M, usize), RealWireError>;
+
+/// `ndx` + `iflags` + the optional basis type byte, alternate name and
+/// xattr exchange: the `write_ndx_and_attrs` / `read_ndx_and_attrs`
+/// prefix of every per-file message in both directions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ItemHeader {
+    ndx: i32,
+    iflags: u16,
+    basis_type: Option<u8>,
+    xname: Option<Vec<u8>>,
+    /// `ITEM_REPORT_XATTR` payload (`send_xattr_request`).
+    xattrs: Vec<XattrSlot>,
+}
+
+/// One abbreviated xattr in an `ITEM_REPORT_XATTR` exchange: its
+/// 1-based position in the entry's xattr set, plus the full value on
+/// the sender's side (`None` in the generator's request).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct XattrSlot {
+    num: usize,
+    value: Option<Vec<u8>>,
+}
+
+impl ItemHeader {
+    fn encode(&self, state: &mut NdxState) -> Vec<u8> {
+        let mut out = encode_ndx(self.ndx, state);
+        out.extend_from_slice(&encode_item_flags(self.iflags));
+        if self.iflags & ITEM_BASIS_TYPE_FOLLOWS != 0 {
+            out.push(self.basis_type.unwrap_or(0));
+        }
+        if self.iflags & ITEM_XNAME_FOLLOWS != 0 {
+            out.extend_from_slice(&encode_vstring(self.xname.as_deref().unwrap_or_default()));
+        }
+        if self.iflags & ITEM_REPORT_XATTR != 0 {
+            let mut prior = 0;
+            for slot in &self.xattrs {
+                out.extend_from_slice(&encode_varint((slot.num - prior) as i32));
+                prior = slot.num;
+                if let Some(value) = &slot.value {
+                    out.extend_from_slice(&encode_varint(value.len() as i32));
+                    out.extend_from_slice(value);
+                }
+            }
+            out.push(0);
+        }
+        out
+    }
+}
+
+/// Decode the part of an [`ItemHeader`] that follows the ndx.
+/// `with_values` reads the sender's side of the xattr exchange (a value
+/// after every number) rather than the generator's.
+fn decode_item_attrs(
+    buf: &[u8],
+    ndx: i32,
+    with_values: bool,
+) -> Result<(ItemHeader, usize), RealWireError> {
+    let (iflags, mut cursor) = decode_item_flags(buf)?;
+    let basis_type = if iflags & ITEM_BASIS_TYPE_FOLLOWS != 0 {
+        let byte = *buf.get(cursor).ok_or(RealWireError::TruncatedBuffer {
+            at: "basis_type",
+            needed: cursor + 1,
+            available: buf.len(),
+        })?;
+        cursor += 1;
+        Some(byte)
+    } else {
+        None
+    };
+    let xname = if iflags & ITEM_XNAME_FOLLOWS != 0 {
+        let (name, consumed) = decode_vstring(&buf[cursor..])?;
+        cursor += consumed;
+        Some(name)
+    } else {
+        None
+    };
+    let mut xattrs = Vec::new();
+    if iflags & ITEM_REPORT_XATTR != 0 {
+        let mut num = 0usize;
+        loop {
+            let (rel, consumed) = decode_varint(&buf[cursor..])?;
+            cursor += consumed;
+            if rel == 0 {
+                break;
+            }
+            num += usize::try_from(rel).map_err(|_| RealWireError::MetadataRefOutOfRange {
+                table: "xattr request",
+                index: rel,
+                known: num,
+            })?;
+            let value = if with_values {
+                let (len, consumed) = decode_varint(&buf[cursor..])?;
+                cursor += consumed;
+                let len =
+                    usize::try_from(len).map_err(|_| RealWireError::MetadataRefOutOfRange {
+                        table: "xattr value length",
+                        index: len,
+                        known: 0,
+                    })?;
+                let bytes =
+                    buf.get(cursor..cursor + len)
+                        .ok_or(RealWireError::TruncatedBuffer {
+                            at: "xattr value",
+                            needed: cursor + len,
+                            available: buf.len(),
+                        })?;
+                cursor += len;
+                Some(bytes.to_vec())
+            } else {
+                None
+            };
+            xattrs.push(XattrSlot { num, value });
+        }
+    }
+    Ok((
+        ItemHeader {
+            ndx,
+            iflags,
+            basis_type,
+            xname,
+            xattrs,
+        },
+        cursor,
+    ))
+}
+
+/// Receiver-side state that spans the file lists of one tree session.
+#[derive(Debug, Default)]
+struct ReceivedListState {
+    /// `recv_file_entry`'s "last entry", for the `XMIT_SAME_*` fields.
+    previous: Option<FileListEntry>,
+    /// ACL and xattr sets later entries refer back to.
+    tables: FlistMetaTables,
+    /// Path of every hard-link group's first entry, by group number.
+    hlink_leaders: HashMap<i32, String>,
+}
+
+fn tree_entry_for(
+    tree: &TreeFileList,
+    ndx: i32,
+) -> Result<(usize, &FileListEntry), AerorsyncError> {
+    tree.entry(ndx)
+        .ok_or_else(|| AerorsyncError::invalid_frame(format!("ndx {ndx} outside the file list")))
+}
+
+/// Generator side of `ITEM_REPORT_XATTR`: every xattr of `entry` that
+/// arrived abbreviated. The receiver keeps no xattr cache, so all of
+/// them are asked for.
+fn xattr_request(entry: &FileListEntry) -> Vec<XattrSlot> {
+    entry
+        .xattrs
+        .iter()
+        .flatten()
+        .enumerate()
+        .filter(|(_, item)| matches!(item.value, XattrValue::Abbrev { .. }))
+        .map(|(i, _)| XattrSlot {
+            num: i + 1,
+            value: None,
+        })
+        .collect()
+}
+
+/// Sender side: fill in the value of every xattr the generator asked
+/// for (`send_xattr_request` with a file name).
+fn answer_xattr_request(
+    header: &mut ItemHeader,
+    entry: &FileListEntry,
+) -> Result<(), AerorsyncError> {
+    let items = entry.xattrs.as_deref().unwrap_or_default();
+    for slot in &mut header.xattrs {
+        let value = slot
+            .num
+            .checked_sub(1)
+            .and_then(|i| items.get(i))
+            .and_then(|item| match &item.value {
+                XattrValue::Full(value) => Some(value.clone()),
+                XattrValue::Abbrev { .. } => None,
+            })
+            .ok_or_else(|| {
+                AerorsyncError::invalid_frame(format!(
+                    "remote generator asked for xattr #{} of {:?}, which has {}",
+                    slot.num,
+                    entry.path,
+                    items.len()
+                ))
+            })?;
+        slot.value = Some(value);
+    }
+    Ok(())
+}
+
+/// Receiver side: replace the abbreviated xattrs the sender answered
+/// for with their values.
+fn store_xattr_answer(
+    entry: &mut FileListEntry,
+    header: &ItemHeader,
+) -> Result<(), AerorsyncError> {
+    let path = entry.path.clone();
+    let items = entry.xattrs.as_deref_mut().unwrap_or_default();
+    for slot in &header.xattrs {
+        let (Some(item), Some(value)) = (
+            slot.num.checked_sub(1).and_then(|i| items.get_mut(i)),
+            &slot.value,
+        ) else {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "remote sender answered for unknown xattr #{} of {path:?}",
+                slot.num
+            )));
+        };
+        item.value = XattrValue::Full(value.clone());
+    }
+    Ok(())
+}
+
+/// What the remote generator sends a tree-upload sender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GeneratorMessage {
+    Done,
+    /// `sums` is present iff `ITEM_TRANSFER` is set.
+    Request {
+        header: ItemHeader,
+        sums: Option<(SumHead, Vec<SumBlock>)>,
+    },
+    Unexpected(i32),
+}
+
+fn decode_generator_message(
+    buf: &[u8],
+    state: &mut NdxState,
+) -> Result<(GeneratorMessage, usize), RealWireError> {
+    decode_generator_message_with(buf, state, true)
+}
+
+/// `--append` sessions: the generator sends the sum_head without blocks.
+fn decode_append_generator_message(
+    buf: &[u8],
+    state: &mut NdxState,
+) -> Result<(GeneratorMessage, usize), RealWireError> {
+    decode_generator_message_with(buf, state, false)
+}
+
+fn decode_generator_message_with(
+    buf: &[u8],
+    state: &mut NdxState,
+    with_blocks: bool,
+) -> Result<(GeneratorMessage, usize), RealWireError> {
+    let (ndx, mut cursor) = decode_ndx(buf, state)?;
+    if ndx == NDX_DONE {
+        return Ok((GeneratorMessage::Done, cursor));
+    }
+    if ndx < 0 {
+        return Ok((GeneratorMessage::Unexpected(ndx), cursor));
+    }
+    let (header, consumed) = decode_item_attrs(&buf[cursor..], ndx, false)?;
+    cursor += consumed;
+    if header.iflags & ITEM_TRANSFER == 0 {
+        return Ok((GeneratorMessage::Request { header, sums: None }, cursor));
+    }
+    let (head, consumed) = decode_sum_head(&buf[cursor..])?;
+    cursor += consumed;
+    let block_count = if with_blocks { head.count as usize } else { 0 };
+    // Size check first: a large signature set spans many frames and
+    // must not be re-decoded block by block on every retry.
+    let strong_len = head.checksum_length as usize;
+    let needed = cursor + block_count * (4 + strong_len);
+    if buf.len() < needed {
+        return Err(RealWireError::TruncatedBuffer {
+            at: "tree sum_blocks",
+            needed,
+            available: buf.len(),
+        });
+    }
+    let mut blocks = Vec::with_capacity(block_count);
+    for _ in 0..block_count {
+        let (block, consumed) = decode_sum_block(&buf[cursor..], strong_len)?;
+        cursor += consumed;
+        blocks.push(block);
+    }
+    Ok((
+        GeneratorMessage::Request {
+            header,
+            sums: Some((head, blocks)),
+        },
+        cursor,
+    ))
+}
+
+/// What the remote sender sends a tree-download receiver. The delta
+/// tokens after a transfer item's `sum_head` echo are read separately
+/// by `read_tree_delta`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SenderMessage {
+    Done,
+    FlistEof,
+    /// `NDX_FLIST_OFFSET - dir_ndx`: a file list for `dir_ndx` follows.
+    ExtraList {
+        dir_ndx: i32,
+    },
+    Item {
+        header: ItemHeader,
+        sum_head: Option<SumHead>,
+    },
+    Unexpected(i32),
+}
+
+fn decode_sender_message(
+    buf: &[u8],
+    state: &mut NdxState,
+) -> Result<(SenderMessage, usize), RealWireError> {
+    let (ndx, mut cursor) = decode_ndx(buf, state)?;
+    match ndx {
+        NDX_DONE => return Ok((SenderMessage::Done, cursor)),
+        NDX_FLIST_EOF => return Ok((SenderMessage::FlistEof, cursor)),
+        n if n <= NDX_FLIST_OFFSET => {
+            let dir_ndx = NDX_FLIST_OFFSET - n;
+            return Ok((SenderMessage::ExtraList { dir_ndx }, cursor));
+        }
+        n if n < 0 => return Ok((SenderMessage::Unexpected(n), cursor)),
+        _ => {}
+    }
+    let (header, consumed) = decode_item_attrs(&buf[cursor..], ndx, true)?;
+    cursor += consumed;
+    if header.iflags & ITEM_TRANSFER == 0 {
+        return Ok((
+            SenderMessage::Item {
+                header,
+                sum_head: None,
+            },
+            cursor,
+        ));
+    }
+    let (head, consumed) = decode_sum_head(&buf[cursor..])?;
+    cursor += consumed;
+    Ok((
+        SenderMessage::Item {
+            header,
+            sum_head: Some(head),
+        },
+        cursor,
+    ))
+}
+
+/// Decode errors that only mean "the frame boundary fell inside this
+/// message": pull another MSG_DATA frame and retry.
+fn needs_more_bytes(err: &RealWireError) -> bool {
+    match err {
+        RealWireError::TruncatedBuffer { .. }
+        | RealWireError::NdxTruncated { .. }
+        | RealWireError::InvalidNameLen { .. }
+        | RealWireError::InvalidAlgoListLen { .. } => true,
+        RealWireError::DeltaTokenTruncated { at, .. } => *at != "deflated_len_zero",
+        _ => false,
+    }
+}
+
+/// `block_size == 0` plan: the receiver has no baseline, the whole
+/// source travels as one literal.
+fn whole_file_plan(source_data: &[u8]) -> EngineDeltaPlan {
+    let ops = if source_data.is_empty() {
+        Vec::new()
+    } else {
+        vec![EngineDeltaOp::Literal(source_data.to_vec())]
+    };
+    EngineDeltaPlan {
+        ops,
+        copy_blocks: 0,
+        literal_bytes: source_data.len() as u64,
+        total_delta_bytes: source_data.len() as u64,
+        savings_ratio: 1.0,
+        should_use_delta: true,
+    }
+}
+
+/// First byte the file-level checksum covers: `--append` only sums the
+/// appended data (`match.c::match_sums` leaves the skipped prefix out),
+/// `--append-verify` and every other mode the whole file.
+fn checksum_start(append: AppendMode, append_from: Option<u64>) -> u64 {
+    match (append, append_from) {
+        (AppendMode::Append, Some(offset)) => offset,
+        _ => 0,
+    }
+}
+
+/// `match.c` with `updating_basis_file`: an in-place receiver has
+/// already overwritten everything before its write offset, so a block
+/// match pointing there must travel as literal data instead. A match at
+/// or after the write offset is still safe to copy.
+struct InplaceGuard {
+    head: SumHead,
+    /// Receiver write offset after the ops seen so far.
+    out_offset: u64,
+    /// Bytes turned from block matches into literals.
+    converted_bytes: u64,
+}
+
+impl InplaceGuard {
+    fn new(head: SumHead) -> Self {
+        Self {
+            head,
+            out_offset: 0,
+            converted_bytes: 0,
+        }
+    }
+
+    /// Rewrite `ops` (the next ops of the file, in order). `window` holds
+    /// the source bytes starting at `window_start` and must cover every
+    /// block match that needs converting.
+    fn apply(
+        &mut self,
+        ops: &mut [EngineDeltaOp],
+        window: &[u8],
+        window_start: u64,
+    ) -> Result<(), AerorsyncError> {
+        for op in ops.iter_mut() {
+            match op {
+                EngineDeltaOp::Literal(data) => self.out_offset += data.len() as u64,
+                EngineDeltaOp::CopyBlock(idx) => {
+                    let len = self.head.block_len(*idx);
+                    let basis = u64::from(*idx) * self.head.block_length as u64;
+                    if basis < self.out_offset {
+                        let bytes = self
+                            .out_offset
+                            .checked_sub(window_start)
+                            .and_then(|start| {
+                                let start = usize::try_from(start).ok()?;
+                                window.get(start..start.checked_add(len)?)
+                            })
+                            .ok_or_else(|| {
+                                AerorsyncError::new(
+                                    AerorsyncErrorKind::Internal,
+                                    format!(
+                                        "in-place guard lost the source bytes at offset {}",
+                                        self.out_offset
+                                    ),
+                                )
+                            })?;
+                        *op = EngineDeltaOp::Literal(bytes.to_vec());
+                        self.converted_bytes += len as u64;
+                    }
+                    self.out_offset += len as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Signatures of `destination_data` as the generator puts them on the
+/// wire: `signature_block_size` blocks, strong sums truncated to
+/// `A2_2_DOWNLOAD_S2LENGTH`.
+fn build_wire_signatures(
+    destination_data: &[u8],
+    adapter: &dyn DeltaEngineAdapter,
+) -> (SumHead, Vec<SumBlock>) {
+    let block_size = signature_block_size(adapter, destination_data.len() as u64);
+    let engine_sigs = adapter.build_signatures(destination_data, block_size);
+    let sum_blocks: Vec<SumBlock> = engine_sigs.iter().map(wire_sum_block).collect();
+    let head = wire_sum_head(destination_data.len() as u64, block_size, sum_blocks.len());
+    (head, sum_blocks)
+}
+
+/// Compose the sum_head for `count` blocks of `block_size` over a basis
+/// of `file_size` bytes. Remainder is (file_size mod block_size):
+/// identical to rsync's own derivation.
+fn wire_sum_head(file_size: u64, block_size: usize, count: usize) -> SumHead {
+    let remainder_length = if block_size > 0 {
+        (file_size % block_size as u64) as i32
+    } else {
+        0
+    };
+    SumHead {
+        count: count as i32,
+        block_length: block_size as i32,
+        checksum_length: A2_2_DOWNLOAD_S2LENGTH,
+        remainder_length,
+    }
+}
+
+/// One engine signature as a wire sum block, the strong sum truncated to
+/// `A2_2_DOWNLOAD_S2LENGTH`.
+fn wire_sum_block(sig: &EngineSignatureBlock) -> SumBlock {
+    let s2length = A2_2_DOWNLOAD_S2LENGTH as usize;
+    SumBlock {
+        rolling: sig.rolling,
+        strong: sig.strong[..s2length.min(sig.strong.len())].to_vec(),
+    }
+}
+
+/// Rebuild `EngineSignatureBlock`s from wire blocks. The strong bytes are
+/// zero-padded to 32 (engine API shape); only the first
+/// `checksum_length` bytes are ever consulted by the engine for matching.
+fn sum_blocks_to_engine(head: &SumHead, blocks: &[SumBlock]) -> Vec<EngineSignatureBlock> {
+    let block_len = head.block_length as u32;
+    blocks
+        .iter()
+        .enumerate()
+        .map(|(idx, wire)| {
+            let mut strong = [0u8; 32];
+            let take = wire.strong.len().min(32);
+            strong[..take].copy_from_slice(&wire.strong[..take]);
+            EngineSignatureBlock {
+                index: idx as u32,
+                rolling: wire.rolling,
+                strong,
+                block_len,
+            }
+        })
+        .collect()
+}
+
+/// Engine ops to wire ops in one go, for the tree senders. Literals go
+/// through the session's `encoder`, consecutive ones forming one run
+/// that ends at the next matched block or at the end of the file; every
+/// `CopyBlock(idx)` becomes a one-block `CopyRun`. [`DeltaEmitter`] does
+/// the same incrementally for the single-file senders.
+fn engine_ops_to_wire_ops(
+    ops: &[EngineDeltaOp],
+    encoder: &mut LiteralEncoder,
+) -> Result<Vec<DeltaOp>, AerorsyncError> {
+    let mut wire_ops = Vec::with_capacity(ops.len());
+    for (i, op) in ops.iter().enumerate() {
+        match op {
+            EngineDeltaOp::Literal(data) => {
+                let run_ends = !matches!(ops.get(i + 1), Some(EngineDeltaOp::Literal(_)));
+                let records = encoder
+                    .encode_literal(data, run_ends)
+                    .map_err(|e| map_realwire_error(e, "encode delta literal"))?;
+                wire_ops.extend(
+                    records
+                        .into_iter()
+                        .map(|compressed_payload| DeltaOp::Literal { compressed_payload }),
+                );
+            }
+            EngineDeltaOp::CopyBlock(idx) => wire_ops.push(DeltaOp::CopyRun {
+                start_token_index: *idx as i32,
+                run_length: 1,
+            }),
+        }
+    }
+    Ok(wire_ops)
+}
+
+/// Incremental encoder of one file's delta stream, shared by the bulk and
+/// streaming senders. Engine ops go in batch by batch; the wire bytes
+/// pile up in `out` (primed with the request echo) until the caller cuts
+/// them into MSG_DATA frames. Consecutive literals form one run, which
+/// ends at the next matched block or at the end of the file; every
+/// `CopyBlock(idx)` becomes a one-block `CopyRun`.
+struct DeltaEmitter {
+    encoder: LiteralEncoder,
+    state: DeltaStreamState,
+    /// The last literal pushed. Whether its run ends depends on the op
+    /// after it, so it's encoded once that op (or the end) shows up.
+    held: Option<Vec<u8>>,
+    out: Vec<u8>,
+    /// The head of the emitted wire ops, up to `record_budget` bytes.
+    recorded: Vec<DeltaOp>,
+    record_budget: usize,
+}
+
+impl DeltaEmitter {
+    fn new(
+        encoder: LiteralEncoder,
+        format: TokenFormat,
+        prefix: Vec<u8>,
+        record_budget: usize,
+    ) -> Self {
+        Self {
+            encoder,
+            state: DeltaStreamState::with_format(format),
+            held: None,
+            out: prefix,
+            recorded: Vec::new(),
+            record_budget,
+        }
+    }
+
+    /// Encode `ops`, leaving the vector empty for the next batch.
+    fn push_ops(&mut self, ops: &mut Vec<EngineDeltaOp>) -> Result<(), AerorsyncError> {
+        for op in ops.drain(..) {
+            self.push_op(op)?;
+        }
+        Ok(())
+    }
+
+    fn push_op(&mut self, op: EngineDeltaOp) -> Result<(), AerorsyncError> {
+        match op {
+            EngineDeltaOp::Literal(data) => {
+                if let Some(previous) = self.held.replace(data) {
+                    self.encode_literal(&previous, false)?;
+                }
+            }
+            EngineDeltaOp::CopyBlock(idx) => {
+                if let Some(previous) = self.held.take() {
+                    self.encode_literal(&previous, true)?;
+                }
+                self.emit(DeltaOp::CopyRun {
+                    start_token_index: idx as i32,
+                    run_length: 1,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Close the last run and append END_FLAG plus the file checksum.
+    fn finish(&mut self, file_checksum: &[u8]) -> Result<(), AerorsyncError> {
+        if let Some(previous) = self.held.take() {
+            self.encode_literal(&previous, true)?;
+        }
+        self.out
+            .extend_from_slice(encode_delta_end(self.state.format()));
+        self.out.extend_from_slice(file_checksum);
+        Ok(())
+    }
+
+    fn encode_literal(&mut self, data: &[u8], run_ends: bool) -> Result<(), AerorsyncError> {
+        let records = self
+            .encoder
+            .encode_literal(data, run_ends)
+            .map_err(|e| map_realwire_error(e, "encode delta literal"))?;
+        for compressed_payload in records {
+            self.emit(DeltaOp::Literal { compressed_payload });
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, op: DeltaOp) {
+        self.out
+            .extend_from_slice(&encode_delta_op(&op, &mut self.state));
+        let cost = std::mem::size_of::<DeltaOp>()
+            + match &op {
+                DeltaOp::Literal { compressed_payload } => compressed_payload.len(),
+                DeltaOp::CopyRun { .. } => 0,
+            };
+        if cost <= self.record_budget {
+            self.record_budget -= cost;
+            self.recorded.push(op);
+        } else {
+            self.record_budget = 0;
+        }
+    }
+}
+
+/// Engine ops the streaming receiver has decoded but not applied yet.
+#[derive(Default)]
+struct ApplyBatch {
+    ops: Vec<EngineDeltaOp>,
+    literal_bytes: usize,
+    /// Bytes of the file reconstructed so far, skipped blocks included.
+    written: u64,
+    /// The writer updates the basis itself (`--inplace`): a block copied
+    /// onto its own offset is offered to `skip_in_place`.
+    in_place: bool,
+    /// Running file checksum of the reconstructed bytes, when the
+    /// receiver verifies the sender's trailer.
+    hasher: Option<FileHasher>,
+}
+
+impl ApplyBatch {
+    /// Queue the decoded bytes in `literal`, leaving it empty.
+    fn push_literal(&mut self, literal: &mut Vec<u8>) {
+        self.literal_bytes += literal.len();
+        self.ops
+            .push(EngineDeltaOp::Literal(std::mem::take(literal)));
+    }
+
+    async fn flush_if_full(
+        &mut self,
+        baseline: &mut dyn BaselineSource,
+        writer: &mut dyn ReconstructionWriter,
+        block_size: usize,
+    ) -> Result<(), AerorsyncError> {
+        if self.ops.len() < STREAMING_APPLY_BATCH_OPS
+            && self.literal_bytes < STREAMING_READ_CHUNK_BYTES
+        {
+            return Ok(());
+        }
+        self.flush(baseline, writer, block_size).await
+    }
+
+    async fn flush(
+        &mut self,
+        baseline: &mut dyn BaselineSource,
+        writer: &mut dyn ReconstructionWriter,
+        block_size: usize,
+    ) -> Result<(), AerorsyncError> {
+        let apply_error = |e: std::io::Error| {
+            AerorsyncError::invalid_frame(format!("apply_delta_streaming: {e}"))
+        };
+        self.literal_bytes = 0;
+        if !self.in_place && self.hasher.is_none() {
+            self.written += apply_delta_streaming(baseline, self.ops.drain(..), block_size, writer)
+                .await
+                .map_err(apply_error)?;
+            return Ok(());
+        }
+        for op in std::mem::take(&mut self.ops) {
+            let data = match op {
+                EngineDeltaOp::Literal(bytes) => bytes,
+                EngineDeltaOp::CopyBlock(idx) => {
+                    let block = baseline
+                        .read_block(idx, block_size as u32)
+                        .await
+                        .map_err(apply_error)?;
+                    // `receiver.c`: under `--inplace` a block that is
+                    // already at its offset is seeked over, not rewritten.
+                    let at_own_offset = u64::from(idx) * block_size as u64 == self.written;
+                    if self.in_place && at_own_offset && writer.skip_in_place(block.len() as u64) {
+                        if let Some(hasher) = &mut self.hasher {
+                            hasher.update(&block);
+                        }
+                        self.written += block.len() as u64;
+                        continue;
+                    }
+                    block
+                }
+            };
+            if let Some(hasher) = &mut self.hasher {
+                hasher.update(&data);
+            }
+            writer.write_all(&data).await.map_err(apply_error)?;
+            self.written += data.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+/// [`ReconstructionWriter`] over a plain `AsyncWrite`, which never skips.
+struct PlainWriter<'a>(&'a mut (dyn AsyncWrite + Send + Unpin));
+
+impl AsyncWrite for PlainWriter<'_> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut *self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.0).poll_shutdown(cx)
+    }
+}
+
+impl ReconstructionWriter for PlainWriter<'_> {}
+
+/// Close the literal run in progress, if any, and queue what is left of
+/// its decoded bytes.
+fn end_literal_run(
+    decoder: &mut LiteralDecoder,
+    literal: &mut Vec<u8>,
+    in_run: &mut bool,
+    batch: &mut ApplyBatch,
+) -> Result<(), AerorsyncError> {
+    if std::mem::take(in_run) {
+        decoder
+            .finish_run(literal)
+            .map_err(|e| map_realwire_error(e, "decode delta literals"))?;
+    }
+    if !literal.is_empty() {
+        batch.push_literal(literal);
+    }
+    Ok(())
+}
+
+/// Where `wire_ops_to_engine_ops` finds the data of matched blocks for a
+/// decoder that replays it (`zlib`): the whole basis file in memory. The
+/// streaming receiver reads them from its `BaselineSource` instead.
+struct MatchBasis<'a> {
+    data: &'a [u8],
+    block_len: usize,
+}
+
+impl MatchBasis<'_> {
+    fn block(&self, idx: u32) -> Option<&[u8]> {
+        let start = (idx as usize).checked_mul(self.block_len)?;
+        let end = start.saturating_add(self.block_len).min(self.data.len());
+        self.data.get(start..end)
+    }
+}
+
+/// Convert wire delta ops into engine delta ops, decoding literals
+/// through the session's `decoder`. CopyRuns expand 1:1 into
+/// `EngineDeltaOp::CopyBlock(index)` per block in the run and must stay
+/// below `block_count`.
+///
+/// **S8j download-side**: stock rsync flushes the compressor output
+/// whenever it reaches `MAX_DATA_COUNT` and emits a fresh DEFLATED_DATA
+/// frame with the rest, so one logical literal can arrive as N ≥ 1
+/// consecutive `DeltaOp::Literal` records. Every run of records
+/// uninterrupted by a `CopyRun` is decoded as one literal.
+fn wire_ops_to_engine_ops(
+    wire_ops: &[DeltaOp],
+    decoder: &mut LiteralDecoder,
+    block_count: i32,
+    basis: &MatchBasis<'_>,
+) -> Result<Vec<EngineDeltaOp>, AerorsyncError> {
+    let mut out = Vec::with_capacity(wire_ops.len());
+    let mut run: Vec<&[u8]> = Vec::new();
+    for op in wire_ops {
+        match op {
+            DeltaOp::Literal { compressed_payload } => run.push(compressed_payload),
+            DeltaOp::CopyRun {
+                start_token_index,
+                run_length,
+            } => {
+                flush_literal_run(decoder, &mut run, &mut out)?;
+                for k in 0..*run_length {
+                    let block_idx = *start_token_index + i32::from(k);
+                    if block_idx < 0 {
+                        return Err(AerorsyncError::invalid_frame(format!(
+                            "negative block index {block_idx} in delta CopyRun"
+                        )));
+                    }
+                    if block_idx >= block_count {
+                        return Err(AerorsyncError::invalid_frame(format!(
+                            "block index {block_idx} in delta CopyRun past {block_count} blocks"
+                        )));
+                    }
+                    if decoder.needs_match_data() {
+                        let data = basis.block(block_idx as u32).ok_or_else(|| {
+                            AerorsyncError::new(
+                                AerorsyncErrorKind::Internal,
+                                format!("zlib needs the data of matched block {block_idx}"),
+                            )
+                        })?;
+                        decoder
+                            .see_match(data)
+                            .map_err(|e| map_realwire_error(e, "replay matched block"))?;
+                    }
+                    out.push(EngineDeltaOp::CopyBlock(block_idx as u32));
+                }
+            }
+        }
+    }
+    flush_literal_run(decoder, &mut run, &mut out)?;
+    Ok(out)
+}
+
+/// Decode the pending literal run, if any, into one engine literal.
+fn flush_literal_run(
+    decoder: &mut LiteralDecoder,
+    run: &mut Vec<&[u8]>,
+    out: &mut Vec<EngineDeltaOp>,
+) -> Result<(), AerorsyncError> {
+    if run.is_empty() {
+        return Ok(());
+    }
+    let literal = decoder
+        .decode_run(run)
+        .map_err(|e| map_realwire_error(e, "decode delta literals"))?;
+    run.clear();
+    out.push(EngineDeltaOp::Literal(literal));
+    Ok(())
+}
+
+/// Reconstruct a whole-file transfer (`block_length == 0`): only
+/// literals can appear, concatenated in order.
+fn literal_only_data(ops: Vec<EngineDeltaOp>) -> Result<Vec<u8>, AerorsyncError> {
+    let mut out = Vec::new();
+    for op in ops {
+        match op {
+            EngineDeltaOp::Literal(data) => out.extend_from_slice(&data),
+            EngineDeltaOp::CopyBlock(idx) => {
+                return Err(AerorsyncError::invalid_frame(format!(
+                    "CopyBlock({idx}) in a whole-file transfer"
+                )));
+            }
+        }
+    }
+    Ok(out)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aerorsync::engine_adapter::{
+        DeltaEngineAdapter, EngineDeltaOp, EngineDeltaPlan, EngineSignatureBlock,
+    };
+    use crate::aerorsync::events::{classify_oob_frame, AerorsyncEvent, CollectingSink};
+    use crate::aerorsync::fixtures::RealRsyncBaselineByteTranscript;
+    use crate::aerorsync::mock::{MockRemoteShellTransport, MockTransportConfig};
+    use crate::aerorsync::negotiation::AlgorithmPreferences;
+    use crate::aerorsync::real_wire::{
+        compress_zstd_literal_stream, decompress_zstd_literal_stream, encode_delta_stream,
+        encode_server_preamble, ServerPreamble, XattrItem, MAX_DELTA_LITERAL_LEN,
+    };
+    use std::collections::BTreeSet;
+    use xxhash_rust::xxh3::Xxh3Default;
+
+    /// Mock adapter used by A2.2/A2.3 tests. Returns a configurable
+    /// block size, pre-fabricated signatures, and a pre-canned delta
+    /// plan. `apply_delta` returns the destination data with `literal`
+    /// bytes interleaved at the start (simple deterministic output).
+    #[derive(Default)]
+    struct MockSigAdapter {
+        block_size: Option<usize>,
+        signatures: Vec<EngineSignatureBlock>,
+        upload_plan_ops: Vec<EngineDeltaOp>,
+        upload_savings_ratio: f64,
+        upload_should_use_delta: bool,
+    }
+
+    impl MockSigAdapter {
+        fn with_fixed_signatures(block_size: usize, signatures: Vec<EngineSignatureBlock>) -> Self {
+            Self {
+                block_size: Some(block_size),
+                signatures,
+                upload_plan_ops: Vec::new(),
+                upload_savings_ratio: 1.0,
+                upload_should_use_delta: false,
+            }
+        }
+
+        fn with_upload_plan(mut self, ops: Vec<EngineDeltaOp>) -> Self {
+            self.upload_plan_ops = ops;
+            self.upload_should_use_delta = true;
+            self.upload_savings_ratio = 0.5;
+            self
+        }
+    }
+
+    impl DeltaEngineAdapter for MockSigAdapter {
+        fn compute_block_size(&self, _file_size: u64) -> usize {
+            self.block_size.unwrap_or(1024)
+        }
+        fn build_signatures(
+            &self,
+            _destination_data: &[u8],
+            _block_size: usize,
+        ) -> Vec<EngineSignatureBlock> {
+            self.signatures.clone()
+        }
+        fn compute_delta(
+            &self,
+            _source_data: &[u8],
+            _destination_signatures: &[EngineSignatureBlock],
+            _block_size: usize,
+        ) -> EngineDeltaPlan {
+            let literal_bytes: u64 = self
+                .upload_plan_ops
+                .iter()
+                .map(|op| match op {
+                    EngineDeltaOp::Literal(b) => b.len() as u64,
+                    EngineDeltaOp::CopyBlock(_) => 0,
+                })
+                .sum();
+            let copy_blocks: u32 = self
+                .upload_plan_ops
+                .iter()
+                .filter(|op| matches!(op, EngineDeltaOp::CopyBlock(_)))
+                .count() as u32;
+            EngineDeltaPlan {
+                ops: self.upload_plan_ops.clone(),
+                copy_blocks,
+                literal_bytes,
+                total_delta_bytes: literal_bytes,
+                savings_ratio: self.upload_savings_ratio,
+                should_use_delta: self.upload_should_use_delta,
+            }
+        }
+        fn apply_delta(
+            &self,
+            destination_data: &[u8],
+            ops: &[EngineDeltaOp],
+            block_size: usize,
+        ) -> Result<Vec<u8>, String> {
+            // Simple deterministic reconstructor: literal bytes verbatim;
+            // CopyBlock(idx) → destination_data[idx*bs..(idx+1)*bs].
+            let mut out: Vec<u8> = Vec::new();
+            for op in ops {
+                match op {
+                    EngineDeltaOp::Literal(raw) => out.extend_from_slice(raw),
+                    EngineDeltaOp::CopyBlock(idx) => {
+                        let start = (*idx as usize) * block_size;
+                        let end = (start + block_size).min(destination_data.len());
+                        if start >= destination_data.len() {
+                            return Err(format!(
+                                "CopyBlock idx {idx} out of bounds for destination len {}",
+                                destination_data.len()
+                            ));
+                        }
+                        out.extend_from_slice(&destination_data[start..end]);
+                    }
+                }
+            }
+            Ok(out)
+        }
+    }
+
+    /// Build a synthetic server signature-phase payload (bytes as they
+    /// appear inside one or more `MSG_DATA` frames before mux-wrapping).
+    /// The caller decides the chunking.
+    fn build_sig_phase_payload(
+        ndx: i32,
+        iflags: u16,
+        head: &SumHead,
+        blocks: &[SumBlock],
+    ) -> Vec<u8> {
+        use crate::aerorsync::real_wire::{
+            encode_item_flags, encode_ndx, encode_sum_block, encode_sum_head, NdxState,
+        };
+        let mut st = NdxState::new();
+        let mut out = Vec::new();
+        out.extend_from_slice(&encode_ndx(ndx, &mut st));
+        out.extend_from_slice(&encode_item_flags(iflags));
+        out.extend_from_slice(&encode_sum_head(head));
+        for b in blocks {
+            out.extend_from_slice(&encode_sum_block(b));
+        }
+        out
+    }
+
+    fn make_sig_block(rolling: u32, strong_first_byte: u8, s2length: usize) -> SumBlock {
+        SumBlock {
+            rolling,
+            strong: vec![strong_first_byte; s2length],
+        }
+    }
+
+    fn make_engine_sig(
+        index: u32,
+        rolling: u32,
+        strong_first_byte: u8,
+        block_len: u32,
+    ) -> EngineSignatureBlock {
+        let mut strong = [0u8; 32];
+        for b in strong.iter_mut().take(32) {
+            *b = strong_first_byte;
+        }
+        EngineSignatureBlock {
+            index,
+            rolling,
+            strong,
+            block_len,
+        }
+    }
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    // ---- helpers ---------------------------------------------------------
+
+    fn mock_transport() -> MockRemoteShellTransport {
+        MockRemoteShellTransport::new(MockTransportConfig::healthy_upload())
+    }
+
+    fn mock_transport_with_raw_inbound(inbound: Vec<u8>) -> MockRemoteShellTransport {
+        let cfg = MockTransportConfig::healthy_upload().with_raw_inbound(inbound);
+        MockRemoteShellTransport::new(cfg)
+    }
+
+    fn make_driver(
+        transport: MockRemoteShellTransport,
+    ) -> AerorsyncDriver<MockRemoteShellTransport> {
+        AerorsyncDriver::new(transport, CancelHandle::inert())
+    }
+
+    /// Stock rsync 3.2.7 server lists: with our default client lists the
+    /// session settles on xxh128 and zstd, the frozen-oracle profile.
+    const STOCK_SERVER_CHECKSUMS: &str = "xxh128 xxh3 xxh64 md5 md4 sha1 none";
+    const STOCK_SERVER_COMPRESSIONS: &str = "zstd lz4 zlibx zlib none";
+
+    fn canonical_server_preamble_bytes() -> Vec<u8> {
+        // Rsync serialises both lists as SPACE-separated (see
+        // `perform_preamble_exchange` and the frozen oracle capture).
+        // Using commas here once hid a parsing bug that made live
+        // uploads skip zstd compression against stock rsync.
+        server_preamble_with(STOCK_SERVER_CHECKSUMS, STOCK_SERVER_COMPRESSIONS)
+    }
+
+    fn server_preamble_with(checksums: &str, compressions: &str) -> Vec<u8> {
+        encode_server_preamble(&ServerPreamble {
+            protocol_version: 31,
+            compat_flags: 0x07,
+            checksum_algos: checksums.to_string(),
+            compression_algos: compressions.to_string(),
+            checksum_seed: 0xDEAD_BEEF,
+            consumed: 0,
+        })
+    }
+
+    fn mux_frame(tag: MuxTag, payload: &[u8]) -> Vec<u8> {
+        let header = MuxHeader {
+            tag,
+            length: payload.len() as u32,
+        };
+        let mut out = Vec::with_capacity(4 + payload.len());
+        out.extend_from_slice(&header.encode());
+        out.extend_from_slice(payload);
+        out
+    }
+
+    /// Build a `FileListEntry` that the encoder/decoder will round-trip
+    /// under `build_flist_options` (varint flags, always_checksum on,
+    /// preserve_uid/gid on with SAME_UID/SAME_GID gating uid/gid out).
+    /// The flags include `XMIT_LONG_NAME` so the suffix length is encoded
+    /// as a varint: which the path length (9 chars) still fits in.
+    fn sample_file_list_entry(path: &str) -> FileListEntry {
+        // Flags: XMIT_LONG_NAME (0x0040) | XMIT_SAME_MODE (0x0002) |
+        //        XMIT_SAME_TIME (0x0080) | XMIT_SAME_UID (0x0008) |
+        //        XMIT_SAME_GID (0x0010)
+        //: the "all same" upload case where only the name and size are
+        // transmitted. Matches a minimum-viable shape; the 16-byte
+        // checksum is required because B.2 turned `always_checksum` on
+        // in `build_flist_options` to mirror the oracle (`-c` always
+        // active in production dispatch).
+        const XMIT_SAME_MODE: u32 = 0x0002;
+        const XMIT_SAME_UID: u32 = 0x0008;
+        const XMIT_SAME_GID: u32 = 0x0010;
+        const XMIT_LONG_NAME: u32 = 0x0040;
+        const XMIT_SAME_TIME: u32 = 0x0080;
+        FileListEntry {
+            flags: XMIT_LONG_NAME | XMIT_SAME_MODE | XMIT_SAME_UID | XMIT_SAME_GID | XMIT_SAME_TIME,
+            path: path.to_string(),
+            size: 4096,
+            mtime: 0,
+            mtime_nsec: None,
+            mode: 0,
+            uid: None,
+            uid_name: None,
+            gid: None,
+            gid_name: None,
+            // 16 bytes filled with a sentinel; xxh128 length, never
+            // validated against file content in unit tests.
+            checksum: vec![0xAA; 16],
+            link_target: None,
+            hardlink: None,
+            acl: None,
+            xattrs: None,
+        }
+    }
+
+    // ---- A2.0 regression pins (preserved) -------------------------------
+
+    #[test]
+    fn constructor_initialises_phase_and_defaults() {
+        let d = make_driver(mock_transport());
+        assert_eq!(d.phase(), AerorsyncSessionPhase::PreConnect);
+        assert!(!d.committed());
+        assert_eq!(d.protocol_version(), 0);
+        assert_eq!(d.compat_flags(), 0);
+        assert_eq!(d.checksum_seed(), 0);
+        assert!(d.negotiated_checksum_algos().is_empty());
+        assert!(d.negotiated_compression_algos().is_empty());
+        assert!(d.file_list().is_empty());
+        assert_eq!(d.data_bytes_consumed(), 0);
+    }
+
+    #[tokio::test]
+    async fn send_client_preamble_writes_bytes_that_decode_back() {
+        use crate::aerorsync::real_wire::decode_client_preamble;
+        let mut d = make_driver(mock_transport());
+        let mut sink = Vec::new();
+        d.send_client_preamble(&mut sink, 31, "md5,xxh64", "none,zstd")
+            .await
+            .unwrap();
+        let decoded = decode_client_preamble(&sink).unwrap();
+        assert_eq!(decoded.protocol_version, 31);
+        assert_eq!(decoded.checksum_algos, "md5,xxh64");
+        assert_eq!(decoded.compression_algos, "none,zstd");
+        assert_eq!(d.phase(), AerorsyncSessionPhase::ServerPreambleSent);
+    }
+
+    #[tokio::test]
+    async fn receive_server_preamble_populates_driver_state() {
+        let encoded = canonical_server_preamble_bytes();
+        let mut d = make_driver(mock_transport());
+        let consumed = d.receive_server_preamble(&encoded).await.unwrap();
+        assert_eq!(consumed, encoded.len());
+        assert_eq!(d.protocol_version(), 31);
+        assert_eq!(d.compat_flags(), 0x07);
+        assert_eq!(d.checksum_seed(), 0xDEAD_BEEF);
+        assert_eq!(d.negotiated_checksum_algos(), STOCK_SERVER_CHECKSUMS);
+        assert_eq!(d.negotiated_compression_algos(), STOCK_SERVER_COMPRESSIONS);
+        assert_eq!(d.checksum_algo(), ChecksumAlgo::Xxh128);
+        assert_eq!(d.compression_algo(), CompressionAlgo::Zstd);
+        assert_eq!(d.phase(), AerorsyncSessionPhase::ClientPreambleRecvd);
+    }
+
+    #[tokio::test]
+    async fn receive_server_preamble_on_malformed_bytes_marks_failed() {
+        let mut d = make_driver(mock_transport());
+        let err = d.receive_server_preamble(&[0x01]).await.unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::InvalidFrame);
+        assert!(err.detail.contains("server preamble"));
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Failed);
+    }
+
+    #[tokio::test]
+    async fn driver_preamble_exchange_round_trip_matches_frozen_oracle_server_side() {
+        let Some(frozen) = RealRsyncBaselineByteTranscript::try_load_frozen() else {
+            eprintln!("frozen oracle missing: driver preamble pin skipped");
+            return;
+        };
+        let mut d = make_driver(mock_transport());
+        let consumed = d
+            .receive_server_preamble(&frozen.upload_server_to_client)
+            .await
+            .expect("driver must decode frozen server preamble");
+        assert!(consumed > 0);
+        let re_encoded = encode_server_preamble(&ServerPreamble {
+            protocol_version: d.protocol_version(),
+            compat_flags: d.compat_flags(),
+            checksum_algos: d.negotiated_checksum_algos().to_string(),
+            compression_algos: d.negotiated_compression_algos().to_string(),
+            checksum_seed: d.checksum_seed(),
+            consumed: 0,
+        });
+        assert_eq!(
+            re_encoded.as_slice(),
+            &frozen.upload_server_to_client[..consumed],
+            "driver round-trip must be byte-identical to frozen oracle prefix"
+        );
+    }
+
+    #[test]
+    fn cancel_handle_returns_clone_sharing_flag() {
+        let d = make_driver(mock_transport());
+        let h1 = d.cancel_handle();
+        let h2 = d.cancel_handle();
+        assert!(!h1.requested());
+        h1.cancel();
+        assert!(h2.requested());
+        assert!(d.cancel_handle().requested());
+    }
+
+    // ---- A2.1 tests ------------------------------------------------------
+
+    #[tokio::test]
+    async fn driver_upload_writes_preamble_then_filelist_then_terminator() {
+        // Inbound: server preamble + a minimal synthetic signature
+        // phase (upload path in A2.2 drains ndx+iflags+sum_head+blocks
+        // after the file list: without these bytes the test would
+        // fail with TransportFailure instead of the expected stub
+        // frontier.)
+        let head = SumHead {
+            count: 1,
+            block_length: 1024,
+            checksum_length: 2,
+            remainder_length: 0,
+        };
+        let blocks = vec![make_sig_block(0xAAAAAAAA, 0x11, 2)];
+        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let last_raw_outbound = transport.last_raw_outbound.clone();
+        let mut d = make_driver(transport);
+        let mut sink = CollectingSink::default();
+
+        let err = d
+            .drive_upload(
+                RemoteCommandSpec::upload("/remote/target.bin"),
+                sample_file_list_entry("target.bin"),
+                &[],
+                &MockSigAdapter::default(),
+                &mut sink,
+            )
+            .await
+            .unwrap_err();
+        // Stub frontier: sum_head not yet wired.
+        assert_eq!(err.kind, AerorsyncErrorKind::UnsupportedVersion);
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Stub);
+        // A2.3: drive_upload now crosses the PreCommit/PostCommit boundary
+        // during the delta phase. MockSigAdapter returns an empty plan
+        // which still emits END_FLAG + file_checksum, counting as
+        // "first delta material written" → committed flips true.
+        assert!(d.committed());
+
+        // Outbound bytes = encode_client_preamble + mux(entry) + mux(terminator)
+        let guard = last_raw_outbound.lock().unwrap();
+        let outbound_arc = guard.as_ref().expect("raw stream must have been opened");
+        let outbound = outbound_arc.lock().unwrap().clone();
+
+        let expected_client = encode_client_preamble(&ClientPreamble {
+            protocol_version: 31,
+            // B.2: rsync wire protocol uses SPACE-separated algo lists
             // in priority-descending order. The previous pin
             // ("md5,xxh64,xxh128" / "none,zstd") mirrored the pre-B.2
             // driver implementation that stock rsync 3.4.1 rejects as a
             // single unknown algorithm. The values below match the
             // post-fix driver (and the live wire observed against
-            // rsync 3.4.1 / protocol 32).
-            checksum_algos: "xxh128 xxh3 xxh64 md5 md4".to_string(),
+            // rsync 3.4.1 / protocol 32), which are stock rsync's own
+            // client lists.
+            checksum_algos: "xxh128 xxh3 xxh64 md5 md4 sha1".to_string(),
             compression_algos: "zstd lz4 zlibx zlib".to_string(),
             consumed: 0,
         });
@@ -2875,6 +5494,11 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
         let term_bytes = encode_file_list_terminator(&opts);
@@ -2910,6 +5534,11 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let entry = sample_file_list_entry("target.bin");
         let entry_bytes = encode_file_list_entry(&entry, &opts);
@@ -2961,6 +5590,11 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let entry = sample_file_list_entry("target.bin");
         let entry_bytes = encode_file_list_entry(&entry, &opts);
@@ -3075,6 +5709,11 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let entry = sample_file_list_entry("target.bin");
         let entry_bytes = encode_file_list_entry(&entry, &opts);
@@ -3443,6 +6082,11 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
         let term_bytes = encode_file_list_terminator(&opts);
@@ -3613,6 +6257,11 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
         let term_bytes = encode_file_list_terminator(&opts);
@@ -3861,6 +6510,140 @@ mod tests {
         );
     }
 
+    /// Download inbound for `sample_file_list_entry` (4096 bytes) whose
+    /// delta stream is `delta`; `None` ends the capture after the list.
+    fn single_file_download_inbound(delta: Option<&DeltaStreamReport>) -> Vec<u8> {
+        let opts = FileListDecodeOptions {
+            protocol: 31,
+            xfer_flags_as_varint: true,
+            always_checksum: true,
+            csum_len: 16,
+            preserve_uid: true,
+            preserve_gid: true,
+            previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
+        };
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(
+            MuxTag::Data,
+            &encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts),
+        ));
+        inbound.extend_from_slice(&mux_frame(
+            MuxTag::Data,
+            &encode_file_list_terminator(&opts),
+        ));
+        if let Some(report) = delta {
+            inbound.extend_from_slice(&mux_frame(MuxTag::Data, &encode_delta_stream(report)));
+        }
+        inbound
+    }
+
+    fn append_options() -> TransferOptions {
+        TransferOptions {
+            append: AppendMode::Append,
+            ..TransferOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn driver_download_append_keeps_the_local_prefix() {
+        let compressed = compress_zstd_literal_stream(&[b" WORLD".as_slice()]).unwrap();
+        let report = DeltaStreamReport {
+            ops: vec![DeltaOp::Literal {
+                compressed_payload: compressed[0].clone(),
+            }],
+            file_checksum: compute_xxh128_wire(b" WORLD"),
+        };
+        let transport =
+            mock_transport_with_raw_inbound(single_file_download_inbound(Some(&report)));
+        let adapter = MockSigAdapter::with_fixed_signatures(
+            4,
+            vec![
+                make_engine_sig(0, 0xA0, 0x01, 4),
+                make_engine_sig(1, 0xA1, 0x02, 1),
+            ],
+        );
+        let mut d = make_driver(transport);
+        let _ = d
+            .drive_download(
+                RemoteCommandSpec::download("/remote/target.bin").with_options(append_options()),
+                b"HELLO",
+                &adapter,
+                &mut CollectingSink::default(),
+            )
+            .await;
+
+        assert!(!d.transfer_skipped());
+        // The generator announces the local length but no block sums.
+        assert_eq!(d.sent_sum_head().map(SumHead::basis_length), Some(5));
+        assert!(d.sent_signatures().is_empty());
+        assert_eq!(d.reconstructed(), Some(b"HELLO WORLD".as_slice()));
+    }
+
+    #[tokio::test]
+    async fn driver_download_append_skips_a_local_copy_at_least_as_long() {
+        let transport = mock_transport_with_raw_inbound(single_file_download_inbound(None));
+        let mut d = make_driver(transport);
+        let local = vec![0x5A; 5000];
+        let _ = d
+            .drive_download(
+                RemoteCommandSpec::download("/remote/target.bin").with_options(append_options()),
+                &local,
+                &MockSigAdapter::default(),
+                &mut CollectingSink::default(),
+            )
+            .await;
+
+        assert!(d.transfer_skipped());
+        assert!(d.sent_sum_head().is_none(), "no transfer request is sent");
+        assert_eq!(d.reconstructed(), Some(local.as_slice()));
+    }
+
+    #[tokio::test]
+    async fn driver_download_announces_a_partial_dir_basis() {
+        let report = DeltaStreamReport {
+            ops: Vec::new(),
+            file_checksum: vec![0u8; A2_3_FILE_CHECKSUM_LEN],
+        };
+        let transport =
+            mock_transport_with_raw_inbound(single_file_download_inbound(Some(&report)));
+        let last_raw_outbound = transport.last_raw_outbound.clone();
+        let adapter =
+            MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
+        let mut d = make_driver(transport);
+        d.set_partial_basis(true);
+        let _ = d
+            .drive_download(
+                RemoteCommandSpec::download("/remote/target.bin"),
+                b"PART",
+                &adapter,
+                &mut CollectingSink::default(),
+            )
+            .await;
+
+        assert_eq!(d.last_iflags(), 0x8002 | ITEM_BASIS_TYPE_FOLLOWS);
+        let expected = ItemHeader {
+            ndx: A2_2_FIRST_FILE_NDX,
+            iflags: 0x8002 | ITEM_BASIS_TYPE_FOLLOWS,
+            basis_type: Some(FNAMECMP_PARTIAL_DIR),
+            xname: None,
+            xattrs: Vec::new(),
+        }
+        .encode(&mut NdxState::new());
+        let guard = last_raw_outbound.lock().unwrap();
+        let outbound = guard.as_ref().unwrap().lock().unwrap().clone();
+        assert!(
+            outbound
+                .windows(expected.len())
+                .any(|w| w == expected.as_slice()),
+            "item header must carry FNAMECMP_PARTIAL_DIR"
+        );
+    }
+
     #[tokio::test]
     async fn driver_download_delta_decodes_ops_and_reconstructs() {
         // Build a server-side delta stream manually: one CopyRun (run=2)
@@ -3897,6 +6680,11 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
         let term_bytes = encode_file_list_terminator(&opts);
@@ -3944,6 +6732,268 @@ mod tests {
         assert_eq!(d.received_file_checksum(), Some(vec![0xCC; 16].as_slice()),);
     }
 
+    /// Server lists of stock rsync 3.2.7, 3.3.0 and 3.4.1 in a protocol
+    /// 31 session; `negotiation.rs` pins the client lists of the same
+    /// releases.
+    const STOCK_PEER_SERVER_LISTS: [(&str, &str, &str); 3] = [
+        ("3.2.7", STOCK_SERVER_CHECKSUMS, STOCK_SERVER_COMPRESSIONS),
+        ("3.3.0", STOCK_SERVER_CHECKSUMS, STOCK_SERVER_COMPRESSIONS),
+        ("3.4.1", STOCK_SERVER_CHECKSUMS, STOCK_SERVER_COMPRESSIONS),
+    ];
+
+    /// Preference profiles for the transcript matrix, with the pick each
+    /// must reach against a stock server.
+    fn algorithm_profiles() -> Vec<(AlgorithmPreferences, ChecksumAlgo, CompressionAlgo)> {
+        let prefs = AlgorithmPreferences::default;
+        vec![
+            (prefs(), ChecksumAlgo::Xxh128, CompressionAlgo::Zstd),
+            (
+                AlgorithmPreferences {
+                    compress_level: Some(19),
+                    prefer_checksums: vec![ChecksumAlgo::Xxh64],
+                    ..prefs()
+                },
+                ChecksumAlgo::Xxh64,
+                CompressionAlgo::Zstd,
+            ),
+            (
+                AlgorithmPreferences {
+                    prefer_checksums: vec![ChecksumAlgo::Md5],
+                    prefer_compressions: vec![CompressionAlgo::Lz4],
+                    ..prefs()
+                },
+                ChecksumAlgo::Md5,
+                CompressionAlgo::Lz4,
+            ),
+            (
+                prefs().with_lists(Some("xxh3"), Some("zlibx")).unwrap(),
+                ChecksumAlgo::Xxh3,
+                CompressionAlgo::Zlibx,
+            ),
+            (
+                prefs().with_lists(Some("sha1"), Some("zlib")).unwrap(),
+                ChecksumAlgo::Sha1,
+                CompressionAlgo::Zlib,
+            ),
+            (
+                AlgorithmPreferences {
+                    prefer_checksums: vec![ChecksumAlgo::Md4],
+                    prefer_compressions: vec![CompressionAlgo::None],
+                    ..prefs()
+                },
+                ChecksumAlgo::Md4,
+                CompressionAlgo::None,
+            ),
+        ]
+    }
+
+    /// Transcript matrix, upload side: for every stock peer and profile
+    /// the driver settles on the expected pair, and the delta it writes
+    /// decodes back with that pair's codec, framing and checksum.
+    #[tokio::test]
+    async fn upload_transcripts_round_trip_every_algorithm_against_stock_peers() {
+        let source: &[u8] = b"hello\0\0\0world";
+        let basis = vec![0x5Au8; 2048];
+        for (version, checksums, compressions) in STOCK_PEER_SERVER_LISTS {
+            for (prefs, checksum, compression) in algorithm_profiles() {
+                let label = format!("{version} / {checksum} + {compression}");
+                let head = SumHead {
+                    count: 2,
+                    block_length: 1024,
+                    checksum_length: 2,
+                    remainder_length: 0,
+                };
+                let blocks = vec![
+                    make_sig_block(0x11111111, 0xAA, 2),
+                    make_sig_block(0x22222222, 0xBB, 2),
+                ];
+                let mut inbound = server_preamble_with(checksums, compressions);
+                inbound.extend_from_slice(&mux_frame(
+                    MuxTag::Data,
+                    &build_sig_phase_payload(1, 0x8002, &head, &blocks),
+                ));
+                let transport = mock_transport_with_raw_inbound(inbound);
+                let last_raw_outbound = transport.last_raw_outbound.clone();
+                let adapter = MockSigAdapter::default().with_upload_plan(vec![
+                    EngineDeltaOp::Literal(b"hello".to_vec()),
+                    EngineDeltaOp::CopyBlock(0),
+                    EngineDeltaOp::Literal(b"world".to_vec()),
+                    EngineDeltaOp::CopyBlock(1),
+                ]);
+                let options = TransferOptions {
+                    algorithms: prefs.clone(),
+                    ..TransferOptions::default()
+                };
+                let mut d = make_driver(transport);
+                let mut sink = CollectingSink::default();
+                let err = d
+                    .drive_upload(
+                        RemoteCommandSpec::upload("/remote/target.bin").with_options(options),
+                        sample_file_list_entry("target.bin"),
+                        source,
+                        &adapter,
+                        &mut sink,
+                    )
+                    .await
+                    .unwrap_err();
+                assert_eq!(err.kind, AerorsyncErrorKind::UnsupportedVersion, "{label}");
+                assert_eq!(d.checksum_algo(), checksum, "{label}");
+                assert_eq!(d.compression_algo(), compression, "{label}");
+
+                let mut decoder = LiteralDecoder::new(compression);
+                let ops = wire_ops_to_engine_ops(
+                    d.emitted_delta_ops(),
+                    &mut decoder,
+                    2,
+                    &MatchBasis {
+                        data: &basis,
+                        block_len: 1024,
+                    },
+                )
+                .unwrap();
+                assert_eq!(ops, adapter.upload_plan_ops, "{label}");
+
+                let delta = encode_delta_stream_with(
+                    &DeltaStreamReport {
+                        ops: d.emitted_delta_ops().to_vec(),
+                        file_checksum: checksum.digest(source),
+                    },
+                    compression.token_format(),
+                );
+                let guard = last_raw_outbound.lock().unwrap();
+                let outbound = guard.as_ref().unwrap().lock().unwrap().clone();
+                let client_preamble = encode_client_preamble(&ClientPreamble {
+                    protocol_version: 31,
+                    checksum_algos: prefs.checksum_list(),
+                    compression_algos: prefs.compression_list(false),
+                    consumed: 0,
+                });
+                assert!(outbound.starts_with(&client_preamble), "{label}");
+                assert!(
+                    outbound.windows(delta.len()).any(|w| w == delta.as_slice()),
+                    "{label}: delta stream and trailer must reach the wire"
+                );
+            }
+        }
+    }
+
+    /// Transcript matrix, download side: a stock sender's delta, encoded
+    /// with each negotiated pair, reconstructs the file.
+    #[tokio::test]
+    async fn download_transcripts_round_trip_every_algorithm_against_stock_peers() {
+        let literal = b"LITERAL_PAYLOAD_ABC".repeat(3);
+        let destination_data: Vec<u8> = b"BLK1BLK2".to_vec();
+        let mut expected = destination_data.clone();
+        expected.extend_from_slice(&literal);
+        for (version, checksums, compressions) in STOCK_PEER_SERVER_LISTS {
+            for (prefs, checksum, compression) in algorithm_profiles() {
+                let label = format!("{version} / {checksum} + {compression}");
+                let mut encoder = LiteralEncoder::new(compression, None).unwrap();
+                let wire_ops = engine_ops_to_wire_ops(
+                    &[
+                        EngineDeltaOp::CopyBlock(0),
+                        EngineDeltaOp::CopyBlock(1),
+                        EngineDeltaOp::Literal(literal.clone()),
+                    ],
+                    &mut encoder,
+                )
+                .unwrap();
+                let delta_bytes = encode_delta_stream_with(
+                    &DeltaStreamReport {
+                        ops: wire_ops,
+                        file_checksum: checksum.digest(&expected),
+                    },
+                    compression.token_format(),
+                );
+                let opts = FileListDecodeOptions {
+                    protocol: 31,
+                    xfer_flags_as_varint: true,
+                    always_checksum: true,
+                    csum_len: checksum.digest_len(),
+                    preserve_uid: true,
+                    preserve_gid: true,
+                    previous_name: None,
+                    preserve_links: true,
+                    preserve_hard_links: false,
+                    preserve_acls: false,
+                    preserve_xattrs: false,
+                    scope: None,
+                };
+                let entry = FileListEntry {
+                    checksum: checksum.digest(&expected),
+                    ..sample_file_list_entry("target.bin")
+                };
+                let mut inbound = server_preamble_with(checksums, compressions);
+                inbound.extend_from_slice(&mux_frame(
+                    MuxTag::Data,
+                    &encode_file_list_entry(&entry, &opts),
+                ));
+                inbound.extend_from_slice(&mux_frame(
+                    MuxTag::Data,
+                    &encode_file_list_terminator(&opts),
+                ));
+                inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes));
+
+                let adapter = MockSigAdapter::with_fixed_signatures(
+                    4,
+                    vec![
+                        make_engine_sig(0, 0xA0, 0x01, 4),
+                        make_engine_sig(1, 0xA1, 0x02, 4),
+                    ],
+                );
+                let options = TransferOptions {
+                    algorithms: prefs,
+                    ..TransferOptions::default()
+                };
+                let mut d = make_driver(mock_transport_with_raw_inbound(inbound));
+                let mut sink = CollectingSink::default();
+                let err = d
+                    .drive_download(
+                        RemoteCommandSpec::download("/remote/target.bin").with_options(options),
+                        &destination_data,
+                        &adapter,
+                        &mut sink,
+                    )
+                    .await
+                    .unwrap_err();
+                assert_eq!(err.kind, AerorsyncErrorKind::UnsupportedVersion, "{label}");
+                assert_eq!(d.checksum_algo(), checksum, "{label}");
+                assert_eq!(d.compression_algo(), compression, "{label}");
+                assert_eq!(d.reconstructed(), Some(expected.as_slice()), "{label}");
+                assert_eq!(
+                    d.received_file_checksum(),
+                    Some(checksum.digest(&expected).as_slice()),
+                    "{label}"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn client_refuses_a_server_without_a_common_checksum() {
+        let inbound = server_preamble_with("md5 none", STOCK_SERVER_COMPRESSIONS);
+        let options = TransferOptions {
+            algorithms: AlgorithmPreferences::default()
+                .with_lists(Some("xxh128 xxh3"), None)
+                .unwrap(),
+            ..TransferOptions::default()
+        };
+        let mut d = make_driver(mock_transport_with_raw_inbound(inbound));
+        let mut sink = CollectingSink::default();
+        let err = d
+            .drive_upload(
+                RemoteCommandSpec::upload("/remote/target.bin").with_options(options),
+                sample_file_list_entry("target.bin"),
+                b"data",
+                &MockSigAdapter::default(),
+                &mut sink,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::NegotiationFailed);
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Failed);
+    }
+
     /// S8j download-side pin: a logical literal split by the server
     /// across N consecutive `DEFLATED_DATA` frames MUST coalesce back
     /// into a single `EngineDeltaOp::Literal` on the engine plan. This
@@ -4021,6 +7071,11 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
         let term_bytes = encode_file_list_terminator(&opts);
@@ -4118,9 +7173,145 @@ mod tests {
         }];
         let report = DeltaStreamReport {
             ops: wire_ops,
-            file_checksum: vec![0u8; A2_3_FILE_CHECKSUM_LEN],
+            file_checksum: vec![0u8; A2_3_FILE_CHECKSUM_LEN],
+        };
+        let delta_bytes = encode_delta_stream(&report);
+
+        let opts = FileListDecodeOptions {
+            protocol: 31,
+            xfer_flags_as_varint: true,
+            // B.2: align with `build_flist_options()` so test-side
+            // pre-encoded payloads round-trip through the driver decoder.
+            always_checksum: true,
+            csum_len: 16,
+            preserve_uid: true,
+            preserve_gid: true,
+            previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
+        };
+        let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
+        let term_bytes = encode_file_list_terminator(&opts);
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes));
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let adapter =
+            MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
+        let mut d = make_driver(transport);
+        let mut sink = CollectingSink::default();
+        let _ = d
+            .drive_download(
+                RemoteCommandSpec::download("/remote/x"),
+                b"BLK0",
+                &adapter,
+                &mut sink,
+            )
+            .await;
+        assert!(!d.committed(), "download A2.3 never crosses PreCommit");
+    }
+
+    #[tokio::test]
+    async fn driver_download_delta_aborts_on_terminal_oob_post_sigs() {
+        // After the file list phase + local signature emission, server
+        // sends a terminal Error OOB in place of the delta stream.
+        // The driver must bail with RemoteError and committed stays false
+        // (download path never crosses PreCommit in A2.3).
+        let opts = FileListDecodeOptions {
+            protocol: 31,
+            xfer_flags_as_varint: true,
+            // B.2: align with `build_flist_options()` so test-side
+            // pre-encoded payloads round-trip through the driver decoder.
+            always_checksum: true,
+            csum_len: 16,
+            preserve_uid: true,
+            preserve_gid: true,
+            previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
+        };
+        let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
+        let term_bytes = encode_file_list_terminator(&opts);
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Error, b"delta stream crashed"));
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let adapter =
+            MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
+        let mut d = make_driver(transport);
+        let mut sink = CollectingSink::default();
+        let err = d
+            .drive_download(
+                RemoteCommandSpec::download("/remote/x"),
+                b"BLK0",
+                &adapter,
+                &mut sink,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::RemoteError);
+        assert!(err.detail.contains("delta stream crashed"));
+        assert!(!d.committed(), "download stays PreCommit even on error");
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Failed);
+    }
+
+    #[tokio::test]
+    async fn driver_upload_delta_cancel_surfaces_typed_cancelled() {
+        // Cancel the driver before it reaches the delta phase: the
+        // check_cancel guards inside send_delta_phase_single_file must
+        // surface a typed Cancelled error, not a transport failure.
+        let head = SumHead {
+            count: 1,
+            block_length: 1024,
+            checksum_length: 2,
+            remainder_length: 0,
+        };
+        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
+        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let cancel_handle = CancelHandle::new(cancel_flag.clone(), None);
+        let mut d = AerorsyncDriver::new(transport, cancel_handle);
+        let mut sink = CollectingSink::default();
+        cancel_flag.store(true, Ordering::SeqCst);
+        let err = d
+            .drive_upload(
+                RemoteCommandSpec::upload("/remote/x"),
+                sample_file_list_entry("target.bin"),
+                &[],
+                &MockSigAdapter::default(),
+                &mut sink,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn driver_delta_split_across_data_frames_reassembles() {
+        // Split the delta stream across two Data frames. Driver must
+        // accumulate payloads until decode_delta_stream succeeds.
+        use crate::aerorsync::real_wire::encode_delta_stream;
+        let wire_ops = vec![DeltaOp::CopyRun {
+            start_token_index: 0,
+            run_length: 1,
+        }];
+        let report = DeltaStreamReport {
+            ops: wire_ops,
+            file_checksum: vec![0xEE; A2_3_FILE_CHECKSUM_LEN],
         };
         let delta_bytes = encode_delta_stream(&report);
+        let half = delta_bytes.len() / 2;
 
         let opts = FileListDecodeOptions {
             protocol: 31,
@@ -4132,77 +7323,303 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
         let term_bytes = encode_file_list_terminator(&opts);
         let mut inbound = canonical_server_preamble_bytes();
         inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
         inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes[..half]));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes[half..]));
+
         let transport = mock_transport_with_raw_inbound(inbound);
         let adapter =
             MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-        let _ = d
+        let err = d
             .drive_download(
                 RemoteCommandSpec::download("/remote/x"),
                 b"BLK0",
                 &adapter,
                 &mut sink,
             )
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::UnsupportedVersion);
+        assert!(d.reconstructed().is_some());
+        assert_eq!(d.received_file_checksum(), Some(vec![0xEE; 16].as_slice()),);
+    }
+
+    // ---- A2.4 tests ------------------------------------------------------
+
+    fn build_summary_frame_bytes(protocol: u32) -> Vec<u8> {
+        use crate::aerorsync::real_wire::encode_summary_frame;
+        let frame = SummaryFrame {
+            total_read: 12345,
+            total_written: 67890,
+            total_size: 4096,
+            flist_buildtime: Some(7),
+            flist_xfertime: Some(3),
+        };
+        encode_summary_frame(&frame, protocol)
+    }
+
+    async fn drive_upload_to_stub(
+        d: &mut AerorsyncDriver<MockRemoteShellTransport>,
+        sink: &mut CollectingSink,
+    ) {
+        drive_upload_to_stub_with_spec(d, sink, RemoteCommandSpec::upload("/remote/x")).await;
+    }
+
+    async fn drive_aerorsync_upload_to_stub(
+        d: &mut AerorsyncDriver<MockRemoteShellTransport>,
+        sink: &mut CollectingSink,
+    ) {
+        drive_upload_to_stub_with_spec(d, sink, RemoteCommandSpec::aerorsync_upload("/remote/x"))
             .await;
-        assert!(!d.committed(), "download A2.3 never crosses PreCommit");
+    }
+
+    async fn drive_upload_to_stub_with_spec(
+        d: &mut AerorsyncDriver<MockRemoteShellTransport>,
+        sink: &mut CollectingSink,
+        spec: RemoteCommandSpec,
+    ) {
+        // Reach the A2.3 stub frontier so finish_session has a live
+        // stream to finalise.
+        let err = d
+            .drive_upload(
+                spec,
+                sample_file_list_entry("target.bin"),
+                &[],
+                &MockSigAdapter::default(),
+                sink,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::UnsupportedVersion);
     }
 
     #[tokio::test]
-    async fn driver_download_delta_aborts_on_terminal_oob_post_sigs() {
-        // After the file list phase + local signature emission, server
-        // sends a terminal Error OOB in place of the delta stream.
-        // The driver must bail with RemoteError and committed stays false
-        // (download path never crosses PreCommit in A2.3).
-        let opts = FileListDecodeOptions {
-            protocol: 31,
-            xfer_flags_as_varint: true,
-            // B.2: align with `build_flist_options()` so test-side
-            // pre-encoded payloads round-trip through the driver decoder.
-            always_checksum: true,
-            csum_len: 16,
-            preserve_uid: true,
-            preserve_gid: true,
-            previous_name: None,
+    async fn driver_finish_session_upload_emits_ndx_done_phase_loop() {
+        // B.2 Step 5 pin: stock rsync upload has no client->server
+        // SummaryFrame. The client sender emits NDX_DONE for the two
+        // send_files phase transitions, one final send_files NDX_DONE,
+        // then the read_final_goodbye ACK NDX_DONE.
+        let head = SumHead {
+            count: 1,
+            block_length: 1024,
+            checksum_length: 2,
+            remainder_length: 0,
+        };
+        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
+        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
+        // Frozen upload oracle server->client tail:
+        // phase-loop NDX_DONE x3, then read_final_goodbye NDX_DONE x2.
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00, 0x00, 0x00]));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let last_raw_outbound = transport.last_raw_outbound.clone();
+        let mut d = make_driver(transport);
+        let mut sink = CollectingSink::default();
+
+        drive_upload_to_stub(&mut d, &mut sink).await;
+        let outbound_before_finish = {
+            let guard = last_raw_outbound.lock().unwrap();
+            let outbound_arc = guard.as_ref().expect("raw stream must have been opened");
+            let len = outbound_arc.lock().unwrap().len();
+            len
+        };
+
+        d.finish_session(&mut sink)
+            .await
+            .expect("finish_session upload stock-rsync tail");
+
+        let expected_suffix = [
+            mux_frame(MuxTag::Data, &[0x00]),
+            mux_frame(MuxTag::Data, &[0x00]),
+            mux_frame(MuxTag::Data, &[0x00]),
+            mux_frame(MuxTag::Data, &[0x00]),
+        ]
+        .concat();
+        let guard = last_raw_outbound.lock().unwrap();
+        let outbound_arc = guard.as_ref().expect("raw stream must have been opened");
+        let outbound = outbound_arc.lock().unwrap().clone();
+        assert_eq!(
+            &outbound[outbound_before_finish..],
+            expected_suffix.as_slice(),
+            "upload finish must emit only NDX_DONE markers, no SummaryFrame"
+        );
+        assert!(
+            d.received_summary().is_none(),
+            "client-sender upload must not synthesize a SummaryFrame"
+        );
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Complete);
+    }
+
+    #[tokio::test]
+    async fn driver_finish_session_aerorsync_serve_upload_emits_summary_frame_and_completes() {
+        // Dev helper compatibility: aerorsync_serve still expects the
+        // legacy client-emitted NDX_DONE + SummaryFrame and returns one
+        // trailing NDX_DONE byte.
+        let head = SumHead {
+            count: 1,
+            block_length: 1024,
+            checksum_length: 2,
+            remainder_length: 0,
+        };
+        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
+        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
+        // Trailing NDX_DONE (single 0x00 byte in MSG_DATA) from server.
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let last_raw_shutdown = transport.last_raw_shutdown.clone();
+        let last_raw_outbound = transport.last_raw_outbound.clone();
+        let mut d = make_driver(transport);
+        let mut sink = CollectingSink::default();
+
+        drive_aerorsync_upload_to_stub(&mut d, &mut sink).await;
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Stub);
+
+        d.finish_session(&mut sink)
+            .await
+            .expect("finish_session upload happy path");
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Complete);
+        assert_eq!(d.session_role(), Some(SessionRole::Sender));
+
+        // `received_summary()` now holds the LOCALLY emitted summary,
+        // populated from the driver's counters as of the pre-emit
+        // snapshot (matches rsync's `handle_stats` semantics).
+        let summary = d.received_summary().expect("emitted summary cached");
+        assert_eq!(summary.total_size, 4096, "from sample_file_list_entry");
+        assert!(
+            summary.total_written > 0,
+            "total_written must be positive (pre-emit delta bytes)"
+        );
+        // Post-emit wire totals must be >= the pre-emit snapshot, since
+        // the summary bytes themselves contribute to sent_data_bytes.
+        assert!(
+            (summary.total_written as u64) <= d.sent_data_bytes(),
+            "summary.total_written ({}) must be <= post-finish sent_data_bytes ({})",
+            summary.total_written,
+            d.sent_data_bytes()
+        );
+        // `total_read` is snapshotted pre-emit; `read_trailing_ndx_done`
+        // may pull one more byte after that. Invariant: summary value
+        // is at most one byte behind the final driver counter.
+        assert!(
+            summary.total_read as u64 <= d.received_raw_bytes(),
+            "summary.total_read ({}) must be <= final received_raw_bytes ({})",
+            summary.total_read,
+            d.received_raw_bytes()
+        );
+        assert!(
+            d.received_raw_bytes() - summary.total_read as u64 <= 1,
+            "trailing NDX_DONE read must add at most 1 byte after snapshot"
+        );
+
+        // Verify the outbound wire carries a MSG_DATA whose payload
+        // starts with 0x00 (NDX_DONE) followed by the encoded summary.
+        let expected_suffix = {
+            let mut v = vec![0x00];
+            v.extend_from_slice(&encode_summary_frame(summary, 31));
+            v
+        };
+        let guard = last_raw_outbound.lock().unwrap();
+        let outbound_arc = guard.as_ref().expect("raw stream must have been opened");
+        let outbound = outbound_arc.lock().unwrap().clone();
+        assert!(
+            outbound
+                .windows(expected_suffix.len())
+                .any(|w| w == expected_suffix.as_slice()),
+            "outbound must contain NDX_DONE + encoded summary as a single MSG_DATA payload"
+        );
+
+        // Shutdown flag must still be flipped by the driver.
+        let shutdown_arc_guard = last_raw_shutdown.lock().unwrap();
+        let shutdown_arc = shutdown_arc_guard
+            .as_ref()
+            .expect("raw stream must have been opened");
+        assert!(
+            *shutdown_arc.lock().unwrap(),
+            "shutdown_raw_stream must flip the mock flag"
+        );
+    }
+
+    #[tokio::test]
+    async fn driver_finish_session_upload_populates_session_stats_from_counters() {
+        let head = SumHead {
+            count: 1,
+            block_length: 1024,
+            checksum_length: 2,
+            remainder_length: 0,
+        };
+        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
+        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00, 0x00, 0x00]));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let mut d = make_driver(transport);
+        let mut sink = CollectingSink::default();
+
+        drive_upload_to_stub(&mut d, &mut sink).await;
+        d.finish_session(&mut sink).await.unwrap();
+
+        let sent = d.sent_data_bytes();
+        let recv = d.received_raw_bytes();
+        let stats = d.session_stats();
+        assert!(sent > 0, "some data must have been written in upload");
+        assert!(recv > 0, "some data must have been read for sig phase");
+        assert_eq!(stats.bytes_sent, sent);
+        assert_eq!(stats.bytes_received, recv);
+        // Other SessionStats fields remain at their default: A4 populates
+        // files_seen / files_delta / literal_bytes / matched_bytes from
+        // its own instrumentation layer.
+        assert_eq!(stats.files_seen, 0);
+    }
+
+    #[tokio::test]
+    async fn driver_finish_session_upload_aborts_on_terminal_oob_in_trailing_slot() {
+        // If the server sends an OOB Error where a phase-loop NDX_DONE is
+        // expected, finish must bail with RemoteError and phase=Failed.
+        let head = SumHead {
+            count: 1,
+            block_length: 1024,
+            checksum_length: 2,
+            remainder_length: 0,
         };
-        let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
-        let term_bytes = encode_file_list_terminator(&opts);
+        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
+        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
         let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Error, b"delta stream crashed"));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
+        // Terminal Error occupies the trailing NDX_DONE slot.
+        inbound.extend_from_slice(&mux_frame(MuxTag::Error, b"trailing phase crash"));
         let transport = mock_transport_with_raw_inbound(inbound);
-        let adapter =
-            MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-        let err = d
-            .drive_download(
-                RemoteCommandSpec::download("/remote/x"),
-                b"BLK0",
-                &adapter,
-                &mut sink,
-            )
-            .await
-            .unwrap_err();
+
+        drive_upload_to_stub(&mut d, &mut sink).await;
+        let err = d.finish_session(&mut sink).await.unwrap_err();
         assert_eq!(err.kind, AerorsyncErrorKind::RemoteError);
-        assert!(err.detail.contains("delta stream crashed"));
-        assert!(!d.committed(), "download stays PreCommit even on error");
+        assert!(err.detail.contains("trailing phase crash"));
         assert_eq!(d.phase(), AerorsyncSessionPhase::Failed);
     }
 
     #[tokio::test]
-    async fn driver_upload_delta_cancel_surfaces_typed_cancelled() {
-        // Cancel the driver before it reaches the delta phase: the
-        // check_cancel guards inside send_delta_phase_single_file must
-        // surface a typed Cancelled error, not a transport failure.
+    async fn driver_finish_session_cancel_surfaces_typed_cancelled() {
         let head = SumHead {
             count: 1,
             block_length: 1024,
@@ -4213,41 +7630,30 @@ mod tests {
         let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
         let mut inbound = canonical_server_preamble_bytes();
         inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
+        // No summary frame: but a cancel will fire before the read.
         let transport = mock_transport_with_raw_inbound(inbound);
         let cancel_flag = Arc::new(AtomicBool::new(false));
         let cancel_handle = CancelHandle::new(cancel_flag.clone(), None);
         let mut d = AerorsyncDriver::new(transport, cancel_handle);
         let mut sink = CollectingSink::default();
+        drive_aerorsync_upload_to_stub(&mut d, &mut sink).await;
         cancel_flag.store(true, Ordering::SeqCst);
-        let err = d
-            .drive_upload(
-                RemoteCommandSpec::upload("/remote/x"),
-                sample_file_list_entry("target.bin"),
-                &[],
-                &MockSigAdapter::default(),
-                &mut sink,
-            )
-            .await
-            .unwrap_err();
+        let err = d.finish_session(&mut sink).await.unwrap_err();
         assert_eq!(err.kind, AerorsyncErrorKind::Cancelled);
     }
 
     #[tokio::test]
-    async fn driver_delta_split_across_data_frames_reassembles() {
-        // Split the delta stream across two Data frames. Driver must
-        // accumulate payloads until decode_delta_stream succeeds.
-        use crate::aerorsync::real_wire::encode_delta_stream;
+    async fn driver_download_finish_session_preserves_committed_false() {
+        // Full happy download + finish_session → session complete,
+        // committed stays false (A4 flips it when writing to temp file).
         let wire_ops = vec![DeltaOp::CopyRun {
             start_token_index: 0,
             run_length: 1,
         }];
-        let report = DeltaStreamReport {
+        let delta_bytes = encode_delta_stream(&DeltaStreamReport {
             ops: wire_ops,
-            file_checksum: vec![0xEE; A2_3_FILE_CHECKSUM_LEN],
-        };
-        let delta_bytes = encode_delta_stream(&report);
-        let half = delta_bytes.len() / 2;
-
+            file_checksum: vec![0u8; A2_3_FILE_CHECKSUM_LEN],
+        });
         let opts = FileListDecodeOptions {
             protocol: 31,
             xfer_flags_as_varint: true,
@@ -4258,148 +7664,111 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
         let term_bytes = encode_file_list_terminator(&opts);
+        let summary_bytes = build_summary_frame_bytes(31);
+        // S8j: real rsync 3.2.7 emits exactly 3 leading NDX_DONE
+        // markers between the delta stream's file-csum trailer and the
+        // SummaryFrame. `finish_session` on a Receiver-role driver now
+        // drains them before decoding: replicate that shape here.
+        let ndx_done_leading: Vec<u8> = vec![0x00; PRE_SUMMARY_NDX_DONE_COUNT_DOWNLOAD];
         let mut inbound = canonical_server_preamble_bytes();
         inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
         inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes[..half]));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes[half..]));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &ndx_done_leading));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &summary_bytes));
 
         let transport = mock_transport_with_raw_inbound(inbound);
         let adapter =
             MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-        let err = d
+        let _ = d
             .drive_download(
                 RemoteCommandSpec::download("/remote/x"),
                 b"BLK0",
                 &adapter,
                 &mut sink,
             )
-            .await
-            .unwrap_err();
-        assert_eq!(err.kind, AerorsyncErrorKind::UnsupportedVersion);
+            .await;
+        d.finish_session(&mut sink).await.unwrap();
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Complete);
+        assert!(
+            !d.committed(),
+            "download A2.4 stays PreCommit; A4 owns the flip"
+        );
         assert!(d.reconstructed().is_some());
-        assert_eq!(d.received_file_checksum(), Some(vec![0xEE; 16].as_slice()),);
+        assert!(d.received_summary().is_some());
     }
 
-    // ---- A2.4 tests ------------------------------------------------------
+    // ---- S8j tests (xxh128 wire layout) ----------------------------------
 
-    fn build_summary_frame_bytes(protocol: u32) -> Vec<u8> {
-        use crate::aerorsync::real_wire::encode_summary_frame;
-        let frame = SummaryFrame {
-            total_read: 12345,
-            total_written: 67890,
-            total_size: 4096,
-            flist_buildtime: Some(7),
-            flist_xfertime: Some(3),
-        };
-        encode_summary_frame(&frame, protocol)
-    }
+    #[test]
+    fn xxh128_wire_produces_16_bytes_split_lo_le_hi_le() {
+        // Layout invariant pin: `compute_xxh128_wire` must produce exactly
+        // 16 bytes whose first half is the lower 64 bits of the hash in
+        // little-endian, and whose second half is the upper 64 bits also
+        // in little-endian. This mirrors rsync 3.2.7 `checksum.c`:
+        //   SIVAL64(buf, 0, lo);
+        //   SIVAL64(buf, 8, hi);
+        //: `SIVAL64` is the LE 64-bit writer. A future rsync version
+        // that switched byte order would surface here before reaching
+        // lane 3.
+        let payload = b"aeroftp strada-c s8j xxh128 pin";
+        let wire = compute_xxh128_wire(payload);
+        assert_eq!(wire.len(), 16, "xxh128 wire must be exactly 16 bytes");
 
-    async fn drive_upload_to_stub(
-        d: &mut AerorsyncDriver<MockRemoteShellTransport>,
-        sink: &mut CollectingSink,
-    ) {
-        drive_upload_to_stub_with_spec(d, sink, RemoteCommandSpec::upload("/remote/x")).await;
+        let hash = xxh3_128(payload);
+        let lo = hash as u64;
+        let hi = (hash >> 64) as u64;
+        assert_eq!(
+            &wire[0..8],
+            &lo.to_le_bytes(),
+            "first 8 bytes must be lower u64 little-endian (SIVAL64(buf,0,lo))"
+        );
+        assert_eq!(
+            &wire[8..16],
+            &hi.to_le_bytes(),
+            "next 8 bytes must be upper u64 little-endian (SIVAL64(buf,8,hi))"
+        );
     }
 
-    async fn drive_aerorsync_upload_to_stub(
-        d: &mut AerorsyncDriver<MockRemoteShellTransport>,
-        sink: &mut CollectingSink,
-    ) {
-        drive_upload_to_stub_with_spec(d, sink, RemoteCommandSpec::aerorsync_upload("/remote/x"))
-            .await;
+    #[test]
+    fn xxh128_wire_is_deterministic_across_calls() {
+        // Purity pin: the same payload MUST produce the same 16 bytes
+        // every time. Guards against an accidental seed drift if xxhash
+        // library grows a "with_seed" helper default.
+        let payload = b"determinism check";
+        let a = compute_xxh128_wire(payload);
+        let b = compute_xxh128_wire(payload);
+        assert_eq!(a, b);
     }
 
-    async fn drive_upload_to_stub_with_spec(
-        d: &mut AerorsyncDriver<MockRemoteShellTransport>,
-        sink: &mut CollectingSink,
-        spec: RemoteCommandSpec,
-    ) {
-        // Reach the A2.3 stub frontier so finish_session has a live
-        // stream to finalise.
-        let err = d
-            .drive_upload(
-                spec,
-                sample_file_list_entry("target.bin"),
-                &[],
-                &MockSigAdapter::default(),
-                sink,
-            )
-            .await
-            .unwrap_err();
-        assert_eq!(err.kind, AerorsyncErrorKind::UnsupportedVersion);
+    #[test]
+    fn xxh128_wire_differs_for_single_bit_flip() {
+        // Avalanche sanity: flipping one bit of the payload must change
+        // the wire output. Guards against a silent all-zero implementation.
+        let a = compute_xxh128_wire(b"payload-A");
+        let b = compute_xxh128_wire(b"payload-B");
+        assert_ne!(a, b);
     }
 
-    #[tokio::test]
-    async fn driver_finish_session_upload_emits_ndx_done_phase_loop() {
-        // B.2 Step 5 pin: stock rsync upload has no client->server
-        // SummaryFrame. The client sender emits NDX_DONE for the two
-        // send_files phase transitions, one final send_files NDX_DONE,
-        // then the read_final_goodbye ACK NDX_DONE.
-        let head = SumHead {
-            count: 1,
-            block_length: 1024,
-            checksum_length: 2,
-            remainder_length: 0,
-        };
-        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
-        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
-        let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
-        // Frozen upload oracle server->client tail:
-        // phase-loop NDX_DONE x3, then read_final_goodbye NDX_DONE x2.
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00, 0x00, 0x00]));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
-        let transport = mock_transport_with_raw_inbound(inbound);
-        let last_raw_outbound = transport.last_raw_outbound.clone();
-        let mut d = make_driver(transport);
-        let mut sink = CollectingSink::default();
-
-        drive_upload_to_stub(&mut d, &mut sink).await;
-        let outbound_before_finish = {
-            let guard = last_raw_outbound.lock().unwrap();
-            let outbound_arc = guard.as_ref().expect("raw stream must have been opened");
-            let len = outbound_arc.lock().unwrap().len();
-            len
-        };
-
-        d.finish_session(&mut sink)
-            .await
-            .expect("finish_session upload stock-rsync tail");
-
-        let expected_suffix = [
-            mux_frame(MuxTag::Data, &[0x00]),
-            mux_frame(MuxTag::Data, &[0x00]),
-            mux_frame(MuxTag::Data, &[0x00]),
-            mux_frame(MuxTag::Data, &[0x00]),
-        ]
-        .concat();
-        let guard = last_raw_outbound.lock().unwrap();
-        let outbound_arc = guard.as_ref().expect("raw stream must have been opened");
-        let outbound = outbound_arc.lock().unwrap().clone();
-        assert_eq!(
-            &outbound[outbound_before_finish..],
-            expected_suffix.as_slice(),
-            "upload finish must emit only NDX_DONE markers, no SummaryFrame"
-        );
-        assert!(
-            d.received_summary().is_none(),
-            "client-sender upload must not synthesize a SummaryFrame"
-        );
-        assert_eq!(d.phase(), AerorsyncSessionPhase::Complete);
-    }
+    // ---- S8j tests (upload summary emit byte-level) ----------------------
 
     #[tokio::test]
-    async fn driver_finish_session_aerorsync_serve_upload_emits_summary_frame_and_completes() {
-        // Dev helper compatibility: aerorsync_serve still expects the
-        // legacy client-emitted NDX_DONE + SummaryFrame and returns one
-        // trailing NDX_DONE byte.
+    async fn emit_summary_phase_byte_level_layout() {
+        // Byte-level pin: emitted payload is exactly
+        //   [0x00] ++ encode_summary_frame(SummaryFrame{...}, protocol)
+        // wrapped in a single MSG_DATA mux frame. This guards the
+        // sender-side finish semantics against accidental reordering or
+        // split framing in a future refactor.
         let head = SumHead {
             count: 1,
             block_length: 1024,
@@ -4410,172 +7779,210 @@ mod tests {
         let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
         let mut inbound = canonical_server_preamble_bytes();
         inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
-        // Trailing NDX_DONE (single 0x00 byte in MSG_DATA) from server.
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00])); // trailing
         let transport = mock_transport_with_raw_inbound(inbound);
-        let last_raw_shutdown = transport.last_raw_shutdown.clone();
         let last_raw_outbound = transport.last_raw_outbound.clone();
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-
         drive_aerorsync_upload_to_stub(&mut d, &mut sink).await;
-        assert_eq!(d.phase(), AerorsyncSessionPhase::Stub);
-
-        d.finish_session(&mut sink)
-            .await
-            .expect("finish_session upload happy path");
-        assert_eq!(d.phase(), AerorsyncSessionPhase::Complete);
-        assert_eq!(d.session_role(), Some(SessionRole::Sender));
-
-        // `received_summary()` now holds the LOCALLY emitted summary,
-        // populated from the driver's counters as of the pre-emit
-        // snapshot (matches rsync's `handle_stats` semantics).
-        let summary = d.received_summary().expect("emitted summary cached");
-        assert_eq!(summary.total_size, 4096, "from sample_file_list_entry");
-        assert!(
-            summary.total_written > 0,
-            "total_written must be positive (pre-emit delta bytes)"
-        );
-        // Post-emit wire totals must be >= the pre-emit snapshot, since
-        // the summary bytes themselves contribute to sent_data_bytes.
-        assert!(
-            (summary.total_written as u64) <= d.sent_data_bytes(),
-            "summary.total_written ({}) must be <= post-finish sent_data_bytes ({})",
-            summary.total_written,
-            d.sent_data_bytes()
-        );
-        // `total_read` is snapshotted pre-emit; `read_trailing_ndx_done`
-        // may pull one more byte after that. Invariant: summary value
-        // is at most one byte behind the final driver counter.
-        assert!(
-            summary.total_read as u64 <= d.received_raw_bytes(),
-            "summary.total_read ({}) must be <= final received_raw_bytes ({})",
-            summary.total_read,
-            d.received_raw_bytes()
-        );
-        assert!(
-            d.received_raw_bytes() - summary.total_read as u64 <= 1,
-            "trailing NDX_DONE read must add at most 1 byte after snapshot"
-        );
+        d.finish_session(&mut sink).await.unwrap();
 
-        // Verify the outbound wire carries a MSG_DATA whose payload
-        // starts with 0x00 (NDX_DONE) followed by the encoded summary.
-        let expected_suffix = {
-            let mut v = vec![0x00];
-            v.extend_from_slice(&encode_summary_frame(summary, 31));
+        let emitted = d
+            .received_summary()
+            .cloned()
+            .expect("summary cached on emit");
+        let expected_payload = {
+            let mut v = Vec::with_capacity(1 + 9 * 5);
+            v.push(0x00);
+            v.extend_from_slice(&encode_summary_frame(&emitted, 31));
             v
         };
+        let expected_mux_frame = mux_frame(MuxTag::Data, &expected_payload);
         let guard = last_raw_outbound.lock().unwrap();
-        let outbound_arc = guard.as_ref().expect("raw stream must have been opened");
-        let outbound = outbound_arc.lock().unwrap().clone();
+        let arc = guard.as_ref().unwrap();
+        let outbound = arc.lock().unwrap().clone();
         assert!(
             outbound
-                .windows(expected_suffix.len())
-                .any(|w| w == expected_suffix.as_slice()),
-            "outbound must contain NDX_DONE + encoded summary as a single MSG_DATA payload"
-        );
-
-        // Shutdown flag must still be flipped by the driver.
-        let shutdown_arc_guard = last_raw_shutdown.lock().unwrap();
-        let shutdown_arc = shutdown_arc_guard
-            .as_ref()
-            .expect("raw stream must have been opened");
-        assert!(
-            *shutdown_arc.lock().unwrap(),
-            "shutdown_raw_stream must flip the mock flag"
+                .windows(expected_mux_frame.len())
+                .any(|w| w == expected_mux_frame.as_slice()),
+            "outbound must contain the exact MSG_DATA frame for NDX_DONE + summary"
         );
     }
 
+    // ---- S8j tests (NDX_DONE drain download direction) -------------------
+
     #[tokio::test]
-    async fn driver_finish_session_upload_populates_session_stats_from_counters() {
-        let head = SumHead {
-            count: 1,
-            block_length: 1024,
-            checksum_length: 2,
-            remainder_length: 0,
+    async fn download_drain_absorbs_three_leading_ndx_done_in_one_frame() {
+        // A single MSG_DATA carries `[0x00, 0x00, 0x00, summary_bytes…]`.
+        // The drain must strip exactly 3 leading zeros and leave the
+        // summary bytes as seed for `receive_summary_phase`.
+        let wire_ops = vec![DeltaOp::CopyRun {
+            start_token_index: 0,
+            run_length: 1,
+        }];
+        let delta_bytes = encode_delta_stream(&DeltaStreamReport {
+            ops: wire_ops,
+            file_checksum: vec![0u8; A2_3_FILE_CHECKSUM_LEN],
+        });
+        let opts = FileListDecodeOptions {
+            protocol: 31,
+            xfer_flags_as_varint: true,
+            // B.2: align with `build_flist_options()` so test-side
+            // pre-encoded payloads round-trip through the driver decoder.
+            always_checksum: true,
+            csum_len: 16,
+            preserve_uid: true,
+            preserve_gid: true,
+            previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
-        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
-        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
+        let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
+        let term_bytes = encode_file_list_terminator(&opts);
+        let summary_bytes = build_summary_frame_bytes(31);
+
+        // Combine 3 NDX_DONE + summary into a single MSG_DATA frame.
+        let mut combined = Vec::with_capacity(3 + summary_bytes.len());
+        combined.extend_from_slice(&[0x00, 0x00, 0x00]);
+        combined.extend_from_slice(&summary_bytes);
+
         let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00, 0x00, 0x00]));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &combined));
+
         let transport = mock_transport_with_raw_inbound(inbound);
+        let adapter =
+            MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-
-        drive_upload_to_stub(&mut d, &mut sink).await;
+        let _ = d
+            .drive_download(
+                RemoteCommandSpec::download("/remote/x"),
+                b"BLK0",
+                &adapter,
+                &mut sink,
+            )
+            .await;
         d.finish_session(&mut sink).await.unwrap();
-
-        let sent = d.sent_data_bytes();
-        let recv = d.received_raw_bytes();
-        let stats = d.session_stats();
-        assert!(sent > 0, "some data must have been written in upload");
-        assert!(recv > 0, "some data must have been read for sig phase");
-        assert_eq!(stats.bytes_sent, sent);
-        assert_eq!(stats.bytes_received, recv);
-        // Other SessionStats fields remain at their default: A4 populates
-        // files_seen / files_delta / literal_bytes / matched_bytes from
-        // its own instrumentation layer.
-        assert_eq!(stats.files_seen, 0);
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Complete);
+        assert!(d.received_summary().is_some());
+        assert_eq!(d.session_role(), Some(SessionRole::Receiver));
     }
 
     #[tokio::test]
-    async fn driver_finish_session_upload_aborts_on_terminal_oob_in_trailing_slot() {
-        // If the server sends an OOB Error where a phase-loop NDX_DONE is
-        // expected, finish must bail with RemoteError and phase=Failed.
-        let head = SumHead {
-            count: 1,
-            block_length: 1024,
-            checksum_length: 2,
-            remainder_length: 0,
+    async fn download_drain_rejects_non_zero_in_marker_slot() {
+        // If the 3-byte window where rsync MUST emit NDX_DONEs carries
+        // anything other than zero, the drain surfaces InvalidFrame
+        // instead of silently accepting a drifted summary offset.
+        let wire_ops = vec![DeltaOp::CopyRun {
+            start_token_index: 0,
+            run_length: 1,
+        }];
+        let delta_bytes = encode_delta_stream(&DeltaStreamReport {
+            ops: wire_ops,
+            file_checksum: vec![0u8; A2_3_FILE_CHECKSUM_LEN],
+        });
+        let opts = FileListDecodeOptions {
+            protocol: 31,
+            xfer_flags_as_varint: true,
+            // B.2: align with `build_flist_options()` so test-side
+            // pre-encoded payloads round-trip through the driver decoder.
+            always_checksum: true,
+            csum_len: 16,
+            preserve_uid: true,
+            preserve_gid: true,
+            previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
-        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
-        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
+        let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
+        let term_bytes = encode_file_list_terminator(&opts);
+
+        // First byte is NDX_DONE (drain enters the strict path), second
+        // byte is garbage: the drain must refuse.
+        let poisoned = vec![0x00, 0xAB, 0xCD, 0xEF, 0xFE];
         let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
-        // Terminal Error occupies the trailing NDX_DONE slot.
-        inbound.extend_from_slice(&mux_frame(MuxTag::Error, b"trailing phase crash"));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &poisoned));
+
         let transport = mock_transport_with_raw_inbound(inbound);
+        let adapter =
+            MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-
-        drive_upload_to_stub(&mut d, &mut sink).await;
-        let err = d.finish_session(&mut sink).await.unwrap_err();
-        assert_eq!(err.kind, AerorsyncErrorKind::RemoteError);
-        assert!(err.detail.contains("trailing phase crash"));
-        assert_eq!(d.phase(), AerorsyncSessionPhase::Failed);
+        let _ = d
+            .drive_download(
+                RemoteCommandSpec::download("/remote/x"),
+                b"BLK0",
+                &adapter,
+                &mut sink,
+            )
+            .await;
+        let err = d
+            .finish_session(&mut sink)
+            .await
+            .expect_err("drain must reject non-zero in marker slot");
+        assert_eq!(err.kind, AerorsyncErrorKind::InvalidFrame);
+        assert!(err.detail.contains("NDX_DONE"), "detail: {}", err.detail);
     }
 
+    // ---- A4 tests (drive_*_through_delta entry points) -------------------
+
     #[tokio::test]
-    async fn driver_finish_session_cancel_surfaces_typed_cancelled() {
+    async fn drive_upload_through_delta_returns_ok_on_happy_path() {
+        // A4 invariant: the new upload entry point elides the
+        // `UnsupportedVersion` stub sentinel that `drive_upload` emits on
+        // happy path. The inner drive loop reaches post-delta and the
+        // caller gets `Ok(())` so it can call `finish_session` explicitly.
+        //
+        // Inbound: server preamble + signature phase payload (sum_head +
+        // 1 sum_block): same shape as
+        // `driver_upload_writes_preamble_then_filelist_then_terminator`.
         let head = SumHead {
             count: 1,
             block_length: 1024,
             checksum_length: 2,
             remainder_length: 0,
         };
-        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
+        let blocks = vec![make_sig_block(0xAAAAAAAA, 0x11, 2)];
         let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
         let mut inbound = canonical_server_preamble_bytes();
         inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
-        // No summary frame: but a cancel will fire before the read.
         let transport = mock_transport_with_raw_inbound(inbound);
-        let cancel_flag = Arc::new(AtomicBool::new(false));
-        let cancel_handle = CancelHandle::new(cancel_flag.clone(), None);
-        let mut d = AerorsyncDriver::new(transport, cancel_handle);
+        let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-        drive_aerorsync_upload_to_stub(&mut d, &mut sink).await;
-        cancel_flag.store(true, Ordering::SeqCst);
-        let err = d.finish_session(&mut sink).await.unwrap_err();
-        assert_eq!(err.kind, AerorsyncErrorKind::Cancelled);
+        let res = d
+            .drive_upload_through_delta(
+                RemoteCommandSpec::upload("/remote/target.bin"),
+                sample_file_list_entry("target.bin"),
+                &[],
+                &MockSigAdapter::default(),
+                &mut sink,
+            )
+            .await;
+        assert!(res.is_ok(), "through_delta must return Ok, got {:?}", res);
+        // Phase must NOT be Stub (that's the legacy sentinel indicator).
+        assert_ne!(
+            d.phase(),
+            AerorsyncSessionPhase::Stub,
+            "through_delta must not set Stub phase"
+        );
+        // Delta phase crosses the PreCommit→PostCommit boundary.
+        assert!(d.committed());
     }
 
     #[tokio::test]
-    async fn driver_download_finish_session_preserves_committed_false() {
-        // Full happy download + finish_session → session complete,
-        // committed stays false (A4 flips it when writing to temp file).
+    async fn drive_download_through_delta_returns_ok_on_happy_path() {
         let wire_ops = vec![DeltaOp::CopyRun {
             start_token_index: 0,
             run_length: 1,
@@ -4594,275 +8001,584 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
         let term_bytes = encode_file_list_terminator(&opts);
-        let summary_bytes = build_summary_frame_bytes(31);
-        // S8j: real rsync 3.2.7 emits exactly 3 leading NDX_DONE
-        // markers between the delta stream's file-csum trailer and the
-        // SummaryFrame. `finish_session` on a Receiver-role driver now
-        // drains them before decoding: replicate that shape here.
-        let ndx_done_leading: Vec<u8> = vec![0x00; PRE_SUMMARY_NDX_DONE_COUNT_DOWNLOAD];
         let mut inbound = canonical_server_preamble_bytes();
         inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
         inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
         inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &ndx_done_leading));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &summary_bytes));
 
         let transport = mock_transport_with_raw_inbound(inbound);
         let adapter =
             MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-        let _ = d
-            .drive_download(
+        let res = d
+            .drive_download_through_delta(
                 RemoteCommandSpec::download("/remote/x"),
                 b"BLK0",
                 &adapter,
                 &mut sink,
             )
             .await;
-        d.finish_session(&mut sink).await.unwrap();
-        assert_eq!(d.phase(), AerorsyncSessionPhase::Complete);
         assert!(
-            !d.committed(),
-            "download A2.4 stays PreCommit; A4 owns the flip"
+            res.is_ok(),
+            "through_delta download must return Ok, got {:?}",
+            res
         );
         assert!(d.reconstructed().is_some());
-        assert!(d.received_summary().is_some());
+        // Download path leaves committed=false; A4 flips it at temp-file open.
+        assert!(!d.committed());
     }
 
-    // ---- S8j tests (xxh128 wire layout) ----------------------------------
+    #[tokio::test]
+    async fn drive_upload_through_delta_propagates_real_error() {
+        // Pin that the new entry point does NOT mask genuine drive errors
+        // behind an `Ok(())`: when the remote closes mid-phase, the returned
+        // error must be the real `TransportFailure` (not the legacy stub
+        // sentinel, and not `Ok(())`), and `phase = Failed` must be set.
+        let inbound = canonical_server_preamble_bytes(); // no sig phase → EOF mid-flight
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let mut d = make_driver(transport);
+        let mut sink = CollectingSink::default();
+        let err = d
+            .drive_upload_through_delta(
+                RemoteCommandSpec::upload("/remote/target.bin"),
+                sample_file_list_entry("target.bin"),
+                &[],
+                &MockSigAdapter::default(),
+                &mut sink,
+            )
+            .await
+            .expect_err("expected real TransportFailure, not Ok");
+        assert_eq!(err.kind, AerorsyncErrorKind::TransportFailure);
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Failed);
+    }
 
-    #[test]
-    fn xxh128_wire_produces_16_bytes_split_lo_le_hi_le() {
-        // Layout invariant pin: `compute_xxh128_wire` must produce exactly
-        // 16 bytes whose first half is the lower 64 bits of the hash in
-        // little-endian, and whose second half is the upper 64 bits also
-        // in little-endian. This mirrors rsync 3.2.7 `checksum.c`:
-        //   SIVAL64(buf, 0, lo);
-        //   SIVAL64(buf, 8, hi);
-        //: `SIVAL64` is the LE 64-bit writer. A future rsync version
-        // that switched byte order would surface here before reaching
-        // lane 3.
-        let payload = b"aeroftp strada-c s8j xxh128 pin";
-        let wire = compute_xxh128_wire(payload);
-        assert_eq!(wire.len(), 16, "xxh128 wire must be exactly 16 bytes");
+    // ---- P3-T01 W1.2 streaming-send parity pins -------------------------
+    //
+    // These tests are the byte-identical wire pin for
+    // `send_delta_phase_streaming` against `send_delta_phase_single_file`.
+    // If they fail, the streaming path has diverged from the bulk path
+    // on the wire: root-cause is one of:
+    //   1. the producer (W1.1) emits different ops than `compute_delta`
+    //      (covered by `producer_streaming_matches_bulk_*` in
+    //      `engine_adapter.rs`),
+    //   2. the streaming xxh3 trailer no longer matches `xxh3_128`
+    //      bulk (covered by `streaming_xxh3_matches_bulk_xxh3` below),
+    //   3. the post-plan emission code (zstd compression, wire op
+    //      construction, NDX/iflags/sum_head echo, payload framing) was
+    //      changed in only one of the two functions: they MUST stay
+    //      bit-for-bit symmetric until W1.3 lifts the upload cap.
+    //
+    // The tests avoid relying on `MockSigAdapter`: the bulk path now
+    // calls `compute_delta` against `RealEngineAdapter`'s real rolling
+    // checksum engine (see `_adapter` parameter in the streaming path,
+    // currently unused: both paths derive the plan from
+    // `received_signatures` + the source bytes via the producer / bulk
+    // computation, no mock substitution).
 
-        let hash = xxh3_128(payload);
-        let lo = hash as u64;
-        let hi = (hash >> 64) as u64;
-        assert_eq!(
-            &wire[0..8],
-            &lo.to_le_bytes(),
-            "first 8 bytes must be lower u64 little-endian (SIVAL64(buf,0,lo))"
-        );
-        assert_eq!(
-            &wire[8..16],
-            &hi.to_le_bytes(),
-            "next 8 bytes must be upper u64 little-endian (SIVAL64(buf,8,hi))"
-        );
+    fn build_streaming_parity_inbound(head: SumHead, blocks: Vec<SumBlock>) -> Vec<u8> {
+        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
+        inbound
     }
 
+    /// xxh3 streaming digest must equal xxh3 bulk digest for any
+    /// chunking strategy. If this regresses, the streaming send's
+    /// `file_checksum` trailer will silently diverge and rsync will
+    /// reject the upload with "WHOLE FILE IS WRONG" (exit 22).
     #[test]
-    fn xxh128_wire_is_deterministic_across_calls() {
-        // Purity pin: the same payload MUST produce the same 16 bytes
-        // every time. Guards against an accidental seed drift if xxhash
-        // library grows a "with_seed" helper default.
-        let payload = b"determinism check";
-        let a = compute_xxh128_wire(payload);
-        let b = compute_xxh128_wire(payload);
-        assert_eq!(a, b);
+    fn streaming_xxh3_matches_bulk_xxh3() {
+        let payload: Vec<u8> = (0..50_000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        let bulk = xxh3_128(&payload);
+
+        for chunk_size in [1usize, 7, 1024, 4096, 16384, 50_000] {
+            let mut hasher = Xxh3Default::new();
+            for chunk in payload.chunks(chunk_size) {
+                hasher.update(chunk);
+            }
+            assert_eq!(
+                hasher.digest128(),
+                bulk,
+                "xxh3 streaming digest must equal bulk digest for chunk_size={chunk_size}"
+            );
+        }
+
+        // Empty payload edge case.
+        let bulk_empty = xxh3_128(b"");
+        let mut hasher_empty = Xxh3Default::new();
+        hasher_empty.update(b"");
+        assert_eq!(hasher_empty.digest128(), bulk_empty);
     }
 
-    #[test]
-    fn xxh128_wire_differs_for_single_bit_flip() {
-        // Avalanche sanity: flipping one bit of the payload must change
-        // the wire output. Guards against a silent all-zero implementation.
-        let a = compute_xxh128_wire(b"payload-A");
-        let b = compute_xxh128_wire(b"payload-B");
-        assert_ne!(a, b);
+    /// Wire-byte parity pin: bulk and streaming send paths must produce
+    /// the exact same outbound byte sequence on the raw transport. If
+    /// the assertion fails, prefix of the diff is the first byte where
+    /// the two paths diverged: start hunting there.
+    ///
+    /// Both paths use [`CurrentDeltaSyncBridge`] so the bulk plan and
+    /// the streaming plan come from the SAME algorithm
+    /// (`delta_sync::compute_delta` vs. `RollingDeltaPlanProducer`,
+    /// already cross-pinned bit-for-bit by `producer_streaming_matches_bulk_*`
+    /// in `engine_adapter.rs`).
+    async fn assert_send_parity(source: &[u8], head: SumHead, blocks: Vec<SumBlock>) {
+        assert_send_parity_with(source, head, blocks, TransferOptions::default()).await;
+    }
+
+    /// [`assert_send_parity`] under explicit transfer options. Returns the
+    /// shared outbound capture and the wire ops the streaming sender
+    /// emitted.
+    async fn assert_send_parity_with(
+        source: &[u8],
+        head: SumHead,
+        blocks: Vec<SumBlock>,
+        options: TransferOptions,
+    ) -> (Vec<u8>, Vec<DeltaOp>) {
+        use crate::aerorsync::engine_adapter::CurrentDeltaSyncBridge;
+
+        // Bulk path
+        let bulk_inbound = build_streaming_parity_inbound(head, blocks.clone());
+        let bulk_transport = mock_transport_with_raw_inbound(bulk_inbound);
+        let bulk_last = bulk_transport.last_raw_outbound.clone();
+        let mut bulk_d = make_driver(bulk_transport);
+        let mut bulk_sink = CollectingSink::default();
+        let bulk_adapter = CurrentDeltaSyncBridge::new();
+        bulk_d
+            .drive_upload_through_delta(
+                RemoteCommandSpec::upload("/remote/target.bin").with_options(options.clone()),
+                sample_file_list_entry("target.bin"),
+                source,
+                &bulk_adapter,
+                &mut bulk_sink,
+            )
+            .await
+            .expect("bulk path must complete");
+        let bulk_bytes = {
+            let g = bulk_last.lock().unwrap();
+            let arc = g.as_ref().expect("bulk: raw stream must have been opened");
+            let bytes = arc.lock().unwrap().clone();
+            bytes
+        };
+
+        // Streaming path
+        let stream_inbound = build_streaming_parity_inbound(head, blocks);
+        let stream_transport = mock_transport_with_raw_inbound(stream_inbound);
+        let stream_last = stream_transport.last_raw_outbound.clone();
+        let mut stream_d = make_driver(stream_transport);
+        let mut stream_sink = CollectingSink::default();
+        let stream_adapter = CurrentDeltaSyncBridge::new();
+        let cursor = std::io::Cursor::new(source.to_vec());
+        stream_d
+            .drive_upload_through_delta_streaming(
+                RemoteCommandSpec::upload("/remote/target.bin").with_options(options.clone()),
+                sample_file_list_entry("target.bin"),
+                cursor,
+                source.len() as u64,
+                &stream_adapter,
+                &mut stream_sink,
+            )
+            .await
+            .expect("streaming path must complete");
+        let stream_bytes = {
+            let g = stream_last.lock().unwrap();
+            let arc = g
+                .as_ref()
+                .expect("streaming: raw stream must have been opened");
+            let bytes = arc.lock().unwrap().clone();
+            bytes
+        };
+
+        assert_eq!(
+            bulk_bytes.len(),
+            stream_bytes.len(),
+            "outbound length mismatch: bulk={} streaming={}",
+            bulk_bytes.len(),
+            stream_bytes.len()
+        );
+        if bulk_bytes != stream_bytes {
+            let first_diff = bulk_bytes
+                .iter()
+                .zip(stream_bytes.iter())
+                .position(|(a, b)| a != b)
+                .unwrap_or(bulk_bytes.len());
+            panic!(
+                "outbound divergence at byte {first_diff}: bulk={:#04x} streaming={:#04x}",
+                bulk_bytes.get(first_diff).copied().unwrap_or(0),
+                stream_bytes.get(first_diff).copied().unwrap_or(0)
+            );
+        }
+        (stream_bytes, stream_d.emitted_delta_ops().to_vec())
+    }
+
+    /// Empty source against a `block_size == 0` server head: the
+    /// realistic shape: the receiver's local target is missing or
+    /// zero-byte, so its sum_head emits `block_length = 0`. Both bulk
+    /// and streaming paths then go through their respective
+    /// "whole-file no-baseline" short-circuits and produce zero
+    /// `EngineDeltaOp` (no literal token on the wire) plus the xxh3
+    /// trailer of an empty buffer.
+    #[tokio::test]
+    async fn streaming_send_matches_bulk_send_empty_source_block_size_zero() {
+        let head = SumHead {
+            count: 0,
+            block_length: 0,
+            checksum_length: 0,
+            remainder_length: 0,
+        };
+        assert_send_parity(&[], head, Vec::new()).await;
     }
 
-    // ---- S8j tests (upload summary emit byte-level) ----------------------
+    /// Whole-file path with non-empty source: the receiver advertises
+    /// `block_length = 0` (no baseline) so both paths emit the entire
+    /// source as a single literal. Pin that the streaming path drains
+    /// the reader correctly through the whole-file short-circuit
+    /// without calling the producer.
+    #[tokio::test]
+    async fn streaming_send_matches_bulk_send_whole_file_no_baseline() {
+        let head = SumHead {
+            count: 0,
+            block_length: 0,
+            checksum_length: 0,
+            remainder_length: 0,
+        };
+        let source: Vec<u8> = (0..3000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        assert_send_parity(&source, head, Vec::new()).await;
+    }
 
+    /// Source smaller than block_size: producer emits a single literal
+    /// with the full source (no rolling window can be initialised).
     #[tokio::test]
-    async fn emit_summary_phase_byte_level_layout() {
-        // Byte-level pin: emitted payload is exactly
-        //   [0x00] ++ encode_summary_frame(SummaryFrame{...}, protocol)
-        // wrapped in a single MSG_DATA mux frame. This guards the
-        // sender-side finish semantics against accidental reordering or
-        // split framing in a future refactor.
+    async fn streaming_send_matches_bulk_send_smaller_than_block() {
         let head = SumHead {
             count: 1,
             block_length: 1024,
             checksum_length: 2,
             remainder_length: 0,
         };
-        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
-        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
-        let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00])); // trailing
-        let transport = mock_transport_with_raw_inbound(inbound);
-        let last_raw_outbound = transport.last_raw_outbound.clone();
-        let mut d = make_driver(transport);
-        let mut sink = CollectingSink::default();
-        drive_aerorsync_upload_to_stub(&mut d, &mut sink).await;
-        d.finish_session(&mut sink).await.unwrap();
+        let blocks = vec![make_sig_block(0xAAAAAAAA, 0x11, 2)];
+        let source: Vec<u8> = (0..500u32).map(|i| (i & 0xFF) as u8).collect();
+        assert_send_parity(&source, head, blocks).await;
+    }
 
-        let emitted = d
-            .received_summary()
-            .cloned()
-            .expect("summary cached on emit");
-        let expected_payload = {
-            let mut v = Vec::with_capacity(1 + 9 * 5);
-            v.push(0x00);
-            v.extend_from_slice(&encode_summary_frame(&emitted, 31));
-            v
+    /// Source larger than block_size with no signature matches: producer
+    /// streams a long literal interleaved with rolling-window walk; this
+    /// exercises the chunk-boundary drain logic against `compute_delta`.
+    #[tokio::test]
+    async fn streaming_send_matches_bulk_send_disjoint_source() {
+        let head = SumHead {
+            count: 1,
+            block_length: 1024,
+            checksum_length: 2,
+            remainder_length: 0,
         };
-        let expected_mux_frame = mux_frame(MuxTag::Data, &expected_payload);
-        let guard = last_raw_outbound.lock().unwrap();
-        let arc = guard.as_ref().unwrap();
-        let outbound = arc.lock().unwrap().clone();
-        assert!(
-            outbound
-                .windows(expected_mux_frame.len())
-                .any(|w| w == expected_mux_frame.as_slice()),
-            "outbound must contain the exact MSG_DATA frame for NDX_DONE + summary"
-        );
+        let blocks = vec![make_sig_block(0xAAAAAAAA, 0x11, 2)];
+        // 5 KB pseudo-random source: rolling sums won't hit 0xAAAAAAAA.
+        let source: Vec<u8> = (0..5000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        assert_send_parity(&source, head, blocks).await;
     }
 
-    // ---- S8j tests (NDX_DONE drain download direction) -------------------
-
+    /// Source whose first block matches the synthetic signature block:
+    /// producer emits a CopyBlock followed by a trailing literal. Pin
+    /// that the wire CopyRun token matches the bulk path bit-for-bit.
     #[tokio::test]
-    async fn download_drain_absorbs_three_leading_ndx_done_in_one_frame() {
-        // A single MSG_DATA carries `[0x00, 0x00, 0x00, summary_bytes…]`.
-        // The drain must strip exactly 3 leading zeros and leave the
-        // summary bytes as seed for `receive_summary_phase`.
-        let wire_ops = vec![DeltaOp::CopyRun {
-            start_token_index: 0,
-            run_length: 1,
-        }];
-        let delta_bytes = encode_delta_stream(&DeltaStreamReport {
-            ops: wire_ops,
-            file_checksum: vec![0u8; A2_3_FILE_CHECKSUM_LEN],
-        });
-        let opts = FileListDecodeOptions {
-            protocol: 31,
-            xfer_flags_as_varint: true,
-            // B.2: align with `build_flist_options()` so test-side
-            // pre-encoded payloads round-trip through the driver decoder.
-            always_checksum: true,
-            csum_len: 16,
-            preserve_uid: true,
-            preserve_gid: true,
-            previous_name: None,
+    async fn streaming_send_matches_bulk_send_with_one_copyblock() {
+        // Build a destination block whose rolling+strong signature is
+        // computable, then place that block at the start of the source
+        // followed by disjoint tail bytes. The signature phase advertises
+        // exactly that block to the sender.
+        use crate::delta_sync::compute_signatures;
+        const BLOCK_LEN: usize = 1024;
+        let block_bytes: Vec<u8> = (0..BLOCK_LEN as u32)
+            .map(|i| (i.wrapping_mul(0x9E37_79B1) >> 24) as u8)
+            .collect();
+
+        let dest_signatures = compute_signatures(&block_bytes, BLOCK_LEN);
+        assert_eq!(dest_signatures.signatures.len(), 1);
+        let sig0 = &dest_signatures.signatures[0];
+
+        // Build the wire SumBlock the server would have sent. The
+        // `checksum_length` is the s2length the receiver advertised;
+        // we use 16 here so the strong half match logic exercises the
+        // full xxh-style strong field rather than a trivial 2-byte
+        // truncation.
+        let head = SumHead {
+            count: 1,
+            block_length: BLOCK_LEN as i32,
+            checksum_length: 16,
+            remainder_length: 0,
         };
-        let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
-        let term_bytes = encode_file_list_terminator(&opts);
-        let summary_bytes = build_summary_frame_bytes(31);
+        let block = SumBlock {
+            rolling: sig0.rolling,
+            strong: sig0.strong[..16].to_vec(),
+        };
+        let blocks = vec![block];
 
-        // Combine 3 NDX_DONE + summary into a single MSG_DATA frame.
-        let mut combined = Vec::with_capacity(3 + summary_bytes.len());
-        combined.extend_from_slice(&[0x00, 0x00, 0x00]);
-        combined.extend_from_slice(&summary_bytes);
+        // Source = block_bytes (matches) + 700 bytes of disjoint tail.
+        let mut source = block_bytes.clone();
+        source.extend((0..700u32).map(|i| (i.wrapping_mul(0xDEADBEEF) >> 24) as u8));
 
-        let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &combined));
+        // Only the 16-byte prefix is on the wire: the block still matches.
+        let (_, ops) =
+            assert_send_parity_with(&source, head, blocks, TransferOptions::default()).await;
+        assert_eq!(copy_runs(&ops), vec![0]);
+    }
 
-        let transport = mock_transport_with_raw_inbound(inbound);
-        let adapter =
-            MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
-        let mut d = make_driver(transport);
-        let mut sink = CollectingSink::default();
-        let _ = d
-            .drive_download(
-                RemoteCommandSpec::download("/remote/x"),
-                b"BLK0",
+    fn copy_runs(ops: &[DeltaOp]) -> Vec<i32> {
+        ops.iter()
+            .filter_map(|op| match op {
+                DeltaOp::CopyRun {
+                    start_token_index, ..
+                } => Some(*start_token_index),
+                DeltaOp::Literal { .. } => None,
+            })
+            .collect()
+    }
+
+    fn literal_bytes(ops: &[DeltaOp]) -> Vec<u8> {
+        let payloads: Vec<&[u8]> = ops
+            .iter()
+            .filter_map(|op| match op {
+                DeltaOp::Literal { compressed_payload } => Some(compressed_payload.as_slice()),
+                DeltaOp::CopyRun { .. } => None,
+            })
+            .collect();
+        decompress_zstd_literal_stream(&payloads).unwrap()
+    }
+
+    /// `--inplace`: `B A` against basis `A B` may reuse block 1 at
+    /// offset 0, but block 0 is overwritten by then and must travel as
+    /// literal data. The streaming twin shares [`InplaceGuard`], see
+    /// `inplace_guard_turns_backward_matches_into_literals`.
+    #[tokio::test]
+    async fn inplace_upload_never_copies_from_already_written_blocks() {
+        let head = SumHead {
+            count: 2,
+            block_length: 4,
+            checksum_length: 2,
+            remainder_length: 0,
+        };
+        let blocks = vec![
+            make_sig_block(0x11111111, 0xAA, 2),
+            make_sig_block(0x22222222, 0xBB, 2),
+        ];
+        let plan = vec![EngineDeltaOp::CopyBlock(1), EngineDeltaOp::CopyBlock(0)];
+        for (inplace, expected_runs) in [(false, vec![1, 0]), (true, vec![1])] {
+            let inbound = build_streaming_parity_inbound(head, blocks.clone());
+            let mut d = make_driver(mock_transport_with_raw_inbound(inbound));
+            let adapter = MockSigAdapter::default().with_upload_plan(plan.clone());
+            let options = TransferOptions {
+                inplace,
+                ..TransferOptions::default()
+            };
+            d.drive_upload_through_delta(
+                RemoteCommandSpec::upload("/remote/target.bin").with_options(options),
+                sample_file_list_entry("target.bin"),
+                b"BBBBAAAA",
                 &adapter,
-                &mut sink,
+                &mut CollectingSink::default(),
             )
-            .await;
-        d.finish_session(&mut sink).await.unwrap();
-        assert_eq!(d.phase(), AerorsyncSessionPhase::Complete);
-        assert!(d.received_summary().is_some());
-        assert_eq!(d.session_role(), Some(SessionRole::Receiver));
+            .await
+            .expect("upload must complete");
+            assert_eq!(copy_runs(d.emitted_delta_ops()), expected_runs);
+            if inplace {
+                assert_eq!(literal_bytes(d.emitted_delta_ops()), b"AAAA");
+            }
+        }
     }
 
+    /// `--append` ships only what follows the remote length; the file
+    /// checksum covers the appended bytes for `--append` and the whole
+    /// file for `--append-verify`.
     #[tokio::test]
-    async fn download_drain_rejects_non_zero_in_marker_slot() {
-        // If the 3-byte window where rsync MUST emit NDX_DONEs carries
-        // anything other than zero, the drain surfaces InvalidFrame
-        // instead of silently accepting a drifted summary offset.
-        let wire_ops = vec![DeltaOp::CopyRun {
-            start_token_index: 0,
-            run_length: 1,
-        }];
-        let delta_bytes = encode_delta_stream(&DeltaStreamReport {
-            ops: wire_ops,
-            file_checksum: vec![0u8; A2_3_FILE_CHECKSUM_LEN],
-        });
-        let opts = FileListDecodeOptions {
-            protocol: 31,
-            xfer_flags_as_varint: true,
-            // B.2: align with `build_flist_options()` so test-side
-            // pre-encoded payloads round-trip through the driver decoder.
-            always_checksum: true,
-            csum_len: 16,
-            preserve_uid: true,
-            preserve_gid: true,
-            previous_name: None,
+    async fn append_send_ships_the_tail_and_checksums_per_mode() {
+        let head = SumHead {
+            count: 2,
+            block_length: 4,
+            checksum_length: 2,
+            remainder_length: 0,
+        };
+        let source = b"OLDBYTESnew tail";
+        for (mode, summed) in [
+            (AppendMode::Append, &source[8..]),
+            (AppendMode::AppendVerify, &source[..]),
+        ] {
+            let options = TransferOptions {
+                append: mode,
+                ..TransferOptions::default()
+            };
+            let (outbound, ops) = assert_send_parity_with(source, head, Vec::new(), options).await;
+            assert!(copy_runs(&ops).is_empty());
+            assert_eq!(literal_bytes(&ops), b"new tail");
+            let checksum = compute_xxh128_wire(summed);
+            assert!(
+                outbound.windows(checksum.len()).any(|w| w == checksum),
+                "{mode:?}: file checksum must cover the expected range"
+            );
+        }
+    }
+
+    /// Source long enough to span multiple `STREAMING_READ_CHUNK_BYTES`
+    /// reads so the chunk-boundary invariant is exercised on the
+    /// rolling window seam. Memory budget: `~5 MiB` worth of source on
+    /// the heap, fine for CI.
+    #[tokio::test]
+    async fn streaming_send_matches_bulk_send_multi_chunk() {
+        let head = SumHead {
+            count: 1,
+            block_length: 1024,
+            checksum_length: 2,
+            remainder_length: 0,
+        };
+        let blocks = vec![make_sig_block(0xAAAAAAAA, 0x11, 2)];
+        // 5 MiB pseudo-random source → 2 streaming reads of 4 MiB and
+        // 1 MiB respectively.
+        let len = 5 * 1024 * 1024;
+        let source: Vec<u8> = (0..len as u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        assert_send_parity(&source, head, blocks).await;
+    }
+
+    /// P3-T01 W1.3: `block_size == 0` chunked-literal pin.
+    ///
+    /// When the receiver advertises `block_length == 0` (no baseline)
+    /// **and** the source exceeds `STREAMING_READ_CHUNK_BYTES`, the
+    /// streaming path feeds the literal to the emitter in slabs instead
+    /// of accumulating a single `Vec<u8>` of `source_len` bytes (the
+    /// W1.2 shape, OOM-prone on multi-GiB no-baseline uploads).
+    ///
+    /// The slabs form one literal run, and zstd only flushes at the end
+    /// of a run, so the wire bytes are identical to the bulk path's one
+    /// big literal. Companion to
+    /// `streaming_send_matches_bulk_send_whole_file_no_baseline`, which
+    /// pins the same for sources `<= STREAMING_READ_CHUNK_BYTES`.
+    #[tokio::test]
+    async fn streaming_send_block_size_zero_chunks_large_source() {
+        use crate::aerorsync::engine_adapter::CurrentDeltaSyncBridge;
+
+        let head = SumHead {
+            count: 0,
+            block_length: 0,
+            checksum_length: 0,
+            remainder_length: 0,
         };
-        let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
-        let term_bytes = encode_file_list_terminator(&opts);
+        // 5 MiB pseudo-random source. With `STREAMING_READ_CHUNK_BYTES =
+        // 4 MiB`, the streaming path pushes 2 engine literals (4 MiB +
+        // 1 MiB) while the bulk path pushes 1 (5 MiB).
+        let len = 5 * 1024 * 1024usize;
+        let source: Vec<u8> = (0..len as u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
 
-        // First byte is NDX_DONE (drain enters the strict path), second
-        // byte is garbage: the drain must refuse.
-        let poisoned = vec![0x00, 0xAB, 0xCD, 0xEF, 0xFE];
-        let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &poisoned));
+        // Bulk path
+        let bulk_inbound = build_streaming_parity_inbound(head, Vec::new());
+        let bulk_transport = mock_transport_with_raw_inbound(bulk_inbound);
+        let bulk_last = bulk_transport.last_raw_outbound.clone();
+        let mut bulk_d = make_driver(bulk_transport);
+        let mut bulk_sink = CollectingSink::default();
+        let bulk_adapter = CurrentDeltaSyncBridge::new();
+        bulk_d
+            .drive_upload_through_delta(
+                RemoteCommandSpec::upload("/remote/target.bin"),
+                sample_file_list_entry("target.bin"),
+                &source,
+                &bulk_adapter,
+                &mut bulk_sink,
+            )
+            .await
+            .expect("bulk path must complete");
+        let bulk_wire_op_count = bulk_d.emitted_delta_ops.len();
+        let bulk_bytes = {
+            let g = bulk_last.lock().unwrap();
+            let arc = g.as_ref().expect("bulk: raw stream must have been opened");
+            let bytes = arc.lock().unwrap().clone();
+            bytes
+        };
 
-        let transport = mock_transport_with_raw_inbound(inbound);
-        let adapter =
-            MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
-        let mut d = make_driver(transport);
-        let mut sink = CollectingSink::default();
-        let _ = d
-            .drive_download(
-                RemoteCommandSpec::download("/remote/x"),
-                b"BLK0",
-                &adapter,
-                &mut sink,
+        // Streaming path
+        let stream_inbound = build_streaming_parity_inbound(head, Vec::new());
+        let stream_transport = mock_transport_with_raw_inbound(stream_inbound);
+        let stream_last = stream_transport.last_raw_outbound.clone();
+        let mut stream_d = make_driver(stream_transport);
+        let mut stream_sink = CollectingSink::default();
+        let stream_adapter = CurrentDeltaSyncBridge::new();
+        let cursor = std::io::Cursor::new(source.clone());
+        stream_d
+            .drive_upload_through_delta_streaming(
+                RemoteCommandSpec::upload("/remote/target.bin"),
+                sample_file_list_entry("target.bin"),
+                cursor,
+                source.len() as u64,
+                &stream_adapter,
+                &mut stream_sink,
             )
-            .await;
-        let err = d
-            .finish_session(&mut sink)
             .await
-            .expect_err("drain must reject non-zero in marker slot");
-        assert_eq!(err.kind, AerorsyncErrorKind::InvalidFrame);
-        assert!(err.detail.contains("NDX_DONE"), "detail: {}", err.detail);
-    }
+            .expect("streaming path must complete");
+        let stream_wire_op_count = stream_d.emitted_delta_ops.len();
+        let stream_bytes = {
+            let g = stream_last.lock().unwrap();
+            let arc = g
+                .as_ref()
+                .expect("streaming: raw stream must have been opened");
+            let bytes = arc.lock().unwrap().clone();
+            bytes
+        };
 
-    // ---- A4 tests (drive_*_through_delta entry points) -------------------
+        // Pin 1: both paths must complete and emit at least one wire
+        // literal each (proves we did not regress to "zero ops" on the
+        // whole-file branch).
+        assert!(
+            bulk_wire_op_count > 0,
+            "bulk path emitted zero wire ops on a 5 MiB source"
+        );
+        assert!(
+            stream_wire_op_count > 0,
+            "streaming path emitted zero wire ops on a 5 MiB source"
+        );
+
+        // Pin 2: the slab boundary is invisible on the wire.
+        assert_eq!(
+            bulk_bytes, stream_bytes,
+            "block_size==0 slabs must encode as the bulk path's single literal"
+        );
+
+        // Pin 3: the streaming path records at most the head of its ops.
+        let recorded: usize = stream_d
+            .emitted_delta_ops
+            .iter()
+            .map(|op| match op {
+                DeltaOp::Literal { compressed_payload } => {
+                    std::mem::size_of::<DeltaOp>() + compressed_payload.len()
+                }
+                DeltaOp::CopyRun { .. } => std::mem::size_of::<DeltaOp>(),
+            })
+            .sum();
+        assert!(recorded <= EMITTED_OPS_RECORD_BYTES);
+        assert_eq!(
+            stream_d.emitted_delta_ops[..],
+            bulk_d.emitted_delta_ops[..stream_wire_op_count]
+        );
+    }
 
+    /// Sanity: declared `source_len` mismatch aborts with InvalidFrame
+    /// rather than emitting half a delta phase on the wire. Guards
+    /// against silent corruption when the file changes during read.
     #[tokio::test]
-    async fn drive_upload_through_delta_returns_ok_on_happy_path() {
-        // A4 invariant: the new upload entry point elides the
-        // `UnsupportedVersion` stub sentinel that `drive_upload` emits on
-        // happy path. The inner drive loop reaches post-delta and the
-        // caller gets `Ok(())` so it can call `finish_session` explicitly.
-        //
-        // Inbound: server preamble + signature phase payload (sum_head +
-        // 1 sum_block): same shape as
-        // `driver_upload_writes_preamble_then_filelist_then_terminator`.
+    async fn streaming_send_rejects_source_len_mismatch() {
         let head = SumHead {
             count: 1,
             block_length: 1024,
@@ -4870,1004 +8586,1352 @@ mod tests {
             remainder_length: 0,
         };
         let blocks = vec![make_sig_block(0xAAAAAAAA, 0x11, 2)];
-        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
-        let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
+        let inbound = build_streaming_parity_inbound(head, blocks);
         let transport = mock_transport_with_raw_inbound(inbound);
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-        let res = d
-            .drive_upload_through_delta(
+        let source = vec![0u8; 100];
+        let cursor = std::io::Cursor::new(source.clone());
+        let err = d
+            .drive_upload_through_delta_streaming(
                 RemoteCommandSpec::upload("/remote/target.bin"),
                 sample_file_list_entry("target.bin"),
-                &[],
+                cursor,
+                // Lie about the length: declared 200, actual 100.
+                200u64,
                 &MockSigAdapter::default(),
                 &mut sink,
             )
-            .await;
-        assert!(res.is_ok(), "through_delta must return Ok, got {:?}", res);
-        // Phase must NOT be Stub (that's the legacy sentinel indicator).
-        assert_ne!(
-            d.phase(),
-            AerorsyncSessionPhase::Stub,
-            "through_delta must not set Stub phase"
-        );
-        // Delta phase crosses the PreCommit→PostCommit boundary.
-        assert!(d.committed());
+            .await
+            .expect_err("must abort on length mismatch");
+        assert_eq!(err.kind, AerorsyncErrorKind::InvalidFrame);
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Failed);
     }
 
     #[tokio::test]
-    async fn drive_download_through_delta_returns_ok_on_happy_path() {
-        let wire_ops = vec![DeltaOp::CopyRun {
-            start_token_index: 0,
-            run_length: 1,
-        }];
-        let delta_bytes = encode_delta_stream(&DeltaStreamReport {
-            ops: wire_ops,
-            file_checksum: vec![0u8; A2_3_FILE_CHECKSUM_LEN],
-        });
-        let opts = FileListDecodeOptions {
-            protocol: 31,
-            xfer_flags_as_varint: true,
-            // B.2: align with `build_flist_options()` so test-side
-            // pre-encoded payloads round-trip through the driver decoder.
-            always_checksum: true,
-            csum_len: 16,
-            preserve_uid: true,
-            preserve_gid: true,
-            previous_name: None,
+    async fn driver_upload_delta_literal_over_max_len_survives_via_s8j_chunking() {
+        // Historical pre-S8j behaviour: a raw literal whose compressed
+        // blob exceeded `MAX_DELTA_LITERAL_LEN` was rejected with
+        // `InvalidFrame` and the detail string "multi-chunk splitting
+        // deferred". S8j (2026-04-26) removed that bail: the driver
+        // now splits the oversized blob into successive DEFLATED_DATA
+        // tokens of ≤ 16 383 bytes each. Reaching the A2.3 stub
+        // frontier is proof that the delta phase did not abort on the
+        // size check; `driver_upload_delta_splits_large_compressed_literal_*`
+        // covers the chunking shape itself.
+        let mut big_raw = Vec::with_capacity(30_000);
+        let mut state: u32 = 0x12345678;
+        for _ in 0..30_000 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            big_raw.push(state as u8);
+        }
+        let head = SumHead {
+            count: 1,
+            block_length: 1024,
+            checksum_length: 2,
+            remainder_length: 0,
         };
-        let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
-        let term_bytes = encode_file_list_terminator(&opts);
+        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
+        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
         let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes));
-
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
         let transport = mock_transport_with_raw_inbound(inbound);
-        let adapter =
-            MockSigAdapter::with_fixed_signatures(4, vec![make_engine_sig(0, 0xA0, 0x01, 4)]);
+        let adapter = MockSigAdapter::default()
+            .with_upload_plan(vec![EngineDeltaOp::Literal(big_raw.clone())]);
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-        let res = d
-            .drive_download_through_delta(
-                RemoteCommandSpec::download("/remote/x"),
-                b"BLK0",
+        let err = d
+            .drive_upload(
+                RemoteCommandSpec::upload("/remote/x"),
+                sample_file_list_entry("target.bin"),
+                &big_raw,
                 &adapter,
                 &mut sink,
             )
-            .await;
-        assert!(
-            res.is_ok(),
-            "through_delta download must return Ok, got {:?}",
-            res
-        );
-        assert!(d.reconstructed().is_some());
-        // Download path leaves committed=false; A4 flips it at temp-file open.
-        assert!(!d.committed());
+            .await
+            .unwrap_err();
+        // Post-S8j: delta phase succeeds, the stub frontier fires
+        // UnsupportedVersion. Pre-S8j this was InvalidFrame from the
+        // blob-size guard.
+        assert_eq!(err.kind, AerorsyncErrorKind::UnsupportedVersion);
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Stub);
     }
 
     #[tokio::test]
-    async fn drive_upload_through_delta_propagates_real_error() {
-        // Pin that the new entry point does NOT mask genuine drive errors
-        // behind an `Ok(())`: when the remote closes mid-phase, the returned
-        // error must be the real `TransportFailure` (not the legacy stub
-        // sentinel, and not `Ok(())`), and `phase = Failed` must be set.
-        let inbound = canonical_server_preamble_bytes(); // no sig phase → EOF mid-flight
-        let transport = mock_transport_with_raw_inbound(inbound);
+    async fn driver_file_list_round_trip_matches_frozen_oracle_download() {
+        // A2.1 frozen-oracle driver pin. Feed the server -> client byte
+        // stream of the download capture to the driver and verify that:
+        //   (a) the driver decodes at least one `FileListEntry` from the
+        //       real rsync wire bytes (not from our own encoder);
+        //   (b) `committed()` stays false during the file list phase.
+        // Skip-graceful when the frozen oracle is not checked out.
+        //
+        // The download capture continues past the file list terminator
+        // with ndx / sum_head / delta frames that A2.1 does not handle -
+        // the driver will surface an `InvalidFrame` error from the
+        // decoder when it tries to read past the terminator as another
+        // file-list entry. We accept both terminations as long as at
+        // least one entry has already been absorbed.
+        let Some(frozen) = RealRsyncBaselineByteTranscript::try_load_frozen() else {
+            eprintln!("frozen oracle missing: A2.1 driver pin skipped");
+            return;
+        };
+        let transport = mock_transport_with_raw_inbound(frozen.download_server_to_client.clone());
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-        let err = d
-            .drive_upload_through_delta(
-                RemoteCommandSpec::upload("/remote/target.bin"),
-                sample_file_list_entry("target.bin"),
+        let outcome = d
+            .drive_download(
+                RemoteCommandSpec::download("/workspace/download/target.bin"),
                 &[],
                 &MockSigAdapter::default(),
                 &mut sink,
             )
-            .await
-            .expect_err("expected real TransportFailure, not Ok");
-        assert_eq!(err.kind, AerorsyncErrorKind::TransportFailure);
-        assert_eq!(d.phase(), AerorsyncSessionPhase::Failed);
+            .await;
+        // Whatever the outcome, the driver MUST have consumed the preamble
+        // and started the file list phase. Either the happy path reached
+        // the post-terminator stub frontier, or the decoder bailed on a
+        // downstream frame: both are acceptable for A2.1 so long as the
+        // file list decode landed at least one entry.
+        assert!(
+            !d.file_list().is_empty(),
+            "driver must decode at least one FileListEntry from the frozen download stream \
+             (got outcome: {outcome:?})"
+        );
+        assert!(!d.committed(), "file list phase must stay PreCommit");
+        // At minimum the preamble exchange must have populated the state.
+        assert!(
+            d.protocol_version() >= 30,
+            "negotiated protocol must be rsync 30+"
+        );
     }
 
-    // ---- P3-T01 W1.2 streaming-send parity pins -------------------------
+    // ========================================================================
+    // P3-T01 W2.4: drive_download_through_delta_streaming tests
     //
-    // These tests are the byte-identical wire pin for
-    // `send_delta_phase_streaming` against `send_delta_phase_single_file`.
-    // If they fail, the streaming path has diverged from the bulk path
-    // on the wire: root-cause is one of:
-    //   1. the producer (W1.1) emits different ops than `compute_delta`
-    //      (covered by `producer_streaming_matches_bulk_*` in
-    //      `engine_adapter.rs`),
-    //   2. the streaming xxh3 trailer no longer matches `xxh3_128`
-    //      bulk (covered by `streaming_xxh3_matches_bulk_xxh3` below),
-    //   3. the post-plan emission code (zstd compression, wire op
-    //      construction, NDX/iflags/sum_head echo, payload framing) was
-    //      changed in only one of the two functions: they MUST stay
-    //      bit-for-bit symmetric until W1.3 lifts the upload cap.
+    // The 3 existing mock download tests
+    // (`driver_download_delta_decodes_ops_and_reconstructs`,
+    // `driver_download_delta_coalesces_consecutive_literal_chunks_into_one_engine_literal`,
+    // `driver_download_delta_preserves_committed_false`) act as the
+    // non-regression pin for the bulk path. Their continued passing is the
+    // W2.4 acceptance gate "bulk path unchanged".
     //
-    // The tests avoid relying on `MockSigAdapter`: the bulk path now
-    // calls `compute_delta` against `RealEngineAdapter`'s real rolling
-    // checksum engine (see `_adapter` parameter in the streaming path,
-    // currently unused: both paths derive the plan from
-    // `received_signatures` + the source bytes via the producer / bulk
-    // computation, no mock substitution).
-
-    fn build_streaming_parity_inbound(head: SumHead, blocks: Vec<SumBlock>) -> Vec<u8> {
-        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
-        let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
-        inbound
-    }
-
-    /// xxh3 streaming digest must equal xxh3 bulk digest for any
-    /// chunking strategy. If this regresses, the streaming send's
-    /// `file_checksum` trailer will silently diverge and rsync will
-    /// reject the upload with "WHOLE FILE IS WRONG" (exit 22).
-    #[test]
-    fn streaming_xxh3_matches_bulk_xxh3() {
-        let payload: Vec<u8> = (0..50_000u32)
-            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
-            .collect();
-        let bulk = xxh3_128(&payload);
+    // The tests below exercise the new streaming entry point with the
+    // same wire fixture shape (preamble + file list + delta_stream
+    // mux frames), substituting `MemoryBaseline` for the destination
+    // slice's CopyBlock view and a collecting `MockAsyncWriter` for the
+    // reconstructed sink. The fixture is small enough that a careful
+    // reader can trace each assertion back to the wire bytes.
+    //
+    // P3-T01 W2.5: the streaming entry point now takes the writer by
+    // `&mut` instead of via a setter+field. The driver no longer owns the
+    // writer across the call, which lets the W2.5 caller `finalize` the
+    // `StreamingAtomicWriter` without an awkward downcast back through
+    // `Box<dyn AsyncWrite>`. The "guard against missing target" test
+    // from W2.4 is gone because there is no longer any state to
+    // misconfigure.
+    // ========================================================================
 
-        for chunk_size in [1usize, 7, 1024, 4096, 16384, 50_000] {
-            let mut hasher = Xxh3Default::new();
-            for chunk in payload.chunks(chunk_size) {
-                hasher.update(chunk);
-            }
-            assert_eq!(
-                hasher.digest128(),
-                bulk,
-                "xxh3 streaming digest must equal bulk digest for chunk_size={chunk_size}"
-            );
-        }
+    use std::sync::Mutex as StdMutex;
+    use std::task::Poll;
+    use tokio::io::AsyncWrite;
 
-        // Empty payload edge case.
-        let bulk_empty = xxh3_128(b"");
-        let mut hasher_empty = Xxh3Default::new();
-        hasher_empty.update(b"");
-        assert_eq!(hasher_empty.digest128(), bulk_empty);
+    /// Test sink that accumulates every `poll_write` payload into a
+    /// shared `Vec<u8>` so the test body can pin reconstructed bytes
+    /// without ownership games. `Arc<StdMutex<>>` rather than
+    /// `tokio::sync::Mutex` because `poll_write` is sync-context only
+    /// and the lock is held for the duration of one `extend_from_slice`.
+    struct MockAsyncWriter {
+        bytes: Arc<StdMutex<Vec<u8>>>,
     }
 
-    /// Wire-byte parity pin: bulk and streaming send paths must produce
-    /// the exact same outbound byte sequence on the raw transport. If
-    /// the assertion fails, prefix of the diff is the first byte where
-    /// the two paths diverged: start hunting there.
-    ///
-    /// Both paths use [`CurrentDeltaSyncBridge`] so the bulk plan and
-    /// the streaming plan come from the SAME algorithm
-    /// (`delta_sync::compute_delta` vs. `RollingDeltaPlanProducer`,
-    /// already cross-pinned bit-for-bit by `producer_streaming_matches_bulk_*`
-    /// in `engine_adapter.rs`).
-    async fn assert_send_parity(source: &[u8], head: SumHead, blocks: Vec<SumBlock>) {
-        use crate::aerorsync::engine_adapter::CurrentDeltaSyncBridge;
-
-        // Bulk path
-        let bulk_inbound = build_streaming_parity_inbound(head, blocks.clone());
-        let bulk_transport = mock_transport_with_raw_inbound(bulk_inbound);
-        let bulk_last = bulk_transport.last_raw_outbound.clone();
-        let mut bulk_d = make_driver(bulk_transport);
-        let mut bulk_sink = CollectingSink::default();
-        let bulk_adapter = CurrentDeltaSyncBridge::new();
-        bulk_d
-            .drive_upload_through_delta(
-                RemoteCommandSpec::upload("/remote/target.bin"),
-                sample_file_list_entry("target.bin"),
-                source,
-                &bulk_adapter,
-                &mut bulk_sink,
+    impl MockAsyncWriter {
+        fn new() -> (Self, Arc<StdMutex<Vec<u8>>>) {
+            let bytes = Arc::new(StdMutex::new(Vec::new()));
+            (
+                Self {
+                    bytes: bytes.clone(),
+                },
+                bytes,
             )
-            .await
-            .expect("bulk path must complete");
-        let bulk_bytes = {
-            let g = bulk_last.lock().unwrap();
-            let arc = g.as_ref().expect("bulk: raw stream must have been opened");
-            let bytes = arc.lock().unwrap().clone();
-            bytes
-        };
+        }
+    }
 
-        // Streaming path
-        let stream_inbound = build_streaming_parity_inbound(head, blocks);
-        let stream_transport = mock_transport_with_raw_inbound(stream_inbound);
-        let stream_last = stream_transport.last_raw_outbound.clone();
-        let mut stream_d = make_driver(stream_transport);
-        let mut stream_sink = CollectingSink::default();
-        let stream_adapter = CurrentDeltaSyncBridge::new();
-        let cursor = std::io::Cursor::new(source.to_vec());
-        stream_d
-            .drive_upload_through_delta_streaming(
-                RemoteCommandSpec::upload("/remote/target.bin"),
-                sample_file_list_entry("target.bin"),
-                cursor,
-                source.len() as u64,
-                &stream_adapter,
-                &mut stream_sink,
-            )
-            .await
-            .expect("streaming path must complete");
-        let stream_bytes = {
-            let g = stream_last.lock().unwrap();
-            let arc = g
-                .as_ref()
-                .expect("streaming: raw stream must have been opened");
-            let bytes = arc.lock().unwrap().clone();
-            bytes
-        };
+    impl AsyncWrite for MockAsyncWriter {
+        fn poll_write(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.bytes
+                .lock()
+                .expect("MockAsyncWriter lock")
+                .extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
 
-        assert_eq!(
-            bulk_bytes.len(),
-            stream_bytes.len(),
-            "outbound length mismatch: bulk={} streaming={}",
-            bulk_bytes.len(),
-            stream_bytes.len()
-        );
-        if bulk_bytes != stream_bytes {
-            let first_diff = bulk_bytes
-                .iter()
-                .zip(stream_bytes.iter())
-                .position(|(a, b)| a != b)
-                .unwrap_or(bulk_bytes.len());
-            panic!(
-                "outbound divergence at byte {first_diff}: bulk={:#04x} streaming={:#04x}",
-                bulk_bytes.get(first_diff).copied().unwrap_or(0),
-                stream_bytes.get(first_diff).copied().unwrap_or(0)
-            );
+    /// Sink that returns `BrokenPipe` on the first `poll_write`. Used to
+    /// pin error propagation through `apply_delta_streaming` and the
+    /// driver's `receive_delta_phase_streaming` boundary.
+    struct FailingMockWriter;
+    impl AsyncWrite for FailingMockWriter {
+        fn poll_write(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            _buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "mock writer always fails",
+            )))
+        }
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
         }
     }
 
-    /// Empty source against a `block_size == 0` server head: the
-    /// realistic shape: the receiver's local target is missing or
-    /// zero-byte, so its sum_head emits `block_length = 0`. Both bulk
-    /// and streaming paths then go through their respective
-    /// "whole-file no-baseline" short-circuits and produce zero
-    /// `EngineDeltaOp` (no literal token on the wire) plus the xxh3
-    /// trailer of an empty buffer.
-    #[tokio::test]
-    async fn streaming_send_matches_bulk_send_empty_source_block_size_zero() {
-        let head = SumHead {
-            count: 0,
-            block_length: 0,
-            checksum_length: 0,
-            remainder_length: 0,
-        };
-        assert_send_parity(&[], head, Vec::new()).await;
-    }
-
-    /// Whole-file path with non-empty source: the receiver advertises
-    /// `block_length = 0` (no baseline) so both paths emit the entire
-    /// source as a single literal. Pin that the streaming path drains
-    /// the reader correctly through the whole-file short-circuit
-    /// without calling the producer.
-    #[tokio::test]
-    async fn streaming_send_matches_bulk_send_whole_file_no_baseline() {
-        let head = SumHead {
-            count: 0,
-            block_length: 0,
-            checksum_length: 0,
-            remainder_length: 0,
+    /// Build the inbound MSG_DATA-framed stream for the W2.4 download
+    /// fixture: server preamble + file list entry + terminator + delta
+    /// stream (CopyRun(2) + Literal). Returns the raw bytes the mock
+    /// transport emits to the driver.
+    fn streaming_fixture_inbound(literal: &[u8]) -> Vec<u8> {
+        use crate::aerorsync::real_wire::{compress_zstd_literal_stream, encode_delta_stream};
+        let compressed =
+            compress_zstd_literal_stream(&[literal]).expect("zstd compress fixture literal");
+        assert_eq!(compressed.len(), 1);
+        let wire_ops = vec![
+            DeltaOp::CopyRun {
+                start_token_index: 0,
+                run_length: 2,
+            },
+            DeltaOp::Literal {
+                compressed_payload: compressed[0].clone(),
+            },
+        ];
+        let report = DeltaStreamReport {
+            ops: wire_ops,
+            file_checksum: vec![0xCC; A2_3_FILE_CHECKSUM_LEN],
         };
-        let source: Vec<u8> = (0..3000u32)
-            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
-            .collect();
-        assert_send_parity(&source, head, Vec::new()).await;
+        streaming_fixture_inbound_framed(&encode_delta_stream(&report), usize::MAX)
     }
 
-    /// Source smaller than block_size: producer emits a single literal
-    /// with the full source (no rolling window can be initialised).
-    #[tokio::test]
-    async fn streaming_send_matches_bulk_send_smaller_than_block() {
-        let head = SumHead {
-            count: 1,
-            block_length: 1024,
-            checksum_length: 2,
-            remainder_length: 0,
+    /// [`streaming_fixture_inbound`] around any delta stream, cut into
+    /// MSG_DATA frames of at most `frame_len` bytes.
+    fn streaming_fixture_inbound_framed(delta_bytes: &[u8], frame_len: usize) -> Vec<u8> {
+        let opts = FileListDecodeOptions {
+            protocol: 31,
+            xfer_flags_as_varint: true,
+            always_checksum: true,
+            csum_len: 16,
+            preserve_uid: true,
+            preserve_gid: true,
+            previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
-        let blocks = vec![make_sig_block(0xAAAAAAAA, 0x11, 2)];
-        let source: Vec<u8> = (0..500u32).map(|i| (i & 0xFF) as u8).collect();
-        assert_send_parity(&source, head, blocks).await;
-    }
+        let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
+        let term_bytes = encode_file_list_terminator(&opts);
 
-    /// Source larger than block_size with no signature matches: producer
-    /// streams a long literal interleaved with rolling-window walk; this
-    /// exercises the chunk-boundary drain logic against `compute_delta`.
-    #[tokio::test]
-    async fn streaming_send_matches_bulk_send_disjoint_source() {
-        let head = SumHead {
-            count: 1,
-            block_length: 1024,
-            checksum_length: 2,
-            remainder_length: 0,
-        };
-        let blocks = vec![make_sig_block(0xAAAAAAAA, 0x11, 2)];
-        // 5 KB pseudo-random source: rolling sums won't hit 0xAAAAAAAA.
-        let source: Vec<u8> = (0..5000u32)
-            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
-            .collect();
-        assert_send_parity(&source, head, blocks).await;
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
+        for frame in delta_bytes.chunks(frame_len) {
+            inbound.extend_from_slice(&mux_frame(MuxTag::Data, frame));
+        }
+        inbound
     }
 
-    /// Source whose first block matches the synthetic signature block:
-    /// producer emits a CopyBlock followed by a trailing literal. Pin
-    /// that the wire CopyRun token matches the bulk path bit-for-bit.
+    /// W2.4 test 1: happy path: streaming download decodes the wire
+    /// ops and writes the reconstructed bytes (CopyBlock(0) +
+    /// CopyBlock(1) + Literal) into the configured `Streaming(writer)`
+    /// sink. The shape mirrors
+    /// `driver_download_delta_decodes_ops_and_reconstructs` (the
+    /// non-regression pin for the bulk path) so any divergence between
+    /// streaming and bulk is immediately visible.
     #[tokio::test]
-    async fn streaming_send_matches_bulk_send_with_one_copyblock() {
-        // Build a destination block whose rolling+strong signature is
-        // computable, then place that block at the start of the source
-        // followed by disjoint tail bytes. The signature phase advertises
-        // exactly that block to the sender.
-        use crate::delta_sync::compute_signatures;
-        const BLOCK_LEN: usize = 1024;
-        let block_bytes: Vec<u8> = (0..BLOCK_LEN as u32)
-            .map(|i| (i.wrapping_mul(0x9E37_79B1) >> 24) as u8)
-            .collect();
+    async fn driver_download_streaming_through_delta_writes_to_writer() {
+        use crate::aerorsync::engine_adapter::MemoryBaseline;
 
-        let dest_signatures = compute_signatures(&block_bytes, BLOCK_LEN);
-        assert_eq!(dest_signatures.signatures.len(), 1);
-        let sig0 = &dest_signatures.signatures[0];
+        let raw_literal = b"LITERAL_PAYLOAD_ABC";
+        let inbound = streaming_fixture_inbound(raw_literal);
 
-        // Build the wire SumBlock the server would have sent. The
-        // `checksum_length` is the s2length the receiver advertised;
-        // we use 16 here so the strong half match logic exercises the
-        // full xxh-style strong field rather than a trivial 2-byte
-        // truncation.
-        let head = SumHead {
-            count: 1,
-            block_length: BLOCK_LEN as i32,
-            checksum_length: 16,
-            remainder_length: 0,
-        };
-        let block = SumBlock {
-            rolling: sig0.rolling,
-            strong: sig0.strong[..16].to_vec(),
-        };
-        let blocks = vec![block];
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let adapter = MockSigAdapter::with_fixed_signatures(
+            4,
+            vec![
+                make_engine_sig(0, 0xA0, 0x01, 4),
+                make_engine_sig(1, 0xA1, 0x02, 4),
+            ],
+        );
+        let destination_data: Vec<u8> = b"BLK1BLK2".to_vec();
+        let mut baseline = MemoryBaseline::new(destination_data.clone());
 
-        // Source = block_bytes (matches) + 700 bytes of disjoint tail.
-        let mut source = block_bytes.clone();
-        source.extend((0..700u32).map(|i| (i.wrapping_mul(0xDEADBEEF) >> 24) as u8));
+        let (mut writer, captured) = MockAsyncWriter::new();
+        let mut d = make_driver(transport);
 
-        assert_send_parity(&source, head, blocks).await;
-    }
+        let mut sink = CollectingSink::default();
+        d.drive_download_through_delta_streaming(
+            RemoteCommandSpec::download("/remote/target.bin"),
+            &mut baseline,
+            &mut writer,
+            &adapter,
+            &mut sink,
+        )
+        .await
+        .expect("streaming download succeeds");
 
-    /// Source long enough to span multiple `STREAMING_READ_CHUNK_BYTES`
-    /// reads so the chunk-boundary invariant is exercised on the
-    /// rolling window seam. Memory budget: `~5 MiB` worth of source on
-    /// the heap, fine for CI.
-    #[tokio::test]
-    async fn streaming_send_matches_bulk_send_multi_chunk() {
-        let head = SumHead {
-            count: 1,
-            block_length: 1024,
-            checksum_length: 2,
-            remainder_length: 0,
-        };
-        let blocks = vec![make_sig_block(0xAAAAAAAA, 0x11, 2)];
-        // 5 MiB pseudo-random source → 2 streaming reads of 4 MiB and
-        // 1 MiB respectively.
-        let len = 5 * 1024 * 1024;
-        let source: Vec<u8> = (0..len as u32)
-            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
-            .collect();
-        assert_send_parity(&source, head, blocks).await;
+        // Reconstructed = baseline[0..4] + baseline[4..8] + literal.
+        let on_writer = captured.lock().expect("captured lock").clone();
+        assert_eq!(&on_writer[0..4], b"BLK1");
+        assert_eq!(&on_writer[4..8], b"BLK2");
+        assert_eq!(&on_writer[8..], raw_literal.as_slice());
+        // `committed` stays false on the driver: matches the bulk-path
+        // pin in `driver_download_delta_preserves_committed_false`.
+        assert!(
+            !d.committed(),
+            "streaming download must keep committed=false (W2.5 caller flips its own flag)"
+        );
+        // File checksum trailer still surfaces, identical to bulk.
+        assert_eq!(
+            d.received_file_checksum(),
+            Some(vec![0xCC; A2_3_FILE_CHECKSUM_LEN].as_slice())
+        );
     }
 
-    /// P3-T01 W1.3: `block_size == 0` chunked-literal pin.
-    ///
-    /// When the receiver advertises `block_length == 0` (no baseline)
-    /// **and** the source exceeds `STREAMING_READ_CHUNK_BYTES`, the
-    /// streaming path must emit multiple engine literals through the
-    /// session-wide zstd `CCtx` instead of accumulating a single
-    /// `Vec<u8>` of `source_len` bytes (the W1.2 shape, OOM-prone on
-    /// multi-GiB no-baseline uploads).
-    ///
-    /// The observable: the wire bytes diverge from the bulk path's
-    /// because the bulk path emits one big literal (one zstd frame),
-    /// while the streaming path emits N literals (N zstd frames). The
-    /// receiver's session-wide `ZSTD_DCtx` concatenates both shapes to
-    /// the same plaintext per stock rsync's `send_zstd_token`
-    /// semantics, so the divergence is *protocol-equivalent*.
-    ///
-    /// Companion to `streaming_send_matches_bulk_send_whole_file_no_baseline`
-    /// (which pins identity for sources `<= STREAMING_READ_CHUNK_BYTES`).
-    /// Together these two tests pin the split-point at
-    /// `STREAMING_READ_CHUNK_BYTES`.
+    /// W2.4 test 2: pin that the streaming download reads the local copy
+    /// from the **caller-supplied baseline** only: the signatures it
+    /// sends are the ones the bulk path computes over the same bytes
+    /// (outbound byte-identical), and `CopyBlock(idx)` dispatches
+    /// against the baseline.
     #[tokio::test]
-    async fn streaming_send_block_size_zero_chunks_large_source() {
-        use crate::aerorsync::engine_adapter::CurrentDeltaSyncBridge;
-
-        let head = SumHead {
-            count: 0,
-            block_length: 0,
-            checksum_length: 0,
-            remainder_length: 0,
-        };
-        // 5 MiB pseudo-random source. With `STREAMING_READ_CHUNK_BYTES =
-        // 4 MiB`, the streaming path emits 2 engine literals (4 MiB +
-        // 1 MiB) while the bulk path emits 1 (5 MiB).
-        let len = 5 * 1024 * 1024usize;
-        let source: Vec<u8> = (0..len as u32)
-            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
-            .collect();
-
-        // Bulk path
-        let bulk_inbound = build_streaming_parity_inbound(head, Vec::new());
-        let bulk_transport = mock_transport_with_raw_inbound(bulk_inbound);
-        let bulk_last = bulk_transport.last_raw_outbound.clone();
-        let mut bulk_d = make_driver(bulk_transport);
-        let mut bulk_sink = CollectingSink::default();
-        let bulk_adapter = CurrentDeltaSyncBridge::new();
-        bulk_d
-            .drive_upload_through_delta(
-                RemoteCommandSpec::upload("/remote/target.bin"),
-                sample_file_list_entry("target.bin"),
-                &source,
-                &bulk_adapter,
-                &mut bulk_sink,
-            )
-            .await
-            .expect("bulk path must complete");
-        let bulk_wire_op_count = bulk_d.emitted_delta_ops.len();
-        let bulk_bytes = {
-            let g = bulk_last.lock().unwrap();
-            let arc = g.as_ref().expect("bulk: raw stream must have been opened");
-            let bytes = arc.lock().unwrap().clone();
-            bytes
-        };
+    async fn driver_download_streaming_through_delta_consults_baseline_source() {
+        use crate::aerorsync::engine_adapter::{CurrentDeltaSyncBridge, MemoryBaseline};
 
-        // Streaming path
-        let stream_inbound = build_streaming_parity_inbound(head, Vec::new());
-        let stream_transport = mock_transport_with_raw_inbound(stream_inbound);
-        let stream_last = stream_transport.last_raw_outbound.clone();
-        let mut stream_d = make_driver(stream_transport);
-        let mut stream_sink = CollectingSink::default();
-        let stream_adapter = CurrentDeltaSyncBridge::new();
-        let cursor = std::io::Cursor::new(source.clone());
-        stream_d
-            .drive_upload_through_delta_streaming(
-                RemoteCommandSpec::upload("/remote/target.bin"),
-                sample_file_list_entry("target.bin"),
-                cursor,
-                source.len() as u64,
-                &stream_adapter,
-                &mut stream_sink,
+        let raw_literal = b"LITERAL";
+        // Two 512-byte blocks at the engine's minimum block size, with
+        // distinct patterns so the writer shows which block went where.
+        let mut baseline_bytes = vec![b'X'; 512];
+        baseline_bytes.extend_from_slice(&[b'Y'; 512]);
+        let adapter = CurrentDeltaSyncBridge::new();
+
+        let bulk_transport =
+            mock_transport_with_raw_inbound(streaming_fixture_inbound(raw_literal));
+        let bulk_last = bulk_transport.last_raw_outbound.clone();
+        let mut bulk_d = make_driver(bulk_transport);
+        bulk_d
+            .drive_download_through_delta(
+                RemoteCommandSpec::download("/remote/target.bin"),
+                &baseline_bytes,
+                &adapter,
+                &mut CollectingSink::default(),
             )
             .await
-            .expect("streaming path must complete");
-        let stream_wire_op_count = stream_d.emitted_delta_ops.len();
-        let stream_bytes = {
-            let g = stream_last.lock().unwrap();
-            let arc = g
+            .expect("bulk download succeeds");
+
+        let transport = mock_transport_with_raw_inbound(streaming_fixture_inbound(raw_literal));
+        let stream_last = transport.last_raw_outbound.clone();
+        let mut baseline = MemoryBaseline::new(baseline_bytes.clone());
+        let (mut writer, captured) = MockAsyncWriter::new();
+        let mut d = make_driver(transport);
+        d.drive_download_through_delta_streaming(
+            RemoteCommandSpec::download("/remote/target.bin"),
+            &mut baseline,
+            &mut writer,
+            &adapter,
+            &mut CollectingSink::default(),
+        )
+        .await
+        .expect("streaming download succeeds");
+
+        let outbound = |last: &Arc<StdMutex<_>>| {
+            let guard = last.lock().unwrap();
+            let stream: &Option<Arc<StdMutex<Vec<u8>>>> = &guard;
+            let bytes = stream
                 .as_ref()
-                .expect("streaming: raw stream must have been opened");
-            let bytes = arc.lock().unwrap().clone();
+                .expect("raw stream opened")
+                .lock()
+                .unwrap()
+                .clone();
             bytes
         };
-
-        // Pin 1: both paths must complete and emit at least one wire
-        // literal each (proves we did not regress to "zero ops" on the
-        // whole-file branch).
-        assert!(
-            bulk_wire_op_count > 0,
-            "bulk path emitted zero wire ops on a 5 MiB source"
+        assert_eq!(
+            outbound(&stream_last),
+            outbound(&bulk_last),
+            "streaming signatures must match the bulk ones over the same bytes"
         );
+        assert_eq!(d.sent_sum_head(), bulk_d.sent_sum_head());
         assert!(
-            stream_wire_op_count > 0,
-            "streaming path emitted zero wire ops on a 5 MiB source"
+            d.sent_signatures().is_empty(),
+            "the streaming signature phase does not keep its blocks"
         );
 
-        // Pin 2: wire bytes MUST differ. Bulk emits one zstd frame for
-        // the full literal; streaming emits two zstd frames (one per
-        // 4 MiB slab). The session-wide `CCtx` flush boundary between
-        // them is byte-observable in the compressed output.
-        assert_ne!(
-            bulk_bytes, stream_bytes,
-            "block_size==0 with source > STREAMING_READ_CHUNK_BYTES MUST chunk the literal: bulk and streaming wire bytes must differ"
+        let on_writer = captured.lock().expect("captured lock").clone();
+        assert_eq!(
+            &on_writer[..1024],
+            baseline_bytes.as_slice(),
+            "CopyBlock dispatch must consult BaselineSource"
         );
+        assert_eq!(&on_writer[1024..], raw_literal.as_slice());
+    }
 
-        // Pin 3: byte-count delta is small (at most a few KiB of zstd
-        // frame overhead per extra slab). 10% headroom is generous; if
-        // the divergence ever blows past this, something is wrong with
-        // either the chunk size or the zstd CCtx reuse.
-        let len_diff =
-            (bulk_bytes.len() as i64 - stream_bytes.len() as i64).unsigned_abs() as usize;
-        assert!(
-            len_diff < bulk_bytes.len() / 10,
-            "zstd-frame overhead ballooned: bulk={} streaming={} diff={} (>10% of bulk)",
-            bulk_bytes.len(),
-            stream_bytes.len(),
-            len_diff
+    /// The streaming receiver decodes ops as their frames arrive: a delta
+    /// cut into 3-byte frames, with a literal spanning several records,
+    /// reconstructs the same file.
+    #[tokio::test]
+    async fn driver_download_streaming_decodes_delta_split_across_frames() {
+        use crate::aerorsync::engine_adapter::MemoryBaseline;
+
+        let literal: Vec<u8> = (0..200_000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        let mut encoder = LiteralEncoder::new(CompressionAlgo::Zstd, None).unwrap();
+        let mut wire_ops = vec![DeltaOp::CopyRun {
+            start_token_index: 1,
+            run_length: 1,
+        }];
+        wire_ops.extend(
+            encoder
+                .encode_literal(&literal, true)
+                .unwrap()
+                .into_iter()
+                .map(|compressed_payload| DeltaOp::Literal { compressed_payload }),
+        );
+        wire_ops.push(DeltaOp::CopyRun {
+            start_token_index: 0,
+            run_length: 1,
+        });
+        assert!(wire_ops.len() > 3, "the literal must span several records");
+        let report = DeltaStreamReport {
+            ops: wire_ops,
+            file_checksum: vec![0xCC; A2_3_FILE_CHECKSUM_LEN],
+        };
+        let inbound = streaming_fixture_inbound_framed(&encode_delta_stream(&report), 3);
+
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let adapter = MockSigAdapter::with_fixed_signatures(4, Vec::new());
+        let mut baseline = MemoryBaseline::new(b"BLK1BLK2".to_vec());
+        let (mut writer, captured) = MockAsyncWriter::new();
+        let mut d = make_driver(transport);
+        d.drive_download_through_delta_streaming(
+            RemoteCommandSpec::download("/remote/target.bin"),
+            &mut baseline,
+            &mut writer,
+            &adapter,
+            &mut CollectingSink::default(),
+        )
+        .await
+        .expect("streaming download succeeds");
+
+        let mut expected = b"BLK2".to_vec();
+        expected.extend_from_slice(&literal);
+        expected.extend_from_slice(b"BLK1");
+        assert_eq!(*captured.lock().unwrap(), expected);
+        assert_eq!(
+            d.received_file_checksum(),
+            Some(vec![0xCC; A2_3_FILE_CHECKSUM_LEN].as_slice())
         );
     }
 
-    /// Sanity: declared `source_len` mismatch aborts with InvalidFrame
-    /// rather than emitting half a delta phase on the wire. Guards
-    /// against silent corruption when the file changes during read.
+    /// W2.4 test 3: writer that returns `BrokenPipe` on the first
+    /// `poll_write` aborts the download with `InvalidFrame` (the
+    /// `apply_delta_streaming: <io error>` envelope). No panic, no
+    /// silent success.
     #[tokio::test]
-    async fn streaming_send_rejects_source_len_mismatch() {
-        let head = SumHead {
-            count: 1,
-            block_length: 1024,
-            checksum_length: 2,
-            remainder_length: 0,
-        };
-        let blocks = vec![make_sig_block(0xAAAAAAAA, 0x11, 2)];
-        let inbound = build_streaming_parity_inbound(head, blocks);
+    async fn driver_download_streaming_through_delta_writer_failure_aborts() {
+        use crate::aerorsync::engine_adapter::MemoryBaseline;
+
+        let raw_literal = b"WHATEVER";
+        let inbound = streaming_fixture_inbound(raw_literal);
+
         let transport = mock_transport_with_raw_inbound(inbound);
+        let adapter = MockSigAdapter::with_fixed_signatures(
+            4,
+            vec![
+                make_engine_sig(0, 0xA0, 0x01, 4),
+                make_engine_sig(1, 0xA1, 0x02, 4),
+            ],
+        );
+        let destination_data: Vec<u8> = b"BLK1BLK2".to_vec();
+        let mut baseline = MemoryBaseline::new(destination_data.clone());
+
         let mut d = make_driver(transport);
+        let mut writer = FailingMockWriter;
+
         let mut sink = CollectingSink::default();
-        let source = vec![0u8; 100];
-        let cursor = std::io::Cursor::new(source.clone());
         let err = d
-            .drive_upload_through_delta_streaming(
-                RemoteCommandSpec::upload("/remote/target.bin"),
-                sample_file_list_entry("target.bin"),
-                cursor,
-                // Lie about the length: declared 200, actual 100.
-                200u64,
-                &MockSigAdapter::default(),
+            .drive_download_through_delta_streaming(
+                RemoteCommandSpec::download("/remote/target.bin"),
+                &mut baseline,
+                &mut writer,
+                &adapter,
                 &mut sink,
             )
             .await
-            .expect_err("must abort on length mismatch");
-        assert_eq!(err.kind, AerorsyncErrorKind::InvalidFrame);
-        assert_eq!(d.phase(), AerorsyncSessionPhase::Failed);
+            .expect_err("failing writer must propagate an error");
+        assert_eq!(
+            err.kind,
+            AerorsyncErrorKind::InvalidFrame,
+            "writer failure surfaces as InvalidFrame"
+        );
+        assert!(
+            err.detail.contains("apply_delta_streaming"),
+            "error message must reference apply_delta_streaming, got: {}",
+            err.detail
+        );
+        assert_eq!(
+            d.phase(),
+            AerorsyncSessionPhase::Failed,
+            "phase must transition to Failed on writer error"
+        );
+    }
+
+    /// W2.4 test 4: after a successful streaming download,
+    /// `driver.reconstructed()` returns `None`. The bytes flowed
+    /// through the writer; reading them back from RAM would defeat
+    /// the streaming purpose.
+    #[tokio::test]
+    async fn driver_download_streaming_through_delta_keeps_reconstructed_none() {
+        use crate::aerorsync::engine_adapter::MemoryBaseline;
+
+        let raw_literal = b"X";
+        let inbound = streaming_fixture_inbound(raw_literal);
+
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let adapter = MockSigAdapter::with_fixed_signatures(
+            4,
+            vec![
+                make_engine_sig(0, 0xA0, 0x01, 4),
+                make_engine_sig(1, 0xA1, 0x02, 4),
+            ],
+        );
+        let destination_data: Vec<u8> = b"BLK1BLK2".to_vec();
+        let mut baseline = MemoryBaseline::new(destination_data.clone());
+
+        let (mut writer, _captured) = MockAsyncWriter::new();
+        let mut d = make_driver(transport);
+
+        let mut sink = CollectingSink::default();
+        d.drive_download_through_delta_streaming(
+            RemoteCommandSpec::download("/remote/target.bin"),
+            &mut baseline,
+            &mut writer,
+            &adapter,
+            &mut sink,
+        )
+        .await
+        .expect("streaming download succeeds");
+
+        assert!(
+            d.reconstructed().is_none(),
+            "streaming path must NOT populate self.reconstructed"
+        );
+    }
+
+    // ---- recursive tree sessions -----------------------------------------
+
+    fn tree_entry(path: &str, mode: u32, data: &[u8]) -> FileListEntry {
+        let is_file = is_regular_mode(mode);
+        FileListEntry {
+            flags: 0,
+            path: path.to_string(),
+            size: if is_file { data.len() as i64 } else { 0 },
+            mtime: 1_700_000_000,
+            mtime_nsec: Some(0),
+            mode,
+            uid: Some(1000),
+            uid_name: Some("aero".to_string()),
+            gid: Some(1000),
+            gid_name: Some("aero".to_string()),
+            checksum: if is_file {
+                compute_xxh128_wire(data)
+            } else {
+                Vec::new()
+            },
+            link_target: None,
+            hardlink: None,
+            acl: None,
+            xattrs: None,
+        }
+    }
+
+    /// `.`, `a.txt`, `sub/`, `sub/b.txt`: two lists, ndx 1..=3 and 5.
+    fn sample_tree() -> TreeFileList {
+        TreeFileList::from_local_entries(vec![
+            tree_entry(".", 0o040755, b""),
+            tree_entry("a.txt", 0o100644, b"alpha"),
+            tree_entry("sub", 0o040755, b""),
+            tree_entry("sub/b.txt", 0o100644, b"bravo bravo"),
+        ])
+        .unwrap()
+    }
+
+    fn tree_flist_options() -> FileListDecodeOptions<'static> {
+        FileListDecodeOptions {
+            protocol: 31,
+            xfer_flags_as_varint: true,
+            always_checksum: true,
+            csum_len: 16,
+            preserve_uid: true,
+            preserve_gid: true,
+            previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
+        }
+    }
+
+    fn whole_file_head() -> SumHead {
+        SumHead {
+            count: 0,
+            block_length: 0,
+            checksum_length: 0,
+            remainder_length: 0,
+        }
+    }
+
+    #[derive(Default)]
+    struct MemTreeSource {
+        files: HashMap<String, Vec<u8>>,
+        reads: Vec<String>,
+    }
+
+    #[async_trait::async_trait]
+    impl TreeSource for MemTreeSource {
+        async fn read_file(&mut self, path: &str) -> Result<Vec<u8>, AerorsyncError> {
+            self.reads.push(path.to_string());
+            self.files
+                .get(path)
+                .cloned()
+                .ok_or_else(|| AerorsyncError::invalid_frame(format!("no {path}")))
+        }
+    }
+
+    #[derive(Default)]
+    struct MemTreeSink {
+        dirs: Vec<String>,
+        files: HashMap<String, Vec<u8>>,
+        deleted: Vec<String>,
+        symlinks: HashMap<String, String>,
+        links: Vec<(String, String)>,
+        attrs: HashMap<String, FileListEntry>,
+    }
+
+    #[async_trait::async_trait]
+    impl TreeSink for MemTreeSink {
+        async fn create_dir(
+            &mut self,
+            path: &str,
+            _entry: &FileListEntry,
+        ) -> Result<(), AerorsyncError> {
+            self.dirs.push(path.to_string());
+            Ok(())
+        }
+        async fn read_baseline(&mut self, path: &str) -> Result<Option<Vec<u8>>, AerorsyncError> {
+            Ok(self.files.get(path).cloned())
+        }
+        async fn commit_file(
+            &mut self,
+            path: &str,
+            entry: &FileListEntry,
+            data: Vec<u8>,
+        ) -> Result<(), AerorsyncError> {
+            self.files.insert(path.to_string(), data);
+            self.attrs.insert(path.to_string(), entry.clone());
+            Ok(())
+        }
+        async fn create_symlink(
+            &mut self,
+            path: &str,
+            entry: &FileListEntry,
+        ) -> Result<(), AerorsyncError> {
+            let target = entry.link_target.clone().unwrap_or_default();
+            self.symlinks.insert(path.to_string(), target);
+            Ok(())
+        }
+        async fn hard_link(&mut self, leader: &str, path: &str) -> Result<(), AerorsyncError> {
+            self.links.push((leader.to_string(), path.to_string()));
+            Ok(())
+        }
+        async fn apply_attrs(
+            &mut self,
+            path: &str,
+            entry: &FileListEntry,
+        ) -> Result<(), AerorsyncError> {
+            self.attrs.insert(path.to_string(), entry.clone());
+            Ok(())
+        }
+        async fn delete_extraneous(
+            &mut self,
+            dir: &str,
+            keep: &BTreeSet<String>,
+        ) -> Result<u64, AerorsyncError> {
+            let mut doomed: Vec<String> = self
+                .files
+                .keys()
+                .filter(|path| {
+                    let (parent, name) = path.rsplit_once('/').unwrap_or((".", path.as_str()));
+                    parent == dir && !keep.contains(name)
+                })
+                .cloned()
+                .collect();
+            doomed.sort();
+            for path in &doomed {
+                self.files.remove(path);
+            }
+            self.deleted.extend(doomed.iter().cloned());
+            Ok(doomed.len() as u64)
+        }
     }
 
     #[tokio::test]
-    async fn driver_upload_delta_literal_over_max_len_survives_via_s8j_chunking() {
-        // Historical pre-S8j behaviour: a raw literal whose compressed
-        // blob exceeded `MAX_DELTA_LITERAL_LEN` was rejected with
-        // `InvalidFrame` and the detail string "multi-chunk splitting
-        // deferred". S8j (2026-04-26) removed that bail: the driver
-        // now splits the oversized blob into successive DEFLATED_DATA
-        // tokens of ≤ 16 383 bytes each. Reaching the A2.3 stub
-        // frontier is proof that the delta phase did not abort on the
-        // size check; `driver_upload_delta_splits_large_compressed_literal_*`
-        // covers the chunking shape itself.
-        let mut big_raw = Vec::with_capacity(30_000);
-        let mut state: u32 = 0x12345678;
-        for _ in 0..30_000 {
-            state ^= state << 13;
-            state ^= state >> 17;
-            state ^= state << 5;
-            big_raw.push(state as u8);
-        }
-        let head = SumHead {
-            count: 1,
-            block_length: 1024,
-            checksum_length: 2,
-            remainder_length: 0,
-        };
-        let blocks = vec![make_sig_block(0x11, 0x22, 2)];
-        let sig_payload = build_sig_phase_payload(1, 0x8002, &head, &blocks);
+    async fn drive_upload_tree_answers_requests_and_frees_lists() {
+        let tree = sample_tree();
+        let mut st = NdxState::new();
         let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &sig_payload));
+        // The generator wants both files whole (no baseline remotely).
+        for ndx in [2, 5] {
+            let mut payload = ItemHeader {
+                ndx,
+                iflags: ITEM_TRANSFER,
+                basis_type: None,
+                xname: None,
+                xattrs: Vec::new(),
+            }
+            .encode(&mut st);
+            payload.extend_from_slice(&encode_sum_head(&whole_file_head()));
+            inbound.extend_from_slice(&mux_frame(MuxTag::Data, &payload));
+        }
+        // Two list frees + two phase advances, then read_final_goodbye.
+        for _ in 0..4 {
+            inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        }
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+
         let transport = mock_transport_with_raw_inbound(inbound);
-        let adapter = MockSigAdapter::default()
-            .with_upload_plan(vec![EngineDeltaOp::Literal(big_raw.clone())]);
+        let last_raw_outbound = transport.last_raw_outbound.clone();
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-        let err = d
-            .drive_upload(
-                RemoteCommandSpec::upload("/remote/x"),
-                sample_file_list_entry("target.bin"),
-                &big_raw,
-                &adapter,
+        let mut source = MemTreeSource::default();
+        source.files.insert("a.txt".into(), b"alpha".to_vec());
+        source
+            .files
+            .insert("sub/b.txt".into(), b"bravo bravo".to_vec());
+
+        let report = d
+            .drive_upload_tree(
+                RemoteCommandSpec::upload("/remote/dir/"),
+                &tree,
+                &mut source,
+                &MockSigAdapter::default(),
                 &mut sink,
             )
             .await
-            .unwrap_err();
-        // Post-S8j: delta phase succeeds, the stub frontier fires
-        // UnsupportedVersion. Pre-S8j this was InvalidFrame from the
-        // blob-size guard.
-        assert_eq!(err.kind, AerorsyncErrorKind::UnsupportedVersion);
-        assert_eq!(d.phase(), AerorsyncSessionPhase::Stub);
+            .expect("tree upload");
+
+        assert_eq!(report.files_total, 2);
+        assert_eq!(report.files_transferred, 2);
+        assert_eq!(report.dirs_total, 2);
+        assert_eq!(report.bytes_transferred, 16);
+        assert_eq!(source.reads, vec!["a.txt", "sub/b.txt"]);
+        assert!(d.committed());
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Complete);
+
+        // The extra list for `sub` (dir_ndx 1) went out, followed by
+        // NDX_FLIST_EOF.
+        let guard = last_raw_outbound.lock().unwrap();
+        let outbound = guard.as_ref().unwrap().lock().unwrap().clone();
+        let marker = encode_ndx(NDX_FLIST_OFFSET - 1, &mut NdxState::new());
+        assert!(
+            outbound
+                .windows(marker.len())
+                .any(|w| w == marker.as_slice()),
+            "extra list header for dir_ndx 1 must be on the wire"
+        );
     }
 
     #[tokio::test]
-    async fn driver_file_list_round_trip_matches_frozen_oracle_download() {
-        // A2.1 frozen-oracle driver pin. Feed the server -> client byte
-        // stream of the download capture to the driver and verify that:
-        //   (a) the driver decodes at least one `FileListEntry` from the
-        //       real rsync wire bytes (not from our own encoder);
-        //   (b) `committed()` stays false during the file list phase.
-        // Skip-graceful when the frozen oracle is not checked out.
-        //
-        // The download capture continues past the file list terminator
-        // with ndx / sum_head / delta frames that A2.1 does not handle -
-        // the driver will surface an `InvalidFrame` error from the
-        // decoder when it tries to read past the terminator as another
-        // file-list entry. We accept both terminations as long as at
-        // least one entry has already been absorbed.
-        let Some(frozen) = RealRsyncBaselineByteTranscript::try_load_frozen() else {
-            eprintln!("frozen oracle missing: A2.1 driver pin skipped");
-            return;
+    async fn drive_upload_tree_echoes_attribute_only_items() {
+        let tree = sample_tree();
+        let mut st = NdxState::new();
+        let mut inbound = canonical_server_preamble_bytes();
+        let header = ItemHeader {
+            ndx: 2,
+            iflags: ITEM_REPORT_CHANGE,
+            basis_type: None,
+            xname: None,
+            xattrs: Vec::new(),
         };
-        let transport = mock_transport_with_raw_inbound(frozen.download_server_to_client.clone());
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &header.encode(&mut st)));
+        for _ in 0..6 {
+            inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        }
+
+        let transport = mock_transport_with_raw_inbound(inbound);
         let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-        let outcome = d
-            .drive_download(
-                RemoteCommandSpec::download("/workspace/download/target.bin"),
-                &[],
+        let mut source = MemTreeSource::default();
+        let report = d
+            .drive_upload_tree(
+                RemoteCommandSpec::upload("/remote/dir/"),
+                &tree,
+                &mut source,
                 &MockSigAdapter::default(),
                 &mut sink,
             )
-            .await;
-        // Whatever the outcome, the driver MUST have consumed the preamble
-        // and started the file list phase. Either the happy path reached
-        // the post-terminator stub frontier, or the decoder bailed on a
-        // downstream frame: both are acceptable for A2.1 so long as the
-        // file list decode landed at least one entry.
-        assert!(
-            !d.file_list().is_empty(),
-            "driver must decode at least one FileListEntry from the frozen download stream \
-             (got outcome: {outcome:?})"
-        );
-        assert!(!d.committed(), "file list phase must stay PreCommit");
-        // At minimum the preamble exchange must have populated the state.
-        assert!(
-            d.protocol_version() >= 30,
-            "negotiated protocol must be rsync 30+"
-        );
+            .await
+            .expect("tree upload");
+        assert_eq!(report.files_transferred, 0);
+        assert!(source.reads.is_empty(), "unchanged files are never read");
+        assert!(!d.committed());
     }
 
-    // ========================================================================
-    // P3-T01 W2.4: drive_download_through_delta_streaming tests
-    //
-    // The 3 existing mock download tests
-    // (`driver_download_delta_decodes_ops_and_reconstructs`,
-    // `driver_download_delta_coalesces_consecutive_literal_chunks_into_one_engine_literal`,
-    // `driver_download_delta_preserves_committed_false`) act as the
-    // non-regression pin for the bulk path. Their continued passing is the
-    // W2.4 acceptance gate "bulk path unchanged".
-    //
-    // The tests below exercise the new streaming entry point with the
-    // same wire fixture shape (preamble + file list + delta_stream
-    // mux frames), substituting `MemoryBaseline` for the destination
-    // slice's CopyBlock view and a collecting `MockAsyncWriter` for the
-    // reconstructed sink. The fixture is small enough that a careful
-    // reader can trace each assertion back to the wire bytes.
-    //
-    // P3-T01 W2.5: the streaming entry point now takes the writer by
-    // `&mut` instead of via a setter+field. The driver no longer owns the
-    // writer across the call, which lets the W2.5 caller `finalize` the
-    // `StreamingAtomicWriter` without an awkward downcast back through
-    // `Box<dyn AsyncWrite>`. The "guard against missing target" test
-    // from W2.4 is gone because there is no longer any state to
-    // misconfigure.
-    // ========================================================================
-
-    use std::sync::Mutex as StdMutex;
-    use std::task::Poll;
-    use tokio::io::AsyncWrite;
+    /// Inbound for a download of `sample_tree()` where only `sub/b.txt`
+    /// (ndx 5) travels as a whole-file literal. `top_io_error` is the
+    /// io_error count the sender appends to the top-level list.
+    fn sample_tree_download_inbound(top_io_error: u8) -> Vec<u8> {
+        let tree = sample_tree();
+        let opts = tree_flist_options();
+        let mut st = NdxState::new();
+        let mut flist = Vec::new();
+        for entry in &tree.segments()[0].entries {
+            flist.extend_from_slice(&encode_file_list_entry(entry, &opts));
+        }
+        flist.extend_from_slice(&encode_file_list_terminator(&opts));
+        // Varint mode: the terminator's last byte is the io_error count.
+        *flist.last_mut().unwrap() = top_io_error;
+        let mut extra = encode_ndx(NDX_FLIST_OFFSET - 1, &mut st);
+        for entry in &tree.segments()[1].entries {
+            extra.extend_from_slice(&encode_file_list_entry(entry, &opts));
+        }
+        extra.extend_from_slice(&encode_file_list_terminator(&opts));
+        extra.extend_from_slice(&encode_ndx(NDX_FLIST_EOF, &mut st));
+
+        // Only `sub/b.txt` (ndx 5) travels: `a.txt` already matches.
+        let mut encoder = LiteralEncoder::new(CompressionAlgo::Zstd, None).unwrap();
+        let wire_ops = engine_ops_to_wire_ops(
+            &[EngineDeltaOp::Literal(b"bravo bravo".to_vec())],
+            &mut encoder,
+        )
+        .unwrap();
+        let mut item = ItemHeader {
+            ndx: 5,
+            iflags: ITEM_TRANSFER,
+            basis_type: None,
+            xname: None,
+            xattrs: Vec::new(),
+        }
+        .encode(&mut st);
+        item.extend_from_slice(&encode_sum_head(&whole_file_head()));
+        item.extend_from_slice(&encode_delta_stream(&DeltaStreamReport {
+            ops: wire_ops,
+            file_checksum: compute_xxh128_wire(b"bravo bravo"),
+        }));
 
-    /// Test sink that accumulates every `poll_write` payload into a
-    /// shared `Vec<u8>` so the test body can pin reconstructed bytes
-    /// without ownership games. `Arc<StdMutex<>>` rather than
-    /// `tokio::sync::Mutex` because `poll_write` is sync-context only
-    /// and the lock is held for the duration of one `extend_from_slice`.
-    struct MockAsyncWriter {
-        bytes: Arc<StdMutex<Vec<u8>>>,
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &flist));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &extra));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &item));
+        // Two list frees + two phase advances.
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00, 0x00, 0x00, 0x00]));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &build_summary_frame_bytes(31)));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        inbound
     }
 
-    impl MockAsyncWriter {
-        fn new() -> (Self, Arc<StdMutex<Vec<u8>>>) {
-            let bytes = Arc::new(StdMutex::new(Vec::new()));
-            (
-                Self {
-                    bytes: bytes.clone(),
-                },
-                bytes,
+    #[tokio::test]
+    async fn drive_download_tree_skips_matching_files_and_rebuilds_the_rest() {
+        let inbound = sample_tree_download_inbound(0);
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let mut d = make_driver(transport);
+        let mut events = CollectingSink::default();
+        let mut sink = MemTreeSink::default();
+        sink.files.insert("a.txt".into(), b"alpha".to_vec());
+
+        let report = d
+            .drive_download_tree(
+                RemoteCommandSpec::download("/remote/dir/"),
+                &mut sink,
+                &MockSigAdapter::default(),
+                &mut events,
             )
-        }
-    }
+            .await
+            .expect("tree download");
 
-    impl AsyncWrite for MockAsyncWriter {
-        fn poll_write(
-            self: std::pin::Pin<&mut Self>,
-            _cx: &mut std::task::Context<'_>,
-            buf: &[u8],
-        ) -> Poll<std::io::Result<usize>> {
-            self.bytes
-                .lock()
-                .expect("MockAsyncWriter lock")
-                .extend_from_slice(buf);
-            Poll::Ready(Ok(buf.len()))
-        }
-        fn poll_flush(
-            self: std::pin::Pin<&mut Self>,
-            _cx: &mut std::task::Context<'_>,
-        ) -> Poll<std::io::Result<()>> {
-            Poll::Ready(Ok(()))
-        }
-        fn poll_shutdown(
-            self: std::pin::Pin<&mut Self>,
-            _cx: &mut std::task::Context<'_>,
-        ) -> Poll<std::io::Result<()>> {
-            Poll::Ready(Ok(()))
-        }
+        assert_eq!(report.files_total, 2);
+        assert_eq!(report.files_transferred, 1);
+        assert_eq!(report.dirs_total, 2);
+        assert_eq!(sink.dirs, vec![".", "sub"]);
+        assert_eq!(sink.files["sub/b.txt"], b"bravo bravo");
+        assert!(d.received_summary().is_some());
+        assert!(!d.committed());
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Complete);
     }
 
-    /// Sink that returns `BrokenPipe` on the first `poll_write`. Used to
-    /// pin error propagation through `apply_delta_streaming` and the
-    /// driver's `install_reconstructed_from_wire_streaming` boundary.
-    struct FailingMockWriter;
-    impl AsyncWrite for FailingMockWriter {
-        fn poll_write(
-            self: std::pin::Pin<&mut Self>,
-            _cx: &mut std::task::Context<'_>,
-            _buf: &[u8],
-        ) -> Poll<std::io::Result<usize>> {
-            Poll::Ready(Err(std::io::Error::new(
-                std::io::ErrorKind::BrokenPipe,
-                "mock writer always fails",
-            )))
-        }
-        fn poll_flush(
-            self: std::pin::Pin<&mut Self>,
-            _cx: &mut std::task::Context<'_>,
-        ) -> Poll<std::io::Result<()>> {
-            Poll::Ready(Ok(()))
-        }
-        fn poll_shutdown(
-            self: std::pin::Pin<&mut Self>,
-            _cx: &mut std::task::Context<'_>,
-        ) -> Poll<std::io::Result<()>> {
-            Poll::Ready(Ok(()))
+    fn link_options() -> TransferOptions {
+        TransferOptions {
+            hard_links: true,
+            xattrs: true,
+            ..TransferOptions::default()
         }
     }
 
-    /// Build the inbound MSG_DATA-framed stream for the W2.4 download
-    /// fixture: server preamble + file list entry + terminator + delta
-    /// stream (CopyRun(2) + Literal). Returns the raw bytes the mock
-    /// transport emits to the driver.
-    fn streaming_fixture_inbound(literal: &[u8]) -> Vec<u8> {
-        use crate::aerorsync::real_wire::{compress_zstd_literal_stream, encode_delta_stream};
-        let compressed =
-            compress_zstd_literal_stream(&[literal]).expect("zstd compress fixture literal");
-        assert_eq!(compressed.len(), 1);
-        let wire_ops = vec![
-            DeltaOp::CopyRun {
-                start_token_index: 0,
-                run_length: 2,
+    /// `.`, `a.txt` (with a long and a short xattr), `b.txt` (hard link
+    /// to `a.txt`) and `link` (symlink to `a.txt`): one list, ndx 1..=4.
+    fn linked_tree() -> TreeFileList {
+        let xattrs = vec![
+            XattrItem {
+                name: "user.big".to_string(),
+                value: XattrValue::Full(vec![0x42; 40]),
             },
-            DeltaOp::Literal {
-                compressed_payload: compressed[0].clone(),
+            XattrItem {
+                name: "user.small".to_string(),
+                value: XattrValue::Full(b"v".to_vec()),
             },
         ];
-        let report = DeltaStreamReport {
-            ops: wire_ops,
-            file_checksum: vec![0xCC; A2_3_FILE_CHECKSUM_LEN],
-        };
-        let delta_bytes = encode_delta_stream(&report);
-
-        let opts = FileListDecodeOptions {
-            protocol: 31,
-            xfer_flags_as_varint: true,
-            always_checksum: true,
-            csum_len: 16,
-            preserve_uid: true,
-            preserve_gid: true,
-            previous_name: None,
-        };
-        let entry_bytes = encode_file_list_entry(&sample_file_list_entry("target.bin"), &opts);
-        let term_bytes = encode_file_list_terminator(&opts);
-
-        let mut inbound = canonical_server_preamble_bytes();
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &entry_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &term_bytes));
-        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &delta_bytes));
-        inbound
+        let mut a = tree_entry("a.txt", 0o100644, b"alpha");
+        a.xattrs = Some(xattrs.clone());
+        let mut b = tree_entry("b.txt", 0o100644, b"alpha");
+        b.xattrs = Some(xattrs);
+        let mut link = tree_entry("link", 0o120777, b"");
+        link.link_target = Some("a.txt".to_string());
+        let inodes = HashMap::from([("a.txt".to_string(), (1, 7)), ("b.txt".to_string(), (1, 7))]);
+        TreeFileList::from_local_entries_linked(
+            vec![tree_entry(".", 0o040755, b""), a, b, link],
+            &inodes,
+        )
+        .unwrap()
+    }
+
+    fn linked_flist_options(scope: Option<FlistScope<'_>>) -> FileListDecodeOptions<'_> {
+        FileListDecodeOptions {
+            preserve_hard_links: true,
+            preserve_xattrs: true,
+            scope,
+            ..tree_flist_options()
+        }
     }
 
-    /// W2.4 test 1: happy path: streaming download decodes the wire
-    /// ops and writes the reconstructed bytes (CopyBlock(0) +
-    /// CopyBlock(1) + Literal) into the configured `Streaming(writer)`
-    /// sink. The shape mirrors
-    /// `driver_download_delta_decodes_ops_and_reconstructs` (the
-    /// non-regression pin for the bulk path) so any divergence between
-    /// streaming and bulk is immediately visible.
     #[tokio::test]
-    async fn driver_download_streaming_through_delta_writes_to_writer() {
-        use crate::aerorsync::engine_adapter::MemoryBaseline;
+    async fn drive_download_tree_links_entries_and_fetches_long_xattrs() {
+        let tree = linked_tree();
+        let entries = &tree.segments()[0].entries;
+        let mut tables = FlistMetaTables::new();
+        let mut flist = Vec::new();
+        for (i, entry) in entries.iter().enumerate() {
+            let opts = linked_flist_options(Some(FlistScope {
+                ndx_start: 1,
+                entries: &entries[..i],
+                tables: &tables,
+            }));
+            flist.extend_from_slice(&encode_file_list_entry(entry, &opts));
+            tables.absorb(entry);
+        }
+        let mut st = NdxState::new();
+        flist.extend_from_slice(&encode_file_list_terminator(&tree_flist_options()));
+        flist.extend_from_slice(&encode_ndx(NDX_FLIST_EOF, &mut st));
+
+        // `a.txt` travels whole, with the value of `user.big` the
+        // generator asked for.
+        let mut encoder = LiteralEncoder::new(CompressionAlgo::Zstd, None).unwrap();
+        let wire_ops =
+            engine_ops_to_wire_ops(&[EngineDeltaOp::Literal(b"alpha".to_vec())], &mut encoder)
+                .unwrap();
+        let mut item = ItemHeader {
+            ndx: 2,
+            iflags: ITEM_TRANSFER | ITEM_REPORT_XATTR,
+            basis_type: None,
+            xname: None,
+            xattrs: vec![XattrSlot {
+                num: 1,
+                value: Some(vec![0x42; 40]),
+            }],
+        }
+        .encode(&mut st);
+        item.extend_from_slice(&encode_sum_head(&whole_file_head()));
+        item.extend_from_slice(&encode_delta_stream(&DeltaStreamReport {
+            ops: wire_ops,
+            file_checksum: compute_xxh128_wire(b"alpha"),
+        }));
 
-        let raw_literal = b"LITERAL_PAYLOAD_ABC";
-        let inbound = streaming_fixture_inbound(raw_literal);
+        let mut inbound = canonical_server_preamble_bytes();
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &flist));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &item));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00, 0x00, 0x00]));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &build_summary_frame_bytes(31)));
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
 
         let transport = mock_transport_with_raw_inbound(inbound);
-        let adapter = MockSigAdapter::with_fixed_signatures(
-            4,
-            vec![
-                make_engine_sig(0, 0xA0, 0x01, 4),
-                make_engine_sig(1, 0xA1, 0x02, 4),
-            ],
+        let last_raw_outbound = transport.last_raw_outbound.clone();
+        let mut d = make_driver(transport);
+        let mut events = CollectingSink::default();
+        let mut sink = MemTreeSink::default();
+        let report = d
+            .drive_download_tree(
+                RemoteCommandSpec::download("/remote/dir/").with_options(link_options()),
+                &mut sink,
+                &MockSigAdapter::default(),
+                &mut events,
+            )
+            .await
+            .expect("tree download");
+
+        assert_eq!(report.files_transferred, 1);
+        assert_eq!(sink.files["a.txt"], b"alpha");
+        assert!(!sink.files.contains_key("b.txt"), "followers carry no data");
+        assert_eq!(sink.links, vec![("a.txt".to_string(), "b.txt".to_string())]);
+        assert_eq!(sink.symlinks["link"], "a.txt");
+        let xattrs = sink.attrs["a.txt"].xattrs.clone().unwrap();
+        assert_eq!(xattrs[0].value, XattrValue::Full(vec![0x42; 40]));
+        assert_eq!(xattrs[1].value, XattrValue::Full(b"v".to_vec()));
+
+        // The request named xattr #1 of ndx 2: `varint(1)` then `0`.
+        let guard = last_raw_outbound.lock().unwrap();
+        let outbound = guard.as_ref().unwrap().lock().unwrap().clone();
+        let request = ItemHeader {
+            ndx: 2,
+            iflags: ITEM_TRANSFER | ITEM_REPORT_CHANGE | ITEM_REPORT_XATTR,
+            basis_type: None,
+            xname: None,
+            xattrs: vec![XattrSlot {
+                num: 1,
+                value: None,
+            }],
+        }
+        .encode(&mut NdxState::new());
+        assert!(
+            outbound
+                .windows(request.len())
+                .any(|w| w == request.as_slice()),
+            "xattr request must be on the wire"
         );
-        let destination_data: Vec<u8> = b"BLK1BLK2".to_vec();
-        let mut baseline = MemoryBaseline::new(destination_data.clone());
+    }
 
-        let (mut writer, captured) = MockAsyncWriter::new();
-        let mut d = make_driver(transport);
+    #[tokio::test]
+    async fn drive_upload_tree_answers_xattr_requests_for_unchanged_files() {
+        let tree = linked_tree();
+        let mut st = NdxState::new();
+        let mut inbound = canonical_server_preamble_bytes();
+        let header = ItemHeader {
+            ndx: 2,
+            iflags: ITEM_REPORT_XATTR,
+            basis_type: None,
+            xname: None,
+            xattrs: vec![XattrSlot {
+                num: 1,
+                value: None,
+            }],
+        };
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &header.encode(&mut st)));
+        for _ in 0..5 {
+            inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
+        }
 
+        let transport = mock_transport_with_raw_inbound(inbound);
+        let last_raw_outbound = transport.last_raw_outbound.clone();
+        let mut d = make_driver(transport);
         let mut sink = CollectingSink::default();
-        d.drive_download_through_delta_streaming(
-            RemoteCommandSpec::download("/remote/target.bin"),
-            &destination_data,
-            &mut baseline,
-            &mut writer,
-            &adapter,
-            &mut sink,
-        )
-        .await
-        .expect("streaming download succeeds");
+        let mut source = MemTreeSource::default();
+        let report = d
+            .drive_upload_tree(
+                RemoteCommandSpec::upload("/remote/dir/").with_options(link_options()),
+                &tree,
+                &mut source,
+                &MockSigAdapter::default(),
+                &mut sink,
+            )
+            .await
+            .expect("tree upload");
+        assert_eq!(report.files_transferred, 0);
+        assert!(source.reads.is_empty());
 
-        // Reconstructed = baseline[0..4] + baseline[4..8] + literal.
-        let on_writer = captured.lock().expect("captured lock").clone();
-        assert_eq!(&on_writer[0..4], b"BLK1");
-        assert_eq!(&on_writer[4..8], b"BLK2");
-        assert_eq!(&on_writer[8..], raw_literal.as_slice());
-        // `committed` stays false on the driver: matches the bulk-path
-        // pin in `driver_download_delta_preserves_committed_false`.
+        let guard = last_raw_outbound.lock().unwrap();
+        let outbound = guard.as_ref().unwrap().lock().unwrap().clone();
+        let answer = [&[0x01, 40][..], &[0x42; 40][..], &[0x00][..]].concat();
         assert!(
-            !d.committed(),
-            "streaming download must keep committed=false (W2.5 caller flips its own flag)"
-        );
-        // File checksum trailer still surfaces, identical to bulk.
-        assert_eq!(
-            d.received_file_checksum(),
-            Some(vec![0xCC; A2_3_FILE_CHECKSUM_LEN].as_slice())
+            outbound
+                .windows(answer.len())
+                .any(|w| w == answer.as_slice()),
+            "the value of user.big must be echoed"
         );
     }
 
-    /// W2.4 test 2: pin that the driver dispatches `CopyBlock(idx)`
-    /// against the **caller-supplied baseline**, not the
-    /// `destination_data` slice that the signature phase consumed. Uses
-    /// distinct byte patterns for the two so a divergence is loud:
-    /// the writer receives the baseline pattern, never the destination
-    /// pattern.
-    #[tokio::test]
-    async fn driver_download_streaming_through_delta_consults_baseline_source() {
-        use crate::aerorsync::engine_adapter::MemoryBaseline;
+    fn delete_options() -> TransferOptions {
+        TransferOptions {
+            delete: true,
+            ..TransferOptions::default()
+        }
+    }
 
-        let raw_literal = b"LITERAL";
-        let inbound = streaming_fixture_inbound(raw_literal);
+    #[tokio::test]
+    async fn drive_download_tree_deletes_extraneous_entries_per_list() {
+        let transport = mock_transport_with_raw_inbound(sample_tree_download_inbound(0));
+        let mut d = make_driver(transport);
+        let mut events = CollectingSink::default();
+        let mut sink = MemTreeSink::default();
+        sink.files.insert("a.txt".into(), b"alpha".to_vec());
+        sink.files.insert("stale.txt".into(), b"gone".to_vec());
+        sink.files
+            .insert("sub/old.bin".into(), b"gone too".to_vec());
+
+        let report = d
+            .drive_download_tree(
+                RemoteCommandSpec::download("/remote/dir/").with_options(delete_options()),
+                &mut sink,
+                &MockSigAdapter::default(),
+                &mut events,
+            )
+            .await
+            .expect("tree download");
 
-        let transport = mock_transport_with_raw_inbound(inbound);
-        let adapter = MockSigAdapter::with_fixed_signatures(
-            4,
-            vec![
-                make_engine_sig(0, 0xA0, 0x01, 4),
-                make_engine_sig(1, 0xA1, 0x02, 4),
-            ],
-        );
-        // Distinct patterns so the assertion can tell them apart.
-        let destination_data: Vec<u8> = b"AAAABBBB".to_vec();
-        let baseline_bytes: Vec<u8> = b"XXXXYYYY".to_vec();
-        let mut baseline = MemoryBaseline::new(baseline_bytes);
+        assert_eq!(report.entries_deleted, 2);
+        assert_eq!(sink.deleted, vec!["stale.txt", "sub/old.bin"]);
+        assert_eq!(sink.files["a.txt"], b"alpha");
+        assert_eq!(sink.files["sub/b.txt"], b"bravo bravo");
+    }
 
-        let (mut writer, captured) = MockAsyncWriter::new();
+    #[tokio::test]
+    async fn drive_download_tree_skips_deletion_after_a_sender_io_error() {
+        let transport = mock_transport_with_raw_inbound(sample_tree_download_inbound(1));
         let mut d = make_driver(transport);
+        let mut events = CollectingSink::default();
+        let mut sink = MemTreeSink::default();
+        sink.files.insert("stale.txt".into(), b"kept".to_vec());
 
-        let mut sink = CollectingSink::default();
-        d.drive_download_through_delta_streaming(
-            RemoteCommandSpec::download("/remote/target.bin"),
-            &destination_data,
-            &mut baseline,
-            &mut writer,
-            &adapter,
-            &mut sink,
-        )
-        .await
-        .expect("streaming download succeeds");
+        let report = d
+            .drive_download_tree(
+                RemoteCommandSpec::download("/remote/dir/").with_options(delete_options()),
+                &mut sink,
+                &MockSigAdapter::default(),
+                &mut events,
+            )
+            .await
+            .expect("tree download");
 
-        let on_writer = captured.lock().expect("captured lock").clone();
-        // CopyBlock(0)+CopyBlock(1) must read from the BASELINE, not
-        // the destination. If we see "AAAABBBB" the dispatcher is
-        // reading from the wrong source.
-        assert_eq!(
-            &on_writer[0..8],
-            b"XXXXYYYY",
-            "CopyBlock dispatch must consult BaselineSource, not destination_data"
-        );
-        assert_eq!(&on_writer[8..], raw_literal.as_slice());
+        assert_eq!(report.entries_deleted, 0);
+        assert!(sink.deleted.is_empty());
+        assert_eq!(sink.files["stale.txt"], b"kept");
     }
 
-    /// W2.4 test 3: writer that returns `BrokenPipe` on the first
-    /// `poll_write` aborts the download with `InvalidFrame` (the
-    /// `apply_delta_streaming: <io error>` envelope). No panic, no
-    /// silent success.
     #[tokio::test]
-    async fn driver_download_streaming_through_delta_writer_failure_aborts() {
-        use crate::aerorsync::engine_adapter::MemoryBaseline;
-
-        let raw_literal = b"WHATEVER";
-        let inbound = streaming_fixture_inbound(raw_literal);
-
+    async fn drive_tree_requires_incremental_recursion() {
+        let mut inbound = encode_server_preamble(&ServerPreamble {
+            protocol_version: 31,
+            compat_flags: 0x06,
+            checksum_algos: "md5 xxh64".to_string(),
+            compression_algos: "none zstd".to_string(),
+            checksum_seed: 0xDEAD_BEEF,
+            consumed: 0,
+        });
+        inbound.extend_from_slice(&mux_frame(MuxTag::Data, &[0x00]));
         let transport = mock_transport_with_raw_inbound(inbound);
-        let adapter = MockSigAdapter::with_fixed_signatures(
-            4,
-            vec![
-                make_engine_sig(0, 0xA0, 0x01, 4),
-                make_engine_sig(1, 0xA1, 0x02, 4),
-            ],
-        );
-        let destination_data: Vec<u8> = b"BLK1BLK2".to_vec();
-        let mut baseline = MemoryBaseline::new(destination_data.clone());
-
         let mut d = make_driver(transport);
-        let mut writer = FailingMockWriter;
-
-        let mut sink = CollectingSink::default();
+        let mut events = CollectingSink::default();
+        let mut sink = MemTreeSink::default();
         let err = d
-            .drive_download_through_delta_streaming(
-                RemoteCommandSpec::download("/remote/target.bin"),
-                &destination_data,
-                &mut baseline,
-                &mut writer,
-                &adapter,
+            .drive_download_tree(
+                RemoteCommandSpec::download("/remote/dir/"),
                 &mut sink,
+                &MockSigAdapter::default(),
+                &mut events,
             )
             .await
-            .expect_err("failing writer must propagate an error");
-        assert_eq!(
-            err.kind,
-            AerorsyncErrorKind::InvalidFrame,
-            "writer failure surfaces as InvalidFrame"
-        );
-        assert!(
-            err.detail.contains("apply_delta_streaming"),
-            "error message must reference apply_delta_streaming, got: {}",
-            err.detail
-        );
-        assert_eq!(
-            d.phase(),
-            AerorsyncSessionPhase::Failed,
-            "phase must transition to Failed on writer error"
-        );
+            .unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::UnsupportedVersion);
+        assert_eq!(d.phase(), AerorsyncSessionPhase::Failed);
     }
 
-    /// W2.4 test 4: after a successful streaming download,
-    /// `driver.reconstructed()` returns `None`. The bytes flowed
-    /// through the writer; reading them back from RAM would defeat
-    /// the streaming purpose.
-    #[tokio::test]
-    async fn driver_download_streaming_through_delta_keeps_reconstructed_none() {
-        use crate::aerorsync::engine_adapter::MemoryBaseline;
+    #[test]
+    fn generator_message_waits_for_all_signature_blocks() {
+        let mut st = NdxState::new();
+        let head = SumHead {
+            count: 2,
+            block_length: 4,
+            checksum_length: 2,
+            remainder_length: 0,
+        };
+        let mut buf = ItemHeader {
+            ndx: 3,
+            iflags: ITEM_TRANSFER,
+            basis_type: None,
+            xname: None,
+            xattrs: Vec::new(),
+        }
+        .encode(&mut st);
+        buf.extend_from_slice(&encode_sum_head(&head));
+        buf.extend_from_slice(&encode_sum_block(&make_sig_block(1, 0xAA, 2)));
+
+        let err = decode_generator_message(&buf, &mut NdxState::new()).unwrap_err();
+        assert!(needs_more_bytes(&err), "{err:?}");
+
+        buf.extend_from_slice(&encode_sum_block(&make_sig_block(2, 0xBB, 2)));
+        let (message, consumed) = decode_generator_message(&buf, &mut NdxState::new()).unwrap();
+        assert_eq!(consumed, buf.len());
+        match message {
+            GeneratorMessage::Request {
+                header,
+                sums: Some((decoded_head, blocks)),
+            } => {
+                assert_eq!(header.ndx, 3);
+                assert_eq!(decoded_head, head);
+                assert_eq!(blocks.len(), 2);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
 
-        let raw_literal = b"X";
-        let inbound = streaming_fixture_inbound(raw_literal);
+    #[test]
+    fn append_generator_message_carries_a_head_without_blocks() {
+        let head = SumHead {
+            count: 2,
+            block_length: 4,
+            checksum_length: 2,
+            remainder_length: 1,
+        };
+        let mut buf = ItemHeader {
+            ndx: 3,
+            iflags: ITEM_TRANSFER,
+            basis_type: None,
+            xname: None,
+            xattrs: Vec::new(),
+        }
+        .encode(&mut NdxState::new());
+        buf.extend_from_slice(&encode_sum_head(&head));
+
+        let err = decode_generator_message(&buf, &mut NdxState::new()).unwrap_err();
+        assert!(needs_more_bytes(&err), "{err:?}");
+        let (message, consumed) =
+            decode_append_generator_message(&buf, &mut NdxState::new()).unwrap();
+        assert_eq!(consumed, buf.len());
+        match message {
+            GeneratorMessage::Request {
+                sums: Some((decoded_head, blocks)),
+                ..
+            } => {
+                assert_eq!(decoded_head.basis_length(), 5);
+                assert!(blocks.is_empty());
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
 
-        let transport = mock_transport_with_raw_inbound(inbound);
-        let adapter = MockSigAdapter::with_fixed_signatures(
-            4,
+    #[test]
+    fn inplace_guard_turns_backward_matches_into_literals() {
+        let head = SumHead {
+            count: 3,
+            block_length: 4,
+            checksum_length: 2,
+            remainder_length: 0,
+        };
+        let source = b"BBBBAAAAxyCCCC";
+        let mut ops = vec![
+            EngineDeltaOp::CopyBlock(1),
+            EngineDeltaOp::CopyBlock(0),
+            EngineDeltaOp::Literal(b"xy".to_vec()),
+            EngineDeltaOp::CopyBlock(2),
+        ];
+        let mut guard = InplaceGuard::new(head);
+        guard.apply(&mut ops[..2], source, 0).unwrap();
+        // Second call sees only the tail of the source, like the
+        // streaming sender's sliding window.
+        guard.apply(&mut ops[2..], &source[8..], 8).unwrap();
+
+        assert_eq!(
+            ops,
             vec![
-                make_engine_sig(0, 0xA0, 0x01, 4),
-                make_engine_sig(1, 0xA1, 0x02, 4),
-            ],
+                EngineDeltaOp::CopyBlock(1),
+                EngineDeltaOp::Literal(b"AAAA".to_vec()),
+                EngineDeltaOp::Literal(b"xy".to_vec()),
+                EngineDeltaOp::Literal(b"CCCC".to_vec()),
+            ]
         );
-        let destination_data: Vec<u8> = b"BLK1BLK2".to_vec();
-        let mut baseline = MemoryBaseline::new(destination_data.clone());
+        assert_eq!(guard.converted_bytes, 8);
 
-        let (mut writer, _captured) = MockAsyncWriter::new();
-        let mut d = make_driver(transport);
+        let mut late = vec![EngineDeltaOp::CopyBlock(0)];
+        let err = guard.apply(&mut late, b"", 100).unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::Internal);
+    }
 
-        let mut sink = CollectingSink::default();
-        d.drive_download_through_delta_streaming(
-            RemoteCommandSpec::download("/remote/target.bin"),
-            &destination_data,
-            &mut baseline,
-            &mut writer,
-            &adapter,
-            &mut sink,
-        )
-        .await
-        .expect("streaming download succeeds");
+    #[test]
+    fn sender_message_decodes_extra_list_and_item_extras() {
+        let mut st = NdxState::new();
+        let mut buf = encode_ndx(NDX_FLIST_OFFSET - 4, &mut st);
+        buf.extend_from_slice(
+            &ItemHeader {
+                ndx: 7,
+                iflags: ITEM_BASIS_TYPE_FOLLOWS | ITEM_XNAME_FOLLOWS,
+                basis_type: Some(1),
+                xname: Some(b"alt".to_vec()),
+                xattrs: Vec::new(),
+            }
+            .encode(&mut st),
+        );
+        buf.extend_from_slice(&encode_ndx(NDX_FLIST_EOF, &mut st));
+
+        let mut rd = NdxState::new();
+        let (first, used) = decode_sender_message(&buf, &mut rd).unwrap();
+        assert_eq!(first, SenderMessage::ExtraList { dir_ndx: 4 });
+        let (second, used2) = decode_sender_message(&buf[used..], &mut rd).unwrap();
+        match second {
+            SenderMessage::Item {
+                header,
+                sum_head: None,
+            } => {
+                assert_eq!(header.ndx, 7);
+                assert_eq!(header.basis_type, Some(1));
+                assert_eq!(header.xname.as_deref(), Some(&b"alt"[..]));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        let (third, _) = decode_sender_message(&buf[used + used2..], &mut rd).unwrap();
+        assert_eq!(third, SenderMessage::FlistEof);
+    }
 
-        assert!(
-            d.reconstructed().is_none(),
-            "streaming path must NOT populate self.reconstructed"
+    #[test]
+    fn wire_ops_to_engine_ops_rejects_out_of_range_blocks() {
+        let ops = [DeltaOp::CopyRun {
+            start_token_index: 1,
+            run_length: 2,
+        }];
+        let mut decoder = LiteralDecoder::new(CompressionAlgo::Zstd);
+        let basis = MatchBasis {
+            data: &[],
+            block_len: 0,
+        };
+        let err = wire_ops_to_engine_ops(&ops, &mut decoder, 2, &basis).unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::InvalidFrame);
+        let ok = wire_ops_to_engine_ops(&ops, &mut decoder, 3, &basis).unwrap();
+        assert_eq!(
+            ok,
+            vec![EngineDeltaOp::CopyBlock(1), EngineDeltaOp::CopyBlock(2)]
         );
     }
 }
@@ -0,0 +1,449 @@
+//! Checksum and compression negotiation (`compat.c::negotiate_the_strings`).
+//!
+//! With `CF_VARINT_FLIST_FLAGS` both peers send a space-separated list of
+//! checksum names, and under `-z` a list of compressor names, right after
+//! the compat flags. Each side then picks the first name of the *client*
+//! list that the server list also carries, so both land on the same
+//! answer without another round trip. Stock lists, for reference:
+//!
+//! ```text
+//! client: xxh128 xxh3 xxh64 md5 md4 sha1        zstd lz4 zlibx zlib
+//! server: xxh128 xxh3 xxh64 md5 md4 sha1 none   zstd lz4 zlibx zlib none
+//! ```
+//!
+//! `AlgorithmPreferences` is the user-facing side: names to try first and
+//! names never to offer, plus `--compress-level`. The same environment
+//! variables as rsync (`RSYNC_CHECKSUM_LIST`, `RSYNC_COMPRESS_LIST`) can
+//! replace a list wholesale.
+
+#![cfg(feature = "aerorsync")]
+
+use crate::aerorsync::checksum::ChecksumAlgo;
+use crate::aerorsync::compression::CompressionAlgo;
+use crate::aerorsync::types::{AerorsyncError, AerorsyncErrorKind};
+
+/// rsync's override for the checksum list.
+pub const CHECKSUM_LIST_ENV: &str = "RSYNC_CHECKSUM_LIST";
+/// rsync's override for the compression list.
+pub const COMPRESS_LIST_ENV: &str = "RSYNC_COMPRESS_LIST";
+
+/// First name of `client_list` that `server_list` also carries.
+pub fn negotiate_name(client_list: &str, server_list: &str) -> Option<String> {
+    client_list
+        .split_ascii_whitespace()
+        .find(|name| server_list.split_ascii_whitespace().any(|s| s == *name))
+        .map(str::to_string)
+}
+
+/// Which algorithms a session offers, and in what order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AlgorithmPreferences {
+    /// Offered first, in this order. The others keep their default
+    /// order after them.
+    pub prefer_checksums: Vec<ChecksumAlgo>,
+    /// Never offered.
+    pub forbid_checksums: Vec<ChecksumAlgo>,
+    /// Offered first, in this order. Listing `None` here lets a client
+    /// turn compression off while still sending `-z`.
+    pub prefer_compressions: Vec<CompressionAlgo>,
+    /// Never offered.
+    pub forbid_compressions: Vec<CompressionAlgo>,
+    /// `--compress-level`, checked against whichever compressor wins.
+    pub compress_level: Option<i32>,
+}
+
+impl AlgorithmPreferences {
+    /// Checksums in offer order.
+    pub fn checksums(&self) -> Vec<ChecksumAlgo> {
+        ordered(
+            &self.prefer_checksums,
+            &ChecksumAlgo::ALL,
+            &self.forbid_checksums,
+        )
+    }
+
+    /// Compressors in offer order. A client leaves `none` out unless it
+    /// is preferred, like stock rsync; a server offers it last so
+    /// clients without a common compressor still connect.
+    pub fn compressions(&self, server: bool) -> Vec<CompressionAlgo> {
+        let defaults: &[CompressionAlgo] = if server {
+            &CompressionAlgo::ALL
+        } else {
+            &CompressionAlgo::ALL[..CompressionAlgo::ALL.len() - 1]
+        };
+        ordered(
+            &self.prefer_compressions,
+            defaults,
+            &self.forbid_compressions,
+        )
+    }
+
+    /// The checksum list as sent on the wire.
+    pub fn checksum_list(&self) -> String {
+        join(self.checksums().into_iter().map(ChecksumAlgo::name))
+    }
+
+    /// The compression list as sent on the wire.
+    pub fn compression_list(&self, server: bool) -> String {
+        join(
+            self.compressions(server)
+                .into_iter()
+                .map(CompressionAlgo::name),
+        )
+    }
+
+    /// Reject preferences that leave nothing to offer or a level no
+    /// compressor accepts.
+    pub fn validate(&self) -> Result<(), AerorsyncError> {
+        if self.checksums().is_empty() {
+            return Err(negotiation_error("every checksum algorithm is forbidden"));
+        }
+        if self.compressions(true).is_empty() {
+            return Err(negotiation_error(
+                "every compression algorithm is forbidden",
+            ));
+        }
+        if let Some(level) = self.compress_level {
+            let fits = self
+                .compressions(true)
+                .into_iter()
+                .filter_map(CompressionAlgo::level_range)
+                .any(|range| range.contains(&level));
+            if !fits {
+                return Err(negotiation_error(format!(
+                    "compress level {level} is out of range for every offered compressor"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checksum a client picks from the server's list.
+    pub fn pick_checksum(&self, server_list: &str) -> Result<ChecksumAlgo, AerorsyncError> {
+        pick(&self.checksum_list(), server_list, "checksum")
+            .and_then(|name| known(ChecksumAlgo::from_name(&name), "checksum", &name))
+    }
+
+    /// Compressor a client picks from the server's list.
+    pub fn pick_compression(&self, server_list: &str) -> Result<CompressionAlgo, AerorsyncError> {
+        pick(&self.compression_list(false), server_list, "compression")
+            .and_then(|name| known(CompressionAlgo::from_name(&name), "compression", &name))
+    }
+
+    /// Checksum a server picks from the client's list.
+    pub fn accept_checksum(&self, client_list: &str) -> Result<ChecksumAlgo, AerorsyncError> {
+        pick(client_list, &self.checksum_list(), "checksum")
+            .and_then(|name| known(ChecksumAlgo::from_name(&name), "checksum", &name))
+    }
+
+    /// Compressor a server picks from the client's list.
+    pub fn accept_compression(&self, client_list: &str) -> Result<CompressionAlgo, AerorsyncError> {
+        pick(client_list, &self.compression_list(true), "compression")
+            .and_then(|name| known(CompressionAlgo::from_name(&name), "compression", &name))
+    }
+
+    /// `--compress-level` for `algo`: the configured level when that
+    /// compressor accepts it, its default otherwise.
+    pub fn level_for(&self, algo: CompressionAlgo) -> Option<i32> {
+        match (self.compress_level, algo.level_range()) {
+            (Some(level), Some(range)) if range.contains(&level) => Some(level),
+            _ => algo.default_level(),
+        }
+    }
+
+    /// Apply `RSYNC_CHECKSUM_LIST` / `RSYNC_COMPRESS_LIST`. A set variable
+    /// replaces the corresponding preference: its names, in order, are the
+    /// only ones offered. Unknown names are an error, as in rsync.
+    pub fn with_env(self) -> Result<Self, AerorsyncError> {
+        self.with_lists(
+            std::env::var(CHECKSUM_LIST_ENV).ok().as_deref(),
+            std::env::var(COMPRESS_LIST_ENV).ok().as_deref(),
+        )
+    }
+
+    /// `with_env` on explicit values.
+    pub fn with_lists(
+        mut self,
+        checksums: Option<&str>,
+        compressions: Option<&str>,
+    ) -> Result<Self, AerorsyncError> {
+        if let Some(list) = checksums.filter(|l| !l.trim().is_empty()) {
+            let wanted = parse_list(list, ChecksumAlgo::from_name, "checksum")?;
+            self.forbid_checksums = ChecksumAlgo::ALL
+                .into_iter()
+                .filter(|a| !wanted.contains(a))
+                .collect();
+            self.prefer_checksums = wanted;
+        }
+        if let Some(list) = compressions.filter(|l| !l.trim().is_empty()) {
+            let wanted = parse_list(list, CompressionAlgo::from_name, "compression")?;
+            self.forbid_compressions = CompressionAlgo::ALL
+                .into_iter()
+                .filter(|a| !wanted.contains(a))
+                .collect();
+            self.prefer_compressions = wanted;
+        }
+        self.validate()?;
+        Ok(self)
+    }
+}
+
+fn ordered<T: Copy + PartialEq>(prefer: &[T], defaults: &[T], forbid: &[T]) -> Vec<T> {
+    let mut out: Vec<T> = Vec::with_capacity(defaults.len() + 1);
+    for algo in prefer.iter().chain(defaults) {
+        if !forbid.contains(algo) && !out.contains(algo) {
+            out.push(*algo);
+        }
+    }
+    out
+}
+
+fn join<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    names.collect::<Vec<_>>().join(" ")
+}
+
+fn parse_list<T: PartialEq>(
+    list: &str,
+    parse: impl Fn(&str) -> Option<T>,
+    what: &str,
+) -> Result<Vec<T>, AerorsyncError> {
+    let mut out = Vec::new();
+    for name in list.split(|c: char| c.is_ascii_whitespace() || c == ',') {
+        if name.is_empty() {
+            continue;
+        }
+        let algo = parse(&name.to_ascii_lowercase())
+            .ok_or_else(|| negotiation_error(format!("unknown {what} name {name:?}")))?;
+        if !out.contains(&algo) {
+            out.push(algo);
+        }
+    }
+    Ok(out)
+}
+
+fn pick(client_list: &str, server_list: &str, what: &str) -> Result<String, AerorsyncError> {
+    negotiate_name(client_list, server_list).ok_or_else(|| {
+        negotiation_error(format!(
+            "no common {what}: client offers {client_list:?}, server offers {server_list:?}"
+        ))
+    })
+}
+
+fn known<T>(algo: Option<T>, what: &str, name: &str) -> Result<T, AerorsyncError> {
+    algo.ok_or_else(|| negotiation_error(format!("negotiated {what} {name:?} is not supported")))
+}
+
+fn negotiation_error(detail: impl Into<String>) -> AerorsyncError {
+    AerorsyncError::new(AerorsyncErrorKind::NegotiationFailed, detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Default lists each stock release sends as a client, shaped after
+    /// the frozen 3.2.7 -> 3.4 capture; as a server it appends `none` to
+    /// both. 3.3 and 3.4 did not change what a protocol-31 session
+    /// negotiates.
+    struct StockPeer {
+        version: &'static str,
+        checksums: &'static str,
+        compressions: &'static str,
+    }
+
+    const STOCK_PEERS: [StockPeer; 3] = [
+        StockPeer {
+            version: "3.2.7",
+            checksums: "xxh128 xxh3 xxh64 md5 md4 sha1",
+            compressions: "zstd lz4 zlibx zlib",
+        },
+        StockPeer {
+            version: "3.3.0",
+            checksums: "xxh128 xxh3 xxh64 md5 md4 sha1",
+            compressions: "zstd lz4 zlibx zlib",
+        },
+        StockPeer {
+            version: "3.4.1",
+            checksums: "xxh128 xxh3 xxh64 md5 md4 sha1",
+            compressions: "zstd lz4 zlibx zlib",
+        },
+    ];
+
+    fn as_server(list: &str) -> String {
+        format!("{list} none")
+    }
+
+    fn prefs() -> AlgorithmPreferences {
+        AlgorithmPreferences::default()
+    }
+
+    #[test]
+    fn default_client_lists_match_stock_rsync() {
+        for peer in &STOCK_PEERS {
+            assert_eq!(prefs().checksum_list(), peer.checksums, "{}", peer.version);
+            assert_eq!(prefs().compression_list(false), peer.compressions);
+            assert_eq!(prefs().compression_list(true), as_server(peer.compressions));
+        }
+    }
+
+    /// Matrix: our client against each stock server, and each stock client
+    /// against our server, for a set of preference profiles. Each cell
+    /// checks that both ends reach the same pick, as they must without
+    /// another round trip.
+    #[test]
+    fn negotiation_matrix_against_stock_peers() {
+        let profiles: Vec<(&str, AlgorithmPreferences, ChecksumAlgo, CompressionAlgo)> = vec![
+            (
+                "default",
+                prefs(),
+                ChecksumAlgo::Xxh128,
+                CompressionAlgo::Zstd,
+            ),
+            (
+                "prefer md5 + lz4",
+                AlgorithmPreferences {
+                    prefer_checksums: vec![ChecksumAlgo::Md5],
+                    prefer_compressions: vec![CompressionAlgo::Lz4],
+                    ..prefs()
+                },
+                ChecksumAlgo::Md5,
+                CompressionAlgo::Lz4,
+            ),
+            (
+                "forbid xxhash + zstd",
+                AlgorithmPreferences {
+                    forbid_checksums: vec![
+                        ChecksumAlgo::Xxh128,
+                        ChecksumAlgo::Xxh3,
+                        ChecksumAlgo::Xxh64,
+                    ],
+                    forbid_compressions: vec![CompressionAlgo::Zstd, CompressionAlgo::Lz4],
+                    ..prefs()
+                },
+                ChecksumAlgo::Md5,
+                CompressionAlgo::Zlibx,
+            ),
+            (
+                "sha1 + zlib only",
+                prefs().with_lists(Some("sha1"), Some("zlib")).unwrap(),
+                ChecksumAlgo::Sha1,
+                CompressionAlgo::Zlib,
+            ),
+            (
+                "compression off",
+                AlgorithmPreferences {
+                    prefer_compressions: vec![CompressionAlgo::None],
+                    ..prefs()
+                },
+                ChecksumAlgo::Xxh128,
+                CompressionAlgo::None,
+            ),
+        ];
+
+        for peer in &STOCK_PEERS {
+            for (label, ours, checksum, compression) in &profiles {
+                // Our client, stock server.
+                let server_checksums = as_server(peer.checksums);
+                let server_compressions = as_server(peer.compressions);
+                assert_eq!(
+                    ours.pick_checksum(&server_checksums).unwrap(),
+                    *checksum,
+                    "{} client / {label}",
+                    peer.version
+                );
+                assert_eq!(
+                    ours.pick_compression(&server_compressions).unwrap(),
+                    *compression,
+                    "{} client / {label}",
+                    peer.version
+                );
+                // What the stock server computes from our lists.
+                assert_eq!(
+                    negotiate_name(&ours.checksum_list(), &server_checksums).as_deref(),
+                    Some(checksum.name())
+                );
+                assert_eq!(
+                    negotiate_name(&ours.compression_list(false), &server_compressions).as_deref(),
+                    Some(compression.name())
+                );
+            }
+
+            // Stock client, our server: the client's order wins, whatever
+            // the server prefers, unless the server forbids the name.
+            let server = AlgorithmPreferences {
+                prefer_checksums: vec![ChecksumAlgo::Sha1],
+                prefer_compressions: vec![CompressionAlgo::Zlib],
+                ..prefs()
+            };
+            assert_eq!(
+                server.accept_checksum(peer.checksums).unwrap(),
+                ChecksumAlgo::Xxh128
+            );
+            assert_eq!(
+                server.accept_compression(peer.compressions).unwrap(),
+                CompressionAlgo::Zstd
+            );
+            let strict = prefs().with_lists(Some("md4"), Some("zlibx none")).unwrap();
+            assert_eq!(
+                strict.accept_checksum(peer.checksums).unwrap(),
+                ChecksumAlgo::Md4
+            );
+            assert_eq!(
+                strict.accept_compression(peer.compressions).unwrap(),
+                CompressionAlgo::Zlibx
+            );
+        }
+    }
+
+    #[test]
+    fn pre_3_2_peers_without_xxhash_fall_back_to_md5() {
+        // rsync built without xxhash support.
+        let server = "md5 md4 sha1 none";
+        assert_eq!(prefs().pick_checksum(server).unwrap(), ChecksumAlgo::Md5);
+    }
+
+    #[test]
+    fn no_common_name_is_a_negotiation_failure() {
+        let ours = prefs().with_lists(Some("sha1"), None).unwrap();
+        let err = ours.pick_checksum("xxh128 md5 none").unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::NegotiationFailed);
+        let err = prefs().pick_compression("zlib2 none").unwrap_err();
+        assert_eq!(err.kind, AerorsyncErrorKind::NegotiationFailed);
+    }
+
+    #[test]
+    fn env_style_lists_replace_the_defaults() {
+        let p = prefs()
+            .with_lists(Some("md5, xxhash"), Some("LZ4 none"))
+            .unwrap();
+        assert_eq!(p.checksum_list(), "md5 xxh64");
+        assert_eq!(p.compression_list(false), "lz4 none");
+        assert_eq!(p.compression_list(true), "lz4 none");
+        assert!(prefs().with_lists(Some("crc32"), None).is_err());
+        // Empty values are ignored, like an unset variable.
+        assert_eq!(prefs().with_lists(Some(" "), None).unwrap(), prefs());
+    }
+
+    #[test]
+    fn validate_rejects_empty_offers_and_bad_levels() {
+        let p = AlgorithmPreferences {
+            forbid_checksums: ChecksumAlgo::ALL.to_vec(),
+            ..prefs()
+        };
+        assert!(p.validate().is_err());
+        let p = AlgorithmPreferences {
+            compress_level: Some(23),
+            ..prefs()
+        };
+        assert!(p.validate().is_err());
+        let p = AlgorithmPreferences {
+            compress_level: Some(19),
+            ..prefs()
+        };
+        assert!(p.validate().is_ok());
+        assert_eq!(p.level_for(CompressionAlgo::Zstd), Some(19));
+        // Out of zlib's range: the zlib default applies instead.
+        assert_eq!(p.level_for(CompressionAlgo::Zlib), Some(6));
+        assert_eq!(p.level_for(CompressionAlgo::Lz4), None);
+    }
+}
@@ -164,6 +164,40 @@ pub enum RealWireError {
     ZstdDecompressionFailed {
         reason: String,
     },
+    /// A file-list entry carries a file type (device, fifo, socket, or
+    /// a symlink without `-l`) whose extra wire fields this decoder does
+    /// not parse. Surfaced instead of mis-aligning the rest of the list.
+    UnsupportedFileType {
+        mode: u32,
+    },
+    /// A hard-link follower names a first entry in the current list
+    /// that has not been decoded (or no list context was supplied).
+    HardlinkLeaderMissing {
+        first_ndx: i32,
+    },
+    /// An ACL or xattr back-reference (`ndx + 1` on the wire) points
+    /// past the entries recorded so far in this session.
+    MetadataRefOutOfRange {
+        table: &'static str,
+        index: i64,
+        known: usize,
+    },
+    /// An xattr name arrived without its trailing NUL
+    /// (`xattrs.c::receive_xattr`'s "Invalid xattr name received").
+    InvalidXattrName,
+    /// A simple-format literal record declares more bytes than any
+    /// sender emits in one record (`simple_send_token` chunks at
+    /// `CHUNK_SIZE`). Rejected rather than buffered.
+    DeltaLiteralTooLong {
+        len: usize,
+        max: usize,
+    },
+    /// An lz4 or zlib literal payload could not be encoded or decoded.
+    /// zstd failures keep their own variant.
+    LiteralCodecFailed {
+        algo: &'static str,
+        reason: String,
+    },
 }
 
 impl fmt::Display for RealWireError {
@@ -265,12 +299,68 @@ impl fmt::Display for RealWireError {
             RealWireError::ZstdDecompressionFailed { reason } => {
                 write!(f, "zstd decompression failed: {reason}")
             }
+            RealWireError::UnsupportedFileType { mode } => {
+                write!(f, "unsupported file type in file list: mode {mode:#o}")
+            }
+            RealWireError::HardlinkLeaderMissing { first_ndx } => {
+                write!(
+                    f,
+                    "hard-link follower refers to ndx {first_ndx}, not decoded in this list"
+                )
+            }
+            RealWireError::MetadataRefOutOfRange {
+                table,
+                index,
+                known,
+            } => {
+                write!(
+                    f,
+                    "{table} reference {index} out of range ({known} recorded)"
+                )
+            }
+            RealWireError::InvalidXattrName => {
+                write!(f, "xattr name without trailing NUL")
+            }
+            RealWireError::DeltaLiteralTooLong { len, max } => {
+                write!(f, "delta literal record of {len} bytes exceeds {max}")
+            }
+            RealWireError::LiteralCodecFailed { algo, reason } => {
+                write!(f, "{algo} literal codec failed: {reason}")
+            }
         }
     }
 }
 
 impl std::error::Error for RealWireError {}
 
+/// `CF_INC_RECURSE` bit of `ServerPreamble::compat_flags` (`compat.c`).
+/// When set, the file list travels as one top-level list plus one extra
+/// list per directory (`NDX_FLIST_OFFSET - dir_ndx` headers) and the
+/// first list starts at ndx 1 instead of 0.
+pub const CF_INC_RECURSE: i32 = 1 << 0;
+
+/// `CF_SYMLINK_TIMES`: symlinks carry their own mtime (`-e.L`).
+pub const CF_SYMLINK_TIMES: i32 = 1 << 1;
+/// `CF_SYMLINK_ICONV`: symlink targets go through `--iconv` (`-e.s`).
+pub const CF_SYMLINK_ICONV: i32 = 1 << 2;
+/// `CF_SAFE_FLIST`: file-list errors are sent safely (`-e.f`).
+pub const CF_SAFE_FLIST: i32 = 1 << 3;
+/// `CF_AVOID_XATTR_OPTIM`: xattr values are always sent (`-e.x`).
+pub const CF_AVOID_XATTR_OPTIM: i32 = 1 << 4;
+/// `CF_CHKSUM_SEED_FIX`: the seed is mixed in the fixed order (`-e.C`).
+pub const CF_CHKSUM_SEED_FIX: i32 = 1 << 5;
+/// `CF_INPLACE_PARTIAL_DIR`: `--inplace` works with `--partial-dir`
+/// (`-e.I`).
+pub const CF_INPLACE_PARTIAL_DIR: i32 = 1 << 6;
+
+/// `CF_VARINT_FLIST_FLAGS`: transfer flags travel as varints and both
+/// sides exchange checksum / compression name lists. Set by a server
+/// whose client sent `v` in its `-e.` info (rsync 3.2+).
+pub const CF_VARINT_FLIST_FLAGS: i32 = 1 << 7;
+
+/// `CF_ID0_NAMES`: uid/gid 0 are sent with their names too (`-e.u`).
+pub const CF_ID0_NAMES: i32 = 1 << 8;
+
 /// Decoded view of the server-side preamble (bytes before multiplex kicks in).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ServerPreamble {
@@ -1147,8 +1237,10 @@ pub fn encode_varlong(x: i64, min_bytes: u8) -> Vec<u8> {
 // when `CF_VARINT_FLIST_FLAGS` is negotiated.
 //
 // Field order mirrors `flist.c::send_file_entry` / `recv_file_entry` in
-// rsync 3.2.7, restricted to protocol ≥ 31 + regular file + non-hlink.
-// Device, symlink, and hardlink paths will ship in a later sinergia.
+// rsync 3.2.7 for protocol ≥ 31: regular files, directories and
+// symlinks (`-l`), hard-link groups (`-H`), and the ACL / xattr
+// trailers `send_file_name` appends after each entry (`-A`, `-X`).
+// Device entries are still refused.
 // =============================================================================
 
 // --- XMIT flag constants (rsync.h, protocol 30+) -----------------------------
@@ -1171,6 +1263,39 @@ pub const XMIT_IO_ERROR_ENDLIST: u32 = 1 << 12;
 pub const XMIT_MOD_NSEC: u32 = 1 << 13;
 pub const XMIT_SAME_ATIME: u32 = 1 << 14;
 
+// --- File type bits carried in `FileListEntry::mode` (POSIX stat.h) ---------
+
+pub const S_IFMT: u32 = 0o170_000;
+pub const S_IFREG: u32 = 0o100_000;
+pub const S_IFDIR: u32 = 0o040_000;
+pub const S_IFLNK: u32 = 0o120_000;
+
+// --- ACL / xattr trailers (acls.c, xattrs.c) ---------------------------------
+
+/// `send_rsync_acl` flag byte: which ACL objects follow.
+pub const XMIT_ACL_USER_OBJ: u8 = 1 << 0;
+pub const XMIT_ACL_GROUP_OBJ: u8 = 1 << 1;
+pub const XMIT_ACL_MASK_OBJ: u8 = 1 << 2;
+pub const XMIT_ACL_OTHER_OBJ: u8 = 1 << 3;
+pub const XMIT_ACL_NAME_LIST: u8 = 1 << 4;
+/// Low bits of the `access << 2` varint `send_ida_entries` writes.
+pub const XFLAG_ACL_NAME_FOLLOWS: u32 = 1 << 0;
+pub const XFLAG_ACL_NAME_IS_USER: u32 = 1 << 1;
+/// Xattr values longer than this travel as an MD5 digest; the receiver
+/// asks for the ones it needs with `ITEM_REPORT_XATTR`.
+pub const XATTR_MAX_FULL_DATUM: usize = 32;
+pub const XATTR_DIGEST_LEN: usize = 16;
+
+/// Whether an entry with `mode` carries the `always_checksum` trailer.
+/// `flist.c::send_file_entry` only writes it for `S_ISREG` files
+/// (protocol >= 28). A mode without file-type bits (SAME_MODE with no
+/// previous entry, hand-built fixtures) is treated as a regular file,
+/// which is what every single-file transcript carries.
+pub fn flist_mode_carries_checksum(mode: u32) -> bool {
+    let kind = mode & S_IFMT;
+    kind == 0 || kind == S_IFREG
+}
+
 // --- Public option / outcome / entry types ----------------------------------
 
 /// Caller-supplied context needed to decode a file-list entry. The rsync
@@ -1201,6 +1326,20 @@ pub struct FileListDecodeOptions<'a> {
     /// Last file's name, used when `XMIT_SAME_NAME` with `l1 > 0` asks
     /// us to reuse its prefix.
     pub previous_name: Option<&'a str>,
+    /// `-l` / `--links`: symlink entries carry their target after the
+    /// owner fields.
+    pub preserve_links: bool,
+    /// `-H` / `--hard-links`: `XMIT_HLINKED` entries carry their group
+    /// leader's ndx (protocol 30+).
+    pub preserve_hard_links: bool,
+    /// `-A` / `--acls`: every non-symlink entry is followed by its
+    /// access ACL, directories also by their default ACL.
+    pub preserve_acls: bool,
+    /// `-X` / `--xattrs`: every entry is followed by its xattr set.
+    pub preserve_xattrs: bool,
+    /// List context for hard-link followers and the ACL / xattr
+    /// back-references. `None` for single-entry lists.
+    pub scope: Option<FlistScope<'a>>,
 }
 
 impl<'a> FileListDecodeOptions<'a> {
@@ -1216,12 +1355,19 @@ impl<'a> FileListDecodeOptions<'a> {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         }
     }
 }
 
 /// What `decode_file_list_entry` yielded. The caller then either
 /// appends the entry to its working file-list or finalises the list.
+/// `EndOfList` comes once per list, so the size gap is not worth a box.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FileListDecodeOutcome {
     Entry(FileListEntry),
@@ -1232,9 +1378,8 @@ pub enum FileListDecodeOutcome {
     },
 }
 
-/// Decoded file-list entry (regular file, protocol ≥ 31 path). Device /
-/// symlink / hardlink extensions are deferred: they land in a later
-/// sinergia when we encounter them in a transcript.
+/// Decoded file-list entry (protocol ≥ 31 path): a regular file,
+/// directory or symlink, with the optional `-H` / `-A` / `-X` extras.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FileListEntry {
     pub flags: u32,
@@ -1250,6 +1395,125 @@ pub struct FileListEntry {
     /// Raw checksum bytes when `always_checksum` is active. Length
     /// equals `options.csum_len`; empty otherwise.
     pub checksum: Vec<u8>,
+    /// Symlink target (`-l`); `None` for other entries.
+    pub link_target: Option<String>,
+    /// Hard-link group membership (`-H`).
+    pub hardlink: Option<HardlinkRef>,
+    /// ACLs in their wire form (`-A`).
+    pub acl: Option<EntryAcl>,
+    /// Extended attributes, sorted by name (`-X`).
+    pub xattrs: Option<Vec<XattrItem>>,
+}
+
+/// Hard-link group membership of a non-directory entry (protocol 30+).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HardlinkRef {
+    /// First member of its group on the wire (`XMIT_HLINK_FIRST`).
+    First,
+    /// Later member. `first_ndx` is the ndx of the group's first
+    /// entry; when that entry is in the same list, the follower's
+    /// attributes are not sent again but copied from it.
+    Follower { first_ndx: i32 },
+}
+
+/// One named ACL entry (`id_access`). `name` only travels the first
+/// time an id is sent without `--numeric-ids`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AclIdAccess {
+    pub id: u32,
+    pub is_user: bool,
+    /// `rwx` bits.
+    pub access: u8,
+    pub name: Option<String>,
+}
+
+/// `rsync_acl`: the POSIX ACL objects plus the named entries. A `None`
+/// object is `NO_ENTRY`: absent, or left out because the mode bits
+/// already carry it (access ACLs travel stripped, see
+/// `attrs::strip_access_acl`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RsyncAcl {
+    pub user_obj: Option<u8>,
+    pub group_obj: Option<u8>,
+    pub mask_obj: Option<u8>,
+    pub other_obj: Option<u8>,
+    pub names: Vec<AclIdAccess>,
+}
+
+/// `-A` payload of one entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryAcl {
+    pub access: RsyncAcl,
+    /// Default ACL; present for directories only.
+    pub default: Option<RsyncAcl>,
+}
+
+/// One extended attribute. `name` excludes the NUL the wire carries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XattrItem {
+    pub name: String,
+    pub value: XattrValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XattrValue {
+    /// The value itself: on the sender, for short values, and once an
+    /// abbreviated value has been fetched.
+    Full(Vec<u8>),
+    /// A value longer than `XATTR_MAX_FULL_DATUM` as received: its
+    /// length and MD5, to be requested if the receiver needs it.
+    Abbrev {
+        len: usize,
+        digest: [u8; XATTR_DIGEST_LEN],
+    },
+}
+
+/// List context for the list-relative parts of an entry.
+#[derive(Debug, Clone, Copy)]
+pub struct FlistScope<'a> {
+    /// ndx of the first entry of the list being coded.
+    pub ndx_start: i32,
+    /// Entries of that list already coded, in wire order.
+    pub entries: &'a [FileListEntry],
+    /// Session-wide ACL / xattr tables.
+    pub tables: &'a FlistMetaTables,
+}
+
+/// Session-wide tables behind the ACL and xattr back-references. Both
+/// peers append every literal ACL (access and default apart) and every
+/// literal xattr set in wire order; later entries name a match by its
+/// index instead of repeating it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlistMetaTables {
+    access_acls: Vec<RsyncAcl>,
+    default_acls: Vec<RsyncAcl>,
+    xattr_sets: Vec<Vec<XattrItem>>,
+}
+
+impl FlistMetaTables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `entry`'s ACLs and xattr set once it has been encoded or
+    /// decoded. Sets already recorded are skipped: a sender only sends
+    /// a literal for a set it has not sent before.
+    pub fn absorb(&mut self, entry: &FileListEntry) {
+        fn add<T: PartialEq + Clone>(table: &mut Vec<T>, item: &T) {
+            if !table.contains(item) {
+                table.push(item.clone());
+            }
+        }
+        if let Some(acl) = &entry.acl {
+            add(&mut self.access_acls, &acl.access);
+            if let Some(default) = &acl.default {
+                add(&mut self.default_acls, default);
+            }
+        }
+        if let Some(xattrs) = &entry.xattrs {
+            add(&mut self.xattr_sets, xattrs);
+        }
+    }
 }
 
 /// Read `len` bytes from `buf[offset..]` and interpret them as UTF-8.
@@ -1313,6 +1577,23 @@ pub fn decode_file_list_entry(
     buf: &[u8],
     options: &FileListDecodeOptions,
 ) -> Result<(FileListDecodeOutcome, usize), RealWireError> {
+    decode_file_list_entry_after(buf, options, None)
+}
+
+/// Multi-entry variant of [`decode_file_list_entry`]. `previous` is the
+/// last entry decoded on this session (rsync keeps the `lastname` /
+/// `mode` / `modtime` / `uid` / `gid` statics of `recv_file_entry`
+/// alive across every list, extra lists included), and resolves the
+/// `XMIT_SAME_*` compression bits to the values they stand for. When
+/// `options.previous_name` is set it wins over `previous.path`.
+pub fn decode_file_list_entry_after(
+    buf: &[u8],
+    options: &FileListDecodeOptions,
+    previous: Option<&FileListEntry>,
+) -> Result<(FileListDecodeOutcome, usize), RealWireError> {
+    let previous_name = options
+        .previous_name
+        .or_else(|| previous.map(|p| p.path.as_str()));
     let mut cursor = 0;
 
     // --- 1. Flags -----------------------------------------------------------
@@ -1349,9 +1630,7 @@ pub fn decode_file_list_entry(
         let l1 = buf[cursor] as usize;
         cursor += 1;
         if l1 > 0 {
-            let prev = options
-                .previous_name
-                .ok_or(RealWireError::SameNameWithoutPrevious)?;
+            let prev = previous_name.ok_or(RealWireError::SameNameWithoutPrevious)?;
             if l1 > prev.len() {
                 return Err(RealWireError::SameNamePrefixTooLong {
                     l1,
@@ -1403,13 +1682,314 @@ pub fn decode_file_list_entry(
         }
     };
 
+    // --- 2b. Hard-link group (protocol 30+, `-H`) --------------------------
+    let hardlink = decode_hardlink_ref(buf, &mut cursor, flags, options)?;
+
+    // A follower of an entry in the same list carries no attributes of
+    // its own: `recv_file_entry` copies them from the first entry.
+    let body = match same_list_hardlink_leader(hardlink, options)? {
+        Some(first) => EntryBody::copied_from(first),
+        None => {
+            let (body, next) = decode_entry_body(buf, cursor, flags, options, previous)?;
+            cursor = next;
+            body
+        }
+    };
+
+    // --- 10. ACL and xattr trailers (`-A`, `-X`) ---------------------------
+    let tables = options.scope.map(|scope| scope.tables);
+    let acl = if options.preserve_acls && body.mode & S_IFMT != S_IFLNK {
+        let (access, consumed) = decode_rsync_acl(
+            &buf[cursor..],
+            tables.map_or(&[][..], |t| t.access_acls.as_slice()),
+            "access ACL",
+        )?;
+        cursor += consumed;
+        let default = if body.mode & S_IFMT == S_IFDIR {
+            let (default, consumed) = decode_rsync_acl(
+                &buf[cursor..],
+                tables.map_or(&[][..], |t| t.default_acls.as_slice()),
+                "default ACL",
+            )?;
+            cursor += consumed;
+            Some(default)
+        } else {
+            None
+        };
+        Some(EntryAcl { access, default })
+    } else {
+        None
+    };
+    let xattrs = if options.preserve_xattrs {
+        let (set, consumed) = decode_xattr_set(
+            &buf[cursor..],
+            tables.map_or(&[][..], |t| t.xattr_sets.as_slice()),
+        )?;
+        cursor += consumed;
+        Some(set)
+    } else {
+        None
+    };
+
+    Ok((
+        FileListDecodeOutcome::Entry(FileListEntry {
+            flags,
+            path,
+            size: body.size,
+            mtime: body.mtime,
+            mtime_nsec: body.mtime_nsec,
+            mode: body.mode,
+            uid: body.uid,
+            uid_name: body.uid_name,
+            gid: body.gid,
+            gid_name: body.gid_name,
+            checksum: body.checksum,
+            link_target: body.link_target,
+            hardlink,
+            acl,
+            xattrs,
+        }),
+        cursor,
+    ))
+}
+
+/// Read the `-H` group membership that follows the name.
+fn decode_hardlink_ref(
+    buf: &[u8],
+    cursor: &mut usize,
+    flags: u32,
+    options: &FileListDecodeOptions,
+) -> Result<Option<HardlinkRef>, RealWireError> {
+    if !options.preserve_hard_links || options.protocol < 30 || flags & XMIT_HLINKED == 0 {
+        return Ok(None);
+    }
+    if flags & XMIT_HLINK_FIRST != 0 {
+        return Ok(Some(HardlinkRef::First));
+    }
+    let (first_ndx, consumed) = decode_varint(&buf[*cursor..])?;
+    *cursor += consumed;
+    Ok(Some(HardlinkRef::Follower {
+        first_ndx: first_ndx as i32,
+    }))
+}
+
+/// The group's first entry when `hardlink` is a follower whose first
+/// entry sits in the list being coded (`first_ndx >= ndx_start`).
+fn same_list_hardlink_leader<'a>(
+    hardlink: Option<HardlinkRef>,
+    options: &FileListDecodeOptions<'a>,
+) -> Result<Option<&'a FileListEntry>, RealWireError> {
+    let Some(HardlinkRef::Follower { first_ndx }) = hardlink else {
+        return Ok(None);
+    };
+    let Some(scope) = options.scope else {
+        return Err(RealWireError::HardlinkLeaderMissing { first_ndx });
+    };
+    if first_ndx < scope.ndx_start {
+        return Ok(None);
+    }
+    usize::try_from(first_ndx - scope.ndx_start)
+        .ok()
+        .and_then(|i| scope.entries.get(i))
+        .map(Some)
+        .ok_or(RealWireError::HardlinkLeaderMissing { first_ndx })
+}
+
+/// `recv_rsync_acl`: a back-reference into `known` (`ndx + 1`) or a
+/// literal ACL (`0`, flag byte, the objects, the named entries).
+fn decode_rsync_acl(
+    buf: &[u8],
+    known: &[RsyncAcl],
+    table: &'static str,
+) -> Result<(RsyncAcl, usize), RealWireError> {
+    let (ndx, mut cursor) = decode_varint(buf)?;
+    if ndx != 0 {
+        let acl = usize::try_from(ndx - 1)
+            .ok()
+            .and_then(|i| known.get(i))
+            .ok_or(RealWireError::MetadataRefOutOfRange {
+                table,
+                index: ndx - 1,
+                known: known.len(),
+            })?;
+        return Ok((acl.clone(), cursor));
+    }
+    let flags = *buf.get(cursor).ok_or(RealWireError::TruncatedBuffer {
+        at: "acl_flags",
+        needed: 1,
+        available: 0,
+    })?;
+    cursor += 1;
+    let mut acl = RsyncAcl::default();
+    for (bit, slot) in [
+        (XMIT_ACL_USER_OBJ, &mut acl.user_obj),
+        (XMIT_ACL_GROUP_OBJ, &mut acl.group_obj),
+        (XMIT_ACL_MASK_OBJ, &mut acl.mask_obj),
+        (XMIT_ACL_OTHER_OBJ, &mut acl.other_obj),
+    ] {
+        if flags & bit != 0 {
+            let (bits, consumed) = decode_varint(&buf[cursor..])?;
+            cursor += consumed;
+            *slot = Some(bits as u8);
+        }
+    }
+    if flags & XMIT_ACL_NAME_LIST != 0 {
+        let (count, consumed) = decode_varint(&buf[cursor..])?;
+        cursor += consumed;
+        for _ in 0..count.max(0) {
+            let (id, consumed) = decode_varint(&buf[cursor..])?;
+            cursor += consumed;
+            let (xbits, consumed) = decode_varint(&buf[cursor..])?;
+            cursor += consumed;
+            let xbits = xbits as u32;
+            let name = if xbits & XFLAG_ACL_NAME_FOLLOWS != 0 {
+                let len = *buf.get(cursor).ok_or(RealWireError::TruncatedBuffer {
+                    at: "acl_name_len",
+                    needed: 1,
+                    available: 0,
+                })? as usize;
+                cursor += 1;
+                let name = read_utf8_slice(buf, cursor, len)?;
+                cursor += len;
+                Some(name)
+            } else {
+                None
+            };
+            acl.names.push(AclIdAccess {
+                id: id as u32,
+                is_user: xbits & XFLAG_ACL_NAME_IS_USER != 0,
+                access: (xbits >> 2) as u8,
+                name,
+            });
+        }
+    }
+    Ok((acl, cursor))
+}
+
+/// `receive_xattr`: a back-reference into `known` or a literal set of
+/// `(name_len incl. NUL, datum_len, name, value-or-digest)` items.
+fn decode_xattr_set(
+    buf: &[u8],
+    known: &[Vec<XattrItem>],
+) -> Result<(Vec<XattrItem>, usize), RealWireError> {
+    let (ndx, mut cursor) = decode_varint(buf)?;
+    if ndx != 0 {
+        let set = usize::try_from(ndx - 1)
+            .ok()
+            .and_then(|i| known.get(i))
+            .ok_or(RealWireError::MetadataRefOutOfRange {
+                table: "xattr set",
+                index: ndx - 1,
+                known: known.len(),
+            })?;
+        return Ok((set.clone(), cursor));
+    }
+    let (count, consumed) = decode_varint(&buf[cursor..])?;
+    cursor += consumed;
+    let mut items = Vec::new();
+    for _ in 0..count.max(0) {
+        let (name_len, consumed) = decode_varint(&buf[cursor..])?;
+        cursor += consumed;
+        let (datum_len, consumed) = decode_varint(&buf[cursor..])?;
+        cursor += consumed;
+        let (Ok(name_len), Ok(datum_len)) = (usize::try_from(name_len), usize::try_from(datum_len))
+        else {
+            return Err(RealWireError::InvalidXattrName);
+        };
+        if name_len == 0 {
+            return Err(RealWireError::InvalidXattrName);
+        }
+        let name = read_utf8_slice(buf, cursor, name_len - 1)?;
+        match buf.get(cursor + name_len - 1) {
+            Some(0) => {}
+            Some(_) => return Err(RealWireError::InvalidXattrName),
+            None => {
+                return Err(RealWireError::TruncatedBuffer {
+                    at: "xattr_name_nul",
+                    needed: 1,
+                    available: 0,
+                })
+            }
+        }
+        cursor += name_len;
+        let wire_len = if datum_len > XATTR_MAX_FULL_DATUM {
+            XATTR_DIGEST_LEN
+        } else {
+            datum_len
+        };
+        let bytes = buf
+            .get(cursor..cursor + wire_len)
+            .ok_or(RealWireError::TruncatedBuffer {
+                at: "xattr_datum",
+                needed: wire_len,
+                available: buf.len().saturating_sub(cursor),
+            })?;
+        cursor += wire_len;
+        let value = if datum_len > XATTR_MAX_FULL_DATUM {
+            let mut digest = [0u8; XATTR_DIGEST_LEN];
+            digest.copy_from_slice(bytes);
+            XattrValue::Abbrev {
+                len: datum_len,
+                digest,
+            }
+        } else {
+            XattrValue::Full(bytes.to_vec())
+        };
+        items.push(XattrItem { name, value });
+    }
+    Ok((items, cursor))
+}
+
+/// Attribute fields of a file-list entry: everything `send_file_entry`
+/// leaves out for a hard-link follower of an entry in the same list.
+struct EntryBody {
+    size: i64,
+    mtime: i64,
+    mtime_nsec: Option<i32>,
+    mode: u32,
+    uid: Option<i64>,
+    uid_name: Option<String>,
+    gid: Option<i64>,
+    gid_name: Option<String>,
+    link_target: Option<String>,
+    checksum: Vec<u8>,
+}
+
+impl EntryBody {
+    /// `recv_file_entry`'s copy from the group's first entry. Names are
+    /// not repeated: they went out with the first entry, if at all.
+    fn copied_from(first: &FileListEntry) -> Self {
+        Self {
+            size: first.size,
+            mtime: first.mtime,
+            mtime_nsec: first.mtime_nsec,
+            mode: first.mode,
+            uid: first.uid,
+            uid_name: None,
+            gid: first.gid,
+            gid_name: None,
+            link_target: first.link_target.clone(),
+            checksum: first.checksum.clone(),
+        }
+    }
+}
+
+/// Steps 3-9 of `recv_file_entry`, starting at `cursor`. Returns the
+/// body and the cursor past it.
+fn decode_entry_body(
+    buf: &[u8],
+    mut cursor: usize,
+    flags: u32,
+    options: &FileListDecodeOptions,
+    previous: Option<&FileListEntry>,
+) -> Result<(EntryBody, usize), RealWireError> {
     // --- 3. Size (varlong, min_bytes=3) ------------------------------------
     let (size, consumed_size) = decode_varlong(&buf[cursor..], 3)?;
     cursor += consumed_size;
 
     // --- 4. mtime (varlong, min_bytes=4) unless XMIT_SAME_TIME -------------
     let mtime: i64 = if flags & XMIT_SAME_TIME != 0 {
-        0
+        previous.map(|p| p.mtime).unwrap_or(0)
     } else if options.protocol >= 30 {
         let (m, consumed) = decode_varlong(&buf[cursor..], 4)?;
         cursor += consumed;
@@ -1440,7 +2020,7 @@ pub fn decode_file_list_entry(
 
     // --- 6. Mode (u32 LE) unless XMIT_SAME_MODE ----------------------------
     let mode: u32 = if flags & XMIT_SAME_MODE != 0 {
-        0
+        previous.map(|p| p.mode).unwrap_or(0)
     } else {
         if cursor + 4 > buf.len() {
             return Err(RealWireError::TruncatedBuffer {
@@ -1476,6 +2056,8 @@ pub fn decode_file_list_entry(
             None
         };
         (Some(uid_raw), name)
+    } else if options.preserve_uid {
+        (previous.and_then(|p| p.uid), None)
     } else {
         (None, None)
     };
@@ -1501,30 +2083,52 @@ pub fn decode_file_list_entry(
             None
         };
         (Some(gid_raw), name)
+    } else if options.preserve_gid {
+        (previous.and_then(|p| p.gid), None)
     } else {
         (None, None)
     };
 
-    // --- 9. Checksum (always_checksum active) ------------------------------
-    let checksum = if options.always_checksum && options.csum_len > 0 {
-        if cursor + options.csum_len > buf.len() {
-            return Err(RealWireError::TruncatedBuffer {
-                at: "flist_checksum",
-                needed: options.csum_len,
-                available: buf.len().saturating_sub(cursor),
-            });
-        }
-        let v = buf[cursor..cursor + options.csum_len].to_vec();
-        cursor += options.csum_len;
-        v
+    // --- 8b. Symlink target (`-l`, varint30 length, no NUL) ---------------
+    // Device numbers would sit here too; those entries are refused
+    // instead of mis-reading the bytes that follow as the next entry.
+    let kind = mode & S_IFMT;
+    let link_target = if kind == S_IFLNK && options.preserve_links {
+        let (len, consumed) = decode_varint(&buf[cursor..])?;
+        cursor += consumed;
+        let len = usize::try_from(len).map_err(|_| RealWireError::InvalidNameLen {
+            declared: 0,
+            available: 0,
+        })?;
+        let target = read_utf8_slice(buf, cursor, len)?;
+        cursor += len;
+        Some(target)
     } else {
-        Vec::new()
+        None
     };
+    if kind != 0 && kind != S_IFREG && kind != S_IFDIR && link_target.is_none() {
+        return Err(RealWireError::UnsupportedFileType { mode });
+    }
+
+    // --- 9. Checksum (always_checksum active, regular files only) ----------
+    let checksum =
+        if options.always_checksum && options.csum_len > 0 && flist_mode_carries_checksum(mode) {
+            if cursor + options.csum_len > buf.len() {
+                return Err(RealWireError::TruncatedBuffer {
+                    at: "flist_checksum",
+                    needed: options.csum_len,
+                    available: buf.len().saturating_sub(cursor),
+                });
+            }
+            let v = buf[cursor..cursor + options.csum_len].to_vec();
+            cursor += options.csum_len;
+            v
+        } else {
+            Vec::new()
+        };
 
     Ok((
-        FileListDecodeOutcome::Entry(FileListEntry {
-            flags,
-            path,
+        EntryBody {
             size,
             mtime,
             mtime_nsec,
@@ -1533,8 +2137,9 @@ pub fn decode_file_list_entry(
             uid_name,
             gid,
             gid_name,
+            link_target,
             checksum,
-        }),
+        },
         cursor,
     ))
 }
@@ -1622,6 +2227,52 @@ pub fn encode_file_list_entry(entry: &FileListEntry, options: &FileListDecodeOpt
     }
     out.extend_from_slice(suffix);
 
+    // --- 2b. Hard-link group (protocol 30+, `-H`) ------------------------
+    // A follower of an entry in the same list stops after its leader's
+    // ndx (`send_file_entry`'s `goto the_end`).
+    let mut same_list_follower = false;
+    if options.preserve_hard_links && options.protocol >= 30 {
+        if let Some(HardlinkRef::Follower { first_ndx }) = entry.hardlink {
+            out.extend_from_slice(&encode_varint(first_ndx));
+            same_list_follower = options
+                .scope
+                .is_some_and(|scope| first_ndx >= scope.ndx_start);
+        }
+    }
+    if !same_list_follower {
+        encode_entry_body(&mut out, entry, options);
+    }
+
+    // --- 10. ACL and xattr trailers (`-A`, `-X`) -------------------------
+    let tables = options.scope.map(|scope| scope.tables);
+    if options.preserve_acls && entry.mode & S_IFMT != S_IFLNK {
+        let acl = entry.acl.clone().unwrap_or_default();
+        encode_rsync_acl(
+            &mut out,
+            &acl.access,
+            tables.map_or(&[][..], |t| t.access_acls.as_slice()),
+        );
+        if entry.mode & S_IFMT == S_IFDIR {
+            encode_rsync_acl(
+                &mut out,
+                &acl.default.unwrap_or_default(),
+                tables.map_or(&[][..], |t| t.default_acls.as_slice()),
+            );
+        }
+    }
+    if options.preserve_xattrs {
+        encode_xattr_set(
+            &mut out,
+            entry.xattrs.as_deref().unwrap_or_default(),
+            tables.map_or(&[][..], |t| t.xattr_sets.as_slice()),
+        );
+    }
+
+    out
+}
+
+/// Steps 3-9 of `send_file_entry`: size through checksum.
+fn encode_entry_body(out: &mut Vec<u8>, entry: &FileListEntry, options: &FileListDecodeOptions) {
     // --- 3. Size (varlong, min_bytes=3) ----------------------------------
     out.extend_from_slice(&encode_varlong(entry.size, 3));
 
@@ -1679,8 +2330,15 @@ pub fn encode_file_list_entry(entry: &FileListEntry, options: &FileListDecodeOpt
         }
     }
 
-    // --- 9. Checksum (always_checksum) ------------------------------------
-    if options.always_checksum && options.csum_len > 0 {
+    // --- 8b. Symlink target (`-l`) ---------------------------------------
+    if options.preserve_links && entry.mode & S_IFMT == S_IFLNK {
+        let target = entry.link_target.as_deref().unwrap_or("");
+        out.extend_from_slice(&encode_varint(target.len() as i32));
+        out.extend_from_slice(target.as_bytes());
+    }
+
+    // --- 9. Checksum (always_checksum, regular files only) ----------------
+    if options.always_checksum && options.csum_len > 0 && flist_mode_carries_checksum(entry.mode) {
         assert_eq!(
             entry.checksum.len(),
             options.csum_len,
@@ -1690,8 +2348,87 @@ pub fn encode_file_list_entry(entry: &FileListEntry, options: &FileListDecodeOpt
         );
         out.extend_from_slice(&entry.checksum);
     }
+}
 
-    out
+/// `send_rsync_acl`: `ndx + 1` of an equal ACL already sent, or `0` and
+/// the literal ACL.
+fn encode_rsync_acl(out: &mut Vec<u8>, acl: &RsyncAcl, known: &[RsyncAcl]) {
+    if let Some(ndx) = known.iter().position(|k| k == acl) {
+        out.extend_from_slice(&encode_varint(ndx as i32 + 1));
+        return;
+    }
+    out.extend_from_slice(&encode_varint(0));
+    let objects = [
+        (XMIT_ACL_USER_OBJ, acl.user_obj),
+        (XMIT_ACL_GROUP_OBJ, acl.group_obj),
+        (XMIT_ACL_MASK_OBJ, acl.mask_obj),
+        (XMIT_ACL_OTHER_OBJ, acl.other_obj),
+    ];
+    let mut flags = objects
+        .iter()
+        .filter(|(_, bits)| bits.is_some())
+        .fold(0u8, |flags, (bit, _)| flags | bit);
+    if !acl.names.is_empty() {
+        flags |= XMIT_ACL_NAME_LIST;
+    }
+    out.push(flags);
+    for bits in objects.iter().filter_map(|(_, bits)| *bits) {
+        out.extend_from_slice(&encode_varint(i32::from(bits)));
+    }
+    if acl.names.is_empty() {
+        return;
+    }
+    out.extend_from_slice(&encode_varint(acl.names.len() as i32));
+    for ida in &acl.names {
+        let mut xbits = u32::from(ida.access) << 2;
+        if ida.is_user {
+            xbits |= XFLAG_ACL_NAME_IS_USER;
+        }
+        out.extend_from_slice(&encode_varint(ida.id as i32));
+        match &ida.name {
+            Some(name) => {
+                assert!(
+                    name.len() <= u8::MAX as usize,
+                    "ACL name length {} exceeds u8 wire encoding",
+                    name.len()
+                );
+                out.extend_from_slice(&encode_varint((xbits | XFLAG_ACL_NAME_FOLLOWS) as i32));
+                out.push(name.len() as u8);
+                out.extend_from_slice(name.as_bytes());
+            }
+            None => out.extend_from_slice(&encode_varint(xbits as i32)),
+        }
+    }
+}
+
+/// `send_xattr`: `ndx + 1` of an equal set already sent, or `0` and the
+/// literal set. Values past `XATTR_MAX_FULL_DATUM` go out as their MD5.
+fn encode_xattr_set(out: &mut Vec<u8>, items: &[XattrItem], known: &[Vec<XattrItem>]) {
+    use md5::{Digest, Md5};
+
+    if let Some(ndx) = known.iter().position(|k| k.as_slice() == items) {
+        out.extend_from_slice(&encode_varint(ndx as i32 + 1));
+        return;
+    }
+    out.extend_from_slice(&encode_varint(0));
+    out.extend_from_slice(&encode_varint(items.len() as i32));
+    for item in items {
+        let datum_len = match &item.value {
+            XattrValue::Full(value) => value.len(),
+            XattrValue::Abbrev { len, .. } => *len,
+        };
+        out.extend_from_slice(&encode_varint(item.name.len() as i32 + 1));
+        out.extend_from_slice(&encode_varint(datum_len as i32));
+        out.extend_from_slice(item.name.as_bytes());
+        out.push(0);
+        match &item.value {
+            XattrValue::Full(value) if value.len() > XATTR_MAX_FULL_DATUM => {
+                out.extend_from_slice(&Md5::digest(value));
+            }
+            XattrValue::Full(value) => out.extend_from_slice(value),
+            XattrValue::Abbrev { digest, .. } => out.extend_from_slice(digest),
+        }
+    }
 }
 
 /// Encode a file-list terminator. In `xfer_flags_as_varint` mode this
@@ -1800,6 +2537,33 @@ pub struct SumHead {
     pub remainder_length: i32,
 }
 
+impl SumHead {
+    /// Length of the basis file the head describes, as
+    /// `sender.c::receive_sums` derives `flength`. In `--append` mode
+    /// this is where the receiver's copy ends and the new data starts.
+    pub fn basis_length(&self) -> u64 {
+        if self.count <= 0 || self.block_length <= 0 {
+            return 0;
+        }
+        let full = self.count as u64 * self.block_length as u64;
+        if self.remainder_length > 0 {
+            full - (self.block_length - self.remainder_length) as u64
+        } else {
+            full
+        }
+    }
+
+    /// Length of block `index`: `remainder_length` for the last block
+    /// when the file does not end on a block boundary.
+    pub fn block_len(&self, index: u32) -> usize {
+        if self.remainder_length > 0 && i64::from(index) == i64::from(self.count) - 1 {
+            self.remainder_length as usize
+        } else {
+            self.block_length.max(0) as usize
+        }
+    }
+}
+
 /// Decoded signature block: 4-byte LE rolling checksum + `strong_len`
 /// bytes of strong checksum.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -1979,6 +2743,77 @@ pub fn encode_item_flags(flags: u16) -> [u8; 2] {
     flags.to_le_bytes()
 }
 
+/// `ITEM_*` bits of the iflags shortint (`rsync.h`). Only the bits the
+/// native driver reacts to are spelled out.
+pub const ITEM_REPORT_CHANGE: u16 = 1 << 1;
+/// `-X`: abbreviated xattr numbers (generator) or their values
+/// (sender) follow the iflags and the alternate name.
+pub const ITEM_REPORT_XATTR: u16 = 1 << 8;
+/// A one-byte `fnamecmp_type` follows the iflags.
+pub const ITEM_BASIS_TYPE_FOLLOWS: u16 = 1 << 11;
+/// A `write_vstring` alternate name follows the iflags (and the basis
+/// type byte, when both are set).
+pub const ITEM_XNAME_FOLLOWS: u16 = 1 << 12;
+pub const ITEM_IS_NEW: u16 = 1 << 13;
+pub const ITEM_LOCAL_CHANGE: u16 = 1 << 14;
+/// The generator asks for file data: `sum_head` + blocks follow on the
+/// generator side, `sum_head` echo + delta tokens on the sender side.
+pub const ITEM_TRANSFER: u16 = 1 << 15;
+
+/// `fnamecmp_type` byte after `ITEM_BASIS_TYPE_FOLLOWS`: which file the
+/// generator used as delta basis (`rsync.h`). `FNAMECMP_FNAME` is the
+/// implicit default and never needs the byte.
+pub const FNAMECMP_FNAME: u8 = 0x80;
+pub const FNAMECMP_PARTIAL_DIR: u8 = 0x81;
+pub const FNAMECMP_BACKUP: u8 = 0x82;
+pub const FNAMECMP_FUZZY: u8 = 0x83;
+
+/// Decode a `write_vstring`: one length byte, or two when the high bit
+/// of the first is set (`((b0 & 0x7F) << 8) + b1`), then the raw bytes.
+pub fn decode_vstring(buf: &[u8]) -> Result<(Vec<u8>, usize), RealWireError> {
+    let Some(&first) = buf.first() else {
+        return Err(RealWireError::TruncatedBuffer {
+            at: "vstring_len",
+            needed: 1,
+            available: 0,
+        });
+    };
+    let (len, header) = if first & 0x80 != 0 {
+        if buf.len() < 2 {
+            return Err(RealWireError::TruncatedBuffer {
+                at: "vstring_len_ext",
+                needed: 2,
+                available: buf.len(),
+            });
+        }
+        ((usize::from(first & 0x7F) << 8) + usize::from(buf[1]), 2)
+    } else {
+        (usize::from(first), 1)
+    };
+    if buf.len() < header + len {
+        return Err(RealWireError::TruncatedBuffer {
+            at: "vstring_body",
+            needed: header + len,
+            available: buf.len(),
+        });
+    }
+    Ok((buf[header..header + len].to_vec(), header + len))
+}
+
+/// Encode a `write_vstring`. Panics past the 15-bit length budget,
+/// which rsync itself rejects with `overflow_exit`.
+pub fn encode_vstring(bytes: &[u8]) -> Vec<u8> {
+    let len = bytes.len();
+    assert!(len <= 0x7FFF, "vstring length {len} exceeds 0x7FFF");
+    let mut out = Vec::with_capacity(len + 2);
+    if len > 0x7F {
+        out.push(((len >> 8) as u8) | 0x80);
+    }
+    out.push((len & 0xFF) as u8);
+    out.extend_from_slice(bytes);
+    out
+}
+
 /// Decode a `write_sum_head`: four int32 LE fields with bounds
 /// validation matching `io.c::read_sum_head` (count >= 0, 0 <= blength
 /// <= MAX_BLOCK_SIZE, 0 <= s2length <= MAX_DIGEST_LEN, 0 <= remainder
@@ -2109,15 +2944,20 @@ pub fn encode_file_checksum(bytes: &[u8]) -> Vec<u8> {
 // record (a self-contained zlib stream or a self-contained zstd frame).
 // The outer tag parsing is bit-identical.
 //
-// The decoder below deals with the **compressed outer framing** only -
-// appropriate for the Strada C frozen oracle (proto 31 + zstd negotiated via
-// `CF_VARINT_FLIST_FLAGS + negotiate_the_strings`). The payload inside each
-// `Literal` is returned as opaque `compressed_payload: Vec<u8>`; real zstd
-// decoding is performed downstream in the driver (`delta_transport_impl`)
-// where the decompressor owns window state across frames. The
-// `simple_send_token` (uncompressed) shape is intentionally not implemented
-// here: it is unreachable via the frozen oracle and would require a
-// separate capture lane before we can wire it.
+// The decoder below handles both framings, picked by the `TokenFormat`
+// carried in `DeltaStreamState`: the compressed outer framing whenever a
+// compressor was negotiated (the Strada C frozen oracle elects zstd), the
+// simple one when the negotiation settled on `none`. The payload inside
+// each `Literal` is returned as opaque `compressed_payload: Vec<u8>`;
+// decoding is performed downstream in the driver through
+// `compression::LiteralDecoder`, which owns the codec state across
+// records and files.
+//
+// Simple framing (`simple_send_token` / `simple_recv_token`): one int32 LE
+// per record. `n > 0` is followed by `n` raw literal bytes (the sender
+// chunks at `CHUNK_SIZE`, 32 KiB), `n < 0` is the single-block match
+// `-(n + 1)`, `0` ends the file. There are no runs: a run of matched
+// blocks travels as one record per block.
 //
 // After the tag stream a single `END_FLAG=0x00` marks end-of-tokens for the
 // current file. Immediately after the END_FLAG the sender writes the
@@ -2172,26 +3012,61 @@ pub enum DeltaOp {
     Literal { compressed_payload: Vec<u8> },
 }
 
+/// Largest literal record of the simple framing: `token.c::CHUNK_SIZE`.
+pub const SIMPLE_TOKEN_CHUNK_LEN: usize = 32 * 1024;
+
+/// Outer framing of a delta stream. rsync picks it from the negotiated
+/// compressor: `simple_send_token` for `none`, the tagged framing for
+/// zstd / lz4 / zlibx / zlib.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TokenFormat {
+    #[default]
+    Compressed,
+    Simple,
+}
+
+impl TokenFormat {
+    /// Largest payload one `DeltaOp::Literal` may carry in this framing.
+    pub fn max_literal_len(self) -> usize {
+        match self {
+            TokenFormat::Compressed => MAX_DELTA_LITERAL_LEN,
+            TokenFormat::Simple => SIMPLE_TOKEN_CHUNK_LEN,
+        }
+    }
+}
+
 /// State carried between successive `decode_delta_op` calls on the **same**
 /// file. Tracks the "last_run_end" baseline used to interpret relative
-/// tokens (`TOKEN_REL` / `TOKENRUN_REL`).
+/// tokens (`TOKEN_REL` / `TOKENRUN_REL`) and the framing in use.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct DeltaStreamState {
     /// The token index one past the end of the most recently decoded
     /// CopyRun. Starts at 0 per `token.c::send_deflated_token` (static
     /// `last_run_end = 0` after token_init).
     last_run_end: i32,
+    format: TokenFormat,
 }
 
 impl DeltaStreamState {
     pub fn new() -> Self {
-        Self { last_run_end: 0 }
+        Self::with_format(TokenFormat::Compressed)
+    }
+
+    pub fn with_format(format: TokenFormat) -> Self {
+        Self {
+            last_run_end: 0,
+            format,
+        }
     }
 
     /// Current last_run_end baseline. White-box access for tests.
     pub fn last_run_end(&self) -> i32 {
         self.last_run_end
     }
+
+    pub fn format(&self) -> TokenFormat {
+        self.format
+    }
 }
 
 /// Outcome of a single `decode_delta_op` call.
@@ -2231,6 +3106,9 @@ pub fn decode_delta_op(
             available: 0,
         });
     }
+    if state.format == TokenFormat::Simple {
+        return decode_simple_token(buf);
+    }
     let tag = buf[0];
 
     // END_FLAG: single byte, no state mutation.
@@ -2365,6 +3243,52 @@ pub fn decode_delta_op(
     })
 }
 
+/// `simple_recv_token`: one int32 LE record, see the section header.
+fn decode_simple_token(buf: &[u8]) -> Result<(DeltaOpOutcome, usize), RealWireError> {
+    if buf.len() < 4 {
+        return Err(RealWireError::DeltaTokenTruncated {
+            at: "simple_record",
+            needed: 4,
+            available: buf.len(),
+        });
+    }
+    let n = i32::from_le_bytes(buf[0..4].try_into().unwrap());
+    if n == 0 {
+        return Ok((DeltaOpOutcome::EndFlag, 4));
+    }
+    if n < 0 {
+        // -(n + 1) without overflowing on i32::MIN.
+        let token = -(n + 1);
+        return Ok((
+            DeltaOpOutcome::Op(DeltaOp::CopyRun {
+                start_token_index: token,
+                run_length: 1,
+            }),
+            4,
+        ));
+    }
+    let len = n as usize;
+    if len > SIMPLE_TOKEN_CHUNK_LEN {
+        return Err(RealWireError::DeltaLiteralTooLong {
+            len,
+            max: SIMPLE_TOKEN_CHUNK_LEN,
+        });
+    }
+    if buf.len() < 4 + len {
+        return Err(RealWireError::DeltaTokenTruncated {
+            at: "simple_payload",
+            needed: len,
+            available: buf.len() - 4,
+        });
+    }
+    Ok((
+        DeltaOpOutcome::Op(DeltaOp::Literal {
+            compressed_payload: buf[4..4 + len].to_vec(),
+        }),
+        4 + len,
+    ))
+}
+
 /// Decode the file-level strong checksum that follows `END_FLAG`.
 /// The length is not encoded on the wire: it comes from the earlier
 /// algorithm negotiation (commonly 16 bytes for MD5 / xxh128, 20 for
@@ -2400,6 +3324,9 @@ pub fn decode_file_checksum(buf: &[u8], len: usize) -> Result<(Vec<u8>, usize),
 /// `MAX_DELTA_LITERAL_LEN`: both are caller programming errors
 /// (rsync never emits them; the decoder rejects them).
 pub fn encode_delta_op(op: &DeltaOp, state: &mut DeltaStreamState) -> Vec<u8> {
+    if state.format == TokenFormat::Simple {
+        return encode_simple_token(op);
+    }
     match op {
         DeltaOp::Literal { compressed_payload } => {
             let len = compressed_payload.len();
@@ -2452,16 +3379,61 @@ pub fn encode_delta_op(op: &DeltaOp, state: &mut DeltaStreamState) -> Vec<u8> {
     }
 }
 
+/// `simple_send_token`: literals as `n` + raw bytes, each matched
+/// block as `-(token + 1)`. Same panics as `encode_delta_op`, with
+/// `SIMPLE_TOKEN_CHUNK_LEN` as the literal cap.
+fn encode_simple_token(op: &DeltaOp) -> Vec<u8> {
+    match op {
+        DeltaOp::Literal { compressed_payload } => {
+            let len = compressed_payload.len();
+            assert!(
+                (1..=SIMPLE_TOKEN_CHUNK_LEN).contains(&len),
+                "Literal payload length {} outside valid 1..={} range",
+                len,
+                SIMPLE_TOKEN_CHUNK_LEN
+            );
+            let mut out = Vec::with_capacity(4 + len);
+            out.extend_from_slice(&(len as i32).to_le_bytes());
+            out.extend_from_slice(compressed_payload);
+            out
+        }
+        DeltaOp::CopyRun {
+            start_token_index,
+            run_length,
+        } => {
+            let mut out = Vec::with_capacity(4 * usize::from(*run_length));
+            for k in 0..i32::from(*run_length) {
+                let token = start_token_index.wrapping_add(k);
+                out.extend_from_slice(&token.wrapping_neg().wrapping_sub(1).to_le_bytes());
+            }
+            out
+        }
+    }
+}
+
+/// End-of-tokens marker of `format`: `END_FLAG` or a zero int32.
+pub fn encode_delta_end(format: TokenFormat) -> &'static [u8] {
+    match format {
+        TokenFormat::Compressed => &[TOKEN_END_FLAG],
+        TokenFormat::Simple => &[0, 0, 0, 0],
+    }
+}
+
 /// Encode a complete delta stream: every op via `encode_delta_op`,
 /// then the `END_FLAG` sentinel, then the raw `file_checksum` bytes.
 /// Symmetric to `decode_delta_stream`.
 pub fn encode_delta_stream(report: &DeltaStreamReport) -> Vec<u8> {
-    let mut state = DeltaStreamState::new();
-    let mut out = Vec::with_capacity(report.ops.len() * 4 + 1 + report.file_checksum.len());
+    encode_delta_stream_with(report, TokenFormat::Compressed)
+}
+
+/// [`encode_delta_stream`] in an explicit framing.
+pub fn encode_delta_stream_with(report: &DeltaStreamReport, format: TokenFormat) -> Vec<u8> {
+    let mut state = DeltaStreamState::with_format(format);
+    let mut out = Vec::with_capacity(report.ops.len() * 4 + 4 + report.file_checksum.len());
     for op in &report.ops {
         out.extend_from_slice(&encode_delta_op(op, &mut state));
     }
-    out.push(TOKEN_END_FLAG);
+    out.extend_from_slice(encode_delta_end(format));
     out.extend_from_slice(&report.file_checksum);
     out
 }
@@ -2476,7 +3448,17 @@ pub fn decode_delta_stream(
     checksum_len: usize,
     sum_head_count: Option<i32>,
 ) -> Result<(DeltaStreamReport, usize), RealWireError> {
-    let mut state = DeltaStreamState::new();
+    decode_delta_stream_with(buf, checksum_len, sum_head_count, TokenFormat::Compressed)
+}
+
+/// [`decode_delta_stream`] in an explicit framing.
+pub fn decode_delta_stream_with(
+    buf: &[u8],
+    checksum_len: usize,
+    sum_head_count: Option<i32>,
+    format: TokenFormat,
+) -> Result<(DeltaStreamReport, usize), RealWireError> {
+    let mut state = DeltaStreamState::with_format(format);
     let mut cursor = 0usize;
     let mut ops: Vec<DeltaOp> = Vec::new();
 
@@ -2552,37 +3534,8 @@ pub fn decompress_zstd_literal_stream(payloads: &[&[u8]]) -> Result<Vec<u8>, Rea
     if total == 0 {
         return Ok(Vec::new());
     }
-
-    use zstd::zstd_safe::{DCtx, InBuffer, OutBuffer};
-
-    let mut ctx = DCtx::create();
-    let mut out: Vec<u8> = Vec::new();
-    // Pre-allocate an output staging buffer sized to one zstd stream
-    // block. Each call to `decompress_stream` writes up to this many
-    // bytes; we copy the produced bytes into `out` after each call.
-    let staging_capacity = DCtx::out_size().max(1 << 14);
-    let mut staging: Vec<u8> = vec![0u8; staging_capacity];
-
-    for payload in payloads {
-        if payload.is_empty() {
-            continue;
-        }
-        let mut input = InBuffer::around(payload);
-        // Loop until the current payload is fully consumed: each
-        // `decompress_stream` call may produce 0 to `staging_capacity`
-        // output bytes depending on how much of the frame is ready.
-        while input.pos < payload.len() {
-            let mut output = OutBuffer::around(&mut staging[..]);
-            ctx.decompress_stream(&mut output, &mut input)
-                .map_err(|code| {
-                    let reason = zstd::zstd_safe::get_error_name(code).to_string();
-                    RealWireError::ZstdDecompressionFailed { reason }
-                })?;
-            out.extend_from_slice(output.as_slice());
-        }
-    }
-
-    Ok(out)
+    let per_payload = ZstdLiteralDecompressor::new().decompress_payloads(payloads)?;
+    Ok(per_payload.concat())
 }
 
 /// Decompress a sequence of compressed literal payloads in wire order
@@ -2602,31 +3555,84 @@ pub fn decompress_zstd_literal_stream(payloads: &[&[u8]]) -> Result<Vec<u8>, Rea
 pub fn decompress_zstd_literal_stream_boundaries(
     payloads: &[&[u8]],
 ) -> Result<Vec<Vec<u8>>, RealWireError> {
-    use zstd::zstd_safe::{DCtx, InBuffer, OutBuffer};
-
-    let mut ctx = DCtx::create();
-    let staging_capacity = DCtx::out_size().max(1 << 14);
-    let mut staging: Vec<u8> = vec![0u8; staging_capacity];
-    let mut results: Vec<Vec<u8>> = Vec::with_capacity(payloads.len());
-
-    for payload in payloads {
-        let mut this_out: Vec<u8> = Vec::new();
-        if !payload.is_empty() {
-            let mut input = InBuffer::around(payload);
-            while input.pos < payload.len() {
-                let mut output = OutBuffer::around(&mut staging[..]);
-                ctx.decompress_stream(&mut output, &mut input)
-                    .map_err(|code| {
-                        let reason = zstd::zstd_safe::get_error_name(code).to_string();
-                        RealWireError::ZstdDecompressionFailed { reason }
-                    })?;
-                this_out.extend_from_slice(output.as_slice());
+    ZstdLiteralDecompressor::new().decompress_payloads(payloads)
+}
+
+/// Session-wide zstd decoder for `DEFLATED_DATA` literals. Owns the
+/// `DCtx` that `recv_zstd_token` keeps in a static for the whole
+/// transfer: a multi-file session MUST push every file's literals
+/// through the same instance, because the sender's `CCtx` is never
+/// reset between files either.
+pub struct ZstdLiteralDecompressor {
+    ctx: zstd::zstd_safe::DCtx<'static>,
+    staging: Vec<u8>,
+}
+
+impl ZstdLiteralDecompressor {
+    pub fn new() -> Self {
+        use zstd::zstd_safe::DCtx;
+        let staging_capacity = DCtx::out_size().max(1 << 14);
+        Self {
+            ctx: DCtx::create(),
+            staging: vec![0u8; staging_capacity],
+        }
+    }
+
+    /// Decompress `payloads` in wire order, one output per input
+    /// (empty inputs map to empty outputs). Context state carries over
+    /// to the next call.
+    pub fn decompress_payloads(
+        &mut self,
+        payloads: &[&[u8]],
+    ) -> Result<Vec<Vec<u8>>, RealWireError> {
+        let mut results: Vec<Vec<u8>> = Vec::with_capacity(payloads.len());
+        for payload in payloads {
+            let mut this_out: Vec<u8> = Vec::new();
+            self.decompress_into(payload, &mut this_out)?;
+            results.push(this_out);
+        }
+        Ok(results)
+    }
+
+    /// Decompress one payload and append the bytes it releases to `out`.
+    /// A record cut mid-block releases what the context can decode so
+    /// far; the rest follows with the next record of the run.
+    pub fn decompress_into(
+        &mut self,
+        payload: &[u8],
+        out: &mut Vec<u8>,
+    ) -> Result<(), RealWireError> {
+        use zstd::zstd_safe::{InBuffer, OutBuffer};
+
+        if payload.is_empty() {
+            return Ok(());
+        }
+        let mut input = InBuffer::around(payload);
+        // Loop until the payload is fully consumed and the last call
+        // left room in `staging`: each `decompress_stream` call may
+        // produce 0 to `staging.len()` output bytes depending on how much
+        // of the frame is ready, and a full buffer may hide more.
+        loop {
+            let mut output = OutBuffer::around(&mut self.staging[..]);
+            self.ctx
+                .decompress_stream(&mut output, &mut input)
+                .map_err(|code| {
+                    let reason = zstd::zstd_safe::get_error_name(code).to_string();
+                    RealWireError::ZstdDecompressionFailed { reason }
+                })?;
+            let produced = output.pos();
+            out.extend_from_slice(output.as_slice());
+            if input.pos >= payload.len() && produced < self.staging.len() {
+                return Ok(());
             }
         }
-        results.push(this_out);
     }
+}
 
-    Ok(results)
+impl Default for ZstdLiteralDecompressor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 // ----------------------------------------------------------------------------
@@ -2661,59 +3667,110 @@ pub fn decompress_zstd_literal_stream_boundaries(
 ///
 /// Available only when `aerorsync` is enabled.
 pub fn compress_zstd_literal_stream(payloads: &[&[u8]]) -> Result<Vec<Vec<u8>>, RealWireError> {
-    use zstd::zstd_safe::zstd_sys::ZSTD_EndDirective;
-    use zstd::zstd_safe::{CCtx, CParameter, InBuffer, OutBuffer};
-
-    let mut ctx = CCtx::create();
-    // Match rsync's negotiated default level. `send_zstd_token` honours
-    // `--compress-level` via `ZSTD_c_compressionLevel` set in `setup_zstd`
-    // (token.c:608+). Default 3 is the rsync default for `--zstd` without
-    // an explicit level. Round-trip semantics are insensitive to the
-    // exact level: any level decodes via the same DCtx loop.
-    ctx.set_parameter(CParameter::CompressionLevel(3))
-        .map_err(|code| RealWireError::ZstdDecompressionFailed {
-            reason: format!(
-                "set CompressionLevel: {}",
-                zstd::zstd_safe::get_error_name(code)
-            ),
-        })?;
+    ZstdLiteralCompressor::new()?.compress_payloads(payloads)
+}
 
-    let staging_capacity = CCtx::out_size().max(1 << 14);
-    let mut staging: Vec<u8> = vec![0u8; staging_capacity];
+/// Session-wide zstd encoder for `DEFLATED_DATA` literals: the
+/// persistent `CCtx` behind [`compress_zstd_literal_stream`]. Multi-file
+/// sessions keep one instance alive across files, mirroring the static
+/// `zstd_cctx` of `send_zstd_token`.
+pub struct ZstdLiteralCompressor {
+    ctx: zstd::zstd_safe::CCtx<'static>,
+    staging: Vec<u8>,
+}
 
-    let mut blobs: Vec<Vec<u8>> = Vec::with_capacity(payloads.len());
-    for payload in payloads {
-        if payload.is_empty() {
-            continue;
+impl ZstdLiteralCompressor {
+    pub fn new() -> Result<Self, RealWireError> {
+        // Default 3 is the rsync default for `--zstd` without an explicit
+        // level.
+        Self::with_level(3)
+    }
+
+    /// Encoder at an explicit `--compress-level`. `send_zstd_token`
+    /// applies it via `ZSTD_c_compressionLevel` in `setup_zstd`
+    /// (token.c:608+). Round-trip semantics are insensitive to the
+    /// exact level: any level decodes via the same DCtx loop.
+    pub fn with_level(level: i32) -> Result<Self, RealWireError> {
+        use zstd::zstd_safe::{CCtx, CParameter};
+
+        let mut ctx = CCtx::create();
+        ctx.set_parameter(CParameter::CompressionLevel(level))
+            .map_err(|code| RealWireError::ZstdDecompressionFailed {
+                reason: format!(
+                    "set CompressionLevel: {}",
+                    zstd::zstd_safe::get_error_name(code)
+                ),
+            })?;
+        let staging_capacity = CCtx::out_size().max(1 << 14);
+        Ok(Self {
+            ctx,
+            staging: vec![0u8; staging_capacity],
+        })
+    }
+
+    /// Compress `payloads` through the session context. Returns one
+    /// compressed blob per non-empty input payload, in encounter order.
+    pub fn compress_payloads(&mut self, payloads: &[&[u8]]) -> Result<Vec<Vec<u8>>, RealWireError> {
+        let mut blobs: Vec<Vec<u8>> = Vec::with_capacity(payloads.len());
+        for payload in payloads {
+            if payload.is_empty() {
+                continue;
+            }
+            let mut blob: Vec<u8> = Vec::new();
+            self.compress_into(payload, true, &mut blob)?;
+            blobs.push(blob);
         }
-        let mut blob: Vec<u8> = Vec::new();
+
+        Ok(blobs)
+    }
+
+    /// Feed `payload` through the session context and append whatever
+    /// compressed bytes are ready to `out`. With `flush` the encoder is
+    /// drained so everything fed so far becomes decodable (the
+    /// `ZSTD_e_flush` a run end gets in `send_zstd_token`); without it
+    /// zstd keeps buffering up to a block, which is how rsync compresses
+    /// the literal chunks of one run (`token == -2`) as a single stream.
+    pub fn compress_into(
+        &mut self,
+        payload: &[u8],
+        flush: bool,
+        out: &mut Vec<u8>,
+    ) -> Result<(), RealWireError> {
+        use zstd::zstd_safe::zstd_sys::ZSTD_EndDirective;
+        use zstd::zstd_safe::{InBuffer, OutBuffer};
+
         let mut input = InBuffer::around(payload);
 
         // Continue mode: feed the payload until the encoder has read
         // every byte. May or may not produce output bytes during this
         // pass (zstd buffers internally until block boundaries).
         while input.pos < payload.len() {
-            let mut output = OutBuffer::around(&mut staging[..]);
-            ctx.compress_stream2(&mut output, &mut input, ZSTD_EndDirective::ZSTD_e_continue)
+            let mut output = OutBuffer::around(&mut self.staging[..]);
+            self.ctx
+                .compress_stream2(&mut output, &mut input, ZSTD_EndDirective::ZSTD_e_continue)
                 .map_err(|code| RealWireError::ZstdDecompressionFailed {
                     reason: format!(
                         "compress_stream2 Continue: {}",
                         zstd::zstd_safe::get_error_name(code)
                     ),
                 })?;
-            blob.extend_from_slice(output.as_slice());
+            out.extend_from_slice(output.as_slice());
+        }
+        if !flush {
+            return Ok(());
         }
 
-        // Flush mode: drain the encoder so this payload's bytes land in
-        // a DEFLATED_DATA-shippable block. Loop until `compress_stream2`
+        // Flush mode: drain the encoder so the payload's bytes land in a
+        // DEFLATED_DATA-shippable block. Loop until `compress_stream2`
         // returns 0 (no more buffered bytes pending). MUST NOT use
-        // `EndDirective::End`: the receiver's `recv_zstd_token` does
-        // not expect a frame epilogue.
+        // `EndDirective::End`: the receiver's `recv_zstd_token` does not
+        // expect a frame epilogue.
         let empty: &[u8] = &[];
         loop {
             let mut empty_in = InBuffer::around(empty);
-            let mut output = OutBuffer::around(&mut staging[..]);
-            let remaining = ctx
+            let mut output = OutBuffer::around(&mut self.staging[..]);
+            let remaining = self
+                .ctx
                 .compress_stream2(&mut output, &mut empty_in, ZSTD_EndDirective::ZSTD_e_flush)
                 .map_err(|code| RealWireError::ZstdDecompressionFailed {
                     reason: format!(
@@ -2721,16 +3778,12 @@ pub fn compress_zstd_literal_stream(payloads: &[&[u8]]) -> Result<Vec<Vec<u8>>,
                         zstd::zstd_safe::get_error_name(code)
                     ),
                 })?;
-            blob.extend_from_slice(output.as_slice());
+            out.extend_from_slice(output.as_slice());
             if remaining == 0 {
-                break;
+                return Ok(());
             }
         }
-
-        blobs.push(blob);
     }
-
-    Ok(blobs)
 }
 
 // =============================================================================
@@ -3880,6 +4933,11 @@ mod tests {
             preserve_uid: false,
             preserve_gid: false,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let (outcome, consumed) = decode_file_list_entry(&buf, &opts).unwrap();
         assert_eq!(consumed, buf.len());
@@ -3919,6 +4977,11 @@ mod tests {
             preserve_uid: false,
             preserve_gid: false,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let (outcome, _) = decode_file_list_entry(&buf, &opts).unwrap();
         let entry = match outcome {
@@ -3967,6 +5030,11 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: Some("/tmp"),
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         };
         let (outcome, _consumed) = decode_file_list_entry(&buf, &opts).unwrap();
         let entry = match outcome {
@@ -4111,6 +5179,29 @@ mod tests {
         assert_eq!(head.remainder_length, 344);
         // Encoder parity.
         assert_eq!(encode_sum_head(&head), bytes);
+        // 374 full blocks + a 344-byte tail.
+        assert_eq!(head.basis_length(), 374 * 700 + 344);
+        assert_eq!(head.block_len(0), 700);
+        assert_eq!(head.block_len(374), 344);
+    }
+
+    #[test]
+    fn sum_head_basis_length_handles_exact_and_empty_files() {
+        let exact = SumHead {
+            count: 4,
+            block_length: 700,
+            checksum_length: 2,
+            remainder_length: 0,
+        };
+        assert_eq!(exact.basis_length(), 2800);
+        assert_eq!(exact.block_len(3), 700);
+        let empty = SumHead {
+            count: 0,
+            block_length: 0,
+            checksum_length: 0,
+            remainder_length: 0,
+        };
+        assert_eq!(empty.basis_length(), 0);
     }
 
     #[test]
@@ -4262,7 +5353,10 @@ mod tests {
     fn decode_delta_op_token_rel_advances_last_run_end_by_one() {
         // TOKEN_REL with rel=5 after last_run_end=10 → start=15, run=1,
         // last_run_end advances to 16.
-        let mut state = DeltaStreamState { last_run_end: 10 };
+        let mut state = DeltaStreamState {
+            last_run_end: 10,
+            ..DeltaStreamState::new()
+        };
         let tag = TOKEN_REL | 5;
         let (outcome, consumed) = decode_delta_op(&[tag], &mut state).unwrap();
         assert_eq!(
@@ -5062,6 +6156,10 @@ mod tests {
             gid: Some(1000),
             gid_name: Some("axpnet".to_string()),
             checksum: vec![0xAB; 16],
+            link_target: None,
+            hardlink: None,
+            acl: None,
+            xattrs: None,
         }
     }
 
@@ -5071,6 +6169,50 @@ mod tests {
         assert_flist_entry_round_trip(baseline_entry(), &opts);
     }
 
+    #[test]
+    fn encode_file_list_entry_round_trip_directory_has_no_checksum() {
+        let mut entry = baseline_entry();
+        entry.path = "subdir".to_string();
+        entry.size = 0;
+        entry.mode = 0o040_755;
+        entry.checksum = Vec::new();
+        let opts = frozen_oracle_options_for_test(None);
+        assert!(opts.always_checksum);
+        assert_flist_entry_round_trip(entry, &opts);
+    }
+
+    #[test]
+    fn decode_file_list_entry_after_resolves_same_fields_from_previous() {
+        let first = baseline_entry();
+        let mut second = baseline_entry();
+        second.flags = XMIT_SAME_NAME
+            | XMIT_SAME_TIME
+            | XMIT_SAME_MODE
+            | XMIT_SAME_UID
+            | XMIT_SAME_GID
+            | XMIT_MOD_NSEC;
+        second.path = "upload.bin.part2".to_string();
+        second.uid_name = None;
+        second.gid_name = None;
+        let bytes =
+            encode_file_list_entry(&second, &frozen_oracle_options_for_test(Some("upload.bin")));
+
+        let opts = frozen_oracle_options_for_test(None);
+        let (outcome, consumed) =
+            decode_file_list_entry_after(&bytes, &opts, Some(&first)).unwrap();
+        assert_eq!(consumed, bytes.len());
+        match outcome {
+            FileListDecodeOutcome::Entry(decoded) => {
+                assert_eq!(decoded.path, "upload.bin.part2");
+                assert_eq!(decoded.mtime, first.mtime);
+                assert_eq!(decoded.mode, first.mode);
+                assert_eq!(decoded.uid, first.uid);
+                assert_eq!(decoded.gid, first.gid);
+            }
+            other => panic!("expected Entry, got {other:?}"),
+        }
+    }
+
     #[test]
     fn encode_file_list_entry_round_trip_with_long_name() {
         let mut entry = baseline_entry();
@@ -5136,6 +6278,10 @@ mod tests {
             gid: None,
             gid_name: None,
             checksum: vec![0; 16],
+            link_target: None,
+            hardlink: None,
+            acl: None,
+            xattrs: None,
         };
         let mut opts = FileListDecodeOptions::frozen_oracle_default();
         opts.preserve_uid = false;
@@ -5169,6 +6315,10 @@ mod tests {
             gid: None, // gated out by SAME_GID
             gid_name: None,
             checksum: vec![],
+            link_target: None,
+            hardlink: None,
+            acl: None,
+            xattrs: None,
         };
         let mut opts = FileListDecodeOptions::frozen_oracle_default();
         opts.xfer_flags_as_varint = false;
@@ -5193,6 +6343,10 @@ mod tests {
             gid: Some(1000),
             gid_name: None,
             checksum: vec![],
+            link_target: None,
+            hardlink: None,
+            acl: None,
+            xattrs: None,
         };
         let mut opts = FileListDecodeOptions::frozen_oracle_default();
         opts.xfer_flags_as_varint = false;
@@ -5242,6 +6396,241 @@ mod tests {
         assert_eq!(suffix.len(), 45);
     }
 
+    // -------------------------------------------------------------------------
+    // Symlinks, hard links, ACLs and xattrs (`-l -H -A -X`).
+    // -------------------------------------------------------------------------
+
+    fn metadata_options_for_test<'a>(scope: Option<FlistScope<'a>>) -> FileListDecodeOptions<'a> {
+        let mut opts = FileListDecodeOptions::frozen_oracle_default();
+        opts.preserve_hard_links = true;
+        opts.preserve_acls = true;
+        opts.preserve_xattrs = true;
+        opts.scope = scope;
+        opts
+    }
+
+    fn decode_entry_for_test(bytes: &[u8], opts: &FileListDecodeOptions) -> FileListEntry {
+        let (outcome, consumed) = decode_file_list_entry(bytes, opts).expect("entry must decode");
+        assert_eq!(
+            consumed,
+            bytes.len(),
+            "decoder should consume the whole slice"
+        );
+        match outcome {
+            FileListDecodeOutcome::Entry(entry) => entry,
+            other => panic!("expected Entry, got {other:?}"),
+        }
+    }
+
+    fn sample_acl() -> EntryAcl {
+        EntryAcl {
+            access: RsyncAcl {
+                mask_obj: Some(0o5),
+                names: vec![AclIdAccess {
+                    id: 1001,
+                    is_user: true,
+                    access: 0o6,
+                    name: Some("backup".to_string()),
+                }],
+                ..RsyncAcl::default()
+            },
+            default: None,
+        }
+    }
+
+    #[test]
+    fn symlink_entry_round_trips_its_target() {
+        let mut entry = baseline_entry();
+        entry.path = "current".to_string();
+        entry.size = 7;
+        entry.mode = S_IFLNK | 0o777;
+        entry.checksum = Vec::new();
+        entry.link_target = Some("v2/data".to_string());
+        let opts = frozen_oracle_options_for_test(None);
+        let decoded = decode_entry_for_test(&encode_file_list_entry(&entry, &opts), &opts);
+        assert_eq!(decoded.link_target.as_deref(), Some("v2/data"));
+        assert!(decoded.checksum.is_empty(), "symlinks carry no checksum");
+        assert_eq!(decoded.mode, entry.mode);
+    }
+
+    #[test]
+    fn same_list_hardlink_follower_skips_its_body() {
+        let tables = FlistMetaTables::new();
+        let mut leader = baseline_entry();
+        leader.flags |= XMIT_HLINKED | XMIT_HLINK_FIRST;
+        leader.hardlink = Some(HardlinkRef::First);
+        let leaders = [leader.clone()];
+        let scope = FlistScope {
+            ndx_start: 1,
+            entries: &leaders,
+            tables: &tables,
+        };
+        let mut opts = metadata_options_for_test(Some(scope));
+        opts.preserve_acls = false;
+        opts.preserve_xattrs = false;
+
+        let mut follower = leader.clone();
+        follower.flags = XMIT_HLINKED;
+        follower.path = "upload.lnk".to_string();
+        follower.hardlink = Some(HardlinkRef::Follower { first_ndx: 1 });
+        let bytes = encode_file_list_entry(&follower, &opts);
+        // flags, name length, name, leader ndx: nothing else.
+        let mut expected = encode_varint(XMIT_HLINKED as i32);
+        expected.push(10);
+        expected.extend_from_slice(b"upload.lnk");
+        expected.extend_from_slice(&encode_varint(1));
+        assert_eq!(bytes, expected);
+
+        let decoded = decode_entry_for_test(&bytes, &opts);
+        assert_eq!(
+            decoded.hardlink,
+            Some(HardlinkRef::Follower { first_ndx: 1 })
+        );
+        assert_eq!(decoded.size, leader.size);
+        assert_eq!(decoded.mtime, leader.mtime);
+        assert_eq!(decoded.mode, leader.mode);
+        assert_eq!(decoded.checksum, leader.checksum);
+    }
+
+    #[test]
+    fn earlier_list_hardlink_follower_keeps_its_body() {
+        let tables = FlistMetaTables::new();
+        let scope = FlistScope {
+            ndx_start: 8,
+            entries: &[],
+            tables: &tables,
+        };
+        let opts = metadata_options_for_test(Some(scope));
+        let mut follower = baseline_entry();
+        follower.flags |= XMIT_HLINKED;
+        follower.hardlink = Some(HardlinkRef::Follower { first_ndx: 3 });
+        let decoded = decode_entry_for_test(&encode_file_list_entry(&follower, &opts), &opts);
+        assert_eq!(decoded.hardlink, follower.hardlink);
+        assert_eq!(decoded.size, follower.size);
+        assert_eq!(decoded.uid_name, follower.uid_name);
+    }
+
+    #[test]
+    fn same_list_follower_without_its_leader_is_rejected() {
+        let tables = FlistMetaTables::new();
+        let scope = FlistScope {
+            ndx_start: 1,
+            entries: &[],
+            tables: &tables,
+        };
+        let opts = metadata_options_for_test(Some(scope));
+        let mut bytes = encode_varint(XMIT_HLINKED as i32);
+        bytes.push(1);
+        bytes.push(b'x');
+        bytes.extend_from_slice(&encode_varint(1));
+        assert!(matches!(
+            decode_file_list_entry(&bytes, &opts),
+            Err(RealWireError::HardlinkLeaderMissing { .. })
+        ));
+    }
+
+    #[test]
+    fn acl_and_xattr_sets_become_back_references_once_sent() {
+        use md5::{Digest, Md5};
+
+        let mut entry = baseline_entry();
+        entry.acl = Some(sample_acl());
+        entry.xattrs = Some(vec![
+            XattrItem {
+                name: "user.big".to_string(),
+                value: XattrValue::Full(vec![0x42; 40]),
+            },
+            XattrItem {
+                name: "user.small".to_string(),
+                value: XattrValue::Full(b"v".to_vec()),
+            },
+        ]);
+
+        let mut sender_tables = FlistMetaTables::new();
+        let mut receiver_tables = FlistMetaTables::new();
+        let first_bytes = {
+            let opts = metadata_options_for_test(Some(FlistScope {
+                ndx_start: 1,
+                entries: &[],
+                tables: &sender_tables,
+            }));
+            encode_file_list_entry(&entry, &opts)
+        };
+        let first = {
+            let opts = metadata_options_for_test(Some(FlistScope {
+                ndx_start: 1,
+                entries: &[],
+                tables: &receiver_tables,
+            }));
+            decode_entry_for_test(&first_bytes, &opts)
+        };
+        assert_eq!(first.acl, entry.acl);
+        let xattrs = first.xattrs.clone().expect("xattrs decoded");
+        assert_eq!(xattrs[1], entry.xattrs.as_ref().unwrap()[1]);
+        let digest: [u8; XATTR_DIGEST_LEN] = Md5::digest([0x42; 40]).into();
+        assert_eq!(
+            xattrs[0].value,
+            XattrValue::Abbrev { len: 40, digest },
+            "long values travel as their MD5"
+        );
+        sender_tables.absorb(&entry);
+        receiver_tables.absorb(&first);
+
+        let mut second = entry.clone();
+        second.flags = XMIT_SAME_UID | XMIT_SAME_GID | XMIT_MOD_NSEC;
+        second.path = "again.bin".to_string();
+        let second_bytes = {
+            let opts = metadata_options_for_test(Some(FlistScope {
+                ndx_start: 1,
+                entries: &[],
+                tables: &sender_tables,
+            }));
+            encode_file_list_entry(&second, &opts)
+        };
+        // Access ACL index 0 and xattr set index 0, each sent as `ndx + 1`.
+        assert!(second_bytes.ends_with(&[0x01, 0x01]), "{second_bytes:02x?}");
+        let decoded = {
+            let opts = metadata_options_for_test(Some(FlistScope {
+                ndx_start: 1,
+                entries: &[],
+                tables: &receiver_tables,
+            }));
+            decode_file_list_entry_after(&second_bytes, &opts, Some(&first))
+                .expect("back-references decode")
+                .0
+        };
+        match decoded {
+            FileListDecodeOutcome::Entry(decoded) => {
+                assert_eq!(decoded.acl, first.acl);
+                assert_eq!(decoded.xattrs, first.xattrs);
+            }
+            other => panic!("expected Entry, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn acl_back_reference_past_the_table_is_rejected() {
+        let tables = FlistMetaTables::new();
+        let mut opts = metadata_options_for_test(Some(FlistScope {
+            ndx_start: 1,
+            entries: &[],
+            tables: &tables,
+        }));
+        opts.preserve_xattrs = false;
+        let plain = frozen_oracle_options_for_test(None);
+        let mut bytes = encode_file_list_entry(&baseline_entry(), &plain);
+        // Reference to access ACL 4 while none has been sent.
+        bytes.extend_from_slice(&encode_varint(5));
+        assert!(matches!(
+            decode_file_list_entry(&bytes, &opts),
+            Err(RealWireError::MetadataRefOutOfRange {
+                index: 4,
+                known: 0,
+                ..
+            })
+        ));
+    }
+
     // -------------------------------------------------------------------------
     // B.2 Step 3: Byte-level pin against the frozen oracle's first
     // MSG_DATA payload (file list entry + checksum + end-of-flist +
@@ -5273,6 +6662,10 @@ mod tests {
                 0x0c, 0x22, 0x11, 0xc6, 0xe2, 0xe7, 0xc9, 0x9c, 0x96, 0xc3, 0xfd, 0xfb, 0x51, 0x2c,
                 0x82, 0xc9,
             ],
+            link_target: None,
+            hardlink: None,
+            acl: None,
+            xattrs: None,
         }
     }
 
@@ -5288,6 +6681,11 @@ mod tests {
             preserve_uid: true,
             preserve_gid: true,
             previous_name: None,
+            preserve_links: true,
+            preserve_hard_links: false,
+            preserve_acls: false,
+            preserve_xattrs: false,
+            scope: None,
         }
     }
 
@@ -5819,6 +7217,46 @@ mod tests {
         assert_eq!(r.data_bytes_consumed(), expected);
     }
 
+    #[test]
+    fn vstring_round_trips_short_and_two_byte_lengths() {
+        for len in [0usize, 5, 0x7F, 0x80, 300] {
+            let body = vec![b'x'; len];
+            let wire = encode_vstring(&body);
+            assert_eq!(wire.len(), len + if len > 0x7F { 2 } else { 1 });
+            let (decoded, consumed) = decode_vstring(&wire).unwrap();
+            assert_eq!(decoded, body);
+            assert_eq!(consumed, wire.len());
+        }
+        let wire = encode_vstring(b"abc");
+        assert!(matches!(
+            decode_vstring(&wire[..2]),
+            Err(RealWireError::TruncatedBuffer {
+                at: "vstring_body",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn zstd_literal_contexts_persist_across_calls() {
+        let mut compressor = ZstdLiteralCompressor::new().unwrap();
+        let mut decompressor = ZstdLiteralDecompressor::new();
+        let first = compressor.compress_payloads(&[b"first literal"]).unwrap();
+        let second = compressor
+            .compress_payloads(&[b"second literal, first literal"])
+            .unwrap();
+        let first: Vec<&[u8]> = first.iter().map(Vec::as_slice).collect();
+        let second: Vec<&[u8]> = second.iter().map(Vec::as_slice).collect();
+        assert_eq!(
+            decompressor.decompress_payloads(&first).unwrap(),
+            vec![b"first literal".to_vec()]
+        );
+        assert_eq!(
+            decompressor.decompress_payloads(&second).unwrap(),
+            vec![b"second literal, first literal".to_vec()]
+        );
+    }
+
     #[test]
     fn mux_stream_reader_error_exit_zero_is_oob_not_terminal() {
         // Regression: ErrorExit with code 0 is non-terminal per events.rs
@@ -13,8 +13,14 @@
 //!     enables `--stats` on the remote command line
 //!   - download → remote runs as Sender (`--sender`) without `--stats`
 //!
-//! Flag order is fixed to match the captured shape.
+//! Flag order is fixed to match the captured shape. `TransferOptions`
+//! only ever adds to it, in the slots `options.c::server_options` uses:
+//! `H` after `l`, `A`/`X` between `p` and `r`, `S` between `c` and `z`,
+//! long options after `--stats`.
 
+use std::path::PathBuf;
+
+use crate::aerorsync::negotiation::AlgorithmPreferences;
 use crate::aerorsync::transport::RemoteExecRequest;
 use crate::aerorsync::types::SessionRole;
 
@@ -22,6 +28,13 @@ use crate::aerorsync::types::SessionRole;
 /// Spelled out: log, gid, Devices, times, perms, recursion, z (compress request),
 /// extended attribute chars `.iLsfxCIvu` (incremental + extras).
 pub const OBSERVED_COMPACT_FLAGS: &str = "-logDtprcze.iLsfxCIvu";
+/// `OBSERVED_COMPACT_FLAGS` split where `server_options` inserts the
+/// optional short flags (`H` for `--hard-links`, `A`/`X` for
+/// `--acls`/`--xattrs`, `S` for `--sparse`).
+const COMPACT_FLAGS_LINKS: &str = "-l";
+const COMPACT_FLAGS_META: &str = "ogDtp";
+const COMPACT_FLAGS_WALK: &str = "rc";
+const COMPACT_FLAGS_TAIL: &str = "ze.iLsfxCIvu";
 pub const AERORSYNC_SERVER_PROGRAM: &str = "/opt/aerorsync/bin/aerorsync_serve";
 
 /// Working directory placeholder passed to `rsync --server`.
@@ -34,6 +47,138 @@ pub enum RemoteCommandFlavor {
     AerorsyncServe,
 }
 
+/// `--append` flavour. `Append` trusts the bytes already on the
+/// receiver; `AppendVerify` folds them into the whole-file checksum.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AppendMode {
+    #[default]
+    Off,
+    Append,
+    AppendVerify,
+}
+
+/// Receiver-side transfer behaviour shared with the classic rsync
+/// command line. Every option is off by default, which keeps the
+/// captured command shape byte-identical.
+///
+/// Which side acts on an option depends on the direction: on upload the
+/// remote receiver does (so it goes on the command line), on download
+/// the local driver and writer do (so only the flags `server_options`
+/// forwards to a sender are emitted).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransferOptions {
+    /// `--delete`: remove receiver files missing from the sender's list
+    /// (delete-during for protocol 30+).
+    pub delete: bool,
+    /// `--inplace`: update the destination file directly.
+    pub inplace: bool,
+    /// `--append` / `--append-verify`. Implies in-place updates.
+    pub append: AppendMode,
+    /// `--partial-dir DIR`: keep interrupted transfers in `DIR` and use
+    /// them as basis on the next run. Ignored with `--inplace`, as rsync
+    /// does.
+    pub partial_dir: Option<String>,
+    /// `--sparse`: turn runs of zeros into holes on the receiver.
+    pub sparse: bool,
+    /// `--mkpath`: create missing parent directories of the destination.
+    pub mkpath: bool,
+    /// `--hard-links`: keep hard-linked files linked on the receiver.
+    /// Symlinks, permissions, owner and group (`-l -p -o -g`) are
+    /// always preserved and need no option.
+    pub hard_links: bool,
+    /// `--acls`: carry POSIX access and default ACLs.
+    pub acls: bool,
+    /// `--xattrs`: carry extended attributes (`user.*` only unless the
+    /// receiver runs as root).
+    pub xattrs: bool,
+    /// Checksum and compressor preferences for the preamble
+    /// negotiation, plus `--compress-level`.
+    pub algorithms: AlgorithmPreferences,
+    /// `--write-batch FILE`: keep a copy of the sender stream in `FILE`
+    /// plus a `FILE.sh` replay script. Local only, never forwarded.
+    pub write_batch: Option<PathBuf>,
+}
+
+impl TransferOptions {
+    /// `--inplace` as rsync resolves it: explicit, or implied by `--append`.
+    pub fn writes_in_place(&self) -> bool {
+        self.inplace || self.append != AppendMode::Off
+    }
+
+    /// The partial dir that is actually in effect.
+    pub fn effective_partial_dir(&self) -> Option<&str> {
+        if self.writes_in_place() {
+            None
+        } else {
+            self.partial_dir.as_deref()
+        }
+    }
+
+    /// Short-option bundle for the remote command line.
+    pub fn compact_flags(&self) -> String {
+        let mut flags = String::from(COMPACT_FLAGS_LINKS);
+        if self.hard_links {
+            flags.push('H');
+        }
+        flags.push_str(COMPACT_FLAGS_META);
+        if self.acls {
+            flags.push('A');
+        }
+        if self.xattrs {
+            flags.push('X');
+        }
+        flags.push_str(COMPACT_FLAGS_WALK);
+        if self.sparse {
+            flags.push('S');
+        }
+        flags.push_str(COMPACT_FLAGS_TAIL);
+        flags
+    }
+
+    /// Client argv of a `--read-batch` replay of this transfer: the
+    /// short bundle without the server-only tail, then the long options
+    /// a receiver acts on.
+    pub fn read_batch_args(&self) -> Vec<String> {
+        let mut flags = self.compact_flags();
+        flags.truncate(flags.len() - COMPACT_FLAGS_TAIL.len());
+        flags.push('z');
+        let mut args = vec![flags];
+        args.extend(self.long_args(SessionRole::Receiver));
+        args
+    }
+
+    /// Long options `server_options` forwards to a remote in `role`.
+    fn long_args(&self, remote_role: SessionRole) -> Vec<String> {
+        let remote_receives = remote_role == SessionRole::Receiver;
+        let mut args = Vec::new();
+        if self.delete && remote_receives {
+            args.push("--delete".to_string());
+        }
+        match self.append {
+            AppendMode::Off if self.inplace => args.push("--inplace".to_string()),
+            AppendMode::Off => {}
+            AppendMode::Append => args.push("--append".to_string()),
+            AppendMode::AppendVerify => {
+                args.push("--append".to_string());
+                args.push("--append".to_string());
+            }
+        }
+        if remote_receives {
+            if let Some(dir) = self.effective_partial_dir() {
+                args.push("--partial-dir".to_string());
+                args.push(dir.to_string());
+            }
+            if self.mkpath {
+                args.push("--mkpath".to_string());
+            }
+        }
+        if let Some(level) = self.algorithms.compress_level {
+            args.push(format!("--compress-level={level}"));
+        }
+        args
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RemoteCommandSpec {
     /// The remote role. For upload this is `Receiver`; for download `Sender`.
@@ -45,6 +190,9 @@ pub struct RemoteCommandSpec {
     pub emit_stats: bool,
     /// Which remote command shape should be emitted.
     pub flavor: RemoteCommandFlavor,
+    /// Optional rsync transfer behaviour. Only the `WrapperParity`
+    /// flavor forwards it.
+    pub options: TransferOptions,
 }
 
 impl RemoteCommandSpec {
@@ -54,6 +202,7 @@ impl RemoteCommandSpec {
             remote_target: remote_target.into(),
             emit_stats: true,
             flavor: RemoteCommandFlavor::WrapperParity,
+            options: TransferOptions::default(),
         }
     }
 
@@ -63,6 +212,7 @@ impl RemoteCommandSpec {
             remote_target: remote_target.into(),
             emit_stats: false,
             flavor: RemoteCommandFlavor::WrapperParity,
+            options: TransferOptions::default(),
         }
     }
 
@@ -72,6 +222,7 @@ impl RemoteCommandSpec {
             remote_target: remote_target.into(),
             emit_stats: true,
             flavor: RemoteCommandFlavor::AerorsyncServe,
+            options: TransferOptions::default(),
         }
     }
 
@@ -81,9 +232,15 @@ impl RemoteCommandSpec {
             remote_target: remote_target.into(),
             emit_stats: false,
             flavor: RemoteCommandFlavor::AerorsyncServe,
+            options: TransferOptions::default(),
         }
     }
 
+    pub fn with_options(mut self, options: TransferOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     /// Produce the argv for `rsync --server [--sender] <flags> [--stats] . <target>`
     /// in the exact order observed in the capture.
     pub fn to_args(&self) -> Vec<String> {
@@ -94,10 +251,11 @@ impl RemoteCommandSpec {
                 if self.remote_role == SessionRole::Sender {
                     args.push("--sender".to_string());
                 }
-                args.push(OBSERVED_COMPACT_FLAGS.to_string());
+                args.push(self.options.compact_flags());
                 if self.emit_stats {
                     args.push("--stats".to_string());
                 }
+                args.extend(self.options.long_args(self.remote_role));
                 args.push(REMOTE_WORKDIR_PLACEHOLDER.to_string());
                 args.push(self.remote_target.clone());
                 args
@@ -186,4 +344,133 @@ mod tests {
         assert_eq!(argv.first().map(String::as_str), Some("--server"));
         assert_eq!(argv.get(1).map(String::as_str), Some("--sender"));
     }
+
+    #[test]
+    fn default_options_keep_the_captured_flag_bundle() {
+        assert_eq!(
+            TransferOptions::default().compact_flags(),
+            OBSERVED_COMPACT_FLAGS
+        );
+        let argv = RemoteCommandSpec::upload("/dst/file").to_args();
+        assert_eq!(argv.len(), 5, "no long option sneaks in by default");
+    }
+
+    #[test]
+    fn upload_options_follow_server_options_order() {
+        let options = TransferOptions {
+            delete: true,
+            inplace: false,
+            append: AppendMode::Off,
+            partial_dir: Some(".rsync-partial".to_string()),
+            sparse: true,
+            mkpath: true,
+            ..TransferOptions::default()
+        };
+        let argv = RemoteCommandSpec::upload("/dst/")
+            .with_options(options)
+            .to_args();
+        assert_eq!(
+            argv,
+            [
+                "--server",
+                "-logDtprcSze.iLsfxCIvu",
+                "--stats",
+                "--delete",
+                "--partial-dir",
+                ".rsync-partial",
+                "--mkpath",
+                ".",
+                "/dst/",
+            ]
+        );
+    }
+
+    #[test]
+    fn download_options_only_forward_what_a_sender_needs() {
+        let options = TransferOptions {
+            delete: true,
+            inplace: true,
+            append: AppendMode::Off,
+            partial_dir: Some("partial".to_string()),
+            sparse: false,
+            mkpath: true,
+            ..TransferOptions::default()
+        };
+        let argv = RemoteCommandSpec::download("/src/")
+            .with_options(options)
+            .to_args();
+        assert_eq!(
+            argv,
+            [
+                "--server",
+                "--sender",
+                OBSERVED_COMPACT_FLAGS,
+                "--inplace",
+                ".",
+                "/src/"
+            ]
+        );
+    }
+
+    #[test]
+    fn compress_level_is_forwarded_to_either_role() {
+        let options = TransferOptions {
+            algorithms: AlgorithmPreferences {
+                compress_level: Some(9),
+                ..AlgorithmPreferences::default()
+            },
+            ..TransferOptions::default()
+        };
+        for spec in [
+            RemoteCommandSpec::upload("/dst/"),
+            RemoteCommandSpec::download("/src/"),
+        ] {
+            let argv = spec.with_options(options.clone()).to_args();
+            assert!(argv.iter().any(|a| a == "--compress-level=9"), "{argv:?}");
+        }
+    }
+
+    #[test]
+    fn metadata_flags_take_their_server_options_slots() {
+        let options = TransferOptions {
+            hard_links: true,
+            acls: true,
+            xattrs: true,
+            sparse: true,
+            ..TransferOptions::default()
+        };
+        assert_eq!(options.compact_flags(), "-lHogDtpAXrcSze.iLsfxCIvu");
+        let xattrs_only = TransferOptions {
+            xattrs: true,
+            ..TransferOptions::default()
+        };
+        assert_eq!(xattrs_only.compact_flags(), "-logDtpXrcze.iLsfxCIvu");
+    }
+
+    #[test]
+    fn append_verify_is_a_doubled_append_and_drops_partial_dir() {
+        let options = TransferOptions {
+            append: AppendMode::AppendVerify,
+            inplace: true,
+            partial_dir: Some("partial".to_string()),
+            ..TransferOptions::default()
+        };
+        assert!(options.writes_in_place());
+        assert_eq!(options.effective_partial_dir(), None);
+        let argv = RemoteCommandSpec::upload("/dst/file")
+            .with_options(options)
+            .to_args();
+        assert_eq!(
+            argv,
+            [
+                "--server",
+                OBSERVED_COMPACT_FLAGS,
+                "--stats",
+                "--append",
+                "--append",
+                ".",
+                "/dst/file",
+            ]
+        );
+    }
 }
@@ -18,7 +18,8 @@
 //!
 //!   1. `flush` + `sync_all` on the temp.
 //!   2. drop the file handle (cross-platform safety on rename).
-//!   3. optional `chmod` (Unix only) and `mtime` on the temp.
+//!   3. optional owner, xattrs and ACLs (`with_attrs`), then `chmod`
+//!      (Unix only) and `mtime` on the temp.
 //!   4. `rename` temp → target. This is the atomic cutover.
 //!
 //! On a kill-9 between `new()` and `finalize()`:
@@ -52,23 +53,47 @@
 //! `write_atomic_chunked` carries a per-instance pid/counter/nanos salt
 //! for that reason; `StreamingAtomicWriter` deliberately does not, so the
 //! orphan cleanup story stays simple.
+//!
+//! # In-place, sparse and partial files
+//!
+//! The rsync transfer options change the model above:
+//!
+//! * `in_place(target, offset)` (`--inplace` / `--append`) writes the
+//!   target directly from `offset` on. There is no temp and no rename;
+//!   `finalize` truncates the file to `offset + bytes_written`. A crash
+//!   leaves a half-updated target, exactly like stock rsync.
+//!   Blocks the delta copies onto their own offset are stepped over
+//!   (`ReconstructionWriter::skip_in_place`), so only changed blocks
+//!   are rewritten.
+//! * `with_sparse(true)` (`--sparse`) seeks over every all-zero
+//!   `SPARSE_WRITE_SIZE` window instead of writing it, so the file
+//!   system can leave a hole; `finalize` sets the final length.
+//! * `keep_partial(partial_path)` (`--partial-dir`) moves the temp into
+//!   the partial dir after a failed transfer, so the next run can use it
+//!   as its basis.
 
 #![cfg(feature = "aerorsync")]
 
 use std::ffi::OsString;
-use std::io;
+use std::io::{self, SeekFrom};
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::task::{ready, Context, Poll};
 
 use tokio::fs::{self, OpenOptions};
-use tokio::io::AsyncWrite;
+use tokio::io::{AsyncSeek, AsyncSeekExt, AsyncWrite};
 
+use crate::aerorsync::attrs::FileAttrs;
 use crate::aerorsync::delta_transport_impl::WriteAtomicError;
+use crate::aerorsync::engine_adapter::ReconstructionWriter;
 
 /// Fixed temp suffix appended to the destination path.
 const TEMP_SUFFIX: &str = ".aerotmp";
 
+/// Sparse-mode write window, `fileio.c::write_sparse`'s `SPARSE_WRITE_SIZE`.
+/// A window made only of zero bytes becomes a hole.
+const SPARSE_WRITE_SIZE: usize = 1024;
+
 /// Append `TEMP_SUFFIX` to `target` preserving the original extension.
 /// `data.tar.gz` becomes `data.tar.gz.aerotmp`, not `data.tar.aerotmp`.
 fn temp_path_for_streaming(target: &Path) -> PathBuf {
@@ -102,6 +127,16 @@ pub struct StreamingAtomicWriter {
     /// flag and so a future refactor that splits `finalize` across
     /// state transitions has a place to record the cutover.
     committed: bool,
+    /// `Some(offset)` when writing `target` in place from `offset`.
+    in_place_from: Option<u64>,
+    sparse: bool,
+    /// Bytes accepted but not yet skipped on disk: zero windows in
+    /// sparse mode, unchanged blocks in place (`skip_in_place`).
+    pending_hole: u64,
+    /// A pending seek has been started and awaits `poll_complete`.
+    seeking: bool,
+    /// `-o -g -X -A` metadata applied by `finalize` before the mode.
+    attrs: Option<FileAttrs>,
 }
 
 impl StreamingAtomicWriter {
@@ -126,9 +161,53 @@ impl StreamingAtomicWriter {
             file,
             bytes_written: 0,
             committed: false,
+            in_place_from: None,
+            sparse: false,
+            pending_hole: 0,
+            seeking: false,
+            attrs: None,
+        })
+    }
+
+    /// Open `target` itself for writing from `start_offset` on
+    /// (`--inplace` passes 0, `--append` the local length). The target
+    /// is created when missing and its bytes before `start_offset` are
+    /// left alone. `temp_path` returns `target`.
+    pub async fn in_place(target: &Path, start_offset: u64) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(target)
+            .await?;
+        file.seek(SeekFrom::Start(start_offset)).await?;
+        Ok(Self {
+            target: target.to_path_buf(),
+            temp: target.to_path_buf(),
+            file,
+            bytes_written: 0,
+            committed: false,
+            in_place_from: Some(start_offset),
+            sparse: false,
+            pending_hole: 0,
+            seeking: false,
+            attrs: None,
         })
     }
 
+    /// Turn `--sparse` handling on or off. Call before the first write.
+    pub fn with_sparse(mut self, sparse: bool) -> Self {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Owner, xattrs and ACLs to apply on `finalize`, after the data is
+    /// synced and before `mode` and `mtime`, like rsync's `set_file_attrs`.
+    pub fn with_attrs(mut self, attrs: FileAttrs) -> Self {
+        self.attrs = (!attrs.is_empty()).then_some(attrs);
+        self
+    }
+
     /// Total bytes successfully written through `AsyncWrite::poll_write`.
     /// Updated only when `poll_write` returns `Ready(Ok(n))`.
     pub fn bytes_written(&self) -> u64 {
@@ -153,12 +232,14 @@ impl StreamingAtomicWriter {
     ///   1. `flush` + `sync_all` on the open handle.
     ///   2. drop the handle (some kernels require this before rename
     ///      for cache coherency, mirroring `write_atomic_chunked`).
-    ///   3. apply `mode` to the temp (Unix only: silently ignored on
+    ///   3. apply the `with_attrs` metadata, stage `xattr`, `chown` or
+    ///      `acl` on failure.
+    ///   4. apply `mode` to the temp (Unix only: silently ignored on
     ///      other platforms because the underlying `set_permissions`
     ///      cannot map the bits faithfully).
-    ///   4. apply `mtime` (seconds + nanoseconds) to the temp via the
+    ///   5. apply `mtime` (seconds + nanoseconds) to the temp via the
     ///      `filetime` crate, matching `write_atomic_chunked` semantics.
-    ///   5. `rename` temp → target.
+    ///   6. `rename` temp → target.
     ///
     /// Errors map to `WriteAtomicError::PostOpen { stage, source }` so
     /// the caller can route them through the same R3 cutover-boundary
@@ -175,25 +256,78 @@ impl StreamingAtomicWriter {
             target,
             temp,
             file,
-            bytes_written: _,
+            bytes_written,
             mut committed,
+            in_place_from,
+            sparse,
+            attrs,
+            ..
         } = self;
-        let result = finalize_steps(&target, &temp, file, mode, mtime, &mut committed).await;
-        if result.is_err() {
+        // In-place writes may end before the old data did, and a
+        // trailing sparse hole was never written at all.
+        let final_len =
+            (in_place_from.is_some() || sparse).then(|| in_place_from.unwrap_or(0) + bytes_written);
+        let rename_to = in_place_from.is_none().then_some(target.as_path());
+        let result = finalize_steps(
+            rename_to,
+            &temp,
+            file,
+            final_len,
+            attrs.as_ref(),
+            mode,
+            mtime,
+            &mut committed,
+        )
+        .await;
+        if result.is_err() && in_place_from.is_none() {
             // Best-effort cleanup; we are already on the failure path.
             let _ = fs::remove_file(&temp).await;
         }
         result
     }
+
+    /// `--partial-dir`: keep what was received so far as `partial_path`
+    /// instead of finalizing. Parent directories are created as needed.
+    /// A no-op for in-place writers, whose partial data already lives in
+    /// the target.
+    pub async fn keep_partial(self, partial_path: &Path) -> io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        if self.in_place_from.is_some() {
+            return Ok(());
+        }
+        let Self {
+            temp,
+            mut file,
+            bytes_written,
+            sparse,
+            ..
+        } = self;
+        file.flush().await?;
+        if sparse {
+            file.set_len(bytes_written).await?;
+        }
+        drop(file);
+        if let Some(parent) = partial_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::rename(&temp, partial_path).await
+    }
 }
 
 /// Drives the commit pipeline. Split into a free function so `finalize`
 /// can consume `self` cleanly (destructuring up front) and still recover
 /// the temp path for cleanup on the error arm.
+///
+/// `rename_to` is `None` for in-place writers (`temp` is the target);
+/// `final_len` truncates or extends the file before it is synced.
+#[allow(clippy::too_many_arguments)]
 async fn finalize_steps(
-    target: &Path,
+    rename_to: Option<&Path>,
     temp: &Path,
     mut file: tokio::fs::File,
+    final_len: Option<u64>,
+    attrs: Option<&FileAttrs>,
     mode: Option<u32>,
     mtime: Option<(i64, u32)>,
     committed: &mut bool,
@@ -204,6 +338,14 @@ async fn finalize_steps(
         stage: "flush",
         source: e,
     })?;
+    if let Some(len) = final_len {
+        file.set_len(len)
+            .await
+            .map_err(|e| WriteAtomicError::PostOpen {
+                stage: "set_len",
+                source: e,
+            })?;
+    }
     file.sync_all()
         .await
         .map_err(|e| WriteAtomicError::PostOpen {
@@ -216,6 +358,13 @@ async fn finalize_steps(
     // inode. Cheap to drop explicitly.
     drop(file);
 
+    // Owner before mode: `chown` clears the set-id bits `chmod` restores.
+    if let Some(attrs) = attrs {
+        attrs
+            .apply(temp)
+            .map_err(|(stage, source)| WriteAtomicError::PostOpen { stage, source })?;
+    }
+
     #[cfg(unix)]
     if let Some(mode) = mode {
         use std::os::unix::fs::PermissionsExt;
@@ -240,15 +389,31 @@ async fn finalize_steps(
     }
 
     *committed = true;
-    fs::rename(temp, target)
-        .await
-        .map_err(|e| WriteAtomicError::PostOpen {
-            stage: "rename",
-            source: e,
-        })?;
+    if let Some(target) = rename_to {
+        fs::rename(temp, target)
+            .await
+            .map_err(|e| WriteAtomicError::PostOpen {
+                stage: "rename",
+                source: e,
+            })?;
+    }
     Ok(())
 }
 
+impl ReconstructionWriter for StreamingAtomicWriter {
+    /// Only an in-place writer keeps the basis bytes under its write
+    /// position; the skip is a seek taken before the next write, or
+    /// covered by the final length when none follows.
+    fn skip_in_place(&mut self, len: u64) -> bool {
+        if self.in_place_from.is_none() {
+            return false;
+        }
+        self.pending_hole += len;
+        self.bytes_written += len;
+        true
+    }
+}
+
 impl AsyncWrite for StreamingAtomicWriter {
     fn poll_write(
         self: Pin<&mut Self>,
@@ -256,7 +421,35 @@ impl AsyncWrite for StreamingAtomicWriter {
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
         let me = self.get_mut();
-        let result = Pin::new(&mut me.file).poll_write(cx, buf);
+        // Sparse mode works one window at a time: an all-zero window
+        // only grows the pending hole, anything else first seeks past
+        // the hole and is then written as-is.
+        let window = if me.sparse && !buf.is_empty() {
+            let window = &buf[..buf.len().min(SPARSE_WRITE_SIZE)];
+            if window.iter().all(|b| *b == 0) {
+                me.pending_hole += window.len() as u64;
+                me.bytes_written += window.len() as u64;
+                return Poll::Ready(Ok(window.len()));
+            }
+            window
+        } else {
+            buf
+        };
+        if me.pending_hole > 0 && !window.is_empty() {
+            if !me.seeking {
+                // `start_seek` refuses to run while a write is in flight.
+                ready!(Pin::new(&mut me.file).poll_complete(cx))?;
+                let hole = i64::try_from(me.pending_hole).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "pending seek too large")
+                })?;
+                Pin::new(&mut me.file).start_seek(SeekFrom::Current(hole))?;
+                me.seeking = true;
+            }
+            ready!(Pin::new(&mut me.file).poll_complete(cx))?;
+            me.seeking = false;
+            me.pending_hole = 0;
+        }
+        let result = Pin::new(&mut me.file).poll_write(cx, window);
         if let Poll::Ready(Ok(n)) = &result {
             me.bytes_written += *n as u64;
         }
@@ -390,6 +583,45 @@ mod tests {
         assert_eq!(ft.nanoseconds(), mtime.1, "mtime nanoseconds must match");
     }
 
+    /// Owner and xattrs from `with_attrs` land on the committed file,
+    /// and the mode applied after them is not disturbed.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn streaming_atomic_writer_applies_attrs_before_mode() {
+        use std::collections::BTreeMap;
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let dir = fresh_tempdir();
+        let target = dir.path().join("attrs.bin");
+        let probe = dir.path().join("probe");
+        std::fs::write(&probe, b"").unwrap();
+        let xattrs_supported = xattr::set(&probe, "user.probe", b"1").is_ok();
+
+        let gid = std::fs::metadata(dir.path()).unwrap().gid();
+        let attrs = FileAttrs {
+            gid: Some(gid),
+            xattrs: xattrs_supported
+                .then(|| BTreeMap::from([("user.origin".to_string(), b"aerorsync".to_vec())])),
+            ..FileAttrs::default()
+        };
+        let mut w = StreamingAtomicWriter::new(&target)
+            .await
+            .expect("new")
+            .with_attrs(attrs);
+        w.write_all(b"data").await.expect("write");
+        w.finalize(Some(0o2750), None).await.expect("finalize");
+
+        let meta = std::fs::metadata(&target).unwrap();
+        assert_eq!(meta.gid(), gid);
+        assert_eq!(meta.permissions().mode() & 0o7777, 0o2750);
+        if xattrs_supported {
+            assert_eq!(
+                xattr::get(&target, "user.origin").unwrap().as_deref(),
+                Some(&b"aerorsync"[..])
+            );
+        }
+    }
+
     /// Test 5: `bytes_written` accumulates accurately across N writes,
     /// including a zero-length write (poll_write may legitimately
     /// return Ready(Ok(0)) for empty buffers; the counter must not
@@ -510,6 +742,120 @@ mod tests {
         assert_eq!(on_disk, expected);
     }
 
+    #[tokio::test]
+    async fn in_place_writer_overwrites_from_offset_and_truncates() {
+        let dir = fresh_tempdir();
+        let target = dir.path().join("inplace.bin");
+        tokio::fs::write(&target, b"0123456789ABCDEF")
+            .await
+            .expect("seed target");
+
+        let mut w = StreamingAtomicWriter::in_place(&target, 4)
+            .await
+            .expect("in_place");
+        assert_eq!(w.temp_path(), target.as_path());
+        w.write_all(b"xyz").await.expect("write");
+        w.finalize(None, None).await.expect("finalize");
+
+        let bytes = tokio::fs::read(&target).await.expect("read");
+        assert_eq!(bytes, b"0123xyz");
+        assert!(!temp_path_for_streaming(&target).exists());
+    }
+
+    /// Skipped blocks keep the bytes already on disk, a trailing skip
+    /// included; a temp writer refuses to skip.
+    #[tokio::test]
+    async fn in_place_writer_steps_over_unchanged_blocks() {
+        let dir = fresh_tempdir();
+        let target = dir.path().join("image.bin");
+        tokio::fs::write(&target, b"AAAABBBBCCCCDDDD")
+            .await
+            .expect("seed target");
+
+        let mut w = StreamingAtomicWriter::in_place(&target, 0)
+            .await
+            .expect("in_place");
+        assert!(w.skip_in_place(4));
+        w.write_all(b"bbbb").await.expect("write");
+        assert!(w.skip_in_place(4));
+        w.write_all(b"dd").await.expect("write");
+        assert!(w.skip_in_place(2));
+        assert_eq!(w.bytes_written(), 16);
+        w.finalize(None, None).await.expect("finalize");
+        let bytes = tokio::fs::read(&target).await.expect("read");
+        assert_eq!(bytes, b"AAAAbbbbCCCCddDD");
+
+        let mut temp = StreamingAtomicWriter::new(&target).await.expect("new");
+        assert!(!temp.skip_in_place(4));
+        assert_eq!(temp.bytes_written(), 0);
+    }
+
+    /// `--append`: the writer starts at the local length and only adds.
+    #[tokio::test]
+    async fn in_place_writer_appends_after_the_local_copy() {
+        let dir = fresh_tempdir();
+        let target = dir.path().join("log.txt");
+        tokio::fs::write(&target, b"HELLO").await.expect("seed");
+
+        let mut w = StreamingAtomicWriter::in_place(&target, 5)
+            .await
+            .expect("in_place");
+        w.write_all(b" WORLD").await.expect("write");
+        w.finalize(None, None).await.expect("finalize");
+
+        let bytes = tokio::fs::read(&target).await.expect("read");
+        assert_eq!(bytes, b"HELLO WORLD");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn sparse_writer_leaves_zero_windows_as_holes() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = fresh_tempdir();
+        let target = dir.path().join("sparse.img");
+        let zeros = vec![0u8; 1024 * 1024];
+
+        let mut w = StreamingAtomicWriter::new(&target)
+            .await
+            .expect("new")
+            .with_sparse(true);
+        w.write_all(b"head").await.expect("head");
+        w.write_all(&zeros).await.expect("hole");
+        w.write_all(b"tail").await.expect("tail");
+        w.write_all(&zeros).await.expect("trailing hole");
+        assert_eq!(w.bytes_written(), 8 + 2 * zeros.len() as u64);
+        w.finalize(None, None).await.expect("finalize");
+
+        let bytes = tokio::fs::read(&target).await.expect("read");
+        let mut expected = b"head".to_vec();
+        expected.extend_from_slice(&zeros);
+        expected.extend_from_slice(b"tail");
+        expected.extend_from_slice(&zeros);
+        assert_eq!(bytes, expected);
+        // Allocation is a file-system property; only assert that the
+        // holes did not cost a full allocation.
+        let meta = tokio::fs::metadata(&target).await.expect("metadata");
+        assert!(meta.blocks() * 512 < meta.len(), "{meta:?}");
+    }
+
+    #[tokio::test]
+    async fn keep_partial_moves_the_temp_into_the_partial_dir() {
+        let dir = fresh_tempdir();
+        let target = dir.path().join("big.bin");
+        let partial = dir.path().join(".rsync-partial").join("big.bin");
+
+        let mut w = StreamingAtomicWriter::new(&target).await.expect("new");
+        w.write_all(b"first half").await.expect("write");
+        let temp = w.temp_path().to_path_buf();
+        w.keep_partial(&partial).await.expect("keep_partial");
+
+        assert!(!target.exists());
+        assert!(!temp.exists());
+        let bytes = tokio::fs::read(&partial).await.expect("read partial");
+        assert_eq!(bytes, b"first half");
+    }
+
     /// Diagnostic helper: `temp_path_for_streaming` preserves the full
     /// extension chain (the deviation from the plan documented in the
     /// module docstring).
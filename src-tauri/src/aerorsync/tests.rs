@@ -19,13 +19,15 @@ use crate::aerorsync::engine_adapter::{
 };
 use crate::aerorsync::events::{classify_oob_frame, AerorsyncEvent, BailingSink, EventSink};
 use crate::aerorsync::fixtures::{
-    BaselineCounters, RealRsyncBaselineByteTranscript, RealRsyncTranscriptPaths,
-    BASELINE_LITERAL_BYTES, BASELINE_MATCHED_BYTES, DOWNLOAD_REMOTE_COMMAND,
-    REAL_RSYNC_FROZEN_TRANSCRIPT_REL, REAL_RSYNC_LANE_PORT, UPLOAD_REMOTE_COMMAND,
+    BaselineCounters, RealRsyncBaselineByteTranscript, RealRsyncPeerCapture,
+    RealRsyncTranscriptPaths, BASELINE_LITERAL_BYTES, BASELINE_MATCHED_BYTES,
+    DOWNLOAD_REMOTE_COMMAND, REAL_RSYNC_FROZEN_TRANSCRIPT_REL, REAL_RSYNC_LANE_PORT,
+    REAL_RSYNC_OPTION_SCENARIOS, UPLOAD_REMOTE_COMMAND,
 };
 use crate::aerorsync::mock::{
     MockRemoteShellTransport, MockTransportConfig, OpenStreamBehavior, ReadExhaustedBehavior,
 };
+use crate::aerorsync::negotiation::negotiate_name;
 use crate::aerorsync::planner::{TransferCandidate, TransferPlanner};
 use crate::aerorsync::protocol::{
     AerorsyncFrameCodec, DeltaInstruction, ErrorMessage, FileMetadataMessage, FrameCodec,
@@ -41,7 +43,7 @@ use crate::aerorsync::real_wire::{
     DeltaOp, FileListDecodeOptions, FileListDecodeOutcome, MuxDemuxer, MuxHeader, MuxTag, NdxState,
     NDX_DONE, NDX_FLIST_EOF,
 };
-use crate::aerorsync::remote_command::RemoteCommandSpec;
+use crate::aerorsync::remote_command::{AppendMode, RemoteCommandSpec, TransferOptions};
 use crate::aerorsync::session::{AerorsyncSession, SessionState};
 use crate::aerorsync::transport::CancelHandle;
 use crate::aerorsync::transport::RemoteShellTransport;
@@ -3749,3 +3751,108 @@ fn compress_zstd_literal_stream_round_trips_through_frozen_oracle_payloads() {
         "round-trip raw -> compress -> decompress drifted from original"
     );
 }
+
+// ---------------------------------------------------------------------------
+// Stock-peer option captures (`frozen-peers/`): remote command shape and
+// wire streams of real rsync sessions with delete / inplace / append /
+// partial-dir / sparse / mkpath / hard-links.
+// ---------------------------------------------------------------------------
+
+/// The peer whose option captures pin `TransferOptions::compact_flags` and
+/// `long_args`. Older peers only feed the stream replay and negotiation.
+const OPTION_COMMAND_PEER: &str = "3.4";
+
+fn option_scenario_spec(scenario: &str, remote_target: String) -> RemoteCommandSpec {
+    let options = match scenario {
+        "baseline_download" => return RemoteCommandSpec::download(remote_target),
+        "baseline_upload" => TransferOptions::default(),
+        "delete" => TransferOptions {
+            delete: true,
+            ..TransferOptions::default()
+        },
+        "inplace" => TransferOptions {
+            inplace: true,
+            ..TransferOptions::default()
+        },
+        "append" => TransferOptions {
+            append: AppendMode::Append,
+            ..TransferOptions::default()
+        },
+        "append_verify" => TransferOptions {
+            append: AppendMode::AppendVerify,
+            ..TransferOptions::default()
+        },
+        "partial_dir" => TransferOptions {
+            partial_dir: Some(".rsync-partial".to_string()),
+            ..TransferOptions::default()
+        },
+        "sparse" => TransferOptions {
+            sparse: true,
+            ..TransferOptions::default()
+        },
+        "mkpath" => TransferOptions {
+            mkpath: true,
+            ..TransferOptions::default()
+        },
+        "hard_links" => TransferOptions {
+            hard_links: true,
+            ..TransferOptions::default()
+        },
+        other => panic!("no TransferOptions for scenario {other}"),
+    };
+    RemoteCommandSpec::upload(remote_target).with_options(options)
+}
+
+#[test]
+fn real_rsync_option_commands_match_remote_command_spec() {
+    for scenario in REAL_RSYNC_OPTION_SCENARIOS {
+        let capture = RealRsyncPeerCapture::load(OPTION_COMMAND_PEER, scenario);
+        let captured = capture.remote_args();
+        let target = captured
+            .last()
+            .cloned()
+            .expect("remote command has a target");
+        assert_eq!(
+            option_scenario_spec(scenario, target).to_args(),
+            captured,
+            "rsync {OPTION_COMMAND_PEER} / {scenario}: {}",
+            capture.remote_command
+        );
+    }
+}
+
+#[test]
+fn real_rsync_peer_option_streams_replay_cleanly() {
+    for capture in &RealRsyncPeerCapture::load_all() {
+        let label = format!("rsync {} / {}", capture.peer, capture.scenario);
+        let client = decode_client_preamble(&capture.client_to_server)
+            .unwrap_or_else(|e| panic!("{label}: client preamble: {e:?}"));
+        let server = decode_server_preamble(&capture.server_to_client)
+            .unwrap_or_else(|e| panic!("{label}: server preamble: {e:?}"));
+        assert!(
+            (31..=32).contains(&server.protocol_version),
+            "{label}: protocol {}",
+            server.protocol_version
+        );
+        // Both ends are the same stock release, so they must agree.
+        assert!(
+            negotiate_name(&client.checksum_algos, &server.checksum_algos).is_some(),
+            "{label}: no common checksum"
+        );
+
+        // A successful session carries no terminal error frame, and every
+        // multiplexed byte after the preamble decodes.
+        let report = reassemble_until_terminal(&capture.server_to_client[server.consumed..])
+            .unwrap_or_else(|e| panic!("{label}: server stream: {e:?}"));
+        assert!(
+            report.terminal.is_none(),
+            "{label}: terminal event {:?}",
+            report.terminal
+        );
+        assert_eq!(
+            report.consumed_bytes,
+            capture.server_to_client.len() - server.consumed,
+            "{label}: trailing bytes after the last mux frame"
+        );
+    }
+}
@@ -153,6 +153,15 @@ pub trait RawByteStream: Send {
     async fn read_bytes(&mut self, max: usize) -> Result<Vec<u8>, AerorsyncError>;
     async fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), AerorsyncError>;
     async fn shutdown(&mut self) -> Result<(), AerorsyncError>;
+
+    /// Protocol version both peers already agreed on before the stream
+    /// was handed over. `None` for remote-shell streams, where the
+    /// binary preamble opens with the 4-byte version exchange; an rsync
+    /// daemon settles the version in its `@RSYNCD:` greeting instead,
+    /// so the preamble starts at the compat flags.
+    fn agreed_protocol(&self) -> Option<u32> {
+        None
+    }
 }
 
 /// Transport that can open a raw byte-stream session in addition to the
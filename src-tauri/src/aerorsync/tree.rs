@@ -0,0 +1,833 @@
+//! Directory-tree bookkeeping for recursive aerorsync sessions.
+//!
+//! Stock rsync with `-r` and incremental recursion (`CF_INC_RECURSE`)
+//! does not ship one flat file list. The sender emits a top-level list
+//! (the transfer root `.` plus its direct children), then one extra list
+//! per directory, each prefixed on the wire by
+//! `NDX_FLIST_OFFSET - dir_ndx`. Both peers sort every list with
+//! `flist.c::f_name_cmp` and address files by
+//! `ndx = list.ndx_start + sorted_position`, so the two sides only agree
+//! on an ndx if they replicate the same ordering and numbering rules.
+//! [`TreeFileList`] is that shared model: the upload path builds it from
+//! a local scan, the download path grows it segment by segment as the
+//! remote sender streams lists in.
+//!
+//! The [`TreeSource`] / [`TreeSink`] seams keep the native driver free
+//! of filesystem access, the same way the single-file entry points take
+//! byte slices and `AsyncWrite` sinks instead of paths.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use async_trait::async_trait;
+
+use crate::aerorsync::real_wire::{
+    FileListEntry, HardlinkRef, S_IFDIR, S_IFLNK, S_IFMT, S_IFREG, XMIT_EXTENDED_FLAGS,
+    XMIT_GROUP_NAME_FOLLOWS, XMIT_HLINKED, XMIT_HLINK_FIRST, XMIT_LONG_NAME, XMIT_MOD_NSEC,
+    XMIT_TOP_DIR, XMIT_USER_NAME_FOLLOWS,
+};
+use crate::aerorsync::types::AerorsyncError;
+
+/// Path of the transfer root inside every tree file list.
+pub const TREE_ROOT_PATH: &str = ".";
+
+/// Whether `mode` describes a directory.
+pub fn is_dir_mode(mode: u32) -> bool {
+    mode & S_IFMT == S_IFDIR
+}
+
+/// Whether `mode` describes a symbolic link.
+pub fn is_symlink_mode(mode: u32) -> bool {
+    mode & S_IFMT == S_IFLNK
+}
+
+/// Whether `mode` describes a regular file. Modes without file-type
+/// bits count as regular, matching `flist_mode_carries_checksum`.
+pub fn is_regular_mode(mode: u32) -> bool {
+    let kind = mode & S_IFMT;
+    kind == 0 || kind == S_IFREG
+}
+
+// --- f_name_cmp --------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum NameType {
+    /// Non-directories, and the `.` directory: sort first.
+    Item,
+    /// Directories, and the dirname part of any nested path.
+    Path,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NameState {
+    Dir,
+    Slash,
+    Base,
+    Trailing,
+}
+
+/// Byte cursor over one side of `f_name_cmp`. Walks `dirname`, `/`,
+/// `basename` and (for directories) a trailing `/`, exactly like the
+/// `s_DIR → s_SLASH → s_BASE → s_TRAILING` machine in `flist.c`.
+struct NameCursor<'a> {
+    basename: &'a [u8],
+    is_dir: bool,
+    state: NameState,
+    ty: NameType,
+    cur: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> NameCursor<'a> {
+    fn new(dirname: Option<&'a str>, basename: &'a str, is_dir: bool) -> Self {
+        let mut cursor = Self {
+            basename: basename.as_bytes(),
+            is_dir,
+            state: NameState::Dir,
+            ty: NameType::Path,
+            cur: b"",
+            pos: 0,
+        };
+        match dirname {
+            Some(dir) => cursor.cur = dir.as_bytes(),
+            None => cursor.enter_basename(),
+        }
+        cursor
+    }
+
+    fn enter_basename(&mut self) {
+        self.ty = if self.is_dir {
+            NameType::Path
+        } else {
+            NameType::Item
+        };
+        if self.ty == NameType::Path && self.basename == b"." {
+            self.ty = NameType::Item;
+            self.state = NameState::Trailing;
+            self.cur = b"";
+        } else {
+            self.state = NameState::Base;
+            self.cur = self.basename;
+        }
+        self.pos = 0;
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.cur.len()
+    }
+
+    fn peek(&self) -> u8 {
+        self.cur.get(self.pos).copied().unwrap_or(0)
+    }
+
+    fn bump(&mut self) {
+        if !self.at_end() {
+            self.pos += 1;
+        }
+    }
+
+    fn advance(&mut self) {
+        match self.state {
+            NameState::Dir => {
+                self.state = NameState::Slash;
+                self.cur = b"/";
+                self.pos = 0;
+            }
+            NameState::Slash => self.enter_basename(),
+            NameState::Base => {
+                self.state = NameState::Trailing;
+                if self.ty == NameType::Path {
+                    self.cur = b"/";
+                    self.pos = 0;
+                } else {
+                    self.ty = NameType::Item;
+                }
+            }
+            NameState::Trailing => self.ty = NameType::Item,
+        }
+    }
+}
+
+fn split_path(path: &str) -> (Option<&str>, &str) {
+    match path.rsplit_once('/') {
+        Some((dir, base)) => (Some(dir), base),
+        None => (None, path),
+    }
+}
+
+/// Port of `flist.c::f_name_cmp` for protocol >= 29: within one
+/// directory non-directories sort before directories, `.` sorts before
+/// everything, and the rest is a bytewise comparison of the full path
+/// where a directory compares as if it carried a trailing `/`.
+pub fn f_name_cmp(a: &FileListEntry, b: &FileListEntry) -> Ordering {
+    let (dir_a, base_a) = split_path(&a.path);
+    let (dir_b, base_b) = split_path(&b.path);
+    // rsync compares interned dirname pointers: entries sharing a
+    // directory skip straight to the basename.
+    let (dir_a, dir_b) = if dir_a == dir_b {
+        (None, None)
+    } else {
+        (dir_a, dir_b)
+    };
+    let mut ca = NameCursor::new(dir_a, base_a, is_dir_mode(a.mode));
+    let mut cb = NameCursor::new(dir_b, base_b, is_dir_mode(b.mode));
+    if ca.ty != cb.ty {
+        return ca.ty.cmp(&cb.ty);
+    }
+    loop {
+        if ca.at_end() {
+            ca.advance();
+            if !cb.at_end() && ca.ty != cb.ty {
+                return ca.ty.cmp(&cb.ty);
+            }
+        }
+        if cb.at_end() {
+            cb.advance();
+            if ca.ty != cb.ty {
+                return ca.ty.cmp(&cb.ty);
+            }
+        }
+        if ca.at_end() && cb.at_end() {
+            return Ordering::Equal;
+        }
+        let (x, y) = (ca.peek(), cb.peek());
+        if x != y {
+            return x.cmp(&y);
+        }
+        ca.bump();
+        cb.bump();
+    }
+}
+
+/// Reject list paths that could escape the transfer root: absolute
+/// paths, empty / `.` / `..` components, NUL bytes. The root itself is
+/// spelled `.` and is accepted as-is.
+pub fn sanitize_relative_path(path: &str) -> Result<(), AerorsyncError> {
+    if path == TREE_ROOT_PATH {
+        return Ok(());
+    }
+    if path.is_empty() || path.starts_with('/') || path.contains('\0') {
+        return Err(AerorsyncError::invalid_frame(format!(
+            "unsafe path in file list: {path:?}"
+        )));
+    }
+    for component in path.split('/') {
+        if component.is_empty() || component == "." || component == ".." {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "unsafe path in file list: {path:?}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+// --- TreeFileList ------------------------------------------------------------
+
+/// One wire file list: the top-level list or the contents of one
+/// directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeSegment {
+    /// `dir_ndx` of the directory this list expands; `None` for the
+    /// top-level list.
+    pub parent_dir_ndx: Option<i32>,
+    /// ndx of the first entry. The top-level list starts at 1; every
+    /// later list at `prev.ndx_start + prev.len + 1` (`flist_new`).
+    pub ndx_start: i32,
+    /// Entries in `f_name_cmp` order.
+    pub entries: Vec<FileListEntry>,
+}
+
+impl TreeSegment {
+    /// Base names of the directory's children in this list (the root
+    /// entry itself excluded): what `--delete` keeps.
+    pub fn child_names(&self) -> BTreeSet<String> {
+        self.entries
+            .iter()
+            .filter(|e| e.path != TREE_ROOT_PATH)
+            .map(|e| split_path(&e.path).1.to_string())
+            .collect()
+    }
+}
+
+/// Incremental-recursion file list shared by both tree directions.
+#[derive(Debug, Clone, Default)]
+pub struct TreeFileList {
+    segments: Vec<TreeSegment>,
+    /// Directory paths in `dir_ndx` order. The root is stored as `""`.
+    dirs: Vec<String>,
+}
+
+impl TreeFileList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the sender-side list from a flat local scan. `entries` must
+    /// contain the root (`.`, a directory) plus every directory, regular
+    /// file and symlink below it, with `/`-separated relative paths.
+    /// Lists are laid out in the pre-order `send_extra_file_list` walks,
+    /// and the transmit flags are recomputed for the resulting send order.
+    pub fn from_local_entries(entries: Vec<FileListEntry>) -> Result<Self, AerorsyncError> {
+        Self::from_local_entries_linked(entries, &HashMap::new())
+    }
+
+    /// `from_local_entries` for `-H`: `inodes` maps the path of every
+    /// multiply-linked non-directory to its `(dev, ino)`. Entries sharing
+    /// one form a hard-link group; the first in send order goes out in
+    /// full and the others name it by ndx.
+    pub fn from_local_entries_linked(
+        entries: Vec<FileListEntry>,
+        inodes: &HashMap<String, (u64, u64)>,
+    ) -> Result<Self, AerorsyncError> {
+        let mut root: Option<FileListEntry> = None;
+        let mut by_parent: BTreeMap<String, Vec<FileListEntry>> = BTreeMap::new();
+        let mut dir_paths: BTreeSet<String> = BTreeSet::new();
+        for entry in entries {
+            sanitize_relative_path(&entry.path)?;
+            if entry.path == TREE_ROOT_PATH {
+                if !is_dir_mode(entry.mode) || root.is_some() {
+                    return Err(AerorsyncError::invalid_frame(
+                        "tree root must be a single directory entry",
+                    ));
+                }
+                root = Some(entry);
+                continue;
+            }
+            if is_dir_mode(entry.mode) {
+                dir_paths.insert(entry.path.clone());
+            }
+            let parent = split_path(&entry.path).0.unwrap_or("").to_string();
+            by_parent.entry(parent).or_default().push(entry);
+        }
+        let root = root.ok_or_else(|| AerorsyncError::invalid_frame("tree has no root entry"))?;
+        if let Some(orphan) = by_parent
+            .keys()
+            .find(|parent| !parent.is_empty() && !dir_paths.contains(*parent))
+        {
+            return Err(AerorsyncError::invalid_frame(format!(
+                "tree entry parent {orphan:?} is not a directory in the scan"
+            )));
+        }
+
+        let mut tree = Self::new();
+        let mut top = by_parent.remove("").unwrap_or_default();
+        top.push(root);
+        tree.push_segment(None, top)?;
+        let mut pending: Vec<i32> = (1..tree.dirs.len() as i32).rev().collect();
+        while let Some(dir_ndx) = pending.pop() {
+            let path = tree.dirs[dir_ndx as usize].clone();
+            let children = by_parent.remove(&path).unwrap_or_default();
+            let first_new = tree.dirs.len() as i32;
+            tree.push_segment(Some(dir_ndx), children)?;
+            pending.extend((first_new..tree.dirs.len() as i32).rev());
+        }
+        tree.assign_send_flags(inodes);
+        Ok(tree)
+    }
+
+    /// Append one list (top-level when `parent_dir_ndx` is `None`).
+    /// Sorts it, assigns its ndx range and registers its directories.
+    /// Used as-is by the receiving side for every list read off the
+    /// wire. Returns the new segment index.
+    pub fn push_segment(
+        &mut self,
+        parent_dir_ndx: Option<i32>,
+        mut entries: Vec<FileListEntry>,
+    ) -> Result<usize, AerorsyncError> {
+        let parent_path = match (parent_dir_ndx, self.segments.is_empty()) {
+            (None, true) => None,
+            (Some(dir_ndx), false) => {
+                let path = usize::try_from(dir_ndx)
+                    .ok()
+                    .and_then(|i| self.dirs.get(i))
+                    .ok_or_else(|| {
+                        AerorsyncError::invalid_frame(format!(
+                            "extra file list for unknown dir_ndx {dir_ndx}"
+                        ))
+                    })?;
+                Some(path.clone())
+            }
+            (None, false) => {
+                return Err(AerorsyncError::invalid_frame(
+                    "second top-level file list in one session",
+                ))
+            }
+            (Some(dir_ndx), true) => {
+                return Err(AerorsyncError::invalid_frame(format!(
+                    "extra file list for dir_ndx {dir_ndx} before the top-level list"
+                )))
+            }
+        };
+        for entry in &entries {
+            sanitize_relative_path(&entry.path)?;
+            let parent = split_path(&entry.path).0;
+            let in_place = match &parent_path {
+                None => parent.is_none(),
+                Some(dir) => parent == Some(dir.as_str()),
+            };
+            if !in_place {
+                return Err(AerorsyncError::invalid_frame(format!(
+                    "file list entry {:?} outside its directory",
+                    entry.path
+                )));
+            }
+        }
+        entries.sort_by(f_name_cmp);
+        let ndx_start = self.next_ndx_start();
+        for entry in entries.iter().filter(|e| is_dir_mode(e.mode)) {
+            let key = if entry.path == TREE_ROOT_PATH {
+                String::new()
+            } else {
+                entry.path.clone()
+            };
+            self.dirs.push(key);
+        }
+        self.segments.push(TreeSegment {
+            parent_dir_ndx,
+            ndx_start,
+            entries,
+        });
+        Ok(self.segments.len() - 1)
+    }
+
+    /// ndx the next list pushed will start at: 1 for the top-level
+    /// list, then `prev.ndx_start + prev.len + 1` (`flist_new`).
+    pub fn next_ndx_start(&self) -> i32 {
+        match self.segments.last() {
+            None => 1,
+            Some(prev) => prev.ndx_start + prev.entries.len() as i32 + 1,
+        }
+    }
+
+    /// Rewrite the transmit flags for the send order: `XMIT_TOP_DIR` on
+    /// the root, `XMIT_MOD_NSEC` when nanoseconds are known, owner and
+    /// group names only the first time an id goes out (`add_uid`),
+    /// `XMIT_LONG_NAME` past 255 bytes, the hard-link group bits, and
+    /// the `send_file_entry` fallback so no entry goes out with zero
+    /// flags.
+    fn assign_send_flags(&mut self, inodes: &HashMap<String, (u64, u64)>) {
+        let mut seen_uids: BTreeSet<i64> = BTreeSet::new();
+        let mut seen_gids: BTreeSet<i64> = BTreeSet::new();
+        // Only inodes listed more than once need the group machinery.
+        let mut link_counts: HashMap<(u64, u64), usize> = HashMap::new();
+        for entry in self.entries().filter(|e| !is_dir_mode(e.mode)) {
+            if let Some(key) = inodes.get(&entry.path) {
+                *link_counts.entry(*key).or_default() += 1;
+            }
+        }
+        let mut leaders: HashMap<(u64, u64), i32> = HashMap::new();
+        let positions = self
+            .segments
+            .iter()
+            .flat_map(|s| (0..s.entries.len()).map(move |pos| s.ndx_start + pos as i32));
+        let positions: Vec<i32> = positions.collect();
+        let entries = self.segments.iter_mut().flat_map(|s| s.entries.iter_mut());
+        for (entry, ndx) in entries.zip(positions) {
+            let mut flags = 0u32;
+            entry.hardlink = None;
+            let group = inodes
+                .get(&entry.path)
+                .filter(|key| !is_dir_mode(entry.mode) && link_counts[*key] > 1);
+            if let Some(key) = group {
+                flags |= XMIT_HLINKED;
+                match leaders.get(key) {
+                    Some(&first_ndx) => entry.hardlink = Some(HardlinkRef::Follower { first_ndx }),
+                    None => {
+                        leaders.insert(*key, ndx);
+                        flags |= XMIT_HLINK_FIRST;
+                        entry.hardlink = Some(HardlinkRef::First);
+                    }
+                }
+            }
+            if entry.path == TREE_ROOT_PATH {
+                flags |= XMIT_TOP_DIR;
+            }
+            if entry.path.len() > 255 {
+                flags |= XMIT_LONG_NAME;
+            }
+            if entry.mtime_nsec.is_some() {
+                flags |= XMIT_MOD_NSEC;
+            }
+            match entry.uid {
+                Some(uid) if entry.uid_name.is_some() && seen_uids.insert(uid) => {
+                    flags |= XMIT_USER_NAME_FOLLOWS;
+                }
+                _ => entry.uid_name = None,
+            }
+            match entry.gid {
+                Some(gid) if entry.gid_name.is_some() && seen_gids.insert(gid) => {
+                    flags |= XMIT_GROUP_NAME_FOLLOWS;
+                }
+                _ => entry.gid_name = None,
+            }
+            if flags == 0 {
+                flags = if is_dir_mode(entry.mode) {
+                    XMIT_EXTENDED_FLAGS
+                } else {
+                    XMIT_TOP_DIR
+                };
+            }
+            entry.flags = flags;
+        }
+    }
+
+    pub fn segments(&self) -> &[TreeSegment] {
+        &self.segments
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Resolve a wire ndx to `(segment index, entry)`.
+    pub fn entry(&self, ndx: i32) -> Option<(usize, &FileListEntry)> {
+        let seg_idx = self
+            .segments
+            .partition_point(|s| s.ndx_start <= ndx)
+            .checked_sub(1)?;
+        let segment = &self.segments[seg_idx];
+        let pos = usize::try_from(ndx - segment.ndx_start).ok()?;
+        segment.entries.get(pos).map(|e| (seg_idx, e))
+    }
+
+    /// Relative path of directory `dir_ndx` (`""` for the root).
+    pub fn dir_path(&self, dir_ndx: i32) -> Option<&str> {
+        usize::try_from(dir_ndx)
+            .ok()
+            .and_then(|i| self.dirs.get(i))
+            .map(String::as_str)
+    }
+
+    /// Sink path of the directory segment `index` expands (`.` for the
+    /// top-level list).
+    pub fn segment_dir(&self, index: usize) -> Option<&str> {
+        match self.segments.get(index)?.parent_dir_ndx {
+            None => Some(TREE_ROOT_PATH),
+            Some(dir_ndx) => self.dir_path(dir_ndx),
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &FileListEntry> {
+        self.segments.iter().flat_map(|s| s.entries.iter())
+    }
+
+    /// Hard-link followers in send order, each with the ndx of its
+    /// group's first entry.
+    pub fn hardlink_followers(&self) -> impl Iterator<Item = (&FileListEntry, i32)> {
+        self.entries().filter_map(|e| match e.hardlink {
+            Some(HardlinkRef::Follower { first_ndx }) => Some((e, first_ndx)),
+            _ => None,
+        })
+    }
+
+    /// Number of regular files across every list.
+    pub fn file_count(&self) -> usize {
+        self.entries().filter(|e| is_regular_mode(e.mode)).count()
+    }
+
+    /// Sum of regular file sizes across every list.
+    pub fn total_size(&self) -> u64 {
+        self.entries()
+            .filter(|e| is_regular_mode(e.mode))
+            .map(|e| e.size.max(0) as u64)
+            .sum()
+    }
+}
+
+// --- source / sink seams -----------------------------------------------------
+
+/// Upload-side file provider. Paths are the list paths (relative,
+/// `/`-separated, already sanitized).
+#[async_trait]
+pub trait TreeSource: Send {
+    /// Full contents of the regular file at `path`.
+    async fn read_file(&mut self, path: &str) -> Result<Vec<u8>, AerorsyncError>;
+}
+
+/// Download-side destination. Paths are the list paths (relative,
+/// `/`-separated, already sanitized; `.` is the destination root).
+#[async_trait]
+pub trait TreeSink: Send {
+    /// Make sure the directory exists.
+    async fn create_dir(&mut self, path: &str, entry: &FileListEntry)
+        -> Result<(), AerorsyncError>;
+
+    /// Current local contents of `path`, used as the delta baseline and
+    /// for the unchanged-file check. `None` when the file is absent.
+    async fn read_baseline(&mut self, path: &str) -> Result<Option<Vec<u8>>, AerorsyncError>;
+
+    /// Modification time (whole seconds) of the local copy of `path`,
+    /// for the size-and-mtime quick check of sessions without `-c`.
+    /// `None` when unknown, which makes the file go through the delta.
+    async fn baseline_mtime(&mut self, _path: &str) -> Result<Option<i64>, AerorsyncError> {
+        Ok(None)
+    }
+
+    /// Install the reconstructed contents of `path`, with the entry's
+    /// mode, mtime and, when the list carries them, owner, xattrs and
+    /// ACLs.
+    async fn commit_file(
+        &mut self,
+        path: &str,
+        entry: &FileListEntry,
+        data: Vec<u8>,
+    ) -> Result<(), AerorsyncError>;
+
+    /// Make `path` a symlink to `entry.link_target`, replacing whatever
+    /// non-directory is there.
+    async fn create_symlink(
+        &mut self,
+        path: &str,
+        entry: &FileListEntry,
+    ) -> Result<(), AerorsyncError>;
+
+    /// `-H`: make `path` another name of `leader`, which is already in
+    /// place.
+    async fn hard_link(&mut self, leader: &str, path: &str) -> Result<(), AerorsyncError>;
+
+    /// Bring the metadata of an entry whose contents are already up to
+    /// date in line with the list: the same metadata `commit_file`
+    /// installs, without touching the data.
+    async fn apply_attrs(
+        &mut self,
+        path: &str,
+        entry: &FileListEntry,
+    ) -> Result<(), AerorsyncError>;
+
+    /// `--delete`: remove whatever sits directly inside directory `dir`
+    /// under a name not in `keep`, recursively. Returns the number of
+    /// top-level entries removed.
+    async fn delete_extraneous(
+        &mut self,
+        dir: &str,
+        keep: &BTreeSet<String>,
+    ) -> Result<u64, AerorsyncError>;
+}
+
+/// Per-session counters returned by the tree drive entry points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TreeTransferReport {
+    /// Regular files in the file list.
+    pub files_total: u64,
+    /// Regular files whose data crossed the wire.
+    pub files_transferred: u64,
+    /// Directories in the file list, root included.
+    pub dirs_total: u64,
+    /// Sum of the transferred files' sizes.
+    pub bytes_transferred: u64,
+    /// Receiver entries removed by `--delete`.
+    pub entries_deleted: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, mode: u32) -> FileListEntry {
+        FileListEntry {
+            flags: 0,
+            path: path.to_string(),
+            size: 0,
+            mtime: 1_700_000_000,
+            mtime_nsec: None,
+            mode,
+            uid: Some(1000),
+            uid_name: Some("alice".to_string()),
+            gid: Some(1000),
+            gid_name: Some("staff".to_string()),
+            checksum: Vec::new(),
+            link_target: None,
+            hardlink: None,
+            acl: None,
+            xattrs: None,
+        }
+    }
+
+    fn file(path: &str) -> FileListEntry {
+        entry(path, S_IFREG | 0o644)
+    }
+
+    fn dir(path: &str) -> FileListEntry {
+        entry(path, S_IFDIR | 0o755)
+    }
+
+    fn sorted_paths(mut entries: Vec<FileListEntry>) -> Vec<String> {
+        entries.sort_by(f_name_cmp);
+        entries.into_iter().map(|e| e.path).collect()
+    }
+
+    #[test]
+    fn f_name_cmp_puts_dot_then_files_then_dirs() {
+        let got = sorted_paths(vec![
+            dir("sub"),
+            file("b.txt"),
+            dir("."),
+            file("a.txt"),
+            dir("alpha"),
+        ]);
+        assert_eq!(got, vec![".", "a.txt", "b.txt", "alpha", "sub"]);
+    }
+
+    #[test]
+    fn f_name_cmp_compares_dirs_with_trailing_slash() {
+        // "a-b" (0x2D) < "a/" (0x2F) < "a0" (0x30) once both are dirs.
+        let got = sorted_paths(vec![dir("a0"), dir("a"), dir("a-b")]);
+        assert_eq!(got, vec!["a-b", "a", "a0"]);
+    }
+
+    #[test]
+    fn f_name_cmp_orders_nested_paths_across_directories() {
+        let got = sorted_paths(vec![
+            file("x/z/f"),
+            file("x/a"),
+            dir("x/z"),
+            file("x.txt"),
+            dir("x"),
+        ]);
+        assert_eq!(got, vec!["x.txt", "x", "x/a", "x/z", "x/z/f"]);
+    }
+
+    #[test]
+    fn sanitize_rejects_escaping_paths() {
+        assert!(sanitize_relative_path(".").is_ok());
+        assert!(sanitize_relative_path("a/b.txt").is_ok());
+        for bad in [
+            "",
+            "/etc/passwd",
+            "../x",
+            "a/../../x",
+            "a//b",
+            "a/./b",
+            "a/",
+        ] {
+            assert!(sanitize_relative_path(bad).is_err(), "{bad:?} accepted");
+        }
+    }
+
+    #[test]
+    fn from_local_entries_lays_out_pre_order_segments() {
+        let tree = TreeFileList::from_local_entries(vec![
+            dir("."),
+            dir("b"),
+            dir("a"),
+            dir("a/inner"),
+            file("a/inner/deep.bin"),
+            file("a/one.txt"),
+            file("top.txt"),
+        ])
+        .unwrap();
+        let segs = tree.segments();
+        assert_eq!(segs.len(), 4);
+        assert_eq!(segs[0].parent_dir_ndx, None);
+        assert_eq!(segs[0].ndx_start, 1);
+        let top: Vec<&str> = segs[0].entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(top, vec![".", "top.txt", "a", "b"]);
+        // dir table: "" (0), "a" (1), "b" (2), then a's children.
+        assert_eq!(segs[1].parent_dir_ndx, Some(1));
+        assert_eq!(segs[1].ndx_start, 6);
+        let a: Vec<&str> = segs[1].entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(a, vec!["a/one.txt", "a/inner"]);
+        assert_eq!(tree.dir_path(3), Some("a/inner"));
+        assert_eq!(segs[2].parent_dir_ndx, Some(3));
+        assert_eq!(segs[2].ndx_start, 9);
+        assert_eq!(segs[3].parent_dir_ndx, Some(2));
+        assert!(segs[3].entries.is_empty());
+        assert_eq!(segs[3].ndx_start, 11);
+        let deep = tree.entry(9).map(|(s, e)| (s, e.path.as_str()));
+        assert_eq!(deep, Some((2, "a/inner/deep.bin")));
+        // ndx 5 and 10 fall in the one-slot gaps between lists.
+        assert!(tree.entry(5).is_none());
+        assert!(tree.entry(10).is_none());
+        assert_eq!(tree.file_count(), 3);
+        assert_eq!(tree.segment_dir(0), Some(TREE_ROOT_PATH));
+        assert_eq!(tree.segment_dir(2), Some("a/inner"));
+        assert_eq!(tree.segment_dir(4), None);
+        let keep: Vec<String> = segs[0].child_names().into_iter().collect();
+        assert_eq!(keep, vec!["a", "b", "top.txt"]);
+        let keep: Vec<String> = segs[1].child_names().into_iter().collect();
+        assert_eq!(keep, vec!["inner", "one.txt"]);
+    }
+
+    #[test]
+    fn from_local_entries_sends_owner_names_once() {
+        let tree = TreeFileList::from_local_entries(vec![dir("."), file("a"), file("b")]).unwrap();
+        let flags: Vec<u32> = tree.entries().map(|e| e.flags).collect();
+        assert_eq!(
+            flags[0],
+            XMIT_TOP_DIR | XMIT_USER_NAME_FOLLOWS | XMIT_GROUP_NAME_FOLLOWS
+        );
+        assert_eq!(flags[1], XMIT_TOP_DIR);
+        assert_eq!(flags[2], XMIT_TOP_DIR);
+        assert!(tree.entries().skip(1).all(|e| e.uid_name.is_none()));
+    }
+
+    #[test]
+    fn from_local_entries_linked_groups_hard_links_in_send_order() {
+        let inodes = HashMap::from([
+            ("z.txt".to_string(), (1, 10)),
+            ("a.txt".to_string(), (1, 10)),
+            ("d/b.txt".to_string(), (1, 10)),
+            // Alone in the transfer: no group.
+            ("lonely".to_string(), (1, 11)),
+        ]);
+        let tree = TreeFileList::from_local_entries_linked(
+            vec![
+                dir("."),
+                file("z.txt"),
+                file("a.txt"),
+                file("lonely"),
+                dir("d"),
+                file("d/b.txt"),
+            ],
+            &inodes,
+        )
+        .unwrap();
+        // Top list: ".", "a.txt", "lonely", "z.txt", "d" from ndx 1.
+        let a = tree.entry(2).unwrap().1;
+        assert_eq!(a.path, "a.txt");
+        assert_eq!(a.hardlink, Some(HardlinkRef::First));
+        assert_eq!(
+            a.flags & (XMIT_HLINKED | XMIT_HLINK_FIRST),
+            XMIT_HLINKED | XMIT_HLINK_FIRST
+        );
+        let lonely = tree.entry(3).unwrap().1;
+        assert_eq!(lonely.hardlink, None);
+        assert_eq!(lonely.flags & XMIT_HLINKED, 0);
+        let followers: Vec<(&str, i32)> = tree
+            .hardlink_followers()
+            .map(|(e, first)| (e.path.as_str(), first))
+            .collect();
+        assert_eq!(followers, vec![("z.txt", 2), ("d/b.txt", 2)]);
+        assert!(tree
+            .hardlink_followers()
+            .all(|(e, _)| e.flags & (XMIT_HLINKED | XMIT_HLINK_FIRST) == XMIT_HLINKED));
+        assert_eq!(tree.next_ndx_start(), 9);
+    }
+
+    #[test]
+    fn symlinks_sort_and_count_as_non_files() {
+        let mut link = entry("link", S_IFLNK | 0o777);
+        link.link_target = Some("top.txt".to_string());
+        let tree = TreeFileList::from_local_entries(vec![dir("."), file("top.txt"), link]).unwrap();
+        assert_eq!(tree.file_count(), 1);
+        assert!(is_symlink_mode(tree.entry(2).unwrap().1.mode));
+    }
+
+    #[test]
+    fn from_local_entries_rejects_orphans_and_missing_root() {
+        assert!(TreeFileList::from_local_entries(vec![file("a")]).is_err());
+        assert!(TreeFileList::from_local_entries(vec![dir("."), file("missing/a")]).is_err());
+    }
+
+    #[test]
+    fn push_segment_validates_parent_and_placement() {
+        let mut tree = TreeFileList::new();
+        assert!(tree.push_segment(Some(0), vec![]).is_err());
+        tree.push_segment(None, vec![dir("."), dir("d")]).unwrap();
+        assert!(tree.push_segment(Some(7), vec![]).is_err());
+        assert!(tree.push_segment(Some(1), vec![file("other/x")]).is_err());
+        assert!(tree.push_segment(Some(1), vec![file("d/../../x")]).is_err());
+        let idx = tree.push_segment(Some(1), vec![file("d/x")]).unwrap();
+        assert_eq!(tree.segments()[idx].ndx_start, 4);
+    }
+}
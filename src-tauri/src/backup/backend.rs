@@ -0,0 +1,201 @@
+//! Storage backend for backup repositories.
+//!
+//! The repository only needs whole-object reads and writes, flat listings
+//! and deletes, which every `StorageProvider` offers. Keeping that surface
+//! behind a trait lets the engine run against S3, B2, SFTP, WebDAV and the
+//! rest without provider-specific code, and lets tests use a plain
+//! in-memory map.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use crate::providers::{ProviderError, StorageProvider};
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::io::Write;
+
+/// Object store view of a repository. Paths are relative to the
+/// repository root and use `/` separators (`data/ab/ab12…`).
+#[async_trait]
+pub trait BackupBackend: Send {
+    async fn read(&mut self, path: &str) -> Result<Vec<u8>, String>;
+    async fn write(&mut self, path: &str, data: &[u8]) -> Result<(), String>;
+    /// Names and sizes of the entries directly under `dir`. A missing
+    /// directory lists as empty.
+    async fn list(&mut self, dir: &str) -> Result<Vec<(String, u64)>, String>;
+    async fn delete(&mut self, path: &str) -> Result<(), String>;
+    async fn exists(&mut self, path: &str) -> Result<bool, String>;
+}
+
+/// [`BackupBackend`] over any connected provider, rooted at `root`.
+pub struct ProviderBackend {
+    provider: Box<dyn StorageProvider>,
+    root: String,
+    /// Directories already created (or found) during this session.
+    known_dirs: HashSet<String>,
+}
+
+impl ProviderBackend {
+    pub fn new(provider: Box<dyn StorageProvider>, root: &str) -> Self {
+        let root = root.trim_end_matches('/');
+        Self {
+            provider,
+            root: if root.is_empty() {
+                "/".to_string()
+            } else {
+                root.to_string()
+            },
+            known_dirs: HashSet::new(),
+        }
+    }
+
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Give the provider back, e.g. to disconnect it.
+    pub fn into_provider(self) -> Box<dyn StorageProvider> {
+        self.provider
+    }
+
+    fn full_path(&self, rel: &str) -> String {
+        let rel = rel.trim_matches('/');
+        match (self.root.as_str(), rel) {
+            (root, "") => root.to_string(),
+            ("/", rel) => format!("/{}", rel),
+            (root, rel) => format!("{}/{}", root, rel),
+        }
+    }
+
+    /// Create `rel` and its parents. `mkdir` on an existing directory fails
+    /// on most providers, so errors are ignored here and surface on the
+    /// following upload instead.
+    async fn ensure_dir(&mut self, rel: &str) {
+        let mut current = String::new();
+        for part in rel.split('/').filter(|p| !p.is_empty()) {
+            if !current.is_empty() {
+                current.push('/');
+            }
+            current.push_str(part);
+            if self.known_dirs.contains(&current) {
+                continue;
+            }
+            let full = self.full_path(&current);
+            if !matches!(self.provider.exists(&full).await, Ok(true)) {
+                let _ = self.provider.mkdir(&full).await;
+            }
+            self.known_dirs.insert(current.clone());
+        }
+    }
+}
+
+#[async_trait]
+impl BackupBackend for ProviderBackend {
+    async fn read(&mut self, path: &str) -> Result<Vec<u8>, String> {
+        let full = self.full_path(path);
+        self.provider
+            .download_to_bytes(&full)
+            .await
+            .map_err(|e| format!("Read {}: {}", full, e))
+    }
+
+    async fn write(&mut self, path: &str, data: &[u8]) -> Result<(), String> {
+        if let Some((parent, _)) = path.rsplit_once('/') {
+            self.ensure_dir(parent).await;
+        }
+        let mut tmp = tempfile::NamedTempFile::new().map_err(|e| format!("Temp file: {}", e))?;
+        tmp.write_all(data)
+            .and_then(|_| tmp.flush())
+            .map_err(|e| format!("Temp file: {}", e))?;
+        let full = self.full_path(path);
+        self.provider
+            .upload(&tmp.path().to_string_lossy(), &full, None)
+            .await
+            .map_err(|e| format!("Write {}: {}", full, e))
+    }
+
+    async fn list(&mut self, dir: &str) -> Result<Vec<(String, u64)>, String> {
+        let full = self.full_path(dir);
+        match self.provider.list(&full).await {
+            Ok(entries) => Ok(entries
+                .into_iter()
+                .filter(|e| e.name != "." && e.name != "..")
+                .map(|e| (e.name, e.size))
+                .collect()),
+            Err(ProviderError::NotFound(_)) => Ok(Vec::new()),
+            Err(e) => Err(format!("List {}: {}", full, e)),
+        }
+    }
+
+    async fn delete(&mut self, path: &str) -> Result<(), String> {
+        let full = self.full_path(path);
+        self.provider
+            .delete(&full)
+            .await
+            .map_err(|e| format!("Delete {}: {}", full, e))
+    }
+
+    async fn exists(&mut self, path: &str) -> Result<bool, String> {
+        let full = self.full_path(path);
+        self.provider
+            .exists(&full)
+            .await
+            .map_err(|e| format!("Stat {}: {}", full, e))
+    }
+}
+
+/// In-memory backend for tests.
+#[cfg(test)]
+#[derive(Default, Clone)]
+pub struct MemoryBackend {
+    pub objects: std::sync::Arc<std::sync::Mutex<std::collections::BTreeMap<String, Vec<u8>>>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl BackupBackend for MemoryBackend {
+    async fn read(&mut self, path: &str) -> Result<Vec<u8>, String> {
+        self.objects
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| format!("{}: not found", path))
+    }
+
+    async fn write(&mut self, path: &str, data: &[u8]) -> Result<(), String> {
+        self.objects
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    async fn list(&mut self, dir: &str) -> Result<Vec<(String, u64)>, String> {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        let mut out: Vec<(String, u64)> = Vec::new();
+        for (path, data) in self.objects.lock().unwrap().iter() {
+            let Some(rest) = path.strip_prefix(&prefix) else {
+                continue;
+            };
+            match rest.split_once('/') {
+                Some((sub, _)) => {
+                    if out.last().map(|(n, _)| n.as_str()) != Some(sub) {
+                        out.push((sub.to_string(), 0));
+                    }
+                }
+                None => out.push((rest.to_string(), data.len() as u64)),
+            }
+        }
+        Ok(out)
+    }
+
+    async fn delete(&mut self, path: &str) -> Result<(), String> {
+        self.objects.lock().unwrap().remove(path);
+        Ok(())
+    }
+
+    async fn exists(&mut self, path: &str) -> Result<bool, String> {
+        Ok(self.objects.lock().unwrap().contains_key(path))
+    }
+}
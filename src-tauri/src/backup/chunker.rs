@@ -0,0 +1,255 @@
+//! Content-defined chunking (FastCDC with a gear rolling hash).
+//!
+//! Boundaries depend on the bytes around them, not on their offset, so an
+//! insertion near the start of a file only changes the chunks it touches and
+//! every later chunk deduplicates against the previous snapshot. The gear
+//! table is derived from a per-repository seed: two repositories cut the same
+//! file at different places, which keeps chunk sizes from leaking content
+//! fingerprints across repositories.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+
+/// Chunk size bounds, stored in the repository config so every client cuts
+/// files the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkerParams {
+    pub min_size: usize,
+    pub avg_size: usize,
+    pub max_size: usize,
+}
+
+impl Default for ChunkerParams {
+    /// 512 KiB / 1 MiB / 8 MiB: large enough that object-store request
+    /// counts stay reasonable, small enough to dedup edited files well.
+    fn default() -> Self {
+        Self {
+            min_size: 512 * 1024,
+            avg_size: 1024 * 1024,
+            max_size: 8 * 1024 * 1024,
+        }
+    }
+}
+
+impl ChunkerParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_size == 0
+            || self.min_size > self.avg_size
+            || self.avg_size > self.max_size
+            || !self.avg_size.is_power_of_two()
+        {
+            return Err(format!(
+                "Invalid chunker sizes {}/{}/{} (need 0 < min <= avg <= max, avg a power of two)",
+                self.min_size, self.avg_size, self.max_size
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Gear-hash chunker. Cheap to clone; holds no per-file state.
+#[derive(Clone)]
+pub struct Chunker {
+    gear: Box<[u64; 256]>,
+    params: ChunkerParams,
+    /// Stricter mask used before the average size (fewer early cuts).
+    mask_small: u64,
+    /// Looser mask used after the average size (fewer forced max cuts).
+    mask_large: u64,
+}
+
+impl Chunker {
+    pub fn new(seed: u64, params: ChunkerParams) -> Self {
+        let mut gear = Box::new([0u64; 256]);
+        let mut state = seed;
+        for slot in gear.iter_mut() {
+            *slot = splitmix64(&mut state);
+        }
+        let bits = params.avg_size.trailing_zeros();
+        Self {
+            gear,
+            params,
+            mask_small: top_bits_mask(bits + 1),
+            mask_large: top_bits_mask(bits.saturating_sub(1)),
+        }
+    }
+
+    pub fn params(&self) -> ChunkerParams {
+        self.params
+    }
+
+    /// Length of the next chunk at the start of `data`. `data` should hold at
+    /// least `max_size` bytes unless it is the tail of the input.
+    pub fn cut(&self, data: &[u8]) -> usize {
+        let ChunkerParams {
+            min_size,
+            avg_size,
+            max_size,
+        } = self.params;
+        if data.len() <= min_size {
+            return data.len();
+        }
+        let end = data.len().min(max_size);
+        let normal = avg_size.min(end);
+        let mut hash = 0u64;
+        let mut i = min_size;
+        while i < normal {
+            hash = (hash << 1).wrapping_add(self.gear[data[i] as usize]);
+            if hash & self.mask_small == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        while i < end {
+            hash = (hash << 1).wrapping_add(self.gear[data[i] as usize]);
+            if hash & self.mask_large == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        end
+    }
+
+    /// Split an in-memory buffer into chunks.
+    pub fn split<'a>(&self, data: &'a [u8]) -> Vec<&'a [u8]> {
+        let mut chunks = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            let n = self.cut(rest);
+            chunks.push(&rest[..n]);
+            rest = &rest[n..];
+        }
+        chunks
+    }
+
+    /// Stream chunks out of a reader, holding at most two `max_size`
+    /// buffers in memory.
+    pub fn reader<R: Read>(&self, inner: R) -> ChunkReader<'_, R> {
+        ChunkReader {
+            chunker: self,
+            inner,
+            buf: Vec::new(),
+            eof: false,
+        }
+    }
+}
+
+/// Iterator-like adapter returned by [`Chunker::reader`].
+pub struct ChunkReader<'a, R> {
+    chunker: &'a Chunker,
+    inner: R,
+    buf: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: Read> ChunkReader<'_, R> {
+    pub fn next_chunk(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        let max = self.chunker.params.max_size;
+        while !self.eof && self.buf.len() < max {
+            let start = self.buf.len();
+            self.buf.resize(max, 0);
+            let n = match self.inner.read(&mut self.buf[start..]) {
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => 0,
+                Err(e) => {
+                    self.buf.truncate(start);
+                    return Err(e);
+                }
+            };
+            self.buf.truncate(start + n);
+            if n == 0 {
+                self.eof = true;
+            }
+        }
+        if self.buf.is_empty() {
+            return Ok(None);
+        }
+        let n = self.chunker.cut(&self.buf);
+        let rest = self.buf.split_off(n);
+        Ok(Some(std::mem::replace(&mut self.buf, rest)))
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Mask selecting the `bits` most significant bits. The gear hash shifts
+/// left, so the high bits mix in the most input bytes.
+fn top_bits_mask(bits: u32) -> u64 {
+    match bits {
+        0 => 0,
+        b if b >= 64 => u64::MAX,
+        b => !0u64 << (64 - b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small() -> ChunkerParams {
+        ChunkerParams {
+            min_size: 256,
+            avg_size: 1024,
+            max_size: 4096,
+        }
+    }
+
+    fn pseudo_random(len: usize, mut seed: u64) -> Vec<u8> {
+        (0..len).map(|_| splitmix64(&mut seed) as u8).collect()
+    }
+
+    #[test]
+    fn chunks_respect_bounds_and_cover_input() {
+        let chunker = Chunker::new(7, small());
+        let data = pseudo_random(200_000, 1);
+        let chunks = chunker.split(&data);
+        assert_eq!(chunks.concat(), data);
+        for c in &chunks[..chunks.len() - 1] {
+            assert!(c.len() >= 256 && c.len() <= 4096, "len {}", c.len());
+        }
+        let avg = data.len() / chunks.len();
+        assert!((512..=2048).contains(&avg), "avg {}", avg);
+    }
+
+    #[test]
+    fn insertion_only_disturbs_nearby_chunks() {
+        let chunker = Chunker::new(7, small());
+        let data = pseudo_random(100_000, 2);
+        let mut edited = data.clone();
+        edited.splice(10..10, b"inserted bytes".iter().copied());
+        let before: std::collections::HashSet<&[u8]> = chunker.split(&data).into_iter().collect();
+        let after = chunker.split(&edited);
+        let shared = after.iter().filter(|c| before.contains(*c)).count();
+        assert!(shared + 3 >= after.len(), "{} of {}", shared, after.len());
+    }
+
+    #[test]
+    fn reader_matches_in_memory_split() {
+        let chunker = Chunker::new(99, small());
+        let data = pseudo_random(50_000, 3);
+        let mut reader = chunker.reader(std::io::Cursor::new(data.clone()));
+        let mut streamed = Vec::new();
+        while let Some(c) = reader.next_chunk().unwrap() {
+            streamed.push(c);
+        }
+        let split: Vec<Vec<u8>> = chunker.split(&data).iter().map(|c| c.to_vec()).collect();
+        assert_eq!(streamed, split);
+        assert!(ChunkerParams::default().validate().is_ok());
+        assert!(ChunkerParams {
+            min_size: 10,
+            avg_size: 1000,
+            max_size: 2000
+        }
+        .validate()
+        .is_err());
+    }
+}
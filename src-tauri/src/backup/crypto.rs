@@ -0,0 +1,227 @@
+//! Repository keys and object sealing.
+//!
+//! Same construction as AeroVault v2: an Argon2id key-encryption key (128 MiB,
+//! t=4, p=4 by default) unwraps a random master key with AES-KW (RFC 3394),
+//! and every object is compressed with zstd then sealed with AES-256-GCM-SIV.
+//! The object name is bound as associated data, so a server cannot swap two
+//! chunks or replay one snapshot file under another name. Chunk ids are
+//! HMAC-SHA256 of the plaintext under a key the server never sees.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use aes_gcm_siv::aead::{Aead, KeyInit, Payload};
+use aes_gcm_siv::{Aes256GcmSiv, Nonce};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// Leading byte of every sealed object.
+const SEAL_VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+/// zstd level for chunk payloads: fast, and most of the win on text.
+const ZSTD_LEVEL: i32 = 3;
+
+const PLAIN_RAW: u8 = 0;
+const PLAIN_ZSTD: u8 = 1;
+
+/// Argon2id parameters stored (with the salt) in the plaintext repo config.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub algorithm: String,
+    /// Memory cost in KiB.
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+    /// Base64 salt.
+    pub salt: String,
+}
+
+impl KdfParams {
+    /// AeroVault-grade cost with a fresh random salt.
+    pub fn strong() -> Self {
+        Self::with_cost(131072, 4, 4)
+    }
+
+    pub fn with_cost(m_cost: u32, t_cost: u32, p_cost: u32) -> Self {
+        Self {
+            algorithm: "argon2id".to_string(),
+            m_cost,
+            t_cost,
+            p_cost,
+            salt: base64::engine::general_purpose::STANDARD.encode(crate::crypto::random_bytes(32)),
+        }
+    }
+
+    pub fn derive(&self, password: &str) -> Result<Zeroizing<[u8; 32]>, String> {
+        if self.algorithm != "argon2id" {
+            return Err(format!("Unsupported KDF '{}'", self.algorithm));
+        }
+        let salt = base64::engine::general_purpose::STANDARD
+            .decode(&self.salt)
+            .map_err(|e| format!("Invalid KDF salt: {}", e))?;
+        let params = argon2::Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| format!("Argon2 params: {}", e))?;
+        let argon2 =
+            argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+        let mut key = Zeroizing::new([0u8; 32]);
+        argon2
+            .hash_password_into(password.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| format!("Argon2 derive: {}", e))?;
+        Ok(key)
+    }
+}
+
+/// 512-bit repository master key: 256-bit encryption key followed by a
+/// 256-bit chunk-id MAC key.
+pub struct MasterKey {
+    bytes: Zeroizing<[u8; 64]>,
+}
+
+impl MasterKey {
+    pub fn generate() -> Self {
+        let mut bytes = Zeroizing::new([0u8; 64]);
+        bytes.copy_from_slice(&crate::crypto::random_bytes(64));
+        Self { bytes }
+    }
+
+    /// Wrap the master key under `kek` with AES-KW.
+    pub fn wrap(&self, kek: &[u8; 32]) -> Result<Vec<u8>, String> {
+        let kek: aes_kw::Kek<aes_gcm::aes::Aes256> = aes_kw::Kek::from(*kek);
+        let mut out = vec![0u8; 64 + 8];
+        kek.wrap(self.bytes.as_ref(), &mut out)
+            .map_err(|e| format!("AES-KW wrap: {}", e))?;
+        Ok(out)
+    }
+
+    pub fn unwrap(kek: &[u8; 32], wrapped: &[u8]) -> Result<Self, String> {
+        if wrapped.len() != 64 + 8 {
+            return Err("Corrupted repository key".to_string());
+        }
+        let kek: aes_kw::Kek<aes_gcm::aes::Aes256> = aes_kw::Kek::from(*kek);
+        let mut bytes = Zeroizing::new([0u8; 64]);
+        kek.unwrap(wrapped, bytes.as_mut())
+            .map_err(|_| "Wrong password (repository key did not unwrap)".to_string())?;
+        Ok(Self { bytes })
+    }
+
+    fn enc_key(&self) -> &[u8] {
+        &self.bytes[..32]
+    }
+
+    fn mac_key(&self) -> &[u8] {
+        &self.bytes[32..]
+    }
+
+    /// Seed for the repository's gear table, derived so it never needs its
+    /// own storage.
+    pub fn chunker_seed(&self) -> u64 {
+        let hk = hkdf::Hkdf::<Sha256>::new(None, self.mac_key());
+        let mut seed = [0u8; 8];
+        hk.expand(b"aeroftp-backup-chunker", &mut seed)
+            .expect("HKDF expand");
+        u64::from_le_bytes(seed)
+    }
+
+    /// Keyed content id of a plaintext blob (64 hex chars).
+    pub fn blob_id(&self, data: &[u8]) -> String {
+        let mut mac =
+            <Hmac<Sha256> as Mac>::new_from_slice(self.mac_key()).expect("HMAC accepts any key");
+        mac.update(data);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Compress (when it helps) and encrypt `plaintext`, binding `name`.
+    pub fn seal(&self, name: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let mut inner = Vec::with_capacity(plaintext.len() + 1);
+        match zstd::bulk::compress(plaintext, ZSTD_LEVEL) {
+            Ok(packed) if packed.len() < plaintext.len() => {
+                inner.push(PLAIN_ZSTD);
+                inner.extend_from_slice(&packed);
+            }
+            _ => {
+                inner.push(PLAIN_RAW);
+                inner.extend_from_slice(plaintext);
+            }
+        }
+        let nonce_bytes = crate::crypto::random_bytes(NONCE_LEN);
+        let cipher = Aes256GcmSiv::new_from_slice(self.enc_key()).expect("32-byte key");
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce_bytes),
+                Payload {
+                    msg: &inner,
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|e| format!("AES-GCM-SIV encrypt: {}", e))?;
+        let mut out = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        out.push(SEAL_VERSION);
+        out.extend_from_slice(&nonce_bytes);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    /// Reverse of [`MasterKey::seal`]. Fails if the object was tampered
+    /// with or stored under a different name.
+    pub fn open(&self, name: &str, sealed: &[u8]) -> Result<Vec<u8>, String> {
+        if sealed.len() < 1 + NONCE_LEN || sealed[0] != SEAL_VERSION {
+            return Err(format!("{}: not a sealed backup object", name));
+        }
+        let cipher = Aes256GcmSiv::new_from_slice(self.enc_key()).expect("32-byte key");
+        let inner = cipher
+            .decrypt(
+                Nonce::from_slice(&sealed[1..1 + NONCE_LEN]),
+                Payload {
+                    msg: &sealed[1 + NONCE_LEN..],
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|_| format!("{}: authentication failed", name))?;
+        match inner.split_first() {
+            Some((&PLAIN_RAW, rest)) => Ok(rest.to_vec()),
+            Some((&PLAIN_ZSTD, rest)) => {
+                zstd::stream::decode_all(rest).map_err(|e| format!("{}: zstd decode: {}", name, e))
+            }
+            _ => Err(format!("{}: unknown payload encoding", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seal_round_trip_binds_name() {
+        let key = MasterKey::generate();
+        let text = b"hello hello hello hello hello hello".repeat(20);
+        let sealed = key.seal("data/ab/abc", &text).unwrap();
+        assert!(sealed.len() < text.len(), "compressible payload shrinks");
+        assert_eq!(key.open("data/ab/abc", &sealed).unwrap(), text);
+        assert!(key.open("data/ab/abd", &sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(key.open("data/ab/abc", &tampered).is_err());
+        assert!(MasterKey::generate().open("data/ab/abc", &sealed).is_err());
+    }
+
+    #[test]
+    fn master_key_wraps_under_password() {
+        let kdf = KdfParams::with_cost(1024, 1, 1);
+        let kek = kdf.derive("correct horse").unwrap();
+        let key = MasterKey::generate();
+        let wrapped = key.wrap(&kek).unwrap();
+
+        let back = MasterKey::unwrap(&kek, &wrapped).unwrap();
+        assert_eq!(back.blob_id(b"x"), key.blob_id(b"x"));
+        assert_eq!(back.chunker_seed(), key.chunker_seed());
+
+        let wrong = kdf.derive("wrong").unwrap();
+        assert!(MasterKey::unwrap(&wrong, &wrapped).is_err());
+    }
+}
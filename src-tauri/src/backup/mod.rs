@@ -0,0 +1,31 @@
+//! Backup engine: content-addressed, deduplicated, encrypted snapshots
+//! stored on any `StorageProvider`.
+//!
+//! Where sync mirrors the current state and `sync_versioning` keeps whole
+//! file copies, a backup repository keeps many point-in-time snapshots for
+//! roughly the cost of one: files are cut into content-defined chunks, each
+//! chunk is stored once (zstd, AES-256-GCM-SIV), and a snapshot is just a
+//! tree of chunk references. Retention (`forget`) and garbage collection
+//! (`prune`) are separate steps, restic-style.
+//!
+//! Exposes no Tauri or Clap types; the CLI drives it through
+//! [`Repository`] over a [`ProviderBackend`].
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+pub mod backend;
+pub mod chunker;
+pub mod crypto;
+pub mod repo;
+pub mod retention;
+
+pub use backend::{BackupBackend, ProviderBackend};
+pub use chunker::{Chunker, ChunkerParams};
+pub use crypto::{KdfParams, MasterKey};
+pub use repo::{
+    local_hostname, BackupOptions, BackupOutcome, BackupSummary, BlobRef, CheckReport,
+    ForgetDecision, NodeKind, PruneSummary, RepoConfig, Repository, RestoreOptions, RestoreSummary,
+    Snapshot, TreeNode, REPO_VERSION,
+};
+pub use retention::RetentionPolicy;
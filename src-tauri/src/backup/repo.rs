@@ -0,0 +1,1447 @@
+//! Backup repository: snapshots, deduplicated blobs, locks and maintenance.
+//!
+//! Layout under the repository root (every file except `config` is sealed
+//! with the repository key, see [`super::crypto`]):
+//!
+//! ```text
+//! config              plaintext JSON: version, KDF params, wrapped master key
+//! data/<ab>/<id>      one content-defined chunk per object, id = HMAC(plaintext)
+//! index/<id>          blob ids added by one backup run (dedup lookup table)
+//! snapshots/<id>      snapshot metadata; the file tree is itself stored as blobs
+//! locks/<id>          shared (backup, restore, check) or exclusive (forget, prune)
+//! ```
+//!
+//! Objects are written data first, then index, then snapshot, so an
+//! interrupted backup leaves only unreferenced blobs that `prune` removes.
+//! Nothing is ever rewritten in place: a snapshot is immutable once stored.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use super::backend::BackupBackend;
+use super::chunker::{Chunker, ChunkerParams};
+use super::crypto::{KdfParams, MasterKey};
+use super::retention::RetentionPolicy;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const REPO_VERSION: u32 = 1;
+const CONFIG_FILE: &str = "config";
+/// A lock not refreshed for this long belongs to a dead process.
+const LOCK_STALE_SECS: i64 = 30 * 60;
+/// Long backups rewrite their lock this often so it never looks stale.
+const LOCK_REFRESH: Duration = Duration::from_secs(5 * 60);
+
+/// Plaintext repository header.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoConfig {
+    pub version: u32,
+    pub id: String,
+    pub created: DateTime<Utc>,
+    pub kdf: KdfParams,
+    /// Base64 AES-KW wrapped master key.
+    pub key: String,
+    pub chunker: ChunkerParams,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+/// One chunk of a file: content id plus plaintext length, so readers can
+/// seek without fetching earlier chunks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobRef {
+    pub id: String,
+    pub size: u32,
+}
+
+/// Entry of a snapshot tree. Paths are relative to the backup source and use
+/// `/`; parents always precede their children.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeNode {
+    pub path: String,
+    pub kind: NodeKind,
+    #[serde(default)]
+    pub size: u64,
+    /// Modification time, Unix seconds.
+    pub mtime: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<BlobRef>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupSummary {
+    pub files: u64,
+    pub dirs: u64,
+    pub symlinks: u64,
+    /// Total plaintext size of the files in the snapshot.
+    pub bytes: u64,
+    pub files_new: u64,
+    pub files_changed: u64,
+    pub files_unmodified: u64,
+    pub blobs_new: u64,
+    /// Plaintext bytes that were not already in the repository.
+    pub bytes_new: u64,
+    /// Bytes actually uploaded after compression and encryption.
+    pub bytes_stored: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(skip)]
+    pub id: String,
+    pub time: DateTime<Utc>,
+    pub hostname: String,
+    /// Absolute path of the backed-up directory or file.
+    pub source: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Blobs holding the JSON-encoded `Vec<TreeNode>`.
+    pub tree: Vec<String>,
+    pub summary: BackupSummary,
+}
+
+impl Snapshot {
+    pub fn short_id(&self) -> &str {
+        &self.id[..self.id.len().min(8)]
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BackupOptions {
+    pub tags: Vec<String>,
+    /// Glob patterns matched against the relative path and the file name.
+    pub excludes: Vec<String>,
+    /// Override the recorded hostname (defaults to this machine).
+    pub hostname: Option<String>,
+    /// Snapshot whose unchanged files are reused without rereading them.
+    /// Defaults to the latest snapshot of the same host and source.
+    pub parent: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupOutcome {
+    pub snapshot: Snapshot,
+    /// Entries that could not be read and are missing from the snapshot,
+    /// and damaged snapshots skipped while looking for a parent.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RestoreOptions {
+    /// Glob patterns: restore only entries matching one of them (or lying
+    /// below a matching directory). Empty restores everything.
+    pub include: Vec<String>,
+    /// Glob patterns of entries (and subtrees) to leave out.
+    pub exclude: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RestoreSummary {
+    pub files: u64,
+    pub dirs: u64,
+    pub symlinks: u64,
+    pub bytes: u64,
+    /// Files already present with the same size and mtime.
+    pub unchanged: u64,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ForgetDecision {
+    pub id: String,
+    pub time: DateTime<Utc>,
+    pub hostname: String,
+    pub source: String,
+    pub keep: bool,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PruneSummary {
+    pub snapshots: usize,
+    pub blobs_total: usize,
+    pub blobs_referenced: usize,
+    pub blobs_deleted: usize,
+    pub bytes_freed: u64,
+    /// Referenced by a snapshot but absent from `data/`.
+    pub blobs_missing: usize,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CheckReport {
+    pub snapshots: usize,
+    pub blobs: usize,
+    pub blobs_read: usize,
+    pub blobs_unused: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexFile {
+    blobs: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LockFile {
+    hostname: String,
+    pid: u32,
+    time: DateTime<Utc>,
+    exclusive: bool,
+}
+
+/// Name recorded in snapshots and locks.
+pub fn local_hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| {
+            std::fs::read_to_string("/etc/hostname")
+                .ok()
+                .map(|s| s.trim().to_string())
+        })
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+fn blob_path(id: &str) -> String {
+    format!("data/{}/{}", &id[..2], id)
+}
+
+fn is_blob_id(id: &str) -> bool {
+    id.len() == 64
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase())
+}
+
+fn random_id() -> String {
+    hex::encode(crate::crypto::random_bytes(32))
+}
+
+/// `rel` as a relative path with no `..`, root or prefix components, so a
+/// snapshot can never write outside the restore target.
+fn safe_relative(rel: &str) -> Option<PathBuf> {
+    let path = Path::new(rel);
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!out.as_os_str().is_empty()).then_some(out)
+}
+
+fn compile_globs(patterns: &[String]) -> Result<Vec<globset::GlobMatcher>, String> {
+    patterns
+        .iter()
+        .map(|pat| {
+            globset::Glob::new(pat)
+                .map(|g| g.compile_matcher())
+                .map_err(|e| format!("Invalid pattern '{}': {}", pat, e))
+        })
+        .collect()
+}
+
+fn unix_mtime(meta: &std::fs::Metadata) -> i64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(unix)]
+fn unix_mode(meta: &std::fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(meta.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn unix_mode(_meta: &std::fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// True when a pattern matches `path`, its file name, or any ancestor, so a
+/// directory pattern selects its whole subtree.
+fn matches_path_or_ancestor(matchers: &[globset::GlobMatcher], path: &str) -> bool {
+    let mut current = path;
+    loop {
+        let name = current.rsplit('/').next().unwrap_or(current);
+        if matchers
+            .iter()
+            .any(|m| m.is_match(current) || m.is_match(name))
+        {
+            return true;
+        }
+        match current.rsplit_once('/') {
+            Some((parent, _)) => current = parent,
+            None => return false,
+        }
+    }
+}
+
+pub struct Repository<B: BackupBackend> {
+    backend: B,
+    config: RepoConfig,
+    key: MasterKey,
+    chunker: Chunker,
+    /// Blob ids known to exist, from the index plus this session's uploads.
+    known: HashSet<String>,
+    lock: Option<(String, bool, Instant)>,
+}
+
+impl<B: BackupBackend> Repository<B> {
+    /// Create a new repository. Fails if one already exists at the root.
+    pub async fn init(mut backend: B, password: &str, kdf: KdfParams) -> Result<Self, String> {
+        if backend.exists(CONFIG_FILE).await? {
+            return Err("A backup repository already exists at this location".to_string());
+        }
+        let key = MasterKey::generate();
+        let kek = kdf.derive(password)?;
+        let config = RepoConfig {
+            version: REPO_VERSION,
+            id: hex::encode(crate::crypto::random_bytes(16)),
+            created: Utc::now(),
+            kdf,
+            key: base64::engine::general_purpose::STANDARD.encode(key.wrap(&kek)?),
+            chunker: ChunkerParams::default(),
+        };
+        let json = serde_json::to_vec_pretty(&config).map_err(|e| e.to_string())?;
+        backend.write(CONFIG_FILE, &json).await?;
+        Ok(Self::assemble(backend, config, key))
+    }
+
+    /// Open an existing repository and load its blob index.
+    pub async fn open(mut backend: B, password: &str) -> Result<Self, String> {
+        let raw = backend
+            .read(CONFIG_FILE)
+            .await
+            .map_err(|e| format!("No backup repository found ({})", e))?;
+        let config: RepoConfig = serde_json::from_slice(&raw)
+            .map_err(|e| format!("Invalid repository config: {}", e))?;
+        if config.version != REPO_VERSION {
+            return Err(format!(
+                "Unsupported repository version {} (this build reads {})",
+                config.version, REPO_VERSION
+            ));
+        }
+        config.chunker.validate()?;
+        let wrapped = base64::engine::general_purpose::STANDARD
+            .decode(&config.key)
+            .map_err(|e| format!("Invalid repository key: {}", e))?;
+        let kek = config.kdf.derive(password)?;
+        let key = MasterKey::unwrap(&kek, &wrapped)?;
+        let mut repo = Self::assemble(backend, config, key);
+        repo.load_index().await?;
+        Ok(repo)
+    }
+
+    fn assemble(backend: B, config: RepoConfig, key: MasterKey) -> Self {
+        let chunker = Chunker::new(key.chunker_seed(), config.chunker);
+        Self {
+            backend,
+            config,
+            key,
+            chunker,
+            known: HashSet::new(),
+            lock: None,
+        }
+    }
+
+    pub fn config(&self) -> &RepoConfig {
+        &self.config
+    }
+
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+
+    async fn read_sealed(&mut self, name: &str) -> Result<Vec<u8>, String> {
+        let sealed = self.backend.read(name).await?;
+        self.key.open(name, &sealed)
+    }
+
+    async fn write_sealed(&mut self, name: &str, plaintext: &[u8]) -> Result<usize, String> {
+        let sealed = self.key.seal(name, plaintext)?;
+        self.backend.write(name, &sealed).await?;
+        Ok(sealed.len())
+    }
+
+    async fn load_index(&mut self) -> Result<(), String> {
+        self.known.clear();
+        for (name, _) in self.backend.list("index").await? {
+            let raw = self.read_sealed(&format!("index/{}", name)).await?;
+            let index: IndexFile = serde_json::from_slice(&raw)
+                .map_err(|e| format!("Invalid index {}: {}", name, e))?;
+            self.known.extend(index.blobs);
+        }
+        Ok(())
+    }
+
+    /// Store `data` unless an identical blob already exists. Returns the
+    /// reference and the number of bytes uploaded (0 when deduplicated).
+    async fn store_blob(&mut self, data: &[u8]) -> Result<(BlobRef, usize), String> {
+        let id = self.key.blob_id(data);
+        let blob = BlobRef {
+            id: id.clone(),
+            size: data.len() as u32,
+        };
+        if self.known.contains(&id) {
+            return Ok((blob, 0));
+        }
+        let stored = self.write_sealed(&blob_path(&id), data).await?;
+        self.known.insert(id);
+        Ok((blob, stored))
+    }
+
+    /// Fetch, decrypt and verify one blob.
+    pub async fn read_blob(&mut self, id: &str) -> Result<Vec<u8>, String> {
+        if !is_blob_id(id) {
+            return Err(format!("Invalid blob id '{}'", id));
+        }
+        let data = self.read_sealed(&blob_path(id)).await?;
+        if self.key.blob_id(&data) != id {
+            return Err(format!("Blob {} does not match its id", id));
+        }
+        Ok(data)
+    }
+
+    // ── Locks ─────────────────────────────────────────────────────
+
+    async fn read_locks(&mut self) -> Result<Vec<(String, LockFile)>, String> {
+        let mut locks = Vec::new();
+        for (name, _) in self.backend.list("locks").await? {
+            // A lock removed between list and read is simply gone.
+            let Ok(raw) = self.read_sealed(&format!("locks/{}", name)).await else {
+                continue;
+            };
+            if let Ok(lock) = serde_json::from_slice::<LockFile>(&raw) {
+                locks.push((name, lock));
+            }
+        }
+        Ok(locks)
+    }
+
+    async fn write_lock(&mut self, name: &str, exclusive: bool) -> Result<(), String> {
+        let lock = LockFile {
+            hostname: local_hostname(),
+            pid: std::process::id(),
+            time: Utc::now(),
+            exclusive,
+        };
+        let json = serde_json::to_vec(&lock).map_err(|e| e.to_string())?;
+        self.write_sealed(&format!("locks/{}", name), &json).await?;
+        Ok(())
+    }
+
+    /// Take a shared or exclusive lock. Writes the lock first and then
+    /// re-checks, so two clients racing for conflicting locks both back off.
+    pub async fn lock(&mut self, exclusive: bool) -> Result<(), String> {
+        let conflict = |locks: &[(String, LockFile)], own: Option<&str>| {
+            let now = Utc::now();
+            locks
+                .iter()
+                .filter(|(name, _)| Some(name.as_str()) != own)
+                .filter(|(_, l)| (now - l.time).num_seconds() < LOCK_STALE_SECS)
+                .find(|(_, l)| exclusive || l.exclusive)
+                .map(|(_, l)| {
+                    format!(
+                        "Repository is locked by {} (pid {}, {}lock since {}); wait or run 'backup unlock'",
+                        l.hostname,
+                        l.pid,
+                        if l.exclusive { "exclusive " } else { "" },
+                        l.time.format("%Y-%m-%d %H:%M:%S UTC")
+                    )
+                })
+        };
+        let locks = self.read_locks().await?;
+        if let Some(msg) = conflict(&locks, None) {
+            return Err(msg);
+        }
+        let name = random_id();
+        self.write_lock(&name, exclusive).await?;
+        let locks = self.read_locks().await?;
+        if let Some(msg) = conflict(&locks, Some(&name)) {
+            let _ = self.backend.delete(&format!("locks/{}", name)).await;
+            return Err(msg);
+        }
+        self.lock = Some((name, exclusive, Instant::now()));
+        Ok(())
+    }
+
+    async fn refresh_lock(&mut self) -> Result<(), String> {
+        if let Some((name, exclusive, at)) = self.lock.clone() {
+            if at.elapsed() >= LOCK_REFRESH {
+                self.write_lock(&name, exclusive).await?;
+                self.lock = Some((name, exclusive, Instant::now()));
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn unlock(&mut self) -> Result<(), String> {
+        if let Some((name, _, _)) = self.lock.take() {
+            self.backend.delete(&format!("locks/{}", name)).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove every lock, including ones held by live processes. Returns
+    /// how many were removed.
+    pub async fn break_locks(&mut self) -> Result<usize, String> {
+        let names = self.backend.list("locks").await?;
+        for (name, _) in &names {
+            self.backend.delete(&format!("locks/{}", name)).await?;
+        }
+        self.lock = None;
+        Ok(names.len())
+    }
+
+    // ── Snapshots and trees ───────────────────────────────────────
+
+    async fn load_snapshots(&mut self) -> Result<(Vec<Snapshot>, Vec<String>), String> {
+        let mut snapshots = Vec::new();
+        let mut errors = Vec::new();
+        for (name, _) in self.backend.list("snapshots").await? {
+            let parsed = match self.read_sealed(&format!("snapshots/{}", name)).await {
+                Ok(raw) => serde_json::from_slice::<Snapshot>(&raw).map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            match parsed {
+                Ok(mut snap) => {
+                    snap.id = name;
+                    snapshots.push(snap);
+                }
+                Err(e) => errors.push(format!("snapshot {}: {}", name, e)),
+            }
+        }
+        snapshots.sort_by(|a, b| a.time.cmp(&b.time).then_with(|| a.id.cmp(&b.id)));
+        Ok((snapshots, errors))
+    }
+
+    /// All readable snapshots, oldest first, plus one warning for each
+    /// snapshot file that could not be read or parsed. A damaged snapshot
+    /// is skipped rather than failing the listing; `check` reports it.
+    pub async fn snapshots(&mut self) -> Result<(Vec<Snapshot>, Vec<String>), String> {
+        self.load_snapshots().await
+    }
+
+    /// Resolve `latest` or a (unique) snapshot id prefix.
+    pub async fn find_snapshot(&mut self, spec: &str) -> Result<Snapshot, String> {
+        let (mut snapshots, _) = self.snapshots().await?;
+        if spec == "latest" {
+            return snapshots
+                .pop()
+                .ok_or_else(|| "Repository has no snapshots".to_string());
+        }
+        let mut matches: Vec<Snapshot> = snapshots
+            .into_iter()
+            .filter(|s| s.id.starts_with(spec))
+            .collect();
+        match matches.len() {
+            0 => Err(format!("No snapshot matches '{}'", spec)),
+            1 => Ok(matches.remove(0)),
+            n => Err(format!("'{}' is ambiguous ({} snapshots match)", spec, n)),
+        }
+    }
+
+    pub async fn load_tree(&mut self, snapshot: &Snapshot) -> Result<Vec<TreeNode>, String> {
+        let mut raw = Vec::new();
+        for id in &snapshot.tree {
+            raw.extend(self.read_blob(id).await?);
+        }
+        serde_json::from_slice(&raw)
+            .map_err(|e| format!("Invalid tree in snapshot {}: {}", snapshot.short_id(), e))
+    }
+
+    // ── Backup ────────────────────────────────────────────────────
+
+    /// Back up `source` (a directory or a single file) as a new snapshot.
+    pub async fn backup(
+        &mut self,
+        source: &Path,
+        opts: &BackupOptions,
+    ) -> Result<BackupOutcome, String> {
+        self.lock(false).await?;
+        let result = self.backup_locked(source, opts).await;
+        let _ = self.unlock().await;
+        result
+    }
+
+    async fn backup_locked(
+        &mut self,
+        source: &Path,
+        opts: &BackupOptions,
+    ) -> Result<BackupOutcome, String> {
+        let source = std::fs::canonicalize(source)
+            .map_err(|e| format!("Cannot read {}: {}", source.display(), e))?;
+        let source_str = source.to_string_lossy().to_string();
+        let hostname = opts.hostname.clone().unwrap_or_else(local_hostname);
+        let excludes = compile_globs(&opts.excludes)?;
+
+        let mut warnings = Vec::new();
+        let parent = match &opts.parent {
+            Some(spec) => Some(self.find_snapshot(spec).await?),
+            None => {
+                // An unreadable snapshot can't be a parent; it only costs
+                // dedup against its tree, so the backup goes on without it.
+                let (snapshots, skipped) = self.snapshots().await?;
+                warnings.extend(skipped);
+                snapshots
+                    .into_iter()
+                    .rev()
+                    .find(|s| s.hostname == hostname && s.source == source_str)
+            }
+        };
+        let previous: HashMap<String, TreeNode> = match &parent {
+            Some(p) => self
+                .load_tree(p)
+                .await?
+                .into_iter()
+                .filter(|n| n.kind == NodeKind::File)
+                .map(|n| (n.path.clone(), n))
+                .collect(),
+            None => HashMap::new(),
+        };
+
+        let single_file = source.is_file();
+        let mut nodes = Vec::new();
+        let mut summary = BackupSummary::default();
+        let mut new_blobs = Vec::new();
+
+        let mut walker = walkdir::WalkDir::new(&source)
+            .follow_links(false)
+            .sort_by_file_name()
+            .into_iter();
+        while let Some(entry) = walker.next() {
+            self.refresh_lock().await?;
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) => {
+                    warnings.push(e.to_string());
+                    continue;
+                }
+            };
+            let rel = if single_file {
+                entry.file_name().to_string_lossy().to_string()
+            } else {
+                entry
+                    .path()
+                    .strip_prefix(&source)
+                    .unwrap_or(entry.path())
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            };
+            if rel.is_empty() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy();
+            if excludes
+                .iter()
+                .any(|m| m.is_match(&rel) || m.is_match(&*name))
+            {
+                if entry.file_type().is_dir() {
+                    walker.skip_current_dir();
+                }
+                continue;
+            }
+            let meta = match entry.metadata() {
+                Ok(m) => m,
+                Err(e) => {
+                    warnings.push(format!("{}: {}", rel, e));
+                    continue;
+                }
+            };
+            let mut node = TreeNode {
+                path: rel.clone(),
+                kind: NodeKind::File,
+                size: 0,
+                mtime: unix_mtime(&meta),
+                mode: unix_mode(&meta),
+                target: None,
+                chunks: Vec::new(),
+            };
+            let file_type = entry.file_type();
+            if file_type.is_symlink() {
+                match std::fs::read_link(entry.path()) {
+                    Ok(target) => {
+                        node.kind = NodeKind::Symlink;
+                        node.target = Some(target.to_string_lossy().to_string());
+                        summary.symlinks += 1;
+                    }
+                    Err(e) => {
+                        warnings.push(format!("{}: {}", rel, e));
+                        continue;
+                    }
+                }
+            } else if file_type.is_dir() {
+                node.kind = NodeKind::Dir;
+                summary.dirs += 1;
+            } else if file_type.is_file() {
+                node.size = meta.len();
+                let unchanged = previous
+                    .get(&rel)
+                    .filter(|p| p.size == node.size && p.mtime == node.mtime);
+                if let Some(prev) = unchanged {
+                    node.chunks = prev.chunks.clone();
+                    summary.files_unmodified += 1;
+                } else {
+                    match self
+                        .store_file(entry.path(), &mut summary, &mut new_blobs)
+                        .await
+                    {
+                        Ok(chunks) => node.chunks = chunks,
+                        Err(e) => {
+                            warnings.push(format!("{}: {}", rel, e));
+                            continue;
+                        }
+                    }
+                    if previous.contains_key(&rel) {
+                        summary.files_changed += 1;
+                    } else {
+                        summary.files_new += 1;
+                    }
+                }
+                summary.files += 1;
+                summary.bytes += node.size;
+            } else {
+                // Sockets, FIFOs and device nodes are not backed up.
+                continue;
+            }
+            nodes.push(node);
+        }
+
+        let tree_json = serde_json::to_vec(&nodes).map_err(|e| e.to_string())?;
+        let mut tree = Vec::new();
+        for piece in self.chunker.clone().split(&tree_json) {
+            let (blob, stored) = self.store_blob(piece).await?;
+            if stored > 0 {
+                summary.bytes_stored += stored as u64;
+                new_blobs.push(blob.id.clone());
+            }
+            tree.push(blob.id);
+        }
+
+        if !new_blobs.is_empty() {
+            let index =
+                serde_json::to_vec(&IndexFile { blobs: new_blobs }).map_err(|e| e.to_string())?;
+            self.write_sealed(&format!("index/{}", random_id()), &index)
+                .await?;
+        }
+
+        let mut snapshot = Snapshot {
+            id: String::new(),
+            time: Utc::now(),
+            hostname,
+            source: source_str,
+            tags: opts.tags.clone(),
+            parent: parent.map(|p| p.id),
+            tree,
+            summary,
+        };
+        let json = serde_json::to_vec(&snapshot).map_err(|e| e.to_string())?;
+        snapshot.id = hex::encode(Sha256::digest(&json));
+        self.write_sealed(&format!("snapshots/{}", snapshot.id), &json)
+            .await?;
+        Ok(BackupOutcome { snapshot, warnings })
+    }
+
+    async fn store_file(
+        &mut self,
+        path: &Path,
+        summary: &mut BackupSummary,
+        new_blobs: &mut Vec<String>,
+    ) -> Result<Vec<BlobRef>, String> {
+        let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        let chunker = self.chunker.clone();
+        let mut reader = chunker.reader(std::io::BufReader::new(file));
+        let mut chunks = Vec::new();
+        while let Some(chunk) = reader.next_chunk().map_err(|e| e.to_string())? {
+            let (blob, stored) = self.store_blob(&chunk).await?;
+            if stored > 0 {
+                summary.blobs_new += 1;
+                summary.bytes_new += chunk.len() as u64;
+                summary.bytes_stored += stored as u64;
+                new_blobs.push(blob.id.clone());
+            }
+            chunks.push(blob);
+            self.refresh_lock().await?;
+        }
+        Ok(chunks)
+    }
+
+    // ── Restore ───────────────────────────────────────────────────
+
+    /// Restore `snapshot` under `target`. Files already present with the
+    /// same size and mtime are left alone, so an interrupted restore can be
+    /// re-run cheaply.
+    pub async fn restore(
+        &mut self,
+        snapshot: &Snapshot,
+        target: &Path,
+        opts: &RestoreOptions,
+    ) -> Result<RestoreSummary, String> {
+        self.lock(false).await?;
+        let result = self.restore_locked(snapshot, target, opts).await;
+        let _ = self.unlock().await;
+        result
+    }
+
+    async fn restore_locked(
+        &mut self,
+        snapshot: &Snapshot,
+        target: &Path,
+        opts: &RestoreOptions,
+    ) -> Result<RestoreSummary, String> {
+        let nodes = self.load_tree(snapshot).await?;
+        std::fs::create_dir_all(target)
+            .map_err(|e| format!("Cannot create {}: {}", target.display(), e))?;
+        let include = compile_globs(&opts.include)?;
+        let exclude = compile_globs(&opts.exclude)?;
+        let mut summary = RestoreSummary::default();
+        let mut dirs = Vec::new();
+
+        let selected = nodes.iter().filter(|n| {
+            (include.is_empty() || matches_path_or_ancestor(&include, &n.path))
+                && !matches_path_or_ancestor(&exclude, &n.path)
+        });
+        for node in selected {
+            let Some(rel) = safe_relative(&node.path) else {
+                summary
+                    .warnings
+                    .push(format!("{}: unsafe path skipped", node.path));
+                continue;
+            };
+            let dest = target.join(rel);
+            let outcome = match node.kind {
+                NodeKind::Dir => std::fs::create_dir_all(&dest).map(|_| {
+                    summary.dirs += 1;
+                    dirs.push((dest.clone(), node));
+                }),
+                NodeKind::Symlink => restore_symlink(&dest, node.target.as_deref().unwrap_or(""))
+                    .map(|_| summary.symlinks += 1),
+                NodeKind::File => {
+                    if file_matches(&dest, node) {
+                        summary.unchanged += 1;
+                        continue;
+                    }
+                    match self.restore_file(&dest, node).await {
+                        Ok(()) => {
+                            summary.files += 1;
+                            summary.bytes += node.size;
+                            apply_node_meta(&dest, node)
+                        }
+                        Err(e) => {
+                            summary.warnings.push(format!("{}: {}", node.path, e));
+                            continue;
+                        }
+                    }
+                }
+            };
+            if let Err(e) = outcome {
+                summary.warnings.push(format!("{}: {}", node.path, e));
+            }
+        }
+        // Children are written first, so directory times are set last,
+        // deepest first.
+        for (dest, node) in dirs.iter().rev() {
+            if let Err(e) = apply_node_meta(dest, node) {
+                summary.warnings.push(format!("{}: {}", node.path, e));
+            }
+        }
+        Ok(summary)
+    }
+
+    async fn restore_file(&mut self, dest: &Path, node: &TreeNode) -> Result<(), String> {
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut tmp_name = dest.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".aerobackup-tmp");
+        let tmp = dest.with_file_name(tmp_name);
+        let written = self.write_chunks(&tmp, &node.chunks).await;
+        let result = written.and_then(|_| std::fs::rename(&tmp, dest).map_err(|e| e.to_string()));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    async fn write_chunks(&mut self, path: &Path, chunks: &[BlobRef]) -> Result<(), String> {
+        use std::io::Write;
+        let mut file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+        for chunk in chunks {
+            let data = self.read_blob(&chunk.id).await?;
+            if data.len() != chunk.size as usize {
+                return Err(format!("blob {} has the wrong length", chunk.id));
+            }
+            file.write_all(&data).map_err(|e| e.to_string())?;
+        }
+        file.sync_all().map_err(|e| e.to_string())
+    }
+
+    // ── Forget and prune ──────────────────────────────────────────
+
+    /// Apply `policy` to each (hostname, source) group and delete the
+    /// snapshots it does not keep. Blobs are only freed by [`Self::prune`].
+    pub async fn forget(
+        &mut self,
+        policy: &RetentionPolicy,
+        dry_run: bool,
+    ) -> Result<Vec<ForgetDecision>, String> {
+        if policy.is_empty() {
+            return Err(
+                "Refusing to forget every snapshot: set at least one --keep-* rule".to_string(),
+            );
+        }
+        if !dry_run {
+            self.lock(true).await?;
+        }
+        let result = self.forget_locked(policy, dry_run).await;
+        if !dry_run {
+            let _ = self.unlock().await;
+        }
+        result
+    }
+
+    async fn forget_locked(
+        &mut self,
+        policy: &RetentionPolicy,
+        dry_run: bool,
+    ) -> Result<Vec<ForgetDecision>, String> {
+        // An unreadable snapshot has no time or group to apply the policy
+        // to, so it is left alone.
+        let (snapshots, _) = self.snapshots().await?;
+        let mut groups: HashMap<(String, String), Vec<&Snapshot>> = HashMap::new();
+        for snap in &snapshots {
+            groups
+                .entry((snap.hostname.clone(), snap.source.clone()))
+                .or_default()
+                .push(snap);
+        }
+        let mut decisions = Vec::new();
+        for group in groups.values() {
+            let times: Vec<DateTime<Utc>> = group.iter().map(|s| s.time).collect();
+            for (snap, reasons) in group.iter().zip(policy.apply(&times)) {
+                decisions.push(ForgetDecision {
+                    id: snap.id.clone(),
+                    time: snap.time,
+                    hostname: snap.hostname.clone(),
+                    source: snap.source.clone(),
+                    keep: !reasons.is_empty(),
+                    reasons: reasons.iter().map(|r| r.to_string()).collect(),
+                });
+            }
+        }
+        decisions.sort_by(|a, b| {
+            (&a.hostname, &a.source, a.time).cmp(&(&b.hostname, &b.source, b.time))
+        });
+        if !dry_run {
+            for d in decisions.iter().filter(|d| !d.keep) {
+                self.backend.delete(&format!("snapshots/{}", d.id)).await?;
+            }
+        }
+        Ok(decisions)
+    }
+
+    /// Delete specific snapshots by id or unique id prefix.
+    pub async fn forget_snapshots(&mut self, specs: &[String]) -> Result<Vec<Snapshot>, String> {
+        self.lock(true).await?;
+        let mut removed = Vec::new();
+        let mut result = Ok(());
+        for spec in specs {
+            match self.find_snapshot(spec).await {
+                Ok(snap) => {
+                    if let Err(e) = self.backend.delete(&format!("snapshots/{}", snap.id)).await {
+                        result = Err(e);
+                        break;
+                    }
+                    removed.push(snap);
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        let _ = self.unlock().await;
+        result.map(|_| removed)
+    }
+
+    /// Blob ids present in `data/`, with their stored sizes.
+    async fn list_data(&mut self) -> Result<HashMap<String, u64>, String> {
+        let mut present = HashMap::new();
+        for (prefix, _) in self.backend.list("data").await? {
+            for (name, size) in self.backend.list(&format!("data/{}", prefix)).await? {
+                if is_blob_id(&name) {
+                    present.insert(name, size);
+                }
+            }
+        }
+        Ok(present)
+    }
+
+    async fn referenced_blobs(
+        &mut self,
+        snapshots: &[Snapshot],
+        errors: &mut Vec<String>,
+    ) -> Result<HashSet<String>, String> {
+        let mut referenced = HashSet::new();
+        for snap in snapshots {
+            referenced.extend(snap.tree.iter().cloned());
+            match self.load_tree(snap).await {
+                Ok(nodes) => referenced.extend(
+                    nodes
+                        .into_iter()
+                        .flat_map(|n| n.chunks.into_iter().map(|c| c.id)),
+                ),
+                Err(e) => errors.push(e),
+            }
+        }
+        Ok(referenced)
+    }
+
+    /// Delete blobs no snapshot references and rewrite the index.
+    pub async fn prune(&mut self, dry_run: bool) -> Result<PruneSummary, String> {
+        self.lock(true).await?;
+        let result = self.prune_locked(dry_run).await;
+        let _ = self.unlock().await;
+        result
+    }
+
+    async fn prune_locked(&mut self, dry_run: bool) -> Result<PruneSummary, String> {
+        let (snapshots, mut errors) = self.load_snapshots().await?;
+        let referenced = self.referenced_blobs(&snapshots, &mut errors).await?;
+        if let Some(e) = errors.into_iter().next() {
+            // Deleting blobs while a snapshot or tree is unreadable could
+            // destroy data it still needs.
+            return Err(format!("Prune aborted: {}", e));
+        }
+        let present = self.list_data().await?;
+        let unused: Vec<(&String, &u64)> = present
+            .iter()
+            .filter(|(id, _)| !referenced.contains(*id))
+            .collect();
+        let mut summary = PruneSummary {
+            snapshots: snapshots.len(),
+            blobs_total: present.len(),
+            blobs_referenced: present.len() - unused.len(),
+            blobs_deleted: unused.len(),
+            bytes_freed: unused.iter().map(|(_, size)| **size).sum(),
+            blobs_missing: referenced
+                .iter()
+                .filter(|id| !present.contains_key(*id))
+                .count(),
+            dry_run,
+        };
+        if dry_run {
+            return Ok(summary);
+        }
+
+        // New index first: if anything below fails, the old indexes still
+        // cover every blob.
+        let keep: Vec<String> = present
+            .keys()
+            .filter(|id| referenced.contains(*id))
+            .cloned()
+            .collect();
+        let old_indexes = self.backend.list("index").await?;
+        let index_name = random_id();
+        let index = serde_json::to_vec(&IndexFile {
+            blobs: keep.clone(),
+        })
+        .map_err(|e| e.to_string())?;
+        self.write_sealed(&format!("index/{}", index_name), &index)
+            .await?;
+        for (name, _) in old_indexes {
+            if name != index_name {
+                self.backend.delete(&format!("index/{}", name)).await?;
+            }
+        }
+        self.known = keep.into_iter().collect();
+
+        let mut deleted = 0;
+        for (id, _) in unused {
+            self.backend.delete(&blob_path(id)).await?;
+            deleted += 1;
+        }
+        summary.blobs_deleted = deleted;
+        Ok(summary)
+    }
+
+    // ── Check ─────────────────────────────────────────────────────
+
+    /// Verify that every snapshot decrypts, every tree parses and every
+    /// referenced blob exists. With `read_data`, also download and verify
+    /// every blob.
+    pub async fn check(&mut self, read_data: bool) -> Result<CheckReport, String> {
+        self.lock(false).await?;
+        let result = self.check_locked(read_data).await;
+        let _ = self.unlock().await;
+        result
+    }
+
+    async fn check_locked(&mut self, read_data: bool) -> Result<CheckReport, String> {
+        let (snapshots, mut errors) = self.load_snapshots().await?;
+        let mut referenced = HashSet::new();
+        for snap in &snapshots {
+            referenced.extend(snap.tree.iter().cloned());
+            match self.load_tree(snap).await {
+                Ok(nodes) => {
+                    for node in nodes {
+                        referenced.extend(node.chunks.into_iter().map(|c| c.id));
+                    }
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        let present = self.list_data().await?;
+        let mut missing: Vec<&String> = referenced
+            .iter()
+            .filter(|id| !present.contains_key(*id))
+            .collect();
+        missing.sort();
+        for id in missing {
+            errors.push(format!("blob {} is referenced but missing", id));
+        }
+        let mut stale_index: Vec<&String> = self
+            .known
+            .iter()
+            .filter(|id| !present.contains_key(*id))
+            .collect();
+        stale_index.sort();
+        for id in stale_index {
+            errors.push(format!(
+                "index lists missing blob {} (run prune to rebuild the index)",
+                id
+            ));
+        }
+
+        let mut report = CheckReport {
+            snapshots: snapshots.len(),
+            blobs: present.len(),
+            blobs_unused: present
+                .keys()
+                .filter(|id| !referenced.contains(*id))
+                .count(),
+            ..Default::default()
+        };
+        if read_data {
+            let mut ids: Vec<String> = present.into_keys().collect();
+            ids.sort();
+            for id in ids {
+                self.refresh_lock().await?;
+                match self.read_blob(&id).await {
+                    Ok(_) => report.blobs_read += 1,
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+        report.errors = errors;
+        Ok(report)
+    }
+}
+
+fn file_matches(dest: &Path, node: &TreeNode) -> bool {
+    std::fs::symlink_metadata(dest)
+        .map(|m| m.is_file() && m.len() == node.size && unix_mtime(&m) == node.mtime)
+        .unwrap_or(false)
+}
+
+#[cfg(unix)]
+fn restore_symlink(dest: &Path, target: &str) -> std::io::Result<()> {
+    if std::fs::symlink_metadata(dest).is_ok() {
+        std::fs::remove_file(dest)?;
+    }
+    std::os::unix::fs::symlink(target, dest)
+}
+
+#[cfg(not(unix))]
+fn restore_symlink(_dest: &Path, _target: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "symlinks are not restored on this platform",
+    ))
+}
+
+fn apply_node_meta(dest: &Path, node: &TreeNode) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if let Some(mode) = node.mode {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dest, std::fs::Permissions::from_mode(mode))?;
+    }
+    filetime::set_file_mtime(dest, filetime::FileTime::from_unix_time(node.mtime, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::backend::MemoryBackend;
+    use super::*;
+
+    fn fast_kdf() -> KdfParams {
+        KdfParams::with_cost(1024, 1, 1)
+    }
+
+    async fn small_repo(backend: MemoryBackend) -> Repository<MemoryBackend> {
+        let mut repo = Repository::init(backend, "pw", fast_kdf()).await.unwrap();
+        // Small chunks so the tests exercise multi-chunk files cheaply.
+        repo.config.chunker = ChunkerParams {
+            min_size: 1024,
+            avg_size: 4096,
+            max_size: 16384,
+        };
+        repo.chunker = Chunker::new(repo.key.chunker_seed(), repo.config.chunker);
+        repo
+    }
+
+    fn noise(len: usize, seed: u8) -> Vec<u8> {
+        let mut x = seed as u32 | 1;
+        (0..len)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn backup_dedups_and_restores() {
+        let src = tempfile::tempdir().unwrap();
+        std::fs::create_dir(src.path().join("sub")).unwrap();
+        std::fs::write(src.path().join("sub/big.bin"), noise(100_000, 1)).unwrap();
+        std::fs::write(src.path().join("copy.bin"), noise(100_000, 1)).unwrap();
+        std::fs::write(src.path().join("skip.tmp"), b"ignored").unwrap();
+        std::fs::write(src.path().join("empty"), b"").unwrap();
+
+        let backend = MemoryBackend::default();
+        let mut repo = small_repo(backend.clone()).await;
+        let opts = BackupOptions {
+            excludes: vec!["*.tmp".to_string()],
+            ..Default::default()
+        };
+        let first = repo.backup(src.path(), &opts).await.unwrap();
+        assert!(first.warnings.is_empty());
+        let s = &first.snapshot.summary;
+        assert_eq!((s.files, s.dirs, s.files_new), (3, 1, 3));
+        // The identical copy adds no data.
+        assert!(s.bytes_new <= 100_000, "bytes_new {}", s.bytes_new);
+
+        // Append to one file: only its tail is new, the other is reused.
+        let mut grown = noise(100_000, 1);
+        grown.extend(noise(5_000, 9));
+        std::fs::write(src.path().join("sub/big.bin"), &grown).unwrap();
+        filetime::set_file_mtime(
+            src.path().join("sub/big.bin"),
+            filetime::FileTime::from_unix_time(1_000_000, 0),
+        )
+        .unwrap();
+        let second = repo.backup(src.path(), &opts).await.unwrap();
+        let s2 = &second.snapshot.summary;
+        assert_eq!((s2.files_changed, s2.files_unmodified), (1, 2));
+        assert!(s2.bytes_new < 30_000, "bytes_new {}", s2.bytes_new);
+        assert_eq!(
+            second.snapshot.parent.as_deref(),
+            Some(first.snapshot.id.as_str())
+        );
+
+        // Reopen with the password and restore the latest snapshot.
+        let mut repo = Repository::open(repo.into_backend(), "pw").await.unwrap();
+        assert!(Repository::open(backend.clone(), "nope").await.is_err());
+        let latest = repo.find_snapshot("latest").await.unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let summary = repo
+            .restore(&latest, out.path(), &RestoreOptions::default())
+            .await
+            .unwrap();
+        assert!(summary.warnings.is_empty(), "{:?}", summary.warnings);
+        assert_eq!(
+            std::fs::read(out.path().join("sub/big.bin")).unwrap(),
+            grown
+        );
+        assert!(!out.path().join("skip.tmp").exists());
+        let mtime = std::fs::metadata(out.path().join("sub/big.bin"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        assert_eq!(
+            mtime
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            1_000_000
+        );
+
+        // Re-running the restore rewrites nothing.
+        let again = repo
+            .restore(&latest, out.path(), &RestoreOptions::default())
+            .await
+            .unwrap();
+        assert_eq!((again.files, again.unchanged), (0, 3));
+
+        let partial = tempfile::tempdir().unwrap();
+        let only_sub = RestoreOptions {
+            include: vec!["sub".to_string()],
+            ..Default::default()
+        };
+        let summary = repo
+            .restore(&latest, partial.path(), &only_sub)
+            .await
+            .unwrap();
+        assert_eq!(summary.files, 1);
+        assert!(partial.path().join("sub/big.bin").exists());
+        assert!(!partial.path().join("copy.bin").exists());
+
+        let report = repo.check(true).await.unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.snapshots, 2);
+        assert_eq!(report.blobs_read, report.blobs);
+        // No locks left behind.
+        assert!(backend.clone().list("locks").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn forget_and_prune_free_unreferenced_data() {
+        let src = tempfile::tempdir().unwrap();
+        let backend = MemoryBackend::default();
+        let mut repo = small_repo(backend.clone()).await;
+        for seed in 1..=3 {
+            std::fs::write(src.path().join("f.bin"), noise(40_000, seed)).unwrap();
+            filetime::set_file_mtime(
+                src.path().join("f.bin"),
+                filetime::FileTime::from_unix_time(seed as i64 * 1000, 0),
+            )
+            .unwrap();
+            repo.backup(src.path(), &BackupOptions::default())
+                .await
+                .unwrap();
+        }
+        let policy = RetentionPolicy {
+            keep_last: 1,
+            ..Default::default()
+        };
+        let preview = repo.forget(&policy, true).await.unwrap();
+        assert_eq!(preview.iter().filter(|d| !d.keep).count(), 2);
+        assert_eq!(repo.snapshots().await.unwrap().0.len(), 3);
+
+        repo.forget(&policy, false).await.unwrap();
+        assert_eq!(repo.snapshots().await.unwrap().0.len(), 1);
+        assert!(repo
+            .forget(&RetentionPolicy::default(), false)
+            .await
+            .is_err());
+
+        let pruned = repo.prune(false).await.unwrap();
+        assert!(pruned.blobs_deleted > 0);
+        assert_eq!(pruned.blobs_missing, 0);
+        let report = repo.check(true).await.unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.blobs_unused, 0);
+
+        // A corrupted blob is caught by check --read-data.
+        let victim = {
+            let objects = backend.objects.lock().unwrap();
+            objects
+                .keys()
+                .find(|k| k.starts_with("data/"))
+                .unwrap()
+                .clone()
+        };
+        backend.objects.lock().unwrap().get_mut(&victim).unwrap()[20] ^= 0xff;
+        let report = repo.check(true).await.unwrap();
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn damaged_snapshot_is_skipped_except_by_prune() {
+        let src = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join("f.bin"), noise(20_000, 7)).unwrap();
+        let backend = MemoryBackend::default();
+        let mut repo = small_repo(backend.clone()).await;
+        for _ in 0..2 {
+            repo.backup(src.path(), &BackupOptions::default())
+                .await
+                .unwrap();
+        }
+        let (snapshots, _) = repo.snapshots().await.unwrap();
+        let victim = format!("snapshots/{}", snapshots[1].id);
+        backend.objects.lock().unwrap().get_mut(&victim).unwrap()[20] ^= 0xff;
+
+        let (snapshots, skipped) = repo.snapshots().await.unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains(&victim["snapshots/".len()..]));
+
+        // The damaged latest snapshot can't be the parent; the older one is.
+        let outcome = repo
+            .backup(src.path(), &BackupOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(outcome.warnings.len(), 1);
+        assert_eq!(
+            outcome.snapshot.parent.as_deref(),
+            Some(snapshots[0].id.as_str())
+        );
+
+        let policy = RetentionPolicy {
+            keep_last: 1,
+            ..Default::default()
+        };
+        let decisions = repo.forget(&policy, true).await.unwrap();
+        assert_eq!(decisions.len(), 2);
+
+        let err = repo.prune(true).await.unwrap_err();
+        assert!(err.starts_with("Prune aborted"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn exclusive_lock_blocks_other_clients() {
+        let backend = MemoryBackend::default();
+        let mut a = small_repo(backend.clone()).await;
+        let mut b = Repository::open(backend.clone(), "pw").await.unwrap();
+        a.lock(true).await.unwrap();
+        let err = b.lock(false).await.unwrap_err();
+        assert!(err.contains("locked"), "{}", err);
+        a.unlock().await.unwrap();
+        b.lock(false).await.unwrap();
+        // Shared locks coexist; exclusive waits for them.
+        a.lock(false).await.unwrap();
+        assert!(a.lock(true).await.is_err());
+        assert_eq!(b.break_locks().await.unwrap(), 2);
+    }
+
+    #[test]
+    fn restore_paths_stay_inside_target() {
+        assert!(safe_relative("../etc/passwd").is_none());
+        assert!(safe_relative("/etc/passwd").is_none());
+        assert!(safe_relative("a/../../b").is_none());
+        assert_eq!(safe_relative("a/./b"), Some(PathBuf::from("a/b")));
+        let docs = compile_globs(&["docs".to_string()]).unwrap();
+        assert!(matches_path_or_ancestor(&docs, "docs/a/b.txt"));
+        assert!(!matches_path_or_ancestor(&docs, "docsx/a.txt"));
+        let txt = compile_globs(&["*.txt".to_string()]).unwrap();
+        assert!(matches_path_or_ancestor(&txt, "a/b.txt"));
+        assert!(!matches_path_or_ancestor(&txt, "a/b.bin"));
+    }
+}
@@ -0,0 +1,138 @@
+//! Snapshot retention policies for `backup forget`.
+//!
+//! Each `keep_*` rule walks the snapshots newest first and keeps the newest
+//! snapshot of each hour/day/week/month/year bucket until its count runs out.
+//! A snapshot is kept when any rule keeps it, the same semantics as restic's
+//! `forget --keep-*`, so existing schedules translate one to one. Buckets are
+//! computed in local time.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    #[serde(default)]
+    pub keep_last: u32,
+    #[serde(default)]
+    pub keep_hourly: u32,
+    #[serde(default)]
+    pub keep_daily: u32,
+    #[serde(default)]
+    pub keep_weekly: u32,
+    #[serde(default)]
+    pub keep_monthly: u32,
+    #[serde(default)]
+    pub keep_yearly: u32,
+}
+
+impl RetentionPolicy {
+    /// True when no rule is set: applying it would forget everything, so
+    /// callers refuse it instead.
+    pub fn is_empty(&self) -> bool {
+        self.rules().iter().all(|(_, count, _)| *count == 0)
+    }
+
+    fn rules(&self) -> [(&'static str, u32, &'static str); 6] {
+        [
+            ("last", self.keep_last, ""),
+            ("hourly", self.keep_hourly, "%Y-%m-%d %H"),
+            ("daily", self.keep_daily, "%Y-%m-%d"),
+            ("weekly", self.keep_weekly, "%G-W%V"),
+            ("monthly", self.keep_monthly, "%Y-%m"),
+            ("yearly", self.keep_yearly, "%Y"),
+        ]
+    }
+
+    /// For each entry of `times`, the rules that keep it. An empty list
+    /// means the snapshot is forgotten.
+    pub fn apply(&self, times: &[DateTime<Utc>]) -> Vec<Vec<&'static str>> {
+        let mut order: Vec<usize> = (0..times.len()).collect();
+        order.sort_by(|a, b| times[*b].cmp(&times[*a]));
+
+        let mut reasons = vec![Vec::new(); times.len()];
+        for (rule, count, bucket_format) in self.rules() {
+            let mut remaining = count;
+            let mut last_bucket: Option<String> = None;
+            for &idx in &order {
+                if remaining == 0 {
+                    break;
+                }
+                let bucket = if bucket_format.is_empty() {
+                    idx.to_string()
+                } else {
+                    times[idx]
+                        .with_timezone(&Local)
+                        .format(bucket_format)
+                        .to_string()
+                };
+                if last_bucket.as_deref() != Some(bucket.as_str()) {
+                    reasons[idx].push(rule);
+                    remaining -= 1;
+                    last_bucket = Some(bucket);
+                }
+            }
+        }
+        reasons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn keeps_newest_per_bucket() {
+        let base = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        // Two snapshots a day, 40 days.
+        let times: Vec<DateTime<Utc>> = (0..80)
+            .map(|i| base + Duration::hours(12 * i as i64))
+            .collect();
+        let policy = RetentionPolicy {
+            keep_last: 2,
+            keep_daily: 7,
+            keep_monthly: 3,
+            ..Default::default()
+        };
+        let reasons = policy.apply(&times);
+        let kept: Vec<usize> = (0..times.len())
+            .filter(|i| !reasons[*i].is_empty())
+            .collect();
+
+        // Newest two always kept by "last".
+        assert!(reasons[79].contains(&"last") && reasons[78].contains(&"last"));
+        // Seven distinct daily buckets, newest snapshot of each day.
+        let daily: Vec<usize> = kept
+            .iter()
+            .copied()
+            .filter(|i| reasons[*i].contains(&"daily"))
+            .collect();
+        assert_eq!(daily.len(), 7);
+        // Two months in range (March, April): monthly keeps one each.
+        let monthly = kept
+            .iter()
+            .filter(|i| reasons[**i].contains(&"monthly"))
+            .count();
+        assert_eq!(monthly, 2);
+        assert!(
+            reasons[0].is_empty(),
+            "oldest snapshot of a kept month is not its newest"
+        );
+        assert!(kept.len() < times.len());
+    }
+
+    #[test]
+    fn empty_policy_is_detected() {
+        assert!(RetentionPolicy::default().is_empty());
+        let policy = RetentionPolicy {
+            keep_weekly: 1,
+            ..Default::default()
+        };
+        assert!(!policy.is_empty());
+        let t = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(policy.apply(&[t, t - Duration::days(1)])[0], vec!["weekly"]);
+    }
+}
@@ -60,19 +60,52 @@ use axum::{
         HeaderMap, HeaderValue, Method, StatusCode,
     },
     response::{Html, IntoResponse, Response},
-    routing::{any, get},
+    routing::{any, get, post},
     Router,
 };
 use base64::Engine as _;
 use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use ftp_client_gui_lib::backup::{
+    BackupBackend, BackupOptions, KdfParams, ProviderBackend, PruneSummary, Repository,
+    RestoreOptions, RetentionPolicy, Snapshot,
+};
+use ftp_client_gui_lib::checksum_cache::ChecksumCache;
 use ftp_client_gui_lib::profile_loader::{
     apply_profile_options, apply_s3_profile_defaults, S3_ENDPOINT_SOURCE_META_KEY,
     S3_PATH_STYLE_SOURCE_META_KEY, S3_PROVIDER_ID_META_KEY, S3_REGION_SOURCE_META_KEY,
 };
+use ftp_client_gui_lib::providers::encoding::with_name_encoding;
 use ftp_client_gui_lib::providers::{
     ProviderConfig, ProviderError, ProviderFactory, ProviderType, RemoteEntry, ShareLinkOptions,
     StorageProvider, MAX_DOWNLOAD_TO_BYTES,
 };
+use ftp_client_gui_lib::sync::{
+    delete_sync_journal, load_sync_journal, save_sync_journal, CompareDirection,
+};
+use ftp_client_gui_lib::sync_core::{
+    apply_local_meta, as_mergeable_text, create_local_symlink, decode_xattrs, encode_xattrs,
+    is_safe_link, link_file_path, local_fs_case_insensitive, merge3, plan_links, plan_names,
+    read_local_meta, scan_local_links, strip_link_suffix, strip_xattr_suffix, xattr_file_path,
+    ChmodRules, CollisionPolicy, CollisionSide, FileMeta, HardlinkGroups, LinkEntry, LinkMode,
+    LinkPlan, MergeBaseStore, MergeResult, MetaDiff, MetaOptions, NameOptions, NamePlan,
+    MAX_MERGE_BYTES,
+};
+use ftp_client_gui_lib::sync_hooks::{
+    NotifyOn, NotifySink, SyncHookEvent, SyncHooks, SyncRunReport,
+};
+use ftp_client_gui_lib::sync_ignore::AeroIgnore;
+use ftp_client_gui_lib::sync_job_file::SyncJobFile;
+use ftp_client_gui_lib::sync_planner::{
+    deferral_journal, history_throughput, pending_paths, record_speed_test, Deadline, PlanItem,
+    Throughput, TransferBudget, TransferKind, TransferOrder, TransferPlanner,
+};
+use ftp_client_gui_lib::sync_scheduler::{
+    load_sync_jobs, update_sync_jobs, SyncJob, SyncJobOutcome, SyncJobRun, SyncJobTrigger,
+    TimeWindow,
+};
+use ftp_client_gui_lib::sync_versioning::{
+    is_versions_path, SyncVersioning, VersioningStrategy, VERSIONS_DIR_NAME,
+};
 use ftp_client_gui_lib::util::shutdown_signal;
 use futures_util::StreamExt;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
@@ -356,6 +389,104 @@ struct Cli {
     #[arg(long, global = true)]
     fast_list: bool,
 
+    /// Refresh the remote side of `sync --direction both` from the provider
+    /// change feed stored in the bisync snapshot instead of a full rescan
+    /// (Google Drive; other providers keep scanning)
+    #[arg(long, global = true)]
+    change_feed: bool,
+
+    /// Archive remote files that `sync` overwrites or deletes into
+    /// `<remote>/.aeroversions/`: trash_can[:days], simple[:copies], staggered.
+    /// Expired versions are pruned after each sync that ends without errors
+    #[arg(long, global = true)]
+    remote_versioning: Option<String>,
+
+    /// What `sync` does with paths that would collapse into one file on the
+    /// destination (case or Unicode normalization variants): rename, skip
+    /// (default) or fail
+    #[arg(long, global = true)]
+    name_collision: Option<String>,
+
+    /// Recreate symlinks on the `sync` destination: natively on SFTP and
+    /// local disks, as `<name>.rclonelink` files on other backends
+    #[arg(long, global = true)]
+    links: bool,
+
+    /// Follow symlinks and transfer the files they point to
+    #[arg(long, global = true)]
+    copy_links: bool,
+
+    /// Ignore symlinks that are absolute or point outside the synced tree
+    #[arg(long, global = true)]
+    safe_links: bool,
+
+    /// Upload each group of hardlinked local files once during `sync`; the
+    /// other members are hard-linked or server-side copied on the remote
+    #[arg(long, global = true)]
+    hard_links: bool,
+
+    /// `sync`: preserve POSIX permissions (SFTP, FTP `SITE CHMOD`, local disks)
+    #[arg(long, global = true)]
+    perms: bool,
+
+    /// `sync`: preserve the numeric owner and group where the destination allows it
+    #[arg(long, global = true)]
+    owner: bool,
+
+    /// `sync`: rewrite permissions on the destination, rsync style (`D755,F644`, `Fo-w,+X`)
+    #[arg(long, global = true, value_name = "ITEMS")]
+    chmod: Option<String>,
+
+    /// `sync`: preserve extended attributes (kept in `<name>.aeroxattrs` files on remotes)
+    #[arg(long, global = true)]
+    xattrs: bool,
+
+    /// `sync`: shell command run first, with the event JSON on stdin; a
+    /// non-zero exit aborts the sync (repeatable)
+    #[arg(long, global = true, value_name = "COMMAND")]
+    pre_hook: Vec<String>,
+
+    /// `sync`: shell command run afterwards, with the sync report JSON on stdin (repeatable)
+    #[arg(long, global = true, value_name = "COMMAND")]
+    post_hook: Vec<String>,
+
+    /// `sync`: notification sink: https://..., ntfy://host/topic,
+    /// gotify://host/<token>, smtp[s]://user:pass@host/?from=..&to=.., desktop:// (repeatable)
+    #[arg(long, global = true, value_name = "URL")]
+    notify: Vec<String>,
+
+    /// `sync`: when to send notifications: failure (default), success, always
+    #[arg(long, global = true)]
+    notify_on: Option<String>,
+
+    /// `sync`: seconds a single hook may run before it is killed
+    #[arg(long, global = true, default_value_t = 3600)]
+    hook_timeout: u64,
+
+    /// `sync`: order transfers: discovery (default), smallest-first,
+    /// largest-first, newest-first
+    #[arg(long, global = true, value_name = "ORDER")]
+    order_by: Option<String>,
+
+    /// `sync`: transfer paths matching GLOB before the rest; earlier
+    /// patterns go first (repeatable)
+    #[arg(long, global = true, value_name = "GLOB")]
+    priority: Vec<String>,
+
+    /// `sync`: stop starting transfers that would not finish within this
+    /// time (`90m`, `2h`); the rest is left to the next run
+    #[arg(long, global = true, value_name = "DURATION")]
+    max_duration: Option<String>,
+
+    /// `sync`: like `--max-duration`, up to the next local HH:MM (`06:00`)
+    #[arg(long, global = true, value_name = "HH:MM")]
+    stop_at: Option<String>,
+
+    /// `sync`: skip paths matched by the gitignore-style `.aeroignore` file
+    /// in the local directory
+    #[arg(long, global = true)]
+    aeroignore: bool,
+
     /// Write downloads directly to final path (no .aerotmp temp file)
     #[arg(long, global = true)]
     inplace: bool,
@@ -937,7 +1068,7 @@ enum Commands {
         /// Consume a reconcile JSON file instead of re-scanning local and remote trees
         #[arg(long)]
         from_reconcile: Option<String>,
-        /// Conflict resolution for --direction both: newer, older, larger, smaller, rename, skip (default: newer)
+        /// Conflict resolution for --direction both: newer, older, larger, smaller, rename, skip, merge (default: newer)
         #[arg(long, default_value = "newer")]
         conflict_mode: String,
         /// Trust size-only matches and skip transfers even when mtimes differ
@@ -964,6 +1095,15 @@ enum Commands {
         /// Skip the initial full sync on startup
         #[arg(long)]
         watch_no_initial: bool,
+        /// Take endpoints and options from a TOML or YAML job file; flags on
+        /// the command line override it
+        #[arg(long, value_name = "FILE")]
+        job: Option<String>,
+    },
+    /// List, restore, or prune remote versions archived by `sync --remote-versioning`
+    Versions {
+        #[command(subcommand)]
+        command: VersionsCommands,
     },
     /// Preflight checks and risk summary before sync
     SyncDoctor {
@@ -988,7 +1128,7 @@ enum Commands {
         /// Detect renamed files by hash to avoid re-upload
         #[arg(long)]
         track_renames: bool,
-        /// Conflict resolution for --direction both: newer, older, larger, smaller, rename, skip
+        /// Conflict resolution for --direction both: newer, older, larger, smaller, rename, skip, merge
         #[arg(long, default_value = "newer")]
         conflict_mode: String,
         /// Discard previous bisync snapshot and rebuild from scratch
@@ -1049,6 +1189,32 @@ enum Commands {
         /// Mount as read-only (default: read-write)
         #[arg(long)]
         read_only: bool,
+        /// VFS cache mode: off (stream reads, upload on close), writes (write-back
+        /// upload queue), full (also cache reads on disk). Linux only.
+        #[arg(long, default_value = "off")]
+        vfs_cache_mode: String,
+        /// Maximum size of the VFS cache, e.g. "10G" (default: unlimited)
+        #[arg(long)]
+        vfs_cache_max_size: Option<String>,
+        /// Evict cached files unused for this long, e.g. "30m", "1h", "7d"
+        #[arg(long, default_value = "1h")]
+        vfs_cache_max_age: String,
+        /// Extra data fetched ahead of sequential reads, e.g. "1M"
+        #[arg(long, default_value = "1M")]
+        vfs_read_ahead: String,
+        /// Size of the chunks fetched and cached in full mode, e.g. "4M"
+        #[arg(long, default_value = "4M")]
+        vfs_read_chunk_size: String,
+        /// Delay before a closed file is uploaded in writes/full mode, e.g. "5s"
+        #[arg(long, default_value = "5s")]
+        vfs_write_back: String,
+        /// VFS cache directory (default: <user cache dir>/aeroftp/vfs/<mount id>)
+        #[arg(long)]
+        cache_dir: Option<String>,
+        /// How often to poll the remote change feed (providers with change
+        /// tracking, e.g. Google Drive), e.g. "30s", "1m". 0 disables. Linux only.
+        #[arg(long, default_value = "1m")]
+        poll_interval: String,
     },
     /// Transfer files between two saved profiles (cross-profile copy)
     Transfer {
@@ -1288,6 +1454,16 @@ enum Commands {
         #[command(subcommand)]
         command: CryptCommands,
     },
+    /// Deduplicated, encrypted backup snapshots on any provider
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommands,
+    },
+    /// AeroVault v2 encrypted containers (.aerovault)
+    Vault {
+        #[command(subcommand)]
+        command: VaultCommands,
+    },
     /// rclone-crypt compatible operations (separate from `crypt` overlay)
     #[command(name = "rclone-crypt")]
     RcloneCrypt {
@@ -1310,6 +1486,12 @@ enum Commands {
         #[command(subcommand)]
         command: ImportCommands,
     },
+    /// rsync batch files: replay a recorded delta onto local replicas
+    #[cfg(feature = "aerorsync")]
+    Delta {
+        #[command(subcommand)]
+        command: DeltaCommands,
+    },
 }
 
 #[derive(Subcommand)]
@@ -1434,6 +1616,31 @@ enum ImportCommands {
     },
 }
 
+#[cfg(feature = "aerorsync")]
+#[derive(Subcommand)]
+enum DeltaCommands {
+    /// Apply a batch file to a copy of the tree it was recorded against
+    /// (same as `rsync --read-batch=BATCH DEST`)
+    Apply {
+        /// Batch file written by `--write-batch` (aeroftp or stock rsync)
+        batch: String,
+        /// Local replica to update
+        dest: String,
+        /// Checksum of the recording session (default: from BATCH.sh, else md5)
+        #[arg(long)]
+        checksum_choice: Option<String>,
+        /// Compressor of the recording session (default: from BATCH.sh, else zlib)
+        #[arg(long)]
+        compress_choice: Option<String>,
+        /// Delete files in DEST that the recorded file list does not have
+        #[arg(long)]
+        delete: bool,
+        /// Create DEST and its missing parents
+        #[arg(long)]
+        mkpath: bool,
+    },
+}
+
 #[derive(Subcommand)]
 enum DaemonCommands {
     /// Start the background daemon
@@ -1474,6 +1681,97 @@ enum JobCommands {
         /// Job ID
         id: String,
     },
+    /// Named sync jobs run by the daemon on cron schedules
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleCommands {
+    /// Register a named sync job (uses --profile when given)
+    Add {
+        /// Unique job name
+        name: String,
+        /// Local directory
+        local: String,
+        /// Remote directory
+        remote: String,
+        /// Cron expression (e.g. "30 2 * * *", "*/15 8-18 * * mon-fri", "@daily")
+        #[arg(long)]
+        cron: String,
+        /// Server URL (omit when using --profile)
+        #[arg(long)]
+        url: Option<String>,
+        /// Only start scheduled runs inside this window (HH:MM-HH:MM, may wrap midnight)
+        #[arg(long)]
+        window: Option<String>,
+        /// Days the window applies to (e.g. "mon,tue,fri"; default: every day)
+        #[arg(long, default_value = "", requires = "window")]
+        days: String,
+        /// Register the job without scheduling it
+        #[arg(long)]
+        disabled: bool,
+        /// Extra sync flags after `--` (e.g. -- --direction upload --delete)
+        #[arg(last = true, allow_hyphen_values = true)]
+        options: Vec<String>,
+    },
+    /// List named sync jobs with next run and last result
+    List,
+    /// Remove a named sync job
+    Remove {
+        /// Job name
+        name: String,
+    },
+    /// Run a named sync job now (through the daemon when it is running)
+    RunNow {
+        /// Job name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum VersionsCommands {
+    /// List archived versions under <remote>/.aeroversions/ (newest first)
+    List {
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote sync root
+        #[arg(default_value = "/")]
+        remote: String,
+        /// Only list versions of this file (path relative to the sync root)
+        #[arg(long)]
+        file: Option<String>,
+    },
+    /// Restore an archived version to its original path
+    Restore {
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote sync root
+        #[arg(default_value = "/")]
+        remote: String,
+        /// File to restore (path relative to the sync root)
+        #[arg(long)]
+        file: String,
+        /// Version timestamp as shown by `versions list` (default: newest)
+        #[arg(long)]
+        version: Option<String>,
+    },
+    /// Delete archived versions the retention strategy no longer keeps
+    Cleanup {
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote sync root
+        #[arg(default_value = "/")]
+        remote: String,
+        /// Retention: trash_can[:days] (default 30), simple[:copies] (default 5), staggered
+        #[arg(long, default_value = "trash_can")]
+        strategy: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -1535,6 +1833,204 @@ enum CryptCommands {
     },
 }
 
+#[derive(Subcommand)]
+enum BackupCommands {
+    /// Create an encrypted, deduplicating backup repository on a remote
+    Init {
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote directory for the repository
+        #[arg(default_value = "/")]
+        path: String,
+        /// Repository password (or will prompt interactively)
+        #[arg(long, env = "AEROFTP_BACKUP_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+    },
+    /// Back up a local directory or file as a new snapshot
+    Run {
+        /// Local directory or file to back up
+        source: String,
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote repository directory
+        #[arg(default_value = "/")]
+        path: String,
+        /// Repository password
+        #[arg(long, env = "AEROFTP_BACKUP_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// Tag the snapshot (repeatable)
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Exclude patterns (can repeat: --exclude "*.tmp" --exclude ".git")
+        #[arg(long, short)]
+        exclude: Vec<String>,
+        /// Hostname recorded in the snapshot (default: this machine)
+        #[arg(long)]
+        host: Option<String>,
+        /// Snapshot to compare against for unchanged files (default: latest of this host and source)
+        #[arg(long)]
+        parent: Option<String>,
+    },
+    /// List the snapshots in a repository
+    Snapshots {
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote repository directory
+        #[arg(default_value = "/")]
+        path: String,
+        /// Repository password
+        #[arg(long, env = "AEROFTP_BACKUP_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// Only show snapshots taken on this host
+        #[arg(long)]
+        host: Option<String>,
+    },
+    /// Restore a snapshot into a local directory (--include/--exclude-global select paths)
+    Restore {
+        /// Snapshot id, unique id prefix, or "latest"
+        snapshot: String,
+        /// Local directory to restore into
+        target: String,
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote repository directory
+        #[arg(default_value = "/")]
+        path: String,
+        /// Repository password
+        #[arg(long, env = "AEROFTP_BACKUP_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+    },
+    /// Remove snapshots by id or by retention policy
+    Forget {
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote repository directory
+        #[arg(default_value = "/")]
+        path: String,
+        /// Repository password
+        #[arg(long, env = "AEROFTP_BACKUP_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// Snapshot id or prefix to remove (repeatable)
+        #[arg(long = "snapshot")]
+        snapshots: Vec<String>,
+        /// Keep the N most recent snapshots
+        #[arg(long, default_value_t = 0)]
+        keep_last: u32,
+        /// Keep the newest snapshot of each of the last N hours
+        #[arg(long, default_value_t = 0)]
+        keep_hourly: u32,
+        /// Keep the newest snapshot of each of the last N days
+        #[arg(long, default_value_t = 0)]
+        keep_daily: u32,
+        /// Keep the newest snapshot of each of the last N weeks
+        #[arg(long, default_value_t = 0)]
+        keep_weekly: u32,
+        /// Keep the newest snapshot of each of the last N months
+        #[arg(long, default_value_t = 0)]
+        keep_monthly: u32,
+        /// Keep the newest snapshot of each of the last N years
+        #[arg(long, default_value_t = 0)]
+        keep_yearly: u32,
+        /// Run prune afterwards to free the data
+        #[arg(long)]
+        prune: bool,
+        /// Show what the policy would remove without removing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Delete data no longer referenced by any snapshot
+    Prune {
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote repository directory
+        #[arg(default_value = "/")]
+        path: String,
+        /// Repository password
+        #[arg(long, env = "AEROFTP_BACKUP_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// Report what would be deleted without deleting
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Verify repository integrity
+    Check {
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote repository directory
+        #[arg(default_value = "/")]
+        path: String,
+        /// Repository password
+        #[arg(long, env = "AEROFTP_BACKUP_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// Download and verify every chunk (slow, reads the whole repository)
+        #[arg(long)]
+        read_data: bool,
+    },
+    /// Remove locks left behind by interrupted runs
+    Unlock {
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote repository directory
+        #[arg(default_value = "/")]
+        path: String,
+        /// Repository password
+        #[arg(long, env = "AEROFTP_BACKUP_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+    },
+    /// Browse all snapshots as a read-only filesystem (Linux, FUSE)
+    Mount {
+        /// Local mount point (empty directory)
+        mountpoint: String,
+        /// Server URL (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Remote repository directory
+        #[arg(default_value = "/")]
+        path: String,
+        /// Repository password
+        #[arg(long, env = "AEROFTP_BACKUP_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// Allow other users to access the mount (requires user_allow_other in /etc/fuse.conf)
+        #[arg(long)]
+        allow_other: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum VaultCommands {
+    /// Use a vault as a read-write folder (Linux, FUSE). Files are decrypted
+    /// when first read; changes are written back on unmount.
+    Mount {
+        /// Vault file: a local path, or a remote path with --profile or URL
+        vault: String,
+        /// Local mount point (empty directory)
+        mountpoint: String,
+        /// Server URL when the vault is stored on a remote (omit when using --profile)
+        #[arg(default_value = "_", hide_default_value = true)]
+        url: String,
+        /// Vault password (or will prompt interactively)
+        #[arg(long, env = "AEROFTP_VAULT_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// Also commit changes every interval while mounted, e.g. "5m". 0 commits on unmount only
+        #[arg(long, default_value = "0")]
+        commit_interval: String,
+        /// Mount read-only
+        #[arg(long)]
+        read_only: bool,
+        /// Allow other users to access the mount (requires user_allow_other in /etc/fuse.conf)
+        #[arg(long)]
+        allow_other: bool,
+    },
+}
+
 #[derive(Subcommand)]
 enum RcloneCryptCommands {
     /// Encrypt and upload a file using the rclone crypt format
@@ -1816,6 +2312,9 @@ struct CliSyncResult {
     /// output is unchanged for callers that never pass `--dry-run`.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     plan: Vec<CliSyncPlanEntry>,
+    /// Transfers left for the next run because the deadline came first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    deferred: Vec<String>,
 }
 
 /// Single plan entry surfaced in `sync --dry-run --json`.
@@ -1835,6 +2334,12 @@ struct CliSyncPlanEntry {
     remote_size: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     conflict_path: Option<String>,
+    /// Symlink target (`symlink_*`) or hardlink group leader (`hardlink`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    link_target: Option<String>,
+    /// Permission/owner/xattr changes (`metadata_*`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<MetaDiff>,
 }
 
 /// Stats returned by cmd_sync for watch mode output enrichment.
@@ -1846,6 +2351,7 @@ struct SyncCycleStats {
     deleted: u32,
     skipped: u32,
     error_count: u32,
+    errors: Vec<String>,
 }
 
 impl From<i32> for SyncCycleStats {
@@ -2493,8 +2999,62 @@ fn expand_aliases(args: &[String], config: &CliConfigFile) -> Result<Vec<String>
     Err("Alias expansion exceeded maximum depth (8)".to_string())
 }
 
+/// `sync --job FILE`: swap the option for the endpoints and flags of the job
+/// file. Runs before the config defaults so the precedence is command line,
+/// then job file, then config. Scalar flags already on the command line win;
+/// repeatable ones (`--exclude`, hooks, notify sinks) add to the file's.
+fn expand_sync_job(args: &[String]) -> Result<Vec<String>, String> {
+    let Some(cmd_idx) = first_command_index(args) else {
+        return Ok(args.to_vec());
+    };
+    if args[cmd_idx] != "sync" {
+        return Ok(args.to_vec());
+    }
+    let mut job_path = None;
+    let mut rest = Vec::new();
+    let mut tail = args[cmd_idx + 1..].iter();
+    while let Some(arg) = tail.next() {
+        if arg == "--" {
+            rest.push(arg.clone());
+            rest.extend(tail.by_ref().cloned());
+        } else if arg == "--job" {
+            job_path = Some(tail.next().ok_or("--job needs a file path")?.clone());
+        } else if let Some(path) = arg.strip_prefix("--job=") {
+            job_path = Some(path.to_string());
+        } else {
+            rest.push(arg.clone());
+        }
+    }
+    let Some(job_path) = job_path else {
+        return Ok(args.to_vec());
+    };
+    let job = SyncJobFile::load(Path::new(&job_path))?;
+
+    let mut expanded = args[..=cmd_idx].to_vec();
+    let endpoints = &job.endpoints;
+    expanded.push(endpoints.url.clone().unwrap_or_else(|| "_".to_string()));
+    expanded.push(endpoints.local.clone());
+    expanded.push(endpoints.remote.clone());
+    if let Some(profile) = &endpoints.profile {
+        if !arg_present(args, "--profile", Some("-P")) {
+            expanded.push("--profile".to_string());
+            expanded.push(profile.clone());
+        }
+    }
+    for flag in job.cli_flags() {
+        if !flag.repeatable && arg_present(args, flag.name, None) {
+            continue;
+        }
+        expanded.push(flag.name.to_string());
+        expanded.extend(flag.value);
+    }
+    expanded.extend(rest);
+    Ok(expanded)
+}
+
 fn prepare_cli_args(args: Vec<String>) -> Result<Vec<String>, String> {
     let config = load_cli_config()?;
+    let args = expand_sync_job(&args)?;
     let with_defaults = apply_config_defaults(&args, &config);
     expand_aliases(&with_defaults, &config)
 }
@@ -3168,6 +3728,19 @@ fn hash_local_file_sha256(path: &Path) -> Option<String> {
     Some(format!("{:x}", sha2::Sha256::digest(&data)))
 }
 
+/// SHA-256 of a local file, served from the persistent checksum cache when
+/// one is open and the file is unchanged since it was last hashed.
+fn cached_local_sha256(
+    cache: Option<&ChecksumCache>,
+    path: &Path,
+    meta: Option<&std::fs::Metadata>,
+) -> Option<String> {
+    match (cache, meta) {
+        (Some(cache), Some(meta)) => cache.checksum(path, meta, "sha256").ok(),
+        _ => hash_local_file_sha256(path),
+    }
+}
+
 fn scan_local_tree_with_progress(
     root: &str,
     opts: &ftp_client_gui_lib::sync_core::ScanOptions,
@@ -3228,14 +3801,18 @@ fn scan_local_tree_with_progress(
 
         let meta = walk_entry.metadata().ok();
         let size = meta.as_ref().map(|value| value.len()).unwrap_or(0);
-        let mtime = meta.and_then(|value| {
+        let mtime = meta.as_ref().and_then(|value| {
             value.modified().ok().map(|timestamp| {
                 let dt: chrono::DateTime<chrono::Utc> = timestamp.into();
                 dt.format("%Y-%m-%dT%H:%M:%S").to_string()
             })
         });
         let sha256 = if opts.compute_checksum {
-            hash_local_file_sha256(walk_entry.path())
+            cached_local_sha256(
+                opts.checksum_cache.as_deref(),
+                walk_entry.path(),
+                meta.as_ref(),
+            )
         } else {
             None
         };
@@ -6197,14 +6774,15 @@ fn cmd_agent_info(cli: &Cli) -> i32 {
                 {"name": "mount", "syntax": "aeroftp-cli mount --profile NAME /mountpoint", "description": "Expose a remote as a local filesystem"},
                 {"name": "serve", "syntax": "aeroftp-cli serve <http|webdav|ftp|sftp> --profile NAME /path", "description": "Expose a remote over a local protocol bridge"},
                 {"name": "daemon", "syntax": "aeroftp-cli daemon <start|stop|status>", "description": "Manage the background jobs daemon"},
-                {"name": "jobs", "syntax": "aeroftp-cli jobs <add|list|status|cancel>", "description": "Manage queued background jobs"},
+                {"name": "jobs", "syntax": "aeroftp-cli jobs <add|list|status|cancel|schedule>", "description": "Manage queued background jobs and cron-scheduled named sync jobs"},
                 {"name": "crypt", "syntax": "aeroftp-cli crypt <init|ls|put|get> --profile NAME /path", "description": "Use encrypted overlay storage"},
+                {"name": "backup", "syntax": "aeroftp-cli backup <init|run|snapshots|restore|forget|prune|check|unlock|mount> --profile NAME /repo", "description": "Deduplicated, encrypted backup snapshots"},
                 {"name": "batch", "syntax": "aeroftp-cli batch file.aeroftp", "description": "Run batch automation scripts"},
                 {"name": "agent-info", "syntax": "aeroftp-cli agent-info --json", "description": "Show machine-readable CLI capabilities"}
             ]
         },
         "capabilities": {
-            "main_command_groups": 38,
+            "main_command_groups": 39,
             "agent_native_tools": cli_tool_definitions().len(),
             "hash_algorithms": ["md5", "sha1", "sha256", "sha512", "blake3"],
             "serve_protocols": ["http", "webdav", "ftp", "sftp"],
@@ -7733,7 +8311,22 @@ async fn create_and_connect(
                         )
                         .await
                         {
-                            return result;
+                            let (provider, path) = result?;
+                            let encoding = profile
+                                .get("options")
+                                .and_then(|o| o.get("encoding"))
+                                .and_then(|v| v.as_str());
+                            return match with_name_encoding(provider, encoding) {
+                                Ok(provider) => Ok((provider, path)),
+                                Err(e) => {
+                                    print_error(
+                                        format,
+                                        &format!("Failed to create provider: {}", e),
+                                        provider_error_to_exit_code(&e),
+                                    );
+                                    Err(provider_error_to_exit_code(&e))
+                                }
+                            };
                         }
                     }
                 }
@@ -7856,8 +8449,13 @@ struct ServeHttpState {
 struct DaemonApiState {
     conn: Arc<std::sync::Mutex<rusqlite::Connection>>,
     auth_token: String,
+    /// Named sync jobs currently running, for overlap prevention
+    running_sync_jobs: RunningSyncJobs,
+    quiet: bool,
 }
 
+type RunningSyncJobs = Arc<std::sync::Mutex<std::collections::HashSet<String>>>;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct ServeAuthCredentials {
     username: String,
@@ -11524,6 +12122,7 @@ async fn cmd_get_recursive(
                 errors,
                 elapsed_secs: elapsed.as_secs_f64(),
                 plan: Vec::new(),
+                deferred: Vec::new(),
             });
         }
     }
@@ -11684,6 +12283,7 @@ async fn cmd_get_glob(
                 errors,
                 elapsed_secs: elapsed.as_secs_f64(),
                 plan: Vec::new(),
+                deferred: Vec::new(),
             });
         }
     }
@@ -12114,6 +12714,7 @@ async fn cmd_put_recursive(
                 errors,
                 elapsed_secs: elapsed.as_secs_f64(),
                 plan: Vec::new(),
+                deferred: Vec::new(),
             });
         }
     }
@@ -13238,14 +13839,89 @@ async fn cmd_import_filezilla(path: Option<String>, json: bool) -> i32 {
 /// `path` may be `-` to read from stdin. The conversion preserves rclone's
 /// first-match-wins semantics under gitignore last-match-wins by reversing
 /// the rule order; warnings are reported to the user.
-async fn cmd_import_rclone_filter(
-    path: String,
-    output: Option<String>,
-    force: bool,
-    json: bool,
+#[cfg(feature = "aerorsync")]
+#[allow(clippy::too_many_arguments)]
+async fn cmd_delta_apply(
+    batch: &str,
+    dest: &str,
+    checksum_choice: Option<&str>,
+    compress_choice: Option<&str>,
+    delete: bool,
+    mkpath: bool,
+    cli: &Cli,
+    format: OutputFormat,
 ) -> i32 {
-    use ftp_client_gui_lib::rclone_filter::rclone_filter_to_aeroignore;
-    use std::io::Read;
+    use ftp_client_gui_lib::aerorsync::batch::{apply_batch, BatchReplayOptions};
+    use ftp_client_gui_lib::aerorsync::checksum::ChecksumAlgo;
+    use ftp_client_gui_lib::aerorsync::compression::CompressionAlgo;
+    use ftp_client_gui_lib::aerorsync::remote_command::TransferOptions;
+
+    let checksum = match checksum_choice.map(|name| (name, ChecksumAlgo::from_name(name))) {
+        Some((name, None)) => {
+            print_error(format, &format!("Unknown checksum: {}", name), 2);
+            return 2;
+        }
+        Some((_, algo)) => algo,
+        None => None,
+    };
+    let compression = match compress_choice.map(|name| (name, CompressionAlgo::from_name(name))) {
+        Some((name, None)) => {
+            print_error(format, &format!("Unknown compressor: {}", name), 2);
+            return 2;
+        }
+        Some((_, algo)) => algo,
+        None => None,
+    };
+    let options = BatchReplayOptions {
+        checksum,
+        compression,
+        transfer_options: TransferOptions {
+            delete,
+            mkpath,
+            ..TransferOptions::default()
+        },
+    };
+
+    match apply_batch(Path::new(batch), Path::new(dest), &options).await {
+        Ok(report) => {
+            if matches!(format, OutputFormat::Json) {
+                print_json(&serde_json::json!({
+                    "status": "ok",
+                    "batch": batch,
+                    "dest": dest,
+                    "files_total": report.files_total,
+                    "files_transferred": report.files_transferred,
+                    "dirs_total": report.dirs_total,
+                    "bytes_transferred": report.bytes_transferred,
+                    "entries_deleted": report.entries_deleted,
+                }));
+            } else if !cli.quiet {
+                println!(
+                    "Applied {}: {} of {} files updated ({}), {} deleted",
+                    batch,
+                    report.files_transferred,
+                    report.files_total,
+                    format_size(report.bytes_transferred),
+                    report.entries_deleted
+                );
+            }
+            0
+        }
+        Err(e) => {
+            print_error(format, &format!("Batch replay failed: {}", e), 1);
+            1
+        }
+    }
+}
+
+async fn cmd_import_rclone_filter(
+    path: String,
+    output: Option<String>,
+    force: bool,
+    json: bool,
+) -> i32 {
+    use ftp_client_gui_lib::rclone_filter::rclone_filter_to_aeroignore;
+    use std::io::Read;
 
     // Read the input file (or stdin if path == "-").
     let content = if path == "-" {
@@ -14033,6 +14709,24 @@ async fn cmd_speed(
         }
     }
 
+    // Shared with the GUI speed test; `sync --max-duration` plans with it.
+    if let Some(host) = target_host(url, cli) {
+        let rates = Throughput {
+            upload_bps: Some(result.upload_speed_bps as f64),
+            download_bps: Some(result.download_speed_bps as f64),
+        };
+        if let Err(e) = record_speed_test(
+            &host,
+            &result.protocol,
+            result.test_size,
+            rates,
+            result.integrity_verified,
+            result.cleanup_ok,
+        ) {
+            eprintln!("warning: could not record speed test history: {}", e);
+        }
+    }
+
     // Exit code 4 only when an integrity check ran AND failed.
     // If integrity was explicitly skipped, that is not an error.
     let exit_code = if !result.integrity_checked || result.integrity_verified {
@@ -15984,6 +16678,16 @@ struct BisyncSnapshot {
     synced_at: String,
     /// Map of relative_path → (size, mtime_iso_or_empty)
     files: HashMap<String, (u64, String)>,
+    /// Provider change-feed cursor plus the remote listing it applies to.
+    /// Only present for runs with `--change-feed` on a provider that
+    /// supports change tracking.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    change_feed: Option<ftp_client_gui_lib::sync_core::ChangeFeedState>,
+    /// Map of relative_path → SHA-256 of the content at the last sync, for
+    /// `--conflict-mode merge`. The content itself lives in the merge store
+    /// (see `bisync_merge_store`).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    merge_bases: HashMap<String, String>,
 }
 
 const BISYNC_SNAPSHOT_FILE: &str = ".aeroftp-bisync.json";
@@ -15998,6 +16702,8 @@ fn save_bisync_snapshot(
     local_dir: &str,
     local_entries: &[(String, u64, Option<String>)],
     remote_entries: &[(String, u64, Option<String>)],
+    change_feed: Option<ftp_client_gui_lib::sync_core::ChangeFeedState>,
+    merge_bases: HashMap<String, String>,
 ) {
     let mut files = HashMap::new();
     // Merge both sides - after a successful sync they should be equal
@@ -16009,6 +16715,8 @@ fn save_bisync_snapshot(
     let snapshot = BisyncSnapshot {
         synced_at: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
         files,
+        change_feed,
+        merge_bases,
     };
     // S1: the snapshot lives on the LOCAL side, not the remote.
     // Ensure the local dir exists before writing: download-first runs
@@ -16044,6 +16752,280 @@ fn save_bisync_snapshot(
     }
 }
 
+/// Merge bases for `--conflict-mode merge`, one content-addressed store per
+/// local sync root under the CLI state dir (never inside the synced tree).
+fn bisync_merge_store(local_dir: &str) -> Option<MergeBaseStore> {
+    use sha2::Digest;
+    let root = std::fs::canonicalize(local_dir).ok()?;
+    let key = format!(
+        "{:x}",
+        sha2::Sha256::digest(root.to_string_lossy().as_bytes())
+    );
+    Some(MergeBaseStore::new(
+        cli_state_dir().ok()?.join("bisync-merge").join(&key[..16]),
+    ))
+}
+
+/// Merge bases for the next `--conflict-mode merge` run. Only files in
+/// `changed` (transferred, merged or edited since the last snapshot) are read
+/// and stored when they are small text files; every other file keeps the base
+/// it had in `previous`. Bases no longer referenced are pruned.
+fn collect_merge_bases(
+    local_dir: &str,
+    previous: Option<&HashMap<String, String>>,
+    entries: &[&[(String, u64, Option<String>)]],
+    changed: &std::collections::HashSet<&str>,
+) -> HashMap<String, String> {
+    let mut bases = HashMap::new();
+    let Some(store) = bisync_merge_store(local_dir) else {
+        return bases;
+    };
+    for (path, size, _) in entries.iter().flat_map(|side| side.iter()) {
+        if bases.contains_key(path) {
+            continue;
+        }
+        if !changed.contains(path.as_str()) {
+            if let Some(hash) = previous.and_then(|bases| bases.get(path)) {
+                bases.insert(path.clone(), hash.clone());
+            }
+            continue;
+        }
+        if *size > MAX_MERGE_BYTES {
+            continue;
+        }
+        let Ok(bytes) = std::fs::read(Path::new(local_dir).join(path)) else {
+            continue;
+        };
+        if let Some(text) = as_mergeable_text(&bytes) {
+            match store.put(text) {
+                Ok(hash) => {
+                    bases.insert(path.clone(), hash);
+                }
+                Err(e) => eprintln!("Warning: merge base for {}: {}", path, e),
+            }
+        }
+    }
+    store.retain(&bases.values().cloned().collect());
+    bases
+}
+
+/// Write a local file through a temp sibling + rename.
+fn write_local_file_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".aerotmp");
+    let tmp = PathBuf::from(tmp);
+    std::fs::write(&tmp, data)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&tmp);
+            e.to_string()
+        })
+}
+
+/// Resolve one `--conflict-mode merge` conflict against its merge base.
+///
+/// Returns `None` once both sides hold the cleanly merged text. When the
+/// edits overlap, or the file cannot be merged (binary, too large, no base),
+/// it falls back to the `rename` strategy: the remote version wins at `rel`
+/// and the local side is kept in a conflict copy on both sides, annotated
+/// with diff3 conflict markers when a merge was attempted. Returns the
+/// conflict copy path in that case. `remote_rel` is the remote spelling of
+/// `rel` (see `--name-collision`).
+async fn merge_conflicted_file(
+    provider: &mut dyn StorageProvider,
+    versioning: &SyncVersioning,
+    local: &str,
+    remote: &str,
+    rel: &str,
+    remote_rel: &str,
+    base: Option<&str>,
+) -> Result<Option<String>, String> {
+    if validate_relative_path(rel).is_none() || validate_relative_path(remote_rel).is_none() {
+        return Err("unsafe path (traversal rejected)".to_string());
+    }
+    let local_path = Path::new(local).join(rel);
+    let remote_path = format!("{}/{}", remote.trim_end_matches('/'), remote_rel);
+    let local_bytes = std::fs::read(&local_path).map_err(|e| e.to_string())?;
+    let remote_bytes = provider
+        .download_to_bytes(&remote_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    if local_bytes == remote_bytes {
+        return Ok(None);
+    }
+
+    let merged = match (
+        base,
+        as_mergeable_text(&local_bytes),
+        as_mergeable_text(&remote_bytes),
+    ) {
+        (Some(base), Some(local_text), Some(remote_text)) => {
+            Some(merge3(base, local_text, remote_text))
+        }
+        _ => None,
+    };
+    let conflict_bytes = match merged {
+        Some(MergeResult::Clean(text)) => {
+            if text.as_bytes() != local_bytes.as_slice() {
+                write_local_file_atomic(&local_path, text.as_bytes())?;
+            }
+            if text.as_bytes() != remote_bytes.as_slice() {
+                if versioning.is_enabled() {
+                    versioning
+                        .archive_remote(provider, remote, remote_rel, true)
+                        .await?;
+                }
+                provider
+                    .upload(&local_path.to_string_lossy(), &remote_path, None)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            return Ok(None);
+        }
+        Some(MergeResult::Conflict { merged, .. }) => merged.into_bytes(),
+        None => local_bytes,
+    };
+
+    let conflict_path = conflict_rename_path(rel);
+    let local_conflict = Path::new(local).join(&conflict_path);
+    write_local_file_atomic(&local_conflict, &conflict_bytes)?;
+    provider
+        .upload(
+            &local_conflict.to_string_lossy(),
+            &format!("{}/{}", remote.trim_end_matches('/'), conflict_path),
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    write_local_file_atomic(&local_path, &remote_bytes)?;
+    Ok(Some(conflict_path))
+}
+
+/// How a remote symlink is stored, for `sync --links`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RemoteLinkKind {
+    /// A real symlink (SFTP).
+    Native,
+    /// A `<name>.rclonelink` file holding the target.
+    StandIn,
+}
+
+/// Create or replace one remote symlink for `sync --links`: a native link
+/// when the backend has them, otherwise a `.rclonelink` file. `existing` is
+/// how the link is currently stored on the remote, if it exists.
+async fn write_remote_link(
+    provider: &mut dyn StorageProvider,
+    remote: &str,
+    link: &LinkEntry,
+    existing: Option<RemoteLinkKind>,
+) -> Result<(), String> {
+    if validate_relative_path(&link.rel_path).is_none() {
+        return Err("unsafe path (traversal rejected)".to_string());
+    }
+    if let Some(kind) = existing {
+        delete_remote_link(provider, remote, &link.rel_path, kind).await?;
+    }
+    let remote_path = format!("{}/{}", remote.trim_end_matches('/'), link.rel_path);
+    if let Some(parent) = Path::new(&remote_path).parent() {
+        let _ = provider.mkdir(&parent.to_string_lossy()).await;
+    }
+    if provider.supports_symlinks() {
+        match provider.create_symlink(&link.target, &remote_path).await {
+            Ok(()) => return Ok(()),
+            Err(ProviderError::NotSupported(_)) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    let mut temp = NamedTempFile::new().map_err(|e| e.to_string())?;
+    temp.write_all(link.target.as_bytes())
+        .and_then(|_| temp.flush())
+        .map_err(|e| e.to_string())?;
+    provider
+        .upload(
+            &temp.path().to_string_lossy(),
+            &format!(
+                "{}/{}",
+                remote.trim_end_matches('/'),
+                link_file_path(&link.rel_path)
+            ),
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Apply permission, owner and xattr changes to one remote entry for
+/// `sync --perms/--owner/--chmod/--xattrs`. Owner changes the server
+/// refuses are skipped; xattrs are written to the entry's `.aeroxattrs` file.
+async fn apply_remote_meta(
+    provider: &mut dyn StorageProvider,
+    remote: &str,
+    rel: &str,
+    diff: &MetaDiff,
+    has_xattr_file: bool,
+) -> Result<(), String> {
+    if validate_relative_path(rel).is_none() {
+        return Err("unsafe path (traversal rejected)".to_string());
+    }
+    let remote_path = format!("{}/{}", remote.trim_end_matches('/'), rel);
+    if let Some(mode) = diff.mode {
+        provider
+            .chmod(&remote_path, mode)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    if diff.uid.is_some() || diff.gid.is_some() {
+        match provider.chown(&remote_path, diff.uid, diff.gid).await {
+            Ok(()) | Err(ProviderError::PermissionDenied(_)) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    if let Some(xattrs) = &diff.xattrs {
+        let xattr_path = format!("{}/{}", remote.trim_end_matches('/'), xattr_file_path(rel));
+        if xattrs.is_empty() {
+            if has_xattr_file {
+                provider
+                    .delete(&xattr_path)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+        } else {
+            let mut temp = NamedTempFile::new().map_err(|e| e.to_string())?;
+            temp.write_all(&encode_xattrs(xattrs))
+                .and_then(|_| temp.flush())
+                .map_err(|e| e.to_string())?;
+            provider
+                .upload(&temp.path().to_string_lossy(), &xattr_path, None)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove a remote symlink synced by `--links`, in whichever form it is stored.
+async fn delete_remote_link(
+    provider: &mut dyn StorageProvider,
+    remote: &str,
+    rel: &str,
+    kind: RemoteLinkKind,
+) -> Result<(), String> {
+    if validate_relative_path(rel).is_none() {
+        return Err("unsafe path (traversal rejected)".to_string());
+    }
+    let stored = match kind {
+        RemoteLinkKind::Native => rel.to_string(),
+        RemoteLinkKind::StandIn => link_file_path(rel),
+    };
+    provider
+        .delete(&format!("{}/{}", remote.trim_end_matches('/'), stored))
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Parse an mtime string to a comparable timestamp (seconds since epoch).
 fn parse_mtime_secs(s: &str) -> Option<i64> {
     // Try ISO 8601 with timezone
@@ -16117,7 +17099,8 @@ fn compare_mtime(a: Option<&str>, b: Option<&str>) -> std::cmp::Ordering {
 }
 
 /// Resolve a conflict between local and remote file for --direction both.
-/// Returns: "upload" (local wins), "download" (remote wins), "rename" (keep both), or "skip".
+/// Returns: "upload" (local wins), "download" (remote wins), "rename" (keep both),
+/// "merge" (three-way merge on the last-synced base, `--conflict-mode merge`), or "skip".
 fn resolve_conflict(
     conflict_mode: &str,
     local_size: u64,
@@ -16172,10 +17155,21 @@ fn resolve_conflict(
             }
         }
         "rename" => "rename",
+        "merge" => "merge",
         _ => "skip", // "skip" or unknown
     }
 }
 
+/// Conflict copy for the `rename` strategy: "a.txt" -> "a.conflict-<ts>.txt".
+fn conflict_rename_path(path: &str) -> String {
+    let ts = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f");
+    if let Some(dot_pos) = path.rfind('.') {
+        format!("{}.conflict-{}{}", &path[..dot_pos], ts, &path[dot_pos..])
+    } else {
+        format!("{}.conflict-{}", path, ts)
+    }
+}
+
 fn partition_conflict_rename_downloads<'a>(
     to_download: Vec<&'a str>,
     to_conflict_upload: &[(String, String)],
@@ -16275,6 +17269,58 @@ async fn cmd_sync(
         );
         return 5.into();
     }
+    let remote_versioning = match cli.remote_versioning.as_deref() {
+        None => VersioningStrategy::Disabled,
+        Some(raw) => match VersioningStrategy::from_name(raw) {
+            Some(strategy) => strategy,
+            None => {
+                print_error(
+                    format,
+                    &format!(
+                        "Invalid --remote-versioning '{}'. Expected one of: disabled, trash_can[:days], simple[:copies], staggered",
+                        raw
+                    ),
+                    5,
+                );
+                return 5.into();
+            }
+        },
+    };
+    let versioning = SyncVersioning::new(Path::new(local), remote_versioning);
+    let name_collision = match cli.name_collision.as_deref().map(CollisionPolicy::parse) {
+        None => CollisionPolicy::default(),
+        Some(Ok(policy)) => policy,
+        Some(Err(e)) => {
+            print_error(format, &e, 5);
+            return 5.into();
+        }
+    };
+    let link_mode = match LinkMode::from_flags(cli.links, cli.copy_links) {
+        Ok(mode) => mode,
+        Err(e) => {
+            print_error(format, &e, 5);
+            return 5.into();
+        }
+    };
+    let meta_opts = MetaOptions {
+        perms: cli.perms,
+        owner: cli.owner,
+        xattrs: cli.xattrs,
+        chmod: match cli.chmod.as_deref().map(ChmodRules::parse).transpose() {
+            Ok(rules) => rules,
+            Err(e) => {
+                print_error(format, &e, 5);
+                return 5.into();
+            }
+        },
+    };
+    let (planner, deadline) = match cli_sync_planner(cli) {
+        Ok(v) => v,
+        Err(e) => {
+            print_error(format, &e, 5);
+            return 5.into();
+        }
+    };
 
     let (mut provider, initial_path) = match create_and_connect(url, cli, format).await {
         Ok(v) => v,
@@ -16285,6 +17331,31 @@ async fn cmd_sync(
     let quiet = cli.quiet || matches!(format, OutputFormat::Json);
     let start = Instant::now();
 
+    // What the remote can store: without chmod/chown a backend keeps neither,
+    // so uploads leave them out rather than retrying them on every run.
+    let remote_meta_opts = MetaOptions {
+        perms: meta_opts.perms && provider.supports_chmod(),
+        owner: meta_opts.owner && provider.supports_chown(),
+        chmod: meta_opts
+            .chmod
+            .clone()
+            .filter(|_| provider.supports_chmod()),
+        xattrs: meta_opts.xattrs,
+    };
+    let mut unstorable = Vec::new();
+    if meta_opts.tracks_mode() && !remote_meta_opts.tracks_mode() {
+        unstorable.push("permissions");
+    }
+    if meta_opts.owner && !remote_meta_opts.owner {
+        unstorable.push("owners");
+    }
+    if !unstorable.is_empty() && direction != "download" && !quiet {
+        eprintln!(
+            "Note: this backend cannot store {}; they are only synced on download",
+            unstorable.join(" or ")
+        );
+    }
+
     if !quiet {
         if let Some(reconcile_path) = from_reconcile {
             eprintln!("Using reconcile plan: {}", reconcile_path);
@@ -16308,8 +17379,17 @@ async fn cmd_sync(
     let scan_depth = cli.max_depth.map(|d| d as usize).unwrap_or(100);
 
     let mut reconcile_plan: Option<ReconcileSyncPlan> = None;
+    // --change-feed: cursor to persist with the bisync snapshot. Stays None
+    // when the flag is off, the provider has no feed, or the full scan was
+    // truncated (an incomplete listing must never seed incremental runs).
+    let mut change_feed_next: Option<ftp_client_gui_lib::sync_core::ChangeFeedState> = None;
+    // --links: remote symlinks found by the scan, and how each is stored.
+    let mut remote_links: Vec<LinkEntry> = Vec::new();
+    let mut remote_link_kinds: HashMap<String, RemoteLinkKind> = HashMap::new();
+    // --perms/--owner/--chmod/--xattrs: metadata of remote entries by path.
+    let mut remote_meta: HashMap<String, FileMeta> = HashMap::new();
     #[allow(clippy::type_complexity)]
-    let (local_entries, remote_entries): (
+    let (mut local_entries, mut remote_entries): (
         Vec<(String, u64, Option<String>)>,
         Vec<(String, u64, Option<String>)>,
     ) = if let Some(reconcile_path) = from_reconcile {
@@ -16333,11 +17413,12 @@ async fn cmd_sync(
         {
             pre
         } else {
-            let walker = walkdir::WalkDir::new(local)
-                .follow_links(false)
-                .max_depth(scan_depth);
+            let mut walker = walkdir::WalkDir::new(local)
+                .follow_links(link_mode == LinkMode::Follow)
+                .max_depth(scan_depth)
+                .into_iter();
             let mut entries = Vec::new();
-            for entry in walker {
+            while let Some(entry) = walker.next() {
                 if entries.len() >= 500_000 {
                     eprintln!("Warning: local scan capped at 500,000 entries");
                     break;
@@ -16346,15 +17427,27 @@ async fn cmd_sync(
                     Ok(e) => e,
                     Err(_) => continue,
                 };
-                if !entry.file_type().is_file() {
-                    continue;
-                }
                 let relative = entry
                     .path()
                     .strip_prefix(local)
                     .unwrap_or(entry.path())
                     .to_string_lossy()
                     .replace('\\', "/");
+                // --safe-links: with --copy-links, never follow a link that
+                // leaves the tree (nor descend into such a directory).
+                if cli.safe_links
+                    && entry.path_is_symlink()
+                    && !std::fs::read_link(entry.path())
+                        .is_ok_and(|target| is_safe_link(&relative, &target.to_string_lossy()))
+                {
+                    if entry.file_type().is_dir() {
+                        walker.skip_current_dir();
+                    }
+                    continue;
+                }
+                if !entry.file_type().is_file() {
+                    continue;
+                }
                 if relative.is_empty() || relative == BISYNC_SNAPSHOT_FILE {
                     continue;
                 }
@@ -16398,15 +17491,85 @@ async fn cmd_sync(
         }
 
         let mut remote_entries: Vec<(String, u64, Option<String>)> = Vec::new();
+        let mut used_change_feed = false;
+        if cli.change_feed && !cli.no_check_dest && direction == "both" && !resync {
+            let previous = load_bisync_snapshot(local)
+                .and_then(|snap| snap.change_feed)
+                .filter(|state| state.matches_root(remote));
+            if let Some(mut state) = previous {
+                match ftp_client_gui_lib::sync_core::refresh_from_change_feed(
+                    provider.as_mut(),
+                    &mut state,
+                )
+                .await
+                {
+                    Ok(stats) => {
+                        if !quiet {
+                            eprintln!(
+                                "Change feed: {} change(s), {} updated, {} removed, {} folder move(s)",
+                                stats.changes_seen, stats.upserted, stats.removed, stats.moved_dirs
+                            );
+                        }
+                        let max_depth = cli.max_depth.map(|d| d as usize);
+                        for (relative, file) in &state.files {
+                            if relative == BISYNC_SNAPSHOT_FILE {
+                                continue;
+                            }
+                            if let Some(max_d) = max_depth {
+                                if relative.matches('/').count() >= max_d {
+                                    continue;
+                                }
+                            }
+                            let name = relative.rsplit('/').next().unwrap_or(relative);
+                            if exclude_matchers
+                                .iter()
+                                .any(|m| m.is_match(relative) || m.is_match(name))
+                            {
+                                continue;
+                            }
+                            if let Some(ref set) = files_from_set {
+                                if !set.contains(relative.as_str()) {
+                                    continue;
+                                }
+                            }
+                            remote_entries.push((relative.clone(), file.size, file.mtime.clone()));
+                        }
+                        used_change_feed = true;
+                        change_feed_next = Some(state);
+                    }
+                    Err(reason) => {
+                        if !quiet {
+                            eprintln!("Note: {}; falling back to full remote scan", reason);
+                        }
+                    }
+                }
+            }
+            if !used_change_feed {
+                match ftp_client_gui_lib::sync_core::capture_change_token(provider.as_mut()).await {
+                    Some(token) => {
+                        change_feed_next = Some(
+                            ftp_client_gui_lib::sync_core::ChangeFeedState::new(token, remote),
+                        );
+                    }
+                    None => {
+                        if !quiet {
+                            eprintln!(
+                                "Note: --change-feed not supported by this provider; using standard scan"
+                            );
+                        }
+                    }
+                }
+            }
+        }
         if cli.no_check_dest {
             if !quiet {
                 eprintln!(
                     "Note: --no-check-dest skipping remote scan (assuming empty destination)"
                 );
             }
-        } else {
+        } else if !used_change_feed {
             let mut used_fast_list = false;
-            if cli.fast_list {
+            if cli.fast_list && change_feed_next.is_none() {
                 if let Some(s3) = provider
                     .as_any_mut()
                     .downcast_mut::<ftp_client_gui_lib::providers::s3::S3Provider>()
@@ -16478,20 +17641,24 @@ async fn cmd_sync(
                 let remote_scan_depth = cli.max_depth.map(|d| d as usize).unwrap_or(MAX_SCAN_DEPTH);
                 let mut queue: Vec<(String, String, usize)> =
                     vec![(remote.to_string(), String::new(), 0)];
+                let mut scan_complete = true;
                 while let Some((abs_dir, rel_prefix, depth)) = queue.pop() {
                     if cancelled.load(Ordering::Relaxed) {
+                        scan_complete = false;
                         break;
                     }
                     if depth >= remote_scan_depth {
                         if !quiet {
                             eprintln!("Warning: max scan depth reached at {}", abs_dir);
                         }
+                        scan_complete = false;
                         continue;
                     }
                     if remote_entries.len() >= MAX_SCAN_ENTRIES {
                         if !quiet {
                             eprintln!("Warning: max entries reached during remote scan");
                         }
+                        scan_complete = false;
                         break;
                     }
                     match provider.list(&abs_dir).await {
@@ -16502,6 +17669,59 @@ async fn cmd_sync(
                                 } else {
                                     format!("{}/{}", rel_prefix, e.name)
                                 };
+                                if is_versions_path(&entry_rel) {
+                                    continue;
+                                }
+                                if e.is_symlink {
+                                    if cli.safe_links
+                                        && e.link_target
+                                            .as_deref()
+                                            .is_some_and(|t| !is_safe_link(&entry_rel, t))
+                                    {
+                                        continue;
+                                    }
+                                    if let (LinkMode::Preserve, Some(target)) =
+                                        (link_mode, e.link_target.clone())
+                                    {
+                                        if !exclude_matchers
+                                            .iter()
+                                            .any(|m| m.is_match(&entry_rel) || m.is_match(&e.name))
+                                        {
+                                            remote_link_kinds
+                                                .insert(entry_rel.clone(), RemoteLinkKind::Native);
+                                            remote_links.push(LinkEntry {
+                                                rel_path: entry_rel,
+                                                target,
+                                            });
+                                        }
+                                        continue;
+                                    }
+                                }
+                                if meta_opts.is_enabled() {
+                                    remote_meta.insert(
+                                        entry_rel.clone(),
+                                        FileMeta::from_listing(
+                                            e.permissions.as_deref(),
+                                            e.owner.as_deref(),
+                                            e.group.as_deref(),
+                                        ),
+                                    );
+                                }
+                                // Seed the change-feed listing before excludes
+                                // so later runs can re-filter with other flags.
+                                if let Some(state) = change_feed_next.as_mut() {
+                                    let id = e.metadata.get("id").map(String::as_str);
+                                    if e.is_dir {
+                                        state.record_dir(&entry_rel, id);
+                                    } else {
+                                        state.record_file(
+                                            &entry_rel,
+                                            e.size,
+                                            e.modified.clone(),
+                                            id,
+                                        );
+                                    }
+                                }
                                 if e.is_dir {
                                     queue.push((e.path.clone(), entry_rel, depth + 1));
                                 } else {
@@ -16522,33 +17742,194 @@ async fn cmd_sync(
                             if !quiet {
                                 eprintln!("Warning: cannot scan {}: {}", abs_dir, e);
                             }
+                            scan_complete = false;
                         }
                     }
                 }
+                if !scan_complete && change_feed_next.take().is_some() && !quiet {
+                    eprintln!("Note: remote scan incomplete; change feed cursor not saved");
+                }
             }
         }
 
         (local_entries, remote_entries)
     };
+    // Archived versions (.aeroversions/) never take part in the sync itself.
+    local_entries.retain(|(path, _, _)| !is_versions_path(path));
+    remote_entries.retain(|(path, _, _)| !is_versions_path(path));
+    // --aeroignore: the local root's ignore file filters both sides, so an
+    // ignored path is neither uploaded, downloaded nor deleted.
+    if cli.aeroignore {
+        if let Some(ignore) = AeroIgnore::load(Path::new(local)) {
+            local_entries.retain(|(path, _, _)| !ignore.is_path_ignored(path));
+            remote_entries.retain(|(path, _, _)| !ignore.is_path_ignored(path));
+        }
+    }
+
+    // --links: symlinks are synced apart from files. On backends without
+    // symlinks they are stored as `<name>.rclonelink` files holding the target.
+    let mut local_links: Vec<LinkEntry> = Vec::new();
+    if link_mode == LinkMode::Preserve && reconcile_plan.is_none() {
+        let link_scan = ftp_client_gui_lib::sync_core::ScanOptions {
+            max_depth: Some(scan_depth),
+            exclude_patterns: exclude.to_vec(),
+            files_from: files_from_set.clone(),
+            skip_filenames: vec![BISYNC_SNAPSHOT_FILE.to_string()],
+            safe_links: cli.safe_links,
+            ..Default::default()
+        };
+        local_links = scan_local_links(local, &link_scan);
+        local_links.retain(|link| !is_versions_path(&link.rel_path));
 
-    // Build comparison maps
-    let local_map: HashMap<&str, (u64, Option<&str>)> = local_entries
-        .iter()
-        .map(|(p, s, m)| (p.as_str(), (*s, m.as_deref())))
-        .collect();
-    let remote_map: HashMap<&str, (u64, Option<&str>)> = remote_entries
-        .iter()
-        .map(|(p, s, m)| (p.as_str(), (*s, m.as_deref())))
-        .collect();
-
-    // Load previous snapshot for bisync delta detection (--direction both only)
-    let prev_snapshot = if direction == "both" && !resync {
-        load_bisync_snapshot(local)
-    } else {
-        if resync && !quiet {
-            eprintln!("--resync: ignoring previous snapshot, full scan");
-        }
-        None
+        let mut stand_ins: Vec<String> = Vec::new();
+        remote_entries.retain(|(path, _, _)| match strip_link_suffix(path) {
+            Some(link) => {
+                stand_ins.push(link.to_string());
+                false
+            }
+            None => true,
+        });
+        for link in stand_ins {
+            let remote_path = format!("{}/{}", remote.trim_end_matches('/'), link_file_path(&link));
+            match provider.download_to_bytes(&remote_path).await {
+                Ok(bytes) => {
+                    let target = String::from_utf8_lossy(&bytes).into_owned();
+                    if cli.safe_links && !is_safe_link(&link, &target) {
+                        continue;
+                    }
+                    remote_link_kinds.insert(link.clone(), RemoteLinkKind::StandIn);
+                    remote_links.push(LinkEntry {
+                        rel_path: link,
+                        target,
+                    });
+                }
+                Err(e) => {
+                    if !quiet {
+                        eprintln!("Warning: cannot read link file {}: {}", remote_path, e);
+                    }
+                }
+            }
+        }
+    }
+    let link_plan = if link_mode == LinkMode::Preserve {
+        plan_links(
+            &local_links,
+            &remote_links,
+            direction != "download",
+            direction != "upload",
+            delete,
+        )
+    } else {
+        LinkPlan::default()
+    };
+
+    // --xattrs: remote xattrs live in a `<name>.aeroxattrs` file next to each
+    // entry; they are read into the remote metadata, never synced as files.
+    let mut remote_xattr_files: std::collections::HashSet<String> =
+        std::collections::HashSet::new();
+    if meta_opts.xattrs && reconcile_plan.is_none() {
+        remote_entries.retain(|(path, _, _)| match strip_xattr_suffix(path) {
+            Some(base) => {
+                remote_xattr_files.insert(base.to_string());
+                false
+            }
+            None => true,
+        });
+        for base in &remote_xattr_files {
+            let remote_path = format!("{}/{}", remote.trim_end_matches('/'), xattr_file_path(base));
+            let read = provider
+                .download_to_bytes(&remote_path)
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| decode_xattrs(&bytes));
+            match read {
+                Ok(xattrs) => remote_meta.entry(base.clone()).or_default().xattrs = xattrs,
+                Err(e) => {
+                    if !quiet {
+                        eprintln!("Warning: cannot read xattr file {}: {}", remote_path, e);
+                    }
+                }
+            }
+        }
+    }
+
+    // Pair NFC/NFD and case variants of the same file, and resolve paths that
+    // would collapse into one file on the destination. From here on remote
+    // entries carry their local-namespace path; `name_plan.remote_path()`
+    // maps back for remote operations. Reconcile plans are replayed verbatim.
+    let name_plan = if reconcile_plan.is_some() {
+        NamePlan::default()
+    } else {
+        let options = NameOptions {
+            local_case_insensitive: local_fs_case_insensitive(),
+            remote_case_insensitive: provider.is_case_insensitive(),
+            policy: name_collision,
+            uploads: direction != "download",
+            downloads: direction != "upload",
+        };
+        match plan_names(
+            local_entries.iter().map(|(p, _, _)| p.as_str()),
+            remote_entries.iter().map(|(p, _, _)| p.as_str()),
+            options,
+        ) {
+            Ok(plan) => plan,
+            Err(collisions) => {
+                let groups: Vec<String> = collisions.iter().map(|c| c.paths.join(" | ")).collect();
+                print_error(
+                    format,
+                    &format!(
+                        "{} name collision(s) on a case-insensitive or normalizing target (use --name-collision rename|skip): {}",
+                        collisions.len(),
+                        groups.join("; ")
+                    ),
+                    5,
+                );
+                let _ = provider.disconnect().await;
+                return 5.into();
+            }
+        }
+    };
+    if !quiet {
+        for collision in &name_plan.collisions {
+            eprintln!(
+                "Name collision ({} {:?}): {}",
+                if collision.side == CollisionSide::Local {
+                    "local"
+                } else {
+                    "remote"
+                },
+                collision.kind,
+                collision.paths.join(" | ")
+            );
+        }
+    }
+    local_entries.retain(|(path, _, _)| !name_plan.skip_local.contains(path));
+    remote_entries.retain(|(path, _, _)| !name_plan.skip_remote.contains(path));
+    for (path, _, _) in remote_entries.iter_mut() {
+        if let Some(local_path) = name_plan.remote_to_local.get(path.as_str()) {
+            *path = local_path.clone();
+        }
+    }
+    let skipped_collisions = name_plan.skip_local.union(&name_plan.skip_remote).count() as u32;
+
+    // Build comparison maps
+    let local_map: HashMap<&str, (u64, Option<&str>)> = local_entries
+        .iter()
+        .map(|(p, s, m)| (p.as_str(), (*s, m.as_deref())))
+        .collect();
+    let remote_map: HashMap<&str, (u64, Option<&str>)> = remote_entries
+        .iter()
+        .map(|(p, s, m)| (p.as_str(), (*s, m.as_deref())))
+        .collect();
+
+    // Load previous snapshot for bisync delta detection (--direction both only)
+    let prev_snapshot = if direction == "both" && !resync {
+        load_bisync_snapshot(local)
+    } else {
+        if resync && !quiet {
+            eprintln!("--resync: ignoring previous snapshot, full scan");
+        }
+        None
     };
 
     let default_time_val = resolve_default_time(cli);
@@ -16577,8 +17958,10 @@ async fn cmd_sync(
     let mut to_delete_local: Vec<&str> = Vec::new();
     // Conflict renames: (original_relative_path, conflict_suffixed_remote_path)
     let mut to_conflict_upload: Vec<(String, String)> = Vec::new();
+    // --conflict-mode merge: (relative_path, merge base hash)
+    let mut to_merge: Vec<(&str, String)> = Vec::new();
     let mut conflicts_resolved: u32 = 0;
-    let mut skipped: u32 = 0;
+    let mut skipped: u32 = skipped_collisions;
 
     if reconcile_plan.is_some() {
         to_upload = owned_to_upload.iter().map(String::as_str).collect();
@@ -16610,13 +17993,23 @@ async fn cmd_sync(
                         "rename" => {
                             // Keep both: download remote version, upload local with conflict suffix
                             to_download.push(path);
-                            let ts = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f");
-                            let conflict_path = if let Some(dot_pos) = path.rfind('.') {
-                                format!("{}.conflict-{}{}", &path[..dot_pos], ts, &path[dot_pos..])
+                            to_conflict_upload.push((path.to_string(), conflict_rename_path(path)));
+                            conflicts_resolved += 1;
+                        }
+                        "merge" => {
+                            // Three-way merge needs the content from the last sync;
+                            // without it the conflict is handled like "rename".
+                            let base = prev_snapshot
+                                .as_ref()
+                                .and_then(|snap| snap.merge_bases.get(*path))
+                                .filter(|_| *size <= MAX_MERGE_BYTES && *rsize <= MAX_MERGE_BYTES);
+                            if let Some(base) = base {
+                                to_merge.push((*path, base.clone()));
                             } else {
-                                format!("{}.conflict-{}", path, ts)
-                            };
-                            to_conflict_upload.push((path.to_string(), conflict_path));
+                                to_download.push(path);
+                                to_conflict_upload
+                                    .push((path.to_string(), conflict_rename_path(path)));
+                            }
                             conflicts_resolved += 1;
                         }
                         _ => {
@@ -16714,13 +18107,15 @@ async fn cmd_sync(
             eprintln!("Checking for renamed files...");
         }
         // Build hash map of files to upload (local side)
+        let checksum_cache = ChecksumCache::shared_for_root(Path::new(local));
         let mut upload_hashes: std::collections::HashMap<String, Vec<String>> =
             std::collections::HashMap::new();
         for up_path in &to_upload {
             let local_file = std::path::Path::new(local).join(up_path);
-            if let Ok(data) = std::fs::read(&local_file) {
-                use sha2::Digest;
-                let hash = format!("{:x}", sha2::Sha256::digest(&data));
+            let meta = std::fs::metadata(&local_file).ok();
+            if let Some(hash) =
+                cached_local_sha256(checksum_cache.as_deref(), &local_file, meta.as_ref())
+            {
                 upload_hashes
                     .entry(hash)
                     .or_default()
@@ -16733,10 +18128,11 @@ async fn cmd_sync(
         let mut matched_deletes: std::collections::HashSet<String> =
             std::collections::HashSet::new();
         for del_path in &to_delete_remote {
+            let remote_rel = name_plan.remote_path(del_path);
             let remote_full = if remote.ends_with('/') {
-                format!("{}{}", remote, del_path)
+                format!("{}{}", remote, remote_rel)
             } else {
-                format!("{}/{}", remote, del_path)
+                format!("{}/{}", remote, remote_rel)
             };
             if let Ok(data) = provider.download_to_bytes(&remote_full).await {
                 use sha2::Digest;
@@ -16860,6 +18256,80 @@ async fn cmd_sync(
         }
     }
 
+    // --hard-links: a file whose hardlink group leader will be on the remote
+    // is linked (or server-side copied) to it there instead of uploaded.
+    let mut to_hardlink: Vec<(&str, String)> = Vec::new();
+    if cli.hard_links
+        && reconcile_plan.is_none()
+        && (provider.supports_hardlinks() || provider.supports_server_copy())
+    {
+        let groups =
+            HardlinkGroups::detect(local, local_entries.iter().map(|(p, _, _)| p.as_str()));
+        let uploading: std::collections::HashSet<&str> = to_upload.iter().copied().collect();
+        let downloading: std::collections::HashSet<&str> = to_download.iter().copied().collect();
+        to_upload.retain(|path| {
+            let Some(leader) = groups.leader(path) else {
+                return true;
+            };
+            let leader_on_remote = uploading.contains(leader)
+                || (remote_map.contains_key(leader) && !downloading.contains(leader));
+            if leader_on_remote {
+                to_hardlink.push((*path, leader.to_string()));
+            }
+            !leader_on_remote
+        });
+    }
+
+    // --perms/--owner/--chmod/--xattrs: in one-way syncs, files whose content
+    // already matches (and every directory) only get their metadata updated.
+    let mut to_set_meta: Vec<(String, bool, MetaDiff)> = Vec::new();
+    if meta_opts.is_enabled() && reconcile_plan.is_none() && direction != "both" {
+        let upload = direction == "upload";
+        let (source_map, dest_map, opts) = if upload {
+            (&local_map, &remote_map, &remote_meta_opts)
+        } else {
+            (&remote_map, &local_map, &meta_opts)
+        };
+        let transferring: std::collections::HashSet<&str> = to_upload
+            .iter()
+            .chain(to_download.iter())
+            .chain(to_hardlink.iter().map(|(path, _)| path))
+            .copied()
+            .collect();
+        let mut dirs: std::collections::BTreeSet<String> = std::collections::BTreeSet::new();
+        for path in source_map.keys() {
+            let mut parent = Path::new(path).parent();
+            while let Some(dir) = parent.filter(|dir| !dir.as_os_str().is_empty()) {
+                if !dirs.insert(dir.to_string_lossy().replace('\\', "/")) {
+                    break;
+                }
+                parent = dir.parent();
+            }
+        }
+        let files = source_map
+            .keys()
+            .filter(|path| dest_map.contains_key(*path) && !transferring.contains(*path))
+            .map(|path| (path.to_string(), false));
+        for (path, is_dir) in files.chain(dirs.into_iter().map(|dir| (dir, true))) {
+            let local_meta = read_local_meta(&Path::new(local).join(&path), &meta_opts);
+            let remote_rel = if is_dir {
+                path.as_str()
+            } else {
+                name_plan.remote_path(&path)
+            };
+            let remote_side = remote_meta.get(remote_rel).cloned().unwrap_or_default();
+            let diff = if upload {
+                opts.diff(&local_meta, &remote_side, is_dir)
+            } else {
+                opts.diff(&remote_side, &local_meta, is_dir)
+            };
+            if !diff.is_empty() {
+                to_set_meta.push((path, is_dir, diff));
+            }
+        }
+        to_set_meta.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+
     if !quiet {
         let conflict_info = if conflicts_resolved > 0 {
             format!(
@@ -16870,15 +18340,36 @@ async fn cmd_sync(
             String::new()
         };
         eprintln!(
-            "\nSync plan: {} upload, {} download, {} delete, {} rename, {} conflict-rename, {} skipped{}",
+            "\nSync plan: {} upload, {} download, {} delete, {} rename, {} conflict-rename, {} merge, {} skipped{}",
             to_upload.len(),
             to_download.len(),
             to_delete_remote.len() + to_delete_local.len(),
             renames.len(),
             to_conflict_upload.len(),
+            to_merge.len(),
             skipped,
             conflict_info
         );
+        if !to_hardlink.is_empty() {
+            eprintln!(
+                "Hardlinks: {} file(s) linked to an uploaded copy instead of uploaded",
+                to_hardlink.len()
+            );
+        }
+        if !link_plan.is_empty() || !link_plan.conflicts.is_empty() {
+            eprintln!(
+                "Symlinks: {} to create, {} to delete, {} conflict(s) left untouched",
+                link_plan.create_remote.len() + link_plan.create_local.len(),
+                link_plan.delete_remote.len() + link_plan.delete_local.len(),
+                link_plan.conflicts.len()
+            );
+        }
+        if !to_set_meta.is_empty() {
+            eprintln!(
+                "Metadata: {} entr(ies) to update without a transfer",
+                to_set_meta.len()
+            );
+        }
     }
 
     // --max-delete safety check
@@ -16920,6 +18411,32 @@ async fn cmd_sync(
                 for (orig, conflict) in &to_conflict_upload {
                     println!("  CONFLICT-RENAME  {} -> {}", orig, conflict);
                 }
+                for (p, _) in &to_merge {
+                    println!("  MERGE  {}", p);
+                }
+                for (p, leader) in &to_hardlink {
+                    println!("  HARDLINK  {} => {}", p, leader);
+                }
+                for link in &link_plan.create_remote {
+                    println!("  SYMLINK (remote)  {} -> {}", link.rel_path, link.target);
+                }
+                for link in &link_plan.create_local {
+                    println!("  SYMLINK (local)  {} -> {}", link.rel_path, link.target);
+                }
+                for p in &link_plan.delete_remote {
+                    println!("  DELETE LINK (remote)  {}", p);
+                }
+                for p in &link_plan.delete_local {
+                    println!("  DELETE LINK (local)  {}", p);
+                }
+                let meta_side = if direction == "upload" {
+                    "remote"
+                } else {
+                    "local"
+                };
+                for (p, _, diff) in &to_set_meta {
+                    println!("  META ({})  {}  {}", meta_side, p, diff.describe());
+                }
                 println!("\n(dry run - no changes made)");
             }
             OutputFormat::Json => {
@@ -16933,7 +18450,8 @@ async fn cmd_sync(
                         + to_download.len()
                         + to_delete_remote.len()
                         + to_delete_local.len()
-                        + to_conflict_upload.len(),
+                        + to_conflict_upload.len()
+                        + to_merge.len(),
                 );
                 for p in &to_upload {
                     plan.push(CliSyncPlanEntry {
@@ -16942,6 +18460,8 @@ async fn cmd_sync(
                         local_size: local_map.get(*p).map(|(s, _)| *s),
                         remote_size: remote_map.get(*p).map(|(s, _)| *s),
                         conflict_path: None,
+                        link_target: None,
+                        metadata: None,
                     });
                 }
                 for p in &to_download {
@@ -16951,6 +18471,8 @@ async fn cmd_sync(
                         local_size: local_map.get(*p).map(|(s, _)| *s),
                         remote_size: remote_map.get(*p).map(|(s, _)| *s),
                         conflict_path: None,
+                        link_target: None,
+                        metadata: None,
                     });
                 }
                 for p in &to_delete_remote {
@@ -16960,6 +18482,8 @@ async fn cmd_sync(
                         local_size: None,
                         remote_size: remote_map.get(*p).map(|(s, _)| *s),
                         conflict_path: None,
+                        link_target: None,
+                        metadata: None,
                     });
                 }
                 for p in &to_delete_local {
@@ -16969,6 +18493,8 @@ async fn cmd_sync(
                         local_size: local_map.get(*p).map(|(s, _)| *s),
                         remote_size: None,
                         conflict_path: None,
+                        link_target: None,
+                        metadata: None,
                     });
                 }
                 for (orig, conflict) in &to_conflict_upload {
@@ -16978,28 +18504,85 @@ async fn cmd_sync(
                         local_size: local_map.get(orig.as_str()).map(|(s, _)| *s),
                         remote_size: remote_map.get(orig.as_str()).map(|(s, _)| *s),
                         conflict_path: Some(conflict.clone()),
+                        link_target: None,
+                        metadata: None,
+                    });
+                }
+                for (p, _) in &to_merge {
+                    plan.push(CliSyncPlanEntry {
+                        op: "merge",
+                        path: (*p).to_string(),
+                        local_size: local_map.get(*p).map(|(s, _)| *s),
+                        remote_size: remote_map.get(*p).map(|(s, _)| *s),
+                        conflict_path: None,
+                        link_target: None,
+                        metadata: None,
+                    });
+                }
+                for (p, leader) in &to_hardlink {
+                    plan.push(CliSyncPlanEntry {
+                        op: "hardlink",
+                        path: (*p).to_string(),
+                        local_size: local_map.get(*p).map(|(s, _)| *s),
+                        remote_size: remote_map.get(*p).map(|(s, _)| *s),
+                        link_target: Some(leader.clone()),
+                        ..Default::default()
                     });
                 }
+                for (op, links) in [
+                    ("symlink_remote", &link_plan.create_remote),
+                    ("symlink_local", &link_plan.create_local),
+                ] {
+                    plan.extend(links.iter().map(|link| CliSyncPlanEntry {
+                        op,
+                        path: link.rel_path.clone(),
+                        link_target: Some(link.target.clone()),
+                        ..Default::default()
+                    }));
+                }
+                for (op, paths) in [
+                    ("delete_link_remote", &link_plan.delete_remote),
+                    ("delete_link_local", &link_plan.delete_local),
+                ] {
+                    plan.extend(paths.iter().map(|p| CliSyncPlanEntry {
+                        op,
+                        path: p.clone(),
+                        ..Default::default()
+                    }));
+                }
+                let meta_op = if direction == "upload" {
+                    "metadata_remote"
+                } else {
+                    "metadata_local"
+                };
+                plan.extend(to_set_meta.iter().map(|(p, _, diff)| CliSyncPlanEntry {
+                    op: meta_op,
+                    path: p.clone(),
+                    metadata: Some(diff.clone()),
+                    ..Default::default()
+                }));
                 print_json(&CliSyncResult {
                     status: "dry_run",
-                    uploaded: to_upload.len() as u32,
+                    uploaded: (to_upload.len() + to_hardlink.len()) as u32,
                     downloaded: to_download.len() as u32,
                     deleted: (to_delete_remote.len() + to_delete_local.len()) as u32,
                     skipped,
                     errors: vec![],
                     elapsed_secs: start.elapsed().as_secs_f64(),
                     plan,
+                    deferred: Vec::new(),
                 });
             }
         }
         let _ = provider.disconnect().await;
         return SyncCycleStats {
             exit_code: 0,
-            uploaded: to_upload.len() as u32,
+            uploaded: (to_upload.len() + to_hardlink.len()) as u32,
             downloaded: to_download.len() as u32,
             deleted: (to_delete_remote.len() + to_delete_local.len()) as u32,
             skipped,
             error_count: 0,
+            errors: Vec::new(),
         };
     }
 
@@ -17020,17 +18603,50 @@ async fn cmd_sync(
     let mut downloaded: u32 = 0;
     let mut deleted: u32 = 0;
     let mut errors: Vec<String> = Vec::new();
+    // Files transferred this run, which take the source's metadata afterwards.
+    let mut uploaded_paths: Vec<String> = Vec::new();
+    let mut downloaded_paths: Vec<String> = Vec::new();
+
+    // Transfers a previous run deferred at its deadline go first this time.
+    let journal_local = std::fs::canonicalize(local)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| local.to_string());
+    let resumed = load_sync_journal(&journal_local, remote)
+        .ok()
+        .flatten()
+        .filter(|journal| journal.has_resumable_entries())
+        .map(|journal| pending_paths(&journal))
+        .unwrap_or_default();
+    let resuming = !resumed.is_empty();
+    if resuming && !quiet {
+        eprintln!(
+            "Resuming {} transfer(s) deferred by the previous run",
+            resumed.len()
+        );
+    }
+    let planner = planner.with_resumed(resumed);
 
-    let upload_jobs: Vec<(String, String, String, u64)> = to_upload
+    let mut upload_jobs: Vec<(String, String, String, u64)> = to_upload
         .iter()
         .map(|path| {
             let relative = (*path).to_string();
             let local_path = Path::new(local).join(path).to_string_lossy().to_string();
-            let remote_path = format!("{}/{}", remote.trim_end_matches('/'), path);
+            let remote_path = format!(
+                "{}/{}",
+                remote.trim_end_matches('/'),
+                name_plan.remote_path(path)
+            );
             let size = local_map.get(*path).map(|(size, _)| *size).unwrap_or(0);
             (relative, local_path, remote_path, size)
         })
         .collect();
+    planner.sort(&mut upload_jobs, |(path, _, _, size)| PlanItem {
+        path,
+        size: *size,
+        mtime: local_map
+            .get(path.as_str())
+            .and_then(|(_, mtime)| mtime.and_then(parse_mtime_secs)),
+    });
 
     let total_transfer_files = upload_jobs.len() + to_download.len();
     let total_transfer_bytes: u64 = upload_jobs.iter().map(|(_, _, _, size)| *size).sum::<u64>()
@@ -17056,6 +18672,17 @@ async fn cmd_sync(
         })
         .filter(|dir| !dir.is_empty() && dir != "/")
         .collect();
+    upload_dirs.extend(to_hardlink.iter().filter_map(|(path, _)| {
+        let remote_path = format!(
+            "{}/{}",
+            remote.trim_end_matches('/'),
+            name_plan.remote_path(path)
+        );
+        Path::new(&remote_path)
+            .parent()
+            .map(|parent| parent.to_string_lossy().to_string())
+            .filter(|dir| !dir.is_empty() && dir != "/")
+    }));
     upload_dirs.sort_by(|left, right| {
         let left_depth = left.matches('/').count();
         let right_depth = right.matches('/').count();
@@ -17066,6 +18693,22 @@ async fn cmd_sync(
         let _ = provider.mkdir(dir).await;
     }
 
+    let history = deadline
+        .and_then(|_| target_host(url, cli))
+        .and_then(|host| history_throughput(&host));
+    let budget = TransferBudget::new(deadline, history);
+    if let (Some(deadline), false) = (budget.deadline(), quiet) {
+        eprintln!(
+            "Planning to finish by {}{}",
+            deadline.wall.format("%Y-%m-%d %H:%M"),
+            if history.is_some() {
+                " (throughput from speed test history)"
+            } else {
+                ""
+            }
+        );
+    }
+
     let aggregate = Arc::new(AtomicU64::new(0));
     let overall_pb = if !quiet && total_transfer_bytes > 0 {
         Some(create_overall_progress_bar(
@@ -17076,29 +18719,72 @@ async fn cmd_sync(
         None
     };
 
+    // --remote-versioning: a remote file is archived right before the upload
+    // that overwrites it, once the deadline has admitted that upload, so a
+    // deferred upload leaves it in place. A file that cannot be archived is
+    // not overwritten. Without server-side copy the archive is a rename,
+    // which is undone when the upload then fails.
+    let archive_moves = !provider.supports_server_copy();
+    let archiver = AsyncMutex::new(provider.as_mut());
     let upload_results = futures_util::stream::iter(upload_jobs.into_iter().map(
-        |(path, local_path, remote_path, _size)| {
+        |(path, local_path, remote_path, size)| {
             let cancelled = cancelled.clone();
             let aggregate = aggregate.clone();
             let overall_pb = overall_pb.clone();
+            let budget = &budget;
+            let archiver = &archiver;
+            let versioning = &versioning;
+            let remote_map = &remote_map;
+            let name_plan = &name_plan;
             async move {
                 if cancelled.load(Ordering::Relaxed) {
                     return Err(format!("upload {}: cancelled", path));
                 }
-                match upload_transfer_task(
+                let transferred = aggregate.load(Ordering::Relaxed);
+                if !budget.admit(&path, TransferKind::Upload, size, transferred) {
+                    return Ok(None);
+                }
+                let mut archived = None;
+                if versioning.is_enabled() && remote_map.contains_key(path.as_str()) {
+                    let mut p = archiver.lock().await;
+                    match versioning
+                        .archive_remote(&mut **p, remote, name_plan.remote_path(&path), true)
+                        .await
+                    {
+                        Ok(archive_path) => archived = Some(archive_path),
+                        Err(e) => {
+                            budget.finish(size);
+                            return Err(format!("archive remote {}: {}", path, e));
+                        }
+                    }
+                }
+                let result = upload_transfer_task(
                     url,
                     local_path,
-                    remote_path,
+                    remote_path.clone(),
                     cli,
                     format,
                     Some(aggregate),
                     overall_pb,
                     resolve_max_transfer(cli),
                 )
-                .await
-                {
-                    Ok(()) => Ok(path),
-                    Err(err) => Err(format!("upload {}: {}", path, err)),
+                .await;
+                budget.finish(size);
+                match result {
+                    Ok(()) => Ok(Some(path)),
+                    Err(err) => {
+                        if let (Some(archive_path), true) = (archived, archive_moves) {
+                            let mut p = archiver.lock().await;
+                            let _ = p.delete(&remote_path).await;
+                            if let Err(e) = p.rename(&archive_path, &remote_path).await {
+                                return Err(format!(
+                                    "upload {}: {} (previous version left at {}: {})",
+                                    path, err, archive_path, e
+                                ));
+                            }
+                        }
+                        Err(format!("upload {}: {}", path, err))
+                    }
                 }
             }
         },
@@ -17109,11 +18795,116 @@ async fn cmd_sync(
 
     for result in upload_results {
         match result {
-            Ok(_) => uploaded += 1,
+            Ok(Some(path)) => {
+                uploaded += 1;
+                uploaded_paths.push(path);
+            }
+            Ok(None) => {}
             Err(err) => errors.push(err),
         }
     }
 
+    // --hard-links: link the other group members to the leader's remote copy,
+    // falling back to server-side copy, then to a regular upload. A member
+    // whose leader was deferred or failed this run is uploaded on its own.
+    let leaders_ready: std::collections::HashSet<&str> = {
+        let scheduled: std::collections::HashSet<&str> = to_upload.iter().copied().collect();
+        let done: std::collections::HashSet<&str> =
+            uploaded_paths.iter().map(String::as_str).collect();
+        to_hardlink
+            .iter()
+            .map(|(_, leader)| leader.as_str())
+            .filter(|leader| done.contains(leader) || !scheduled.contains(leader))
+            .collect()
+    };
+    for (path, leader) in &to_hardlink {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        let size = local_map.get(*path).map(|(size, _)| *size).unwrap_or(0);
+        let transferred = aggregate.load(Ordering::Relaxed);
+        if !budget.admit(path, TransferKind::Upload, size, transferred) {
+            continue;
+        }
+        let remote_path = format!(
+            "{}/{}",
+            remote.trim_end_matches('/'),
+            name_plan.remote_path(path)
+        );
+        let mut archived = None;
+        if versioning.is_enabled() && remote_map.contains_key(path) {
+            match versioning
+                .archive_remote(provider.as_mut(), remote, name_plan.remote_path(path), true)
+                .await
+            {
+                Ok(archive_path) => archived = Some(archive_path),
+                Err(e) => {
+                    budget.finish(size);
+                    errors.push(format!("archive remote {}: {}", path, e));
+                    continue;
+                }
+            }
+        }
+        if leaders_ready.contains(leader.as_str()) {
+            let leader_path = format!(
+                "{}/{}",
+                remote.trim_end_matches('/'),
+                name_plan.remote_path(leader)
+            );
+            if remote_map.contains_key(path) {
+                let _ = provider.delete(&remote_path).await;
+            }
+            let mut result = Err(ProviderError::NotSupported("hard_link".to_string()));
+            if provider.supports_hardlinks() {
+                result = provider.hard_link(&leader_path, &remote_path).await;
+            }
+            if result.is_err() && provider.supports_server_copy() {
+                result = provider.server_copy(&leader_path, &remote_path).await;
+            }
+            if result.is_ok() {
+                budget.finish(size);
+                uploaded += 1;
+                uploaded_paths.push(path.to_string());
+                if !quiet {
+                    eprintln!("  HARDLINK  {} => {}", path, leader);
+                }
+                continue;
+            }
+        }
+        let local_path = Path::new(local).join(path).to_string_lossy().to_string();
+        let result = upload_transfer_task(
+            url,
+            local_path,
+            remote_path.clone(),
+            cli,
+            format,
+            None,
+            None,
+            resolve_max_transfer(cli),
+        )
+        .await;
+        budget.finish(size);
+        match result {
+            Ok(()) => {
+                uploaded += 1;
+                uploaded_paths.push(path.to_string());
+            }
+            Err(e) => {
+                if let (Some(archive_path), true) = (archived, archive_moves) {
+                    let _ = provider.delete(&remote_path).await;
+                    if let Err(re) = provider.rename(&archive_path, &remote_path).await {
+                        errors.push(format!(
+                            "upload {}: {} (previous version left at {}: {})",
+                            path, e, archive_path, re
+                        ));
+                        continue;
+                    }
+                }
+                errors.push(format!("upload {}: {}", path, e));
+            }
+        }
+    }
+
     let (normal_download_paths, gated_conflict_download_paths) =
         partition_conflict_rename_downloads(to_download.clone(), &to_conflict_upload);
 
@@ -17131,7 +18922,15 @@ async fn cmd_sync(
             .to_string_lossy()
             .to_string();
         let remote_conflict = format!("{}/{}", remote.trim_end_matches('/'), conflict_path);
-        match upload_transfer_task(
+        let size = local_map
+            .get(orig_path.as_str())
+            .map(|(size, _)| *size)
+            .unwrap_or(0);
+        let transferred = aggregate.load(Ordering::Relaxed);
+        if !budget.admit(orig_path, TransferKind::Upload, size, transferred) {
+            continue;
+        }
+        let result = upload_transfer_task(
             url,
             local_path,
             remote_conflict,
@@ -17141,8 +18940,9 @@ async fn cmd_sync(
             None,
             resolve_max_transfer(cli),
         )
-        .await
-        {
+        .await;
+        budget.finish(size);
+        match result {
             Ok(()) => {
                 conflict_uploaded += 1;
                 preserved_conflict_downloads.insert(orig_path.clone());
@@ -17169,24 +18969,40 @@ async fn cmd_sync(
         }
         let relative = path.to_string();
         let local_path = Path::new(local).join(path).to_string_lossy().to_string();
-        let remote_path = format!("{}/{}", remote.trim_end_matches('/'), path);
+        let remote_path = format!(
+            "{}/{}",
+            remote.trim_end_matches('/'),
+            name_plan.remote_path(path)
+        );
         let size = remote_map.get(path).map(|(size, _)| *size).unwrap_or(0);
         download_jobs.push((relative, local_path, remote_path, size));
     }
+    planner.sort(&mut download_jobs, |(path, _, _, size)| PlanItem {
+        path,
+        size: *size,
+        mtime: remote_map
+            .get(path.as_str())
+            .and_then(|(_, mtime)| mtime.and_then(parse_mtime_secs)),
+    });
 
     let download_results = futures_util::stream::iter(download_jobs.into_iter().map(
-        |(path, local_path, remote_path, _size)| {
+        |(path, local_path, remote_path, size)| {
             let cancelled = cancelled.clone();
             let aggregate = aggregate.clone();
             let overall_pb = overall_pb.clone();
+            let budget = &budget;
             async move {
                 if cancelled.load(Ordering::Relaxed) {
                     return Err(format!("download {}: cancelled", path));
                 }
+                let transferred = aggregate.load(Ordering::Relaxed);
+                if !budget.admit(&path, TransferKind::Download, size, transferred) {
+                    return Ok(None);
+                }
                 if let Some(parent) = Path::new(&local_path).parent() {
                     let _ = std::fs::create_dir_all(parent);
                 }
-                match download_transfer_task(
+                let result = download_transfer_task(
                     url,
                     remote_path,
                     local_path,
@@ -17196,9 +19012,10 @@ async fn cmd_sync(
                     overall_pb,
                     resolve_max_transfer(cli),
                 )
-                .await
-                {
-                    Ok(()) => Ok(path),
+                .await;
+                budget.finish(size);
+                match result {
+                    Ok(()) => Ok(Some(path)),
                     Err(err) => Err(format!("download {}: {}", path, err)),
                 }
             }
@@ -17210,7 +19027,11 @@ async fn cmd_sync(
 
     for result in download_results {
         match result {
-            Ok(_) => downloaded += 1,
+            Ok(Some(path)) => {
+                downloaded += 1;
+                downloaded_paths.push(path);
+            }
+            Ok(None) => {}
             Err(err) => errors.push(err),
         }
     }
@@ -17219,22 +19040,98 @@ async fn cmd_sync(
         pb.finish_and_clear();
     }
 
-    // Execute renames (--track-renames)
-    let mut renamed = 0u32;
-    for (old_remote, new_local) in &renames {
-        if cancelled.load(Ordering::Relaxed) {
-            break;
+    // Deadline reached: journal what is left so the next run starts with it.
+    let deferred = budget.deferred();
+    if !deferred.is_empty() {
+        let local_size = |path: &str| local_map.get(path).map(|(size, _)| *size).unwrap_or(0);
+        let remote_size = |path: &str| remote_map.get(path).map(|(size, _)| *size).unwrap_or(0);
+        let mut completed: Vec<(String, TransferKind, u64)> = Vec::new();
+        for path in &uploaded_paths {
+            completed.push((path.clone(), TransferKind::Upload, local_size(path)));
         }
-        let old_path = format!("{}/{}", remote.trim_end_matches('/'), old_remote);
-        let new_path = format!("{}/{}", remote.trim_end_matches('/'), new_local);
-        if !dry_run {
-            match provider.rename(&old_path, &new_path).await {
-                Ok(()) => {
-                    renamed += 1;
-                    if !quiet {
-                        eprintln!("  RENAME {} → {}", old_remote, new_local);
-                    }
-                }
+        for path in &downloaded_paths {
+            completed.push((path.clone(), TransferKind::Download, remote_size(path)));
+        }
+        let compare_direction = match direction {
+            "upload" => CompareDirection::LocalToRemote,
+            "download" => CompareDirection::RemoteToLocal,
+            _ => CompareDirection::Bidirectional,
+        };
+        let journal = deferral_journal(
+            &journal_local,
+            remote,
+            compare_direction,
+            &completed,
+            &deferred,
+        );
+        if let Err(e) = save_sync_journal(&journal) {
+            errors.push(format!("sync journal: {}", e));
+        }
+    } else if resuming {
+        let _ = delete_sync_journal(&journal_local, remote);
+    }
+
+    // --conflict-mode merge: replay both edits on the last-synced content.
+    let mut merged = 0u32;
+    if !to_merge.is_empty() {
+        let store = bisync_merge_store(local);
+        for (path, base_hash) in &to_merge {
+            if cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+            let base = store.as_ref().and_then(|store| store.get(base_hash));
+            match merge_conflicted_file(
+                provider.as_mut(),
+                &versioning,
+                local,
+                remote,
+                path,
+                name_plan.remote_path(path),
+                base.as_deref(),
+            )
+            .await
+            {
+                Ok(None) => {
+                    merged += 1;
+                    if !quiet {
+                        eprintln!("  MERGE  {}", path);
+                    }
+                }
+                Ok(Some(conflict_path)) => {
+                    conflict_uploaded += 1;
+                    if !quiet {
+                        eprintln!("  MERGE-CONFLICT  {} -> {}", path, conflict_path);
+                    }
+                }
+                Err(e) => errors.push(format!("merge {}: {}", path, e)),
+            }
+        }
+    }
+
+    // Execute renames (--track-renames)
+    let mut renamed = 0u32;
+    for (old_remote, new_local) in &renames {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        let old_path = format!(
+            "{}/{}",
+            remote.trim_end_matches('/'),
+            name_plan.remote_path(old_remote)
+        );
+        let new_path = format!(
+            "{}/{}",
+            remote.trim_end_matches('/'),
+            name_plan.remote_path(new_local)
+        );
+        if !dry_run {
+            match provider.rename(&old_path, &new_path).await {
+                Ok(()) => {
+                    renamed += 1;
+                    if !quiet {
+                        eprintln!("  RENAME {} → {}", old_remote, new_local);
+                    }
+                }
                 Err(e) => errors.push(format!("rename {} → {}: {}", old_remote, new_local, e)),
             }
         } else {
@@ -17253,7 +19150,27 @@ async fn cmd_sync(
             ));
             continue;
         }
-        let remote_path = format!("{}/{}", remote.trim_end_matches('/'), path);
+        if versioning.is_enabled() {
+            // The move into .aeroversions/ is the delete.
+            match versioning
+                .archive_remote(
+                    provider.as_mut(),
+                    remote,
+                    name_plan.remote_path(path),
+                    false,
+                )
+                .await
+            {
+                Ok(_) => deleted += 1,
+                Err(e) => errors.push(format!("delete remote {}: {}", path, e)),
+            }
+            continue;
+        }
+        let remote_path = format!(
+            "{}/{}",
+            remote.trim_end_matches('/'),
+            name_plan.remote_path(path)
+        );
         match provider.delete(&remote_path).await {
             Ok(()) => deleted += 1,
             Err(e) => errors.push(format!("delete remote {}: {}", path, e)),
@@ -17288,9 +19205,168 @@ async fn cmd_sync(
         }
     }
 
-    // Save bisync snapshot after successful sync (--direction both)
-    if direction == "both" && errors.is_empty() && !dry_run {
-        save_bisync_snapshot(local, &local_entries, &remote_entries);
+    // --links: symlinks go last so file deletions above cannot remove them.
+    let mut linked = 0u32;
+    for link in &link_plan.create_remote {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        let existing = remote_link_kinds.get(&link.rel_path).copied();
+        match write_remote_link(provider.as_mut(), remote, link, existing).await {
+            Ok(()) => {
+                linked += 1;
+                if !quiet {
+                    eprintln!("  SYMLINK  {} -> {}", link.rel_path, link.target);
+                }
+            }
+            Err(e) => errors.push(format!("symlink remote {}: {}", link.rel_path, e)),
+        }
+    }
+    for link in &link_plan.create_local {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        if validate_relative_path(&link.rel_path).is_none() {
+            errors.push(format!(
+                "symlink local {}: unsafe path (traversal rejected)",
+                link.rel_path
+            ));
+            continue;
+        }
+        match create_local_symlink(&link.target, &Path::new(local).join(&link.rel_path)) {
+            Ok(()) => {
+                linked += 1;
+                if !quiet {
+                    eprintln!("  SYMLINK (local)  {} -> {}", link.rel_path, link.target);
+                }
+            }
+            Err(e) => errors.push(format!("symlink local {}: {}", link.rel_path, e)),
+        }
+    }
+    for path in &link_plan.delete_remote {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        let kind = remote_link_kinds
+            .get(path)
+            .copied()
+            .unwrap_or(RemoteLinkKind::Native);
+        match delete_remote_link(provider.as_mut(), remote, path, kind).await {
+            Ok(()) => deleted += 1,
+            Err(e) => errors.push(format!("delete remote link {}: {}", path, e)),
+        }
+    }
+    for path in &link_plan.delete_local {
+        if validate_relative_path(path).is_none() {
+            errors.push(format!(
+                "delete local link {}: unsafe path (traversal rejected)",
+                path
+            ));
+            continue;
+        }
+        match std::fs::remove_file(Path::new(local).join(path)) {
+            Ok(()) => deleted += 1,
+            Err(e) => errors.push(format!("delete local link {}: {}", path, e)),
+        }
+    }
+
+    // --perms/--owner/--chmod/--xattrs: transferred files take the source's
+    // metadata, then the metadata-only updates planned above are applied.
+    let mut meta_updated = 0u32;
+    if meta_opts.is_enabled() && reconcile_plan.is_none() {
+        let local_meta = |path: &str| read_local_meta(&Path::new(local).join(path), &meta_opts);
+        let remote_side = |path: &str| {
+            remote_meta
+                .get(name_plan.remote_path(path))
+                .cloned()
+                .unwrap_or_default()
+        };
+        let mut updates: Vec<(String, bool, bool, MetaDiff)> = Vec::new();
+        for path in &uploaded_paths {
+            let diff = remote_meta_opts.diff(&local_meta(path), &remote_side(path), false);
+            updates.push((path.clone(), false, true, diff));
+        }
+        for path in &downloaded_paths {
+            let diff = meta_opts.diff(&remote_side(path), &local_meta(path), false);
+            updates.push((path.clone(), false, false, diff));
+        }
+        let to_remote = direction == "upload";
+        updates.extend(
+            to_set_meta
+                .iter()
+                .map(|(path, is_dir, diff)| (path.clone(), *is_dir, to_remote, diff.clone())),
+        );
+        for (path, is_dir, to_remote, diff) in updates {
+            if cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+            if diff.is_empty() {
+                continue;
+            }
+            let result = if to_remote {
+                let rel = if is_dir {
+                    path.as_str()
+                } else {
+                    name_plan.remote_path(&path)
+                };
+                let has_xattr_file = remote_xattr_files.contains(rel);
+                apply_remote_meta(provider.as_mut(), remote, rel, &diff, has_xattr_file).await
+            } else {
+                apply_local_meta(&Path::new(local).join(&path), &diff)
+            };
+            match result {
+                Ok(()) => meta_updated += 1,
+                Err(e) => errors.push(format!("metadata {}: {}", path, e)),
+            }
+        }
+        // A deleted entry takes its xattr file with it.
+        for path in &to_delete_remote {
+            let rel = name_plan.remote_path(path);
+            if remote_xattr_files.contains(rel) {
+                let xattr_path =
+                    format!("{}/{}", remote.trim_end_matches('/'), xattr_file_path(rel));
+                let _ = provider.delete(&xattr_path).await;
+            }
+        }
+    }
+
+    // Save bisync snapshot after successful sync (--direction both). A run
+    // that deferred transfers is not in sync yet, so it keeps the old one.
+    if direction == "both" && errors.is_empty() && deferred.is_empty() && !dry_run {
+        let merge_bases = if conflict_mode == "merge" {
+            let mut changed: std::collections::HashSet<&str> = uploaded_paths
+                .iter()
+                .chain(downloaded_paths.iter())
+                .map(String::as_str)
+                .chain(to_merge.iter().map(|(path, _)| *path))
+                .collect();
+            for (path, size, mtime) in &local_entries {
+                let unchanged = prev_snapshot
+                    .as_ref()
+                    .and_then(|snap| snap.files.get(path))
+                    .is_some_and(|(prev_size, prev_mtime)| {
+                        prev_size == size && prev_mtime == mtime.as_deref().unwrap_or("")
+                    });
+                if !unchanged {
+                    changed.insert(path);
+                }
+            }
+            collect_merge_bases(
+                local,
+                prev_snapshot.as_ref().map(|snap| &snap.merge_bases),
+                &[local_entries.as_slice(), remote_entries.as_slice()],
+                &changed,
+            )
+        } else {
+            HashMap::new()
+        };
+        save_bisync_snapshot(
+            local,
+            &local_entries,
+            &remote_entries,
+            change_feed_next,
+            merge_bases,
+        );
         if !quiet {
             eprintln!(
                 "Bisync snapshot saved to {}/{}",
@@ -17299,20 +19375,53 @@ async fn cmd_sync(
         }
     }
 
+    // --remote-versioning retention only runs after a clean pass, so a failed
+    // run never prunes a version it might be needed to recover from.
+    if versioning.is_enabled() && errors.is_empty() && !dry_run {
+        match versioning.cleanup_remote(provider.as_mut(), remote).await {
+            Ok(stats) if stats.deleted_count > 0 && !quiet => eprintln!(
+                "Pruned {} expired version(s) from {}/{} ({})",
+                stats.deleted_count,
+                remote.trim_end_matches('/'),
+                VERSIONS_DIR_NAME,
+                format_size(stats.freed_bytes)
+            ),
+            Ok(_) => {}
+            Err(e) => {
+                if !quiet {
+                    eprintln!("Warning: version cleanup failed: {}", e);
+                }
+            }
+        }
+    }
+
     let elapsed = start.elapsed();
 
     match format {
         OutputFormat::Text => {
             if !cli.quiet {
                 println!(
-                    "\nSync complete: {} uploaded, {} downloaded, {} deleted, {} renamed, {} conflict-renamed in {:.1}s",
+                    "\nSync complete: {} uploaded, {} downloaded, {} deleted, {} renamed, {} conflict-renamed, {} merged in {:.1}s",
                     uploaded,
                     downloaded,
                     deleted,
                     renamed,
                     conflict_uploaded,
+                    merged,
                     elapsed.as_secs_f64()
                 );
+                if linked > 0 {
+                    println!("{} symlink(s) created", linked);
+                }
+                if meta_updated > 0 {
+                    println!("Metadata applied to {} entr(ies)", meta_updated);
+                }
+                if !deferred.is_empty() {
+                    println!(
+                        "Deadline reached: {} transfer(s) deferred to the next run",
+                        deferred.len()
+                    );
+                }
                 for err in &errors {
                     eprintln!("  Error: {}", err);
                 }
@@ -17320,7 +19429,13 @@ async fn cmd_sync(
         }
         OutputFormat::Json => {
             print_json(&CliSyncResult {
-                status: if errors.is_empty() { "ok" } else { "partial" },
+                status: if !errors.is_empty() {
+                    "partial"
+                } else if !deferred.is_empty() {
+                    "deferred"
+                } else {
+                    "ok"
+                },
                 uploaded,
                 downloaded,
                 deleted,
@@ -17328,6 +19443,7 @@ async fn cmd_sync(
                 errors: errors.clone(),
                 elapsed_secs: elapsed.as_secs_f64(),
                 plan: Vec::new(),
+                deferred: deferred.into_iter().map(|(path, _)| path).collect(),
             });
         }
     }
@@ -17340,6 +19456,101 @@ async fn cmd_sync(
         deleted,
         skipped,
         error_count: errors.len() as u32,
+        errors,
+    }
+}
+
+/// `--pre-hook`, `--post-hook` and `--notify` settings for `sync`.
+fn cli_sync_hooks(cli: &Cli) -> Result<SyncHooks, String> {
+    Ok(SyncHooks {
+        pre: cli.pre_hook.clone(),
+        post: cli.post_hook.clone(),
+        sinks: cli
+            .notify
+            .iter()
+            .map(|spec| NotifySink::parse(spec))
+            .collect::<Result<_, _>>()?,
+        notify_on: cli
+            .notify_on
+            .as_deref()
+            .map(NotifyOn::parse)
+            .transpose()?
+            .unwrap_or_default(),
+        timeout: std::time::Duration::from_secs(cli.hook_timeout),
+    })
+}
+
+/// `--order-by`, `--priority`, `--max-duration` and `--stop-at` settings for `sync`.
+fn cli_sync_planner(cli: &Cli) -> Result<(TransferPlanner, Option<Deadline>), String> {
+    let order = cli
+        .order_by
+        .as_deref()
+        .map(TransferOrder::parse)
+        .transpose()?
+        .unwrap_or_default();
+    let planner = TransferPlanner::new(order, &cli.priority)?;
+    let deadline = Deadline::resolve(cli.max_duration.as_deref(), cli.stop_at.as_deref())?;
+    Ok((planner, deadline))
+}
+
+/// Host of a connection target, the key of the speed test history.
+fn target_host(url: &str, cli: &Cli) -> Option<String> {
+    let Some(profile_name) = cli.profile.as_deref() else {
+        return url::Url::parse(url).ok()?.host_str().map(str::to_string);
+    };
+    let store = open_vault(cli).ok()?;
+    let profiles: Vec<serde_json::Value> =
+        serde_json::from_str(&store.get("config_server_profiles").ok()?).ok()?;
+    let field = |p: &serde_json::Value, key: &str| {
+        p.get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    };
+    profiles
+        .iter()
+        .find(|p| {
+            field(p, "name").eq_ignore_ascii_case(profile_name) || field(p, "id") == profile_name
+        })
+        .map(|p| field(p, "host"))
+        .filter(|host| !host.is_empty())
+}
+
+/// Run post-sync hooks and notifications once a `sync` is over.
+/// `aborted` carries the pre-sync hook failure that stopped it.
+async fn finish_cli_sync_hooks(
+    hooks: &SyncHooks,
+    event: SyncHookEvent,
+    stats: &SyncCycleStats,
+    aborted: Option<String>,
+    elapsed: std::time::Duration,
+    quiet: bool,
+) {
+    if hooks.post.is_empty() && hooks.sinks.is_empty() {
+        return;
+    }
+    let status = match (&aborted, stats.exit_code) {
+        (Some(_), _) => "aborted",
+        (None, 0) => "ok",
+        (None, 4) if stats.error_count > 0 => "partial",
+        _ => "failed",
+    };
+    let mut errors = stats.errors.clone();
+    errors.extend(aborted);
+    let event = event.into_post_sync(SyncRunReport {
+        status: status.to_string(),
+        exit_code: stats.exit_code,
+        uploaded: stats.uploaded,
+        downloaded: stats.downloaded,
+        deleted: stats.deleted,
+        skipped: stats.skipped,
+        errors,
+        elapsed_secs: elapsed.as_secs_f64(),
+    });
+    for warning in hooks.finish(&event).await {
+        if !quiet {
+            eprintln!("Warning: {}", warning);
+        }
     }
 }
 
@@ -18067,6 +20278,13 @@ async fn cmd_ncdu(
 #[cfg(target_os = "linux")]
 mod fuse_mount {
     use super::*;
+    use ftp_client_gui_lib::backup::{BlobRef, NodeKind};
+    use ftp_client_gui_lib::sync_core::{capture_change_token, ChangeIndex, ChangeTargets};
+    use ftp_client_gui_lib::vault_mount::{CommitStats, VaultMount};
+    use ftp_client_gui_lib::vault_remote;
+    use ftp_client_gui_lib::vfs_cache::{
+        self, PendingUpload, ReadAheadBuffer, VfsCache, VfsCacheMode, VfsCacheOptions,
+    };
     use fuser::{
         FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyCreate, ReplyData,
         ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyOpen, ReplyStatfs, ReplyWrite, Request,
@@ -18079,6 +20297,11 @@ mod fuse_mount {
     const BLOCK_SIZE: u32 = 512;
     /// Read chunk size: 4 MB per read call
     const READ_CHUNK: u64 = 4 * 1024 * 1024;
+    /// File handle flags: opened for writing / holds a VFS cache pin
+    const FH_WRITE: u64 = 1;
+    const FH_PINNED: u64 = 2;
+    /// How often the upload task also enforces the VFS cache limits
+    const EVICT_INTERVAL: Duration = Duration::from_secs(60);
 
     #[derive(Clone, Debug)]
     struct CachedEntry {
@@ -18104,14 +20327,23 @@ mod fuse_mount {
         uid: u32,
         gid: u32,
         /// inode → remote path
-        inode_path: Mutex<HashMap<u64, String>>,
+        inode_path: Arc<Mutex<HashMap<u64, String>>>,
         /// remote path → inode
-        path_inode: Mutex<HashMap<String, u64>>,
+        path_inode: Arc<Mutex<HashMap<String, u64>>>,
         /// inode → cached metadata
-        cache: Mutex<HashMap<u64, CachedEntry>>,
+        cache: Arc<Mutex<HashMap<u64, CachedEntry>>>,
         /// Next available inode number
         next_inode: Mutex<u64>,
         quiet: bool,
+        /// On-disk VFS cache (`--vfs-cache-mode writes|full`)
+        vfs: Option<Arc<VfsCache>>,
+        /// Streamed reads: inode → last block fetched with read-ahead
+        read_buffers: Arc<Mutex<HashMap<u64, ReadAheadBuffer>>>,
+        /// inode → end of the last read, to spot sequential access
+        read_pos: Mutex<HashMap<u64, u64>>,
+        read_ahead: u64,
+        /// Provider ids seen in listings, when remote changes are polled
+        change_index: Option<Arc<Mutex<ChangeIndex>>>,
     }
 
     impl AeroFuseFs {
@@ -18121,6 +20353,8 @@ mod fuse_mount {
             cache_ttl_secs: u64,
             read_only: bool,
             quiet: bool,
+            vfs: Option<Arc<VfsCache>>,
+            read_ahead: u64,
         ) -> Self {
             let rt = tokio::runtime::Builder::new_multi_thread()
                 .enable_all()
@@ -18154,11 +20388,29 @@ mod fuse_mount {
                 flush_ok: Mutex::new(std::collections::HashSet::new()),
                 uid: cur_uid,
                 gid: cur_gid,
-                inode_path: Mutex::new(inode_path),
-                path_inode: Mutex::new(path_inode),
-                cache: Mutex::new(cache),
+                inode_path: Arc::new(Mutex::new(inode_path)),
+                path_inode: Arc::new(Mutex::new(path_inode)),
+                cache: Arc::new(Mutex::new(cache)),
                 next_inode: Mutex::new(2),
                 quiet,
+                vfs,
+                read_buffers: Arc::new(Mutex::new(HashMap::new())),
+                read_pos: Mutex::new(HashMap::new()),
+                read_ahead,
+                change_index: None,
+            }
+        }
+
+        /// Follow the provider's change feed: listings feed `index` so that
+        /// removals and renames can be mapped back to inodes, and the
+        /// returned handle drops whatever the feed reports as changed.
+        pub fn track_changes(&mut self, index: Arc<Mutex<ChangeIndex>>) -> MountInvalidator {
+            self.change_index = Some(index);
+            MountInvalidator {
+                inode_path: self.inode_path.clone(),
+                path_inode: self.path_inode.clone(),
+                cache: self.cache.clone(),
+                read_buffers: self.read_buffers.clone(),
             }
         }
 
@@ -18220,6 +20472,15 @@ mod fuse_mount {
                 p.list(&path_owned).await.ok()
             })?;
 
+            if let Some(index) = &self.change_index {
+                let mut index = index.lock().unwrap();
+                for e in &entries {
+                    if let Some(id) = e.metadata.get("id") {
+                        index.record(id, &e.path);
+                    }
+                }
+            }
+
             let mut child_inodes = Vec::with_capacity(entries.len());
             for e in &entries {
                 let child_ino = self.alloc_inode(&e.path);
@@ -18228,13 +20489,10 @@ mod fuse_mount {
                 let attr = if e.is_dir {
                     dir_attr(child_ino, 0, self.uid, self.gid)
                 } else {
-                    file_attr(
-                        child_ino,
-                        e.size,
-                        parse_mtime_to_system(&e.modified),
-                        self.uid,
-                        self.gid,
-                    )
+                    let (size, mtime) = self
+                        .local_attr(&e.path)
+                        .unwrap_or((e.size, parse_mtime_to_system(&e.modified)));
+                    file_attr(child_ino, size, mtime, self.uid, self.gid)
                 };
 
                 self.set_cached(
@@ -18247,6 +20505,18 @@ mod fuse_mount {
                 );
             }
 
+            // Files written through the mount that are not uploaded yet
+            if let Some(vfs) = &self.vfs {
+                for (child, size, mtime) in vfs.dirty_children(path) {
+                    if entries.iter().any(|e| e.path == child) {
+                        continue;
+                    }
+                    let child_ino = self.alloc_inode(&child);
+                    child_inodes.push(child_ino);
+                    self.set_local_attr(child_ino, size, mtime);
+                }
+            }
+
             let cached = CachedEntry {
                 attr: dir_attr(ino, entries.len() as u64, self.uid, self.gid),
                 children: Some(child_inodes),
@@ -18263,89 +20533,306 @@ mod fuse_mount {
             let entry = self.rt.block_on(async {
                 let mut p = provider.lock().await;
                 p.stat(&path_owned).await.ok()
-            })?;
+            });
 
-            let attr = if entry.is_dir {
-                dir_attr(ino, 0, self.uid, self.gid)
-            } else {
-                file_attr(
+            // A file not uploaded yet exists only in the VFS cache
+            let attr = match (entry, self.local_attr(path)) {
+                (Some(entry), _) if entry.is_dir => dir_attr(ino, 0, self.uid, self.gid),
+                (_, Some((size, mtime))) => file_attr(ino, size, mtime, self.uid, self.gid),
+                (Some(entry), None) => file_attr(
                     ino,
                     entry.size,
                     parse_mtime_to_system(&entry.modified),
                     self.uid,
                     self.gid,
-                )
+                ),
+                (None, None) => return None,
             };
 
             let cached = CachedEntry {
                 attr,
-                children: if entry.is_dir { None } else { Some(Vec::new()) },
+                children: if attr.kind == FileType::Directory {
+                    None
+                } else {
+                    Some(Vec::new())
+                },
                 fetched_at: Instant::now(),
             };
             self.set_cached(ino, cached.clone());
             Some(cached)
         }
-    }
 
-    fn dir_attr(ino: u64, _nlink: u64, uid: u32, gid: u32) -> FileAttr {
-        let now = SystemTime::now();
-        FileAttr {
-            ino,
-            size: 0,
-            blocks: 0,
-            atime: now,
-            mtime: now,
-            ctime: now,
-            crtime: now,
-            kind: FileType::Directory,
-            perm: 0o755,
-            nlink: 2,
-            uid,
-            gid,
-            rdev: 0,
-            blksize: BLOCK_SIZE,
-            flags: 0,
+        /// Size and mtime of a file with local changes, which override the
+        /// remote listing until the upload lands.
+        fn local_attr(&self, path: &str) -> Option<(u64, SystemTime)> {
+            self.vfs.as_ref()?.dirty_attr(path)
         }
-    }
 
-    fn file_attr(ino: u64, size: u64, mtime: SystemTime, uid: u32, gid: u32) -> FileAttr {
-        FileAttr {
-            ino,
-            size,
-            blocks: size.div_ceil(BLOCK_SIZE as u64),
-            atime: mtime,
-            mtime,
-            ctime: mtime,
-            crtime: mtime,
-            kind: FileType::RegularFile,
-            perm: 0o644,
-            nlink: 1,
-            uid,
-            gid,
-            rdev: 0,
-            blksize: BLOCK_SIZE,
-            flags: 0,
+        /// Record a local change in the attribute cache.
+        fn set_local_attr(&self, ino: u64, size: u64, mtime: SystemTime) {
+            self.set_cached(
+                ino,
+                CachedEntry {
+                    attr: file_attr(ino, size, mtime, self.uid, self.gid),
+                    children: Some(Vec::new()),
+                    fetched_at: Instant::now(),
+                },
+            );
         }
-    }
 
-    fn parse_mtime_to_system(mtime: &Option<String>) -> SystemTime {
-        mtime
-            .as_deref()
-            .and_then(|s| {
-                chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
-                    .ok()
-                    .or_else(|| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok())
-            })
-            .map(|dt| {
-                let ts = dt.and_utc().timestamp();
-                UNIX_EPOCH + Duration::from_secs(ts.max(0) as u64)
-            })
-            .unwrap_or(SystemTime::now())
-    }
+        /// Remote size and mtime of `ino` as last seen. The TTL is not
+        /// checked: the VFS cache only needs them to notice a changed file.
+        fn fingerprint(&self, ino: u64, path: &str) -> Option<(u64, i64)> {
+            let cached = self.cache.lock().unwrap().get(&ino).cloned();
+            let entry = cached.or_else(|| self.fetch_stat(ino, path))?;
+            let mtime = entry
+                .attr
+                .mtime
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            Some((entry.attr.size, mtime))
+        }
 
-    impl Filesystem for AeroFuseFs {
-        fn getattr(&mut self, _req: &Request, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
-            let ttl = self.cache_ttl;
+        /// Start tracking `path` in the VFS cache at its remote fingerprint.
+        fn prepare_cached(&self, vfs: &VfsCache, ino: u64, path: &str) -> bool {
+            match self.fingerprint(ino, path) {
+                Some((size, mtime)) => vfs.prepare(path, size, mtime).is_ok(),
+                None => vfs.contains(path),
+            }
+        }
+
+        /// Fetch the missing parts of `offset..offset + len` into the VFS
+        /// cache. Providers without range reads get the whole file
+        /// downloaded once, if it fits within the cache size limit; any
+        /// other failed range read fails the fill.
+        fn fill_cache(&self, vfs: &VfsCache, path: &str, offset: u64, len: u64) -> bool {
+            let runs = vfs.missing_ranges(path, offset, len);
+            if runs.is_empty() {
+                return true;
+            }
+            let chunk = vfs.options().chunk_size;
+            let step = chunk * (READ_CHUNK / chunk).max(1);
+            let provider = self.provider.clone();
+            let ranged = self.rt.block_on(async {
+                let mut p = provider.lock().await;
+                for (start, run_len) in runs {
+                    let end = start + run_len;
+                    let mut pos = start;
+                    while pos < end {
+                        let n = (end - pos).min(step);
+                        // The error says whether range reads are unsupported.
+                        let data = match p.read_range(path, pos, n).await {
+                            Ok(data) => data,
+                            Err(ProviderError::NotSupported(_)) => return Err(true),
+                            Err(_) => return Err(false),
+                        };
+                        if data.len() as u64 != n {
+                            return Err(false);
+                        }
+                        vfs.store(path, pos, &data).map_err(|_| false)?;
+                        pos += n;
+                    }
+                }
+                Ok(())
+            });
+            let Err(unsupported) = ranged else {
+                return true;
+            };
+            if !unsupported || !vfs.fits_whole(path) {
+                return false;
+            }
+
+            let part = vfs.data_path(path).with_extension("part");
+            let downloaded = self.rt.block_on(async {
+                let mut p = provider.lock().await;
+                p.download(path, &part.to_string_lossy(), None).await
+            });
+            if downloaded.is_err() {
+                let _ = std::fs::remove_file(&part);
+                return false;
+            }
+            vfs.adopt(path, &part).is_ok()
+        }
+
+        /// Make `path` fully available in the VFS cache before a local change.
+        fn ensure_local(&self, vfs: &VfsCache, ino: u64, path: &str) -> bool {
+            if !vfs.contains(path) && !self.prepare_cached(vfs, ino, path) {
+                return false;
+            }
+            vfs.is_complete(path) || self.fill_cache(vfs, path, 0, u64::MAX)
+        }
+
+        /// Serve a read from the VFS cache: files with local changes always,
+        /// everything in full mode, fetching missing chunks (plus read-ahead
+        /// on sequential access) first. `None` falls back to streaming.
+        fn read_cached(
+            &self,
+            vfs: &VfsCache,
+            ino: u64,
+            path: &str,
+            offset: u64,
+            len: u64,
+            sequential: bool,
+        ) -> Option<Vec<u8>> {
+            if vfs.options().mode.caches_reads() {
+                if !self.prepare_cached(vfs, ino, path) {
+                    return None;
+                }
+                let span = if sequential {
+                    len.saturating_add(vfs.options().read_ahead)
+                } else {
+                    len
+                };
+                if !self.fill_cache(vfs, path, offset, span) {
+                    return None;
+                }
+            } else if !vfs.contains(path) || !self.prepare_cached(vfs, ino, path) {
+                return None;
+            }
+            vfs.read(path, offset, len).ok().flatten()
+        }
+    }
+
+    /// Something the kernel must forget after a remote change.
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    enum KernelInval {
+        /// Attributes and cached pages of an inode (a directory's listing)
+        Inode(u64),
+        /// The dentry `name` under a parent inode
+        Entry(u64, String),
+    }
+
+    /// Handle on the inode tables of a mounted [`AeroFuseFs`], used by the
+    /// change poller once the filesystem itself belongs to the session.
+    pub struct MountInvalidator {
+        inode_path: Arc<Mutex<HashMap<u64, String>>>,
+        path_inode: Arc<Mutex<HashMap<String, u64>>>,
+        cache: Arc<Mutex<HashMap<u64, CachedEntry>>>,
+        read_buffers: Arc<Mutex<HashMap<u64, ReadAheadBuffer>>>,
+    }
+
+    impl MountInvalidator {
+        /// Drop our cached metadata for everything `targets` touches and
+        /// return what the kernel has to drop as well. Paths the kernel was
+        /// never shown have no inode and are skipped.
+        fn forget(&self, targets: &ChangeTargets) -> Vec<KernelInval> {
+            let mut invals = Vec::new();
+            {
+                let path_inode = self.path_inode.lock().unwrap();
+                let mut touch = |path: &str| {
+                    if let Some(&ino) = path_inode.get(path) {
+                        invals.push(KernelInval::Inode(ino));
+                    }
+                    let Some((parent, name)) = path.rsplit_once('/') else {
+                        return;
+                    };
+                    let parent = if parent.is_empty() { "/" } else { parent };
+                    if let Some(&parent_ino) = path_inode.get(parent) {
+                        if !name.is_empty() {
+                            invals.push(KernelInval::Entry(parent_ino, name.to_string()));
+                        }
+                        invals.push(KernelInval::Inode(parent_ino));
+                    }
+                };
+
+                if targets.unplaced {
+                    // Nothing can be trusted: forget the whole tree
+                    for path in path_inode.keys() {
+                        touch(path);
+                    }
+                } else {
+                    for path in &targets.paths {
+                        touch(path);
+                    }
+                    for dir in &targets.subtrees {
+                        let prefix = format!("{}/", dir.trim_end_matches('/'));
+                        for (path, &ino) in path_inode.iter() {
+                            if path.starts_with(&prefix) {
+                                invals.push(KernelInval::Inode(ino));
+                            }
+                        }
+                    }
+                }
+            }
+            invals.sort();
+            invals.dedup();
+
+            let mut cache = self.cache.lock().unwrap();
+            let mut read_buffers = self.read_buffers.lock().unwrap();
+            for inval in &invals {
+                if let KernelInval::Inode(ino) = inval {
+                    cache.remove(ino);
+                    read_buffers.remove(ino);
+                }
+            }
+            invals
+        }
+
+        fn known_inodes(&self) -> usize {
+            self.inode_path.lock().unwrap().len()
+        }
+    }
+
+    fn dir_attr(ino: u64, _nlink: u64, uid: u32, gid: u32) -> FileAttr {
+        let now = SystemTime::now();
+        FileAttr {
+            ino,
+            size: 0,
+            blocks: 0,
+            atime: now,
+            mtime: now,
+            ctime: now,
+            crtime: now,
+            kind: FileType::Directory,
+            perm: 0o755,
+            nlink: 2,
+            uid,
+            gid,
+            rdev: 0,
+            blksize: BLOCK_SIZE,
+            flags: 0,
+        }
+    }
+
+    fn file_attr(ino: u64, size: u64, mtime: SystemTime, uid: u32, gid: u32) -> FileAttr {
+        FileAttr {
+            ino,
+            size,
+            blocks: size.div_ceil(BLOCK_SIZE as u64),
+            atime: mtime,
+            mtime,
+            ctime: mtime,
+            crtime: mtime,
+            kind: FileType::RegularFile,
+            perm: 0o644,
+            nlink: 1,
+            uid,
+            gid,
+            rdev: 0,
+            blksize: BLOCK_SIZE,
+            flags: 0,
+        }
+    }
+
+    fn parse_mtime_to_system(mtime: &Option<String>) -> SystemTime {
+        mtime
+            .as_deref()
+            .and_then(|s| {
+                chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
+                    .ok()
+                    .or_else(|| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok())
+            })
+            .map(|dt| {
+                let ts = dt.and_utc().timestamp();
+                UNIX_EPOCH + Duration::from_secs(ts.max(0) as u64)
+            })
+            .unwrap_or(SystemTime::now())
+    }
+
+    impl Filesystem for AeroFuseFs {
+        fn getattr(&mut self, _req: &Request, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+            let ttl = self.cache_ttl;
 
             // Root inode: always return directory attr without provider call
             if ino == ROOT_INODE {
@@ -18474,17 +20961,28 @@ mod fuse_mount {
         }
 
         fn open(&mut self, _req: &Request, ino: u64, flags: i32, reply: ReplyOpen) {
-            if self.get_path(ino).is_none() {
+            let Some(path) = self.get_path(ino) else {
                 reply.error(libc::ENOENT);
                 return;
-            }
+            };
             // Check write intent on read-only mount
             let write_flags = libc::O_WRONLY | libc::O_RDWR | libc::O_APPEND | libc::O_TRUNC;
-            if self.read_only && (flags & write_flags) != 0 {
+            let write = (flags & write_flags) != 0;
+            if self.read_only && write {
                 reply.error(libc::EROFS);
                 return;
             }
-            reply.opened(0, 0);
+            let mut fh = if write { FH_WRITE } else { 0 };
+            // Pin the cache entry while the file is open, so it is not evicted
+            if let Some(vfs) = &self.vfs {
+                if (write || vfs.options().mode.caches_reads() || vfs.contains(&path))
+                    && self.prepare_cached(vfs, ino, &path)
+                {
+                    vfs.open_file(&path, write);
+                    fh |= FH_PINNED;
+                }
+            }
+            reply.opened(fh, 0);
         }
 
         fn read(
@@ -18506,16 +21004,72 @@ mod fuse_mount {
             let provider = self.provider.clone();
             let len = size as u64;
             let off = offset as u64;
+            let sequential = {
+                let mut pos = self.read_pos.lock().unwrap();
+                let sequential = off == 0 || pos.get(&ino) == Some(&off);
+                pos.insert(ino, off + len);
+                sequential
+            };
+
+            if let Some(vfs) = &self.vfs {
+                if let Some(data) = self.read_cached(vfs, ino, &path, off, len, sequential) {
+                    reply.data(&data);
+                    return;
+                }
+            }
+
+            // Streamed read: serve from the read-ahead buffer when possible
+            let buffered = self
+                .read_buffers
+                .lock()
+                .unwrap()
+                .get(&ino)
+                .and_then(|b| b.get(off, len).map(|d| d.to_vec()));
+            if let Some(data) = buffered {
+                reply.data(&data);
+                return;
+            }
 
+            let want = len.min(READ_CHUNK);
+            let fetch = if sequential {
+                len.saturating_add(self.read_ahead)
+                    .min(READ_CHUNK)
+                    .max(want)
+            } else {
+                want
+            };
             let result = self.rt.block_on(async {
                 let mut p = provider.lock().await;
-                p.read_range(&path, off, len.min(READ_CHUNK)).await
+                p.read_range(&path, off, fetch).await
             });
 
             match result {
-                Ok(data) => reply.data(&data),
+                Ok(data) => {
+                    reply.data(&data[..data.len().min(want as usize)]);
+                    self.read_buffers
+                        .lock()
+                        .unwrap()
+                        .entry(ino)
+                        .or_default()
+                        .fill(off, data, fetch);
+                }
                 Err(_) => {
-                    // Fallback: download full file (for providers without range support)
+                    // Fallback: download full file (for providers without range support).
+                    // With a VFS cache the download goes to disk, so any size works.
+                    if let Some(vfs) = &self.vfs {
+                        let data = if self.prepare_cached(vfs, ino, &path)
+                            && self.fill_cache(vfs, &path, 0, u64::MAX)
+                        {
+                            vfs.read(&path, off, len).ok().flatten()
+                        } else {
+                            None
+                        };
+                        match data {
+                            Some(data) => reply.data(&data),
+                            None => reply.error(libc::EIO),
+                        }
+                        return;
+                    }
                     // Safety cap: refuse to download files >64MB in fallback to prevent OOM
                     let file_size = self.get_cached(ino).map(|c| c.attr.size).unwrap_or(0);
                     if file_size > 64 * 1024 * 1024 {
@@ -18562,6 +21116,21 @@ mod fuse_mount {
             let child_path = Self::child_path(&parent_path, &child_name);
             let child_ino = self.alloc_inode(&child_path);
 
+            if let Some(vfs) = &self.vfs {
+                if let Err(e) = vfs.create(&child_path, true) {
+                    eprintln!("create error: {}", e);
+                    reply.error(libc::EIO);
+                    return;
+                }
+                vfs.open_file(&child_path, true);
+                let now = SystemTime::now();
+                self.set_local_attr(child_ino, 0, now);
+                self.invalidate_dir(parent);
+                let attr = file_attr(child_ino, 0, now, self.uid, self.gid);
+                reply.created(&self.cache_ttl, &attr, 0, FH_WRITE | FH_PINNED, 0);
+                return;
+            }
+
             // Create secure tempfile for write buffering
             let tmp = tempfile::Builder::new()
                 .prefix("aeroftp_fuse_")
@@ -18585,7 +21154,7 @@ mod fuse_mount {
             );
             self.invalidate_dir(parent);
 
-            reply.created(&ttl, &attr, 0, 0, 0);
+            reply.created(&ttl, &attr, 0, FH_WRITE, 0);
         }
 
         fn write(
@@ -18605,6 +21174,27 @@ mod fuse_mount {
                 return;
             }
 
+            if let Some(vfs) = &self.vfs {
+                let Some(path) = self.get_path(ino) else {
+                    reply.error(libc::ENOENT);
+                    return;
+                };
+                if !self.ensure_local(vfs, ino, &path) {
+                    reply.error(libc::EIO);
+                    return;
+                }
+                if let Err(e) = vfs.write(&path, offset as u64, data) {
+                    eprintln!("write error: {}", e);
+                    reply.error(libc::EIO);
+                    return;
+                }
+                if let Some((size, mtime)) = vfs.dirty_attr(&path) {
+                    self.set_local_attr(ino, size, mtime);
+                }
+                reply.written(data.len() as u32);
+                return;
+            }
+
             let buffers = self.write_buffers.lock().unwrap();
             let Some(tmp_path) = buffers.get(&ino).cloned() else {
                 // No write buffer - this file wasn't opened for writing via create
@@ -18657,6 +21247,17 @@ mod fuse_mount {
             _lock_owner: u64,
             reply: ReplyEmpty,
         ) {
+            // With a VFS cache the upload happens in the background; flush
+            // only makes the data durable on local disk.
+            if let Some(vfs) = &self.vfs {
+                let synced = self.get_path(ino).map_or(Ok(()), |path| vfs.sync(&path));
+                match synced {
+                    Ok(()) => reply.ok(),
+                    Err(_) => reply.error(libc::EIO),
+                }
+                return;
+            }
+
             let buffers = self.write_buffers.lock().unwrap();
             let Some(tmp_path) = buffers.get(&ino).cloned() else {
                 reply.ok();
@@ -18699,12 +21300,28 @@ mod fuse_mount {
             &mut self,
             _req: &Request,
             ino: u64,
-            _fh: u64,
+            fh: u64,
             _flags: i32,
             _lock_owner: Option<u64>,
             _flush: bool,
             reply: ReplyEmpty,
         ) {
+            self.read_buffers.lock().unwrap().remove(&ino);
+            self.read_pos.lock().unwrap().remove(&ino);
+            if let Some(vfs) = &self.vfs {
+                if fh & FH_PINNED != 0 {
+                    if let Some(path) = self.get_path(ino) {
+                        if let Err(e) = vfs.close_file(&path, fh & FH_WRITE != 0) {
+                            eprintln!(
+                                "aeroftp-fuse: cannot flush {} to the VFS cache: {}",
+                                path, e
+                            );
+                        }
+                    }
+                }
+                reply.ok();
+                return;
+            }
             if let Some(tmp_path) = self.write_buffers.lock().unwrap().remove(&ino) {
                 if self.flush_ok.lock().unwrap().remove(&ino) {
                     // Flush succeeded - safe to delete tempfile
@@ -18779,6 +21396,11 @@ mod fuse_mount {
             let child_name = name.to_string_lossy();
             let child_path = Self::child_path(&parent_path, &child_name);
 
+            // A file that was never uploaded is not on the remote yet
+            let local_only = self
+                .vfs
+                .as_ref()
+                .is_some_and(|v| v.is_local_only(&child_path));
             let provider = self.provider.clone();
             let p = child_path.clone();
             let result = self.rt.block_on(async {
@@ -18786,17 +21408,19 @@ mod fuse_mount {
                 prov.delete(&p).await
             });
 
-            match result {
-                Ok(()) => {
-                    self.invalidate_dir(parent);
-                    // Remove from all maps to prevent inode leak
-                    if let Some(ino) = self.path_inode.lock().unwrap().remove(&child_path) {
-                        self.inode_path.lock().unwrap().remove(&ino);
-                        self.cache.lock().unwrap().remove(&ino);
-                    }
-                    reply.ok();
+            if result.is_ok() || local_only {
+                if let Some(vfs) = &self.vfs {
+                    vfs.remove(&child_path);
                 }
-                Err(_) => reply.error(libc::EIO),
+                self.invalidate_dir(parent);
+                // Remove from all maps to prevent inode leak
+                if let Some(ino) = self.path_inode.lock().unwrap().remove(&child_path) {
+                    self.inode_path.lock().unwrap().remove(&ino);
+                    self.cache.lock().unwrap().remove(&ino);
+                }
+                reply.ok();
+            } else {
+                reply.error(libc::EIO);
             }
         }
 
@@ -18821,6 +21445,9 @@ mod fuse_mount {
 
             match result {
                 Ok(()) => {
+                    if let Some(vfs) = &self.vfs {
+                        vfs.remove(&child_path);
+                    }
                     self.invalidate_dir(parent);
                     if let Some(ino) = self.path_inode.lock().unwrap().remove(&child_path) {
                         self.inode_path.lock().unwrap().remove(&ino);
@@ -18857,6 +21484,10 @@ mod fuse_mount {
             let old_path = Self::child_path(&parent_path, &name.to_string_lossy());
             let new_path = Self::child_path(&newparent_path, &newname.to_string_lossy());
 
+            let local_only = self
+                .vfs
+                .as_ref()
+                .is_some_and(|v| v.is_local_only(&old_path));
             let provider = self.provider.clone();
             let from = old_path.clone();
             let to = new_path.clone();
@@ -18865,8 +21496,20 @@ mod fuse_mount {
                 prov.rename(&from, &to).await
             });
 
+            // A file never uploaded is renamed in the VFS cache only and
+            // uploaded under its new name.
+            let result = if local_only {
+                result.or(Ok(()))
+            } else {
+                result
+            };
             match result {
                 Ok(()) => {
+                    if let Some(vfs) = &self.vfs {
+                        if let Err(e) = vfs.rename(&old_path, &new_path) {
+                            eprintln!("aeroftp-fuse: VFS cache rename failed: {}", e);
+                        }
+                    }
                     self.invalidate_dir(parent);
                     if newparent != parent {
                         self.invalidate_dir(newparent);
@@ -18918,6 +21561,29 @@ mod fuse_mount {
             reply: ReplyAttr,
         ) {
             // Handle truncation for write support
+            if let (Some(new_size), Some(vfs)) = (size, &self.vfs) {
+                let Some(path) = self.get_path(ino) else {
+                    reply.error(libc::ENOENT);
+                    return;
+                };
+                // Truncating to zero needs nothing from the remote
+                let truncated = if new_size == 0 {
+                    vfs.create(&path, false).is_ok()
+                } else {
+                    self.ensure_local(vfs, ino, &path) && vfs.truncate(&path, new_size).is_ok()
+                };
+                if !truncated {
+                    reply.error(libc::EIO);
+                    return;
+                }
+                let mtime = vfs
+                    .dirty_attr(&path)
+                    .map_or_else(SystemTime::now, |(_, m)| m);
+                self.set_local_attr(ino, new_size, mtime);
+                let attr = file_attr(ino, new_size, mtime, self.uid, self.gid);
+                reply.attr(&self.cache_ttl, &attr);
+                return;
+            }
             if let Some(new_size) = size {
                 if let Some(tmp_path) = self.write_buffers.lock().unwrap().get(&ino) {
                     let _ = std::fs::OpenOptions::new()
@@ -18995,18 +21661,8 @@ mod fuse_mount {
         Ok(())
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub async fn cmd_mount(
-        url: &str,
-        mountpoint: &str,
-        path: &str,
-        cache_ttl: u64,
-        allow_other: bool,
-        read_only: bool,
-        cli: &Cli,
-        format: OutputFormat,
-    ) -> i32 {
-        // Validate mount point exists and is an empty directory
+    /// Validate that the mount point exists and is an empty directory.
+    fn check_mountpoint(mountpoint: &str, format: OutputFormat) -> Result<(), i32> {
         let mp = std::path::Path::new(mountpoint);
         if !mp.exists() {
             print_error(
@@ -19014,7 +21670,7 @@ mod fuse_mount {
                 &format!("Mount point does not exist: {}", mountpoint),
                 5,
             );
-            return 5;
+            return Err(5);
         }
         if !mp.is_dir() {
             print_error(
@@ -19022,7 +21678,7 @@ mod fuse_mount {
                 &format!("Mount point is not a directory: {}", mountpoint),
                 5,
             );
-            return 5;
+            return Err(5);
         }
         // Check if empty (allow "." and "..")
         if let Ok(mut rd) = std::fs::read_dir(mp) {
@@ -19032,32 +21688,279 @@ mod fuse_mount {
                     &format!("Mount point is not empty: {}", mountpoint),
                     5,
                 );
-                return 5;
+                return Err(5);
             }
         }
+        Ok(())
+    }
 
-        let (provider, initial_path) = match create_and_connect(url, cli, format).await {
-            Ok(v) => v,
-            Err(code) => return code,
-        };
-
-        let base_path = normalize_remote_path(&resolve_cli_remote_path(&initial_path, path));
-
-        let quiet = cli.quiet || matches!(format, OutputFormat::Json);
-        if !quiet {
-            eprintln!(
-                "Mounting {} on {} ({}, cache TTL {}s)",
-                base_path,
-                mountpoint,
-                if read_only { "read-only" } else { "read-write" },
-                cache_ttl
-            );
-            eprintln!("Press Ctrl+C to unmount");
-        }
+    /// Build the VFS cache options from the `mount --vfs-*` flags.
+    pub fn vfs_cache_options(
+        mode: &str,
+        max_size: Option<&str>,
+        max_age: &str,
+        read_ahead: &str,
+        chunk_size: &str,
+        write_back: &str,
+    ) -> Result<VfsCacheOptions, String> {
+        let chunk_size = parse_size_filter(chunk_size)?;
+        if chunk_size == 0 {
+            return Err("--vfs-read-chunk-size must be greater than zero".to_string());
+        }
+        Ok(VfsCacheOptions {
+            mode: VfsCacheMode::parse(mode)?,
+            max_size: max_size.map(parse_size_filter).transpose()?,
+            max_age: Duration::from_secs(parse_age_filter(max_age)?),
+            chunk_size,
+            read_ahead: parse_size_filter(read_ahead)?,
+            write_back: Duration::from_secs(parse_age_filter(write_back)?),
+        })
+    }
 
+    /// Upload one dirty VFS cache entry. Returns whether it landed.
+    async fn upload_pending(
+        cache: &VfsCache,
+        provider: &AsyncMutex<Box<dyn StorageProvider>>,
+        upload: &PendingUpload,
+        quiet: bool,
+    ) -> bool {
+        let local = upload.local.to_string_lossy().to_string();
+        let result = {
+            let mut p = provider.lock().await;
+            p.upload(&local, &upload.path, None).await
+        };
+        match result {
+            Ok(()) => {
+                if let Err(e) = cache.upload_finished(&upload.path, upload.generation) {
+                    eprintln!(
+                        "aeroftp-fuse: cannot update VFS cache for {}: {}",
+                        upload.path, e
+                    );
+                }
+                true
+            }
+            Err(e) => {
+                if let Some(delay) = cache.upload_failed(&upload.path, upload.generation) {
+                    if !quiet {
+                        eprintln!(
+                            "aeroftp-fuse: upload of {} failed ({}), retrying in {}s",
+                            upload.path,
+                            e,
+                            delay.as_secs()
+                        );
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    /// Background write-back: upload dirty files once their delay has
+    /// passed and keep the cache within its size and age limits.
+    async fn run_upload_queue(
+        cache: Arc<VfsCache>,
+        provider: Arc<AsyncMutex<Box<dyn StorageProvider>>>,
+        mut stop: tokio::sync::watch::Receiver<bool>,
+        quiet: bool,
+    ) {
+        let mut tick = tokio::time::interval(Duration::from_secs(1));
+        let mut last_evict = Instant::now();
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {}
+                _ = stop.changed() => break,
+            }
+            for upload in cache.due_uploads() {
+                upload_pending(&cache, &provider, &upload, quiet).await;
+            }
+            if last_evict.elapsed() >= EVICT_INTERVAL {
+                let _ = cache.evict();
+                last_evict = Instant::now();
+            }
+        }
+    }
+
+    /// Follow the provider's change feed and make the kernel forget what
+    /// changed remotely, so edits made elsewhere show up on the mount within
+    /// one poll instead of after the attribute TTL. Files with local changes
+    /// keep their cached data: the VFS cache never drops dirty items.
+    #[allow(clippy::too_many_arguments)]
+    async fn run_change_poller(
+        provider: Arc<AsyncMutex<Box<dyn StorageProvider>>>,
+        invalidator: MountInvalidator,
+        index: Arc<Mutex<ChangeIndex>>,
+        notifier: fuser::Notifier,
+        mut token: String,
+        interval: Duration,
+        mut stop: tokio::sync::watch::Receiver<bool>,
+        quiet: bool,
+    ) {
+        let mut tick = tokio::time::interval(interval);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        tick.tick().await;
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {}
+                _ = stop.changed() => break,
+            }
+            let polled = {
+                let mut p = provider.lock().await;
+                p.list_changes(&token).await
+            };
+            let targets = match polled {
+                Ok((changes, next)) => {
+                    if !next.is_empty() {
+                        token = next;
+                    }
+                    let mut index = index.lock().unwrap();
+                    index.resolve(&changes)
+                }
+                Err(ProviderError::NotFound(_)) => {
+                    // Expired token: start over from now and forget everything
+                    let fresh = {
+                        let mut p = provider.lock().await;
+                        capture_change_token(p.as_mut()).await
+                    };
+                    let Some(fresh) = fresh else {
+                        continue;
+                    };
+                    token = fresh;
+                    if !quiet {
+                        eprintln!(
+                            "aeroftp-fuse: change feed expired, refreshing {} cached entries",
+                            invalidator.known_inodes()
+                        );
+                    }
+                    ChangeTargets {
+                        unplaced: true,
+                        ..ChangeTargets::default()
+                    }
+                }
+                // Transient failure: the token is still good, retry next tick
+                Err(_) => continue,
+            };
+            if targets.is_empty() {
+                continue;
+            }
+
+            let invals = invalidator.forget(&targets);
+            let notifier = notifier.clone();
+            // The kernel may need the FUSE thread to answer requests before
+            // an invalidation returns, so keep it off the async workers.
+            let _ = tokio::task::spawn_blocking(move || {
+                for inval in invals {
+                    // ENOENT (already dropped by the kernel) is ignored by fuser
+                    let _ = match &inval {
+                        KernelInval::Inode(ino) => notifier.inval_inode(*ino, 0, 0),
+                        KernelInval::Entry(parent, name) => {
+                            notifier.inval_entry(*parent, OsStr::new(name))
+                        }
+                    };
+                }
+            })
+            .await;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn cmd_mount(
+        url: &str,
+        mountpoint: &str,
+        path: &str,
+        cache_ttl: u64,
+        allow_other: bool,
+        read_only: bool,
+        vfs_opts: VfsCacheOptions,
+        cache_dir: Option<&str>,
+        poll_interval: Duration,
+        cli: &Cli,
+        format: OutputFormat,
+    ) -> i32 {
+        if let Err(code) = check_mountpoint(mountpoint, format) {
+            return code;
+        }
+
+        let (provider, initial_path) = match create_and_connect(url, cli, format).await {
+            Ok(v) => v,
+            Err(code) => return code,
+        };
+
+        let base_path = normalize_remote_path(&resolve_cli_remote_path(&initial_path, path));
         let provider_arc = Arc::new(AsyncMutex::new(provider));
 
-        let fs = AeroFuseFs::new(provider_arc.clone(), base_path, cache_ttl, read_only, quiet);
+        let vfs = if vfs_opts.mode.caches_writes() {
+            let identity = format!(
+                "{}|{}|{}",
+                cli.profile.as_deref().unwrap_or(""),
+                url,
+                base_path
+            );
+            let opened = cache_dir
+                .map(PathBuf::from)
+                .or_else(|| vfs_cache::default_cache_root(&identity))
+                .ok_or_else(|| "cannot determine a cache directory, pass --cache-dir".to_string())
+                .and_then(|root| {
+                    VfsCache::open(&root, vfs_opts.clone())
+                        .map_err(|e| format!("{}: {}", root.display(), e))
+                });
+            match opened {
+                Ok(cache) => Some(Arc::new(cache)),
+                Err(e) => {
+                    print_error(format, &format!("Cannot open VFS cache: {}", e), 5);
+                    let mut p = provider_arc.lock().await;
+                    let _ = p.disconnect().await;
+                    return 5;
+                }
+            }
+        } else {
+            None
+        };
+
+        let quiet = cli.quiet || matches!(format, OutputFormat::Json);
+        if !quiet {
+            eprintln!(
+                "Mounting {} on {} ({}, cache TTL {}s, VFS cache {})",
+                base_path,
+                mountpoint,
+                if read_only { "read-only" } else { "read-write" },
+                cache_ttl,
+                vfs_opts.mode.as_str()
+            );
+            if let Some(cache) = &vfs {
+                let pending = cache.pending_uploads().len();
+                if pending > 0 {
+                    eprintln!(
+                        "{} file(s) left by an earlier session queued for upload",
+                        pending
+                    );
+                }
+            }
+            eprintln!("Press Ctrl+C to unmount");
+        }
+
+        // Capture the change token before the first listing so that nothing
+        // changed in between is missed
+        let change_token = if poll_interval.is_zero() {
+            None
+        } else {
+            let mut p = provider_arc.lock().await;
+            capture_change_token(p.as_mut()).await
+        };
+
+        let mut fs = AeroFuseFs::new(
+            provider_arc.clone(),
+            base_path.clone(),
+            cache_ttl,
+            read_only,
+            quiet,
+            vfs.clone(),
+            vfs_opts.read_ahead,
+        );
+        let change_feed = change_token.map(|token| {
+            let index = Arc::new(Mutex::new(ChangeIndex::new(&base_path)));
+            let invalidator = fs.track_changes(index.clone());
+            (token, index, invalidator)
+        });
 
         let mut options = vec![
             MountOption::FSName("aeroftp".to_string()),
@@ -19098,13 +22001,67 @@ mod fuse_mount {
             }
         };
 
+        let (stop_tx, stop_rx) = tokio::sync::watch::channel(false);
+        let uploader = vfs.clone().map(|cache| {
+            tokio::spawn(run_upload_queue(
+                cache,
+                provider_arc.clone(),
+                stop_rx.clone(),
+                quiet,
+            ))
+        });
+        if change_feed.is_some() && !quiet {
+            eprintln!("Watching remote changes every {}s", poll_interval.as_secs());
+        }
+        let poller = change_feed.map(|(token, index, invalidator)| {
+            tokio::spawn(run_change_poller(
+                provider_arc.clone(),
+                invalidator,
+                index,
+                session.notifier(),
+                token,
+                poll_interval,
+                stop_rx,
+                quiet,
+            ))
+        });
+
         // Wait for a real shutdown signal (SIGINT/SIGTERM). On any return
         // path `session` is dropped → FUSE unmount runs → kernel releases
         // the mountpoint. Previously the mount lived forever because the
         // blocking `mount2` had no cancellation surface.
         let _ = shutdown_signal().await;
+        let _ = stop_tx.send(true);
+        if let Some(task) = poller {
+            let _ = task.await;
+        }
         drop(session);
 
+        // Flush the write-back queue before disconnecting. Whatever fails
+        // stays dirty in the cache and is retried by the next mount.
+        if let Some(task) = uploader {
+            let _ = task.await;
+        }
+        if let Some(cache) = &vfs {
+            let pending = cache.pending_uploads();
+            if !pending.is_empty() && !quiet {
+                eprintln!("Uploading {} pending file(s)...", pending.len());
+            }
+            let mut failed = 0;
+            for upload in &pending {
+                if !upload_pending(cache, &provider_arc, upload, quiet).await {
+                    failed += 1;
+                }
+            }
+            if failed > 0 {
+                eprintln!(
+                    "aeroftp-fuse: {} file(s) not uploaded; kept in {} for the next mount",
+                    failed,
+                    cache.root().display()
+                );
+            }
+        }
+
         // Cleanup
         let mut p = provider_arc.lock().await;
         let _ = p.disconnect().await;
@@ -19114,249 +22071,1247 @@ mod fuse_mount {
         }
         0
     }
-}
 
-#[cfg(target_os = "linux")]
-use fuse_mount::cmd_mount;
+    // ── Snapshot view (`backup mount`) ────────────────────────────
 
-/// Windows mount: WebDAV bridge - starts a local WebDAV server and maps it as a drive letter.
-#[cfg(windows)]
-async fn cmd_mount_windows(
-    url: &str,
-    drive_letter: &str,
-    path: &str,
-    read_only: bool,
-    cli: &Cli,
-    format: OutputFormat,
-) -> i32 {
-    let quiet = cli.quiet || matches!(format, OutputFormat::Json);
+    /// Decrypted chunks kept in memory for sequential reads.
+    const BLOB_CACHE_SIZE: usize = 8;
+    /// Snapshots never change, so the kernel may cache attributes freely.
+    const SNAPSHOT_TTL: Duration = Duration::from_secs(3600);
 
-    // Validate drive letter (e.g., "Z:", "Z", "z:")
-    let letter = drive_letter.trim_end_matches(':').to_uppercase();
-    if letter.len() != 1
-        || !letter
-            .chars()
-            .next()
-            .map(|c| c.is_ascii_uppercase())
-            .unwrap_or(false)
-    {
-        print_error(
-            format,
-            &format!(
-                "Invalid drive letter '{}'. Use a single letter like 'Z:' or 'Z'",
-                drive_letter
-            ),
-            5,
-        );
-        return 5;
+    struct SnapNode {
+        name: String,
+        parent: u64,
+        attr: FileAttr,
+        target: Option<String>,
+        chunks: Vec<BlobRef>,
+        /// `None` for a snapshot directory whose tree is not loaded yet.
+        children: Option<Vec<u64>>,
+        /// Index into `snapshots` for snapshot directories.
+        snapshot: Option<usize>,
+    }
+
+    /// Read-only filesystem exposing one directory per snapshot. Trees are
+    /// loaded on first access and file data is fetched chunk by chunk, so
+    /// opening a single file never downloads more than it reads.
+    pub struct BackupFuseFs {
+        rt: tokio::runtime::Runtime,
+        repo: Arc<AsyncMutex<BackupRepo>>,
+        snapshots: Vec<Snapshot>,
+        /// inode N lives at index N - 1
+        nodes: Vec<SnapNode>,
+        blob_cache: std::collections::VecDeque<(String, Arc<Vec<u8>>)>,
+        uid: u32,
+        gid: u32,
     }
-    let drive = format!("{}:", letter);
 
-    // Find a free local port for the WebDAV server
-    let listener = match std::net::TcpListener::bind("127.0.0.1:0") {
-        Ok(l) => l,
-        Err(e) => {
-            print_error(format, &format!("Cannot bind local port: {}", e), 1);
-            return 1;
+    impl BackupFuseFs {
+        pub fn new(repo: Arc<AsyncMutex<BackupRepo>>, snapshots: Vec<Snapshot>) -> Self {
+            let rt = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to create FUSE tokio runtime");
+            let uid = unsafe { libc::getuid() };
+            let gid = unsafe { libc::getgid() };
+            let mut fs = Self {
+                rt,
+                repo,
+                snapshots: Vec::new(),
+                nodes: Vec::new(),
+                blob_cache: std::collections::VecDeque::new(),
+                uid,
+                gid,
+            };
+            let root = fs.push_node(ROOT_INODE, String::new(), dir_attr(0, 0, uid, gid));
+            fs.nodes[0].children = Some(Vec::new());
+            for (idx, snap) in snapshots.iter().enumerate() {
+                let name = format!(
+                    "{}_{}",
+                    snap.time
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%dT%H:%M:%S"),
+                    snap.short_id()
+                );
+                let mut attr = dir_attr(0, 0, uid, gid);
+                attr.mtime = snap.time.into();
+                attr.ctime = attr.mtime;
+                attr.perm = 0o555;
+                let ino = fs.push_node(root, name, attr);
+                fs.nodes[(ino - 1) as usize].snapshot = Some(idx);
+            }
+            fs.snapshots = snapshots;
+            fs
+        }
+
+        fn push_node(&mut self, parent: u64, name: String, mut attr: FileAttr) -> u64 {
+            let ino = self.nodes.len() as u64 + 1;
+            attr.ino = ino;
+            let is_dir = attr.kind == FileType::Directory;
+            self.nodes.push(SnapNode {
+                name,
+                parent,
+                attr,
+                target: None,
+                chunks: Vec::new(),
+                children: is_dir.then(Vec::new),
+                snapshot: None,
+            });
+            if ino != ROOT_INODE {
+                if let Some(children) = self.nodes[(parent - 1) as usize].children.as_mut() {
+                    children.push(ino);
+                }
+            }
+            ino
         }
-    };
-    let port = listener.local_addr().unwrap().port();
-    drop(listener);
 
-    let addr = format!("127.0.0.1:{}", port);
+        fn node(&self, ino: u64) -> Option<&SnapNode> {
+            ino.checked_sub(1).and_then(|i| self.nodes.get(i as usize))
+        }
 
-    if !quiet {
-        eprintln!(
-            "Mounting via WebDAV bridge on {} ({}{})",
-            drive,
-            if read_only { "read-only, " } else { "" },
-            addr,
-        );
-    }
+        /// Load the tree behind a snapshot directory the first time it is
+        /// listed or looked into.
+        fn ensure_loaded(&mut self, ino: u64) -> bool {
+            let Some(node) = self.node(ino) else {
+                return false;
+            };
+            let Some(idx) = node.snapshot else {
+                return true;
+            };
+            if node.children.is_some() {
+                return true;
+            }
+            let snap = self.snapshots[idx].clone();
+            let repo = self.repo.clone();
+            let tree = self.rt.block_on(async {
+                let mut r = repo.lock().await;
+                r.load_tree(&snap).await
+            });
+            let Ok(tree) = tree else {
+                return false;
+            };
+            self.nodes[(ino - 1) as usize].children = Some(Vec::new());
+            let mut by_path: HashMap<String, u64> = HashMap::new();
+            for entry in tree {
+                let (parent, name) = match entry.path.rsplit_once('/') {
+                    Some((dir, name)) => (by_path.get(dir).copied().unwrap_or(ino), name),
+                    None => (ino, entry.path.as_str()),
+                };
+                let mtime = UNIX_EPOCH + Duration::from_secs(entry.mtime.max(0) as u64);
+                let mut attr = match entry.kind {
+                    NodeKind::Dir => dir_attr(0, 0, self.uid, self.gid),
+                    NodeKind::File => file_attr(0, entry.size, mtime, self.uid, self.gid),
+                    NodeKind::Symlink => {
+                        let len = entry.target.as_deref().map_or(0, str::len) as u64;
+                        let mut attr = file_attr(0, len, mtime, self.uid, self.gid);
+                        attr.kind = FileType::Symlink;
+                        attr.perm = 0o777;
+                        attr
+                    }
+                };
+                attr.mtime = mtime;
+                attr.ctime = mtime;
+                if let Some(mode) = entry.mode.filter(|_| entry.kind != NodeKind::Symlink) {
+                    attr.perm = (mode & 0o7777) as u16;
+                }
+                let child = self.push_node(parent, name.to_string(), attr);
+                let slot = &mut self.nodes[(child - 1) as usize];
+                slot.target = entry.target;
+                slot.chunks = entry.chunks;
+                if entry.kind == NodeKind::Dir {
+                    by_path.insert(entry.path, child);
+                }
+            }
+            true
+        }
 
-    // Start the WebDAV server directly - it runs until Ctrl+C
-    // We run it concurrently with the drive mapping using tokio::select
-    if !quiet {
-        eprintln!("Starting WebDAV server on {}...", addr);
+        fn blob(&mut self, id: &str) -> Option<Arc<Vec<u8>>> {
+            if let Some(pos) = self.blob_cache.iter().position(|(k, _)| k == id) {
+                let hit = self.blob_cache.remove(pos)?;
+                let data = hit.1.clone();
+                self.blob_cache.push_front(hit);
+                return Some(data);
+            }
+            let repo = self.repo.clone();
+            let data = self
+                .rt
+                .block_on(async {
+                    let mut r = repo.lock().await;
+                    r.read_blob(id).await
+                })
+                .ok()?;
+            let data = Arc::new(data);
+            self.blob_cache.push_front((id.to_string(), data.clone()));
+            self.blob_cache.truncate(BLOB_CACHE_SIZE);
+            Some(data)
+        }
     }
 
-    // Connect provider first
-    let (provider, initial_path) = match create_and_connect(url, cli, format).await {
-        Ok(v) => v,
-        Err(code) => return code,
-    };
-
-    let base_path = normalize_remote_path(&resolve_cli_remote_path(&initial_path, path));
-    let provider_label = if let Some(profile) = &cli.profile {
-        format!("profile {}", profile)
-    } else {
-        provider.display_name()
-    };
+    impl Filesystem for BackupFuseFs {
+        fn getattr(&mut self, _req: &Request, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+            match self.node(ino) {
+                Some(node) => reply.attr(&SNAPSHOT_TTL, &node.attr),
+                None => reply.error(libc::ENOENT),
+            }
+        }
 
-    let state = ServeHttpState {
-        provider: Arc::new(AsyncMutex::new(provider)),
-        provider_label,
-        base_path,
-        auth_token: None, // local-only WebDAV bridge for Windows mount - no auth needed
-    };
+        fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
+            if !self.ensure_loaded(parent) {
+                reply.error(libc::EIO);
+                return;
+            }
+            let name = name.to_string_lossy();
+            let found = self
+                .node(parent)
+                .and_then(|p| p.children.as_ref())
+                .and_then(|children| {
+                    children
+                        .iter()
+                        .find(|c| self.node(**c).is_some_and(|n| n.name == name))
+                })
+                .and_then(|c| self.node(*c));
+            match found {
+                Some(node) => reply.entry(&SNAPSHOT_TTL, &node.attr, 0),
+                None => reply.error(libc::ENOENT),
+            }
+        }
 
-    let app = Router::new()
-        .route("/", any(webdav_root_handler))
-        .route("/{*path}", any(webdav_path_handler))
-        .layer(DefaultBodyLimit::max(WEBDAV_MAX_UPLOAD_BYTES))
-        .with_state(state.clone());
-
-    let bind_addr: SocketAddr = addr.parse().unwrap();
-    let listener = match tokio::net::TcpListener::bind(bind_addr).await {
-        Ok(l) => l,
-        Err(e) => {
-            print_error(format, &format!("Failed to bind {}: {}", addr, e), 1);
-            return 1;
+        fn readdir(
+            &mut self,
+            _req: &Request,
+            ino: u64,
+            _fh: u64,
+            offset: i64,
+            mut reply: ReplyDirectory,
+        ) {
+            if !self.ensure_loaded(ino) {
+                reply.error(libc::EIO);
+                return;
+            }
+            let Some(node) = self.node(ino) else {
+                reply.error(libc::ENOENT);
+                return;
+            };
+            let Some(children) = node.children.as_ref() else {
+                reply.error(libc::ENOTDIR);
+                return;
+            };
+            let mut entries: Vec<(u64, FileType, &str)> = vec![
+                (ino, FileType::Directory, "."),
+                (node.parent, FileType::Directory, ".."),
+            ];
+            for child in children {
+                if let Some(c) = self.node(*child) {
+                    entries.push((*child, c.attr.kind, c.name.as_str()));
+                }
+            }
+            for (i, (ino, kind, name)) in entries.iter().enumerate().skip(offset as usize) {
+                if reply.add(*ino, (i + 1) as i64, *kind, name) {
+                    break; // buffer full
+                }
+            }
+            reply.ok();
         }
-    };
 
-    // Spawn WebDAV server in background
-    let server_handle = tokio::spawn(async move {
-        axum::serve(listener, app)
-            .with_graceful_shutdown(async {
-                let _ = shutdown_signal().await;
-            })
-            .await
-    });
+        fn readlink(&mut self, _req: &Request, ino: u64, reply: ReplyData) {
+            match self.node(ino).and_then(|n| n.target.as_ref()) {
+                Some(target) => reply.data(target.as_bytes()),
+                None => reply.error(libc::EINVAL),
+            }
+        }
 
-    // Wait for server to be ready
-    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        fn open(&mut self, _req: &Request, ino: u64, flags: i32, reply: ReplyOpen) {
+            if self.node(ino).is_none() {
+                reply.error(libc::ENOENT);
+                return;
+            }
+            let write_flags = libc::O_WRONLY | libc::O_RDWR | libc::O_APPEND | libc::O_TRUNC;
+            if flags & write_flags != 0 {
+                reply.error(libc::EROFS);
+                return;
+            }
+            reply.opened(0, 0);
+        }
 
-    // Map as network drive using net use
-    let webdav_url = format!("http://{}", addr);
-    if !quiet {
-        eprintln!("Mapping drive: net use {} {}", drive, webdav_url);
+        fn read(
+            &mut self,
+            _req: &Request,
+            ino: u64,
+            _fh: u64,
+            offset: i64,
+            size: u32,
+            _flags: i32,
+            _lock_owner: Option<u64>,
+            reply: ReplyData,
+        ) {
+            let Some(node) = self.node(ino) else {
+                reply.error(libc::ENOENT);
+                return;
+            };
+            let start = offset.max(0) as u64;
+            let end = (start + size as u64).min(node.attr.size);
+            let chunks = node.chunks.clone();
+            let mut out = Vec::with_capacity(end.saturating_sub(start) as usize);
+            let mut chunk_start = 0u64;
+            for chunk in &chunks {
+                let chunk_end = chunk_start + chunk.size as u64;
+                if chunk_end > start && chunk_start < end {
+                    let Some(data) = self.blob(&chunk.id) else {
+                        reply.error(libc::EIO);
+                        return;
+                    };
+                    let from = start.saturating_sub(chunk_start) as usize;
+                    let to = ((end - chunk_start) as usize).min(data.len());
+                    out.extend_from_slice(&data[from.min(to)..to]);
+                }
+                if chunk_end >= end {
+                    break;
+                }
+                chunk_start = chunk_end;
+            }
+            reply.data(&out);
+        }
     }
 
-    let map_result = std::process::Command::new("net")
-        .args(["use", &drive, &webdav_url, "/persistent:no"])
-        .output();
-
-    match map_result {
-        Ok(output) if output.status.success() => {
-            if !quiet {
-                eprintln!(
-                    "Drive {} mapped successfully. Press Ctrl+C to unmount.",
-                    drive
-                );
-            }
+    pub async fn cmd_backup_mount(
+        mountpoint: &str,
+        url: &str,
+        path: &str,
+        password: &str,
+        allow_other: bool,
+        cli: &Cli,
+        format: OutputFormat,
+    ) -> i32 {
+        if let Err(code) = check_mountpoint(mountpoint, format) {
+            return code;
         }
-        Ok(output) => {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            print_error(
-                format,
-                &format!("Failed to map drive {}: {}", drive, stderr.trim()),
-                99,
+        let mut repo = match open_backup_repo(url, path, password, cli, format).await {
+            Ok(r) => r,
+            Err(code) => return code,
+        };
+        let snapshots = match repo.snapshots().await {
+            Ok((snapshots, skipped)) => {
+                for w in &skipped {
+                    eprintln!("Warning: {}", w);
+                }
+                snapshots
+            }
+            Err(e) => {
+                print_error(format, &format!("Cannot list snapshots: {}", e), 4);
+                close_backup_repo(repo).await;
+                return 4;
+            }
+        };
+
+        let quiet = cli.quiet || matches!(format, OutputFormat::Json);
+        if !quiet {
+            eprintln!(
+                "Mounting {} snapshot(s) read-only on {}",
+                snapshots.len(),
+                mountpoint
             );
-            server_handle.abort();
-            return 99;
-        }
-        Err(e) => {
-            print_error(format, &format!("Cannot execute 'net use': {}", e), 99);
-            server_handle.abort();
-            return 99;
+            eprintln!("Press Ctrl+C to unmount");
         }
-    }
 
-    // Wait for server (blocks until Ctrl+C)
-    let _ = server_handle.await;
+        let repo_arc = Arc::new(AsyncMutex::new(repo));
+        let fs = BackupFuseFs::new(repo_arc.clone(), snapshots);
+        let mut options = vec![
+            MountOption::FSName("aeroftp-backup".to_string()),
+            MountOption::Subtype("aeroftp".to_string()),
+            MountOption::RO,
+        ];
+        if allow_other {
+            options.push(MountOption::AllowOther);
+        }
 
-    // Cleanup: unmap drive
-    if !quiet {
-        eprintln!("\nUnmapping {}...", drive);
-    }
-    let _ = std::process::Command::new("net")
-        .args(["use", &drive, "/delete", "/yes"])
-        .output();
+        let mountpoint_owned = mountpoint.to_string();
+        let session_result = tokio::task::spawn_blocking(move || {
+            fuser::spawn_mount2(fs, &mountpoint_owned, &options)
+        })
+        .await;
+        let session = match session_result {
+            Ok(Ok(s)) => s,
+            Ok(Err(e)) => {
+                print_error(format, &format!("Mount failed: {}", e), 99);
+                return 99;
+            }
+            Err(e) => {
+                print_error(format, &format!("Mount task failed: {}", e), 99);
+                return 99;
+            }
+        };
 
-    // Disconnect provider
-    let mut p = state.provider.lock().await;
-    let _ = p.disconnect().await;
+        let _ = shutdown_signal().await;
+        drop(session);
 
-    if !quiet {
-        eprintln!("Unmounted {} successfully", drive);
+        if let Ok(repo) = Arc::try_unwrap(repo_arc) {
+            close_backup_repo(repo.into_inner()).await;
+        }
+        if !quiet {
+            eprintln!("Unmounted successfully");
+        }
+        0
     }
-    0
-}
-
-// ── Daemon + Jobs ────────────────────────────────────────────────
 
-fn daemon_config_dir() -> PathBuf {
-    let dir = dirs::config_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("aeroftp");
-    let _ = std::fs::create_dir_all(&dir);
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let _ = std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700));
-    }
-    dir
-}
+    // ── Vault view (`vault mount`) ────────────────────────────────
 
-fn daemon_pid_path() -> PathBuf {
-    daemon_config_dir().join("daemon.pid")
-}
-fn daemon_db_path() -> PathBuf {
-    daemon_config_dir().join("jobs.db")
-}
-fn _daemon_log_path() -> PathBuf {
-    daemon_config_dir().join("daemon.log")
-}
+    /// Only this mount changes the tree, so short TTLs are plenty.
+    const VAULT_TTL: Duration = Duration::from_secs(1);
 
-fn daemon_read_pid() -> Option<u32> {
-    std::fs::read_to_string(daemon_pid_path())
-        .ok()?
-        .trim()
-        .parse()
-        .ok()
-}
+    /// Read-write filesystem over an unlocked AeroVault. Changes live in the
+    /// staging directory of [`VaultMount`] until the next commit.
+    pub struct VaultFuseFs {
+        vault: Arc<Mutex<VaultMount>>,
+        read_only: bool,
+        uid: u32,
+        gid: u32,
+    }
 
-fn daemon_is_running() -> bool {
-    if let Some(pid) = daemon_read_pid() {
-        // Check if process exists
-        #[cfg(unix)]
-        {
-            let result = unsafe { libc::kill(pid as i32, 0) };
-            result == 0
+    impl VaultFuseFs {
+        pub fn new(vault: Arc<Mutex<VaultMount>>, read_only: bool) -> Self {
+            Self {
+                vault,
+                read_only,
+                uid: unsafe { libc::getuid() },
+                gid: unsafe { libc::getgid() },
+            }
         }
-        #[cfg(not(unix))]
-        {
-            let _ = pid;
-            false
+
+        fn attr(&self, mount: &VaultMount, ino: u64) -> Option<FileAttr> {
+            let node = mount.node(ino)?;
+            Some(if node.is_dir {
+                dir_attr(ino, 0, self.uid, self.gid)
+            } else {
+                file_attr(ino, node.size, node.mtime, self.uid, self.gid)
+            })
         }
-    } else {
-        false
     }
-}
 
-fn daemon_addr() -> String {
-    // Read addr from pid file's sibling
-    let addr_path = daemon_config_dir().join("daemon.addr");
-    std::fs::read_to_string(addr_path)
-        .unwrap_or_else(|_| "127.0.0.1:14320".to_string())
-        .trim()
-        .to_string()
-}
+    fn vault_errno(e: &std::io::Error) -> i32 {
+        use std::io::ErrorKind;
+        e.raw_os_error().unwrap_or(match e.kind() {
+            ErrorKind::NotFound => libc::ENOENT,
+            ErrorKind::AlreadyExists => libc::EEXIST,
+            ErrorKind::DirectoryNotEmpty => libc::ENOTEMPTY,
+            ErrorKind::IsADirectory => libc::EISDIR,
+            ErrorKind::NotADirectory => libc::ENOTDIR,
+            ErrorKind::InvalidInput => libc::EINVAL,
+            _ => libc::EIO,
+        })
+    }
 
-fn daemon_token_path() -> PathBuf {
-    daemon_config_dir().join("daemon.token")
-}
+    impl Filesystem for VaultFuseFs {
+        fn getattr(&mut self, _req: &Request, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+            let mount = self.vault.lock().unwrap();
+            match self.attr(&mount, ino) {
+                Some(attr) => reply.attr(&VAULT_TTL, &attr),
+                None => reply.error(libc::ENOENT),
+            }
+        }
 
-fn daemon_auth_token() -> Option<String> {
-    std::fs::read_to_string(daemon_token_path())
-        .ok()
-        .map(|token| token.trim().to_string())
-        .filter(|token| !token.is_empty())
-}
+        fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
+            let mount = self.vault.lock().unwrap();
+            let found = mount
+                .lookup(parent, &name.to_string_lossy())
+                .and_then(|ino| self.attr(&mount, ino));
+            match found {
+                Some(attr) => reply.entry(&VAULT_TTL, &attr, 0),
+                None => reply.error(libc::ENOENT),
+            }
+        }
 
-fn write_daemon_auth_token(token: &str) -> Result<(), String> {
-    let path = daemon_token_path();
+        fn readdir(
+            &mut self,
+            _req: &Request,
+            ino: u64,
+            _fh: u64,
+            offset: i64,
+            mut reply: ReplyDirectory,
+        ) {
+            let mount = self.vault.lock().unwrap();
+            let Some(node) = mount.node(ino) else {
+                reply.error(libc::ENOENT);
+                return;
+            };
+            if !node.is_dir {
+                reply.error(libc::ENOTDIR);
+                return;
+            }
+            let mut entries: Vec<(u64, FileType, &str)> = vec![
+                (ino, FileType::Directory, "."),
+                (node.parent, FileType::Directory, ".."),
+            ];
+            let children = mount.children(ino);
+            for child in &children {
+                if let Some(c) = mount.node(*child) {
+                    let kind = if c.is_dir {
+                        FileType::Directory
+                    } else {
+                        FileType::RegularFile
+                    };
+                    entries.push((*child, kind, c.name.as_str()));
+                }
+            }
+            for (i, (ino, kind, name)) in entries.iter().enumerate().skip(offset as usize) {
+                if reply.add(*ino, (i + 1) as i64, *kind, name) {
+                    break; // buffer full
+                }
+            }
+            reply.ok();
+        }
+
+        fn open(&mut self, _req: &Request, ino: u64, flags: i32, reply: ReplyOpen) {
+            if self.vault.lock().unwrap().node(ino).is_none() {
+                reply.error(libc::ENOENT);
+                return;
+            }
+            let write_flags = libc::O_WRONLY | libc::O_RDWR | libc::O_APPEND | libc::O_TRUNC;
+            if self.read_only && flags & write_flags != 0 {
+                reply.error(libc::EROFS);
+                return;
+            }
+            reply.opened(0, 0);
+        }
+
+        fn read(
+            &mut self,
+            _req: &Request,
+            ino: u64,
+            _fh: u64,
+            offset: i64,
+            size: u32,
+            _flags: i32,
+            _lock_owner: Option<u64>,
+            reply: ReplyData,
+        ) {
+            let result = self
+                .vault
+                .lock()
+                .unwrap()
+                .read(ino, offset.max(0) as u64, size);
+            match result {
+                Ok(data) => reply.data(&data),
+                Err(e) => reply.error(vault_errno(&e)),
+            }
+        }
+
+        fn create(
+            &mut self,
+            _req: &Request,
+            parent: u64,
+            name: &OsStr,
+            _mode: u32,
+            _umask: u32,
+            _flags: i32,
+            reply: ReplyCreate,
+        ) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
+            let mut mount = self.vault.lock().unwrap();
+            match mount.create(parent, &name.to_string_lossy()) {
+                Ok(ino) => match self.attr(&mount, ino) {
+                    Some(attr) => reply.created(&VAULT_TTL, &attr, 0, 0, 0),
+                    None => reply.error(libc::EIO),
+                },
+                Err(e) => reply.error(vault_errno(&e)),
+            }
+        }
+
+        fn write(
+            &mut self,
+            _req: &Request,
+            ino: u64,
+            _fh: u64,
+            offset: i64,
+            data: &[u8],
+            _write_flags: u32,
+            _flags: i32,
+            _lock_owner: Option<u64>,
+            reply: ReplyWrite,
+        ) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
+            let result = self
+                .vault
+                .lock()
+                .unwrap()
+                .write(ino, offset.max(0) as u64, data);
+            match result {
+                Ok(written) => reply.written(written),
+                Err(e) => reply.error(vault_errno(&e)),
+            }
+        }
+
+        fn mkdir(
+            &mut self,
+            _req: &Request,
+            parent: u64,
+            name: &OsStr,
+            _mode: u32,
+            _umask: u32,
+            reply: ReplyEntry,
+        ) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
+            let mut mount = self.vault.lock().unwrap();
+            match mount.mkdir(parent, &name.to_string_lossy()) {
+                Ok(ino) => match self.attr(&mount, ino) {
+                    Some(attr) => reply.entry(&VAULT_TTL, &attr, 0),
+                    None => reply.error(libc::EIO),
+                },
+                Err(e) => reply.error(vault_errno(&e)),
+            }
+        }
+
+        fn unlink(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
+            let result = self
+                .vault
+                .lock()
+                .unwrap()
+                .unlink(parent, &name.to_string_lossy());
+            match result {
+                Ok(()) => reply.ok(),
+                Err(e) => reply.error(vault_errno(&e)),
+            }
+        }
+
+        fn rmdir(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
+            let result = self
+                .vault
+                .lock()
+                .unwrap()
+                .rmdir(parent, &name.to_string_lossy());
+            match result {
+                Ok(()) => reply.ok(),
+                Err(e) => reply.error(vault_errno(&e)),
+            }
+        }
+
+        fn rename(
+            &mut self,
+            _req: &Request,
+            parent: u64,
+            name: &OsStr,
+            newparent: u64,
+            newname: &OsStr,
+            _flags: u32,
+            reply: ReplyEmpty,
+        ) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
+            let result = self.vault.lock().unwrap().rename(
+                parent,
+                &name.to_string_lossy(),
+                newparent,
+                &newname.to_string_lossy(),
+            );
+            match result {
+                Ok(()) => reply.ok(),
+                Err(e) => reply.error(vault_errno(&e)),
+            }
+        }
+
+        fn setattr(
+            &mut self,
+            _req: &Request,
+            ino: u64,
+            _mode: Option<u32>,
+            _uid: Option<u32>,
+            _gid: Option<u32>,
+            size: Option<u64>,
+            _atime: Option<fuser::TimeOrNow>,
+            _mtime: Option<fuser::TimeOrNow>,
+            _ctime: Option<SystemTime>,
+            _fh: Option<u64>,
+            _crtime: Option<SystemTime>,
+            _chgtime: Option<SystemTime>,
+            _bkuptime: Option<SystemTime>,
+            _flags: Option<u32>,
+            reply: ReplyAttr,
+        ) {
+            let mut mount = self.vault.lock().unwrap();
+            if let Some(size) = size {
+                if self.read_only {
+                    reply.error(libc::EROFS);
+                    return;
+                }
+                if let Err(e) = mount.truncate(ino, size) {
+                    reply.error(vault_errno(&e));
+                    return;
+                }
+            }
+            match self.attr(&mount, ino) {
+                Some(attr) => reply.attr(&VAULT_TTL, &attr),
+                None => reply.error(libc::ENOENT),
+            }
+        }
+    }
+
+    /// A vault downloaded from a remote: the mount works on the temp copy,
+    /// which is uploaded again after each commit.
+    struct RemoteVault {
+        provider: Arc<AsyncMutex<Box<dyn StorageProvider>>>,
+        path: String,
+        local: PathBuf,
+        /// Size and mtime of the remote file when last downloaded or uploaded
+        seen: AsyncMutex<(u64, Option<String>)>,
+        /// A commit landed locally but is not uploaded yet
+        unpushed: AtomicBool,
+    }
+
+    impl RemoteVault {
+        /// Upload the committed vault unless the remote copy changed since
+        /// this mount last saw it: overwriting would lose those changes.
+        async fn push(&self) -> Result<(), String> {
+            if !self.unpushed.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            let mut p = self.provider.lock().await;
+            let mut seen = self.seen.lock().await;
+            let current = p
+                .stat(&self.path)
+                .await
+                .map(|e| (e.size, e.modified))
+                .map_err(|e| format!("Cannot check {}: {}", self.path, e))?;
+            if current != *seen {
+                return Err(format!(
+                    "{} changed on the remote since it was mounted, not overwriting it",
+                    self.path
+                ));
+            }
+            let local = self.local.to_string_lossy();
+            vault_remote::upload_vault(p.as_mut(), &local, &self.path).await?;
+            self.unpushed.store(false, Ordering::SeqCst);
+            if let Ok(e) = p.stat(&self.path).await {
+                *seen = (e.size, e.modified);
+            }
+            Ok(())
+        }
+    }
+
+    /// Commit the mount's changes off the async workers, then upload the
+    /// vault when it lives on a remote.
+    async fn commit_vault(
+        vault: &Arc<Mutex<VaultMount>>,
+        remote: Option<&RemoteVault>,
+    ) -> Result<CommitStats, String> {
+        let mount = vault.clone();
+        let stats = tokio::task::spawn_blocking(move || mount.lock().unwrap().commit())
+            .await
+            .map_err(|e| format!("Commit task failed: {}", e))??;
+        if let Some(remote) = remote {
+            if stats != CommitStats::default() {
+                remote.unpushed.store(true, Ordering::SeqCst);
+            }
+            remote.push().await?;
+        }
+        Ok(stats)
+    }
+
+    fn report_commit(stats: &CommitStats) {
+        if stats == &CommitStats::default() {
+            return;
+        }
+        eprintln!(
+            "Vault updated: {} written, {} moved, {} folder(s) created, {} removed{}",
+            stats.written,
+            stats.moved,
+            stats.created_dirs,
+            stats.deleted,
+            if stats.reclaimed > 0 {
+                format!(", {} reclaimed", format_size(stats.reclaimed))
+            } else {
+                String::new()
+            }
+        );
+    }
+
+    async fn run_vault_commits(
+        vault: Arc<Mutex<VaultMount>>,
+        remote: Option<Arc<RemoteVault>>,
+        interval: Duration,
+        mut stop: tokio::sync::watch::Receiver<bool>,
+        quiet: bool,
+    ) {
+        let mut tick = tokio::time::interval(interval);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        tick.tick().await;
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {}
+                _ = stop.changed() => break,
+            }
+            match commit_vault(&vault, remote.as_deref()).await {
+                Ok(stats) if !quiet => report_commit(&stats),
+                Ok(_) => {}
+                Err(e) => eprintln!("aeroftp-fuse: vault commit failed: {}", e),
+            }
+        }
+    }
+
+    /// Drop the temp copy of a remote vault and disconnect.
+    async fn release_remote_vault(remote: Option<Arc<RemoteVault>>) {
+        if let Some(remote) = remote {
+            let _ = vault_remote::cleanup_temp(&remote.local.to_string_lossy());
+            let mut p = remote.provider.lock().await;
+            let _ = p.disconnect().await;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn cmd_vault_mount(
+        vault_path: &str,
+        mountpoint: &str,
+        url: &str,
+        password: &str,
+        allow_other: bool,
+        read_only: bool,
+        commit_interval: Duration,
+        cli: &Cli,
+        format: OutputFormat,
+    ) -> i32 {
+        if let Err(code) = check_mountpoint(mountpoint, format) {
+            return code;
+        }
+        let quiet = cli.quiet || matches!(format, OutputFormat::Json);
+
+        // A vault on a remote is downloaded to a private temp copy first
+        let remote = if url != "_" || cli.profile.is_some() {
+            let (mut provider, initial_path) = match create_and_connect(url, cli, format).await {
+                Ok(v) => v,
+                Err(code) => return code,
+            };
+            let path = normalize_remote_path(&resolve_cli_remote_path(&initial_path, vault_path));
+            let seen = match provider.stat(&path).await {
+                Ok(e) => (e.size, e.modified),
+                Err(e) => {
+                    print_error(format, &format!("{}: {}", path, e), 2);
+                    let _ = provider.disconnect().await;
+                    return 2;
+                }
+            };
+            if !quiet {
+                eprintln!("Downloading {} ({})...", path, format_size(seen.0));
+            }
+            match vault_remote::download_to_temp(provider.as_mut(), &path).await {
+                Ok(local) => Some(Arc::new(RemoteVault {
+                    provider: Arc::new(AsyncMutex::new(provider)),
+                    path,
+                    local,
+                    seen: AsyncMutex::new(seen),
+                    unpushed: AtomicBool::new(false),
+                })),
+                Err(e) => {
+                    print_error(format, &e, 4);
+                    let _ = provider.disconnect().await;
+                    return 4;
+                }
+            }
+        } else {
+            None
+        };
+        let local = remote
+            .as_ref()
+            .map(|r| r.local.clone())
+            .unwrap_or_else(|| PathBuf::from(vault_path));
+
+        let staging =
+            std::env::temp_dir().join(format!("aerovault_mount_{}", uuid::Uuid::new_v4()));
+        // Argon2id takes a moment: keep it off the async workers
+        let opened = {
+            let local = local.clone();
+            let password = password.to_string();
+            let staging = staging.clone();
+            tokio::task::spawn_blocking(move || {
+                let vault = aerovault::Vault::open(&local, password).map_err(|e| {
+                    let code = if matches!(
+                        e,
+                        aerovault::Error::Crypto(aerovault::error::CryptoError::KeyUnwrap)
+                    ) {
+                        6
+                    } else {
+                        5
+                    };
+                    (format!("Cannot open {}: {}", local.display(), e), code)
+                })?;
+                VaultMount::open(vault, &staging).map_err(|e| (e, 5))
+            })
+            .await
+        };
+        let mount = match opened {
+            Ok(Ok(m)) => m,
+            Ok(Err((e, code))) => {
+                print_error(format, &e, code);
+                release_remote_vault(remote).await;
+                return code;
+            }
+            Err(e) => {
+                print_error(format, &format!("Vault task failed: {}", e), 99);
+                release_remote_vault(remote).await;
+                return 99;
+            }
+        };
+
+        if !quiet {
+            eprintln!(
+                "Mounting {} on {} ({})",
+                remote
+                    .as_ref()
+                    .map(|r| r.path.as_str())
+                    .unwrap_or(vault_path),
+                mountpoint,
+                if read_only { "read-only" } else { "read-write" }
+            );
+            eprintln!("Press Ctrl+C to unmount");
+        }
+
+        let vault = Arc::new(Mutex::new(mount));
+        let fs = VaultFuseFs::new(vault.clone(), read_only);
+        let mut options = vec![
+            MountOption::FSName("aeroftp-vault".to_string()),
+            MountOption::Subtype("aeroftp".to_string()),
+            MountOption::DefaultPermissions,
+        ];
+        if read_only {
+            options.push(MountOption::RO);
+        }
+        if allow_other {
+            options.push(MountOption::AllowOther);
+        }
+
+        let mountpoint_owned = mountpoint.to_string();
+        let session_result = tokio::task::spawn_blocking(move || {
+            fuser::spawn_mount2(fs, &mountpoint_owned, &options)
+        })
+        .await;
+        let session = match session_result {
+            Ok(Ok(s)) => s,
+            Ok(Err(e)) => {
+                print_error(format, &format!("Mount failed: {}", e), 99);
+                if let Ok(mount) = Arc::try_unwrap(vault) {
+                    mount.into_inner().unwrap().close();
+                }
+                release_remote_vault(remote).await;
+                return 99;
+            }
+            Err(e) => {
+                print_error(format, &format!("Mount task failed: {}", e), 99);
+                release_remote_vault(remote).await;
+                let _ = std::fs::remove_dir_all(&staging);
+                return 99;
+            }
+        };
+
+        let (stop_tx, stop_rx) = tokio::sync::watch::channel(false);
+        let committer = (!read_only && !commit_interval.is_zero()).then(|| {
+            tokio::spawn(run_vault_commits(
+                vault.clone(),
+                remote.clone(),
+                commit_interval,
+                stop_rx,
+                quiet,
+            ))
+        });
+
+        let _ = shutdown_signal().await;
+        let _ = stop_tx.send(true);
+        if let Some(task) = committer {
+            let _ = task.await;
+        }
+        drop(session);
+
+        // Write back whatever is left. On failure the decrypted changes stay
+        // in the staging directory and the temp copy is kept, so nothing is
+        // lost silently.
+        let committed = commit_vault(&vault, remote.as_deref()).await;
+        let code = match &committed {
+            Ok(stats) => {
+                if !quiet {
+                    report_commit(stats);
+                }
+                if let Ok(mount) = Arc::try_unwrap(vault) {
+                    mount.into_inner().unwrap().close();
+                }
+                release_remote_vault(remote).await;
+                0
+            }
+            Err(e) => {
+                let dirty = vault.lock().unwrap().is_dirty();
+                print_error(format, &format!("Vault not fully saved: {}", e), 4);
+                if dirty {
+                    eprintln!("Unsaved changes are kept in {}", staging.display());
+                }
+                if let Some(remote) = &remote {
+                    eprintln!("The updated vault is kept in {}", remote.local.display());
+                    let mut p = remote.provider.lock().await;
+                    let _ = p.disconnect().await;
+                }
+                4
+            }
+        };
+        if code == 0 && !quiet {
+            eprintln!("Unmounted successfully");
+        }
+        code
+    }
+}
+
+#[cfg(target_os = "linux")]
+use fuse_mount::{cmd_backup_mount, cmd_mount, cmd_vault_mount, vfs_cache_options};
+
+/// Windows mount: WebDAV bridge - starts a local WebDAV server and maps it as a drive letter.
+#[cfg(windows)]
+async fn cmd_mount_windows(
+    url: &str,
+    drive_letter: &str,
+    path: &str,
+    read_only: bool,
+    cli: &Cli,
+    format: OutputFormat,
+) -> i32 {
+    let quiet = cli.quiet || matches!(format, OutputFormat::Json);
+
+    // Validate drive letter (e.g., "Z:", "Z", "z:")
+    let letter = drive_letter.trim_end_matches(':').to_uppercase();
+    if letter.len() != 1
+        || !letter
+            .chars()
+            .next()
+            .map(|c| c.is_ascii_uppercase())
+            .unwrap_or(false)
+    {
+        print_error(
+            format,
+            &format!(
+                "Invalid drive letter '{}'. Use a single letter like 'Z:' or 'Z'",
+                drive_letter
+            ),
+            5,
+        );
+        return 5;
+    }
+    let drive = format!("{}:", letter);
+
+    // Find a free local port for the WebDAV server
+    let listener = match std::net::TcpListener::bind("127.0.0.1:0") {
+        Ok(l) => l,
+        Err(e) => {
+            print_error(format, &format!("Cannot bind local port: {}", e), 1);
+            return 1;
+        }
+    };
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let addr = format!("127.0.0.1:{}", port);
+
+    if !quiet {
+        eprintln!(
+            "Mounting via WebDAV bridge on {} ({}{})",
+            drive,
+            if read_only { "read-only, " } else { "" },
+            addr,
+        );
+    }
+
+    // Start the WebDAV server directly - it runs until Ctrl+C
+    // We run it concurrently with the drive mapping using tokio::select
+    if !quiet {
+        eprintln!("Starting WebDAV server on {}...", addr);
+    }
+
+    // Connect provider first
+    let (provider, initial_path) = match create_and_connect(url, cli, format).await {
+        Ok(v) => v,
+        Err(code) => return code,
+    };
+
+    let base_path = normalize_remote_path(&resolve_cli_remote_path(&initial_path, path));
+    let provider_label = if let Some(profile) = &cli.profile {
+        format!("profile {}", profile)
+    } else {
+        provider.display_name()
+    };
+
+    let state = ServeHttpState {
+        provider: Arc::new(AsyncMutex::new(provider)),
+        provider_label,
+        base_path,
+        auth_token: None, // local-only WebDAV bridge for Windows mount - no auth needed
+    };
+
+    let app = Router::new()
+        .route("/", any(webdav_root_handler))
+        .route("/{*path}", any(webdav_path_handler))
+        .layer(DefaultBodyLimit::max(WEBDAV_MAX_UPLOAD_BYTES))
+        .with_state(state.clone());
+
+    let bind_addr: SocketAddr = addr.parse().unwrap();
+    let listener = match tokio::net::TcpListener::bind(bind_addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            print_error(format, &format!("Failed to bind {}: {}", addr, e), 1);
+            return 1;
+        }
+    };
+
+    // Spawn WebDAV server in background
+    let server_handle = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_signal().await;
+            })
+            .await
+    });
+
+    // Wait for server to be ready
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    // Map as network drive using net use
+    let webdav_url = format!("http://{}", addr);
+    if !quiet {
+        eprintln!("Mapping drive: net use {} {}", drive, webdav_url);
+    }
+
+    let map_result = std::process::Command::new("net")
+        .args(["use", &drive, &webdav_url, "/persistent:no"])
+        .output();
+
+    match map_result {
+        Ok(output) if output.status.success() => {
+            if !quiet {
+                eprintln!(
+                    "Drive {} mapped successfully. Press Ctrl+C to unmount.",
+                    drive
+                );
+            }
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            print_error(
+                format,
+                &format!("Failed to map drive {}: {}", drive, stderr.trim()),
+                99,
+            );
+            server_handle.abort();
+            return 99;
+        }
+        Err(e) => {
+            print_error(format, &format!("Cannot execute 'net use': {}", e), 99);
+            server_handle.abort();
+            return 99;
+        }
+    }
+
+    // Wait for server (blocks until Ctrl+C)
+    let _ = server_handle.await;
+
+    // Cleanup: unmap drive
+    if !quiet {
+        eprintln!("\nUnmapping {}...", drive);
+    }
+    let _ = std::process::Command::new("net")
+        .args(["use", &drive, "/delete", "/yes"])
+        .output();
+
+    // Disconnect provider
+    let mut p = state.provider.lock().await;
+    let _ = p.disconnect().await;
+
+    if !quiet {
+        eprintln!("Unmounted {} successfully", drive);
+    }
+    0
+}
+
+// ── Daemon + Jobs ────────────────────────────────────────────────
+
+fn daemon_config_dir() -> PathBuf {
+    let dir = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("aeroftp");
+    let _ = std::fs::create_dir_all(&dir);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700));
+    }
+    dir
+}
+
+fn daemon_pid_path() -> PathBuf {
+    daemon_config_dir().join("daemon.pid")
+}
+fn daemon_db_path() -> PathBuf {
+    daemon_config_dir().join("jobs.db")
+}
+fn _daemon_log_path() -> PathBuf {
+    daemon_config_dir().join("daemon.log")
+}
+
+fn daemon_read_pid() -> Option<u32> {
+    std::fs::read_to_string(daemon_pid_path())
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn daemon_is_running() -> bool {
+    if let Some(pid) = daemon_read_pid() {
+        // Check if process exists
+        #[cfg(unix)]
+        {
+            let result = unsafe { libc::kill(pid as i32, 0) };
+            result == 0
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = pid;
+            false
+        }
+    } else {
+        false
+    }
+}
+
+fn daemon_addr() -> String {
+    // Read addr from pid file's sibling
+    let addr_path = daemon_config_dir().join("daemon.addr");
+    std::fs::read_to_string(addr_path)
+        .unwrap_or_else(|_| "127.0.0.1:14320".to_string())
+        .trim()
+        .to_string()
+}
+
+fn daemon_token_path() -> PathBuf {
+    daemon_config_dir().join("daemon.token")
+}
+
+fn daemon_auth_token() -> Option<String> {
+    std::fs::read_to_string(daemon_token_path())
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+fn write_daemon_auth_token(token: &str) -> Result<(), String> {
+    let path = daemon_token_path();
     #[cfg(unix)]
     {
         use std::io::Write;
@@ -19516,6 +23471,178 @@ fn jobs_update_status(
     );
 }
 
+// ── Named sync jobs ──────────────────────────────────────────────
+
+/// Run one named sync job as an `aeroftp-cli sync` subprocess and append
+/// the result to the job's history.
+async fn run_named_sync_job(name: &str, trigger: SyncJobTrigger) -> Result<SyncJobRun, String> {
+    let job = load_sync_jobs()?
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("Sync job not found: {}", name))?;
+    let exe = std::env::current_exe().map_err(|e| format!("Cannot locate aeroftp-cli: {}", e))?;
+
+    let started_at = chrono::Utc::now();
+    let output = tokio::process::Command::new(exe)
+        .args(job.cli_args())
+        .env("AEROFTP_SYNC_JOB", &job.name)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .output()
+        .await;
+    let run = match output {
+        Ok(out) => {
+            let success = out.status.success();
+            // The last stderr line is the sync's own error summary
+            let message = if success {
+                None
+            } else {
+                String::from_utf8_lossy(&out.stderr)
+                    .lines()
+                    .rev()
+                    .map(str::trim)
+                    .find(|l| !l.is_empty())
+                    .map(str::to_string)
+            };
+            SyncJobRun {
+                started_at,
+                finished_at: Some(chrono::Utc::now()),
+                trigger,
+                outcome: if success {
+                    SyncJobOutcome::Success
+                } else {
+                    SyncJobOutcome::Failed
+                },
+                exit_code: out.status.code(),
+                message,
+            }
+        }
+        Err(e) => SyncJobRun {
+            started_at,
+            finished_at: Some(chrono::Utc::now()),
+            trigger,
+            outcome: SyncJobOutcome::Failed,
+            exit_code: None,
+            message: Some(format!("Cannot start sync: {}", e)),
+        },
+    };
+
+    let recorded = run.clone();
+    update_sync_jobs(|registry| {
+        if let Some(job) = registry.get_mut(name) {
+            job.record_run(recorded);
+        }
+        Ok(())
+    })?;
+    Ok(run)
+}
+
+/// Start a named sync job in the background unless it is already running.
+/// Returns `false` when the previous run has not finished yet.
+fn start_named_sync_job(
+    name: String,
+    trigger: SyncJobTrigger,
+    running: RunningSyncJobs,
+    quiet: bool,
+) -> bool {
+    if !running.lock().unwrap().insert(name.clone()) {
+        return false;
+    }
+    if !quiet {
+        eprintln!("Sync job '{}' started", name);
+    }
+    tokio::spawn(async move {
+        let result = run_named_sync_job(&name, trigger).await;
+        running.lock().unwrap().remove(&name);
+        if quiet {
+            return;
+        }
+        match result {
+            Ok(run) if run.outcome == SyncJobOutcome::Success => {
+                eprintln!("Sync job '{}' finished", name);
+            }
+            Ok(run) => eprintln!(
+                "Sync job '{}' failed (exit {}): {}",
+                name,
+                run.exit_code.map_or("-".to_string(), |c| c.to_string()),
+                run.message.as_deref().unwrap_or("no details")
+            ),
+            Err(e) => eprintln!("Sync job '{}': {}", name, e),
+        }
+    });
+    true
+}
+
+/// Daemon loop that starts named sync jobs when their cron fire time
+/// arrives. A fire time that finds the previous run still active is
+/// recorded as skipped instead of starting a second copy.
+async fn sync_job_scheduler_loop(running: RunningSyncJobs, quiet: bool) {
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(30));
+    loop {
+        ticker.tick().await;
+        let now = chrono::Local::now();
+        let due = match load_sync_jobs() {
+            Ok(registry) => registry.due_jobs(&now),
+            Err(e) => {
+                if !quiet {
+                    eprintln!("Sync scheduler: {}", e);
+                }
+                continue;
+            }
+        };
+        if due.is_empty() {
+            continue;
+        }
+
+        let busy: Vec<String> = {
+            let running = running.lock().unwrap();
+            due.iter()
+                .filter(|name| running.contains(*name))
+                .cloned()
+                .collect()
+        };
+        let marked = update_sync_jobs(|registry| {
+            for name in &due {
+                let Some(job) = registry.get_mut(name) else {
+                    continue;
+                };
+                job.last_scheduled = Some(now.with_timezone(&chrono::Utc));
+                if busy.contains(name) {
+                    job.record_run(SyncJobRun {
+                        started_at: chrono::Utc::now(),
+                        finished_at: None,
+                        trigger: SyncJobTrigger::Schedule,
+                        outcome: SyncJobOutcome::SkippedOverlap,
+                        exit_code: None,
+                        message: Some("Previous run still in progress".to_string()),
+                    });
+                }
+            }
+            Ok(())
+        });
+        if let Err(e) = marked {
+            if !quiet {
+                eprintln!("Sync scheduler: {}", e);
+            }
+            continue;
+        }
+
+        for name in due {
+            if busy.contains(&name) {
+                if !quiet {
+                    eprintln!(
+                        "Sync job '{}' skipped: previous run still in progress",
+                        name
+                    );
+                }
+                continue;
+            }
+            start_named_sync_job(name, SyncJobTrigger::Schedule, running.clone(), quiet);
+        }
+    }
+}
+
 // ── Daemon HTTP API ──────────────────────────────────────────────
 
 async fn daemon_health_handler(
@@ -19534,11 +23661,13 @@ async fn daemon_health_handler(
     let jobs = jobs_list_all(&state.conn.lock().unwrap());
     let running = jobs.iter().filter(|j| j.status == "running").count();
     let queued = jobs.iter().filter(|j| j.status == "queued").count();
+    let running_sync_jobs = state.running_sync_jobs.lock().unwrap().len();
     axum::Json(serde_json::json!({
         "status": "ok",
         "pid": std::process::id(),
         "running_jobs": running,
         "queued_jobs": queued,
+        "running_sync_jobs": running_sync_jobs,
     }))
     .into_response()
 }
@@ -19638,6 +23767,53 @@ async fn daemon_job_cancel_handler(
     axum::Json(serde_json::json!({"status": "cancelled", "id": id})).into_response()
 }
 
+async fn daemon_schedule_run_handler(
+    State(state): State<DaemonApiState>,
+    headers: HeaderMap,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Response {
+    if let Some(response) = ensure_request_authorized(
+        &headers,
+        Some(state.auth_token.as_str()),
+        "AeroFTP daemon",
+        "Daemon authentication required. Use the daemon token as a Bearer token or as the Basic-auth password.",
+    ) {
+        return response;
+    }
+
+    let (status, body) = match load_sync_jobs() {
+        Ok(registry) if registry.get(&name).is_none() => (
+            StatusCode::NOT_FOUND,
+            serde_json::json!({"error": format!("Sync job not found: {}", name)}),
+        ),
+        Ok(_) => {
+            if start_named_sync_job(
+                name.clone(),
+                SyncJobTrigger::Manual,
+                state.running_sync_jobs.clone(),
+                state.quiet,
+            ) {
+                (
+                    StatusCode::OK,
+                    serde_json::json!({"status": "started", "name": name}),
+                )
+            } else {
+                (
+                    StatusCode::CONFLICT,
+                    serde_json::json!({"error": format!("Sync job '{}' is already running", name)}),
+                )
+            }
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            serde_json::json!({"error": e}),
+        ),
+    };
+    let mut response = axum::Json(body).into_response();
+    *response.status_mut() = status;
+    response
+}
+
 async fn cmd_daemon_start(
     addr_str: &str,
     allow_remote_bind: bool,
@@ -19717,9 +23893,14 @@ async fn cmd_daemon_start(
     }
 
     // Build HTTP API
+    let running_sync_jobs = RunningSyncJobs::default();
+    tokio::spawn(sync_job_scheduler_loop(running_sync_jobs.clone(), quiet));
+
     let state = DaemonApiState {
         conn,
         auth_token: daemon_token,
+        running_sync_jobs,
+        quiet,
     };
 
     let app = Router::new()
@@ -19732,6 +23913,10 @@ async fn cmd_daemon_start(
             "/api/jobs/{id}",
             get(daemon_job_status_handler).delete(daemon_job_cancel_handler),
         )
+        .route(
+            "/api/schedule/{name}/run",
+            post(daemon_schedule_run_handler),
+        )
         .with_state(state);
 
     let listener = match tokio::net::TcpListener::bind(bind_addr).await {
@@ -19975,11 +24160,255 @@ async fn cmd_jobs_cancel(id: &str, format: OutputFormat) -> i32 {
                     println!("Job {} cancelled.", id);
                 }
             }
-            0
+            0
+        }
+        Err(e) => {
+            print_error(format, &format!("Cannot reach daemon: {}", e), 1);
+            1
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_jobs_schedule_add(
+    name: &str,
+    local: &str,
+    remote: &str,
+    cron: &str,
+    url: Option<&str>,
+    window: Option<&str>,
+    days: &str,
+    disabled: bool,
+    options: &[String],
+    cli: &Cli,
+    format: OutputFormat,
+) -> i32 {
+    if url.is_none() && cli.profile.is_none() {
+        print_error(format, "Named sync jobs need --url or --profile", 5);
+        return 5;
+    }
+    let time_window = match window.map(|w| TimeWindow::parse(w, days)).transpose() {
+        Ok(w) => w,
+        Err(e) => {
+            print_error(format, &e, 5);
+            return 5;
+        }
+    };
+    // The daemon runs jobs from its own working directory
+    let local = match std::env::current_dir() {
+        Ok(cwd) if Path::new(local).is_relative() => cwd.join(local).display().to_string(),
+        _ => local.to_string(),
+    };
+
+    let job = SyncJob {
+        name: name.to_string(),
+        local,
+        remote: remote.to_string(),
+        url: url.map(str::to_string),
+        profile: cli.profile.clone(),
+        options: options.to_vec(),
+        cron: cron.to_string(),
+        time_window,
+        enabled: !disabled,
+        created_at: chrono::Utc::now(),
+        last_scheduled: None,
+        history: Vec::new(),
+    };
+    let next_run = job.next_fire_after(&chrono::Local::now());
+    if let Err(e) = update_sync_jobs(|registry| registry.add(job.clone())) {
+        print_error(format, &e, 5);
+        return 5;
+    }
+
+    match format {
+        OutputFormat::Text => {
+            println!("Sync job '{}' added: {}", name, job.cli_args().join(" "));
+            match next_run {
+                Some(next) if job.enabled => {
+                    println!("  Next run: {}", next.format("%Y-%m-%d %H:%M"))
+                }
+                Some(_) => println!("  Disabled: not scheduled"),
+                None => println!("  Warning: the cron expression never fires inside the window"),
+            }
+            if !daemon_is_running() {
+                println!("  Start the daemon to run it on schedule: aeroftp-cli daemon start");
+            }
+        }
+        OutputFormat::Json => print_json(&serde_json::json!({
+            "status": "ok",
+            "job": job,
+            "next_run": next_run.map(|n| n.to_rfc3339()),
+        })),
+    }
+    0
+}
+
+fn cmd_jobs_schedule_list(format: OutputFormat) -> i32 {
+    let registry = match load_sync_jobs() {
+        Ok(r) => r,
+        Err(e) => {
+            print_error(format, &e, 5);
+            return 5;
+        }
+    };
+    let now = chrono::Local::now();
+    let next_run = |job: &SyncJob| job.enabled.then(|| job.next_fire_after(&now)).flatten();
+
+    match format {
+        OutputFormat::Json => {
+            let jobs: Vec<serde_json::Value> = registry
+                .jobs
+                .iter()
+                .map(|job| {
+                    let mut value = serde_json::json!(job);
+                    value["next_run"] = serde_json::json!(next_run(job).map(|n| n.to_rfc3339()));
+                    value
+                })
+                .collect();
+            print_json(&serde_json::json!({ "jobs": jobs }));
+        }
+        OutputFormat::Text => {
+            if registry.jobs.is_empty() {
+                println!("No sync jobs.");
+                return 0;
+            }
+            println!(
+                "{:<16} {:<18} {:<17} {:<24} Sync",
+                "Name", "Cron", "Next run", "Last result"
+            );
+            println!("{}", "-".repeat(100));
+            for job in &registry.jobs {
+                let next = match next_run(job) {
+                    Some(n) => n.format("%Y-%m-%d %H:%M").to_string(),
+                    None if !job.enabled => "disabled".to_string(),
+                    None => "never".to_string(),
+                };
+                let last = match job.last_run() {
+                    Some(run) => format!(
+                        "{} {}",
+                        match run.outcome {
+                            SyncJobOutcome::Success => "ok",
+                            SyncJobOutcome::Failed => "FAILED",
+                            SyncJobOutcome::SkippedOverlap => "skipped",
+                        },
+                        run.started_at
+                            .with_timezone(&chrono::Local)
+                            .format("%m-%d %H:%M")
+                    ),
+                    None => "-".to_string(),
+                };
+                println!(
+                    "{:<16} {:<18} {:<17} {:<24} {} -> {}",
+                    job.name, job.cron, next, last, job.local, job.remote
+                );
+            }
+        }
+    }
+    0
+}
+
+fn cmd_jobs_schedule_remove(name: &str, format: OutputFormat) -> i32 {
+    match update_sync_jobs(|registry| Ok(registry.remove(name))) {
+        Ok(true) => {
+            match format {
+                OutputFormat::Text => println!("Sync job '{}' removed.", name),
+                OutputFormat::Json => print_json(&serde_json::json!({
+                    "status": "ok",
+                    "name": name,
+                    "removed": true,
+                })),
+            }
+            0
+        }
+        Ok(false) => {
+            print_error(format, &format!("Sync job not found: {}", name), 2);
+            2
+        }
+        Err(e) => {
+            print_error(format, &e, 5);
+            5
+        }
+    }
+}
+
+async fn cmd_jobs_schedule_run_now(name: &str, format: OutputFormat) -> i32 {
+    // Through the daemon, so the run shares its overlap prevention
+    if daemon_is_running() {
+        let url = format!("http://{}/api/schedule/{}/run", daemon_addr(), name);
+        return match daemon_request(reqwest::Client::new().post(&url))
+            .send()
+            .await
+        {
+            Ok(resp) => {
+                let status = resp.status();
+                if status == reqwest::StatusCode::UNAUTHORIZED {
+                    print_error(format, &daemon_auth_failure_message(), 6);
+                    return 6;
+                }
+                let json = resp
+                    .json::<serde_json::Value>()
+                    .await
+                    .unwrap_or(serde_json::Value::Null);
+                let error = json.get("error").and_then(|v| v.as_str());
+                match (status, error) {
+                    (reqwest::StatusCode::NOT_FOUND, Some(e)) => {
+                        print_error(format, e, 2);
+                        2
+                    }
+                    (_, Some(e)) => {
+                        print_error(format, e, 4);
+                        4
+                    }
+                    _ => {
+                        match format {
+                            OutputFormat::Json => print_json(&json),
+                            OutputFormat::Text => println!(
+                                "Sync job '{}' started by the daemon. Check results with: aeroftp-cli jobs schedule list",
+                                name
+                            ),
+                        }
+                        0
+                    }
+                }
+            }
+            Err(e) => {
+                print_error(format, &format!("Cannot reach daemon: {}", e), 1);
+                1
+            }
+        };
+    }
+
+    match run_named_sync_job(name, SyncJobTrigger::Manual).await {
+        Ok(run) => {
+            match format {
+                OutputFormat::Json => print_json(&serde_json::json!({
+                    "status": "ok",
+                    "name": name,
+                    "run": run,
+                })),
+                OutputFormat::Text => match run.outcome {
+                    SyncJobOutcome::Success => println!("Sync job '{}' finished.", name),
+                    _ => eprintln!(
+                        "Sync job '{}' failed: {}",
+                        name,
+                        run.message.as_deref().unwrap_or("no details")
+                    ),
+                },
+            }
+            if run.outcome == SyncJobOutcome::Success {
+                0
+            } else {
+                run.exit_code.filter(|c| *c != 0).unwrap_or(4)
+            }
         }
         Err(e) => {
-            print_error(format, &format!("Cannot reach daemon: {}", e), 1);
-            1
+            let code = if e.starts_with("Sync job not found") {
+                2
+            } else {
+                5
+            };
+            print_error(format, &e, code);
+            code
         }
     }
 }
@@ -20856,6 +25285,7 @@ async fn cmd_put_glob(
                 errors,
                 elapsed_secs: elapsed.as_secs_f64(),
                 plan: Vec::new(),
+                deferred: Vec::new(),
             });
         }
     }
@@ -21038,6 +25468,169 @@ fn start_watch_watcher(
     }
 }
 
+/// `versions list|restore|cleanup`: operate on the remote `.aeroversions/`
+/// folder written by `sync --remote-versioning`.
+async fn cmd_versions(
+    url: &str,
+    remote: &str,
+    command: &VersionsCommands,
+    cli: &Cli,
+    format: OutputFormat,
+) -> i32 {
+    let strategy = match command {
+        VersionsCommands::Cleanup { strategy, .. } => {
+            match VersioningStrategy::from_name(strategy) {
+                Some(s) if s != VersioningStrategy::Disabled => s,
+                _ => {
+                    print_error(
+                        format,
+                        &format!(
+                            "Invalid --strategy '{}'. Expected one of: trash_can[:days], simple[:copies], staggered",
+                            strategy
+                        ),
+                        5,
+                    );
+                    return 5;
+                }
+            }
+        }
+        _ => VersioningStrategy::default(),
+    };
+    // Remote-only: the local sync root is never touched.
+    let versioning = SyncVersioning::new(Path::new(""), strategy);
+
+    let (mut provider, initial_path) = match create_and_connect(url, cli, format).await {
+        Ok(v) => v,
+        Err(code) => return code,
+    };
+    let remote = resolve_cli_remote_path(&initial_path, remote);
+
+    let code = match command {
+        VersionsCommands::List { file, .. } => {
+            let listed = match file {
+                Some(rel) => {
+                    versioning
+                        .list_remote_versions(provider.as_mut(), &remote, rel)
+                        .await
+                }
+                None => {
+                    versioning
+                        .list_all_remote_versions(provider.as_mut(), &remote)
+                        .await
+                }
+            };
+            match listed {
+                Ok(versions) => {
+                    match format {
+                        OutputFormat::Text => {
+                            for v in &versions {
+                                println!(
+                                    "{}  {:>10}  {}",
+                                    v.archived_at,
+                                    format_size(v.size),
+                                    v.original_relative
+                                );
+                            }
+                            if !cli.quiet {
+                                eprintln!("\n{} archived version(s)", versions.len());
+                            }
+                        }
+                        OutputFormat::Json => {
+                            print_json(&serde_json::json!({
+                                "remote": remote,
+                                "count": versions.len(),
+                                "versions": versions,
+                            }));
+                        }
+                    }
+                    0
+                }
+                Err(e) => {
+                    print_error(format, &format!("List versions failed: {}", e), 4);
+                    4
+                }
+            }
+        }
+        VersionsCommands::Restore { file, version, .. } => {
+            let chosen = match versioning
+                .list_remote_versions(provider.as_mut(), &remote, file)
+                .await
+            {
+                Ok(versions) => versions
+                    .into_iter()
+                    .find(|v| version.as_deref().is_none_or(|ts| v.archived_at == ts)),
+                Err(e) => {
+                    print_error(format, &format!("List versions failed: {}", e), 4);
+                    let _ = provider.disconnect().await;
+                    return 4;
+                }
+            };
+            match chosen {
+                None => {
+                    print_error(
+                        format,
+                        &format!("No archived version found for {}", file),
+                        2,
+                    );
+                    2
+                }
+                Some(v) => match versioning
+                    .restore_remote(provider.as_mut(), &remote, &v)
+                    .await
+                {
+                    Ok(()) => {
+                        match format {
+                            OutputFormat::Text => {
+                                if !cli.quiet {
+                                    println!("Restored {} from version {}", file, v.archived_at);
+                                }
+                            }
+                            OutputFormat::Json => {
+                                print_json(&serde_json::json!({
+                                    "restored": file,
+                                    "archived_at": v.archived_at,
+                                    "from": v.archive_path,
+                                }));
+                            }
+                        }
+                        0
+                    }
+                    Err(e) => {
+                        print_error(format, &format!("Restore failed: {}", e), 4);
+                        4
+                    }
+                },
+            }
+        }
+        VersionsCommands::Cleanup { .. } => {
+            match versioning.cleanup_remote(provider.as_mut(), &remote).await {
+                Ok(stats) => {
+                    match format {
+                        OutputFormat::Text => {
+                            if !cli.quiet {
+                                println!(
+                                    "Removed {} archived version(s), freed {}",
+                                    stats.deleted_count,
+                                    format_size(stats.freed_bytes)
+                                );
+                            }
+                        }
+                        OutputFormat::Json => print_json(&serde_json::json!(stats)),
+                    }
+                    0
+                }
+                Err(e) => {
+                    print_error(format, &format!("Cleanup failed: {}", e), 4);
+                    4
+                }
+            }
+        }
+    };
+
+    let _ = provider.disconnect().await;
+    code
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn cmd_sync_watch(
     url: &str,
@@ -21106,9 +25699,21 @@ async fn cmd_sync_watch(
         };
 
     // Bridge std mpsc to tokio mpsc so we can use tokio::select!
+    // --track-renames hashes through the checksum cache, so changed paths
+    // drop their cached digests on the way.
+    let checksum_cache = if track_renames {
+        ChecksumCache::shared_for_root(local_path)
+    } else {
+        None
+    };
     let (async_tx, mut async_rx) = tokio::sync::mpsc::channel::<Vec<std::path::PathBuf>>(64);
     std::thread::spawn(move || {
         while let Ok(paths) = std_rx.recv() {
+            if let Some(cache) = &checksum_cache {
+                for path in &paths {
+                    cache.invalidate(path);
+                }
+            }
             if async_tx.blocking_send(paths).is_err() {
                 break;
             }
@@ -21227,405 +25832,1022 @@ async fn cmd_sync_watch(
                 }
             }
 
-            // Drain any watcher events accumulated during the sync
-            while async_rx.try_recv().is_ok() {}
+            // Drain any watcher events accumulated during the sync
+            while async_rx.try_recv().is_ok() {}
+
+            stats.exit_code
+        }};
+    }
+
+    // Pre-compile exclude matchers for incremental scan
+    let exclude_matchers: Vec<globset::GlobMatcher> = exclude
+        .iter()
+        .filter_map(|pat| globset::Glob::new(pat).ok().map(|g| g.compile_matcher()))
+        .collect();
+
+    // Incremental scan is only safe when:
+    // - direction is not download-only (local scan is irrelevant)
+    // - track_renames is off (needs full file set for hash matching)
+    let use_incremental = direction != "download" && !track_renames;
+
+    // Local snapshot: populated after each full scan for incremental merging
+    let mut local_snapshot: std::collections::HashMap<String, (u64, Option<String>)> =
+        std::collections::HashMap::new();
+
+    // Helper: build snapshot from a full local walkdir scan
+    let build_snapshot =
+        |local_dir: &str| -> std::collections::HashMap<String, (u64, Option<String>)> {
+            let mut snap = std::collections::HashMap::new();
+            let walker = walkdir::WalkDir::new(local_dir)
+                .follow_links(false)
+                .max_depth(100);
+            for entry in walker {
+                let entry = match entry {
+                    Ok(e) => e,
+                    Err(_) => continue,
+                };
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let relative = match entry.path().strip_prefix(local_dir) {
+                    Ok(r) => r.to_string_lossy().replace('\\', "/"),
+                    Err(_) => continue,
+                };
+                if relative.is_empty() || relative == BISYNC_SNAPSHOT_FILE {
+                    continue;
+                }
+                if let Ok(meta) = entry.metadata() {
+                    let size = meta.len();
+                    let mtime = meta.modified().ok().map(|t| {
+                        let dt: chrono::DateTime<chrono::Utc> = t.into();
+                        dt.format("%Y-%m-%dT%H:%M:%S").to_string()
+                    });
+                    snap.insert(relative, (size, mtime));
+                }
+            }
+            snap
+        };
+
+    // Initial sync
+    if !watch_no_initial {
+        let code = run_sync_cycle!("initial");
+        if cancelled.load(Ordering::SeqCst) {
+            return code;
+        }
+    }
+
+    // Build initial snapshot after first sync (or immediately if --watch-no-initial)
+    if use_incremental {
+        local_snapshot = build_snapshot(local);
+    }
+
+    // Watch loop
+    loop {
+        tokio::select! {
+            biased; // prioritize ctrl_c
+
+            _ = shutdown_tick.tick() => {
+                if cancelled.load(Ordering::SeqCst) {
+                    if syncing.load(Ordering::SeqCst) {
+                        continue;
+                    }
+                    if !quiet {
+                        eprintln!("\nWatch mode stopped. {} sync cycles completed.", cycle_count);
+                    }
+                    return 0;
+                }
+            }
+
+            Some(changed_paths) = async_rx.recv() => {
+                // Suppress if sync in progress
+                if syncing.load(Ordering::SeqCst) {
+                    while async_rx.try_recv().is_ok() {}
+                    continue;
+                }
+                // Cooldown check
+                if last_sync_completed.elapsed() < cooldown_dur {
+                    while async_rx.try_recv().is_ok() {}
+                    continue;
+                }
+                let path_count = changed_paths.len();
+                let trigger = format!("watcher: {} paths", path_count);
+
+                if use_incremental {
+                    let entries = incremental_local_scan(
+                        local_path,
+                        &changed_paths,
+                        &local_snapshot,
+                        &exclude_matchers,
+                    );
+                    // Update snapshot with changes
+                    for (ref rel, size, ref mtime) in &entries {
+                        local_snapshot.insert(rel.clone(), (*size, mtime.clone()));
+                    }
+                    // Remove deleted files from snapshot
+                    for p in &changed_paths {
+                        if let Ok(rel) = p.strip_prefix(local_path) {
+                            let rel_str = rel.to_string_lossy().replace('\\', "/");
+                            if !entries.iter().any(|(r, _, _)| r == &rel_str) {
+                                local_snapshot.remove(&rel_str);
+                            }
+                        }
+                    }
+                    run_sync_cycle!(trigger.as_str(), Some(entries));
+                    if cancelled.load(Ordering::SeqCst) {
+                        if !quiet {
+                            eprintln!("\nWatch mode stopped. {} sync cycles completed.", cycle_count);
+                        }
+                        return 0;
+                    }
+                } else {
+                    run_sync_cycle!(trigger.as_str());
+                    if cancelled.load(Ordering::SeqCst) {
+                        if !quiet {
+                            eprintln!("\nWatch mode stopped. {} sync cycles completed.", cycle_count);
+                        }
+                        return 0;
+                    }
+                }
+            }
+
+            _ = rescan_tick.tick() => {
+                if syncing.load(Ordering::SeqCst) {
+                    continue;
+                }
+                run_sync_cycle!("rescan");
+                // Rebuild snapshot after full rescan
+                if use_incremental {
+                    local_snapshot = build_snapshot(local);
+                }
+                if cancelled.load(Ordering::SeqCst) {
+                    if !quiet {
+                        eprintln!("\nWatch mode stopped. {} sync cycles completed.", cycle_count);
+                    }
+                    return 0;
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn cmd_sync_doctor(
+    url: &str,
+    local: &str,
+    remote: &str,
+    direction: &str,
+    delete: bool,
+    exclude: &[String],
+    track_renames: bool,
+    conflict_mode: &str,
+    resync: bool,
+    checksum: bool,
+    cli: &Cli,
+    format: OutputFormat,
+) -> i32 {
+    if !is_valid_sync_direction(direction) {
+        print_error(
+            format,
+            &format!(
+                "Invalid sync direction '{}'. Expected one of: upload, download, both",
+                direction
+            ),
+            5,
+        );
+        return 5;
+    }
+    let name_collision = match cli.name_collision.as_deref().map(CollisionPolicy::parse) {
+        None => CollisionPolicy::default(),
+        Some(Ok(policy)) => policy,
+        Some(Err(e)) => {
+            print_error(format, &e, 5);
+            return 5;
+        }
+    };
+
+    let doctor_cfg = if let Some(profile_name) = cli.profile.as_deref() {
+        match profile_to_provider_config(profile_name, cli, format) {
+            Ok((cfg, _)) => Some(cfg),
+            Err(code) => return code,
+        }
+    } else {
+        None
+    };
 
-            stats.exit_code
-        }};
+    let (mut provider, initial_path) = match create_and_connect(url, cli, format).await {
+        Ok(v) => v,
+        Err(code) => return code,
+    };
+    let remote = resolve_cli_remote_path(&initial_path, remote);
+
+    let local_dir = Path::new(local);
+    if !local_dir.is_dir() {
+        print_error(
+            format,
+            &format!("Local path is not a directory: {}", local),
+            5,
+        );
+        let _ = provider.disconnect().await;
+        return 5;
     }
 
-    // Pre-compile exclude matchers for incremental scan
     let exclude_matchers: Vec<globset::GlobMatcher> = exclude
         .iter()
         .filter_map(|pat| globset::Glob::new(pat).ok().map(|g| g.compile_matcher()))
         .collect();
 
-    // Incremental scan is only safe when:
-    // - direction is not download-only (local scan is irrelevant)
-    // - track_renames is off (needs full file set for hash matching)
-    let use_incremental = direction != "download" && !track_renames;
-
-    // Local snapshot: populated after each full scan for incremental merging
-    let mut local_snapshot: std::collections::HashMap<String, (u64, Option<String>)> =
-        std::collections::HashMap::new();
+    let mut local_paths: Vec<String> = Vec::new();
+    let mut local_bytes = 0u64;
+    for entry in walkdir::WalkDir::new(local)
+        .follow_links(false)
+        .max_depth(100)
+    {
+        let Ok(entry) = entry else { continue };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(local)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        if relative.is_empty() || relative == BISYNC_SNAPSHOT_FILE {
+            continue;
+        }
+        let fname = entry.file_name().to_string_lossy();
+        let fname_ref: &str = fname.as_ref();
+        if exclude_matchers
+            .iter()
+            .any(|m| m.is_match(&relative) || m.is_match(fname_ref))
+        {
+            continue;
+        }
+        local_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        local_paths.push(relative);
+    }
+    let local_files = local_paths.len();
 
-    // Helper: build snapshot from a full local walkdir scan
-    let build_snapshot =
-        |local_dir: &str| -> std::collections::HashMap<String, (u64, Option<String>)> {
-            let mut snap = std::collections::HashMap::new();
-            let walker = walkdir::WalkDir::new(local_dir)
-                .follow_links(false)
-                .max_depth(100);
-            for entry in walker {
-                let entry = match entry {
-                    Ok(e) => e,
-                    Err(_) => continue,
-                };
-                if !entry.file_type().is_file() {
-                    continue;
+    let remote_root_ok = provider.list(&remote).await.is_ok();
+    let mut remote_paths: Vec<String> = Vec::new();
+    let mut remote_bytes = 0u64;
+    if remote_root_ok {
+        let mut queue: Vec<(String, usize)> = vec![(remote.to_string(), 0)];
+        while let Some((dir, depth)) = queue.pop() {
+            if depth >= MAX_SCAN_DEPTH || remote_paths.len() >= MAX_SCAN_ENTRIES {
+                break;
+            }
+            if let Ok(entries) = provider.list(&dir).await {
+                for e in entries {
+                    if e.is_dir {
+                        queue.push((e.path.clone(), depth + 1));
+                    } else {
+                        let relative = e
+                            .path
+                            .strip_prefix(&remote)
+                            .unwrap_or(&e.path)
+                            .trim_start_matches('/')
+                            .to_string();
+                        if relative.is_empty() || relative == BISYNC_SNAPSHOT_FILE {
+                            continue;
+                        }
+                        if exclude_matchers
+                            .iter()
+                            .any(|m| m.is_match(&relative) || m.is_match(&e.name))
+                        {
+                            continue;
+                        }
+                        remote_bytes += e.size;
+                        remote_paths.push(relative);
+                    }
                 }
-                let relative = match entry.path().strip_prefix(local_dir) {
-                    Ok(r) => r.to_string_lossy().replace('\\', "/"),
-                    Err(_) => continue,
-                };
-                if relative.is_empty() || relative == BISYNC_SNAPSHOT_FILE {
-                    continue;
+            }
+        }
+    }
+    let remote_files = remote_paths.len();
+
+    // Paths that would collapse into one file on the destination: case
+    // variants on a case-insensitive side, NFC/NFD variants anywhere.
+    let local_case_insensitive = local_fs_case_insensitive();
+    let remote_case_insensitive = provider.is_case_insensitive();
+    let collisions = plan_names(
+        local_paths.iter().map(String::as_str),
+        remote_paths.iter().map(String::as_str),
+        NameOptions {
+            local_case_insensitive,
+            remote_case_insensitive,
+            policy: CollisionPolicy::Skip,
+            uploads: direction != "download",
+            downloads: direction != "upload",
+        },
+    )
+    .map(|plan| plan.collisions)
+    .unwrap_or_default();
+
+    let mut checks = vec![
+        serde_json::json!({"name": "local_path_exists", "ok": true, "path": local}),
+        serde_json::json!({"name": "remote_path_reachable", "ok": remote_root_ok, "path": remote}),
+    ];
+    if !exclude.is_empty() {
+        checks.push(
+            serde_json::json!({"name": "exclude_patterns", "ok": true, "count": exclude.len()}),
+        );
+    }
+    checks.push(serde_json::json!({
+        "name": "name_collisions",
+        "ok": collisions.is_empty(),
+        "count": collisions.len(),
+        "local_case_insensitive": local_case_insensitive,
+        "remote_case_insensitive": remote_case_insensitive,
+        "policy": name_collision,
+        "collisions": collisions.iter().take(50).collect::<Vec<_>>(),
+    }));
+
+    let mut risks = Vec::new();
+    if let Some(cfg) = doctor_cfg.as_ref() {
+        push_s3_doctor_checks(&mut checks, &mut risks, "remote", cfg);
+    }
+    if delete {
+        risks.push("delete is enabled; sync may remove orphaned files".to_string());
+    }
+    if direction == "both" {
+        risks.push(format!(
+            "bidirectional sync can resolve conflicts automatically using conflict_mode={}",
+            conflict_mode
+        ));
+    }
+    if resync {
+        risks.push("resync is enabled; previous bisync snapshot will be ignored".to_string());
+    }
+    if !track_renames && direction == "both" {
+        risks.push("track-renames is disabled; moved files may be recopied".to_string());
+    }
+    if checksum {
+        risks
+            .push("checksum is enabled; later verification may be slower but stricter".to_string());
+    }
+    if !remote_root_ok {
+        risks.push("remote path could not be listed".to_string());
+    }
+    if !collisions.is_empty() {
+        risks.push(format!(
+            "{} group(s) of paths differ only by case or Unicode normalization and would collapse into one file; sync will {} them (--name-collision)",
+            collisions.len(),
+            match name_collision {
+                CollisionPolicy::Rename => "rename",
+                CollisionPolicy::Skip => "skip",
+                CollisionPolicy::Fail => "abort on",
+            }
+        ));
+    }
+
+    let suggested_next_command =
+        format!(
+        "aeroftp-cli sync --profile \"{}\" \"{}\" \"{}\" --direction {} --dry-run --json{}{}{}{}{}",
+        profile_or_placeholder(cli),
+        shell_double_quote(local),
+        shell_double_quote(&remote),
+        shell_double_quote(direction),
+        if delete { " --delete" } else { "" },
+        if track_renames { " --track-renames" } else { "" },
+        if resync { " --resync" } else { "" },
+        match cli.name_collision.as_deref() {
+            Some(policy) => format!(" --name-collision {}", shell_double_quote(policy)),
+            None => String::new(),
+        },
+        if exclude.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " {}",
+                exclude
+                    .iter()
+                    .map(|pattern| format!("--exclude \"{}\"", shell_double_quote(pattern)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )
+        }
+    );
+
+    let result = CliDoctorResult {
+        status: if remote_root_ok && collisions.is_empty() {
+            "ok"
+        } else {
+            "attention"
+        },
+        doctor: "sync".to_string(),
+        summary: serde_json::json!({
+            "direction": direction,
+            "local_files": local_files,
+            "local_bytes": local_bytes,
+            "remote_files": remote_files,
+            "remote_bytes": remote_bytes,
+            "delete": delete,
+            "track_renames": track_renames,
+            "conflict_mode": conflict_mode,
+            "resync": resync,
+            "name_collisions": collisions.len(),
+        }),
+        checks,
+        risks,
+        suggested_next_command,
+    };
+
+    match format {
+        OutputFormat::Json => print_json(&result),
+        OutputFormat::Text => {
+            println!("Sync doctor");
+            println!(
+                "  Local:  {} file(s), {}",
+                local_files,
+                format_size(local_bytes)
+            );
+            println!(
+                "  Remote: {} file(s), {}",
+                remote_files,
+                format_size(remote_bytes)
+            );
+            println!("  Direction: {}", direction);
+            if !result.risks.is_empty() {
+                println!("  Risks:");
+                for risk in &result.risks {
+                    println!("    - {}", risk);
                 }
-                if let Ok(meta) = entry.metadata() {
-                    let size = meta.len();
-                    let mtime = meta.modified().ok().map(|t| {
-                        let dt: chrono::DateTime<chrono::Utc> = t.into();
-                        dt.format("%Y-%m-%dT%H:%M:%S").to_string()
-                    });
-                    snap.insert(relative, (size, mtime));
+            }
+            if !collisions.is_empty() {
+                println!("  Name collisions:");
+                for collision in &collisions {
+                    println!("    - {}", collision.paths.join(" | "));
                 }
             }
-            snap
-        };
-
-    // Initial sync
-    if !watch_no_initial {
-        let code = run_sync_cycle!("initial");
-        if cancelled.load(Ordering::SeqCst) {
-            return code;
+            if !cli.quiet {
+                eprintln!("Next: {}", result.suggested_next_command);
+            }
         }
     }
 
-    // Build initial snapshot after first sync (or immediately if --watch-no-initial)
-    if use_incremental {
-        local_snapshot = build_snapshot(local);
+    let _ = provider.disconnect().await;
+    if remote_root_ok {
+        0
+    } else {
+        4
     }
+}
 
-    // Watch loop
-    loop {
-        tokio::select! {
-            biased; // prioritize ctrl_c
+// ── Backup Repositories (deduplicated encrypted snapshots) ───────
 
-            _ = shutdown_tick.tick() => {
-                if cancelled.load(Ordering::SeqCst) {
-                    if syncing.load(Ordering::SeqCst) {
-                        continue;
-                    }
-                    if !quiet {
-                        eprintln!("\nWatch mode stopped. {} sync cycles completed.", cycle_count);
-                    }
-                    return 0;
-                }
-            }
+type BackupRepo = Repository<ProviderBackend>;
 
-            Some(changed_paths) = async_rx.recv() => {
-                // Suppress if sync in progress
-                if syncing.load(Ordering::SeqCst) {
-                    while async_rx.try_recv().is_ok() {}
-                    continue;
-                }
-                // Cooldown check
-                if last_sync_completed.elapsed() < cooldown_dur {
-                    while async_rx.try_recv().is_ok() {}
-                    continue;
-                }
-                let path_count = changed_paths.len();
-                let trigger = format!("watcher: {} paths", path_count);
+/// Connect and open the repository at `path`, printing the error and
+/// returning the exit code on failure.
+async fn open_backup_repo(
+    url: &str,
+    path: &str,
+    password: &str,
+    cli: &Cli,
+    format: OutputFormat,
+) -> Result<BackupRepo, i32> {
+    let (provider, initial_path) = create_and_connect(url, cli, format).await?;
+    let base_path = normalize_remote_path(&resolve_cli_remote_path(&initial_path, path));
+    let mut backend = ProviderBackend::new(provider, &base_path);
+    if !matches!(backend.exists("config").await, Ok(true)) {
+        print_error(
+            format,
+            &format!(
+                "No backup repository at {}. Run 'backup init' first.",
+                base_path
+            ),
+            2,
+        );
+        let _ = backend.into_provider().disconnect().await;
+        return Err(2);
+    }
+    match Repository::open(backend, password).await {
+        Ok(repo) => Ok(repo),
+        Err(e) => {
+            let code = if e.starts_with("Wrong password") {
+                6
+            } else {
+                5
+            };
+            print_error(format, &e, code);
+            Err(code)
+        }
+    }
+}
 
-                if use_incremental {
-                    let entries = incremental_local_scan(
-                        local_path,
-                        &changed_paths,
-                        &local_snapshot,
-                        &exclude_matchers,
-                    );
-                    // Update snapshot with changes
-                    for (ref rel, size, ref mtime) in &entries {
-                        local_snapshot.insert(rel.clone(), (*size, mtime.clone()));
-                    }
-                    // Remove deleted files from snapshot
-                    for p in &changed_paths {
-                        if let Ok(rel) = p.strip_prefix(local_path) {
-                            let rel_str = rel.to_string_lossy().replace('\\', "/");
-                            if !entries.iter().any(|(r, _, _)| r == &rel_str) {
-                                local_snapshot.remove(&rel_str);
-                            }
-                        }
-                    }
-                    run_sync_cycle!(trigger.as_str(), Some(entries));
-                    if cancelled.load(Ordering::SeqCst) {
-                        if !quiet {
-                            eprintln!("\nWatch mode stopped. {} sync cycles completed.", cycle_count);
-                        }
-                        return 0;
-                    }
-                } else {
-                    run_sync_cycle!(trigger.as_str());
-                    if cancelled.load(Ordering::SeqCst) {
-                        if !quiet {
-                            eprintln!("\nWatch mode stopped. {} sync cycles completed.", cycle_count);
-                        }
-                        return 0;
-                    }
-                }
+async fn close_backup_repo(repo: BackupRepo) {
+    let _ = repo.into_backend().into_provider().disconnect().await;
+}
+
+async fn cmd_backup_init(
+    url: &str,
+    path: &str,
+    password: &str,
+    cli: &Cli,
+    format: OutputFormat,
+) -> i32 {
+    let (provider, initial_path) = match create_and_connect(url, cli, format).await {
+        Ok(v) => v,
+        Err(code) => return code,
+    };
+    let base_path = normalize_remote_path(&resolve_cli_remote_path(&initial_path, path));
+    let backend = ProviderBackend::new(provider, &base_path);
+    match Repository::init(backend, password, KdfParams::strong()).await {
+        Ok(repo) => {
+            if matches!(format, OutputFormat::Json) {
+                print_json(&serde_json::json!({
+                    "status": "ok",
+                    "path": base_path,
+                    "id": repo.config().id,
+                }));
+            } else if !cli.quiet {
+                println!(
+                    "Backup repository {} created at {}",
+                    repo.config().id,
+                    base_path
+                );
+                println!("Chunks: content-defined, zstd, AES-256-GCM-SIV");
+                println!("KDF: Argon2id (128 MB, 4 iterations)");
+                println!("Keep the password safe: without it the data cannot be recovered.");
             }
+            close_backup_repo(repo).await;
+            0
+        }
+        Err(e) => {
+            let code = if e.contains("already exists") { 9 } else { 4 };
+            print_error(format, &format!("Failed to init repository: {}", e), code);
+            code
+        }
+    }
+}
 
-            _ = rescan_tick.tick() => {
-                if syncing.load(Ordering::SeqCst) {
-                    continue;
-                }
-                run_sync_cycle!("rescan");
-                // Rebuild snapshot after full rescan
-                if use_incremental {
-                    local_snapshot = build_snapshot(local);
-                }
-                if cancelled.load(Ordering::SeqCst) {
-                    if !quiet {
-                        eprintln!("\nWatch mode stopped. {} sync cycles completed.", cycle_count);
-                    }
-                    return 0;
-                }
+#[allow(clippy::too_many_arguments)]
+async fn cmd_backup_run(
+    source: &str,
+    url: &str,
+    path: &str,
+    password: &str,
+    opts: BackupOptions,
+    cli: &Cli,
+    format: OutputFormat,
+) -> i32 {
+    if !Path::new(source).exists() {
+        print_error(format, &format!("Source not found: {}", source), 2);
+        return 2;
+    }
+    let mut repo = match open_backup_repo(url, path, password, cli, format).await {
+        Ok(r) => r,
+        Err(code) => return code,
+    };
+    let started = Instant::now();
+    let result = repo.backup(Path::new(source), &opts).await;
+    close_backup_repo(repo).await;
+    let outcome = match result {
+        Ok(o) => o,
+        Err(e) => {
+            print_error(format, &format!("Backup failed: {}", e), 4);
+            return 4;
+        }
+    };
+    let snap = &outcome.snapshot;
+    let s = &snap.summary;
+    if matches!(format, OutputFormat::Json) {
+        print_json(&serde_json::json!({
+            "status": if outcome.warnings.is_empty() { "ok" } else { "partial" },
+            "snapshot": snap.id,
+            "parent": snap.parent,
+            "summary": s,
+            "warnings": outcome.warnings,
+            "elapsed_secs": started.elapsed().as_secs_f64(),
+        }));
+    } else {
+        for w in &outcome.warnings {
+            eprintln!("Warning: {}", w);
+        }
+        if !cli.quiet {
+            println!(
+                "Files: {} new, {} changed, {} unmodified ({} dirs, {} symlinks)",
+                s.files_new, s.files_changed, s.files_unmodified, s.dirs, s.symlinks
+            );
+            println!(
+                "Added to repository: {} ({} stored, {} new chunks) of {} total",
+                format_size(s.bytes_new),
+                format_size(s.bytes_stored),
+                s.blobs_new,
+                format_size(s.bytes)
+            );
+            println!(
+                "Snapshot {} saved in {:.1}s",
+                snap.short_id(),
+                started.elapsed().as_secs_f64()
+            );
+        }
+    }
+    if outcome.warnings.is_empty() {
+        0
+    } else {
+        4
+    }
+}
+
+async fn cmd_backup_snapshots(
+    url: &str,
+    path: &str,
+    password: &str,
+    host: Option<&str>,
+    cli: &Cli,
+    format: OutputFormat,
+) -> i32 {
+    let mut repo = match open_backup_repo(url, path, password, cli, format).await {
+        Ok(r) => r,
+        Err(code) => return code,
+    };
+    let result = repo.snapshots().await;
+    close_backup_repo(repo).await;
+    let snapshots: Vec<Snapshot> = match result {
+        Ok((list, skipped)) => {
+            for w in &skipped {
+                eprintln!("Warning: {}", w);
             }
+            list.into_iter()
+                .filter(|s| host.is_none_or(|h| s.hostname == h))
+                .collect()
+        }
+        Err(e) => {
+            print_error(format, &format!("Cannot list snapshots: {}", e), 4);
+            return 4;
+        }
+    };
+    if matches!(format, OutputFormat::Json) {
+        let items: Vec<serde_json::Value> = snapshots
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "id": s.id,
+                    "short_id": s.short_id(),
+                    "time": s.time.to_rfc3339(),
+                    "hostname": s.hostname,
+                    "source": s.source,
+                    "tags": s.tags,
+                    "parent": s.parent,
+                    "summary": s.summary,
+                })
+            })
+            .collect();
+        print_json(&items);
+        return 0;
+    }
+    if snapshots.is_empty() {
+        if !cli.quiet {
+            println!("No snapshots.");
+        }
+        return 0;
+    }
+    println!(
+        "{:<10} {:<19} {:<16} {:>8} {:>10}  Source",
+        "ID", "Time", "Host", "Files", "Size"
+    );
+    for s in &snapshots {
+        let tags = if s.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", s.tags.join(","))
+        };
+        println!(
+            "{:<10} {:<19} {:<16} {:>8} {:>10}  {}{}",
+            s.short_id(),
+            s.time
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            s.hostname,
+            s.summary.files,
+            format_size(s.summary.bytes),
+            s.source,
+            tags
+        );
+    }
+    if !cli.quiet {
+        println!("{} snapshot(s)", snapshots.len());
+    }
+    0
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn cmd_backup_restore(
+    snapshot: &str,
+    target: &str,
+    url: &str,
+    path: &str,
+    password: &str,
+    opts: RestoreOptions,
+    cli: &Cli,
+    format: OutputFormat,
+) -> i32 {
+    let mut repo = match open_backup_repo(url, path, password, cli, format).await {
+        Ok(r) => r,
+        Err(code) => return code,
+    };
+    let snap = match repo.find_snapshot(snapshot).await {
+        Ok(s) => s,
+        Err(e) => {
+            print_error(format, &e, 2);
+            close_backup_repo(repo).await;
+            return 2;
+        }
+    };
+    if !cli.quiet && matches!(format, OutputFormat::Text) {
+        eprintln!(
+            "Restoring snapshot {} ({}) to {}",
+            snap.short_id(),
+            snap.source,
+            target
+        );
+    }
+    let result = repo.restore(&snap, Path::new(target), &opts).await;
+    close_backup_repo(repo).await;
+    let summary = match result {
+        Ok(s) => s,
+        Err(e) => {
+            print_error(format, &format!("Restore failed: {}", e), 4);
+            return 4;
+        }
+    };
+    if matches!(format, OutputFormat::Json) {
+        print_json(&serde_json::json!({
+            "status": if summary.warnings.is_empty() { "ok" } else { "partial" },
+            "snapshot": snap.id,
+            "target": target,
+            "summary": summary,
+        }));
+    } else {
+        for w in &summary.warnings {
+            eprintln!("Warning: {}", w);
+        }
+        if !cli.quiet {
+            println!(
+                "Restored {} file(s), {} ({} unchanged, {} dirs, {} symlinks)",
+                summary.files,
+                format_size(summary.bytes),
+                summary.unchanged,
+                summary.dirs,
+                summary.symlinks
+            );
         }
     }
+    if summary.warnings.is_empty() {
+        0
+    } else {
+        4
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
-async fn cmd_sync_doctor(
+async fn cmd_backup_forget(
     url: &str,
-    local: &str,
-    remote: &str,
-    direction: &str,
-    delete: bool,
-    exclude: &[String],
-    track_renames: bool,
-    conflict_mode: &str,
-    resync: bool,
-    checksum: bool,
+    path: &str,
+    password: &str,
+    policy: RetentionPolicy,
+    ids: &[String],
+    prune: bool,
+    dry_run: bool,
     cli: &Cli,
     format: OutputFormat,
 ) -> i32 {
-    if !is_valid_sync_direction(direction) {
+    if ids.is_empty() && policy.is_empty() {
         print_error(
             format,
-            &format!(
-                "Invalid sync direction '{}'. Expected one of: upload, download, both",
-                direction
-            ),
+            "Nothing to forget: pass --snapshot <ID> or at least one --keep-* rule",
             5,
         );
         return 5;
     }
-
-    let doctor_cfg = if let Some(profile_name) = cli.profile.as_deref() {
-        match profile_to_provider_config(profile_name, cli, format) {
-            Ok((cfg, _)) => Some(cfg),
-            Err(code) => return code,
-        }
-    } else {
-        None
-    };
-
-    let (mut provider, initial_path) = match create_and_connect(url, cli, format).await {
-        Ok(v) => v,
-        Err(code) => return code,
-    };
-    let remote = resolve_cli_remote_path(&initial_path, remote);
-
-    let local_dir = Path::new(local);
-    if !local_dir.is_dir() {
-        print_error(
-            format,
-            &format!("Local path is not a directory: {}", local),
-            5,
-        );
-        let _ = provider.disconnect().await;
+    if !ids.is_empty() && dry_run {
+        print_error(format, "--dry-run only applies to --keep-* policies", 5);
         return 5;
     }
+    let mut repo = match open_backup_repo(url, path, password, cli, format).await {
+        Ok(r) => r,
+        Err(code) => return code,
+    };
+    let json = matches!(format, OutputFormat::Json);
+    let mut report = serde_json::Map::new();
+    let mut code = 0;
 
-    let exclude_matchers: Vec<globset::GlobMatcher> = exclude
-        .iter()
-        .filter_map(|pat| globset::Glob::new(pat).ok().map(|g| g.compile_matcher()))
-        .collect();
-
-    let mut local_files = 0usize;
-    let mut local_bytes = 0u64;
-    for entry in walkdir::WalkDir::new(local)
-        .follow_links(false)
-        .max_depth(100)
-    {
-        let Ok(entry) = entry else { continue };
-        if !entry.file_type().is_file() {
-            continue;
-        }
-        let relative = entry
-            .path()
-            .strip_prefix(local)
-            .unwrap_or(entry.path())
-            .to_string_lossy()
-            .replace('\\', "/");
-        if relative.is_empty() || relative == BISYNC_SNAPSHOT_FILE {
-            continue;
-        }
-        let fname = entry.file_name().to_string_lossy();
-        let fname_ref: &str = fname.as_ref();
-        if exclude_matchers
-            .iter()
-            .any(|m| m.is_match(&relative) || m.is_match(fname_ref))
-        {
-            continue;
+    if !ids.is_empty() {
+        match repo.forget_snapshots(ids).await {
+            Ok(removed) => {
+                if !json && !cli.quiet {
+                    for s in &removed {
+                        println!("Removed snapshot {} ({})", s.short_id(), s.source);
+                    }
+                }
+                let removed: Vec<&str> = removed.iter().map(|s| s.id.as_str()).collect();
+                report.insert("removed".into(), serde_json::json!(removed));
+            }
+            Err(e) => {
+                print_error(format, &format!("Forget failed: {}", e), 4);
+                code = 4;
+            }
         }
-        local_files += 1;
-        local_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
     }
-
-    let remote_root_ok = provider.list(&remote).await.is_ok();
-    let mut remote_files = 0usize;
-    let mut remote_bytes = 0u64;
-    if remote_root_ok {
-        let mut queue: Vec<(String, usize)> = vec![(remote.to_string(), 0)];
-        while let Some((dir, depth)) = queue.pop() {
-            if depth >= MAX_SCAN_DEPTH || remote_files >= MAX_SCAN_ENTRIES {
-                break;
-            }
-            if let Ok(entries) = provider.list(&dir).await {
-                for e in entries {
-                    if e.is_dir {
-                        queue.push((e.path.clone(), depth + 1));
-                    } else {
-                        let relative = e
-                            .path
-                            .strip_prefix(&remote)
-                            .unwrap_or(&e.path)
-                            .trim_start_matches('/')
-                            .to_string();
-                        if relative.is_empty() || relative == BISYNC_SNAPSHOT_FILE {
-                            continue;
+    if code == 0 && !policy.is_empty() {
+        match repo.forget(&policy, dry_run).await {
+            Ok(decisions) => {
+                if !json {
+                    let mut group = None;
+                    for d in &decisions {
+                        let key = (d.hostname.as_str(), d.source.as_str());
+                        if group != Some(key) {
+                            println!("{} {}:", d.hostname, d.source);
+                            group = Some(key);
                         }
-                        if exclude_matchers
-                            .iter()
-                            .any(|m| m.is_match(&relative) || m.is_match(&e.name))
-                        {
-                            continue;
-                        }
-                        remote_files += 1;
-                        remote_bytes += e.size;
+                        println!(
+                            "  {} {}  {}",
+                            if d.keep { "keep  " } else { "forget" },
+                            &d.id[..8.min(d.id.len())],
+                            if d.keep {
+                                d.reasons.join(", ")
+                            } else {
+                                d.time
+                                    .with_timezone(&chrono::Local)
+                                    .format("%Y-%m-%d %H:%M:%S")
+                                    .to_string()
+                            }
+                        );
+                    }
+                    if dry_run && !cli.quiet {
+                        println!("Dry run: no snapshot was removed.");
                     }
                 }
+                report.insert("decisions".into(), serde_json::json!(decisions));
+            }
+            Err(e) => {
+                print_error(format, &format!("Forget failed: {}", e), 4);
+                code = 4;
             }
         }
     }
-
-    let mut checks = vec![
-        serde_json::json!({"name": "local_path_exists", "ok": true, "path": local}),
-        serde_json::json!({"name": "remote_path_reachable", "ok": remote_root_ok, "path": remote}),
-    ];
-    if !exclude.is_empty() {
-        checks.push(
-            serde_json::json!({"name": "exclude_patterns", "ok": true, "count": exclude.len()}),
-        );
-    }
-
-    let mut risks = Vec::new();
-    if let Some(cfg) = doctor_cfg.as_ref() {
-        push_s3_doctor_checks(&mut checks, &mut risks, "remote", cfg);
-    }
-    if delete {
-        risks.push("delete is enabled; sync may remove orphaned files".to_string());
-    }
-    if direction == "both" {
-        risks.push(format!(
-            "bidirectional sync can resolve conflicts automatically using conflict_mode={}",
-            conflict_mode
-        ));
-    }
-    if resync {
-        risks.push("resync is enabled; previous bisync snapshot will be ignored".to_string());
-    }
-    if !track_renames && direction == "both" {
-        risks.push("track-renames is disabled; moved files may be recopied".to_string());
-    }
-    if checksum {
-        risks
-            .push("checksum is enabled; later verification may be slower but stricter".to_string());
+    if code == 0 && prune && !dry_run {
+        match repo.prune(false).await {
+            Ok(summary) => {
+                if !json && !cli.quiet {
+                    print_prune_summary(&summary);
+                }
+                report.insert("prune".into(), serde_json::json!(summary));
+            }
+            Err(e) => {
+                print_error(format, &format!("Prune failed: {}", e), 4);
+                code = 4;
+            }
+        }
     }
-    if !remote_root_ok {
-        risks.push("remote path could not be listed".to_string());
+    close_backup_repo(repo).await;
+    if json && code == 0 {
+        report.insert("status".into(), serde_json::json!("ok"));
+        report.insert("dry_run".into(), serde_json::json!(dry_run));
+        print_json(&report);
     }
+    code
+}
 
-    let suggested_next_command =
-        format!(
-        "aeroftp-cli sync --profile \"{}\" \"{}\" \"{}\" --direction {} --dry-run --json{}{}{}{}",
-        profile_or_placeholder(cli),
-        shell_double_quote(local),
-        shell_double_quote(&remote),
-        shell_double_quote(direction),
-        if delete { " --delete" } else { "" },
-        if track_renames { " --track-renames" } else { "" },
-        if resync { " --resync" } else { "" },
-        if exclude.is_empty() {
-            String::new()
+fn print_prune_summary(summary: &PruneSummary) {
+    println!(
+        "{} {} unreferenced chunk(s), {} ({} of {} chunks still referenced by {} snapshot(s))",
+        if summary.dry_run {
+            "Would delete"
         } else {
-            format!(
-                " {}",
-                exclude
-                    .iter()
-                    .map(|pattern| format!("--exclude \"{}\"", shell_double_quote(pattern)))
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            )
-        }
+            "Deleted"
+        },
+        summary.blobs_deleted,
+        format_size(summary.bytes_freed),
+        summary.blobs_referenced,
+        summary.blobs_total,
+        summary.snapshots
     );
+    if summary.blobs_missing > 0 {
+        eprintln!(
+            "Warning: {} referenced chunk(s) are missing; run 'backup check'",
+            summary.blobs_missing
+        );
+    }
+}
 
-    let result = CliDoctorResult {
-        status: if remote_root_ok { "ok" } else { "attention" },
-        doctor: "sync".to_string(),
-        summary: serde_json::json!({
-            "direction": direction,
-            "local_files": local_files,
-            "local_bytes": local_bytes,
-            "remote_files": remote_files,
-            "remote_bytes": remote_bytes,
-            "delete": delete,
-            "track_renames": track_renames,
-            "conflict_mode": conflict_mode,
-            "resync": resync,
-        }),
-        checks,
-        risks,
-        suggested_next_command,
+async fn cmd_backup_prune(
+    url: &str,
+    path: &str,
+    password: &str,
+    dry_run: bool,
+    cli: &Cli,
+    format: OutputFormat,
+) -> i32 {
+    let mut repo = match open_backup_repo(url, path, password, cli, format).await {
+        Ok(r) => r,
+        Err(code) => return code,
     };
+    let result = repo.prune(dry_run).await;
+    close_backup_repo(repo).await;
+    match result {
+        Ok(summary) => {
+            if matches!(format, OutputFormat::Json) {
+                print_json(&summary);
+            } else if !cli.quiet {
+                print_prune_summary(&summary);
+            }
+            0
+        }
+        Err(e) => {
+            print_error(format, &format!("Prune failed: {}", e), 4);
+            4
+        }
+    }
+}
 
-    match format {
-        OutputFormat::Json => print_json(&result),
-        OutputFormat::Text => {
-            println!("Sync doctor");
-            println!(
-                "  Local:  {} file(s), {}",
-                local_files,
-                format_size(local_bytes)
-            );
+async fn cmd_backup_check(
+    url: &str,
+    path: &str,
+    password: &str,
+    read_data: bool,
+    cli: &Cli,
+    format: OutputFormat,
+) -> i32 {
+    let mut repo = match open_backup_repo(url, path, password, cli, format).await {
+        Ok(r) => r,
+        Err(code) => return code,
+    };
+    let result = repo.check(read_data).await;
+    close_backup_repo(repo).await;
+    let report = match result {
+        Ok(r) => r,
+        Err(e) => {
+            print_error(format, &format!("Check failed: {}", e), 4);
+            return 4;
+        }
+    };
+    if matches!(format, OutputFormat::Json) {
+        print_json(&serde_json::json!({
+            "status": if report.errors.is_empty() { "ok" } else { "error" },
+            "report": report,
+        }));
+    } else {
+        for e in &report.errors {
+            eprintln!("Error: {}", e);
+        }
+        if !cli.quiet {
             println!(
-                "  Remote: {} file(s), {}",
-                remote_files,
-                format_size(remote_bytes)
+                "{} snapshot(s), {} chunk(s){}, {} unreferenced",
+                report.snapshots,
+                report.blobs,
+                if read_data {
+                    format!(" ({} read and verified)", report.blobs_read)
+                } else {
+                    String::new()
+                },
+                report.blobs_unused
             );
-            println!("  Direction: {}", direction);
-            if !result.risks.is_empty() {
-                println!("  Risks:");
-                for risk in &result.risks {
-                    println!("    - {}", risk);
-                }
-            }
-            if !cli.quiet {
-                eprintln!("Next: {}", result.suggested_next_command);
+            if report.errors.is_empty() {
+                println!("No errors found.");
+            } else {
+                println!("{} error(s) found.", report.errors.len());
             }
         }
     }
-
-    let _ = provider.disconnect().await;
-    if remote_root_ok {
+    if report.errors.is_empty() {
         0
     } else {
         4
     }
 }
 
+async fn cmd_backup_unlock(
+    url: &str,
+    path: &str,
+    password: &str,
+    cli: &Cli,
+    format: OutputFormat,
+) -> i32 {
+    let mut repo = match open_backup_repo(url, path, password, cli, format).await {
+        Ok(r) => r,
+        Err(code) => return code,
+    };
+    let result = repo.break_locks().await;
+    close_backup_repo(repo).await;
+    match result {
+        Ok(n) => {
+            if matches!(format, OutputFormat::Json) {
+                print_json(&serde_json::json!({"status": "ok", "removed": n}));
+            } else if !cli.quiet {
+                println!("Removed {} lock(s)", n);
+            }
+            0
+        }
+        Err(e) => {
+            print_error(format, &format!("Unlock failed: {}", e), 4);
+            4
+        }
+    }
+}
+
 // ── Head / Tail / Touch / Hashsum / Check ─────────────────────────
 
 async fn cmd_head(
@@ -21926,16 +27148,22 @@ async fn cmd_check(
     // Delegate scan + comparison to sync_core. Both CLI and MCP now share
     // the same implementation, so a fix in one propagates to the other.
     use ftp_client_gui_lib::sync_core::{
-        compare_trees, scan_local_tree, scan_remote_tree, ScanOptions,
+        compare_trees_with, scan_local_tree, scan_remote_tree, ScanOptions,
     };
     let scan_opts = ScanOptions {
         compute_checksum: checksum,
         max_depth: Some(MAX_SCAN_DEPTH),
+        copy_links: cli.copy_links,
+        safe_links: cli.safe_links,
+        checksum_cache: checksum
+            .then(|| ChecksumCache::shared_for_root(local_dir))
+            .flatten(),
         ..Default::default()
     };
     let locals = scan_local_tree(local_path, &scan_opts);
     let remotes = scan_remote_tree(&mut provider, remote_path, &scan_opts).await;
-    let diff = compare_trees(&locals, &remotes, one_way);
+    let case_insensitive = provider.is_case_insensitive() || local_fs_case_insensitive();
+    let diff = compare_trees_with(&locals, &remotes, one_way, case_insensitive);
 
     let match_count = diff.match_count() as u32;
     let differ_count = diff.differ_count() as u32;
@@ -22378,12 +27606,17 @@ async fn cmd_reconcile(
         }
     }
 
-    use ftp_client_gui_lib::sync_core::{compare_trees, ScanOptions};
+    use ftp_client_gui_lib::sync_core::{compare_trees_with, ScanOptions};
     let scan_opts = ScanOptions {
         exclude_patterns: all_exclude,
         compute_checksum: checksum,
         compute_remote_checksum: checksum,
         max_depth: Some(MAX_SCAN_DEPTH),
+        copy_links: cli.copy_links,
+        safe_links: cli.safe_links,
+        checksum_cache: checksum
+            .then(|| ChecksumCache::shared_for_root(local_dir))
+            .flatten(),
         ..Default::default()
     };
     let local_spinner = maybe_create_scan_spinner(format, cli, "Scanning local...");
@@ -22399,7 +27632,8 @@ async fn cmd_reconcile(
     if let Some(pb) = remote_spinner {
         pb.finish_and_clear();
     }
-    let diff = compare_trees(&locals, &remotes, one_way);
+    let case_insensitive = provider.is_case_insensitive() || local_fs_case_insensitive();
+    let diff = compare_trees_with(&locals, &remotes, one_way, case_insensitive);
 
     let matches_group: Vec<serde_json::Value> = diff
         .matches
@@ -28544,6 +33778,14 @@ async fn main() {
             cache_ttl,
             allow_other,
             read_only,
+            vfs_cache_mode,
+            vfs_cache_max_size,
+            vfs_cache_max_age,
+            vfs_read_ahead,
+            vfs_read_chunk_size,
+            vfs_write_back,
+            cache_dir,
+            poll_interval,
         } => {
             let (u, p) = if cli.profile.is_some() && !url.contains("://") && url != "_" {
                 ("_", url.as_str())
@@ -28552,17 +33794,37 @@ async fn main() {
             };
             #[cfg(target_os = "linux")]
             {
-                cmd_mount(
-                    u,
-                    mountpoint,
-                    p,
-                    *cache_ttl,
-                    *allow_other,
-                    *read_only,
-                    &cli,
-                    format,
+                match vfs_cache_options(
+                    vfs_cache_mode,
+                    vfs_cache_max_size.as_deref(),
+                    vfs_cache_max_age,
+                    vfs_read_ahead,
+                    vfs_read_chunk_size,
+                    vfs_write_back,
                 )
-                .await
+                .and_then(|vfs| Ok((vfs, parse_age_filter(poll_interval)?)))
+                {
+                    Ok((vfs, poll_secs)) => {
+                        cmd_mount(
+                            u,
+                            mountpoint,
+                            p,
+                            *cache_ttl,
+                            *allow_other,
+                            *read_only,
+                            vfs,
+                            cache_dir.as_deref(),
+                            std::time::Duration::from_secs(poll_secs),
+                            &cli,
+                            format,
+                        )
+                        .await
+                    }
+                    Err(e) => {
+                        print_error(format, &e, 5);
+                        5
+                    }
+                }
             }
             #[cfg(windows)]
             {
@@ -28574,6 +33836,18 @@ async fn main() {
                 7
             }
         }
+        // Only reachable when an alias smuggles `--job` past expand_sync_job.
+        Commands::Sync { job: Some(job), .. } => {
+            print_error(
+                format,
+                &format!(
+                    "--job '{}' must be given on the command line, not through an alias",
+                    job
+                ),
+                5,
+            );
+            5
+        }
         Commands::Sync {
             url,
             local,
@@ -28599,6 +33873,7 @@ async fn main() {
             watch_cooldown,
             watch_rescan,
             watch_no_initial,
+            job: _,
         } => {
             let (u, l, r) = if cli.profile.is_some() && !url.contains("://") && url != "_" {
                 ("_", url.as_str(), local.as_str())
@@ -28639,51 +33914,76 @@ async fn main() {
                 let max_attempts = cli.retries.max(1);
                 let sleep_dur = parse_retry_sleep(&cli.retries_sleep);
                 let max_transfer_limit = resolve_max_transfer(&cli);
-                let mut last_code = 0i32;
-                for attempt in 1..=max_attempts {
-                    last_code = cmd_sync(
-                        u,
-                        l,
-                        r,
-                        direction,
-                        *dry_run,
-                        *delete,
-                        exclude,
-                        *track_renames,
-                        max_delete.as_deref(),
-                        backup_dir.as_deref(),
-                        backup_suffix,
-                        *suffix_keep_extension,
-                        compare_dest.as_deref(),
-                        copy_dest.as_deref(),
-                        from_reconcile.as_deref(),
-                        conflict_mode,
-                        *skip_matching,
-                        *resync,
-                        &cli,
-                        format,
-                        cancelled.clone(),
-                        None,
-                    )
-                    .await
-                    .exit_code;
-                    if !is_retryable_exit(last_code)
-                        || session_transfer_exceeded(max_transfer_limit)
-                        || attempt == max_attempts
-                    {
-                        break;
-                    }
-                    if !cli.quiet {
-                        eprintln!(
-                            "Attempt {}/{} failed (exit {}), retrying in {:?}...",
-                            attempt, max_attempts, last_code, sleep_dur
-                        );
-                    }
-                    if !sleep_dur.is_zero() {
-                        tokio::time::sleep(sleep_dur).await;
+                let hooks = cli_sync_hooks(&cli);
+                let hook_event = SyncHookEvent::pre_sync(l, r, direction, *dry_run);
+                let hook_start = Instant::now();
+                let pre_hook_error = match &hooks {
+                    Ok(hooks) => hooks.run_pre(&hook_event).await.err(),
+                    Err(_) => None,
+                };
+                let mut last = SyncCycleStats::default();
+                if let Err(e) = &hooks {
+                    print_error(format, e, 5);
+                    last = 5.into();
+                } else if let Some(e) = &pre_hook_error {
+                    print_error(format, &format!("Sync aborted: {}", e), 4);
+                    last = 4.into();
+                } else {
+                    for attempt in 1..=max_attempts {
+                        last = cmd_sync(
+                            u,
+                            l,
+                            r,
+                            direction,
+                            *dry_run,
+                            *delete,
+                            exclude,
+                            *track_renames,
+                            max_delete.as_deref(),
+                            backup_dir.as_deref(),
+                            backup_suffix,
+                            *suffix_keep_extension,
+                            compare_dest.as_deref(),
+                            copy_dest.as_deref(),
+                            from_reconcile.as_deref(),
+                            conflict_mode,
+                            *skip_matching,
+                            *resync,
+                            &cli,
+                            format,
+                            cancelled.clone(),
+                            None,
+                        )
+                        .await;
+                        if !is_retryable_exit(last.exit_code)
+                            || session_transfer_exceeded(max_transfer_limit)
+                            || attempt == max_attempts
+                        {
+                            break;
+                        }
+                        if !cli.quiet {
+                            eprintln!(
+                                "Attempt {}/{} failed (exit {}), retrying in {:?}...",
+                                attempt, max_attempts, last.exit_code, sleep_dur
+                            );
+                        }
+                        if !sleep_dur.is_zero() {
+                            tokio::time::sleep(sleep_dur).await;
+                        }
                     }
                 }
-                last_code
+                if let Ok(hooks) = &hooks {
+                    finish_cli_sync_hooks(
+                        hooks,
+                        hook_event,
+                        &last,
+                        pre_hook_error,
+                        hook_start.elapsed(),
+                        cli.quiet,
+                    )
+                    .await;
+                }
+                last.exit_code
             }
         }
         Commands::SyncDoctor {
@@ -28878,6 +34178,19 @@ async fn main() {
         ),
         Commands::AgentInfo => cmd_agent_info(&cli),
         Commands::AgentConnect { profile } => cmd_agent_connect(&cli, profile).await,
+        Commands::Versions { command } => {
+            let (url, remote) = match command {
+                VersionsCommands::List { url, remote, .. }
+                | VersionsCommands::Restore { url, remote, .. }
+                | VersionsCommands::Cleanup { url, remote, .. } => (url, remote),
+            };
+            let (u, r) = if cli.profile.is_some() && !url.contains("://") && url != "_" {
+                ("_", url.as_str())
+            } else {
+                (url.as_str(), remote.as_str())
+            };
+            cmd_versions(u, r, command, &cli, format).await
+        }
         Commands::Crypt { command } => {
             let resolve_crypt_password = |p: &Option<String>| -> Option<String> {
                 if let Some(pw) = p {
@@ -28954,6 +34267,248 @@ async fn main() {
                 }
             }
         }
+        Commands::Backup { command } => {
+            let resolve_backup_password = |p: &Option<String>| -> Option<String> {
+                if let Some(pw) = p {
+                    return Some(pw.clone());
+                }
+                if std::io::stdin().is_terminal() {
+                    eprint!("Repository password: ");
+                    let _ = std::io::stderr().flush();
+                    rpassword::read_password().ok()
+                } else {
+                    None
+                }
+            };
+            let shift = |url: &String, path: &String| -> (String, String) {
+                if cli.profile.is_some() && !url.contains("://") && url != "_" {
+                    ("_".to_string(), url.clone())
+                } else {
+                    (url.clone(), path.clone())
+                }
+            };
+            let (url, path, password) = match command {
+                BackupCommands::Init {
+                    url,
+                    path,
+                    password,
+                }
+                | BackupCommands::Run {
+                    url,
+                    path,
+                    password,
+                    ..
+                }
+                | BackupCommands::Snapshots {
+                    url,
+                    path,
+                    password,
+                    ..
+                }
+                | BackupCommands::Restore {
+                    url,
+                    path,
+                    password,
+                    ..
+                }
+                | BackupCommands::Forget {
+                    url,
+                    path,
+                    password,
+                    ..
+                }
+                | BackupCommands::Prune {
+                    url,
+                    path,
+                    password,
+                    ..
+                }
+                | BackupCommands::Check {
+                    url,
+                    path,
+                    password,
+                    ..
+                }
+                | BackupCommands::Unlock {
+                    url,
+                    path,
+                    password,
+                }
+                | BackupCommands::Mount {
+                    url,
+                    path,
+                    password,
+                    ..
+                } => {
+                    let (u, p) = shift(url, path);
+                    (u, p, resolve_backup_password(password).unwrap_or_default())
+                }
+            };
+            if password.is_empty() {
+                print_error(
+                    format,
+                    "Repository password required (--password or AEROFTP_BACKUP_PASSWORD)",
+                    5,
+                );
+                5
+            } else {
+                match command {
+                    BackupCommands::Init { .. } => {
+                        cmd_backup_init(&url, &path, &password, &cli, format).await
+                    }
+                    BackupCommands::Run {
+                        source,
+                        tag,
+                        exclude,
+                        host,
+                        parent,
+                        ..
+                    } => {
+                        let mut excludes = exclude.clone();
+                        excludes.extend(cli.exclude_global.iter().cloned());
+                        let opts = BackupOptions {
+                            tags: tag.clone(),
+                            excludes,
+                            hostname: host.clone(),
+                            parent: parent.clone(),
+                        };
+                        cmd_backup_run(source, &url, &path, &password, opts, &cli, format).await
+                    }
+                    BackupCommands::Snapshots { host, .. } => {
+                        cmd_backup_snapshots(&url, &path, &password, host.as_deref(), &cli, format)
+                            .await
+                    }
+                    BackupCommands::Restore {
+                        snapshot, target, ..
+                    } => {
+                        let opts = RestoreOptions {
+                            include: cli.include.clone(),
+                            exclude: cli.exclude_global.clone(),
+                        };
+                        cmd_backup_restore(
+                            snapshot, target, &url, &path, &password, opts, &cli, format,
+                        )
+                        .await
+                    }
+                    BackupCommands::Forget {
+                        snapshots,
+                        keep_last,
+                        keep_hourly,
+                        keep_daily,
+                        keep_weekly,
+                        keep_monthly,
+                        keep_yearly,
+                        prune,
+                        dry_run,
+                        ..
+                    } => {
+                        let policy = RetentionPolicy {
+                            keep_last: *keep_last,
+                            keep_hourly: *keep_hourly,
+                            keep_daily: *keep_daily,
+                            keep_weekly: *keep_weekly,
+                            keep_monthly: *keep_monthly,
+                            keep_yearly: *keep_yearly,
+                        };
+                        cmd_backup_forget(
+                            &url, &path, &password, policy, snapshots, *prune, *dry_run, &cli,
+                            format,
+                        )
+                        .await
+                    }
+                    BackupCommands::Prune { dry_run, .. } => {
+                        cmd_backup_prune(&url, &path, &password, *dry_run, &cli, format).await
+                    }
+                    BackupCommands::Check { read_data, .. } => {
+                        cmd_backup_check(&url, &path, &password, *read_data, &cli, format).await
+                    }
+                    BackupCommands::Unlock { .. } => {
+                        cmd_backup_unlock(&url, &path, &password, &cli, format).await
+                    }
+                    BackupCommands::Mount {
+                        mountpoint,
+                        allow_other,
+                        ..
+                    } => {
+                        #[cfg(target_os = "linux")]
+                        {
+                            cmd_backup_mount(
+                                mountpoint,
+                                &url,
+                                &path,
+                                &password,
+                                *allow_other,
+                                &cli,
+                                format,
+                            )
+                            .await
+                        }
+                        #[cfg(not(target_os = "linux"))]
+                        {
+                            let _ = (mountpoint, allow_other);
+                            print_error(format, "backup mount is only supported on Linux", 7);
+                            7
+                        }
+                    }
+                }
+            }
+        }
+        Commands::Vault { command } => match command {
+            VaultCommands::Mount {
+                vault,
+                mountpoint,
+                url,
+                password,
+                commit_interval,
+                read_only,
+                allow_other,
+            } => {
+                let password = password.clone().or_else(|| {
+                    if std::io::stdin().is_terminal() {
+                        eprint!("Vault password: ");
+                        let _ = std::io::stderr().flush();
+                        rpassword::read_password().ok()
+                    } else {
+                        None
+                    }
+                });
+                match (password, parse_age_filter(commit_interval)) {
+                    (None, _) => {
+                        print_error(
+                            format,
+                            "Vault password required (--password or AEROFTP_VAULT_PASSWORD)",
+                            5,
+                        );
+                        5
+                    }
+                    (_, Err(e)) => {
+                        print_error(format, &e, 5);
+                        5
+                    }
+                    #[cfg(target_os = "linux")]
+                    (Some(password), Ok(commit_secs)) => {
+                        cmd_vault_mount(
+                            vault,
+                            mountpoint,
+                            url,
+                            &password,
+                            *allow_other,
+                            *read_only,
+                            std::time::Duration::from_secs(commit_secs),
+                            &cli,
+                            format,
+                        )
+                        .await
+                    }
+                    #[cfg(not(target_os = "linux"))]
+                    (Some(_), Ok(_)) => {
+                        let _ = (vault, mountpoint, url, read_only, allow_other);
+                        print_error(format, "vault mount is only supported on Linux", 7);
+                        7
+                    }
+                }
+            }
+        },
         Commands::RcloneCrypt { command } => {
             let resolve_rclone_password = |p: &Option<String>| -> Option<String> {
                 if let Some(pw) = p {
@@ -29020,6 +34575,34 @@ async fn main() {
             JobCommands::List => cmd_jobs_list(format).await,
             JobCommands::Status { id } => cmd_jobs_status(id, format).await,
             JobCommands::Cancel { id } => cmd_jobs_cancel(id, format).await,
+            JobCommands::Schedule { command } => match command {
+                ScheduleCommands::Add {
+                    name,
+                    local,
+                    remote,
+                    cron,
+                    url,
+                    window,
+                    days,
+                    disabled,
+                    options,
+                } => cmd_jobs_schedule_add(
+                    name,
+                    local,
+                    remote,
+                    cron,
+                    url.as_deref(),
+                    window.as_deref(),
+                    days,
+                    *disabled,
+                    options,
+                    &cli,
+                    format,
+                ),
+                ScheduleCommands::List => cmd_jobs_schedule_list(format),
+                ScheduleCommands::Remove { name } => cmd_jobs_schedule_remove(name, format),
+                ScheduleCommands::RunNow { name } => cmd_jobs_schedule_run_now(name, format).await,
+            },
         },
         Commands::Import { command } => match command {
             ImportCommands::Rclone { path, json } => cmd_import_rclone(path.clone(), *json).await,
@@ -29035,6 +34618,29 @@ async fn main() {
             } => cmd_import_rclone_filter(path.clone(), output.clone(), *force, *json).await,
         },
         Commands::Audit { command } => cmd_audit(command, &cli, format).await,
+        #[cfg(feature = "aerorsync")]
+        Commands::Delta { command } => match command {
+            DeltaCommands::Apply {
+                batch,
+                dest,
+                checksum_choice,
+                compress_choice,
+                delete,
+                mkpath,
+            } => {
+                cmd_delta_apply(
+                    batch,
+                    dest,
+                    checksum_choice.as_deref(),
+                    compress_choice.as_deref(),
+                    *delete,
+                    *mkpath,
+                    &cli,
+                    format,
+                )
+                .await
+            }
+        },
         Commands::Transfer {
             source_profile,
             dest_profile,
@@ -29280,6 +34886,27 @@ mod tests {
             multi_thread_cutoff: "250M".to_string(),
             default_time: None,
             fast_list: false,
+            change_feed: false,
+            remote_versioning: None,
+            name_collision: None,
+            links: false,
+            copy_links: false,
+            safe_links: false,
+            hard_links: false,
+            perms: false,
+            owner: false,
+            chmod: None,
+            xattrs: false,
+            pre_hook: Vec::new(),
+            post_hook: Vec::new(),
+            notify: Vec::new(),
+            notify_on: None,
+            hook_timeout: 3600,
+            order_by: None,
+            priority: Vec::new(),
+            max_duration: None,
+            stop_at: None,
+            aeroignore: false,
             inplace: false,
             files_from: None,
             files_from_raw: None,
@@ -30364,6 +35991,31 @@ mod tests {
         assert!(gated.is_empty());
     }
 
+    #[test]
+    fn test_resolve_conflict_merge_mode_defers_to_three_way_merge() {
+        let l = Some("2026-04-16T12:00:00");
+        let r = Some("2026-04-16T13:00:00");
+        assert_eq!(resolve_conflict("merge", 10, l, 20, r), "merge");
+        assert_eq!(resolve_conflict("newer", 10, l, 20, r), "download");
+    }
+
+    #[test]
+    fn test_conflict_rename_path_keeps_extension() {
+        let renamed = conflict_rename_path("docs/notes.md");
+        assert!(renamed.starts_with("docs/notes.conflict-"), "{}", renamed);
+        assert!(renamed.ends_with(".md"), "{}", renamed);
+        assert!(conflict_rename_path("Makefile").starts_with("Makefile.conflict-"));
+    }
+
+    #[test]
+    fn test_bisync_snapshot_merge_bases_are_optional() {
+        let snap: BisyncSnapshot =
+            serde_json::from_str(r#"{"synced_at":"2026-04-16T12:00:00Z","files":{}}"#).unwrap();
+        assert!(snap.merge_bases.is_empty());
+        let json = serde_json::to_string(&snap).unwrap();
+        assert!(!json.contains("merge_bases"));
+    }
+
     #[test]
     fn test_load_sync_plan_from_reconcile_upload_mode() {
         let dir = tempfile::tempdir().unwrap();
@@ -31024,4 +36676,46 @@ mod tests {
         assert!((v["percent"].as_f64().unwrap() - 87.5).abs() < 1e-9);
         assert!((v["threshold"].as_f64().unwrap() - 80.0).abs() < 1e-9);
     }
+
+    #[test]
+    fn sync_job_file_expands_and_command_line_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        let job = dir.path().join("photos.toml");
+        std::fs::write(
+            &job,
+            "[endpoints]\nprofile = \"nas\"\nlocal = \"photos\"\nremote = \"/backup\"\n\n\
+             [filters]\nexclude = [\"*.tmp\"]\n\n[bandwidth]\nparallel = 2\n",
+        )
+        .unwrap();
+        let args: Vec<String> = [
+            "aeroftp-cli",
+            "sync",
+            "--job",
+            job.to_str().unwrap(),
+            "--parallel",
+            "8",
+            "--exclude",
+            "*.bak",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let expanded = expand_sync_job(&args).unwrap();
+        let local = dir.path().join("photos").to_string_lossy().into_owned();
+        assert_eq!(
+            expanded[..5],
+            ["aeroftp-cli", "sync", "_", local.as_str(), "/backup"]
+        );
+        assert!(!expanded.iter().any(|a| a == "--job"));
+        assert!(expanded.windows(2).any(|w| w == ["--profile", "nas"]));
+        assert!(expanded.windows(2).any(|w| w == ["--exclude", "*.tmp"]));
+        assert!(expanded.windows(2).any(|w| w == ["--exclude", "*.bak"]));
+        assert_eq!(expanded.iter().filter(|a| *a == "--parallel").count(), 1);
+        assert!(expanded.windows(2).any(|w| w == ["--parallel", "8"]));
+        assert!(Cli::try_parse_from(&expanded).is_ok());
+
+        let plain: Vec<String> = vec!["aeroftp-cli".into(), "ls".into(), "--job".into()];
+        assert_eq!(expand_sync_job(&plain).unwrap(), plain);
+    }
 }
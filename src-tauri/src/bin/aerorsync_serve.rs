@@ -16,9 +16,11 @@ fn main() {
 #[cfg(feature = "aerorsync")]
 mod real_server {
     use clap::{Parser, ValueEnum};
+    use ftp_client_gui_lib::aerorsync::daemon_server::{bind_daemon, serve_daemon, RsyncdConfig};
     use ftp_client_gui_lib::aerorsync::server::{serve_stdio, ProtoServeMode, ProtoServeOptions};
     use ftp_client_gui_lib::aerorsync::types::ProtocolVersion;
     use std::path::PathBuf;
+    use std::sync::Arc;
 
     #[derive(Debug, Clone, Copy, ValueEnum)]
     enum CliMode {
@@ -44,10 +46,81 @@ mod real_server {
 
         #[arg(long)]
         stats: bool,
+
+        /// Run as a standalone rsync daemon (`rsync://`, protocol 31)
+        /// serving the modules of `--config`.
+        #[arg(long, requires = "config", conflicts_with_all = ["probe", "mode"])]
+        daemon: bool,
+
+        /// `rsyncd.conf`-style module configuration for `--daemon`.
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Listen port, overriding the config's `port` (default 873).
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// Listen address, overriding the config's `address`.
+        #[arg(long)]
+        address: Option<String>,
+    }
+
+    fn run_daemon(cli: Cli) {
+        let config_path = cli.config.unwrap_or_default();
+        let mut config = match RsyncdConfig::load(&config_path) {
+            Ok(config) => config,
+            Err(error) => {
+                eprintln!("{}: {error}", config_path.display());
+                std::process::exit(2);
+            }
+        };
+        for warning in &config.warnings {
+            eprintln!("{}: {warning}", config_path.display());
+        }
+        if let Some(port) = cli.port {
+            config.port = port;
+        }
+        if cli.address.is_some() {
+            config.address = cli.address;
+        }
+        // `RSYNC_CHECKSUM_LIST` / `RSYNC_COMPRESS_LIST`, as for `rsyncd`.
+        config.algorithms = match config.algorithms.clone().with_env() {
+            Ok(algorithms) => algorithms,
+            Err(error) => {
+                eprintln!("aerorsync_serve: {}", error.detail);
+                std::process::exit(2);
+            }
+        };
+        tracing_subscriber::fmt().with_target(false).init();
+
+        let runtime = match tokio::runtime::Runtime::new() {
+            Ok(runtime) => runtime,
+            Err(error) => {
+                eprintln!("cannot start the runtime: {error}");
+                std::process::exit(1);
+            }
+        };
+        let result = runtime.block_on(async move {
+            let listener = bind_daemon(&config).await?;
+            eprintln!(
+                "aerorsync_serve: rsync daemon on {} ({} modules)",
+                listener.local_addr()?,
+                config.modules.len()
+            );
+            serve_daemon(listener, Arc::new(config)).await
+        });
+        if let Err(error) = result {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
     }
 
     pub fn run() {
         let cli = Cli::parse();
+        if cli.daemon {
+            run_daemon(cli);
+            return;
+        }
         if cli.probe {
             // B.4: banner aligned with stock `rsync --version` so
             // `parse_probe_protocol` accepts both peers (dev helper and
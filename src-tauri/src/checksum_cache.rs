@@ -0,0 +1,426 @@
+//! Persistent local checksum cache.
+//!
+//! `sync --checksum`, `check --checksum` and `--track-renames` used to re-hash
+//! every local file on every run. This cache remembers each digest under the
+//! file's identity, `(device, inode, size, mtime_ns)`, in one SQLite database
+//! per local root. Any write that changes the size or the nanosecond mtime
+//! misses the cache on its own. A rename keeps the inode, so the digest
+//! follows the file to its new path. The filesystem watcher also drops the
+//! entries for every path it reports, which covers tools that restore the
+//! mtime after writing.
+//!
+//! Digests are stored per algorithm: SHA-256 and BLAKE3 for the sync engine,
+//! plus any provider-specific hash (`md5`, `sha1`, `quickxor`, ...) a caller
+//! computes itself and records with [`ChecksumCache::put`].
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::Digest;
+use std::fs::Metadata;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Algorithms [`ChecksumCache::checksum`] can compute from local content.
+pub const LOCAL_ALGORITHMS: &[&str] = &["sha256", "blake3", "md5", "sha1", "sha512"];
+
+/// How long a writer waits for another process (GUI, daemon, CLI) holding
+/// the database.
+const BUSY_TIMEOUT_MS: u64 = 5000;
+
+/// Identity of a file's content as far as the filesystem can tell without
+/// reading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileKey {
+    pub dev: u64,
+    pub ino: u64,
+    pub size: u64,
+    pub mtime_ns: i64,
+}
+
+impl FileKey {
+    /// Build the key from `stat` data. Platforms without stable inode
+    /// numbers in std use a hash of the path instead, which still detects
+    /// changes but no longer follows renames.
+    pub fn from_metadata(path: &Path, meta: &Metadata) -> Self {
+        #[cfg(unix)]
+        let (dev, ino, mtime_ns) = {
+            use std::os::unix::fs::MetadataExt;
+            (
+                meta.dev(),
+                meta.ino(),
+                meta.mtime() * 1_000_000_000 + meta.mtime_nsec(),
+            )
+        };
+        #[cfg(not(unix))]
+        let (dev, ino, mtime_ns) = {
+            let digest = sha2::Sha256::digest(path.to_string_lossy().as_bytes());
+            let mut ino = [0u8; 8];
+            ino.copy_from_slice(&digest[..8]);
+            let mtime_ns = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_nanos() as i64)
+                .unwrap_or(0);
+            (0, u64::from_le_bytes(ino), mtime_ns)
+        };
+        #[cfg(unix)]
+        let _ = path;
+        Self {
+            dev,
+            ino,
+            size: meta.len(),
+            mtime_ns,
+        }
+    }
+}
+
+/// Per-root digest cache. Cheap to share behind an `Arc`: the connection
+/// is guarded by a mutex and every call is a single statement.
+pub struct ChecksumCache {
+    root: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+impl std::fmt::Debug for ChecksumCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChecksumCache")
+            .field("root", &self.root)
+            .finish()
+    }
+}
+
+/// `~/.cache/aeroftp/checksums/<hash of the root>.db`
+pub fn cache_db_path(root: &Path) -> Result<PathBuf, String> {
+    let base = dirs::cache_dir().ok_or_else(|| "Cannot determine cache directory".to_string())?;
+    let canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let digest = sha2::Sha256::digest(canonical.to_string_lossy().as_bytes());
+    Ok(base
+        .join("aeroftp")
+        .join("checksums")
+        .join(format!("{}.db", hex::encode(&digest[..8]))))
+}
+
+impl ChecksumCache {
+    /// Open (or create) the cache that belongs to the local tree at `root`.
+    pub fn open_for_root(root: &Path) -> Result<Self, String> {
+        let db_path = cache_db_path(root)?;
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+        }
+        let conn = Connection::open(&db_path)
+            .map_err(|e| format!("Cannot open {}: {}", db_path.display(), e))?;
+        Self::with_connection(root, conn)
+    }
+
+    /// [`ChecksumCache::open_for_root`] for callers that treat the cache as
+    /// an optimisation: a failure is logged and hashing runs uncached.
+    pub fn shared_for_root(root: &Path) -> Option<Arc<Self>> {
+        match Self::open_for_root(root) {
+            Ok(cache) => Some(Arc::new(cache)),
+            Err(e) => {
+                log::warn!("Checksum cache disabled for {}: {}", root.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Cache that lives only as long as the value, for tests and one-shot
+    /// callers.
+    pub fn open_in_memory(root: &Path) -> Result<Self, String> {
+        let conn = Connection::open_in_memory().map_err(|e| format!("SQLite: {e}"))?;
+        Self::with_connection(root, conn)
+    }
+
+    fn with_connection(root: &Path, conn: Connection) -> Result<Self, String> {
+        conn.busy_timeout(std::time::Duration::from_millis(BUSY_TIMEOUT_MS))
+            .map_err(|e| format!("Pragma error: {e}"))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;",
+        )
+        .map_err(|e| format!("Pragma error: {e}"))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checksums (
+                dev INTEGER NOT NULL,
+                ino INTEGER NOT NULL,
+                algorithm TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                mtime_ns INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                hex TEXT NOT NULL,
+                PRIMARY KEY (dev, ino, algorithm)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_checksums_path ON checksums(path);",
+        )
+        .map_err(|e| format!("Schema error: {e}"))?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| {
+            log::warn!("Checksum cache mutex was poisoned, recovering: {e}");
+            e.into_inner()
+        })
+    }
+
+    /// Path of `path` relative to the root, `/`-separated. Paths outside
+    /// the root are kept absolute.
+    fn rel_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    /// Cached digest for a file whose identity is still `key`.
+    pub fn get(&self, key: &FileKey, algorithm: &str) -> Option<String> {
+        self.lock()
+            .query_row(
+                "SELECT hex FROM checksums
+                 WHERE dev = ?1 AND ino = ?2 AND algorithm = ?3
+                   AND size = ?4 AND mtime_ns = ?5",
+                params![
+                    key.dev as i64,
+                    key.ino as i64,
+                    algorithm,
+                    key.size as i64,
+                    key.mtime_ns
+                ],
+                |row| row.get(0),
+            )
+            .optional()
+            .ok()
+            .flatten()
+    }
+
+    /// Record a digest, replacing whatever the same inode held before.
+    pub fn put(
+        &self,
+        path: &Path,
+        key: &FileKey,
+        algorithm: &str,
+        hex: &str,
+    ) -> Result<(), String> {
+        self.lock()
+            .execute(
+                "INSERT OR REPLACE INTO checksums
+                 (dev, ino, algorithm, size, mtime_ns, path, hex)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    key.dev as i64,
+                    key.ino as i64,
+                    algorithm,
+                    key.size as i64,
+                    key.mtime_ns,
+                    self.rel_path(path),
+                    hex
+                ],
+            )
+            .map(|_| ())
+            .map_err(|e| format!("Checksum cache write: {e}"))
+    }
+
+    /// Digest of the file at `path` (with `meta` from the caller's walk),
+    /// read from the cache when the file is unchanged and computed and
+    /// stored otherwise. `algorithm` must be one of [`LOCAL_ALGORITHMS`].
+    pub fn checksum(
+        &self,
+        path: &Path,
+        meta: &Metadata,
+        algorithm: &str,
+    ) -> Result<String, String> {
+        let key = FileKey::from_metadata(path, meta);
+        if let Some(hex) = self.get(&key, algorithm) {
+            return Ok(hex);
+        }
+        let hex = hash_file(path, algorithm)?;
+        // A failed write only costs a re-hash next time.
+        if let Err(e) = self.put(path, &key, algorithm, &hex) {
+            log::debug!("{}", e);
+        }
+        Ok(hex)
+    }
+
+    /// Drop every digest recorded for `path` or, when it is a directory,
+    /// for anything below it. Returns the number of entries removed.
+    pub fn invalidate(&self, path: &Path) -> usize {
+        let rel = self.rel_path(path);
+        let escaped = rel
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        self.lock()
+            .execute(
+                "DELETE FROM checksums WHERE path = ?1 OR path LIKE ?2 ESCAPE '\\'",
+                params![rel, format!("{}/%", escaped)],
+            )
+            .unwrap_or(0)
+    }
+
+    /// Number of stored digests, all algorithms together.
+    pub fn len(&self) -> usize {
+        self.lock()
+            .query_row("SELECT COUNT(*) FROM checksums", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|n| n as usize)
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget everything, e.g. after the tree was restored from elsewhere.
+    pub fn clear(&self) -> Result<(), String> {
+        self.lock()
+            .execute("DELETE FROM checksums", [])
+            .map(|_| ())
+            .map_err(|e| format!("Checksum cache clear: {e}"))
+    }
+}
+
+/// Stream `path` through `algorithm` and return the lowercase hex digest.
+pub fn hash_file(path: &Path, algorithm: &str) -> Result<String, String> {
+    let mut file =
+        std::fs::File::open(path).map_err(|e| format!("Open {}: {}", path.display(), e))?;
+    let mut buf = vec![0u8; 256 * 1024];
+    let mut feed = |update: &mut dyn FnMut(&[u8])| -> Result<(), String> {
+        loop {
+            let n = file
+                .read(&mut buf)
+                .map_err(|e| format!("Read {}: {}", path.display(), e))?;
+            if n == 0 {
+                return Ok(());
+            }
+            update(&buf[..n]);
+        }
+    };
+    match algorithm {
+        "sha256" => {
+            let mut h = sha2::Sha256::new();
+            feed(&mut |b| h.update(b))?;
+            Ok(hex::encode(h.finalize()))
+        }
+        "sha512" => {
+            let mut h = sha2::Sha512::new();
+            feed(&mut |b| h.update(b))?;
+            Ok(hex::encode(h.finalize()))
+        }
+        "sha1" => {
+            let mut h = sha1::Sha1::new();
+            feed(&mut |b| h.update(b))?;
+            Ok(hex::encode(h.finalize()))
+        }
+        "md5" => {
+            let mut h = md5::Md5::new();
+            feed(&mut |b| h.update(b))?;
+            Ok(hex::encode(h.finalize()))
+        }
+        "blake3" => {
+            let mut h = blake3::Hasher::new();
+            feed(&mut |b| {
+                h.update(b);
+            })?;
+            Ok(h.finalize().to_hex().to_string())
+        }
+        other => Err(format!(
+            "Cannot compute '{}' locally (supported: {})",
+            other,
+            LOCAL_ALGORITHMS.join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, data: &[u8]) {
+        std::fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn reuses_digest_until_file_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ChecksumCache::open_in_memory(dir.path()).unwrap();
+        let file = dir.path().join("photo.jpg");
+        write(&file, b"first version");
+
+        let meta = std::fs::metadata(&file).unwrap();
+        let first = cache.checksum(&file, &meta, "sha256").unwrap();
+        assert_eq!(first, hash_file(&file, "sha256").unwrap());
+        assert_eq!(cache.len(), 1);
+
+        // Poison the stored digest: a hit must come from the cache.
+        let key = FileKey::from_metadata(&file, &meta);
+        cache.put(&file, &key, "sha256", "cached").unwrap();
+        assert_eq!(cache.checksum(&file, &meta, "sha256").unwrap(), "cached");
+
+        // Different size: the key no longer matches.
+        write(&file, b"second, longer version");
+        let meta = std::fs::metadata(&file).unwrap();
+        assert_eq!(
+            cache.checksum(&file, &meta, "sha256").unwrap(),
+            hash_file(&file, "sha256").unwrap()
+        );
+
+        let b3 = cache.checksum(&file, &meta, "blake3").unwrap();
+        assert_eq!(
+            b3,
+            blake3::hash(b"second, longer version").to_hex().to_string()
+        );
+        assert_eq!(cache.len(), 2);
+        assert!(cache.checksum(&file, &meta, "quickxor").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn digest_follows_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ChecksumCache::open_in_memory(dir.path()).unwrap();
+        let old = dir.path().join("a.txt");
+        write(&old, b"content");
+        let meta = std::fs::metadata(&old).unwrap();
+        cache
+            .put(&old, &FileKey::from_metadata(&old, &meta), "sha256", "kept")
+            .unwrap();
+
+        let new = dir.path().join("b.txt");
+        std::fs::rename(&old, &new).unwrap();
+        let meta = std::fs::metadata(&new).unwrap();
+        assert_eq!(cache.checksum(&new, &meta, "sha256").unwrap(), "kept");
+    }
+
+    #[test]
+    fn invalidate_drops_path_and_children() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ChecksumCache::open_in_memory(dir.path()).unwrap();
+        std::fs::create_dir_all(dir.path().join("albums/2026")).unwrap();
+        for name in ["albums/2026/a.jpg", "albums/2026/b.jpg", "albums_old.jpg"] {
+            let path = dir.path().join(name);
+            write(&path, name.as_bytes());
+            let meta = std::fs::metadata(&path).unwrap();
+            cache.checksum(&path, &meta, "sha256").unwrap();
+        }
+        assert_eq!(cache.len(), 3);
+
+        assert_eq!(cache.invalidate(&dir.path().join("albums")), 2);
+        assert_eq!(cache.len(), 1, "sibling with a common prefix survives");
+        assert_eq!(cache.invalidate(&dir.path().join("albums_old.jpg")), 1);
+        assert!(cache.is_empty());
+    }
+}
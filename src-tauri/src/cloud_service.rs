@@ -15,6 +15,7 @@ use crate::sync::{
     build_comparison_results, validate_relative_path, CompareDirection, CompareOptions,
     FileComparison, FileInfo, SyncAction, SyncStatus,
 };
+use crate::sync_core::{capture_change_token, refresh_from_change_feed, ChangeFeedState};
 // file_watcher module available for Phase 3A+ watcher integration
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -46,6 +47,91 @@ pub enum SyncTask {
     Stop,
 }
 
+/// Persisted change-feed cursor for AeroCloud, valid only while the
+/// exclusion settings it was seeded with stay the same (excluded folders
+/// are never descended, so their content is missing from the listing).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CloudChangeFeed {
+    exclude_patterns: Vec<String>,
+    excluded_folders: Vec<String>,
+    aeroignore: Option<String>,
+    state: ChangeFeedState,
+}
+
+impl CloudChangeFeed {
+    fn seeded(config: &CloudConfig, state: ChangeFeedState) -> Self {
+        Self {
+            exclude_patterns: config.exclude_patterns.clone(),
+            excluded_folders: config.excluded_folders.clone(),
+            aeroignore: read_aeroignore(config),
+            state,
+        }
+    }
+
+    fn matches(&self, config: &CloudConfig) -> bool {
+        self.state.matches_root(&config.remote_folder)
+            && self.exclude_patterns == config.exclude_patterns
+            && self.excluded_folders == config.excluded_folders
+            && self.aeroignore == read_aeroignore(config)
+    }
+}
+
+fn read_aeroignore(config: &CloudConfig) -> Option<String> {
+    std::fs::read_to_string(config.local_folder.join(".aeroignore")).ok()
+}
+
+fn cloud_change_feed_path() -> PathBuf {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")));
+    config_dir.join("aeroftp").join("cloud_change_feed.json")
+}
+
+fn load_cloud_change_feed(config: &CloudConfig) -> Option<CloudChangeFeed> {
+    let content = std::fs::read_to_string(cloud_change_feed_path()).ok()?;
+    let feed: CloudChangeFeed = serde_json::from_str(&content).ok()?;
+    feed.matches(config).then_some(feed)
+}
+
+fn save_cloud_change_feed(feed: &CloudChangeFeed) {
+    let path = cloud_change_feed_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let Ok(content) = serde_json::to_string(feed) else {
+        return;
+    };
+    // Atomic write: temp file + rename, same as cloud_config.json
+    let tmp_path = path.with_extension("tmp");
+    if std::fs::write(&tmp_path, content).is_ok() {
+        let _ = std::fs::rename(&tmp_path, &path);
+    }
+}
+
+fn clear_cloud_change_feed() {
+    let _ = std::fs::remove_file(cloud_change_feed_path());
+}
+
+/// Parse the timestamp formats providers return in `RemoteEntry::modified`.
+fn parse_remote_mtime(s: &str) -> Option<DateTime<Utc>> {
+    // Try RFC 3339 first (with T separator)
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+        .or_else(|| {
+            // Fallback: replace space with T for timestamps like "2026-03-12 00:00:00Z"
+            let fixed = s.replacen(' ', "T", 1);
+            DateTime::parse_from_rfc3339(&fixed)
+                .ok()
+                .map(|dt| dt.with_timezone(&Utc))
+        })
+        .or_else(|| {
+            // Fallback: parse without timezone (assume UTC)
+            chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|naive| naive.and_utc())
+        })
+}
+
 /// Generate a Dropbox-style conflict filename.
 /// Example: `report.pdf` → `report (AeroCloud conflict 2026-03-26 14-30-22 myhost).pdf`
 fn conflict_rename(local_path: &Path) -> String {
@@ -370,10 +456,7 @@ impl CloudService {
 
         // Get file listings
         let local_files = self.scan_local_folder(&config).await?;
-        let remote_files = match self
-            .scan_remote_folder_with_provider(provider, &config)
-            .await
-        {
+        let remote_files = match self.scan_remote_folder_incremental(provider, &config).await {
             Ok(files) => files,
             Err(e) => {
                 // Propagate OAuth 1.0a token revocation to the UI before returning.
@@ -648,6 +731,7 @@ impl CloudService {
                         modified,
                         is_dir,
                         checksum: None,
+                        meta: None,
                     },
                 );
 
@@ -756,6 +840,7 @@ impl CloudService {
                         }),
                         is_dir: entry.is_dir,
                         checksum: None,
+                        meta: None,
                     },
                 );
 
@@ -823,11 +908,16 @@ impl CloudService {
         // Execute action
         match &action {
             SyncAction::Upload => {
-                let remote_path = format!(
-                    "{}/{}",
-                    config.remote_folder.trim_end_matches('/'),
-                    comparison.relative_path
-                );
+                // Overwrite an existing remote file under its own spelling
+                // (it may differ from the local name by Unicode normalization).
+                let remote_path = match &comparison.remote_info {
+                    Some(info) if !comparison.is_dir => info.path.clone(),
+                    _ => format!(
+                        "{}/{}",
+                        config.remote_folder.trim_end_matches('/'),
+                        comparison.relative_path
+                    ),
+                };
 
                 if comparison.is_dir {
                     // Create remote directory
@@ -947,13 +1037,139 @@ impl CloudService {
         Ok(action)
     }
 
-    /// Scan remote folder using any StorageProvider (multi-protocol support)
-    async fn scan_remote_folder_with_provider<P: StorageProvider + ?Sized>(
+    /// Remote listing for providers with a change feed (Google Drive):
+    /// replays the changes since the previous sync onto the persisted
+    /// listing instead of walking the whole tree. Falls back to a full
+    /// scan (which seeds a fresh cursor) when there is no cursor yet, the
+    /// token expired or a change cannot be placed.
+    async fn scan_remote_folder_incremental<P: StorageProvider + ?Sized>(
         &self,
         provider: &mut P,
         config: &CloudConfig,
     ) -> Result<HashMap<String, FileInfo>, String> {
+        if !provider.supports_change_tracking() {
+            return self
+                .scan_remote_folder_with_provider(provider, config, None)
+                .await
+                .map(|(files, _)| files);
+        }
+
+        if let Some(mut feed) = load_cloud_change_feed(config) {
+            match refresh_from_change_feed(provider, &mut feed.state).await {
+                Ok(stats) => {
+                    tracing::info!(
+                        "AeroCloud change feed: {} change(s), {} updated, {} removed",
+                        stats.changes_seen,
+                        stats.upserted,
+                        stats.removed
+                    );
+                    save_cloud_change_feed(&feed);
+                    return Ok(Self::files_from_change_feed(&feed.state, config));
+                }
+                Err(e) => tracing::info!("{}; falling back to full remote scan", e),
+            }
+        }
+
+        // Capture the cursor before scanning so anything that changes
+        // mid-scan is replayed next time rather than lost.
+        let mut seed = capture_change_token(provider)
+            .await
+            .map(|token| ChangeFeedState::new(token, &config.remote_folder));
+        let (files, complete) = self
+            .scan_remote_folder_with_provider(provider, config, seed.as_mut())
+            .await?;
+        match seed {
+            Some(state) if complete => {
+                save_cloud_change_feed(&CloudChangeFeed::seeded(config, state))
+            }
+            _ => clear_cloud_change_feed(),
+        }
+        Ok(files)
+    }
+
+    /// Materialize the change-feed listing into the comparison map. The
+    /// listing is stored unfiltered, so exclusions are applied here.
+    fn files_from_change_feed(
+        state: &ChangeFeedState,
+        config: &CloudConfig,
+    ) -> HashMap<String, FileInfo> {
+        let aeroignore = crate::sync_ignore::AeroIgnore::load(&config.local_folder);
+        let base_path = config.remote_folder.trim_end_matches('/');
+        let pattern_excluded = |path: &str, is_dir: bool| {
+            if let Some(ref ai) = aeroignore {
+                ai.should_exclude(path, is_dir, &config.exclude_patterns)
+            } else {
+                crate::sync::should_exclude(path, &config.exclude_patterns)
+            }
+        };
+        // Mirror the full scan: an excluded folder hides its whole subtree,
+        // while `excluded_folders` keeps the folder itself but not its content.
+        let is_excluded = |relative_path: &str, is_dir: bool| {
+            if pattern_excluded(relative_path, is_dir) {
+                return true;
+            }
+            let mut ancestor_end = relative_path.find('/');
+            while let Some(end) = ancestor_end {
+                let ancestor = &relative_path[..end];
+                if pattern_excluded(ancestor, true)
+                    || config
+                        .excluded_folders
+                        .iter()
+                        .any(|ef| ef.trim_matches('/') == ancestor)
+                {
+                    return true;
+                }
+                ancestor_end = relative_path[end + 1..].find('/').map(|i| end + 1 + i);
+            }
+            false
+        };
+        let file_info =
+            |relative_path: &str, size: u64, modified: Option<&str>, is_dir: bool| FileInfo {
+                name: relative_path
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(relative_path)
+                    .to_string(),
+                path: format!("{}/{}", base_path, relative_path),
+                size,
+                modified: modified.and_then(parse_remote_mtime),
+                is_dir,
+                checksum: None,
+                meta: None,
+            };
+
+        let mut files = HashMap::new();
+        for node in state.ids.values().filter(|n| n.is_dir) {
+            if !is_excluded(&node.rel_path, true) {
+                files.insert(
+                    node.rel_path.clone(),
+                    file_info(&node.rel_path, 0, None, true),
+                );
+            }
+        }
+        for (relative_path, file) in &state.files {
+            if !is_excluded(relative_path, false) {
+                files.insert(
+                    relative_path.clone(),
+                    file_info(relative_path, file.size, file.mtime.as_deref(), false),
+                );
+            }
+        }
+        files
+    }
+
+    /// Scan remote folder using any StorageProvider (multi-protocol support).
+    /// When `seed` is given, every listed entry is also recorded into it for
+    /// later change-feed runs. The returned flag is false when the scan was truncated
+    /// or a folder could not be listed.
+    async fn scan_remote_folder_with_provider<P: StorageProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+        config: &CloudConfig,
+        mut seed: Option<&mut ChangeFeedState>,
+    ) -> Result<(HashMap<String, FileInfo>, bool), String> {
         let mut files = HashMap::new();
+        let mut complete = true;
         let base_path = &config.remote_folder;
         // Load .aeroignore from local sync root (applies to remote paths too)
         let aeroignore = crate::sync_ignore::AeroIgnore::load(&config.local_folder);
@@ -970,18 +1186,23 @@ impl CloudService {
         while let Some((current_path, relative_prefix, depth)) = stack.pop() {
             if depth > MAX_DEPTH {
                 tracing::warn!("Remote scan depth limit reached at {}", current_path);
+                complete = false;
                 continue;
             }
 
             // Navigate to directory
             if provider.cd(&current_path).await.is_err() {
+                complete = false;
                 continue;
             }
 
             // List files using provider
             let entries = match provider.list(".").await {
                 Ok(list) => list,
-                Err(_) => continue,
+                Err(_) => {
+                    complete = false;
+                    continue;
+                }
             };
 
             for entry in entries {
@@ -991,6 +1212,17 @@ impl CloudService {
                     format!("{}/{}", relative_prefix, entry.name)
                 };
 
+                // Seed the change-feed listing before exclusions; they are
+                // re-applied when the listing is materialized
+                if let Some(state) = seed.as_deref_mut() {
+                    let id = entry.metadata.get("id").map(String::as_str);
+                    if entry.is_dir {
+                        state.record_dir(&relative_path, id);
+                    } else {
+                        state.record_file(&relative_path, entry.size, entry.modified.clone(), id);
+                    }
+                }
+
                 // Check exclusions: .aeroignore first, then config patterns
                 let excluded = if let Some(ref ai) = aeroignore {
                     ai.should_exclude(&relative_path, entry.is_dir, &config.exclude_patterns)
@@ -1004,7 +1236,7 @@ impl CloudService {
                 // P1-6: Cap file index at 100K to prevent unbounded memory growth
                 if files.len() >= 100_000 {
                     tracing::warn!("Remote file index cap reached (100K), truncating scan");
-                    return Ok(files);
+                    return Ok((files, false));
                 }
 
                 files.insert(
@@ -1013,29 +1245,12 @@ impl CloudService {
                         name: entry.name.clone(),
                         path: format!("{}/{}", current_path, entry.name),
                         size: entry.size,
-                        modified: entry.modified.and_then(|s| {
-                            // Try RFC 3339 first (with T separator)
-                            DateTime::parse_from_rfc3339(&s)
-                                .ok()
-                                .map(|dt| dt.with_timezone(&Utc))
-                                .or_else(|| {
-                                    // Fallback: replace space with T for timestamps like "2026-03-12 00:00:00Z"
-                                    let fixed = s.replacen(' ', "T", 1);
-                                    DateTime::parse_from_rfc3339(&fixed)
-                                        .ok()
-                                        .map(|dt| dt.with_timezone(&Utc))
-                                })
-                                .or_else(|| {
-                                    // Fallback: parse without timezone (assume UTC)
-                                    chrono::NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S")
-                                        .ok()
-                                        .map(|naive| naive.and_utc())
-                                })
-                        }),
+                        modified: entry.modified.as_deref().and_then(parse_remote_mtime),
                         is_dir: entry.is_dir,
                         // Use provider-supplied content hash if available (e.g. FileLu).
                         // Enables hash-based comparison for providers that don't preserve mtime.
                         checksum: entry.metadata.get("content_hash").cloned(),
+                        meta: None,
                     },
                 );
 
@@ -1060,7 +1275,7 @@ impl CloudService {
             }
         }
 
-        Ok(files)
+        Ok((files, complete))
     }
 
     /// Process a single file comparison using any StorageProvider
@@ -1103,11 +1318,16 @@ impl CloudService {
         // Execute action using provider methods
         match &action {
             SyncAction::Upload => {
-                let remote_path = format!(
-                    "{}/{}",
-                    config.remote_folder.trim_end_matches('/'),
-                    comparison.relative_path
-                );
+                // Overwrite an existing remote file under its own spelling
+                // (it may differ from the local name by Unicode normalization).
+                let remote_path = match &comparison.remote_info {
+                    Some(info) if !comparison.is_dir => info.path.clone(),
+                    _ => format!(
+                        "{}/{}",
+                        config.remote_folder.trim_end_matches('/'),
+                        comparison.relative_path
+                    ),
+                };
 
                 if comparison.is_dir {
                     // Create remote directory
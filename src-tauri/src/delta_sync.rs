@@ -174,6 +174,18 @@ pub fn compute_delta(
     source_data: &[u8],
     sig_table: &SignatureTable,
 ) -> (Vec<DeltaOp>, DeltaResult) {
+    compute_delta_with_strong_len(source_data, sig_table, 32)
+}
+
+/// `compute_delta` against signatures whose strong hash only carries its
+/// first `strong_len` bytes (the rest zero), as when they were truncated
+/// for the wire. Only that prefix is compared.
+pub fn compute_delta_with_strong_len(
+    source_data: &[u8],
+    sig_table: &SignatureTable,
+    strong_len: usize,
+) -> (Vec<DeltaOp>, DeltaResult) {
+    let strong_len = strong_len.clamp(1, 32);
     let block_size = sig_table.block_size;
     let lookup = build_rolling_lookup(&sig_table.signatures);
 
@@ -221,7 +233,7 @@ pub fn compute_delta(
             let strong = strong_hash(window);
 
             for &idx in candidates {
-                if sig_table.signatures[idx].strong == strong {
+                if sig_table.signatures[idx].strong[..strong_len] == strong[..strong_len] {
                     // Match found! Flush literal buffer first
                     if !literal_buf.is_empty() {
                         literal_total += literal_buf.len() as u64;
@@ -475,6 +487,30 @@ mod tests {
         assert_eq!(reconstructed, modified);
     }
 
+    #[test]
+    fn test_truncated_strong_hash_delta() {
+        let mut original = vec![0u8; 4096];
+        for (i, byte) in original.iter_mut().enumerate() {
+            *byte = (i % 251) as u8;
+        }
+        let mut modified = original.clone();
+        modified[2000] ^= 0xFF;
+
+        let mut sigs = compute_signatures(&original, 512);
+        for sig in &mut sigs.signatures {
+            sig.strong[16..].fill(0);
+        }
+
+        // Full-length comparison misses every truncated signature
+        let (_, full) = compute_delta(&modified, &sigs);
+        assert_eq!(full.copy_blocks, 0);
+
+        let (ops, result) = compute_delta_with_strong_len(&modified, &sigs, 16);
+        assert_eq!(result.copy_blocks, 7);
+        let reconstructed = apply_delta(&original, &ops, 512).unwrap();
+        assert_eq!(reconstructed, modified);
+    }
+
     #[test]
     fn test_completely_different_file() {
         let original = vec![0u8; 4096];
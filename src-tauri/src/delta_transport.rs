@@ -63,6 +63,31 @@ pub trait DeltaTransport: Send + Sync {
     /// Upload `local_path` to `remote_path` with delta semantics.
     async fn upload(&self, local_path: &Path, remote_path: &str) -> Result<RsyncStats, RsyncError>;
 
+    /// Download the whole directory `remote_dir` into `local_dir` in one
+    /// session. Default impl returns a soft `TransferFailed`: callers fall
+    /// back to per-file [`download`](Self::download) calls.
+    async fn download_tree(
+        &self,
+        remote_dir: &str,
+        local_dir: &Path,
+    ) -> Result<RsyncStats, RsyncError> {
+        let _ = (remote_dir, local_dir);
+        Err(tree_unsupported(self.name()))
+    }
+
+    /// Upload the whole directory `local_dir` into `remote_dir` in one
+    /// session. Same default as [`download_tree`].
+    ///
+    /// [`download_tree`]: Self::download_tree
+    async fn upload_tree(
+        &self,
+        local_dir: &Path,
+        remote_dir: &str,
+    ) -> Result<RsyncStats, RsyncError> {
+        let _ = (local_dir, remote_dir);
+        Err(tree_unsupported(self.name()))
+    }
+
     /// Begin a session-reuse batch.
     ///
     /// Default impl returns a [`NoopBatch`], a marker that signals the
@@ -86,6 +111,13 @@ pub trait DeltaTransport: Send + Sync {
     }
 }
 
+fn tree_unsupported(transport: &str) -> RsyncError {
+    RsyncError::TransferFailed {
+        exit: -1,
+        stderr: format!("{transport}: directory-tree transfers not supported"),
+    }
+}
+
 /// Stats accumulated by a [`DeltaBatch`] from creation to [`finalize`].
 ///
 /// `session_count` is the headline metric for P3-T01 W3: when the batch reuses
@@ -321,6 +353,21 @@ mod tests {
         assert_eq!(t.name(), "rsync-binary-over-ssh");
     }
 
+    #[tokio::test]
+    async fn default_tree_transfers_are_soft_failures() {
+        let t = RsyncBinaryTransport::new(test_config(), None);
+        match t.download_tree("/remote/dir", Path::new("/tmp/x")).await {
+            Err(RsyncError::TransferFailed { stderr, .. }) => {
+                assert!(stderr.contains("rsync-binary-over-ssh"), "{stderr}");
+            }
+            other => panic!("expected TransferFailed, got {:?}", other),
+        }
+        assert!(matches!(
+            t.upload_tree(Path::new("/tmp/x"), "/remote/dir").await,
+            Err(RsyncError::TransferFailed { .. })
+        ));
+    }
+
     // P3-T01 W3.1: DeltaBatch / NoopBatch / default begin_batch tests.
     // The first two pin the compat contract: any transport that does NOT
     // override `begin_batch` (today: every transport, including
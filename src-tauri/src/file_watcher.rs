@@ -5,6 +5,7 @@
 // Dropbox-style real-time change detection using notify v6 + notify-debouncer-full 0.6
 // Replaces dead watcher.rs (226 lines) with production-grade implementation
 
+use crate::checksum_cache::ChecksumCache;
 #[cfg(test)]
 use notify::event::{CreateKind, RemoveKind};
 use notify::event::{ModifyKind, RenameMode};
@@ -223,6 +224,19 @@ pub fn should_exclude_path(path: &Path) -> bool {
     false
 }
 
+/// Drop cached digests for every path an event touched, before any
+/// filtering: a temp file renamed over a real one still reports the target.
+fn invalidate_checksums<'a>(
+    cache: &Option<Arc<ChecksumCache>>,
+    paths: impl IntoIterator<Item = &'a PathBuf>,
+) {
+    if let Some(cache) = cache {
+        for path in paths {
+            cache.invalidate(path);
+        }
+    }
+}
+
 /// Deduplicate paths from multiple events into a unique set
 fn deduplicate_paths(events: &[DebouncedEvent]) -> Vec<PathBuf> {
     let mut seen = HashSet::new();
@@ -291,6 +305,8 @@ pub struct FileWatcher {
     last_event_at: Arc<std::sync::Mutex<Option<Instant>>>,
     /// Running flag
     running: Arc<AtomicBool>,
+    /// Local checksum cache invalidated by every event
+    checksum_cache: Option<Arc<ChecksumCache>>,
 }
 
 impl FileWatcher {
@@ -308,9 +324,17 @@ impl FileWatcher {
             events_received: Arc::new(std::sync::atomic::AtomicU64::new(0)),
             last_event_at: Arc::new(std::sync::Mutex::new(None)),
             running: Arc::new(AtomicBool::new(false)),
+            checksum_cache: None,
         }
     }
 
+    /// Invalidate `cache` entries for every changed path, so the next
+    /// checksum scan re-hashes exactly the files touched since the last one.
+    pub fn with_checksum_cache(mut self, cache: Arc<ChecksumCache>) -> Self {
+        self.checksum_cache = Some(cache);
+        self
+    }
+
     /// Start watching a directory.
     ///
     /// In `Auto` mode, checks inotify capacity on Linux and falls back
@@ -369,6 +393,7 @@ impl FileWatcher {
         let tx = self.event_tx.clone();
         let events_received = self.events_received.clone();
         let last_event_at = self.last_event_at.clone();
+        let checksum_cache = self.checksum_cache.clone();
 
         let mut debouncer = new_debouncer(
             DEBOUNCE_TIMEOUT,
@@ -376,6 +401,10 @@ impl FileWatcher {
             move |result: DebounceEventResult| {
                 match result {
                     Ok(events) => {
+                        invalidate_checksums(
+                            &checksum_cache,
+                            events.iter().flat_map(|e| e.event.paths.iter()),
+                        );
                         let paths = deduplicate_paths(&events);
                         if paths.is_empty() {
                             return;
@@ -422,12 +451,14 @@ impl FileWatcher {
         let tx = self.event_tx.clone();
         let events_received = self.events_received.clone();
         let last_event_at = self.last_event_at.clone();
+        let checksum_cache = self.checksum_cache.clone();
 
         let config = Config::default().with_poll_interval(POLL_INTERVAL);
 
         let mut watcher = PollWatcher::new(
             move |result: Result<notify::Event, notify::Error>| match result {
                 Ok(event) => {
+                    invalidate_checksums(&checksum_cache, &event.paths);
                     let paths: Vec<PathBuf> = event
                         .paths
                         .into_iter()
@@ -658,4 +689,20 @@ mod tests {
 
         watcher.stop(); // Should not panic on double-stop
     }
+
+    #[test]
+    fn test_events_invalidate_checksum_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(ChecksumCache::open_in_memory(dir.path()).unwrap());
+        let file = dir.path().join("a.txt");
+        std::fs::write(&file, b"a").unwrap();
+        let meta = std::fs::metadata(&file).unwrap();
+        cache.checksum(&file, &meta, "sha256").unwrap();
+
+        // Only the reported path loses its digest.
+        invalidate_checksums(&Some(cache.clone()), &[dir.path().join("a.txt.tmp")]);
+        assert_eq!(cache.len(), 1);
+        invalidate_checksums(&Some(cache.clone()), &[file]);
+        assert!(cache.is_empty());
+    }
 }
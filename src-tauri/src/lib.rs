@@ -31,7 +31,9 @@ pub mod ai_core;
 pub mod ai_stream;
 mod ai_tools;
 mod archive_browse;
+pub mod backup;
 mod chat_history;
+pub mod checksum_cache;
 mod cloud_config;
 mod cloud_provider_factory;
 mod cloud_service;
@@ -54,6 +56,7 @@ mod delta_sync;
 // the `DeltaTransport` trait are cross-platform so the provider
 // `delta_transport()` method can stay cross-OS.
 pub mod delta_sync_rsync;
+pub mod delta_sync_s3;
 pub mod delta_transport;
 mod number_parsing;
 pub mod portable;
@@ -106,16 +109,21 @@ mod ssh_shell;
 pub mod sync;
 mod sync_badge;
 pub mod sync_core;
-mod sync_ignore;
-mod sync_scheduler;
-mod sync_versioning;
+pub mod sync_hooks;
+pub mod sync_ignore;
+pub mod sync_job_file;
+pub mod sync_planner;
+pub mod sync_scheduler;
+pub mod sync_versioning;
 mod totp;
 mod transfer_domain;
 mod transfer_orchestrator;
 mod transfer_pool;
 mod transfer_settings;
 mod tray_badge;
-mod vault_remote;
+pub mod vault_mount;
+pub mod vault_remote;
+pub mod vfs_cache;
 mod windows_acl;
 pub mod winscp_import;
 #[cfg(target_os = "macos")]
@@ -8687,6 +8695,7 @@ pub async fn get_local_files_recursive_with_progress(
                 modified,
                 is_dir,
                 checksum,
+                meta: None,
             };
 
             // P2-1: Cap file index at 1M entries to prevent unbounded memory growth
@@ -8824,6 +8833,7 @@ pub async fn get_local_files_recursive_parallel(
                         modified,
                         is_dir: true,
                         checksum: None,
+                        meta: None,
                     },
                 );
                 continue;
@@ -8855,6 +8865,7 @@ pub async fn get_local_files_recursive_parallel(
                         modified,
                         is_dir: false,
                         checksum,
+                        meta: None,
                     },
                 );
             }
@@ -8875,6 +8886,7 @@ pub async fn get_local_files_recursive_parallel(
                     modified,
                     is_dir,
                     checksum: None,
+                    meta: None,
                 },
             );
         }
@@ -8971,6 +8983,7 @@ async fn get_remote_files_recursive_with_progress(
                 }),
                 is_dir: entry.is_dir,
                 checksum: None,
+                meta: None,
             };
 
             files.insert(relative_path, file_info);
@@ -9528,6 +9541,64 @@ fn import_sync_script_cmd(script_content: String) -> Result<sync::SyncScriptMeta
     sync::import_sync_script(&script_content)
 }
 
+#[derive(serde::Deserialize)]
+struct SyncJobExportArgs {
+    profile_id: String,
+    /// Saved server profile the job connects with, if known.
+    server_profile: Option<String>,
+    name: String,
+    description: String,
+    local_path: String,
+    remote_path: String,
+    exclude_patterns: Vec<String>,
+    /// Panel conflict strategy (`ask`, `newer`, ...).
+    #[serde(default)]
+    conflict_strategy: Option<String>,
+    /// Panel versioning strategy (`disabled`, `trash_can`, ...).
+    #[serde(default)]
+    versioning_strategy: Option<String>,
+    format: String,
+}
+
+#[derive(serde::Serialize)]
+struct SyncJobExport {
+    content: String,
+    /// The panel archives local files before a download changes them. Job
+    /// files have no local versioning, so the setting was left out.
+    versioning_left_out: bool,
+}
+
+#[tauri::command]
+fn export_sync_job_file_cmd(args: SyncJobExportArgs) -> Result<SyncJobExport, String> {
+    let format = sync_job_file::JobFileFormat::parse(&args.format)
+        .ok_or_else(|| format!("Unsupported job file format: {}", args.format))?;
+    let profiles = sync::load_sync_profiles()?;
+    let profile = profiles
+        .iter()
+        .find(|p| p.id == args.profile_id)
+        .ok_or_else(|| format!("Profile '{}' not found", args.profile_id))?;
+    let content = sync_job_file::SyncJobFile::from_profile(
+        &args.name,
+        &args.description,
+        profile,
+        args.server_profile.as_deref(),
+        &args.local_path,
+        &args.remote_path,
+        &args.exclude_patterns,
+        args.conflict_strategy.as_deref(),
+    )
+    .render(format)?;
+    let versioning_left_out = args
+        .versioning_strategy
+        .as_deref()
+        .and_then(sync_versioning::VersioningStrategy::from_name)
+        .is_some_and(|s| s != sync_versioning::VersioningStrategy::Disabled);
+    Ok(SyncJobExport {
+        content,
+        versioning_left_out,
+    })
+}
+
 // =============================
 // Rollback Commands (#154)
 // =============================
@@ -9625,12 +9696,8 @@ fn filter_snapshots_for_pair(
 fn parse_versioning_strategy(
     versioning_strategy: Option<&str>,
 ) -> sync_versioning::VersioningStrategy {
-    match versioning_strategy.unwrap_or("trash_can") {
-        "disabled" => sync_versioning::VersioningStrategy::Disabled,
-        "simple" => sync_versioning::VersioningStrategy::Simple { max_copies: 5 },
-        "staggered" => sync_versioning::VersioningStrategy::Staggered,
-        _ => sync_versioning::VersioningStrategy::TrashCan { max_age_days: 30 },
-    }
+    sync_versioning::VersioningStrategy::from_name(versioning_strategy.unwrap_or("trash_can"))
+        .unwrap_or_default()
 }
 
 fn local_file_matches_snapshot(
@@ -11566,6 +11633,9 @@ async fn background_sync_worker(app: AppHandle) {
         if config.sync_on_change {
             let local_path = config.local_folder.clone();
             let mut fw = file_watcher::FileWatcher::new(watcher_tx.clone());
+            if let Some(cache) = checksum_cache::ChecksumCache::shared_for_root(&local_path) {
+                fw = fw.with_checksum_cache(cache);
+            }
             match fw.start(&local_path, file_watcher::WatcherMode::Auto) {
                 Ok(()) => {
                     info!("Filesystem watcher active on {}", local_path.display());
@@ -13924,6 +13994,7 @@ pub fn run() {
             export_sync_template_cmd,
             import_sync_template_cmd,
             export_sync_script_cmd,
+            export_sync_job_file_cmd,
             import_sync_script_cmd,
             flatten_local_descendants,
             create_sync_snapshot_cmd,
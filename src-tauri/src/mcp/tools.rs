@@ -157,10 +157,42 @@ pub fn tool_definitions() -> Vec<McpToolDef> {
                     "description": "Restrict the sync to this exact set of relative paths (e.g. ['app/foo.ts', 'static/bar.css']). Paths are relative to local_dir/remote_dir. Maps the CLI --files-from flag: use it when the agent already knows which 15 files need syncing and wants to skip the full-tree scan cost."
                 },
                 "max_depth": { "type": "integer", "description": "Max recursion depth (default: 100)" },
-                "summary_only": { "type": "boolean", "description": "Return only summary/planned counters and drop the per-entry plan/errors arrays. Use for large scopes where the full response would exceed MCP size limits. Default: false." }
+                "summary_only": { "type": "boolean", "description": "Return only summary/planned counters and drop the per-entry plan/errors arrays. Use for large scopes where the full response would exceed MCP size limits. Default: false." },
+                "remote_versioning": { "type": "string", "description": "Archive remote files that this sync overwrites or deletes into <remote_dir>/.aeroversions/ instead of losing them. One of disabled (default), trash_can, simple, staggered; trash_can:<days> and simple:<copies> override the retention, which is applied after every sync without errors (summary.versions_pruned). Restore with aeroftp_restore_remote_version." }
             }, "required": ["server", "local_dir", "remote_dir", "direction"] }),
             category: RateCategory::Mutative,
         },
+        McpToolDef {
+            name: "aeroftp_list_remote_versions",
+            description: "List file versions archived on the remote by aeroftp_sync_tree with remote_versioning (stored under <remote_dir>/.aeroversions/). Returns {versions: [{archive_path, original_relative, archived_at, size}]} newest first. Pass `path` to list the versions of a single file.",
+            input_schema: json!({ "type": "object", "properties": {
+                "server": { "type": "string", "description": "Server name or ID" },
+                "remote_dir": { "type": "string", "description": "Remote sync root that holds .aeroversions/" },
+                "path": { "type": "string", "description": "Relative path of one file (default: all archived files)" }
+            }, "required": ["server", "remote_dir"] }),
+            category: RateCategory::ReadOnly,
+        },
+        McpToolDef {
+            name: "aeroftp_restore_remote_version",
+            description: "Restore a remote file from <remote_dir>/.aeroversions/ to its original path. The newest version is used unless `archived_at` (as returned by aeroftp_list_remote_versions) is given. The current remote file, if any, is archived first so the restore itself can be undone.",
+            input_schema: json!({ "type": "object", "properties": {
+                "server": { "type": "string", "description": "Server name or ID" },
+                "remote_dir": { "type": "string", "description": "Remote sync root that holds .aeroversions/" },
+                "path": { "type": "string", "description": "Relative path of the file to restore" },
+                "archived_at": { "type": "string", "description": "Version timestamp to restore (default: newest)" }
+            }, "required": ["server", "remote_dir", "path"] }),
+            category: RateCategory::Mutative,
+        },
+        McpToolDef {
+            name: "aeroftp_cleanup_remote_versions",
+            description: "Apply a retention strategy to <remote_dir>/.aeroversions/ and delete the versions it no longer keeps. Returns {deleted_count, freed_bytes}.",
+            input_schema: json!({ "type": "object", "properties": {
+                "server": { "type": "string", "description": "Server name or ID" },
+                "remote_dir": { "type": "string", "description": "Remote sync root that holds .aeroversions/" },
+                "strategy": { "type": "string", "description": "trash_can (default, 30 days), simple (5 copies), staggered; trash_can:<days> and simple:<copies> override the retention" }
+            }, "required": ["server", "remote_dir"] }),
+            category: RateCategory::Destructive,
+        },
         // upload_many + edit live in MCP_TOOL_DEFS to keep the rich
         // descriptions / nested item-schema (no_clobber inside items[],
         // first-only flag) that downstream agents and the parity tests
@@ -685,7 +717,11 @@ pub async fn execute_tool(
             finish(tool_name, Some(&server), None, result, start)
         }
         "aeroftp_check_tree" => {
-            use crate::sync_core::{compare_trees, scan_local_tree, scan_remote_tree, ScanOptions};
+            use crate::checksum_cache::ChecksumCache;
+            use crate::sync_core::{
+                compare_trees_with, local_fs_case_insensitive, scan_local_tree, scan_remote_tree,
+                ScanOptions,
+            };
 
             let server = match get_str(args, "server") {
                 Ok(s) => s,
@@ -778,6 +814,9 @@ pub async fn execute_tool(
                 files_from,
                 compute_checksum: checksum,
                 compute_remote_checksum: checksum,
+                checksum_cache: checksum
+                    .then(|| ChecksumCache::shared_for_root(std::path::Path::new(&local_dir)))
+                    .flatten(),
                 ..Default::default()
             };
 
@@ -788,7 +827,8 @@ pub async fn execute_tool(
                     let supports_remote_checksum = p.supports_checksum();
                     let locals = scan_local_tree(&local_dir, &opts);
                     let remotes = scan_remote_tree(&mut p, &remote_dir, &opts).await;
-                    let diff = compare_trees(&locals, &remotes, one_way);
+                    let case_insensitive = p.is_case_insensitive() || local_fs_case_insensitive();
+                    let diff = compare_trees_with(&locals, &remotes, one_way, case_insensitive);
                     if summary_only {
                         // Skip the per-entry arrays entirely. Agents call
                         // check_tree on large scopes (thousands of files) just
@@ -892,6 +932,7 @@ pub async fn execute_tool(
             finish(tool_name, Some(&server), Some(&remote_dir), result, start)
         }
         "aeroftp_sync_tree" => {
+            use crate::checksum_cache::ChecksumCache;
             use crate::sync_core::{
                 sync_tree_core, ConflictMode, DeltaPolicy, ScanOptions, SyncDirection, SyncOptions,
             };
@@ -1004,6 +1045,25 @@ pub async fn execute_tool(
                 .and_then(|v| v.as_u64())
                 .map(|n| n as usize);
             let summary_only = get_bool_opt(args, "summary_only").unwrap_or(false);
+            let remote_versioning_raw =
+                get_str_opt(args, "remote_versioning").unwrap_or_else(|| "disabled".into());
+            let remote_versioning = match crate::sync_versioning::VersioningStrategy::from_name(
+                &remote_versioning_raw,
+            ) {
+                Some(strategy) => strategy,
+                None => {
+                    return finish(
+                        tool_name,
+                        Some(&server),
+                        Some(&remote_dir),
+                        err(format!(
+                            "Invalid remote_versioning '{}': expected disabled, trash_can, simple, or staggered",
+                            remote_versioning_raw
+                        )),
+                        start,
+                    );
+                }
+            };
             if !std::path::Path::new(&local_dir).is_dir() {
                 return finish(
                     tool_name,
@@ -1026,8 +1086,13 @@ pub async fn execute_tool(
                     max_depth,
                     compute_checksum: delta_policy.wants_checksums(),
                     compute_remote_checksum: delta_policy.wants_checksums(),
+                    checksum_cache: delta_policy
+                        .wants_checksums()
+                        .then(|| ChecksumCache::shared_for_root(std::path::Path::new(&local_dir)))
+                        .flatten(),
                     ..Default::default()
                 },
+                remote_versioning,
             };
 
             // Entry-time pool invalidate. Wave-1 added an end-of-run
@@ -1209,6 +1274,9 @@ pub async fn execute_tool(
                                 "elapsed_secs": report.elapsed_secs,
                             }),
                         );
+                        if report.versions_pruned > 0 {
+                            summary.insert("versions_pruned".into(), report.versions_pruned.into());
+                        }
                         if let Some(savings) = report.delta_savings.as_ref() {
                             // bytes_saved surfaced as a signed integer so
                             // rsync overhead (total_bytes_sent > total_size,
@@ -1293,6 +1361,119 @@ pub async fn execute_tool(
             };
             finish(tool_name, Some(&server), Some(&remote_dir), result, start)
         }
+        "aeroftp_list_remote_versions"
+        | "aeroftp_restore_remote_version"
+        | "aeroftp_cleanup_remote_versions" => {
+            use crate::sync_versioning::{SyncVersioning, VersioningStrategy};
+
+            let server = match get_str(args, "server") {
+                Ok(s) => s,
+                Err(e) => return finish(tool_name, None, None, err(e), start),
+            };
+            let remote_dir = match get_str(args, "remote_dir") {
+                Ok(s) => s,
+                Err(e) => return finish(tool_name, Some(&server), None, err(e), start),
+            };
+            if let Err(e) = validate_sp(&server, Some(&remote_dir)) {
+                return finish(tool_name, Some(&server), Some(&remote_dir), err(e), start);
+            }
+            let path = get_str_opt(args, "path");
+            if tool_name == "aeroftp_restore_remote_version" && path.is_none() {
+                return finish(
+                    tool_name,
+                    Some(&server),
+                    Some(&remote_dir),
+                    err("Missing required parameter: path".into()),
+                    start,
+                );
+            }
+            let strategy_raw = get_str_opt(args, "strategy").unwrap_or_else(|| "trash_can".into());
+            let strategy = match VersioningStrategy::from_name(&strategy_raw) {
+                Some(strategy) => strategy,
+                None => {
+                    return finish(
+                        tool_name,
+                        Some(&server),
+                        Some(&remote_dir),
+                        err(format!(
+                            "Invalid strategy '{}': expected trash_can, simple, or staggered",
+                            strategy_raw
+                        )),
+                        start,
+                    );
+                }
+            };
+            // Remote-only operations: the local root is never touched.
+            let versioning = SyncVersioning::new(std::path::Path::new(""), strategy);
+
+            let result = match pool.get_provider(&server).await {
+                Err(e) => err(e),
+                Ok(arc) => {
+                    let mut p = arc.lock().await;
+                    let provider = &mut **p;
+                    match tool_name {
+                        "aeroftp_list_remote_versions" => {
+                            let listed = match path.as_deref() {
+                                Some(rel) => {
+                                    versioning
+                                        .list_remote_versions(provider, &remote_dir, rel)
+                                        .await
+                                }
+                                None => {
+                                    versioning
+                                        .list_all_remote_versions(provider, &remote_dir)
+                                        .await
+                                }
+                            };
+                            match listed {
+                                Ok(versions) => ok(json!({
+                                    "remote_dir": remote_dir,
+                                    "count": versions.len(),
+                                    "versions": versions,
+                                })),
+                                Err(e) => err(e),
+                            }
+                        }
+                        "aeroftp_restore_remote_version" => {
+                            let rel = path.clone().unwrap_or_default();
+                            let wanted = get_str_opt(args, "archived_at");
+                            match versioning
+                                .list_remote_versions(provider, &remote_dir, &rel)
+                                .await
+                            {
+                                Err(e) => err(e),
+                                Ok(versions) => {
+                                    let chosen = versions.into_iter().find(|v| {
+                                        wanted.as_deref().is_none_or(|ts| v.archived_at == ts)
+                                    });
+                                    match chosen {
+                                        None => {
+                                            err(format!("No archived version found for {}", rel))
+                                        }
+                                        Some(version) => match versioning
+                                            .restore_remote(provider, &remote_dir, &version)
+                                            .await
+                                        {
+                                            Ok(()) => ok(json!({
+                                                "restored": rel,
+                                                "archived_at": version.archived_at,
+                                                "from": version.archive_path,
+                                            })),
+                                            Err(e) => err(e),
+                                        },
+                                    }
+                                }
+                            }
+                        }
+                        _ => match versioning.cleanup_remote(provider, &remote_dir).await {
+                            Ok(stats) => ok(json!(stats)),
+                            Err(e) => err(e),
+                        },
+                    }
+                }
+            };
+            finish(tool_name, Some(&server), Some(&remote_dir), result, start)
+        }
         _ => finish(
             tool_name,
             None,
@@ -1569,6 +1750,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn remote_version_tools_are_registered_with_expected_categories() {
+        use super::RateCategory;
+        let tools = tool_definitions();
+        for (name, category) in [
+            ("aeroftp_list_remote_versions", RateCategory::ReadOnly),
+            ("aeroftp_restore_remote_version", RateCategory::Mutative),
+            ("aeroftp_cleanup_remote_versions", RateCategory::Destructive),
+        ] {
+            let def = tools
+                .iter()
+                .find(|t| t.name == name)
+                .unwrap_or_else(|| panic!("{} registered", name));
+            assert_eq!(def.category, category, "{}", name);
+        }
+        let sync = tools
+            .iter()
+            .find(|t| t.name == "aeroftp_sync_tree")
+            .expect("aeroftp_sync_tree registered");
+        assert!(sync.input_schema["properties"]["remote_versioning"].is_object());
+    }
+
     #[test]
     fn delete_many_registry_entry_is_present_and_destructive() {
         use super::{tool_definitions, RateCategory};
@@ -1871,6 +2074,7 @@ mod tests {
         use crate::sync_core::{DiffEntry, DiffReport};
         let make = |rel: &str| DiffEntry {
             rel_path: rel.to_string(),
+            remote_rel_path: None,
             local_size: None,
             remote_size: None,
             local_sha256: None,
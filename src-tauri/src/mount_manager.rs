@@ -24,6 +24,7 @@ use std::sync::LazyLock;
 use tokio::sync::Mutex as AsyncMutex;
 
 use crate::credential_store::CredentialStore;
+use crate::vfs_cache::VfsCacheMode;
 
 const SIDECAR_FILENAME: &str = "mounts.json";
 const VAULT_REGISTRY_KEY: &str = "aeroftp_mounts_registry";
@@ -51,6 +52,43 @@ pub struct MountConfig {
     pub auto_start: bool,
     #[serde(default)]
     pub created_at: String,
+    /// FUSE only: what the mount keeps in its on-disk VFS cache.
+    #[serde(default)]
+    pub vfs_cache_mode: VfsCacheMode,
+    /// Cache size limit such as "10G"; unlimited when absent.
+    #[serde(default)]
+    pub vfs_cache_max_size: Option<String>,
+    /// Evict cached files unused for this long, such as "1h".
+    #[serde(default)]
+    pub vfs_cache_max_age: Option<String>,
+    /// Cache directory override (default: per-mount dir under the user cache).
+    #[serde(default)]
+    pub cache_dir: Option<String>,
+}
+
+impl MountConfig {
+    /// VFS cache flags for `aeroftp-cli mount`. The Windows WebDAV bridge
+    /// has no VFS cache, so they are not passed there.
+    #[cfg(not(windows))]
+    fn vfs_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.vfs_cache_mode != VfsCacheMode::Off {
+            args.push("--vfs-cache-mode".to_string());
+            args.push(self.vfs_cache_mode.as_str().to_string());
+        }
+        let values = [
+            ("--vfs-cache-max-size", &self.vfs_cache_max_size),
+            ("--vfs-cache-max-age", &self.vfs_cache_max_age),
+            ("--cache-dir", &self.cache_dir),
+        ];
+        for (flag, value) in values {
+            if let Some(value) = value {
+                args.push(flag.to_string());
+                args.push(value.clone());
+            }
+        }
+        args
+    }
 }
 
 fn default_remote_path() -> String {
@@ -302,6 +340,16 @@ pub fn upsert_config(mut config: MountConfig) -> Result<MountConfig, String> {
     if config.cache_ttl == 0 {
         config.cache_ttl = DEFAULT_CACHE_TTL;
     }
+    for value in [
+        &mut config.vfs_cache_max_size,
+        &mut config.vfs_cache_max_age,
+        &mut config.cache_dir,
+    ] {
+        *value = value
+            .take()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+    }
     validate_mountpoint(&config.mountpoint)?;
 
     if config.created_at.is_empty() {
@@ -418,6 +466,8 @@ pub async fn start_mount(id: &str) -> Result<MountStatus, String> {
     if cfg.allow_other {
         cmd.arg("--allow-other");
     }
+    #[cfg(not(windows))]
+    cmd.args(cfg.vfs_args());
 
     cmd.stdin(std::process::Stdio::null())
         .stdout(std::process::Stdio::null())
@@ -660,6 +710,10 @@ fn render_systemd_unit(cli: &std::path::Path, cfg: &MountConfig) -> String {
     if cfg.allow_other {
         exec.push_str(" --allow-other");
     }
+    for arg in cfg.vfs_args() {
+        exec.push(' ');
+        exec.push_str(&shell_escape(&arg));
+    }
     format!(
         "[Unit]\n\
          Description=AeroFTP mount: {name}\n\
@@ -846,6 +900,24 @@ mod tests {
         assert!(!s.is_empty());
     }
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn systemd_unit_carries_vfs_cache_flags() {
+        let mut cfg: MountConfig =
+            serde_json::from_str(r#"{"id":"m1","name":"n","profile":"p","mountpoint":"/mnt/p"}"#)
+                .unwrap();
+        assert_eq!(cfg.vfs_cache_mode, VfsCacheMode::Off);
+        assert!(cfg.vfs_args().is_empty());
+
+        cfg.vfs_cache_mode = VfsCacheMode::Full;
+        cfg.vfs_cache_max_size = Some("10G".to_string());
+        cfg.cache_dir = Some("/var/cache/my mount".to_string());
+        let unit = render_systemd_unit(std::path::Path::new("/usr/bin/aeroftp-cli"), &cfg);
+        assert!(unit.contains(
+            "--vfs-cache-mode full --vfs-cache-max-size 10G --cache-dir '/var/cache/my mount'"
+        ));
+    }
+
     #[test]
     fn registry_default_is_sidecar() {
         let r = MountRegistry::default();
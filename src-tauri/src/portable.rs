@@ -20,6 +20,8 @@ use std::sync::OnceLock;
 
 const MARKER_FILENAME: &str = "portable.marker";
 const PORTABLE_DATA_DIRNAME: &str = "data";
+/// Bundle identifier from `tauri.conf.json`; names the app config dir.
+const APP_IDENTIFIER: &str = "com.aeroftp.AeroFTP";
 
 /// Cached portable-mode flag. Computed on first access and reused.
 static PORTABLE_ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
@@ -79,6 +81,16 @@ pub fn app_config_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
         .map_err(|e| format!("Cannot resolve app config dir: {e}"))
 }
 
+/// The same directory as [`app_config_dir`], for code that runs without an
+/// `AppHandle` (the CLI). Mirrors Tauri's resolution: the platform config
+/// dir joined with the bundle identifier. Does not create the directory.
+pub fn app_config_dir_detached() -> Option<PathBuf> {
+    if let Some(data_root) = portable_data_root() {
+        return Some(data_root.join("config"));
+    }
+    dirs::config_dir().map(|base| base.join(APP_IDENTIFIER))
+}
+
 /// Resolve the per-app data directory. In portable mode this is
 /// `<exe-dir>/data`; otherwise delegates to Tauri's `app_data_dir`.
 pub fn app_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
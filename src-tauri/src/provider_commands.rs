@@ -3598,6 +3598,7 @@ pub async fn provider_compare_directories(
     use crate::sync::{
         build_comparison_results_with_index, load_sync_index, should_exclude, FileInfo,
     };
+    use crate::sync_core::meta::{read_local_meta, FileMeta};
     use std::collections::HashMap;
 
     let options = options.unwrap_or_default();
@@ -3623,7 +3624,7 @@ pub async fn provider_compare_directories(
     // Get local files (reuse the same logic from lib.rs).
     // Pass the AppHandle so the scan emits throttled progress events -
     // otherwise large trees (e.g. a home directory) look like a stall.
-    let local_files = crate::get_local_files_recursive_with_progress(
+    let mut local_files = crate::get_local_files_recursive_with_progress(
         &local_path,
         &local_path,
         &options.exclude_patterns,
@@ -3633,6 +3634,14 @@ pub async fn provider_compare_directories(
     )
     .await
     .map_err(|e| format!("Failed to scan local directory: {}", e))?;
+    if options.metadata.is_enabled() {
+        for info in local_files.values_mut() {
+            info.meta = Some(read_local_meta(
+                std::path::Path::new(&info.path),
+                &options.metadata,
+            ));
+        }
+    }
 
     let _ = app.emit(
         "sync_scan_progress",
@@ -3723,6 +3732,13 @@ pub async fn provider_compare_directories(
                 modified,
                 is_dir: entry.is_dir,
                 checksum: None,
+                meta: options.metadata.is_enabled().then(|| {
+                    FileMeta::from_listing(
+                        entry.permissions.as_deref(),
+                        entry.owner.as_deref(),
+                        entry.group.as_deref(),
+                    )
+                }),
             };
 
             remote_files.insert(relative_path, file_info);
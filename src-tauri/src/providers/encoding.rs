@@ -0,0 +1,1022 @@
+//! rclone-compatible file name encoding.
+//!
+//! Backends reject different characters: OneDrive refuses `"*:<>?|` and
+//! trailing dots, SMB shares refuse Windows device names such as `CON`, some
+//! FTP servers mangle anything outside ASCII. A profile's `encoding` option
+//! takes the same comma-separated flag list as rclone's `--<backend>-encoding`
+//! (for example `Slash,LtGt,DoubleQuote,Colon,Question,Asterisk,Pipe,
+//! BackSlash,RightPeriod`). Each flagged character is replaced by the same
+//! Unicode lookalike rclone uses before a path reaches the backend, and
+//! mapped back in listings. A lookalike that is already part of a name is
+//! escaped with `‛` (U+201B), so every Linux file name round-trips unchanged.
+//!
+//! Two flags are AeroFTP extensions: `WinReserved` (device names like `CON`
+//! or `LPT1.txt` get a full-width first letter) and `Ascii` (non-ASCII bytes
+//! and `%` are percent-encoded, applied after every other flag).
+//!
+//! [`EncodedProvider`] applies the encoding transparently around any
+//! [`StorageProvider`]; [`ProviderFactory::create`](super::ProviderFactory)
+//! wraps providers whose config carries an `encoding` option.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{
+    ChangeEntry, FileVersion, LockInfo, ProviderError, ProviderType, RemoteEntry,
+    ShareLinkCapabilities, ShareLinkInfo, ShareLinkOptions, ShareLinkResult, SharePermission,
+    StorageInfo, StorageProvider, TransferOptimizationHints,
+};
+
+/// Escape prefix for literal lookalikes (rclone's `QuoteRune`).
+const QUOTE: char = '\u{201B}';
+
+/// Set of encoding flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NameEncoding(u32);
+
+impl NameEncoding {
+    pub const NONE: Self = Self(0);
+    pub const SLASH: Self = Self(1 << 0);
+    pub const LT_GT: Self = Self(1 << 1);
+    pub const SQUARE_BRACKET: Self = Self(1 << 2);
+    pub const SEMICOLON: Self = Self(1 << 3);
+    pub const EXCLAMATION: Self = Self(1 << 4);
+    pub const DOUBLE_QUOTE: Self = Self(1 << 5);
+    pub const SINGLE_QUOTE: Self = Self(1 << 6);
+    pub const BACK_QUOTE: Self = Self(1 << 7);
+    pub const DOLLAR: Self = Self(1 << 8);
+    pub const COLON: Self = Self(1 << 9);
+    pub const QUESTION: Self = Self(1 << 10);
+    pub const ASTERISK: Self = Self(1 << 11);
+    pub const PIPE: Self = Self(1 << 12);
+    pub const HASH: Self = Self(1 << 13);
+    pub const PERCENT: Self = Self(1 << 14);
+    pub const BACK_SLASH: Self = Self(1 << 15);
+    pub const CR_LF: Self = Self(1 << 16);
+    pub const DEL: Self = Self(1 << 17);
+    pub const CTL: Self = Self(1 << 18);
+    pub const LEFT_SPACE: Self = Self(1 << 19);
+    pub const LEFT_PERIOD: Self = Self(1 << 20);
+    pub const LEFT_TILDE: Self = Self(1 << 21);
+    pub const LEFT_CR_LF_HT_VT: Self = Self(1 << 22);
+    pub const RIGHT_SPACE: Self = Self(1 << 23);
+    pub const RIGHT_PERIOD: Self = Self(1 << 24);
+    pub const RIGHT_CR_LF_HT_VT: Self = Self(1 << 25);
+    /// Accepted for rclone compatibility; Rust strings are always valid UTF-8.
+    pub const INVALID_UTF8: Self = Self(1 << 26);
+    pub const DOT: Self = Self(1 << 27);
+    pub const WIN_RESERVED: Self = Self(1 << 28);
+    pub const ASCII: Self = Self(1 << 29);
+
+    const NAMES: &'static [(&'static str, NameEncoding)] = &[
+        ("Slash", Self::SLASH),
+        ("LtGt", Self::LT_GT),
+        ("SquareBracket", Self::SQUARE_BRACKET),
+        ("SemiColon", Self::SEMICOLON),
+        ("Exclamation", Self::EXCLAMATION),
+        ("DoubleQuote", Self::DOUBLE_QUOTE),
+        ("SingleQuote", Self::SINGLE_QUOTE),
+        ("BackQuote", Self::BACK_QUOTE),
+        ("Dollar", Self::DOLLAR),
+        ("Colon", Self::COLON),
+        ("Question", Self::QUESTION),
+        ("Asterisk", Self::ASTERISK),
+        ("Pipe", Self::PIPE),
+        ("Hash", Self::HASH),
+        ("Percent", Self::PERCENT),
+        ("BackSlash", Self::BACK_SLASH),
+        ("CrLf", Self::CR_LF),
+        ("Del", Self::DEL),
+        ("Ctl", Self::CTL),
+        ("LeftSpace", Self::LEFT_SPACE),
+        ("LeftPeriod", Self::LEFT_PERIOD),
+        ("LeftTilde", Self::LEFT_TILDE),
+        ("LeftCrLfHtVt", Self::LEFT_CR_LF_HT_VT),
+        ("RightSpace", Self::RIGHT_SPACE),
+        ("RightPeriod", Self::RIGHT_PERIOD),
+        ("RightCrLfHtVt", Self::RIGHT_CR_LF_HT_VT),
+        ("InvalidUtf8", Self::INVALID_UTF8),
+        ("Dot", Self::DOT),
+        ("WinReserved", Self::WIN_RESERVED),
+        ("Ascii", Self::ASCII),
+    ];
+
+    /// Parse an rclone-style flag list such as `Slash,Colon,RightPeriod`.
+    /// `None` (or an empty string) disables encoding.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut encoding = Self::NONE;
+        for flag in spec.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            if flag.eq_ignore_ascii_case("None") {
+                continue;
+            }
+            let (_, value) = Self::NAMES
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(flag))
+                .ok_or_else(|| format!("Unknown encoding flag '{}'", flag))?;
+            encoding = encoding | *value;
+        }
+        Ok(encoding)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Lookalike for `c` at the given position of a name, if encoded.
+    fn encode_char(self, c: char, first: bool, last: bool) -> Option<char> {
+        let always = match c {
+            '/' if self.contains(Self::SLASH) => Some('\u{FF0F}'),
+            '<' if self.contains(Self::LT_GT) => Some('\u{FF1C}'),
+            '>' if self.contains(Self::LT_GT) => Some('\u{FF1E}'),
+            '[' if self.contains(Self::SQUARE_BRACKET) => Some('\u{FF3B}'),
+            ']' if self.contains(Self::SQUARE_BRACKET) => Some('\u{FF3D}'),
+            ';' if self.contains(Self::SEMICOLON) => Some('\u{FF1B}'),
+            '!' if self.contains(Self::EXCLAMATION) => Some('\u{FF01}'),
+            '"' if self.contains(Self::DOUBLE_QUOTE) => Some('\u{FF02}'),
+            '\'' if self.contains(Self::SINGLE_QUOTE) => Some('\u{FF07}'),
+            '`' if self.contains(Self::BACK_QUOTE) => Some('\u{FF40}'),
+            '$' if self.contains(Self::DOLLAR) => Some('\u{FF04}'),
+            ':' if self.contains(Self::COLON) => Some('\u{FF1A}'),
+            '?' if self.contains(Self::QUESTION) => Some('\u{FF1F}'),
+            '*' if self.contains(Self::ASTERISK) => Some('\u{FF0A}'),
+            '|' if self.contains(Self::PIPE) => Some('\u{FF5C}'),
+            '#' if self.contains(Self::HASH) => Some('\u{FF03}'),
+            '%' if self.contains(Self::PERCENT) => Some('\u{FF05}'),
+            '\\' if self.contains(Self::BACK_SLASH) => Some('\u{FF3C}'),
+            '\r' | '\n' if self.contains(Self::CR_LF) => control_picture(c),
+            '\u{7F}' if self.contains(Self::DEL) => Some('\u{2421}'),
+            '\0'..='\u{1F}' if self.contains(Self::CTL) => control_picture(c),
+            _ => None,
+        };
+        always
+            .or_else(|| first.then(|| self.encode_edge(c, true)).flatten())
+            .or_else(|| last.then(|| self.encode_edge(c, false)).flatten())
+    }
+
+    fn encode_edge(self, c: char, left: bool) -> Option<char> {
+        let (space, period, crlf) = if left {
+            (Self::LEFT_SPACE, Self::LEFT_PERIOD, Self::LEFT_CR_LF_HT_VT)
+        } else {
+            (
+                Self::RIGHT_SPACE,
+                Self::RIGHT_PERIOD,
+                Self::RIGHT_CR_LF_HT_VT,
+            )
+        };
+        match c {
+            ' ' if self.contains(space) => Some('\u{2420}'),
+            '.' if self.contains(period) => Some('\u{FF0E}'),
+            '~' if left && self.contains(Self::LEFT_TILDE) => Some('\u{FF5E}'),
+            '\t' | '\n' | '\u{B}' | '\r' if self.contains(crlf) => control_picture(c),
+            _ => None,
+        }
+    }
+
+    /// Original character for lookalike `c` at the given position, if any.
+    fn decode_char(self, c: char, first: bool, last: bool) -> Option<char> {
+        let original = match c {
+            '\u{2400}'..='\u{241F}' => char::from_u32(c as u32 - 0x2400),
+            '\u{2420}' => Some(' '),
+            '\u{2421}' => Some('\u{7F}'),
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0),
+            _ => None,
+        }?;
+        (self.encode_char(original, first, last) == Some(c)).then_some(original)
+    }
+
+    /// Encode one path component.
+    pub fn encode_name(self, name: &str) -> String {
+        if self.is_empty() || name.is_empty() {
+            return name.to_string();
+        }
+        let encoded = if self.contains(Self::DOT) && (name == "." || name == "..") {
+            "\u{FF0E}".repeat(name.len())
+        } else {
+            self.encode_lookalikes(name)
+        };
+        if self.contains(Self::ASCII) {
+            percent_encode(&encoded)
+        } else {
+            encoded
+        }
+    }
+
+    fn encode_lookalikes(self, name: &str) -> String {
+        let chars: Vec<char> = name.chars().collect();
+        let n = chars.len();
+        // Names that would decode as "." / ".." or as a device name must be
+        // escaped, not just names that contain forbidden characters.
+        let dot_like =
+            self.contains(Self::DOT) && (name == "\u{FF0E}" || name == "\u{FF0E}\u{FF0E}");
+        let win_reserved = self.contains(Self::WIN_RESERVED);
+        let reserved = win_reserved && is_reserved_name(name);
+        let reserved_lookalike = win_reserved
+            && chars
+                .first()
+                .and_then(|c| from_fullwidth_letter(*c))
+                .is_some_and(|c| is_reserved_name(&replace_first(name, c)));
+
+        let mut out = String::with_capacity(name.len());
+        for (i, &c) in chars.iter().enumerate() {
+            let (first, last) = (i == 0, i + 1 == n);
+            if first && reserved {
+                out.push(to_fullwidth_letter(c));
+                continue;
+            }
+            if let Some(lookalike) = self.encode_char(c, first, last) {
+                out.push(lookalike);
+                continue;
+            }
+            if c == QUOTE
+                || dot_like
+                || (first && reserved_lookalike)
+                || self.decode_char(c, first, last).is_some()
+            {
+                out.push(QUOTE);
+            }
+            out.push(c);
+        }
+        out
+    }
+
+    /// Decode one path component produced by [`encode_name`](Self::encode_name).
+    pub fn decode_name(self, name: &str) -> String {
+        if self.is_empty() || name.is_empty() {
+            return name.to_string();
+        }
+        let unescaped;
+        let name = if self.contains(Self::ASCII) {
+            unescaped = percent_decode(name);
+            unescaped.as_str()
+        } else {
+            name
+        };
+        if self.contains(Self::DOT) {
+            match name {
+                "\u{FF0E}" => return ".".to_string(),
+                "\u{FF0E}\u{FF0E}" => return "..".to_string(),
+                _ => {}
+            }
+        }
+
+        let chars: Vec<char> = name.chars().collect();
+        let n = chars.len();
+        let mut out = String::with_capacity(name.len());
+        let mut first_quoted = false;
+        let mut i = 0;
+        while i < n {
+            let c = chars[i];
+            if c == QUOTE && i + 1 < n {
+                first_quoted |= i == 0;
+                out.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            // Positions refer to encoded units: a quoted pair counts as one
+            // character of the original name.
+            let first = out.is_empty();
+            let last = i + 1 == n;
+            out.push(self.decode_char(c, first, last).unwrap_or(c));
+            i += 1;
+        }
+
+        if self.contains(Self::WIN_RESERVED) && !first_quoted {
+            if let Some(ascii) = out.chars().next().and_then(from_fullwidth_letter) {
+                let candidate = replace_first(&out, ascii);
+                if is_reserved_name(&candidate) {
+                    return candidate;
+                }
+            }
+        }
+        out
+    }
+
+    /// Encode every component of a `/`-separated path. `.` and `..` stay
+    /// navigation components here (relative symlink targets use them).
+    pub fn encode_path(self, path: &str) -> String {
+        if self.is_empty() {
+            return path.to_string();
+        }
+        path.split('/')
+            .map(|part| match part {
+                "." | ".." => part.to_string(),
+                _ => self.encode_name(part),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Decode every component of a `/`-separated path.
+    pub fn decode_path(self, path: &str) -> String {
+        if self.is_empty() {
+            return path.to_string();
+        }
+        path.split('/')
+            .map(|part| self.decode_name(part))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+impl std::ops::BitOr for NameEncoding {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl fmt::Display for NameEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("None");
+        }
+        let names: Vec<&str> = Self::NAMES
+            .iter()
+            .filter(|(_, flag)| self.contains(*flag))
+            .map(|(name, _)| *name)
+            .collect();
+        f.write_str(&names.join(","))
+    }
+}
+
+fn control_picture(c: char) -> Option<char> {
+    char::from_u32(0x2400 + c as u32)
+}
+
+fn to_fullwidth_letter(c: char) -> char {
+    if c.is_ascii_alphabetic() {
+        char::from_u32(c as u32 + 0xFEE0).unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+fn from_fullwidth_letter(c: char) -> Option<char> {
+    let ascii = char::from_u32((c as u32).checked_sub(0xFEE0)?)?;
+    ascii.is_ascii_alphabetic().then_some(ascii)
+}
+
+fn replace_first(name: &str, c: char) -> String {
+    let mut chars = name.chars();
+    chars.next();
+    let mut out = String::with_capacity(name.len());
+    out.push(c);
+    out.push_str(chars.as_str());
+    out
+}
+
+/// Windows device names (`CON`, `nul.txt`, `COM1.log`, ...).
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).to_ascii_uppercase();
+    match stem.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => {
+            let bytes = stem.as_bytes();
+            bytes.len() == 4
+                && (stem.starts_with("COM") || stem.starts_with("LPT"))
+                && (b'1'..=b'9').contains(&bytes[3])
+        }
+    }
+}
+
+fn percent_encode(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii() && c != '%' {
+            out.push(c);
+        } else {
+            let mut buf = [0u8; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("%{:02X}", b));
+            }
+        }
+    }
+    out
+}
+
+fn percent_decode(name: &str) -> String {
+    let bytes = name.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                out.push(hi << 4 | lo);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(out).unwrap_or_else(|_| name.to_string())
+}
+
+/// Wrap `provider` in an [`EncodedProvider`] when `spec` names any flag.
+pub fn with_name_encoding(
+    provider: Box<dyn StorageProvider>,
+    spec: Option<&str>,
+) -> Result<Box<dyn StorageProvider>, ProviderError> {
+    let Some(spec) = spec.map(str::trim).filter(|s| !s.is_empty()) else {
+        return Ok(provider);
+    };
+    let encoding = NameEncoding::parse(spec).map_err(ProviderError::InvalidConfig)?;
+    if encoding.is_empty() {
+        return Ok(provider);
+    }
+    Ok(Box::new(EncodedProvider::new(provider, encoding)))
+}
+
+/// Provider adapter that encodes every path on the way in and decodes names
+/// and paths on the way out. `as_any_mut` exposes the wrapped provider, so
+/// provider-specific downcasts keep working (on raw, encoded names).
+pub struct EncodedProvider {
+    inner: Box<dyn StorageProvider>,
+    encoding: NameEncoding,
+}
+
+impl EncodedProvider {
+    pub fn new(inner: Box<dyn StorageProvider>, encoding: NameEncoding) -> Self {
+        Self { inner, encoding }
+    }
+
+    pub fn encoding(&self) -> NameEncoding {
+        self.encoding
+    }
+
+    fn enc(&self, path: &str) -> String {
+        self.encoding.encode_path(path)
+    }
+
+    fn dec(&self, path: &str) -> String {
+        self.encoding.decode_path(path)
+    }
+
+    fn decode_entry(&self, mut entry: RemoteEntry) -> RemoteEntry {
+        entry.name = self.encoding.decode_name(&entry.name);
+        entry.path = self.dec(&entry.path);
+        entry.link_target = entry.link_target.map(|target| self.dec(&target));
+        entry
+    }
+
+    fn decode_entries(&self, entries: Vec<RemoteEntry>) -> Vec<RemoteEntry> {
+        entries.into_iter().map(|e| self.decode_entry(e)).collect()
+    }
+}
+
+#[async_trait]
+impl StorageProvider for EncodedProvider {
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self.inner.as_any_mut()
+    }
+
+    fn provider_type(&self) -> ProviderType {
+        self.inner.provider_type()
+    }
+
+    fn display_name(&self) -> String {
+        self.inner.display_name()
+    }
+
+    fn account_email(&self) -> Option<String> {
+        self.inner.account_email()
+    }
+
+    async fn connect(&mut self) -> Result<(), ProviderError> {
+        self.inner.connect().await
+    }
+
+    async fn disconnect(&mut self) -> Result<(), ProviderError> {
+        self.inner.disconnect().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    async fn list(&mut self, path: &str) -> Result<Vec<RemoteEntry>, ProviderError> {
+        let path = self.enc(path);
+        let entries = self.inner.list(&path).await?;
+        Ok(self.decode_entries(entries))
+    }
+
+    async fn pwd(&mut self) -> Result<String, ProviderError> {
+        let path = self.inner.pwd().await?;
+        Ok(self.dec(&path))
+    }
+
+    async fn cd(&mut self, path: &str) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.cd(&path).await
+    }
+
+    async fn cd_up(&mut self) -> Result<(), ProviderError> {
+        self.inner.cd_up().await
+    }
+
+    async fn download(
+        &mut self,
+        remote_path: &str,
+        local_path: &str,
+        on_progress: Option<Box<dyn Fn(u64, u64) + Send>>,
+    ) -> Result<(), ProviderError> {
+        let remote_path = self.enc(remote_path);
+        self.inner
+            .download(&remote_path, local_path, on_progress)
+            .await
+    }
+
+    async fn download_to_bytes(&mut self, remote_path: &str) -> Result<Vec<u8>, ProviderError> {
+        let remote_path = self.enc(remote_path);
+        self.inner.download_to_bytes(&remote_path).await
+    }
+
+    async fn upload(
+        &mut self,
+        local_path: &str,
+        remote_path: &str,
+        on_progress: Option<Box<dyn Fn(u64, u64) + Send>>,
+    ) -> Result<(), ProviderError> {
+        let remote_path = self.enc(remote_path);
+        self.inner
+            .upload(local_path, &remote_path, on_progress)
+            .await
+    }
+
+    async fn mkdir(&mut self, path: &str) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.mkdir(&path).await
+    }
+
+    async fn delete(&mut self, path: &str) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.delete(&path).await
+    }
+
+    async fn rmdir(&mut self, path: &str) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.rmdir(&path).await
+    }
+
+    async fn rmdir_recursive(&mut self, path: &str) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.rmdir_recursive(&path).await
+    }
+
+    async fn rename(&mut self, from: &str, to: &str) -> Result<(), ProviderError> {
+        let (from, to) = (self.enc(from), self.enc(to));
+        self.inner.rename(&from, &to).await
+    }
+
+    async fn stat(&mut self, path: &str) -> Result<RemoteEntry, ProviderError> {
+        let path = self.enc(path);
+        let entry = self.inner.stat(&path).await?;
+        Ok(self.decode_entry(entry))
+    }
+
+    async fn size(&mut self, path: &str) -> Result<u64, ProviderError> {
+        let path = self.enc(path);
+        self.inner.size(&path).await
+    }
+
+    async fn exists(&mut self, path: &str) -> Result<bool, ProviderError> {
+        let path = self.enc(path);
+        self.inner.exists(&path).await
+    }
+
+    async fn keep_alive(&mut self) -> Result<(), ProviderError> {
+        self.inner.keep_alive().await
+    }
+
+    async fn server_info(&mut self) -> Result<String, ProviderError> {
+        let info = self.inner.server_info().await?;
+        Ok(format!("{} (encoding: {})", info, self.encoding))
+    }
+
+    fn supports_chmod(&self) -> bool {
+        self.inner.supports_chmod()
+    }
+
+    async fn chmod(&mut self, path: &str, mode: u32) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.chmod(&path, mode).await
+    }
+
+    fn supports_chown(&self) -> bool {
+        self.inner.supports_chown()
+    }
+
+    async fn chown(
+        &mut self,
+        path: &str,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.chown(&path, uid, gid).await
+    }
+
+    fn supports_symlinks(&self) -> bool {
+        self.inner.supports_symlinks()
+    }
+
+    async fn create_symlink(&mut self, target: &str, link_path: &str) -> Result<(), ProviderError> {
+        let (target, link_path) = (self.enc(target), self.enc(link_path));
+        self.inner.create_symlink(&target, &link_path).await
+    }
+
+    fn supports_hardlinks(&self) -> bool {
+        self.inner.supports_hardlinks()
+    }
+
+    async fn hard_link(&mut self, existing: &str, link_path: &str) -> Result<(), ProviderError> {
+        let (existing, link_path) = (self.enc(existing), self.enc(link_path));
+        self.inner.hard_link(&existing, &link_path).await
+    }
+
+    fn is_case_insensitive(&self) -> bool {
+        self.inner.is_case_insensitive()
+    }
+
+    fn supports_server_copy(&self) -> bool {
+        self.inner.supports_server_copy()
+    }
+
+    async fn server_copy(&mut self, from: &str, to: &str) -> Result<(), ProviderError> {
+        let (from, to) = (self.enc(from), self.enc(to));
+        self.inner.server_copy(&from, &to).await
+    }
+
+    fn supports_share_links(&self) -> bool {
+        self.inner.supports_share_links()
+    }
+
+    fn share_link_capabilities(&self) -> ShareLinkCapabilities {
+        self.inner.share_link_capabilities()
+    }
+
+    async fn create_share_link(
+        &mut self,
+        path: &str,
+        options: ShareLinkOptions,
+    ) -> Result<ShareLinkResult, ProviderError> {
+        let path = self.enc(path);
+        self.inner.create_share_link(&path, options).await
+    }
+
+    fn supports_import_link(&self) -> bool {
+        self.inner.supports_import_link()
+    }
+
+    async fn import_link(&mut self, link: &str, dest: &str) -> Result<(), ProviderError> {
+        let dest = self.enc(dest);
+        self.inner.import_link(link, &dest).await
+    }
+
+    async fn remove_share_link(&mut self, path: &str) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.remove_share_link(&path).await
+    }
+
+    async fn list_share_links(&mut self, path: &str) -> Result<Vec<ShareLinkInfo>, ProviderError> {
+        let path = self.enc(path);
+        self.inner.list_share_links(&path).await
+    }
+
+    async fn storage_info(&mut self) -> Result<StorageInfo, ProviderError> {
+        self.inner.storage_info().await
+    }
+
+    async fn disk_usage(&mut self, path: &str) -> Result<u64, ProviderError> {
+        let path = self.enc(path);
+        self.inner.disk_usage(&path).await
+    }
+
+    fn supports_find(&self) -> bool {
+        self.inner.supports_find()
+    }
+
+    async fn find(&mut self, path: &str, pattern: &str) -> Result<Vec<RemoteEntry>, ProviderError> {
+        let path = self.enc(path);
+        let entries = self.inner.find(&path, pattern).await?;
+        Ok(self.decode_entries(entries))
+    }
+
+    async fn set_speed_limit(
+        &mut self,
+        upload_kb: u64,
+        download_kb: u64,
+    ) -> Result<(), ProviderError> {
+        self.inner.set_speed_limit(upload_kb, download_kb).await
+    }
+
+    async fn get_speed_limit(&mut self) -> Result<(u64, u64), ProviderError> {
+        self.inner.get_speed_limit().await
+    }
+
+    fn supports_resume(&self) -> bool {
+        self.inner.supports_resume()
+    }
+
+    async fn resume_download(
+        &mut self,
+        remote_path: &str,
+        local_path: &str,
+        offset: u64,
+        on_progress: Option<Box<dyn Fn(u64, u64) + Send>>,
+    ) -> Result<(), ProviderError> {
+        let remote_path = self.enc(remote_path);
+        self.inner
+            .resume_download(&remote_path, local_path, offset, on_progress)
+            .await
+    }
+
+    async fn resume_upload(
+        &mut self,
+        local_path: &str,
+        remote_path: &str,
+        offset: u64,
+        on_progress: Option<Box<dyn Fn(u64, u64) + Send>>,
+    ) -> Result<(), ProviderError> {
+        let remote_path = self.enc(remote_path);
+        self.inner
+            .resume_upload(local_path, &remote_path, offset, on_progress)
+            .await
+    }
+
+    fn supports_versions(&self) -> bool {
+        self.inner.supports_versions()
+    }
+
+    async fn list_versions(&mut self, path: &str) -> Result<Vec<FileVersion>, ProviderError> {
+        let path = self.enc(path);
+        self.inner.list_versions(&path).await
+    }
+
+    async fn download_version(
+        &mut self,
+        path: &str,
+        version_id: &str,
+        local_path: &str,
+    ) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner
+            .download_version(&path, version_id, local_path)
+            .await
+    }
+
+    async fn restore_version(&mut self, path: &str, version_id: &str) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.restore_version(&path, version_id).await
+    }
+
+    fn supports_locking(&self) -> bool {
+        self.inner.supports_locking()
+    }
+
+    async fn lock_file(&mut self, path: &str, timeout: u64) -> Result<LockInfo, ProviderError> {
+        let path = self.enc(path);
+        self.inner.lock_file(&path, timeout).await
+    }
+
+    async fn unlock_file(&mut self, path: &str, lock_token: &str) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.unlock_file(&path, lock_token).await
+    }
+
+    fn supports_thumbnails(&self) -> bool {
+        self.inner.supports_thumbnails()
+    }
+
+    async fn get_thumbnail(&mut self, path: &str) -> Result<String, ProviderError> {
+        let path = self.enc(path);
+        self.inner.get_thumbnail(&path).await
+    }
+
+    fn supports_permissions(&self) -> bool {
+        self.inner.supports_permissions()
+    }
+
+    async fn list_permissions(
+        &mut self,
+        path: &str,
+    ) -> Result<Vec<SharePermission>, ProviderError> {
+        let path = self.enc(path);
+        self.inner.list_permissions(&path).await
+    }
+
+    async fn add_permission(
+        &mut self,
+        path: &str,
+        permission: &SharePermission,
+    ) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.add_permission(&path, permission).await
+    }
+
+    async fn remove_permission(&mut self, path: &str, target: &str) -> Result<(), ProviderError> {
+        let path = self.enc(path);
+        self.inner.remove_permission(&path, target).await
+    }
+
+    fn supports_checksum(&self) -> bool {
+        self.inner.supports_checksum()
+    }
+
+    async fn checksum(&mut self, path: &str) -> Result<HashMap<String, String>, ProviderError> {
+        let path = self.enc(path);
+        self.inner.checksum(&path).await
+    }
+
+    fn supports_remote_upload(&self) -> bool {
+        self.inner.supports_remote_upload()
+    }
+
+    async fn remote_upload(&mut self, url: &str, dest_path: &str) -> Result<(), ProviderError> {
+        let dest_path = self.enc(dest_path);
+        self.inner.remote_upload(url, &dest_path).await
+    }
+
+    fn supports_change_tracking(&self) -> bool {
+        self.inner.supports_change_tracking()
+    }
+
+    async fn get_change_token(&mut self) -> Result<String, ProviderError> {
+        self.inner.get_change_token().await
+    }
+
+    async fn list_changes(
+        &mut self,
+        page_token: &str,
+    ) -> Result<(Vec<ChangeEntry>, String), ProviderError> {
+        let (changes, token) = self.inner.list_changes(page_token).await?;
+        let changes = changes
+            .into_iter()
+            .map(|mut change| {
+                change.name = self.encoding.decode_name(&change.name);
+                change.path = change.path.map(|p| self.dec(&p));
+                change
+            })
+            .collect();
+        Ok((changes, token))
+    }
+
+    fn transfer_optimization_hints(&self) -> TransferOptimizationHints {
+        self.inner.transfer_optimization_hints()
+    }
+
+    fn set_chunk_sizes(&mut self, upload: Option<u64>, download: Option<u64>) {
+        self.inner.set_chunk_sizes(upload, download)
+    }
+
+    fn set_multi_thread_download(&mut self, streams: usize, cutoff_bytes: u64) {
+        self.inner.set_multi_thread_download(streams, cutoff_bytes)
+    }
+
+    fn supports_delta_sync(&self) -> bool {
+        self.inner.supports_delta_sync()
+    }
+
+    async fn read_range(
+        &mut self,
+        path: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>, ProviderError> {
+        let path = self.enc(path);
+        self.inner.read_range(&path, offset, len).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONEDRIVE: &str = "Slash,LtGt,DoubleQuote,Colon,Question,Asterisk,Pipe,BackSlash,Del,Ctl,LeftSpace,LeftTilde,RightSpace,RightPeriod,InvalidUtf8,Dot";
+
+    fn round_trip(encoding: NameEncoding, name: &str) -> String {
+        let encoded = encoding.encode_name(name);
+        assert_eq!(
+            encoding.decode_name(&encoded),
+            name,
+            "encoded as {:?}",
+            encoded
+        );
+        encoded
+    }
+
+    #[test]
+    fn parse_accepts_rclone_flag_lists() {
+        let enc = NameEncoding::parse(ONEDRIVE).unwrap();
+        assert!(enc.contains(NameEncoding::COLON | NameEncoding::RIGHT_PERIOD));
+        assert!(!enc.contains(NameEncoding::HASH));
+        assert!(NameEncoding::parse("None").unwrap().is_empty());
+        assert!(NameEncoding::parse("colon, pipe")
+            .unwrap()
+            .contains(NameEncoding::PIPE));
+        assert!(NameEncoding::parse("Colon,Bogus").is_err());
+        assert_eq!(
+            NameEncoding::parse("Pipe,Colon").unwrap().to_string(),
+            "Colon,Pipe"
+        );
+    }
+
+    #[test]
+    fn onedrive_forbidden_characters_use_rclone_lookalikes() {
+        let enc = NameEncoding::parse(ONEDRIVE).unwrap();
+        assert_eq!(
+            round_trip(enc, "a:b*c?.txt"),
+            "a\u{FF1A}b\u{FF0A}c\u{FF1F}.txt"
+        );
+        assert_eq!(round_trip(enc, "report."), "report\u{FF0E}");
+        assert_eq!(round_trip(enc, " padded "), "\u{2420}padded\u{2420}");
+        assert_eq!(round_trip(enc, "~lock"), "\u{FF5E}lock");
+        assert_eq!(round_trip(enc, ".."), "\u{FF0E}\u{FF0E}");
+        // Only the edges are touched by Left*/Right* flags.
+        assert_eq!(round_trip(enc, "a.b c~"), "a.b c~");
+    }
+
+    #[test]
+    fn existing_lookalikes_are_escaped() {
+        let enc = NameEncoding::parse(ONEDRIVE).unwrap();
+        assert_eq!(round_trip(enc, "a\u{FF1A}b"), "a\u{201B}\u{FF1A}b");
+        assert_eq!(round_trip(enc, "x\u{FF0E}"), "x\u{201B}\u{FF0E}");
+        assert_eq!(round_trip(enc, "\u{FF0E}"), "\u{201B}\u{FF0E}");
+        round_trip(enc, "quote\u{201B}mark");
+        round_trip(enc, "\u{201B}");
+        // A mid-name full-width period is not decoded, so it needs no escape.
+        assert_eq!(round_trip(enc, "a\u{FF0E}b"), "a\u{FF0E}b");
+    }
+
+    #[test]
+    fn control_characters_map_to_control_pictures() {
+        let enc = NameEncoding::CTL | NameEncoding::DEL;
+        assert_eq!(round_trip(enc, "a\tb\u{7F}"), "a\u{2409}b\u{2421}");
+        round_trip(enc, "\u{2409}literal");
+    }
+
+    #[test]
+    fn win_reserved_names_get_a_fullwidth_first_letter() {
+        let enc = NameEncoding::WIN_RESERVED;
+        assert_eq!(round_trip(enc, "CON"), "\u{FF23}ON");
+        assert_eq!(round_trip(enc, "lpt1.txt"), "\u{FF4C}pt1.txt");
+        assert_eq!(round_trip(enc, "CONTACTS"), "CONTACTS");
+        assert_eq!(round_trip(enc, "COM0"), "COM0");
+        assert_eq!(round_trip(enc, "\u{FF23}ON"), "\u{201B}\u{FF23}ON");
+        let both = enc | NameEncoding::RIGHT_PERIOD;
+        round_trip(both, "NUL.");
+    }
+
+    #[test]
+    fn ascii_flag_percent_encodes_everything_else() {
+        let enc = NameEncoding::ASCII | NameEncoding::COLON;
+        assert_eq!(round_trip(enc, "caf\u{e9} 100%"), "caf%C3%A9 100%25");
+        assert_eq!(round_trip(enc, "a:b"), "a%EF%BC%9Ab");
+        round_trip(enc, "%zz and %4");
+    }
+
+    #[test]
+    fn paths_are_encoded_per_component() {
+        let enc = NameEncoding::parse(ONEDRIVE).unwrap();
+        let path = "/Docs/Q1: plan?/notes.";
+        let encoded = enc.encode_path(path);
+        assert_eq!(encoded, "/Docs/Q1\u{FF1A} plan\u{FF1F}/notes\u{FF0E}");
+        assert_eq!(enc.decode_path(&encoded), path);
+        assert_eq!(NameEncoding::NONE.encode_path(path), path);
+        assert_eq!(enc.encode_path("../a:b/."), "../a\u{FF1A}b/.");
+    }
+
+    #[test]
+    fn arbitrary_linux_names_round_trip() {
+        let enc = NameEncoding::parse(ONEDRIVE).unwrap()
+            | NameEncoding::parse("SquareBracket,SemiColon,Exclamation,SingleQuote,BackQuote,Dollar,Hash,Percent,CrLf,LeftPeriod,LeftCrLfHtVt,RightCrLfHtVt,WinReserved,Ascii").unwrap();
+        let alphabet: Vec<char> =
+            " .~:*?\"<>|\\\t\r\n\u{7F}\u{1}%#aC\u{FF0E}\u{FF1A}\u{2420}\u{201B}\u{e9}"
+                .chars()
+                .collect();
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        for _ in 0..5000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let len = (seed % 6) as usize + 1;
+            let name: String = (0..len)
+                .map(|k| alphabet[((seed >> (8 * k)) as usize) % alphabet.len()])
+                .collect();
+            round_trip(enc, &name);
+        }
+        for name in ["CON", "nul.txt", ".", "..", "\u{FF0E}\u{FF0E}"] {
+            round_trip(enc, name);
+        }
+    }
+}
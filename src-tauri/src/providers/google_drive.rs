@@ -231,8 +231,7 @@ impl GoogleDriveProvider {
         Ok(all_files)
     }
 
-    /// Get file by ID (for future stat implementation)
-    #[allow(dead_code)]
+    /// Get file by ID (used by the change feed to walk parent folders)
     async fn get_file(&self, file_id: &str) -> Result<DriveFile, ProviderError> {
         let url = format!(
             "{}/files/{}?fields=id,name,mimeType,size,modifiedTime,parents",
@@ -367,6 +366,76 @@ impl GoogleDriveProvider {
         Ok(current_id)
     }
 
+    /// Resolve a folder ID back to its absolute path by walking `parents`
+    /// up to My Drive root. Used by the change feed, which only reports IDs.
+    /// Returns `None` for folders outside My Drive (shared drives, orphans)
+    /// or when the walk exceeds the depth guard.
+    async fn folder_path_by_id(
+        &self,
+        folder_id: &str,
+        root_id: &str,
+        memo: &mut HashMap<String, Option<String>>,
+    ) -> Option<String> {
+        const MAX_DEPTH: usize = 64;
+
+        if folder_id == root_id || folder_id == "root" {
+            return Some("/".to_string());
+        }
+        if let Some(hit) = memo.get(folder_id) {
+            return hit.clone();
+        }
+        // The path -> id cache already knows most folders the user browsed.
+        if let Some((path, _)) = self
+            .folder_cache
+            .iter()
+            .find(|(_, (id, _))| id == folder_id)
+        {
+            let path = format!("/{}", path.trim_matches('/'));
+            memo.insert(folder_id.to_string(), Some(path.clone()));
+            return Some(path);
+        }
+
+        // Walk upwards collecting names until a known ancestor is reached.
+        let mut chain: Vec<(String, String)> = Vec::new();
+        let mut current = folder_id.to_string();
+        let base = loop {
+            if chain.len() >= MAX_DEPTH {
+                break None;
+            }
+            if current == root_id {
+                break Some("/".to_string());
+            }
+            if let Some(hit) = memo.get(&current) {
+                break hit.clone();
+            }
+            let file = match self.get_file(&current).await {
+                Ok(f) => f,
+                Err(_) => break None,
+            };
+            let parent = file.parents.first().cloned();
+            chain.push((current.clone(), file.name));
+            match parent {
+                Some(p) => current = p,
+                None => break None,
+            }
+        };
+
+        // Unwind the chain from the topmost ancestor down, memoizing every
+        // intermediate folder (including negative results).
+        let mut path = base;
+        for (id, name) in chain.into_iter().rev() {
+            path = path.map(|dir| {
+                if dir == "/" {
+                    format!("/{}", name)
+                } else {
+                    format!("{}/{}", dir, name)
+                }
+            });
+            memo.insert(id, path.clone());
+        }
+        memo.get(folder_id).cloned().flatten()
+    }
+
     /// Check if MIME type is a Google Workspace type and return export MIME + extension
     fn workspace_export_info(mime_type: &str) -> Option<(&'static str, &'static str)> {
         WORKSPACE_EXPORT_MAP
@@ -2272,10 +2341,14 @@ impl StorageProvider for GoogleDriveProvider {
     ) -> Result<(Vec<super::ChangeEntry>, String), ProviderError> {
         let mut all_changes = Vec::new();
         let mut current_token = page_token.to_string();
+        // Folder id -> absolute path, shared across pages so a burst of
+        // changes inside the same folder costs one parent walk.
+        let mut folder_paths: HashMap<String, Option<String>> = HashMap::new();
+        let root_id = self.get_file("root").await?.id;
 
         loop {
             let url = format!(
-                "{}/changes?pageToken={}&fields=changes(fileId,file(name,mimeType,trashed),removed,time),newStartPageToken,nextPageToken&pageSize=1000",
+                "{}/changes?pageToken={}&fields=changes(fileId,file(name,mimeType,trashed,parents,size,modifiedTime),removed,time),newStartPageToken,nextPageToken&pageSize=1000",
                 DRIVE_API_BASE, urlencoding::encode(&current_token)
             );
 
@@ -2288,7 +2361,17 @@ impl StorageProvider for GoogleDriveProvider {
                 .map_err(|e| ProviderError::ConnectionFailed(e.to_string()))?;
 
             if !response.status().is_success() {
+                let status = response.status();
                 let text = response.text().await.unwrap_or_default();
+                // Drive answers 404/410 once a page token is no longer
+                // valid. Surface it as NotFound so callers can tell an
+                // expired cursor apart from a transport failure.
+                if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::GONE {
+                    return Err(ProviderError::NotFound(format!(
+                        "change token expired: {}",
+                        sanitize_api_error(&text)
+                    )));
+                }
                 return Err(ProviderError::Other(format!(
                     "List changes failed: {}",
                     sanitize_api_error(&text)
@@ -2301,6 +2384,10 @@ impl StorageProvider for GoogleDriveProvider {
                 name: Option<String>,
                 mime_type: Option<String>,
                 trashed: Option<bool>,
+                #[serde(default)]
+                parents: Vec<String>,
+                size: Option<String>,
+                modified_time: Option<String>,
             }
             #[derive(Deserialize)]
             #[serde(rename_all = "camelCase")]
@@ -2331,18 +2418,44 @@ impl StorageProvider for GoogleDriveProvider {
                         .and_then(|f| f.trashed)
                         .unwrap_or(false);
                 let change_type = if removed { "deleted" } else { "modified" };
+                let name = change
+                    .file
+                    .as_ref()
+                    .and_then(|f| f.name.clone())
+                    .unwrap_or_default();
+                let path = match change.file.as_ref() {
+                    Some(file) if !name.is_empty() => match file.parents.first() {
+                        Some(parent) => self
+                            .folder_path_by_id(parent, &root_id, &mut folder_paths)
+                            .await
+                            .map(|dir| {
+                                if dir == "/" {
+                                    format!("/{}", name)
+                                } else {
+                                    format!("{}/{}", dir, name)
+                                }
+                            }),
+                        None => None,
+                    },
+                    _ => None,
+                };
 
                 all_changes.push(super::ChangeEntry {
                     file_id: change.file_id.clone().unwrap_or_default(),
-                    name: change
-                        .file
-                        .as_ref()
-                        .and_then(|f| f.name.clone())
-                        .unwrap_or_default(),
+                    name,
                     change_type: change_type.to_string(),
                     mime_type: change.file.as_ref().and_then(|f| f.mime_type.clone()),
                     timestamp: change.time.clone(),
                     removed,
+                    path,
+                    size: change
+                        .file
+                        .as_ref()
+                        .and_then(|f| f.size.as_ref())
+                        .and_then(|s| s.parse().ok()),
+                    modified: change.file.as_ref().and_then(|f| f.modified_time.clone()),
+                    is_dir: change.file.as_ref().and_then(|f| f.mime_type.as_deref())
+                        == Some("application/vnd.google-apps.folder"),
                 });
             }
 
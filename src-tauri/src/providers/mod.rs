@@ -29,6 +29,7 @@ pub mod box_provider;
 pub mod cloudinary;
 pub mod drime_cloud;
 pub mod dropbox;
+pub mod encoding;
 pub mod filelu;
 pub mod filen;
 pub mod fourshared;
@@ -385,11 +386,50 @@ pub trait StorageProvider: Send + Sync {
         Err(ProviderError::NotSupported("chmod".to_string()))
     }
 
+    /// Check if provider can change file ownership
+    fn supports_chown(&self) -> bool {
+        false
+    }
+
+    /// Change the numeric owner and/or group of a file (`None` keeps it)
+    async fn chown(
+        &mut self,
+        _path: &str,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+    ) -> Result<(), ProviderError> {
+        Err(ProviderError::NotSupported("chown".to_string()))
+    }
+
     /// Check if provider supports symlinks
     fn supports_symlinks(&self) -> bool {
         false
     }
 
+    /// Create a symbolic link at `link_path` pointing to `target` (stored verbatim)
+    async fn create_symlink(
+        &mut self,
+        _target: &str,
+        _link_path: &str,
+    ) -> Result<(), ProviderError> {
+        Err(ProviderError::NotSupported("create_symlink".to_string()))
+    }
+
+    /// Check if provider can create hard links
+    fn supports_hardlinks(&self) -> bool {
+        false
+    }
+
+    /// Create a hard link at `link_path` to the existing file `existing`
+    async fn hard_link(&mut self, _existing: &str, _link_path: &str) -> Result<(), ProviderError> {
+        Err(ProviderError::NotSupported("hard_link".to_string()))
+    }
+
+    /// Check if the backend folds case in file names
+    fn is_case_insensitive(&self) -> bool {
+        self.provider_type().is_case_insensitive()
+    }
+
     /// Check if provider supports server-side copy
     fn supports_server_copy(&self) -> bool {
         false
@@ -659,8 +699,14 @@ pub trait StorageProvider: Send + Sync {
 pub struct ProviderFactory;
 
 impl ProviderFactory {
-    /// Create a new provider instance based on configuration
+    /// Create a new provider instance based on configuration.
+    /// An `encoding` option wraps the provider in an [`encoding::EncodedProvider`].
     pub fn create(config: &ProviderConfig) -> Result<Box<dyn StorageProvider>, ProviderError> {
+        let provider = Self::create_backend(config)?;
+        encoding::with_name_encoding(provider, config.extra.get("encoding").map(String::as_str))
+    }
+
+    fn create_backend(config: &ProviderConfig) -> Result<Box<dyn StorageProvider>, ProviderError> {
         match config.provider_type {
             ProviderType::Ftp | ProviderType::Ftps => {
                 let ftp_config = FtpConfig::from_provider_config(config)?;
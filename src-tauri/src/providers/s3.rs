@@ -1009,13 +1009,14 @@ impl S3Provider {
         Ok(etag)
     }
 
-    /// Complete a multipart upload
+    /// Complete a multipart upload, returns the ETag of the new object
+    /// when the response carries one.
     async fn complete_multipart_upload(
         &self,
         key: &str,
         upload_id: &str,
         parts: &[(u32, String)],
-    ) -> Result<(), ProviderError> {
+    ) -> Result<Option<String>, ProviderError> {
         // Build XML body
         let mut xml = String::from("<CompleteMultipartUpload>");
         for (part_number, etag) in parts {
@@ -1058,7 +1059,9 @@ impl S3Provider {
             )));
         }
 
-        Ok(())
+        Ok(self
+            .extract_xml_tag(&body, "ETag")
+            .map(|etag| unquote_etag(&etag)))
     }
 
     /// Upload a file using S3 multipart upload with streaming (no full-file buffering).
@@ -1167,6 +1170,7 @@ impl S3Provider {
 
         self.complete_multipart_upload(key, &upload_id, &parts)
             .await
+            .map(|_| ())
     }
 
     /// Abort a multipart upload
@@ -1444,6 +1448,11 @@ impl S3Provider {
     }
 }
 
+/// Strip the quotes S3 puts around an ETag, raw or entity-escaped.
+fn unquote_etag(etag: &str) -> String {
+    etag.replace("&quot;", "").trim_matches('"').to_string()
+}
+
 /// Extract error message from S3 XML error response
 fn extract_s3_error(body: &str) -> String {
     if body.contains("<Message>") {
@@ -3412,6 +3421,223 @@ impl S3Provider {
     }
 }
 
+// ── Block-level delta upload (UploadPartCopy) ─────────────────────────────
+
+/// Where one part of an [`S3Provider::upload_part_plan`] upload comes from.
+/// Offsets are the same in the local file and in the existing object: a
+/// delta plan only reuses ranges that did not move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartSource {
+    /// Server-side copy of `len` bytes at `offset` of the current object.
+    Copy { offset: u64, len: u64 },
+    /// `len` bytes at `offset` of the local file.
+    Upload { offset: u64, len: u64 },
+}
+
+impl PartSource {
+    pub fn len(&self) -> u64 {
+        match *self {
+            PartSource::Copy { len, .. } | PartSource::Upload { len, .. } => len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl S3Provider {
+    /// Minimum size of every part but the last (S3 multipart limit).
+    pub const MULTIPART_MIN_PART_SIZE: u64 = Self::MULTIPART_THRESHOLD as u64;
+    /// Maximum number of parts in one multipart upload.
+    pub const MULTIPART_MAX_PARTS: u64 = 10_000;
+    /// Largest range a single UploadPartCopy may copy (5 GiB).
+    pub const MULTIPART_MAX_COPY_PART: u64 = 5 * 1024 * 1024 * 1024;
+
+    /// Full URL of the object behind `path`. Identifies the object across
+    /// profiles pointing at the same bucket.
+    pub fn object_url(&self, path: &str) -> String {
+        self.build_url(path.trim_start_matches('/'))
+    }
+
+    /// Part size the regular multipart upload would use.
+    pub fn upload_part_size(&self) -> u64 {
+        self.effective_part_size() as u64
+    }
+
+    /// Whether the endpoint takes multipart uploads at all (Filen Desktop
+    /// S3 answers 501 to CreateMultipartUpload).
+    pub fn supports_multipart(&self) -> bool {
+        !self.is_filen_s3_endpoint()
+    }
+
+    /// Copy a byte range of the object being replaced, as long as it still
+    /// has ETag `source_etag`, into part `part_number` of its upload.
+    /// Returns the ETag of the part.
+    async fn upload_part_copy(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        source_etag: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<String, ProviderError> {
+        let part_num_str = part_number.to_string();
+        let params: &[(&str, &str)] = &[("partNumber", &part_num_str), ("uploadId", upload_id)];
+        let copy_source = format!("/{}/{}", self.config.bucket, urlencoding::encode(key));
+        let range = format!("bytes={}-{}", offset, offset + len - 1);
+        let if_match = format!("\"{}\"", source_etag);
+        let headers: &[(&str, &str)] = &[
+            ("x-amz-copy-source", &copy_source),
+            ("x-amz-copy-source-range", &range),
+            ("x-amz-copy-source-if-match", &if_match),
+        ];
+
+        let response = self
+            .s3_request_ext(Method::PUT, key, Some(params), Some(Vec::new()), headers)
+            .await?;
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if !status.is_success() || body.contains("<Error>") {
+            return Err(ProviderError::TransferFailed(format!(
+                "UploadPartCopy {} failed ({}): {}",
+                part_number,
+                status,
+                sanitize_api_error(&body)
+            )));
+        }
+
+        self.extract_xml_tag(&body, "ETag")
+            .map(|etag| format!("\"{}\"", unquote_etag(&etag)))
+            .ok_or_else(|| {
+                ProviderError::ParseError("Missing ETag in UploadPartCopy response".to_string())
+            })
+    }
+
+    /// Rewrite the object at `remote_path` as a multipart upload assembled
+    /// from `parts`: `Copy` parts are taken server-side from the current
+    /// object (which must still carry `source_etag`), `Upload` parts are
+    /// read from `local_path`. Parts must satisfy the multipart size rules;
+    /// any failure aborts the upload and leaves the object as it was.
+    /// Returns the ETag of the new object.
+    pub async fn upload_part_plan(
+        &self,
+        local_path: &str,
+        remote_path: &str,
+        source_etag: Option<&str>,
+        parts: &[PartSource],
+    ) -> Result<String, ProviderError> {
+        use tokio::io::AsyncReadExt;
+
+        if !self.connected {
+            return Err(ProviderError::NotConnected);
+        }
+        let key = remote_path.trim_start_matches('/');
+        if parts.len() as u64 > Self::MULTIPART_MAX_PARTS {
+            return Err(ProviderError::TransferFailed(format!(
+                "{} parts exceed the multipart limit of {}",
+                parts.len(),
+                Self::MULTIPART_MAX_PARTS
+            )));
+        }
+        if source_etag.is_none() && parts.iter().any(|p| matches!(p, PartSource::Copy { .. })) {
+            return Err(ProviderError::TransferFailed(
+                "copy parts need the ETag of the source object".to_string(),
+            ));
+        }
+
+        let content_type = mime_guess::from_path(local_path)
+            .first_or_octet_stream()
+            .to_string();
+        let upload_id = self
+            .create_multipart_upload(key, Some(&content_type))
+            .await?;
+        let mut file = tokio::fs::File::open(local_path)
+            .await
+            .map_err(ProviderError::IoError)?;
+        let mut etags: Vec<(u32, String)> = Vec::with_capacity(parts.len());
+        let max_parallel = 4usize;
+
+        for (batch_index, batch) in parts.chunks(max_parallel).enumerate() {
+            let mut joinset = tokio::task::JoinSet::new();
+            for (i, part) in batch.iter().enumerate() {
+                let pn = (batch_index * max_parallel + i + 1) as u32;
+                let provider = self.clone();
+                let key_owned = key.to_string();
+                let uid = upload_id.clone();
+                match *part {
+                    PartSource::Copy { offset, len } => {
+                        let etag = source_etag.unwrap_or_default().to_string();
+                        joinset.spawn(async move {
+                            let part_etag = provider
+                                .upload_part_copy(&key_owned, &uid, pn, &etag, offset, len)
+                                .await?;
+                            Ok::<(u32, String), ProviderError>((pn, part_etag))
+                        });
+                    }
+                    PartSource::Upload { offset, len } => {
+                        let mut data = vec![0u8; len as usize];
+                        let read = async {
+                            file.seek(std::io::SeekFrom::Start(offset)).await?;
+                            file.read_exact(&mut data).await
+                        }
+                        .await;
+                        if let Err(e) = read {
+                            joinset.abort_all();
+                            while joinset.join_next().await.is_some() {}
+                            let _ = self.abort_multipart_upload(key, &upload_id).await;
+                            return Err(ProviderError::TransferFailed(format!("Read error: {e}")));
+                        }
+                        joinset.spawn(async move {
+                            let part_etag =
+                                provider.upload_part(&key_owned, &uid, pn, data).await?;
+                            Ok::<(u32, String), ProviderError>((pn, part_etag))
+                        });
+                    }
+                }
+            }
+
+            while let Some(joined) = joinset.join_next().await {
+                let failure = match joined {
+                    Ok(Ok(part)) => {
+                        etags.push(part);
+                        continue;
+                    }
+                    Ok(Err(e)) => e,
+                    Err(e) => ProviderError::TransferFailed(format!("Upload task panicked: {e}")),
+                };
+                joinset.abort_all();
+                while joinset.join_next().await.is_some() {}
+                let _ = self.abort_multipart_upload(key, &upload_id).await;
+                return Err(failure);
+            }
+        }
+        etags.sort_by_key(|(pn, _)| *pn);
+
+        match self
+            .complete_multipart_upload(key, &upload_id, &etags)
+            .await?
+        {
+            Some(etag) => Ok(etag),
+            // Some S3-compatible services leave the ETag out of the response.
+            None => {
+                let response = self.s3_request(Method::HEAD, key, None, None).await?;
+                response
+                    .headers()
+                    .get("etag")
+                    .and_then(|v| v.to_str().ok())
+                    .map(unquote_etag)
+                    .ok_or_else(|| {
+                        ProviderError::ParseError(
+                            "Missing ETag after CompleteMultipartUpload".to_string(),
+                        )
+                    })
+            }
+        }
+    }
+}
+
 // ── S3 fast-list (recursive listing without delimiter) ────────────────────
 
 impl S3Provider {
@@ -3686,6 +3912,16 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_unquote_etag_header_and_xml_forms() {
+        assert_eq!(
+            unquote_etag("\"9b2cf535f27731c974343645a3985328-3\""),
+            "9b2cf535f27731c974343645a3985328-3"
+        );
+        assert_eq!(unquote_etag("&quot;9b2cf535&quot;"), "9b2cf535");
+        assert_eq!(unquote_etag("9b2cf535"), "9b2cf535");
+    }
+
     // ── U-13 multi-thread download: range planner ─────────────────────
 
     fn ranges_cover(total: u64, ranges: &[(u64, u64)]) -> bool {
@@ -1331,10 +1331,100 @@ impl StorageProvider for SftpProvider {
         Ok(())
     }
 
+    fn supports_chown(&self) -> bool {
+        true
+    }
+
+    async fn chown(
+        &mut self,
+        path: &str,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> Result<(), ProviderError> {
+        let sftp = self.get_sftp()?;
+        let full_path = self.normalize_path(path);
+
+        // SFTP v3 sets uid and gid together: keep the current value of
+        // whichever one was not asked for (it would otherwise become 0).
+        let (uid, gid) = match (uid, gid) {
+            (Some(uid), Some(gid)) => (uid, gid),
+            (None, None) => return Ok(()),
+            _ => {
+                let current = sftp.metadata(&full_path).await.map_err(|e| {
+                    classify_russh_err(e, |s| {
+                        ProviderError::ServerError(format!("Failed to stat: {}", s))
+                    })
+                })?;
+                (
+                    uid.or(current.uid).unwrap_or_default(),
+                    gid.or(current.gid).unwrap_or_default(),
+                )
+            }
+        };
+
+        tracing::info!("SFTP: chown {} to {}:{}", full_path, uid, gid);
+
+        let attrs = russh_sftp::protocol::FileAttributes {
+            uid: Some(uid),
+            gid: Some(gid),
+            ..Default::default()
+        };
+
+        sftp.set_metadata(&full_path, attrs).await.map_err(|e| {
+            classify_russh_err(e, |s| {
+                ProviderError::ServerError(format!("Failed to chown: {}", s))
+            })
+        })?;
+
+        Ok(())
+    }
+
     fn supports_symlinks(&self) -> bool {
         true // SFTP supports symlinks
     }
 
+    async fn create_symlink(&mut self, target: &str, link_path: &str) -> Result<(), ProviderError> {
+        let sftp = self.get_sftp()?;
+        let link = self.normalize_path(link_path);
+
+        tracing::info!("SFTP: Creating symlink {} -> {}", link, target);
+
+        // OpenSSH reads SSH_FXP_SYMLINK arguments in the reverse order of the
+        // draft spec, so the target goes first.
+        sftp.symlink(target, link.as_str()).await.map_err(|e| {
+            classify_russh_err(e, |s| {
+                ProviderError::ServerError(format!("Failed to create symlink: {}", s))
+            })
+        })?;
+
+        Ok(())
+    }
+
+    fn supports_hardlinks(&self) -> bool {
+        true // via hardlink@openssh.com, when the server advertises it
+    }
+
+    async fn hard_link(&mut self, existing: &str, link_path: &str) -> Result<(), ProviderError> {
+        let sftp = self.get_sftp()?;
+        let existing = self.normalize_path(existing);
+        let link = self.normalize_path(link_path);
+
+        tracing::info!("SFTP: Hard-linking {} to {}", link, existing);
+
+        let linked = sftp.hardlink(&existing, &link).await.map_err(|e| {
+            classify_russh_err(e, |s| {
+                ProviderError::ServerError(format!("Failed to create hard link: {}", s))
+            })
+        })?;
+        if !linked {
+            return Err(ProviderError::NotSupported(
+                "hardlink@openssh.com extension".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
     fn supports_find(&self) -> bool {
         true
     }
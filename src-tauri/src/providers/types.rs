@@ -224,6 +224,22 @@ impl ProviderType {
     pub fn is_aerocloud(&self) -> bool {
         matches!(self, ProviderType::AeroCloud)
     }
+
+    /// Check if this provider treats names differing only by case as the
+    /// same file (`Readme.md` overwrites `README.md`)
+    pub fn is_case_insensitive(&self) -> bool {
+        matches!(
+            self,
+            ProviderType::Dropbox
+                | ProviderType::OneDrive
+                | ProviderType::Box
+                | ProviderType::ZohoWorkdrive
+                | ProviderType::KDrive
+                | ProviderType::Jottacloud
+                | ProviderType::Koofr
+                | ProviderType::OpenDrive
+        )
+    }
 }
 
 /// Generic provider configuration
@@ -1508,6 +1524,15 @@ pub struct ChangeEntry {
     pub timestamp: Option<String>,
     /// Whether the file was trashed/deleted
     pub removed: bool,
+    /// Absolute path of the entry after the change, when the provider can
+    /// resolve it. `None` for permanent removals (no parent information left).
+    pub path: Option<String>,
+    /// Size in bytes after the change (files only)
+    pub size: Option<u64>,
+    /// Modification time after the change (provider format, usually RFC 3339)
+    pub modified: Option<String>,
+    /// Whether the changed entry is a folder
+    pub is_dir: bool,
 }
 
 /// Transfer progress information (for future progress events)
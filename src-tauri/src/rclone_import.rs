@@ -173,6 +173,23 @@ fn map_webdav_vendor(vendor: &str) -> &'static str {
 
 /// Convert a single rclone remote to an AeroFTP profile.
 fn map_remote(name: &str, remote: &RcloneRemote) -> Option<MappedProfile> {
+    let mut mapped = map_backend(name, remote)?;
+    // `encoding` uses the same flag names in both tools, so it carries over as-is.
+    if let Some(encoding) = remote.get("encoding").filter(|v| !v.is_empty()) {
+        let mut options = match mapped.options.take() {
+            Some(serde_json::Value::Object(m)) => m,
+            _ => serde_json::Map::new(),
+        };
+        options.insert(
+            "encoding".into(),
+            serde_json::Value::String(encoding.clone()),
+        );
+        mapped.options = Some(serde_json::Value::Object(options));
+    }
+    Some(mapped)
+}
+
+fn map_backend(name: &str, remote: &RcloneRemote) -> Option<MappedProfile> {
     let rclone_type = remote.get("type")?.to_lowercase();
 
     // Helper to get and optionally reveal password
@@ -1218,6 +1235,18 @@ region = eu-west-1
         assert_eq!(mapped.protocol, "ftps");
     }
 
+    #[test]
+    fn test_map_carries_encoding() {
+        let mut remote = HashMap::new();
+        remote.insert("type".into(), "ftp".into());
+        remote.insert("host".into(), "ftp.example.com".into());
+        remote.insert("encoding".into(), "Slash,Del,Ctl,RightSpace,Dot".into());
+
+        let mapped = map_remote("test-ftp", &remote).expect("should map FTP");
+        let options = mapped.options.expect("options");
+        assert_eq!(options["encoding"], "Slash,Del,Ctl,RightSpace,Dot");
+    }
+
     #[test]
     fn test_map_unsupported() {
         let mut remote = HashMap::new();
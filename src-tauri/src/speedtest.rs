@@ -1064,7 +1064,15 @@ pub fn init_history_schema(conn: &Connection) -> Result<(), String> {
 }
 
 pub fn init_history_db(app: &AppHandle) -> Result<Connection, String> {
-    let path = history_db_path(app)?;
+    open_history_db(&history_db_path(app)?)
+}
+
+/// History DB location for callers without an `AppHandle` (the CLI).
+pub(crate) fn history_db_path_detached() -> Option<PathBuf> {
+    crate::portable::app_config_dir_detached().map(|dir| dir.join("speedtest_history.db"))
+}
+
+pub(crate) fn open_history_db(path: &std::path::Path) -> Result<Connection, String> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent).map_err(|e| format!("Cannot create config dir: {e}"))?;
         #[cfg(unix)]
@@ -1073,12 +1081,12 @@ pub fn init_history_db(app: &AppHandle) -> Result<Connection, String> {
             let _ = std::fs::set_permissions(parent, std::fs::Permissions::from_mode(0o700));
         }
     }
-    let conn = Connection::open(&path)
+    let conn = Connection::open(path)
         .map_err(|_| "Failed to initialize speed test history database".to_string())?;
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+        let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
     }
     init_history_schema(&conn)?;
     Ok(conn)
@@ -1135,6 +1143,13 @@ pub async fn speedtest_history_record(
     record: SpeedTestHistoryRecordRequest,
 ) -> Result<i64, String> {
     let conn = history_acquire(&db);
+    insert_history_row(&conn, record)
+}
+
+pub(crate) fn insert_history_row(
+    conn: &Connection,
+    record: SpeedTestHistoryRecordRequest,
+) -> Result<i64, String> {
     // Defense in depth (audit P1-11): never persist a user-supplied display name.
     // The column survives for schema-stability with older DB files; new rows
     // store NULL regardless of what the caller sends. Display names are
@@ -1327,6 +1342,38 @@ pub async fn speedtest_history_clear(
     Ok(deleted as u32)
 }
 
+/// Host fingerprint stored in `host_hash`; matches `hashHost` in the UI
+/// (SHA-256 of the lowercased host, first 32 hex chars).
+pub(crate) fn host_hash(host: &str) -> String {
+    let digest = Sha256::digest(host.to_lowercase().as_bytes());
+    hex::encode(digest)[..32].to_string()
+}
+
+/// Median upload and download rate of the recent tests against a host.
+pub(crate) fn median_throughput_for_host(
+    conn: &Connection,
+    host_hash: &str,
+) -> Result<(Option<f64>, Option<f64>), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT upload_bps, download_bps FROM speedtest_results
+             WHERE host_hash = ?1
+             ORDER BY created_at DESC
+             LIMIT 20",
+        )
+        .map_err(|e| format!("Prepare: {e}"))?;
+    let rows: Vec<(f64, f64)> = stmt
+        .query_map(params![host_hash], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Query: {e}"))?
+        .filter_map(Result::ok)
+        .collect();
+    let positive = |values: Vec<f64>| median_f64(values.into_iter().filter(|v| *v > 0.0).collect());
+    Ok((
+        positive(rows.iter().map(|r| r.0).collect()),
+        positive(rows.iter().map(|r| r.1).collect()),
+    ))
+}
+
 fn median_f64(mut values: Vec<f64>) -> Option<f64> {
     if values.is_empty() {
         return None;
@@ -1378,4 +1425,35 @@ mod history_tests {
             .unwrap();
         assert_eq!(count, 1);
     }
+
+    #[test]
+    fn host_medians_ignore_other_hosts() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_history_schema(&conn).unwrap();
+        let hash = host_hash("Files.Example.com");
+        assert_eq!(hash, host_hash("files.example.com"));
+        assert_eq!(hash.len(), 32);
+        for (host, up, down) in [
+            (hash.as_str(), 100.0, 400.0),
+            (hash.as_str(), 300.0, 0.0),
+            ("other", 9000.0, 9000.0),
+        ] {
+            conn.execute(
+                "INSERT INTO speedtest_results
+                 (host_hash, protocol, size_bytes, upload_bps, download_bps,
+                  upload_ms, download_ms, integrity_verified, cleanup_ok)
+                 VALUES (?1, 'sftp', 1048576, ?2, ?3, 1000, 1000, 1, 1)",
+                params![host, up, down],
+            )
+            .unwrap();
+        }
+        let (up, down) = median_throughput_for_host(&conn, &hash).unwrap();
+        assert_eq!(up, Some(200.0));
+        // A failed direction is recorded as 0 and must not drag the median down.
+        assert_eq!(down, Some(400.0));
+        assert_eq!(
+            median_throughput_for_host(&conn, "unknown").unwrap(),
+            (None, None)
+        );
+    }
 }
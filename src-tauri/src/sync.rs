@@ -6,7 +6,10 @@
 
 use crate::delta_transport::DeltaBatch;
 use crate::providers::{ProviderError, StorageProvider};
+use crate::sync_core::meta::{FileMeta, MetaDiff, MetaOptions};
+use crate::sync_core::names::match_remote_names;
 use crate::sync_core::scan::{scan_local_tree, scan_remote_tree, ScanOptions};
+use crate::sync_versioning::{is_versions_path, SyncVersioning, VersioningStrategy};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -50,6 +53,9 @@ pub struct FileInfo {
     pub modified: Option<DateTime<Utc>>,
     pub is_dir: bool,
     pub checksum: Option<String>,
+    /// Mode, owner and xattrs, when the compare asked for them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<FileMeta>,
 }
 
 /// Result of comparing a single file/directory
@@ -67,6 +73,10 @@ pub struct FileComparison {
     /// means the OTHER side deleted it (vs being a genuinely new file).
     #[serde(default)]
     pub previously_synced: bool,
+    /// Permission/owner/xattr changes to apply when the content already
+    /// matches (status `identical`), so they sync without a re-transfer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta_diff: Option<MetaDiff>,
 }
 
 /// Options for comparison
@@ -94,6 +104,9 @@ pub struct CompareOptions {
     /// Maximum file age in seconds (skip older files)
     #[serde(default)]
     pub max_age_secs: Option<u64>,
+    /// Permissions, owner and xattrs to compare (`--perms`, `--owner`, ...)
+    #[serde(default)]
+    pub metadata: MetaOptions,
 }
 
 impl Default for CompareOptions {
@@ -117,6 +130,7 @@ impl Default for CompareOptions {
             max_size: None,
             min_age_secs: None,
             max_age_secs: None,
+            metadata: MetaOptions::default(),
         }
     }
 }
@@ -223,6 +237,9 @@ pub struct SyncOptions {
     pub delete_orphans: bool,
     pub conflict_mode: ConflictMode,
     pub scan: ScanOptions,
+    /// Archive remote files into `<remote_root>/.aeroversions/` before they
+    /// are overwritten or deleted. `Disabled` keeps the old behavior.
+    pub remote_versioning: VersioningStrategy,
 }
 
 impl Default for SyncOptions {
@@ -234,6 +251,7 @@ impl Default for SyncOptions {
             delete_orphans: false,
             conflict_mode: ConflictMode::Larger,
             scan: ScanOptions::default(),
+            remote_versioning: VersioningStrategy::Disabled,
         }
     }
 }
@@ -248,8 +266,9 @@ pub enum SyncPhase {
 }
 
 /// Serializable subset of [`RsyncStats`](crate::rsync_over_ssh::RsyncStats)
-/// carried alongside `FileOutcome` when the delta path actually serviced the
-/// transfer. `bytes_sent` below `total_size` is the savings signal.
+/// carried alongside `FileOutcome` when a delta path (rsync, or S3
+/// `UploadPartCopy`) actually serviced the transfer. `bytes_sent` below
+/// `total_size` is the savings signal.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct DeltaTransferStats {
     pub bytes_sent: u64,
@@ -295,8 +314,8 @@ pub struct DeltaFileEntry {
     pub speedup: f64,
 }
 
-#[cfg(unix)]
 impl DeltaTransferStats {
+    #[cfg(unix)]
     pub(crate) fn from_rsync(stats: &crate::rsync_over_ssh::RsyncStats) -> Self {
         Self {
             bytes_sent: stats.bytes_sent,
@@ -304,6 +323,16 @@ impl DeltaTransferStats {
             speedup: stats.speedup,
         }
     }
+
+    pub(crate) fn from_s3_delta(upload: &crate::delta_sync_s3::S3DeltaUpload) -> Self {
+        Self {
+            bytes_sent: upload.bytes_uploaded,
+            total_size: upload.total_size,
+            // Every block copied server-side (nothing sent) is the best case,
+            // so it counts as if one byte had been sent.
+            speedup: upload.total_size.max(1) as f64 / upload.bytes_uploaded.max(1) as f64,
+        }
+    }
 }
 
 /// Per-file outcome delivered to the progress sink after each operation.
@@ -375,6 +404,9 @@ pub struct SyncReport {
     /// the batch path. May be less than `uploaded + downloaded` if some
     /// files fell back to the single-shot or classic path.
     pub delta_batch_files: Option<u64>,
+    /// Archived remote versions removed by the retention policy after the
+    /// run (0 when remote versioning is disabled).
+    pub versions_pruned: u32,
 }
 
 impl SyncReport {
@@ -829,12 +861,58 @@ fn format_duration(secs: i64) -> String {
     }
 }
 
+/// Re-key remote entries whose relative path differs from a local one only
+/// by Unicode normalization (NFD from macOS vs NFC), so the two are compared
+/// as one file. `FileInfo::path` keeps the real remote spelling.
+fn align_remote_names(
+    local_files: &HashMap<String, FileInfo>,
+    mut remote_files: HashMap<String, FileInfo>,
+) -> HashMap<String, FileInfo> {
+    let renamed = match_remote_names(
+        local_files.keys().map(String::as_str),
+        remote_files.keys().map(String::as_str),
+        false,
+    );
+    for (remote_rel, local_rel) in renamed {
+        if let Some(info) = remote_files.remove(&remote_rel) {
+            remote_files.insert(local_rel, info);
+        }
+    }
+    remote_files
+}
+
+/// Metadata changes for a pair whose content matches. They follow the
+/// compare direction, so a bidirectional compare reports none; sides
+/// without metadata are not compared.
+fn metadata_diff(
+    local: Option<&FileInfo>,
+    remote: Option<&FileInfo>,
+    is_dir: bool,
+    options: &CompareOptions,
+) -> Option<MetaDiff> {
+    if !options.metadata.is_enabled() {
+        return None;
+    }
+    let (local, remote) = (local?.meta.as_ref()?, remote?.meta.as_ref()?);
+    let diff = match options.direction {
+        CompareDirection::LocalToRemote => options.metadata.diff(local, remote, is_dir),
+        CompareDirection::RemoteToLocal => options.metadata.diff(remote, local, is_dir),
+        CompareDirection::Bidirectional => return None,
+    };
+    (!diff.is_empty()).then_some(diff)
+}
+
+fn metadata_sync_reason(diff: Option<&MetaDiff>) -> Option<String> {
+    diff.map(|diff| format!("Metadata differs: {}", diff.describe()))
+}
+
 /// Build comparison results from local and remote file maps
 pub fn build_comparison_results(
     local_files: HashMap<String, FileInfo>,
     remote_files: HashMap<String, FileInfo>,
     options: &CompareOptions,
 ) -> Vec<FileComparison> {
+    let remote_files = align_remote_names(&local_files, remote_files);
     let mut results = Vec::new();
     let mut all_paths: std::collections::HashSet<String> = local_files.keys().cloned().collect();
     all_paths.extend(remote_files.keys().cloned());
@@ -865,8 +943,15 @@ pub fn build_comparison_results(
         let is_dir =
             local.map(|f| f.is_dir).unwrap_or(false) || remote.map(|f| f.is_dir).unwrap_or(false);
 
-        if status != SyncStatus::Identical || is_dir {
-            let sync_reason = generate_sync_reason(&status, local, remote, is_dir);
+        let meta_diff = if status == SyncStatus::Identical {
+            metadata_diff(local, remote, is_dir, options)
+        } else {
+            None
+        };
+
+        if status != SyncStatus::Identical || is_dir || meta_diff.is_some() {
+            let sync_reason = metadata_sync_reason(meta_diff.as_ref())
+                .unwrap_or_else(|| generate_sync_reason(&status, local, remote, is_dir));
             results.push(FileComparison {
                 relative_path: path,
                 status,
@@ -875,6 +960,7 @@ pub fn build_comparison_results(
                 is_dir,
                 sync_reason,
                 previously_synced: false,
+                meta_diff,
             });
         }
     }
@@ -925,7 +1011,9 @@ pub async fn sync_tree_core(
 ) -> SyncReport {
     let start = std::time::Instant::now();
     sink.on_phase(SyncPhase::Scanning);
-    let locals = scan_local_tree(local_root, &opts.scan);
+    let mut locals = scan_local_tree(local_root, &opts.scan);
+    // Archived versions are never part of the synced tree, on either side.
+    locals.retain(|entry| !is_versions_path(&entry.rel_path));
 
     if !opts.dry_run
         && !locals.is_empty()
@@ -934,7 +1022,9 @@ pub async fn sync_tree_core(
         ensure_remote_dir(provider, remote_root).await;
     }
 
-    let remotes = scan_remote_tree(provider, remote_root, &opts.scan).await;
+    let mut remotes = scan_remote_tree(provider, remote_root, &opts.scan).await;
+    remotes.retain(|entry| !is_versions_path(&entry.rel_path));
+    let versioning = SyncVersioning::new(Path::new(local_root), opts.remote_versioning.clone());
 
     sink.on_phase(SyncPhase::Planning);
     let mut report = SyncReport {
@@ -999,21 +1089,60 @@ pub async fn sync_tree_core(
                     if matches!(opts.direction, SyncDirection::Both) {
                         upload_resolved_for_both.insert(local_entry.rel_path.clone());
                     }
-                    let outcome = perform_upload(
-                        provider,
-                        local_root,
-                        remote_root,
-                        SyncTransferSpec {
-                            rel: &local_entry.rel_path,
-                            total: local_entry.size,
-                            decision_policy: decision.decision_policy,
-                            requested_policy: opts.delta_policy,
-                        },
-                        opts.dry_run,
-                        sink,
-                        &mut delta_batch,
-                    )
-                    .await;
+                    let archived = if remote_entry.is_some() && !opts.dry_run {
+                        archive_remote_version(
+                            &versioning,
+                            provider,
+                            remote_root,
+                            &local_entry.rel_path,
+                            true,
+                        )
+                        .await
+                    } else {
+                        Ok(None)
+                    };
+                    let outcome = match archived {
+                        Ok(moved_to) => {
+                            let outcome = perform_upload(
+                                provider,
+                                local_root,
+                                remote_root,
+                                SyncTransferSpec {
+                                    rel: &local_entry.rel_path,
+                                    total: local_entry.size,
+                                    decision_policy: decision.decision_policy,
+                                    requested_policy: opts.delta_policy,
+                                },
+                                opts.dry_run,
+                                sink,
+                                &mut delta_batch,
+                            )
+                            .await;
+                            match moved_to {
+                                Some(archive_path) => {
+                                    settle_moved_archive(
+                                        provider,
+                                        remote_root,
+                                        &local_entry.rel_path,
+                                        &archive_path,
+                                        opts.delta_policy,
+                                        outcome,
+                                    )
+                                    .await
+                                }
+                                None => outcome,
+                            }
+                        }
+                        Err(error) => {
+                            sink.on_file_start(
+                                &local_entry.rel_path,
+                                local_entry.size,
+                                "upload",
+                                decision.decision_policy,
+                            );
+                            FileOutcome::Failed { error }
+                        }
+                    };
                     apply_sync_tree_outcome(
                         &mut report,
                         &local_entry.rel_path,
@@ -1111,6 +1240,7 @@ pub async fn sync_tree_core(
                             &remote_entry.rel_path,
                             opts.delta_policy,
                             opts.dry_run,
+                            &versioning,
                             sink,
                         )
                         .await;
@@ -1172,6 +1302,27 @@ pub async fn sync_tree_core(
         }
     }
 
+    // Retention for `.aeroversions/` only runs after a clean pass, so a
+    // failed run never prunes a version it might be needed to recover from.
+    if versioning.is_enabled() && !opts.dry_run && report.errors.is_empty() {
+        match versioning
+            .cleanup_remote(provider.as_mut(), remote_root)
+            .await
+        {
+            Ok(stats) => {
+                report.versions_pruned = stats.deleted_count;
+                if stats.deleted_count > 0 {
+                    tracing::info!(
+                        "sync: pruned {} remote version(s), {} bytes freed",
+                        stats.deleted_count,
+                        stats.freed_bytes
+                    );
+                }
+            }
+            Err(e) => tracing::warn!("sync: remote version cleanup failed: {e}"),
+        }
+    }
+
     report.elapsed_secs = start.elapsed().as_secs_f64();
     sink.on_phase(SyncPhase::Done);
     report
@@ -1535,6 +1686,51 @@ async fn perform_upload(
     let remote_path = join_clean_remote(remote_root, transfer.rel);
     ensure_remote_parent(provider, &remote_path).await;
 
+    // S3-compatible storage: rewrite the object as a multipart upload that
+    // copies unchanged parts server-side (`UploadPartCopy`) and sends only
+    // the changed ones. `None` for other providers and single-part files.
+    if matches!(transfer.requested_policy, DeltaPolicy::Delta) {
+        match crate::delta_sync_s3::try_s3_delta_upload(
+            &mut **provider,
+            Path::new(&local_path),
+            &remote_path,
+        )
+        .await
+        {
+            Some(Ok(upload)) => {
+                tracing::info!(
+                    "sync.delta: used S3 part-copy path (remote={}, copied={}, uploaded={})",
+                    remote_path,
+                    upload.bytes_copied,
+                    upload.bytes_uploaded
+                );
+                return FileOutcome::Uploaded {
+                    bytes: transfer.total,
+                    delta_stats: Some(DeltaTransferStats::from_s3_delta(&upload)),
+                    fallback_reason: None,
+                };
+            }
+            Some(Err(reason)) => {
+                tracing::info!(
+                    "sync.delta: S3 part-copy fallback to classic (remote={}, reason={})",
+                    remote_path,
+                    reason
+                );
+                return match provider.upload(&local_path, &remote_path, None).await {
+                    Ok(()) => FileOutcome::Uploaded {
+                        bytes: transfer.total,
+                        delta_stats: None,
+                        fallback_reason: Some(reason),
+                    },
+                    Err(e) => FileOutcome::Failed {
+                        error: format!("upload failed: {}", e),
+                    },
+                };
+            }
+            None => {}
+        }
+    }
+
     // P3-T01 W4.2: when a session-reuse batch is open, try it first.
     // The batch keeps a single SSH session alive across N files, so the
     // per-file cost drops to a channel-exec open. On used_delta the
@@ -1779,12 +1975,78 @@ async fn perform_download(
     }
 }
 
+/// Remote versioning: keep the current remote copy of `rel` in
+/// `<remote_root>/.aeroversions/` before sync replaces or removes it.
+/// `keep_original` asks for a server-side copy (overwrite) instead of a move.
+/// Archive `rel` before sync overwrites (`keep_original`) or deletes it.
+/// Returns the archive path when the live file was moved away rather than
+/// copied, so a failed overwrite can put it back.
+async fn archive_remote_version(
+    versioning: &SyncVersioning,
+    provider: &mut Box<dyn StorageProvider>,
+    remote_root: &str,
+    rel: &str,
+    keep_original: bool,
+) -> Result<Option<String>, String> {
+    if !versioning.is_enabled() {
+        return Ok(None);
+    }
+    let moved = !(keep_original && provider.supports_server_copy());
+    versioning
+        .archive_remote(provider.as_mut(), remote_root, rel, keep_original)
+        .await
+        .map(|archive_path| moved.then_some(archive_path))
+        .map_err(|e| format!("versioning failed: {}", e))
+}
+
+/// Finish an upload whose previous version was archived by rename. A
+/// failed upload moves the archived file back so the remote keeps its
+/// content; a successful one notes that delta had no remote basis.
+async fn settle_moved_archive(
+    provider: &mut Box<dyn StorageProvider>,
+    remote_root: &str,
+    rel: &str,
+    archive_path: &str,
+    requested_policy: DeltaPolicy,
+    outcome: FileOutcome,
+) -> FileOutcome {
+    match outcome {
+        FileOutcome::Failed { error } => {
+            let remote_path = join_clean_remote(remote_root, rel);
+            let _ = provider.delete(&remote_path).await;
+            match provider.rename(archive_path, &remote_path).await {
+                Ok(()) => FileOutcome::Failed { error },
+                Err(e) => FileOutcome::Failed {
+                    error: format!(
+                        "{} (previous version left at {}: {})",
+                        error, archive_path, e
+                    ),
+                },
+            }
+        }
+        FileOutcome::Uploaded {
+            bytes,
+            delta_stats: None,
+            fallback_reason: None,
+        } if matches!(requested_policy, DeltaPolicy::Delta) => FileOutcome::Uploaded {
+            bytes,
+            delta_stats: None,
+            fallback_reason: Some(
+                "no delta basis: versioning moved the remote file (no server-side copy)"
+                    .to_string(),
+            ),
+        },
+        other => other,
+    }
+}
+
 async fn perform_remote_delete(
     provider: &mut Box<dyn StorageProvider>,
     remote_root: &str,
     rel: &str,
     decision_policy: DeltaPolicy,
     dry_run: bool,
+    versioning: &SyncVersioning,
     sink: &mut dyn SyncProgressSink,
 ) -> FileOutcome {
     sink.on_file_start(rel, 0, "delete_remote", decision_policy);
@@ -1793,6 +2055,13 @@ async fn perform_remote_delete(
             reason: "dry-run".to_string(),
         };
     }
+    if versioning.is_enabled() {
+        // Moving the file into the archive is the delete.
+        return match archive_remote_version(versioning, provider, remote_root, rel, false).await {
+            Ok(_) => FileOutcome::Deleted,
+            Err(error) => FileOutcome::Failed { error },
+        };
+    }
     let remote_path = join_clean_remote(remote_root, rel);
     match provider.delete(&remote_path).await {
         Ok(()) => FileOutcome::Deleted,
@@ -2030,6 +2299,7 @@ pub fn build_comparison_results_with_index(
     options: &CompareOptions,
     index: Option<&SyncIndex>,
 ) -> Vec<FileComparison> {
+    let remote_files = align_remote_names(&local_files, remote_files);
     let mut results = Vec::new();
     let mut all_paths: std::collections::HashSet<String> = local_files.keys().cloned().collect();
     all_paths.extend(remote_files.keys().cloned());
@@ -2095,8 +2365,15 @@ pub fn build_comparison_results_with_index(
             .map(|idx| idx.files.contains_key(&path))
             .unwrap_or(false);
 
-        if status != SyncStatus::Identical || is_dir {
-            let sync_reason = generate_sync_reason(&status, local, remote, is_dir);
+        let meta_diff = if status == SyncStatus::Identical {
+            metadata_diff(local, remote, is_dir, options)
+        } else {
+            None
+        };
+
+        if status != SyncStatus::Identical || is_dir || meta_diff.is_some() {
+            let sync_reason = metadata_sync_reason(meta_diff.as_ref())
+                .unwrap_or_else(|| generate_sync_reason(&status, local, remote, is_dir));
             results.push(FileComparison {
                 relative_path: path,
                 status,
@@ -2105,6 +2382,7 @@ pub fn build_comparison_results_with_index(
                 is_dir,
                 sync_reason,
                 previously_synced,
+                meta_diff,
             });
         }
     }
@@ -3131,7 +3409,7 @@ fn direction_cli_value(direction: CompareDirection) -> &'static str {
 }
 
 /// Format the retry policy as `Some(("3", "1s"))` if it differs from the CLI defaults.
-fn retry_overrides(policy: &RetryPolicy) -> (Option<u32>, Option<String>) {
+pub(crate) fn retry_overrides(policy: &RetryPolicy) -> (Option<u32>, Option<String>) {
     let max_retries = if policy.max_retries == 3 {
         None
     } else {
@@ -3810,6 +4088,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn s3_delta_with_every_block_copied_is_the_best_speedup() {
+        let upload = |bytes_uploaded| crate::delta_sync_s3::S3DeltaUpload {
+            total_size: 10_000,
+            bytes_uploaded,
+            bytes_copied: 10_000 - bytes_uploaded,
+            parts_uploaded: u32::from(bytes_uploaded > 0),
+            parts_copied: 1,
+        };
+        let all_copied = DeltaTransferStats::from_s3_delta(&upload(0));
+        let half_copied = DeltaTransferStats::from_s3_delta(&upload(5_000));
+        assert_eq!(half_copied.speedup, 2.0);
+        assert!(all_copied.speedup > half_copied.speedup);
+    }
+
     #[test]
     fn delta_savings_average_speedup_recovers_after_zero_to_nonzero_transition() {
         // Pin the "transition from None to Some" path: first delta hit has
@@ -4015,6 +4308,7 @@ mod tests {
             modified: Some(Utc::now()),
             is_dir: false,
             checksum: None,
+            meta: None,
         };
 
         let options = CompareOptions::default();
@@ -4521,4 +4815,271 @@ mod tests {
         assert_eq!(spec.decision_policy, DeltaPolicy::Mtime);
         assert_ne!(spec.requested_policy, spec.decision_policy);
     }
+
+    #[test]
+    fn build_comparison_results_pairs_nfd_remote_with_nfc_local() {
+        let modified = Some(Utc::now());
+        let info = |path: &str| FileInfo {
+            name: path.to_string(),
+            path: format!("/remote/{}", path),
+            size: 5,
+            modified,
+            is_dir: false,
+            checksum: None,
+            meta: None,
+        };
+        let local = HashMap::from([("caf\u{e9}.txt".to_string(), info("caf\u{e9}.txt"))]);
+        let remote = HashMap::from([("cafe\u{301}.txt".to_string(), info("cafe\u{301}.txt"))]);
+
+        let results = build_comparison_results(local, remote, &CompareOptions::default());
+        assert!(
+            results.is_empty(),
+            "expected one identical pair: {:?}",
+            results
+        );
+    }
+
+    #[test]
+    fn build_comparison_results_reports_mode_only_changes() {
+        let modified = Some(Utc::now());
+        let info = |mode: &str| FileInfo {
+            name: "run.sh".to_string(),
+            path: "/x/run.sh".to_string(),
+            size: 5,
+            modified,
+            is_dir: false,
+            checksum: None,
+            meta: Some(FileMeta::from_listing(Some(mode), None, None)),
+        };
+        let local = HashMap::from([("run.sh".to_string(), info("rwxr-xr-x"))]);
+        let remote = HashMap::from([("run.sh".to_string(), info("rw-r--r--"))]);
+        let mut options = CompareOptions {
+            direction: CompareDirection::LocalToRemote,
+            ..Default::default()
+        };
+
+        let results = build_comparison_results(local.clone(), remote.clone(), &options);
+        assert!(results.is_empty(), "metadata not requested: {:?}", results);
+
+        options.metadata.perms = true;
+        let results = build_comparison_results(local, remote, &options);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, SyncStatus::Identical);
+        assert_eq!(results[0].meta_diff.as_ref().unwrap().mode, Some(0o755));
+        assert_eq!(results[0].sync_reason, "Metadata differs: mode 0755");
+    }
+
+    /// Remote tree held in memory: file path -> size. Only what a sync over
+    /// an already matching tree, the version retention and archiving by
+    /// rename touch is real; uploads always fail.
+    struct MemoryProvider {
+        files: std::collections::BTreeMap<String, u64>,
+    }
+
+    #[async_trait::async_trait]
+    impl StorageProvider for MemoryProvider {
+        fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+        fn provider_type(&self) -> crate::providers::ProviderType {
+            crate::providers::ProviderType::Ftp
+        }
+        fn display_name(&self) -> String {
+            "memory".to_string()
+        }
+        async fn connect(&mut self) -> Result<(), ProviderError> {
+            Ok(())
+        }
+        async fn disconnect(&mut self) -> Result<(), ProviderError> {
+            Ok(())
+        }
+        fn is_connected(&self) -> bool {
+            true
+        }
+        async fn list(
+            &mut self,
+            path: &str,
+        ) -> Result<Vec<crate::providers::RemoteEntry>, ProviderError> {
+            use crate::providers::RemoteEntry;
+            let prefix = format!("{}/", path.trim_end_matches('/'));
+            let mut dirs = std::collections::BTreeSet::new();
+            let mut entries = Vec::new();
+            for (file, size) in &self.files {
+                let Some(rest) = file.strip_prefix(&prefix) else {
+                    continue;
+                };
+                match rest.split_once('/') {
+                    Some((dir, _)) => {
+                        dirs.insert(dir.to_string());
+                    }
+                    None => entries.push(RemoteEntry::file(rest.into(), file.clone(), *size)),
+                }
+            }
+            if entries.is_empty() && dirs.is_empty() {
+                return Err(ProviderError::NotFound(path.to_string()));
+            }
+            entries.extend(
+                dirs.into_iter()
+                    .map(|d| RemoteEntry::directory(d.clone(), format!("{}{}", prefix, d))),
+            );
+            Ok(entries)
+        }
+        async fn pwd(&mut self) -> Result<String, ProviderError> {
+            Ok("/".to_string())
+        }
+        async fn cd(&mut self, _path: &str) -> Result<(), ProviderError> {
+            Ok(())
+        }
+        async fn cd_up(&mut self) -> Result<(), ProviderError> {
+            Ok(())
+        }
+        async fn download(
+            &mut self,
+            remote_path: &str,
+            _local_path: &str,
+            _on_progress: Option<Box<dyn Fn(u64, u64) + Send>>,
+        ) -> Result<(), ProviderError> {
+            Err(ProviderError::NotSupported(remote_path.to_string()))
+        }
+        async fn download_to_bytes(&mut self, remote_path: &str) -> Result<Vec<u8>, ProviderError> {
+            Err(ProviderError::NotSupported(remote_path.to_string()))
+        }
+        async fn upload(
+            &mut self,
+            _local_path: &str,
+            remote_path: &str,
+            _on_progress: Option<Box<dyn Fn(u64, u64) + Send>>,
+        ) -> Result<(), ProviderError> {
+            Err(ProviderError::NotSupported(remote_path.to_string()))
+        }
+        async fn mkdir(&mut self, _path: &str) -> Result<(), ProviderError> {
+            Ok(())
+        }
+        async fn delete(&mut self, path: &str) -> Result<(), ProviderError> {
+            self.files
+                .remove(path)
+                .map(|_| ())
+                .ok_or_else(|| ProviderError::NotFound(path.to_string()))
+        }
+        async fn rmdir(&mut self, _path: &str) -> Result<(), ProviderError> {
+            Ok(())
+        }
+        async fn rmdir_recursive(&mut self, _path: &str) -> Result<(), ProviderError> {
+            Ok(())
+        }
+        async fn rename(&mut self, from: &str, to: &str) -> Result<(), ProviderError> {
+            let size = self
+                .files
+                .remove(from)
+                .ok_or_else(|| ProviderError::NotFound(from.to_string()))?;
+            self.files.insert(to.to_string(), size);
+            Ok(())
+        }
+        async fn stat(
+            &mut self,
+            path: &str,
+        ) -> Result<crate::providers::RemoteEntry, ProviderError> {
+            let size = self
+                .files
+                .get(path)
+                .ok_or_else(|| ProviderError::NotFound(path.to_string()))?;
+            let name = path.rsplit('/').next().unwrap_or_default().to_string();
+            Ok(crate::providers::RemoteEntry::file(
+                name,
+                path.to_string(),
+                *size,
+            ))
+        }
+        async fn size(&mut self, path: &str) -> Result<u64, ProviderError> {
+            Ok(self.stat(path).await?.size)
+        }
+        async fn exists(&mut self, path: &str) -> Result<bool, ProviderError> {
+            Ok(self.files.contains_key(path))
+        }
+        async fn keep_alive(&mut self) -> Result<(), ProviderError> {
+            Ok(())
+        }
+        async fn server_info(&mut self) -> Result<String, ProviderError> {
+            Ok("memory".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn sync_tree_core_prunes_remote_versions_after_clean_run() {
+        let local = tempfile::tempdir().unwrap();
+        let versions = [
+            "/r/.aeroversions/a~20240101-000000.txt",
+            "/r/.aeroversions/a~20240102-000000.txt",
+            "/r/.aeroversions/a~20240103-000000.txt",
+        ];
+        let mut provider: Box<dyn StorageProvider> = Box::new(MemoryProvider {
+            files: versions.iter().map(|v| (v.to_string(), 10)).collect(),
+        });
+        let mut opts = SyncOptions {
+            dry_run: true,
+            remote_versioning: VersioningStrategy::Simple { max_copies: 1 },
+            ..SyncOptions::default()
+        };
+        let local_root = local.path().to_string_lossy().to_string();
+
+        // A dry run never prunes.
+        let report = sync_tree_core(
+            &mut provider,
+            &local_root,
+            "/r",
+            &opts,
+            &mut NoopProgressSink,
+        )
+        .await;
+        assert_eq!(report.versions_pruned, 0);
+        assert!(provider.exists(versions[0]).await.unwrap());
+
+        opts.dry_run = false;
+        let report = sync_tree_core(
+            &mut provider,
+            &local_root,
+            "/r",
+            &opts,
+            &mut NoopProgressSink,
+        )
+        .await;
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.versions_pruned, 2);
+        assert!(!provider.exists(versions[0]).await.unwrap());
+        assert!(!provider.exists(versions[1]).await.unwrap());
+        assert!(provider.exists(versions[2]).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn failed_upload_restores_the_version_archived_by_rename() {
+        let local = tempfile::tempdir().unwrap();
+        std::fs::write(local.path().join("a.txt"), [7u8; 20]).unwrap();
+        let mut provider: Box<dyn StorageProvider> = Box::new(MemoryProvider {
+            files: [("/r/a.txt".to_string(), 10)].into_iter().collect(),
+        });
+        let opts = SyncOptions {
+            delta_policy: DeltaPolicy::SizeOnly,
+            remote_versioning: VersioningStrategy::Simple { max_copies: 3 },
+            ..SyncOptions::default()
+        };
+        let local_root = local.path().to_string_lossy().to_string();
+
+        let report = sync_tree_core(
+            &mut provider,
+            &local_root,
+            "/r",
+            &opts,
+            &mut NoopProgressSink,
+        )
+        .await;
+
+        assert_eq!(report.errors.len(), 1, "{:?}", report.errors);
+        assert_eq!(provider.size("/r/a.txt").await.unwrap(), 10);
+        let archived = provider
+            .list("/r/.aeroversions")
+            .await
+            .map(|entries| entries.len())
+            .unwrap_or(0);
+        assert_eq!(archived, 0, "the archived copy must be moved back");
+    }
 }
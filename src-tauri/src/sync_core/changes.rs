@@ -0,0 +1,516 @@
+//! Incremental remote refresh driven by provider change feeds.
+//!
+//! Providers that implement `list_changes` (Google Drive today) can report
+//! what happened since an opaque page token. Instead of re-listing a
+//! 500 000-entry remote tree on every run, the sync front-ends keep a
+//! [`ChangeFeedState`] next to their bisync snapshot: the remote listing as
+//! of the last run, the provider token captured *before* that listing, and
+//! an id index so permanent removals and folder renames can be resolved.
+//! The next run replays the feed onto the stored listing and only falls
+//! back to a full scan when the token is rejected or a change cannot be
+//! placed in the tree.
+//!
+//! Replaying is idempotent: the token is captured before the full scan, so
+//! any change that races the scan is applied again on the next run with the
+//! same end state.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use crate::providers::{ChangeEntry, StorageProvider};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Last known size/mtime of a remote file tracked by the change feed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeFeedFile {
+    pub size: u64,
+    pub mtime: Option<String>,
+}
+
+/// Provider id -> relative path entry. Folders are tracked too so that a
+/// renamed or trashed folder can be applied to every file below it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeFeedNode {
+    pub rel_path: String,
+    pub is_dir: bool,
+}
+
+/// Persisted cursor plus the remote listing it applies to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChangeFeedState {
+    /// Opaque provider token (Drive `startPageToken`).
+    pub token: String,
+    /// Remote root the listing is relative to. A state recorded for another
+    /// root is never reused.
+    pub remote_root: String,
+    /// Remote files relative to `remote_root`.
+    pub files: BTreeMap<String, ChangeFeedFile>,
+    /// Provider id -> node, for removals that carry no path.
+    #[serde(default)]
+    pub ids: HashMap<String, ChangeFeedNode>,
+    /// ISO timestamp of the last successful refresh.
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+/// Counters returned by [`ChangeFeedState::apply`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeFeedApply {
+    /// Changes read from the feed (including ones outside the root).
+    pub changes_seen: usize,
+    /// Files added or updated in the listing.
+    pub upserted: usize,
+    /// Files dropped from the listing.
+    pub removed: usize,
+    /// Folders whose subtree was moved to a new prefix.
+    pub moved_dirs: usize,
+}
+
+impl ChangeFeedState {
+    pub fn new(token: String, remote_root: &str) -> Self {
+        Self {
+            token,
+            remote_root: normalize_root(remote_root),
+            ..Self::default()
+        }
+    }
+
+    /// True when this state was recorded for `remote_root`.
+    pub fn matches_root(&self, remote_root: &str) -> bool {
+        self.remote_root == normalize_root(remote_root)
+    }
+
+    /// Record a file seen by a full scan.
+    pub fn record_file(&mut self, rel: &str, size: u64, mtime: Option<String>, id: Option<&str>) {
+        self.files
+            .insert(rel.to_string(), ChangeFeedFile { size, mtime });
+        if let Some(id) = id.filter(|id| !id.is_empty()) {
+            self.ids.insert(
+                id.to_string(),
+                ChangeFeedNode {
+                    rel_path: rel.to_string(),
+                    is_dir: false,
+                },
+            );
+        }
+    }
+
+    /// Record a folder seen by a full scan. Only the id is kept: folders
+    /// carry no transferable content.
+    pub fn record_dir(&mut self, rel: &str, id: Option<&str>) {
+        if let Some(id) = id.filter(|id| !id.is_empty()) {
+            self.ids.insert(
+                id.to_string(),
+                ChangeFeedNode {
+                    rel_path: rel.to_string(),
+                    is_dir: true,
+                },
+            );
+        }
+    }
+
+    /// Replay `changes` onto the stored listing.
+    ///
+    /// Returns `Err` with a human-readable reason when a change cannot be
+    /// placed safely (non-removal without a resolvable path). The caller
+    /// must then discard the state and run a full scan.
+    pub fn apply(&mut self, changes: &[ChangeEntry]) -> Result<ChangeFeedApply, String> {
+        let mut stats = ChangeFeedApply {
+            changes_seen: changes.len(),
+            ..ChangeFeedApply::default()
+        };
+
+        for change in changes {
+            let known = self.ids.get(&change.file_id).cloned();
+            let new_rel = change
+                .path
+                .as_deref()
+                .and_then(|p| relative_to_root(&self.remote_root, p));
+
+            if change.removed {
+                let target = known.or_else(|| {
+                    new_rel.map(|rel_path| ChangeFeedNode {
+                        rel_path,
+                        is_dir: change.is_dir,
+                    })
+                });
+                if let Some(node) = target {
+                    stats.removed += self.remove_node(&node);
+                }
+                self.ids.remove(&change.file_id);
+                continue;
+            }
+
+            let Some(rel) = new_rel else {
+                if change.path.is_none() {
+                    return Err(format!(
+                        "change for '{}' ({}) has no resolvable path",
+                        change.name, change.file_id
+                    ));
+                }
+                // Moved outside the synced root: behaves like a removal.
+                if let Some(node) = known {
+                    stats.removed += self.remove_node(&node);
+                    self.ids.remove(&change.file_id);
+                }
+                continue;
+            };
+            if rel.is_empty() {
+                // The root folder itself changed (e.g. its mtime): nothing to sync.
+                continue;
+            }
+
+            if change.is_dir {
+                if let Some(old) = known.filter(|n| n.is_dir && n.rel_path != rel) {
+                    self.move_prefix(&old.rel_path, &rel);
+                    stats.moved_dirs += 1;
+                }
+                self.record_dir(&rel, Some(&change.file_id));
+            } else {
+                if let Some(old) = known.filter(|n| !n.is_dir && n.rel_path != rel) {
+                    if self.files.remove(&old.rel_path).is_some() {
+                        stats.removed += 1;
+                    }
+                }
+                self.record_file(
+                    &rel,
+                    change.size.unwrap_or(0),
+                    change.modified.clone(),
+                    Some(&change.file_id),
+                );
+                stats.upserted += 1;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    fn remove_node(&mut self, node: &ChangeFeedNode) -> usize {
+        if !node.is_dir {
+            return usize::from(self.files.remove(&node.rel_path).is_some());
+        }
+        let prefix = format!("{}/", node.rel_path);
+        let before = self.files.len();
+        self.files.retain(|rel, _| !rel.starts_with(&prefix));
+        self.ids
+            .retain(|_, n| n.rel_path != node.rel_path && !n.rel_path.starts_with(&prefix));
+        before - self.files.len()
+    }
+
+    fn move_prefix(&mut self, old: &str, new: &str) {
+        let old_prefix = format!("{}/", old);
+        let moved: Vec<String> = self
+            .files
+            .keys()
+            .filter(|rel| rel.starts_with(&old_prefix))
+            .cloned()
+            .collect();
+        for rel in moved {
+            if let Some(file) = self.files.remove(&rel) {
+                self.files
+                    .insert(format!("{}/{}", new, &rel[old_prefix.len()..]), file);
+            }
+        }
+        for node in self.ids.values_mut() {
+            if node.rel_path.starts_with(&old_prefix) {
+                node.rel_path = format!("{}/{}", new, &node.rel_path[old_prefix.len()..]);
+            }
+        }
+    }
+}
+
+/// Capture the provider's current change token, or `None` when the
+/// provider has no change feed (or the call fails: the caller simply keeps
+/// doing full scans).
+pub async fn capture_change_token<P: StorageProvider + ?Sized>(provider: &mut P) -> Option<String> {
+    if !provider.supports_change_tracking() {
+        return None;
+    }
+    match provider.get_change_token().await {
+        Ok(token) if !token.is_empty() => Some(token),
+        Ok(_) => None,
+        Err(e) => {
+            tracing::debug!("change feed: cannot capture token: {}", e);
+            None
+        }
+    }
+}
+
+/// Pull the feed since `state.token`, replay it, and advance the token.
+///
+/// `Err` means the state is unusable (expired or rejected token, unplaced
+/// change) and the caller must fall back to a full scan. The state is left
+/// untouched in that case.
+pub async fn refresh_from_change_feed<P: StorageProvider + ?Sized>(
+    provider: &mut P,
+    state: &mut ChangeFeedState,
+) -> Result<ChangeFeedApply, String> {
+    if !provider.supports_change_tracking() {
+        return Err("provider has no change feed".to_string());
+    }
+    let (changes, next_token) = provider
+        .list_changes(&state.token)
+        .await
+        .map_err(|e| format!("change token rejected: {}", e))?;
+
+    let mut next = state.clone();
+    let stats = next.apply(&changes)?;
+    next.token = next_token;
+    next.updated_at = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    *state = next;
+    Ok(stats)
+}
+
+/// Absolute paths made stale by a batch of changes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeTargets {
+    /// Entries whose metadata, and whose parent's listing, changed.
+    pub paths: BTreeSet<String>,
+    /// Entries that were moved or removed: if they were folders, everything
+    /// below them is stale too. Removal reports rarely say which it was.
+    pub subtrees: BTreeSet<String>,
+    /// A change could not be placed, so nothing cached can be trusted.
+    pub unplaced: bool,
+}
+
+impl ChangeTargets {
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.subtrees.is_empty() && !self.unplaced
+    }
+}
+
+/// Provider id -> absolute path index for consumers that only cache what
+/// they have seen (FUSE mounts) instead of holding a full listing. Ids are
+/// learned from listings and from the feed itself, so removals and renames
+/// reported without the old path still reach the right entry.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeIndex {
+    root: String,
+    ids: HashMap<String, String>,
+}
+
+impl ChangeIndex {
+    pub fn new(root: &str) -> Self {
+        Self {
+            root: normalize_root(root),
+            ids: HashMap::new(),
+        }
+    }
+
+    /// Remember where the entry with provider id `id` lives.
+    pub fn record(&mut self, id: &str, abs_path: &str) {
+        if !id.is_empty() {
+            self.ids.insert(id.to_string(), normalize_root(abs_path));
+        }
+    }
+
+    /// Resolve `changes` to the paths they touched below the root.
+    pub fn resolve(&mut self, changes: &[ChangeEntry]) -> ChangeTargets {
+        let mut targets = ChangeTargets::default();
+        for change in changes {
+            let old = self.ids.get(&change.file_id).cloned();
+            let new = change
+                .path
+                .as_deref()
+                .map(normalize_root)
+                .filter(|p| relative_to_root(&self.root, p).is_some());
+
+            if change.removed || (new.is_none() && change.path.is_some()) {
+                // Deleted, trashed or moved outside the root
+                match old.or(new) {
+                    Some(path) => {
+                        targets.subtrees.insert(path.clone());
+                        targets.paths.insert(path);
+                    }
+                    None if change.path.is_none() => targets.unplaced = true,
+                    None => {}
+                }
+                self.ids.remove(&change.file_id);
+                continue;
+            }
+
+            let Some(new) = new else {
+                targets.unplaced = true;
+                continue;
+            };
+            if let Some(old) = old.filter(|old| *old != new) {
+                targets.subtrees.insert(old.clone());
+                targets.paths.insert(old);
+            }
+            self.record(&change.file_id, &new);
+            targets.paths.insert(new);
+        }
+        targets
+    }
+}
+
+fn normalize_root(root: &str) -> String {
+    let trimmed = root.trim_end_matches('/');
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else if trimmed.starts_with('/') {
+        trimmed.to_string()
+    } else {
+        format!("/{}", trimmed)
+    }
+}
+
+/// Map an absolute provider path onto `root`. `Some("")` is the root itself,
+/// `None` means the path lives outside the root.
+fn relative_to_root(root: &str, abs: &str) -> Option<String> {
+    let abs = normalize_root(abs);
+    if root == "/" {
+        return Some(abs.trim_start_matches('/').to_string());
+    }
+    if abs == root {
+        return Some(String::new());
+    }
+    abs.strip_prefix(root)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(id: &str, path: Option<&str>, removed: bool, is_dir: bool) -> ChangeEntry {
+        ChangeEntry {
+            file_id: id.to_string(),
+            name: path
+                .and_then(|p| p.rsplit('/').next())
+                .unwrap_or_default()
+                .to_string(),
+            change_type: if removed { "deleted" } else { "modified" }.to_string(),
+            mime_type: None,
+            timestamp: None,
+            removed,
+            path: path.map(str::to_string),
+            size: Some(42),
+            modified: Some("2026-01-02T03:04:05Z".to_string()),
+            is_dir,
+        }
+    }
+
+    fn seeded() -> ChangeFeedState {
+        let mut state = ChangeFeedState::new("t1".to_string(), "/Sync/");
+        state.record_file("a.txt", 1, None, Some("id-a"));
+        state.record_dir("docs", Some("id-docs"));
+        state.record_file("docs/b.txt", 2, None, Some("id-b"));
+        state.record_file("docs/c.txt", 3, None, Some("id-c"));
+        state
+    }
+
+    #[test]
+    fn upserts_files_inside_root_and_ignores_outside() {
+        let mut state = seeded();
+        let stats = state
+            .apply(&[
+                change("id-new", Some("/Sync/new.txt"), false, false),
+                change("id-out", Some("/Elsewhere/x.txt"), false, false),
+            ])
+            .unwrap();
+        assert_eq!(stats.upserted, 1);
+        assert_eq!(state.files["new.txt"].size, 42);
+        assert!(!state.files.keys().any(|k| k.contains("x.txt")));
+    }
+
+    #[test]
+    fn removal_without_path_resolves_by_id() {
+        let mut state = seeded();
+        let stats = state.apply(&[change("id-a", None, true, false)]).unwrap();
+        assert_eq!(stats.removed, 1);
+        assert!(!state.files.contains_key("a.txt"));
+        assert!(!state.ids.contains_key("id-a"));
+    }
+
+    #[test]
+    fn folder_rename_moves_every_child() {
+        let mut state = seeded();
+        let stats = state
+            .apply(&[change("id-docs", Some("/Sync/papers"), false, true)])
+            .unwrap();
+        assert_eq!(stats.moved_dirs, 1);
+        assert!(state.files.contains_key("papers/b.txt"));
+        assert!(state.files.contains_key("papers/c.txt"));
+        assert!(!state.files.contains_key("docs/b.txt"));
+        assert_eq!(state.ids["id-b"].rel_path, "papers/b.txt");
+    }
+
+    #[test]
+    fn trashed_folder_drops_subtree() {
+        let mut state = seeded();
+        let stats = state.apply(&[change("id-docs", None, true, true)]).unwrap();
+        assert_eq!(stats.removed, 2);
+        assert_eq!(state.files.len(), 1);
+        assert!(!state.ids.contains_key("id-b"));
+    }
+
+    #[test]
+    fn file_moved_out_of_root_is_removed() {
+        let mut state = seeded();
+        state
+            .apply(&[change("id-b", Some("/Archive/b.txt"), false, false)])
+            .unwrap();
+        assert!(!state.files.contains_key("docs/b.txt"));
+    }
+
+    #[test]
+    fn file_rename_drops_old_path() {
+        let mut state = seeded();
+        state
+            .apply(&[change("id-a", Some("/Sync/renamed.txt"), false, false)])
+            .unwrap();
+        assert!(!state.files.contains_key("a.txt"));
+        assert!(state.files.contains_key("renamed.txt"));
+    }
+
+    #[test]
+    fn unresolvable_change_requests_full_scan() {
+        let mut state = seeded();
+        assert!(state.apply(&[change("id-x", None, false, false)]).is_err());
+    }
+
+    #[test]
+    fn root_matching_is_slash_insensitive() {
+        let state = ChangeFeedState::new("t".to_string(), "Sync/");
+        assert!(state.matches_root("/Sync"));
+        assert!(!state.matches_root("/Other"));
+        assert_eq!(relative_to_root("/", "/a/b"), Some("a/b".to_string()));
+        assert_eq!(relative_to_root("/Sync", "/Syncer/a"), None);
+    }
+
+    #[test]
+    fn index_resolves_renames_and_removals_by_id() {
+        let mut index = ChangeIndex::new("/Sync");
+        index.record("id-docs", "/Sync/docs");
+        index.record("id-a", "/Sync/a.txt");
+
+        let targets = index.resolve(&[
+            change("id-docs", Some("/Sync/papers"), false, true),
+            change("id-a", None, true, false),
+            change("id-out", Some("/Elsewhere/x.txt"), false, false),
+        ]);
+        let paths: Vec<&str> = targets.paths.iter().map(String::as_str).collect();
+        assert_eq!(paths, ["/Sync/a.txt", "/Sync/docs", "/Sync/papers"]);
+        assert!(targets.subtrees.contains("/Sync/docs"));
+        assert!(!targets.unplaced);
+
+        // The rename was learned: a later edit below it resolves too
+        let targets = index.resolve(&[change("id-docs", Some("/Sync/papers"), false, true)]);
+        assert!(targets.subtrees.is_empty());
+    }
+
+    #[test]
+    fn index_flags_changes_it_cannot_place() {
+        let mut index = ChangeIndex::new("/");
+        assert!(
+            index
+                .resolve(&[change("id-x", None, false, false)])
+                .unplaced
+        );
+        assert!(index.resolve(&[change("id-y", None, true, false)]).unplaced);
+        assert!(index.resolve(&[]).is_empty());
+    }
+}
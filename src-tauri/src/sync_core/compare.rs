@@ -9,12 +9,16 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 // Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
 
+use crate::sync_core::names::match_remote_names;
 use crate::sync_core::scan::{LocalEntry, RemoteEntry};
 
 /// A single compared entry.
 #[derive(Debug, Clone)]
 pub struct DiffEntry {
     pub rel_path: String,
+    /// Remote spelling of the path when it differs from `rel_path` only by
+    /// Unicode normalization (or case, on case-insensitive backends).
+    pub remote_rel_path: Option<String>,
     pub local_size: Option<u64>,
     pub remote_size: Option<u64>,
     pub local_sha256: Option<String>,
@@ -62,13 +66,40 @@ impl DiffReport {
 /// `missing_local` detection), matching the semantics of the CLI
 /// `check --one-way` flag.
 pub fn compare_trees(local: &[LocalEntry], remote: &[RemoteEntry], one_way: bool) -> DiffReport {
+    compare_trees_with(local, remote, one_way, false)
+}
+
+/// [`compare_trees`] for a remote that may fold case.
+///
+/// Paths are always paired across Unicode normalization forms (NFC vs NFD);
+/// with `case_insensitive` they are also paired across case variants, as
+/// long as the pairing is unambiguous.
+pub fn compare_trees_with(
+    local: &[LocalEntry],
+    remote: &[RemoteEntry],
+    one_way: bool,
+    case_insensitive: bool,
+) -> DiffReport {
     use std::collections::HashMap;
 
     let mut report = DiffReport::default();
+    let renamed = match_remote_names(
+        local.iter().map(|e| e.rel_path.as_str()),
+        remote.iter().map(|e| e.rel_path.as_str()),
+        case_insensitive,
+    );
     let local_map: HashMap<&str, &LocalEntry> =
         local.iter().map(|e| (e.rel_path.as_str(), e)).collect();
-    let remote_map: HashMap<&str, &RemoteEntry> =
-        remote.iter().map(|e| (e.rel_path.as_str(), e)).collect();
+    let remote_map: HashMap<&str, &RemoteEntry> = remote
+        .iter()
+        .map(|e| {
+            let key = renamed
+                .get(&e.rel_path)
+                .map(String::as_str)
+                .unwrap_or(e.rel_path.as_str());
+            (key, e)
+        })
+        .collect();
 
     for (rel, local_entry) in &local_map {
         match remote_map.get(rel) {
@@ -93,6 +124,8 @@ pub fn compare_trees(local: &[LocalEntry], remote: &[RemoteEntry], one_way: bool
                 };
                 let entry = DiffEntry {
                     rel_path: (*rel).to_string(),
+                    remote_rel_path: (remote_entry.rel_path != *rel)
+                        .then(|| remote_entry.rel_path.clone()),
                     local_size: Some(local_entry.size),
                     remote_size: Some(remote_entry.size),
                     local_sha256: local_entry.sha256.clone(),
@@ -109,6 +142,7 @@ pub fn compare_trees(local: &[LocalEntry], remote: &[RemoteEntry], one_way: bool
             None => {
                 report.missing_remote.push(DiffEntry {
                     rel_path: (*rel).to_string(),
+                    remote_rel_path: None,
                     local_size: Some(local_entry.size),
                     remote_size: None,
                     local_sha256: local_entry.sha256.clone(),
@@ -125,6 +159,7 @@ pub fn compare_trees(local: &[LocalEntry], remote: &[RemoteEntry], one_way: bool
             if !local_map.contains_key(rel) {
                 report.missing_local.push(DiffEntry {
                     rel_path: (*rel).to_string(),
+                    remote_rel_path: None,
                     local_size: None,
                     remote_size: Some(remote_entry.size),
                     local_sha256: None,
@@ -239,6 +274,25 @@ mod tests {
         assert_eq!(report.missing_local_count(), 0);
     }
 
+    #[test]
+    fn compare_trees_pairs_unicode_and_case_variants() {
+        let locals = vec![local("caf\u{e9}.txt", 4), local("Readme.md", 7)];
+        let remotes = vec![remote("cafe\u{301}.txt", 4), remote("README.md", 7)];
+
+        let report = compare_trees(&locals, &remotes, false);
+        assert_eq!(report.match_count(), 1);
+        assert_eq!(report.matches[0].rel_path, "caf\u{e9}.txt");
+        assert_eq!(
+            report.matches[0].remote_rel_path.as_deref(),
+            Some("cafe\u{301}.txt")
+        );
+        assert_eq!(report.missing_local_count(), 1);
+
+        let report = compare_trees_with(&locals, &remotes, false, true);
+        assert_eq!(report.match_count(), 2);
+        assert!(!report.has_differences());
+    }
+
     #[test]
     fn compare_trees_empty_inputs_yield_no_differences() {
         let report = compare_trees(&[], &[], false);
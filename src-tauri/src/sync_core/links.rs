@@ -0,0 +1,362 @@
+//! Symlink and hardlink handling for sync.
+//!
+//! Symlinks are skipped by default. `--copy-links` follows them and syncs
+//! what they point to; `--links` recreates them on the other side: natively
+//! where the backend has symlinks (SFTP, the local filesystem) and as a small
+//! `<name>.rclonelink` file holding the target elsewhere, the same layout
+//! rclone uses on object stores. `--safe-links` ignores links whose target
+//! is absolute or climbs out of the synced tree.
+//!
+//! Hardlinked local files are grouped by device and inode so only one copy
+//! of each group is uploaded; the rest are linked (`hardlink@openssh.com`)
+//! or server-side copied on the remote.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Suffix of the files that stand in for symlinks on backends without them.
+pub const LINK_SUFFIX: &str = ".rclonelink";
+
+/// How sync treats symbolic links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkMode {
+    /// Leave links out of the sync (default).
+    #[default]
+    Skip,
+    /// Recreate links on the destination (`--links`).
+    Preserve,
+    /// Sync the files and directories links point to (`--copy-links`).
+    Follow,
+}
+
+impl LinkMode {
+    pub fn from_flags(links: bool, copy_links: bool) -> Result<Self, String> {
+        match (links, copy_links) {
+            (true, true) => Err("--links and --copy-links cannot be combined".to_string()),
+            (true, false) => Ok(Self::Preserve),
+            (false, true) => Ok(Self::Follow),
+            (false, false) => Ok(Self::Skip),
+        }
+    }
+}
+
+/// A symbolic link found in a local or remote tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkEntry {
+    pub rel_path: String,
+    pub target: String,
+}
+
+/// `true` when a link at `rel_path` pointing to `target` stays inside the
+/// synced tree, following rsync's `--safe-links` rule.
+pub fn is_safe_link(rel_path: &str, target: &str) -> bool {
+    if target.is_empty()
+        || target.starts_with('/')
+        || target.starts_with('\\')
+        || target.as_bytes().get(1) == Some(&b':')
+    {
+        return false;
+    }
+    let mut depth = rel_path.matches('/').count() as isize;
+    for part in target.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                depth -= 1;
+                if depth < 0 {
+                    return false;
+                }
+            }
+            _ => depth += 1,
+        }
+    }
+    true
+}
+
+/// Object-store path of the file standing in for the link at `rel_path`.
+pub fn link_file_path(rel_path: &str) -> String {
+    format!("{}{}", rel_path, LINK_SUFFIX)
+}
+
+/// Link path for a `.rclonelink` stand-in file, if `rel_path` is one.
+pub fn strip_link_suffix(rel_path: &str) -> Option<&str> {
+    rel_path
+        .strip_suffix(LINK_SUFFIX)
+        .filter(|stem| !stem.is_empty() && !stem.ends_with('/'))
+}
+
+/// Link changes needed to bring the destination in line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkPlan {
+    pub create_remote: Vec<LinkEntry>,
+    pub create_local: Vec<LinkEntry>,
+    pub delete_remote: Vec<String>,
+    pub delete_local: Vec<String>,
+    /// Links present on both sides with different targets in a
+    /// bidirectional sync; left untouched.
+    pub conflicts: Vec<String>,
+}
+
+impl LinkPlan {
+    pub fn is_empty(&self) -> bool {
+        self.create_remote.is_empty()
+            && self.create_local.is_empty()
+            && self.delete_remote.is_empty()
+            && self.delete_local.is_empty()
+    }
+}
+
+/// Compare the links on both sides. `delete` removes destination links that
+/// have no source in one-way syncs; bidirectional syncs never delete links
+/// because there is no previous state to tell a deletion from a creation.
+pub fn plan_links(
+    local: &[LinkEntry],
+    remote: &[LinkEntry],
+    uploads: bool,
+    downloads: bool,
+    delete: bool,
+) -> LinkPlan {
+    let local: BTreeMap<&str, &LinkEntry> =
+        local.iter().map(|l| (l.rel_path.as_str(), l)).collect();
+    let remote: BTreeMap<&str, &LinkEntry> =
+        remote.iter().map(|l| (l.rel_path.as_str(), l)).collect();
+    let both = uploads && downloads;
+    let mut plan = LinkPlan::default();
+
+    for (path, link) in &local {
+        match remote.get(path) {
+            Some(other) if other.target == link.target => {}
+            Some(_) if both => plan.conflicts.push(path.to_string()),
+            Some(_) if uploads => plan.create_remote.push((*link).clone()),
+            None if uploads => plan.create_remote.push((*link).clone()),
+            None if delete && !both => plan.delete_local.push(path.to_string()),
+            _ => {}
+        }
+    }
+    for (path, link) in &remote {
+        match local.get(path) {
+            Some(other) if other.target == link.target => {}
+            Some(_) if both => {}
+            Some(_) if downloads => plan.create_local.push((*link).clone()),
+            None if downloads => plan.create_local.push((*link).clone()),
+            None if delete && !both => plan.delete_remote.push(path.to_string()),
+            _ => {}
+        }
+    }
+    plan
+}
+
+/// Create (or replace) a local symlink at `path` pointing to `target`.
+pub fn create_local_symlink(target: &str, path: &Path) -> std::io::Result<()> {
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if meta.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                "a directory exists at the link path",
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, path)
+    }
+    #[cfg(windows)]
+    {
+        let resolved = path.parent().unwrap_or(Path::new(".")).join(target);
+        if resolved.is_dir() {
+            std::os::windows::fs::symlink_dir(target, path)
+        } else {
+            std::os::windows::fs::symlink_file(target, path)
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = target;
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "symlinks are not supported on this platform",
+        ))
+    }
+}
+
+/// Local files that share an inode. Each group is led by its smallest path,
+/// which is transferred normally; the other members can be linked to it.
+#[derive(Debug, Clone, Default)]
+pub struct HardlinkGroups {
+    leader_of: HashMap<String, String>,
+}
+
+impl HardlinkGroups {
+    /// Group `paths` (relative to `root`) by device and inode.
+    pub fn detect<'a>(root: &str, paths: impl IntoIterator<Item = &'a str>) -> Self {
+        let ids = paths.into_iter().filter_map(|rel| {
+            let meta = std::fs::metadata(Path::new(root).join(rel)).ok()?;
+            file_id(&meta).map(|id| (rel.to_string(), id))
+        });
+        Self::from_ids(ids)
+    }
+
+    /// Group paths that carry the same `(device, inode)` id.
+    pub fn from_ids(ids: impl IntoIterator<Item = (String, (u64, u64))>) -> Self {
+        let mut groups: HashMap<(u64, u64), Vec<String>> = HashMap::new();
+        for (path, id) in ids {
+            groups.entry(id).or_default().push(path);
+        }
+        let mut leader_of = HashMap::new();
+        for mut members in groups.into_values().filter(|m| m.len() > 1) {
+            members.sort();
+            let leader = members[0].clone();
+            for member in members.into_iter().skip(1) {
+                leader_of.insert(member, leader.clone());
+            }
+        }
+        Self { leader_of }
+    }
+
+    /// The path `rel_path` is a hardlink of, unless it leads its own group.
+    pub fn leader(&self, rel_path: &str) -> Option<&str> {
+        self.leader_of.get(rel_path).map(String::as_str)
+    }
+
+    /// Number of files that can be linked instead of transferred.
+    pub fn len(&self) -> usize {
+        self.leader_of.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leader_of.is_empty()
+    }
+}
+
+#[cfg(unix)]
+fn file_id(meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (meta.is_file() && meta.nlink() > 1).then(|| (meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(path: &str, target: &str) -> LinkEntry {
+        LinkEntry {
+            rel_path: path.to_string(),
+            target: target.to_string(),
+        }
+    }
+
+    #[test]
+    fn link_mode_rejects_conflicting_flags() {
+        assert_eq!(LinkMode::from_flags(false, false), Ok(LinkMode::Skip));
+        assert_eq!(LinkMode::from_flags(true, false), Ok(LinkMode::Preserve));
+        assert_eq!(LinkMode::from_flags(false, true), Ok(LinkMode::Follow));
+        assert!(LinkMode::from_flags(true, true).is_err());
+    }
+
+    #[test]
+    fn safe_links_stay_inside_the_tree() {
+        assert!(is_safe_link("a.txt", "b.txt"));
+        assert!(is_safe_link("dir/a", "../b"));
+        assert!(is_safe_link("dir/sub/a", "../../b/./c"));
+        assert!(!is_safe_link("a", "../outside"));
+        assert!(!is_safe_link("dir/a", "../../outside"));
+        assert!(!is_safe_link("dir/a", "x/../../../y"));
+        assert!(!is_safe_link("a", "/etc/passwd"));
+        assert!(!is_safe_link("a", "C:\\Windows"));
+        assert!(!is_safe_link("a", ""));
+    }
+
+    #[test]
+    fn link_files_use_rclone_suffix() {
+        assert_eq!(link_file_path("dir/current"), "dir/current.rclonelink");
+        assert_eq!(
+            strip_link_suffix("dir/current.rclonelink"),
+            Some("dir/current")
+        );
+        assert_eq!(strip_link_suffix(".rclonelink"), None);
+        assert_eq!(strip_link_suffix("dir/.rclonelink"), None);
+        assert_eq!(strip_link_suffix("plain.txt"), None);
+    }
+
+    #[test]
+    fn plan_links_one_way_upload() {
+        let local = vec![
+            link("same", "t"),
+            link("changed", "new"),
+            link("added", "x"),
+        ];
+        let remote = vec![link("same", "t"), link("changed", "old"), link("gone", "y")];
+
+        let plan = plan_links(&local, &remote, true, false, false);
+        assert_eq!(
+            plan.create_remote,
+            vec![link("added", "x"), link("changed", "new")]
+        );
+        assert!(plan.delete_remote.is_empty());
+        assert!(plan.create_local.is_empty());
+
+        let plan = plan_links(&local, &remote, true, false, true);
+        assert_eq!(plan.delete_remote, vec!["gone".to_string()]);
+        assert!(plan.delete_local.is_empty());
+    }
+
+    #[test]
+    fn plan_links_bidirectional_never_deletes() {
+        let local = vec![link("local-only", "a"), link("both", "l")];
+        let remote = vec![link("remote-only", "b"), link("both", "r")];
+
+        let plan = plan_links(&local, &remote, true, true, true);
+        assert_eq!(plan.create_remote, vec![link("local-only", "a")]);
+        assert_eq!(plan.create_local, vec![link("remote-only", "b")]);
+        assert_eq!(plan.conflicts, vec!["both".to_string()]);
+        assert!(plan.delete_remote.is_empty() && plan.delete_local.is_empty());
+    }
+
+    #[test]
+    fn hardlink_groups_pick_smallest_path_as_leader() {
+        let groups = HardlinkGroups::from_ids([
+            ("b/copy".to_string(), (1, 10)),
+            ("a/orig".to_string(), (1, 10)),
+            ("c/third".to_string(), (1, 10)),
+            ("solo".to_string(), (1, 11)),
+            ("other-dev".to_string(), (2, 10)),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups.leader("b/copy"), Some("a/orig"));
+        assert_eq!(groups.leader("c/third"), Some("a/orig"));
+        assert_eq!(groups.leader("a/orig"), None);
+        assert_eq!(groups.leader("solo"), None);
+        assert_eq!(groups.leader("other-dev"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn detect_groups_real_hardlinks_and_symlinks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::write(root.join("a"), b"data").unwrap();
+        std::fs::hard_link(root.join("a"), root.join("b")).unwrap();
+        std::fs::write(root.join("c"), b"data").unwrap();
+
+        let groups = HardlinkGroups::detect(root.to_str().unwrap(), ["a", "b", "c"]);
+        assert_eq!(groups.leader("b"), Some("a"));
+        assert_eq!(groups.leader("c"), None);
+
+        let link_path = root.join("sub/link");
+        create_local_symlink("../a", &link_path).unwrap();
+        create_local_symlink("../c", &link_path).unwrap();
+        assert_eq!(std::fs::read_link(&link_path).unwrap(), Path::new("../c"));
+    }
+}
@@ -0,0 +1,299 @@
+//! Three-way merge for text files changed on both sides of a bisync.
+//!
+//! `aeroftp-cli sync --direction both --conflict-mode merge` keeps the
+//! content of small text files as of the last successful sync (the merge
+//! base). When both sides later change the same file, [`merge3`] replays the
+//! two edits on top of that base diff3-style: hunks touched by only one side
+//! are taken as-is, identical edits collapse, and overlapping edits are kept
+//! between conflict markers for the caller to preserve next to the file.
+//!
+//! Bases live in a [`MergeBaseStore`], a content-addressed directory of
+//! SHA-256 named blobs, so the bisync snapshot only records one hash per path.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use sha2::{Digest, Sha256};
+use similar::algorithms::{myers, Capture};
+use similar::DiffOp;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Largest file (in bytes) whose content is kept as a merge base.
+pub const MAX_MERGE_BYTES: u64 = 256 * 1024;
+
+/// Label of the local side in conflict markers.
+pub const LOCAL_LABEL: &str = "local";
+/// Label of the remote side in conflict markers.
+pub const REMOTE_LABEL: &str = "remote";
+
+/// Result of a three-way merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeResult {
+    /// Every hunk merged cleanly.
+    Clean(String),
+    /// At least one hunk was changed differently on both sides. `merged`
+    /// holds the whole file with diff3-style conflict markers.
+    Conflict { merged: String, conflicts: usize },
+}
+
+/// Return the content as text when it is small enough and looks like text
+/// (valid UTF-8 without NUL bytes).
+pub fn as_mergeable_text(bytes: &[u8]) -> Option<&str> {
+    if bytes.len() as u64 > MAX_MERGE_BYTES || bytes.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(bytes).ok()
+}
+
+/// Merge `local` and `remote`, both derived from `base`, line by line.
+pub fn merge3(base: &str, local: &str, remote: &str) -> MergeResult {
+    let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let local_lines: Vec<&str> = local.split_inclusive('\n').collect();
+    let remote_lines: Vec<&str> = remote.split_inclusive('\n').collect();
+
+    let local_match = line_matches(&base_lines, &local_lines);
+    let remote_match = line_matches(&base_lines, &remote_lines);
+
+    let mut merged = String::with_capacity(local.len().max(remote.len()));
+    let mut conflicts = 0usize;
+    let (mut o, mut a, mut b) = (0usize, 0usize, 0usize);
+
+    loop {
+        // Stable run: base lines matched in sync on both sides.
+        let mut stable = 0usize;
+        while o + stable < base_lines.len()
+            && local_match[o + stable] == Some(a + stable)
+            && remote_match[o + stable] == Some(b + stable)
+        {
+            stable += 1;
+        }
+        for line in &base_lines[o..o + stable] {
+            merged.push_str(line);
+        }
+        o += stable;
+        a += stable;
+        b += stable;
+
+        // Unstable chunk up to the next base line both sides still contain.
+        let next = (o..base_lines.len())
+            .find_map(|i| Some((i, local_match[i]?, remote_match[i]?)))
+            .unwrap_or((base_lines.len(), local_lines.len(), remote_lines.len()));
+        let (next_o, next_a, next_b) = next;
+        if next_o == o && next_a == a && next_b == b {
+            // Only reachable once all three sides are exhausted.
+            break;
+        }
+
+        let base_chunk = &base_lines[o..next_o];
+        let local_chunk = &local_lines[a..next_a];
+        let remote_chunk = &remote_lines[b..next_b];
+        if local_chunk == base_chunk || local_chunk == remote_chunk {
+            merged.extend(remote_chunk.iter().copied());
+        } else if remote_chunk == base_chunk {
+            merged.extend(local_chunk.iter().copied());
+        } else {
+            conflicts += 1;
+            push_marker(&mut merged, &format!("<<<<<<< {}", LOCAL_LABEL));
+            push_chunk(&mut merged, local_chunk);
+            push_marker(&mut merged, "||||||| base");
+            push_chunk(&mut merged, base_chunk);
+            push_marker(&mut merged, "=======");
+            push_chunk(&mut merged, remote_chunk);
+            push_marker(&mut merged, &format!(">>>>>>> {}", REMOTE_LABEL));
+        }
+        o = next_o;
+        a = next_a;
+        b = next_b;
+    }
+
+    if conflicts == 0 {
+        MergeResult::Clean(merged)
+    } else {
+        MergeResult::Conflict { merged, conflicts }
+    }
+}
+
+/// For each base line, the index of the line it is matched to in `other`.
+fn line_matches(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    // Raw Myers ops: the compacting `capture_diff_slices` wrapper can shift
+    // equal runs, which would misalign the line matching below.
+    let mut capture = Capture::new();
+    let _ = myers::diff(&mut capture, base, 0..base.len(), other, 0..other.len());
+    let mut matches = vec![None; base.len()];
+    for op in capture.into_ops() {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = op
+        {
+            for i in 0..len {
+                matches[old_index + i] = Some(new_index + i);
+            }
+        }
+    }
+    matches
+}
+
+fn push_chunk(out: &mut String, lines: &[&str]) {
+    for line in lines {
+        out.push_str(line);
+    }
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn push_marker(out: &mut String, marker: &str) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(marker);
+    out.push('\n');
+}
+
+/// Content-addressed store for merge bases (`<dir>/<sha256>`).
+#[derive(Debug, Clone)]
+pub struct MergeBaseStore {
+    dir: PathBuf,
+}
+
+impl MergeBaseStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Store `content` and return its SHA-256 hex digest.
+    pub fn put(&self, content: &str) -> Result<String, String> {
+        let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let path = self.dir.join(&hash);
+        if path.is_file() {
+            return Ok(hash);
+        }
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("cannot create {}: {}", self.dir.display(), e))?;
+        let tmp = self.dir.join(format!("{}.tmp", hash));
+        std::fs::write(&tmp, content.as_bytes())
+            .and_then(|_| std::fs::rename(&tmp, &path))
+            .map_err(|e| {
+                let _ = std::fs::remove_file(&tmp);
+                format!("cannot store merge base: {}", e)
+            })?;
+        Ok(hash)
+    }
+
+    /// Load a stored base. Returns `None` when missing or corrupted.
+    pub fn get(&self, hash: &str) -> Option<String> {
+        if hash.len() != 64 || !hash.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let data = std::fs::read(self.dir.join(hash)).ok()?;
+        if format!("{:x}", Sha256::digest(&data)) != hash {
+            return None;
+        }
+        String::from_utf8(data).ok()
+    }
+
+    /// Delete every stored base not listed in `keep`.
+    pub fn retain(&self, keep: &HashSet<String>) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !keep.contains(&name) {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge3_combines_edits_to_different_lines() {
+        let base = "a\nb\nc\nd\ne\n";
+        let local = "A\nb\nc\nd\ne\n";
+        let remote = "a\nb\nc\nd\nE\n";
+        assert_eq!(
+            merge3(base, local, remote),
+            MergeResult::Clean("A\nb\nc\nd\nE\n".to_string())
+        );
+    }
+
+    #[test]
+    fn merge3_handles_insertions_and_deletions() {
+        let base = "one\ntwo\nthree\nfour\n";
+        let local = "zero\none\ntwo\nthree\nfour\n";
+        let remote = "one\ntwo\nfour\n";
+        assert_eq!(
+            merge3(base, local, remote),
+            MergeResult::Clean("zero\none\ntwo\nfour\n".to_string())
+        );
+    }
+
+    #[test]
+    fn merge3_collapses_identical_changes() {
+        let base = "x = 1\ny = 2\n";
+        let both = "x = 1\ny = 3\n";
+        assert_eq!(
+            merge3(base, both, both),
+            MergeResult::Clean(both.to_string())
+        );
+    }
+
+    #[test]
+    fn merge3_marks_overlapping_edits() {
+        let base = "a\nb\nc\n";
+        let local = "a\nlocal\nc\n";
+        let remote = "a\nremote\nc\n";
+        match merge3(base, local, remote) {
+            MergeResult::Conflict { merged, conflicts } => {
+                assert_eq!(conflicts, 1);
+                assert_eq!(
+                    merged,
+                    "a\n<<<<<<< local\nlocal\n||||||| base\nb\n=======\nremote\n>>>>>>> remote\nc\n"
+                );
+            }
+            other => panic!("expected conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn merge3_keeps_missing_final_newline() {
+        let base = "a\nx\nb";
+        let local = "a2\nx\nb";
+        let remote = "a\nx\nb2";
+        assert_eq!(
+            merge3(base, local, remote),
+            MergeResult::Clean("a2\nx\nb2".to_string())
+        );
+    }
+
+    #[test]
+    fn mergeable_text_rejects_binary_and_large_files() {
+        assert_eq!(as_mergeable_text(b"hello\n"), Some("hello\n"));
+        assert_eq!(as_mergeable_text(b"bin\0ary"), None);
+        assert_eq!(as_mergeable_text(&[0xff, 0xfe, 0x41]), None);
+        let big = vec![b'a'; MAX_MERGE_BYTES as usize + 1];
+        assert_eq!(as_mergeable_text(&big), None);
+    }
+
+    #[test]
+    fn merge_base_store_round_trips_and_prunes() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let store = MergeBaseStore::new(dir.path().join("bases"));
+        let kept = store.put("keep me\n").expect("put");
+        let dropped = store.put("drop me\n").expect("put");
+        assert_eq!(store.put("keep me\n").expect("put again"), kept);
+        assert_eq!(store.get(&kept).as_deref(), Some("keep me\n"));
+        assert_eq!(store.get("../escape"), None);
+
+        store.retain(&HashSet::from([kept.clone()]));
+        assert!(store.get(&kept).is_some());
+        assert!(store.get(&dropped).is_none());
+    }
+}
@@ -0,0 +1,544 @@
+//! Permission, ownership and extended-attribute preservation for sync.
+//!
+//! `--perms` carries the POSIX mode bits across, `--owner` the numeric
+//! uid/gid (only applied where the destination allows it, like rsync run
+//! as a normal user), `--chmod=D755,F644` rewrites modes on the way, and
+//! `--xattrs` carries extended attributes. Remote backends have no common
+//! xattr API (OpenSSH's SFTP server included), so remote xattrs live in a
+//! `<name>.aeroxattrs` sidecar file next to the object.
+//!
+//! Metadata is compared separately from content: a file whose bytes match
+//! but whose mode differs gets a `chmod`, not a re-upload.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Suffix of the sidecar files that hold xattrs on remote backends.
+pub const XATTR_SUFFIX: &str = ".aeroxattrs";
+
+/// Mode given to new files and directories by `--chmod` without `--perms`.
+const DEFAULT_FILE_MODE: u32 = 0o644;
+const DEFAULT_DIR_MODE: u32 = 0o755;
+
+/// Which entries a `--chmod` item applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ChmodScope {
+    All,
+    Dirs,
+    Files,
+}
+
+/// One `+`, `-` or `=` clause of a symbolic `--chmod` item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ChmodOp {
+    op: char,
+    bits: u32,
+    /// `X`: execute only for directories and files already executable.
+    cond_exec: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ChmodAction {
+    Octal(u32),
+    Symbolic { who: u32, ops: Vec<ChmodOp> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ChmodRule {
+    scope: ChmodScope,
+    action: ChmodAction,
+}
+
+/// Parsed `--chmod` value, e.g. `D755,F644` or `Dg+s,ug+w,Fo-w,+X`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChmodRules(Vec<ChmodRule>);
+
+impl ChmodRules {
+    /// Parse a comma-separated list of rsync `--chmod` items. Each item is
+    /// an optional `D` (directories) or `F` (files) prefix followed by an
+    /// octal mode or a symbolic `chmod` clause.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for item in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (scope, spec) = match item.as_bytes()[0] {
+                b'D' => (ChmodScope::Dirs, &item[1..]),
+                b'F' => (ChmodScope::Files, &item[1..]),
+                _ => (ChmodScope::All, item),
+            };
+            let action = parse_chmod_action(spec)
+                .ok_or_else(|| format!("invalid --chmod item '{}'", item))?;
+            rules.push(ChmodRule { scope, action });
+        }
+        if rules.is_empty() {
+            return Err("--chmod needs at least one item".to_string());
+        }
+        Ok(Self(rules))
+    }
+
+    /// Apply every rule that matches `is_dir` to `mode`, in order.
+    pub fn apply(&self, mode: u32, is_dir: bool) -> u32 {
+        let mut mode = mode & 0o7777;
+        for rule in &self.0 {
+            let applies = match rule.scope {
+                ChmodScope::All => true,
+                ChmodScope::Dirs => is_dir,
+                ChmodScope::Files => !is_dir,
+            };
+            if !applies {
+                continue;
+            }
+            match &rule.action {
+                ChmodAction::Octal(value) => mode = *value,
+                ChmodAction::Symbolic { who, ops } => {
+                    for op in ops {
+                        let mut bits = op.bits;
+                        if op.cond_exec && (is_dir || mode & 0o111 != 0) {
+                            bits |= 0o111;
+                        }
+                        bits &= who;
+                        mode = match op.op {
+                            '+' => mode | bits,
+                            '-' => mode & !bits,
+                            _ => (mode & !who) | bits,
+                        };
+                    }
+                }
+            }
+        }
+        mode & 0o7777
+    }
+}
+
+fn parse_chmod_action(spec: &str) -> Option<ChmodAction> {
+    if !spec.is_empty() && spec.len() <= 4 && spec.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+        return u32::from_str_radix(spec, 8).ok().map(ChmodAction::Octal);
+    }
+    let mut chars = spec.chars().peekable();
+    let mut who = 0;
+    while let Some(c) = chars.peek() {
+        who |= match c {
+            'u' => 0o4700,
+            'g' => 0o2070,
+            'o' => 0o1007,
+            'a' => 0o7777,
+            _ => break,
+        };
+        chars.next();
+    }
+    if who == 0 {
+        who = 0o7777;
+    }
+    let mut ops = Vec::new();
+    while let Some(op) = chars.next() {
+        if !matches!(op, '+' | '-' | '=') {
+            return None;
+        }
+        let mut clause = ChmodOp {
+            op,
+            bits: 0,
+            cond_exec: false,
+        };
+        while let Some(c) = chars.peek() {
+            match c {
+                'r' => clause.bits |= 0o444,
+                'w' => clause.bits |= 0o222,
+                'x' => clause.bits |= 0o111,
+                's' => clause.bits |= 0o6000,
+                't' => clause.bits |= 0o1000,
+                'X' => clause.cond_exec = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        ops.push(clause);
+    }
+    (!ops.is_empty()).then_some(ChmodAction::Symbolic { who, ops })
+}
+
+/// Parse a listing permission string (`rwxr-xr-x`, `drwxr-sr-x`, or an
+/// octal `755`) into mode bits.
+pub fn parse_mode_string(value: &str) -> Option<u32> {
+    let value = value.trim();
+    if !value.is_empty() && value.len() <= 4 && value.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+        return u32::from_str_radix(value, 8).ok();
+    }
+    let bytes = value.as_bytes();
+    let perms = match bytes.len() {
+        9 => bytes,
+        10 | 11 => &bytes[1..10],
+        _ => return None,
+    };
+    let mut mode = 0;
+    for (i, &c) in perms.iter().enumerate() {
+        let bit = 1 << (8 - i);
+        let special = match i {
+            2 => 0o4000,
+            5 => 0o2000,
+            8 => 0o1000,
+            _ => 0,
+        };
+        match (i % 3, c) {
+            (_, b'-') => {}
+            (0, b'r') | (1, b'w') | (2, b'x') => mode |= bit,
+            (2, b's') | (2, b't') => mode |= bit | special,
+            (2, b'S') | (2, b'T') => mode |= special,
+            _ => return None,
+        }
+    }
+    Some(mode)
+}
+
+/// Mode, ownership and xattrs of one file or directory. Fields are `None`
+/// (or empty) when unknown or not requested.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub xattrs: BTreeMap<String, Vec<u8>>,
+}
+
+impl FileMeta {
+    /// Metadata from a remote listing. Owners are only usable when the
+    /// server reports numeric ids (SFTP does; FTP usually sends names).
+    pub fn from_listing(
+        permissions: Option<&str>,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Self {
+        Self {
+            mode: permissions.and_then(parse_mode_string),
+            uid: owner.and_then(|o| o.parse().ok()),
+            gid: group.and_then(|g| g.parse().ok()),
+            xattrs: BTreeMap::new(),
+        }
+    }
+}
+
+/// What `sync` preserves besides file content.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetaOptions {
+    /// `--perms`
+    #[serde(default)]
+    pub perms: bool,
+    /// `--owner`
+    #[serde(default)]
+    pub owner: bool,
+    /// `--xattrs`
+    #[serde(default)]
+    pub xattrs: bool,
+    /// `--chmod`
+    #[serde(default)]
+    pub chmod: Option<ChmodRules>,
+}
+
+impl MetaOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.perms || self.owner || self.xattrs || self.chmod.is_some()
+    }
+
+    /// Whether the mode bits take part in the sync.
+    pub fn tracks_mode(&self) -> bool {
+        self.perms || self.chmod.is_some()
+    }
+
+    /// The metadata `dest` should end up with when synced from `source`.
+    /// Without `--perms`, `--chmod` rewrites the destination's own mode
+    /// (or the default mode of a new entry).
+    pub fn desired(&self, source: &FileMeta, dest: &FileMeta, is_dir: bool) -> FileMeta {
+        let mut want = FileMeta::default();
+        if self.tracks_mode() {
+            let base = if self.perms { source.mode } else { dest.mode };
+            want.mode = match &self.chmod {
+                Some(rules) => {
+                    let fallback = if is_dir {
+                        DEFAULT_DIR_MODE
+                    } else {
+                        DEFAULT_FILE_MODE
+                    };
+                    Some(rules.apply(base.unwrap_or(fallback), is_dir))
+                }
+                None => base,
+            };
+        }
+        if self.owner {
+            want.uid = source.uid;
+            want.gid = source.gid;
+        }
+        if self.xattrs {
+            want.xattrs = source.xattrs.clone();
+        }
+        want
+    }
+
+    /// The changes that bring `dest` in line with `source`.
+    pub fn diff(&self, source: &FileMeta, dest: &FileMeta, is_dir: bool) -> MetaDiff {
+        let want = self.desired(source, dest, is_dir);
+        MetaDiff {
+            mode: want.mode.filter(|m| dest.mode != Some(*m)),
+            uid: want.uid.filter(|u| dest.uid != Some(*u)),
+            gid: want.gid.filter(|g| dest.gid != Some(*g)),
+            xattrs: (self.xattrs && want.xattrs != dest.xattrs).then_some(want.xattrs),
+        }
+    }
+}
+
+/// Metadata to apply to a destination entry. `xattrs` replaces the full
+/// attribute set (names missing from it are removed).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetaDiff {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xattrs: Option<BTreeMap<String, Vec<u8>>>,
+}
+
+impl MetaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.mode.is_none() && self.uid.is_none() && self.gid.is_none() && self.xattrs.is_none()
+    }
+
+    /// Short summary for plans and sync reasons, e.g. `mode 0755, owner 1000:1000`.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(mode) = self.mode {
+            parts.push(format!("mode {:04o}", mode));
+        }
+        if self.uid.is_some() || self.gid.is_some() {
+            let id = |v: Option<u32>| v.map(|n| n.to_string()).unwrap_or_default();
+            parts.push(format!("owner {}:{}", id(self.uid), id(self.gid)));
+        }
+        if let Some(xattrs) = &self.xattrs {
+            parts.push(format!("{} xattr(s)", xattrs.len()));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Read the metadata `opts` asks for from a local path. Platforms without
+/// POSIX metadata report nothing.
+pub fn read_local_meta(path: &Path, opts: &MetaOptions) -> FileMeta {
+    let mut meta = FileMeta::default();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let Ok(md) = std::fs::metadata(path) {
+            if opts.tracks_mode() {
+                meta.mode = Some(md.mode() & 0o7777);
+            }
+            if opts.owner {
+                meta.uid = Some(md.uid());
+                meta.gid = Some(md.gid());
+            }
+        }
+        if opts.xattrs {
+            if let Ok(names) = xattr::list(path) {
+                for name in names {
+                    if let Ok(Some(value)) = xattr::get(path, &name) {
+                        meta.xattrs
+                            .insert(name.to_string_lossy().into_owned(), value);
+                    }
+                }
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (path, opts);
+    meta
+}
+
+/// Apply `diff` to a local path. Ownership changes the process is not
+/// allowed to make are skipped, as rsync does for non-root users.
+pub fn apply_local_meta(path: &Path, diff: &MetaDiff) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if diff.uid.is_some() || diff.gid.is_some() {
+            match std::os::unix::fs::chown(path, diff.uid, diff.gid) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {}
+                Err(e) => return Err(format!("chown: {}", e)),
+            }
+        }
+        if let Some(mode) = diff.mode {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+                .map_err(|e| format!("chmod: {}", e))?;
+        }
+        if let Some(xattrs) = &diff.xattrs {
+            let existing: Vec<_> = xattr::list(path).map(|n| n.collect()).unwrap_or_default();
+            for name in existing {
+                if !xattrs.contains_key(&*name.to_string_lossy()) {
+                    xattr::remove(path, &name).map_err(|e| format!("xattr: {}", e))?;
+                }
+            }
+            for (name, value) in xattrs {
+                xattr::set(path, name, value).map_err(|e| format!("xattr {}: {}", name, e))?;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    if diff.mode.is_some() || diff.xattrs.is_some() {
+        let _ = path;
+        return Err("permissions and xattrs are not supported on this platform".to_string());
+    }
+    Ok(())
+}
+
+/// Relative path of the sidecar that holds `rel_path`'s xattrs remotely.
+pub fn xattr_file_path(rel_path: &str) -> String {
+    format!("{}{}", rel_path, XATTR_SUFFIX)
+}
+
+/// The entry path behind an xattr sidecar, or `None` for other files.
+pub fn strip_xattr_suffix(rel_path: &str) -> Option<&str> {
+    rel_path
+        .strip_suffix(XATTR_SUFFIX)
+        .filter(|base| !base.is_empty() && !base.ends_with('/'))
+}
+
+/// Serialize xattrs for a sidecar file: a JSON object of base64 values.
+pub fn encode_xattrs(xattrs: &BTreeMap<String, Vec<u8>>) -> Vec<u8> {
+    let encoded: BTreeMap<&str, String> = xattrs
+        .iter()
+        .map(|(name, value)| (name.as_str(), BASE64.encode(value)))
+        .collect();
+    serde_json::to_vec_pretty(&encoded).unwrap_or_default()
+}
+
+/// Parse a sidecar written by [`encode_xattrs`].
+pub fn decode_xattrs(data: &[u8]) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let encoded: BTreeMap<String, String> =
+        serde_json::from_slice(data).map_err(|e| format!("invalid xattr file: {}", e))?;
+    encoded
+        .into_iter()
+        .map(|(name, value)| {
+            BASE64
+                .decode(value.as_bytes())
+                .map(|bytes| (name, bytes))
+                .map_err(|e| format!("invalid xattr file: {}", e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chmod_octal_items_are_scoped() {
+        let rules = ChmodRules::parse("D755,F644").unwrap();
+        assert_eq!(rules.apply(0o700, true), 0o755);
+        assert_eq!(rules.apply(0o600, false), 0o644);
+    }
+
+    #[test]
+    fn chmod_symbolic_items() {
+        let rules = ChmodRules::parse("Dg+s,ug+w,Fo-w,+X").unwrap();
+        assert_eq!(rules.apply(0o755, true), 0o2775);
+        assert_eq!(rules.apply(0o646, false), 0o664);
+        assert_eq!(rules.apply(0o744, false), 0o775);
+        assert_eq!(
+            ChmodRules::parse("go=r").unwrap().apply(0o777, false),
+            0o744
+        );
+        assert!(ChmodRules::parse("D8").is_err());
+        assert!(ChmodRules::parse("u~x").is_err());
+        assert!(ChmodRules::parse("").is_err());
+    }
+
+    #[test]
+    fn parse_listing_modes() {
+        assert_eq!(parse_mode_string("rwxr-xr-x"), Some(0o755));
+        assert_eq!(parse_mode_string("-rw-r--r--"), Some(0o644));
+        assert_eq!(parse_mode_string("drwxr-sr-t"), Some(0o3755));
+        assert_eq!(parse_mode_string("-rwSr--r--"), Some(0o4644));
+        assert_eq!(parse_mode_string("0640"), Some(0o640));
+        assert_eq!(parse_mode_string("rwx"), None);
+        assert_eq!(parse_mode_string("-rwqr--r--"), None);
+    }
+
+    #[test]
+    fn diff_only_reports_what_differs() {
+        let opts = MetaOptions {
+            perms: true,
+            owner: true,
+            ..Default::default()
+        };
+        let source = FileMeta::from_listing(Some("rwxr-xr-x"), Some("1000"), Some("100"));
+        let dest = FileMeta::from_listing(Some("rw-r--r--"), Some("1000"), Some("50"));
+        let diff = opts.diff(&source, &dest, false);
+        assert_eq!(diff.mode, Some(0o755));
+        assert_eq!(diff.uid, None);
+        assert_eq!(diff.gid, Some(100));
+        assert_eq!(diff.describe(), "mode 0755, owner :100");
+        assert!(opts.diff(&source, &source, false).is_empty());
+    }
+
+    #[test]
+    fn chmod_without_perms_rewrites_destination_mode() {
+        let opts = MetaOptions {
+            chmod: Some(ChmodRules::parse("F600").unwrap()),
+            ..Default::default()
+        };
+        let source = FileMeta {
+            mode: Some(0o777),
+            ..Default::default()
+        };
+        let dest = FileMeta {
+            mode: Some(0o644),
+            ..Default::default()
+        };
+        assert_eq!(opts.diff(&source, &dest, false).mode, Some(0o600));
+        assert_eq!(opts.diff(&source, &dest, true).mode, None);
+        assert_eq!(
+            opts.desired(&source, &FileMeta::default(), true).mode,
+            Some(0o755)
+        );
+    }
+
+    #[test]
+    fn xattr_sidecar_round_trip() {
+        let mut xattrs = BTreeMap::new();
+        xattrs.insert("user.comment".to_string(), b"hello".to_vec());
+        xattrs.insert("user.bin".to_string(), vec![0, 255, 7]);
+        assert_eq!(decode_xattrs(&encode_xattrs(&xattrs)).unwrap(), xattrs);
+        assert!(decode_xattrs(b"not json").is_err());
+        assert_eq!(xattr_file_path("a/b.txt"), "a/b.txt.aeroxattrs");
+        assert_eq!(strip_xattr_suffix("a/b.txt.aeroxattrs"), Some("a/b.txt"));
+        assert_eq!(strip_xattr_suffix("a/.aeroxattrs"), None);
+        assert_eq!(strip_xattr_suffix("a/b.txt"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn local_mode_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("f");
+        std::fs::write(&path, b"x").unwrap();
+        let diff = MetaDiff {
+            mode: Some(0o640),
+            ..Default::default()
+        };
+        apply_local_meta(&path, &diff).unwrap();
+        let opts = MetaOptions {
+            perms: true,
+            ..Default::default()
+        };
+        assert_eq!(read_local_meta(&path, &opts).mode, Some(0o640));
+    }
+}
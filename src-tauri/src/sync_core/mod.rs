@@ -7,13 +7,19 @@
 //! cross-dependencies.
 //!
 //! The module is feature-flag friendly: it only depends on `StorageProvider`
-//! plus `walkdir`, `globset`, `sha2`, `chrono`, giving a tight `cargo check
-//! --lib` footprint (no extra build time).
+//! and the local `ChecksumCache` plus `walkdir`, `globset`, `sha2`, `chrono`,
+//! `similar`, `unicode-normalization`, giving a tight `cargo check --lib`
+//! footprint (no extra build time).
 
 // SPDX-License-Identifier: GPL-3.0-or-later
 // Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
 
+pub mod changes;
 pub mod compare;
+pub mod links;
+pub mod merge;
+pub mod meta;
+pub mod names;
 pub mod scan;
 
 pub use crate::sync::{
@@ -21,5 +27,24 @@ pub use crate::sync::{
     FileOutcome, NoopProgressSink, SyncDirection, SyncError, SyncOptions, SyncPhase,
     SyncProgressSink, SyncReport,
 };
-pub use compare::{compare_trees, DiffEntry, DiffReport};
-pub use scan::{scan_local_tree, scan_remote_tree, LocalEntry, RemoteEntry, ScanOptions};
+pub use changes::{
+    capture_change_token, refresh_from_change_feed, ChangeFeedApply, ChangeFeedState, ChangeIndex,
+    ChangeTargets,
+};
+pub use compare::{compare_trees, compare_trees_with, DiffEntry, DiffReport};
+pub use links::{
+    create_local_symlink, is_safe_link, link_file_path, plan_links, strip_link_suffix,
+    HardlinkGroups, LinkEntry, LinkMode, LinkPlan, LINK_SUFFIX,
+};
+pub use merge::{as_mergeable_text, merge3, MergeBaseStore, MergeResult, MAX_MERGE_BYTES};
+pub use meta::{
+    apply_local_meta, decode_xattrs, encode_xattrs, parse_mode_string, read_local_meta,
+    strip_xattr_suffix, xattr_file_path, ChmodRules, FileMeta, MetaDiff, MetaOptions, XATTR_SUFFIX,
+};
+pub use names::{
+    find_collisions, local_fs_case_insensitive, name_key, plan_names, CollisionKind,
+    CollisionPolicy, CollisionSide, NameCollision, NameOptions, NamePlan,
+};
+pub use scan::{
+    scan_local_links, scan_local_tree, scan_remote_tree, LocalEntry, RemoteEntry, ScanOptions,
+};
@@ -0,0 +1,526 @@
+//! Unicode normalization and case-collision handling for relative paths.
+//!
+//! macOS hands out NFD-decomposed names while Linux and most cloud APIs keep
+//! whatever bytes they were given (usually NFC), so `café.txt` can show up
+//! as two different strings on the two sides of a sync. Case-insensitive
+//! backends (OneDrive, Box, Dropbox, SMB shares, Windows and macOS volumes)
+//! add a second trap: `Readme.md` and `README.md` are distinct on the source
+//! but land on the same file on the destination.
+//!
+//! [`name_key`] folds a path into the form two names must share to refer to
+//! the same file. [`plan_names`] uses it to pair local and remote entries,
+//! detect collisions on either side and resolve them per
+//! [`CollisionPolicy`].
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+/// Whether the local filesystem is expected to fold case.
+///
+/// Default APFS/HFS+ and NTFS volumes are case-insensitive; Linux
+/// filesystems are not. Case-sensitive APFS volumes are treated as
+/// insensitive, which only makes collision detection stricter.
+pub fn local_fs_case_insensitive() -> bool {
+    cfg!(any(windows, target_os = "macos"))
+}
+
+/// NFC form of `name`.
+pub fn normalize_name(name: &str) -> String {
+    if is_nfc(name) {
+        name.to_string()
+    } else {
+        name.nfc().collect()
+    }
+}
+
+/// Comparison key of a relative path: NFC, lowercased when the target
+/// folds case.
+pub fn name_key(rel_path: &str, case_insensitive: bool) -> String {
+    let nfc = normalize_name(rel_path);
+    if case_insensitive {
+        nfc.to_lowercase()
+    } else {
+        nfc
+    }
+}
+
+/// How to resolve paths that would collapse into one file on the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    /// Keep the first path (in byte order) and give the others a
+    /// `name (collision N).ext` name on the target.
+    Rename,
+    /// Leave every colliding path out of the sync.
+    #[default]
+    Skip,
+    /// Abort before transferring anything.
+    Fail,
+}
+
+impl CollisionPolicy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "rename" => Ok(Self::Rename),
+            "skip" => Ok(Self::Skip),
+            "fail" => Ok(Self::Fail),
+            other => Err(format!(
+                "Invalid name collision policy '{}': use rename, skip or fail",
+                other
+            )),
+        }
+    }
+}
+
+/// Which tree holds the colliding paths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionSide {
+    Local,
+    Remote,
+}
+
+/// Why the paths collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionKind {
+    /// Same name under Unicode normalization (e.g. NFC vs NFD).
+    Unicode,
+    /// Same name once case is folded.
+    Case,
+}
+
+/// A group of paths from one tree that map to a single name on the other.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NameCollision {
+    pub side: CollisionSide,
+    pub kind: CollisionKind,
+    /// Colliding paths, sorted.
+    pub paths: Vec<String>,
+}
+
+/// Case behavior of both trees and the policy to apply on collisions.
+#[derive(Debug, Clone, Copy)]
+pub struct NameOptions {
+    pub local_case_insensitive: bool,
+    pub remote_case_insensitive: bool,
+    pub policy: CollisionPolicy,
+    /// Local paths are written to the remote (check local collisions).
+    pub uploads: bool,
+    /// Remote paths are written locally (check remote collisions).
+    pub downloads: bool,
+}
+
+impl Default for NameOptions {
+    fn default() -> Self {
+        Self {
+            local_case_insensitive: false,
+            remote_case_insensitive: false,
+            policy: CollisionPolicy::default(),
+            uploads: true,
+            downloads: true,
+        }
+    }
+}
+
+/// Outcome of [`plan_names`].
+///
+/// Sync decisions are taken in the local namespace: every remote path is
+/// translated to the local name it pairs with, and translated back when a
+/// remote operation is issued.
+#[derive(Debug, Clone, Default)]
+pub struct NamePlan {
+    /// Remote path -> local-namespace path, where they differ.
+    pub remote_to_local: HashMap<String, String>,
+    /// Local-namespace path -> remote path, where they differ.
+    pub local_to_remote: HashMap<String, String>,
+    /// Local paths left out of the sync by [`CollisionPolicy::Skip`].
+    pub skip_local: HashSet<String>,
+    /// Remote paths left out of the sync by [`CollisionPolicy::Skip`].
+    pub skip_remote: HashSet<String>,
+    /// Every collision found, whatever the policy.
+    pub collisions: Vec<NameCollision>,
+}
+
+impl NamePlan {
+    /// Remote path for a local-namespace path.
+    pub fn remote_path<'a>(&'a self, local_rel: &'a str) -> &'a str {
+        self.local_to_remote
+            .get(local_rel)
+            .map(String::as_str)
+            .unwrap_or(local_rel)
+    }
+
+    /// Local-namespace path for a remote path.
+    pub fn local_path<'a>(&'a self, remote_rel: &'a str) -> &'a str {
+        self.remote_to_local
+            .get(remote_rel)
+            .map(String::as_str)
+            .unwrap_or(remote_rel)
+    }
+}
+
+/// Group `paths` that share a [`name_key`] on a target with the given case
+/// behavior.
+pub fn find_collisions<'a>(
+    paths: impl IntoIterator<Item = &'a str>,
+    case_insensitive: bool,
+    side: CollisionSide,
+) -> Vec<NameCollision> {
+    let mut groups: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for path in paths {
+        groups
+            .entry(name_key(path, case_insensitive))
+            .or_default()
+            .push(path);
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort_unstable();
+            group.dedup();
+            let nfc: HashSet<String> = group.iter().map(|p| normalize_name(p)).collect();
+            let kind = if nfc.len() < group.len() {
+                CollisionKind::Unicode
+            } else {
+                CollisionKind::Case
+            };
+            NameCollision {
+                side,
+                kind,
+                paths: group.into_iter().map(str::to_string).collect(),
+            }
+        })
+        .filter(|c| c.paths.len() > 1)
+        .collect()
+}
+
+/// Deterministic replacement name for the `n`-th extra path of a collision.
+///
+/// Only the final component is renamed: `Docs/Readme.md` becomes
+/// `Docs/Readme (collision 1).md`.
+pub fn collision_rename_path(rel_path: &str, n: usize) -> String {
+    let (dir, file) = match rel_path.rsplit_once('/') {
+        Some((dir, file)) => (Some(dir), file),
+        None => (None, rel_path),
+    };
+    let renamed = match file.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => {
+            format!("{} (collision {}).{}", stem, n, ext)
+        }
+        _ => format!("{} (collision {})", file, n),
+    };
+    match dir {
+        Some(dir) => format!("{}/{}", dir, renamed),
+        None => renamed,
+    }
+}
+
+/// Pair remote paths with local paths that name the same file, e.g. NFD vs
+/// NFC spellings or, when `case_insensitive`, case variants.
+///
+/// Exact matches win; a fuzzy match is only taken when it is unambiguous.
+/// Returns remote path -> local path for the pairs that differ.
+pub fn match_remote_names<'a>(
+    local: impl IntoIterator<Item = &'a str>,
+    remote: impl IntoIterator<Item = &'a str>,
+    case_insensitive: bool,
+) -> HashMap<String, String> {
+    let local: Vec<&str> = local.into_iter().collect();
+    let remote: Vec<&str> = remote.into_iter().collect();
+    let local_set: HashSet<&str> = local.iter().copied().collect();
+    let remote_set: HashSet<&str> = remote.iter().copied().collect();
+
+    let mut local_by_key: HashMap<String, Vec<&str>> = HashMap::new();
+    for path in local.iter().filter(|p| !remote_set.contains(*p)) {
+        local_by_key
+            .entry(name_key(path, case_insensitive))
+            .or_default()
+            .push(path);
+    }
+    let mut remote_by_key: HashMap<String, Vec<&str>> = HashMap::new();
+    for path in remote.iter().filter(|p| !local_set.contains(*p)) {
+        remote_by_key
+            .entry(name_key(path, case_insensitive))
+            .or_default()
+            .push(path);
+    }
+
+    let mut pairs = HashMap::new();
+    for (key, remotes) in remote_by_key {
+        if let ([remote_path], Some([local_path])) = (
+            remotes.as_slice(),
+            local_by_key.get(&key).map(Vec::as_slice),
+        ) {
+            pairs.insert(remote_path.to_string(), local_path.to_string());
+        }
+    }
+    pairs
+}
+
+/// Pair both trees, detect collisions and resolve them per `options.policy`.
+///
+/// Local paths collide when the remote would store them as one file, and
+/// remote paths collide when the local filesystem would; each side is only
+/// checked when `options` says it is written to the other. Returns the
+/// collisions as the error when the policy is [`CollisionPolicy::Fail`].
+pub fn plan_names<'a>(
+    local: impl IntoIterator<Item = &'a str>,
+    remote: impl IntoIterator<Item = &'a str>,
+    options: NameOptions,
+) -> Result<NamePlan, Vec<NameCollision>> {
+    let local: Vec<&str> = local.into_iter().collect();
+    let remote: Vec<&str> = remote.into_iter().collect();
+
+    let mut collisions = Vec::new();
+    if options.uploads {
+        collisions.extend(find_collisions(
+            local.iter().copied(),
+            options.remote_case_insensitive,
+            CollisionSide::Local,
+        ));
+    }
+    if options.downloads {
+        collisions.extend(find_collisions(
+            remote.iter().copied(),
+            options.local_case_insensitive,
+            CollisionSide::Remote,
+        ));
+    }
+    if !collisions.is_empty() && options.policy == CollisionPolicy::Fail {
+        return Err(collisions);
+    }
+
+    let case_insensitive = options.local_case_insensitive || options.remote_case_insensitive;
+    let mut plan = NamePlan::default();
+    match options.policy {
+        CollisionPolicy::Skip => {
+            // Drop every path that folds onto a colliding name, on both
+            // sides, so nothing touches the ambiguous target.
+            let keys: HashSet<String> = collisions
+                .iter()
+                .flat_map(|c| c.paths.iter())
+                .map(|p| name_key(p, case_insensitive))
+                .collect();
+            for path in &local {
+                if keys.contains(&name_key(path, case_insensitive)) {
+                    plan.skip_local.insert(path.to_string());
+                }
+            }
+            for path in &remote {
+                if keys.contains(&name_key(path, case_insensitive)) {
+                    plan.skip_remote.insert(path.to_string());
+                }
+            }
+        }
+        CollisionPolicy::Rename => {
+            for collision in &collisions {
+                for (n, path) in collision.paths.iter().enumerate().skip(1) {
+                    let renamed = collision_rename_path(path, n);
+                    match collision.side {
+                        CollisionSide::Local => {
+                            plan.local_to_remote.insert(path.clone(), renamed);
+                        }
+                        CollisionSide::Remote => {
+                            plan.remote_to_local.insert(path.clone(), renamed.clone());
+                            plan.local_to_remote.insert(renamed, path.clone());
+                        }
+                    }
+                }
+            }
+        }
+        CollisionPolicy::Fail => {}
+    }
+
+    // Remote paths already claimed by a rename target of a local path.
+    let renamed_targets: HashMap<String, String> = plan
+        .local_to_remote
+        .iter()
+        .filter(|(local_rel, _)| !plan.remote_to_local.values().any(|v| v == *local_rel))
+        .map(|(local_rel, remote_rel)| (remote_rel.clone(), local_rel.clone()))
+        .collect();
+    for (remote_rel, local_rel) in &renamed_targets {
+        if remote.contains(&remote_rel.as_str()) {
+            plan.remote_to_local
+                .insert(remote_rel.clone(), local_rel.clone());
+        }
+    }
+
+    let local_candidates: Vec<&str> = local
+        .iter()
+        .copied()
+        .filter(|p| !plan.skip_local.contains(*p) && !plan.local_to_remote.contains_key(*p))
+        .collect();
+    let remote_candidates: Vec<&str> = remote
+        .iter()
+        .copied()
+        .filter(|p| !plan.skip_remote.contains(*p) && !plan.remote_to_local.contains_key(*p))
+        .collect();
+    for (remote_rel, local_rel) in
+        match_remote_names(local_candidates, remote_candidates, case_insensitive)
+    {
+        plan.local_to_remote
+            .insert(local_rel.clone(), remote_rel.clone());
+        plan.remote_to_local.insert(remote_rel, local_rel);
+    }
+
+    plan.collisions = collisions;
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NFC: &str = "caf\u{e9}.txt";
+    const NFD: &str = "cafe\u{301}.txt";
+
+    #[test]
+    fn name_key_folds_normalization_and_case() {
+        assert_eq!(name_key(NFD, false), NFC);
+        assert_eq!(name_key("Docs/README.md", true), "docs/readme.md");
+        assert_ne!(name_key("Docs/README.md", false), "docs/readme.md");
+    }
+
+    #[test]
+    fn find_collisions_reports_kind_and_side() {
+        let found = find_collisions(
+            ["Readme.md", "README.md", "a.txt"],
+            true,
+            CollisionSide::Local,
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, CollisionKind::Case);
+        assert_eq!(found[0].paths, vec!["README.md", "Readme.md"]);
+
+        let found = find_collisions([NFC, NFD], false, CollisionSide::Remote);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, CollisionKind::Unicode);
+        assert_eq!(found[0].side, CollisionSide::Remote);
+
+        assert!(
+            find_collisions(["Readme.md", "README.md"], false, CollisionSide::Local).is_empty()
+        );
+    }
+
+    #[test]
+    fn collision_rename_keeps_directory_and_extension() {
+        assert_eq!(
+            collision_rename_path("Docs/Readme.md", 1),
+            "Docs/Readme (collision 1).md"
+        );
+        assert_eq!(
+            collision_rename_path("Makefile", 2),
+            "Makefile (collision 2)"
+        );
+        assert_eq!(collision_rename_path(".env", 1), ".env (collision 1)");
+    }
+
+    #[test]
+    fn match_remote_names_pairs_unambiguous_variants() {
+        let pairs = match_remote_names([NFC, "Notes.txt"], [NFD, "notes.TXT"], false);
+        assert_eq!(pairs.get(NFD).map(String::as_str), Some(NFC));
+        assert!(!pairs.contains_key("notes.TXT"));
+
+        let pairs = match_remote_names(["Notes.txt"], ["notes.TXT"], true);
+        assert_eq!(
+            pairs.get("notes.TXT").map(String::as_str),
+            Some("Notes.txt")
+        );
+
+        // Exact matches are never re-paired.
+        let pairs = match_remote_names(["a.txt", "A.txt"], ["a.txt"], true);
+        assert!(pairs.is_empty());
+    }
+
+    #[test]
+    fn plan_names_fail_policy_returns_collisions() {
+        let options = NameOptions {
+            remote_case_insensitive: true,
+            policy: CollisionPolicy::Fail,
+            ..Default::default()
+        };
+        let err = plan_names(["Readme.md", "README.md"], [], options).unwrap_err();
+        assert_eq!(err.len(), 1);
+        assert_eq!(err[0].side, CollisionSide::Local);
+    }
+
+    #[test]
+    fn plan_names_skip_policy_drops_both_sides() {
+        let options = NameOptions {
+            remote_case_insensitive: true,
+            policy: CollisionPolicy::Skip,
+            ..Default::default()
+        };
+        let plan = plan_names(
+            ["Readme.md", "README.md", "ok.txt"],
+            ["readme.md", "ok.txt"],
+            options,
+        )
+        .unwrap();
+        assert_eq!(plan.skip_local.len(), 2);
+        assert!(plan.skip_remote.contains("readme.md"));
+        assert!(!plan.skip_local.contains("ok.txt"));
+        assert_eq!(plan.collisions.len(), 1);
+    }
+
+    #[test]
+    fn plan_names_rename_policy_is_stable_across_runs() {
+        let options = NameOptions {
+            remote_case_insensitive: true,
+            policy: CollisionPolicy::Rename,
+            ..Default::default()
+        };
+        let plan = plan_names(["Readme.md", "README.md"], [], options).unwrap();
+        assert_eq!(plan.remote_path("README.md"), "README.md");
+        assert_eq!(plan.remote_path("Readme.md"), "Readme (collision 1).md");
+
+        // Next run: the renamed remote copy pairs back with its source.
+        let plan = plan_names(
+            ["Readme.md", "README.md"],
+            ["README.md", "Readme (collision 1).md"],
+            options,
+        )
+        .unwrap();
+        assert_eq!(plan.local_path("Readme (collision 1).md"), "Readme.md");
+        assert_eq!(plan.local_path("README.md"), "README.md");
+    }
+
+    #[test]
+    fn plan_names_rename_policy_renames_remote_side_locally() {
+        let options = NameOptions {
+            local_case_insensitive: true,
+            policy: CollisionPolicy::Rename,
+            ..Default::default()
+        };
+        let plan = plan_names([], ["A.txt", "a.txt"], options).unwrap();
+        assert_eq!(plan.local_path("A.txt"), "A.txt");
+        assert_eq!(plan.local_path("a.txt"), "a (collision 1).txt");
+        assert_eq!(plan.remote_path("a (collision 1).txt"), "a.txt");
+    }
+
+    #[test]
+    fn plan_names_pairs_nfd_remote_with_nfc_local() {
+        let plan = plan_names([NFC], [NFD], NameOptions::default()).unwrap();
+        assert!(plan.collisions.is_empty());
+        assert_eq!(plan.local_path(NFD), NFC);
+        assert_eq!(plan.remote_path(NFC), NFD);
+    }
+
+    #[test]
+    fn plan_names_ignores_collisions_on_a_side_that_is_not_written() {
+        let options = NameOptions {
+            local_case_insensitive: true,
+            policy: CollisionPolicy::Fail,
+            downloads: false,
+            ..Default::default()
+        };
+        let plan = plan_names(["a.txt"], ["A.txt", "a.txt"], options).unwrap();
+        assert!(plan.collisions.is_empty());
+    }
+}
@@ -5,10 +5,13 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 // Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
 
+use super::links::{is_safe_link, LinkEntry};
+use crate::checksum_cache::ChecksumCache;
 use crate::providers::{ProviderError, StorageProvider};
 use sha2::Digest;
 use std::collections::HashSet;
 use std::path::Path;
+use std::sync::Arc;
 
 /// Soft cap on the number of entries returned from a single scan. Matches
 /// the CLI cap so both front-ends behave identically.
@@ -60,6 +63,15 @@ pub struct ScanOptions {
     /// Paths that should always be skipped regardless of excludes.
     /// Used to skip the bisync snapshot file when syncing a tree.
     pub skip_filenames: Vec<String>,
+    /// Follow local symlinks and scan what they point to (`--copy-links`).
+    /// Off by default: links are left out of the scan.
+    pub copy_links: bool,
+    /// Ignore symlinks that are absolute or point outside the tree
+    /// (`--safe-links`).
+    pub safe_links: bool,
+    /// Reuse local SHA-256 digests across runs. Only consulted when
+    /// `compute_checksum` is set.
+    pub checksum_cache: Option<Arc<ChecksumCache>>,
 }
 
 fn compile_matchers(patterns: &[String]) -> Vec<globset::GlobMatcher> {
@@ -82,24 +94,35 @@ pub fn scan_local_tree(root: &str, opts: &ScanOptions) -> Vec<LocalEntry> {
     let depth = opts.max_depth.unwrap_or(DEFAULT_SCAN_DEPTH);
 
     let mut entries = Vec::new();
-    for walk_entry in walkdir::WalkDir::new(root)
-        .follow_links(false)
+    let mut walker = walkdir::WalkDir::new(root)
+        .follow_links(opts.copy_links)
         .max_depth(depth)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
+        .into_iter();
+    while let Some(walk_entry) = walker.next() {
+        let Ok(walk_entry) = walk_entry else {
+            continue;
+        };
         if entries.len() >= cap {
             break;
         }
-        if !walk_entry.file_type().is_file() {
-            continue;
-        }
         let relative = walk_entry
             .path()
             .strip_prefix(root)
             .unwrap_or(walk_entry.path())
             .to_string_lossy()
             .replace('\\', "/");
+        if opts.safe_links
+            && walk_entry.path_is_symlink()
+            && !is_safe_local_link(&walk_entry, &relative)
+        {
+            if walk_entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            continue;
+        }
+        if !walk_entry.file_type().is_file() {
+            continue;
+        }
         if relative.is_empty() {
             continue;
         }
@@ -118,7 +141,7 @@ pub fn scan_local_tree(root: &str, opts: &ScanOptions) -> Vec<LocalEntry> {
 
         let meta = walk_entry.metadata().ok();
         let size = meta.as_ref().map(|m| m.len()).unwrap_or(0);
-        let mtime = meta.and_then(|m| {
+        let mtime = meta.as_ref().and_then(|m| {
             m.modified().ok().map(|t| {
                 let dt: chrono::DateTime<chrono::Utc> = t.into();
                 dt.format("%Y-%m-%dT%H:%M:%S").to_string()
@@ -126,7 +149,10 @@ pub fn scan_local_tree(root: &str, opts: &ScanOptions) -> Vec<LocalEntry> {
         });
 
         let sha256 = if opts.compute_checksum {
-            compute_sha256(walk_entry.path()).ok()
+            match (&opts.checksum_cache, &meta) {
+                (Some(cache), Some(meta)) => cache.checksum(walk_entry.path(), meta, "sha256").ok(),
+                _ => compute_sha256(walk_entry.path()).ok(),
+            }
         } else {
             None
         };
@@ -141,6 +167,65 @@ pub fn scan_local_tree(root: &str, opts: &ScanOptions) -> Vec<LocalEntry> {
     entries
 }
 
+/// Collect the symlinks under `root` without following them, for `--links`.
+/// Excludes, `files_from` and `safe_links` apply as for regular files.
+pub fn scan_local_links(root: &str, opts: &ScanOptions) -> Vec<LinkEntry> {
+    let matchers = compile_matchers(&opts.exclude_patterns);
+    let cap = opts.max_entries.unwrap_or(MAX_SCAN_ENTRIES);
+    let depth = opts.max_depth.unwrap_or(DEFAULT_SCAN_DEPTH);
+
+    let mut links = Vec::new();
+    for walk_entry in walkdir::WalkDir::new(root)
+        .follow_links(false)
+        .max_depth(depth)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        if links.len() >= cap {
+            break;
+        }
+        if !walk_entry.path_is_symlink() {
+            continue;
+        }
+        let relative = walk_entry
+            .path()
+            .strip_prefix(root)
+            .unwrap_or(walk_entry.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        let fname = walk_entry.file_name().to_string_lossy().into_owned();
+        if relative.is_empty() || opts.skip_filenames.iter().any(|n| n == &fname) {
+            continue;
+        }
+        if !matchers.is_empty() && matches_any(&matchers, &relative, &fname) {
+            continue;
+        }
+        if let Some(ref set) = opts.files_from {
+            if !set.contains(relative.as_str()) {
+                continue;
+            }
+        }
+        let Ok(target) = std::fs::read_link(walk_entry.path()) else {
+            continue;
+        };
+        let target = target.to_string_lossy().replace('\\', "/");
+        if opts.safe_links && !is_safe_link(&relative, &target) {
+            continue;
+        }
+        links.push(LinkEntry {
+            rel_path: relative,
+            target,
+        });
+    }
+    links
+}
+
+fn is_safe_local_link(walk_entry: &walkdir::DirEntry, relative: &str) -> bool {
+    std::fs::read_link(walk_entry.path())
+        .map(|target| is_safe_link(relative, &target.to_string_lossy()))
+        .unwrap_or(false)
+}
+
 /// Recursively list the remote tree rooted at `remote_root`. Uses the
 /// C1/C2-safe canonicalization: relative paths are built from the accumulated
 /// `rel_prefix` + `entry.name`, never by stripping the provider-returned
@@ -416,6 +501,51 @@ mod tests {
         );
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn scan_local_tree_skips_or_follows_symlinks() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path();
+        let outside = tempdir().unwrap();
+        fs::write(root.join("real.txt"), b"x").unwrap();
+        fs::write(outside.path().join("secret.txt"), b"y").unwrap();
+        std::os::unix::fs::symlink("real.txt", root.join("inside")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.join("escape")).unwrap();
+        let root_str = root.to_str().unwrap();
+        let paths = |opts: &ScanOptions| {
+            let mut paths: Vec<String> = scan_local_tree(root_str, opts)
+                .into_iter()
+                .map(|e| e.rel_path)
+                .collect();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(paths(&ScanOptions::default()), vec!["real.txt"]);
+        let follow = ScanOptions {
+            copy_links: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            paths(&follow),
+            vec!["escape/secret.txt", "inside", "real.txt"]
+        );
+        let safe = ScanOptions {
+            copy_links: true,
+            safe_links: true,
+            ..Default::default()
+        };
+        assert_eq!(paths(&safe), vec!["inside", "real.txt"]);
+
+        let mut links = scan_local_links(root_str, &ScanOptions::default());
+        links.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[1].rel_path, "inside");
+        assert_eq!(links[1].target, "real.txt");
+        let safe_links = scan_local_links(root_str, &safe);
+        assert_eq!(safe_links.len(), 1);
+    }
+
     #[test]
     fn scan_local_tree_respects_files_from_filter() {
         let tmp = tempdir().unwrap();
@@ -145,6 +145,21 @@ impl AeroIgnore {
         ignored
     }
 
+    /// Check a file path, including rules that match one of its parent
+    /// directories: `node_modules/` ignores everything below it, and a file
+    /// under an ignored directory cannot be re-included with `!`.
+    pub fn is_path_ignored(&self, relative_path: &str) -> bool {
+        let normalized = relative_path.replace('\\', "/");
+        let mut parent = normalized.as_str();
+        while let Some((dir, _)) = parent.rsplit_once('/') {
+            if self.is_ignored(dir, true) {
+                return true;
+            }
+            parent = dir;
+        }
+        self.is_ignored(&normalized, false)
+    }
+
     /// Check whether a path should be excluded, considering both
     /// .aeroignore rules AND config exclude_patterns.
     /// .aeroignore `!` negation overrides config patterns.
@@ -220,6 +235,16 @@ mod tests {
         assert!(!ignore.is_ignored("build", false));
     }
 
+    #[test]
+    fn test_path_under_ignored_dir() {
+        let ignore = AeroIgnore::parse("node_modules/\nbuild/\n!build/keep.txt").unwrap();
+
+        assert!(ignore.is_path_ignored("node_modules/pkg/index.js"));
+        assert!(ignore.is_path_ignored("app/node_modules/x.js"));
+        assert!(ignore.is_path_ignored("build/keep.txt"));
+        assert!(!ignore.is_path_ignored("src/build.rs"));
+    }
+
     #[test]
     fn test_comments_and_empty() {
         let ignore = AeroIgnore::parse("# comment\n\n  # another\n*.tmp").unwrap();
@@ -0,0 +1,1101 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+//! Declarative sync job files (`aeroftp-cli sync --job jobs/photos.toml`).
+//!
+//! A job file describes one sync in TOML or YAML (picked by extension):
+//! endpoints, filters, conflict policy, versioning, hooks, bandwidth and
+//! safety limits. The CLI turns it into the equivalent `sync` flags, so a job
+//! file behaves exactly like the command line it replaces and flags given on
+//! the command line still win. The GUI exports AeroSync configurations to the
+//! same format, which makes them easy to keep under version control.
+//!
+//! Loading reports every problem it finds with the line it comes from:
+//! syntax and schema errors use the parser's position, semantic errors (an
+//! unknown conflict mode, a bad duration) the line of the offending key.
+
+use crate::sync::{CompareDirection, SyncProfile};
+use crate::sync_core::{ChmodRules, CollisionPolicy};
+use crate::sync_hooks::{NotifyOn, NotifySink};
+use crate::sync_planner::{parse_max_duration, parse_stop_at, TransferOrder};
+use crate::sync_versioning::VersioningStrategy;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Conflict modes accepted by `sync --conflict-mode`.
+const CONFLICT_MODES: &[&str] = &[
+    "newer", "newest", "older", "oldest", "larger", "largest", "smaller", "smallest", "rename",
+    "skip", "merge",
+];
+
+/// On-disk syntax of a job file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobFileFormat {
+    Toml,
+    Yaml,
+}
+
+impl JobFileFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Format implied by the file extension (`.toml`, `.yaml`, `.yml`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::parse)
+    }
+}
+
+/// A declarative sync job. Every section except `endpoints` is optional.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyncJobFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub endpoints: JobEndpoints,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub filters: JobFilters,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub conflicts: JobConflicts,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub versioning: JobVersioning,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub hooks: JobHooks,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub bandwidth: JobBandwidth,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub limits: JobLimits,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub transfer: JobTransfer,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub metadata: JobMetadata,
+}
+
+/// `[endpoints]`: what is synced with what.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobEndpoints {
+    /// Server URL, when not using a saved profile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Saved profile name or ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Local directory; relative paths are resolved against the job file.
+    pub local: String,
+    pub remote: String,
+    /// `upload`, `download` or `both` (the `sync` default).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<String>,
+}
+
+/// `[filters]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobFilters {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude_from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_from: Option<String>,
+    /// Honour `.aeroignore` in the local root.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub aeroignore: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_age: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<u32>,
+}
+
+/// `[conflicts]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobConflicts {
+    /// `--conflict-mode` for bidirectional runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_collision: Option<String>,
+}
+
+/// `[versioning]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobVersioning {
+    /// Remote versioning strategy (`trash_can:30`, `simple:5`, `staggered`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_suffix: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub suffix_keep_extension: bool,
+}
+
+/// `[hooks]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobHooks {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notify: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_on: Option<String>,
+    /// Seconds a single hook may run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// `[bandwidth]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobBandwidth {
+    /// Fixed rate limit (`--limit-rate`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<String>,
+    /// Time-based schedule (`--bwlimit`), e.g. `08:00,512k 18:00,off`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel: Option<usize>,
+}
+
+/// `[limits]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delete: Option<MaxDelete>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_transfer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_at: Option<String>,
+}
+
+/// `max_delete = 50` or `max_delete = "10%"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MaxDelete {
+    Count(u64),
+    Text(String),
+}
+
+impl fmt::Display for MaxDelete {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Count(n) => write!(f, "{}", n),
+            Self::Text(s) => f.write_str(s),
+        }
+    }
+}
+
+/// `[transfer]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobTransfer {
+    /// Delete orphaned files on the destination.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub delete: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub track_renames: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub skip_matching: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub priority: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries_sleep: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub partial: bool,
+}
+
+/// `[metadata]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobMetadata {
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub perms: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub owner: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub xattrs: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chmod: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub links: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub copy_links: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub safe_links: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub hard_links: bool,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// A problem found while loading a job file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobFileIssue {
+    /// 1-based source line, when it could be located.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for JobFileIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// One `sync` flag produced from a job file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobFlag {
+    /// Long flag name, including the leading dashes.
+    pub name: &'static str,
+    /// Flag value; `None` for boolean switches.
+    pub value: Option<String>,
+    /// Repeatable flags add to the command line instead of yielding to it.
+    pub repeatable: bool,
+}
+
+impl JobFlag {
+    fn switch(name: &'static str) -> Self {
+        Self {
+            name,
+            value: None,
+            repeatable: false,
+        }
+    }
+
+    fn value(name: &'static str, value: impl ToString) -> Self {
+        Self {
+            name,
+            value: Some(value.to_string()),
+            repeatable: false,
+        }
+    }
+
+    fn repeated(name: &'static str, value: &str) -> Self {
+        Self {
+            name,
+            value: Some(value.to_string()),
+            repeatable: true,
+        }
+    }
+}
+
+impl SyncJobFile {
+    /// Read, parse and validate a job file. Relative paths inside it are
+    /// resolved against the file's directory and `~/` against the home dir.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let format = JobFileFormat::from_path(path).ok_or_else(|| {
+            format!(
+                "Cannot tell the format of job file '{}': use a .toml, .yaml or .yml extension",
+                path.display()
+            )
+        })?;
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read job file '{}': {}", path.display(), e))?;
+        let mut job = Self::parse(&source, format).map_err(|issues| {
+            let mut msg = format!("Invalid sync job file '{}':", path.display());
+            for issue in &issues {
+                msg.push_str("\n  ");
+                msg.push_str(&issue.to_string());
+            }
+            msg
+        })?;
+        let base = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        job.resolve_paths(base);
+        Ok(job)
+    }
+
+    /// Parse and validate job file content. All problems are reported, not
+    /// just the first one.
+    pub fn parse(source: &str, format: JobFileFormat) -> Result<Self, Vec<JobFileIssue>> {
+        let job: Self = match format {
+            JobFileFormat::Toml => toml::from_str(source).map_err(|e| {
+                vec![JobFileIssue {
+                    line: e
+                        .span()
+                        .map(|span| source[..span.start].matches('\n').count() + 1),
+                    message: e.message().trim().to_string(),
+                }]
+            })?,
+            JobFileFormat::Yaml => serde_yaml_ng::from_str(source).map_err(|e| {
+                let mut message = e.to_string();
+                // serde_yaml_ng appends " at line N column M"; we print the line ourselves.
+                if let Some(idx) = message.find(" at line ") {
+                    message.truncate(idx);
+                }
+                vec![JobFileIssue {
+                    line: e.location().map(|loc| loc.line()),
+                    message,
+                }]
+            })?,
+        };
+        let issues = job.validate(source, format);
+        if issues.is_empty() {
+            Ok(job)
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Semantic checks that the schema cannot express. `source` is only used
+    /// to locate the offending keys.
+    fn validate(&self, source: &str, format: JobFileFormat) -> Vec<JobFileIssue> {
+        let mut issues = Vec::new();
+        let mut check = |section: &str, key: &str, result: Result<(), String>| {
+            if let Err(message) = result {
+                issues.push(JobFileIssue {
+                    line: key_line(source, format, Some(section), key),
+                    message: format!("{}.{}: {}", section, key, message),
+                });
+            }
+        };
+
+        let ep = &self.endpoints;
+        if ep.url.is_some() && ep.profile.is_some() {
+            check(
+                "endpoints",
+                "profile",
+                Err("set either url or profile, not both".to_string()),
+            );
+        }
+        check("endpoints", "local", non_empty(&ep.local));
+        check("endpoints", "remote", non_empty(&ep.remote));
+        if let Some(direction) = &ep.direction {
+            check(
+                "endpoints",
+                "direction",
+                one_of(direction, &["upload", "download", "both"]),
+            );
+        }
+
+        let f = &self.filters;
+        check("filters", "exclude", globs(&f.exclude));
+        check("filters", "include", globs(&f.include));
+        for (key, value) in [("min_size", &f.min_size), ("max_size", &f.max_size)] {
+            if let Some(value) = value {
+                check("filters", key, size(value));
+            }
+        }
+        for (key, value) in [("min_age", &f.min_age), ("max_age", &f.max_age)] {
+            if let Some(value) = value {
+                check("filters", key, age(value));
+            }
+        }
+
+        if let Some(mode) = &self.conflicts.mode {
+            check("conflicts", "mode", one_of(mode, CONFLICT_MODES));
+        }
+        if let Some(policy) = &self.conflicts.name_collision {
+            check(
+                "conflicts",
+                "name_collision",
+                CollisionPolicy::parse(policy).map(drop),
+            );
+        }
+
+        if let Some(strategy) = &self.versioning.strategy {
+            let result = match VersioningStrategy::from_name(strategy) {
+                Some(_) => Ok(()),
+                None => Err(format!(
+                    "invalid strategy '{}': use disabled, trash_can[:days], simple[:copies] or staggered",
+                    strategy
+                )),
+            };
+            check("versioning", "strategy", result);
+        }
+
+        for sink in &self.hooks.notify {
+            check("hooks", "notify", NotifySink::parse(sink).map(drop));
+        }
+        if let Some(on) = &self.hooks.notify_on {
+            check("hooks", "notify_on", NotifyOn::parse(on).map(drop));
+        }
+        if self.hooks.timeout == Some(0) {
+            check(
+                "hooks",
+                "timeout",
+                Err("must be at least 1 second".to_string()),
+            );
+        }
+
+        if let Some(limit) = &self.bandwidth.limit {
+            check("bandwidth", "limit", size(limit));
+        }
+        if let Some(schedule) = &self.bandwidth.schedule {
+            check("bandwidth", "schedule", bandwidth_schedule(schedule));
+        }
+        if let Some(parallel) = self.bandwidth.parallel {
+            if !(1..=32).contains(&parallel) {
+                check(
+                    "bandwidth",
+                    "parallel",
+                    Err(format!("{} is out of range 1-32", parallel)),
+                );
+            }
+        }
+
+        let l = &self.limits;
+        if let Some(max_delete) = &l.max_delete {
+            check("limits", "max_delete", delete_limit(max_delete));
+        }
+        if let Some(max_transfer) = &l.max_transfer {
+            check("limits", "max_transfer", size(max_transfer));
+        }
+        if let Some(duration) = &l.max_duration {
+            check(
+                "limits",
+                "max_duration",
+                parse_max_duration(duration).map(drop),
+            );
+        }
+        if let Some(stop_at) = &l.stop_at {
+            check(
+                "limits",
+                "stop_at",
+                parse_stop_at(stop_at, chrono::Local::now()).map(drop),
+            );
+        }
+
+        let t = &self.transfer;
+        if let Some(order) = &t.order_by {
+            check(
+                "transfer",
+                "order_by",
+                TransferOrder::parse(order).map(drop),
+            );
+        }
+        check("transfer", "priority", globs(&t.priority));
+        if let Some(sleep) = &t.retries_sleep {
+            check("transfer", "retries_sleep", retry_sleep(sleep));
+        }
+
+        let m = &self.metadata;
+        if let Some(chmod) = &m.chmod {
+            check("metadata", "chmod", ChmodRules::parse(chmod).map(drop));
+        }
+        if m.links && m.copy_links {
+            check(
+                "metadata",
+                "copy_links",
+                Err("links and copy_links are mutually exclusive".to_string()),
+            );
+        }
+
+        issues
+    }
+
+    /// Expand `~/` and make relative local paths absolute against `base`.
+    fn resolve_paths(&mut self, base: &Path) {
+        let resolve = |value: &mut String| {
+            *value = resolve_path(value, base).to_string_lossy().into_owned();
+        };
+        resolve(&mut self.endpoints.local);
+        for value in [
+            &mut self.filters.exclude_from,
+            &mut self.filters.include_from,
+            &mut self.filters.files_from,
+            &mut self.versioning.backup_dir,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            resolve(value);
+        }
+        for hook in self.hooks.pre.iter_mut().chain(self.hooks.post.iter_mut()) {
+            // Hooks are shell commands: only a leading `./` script is rebased.
+            if let Some(rest) = hook.strip_prefix("./") {
+                *hook = base.join(rest).to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    /// The `sync` flags equivalent to this job, endpoints excluded.
+    pub fn cli_flags(&self) -> Vec<JobFlag> {
+        let mut flags = Vec::new();
+        let value = |flags: &mut Vec<JobFlag>, name, v: &Option<String>| {
+            if let Some(v) = v {
+                flags.push(JobFlag::value(name, v));
+            }
+        };
+        let switch = |flags: &mut Vec<JobFlag>, name, on: bool| {
+            if on {
+                flags.push(JobFlag::switch(name));
+            }
+        };
+        let repeated = |flags: &mut Vec<JobFlag>, name, values: &[String]| {
+            flags.extend(values.iter().map(|v| JobFlag::repeated(name, v)));
+        };
+
+        value(&mut flags, "--direction", &self.endpoints.direction);
+
+        let f = &self.filters;
+        repeated(&mut flags, "--exclude", &f.exclude);
+        repeated(&mut flags, "--include", &f.include);
+        value(&mut flags, "--exclude-from", &f.exclude_from);
+        value(&mut flags, "--include-from", &f.include_from);
+        value(&mut flags, "--files-from", &f.files_from);
+        switch(&mut flags, "--aeroignore", f.aeroignore);
+        value(&mut flags, "--min-size", &f.min_size);
+        value(&mut flags, "--max-size", &f.max_size);
+        value(&mut flags, "--min-age", &f.min_age);
+        value(&mut flags, "--max-age", &f.max_age);
+        if let Some(depth) = f.max_depth {
+            flags.push(JobFlag::value("--max-depth", depth));
+        }
+
+        value(&mut flags, "--conflict-mode", &self.conflicts.mode);
+        value(
+            &mut flags,
+            "--name-collision",
+            &self.conflicts.name_collision,
+        );
+
+        let v = &self.versioning;
+        value(&mut flags, "--remote-versioning", &v.strategy);
+        value(&mut flags, "--backup-dir", &v.backup_dir);
+        value(&mut flags, "--backup-suffix", &v.backup_suffix);
+        switch(
+            &mut flags,
+            "--suffix-keep-extension",
+            v.suffix_keep_extension,
+        );
+
+        let h = &self.hooks;
+        repeated(&mut flags, "--pre-hook", &h.pre);
+        repeated(&mut flags, "--post-hook", &h.post);
+        repeated(&mut flags, "--notify", &h.notify);
+        value(&mut flags, "--notify-on", &h.notify_on);
+        if let Some(timeout) = h.timeout {
+            flags.push(JobFlag::value("--hook-timeout", timeout));
+        }
+
+        let b = &self.bandwidth;
+        value(&mut flags, "--limit-rate", &b.limit);
+        value(&mut flags, "--bwlimit", &b.schedule);
+        if let Some(parallel) = b.parallel {
+            flags.push(JobFlag::value("--parallel", parallel));
+        }
+
+        let l = &self.limits;
+        if let Some(max_delete) = &l.max_delete {
+            flags.push(JobFlag::value("--max-delete", max_delete));
+        }
+        value(&mut flags, "--max-transfer", &l.max_transfer);
+        value(&mut flags, "--max-duration", &l.max_duration);
+        value(&mut flags, "--stop-at", &l.stop_at);
+
+        let t = &self.transfer;
+        switch(&mut flags, "--delete", t.delete);
+        switch(&mut flags, "--track-renames", t.track_renames);
+        switch(&mut flags, "--skip-matching", t.skip_matching);
+        value(&mut flags, "--order-by", &t.order_by);
+        repeated(&mut flags, "--priority", &t.priority);
+        if let Some(retries) = t.retries {
+            flags.push(JobFlag::value("--retries", retries));
+        }
+        value(&mut flags, "--retries-sleep", &t.retries_sleep);
+        switch(&mut flags, "--partial", t.partial);
+
+        let m = &self.metadata;
+        switch(&mut flags, "--perms", m.perms);
+        switch(&mut flags, "--owner", m.owner);
+        switch(&mut flags, "--xattrs", m.xattrs);
+        value(&mut flags, "--chmod", &m.chmod);
+        switch(&mut flags, "--links", m.links);
+        switch(&mut flags, "--copy-links", m.copy_links);
+        switch(&mut flags, "--safe-links", m.safe_links);
+        switch(&mut flags, "--hard-links", m.hard_links);
+
+        flags
+    }
+
+    /// Build a job from an AeroSync profile, as the GUI export does.
+    /// `conflict_strategy` is the panel setting kept next to the profile;
+    /// `ask` has no job equivalent and is left out. The panel's versioning
+    /// strategy is not taken: it archives *local* files before a download
+    /// changes them, while `[versioning]` only covers the remote side.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_profile(
+        name: &str,
+        description: &str,
+        profile: &SyncProfile,
+        server_profile: Option<&str>,
+        local_path: &str,
+        remote_path: &str,
+        exclude_patterns: &[String],
+        conflict_strategy: Option<&str>,
+    ) -> Self {
+        let (retries, retries_sleep) = crate::sync::retry_overrides(&profile.retry_policy);
+        let conflict_mode = conflict_strategy.filter(|mode| CONFLICT_MODES.contains(mode));
+        Self {
+            name: Some(name.to_string()).filter(|s| !s.is_empty()),
+            description: Some(description.to_string()).filter(|s| !s.is_empty()),
+            endpoints: JobEndpoints {
+                url: None,
+                profile: server_profile.filter(|s| !s.is_empty()).map(str::to_string),
+                local: home_relative(local_path),
+                remote: remote_path.to_string(),
+                direction: Some(
+                    match profile.direction {
+                        CompareDirection::LocalToRemote => "upload",
+                        CompareDirection::RemoteToLocal => "download",
+                        CompareDirection::Bidirectional => "both",
+                    }
+                    .to_string(),
+                ),
+            },
+            filters: JobFilters {
+                exclude: exclude_patterns.to_vec(),
+                ..Default::default()
+            },
+            conflicts: JobConflicts {
+                mode: conflict_mode.map(str::to_string),
+                ..Default::default()
+            },
+            bandwidth: JobBandwidth {
+                parallel: Some(profile.parallel_streams.max(1) as usize),
+                ..Default::default()
+            },
+            transfer: JobTransfer {
+                delete: profile.delete_orphans,
+                retries,
+                retries_sleep,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Serialize with a short header pointing at the schema docs.
+    pub fn render(&self, format: JobFileFormat) -> Result<String, String> {
+        let body = match format {
+            JobFileFormat::Toml => toml::to_string_pretty(self).map_err(|e| e.to_string())?,
+            JobFileFormat::Yaml => serde_yaml_ng::to_string(self).map_err(|e| e.to_string())?,
+        };
+        Ok(format!(
+            "# AeroFTP sync job: aeroftp-cli sync --job <this file>\n\
+             # Schema: docs/CLI-GUIDE.md, \"Job files\"\n\n{}",
+            body
+        ))
+    }
+}
+
+/// 1-based line of `key` within `section` (top level when `None`). Falls back
+/// to the section header, then gives up: the message still names the key.
+fn key_line(
+    source: &str,
+    format: JobFileFormat,
+    section: Option<&str>,
+    key: &str,
+) -> Option<usize> {
+    let mut current: Option<&str> = None;
+    let mut section_line = None;
+    for (idx, raw) in source.lines().enumerate() {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let (name, header) = match format {
+            JobFileFormat::Toml => match trimmed.strip_prefix('[') {
+                Some(header) => (header.trim_matches(['[', ']', ' ']), true),
+                None => match trimmed.split_once('=') {
+                    Some((name, _)) => (name.trim().trim_matches('"'), false),
+                    None => continue,
+                },
+            },
+            JobFileFormat::Yaml => {
+                let Some((name, rest)) = trimmed.split_once(':') else {
+                    continue;
+                };
+                let name = name.trim().trim_matches(['"', '\'']);
+                if raw.starts_with([' ', '\t']) {
+                    (name, false)
+                } else {
+                    // Top-level key: a mapping header when nothing follows.
+                    let rest = rest.trim();
+                    if !(rest.is_empty() || rest.starts_with('#')) {
+                        current = None;
+                    }
+                    (name, rest.is_empty() || rest.starts_with('#'))
+                }
+            }
+        };
+        if header {
+            current = Some(name);
+            if section == Some(name) {
+                section_line = Some(idx + 1);
+            }
+            continue;
+        }
+        if current == section && name == key {
+            return Some(idx + 1);
+        }
+        // TOML dotted keys: `filters.min_size = "1k"` at the top level.
+        if let (None, Some(section)) = (current, section) {
+            if name.strip_prefix(section).and_then(|s| s.strip_prefix('.')) == Some(key) {
+                return Some(idx + 1);
+            }
+        }
+    }
+    section_line
+}
+
+fn resolve_path(value: &str, base: &Path) -> PathBuf {
+    if let Some(rest) = value.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    }
+    let path = Path::new(value.strip_prefix("./").unwrap_or(value));
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base.join(path)
+    }
+}
+
+/// Write paths under the home directory as `~/...` so exported jobs work for
+/// the same user on another machine.
+fn home_relative(path: &str) -> String {
+    if let Some(home) = dirs::home_dir() {
+        if let Ok(rest) = Path::new(path).strip_prefix(&home) {
+            if !rest.as_os_str().is_empty() {
+                return format!("~/{}", rest.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    path.to_string()
+}
+
+fn non_empty(value: &str) -> Result<(), String> {
+    if value.trim().is_empty() {
+        Err("must not be empty".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn one_of(value: &str, allowed: &[&str]) -> Result<(), String> {
+    if allowed.contains(&value) {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid value '{}': expected one of {}",
+            value,
+            allowed.join(", ")
+        ))
+    }
+}
+
+fn globs(patterns: &[String]) -> Result<(), String> {
+    for pattern in patterns {
+        globset::Glob::new(pattern).map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?;
+    }
+    Ok(())
+}
+
+/// Same grammar as the CLI size filters: a number with an optional k/M/G suffix.
+fn size(value: &str) -> Result<(), String> {
+    let s = value.trim();
+    let number = s.strip_suffix(['k', 'K', 'm', 'M', 'g', 'G']).unwrap_or(s);
+    match number.trim().parse::<f64>() {
+        Ok(n) if n >= 0.0 => Ok(()),
+        _ => Err(format!("invalid size '{}': use e.g. 500k, 10M, 2G", value)),
+    }
+}
+
+/// Same grammar as the CLI age filters: a number with an s/m/h/d/w/M/y suffix.
+fn age(value: &str) -> Result<(), String> {
+    let s = value.trim();
+    let number = s
+        .strip_suffix(['s', 'm', 'h', 'd', 'w', 'M', 'y'])
+        .unwrap_or(s);
+    match number.trim().parse::<f64>() {
+        Ok(n) if n >= 0.0 => Ok(()),
+        _ => Err(format!("invalid age '{}': use e.g. 12h, 7d, 2w", value)),
+    }
+}
+
+fn retry_sleep(value: &str) -> Result<(), String> {
+    let s = value.trim();
+    let number = s
+        .strip_suffix("ms")
+        .or_else(|| s.strip_suffix(['s', 'm']))
+        .unwrap_or(s);
+    number
+        .parse::<u64>()
+        .map(drop)
+        .map_err(|_| format!("invalid delay '{}': use e.g. 500ms, 5s, 1m", value))
+}
+
+/// A single rate, or space-separated `HH:MM,rate` entries (`rate` may be `off`).
+fn bandwidth_schedule(value: &str) -> Result<(), String> {
+    if !value.contains(',') {
+        return size(value);
+    }
+    for entry in value.split_whitespace() {
+        let valid = entry.split_once(',').is_some_and(|(time, rate)| {
+            let time_ok = time.split_once(':').is_some_and(|(h, m)| {
+                h.parse::<u32>().is_ok_and(|h| h < 24) && m.parse::<u32>().is_ok_and(|m| m < 60)
+            });
+            time_ok && (rate == "off" || size(rate).is_ok())
+        });
+        if !valid {
+            return Err(format!(
+                "invalid schedule entry '{}': expected HH:MM,RATE (e.g. 08:00,512k or 18:00,off)",
+                entry
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn delete_limit(value: &MaxDelete) -> Result<(), String> {
+    match value {
+        MaxDelete::Count(_) => Ok(()),
+        MaxDelete::Text(text) => {
+            let number = text.trim().strip_suffix('%').unwrap_or(text.trim());
+            match number.parse::<f64>() {
+                Ok(n) if n >= 0.0 => Ok(()),
+                _ => Err(format!(
+                    "invalid limit '{}': use a file count (50) or a percentage (\"10%\")",
+                    text
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHOTOS_TOML: &str = r#"
+name = "photos"
+
+[endpoints]
+profile = "nas"
+local = "Pictures"
+remote = "/backup/photos"
+direction = "upload"
+
+[filters]
+exclude = ["*.tmp", ".DS_Store"]
+aeroignore = true
+max_size = "4G"
+
+[conflicts]
+mode = "newer"
+
+[versioning]
+strategy = "trash_can:30"
+
+[hooks]
+notify = ["ntfy://ntfy.sh/photos"]
+
+[bandwidth]
+schedule = "08:00,512k 18:00,off"
+
+[limits]
+max_delete = "10%"
+stop_at = "06:00"
+
+[transfer]
+delete = true
+"#;
+
+    fn flag_values(job: &SyncJobFile) -> Vec<String> {
+        job.cli_flags()
+            .into_iter()
+            .flat_map(|f| std::iter::once(f.name.to_string()).chain(f.value))
+            .collect()
+    }
+
+    #[test]
+    fn toml_job_maps_to_sync_flags() {
+        let job = SyncJobFile::parse(PHOTOS_TOML, JobFileFormat::Toml).unwrap();
+        assert_eq!(job.endpoints.profile.as_deref(), Some("nas"));
+        let flags = flag_values(&job);
+        for expected in [
+            "--direction",
+            "upload",
+            "--exclude",
+            ".DS_Store",
+            "--aeroignore",
+            "--max-size",
+            "--remote-versioning",
+            "trash_can:30",
+            "--notify",
+            "--bwlimit",
+            "--max-delete",
+            "10%",
+            "--stop-at",
+            "--delete",
+        ] {
+            assert!(flags.iter().any(|f| f == expected), "missing {expected}");
+        }
+        let exclude = job
+            .cli_flags()
+            .into_iter()
+            .find(|f| f.name == "--exclude")
+            .unwrap();
+        assert!(exclude.repeatable);
+    }
+
+    #[test]
+    fn yaml_job_parses_to_the_same_job() {
+        let yaml = "\
+name: photos
+endpoints:
+  profile: nas
+  local: Pictures
+  remote: /backup/photos
+  direction: upload
+filters:
+  exclude: ['*.tmp', .DS_Store]
+  aeroignore: true
+  max_size: 4G
+conflicts:
+  mode: newer
+versioning:
+  strategy: trash_can:30
+hooks:
+  notify: ['ntfy://ntfy.sh/photos']
+bandwidth:
+  schedule: 08:00,512k 18:00,off
+limits:
+  max_delete: 10%
+  stop_at: '06:00'
+transfer:
+  delete: true
+";
+        let from_yaml = SyncJobFile::parse(yaml, JobFileFormat::Yaml).unwrap();
+        let from_toml = SyncJobFile::parse(PHOTOS_TOML, JobFileFormat::Toml).unwrap();
+        assert_eq!(from_yaml, from_toml);
+    }
+
+    #[test]
+    fn schema_errors_carry_the_line() {
+        let source = "[endpoints]\nlocal = \"a\"\nremote = \"/b\"\n\n[filters]\nexclud = [\"x\"]\n";
+        let issues = SyncJobFile::parse(source, JobFileFormat::Toml).unwrap_err();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, Some(6));
+        assert!(issues[0].message.contains("exclud"));
+
+        let yaml = "endpoints:\n  local: a\n  remote: /b\nlimits:\n  max_delet: 3\n";
+        let issues = SyncJobFile::parse(yaml, JobFileFormat::Yaml).unwrap_err();
+        assert_eq!(issues[0].line, Some(5));
+    }
+
+    #[test]
+    fn semantic_errors_are_all_reported_with_lines() {
+        let source = "\
+[endpoints]
+local = \"a\"
+remote = \"/b\"
+direction = \"sideways\"
+
+[conflicts]
+mode = \"loudest\"
+
+[limits]
+max_duration = \"soon\"
+";
+        let issues = SyncJobFile::parse(source, JobFileFormat::Toml).unwrap_err();
+        let lines: Vec<_> = issues.iter().map(|i| i.line).collect();
+        assert_eq!(lines, vec![Some(4), Some(7), Some(10)]);
+        assert!(issues[1].to_string().starts_with("line 7: conflicts.mode:"));
+
+        let yaml = "endpoints:\n  local: a\n  remote: /b\nbandwidth:\n  schedule: 25:00,1M\n";
+        let issues = SyncJobFile::parse(yaml, JobFileFormat::Yaml).unwrap_err();
+        assert_eq!(issues[0].line, Some(5));
+    }
+
+    #[test]
+    fn exported_profile_round_trips() {
+        let mut profile = SyncProfile::mirror();
+        profile.retry_policy.max_retries = 5;
+        let job = SyncJobFile::from_profile(
+            "Site",
+            "",
+            &profile,
+            Some("web"),
+            "/srv/site",
+            "/var/www",
+            &["*.log".to_string()],
+            Some("larger"),
+        );
+        for format in [JobFileFormat::Toml, JobFileFormat::Yaml] {
+            let text = job.render(format).unwrap();
+            assert!(text.starts_with("# AeroFTP sync job"));
+            assert_eq!(SyncJobFile::parse(&text, format).unwrap(), job);
+        }
+        let flags = flag_values(&job);
+        assert!(flags.windows(2).any(|w| w == ["--direction", "upload"]));
+        assert!(flags.windows(2).any(|w| w == ["--retries", "5"]));
+        assert!(flags.iter().any(|f| f == "--delete"));
+        assert!(flags.windows(2).any(|w| w == ["--conflict-mode", "larger"]));
+        // Panel versioning is local-side and never becomes remote versioning.
+        assert_eq!(job.versioning, JobVersioning::default());
+        assert!(!flags.iter().any(|f| f == "--remote-versioning"));
+
+        // GUI-only settings have no job equivalent.
+        let job = SyncJobFile::from_profile(
+            "Site",
+            "",
+            &profile,
+            None,
+            "/srv/site",
+            "/var/www",
+            &[],
+            Some("ask"),
+        );
+        assert_eq!(job.conflicts, JobConflicts::default());
+        assert_eq!(job.endpoints.profile, None);
+    }
+}
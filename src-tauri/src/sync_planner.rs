@@ -0,0 +1,614 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+//! Transfer ordering and deadline planning for sync runs.
+//!
+//! A sync transfers files in discovery order and runs until it is done. The
+//! planner reorders the queue (`--order-by`, `--priority`) and, when a
+//! deadline is set (`--max-duration`, `--stop-at`), only starts a transfer
+//! once the throughput estimate says it will finish in time. Transfers that
+//! would overrun are deferred: the caller stores them as pending entries in
+//! the sync journal, and the next run for the same path pair starts with them.
+//!
+//! The throughput estimate starts from the speed test history of the target
+//! host (the GUI speed test and `aeroftp speed` both record it) and switches
+//! to the rate measured during the run once enough data has moved.
+
+use crate::sync::{
+    CompareDirection, JournalEntryStatus, RetryPolicy, SyncJournal, SyncJournalEntry, VerifyPolicy,
+};
+use chrono::{DateTime, Local, NaiveTime, TimeZone};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Bytes that must have moved before the live rate replaces the history.
+const LIVE_SAMPLE_BYTES: u64 = 4 * 1024 * 1024;
+/// Seconds that must have passed before the live rate replaces the history.
+const LIVE_SAMPLE_SECS: f64 = 3.0;
+/// Estimates are stretched by this factor so a slow patch does not overrun.
+const SAFETY_FACTOR: f64 = 1.2;
+
+/// The order in which a sync starts its transfers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransferOrder {
+    /// The order the scan found the files in.
+    #[default]
+    Discovery,
+    SmallestFirst,
+    LargestFirst,
+    /// Most recently modified first.
+    NewestFirst,
+}
+
+impl TransferOrder {
+    pub const NAMES: &'static [&'static str] = &[
+        "discovery",
+        "smallest-first",
+        "largest-first",
+        "newest-first",
+    ];
+
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        match raw.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "discovery" => Ok(Self::Discovery),
+            "smallest-first" | "smallest" => Ok(Self::SmallestFirst),
+            "largest-first" | "largest" => Ok(Self::LargestFirst),
+            "newest-first" | "newest" => Ok(Self::NewestFirst),
+            _ => Err(format!(
+                "Invalid order '{}'. Expected one of: {}",
+                raw,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Discovery => "discovery",
+            Self::SmallestFirst => "smallest-first",
+            Self::LargestFirst => "largest-first",
+            Self::NewestFirst => "newest-first",
+        }
+    }
+
+    fn compare(self, a: &PlanItem<'_>, b: &PlanItem<'_>) -> Ordering {
+        match self {
+            Self::Discovery => Ordering::Equal,
+            Self::SmallestFirst => a.size.cmp(&b.size),
+            Self::LargestFirst => b.size.cmp(&a.size),
+            // Files without an mtime go last.
+            Self::NewestFirst => b.mtime.cmp(&a.mtime),
+        }
+    }
+}
+
+/// What the planner needs to know about a queued transfer.
+#[derive(Debug, Clone, Copy)]
+pub struct PlanItem<'a> {
+    /// Path relative to the sync root, `/`-separated.
+    pub path: &'a str,
+    pub size: u64,
+    /// Modification time in seconds since the epoch.
+    pub mtime: Option<i64>,
+}
+
+/// Orders the transfer queue of a sync run.
+///
+/// Transfers deferred by the previous run go first, then paths matching a
+/// priority pattern (earlier patterns first), then everything else. Within
+/// each group the [`TransferOrder`] applies; ties keep discovery order.
+#[derive(Debug, Default)]
+pub struct TransferPlanner {
+    order: TransferOrder,
+    priorities: Vec<globset::GlobMatcher>,
+    resumed: HashSet<String>,
+}
+
+impl TransferPlanner {
+    pub fn new(order: TransferOrder, priority_patterns: &[String]) -> Result<Self, String> {
+        let priorities = priority_patterns
+            .iter()
+            .map(|pattern| {
+                globset::Glob::new(pattern)
+                    .map(|glob| glob.compile_matcher())
+                    .map_err(|e| format!("Invalid --priority pattern '{}': {}", pattern, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            order,
+            priorities,
+            resumed: HashSet::new(),
+        })
+    }
+
+    /// Paths left pending by an earlier run, which are started first.
+    pub fn with_resumed(mut self, paths: impl IntoIterator<Item = String>) -> Self {
+        self.resumed.extend(paths);
+        self
+    }
+
+    fn rank(&self, path: &str) -> usize {
+        if self.resumed.contains(path) {
+            return 0;
+        }
+        // Patterns match the full relative path or, for `*.ext` style
+        // patterns, the file name alone.
+        let name = path.rsplit('/').next().unwrap_or(path);
+        self.priorities
+            .iter()
+            .position(|m| m.is_match(path) || m.is_match(name))
+            .map_or(self.priorities.len() + 1, |i| i + 1)
+    }
+
+    /// Sort `items` in place; the sort is stable.
+    pub fn sort<T>(&self, items: &mut [T], describe: impl Fn(&T) -> PlanItem<'_>) {
+        if self.order == TransferOrder::Discovery
+            && self.priorities.is_empty()
+            && self.resumed.is_empty()
+        {
+            return;
+        }
+        items.sort_by(|a, b| {
+            let (a, b) = (describe(a), describe(b));
+            self.rank(a.path)
+                .cmp(&self.rank(b.path))
+                .then_with(|| self.order.compare(&a, &b))
+        });
+    }
+}
+
+/// Parse a `--max-duration` value: `45s`, `90m`, `2h`, `1h30m` or plain seconds.
+pub fn parse_max_duration(raw: &str) -> Result<Duration, String> {
+    let s = raw.trim();
+    if s.is_empty() {
+        return Err("Empty duration".to_string());
+    }
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(format!("Invalid duration '{}': unknown unit '{}'", raw, c)),
+        };
+        let value: u64 = number
+            .parse()
+            .map_err(|_| format!("Invalid duration '{}'", raw))?;
+        total = total.saturating_add(value.saturating_mul(unit));
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!("Invalid duration '{}': missing unit", raw));
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Parse a `--stop-at` wall-clock time (`06:00`, `23:30:15`) into its next
+/// local occurrence after `now`.
+pub fn parse_stop_at(raw: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    let s = raw.trim();
+    let time = NaiveTime::parse_from_str(s, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M:%S"))
+        .map_err(|_| format!("Invalid --stop-at '{}': expected HH:MM", raw))?;
+    let mut day = now.date_naive();
+    for _ in 0..3 {
+        // `earliest` is None only inside a DST gap; try the next day then.
+        if let Some(at) = Local.from_local_datetime(&day.and_time(time)).earliest() {
+            if at > now {
+                return Ok(at);
+            }
+        }
+        day = day.succ_opt().ok_or("Date out of range")?;
+    }
+    Err(format!("Cannot resolve --stop-at '{}'", raw))
+}
+
+/// When a run must be done by.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    pub at: Instant,
+    /// The same moment on the wall clock, for messages.
+    pub wall: DateTime<Local>,
+}
+
+impl Deadline {
+    /// The earlier of `--max-duration` and `--stop-at`, or `None` if neither is set.
+    pub fn resolve(
+        max_duration: Option<&str>,
+        stop_at: Option<&str>,
+    ) -> Result<Option<Self>, String> {
+        let (now, wall_now) = (Instant::now(), Local::now());
+        let mut remaining: Option<Duration> = None;
+        if let Some(raw) = max_duration {
+            remaining = Some(parse_max_duration(raw)?);
+        }
+        if let Some(raw) = stop_at {
+            let until = (parse_stop_at(raw, wall_now)? - wall_now)
+                .to_std()
+                .unwrap_or_default();
+            remaining = Some(remaining.map_or(until, |r| r.min(until)));
+        }
+        Ok(remaining.map(|left| Self {
+            at: now + left,
+            wall: wall_now + chrono::Duration::from_std(left).unwrap_or_default(),
+        }))
+    }
+}
+
+/// Direction of a single transfer; the names match journal entry actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferKind {
+    Upload,
+    Download,
+}
+
+impl TransferKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Upload => "upload",
+            Self::Download => "download",
+        }
+    }
+}
+
+/// Known throughput of a host, in bytes per second.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Throughput {
+    pub upload_bps: Option<f64>,
+    pub download_bps: Option<f64>,
+}
+
+impl Throughput {
+    fn for_kind(&self, kind: TransferKind) -> Option<f64> {
+        match kind {
+            TransferKind::Upload => self.upload_bps,
+            TransferKind::Download => self.download_bps,
+        }
+    }
+}
+
+/// Median throughput of the recent speed tests against `host`, if any.
+pub fn history_throughput(host: &str) -> Option<Throughput> {
+    let path = crate::speedtest::history_db_path_detached()?;
+    if !path.exists() {
+        return None;
+    }
+    let conn = crate::speedtest::open_history_db(&path).ok()?;
+    let (upload_bps, download_bps) =
+        crate::speedtest::median_throughput_for_host(&conn, &crate::speedtest::host_hash(host))
+            .ok()?;
+    (upload_bps.is_some() || download_bps.is_some()).then_some(Throughput {
+        upload_bps,
+        download_bps,
+    })
+}
+
+/// Add a CLI speed test to the shared history so later syncs can plan with it.
+pub fn record_speed_test(
+    host: &str,
+    protocol: &str,
+    size_bytes: u64,
+    rates: Throughput,
+    integrity_verified: bool,
+    cleanup_ok: bool,
+) -> Result<(), String> {
+    let path =
+        crate::speedtest::history_db_path_detached().ok_or("Cannot determine config directory")?;
+    let conn = crate::speedtest::open_history_db(&path)?;
+    let upload_bps = rates.upload_bps.unwrap_or(0.0);
+    let download_bps = rates.download_bps.unwrap_or(0.0);
+    let millis = |bps: f64| {
+        if bps > 0.0 {
+            (size_bytes as f64 / bps * 1000.0) as u64
+        } else {
+            0
+        }
+    };
+    crate::speedtest::insert_history_row(
+        &conn,
+        crate::speedtest::SpeedTestHistoryRecordRequest {
+            server_id: None,
+            server_name: None,
+            host_hash: Some(crate::speedtest::host_hash(host)),
+            protocol: protocol.to_string(),
+            size_bytes,
+            upload_bytes_per_sec: upload_bps,
+            download_bytes_per_sec: download_bps,
+            upload_duration_ms: millis(upload_bps),
+            download_duration_ms: millis(download_bps),
+            integrity_verified,
+            cleanup_ok,
+        },
+    )
+    .map(|_| ())
+}
+
+/// Decides, transfer by transfer, whether there is time left to start it.
+///
+/// Shared by the parallel transfer tasks of one run. `transferred` is the
+/// run's running byte count, which the transfer tasks already maintain.
+#[derive(Debug)]
+pub struct TransferBudget {
+    deadline: Option<Deadline>,
+    history: Throughput,
+    started: Instant,
+    in_flight: AtomicU64,
+    deferred: Mutex<Vec<(String, TransferKind)>>,
+}
+
+impl TransferBudget {
+    pub fn new(deadline: Option<Deadline>, history: Option<Throughput>) -> Self {
+        Self {
+            deadline,
+            history: history.unwrap_or_default(),
+            started: Instant::now(),
+            in_flight: AtomicU64::new(0),
+            deferred: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn deadline(&self) -> Option<&Deadline> {
+        self.deadline.as_ref()
+    }
+
+    /// Rate to plan with: measured once enough data moved, else history.
+    pub fn rate(&self, kind: TransferKind, transferred: u64, now: Instant) -> Option<f64> {
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f64();
+        if transferred >= LIVE_SAMPLE_BYTES && elapsed >= LIVE_SAMPLE_SECS {
+            return Some(transferred as f64 / elapsed);
+        }
+        self.history.for_kind(kind).filter(|bps| *bps > 0.0)
+    }
+
+    /// Claim time for a transfer of `size` bytes. Returns false, and records
+    /// the path as deferred, when it would not finish before the deadline.
+    /// Every admitted transfer must be followed by [`Self::finish`].
+    pub fn admit(&self, path: &str, kind: TransferKind, size: u64, transferred: u64) -> bool {
+        self.admit_at(path, kind, size, transferred, Instant::now())
+    }
+
+    fn admit_at(
+        &self,
+        path: &str,
+        kind: TransferKind,
+        size: u64,
+        transferred: u64,
+        now: Instant,
+    ) -> bool {
+        let Some(deadline) = self.deadline else {
+            return true;
+        };
+        let fits = now < deadline.at
+            && match self.rate(kind, transferred, now) {
+                // Running transfers share the link: this one completes
+                // after the bytes still in flight ahead of it.
+                Some(bps) => {
+                    let queued = self.in_flight.load(Relaxed) + size;
+                    let secs = queued as f64 / bps * SAFETY_FACTOR;
+                    now + Duration::from_secs_f64(secs.min(1e9)) <= deadline.at
+                }
+                // No estimate yet: start it and learn the rate from it.
+                None => true,
+            };
+        if fits {
+            self.in_flight.fetch_add(size, Relaxed);
+        } else {
+            self.deferred
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push((path.to_string(), kind));
+        }
+        fits
+    }
+
+    /// Release the time claimed by an admitted transfer, whatever its outcome.
+    pub fn finish(&self, size: u64) {
+        let release = |v: u64| Some(v.saturating_sub(size));
+        let _ = self.in_flight.fetch_update(Relaxed, Relaxed, release);
+    }
+
+    /// Transfers turned away so far, in the order they were deferred.
+    pub fn deferred(&self) -> Vec<(String, TransferKind)> {
+        self.deferred
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// Journal for a run that stopped at its deadline: the finished transfers
+/// as completed, the deferred ones as pending.
+pub fn deferral_journal(
+    local_path: &str,
+    remote_path: &str,
+    direction: CompareDirection,
+    completed: &[(String, TransferKind, u64)],
+    deferred: &[(String, TransferKind)],
+) -> SyncJournal {
+    let mut journal = SyncJournal::new(
+        local_path.to_string(),
+        remote_path.to_string(),
+        direction,
+        RetryPolicy::default(),
+        VerifyPolicy::default(),
+    );
+    let entry = |path: &str, kind: TransferKind, status, attempts, bytes| SyncJournalEntry {
+        relative_path: path.to_string(),
+        action: kind.as_str().to_string(),
+        status,
+        attempts,
+        last_error: None,
+        verified: None,
+        bytes_transferred: bytes,
+    };
+    journal.entries.extend(
+        completed.iter().map(|(path, kind, bytes)| {
+            entry(path, *kind, JournalEntryStatus::Completed, 1, *bytes)
+        }),
+    );
+    journal.entries.extend(
+        deferred
+            .iter()
+            .map(|(path, kind)| entry(path, *kind, JournalEntryStatus::Pending, 0, 0)),
+    );
+    journal
+}
+
+/// Paths a journal still has to transfer.
+pub fn pending_paths(journal: &SyncJournal) -> Vec<String> {
+    journal
+        .entries
+        .iter()
+        .filter(|e| {
+            matches!(
+                e.status,
+                JournalEntryStatus::Pending | JournalEntryStatus::InProgress
+            )
+        })
+        .map(|e| e.relative_path.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(path: &str, size: u64, mtime: Option<i64>) -> (String, u64, Option<i64>) {
+        (path.to_string(), size, mtime)
+    }
+
+    fn sorted(
+        planner: &TransferPlanner,
+        mut items: Vec<(String, u64, Option<i64>)>,
+    ) -> Vec<String> {
+        planner.sort(&mut items, |(path, size, mtime)| PlanItem {
+            path,
+            size: *size,
+            mtime: *mtime,
+        });
+        items.into_iter().map(|(path, _, _)| path).collect()
+    }
+
+    #[test]
+    fn orders_by_policy_and_priority() {
+        let items = vec![
+            item("a/big.iso", 900, Some(10)),
+            item("b/small.txt", 10, Some(30)),
+            item("db/dump.sql", 500, None),
+            item("c/mid.txt", 100, Some(20)),
+        ];
+        let discovery = TransferPlanner::new(TransferOrder::Discovery, &[]).unwrap();
+        assert_eq!(
+            sorted(&discovery, items.clone()),
+            ["a/big.iso", "b/small.txt", "db/dump.sql", "c/mid.txt"]
+        );
+        let smallest = TransferPlanner::new(TransferOrder::SmallestFirst, &[]).unwrap();
+        assert_eq!(
+            sorted(&smallest, items.clone()),
+            ["b/small.txt", "c/mid.txt", "db/dump.sql", "a/big.iso"]
+        );
+        let newest = TransferPlanner::new(TransferOrder::NewestFirst, &[]).unwrap();
+        assert_eq!(
+            sorted(&newest, items.clone()),
+            ["b/small.txt", "c/mid.txt", "a/big.iso", "db/dump.sql"]
+        );
+        let prioritized = TransferPlanner::new(
+            TransferOrder::LargestFirst,
+            &["db/**".to_string(), "*.txt".to_string()],
+        )
+        .unwrap()
+        .with_resumed(["a/big.iso".to_string()]);
+        assert_eq!(
+            sorted(&prioritized, items),
+            ["a/big.iso", "db/dump.sql", "c/mid.txt", "b/small.txt"]
+        );
+        assert!(TransferOrder::parse("Largest_First").is_ok());
+        assert!(TransferOrder::parse("random").is_err());
+        assert!(TransferPlanner::new(TransferOrder::Discovery, &["a/[".to_string()]).is_err());
+    }
+
+    #[test]
+    fn parses_durations_and_stop_times() {
+        assert_eq!(
+            parse_max_duration("90m").unwrap(),
+            Duration::from_secs(5400)
+        );
+        assert_eq!(
+            parse_max_duration("1h30m").unwrap(),
+            Duration::from_secs(5400)
+        );
+        assert_eq!(parse_max_duration("45").unwrap(), Duration::from_secs(45));
+        assert!(parse_max_duration("2x").is_err());
+        assert!(parse_max_duration("1h30").is_err());
+
+        let now = Local.with_ymd_and_hms(2026, 3, 10, 22, 15, 0).unwrap();
+        let next = parse_stop_at("06:00", now).unwrap();
+        assert_eq!(next.naive_local().to_string(), "2026-03-11 06:00:00");
+        let later_today = parse_stop_at("23:00", now).unwrap();
+        assert_eq!(later_today.naive_local().to_string(), "2026-03-10 23:00:00");
+        assert!(parse_stop_at("25:00", now).is_err());
+    }
+
+    #[test]
+    fn defers_transfers_that_would_overrun() {
+        let start = Instant::now();
+        let deadline = Deadline {
+            at: start + Duration::from_secs(100),
+            wall: Local::now(),
+        };
+        let history = Throughput {
+            upload_bps: Some(1000.0),
+            download_bps: None,
+        };
+        let budget = TransferBudget::new(Some(deadline), Some(history));
+        // 60s of work fits; a second 60s transfer while it runs does not.
+        assert!(budget.admit_at("one", TransferKind::Upload, 50_000, 0, start));
+        assert!(!budget.admit_at("two", TransferKind::Upload, 50_000, 0, start));
+        // A smaller file still fits behind the first one.
+        assert!(budget.admit_at("three", TransferKind::Upload, 20_000, 0, start));
+        budget.finish(50_000);
+        budget.finish(20_000);
+        // Without a download rate the first download starts to measure one.
+        assert!(budget.admit_at("four", TransferKind::Download, 1 << 30, 0, start));
+        budget.finish(1 << 30);
+        // Past the deadline nothing starts.
+        let late = start + Duration::from_secs(101);
+        assert!(!budget.admit_at("five", TransferKind::Upload, 1, 0, late));
+        assert_eq!(
+            budget.deferred(),
+            [
+                ("two".to_string(), TransferKind::Upload),
+                ("five".to_string(), TransferKind::Upload)
+            ]
+        );
+
+        let unlimited = TransferBudget::new(None, None);
+        assert!(unlimited.admit("any", TransferKind::Download, u64::MAX, 0));
+    }
+
+    #[test]
+    fn journal_round_trips_deferred_transfers() {
+        let journal = deferral_journal(
+            "/data",
+            "/backup",
+            CompareDirection::LocalToRemote,
+            &[("done.txt".to_string(), TransferKind::Upload, 42)],
+            &[("later.bin".to_string(), TransferKind::Upload)],
+        );
+        assert!(journal.has_resumable_entries());
+        assert_eq!(journal.count_by_status(&JournalEntryStatus::Completed), 1);
+        assert_eq!(pending_paths(&journal), ["later.bin"]);
+    }
+}
@@ -2,11 +2,12 @@
 // Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
 
 // AeroSync Scheduler Module
-// Interval-based sync scheduling with time window and day-of-week filtering
+// Interval-based sync scheduling with time window and day-of-week filtering,
+// plus a registry of named sync jobs driven by cron expressions
 
-use chrono::{DateTime, Datelike, Local, Timelike, Utc};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 use tracing::{info, warn};
 
@@ -14,6 +15,13 @@ use tracing::{info, warn};
 pub static SCHEDULE_WRITE_LOCK: LazyLock<std::sync::Mutex<()>> =
     LazyLock::new(|| std::sync::Mutex::new(()));
 
+/// Write lock serializing read-modify-write cycles on the named job registry
+static SYNC_JOBS_WRITE_LOCK: LazyLock<std::sync::Mutex<()>> =
+    LazyLock::new(|| std::sync::Mutex::new(()));
+
+/// Number of past runs kept per named sync job
+pub const SYNC_JOB_HISTORY_LIMIT: usize = 20;
+
 // ---------------------------------------------------------------------------
 // Weekday
 // ---------------------------------------------------------------------------
@@ -57,6 +65,22 @@ impl Weekday {
             Self::Sun => Self::Sat,
         }
     }
+
+    /// Parse a day name (`mon`, `Monday`, ...). Case-insensitive.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let lower = value.trim().to_ascii_lowercase();
+        let day = match lower.get(..3).unwrap_or(&lower) {
+            "mon" => Self::Mon,
+            "tue" => Self::Tue,
+            "wed" => Self::Wed,
+            "thu" => Self::Thu,
+            "fri" => Self::Fri,
+            "sat" => Self::Sat,
+            "sun" => Self::Sun,
+            _ => return Err(format!("Unknown weekday '{}'", value)),
+        };
+        Ok(day)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -103,6 +127,43 @@ impl TimeWindow {
         Ok(())
     }
 
+    /// Parse a `HH:MM-HH:MM` window with an optional comma-separated day
+    /// list (e.g. `"22:00-06:00"`, `mon,tue,fri`).
+    pub fn parse(spec: &str, days: &str) -> Result<Self, String> {
+        let (start, end) = spec
+            .split_once('-')
+            .ok_or_else(|| format!("Invalid time window '{}': expected HH:MM-HH:MM", spec))?;
+        let parse_hm = |value: &str| -> Result<(u8, u8), String> {
+            let (h, m) = value
+                .trim()
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid time '{}': expected HH:MM", value))?;
+            let h = h
+                .parse::<u8>()
+                .map_err(|_| format!("Invalid hour in '{}'", value))?;
+            let m = m
+                .parse::<u8>()
+                .map_err(|_| format!("Invalid minute in '{}'", value))?;
+            Ok((h, m))
+        };
+        let (start_hour, start_minute) = parse_hm(start)?;
+        let (end_hour, end_minute) = parse_hm(end)?;
+        let days = days
+            .split(',')
+            .filter(|d| !d.trim().is_empty())
+            .map(Weekday::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        let window = Self {
+            start_hour,
+            start_minute,
+            end_hour,
+            end_minute,
+            days,
+        };
+        window.validate()?;
+        Ok(window)
+    }
+
     /// Check whether the given `(hour, minute)` falls inside this window.
     ///
     /// Handles overnight windows transparently: if `start > end` the window
@@ -290,6 +351,406 @@ impl SyncSchedule {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Cron expressions
+// ---------------------------------------------------------------------------
+
+/// Parsed five-field cron expression (`minute hour day-of-month month
+/// day-of-week`).
+///
+/// Supports `*`, lists (`1,15`), ranges (`1-5`), steps (`*/10`, `8-18/2`),
+/// month and day names (`jan`, `mon`) and the `@hourly`, `@daily`,
+/// `@midnight`, `@weekly`, `@monthly`, `@yearly` / `@annually` shortcuts.
+/// Day-of-week accepts 0-7 with both 0 and 7 meaning Sunday. As in Vixie
+/// cron, when both day fields are restricted a time matches if *either*
+/// of them matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u32,
+    days_of_month: u32,
+    months: u16,
+    days_of_week: u8,
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+const CRON_MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const CRON_DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl CronSchedule {
+    /// Parse a cron expression.
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = expr.trim();
+        let expanded = match expr.to_ascii_lowercase().as_str() {
+            "@hourly" => "0 * * * *".to_string(),
+            "@daily" | "@midnight" => "0 0 * * *".to_string(),
+            "@weekly" => "0 0 * * 0".to_string(),
+            "@monthly" => "0 0 1 * *".to_string(),
+            "@yearly" | "@annually" => "0 0 1 1 *".to_string(),
+            other if other.starts_with('@') => {
+                return Err(format!("Unknown cron shortcut '{}'", expr))
+            }
+            _ => expr.to_string(),
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "Invalid cron expression '{}': expected 5 fields (minute hour day month weekday)",
+                expr
+            ));
+        }
+
+        let minutes = parse_cron_field(fields[0], 0, 59, &[])?;
+        let hours = parse_cron_field(fields[1], 0, 23, &[])?;
+        let days_of_month = parse_cron_field(fields[2], 1, 31, &[])?;
+        let months = parse_cron_field(fields[3], 1, 12, &CRON_MONTH_NAMES)?;
+        let mut days_of_week = parse_cron_field(fields[4], 0, 7, &CRON_DAY_NAMES)?;
+        // 7 is an alias for Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes,
+            hours: hours as u32,
+            days_of_month: days_of_month as u32,
+            months: months as u16,
+            days_of_week: days_of_week as u8,
+            dom_restricted: !fields[2].starts_with('*'),
+            dow_restricted: !fields[4].starts_with('*'),
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let dom = self.days_of_month & (1 << date.day()) != 0;
+        let dow = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+
+    /// Check whether the given wall-clock minute matches the expression.
+    pub fn matches(&self, at: &NaiveDateTime) -> bool {
+        self.matches_day(at.date())
+            && self.hours & (1 << at.hour()) != 0
+            && self.minutes & (1 << at.minute()) != 0
+    }
+
+    /// First matching local time strictly after `after`, scanning up to
+    /// four years ahead (enough for `0 0 29 2 *`). Times that fall into a
+    /// DST gap are skipped.
+    pub fn next_after(&self, after: &DateTime<Local>) -> Option<DateTime<Local>> {
+        let start =
+            after.naive_local().with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        let mut date = start.date();
+        let limit = date + chrono::Duration::days(366 * 4);
+
+        while date <= limit {
+            if self.matches_day(date) {
+                for hour in 0..24u32 {
+                    if self.hours & (1 << hour) == 0 {
+                        continue;
+                    }
+                    for minute in 0..60u32 {
+                        if self.minutes & (1 << minute) == 0 {
+                            continue;
+                        }
+                        let candidate = date.and_hms_opt(hour, minute, 0)?;
+                        if candidate < start {
+                            continue;
+                        }
+                        if let Some(local) = Local.from_local_datetime(&candidate).earliest() {
+                            return Some(local);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+/// Parse one cron field into a bitmask of allowed values.
+fn parse_cron_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value_of = |token: &str| -> Result<u32, String> {
+        let lower = token.to_ascii_lowercase();
+        if let Some(pos) = names.iter().position(|n| *n == lower) {
+            // Month names are 1-based, day names 0-based
+            return Ok(pos as u32 + min);
+        }
+        let n = token
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid cron value '{}'", token))?;
+        if n < min || n > max {
+            return Err(format!("Cron value {} out of range {}-{}", n, min, max));
+        }
+        Ok(n)
+    };
+
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        if part.is_empty() {
+            return Err(format!("Empty entry in cron field '{}'", field));
+        }
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .map_err(|_| format!("Invalid cron step '{}'", step))?;
+                if step == 0 {
+                    return Err(format!("Cron step must be positive in '{}'", part));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((lo, hi)) = range.split_once('-') {
+            (value_of(lo)?, value_of(hi)?)
+        } else {
+            let v = value_of(range)?;
+            // `5/15` means "from 5 to the end of the range every 15"
+            (v, if part.contains('/') { max } else { v })
+        };
+        if lo > hi {
+            return Err(format!("Invalid cron range '{}'", range));
+        }
+        let mut v = lo;
+        while v <= hi {
+            mask |= 1 << v;
+            v += step;
+        }
+    }
+    Ok(mask)
+}
+
+// ---------------------------------------------------------------------------
+// Named sync jobs
+// ---------------------------------------------------------------------------
+
+/// What started a named sync job run.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncJobTrigger {
+    Schedule,
+    Manual,
+}
+
+/// Outcome of a named sync job run.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncJobOutcome {
+    Success,
+    Failed,
+    /// The fire time came while the previous run was still in progress.
+    SkippedOverlap,
+}
+
+/// One entry in a named sync job's run history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncJobRun {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub trigger: SyncJobTrigger,
+    pub outcome: SyncJobOutcome,
+    /// Exit code of the sync process, when it ran.
+    pub exit_code: Option<i32>,
+    /// Short error or status message (last stderr line on failure).
+    pub message: Option<String>,
+}
+
+/// A named sync pair with its own options, cron expression and window.
+///
+/// Jobs are run by `aeroftp-cli daemon start` as `aeroftp-cli sync`
+/// subprocesses, so `options` are plain `sync` flags.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncJob {
+    /// Unique job name (letters, digits, `-`, `_`, `.`).
+    pub name: String,
+    /// Local directory.
+    pub local: String,
+    /// Remote directory.
+    pub remote: String,
+    /// Server URL, when not using a saved profile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Saved profile name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Extra `sync` flags (e.g. `--direction`, `upload`, `--delete`).
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Five-field cron expression or `@daily`-style shortcut.
+    pub cron: String,
+    /// Optional window restricting when scheduled runs may start.
+    #[serde(default)]
+    pub time_window: Option<TimeWindow>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    /// Last fire time consumed by the scheduler (run or skipped).
+    #[serde(default)]
+    pub last_scheduled: Option<DateTime<Utc>>,
+    /// Most recent runs, oldest first, capped at [`SYNC_JOB_HISTORY_LIMIT`].
+    #[serde(default)]
+    pub history: Vec<SyncJobRun>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl SyncJob {
+    /// Validate the name, cron expression and time window.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty()
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(format!(
+                "Invalid job name '{}': use letters, digits, '-', '_' or '.'",
+                self.name
+            ));
+        }
+        CronSchedule::parse(&self.cron)?;
+        if let Some(ref tw) = self.time_window {
+            tw.validate()?;
+        }
+        Ok(())
+    }
+
+    fn window_allows(&self, at: &DateTime<Local>) -> bool {
+        match &self.time_window {
+            Some(w) => w.contains_time_and_day(
+                at.hour() as u8,
+                at.minute() as u8,
+                &Weekday::from_chrono(at.weekday()),
+            ),
+            None => true,
+        }
+    }
+
+    /// Next cron fire time after `after` that also falls inside the time
+    /// window. `None` when the expression is invalid or never matches.
+    pub fn next_fire_after(&self, after: &DateTime<Local>) -> Option<DateTime<Local>> {
+        let cron = CronSchedule::parse(&self.cron).ok()?;
+        let mut cursor = *after;
+        // Bounded so a window that never intersects the cron cannot spin
+        for _ in 0..10_000 {
+            let next = cron.next_after(&cursor)?;
+            if self.window_allows(&next) {
+                return Some(next);
+            }
+            cursor = next;
+        }
+        None
+    }
+
+    /// Whether a scheduled run is due at `now`.
+    ///
+    /// Fire times missed while the daemon was down collapse into a single
+    /// catch-up run.
+    pub fn is_due(&self, now: &DateTime<Local>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let anchor = self.last_scheduled.unwrap_or(self.created_at);
+        match self.next_fire_after(&anchor.with_timezone(&Local)) {
+            Some(next) => next <= *now,
+            None => false,
+        }
+    }
+
+    /// Append a run to the history, dropping the oldest beyond the limit.
+    pub fn record_run(&mut self, run: SyncJobRun) {
+        self.history.push(run);
+        if self.history.len() > SYNC_JOB_HISTORY_LIMIT {
+            let excess = self.history.len() - SYNC_JOB_HISTORY_LIMIT;
+            self.history.drain(..excess);
+        }
+    }
+
+    /// Most recent run, if any.
+    pub fn last_run(&self) -> Option<&SyncJobRun> {
+        self.history.last()
+    }
+
+    /// Arguments for the `aeroftp-cli` subprocess that runs this job.
+    pub fn cli_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(ref profile) = self.profile {
+            args.push("--profile".to_string());
+            args.push(profile.clone());
+        }
+        args.push("sync".to_string());
+        if let Some(ref url) = self.url {
+            args.push(url.clone());
+        }
+        args.push(self.local.clone());
+        args.push(self.remote.clone());
+        args.extend(self.options.iter().cloned());
+        args
+    }
+}
+
+/// Registry of named sync jobs.
+///
+/// Persisted to `~/.config/aeroftp/sync_jobs.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncJobRegistry {
+    #[serde(default)]
+    pub jobs: Vec<SyncJob>,
+}
+
+impl SyncJobRegistry {
+    pub fn get(&self, name: &str) -> Option<&SyncJob> {
+        self.jobs.iter().find(|j| j.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut SyncJob> {
+        self.jobs.iter_mut().find(|j| j.name == name)
+    }
+
+    /// Add a job. Fails if the job is invalid or the name is taken.
+    pub fn add(&mut self, job: SyncJob) -> Result<(), String> {
+        job.validate()?;
+        if self.get(&job.name).is_some() {
+            return Err(format!("Sync job '{}' already exists", job.name));
+        }
+        self.jobs.push(job);
+        Ok(())
+    }
+
+    /// Remove a job by name. Returns `false` when it did not exist.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.jobs.len();
+        self.jobs.retain(|j| j.name != name);
+        self.jobs.len() != before
+    }
+
+    /// Names of jobs whose scheduled run is due at `now`.
+    pub fn due_jobs(&self, now: &DateTime<Local>) -> Vec<String> {
+        self.jobs
+            .iter()
+            .filter(|j| j.is_due(now))
+            .map(|j| j.name.clone())
+            .collect()
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
@@ -435,6 +896,82 @@ pub fn save_sync_schedule(schedule: &SyncSchedule) -> Result<(), String> {
     Ok(())
 }
 
+/// Resolve the path to `~/.config/aeroftp/sync_jobs.json`.
+fn sync_jobs_path() -> Result<PathBuf, String> {
+    let base = dirs::config_dir().ok_or_else(|| "Cannot determine config directory".to_string())?;
+    Ok(base.join("aeroftp").join("sync_jobs.json"))
+}
+
+fn read_sync_jobs(path: &Path) -> Result<SyncJobRegistry, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str::<SyncJobRegistry>(&contents)
+            .map_err(|e| format!("Failed to parse sync jobs at {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SyncJobRegistry::default()),
+        Err(e) => Err(format!(
+            "Failed to read sync jobs at {}: {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+fn write_sync_jobs(path: &Path, registry: &SyncJobRegistry) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            format!(
+                "Failed to create config directory {}: {}",
+                parent.display(),
+                e
+            )
+        })?;
+    }
+
+    let json = serde_json::to_string_pretty(registry)
+        .map_err(|e| format!("Failed to serialize sync jobs: {}", e))?;
+
+    // Atomic write: temp file + rename
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, &json).map_err(|e| {
+        format!(
+            "Failed to write temp sync jobs to {}: {}",
+            tmp_path.display(),
+            e
+        )
+    })?;
+    std::fs::rename(&tmp_path, path).map_err(|e| format!("Failed to rename sync jobs file: {}", e))
+}
+
+/// Load the named sync job registry.
+///
+/// Unlike [`load_sync_schedule`], a corrupt file is an error rather than
+/// an empty default, so a later save cannot silently wipe every job.
+pub fn load_sync_jobs() -> Result<SyncJobRegistry, String> {
+    let _lock = SYNC_JOBS_WRITE_LOCK
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    read_sync_jobs(&sync_jobs_path()?)
+}
+
+/// Load, modify and save the registry under the write lock.
+///
+/// The closure's error aborts the update without writing.
+pub fn update_sync_jobs<R>(
+    f: impl FnOnce(&mut SyncJobRegistry) -> Result<R, String>,
+) -> Result<R, String> {
+    let _lock = SYNC_JOBS_WRITE_LOCK
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let path = sync_jobs_path()?;
+    let mut registry = read_sync_jobs(&path)?;
+    let result = f(&mut registry)?;
+    for job in &registry.jobs {
+        job.validate()?;
+    }
+    write_sync_jobs(&path, &registry)?;
+    info!("Sync jobs saved to {}", path.display());
+    Ok(result)
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -837,4 +1374,195 @@ mod tests {
         assert!(w.contains_time_and_day(2, 0, &Weekday::Sun));
         assert!(!w.contains_time_and_day(12, 0, &Weekday::Mon));
     }
+
+    fn local(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Local> {
+        Local
+            .from_local_datetime(
+                &NaiveDate::from_ymd_opt(y, mo, d)
+                    .unwrap()
+                    .and_hms_opt(h, mi, 0)
+                    .unwrap(),
+            )
+            .earliest()
+            .unwrap()
+    }
+
+    fn sample_job(cron: &str) -> SyncJob {
+        SyncJob {
+            name: "photos".to_string(),
+            local: "/home/u/Photos".to_string(),
+            remote: "/backup/photos".to_string(),
+            url: None,
+            profile: Some("NAS".to_string()),
+            options: vec!["--direction".to_string(), "upload".to_string()],
+            cron: cron.to_string(),
+            time_window: None,
+            enabled: true,
+            created_at: local(2026, 3, 2, 9, 30).with_timezone(&Utc),
+            last_scheduled: None,
+            history: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_cron_parse_fields() {
+        let c = CronSchedule::parse("*/15 8-18/2 * jan,jul mon-fri").unwrap();
+        let at = |y, mo, d, h, mi| {
+            NaiveDate::from_ymd_opt(y, mo, d)
+                .unwrap()
+                .and_hms_opt(h, mi, 0)
+                .unwrap()
+        };
+        // 2026-01-05 is a Monday
+        assert!(c.matches(&at(2026, 1, 5, 8, 45)));
+        assert!(c.matches(&at(2026, 7, 6, 18, 0)));
+        assert!(!c.matches(&at(2026, 1, 5, 9, 0))); // odd hour
+        assert!(!c.matches(&at(2026, 1, 5, 8, 10))); // not a 15-min step
+        assert!(!c.matches(&at(2026, 1, 4, 8, 0))); // Sunday
+        assert!(!c.matches(&at(2026, 2, 2, 8, 0))); // February
+
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("@sometimes").is_err());
+        assert_eq!(
+            CronSchedule::parse("@daily").unwrap(),
+            CronSchedule::parse("0 0 * * *").unwrap()
+        );
+        // 7 and 0 both mean Sunday
+        assert_eq!(
+            CronSchedule::parse("0 0 * * 7").unwrap(),
+            CronSchedule::parse("0 0 * * sun").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cron_day_fields_are_ored_when_both_restricted() {
+        let c = CronSchedule::parse("0 0 1 * mon").unwrap();
+        let midnight = |d| {
+            NaiveDate::from_ymd_opt(2026, 6, d)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        };
+        assert!(c.matches(&midnight(1))); // 1st of month (a Monday too)
+        assert!(c.matches(&midnight(8))); // Monday
+        assert!(!c.matches(&midnight(9))); // Tuesday, not the 1st
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let c = CronSchedule::parse("30 2 * * *").unwrap();
+        assert_eq!(
+            c.next_after(&local(2026, 3, 2, 1, 0)),
+            Some(local(2026, 3, 2, 2, 30))
+        );
+        // Strictly after: the current minute does not count
+        assert_eq!(
+            c.next_after(&local(2026, 3, 2, 2, 30)),
+            Some(local(2026, 3, 3, 2, 30))
+        );
+
+        let leap = CronSchedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(
+            leap.next_after(&local(2026, 3, 1, 0, 0)),
+            Some(local(2028, 2, 29, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_sync_job_due_and_window() {
+        let mut job = sample_job("0 * * * *");
+        // Created 09:30, next fire 10:00
+        assert!(!job.is_due(&local(2026, 3, 2, 9, 59)));
+        assert!(job.is_due(&local(2026, 3, 2, 10, 0)));
+
+        // Missed fires collapse: once 10:00 is consumed, the next is 11:00
+        job.last_scheduled = Some(local(2026, 3, 2, 12, 5).with_timezone(&Utc));
+        assert!(!job.is_due(&local(2026, 3, 2, 12, 59)));
+        assert!(job.is_due(&local(2026, 3, 2, 13, 0)));
+
+        // Window 22:00-06:00 pushes the hourly fire to 22:00
+        job.last_scheduled = None;
+        job.time_window = Some(TimeWindow::parse("22:00-06:00", "").unwrap());
+        assert_eq!(
+            job.next_fire_after(&local(2026, 3, 2, 9, 30)),
+            Some(local(2026, 3, 2, 22, 0))
+        );
+
+        job.enabled = false;
+        assert!(!job.is_due(&local(2026, 3, 3, 0, 0)));
+    }
+
+    #[test]
+    fn test_sync_job_registry_and_history() {
+        let mut reg = SyncJobRegistry::default();
+        reg.add(sample_job("@hourly")).unwrap();
+        assert!(reg.add(sample_job("@daily")).is_err());
+
+        let mut bad = sample_job("@hourly");
+        bad.name = "has space".to_string();
+        assert!(reg.add(bad).is_err());
+
+        let job = reg.get_mut("photos").unwrap();
+        for i in 0..(SYNC_JOB_HISTORY_LIMIT + 5) {
+            job.record_run(SyncJobRun {
+                started_at: Utc::now(),
+                finished_at: None,
+                trigger: SyncJobTrigger::Manual,
+                outcome: SyncJobOutcome::Success,
+                exit_code: Some(i as i32),
+                message: None,
+            });
+        }
+        assert_eq!(job.history.len(), SYNC_JOB_HISTORY_LIMIT);
+        assert_eq!(
+            job.last_run().unwrap().exit_code,
+            Some((SYNC_JOB_HISTORY_LIMIT + 4) as i32)
+        );
+        assert_eq!(
+            job.cli_args(),
+            vec![
+                "--profile",
+                "NAS",
+                "sync",
+                "/home/u/Photos",
+                "/backup/photos",
+                "--direction",
+                "upload"
+            ]
+        );
+
+        assert_eq!(reg.due_jobs(&local(2026, 3, 2, 10, 0)), vec!["photos"]);
+        assert!(reg.remove("photos"));
+        assert!(!reg.remove("photos"));
+    }
+
+    #[test]
+    fn test_sync_jobs_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sync_jobs.json");
+        assert!(read_sync_jobs(&path).unwrap().jobs.is_empty());
+
+        let mut reg = SyncJobRegistry::default();
+        reg.add(sample_job("15 3 * * *")).unwrap();
+        write_sync_jobs(&path, &reg).unwrap();
+        let loaded = read_sync_jobs(&path).unwrap();
+        assert_eq!(loaded.jobs.len(), 1);
+        assert_eq!(loaded.jobs[0].cron, "15 3 * * *");
+
+        std::fs::write(&path, "{not json").unwrap();
+        assert!(read_sync_jobs(&path).is_err());
+    }
+
+    #[test]
+    fn test_time_window_parse() {
+        let w = TimeWindow::parse("22:30-06:00", "mon, Friday").unwrap();
+        assert_eq!((w.start_hour, w.start_minute), (22, 30));
+        assert_eq!(w.days, vec![Weekday::Mon, Weekday::Fri]);
+        assert!(TimeWindow::parse("25:00-06:00", "").is_err());
+        assert!(TimeWindow::parse("22:00", "").is_err());
+        assert!(TimeWindow::parse("22:00-06:00", "funday").is_err());
+    }
 }
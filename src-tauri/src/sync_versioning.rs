@@ -1,7 +1,10 @@
 //! AeroCloud file versioning: automatic backup of overwritten/deleted files.
 //!
 //! When a sync operation would overwrite or delete a local file, the previous
-//! version is archived in `.aeroversions/` with a timestamp suffix.
+//! version is archived in `.aeroversions/` with a timestamp suffix. The same
+//! layout is used on the destination remote (`<remote_root>/.aeroversions/`)
+//! when sync overwrites or deletes remote files, moved there with a
+//! server-side rename or copy.
 //! Three strategies inspired by Syncthing:
 //! - **TrashCan**: keep all, auto-cleanup after N days
 //! - **Simple**: keep last N copies per file
@@ -10,10 +13,28 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 // Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
 
+use crate::providers::StorageProvider;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use tracing::info;
 
+/// Name of the archive folder at the root of a synced tree (local or remote).
+pub const VERSIONS_DIR_NAME: &str = ".aeroversions";
+
+/// Timestamp format embedded in archive names (`<stem>~<ts>.<ext>`).
+const ARCHIVE_TS_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// True when `relative_path` lives inside the archive folder, so sync scans
+/// can keep archived versions out of the comparison.
+pub fn is_versions_path(relative_path: &str) -> bool {
+    let trimmed = relative_path.trim_start_matches('/');
+    trimmed == VERSIONS_DIR_NAME
+        || trimmed
+            .strip_prefix(VERSIONS_DIR_NAME)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
 /// Versioning strategy for archived files.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -47,6 +68,29 @@ impl Default for VersioningStrategy {
     }
 }
 
+impl VersioningStrategy {
+    /// Parse a strategy name as used by the UI, CLI and MCP: `disabled`,
+    /// `trash_can`, `simple` or `staggered`. `trash_can:<days>` and
+    /// `simple:<copies>` override the default retention.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (kind, param) = match name.split_once(':') {
+            Some((kind, param)) => (kind, Some(param.parse::<u32>().ok()?)),
+            None => (name, None),
+        };
+        match (kind, param) {
+            ("disabled", None) => Some(Self::Disabled),
+            ("trash_can", days) => Some(Self::TrashCan {
+                max_age_days: days.unwrap_or_else(default_max_age),
+            }),
+            ("simple", copies) => Some(Self::Simple {
+                max_copies: copies.unwrap_or_else(default_max_copies),
+            }),
+            ("staggered", None) => Some(Self::Staggered),
+            _ => None,
+        }
+    }
+}
+
 /// A single archived version of a file.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VersionEntry {
@@ -60,6 +104,19 @@ pub struct VersionEntry {
     pub size: u64,
 }
 
+/// A single archived version of a remote file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteVersionEntry {
+    /// Remote path of the archived copy
+    pub archive_path: String,
+    /// Original relative path (from the remote sync root)
+    pub original_relative: String,
+    /// Timestamp when archived
+    pub archived_at: String,
+    /// File size in bytes
+    pub size: u64,
+}
+
 /// Statistics from a cleanup operation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CleanupStats {
@@ -107,16 +164,8 @@ impl SyncVersioning {
             .map_err(|_| "File is not inside sync root".to_string())?;
 
         // Build archive path: .aeroversions/<relative_dir>/<stem>~<timestamp>.<ext>
-        let ts = chrono::Utc::now().format("%Y%m%d-%H%M%S");
-        let stem = relative
-            .file_stem()
-            .map(|s| s.to_string_lossy().to_string())
-            .unwrap_or_default();
-        let ext = relative
-            .extension()
-            .map(|e| format!(".{}", e.to_string_lossy()))
-            .unwrap_or_default();
-        let archive_name = format!("{}~{}{}", stem, ts, ext);
+        let ts = chrono::Utc::now().format(ARCHIVE_TS_FORMAT).to_string();
+        let archive_name = archive_file_name(relative, &ts);
 
         let archive_dir = if let Some(parent) = relative.parent() {
             if parent.as_os_str().is_empty() {
@@ -309,149 +358,43 @@ impl SyncVersioning {
 
     /// Run cleanup based on the configured strategy.
     pub fn cleanup(&self) -> Result<CleanupStats, String> {
-        if !self.versions_dir.exists() {
-            return Ok(CleanupStats {
-                deleted_count: 0,
-                freed_bytes: 0,
-            });
-        }
-
-        match &self.strategy {
-            VersioningStrategy::Disabled => Ok(CleanupStats {
-                deleted_count: 0,
-                freed_bytes: 0,
-            }),
-            VersioningStrategy::TrashCan { max_age_days } => self.cleanup_by_age(*max_age_days),
-            VersioningStrategy::Simple { max_copies } => self.cleanup_by_count(*max_copies),
-            VersioningStrategy::Staggered => self.cleanup_staggered(),
-        }
-    }
-
-    /// Delete versions older than max_age_days.
-    fn cleanup_by_age(&self, max_age_days: u32) -> Result<CleanupStats, String> {
-        let cutoff = std::time::SystemTime::now()
-            - std::time::Duration::from_secs(u64::from(max_age_days) * 86400);
         let mut stats = CleanupStats {
             deleted_count: 0,
             freed_bytes: 0,
         };
-
-        self.walk_versions(|path, meta| {
-            if let Ok(modified) = meta.modified() {
-                if modified < cutoff {
-                    stats.freed_bytes += meta.len();
-                    if std::fs::remove_file(path).is_ok() {
-                        stats.deleted_count += 1;
-                    }
-                }
-            }
-        })?;
-
-        self.cleanup_empty_dirs()?;
-        Ok(stats)
-    }
-
-    /// Keep only the newest max_copies versions per original file.
-    fn cleanup_by_count(&self, max_copies: u32) -> Result<CleanupStats, String> {
-        let mut stats = CleanupStats {
-            deleted_count: 0,
-            freed_bytes: 0,
-        };
-
-        // Group by original file (stem before ~)
-        let mut groups: std::collections::HashMap<String, Vec<(PathBuf, u64)>> =
-            std::collections::HashMap::new();
-
-        self.walk_versions(|path, meta| {
-            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                if let Some(tilde_pos) = name.find('~') {
-                    // Group key: parent dir + stem
-                    let parent = path.parent().unwrap_or(Path::new(""));
-                    let key = format!("{}/{}", parent.display(), &name[..tilde_pos]);
-                    groups
-                        .entry(key)
-                        .or_default()
-                        .push((path.to_path_buf(), meta.len()));
-                }
-            }
-        })?;
-
-        for (_key, mut versions) in groups {
-            // Sort by filename (timestamp is in the name): newest first
-            versions.sort_by(|a, b| b.0.cmp(&a.0));
-            for (path, size) in versions.into_iter().skip(max_copies as usize) {
-                if std::fs::remove_file(&path).is_ok() {
-                    stats.deleted_count += 1;
-                    stats.freed_bytes += size;
-                }
-            }
+        if !self.versions_dir.exists() || !self.is_enabled() {
+            return Ok(stats);
         }
 
-        self.cleanup_empty_dirs()?;
-        Ok(stats)
-    }
-
-    /// Staggered cleanup: 1/hour for 24h, 1/day for 30d, 1/week for older.
-    fn cleanup_staggered(&self) -> Result<CleanupStats, String> {
-        let now = std::time::SystemTime::now();
-        let mut stats = CleanupStats {
-            deleted_count: 0,
-            freed_bytes: 0,
-        };
-
-        let mut groups: std::collections::HashMap<
-            String,
-            Vec<(PathBuf, std::time::SystemTime, u64)>,
-        > = std::collections::HashMap::new();
-
+        let mut candidates = Vec::new();
         self.walk_versions(|path, meta| {
-            if let (Some(name), Ok(modified)) =
-                (path.file_name().and_then(|n| n.to_str()), meta.modified())
-            {
-                if let Some(tilde_pos) = name.find('~') {
-                    let parent = path.parent().unwrap_or(Path::new(""));
-                    let key = format!("{}/{}", parent.display(), &name[..tilde_pos]);
-                    groups
-                        .entry(key)
-                        .or_default()
-                        .push((path.to_path_buf(), modified, meta.len()));
-                }
-            }
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            // Group key: parent dir + stem before the first `~`
+            let group = name.find('~').map(|tilde_pos| {
+                let parent = path.parent().unwrap_or(Path::new(""));
+                format!("{}/{}", parent.display(), &name[..tilde_pos])
+            });
+            candidates.push((
+                RetentionItem {
+                    group,
+                    name,
+                    archived: meta.modified().ok(),
+                },
+                path.to_path_buf(),
+                meta.len(),
+            ));
         })?;
 
-        for (_key, mut versions) in groups {
-            versions.sort_by_key(|b| std::cmp::Reverse(b.1)); // Newest first
-
-            let mut kept_in_hour: std::collections::HashMap<u64, bool> =
-                std::collections::HashMap::new();
-            let mut kept_in_day: std::collections::HashMap<u64, bool> =
-                std::collections::HashMap::new();
-            let mut kept_in_week: std::collections::HashMap<u64, bool> =
-                std::collections::HashMap::new();
-
-            for (path, modified, size) in &versions {
-                let age_secs = now
-                    .duration_since(*modified)
-                    .map(|d| d.as_secs())
-                    .unwrap_or(0);
-                let keep = if age_secs < 86400 {
-                    // < 24h: keep 1 per hour
-                    let hour_bucket = age_secs / 3600;
-                    kept_in_hour.insert(hour_bucket, true).is_none()
-                } else if age_secs < 86400 * 30 {
-                    // < 30d: keep 1 per day
-                    let day_bucket = age_secs / 86400;
-                    kept_in_day.insert(day_bucket, true).is_none()
-                } else {
-                    // > 30d: keep 1 per week
-                    let week_bucket = age_secs / (86400 * 7);
-                    kept_in_week.insert(week_bucket, true).is_none()
-                };
-
-                if !keep && std::fs::remove_file(path).is_ok() {
-                    stats.deleted_count += 1;
-                    stats.freed_bytes += size;
-                }
+        let items: Vec<RetentionItem> =
+            candidates.iter().map(|(item, _, _)| item.clone()).collect();
+        for index in select_expired(&self.strategy, SystemTime::now(), &items) {
+            let (_, path, size) = &candidates[index];
+            if std::fs::remove_file(path).is_ok() {
+                stats.deleted_count += 1;
+                stats.freed_bytes += size;
             }
         }
 
@@ -520,6 +463,423 @@ impl SyncVersioning {
         .ok();
         total
     }
+
+    /// Archive a remote file into `<remote_root>/.aeroversions/` before sync
+    /// overwrites or deletes it. With `keep_original` (overwrite) a
+    /// server-side copy is used when the provider supports one; otherwise
+    /// the file is moved with a rename, which also serves as the delete.
+    /// Returns the remote path of the archived copy.
+    pub async fn archive_remote<P: StorageProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+        remote_root: &str,
+        relative_path: &str,
+        keep_original: bool,
+    ) -> Result<String, String> {
+        if !self.is_enabled() {
+            return Err("Versioning is disabled".to_string());
+        }
+        let relative_path = relative_path.trim_matches('/');
+        if relative_path.is_empty() || relative_path.split('/').any(|part| part == "..") {
+            return Err(format!("Invalid relative path: {}", relative_path));
+        }
+
+        let source = remote_join(remote_root, relative_path);
+        let (parent, file_name) = match relative_path.rsplit_once('/') {
+            Some((parent, name)) => (parent, name),
+            None => ("", relative_path),
+        };
+        let archive_rel_dir = if parent.is_empty() {
+            VERSIONS_DIR_NAME.to_string()
+        } else {
+            format!("{}/{}", VERSIONS_DIR_NAME, parent)
+        };
+        ensure_remote_dirs(provider, remote_root, &archive_rel_dir).await;
+
+        let ts = chrono::Utc::now().format(ARCHIVE_TS_FORMAT).to_string();
+        let archive_name = archive_file_name(Path::new(file_name), &ts);
+        let archive_dir = remote_join(remote_root, &archive_rel_dir);
+        let archive_path = unique_remote_archive_path(provider, &archive_dir, &archive_name).await;
+
+        if keep_original && provider.supports_server_copy() {
+            provider
+                .server_copy(&source, &archive_path)
+                .await
+                .map_err(|e| format!("Failed to archive remote file: {}", e))?;
+        } else {
+            provider
+                .rename(&source, &archive_path)
+                .await
+                .map_err(|e| format!("Failed to archive remote file: {}", e))?;
+        }
+
+        info!(
+            "[Versioning] Archived remote {} -> {}",
+            relative_path, archive_path
+        );
+        Ok(archive_path)
+    }
+
+    /// List archived remote versions of a specific file, newest first.
+    pub async fn list_remote_versions<P: StorageProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+        remote_root: &str,
+        relative_path: &str,
+    ) -> Result<Vec<RemoteVersionEntry>, String> {
+        let relative_path = relative_path.trim_matches('/');
+        Ok(self
+            .list_all_remote_versions(provider, remote_root)
+            .await?
+            .into_iter()
+            .filter(|v| v.original_relative == relative_path)
+            .collect())
+    }
+
+    /// List every archived remote version, newest first.
+    pub async fn list_all_remote_versions<P: StorageProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+        remote_root: &str,
+    ) -> Result<Vec<RemoteVersionEntry>, String> {
+        let mut all = Vec::new();
+        for (dir_rel, entry) in walk_remote_versions(provider, remote_root).await? {
+            let Some((original_name, ts)) = parse_archive_name(&entry.name) else {
+                continue;
+            };
+            let original_relative = if dir_rel.is_empty() {
+                original_name
+            } else {
+                format!("{}/{}", dir_rel, original_name)
+            };
+            all.push(RemoteVersionEntry {
+                archive_path: remote_join(
+                    &remote_join(remote_root, VERSIONS_DIR_NAME),
+                    &if dir_rel.is_empty() {
+                        entry.name.clone()
+                    } else {
+                        format!("{}/{}", dir_rel, entry.name)
+                    },
+                ),
+                original_relative,
+                archived_at: ts,
+                size: entry.size,
+            });
+        }
+        all.sort_by(|a, b| b.archived_at.cmp(&a.archived_at));
+        Ok(all)
+    }
+
+    /// Restore an archived remote version to its original location.
+    /// The current remote file (if any) is archived first. The archived copy
+    /// is kept when the provider can copy server-side, moved back otherwise.
+    pub async fn restore_remote<P: StorageProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+        remote_root: &str,
+        version: &RemoteVersionEntry,
+    ) -> Result<(), String> {
+        let versions_root = format!("{}/", remote_join(remote_root, VERSIONS_DIR_NAME));
+        if !version.archive_path.starts_with(&versions_root)
+            || version.archive_path.split('/').any(|part| part == "..")
+        {
+            return Err(format!(
+                "Invalid archive path: must be within {}",
+                versions_root
+            ));
+        }
+        let original = version.original_relative.trim_matches('/');
+        if original.is_empty()
+            || original.split('/').any(|part| part == "..")
+            || is_versions_path(original)
+        {
+            return Err("Invalid restore target: path traversal detected".to_string());
+        }
+
+        let target = remote_join(remote_root, original);
+        if self.is_enabled() && provider.exists(&target).await.unwrap_or(false) {
+            if let Err(e) = self
+                .archive_remote(provider, remote_root, original, false)
+                .await
+            {
+                return Err(format!(
+                    "Failed to archive current remote file before restore: {}",
+                    e
+                ));
+            }
+        }
+        if let Some((parent, _)) = original.rsplit_once('/') {
+            ensure_remote_dirs(provider, remote_root, parent).await;
+        }
+
+        let result = if provider.supports_server_copy() {
+            provider.server_copy(&version.archive_path, &target).await
+        } else {
+            provider.rename(&version.archive_path, &target).await
+        };
+        result.map_err(|e| format!("Failed to restore remote file: {}", e))?;
+
+        info!(
+            "[Versioning] Restored remote {} from {}",
+            original, version.archive_path
+        );
+        Ok(())
+    }
+
+    /// Apply the configured retention strategy to `<remote_root>/.aeroversions/`.
+    /// Ages come from the timestamp in the archive name, since a server-side
+    /// rename keeps the original file's mtime.
+    pub async fn cleanup_remote<P: StorageProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+        remote_root: &str,
+    ) -> Result<CleanupStats, String> {
+        let mut stats = CleanupStats {
+            deleted_count: 0,
+            freed_bytes: 0,
+        };
+        if !self.is_enabled() {
+            return Ok(stats);
+        }
+
+        let versions = self.list_all_remote_versions(provider, remote_root).await?;
+        let items: Vec<RetentionItem> = versions
+            .iter()
+            .map(|v| RetentionItem {
+                group: Some(v.original_relative.clone()),
+                name: v
+                    .archive_path
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                archived: archived_at_time(&v.archived_at),
+            })
+            .collect();
+        for index in select_expired(&self.strategy, SystemTime::now(), &items) {
+            let version = &versions[index];
+            if provider.delete(&version.archive_path).await.is_ok() {
+                stats.deleted_count += 1;
+                stats.freed_bytes += version.size;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// Build `<stem>~<ts><.ext>` for the file name of `relative`.
+fn archive_file_name(relative: &Path, ts: &str) -> String {
+    let stem = relative
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = relative
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    format!("{}~{}{}", stem, ts, ext)
+}
+
+/// Split an archive name back into `(original_name, timestamp)`.
+/// Accepts the `-N` collision suffix added by unique archive paths.
+fn parse_archive_name(name: &str) -> Option<(String, String)> {
+    let tilde_pos = name.rfind('~')?;
+    let stem = &name[..tilde_pos];
+    let rest = &name[tilde_pos + 1..];
+    let ts = rest.get(..15)?;
+    chrono::NaiveDateTime::parse_from_str(ts, ARCHIVE_TS_FORMAT).ok()?;
+    let mut tail = &rest[15..];
+    if let Some(after_dash) = tail.strip_prefix('-') {
+        let digits = after_dash
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after_dash.len());
+        if digits > 0 {
+            tail = &after_dash[digits..];
+        }
+    }
+    if !tail.is_empty() && !tail.starts_with('.') {
+        return None;
+    }
+    Some((format!("{}{}", stem, tail), ts.to_string()))
+}
+
+fn archived_at_time(ts: &str) -> Option<SystemTime> {
+    let naive = chrono::NaiveDateTime::parse_from_str(ts, ARCHIVE_TS_FORMAT).ok()?;
+    let secs = u64::try_from(naive.and_utc().timestamp()).ok()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn remote_join(root: &str, relative: &str) -> String {
+    let root = root.trim_end_matches('/');
+    let relative = relative.trim_start_matches('/');
+    if relative.is_empty() {
+        return if root.is_empty() {
+            "/".to_string()
+        } else {
+            root.to_string()
+        };
+    }
+    format!("{}/{}", root, relative)
+}
+
+/// Create each missing level of `relative_dir` under `remote_root`. Checks
+/// before creating because some providers duplicate existing folders on mkdir.
+async fn ensure_remote_dirs<P: StorageProvider + ?Sized>(
+    provider: &mut P,
+    remote_root: &str,
+    relative_dir: &str,
+) {
+    let mut current = String::new();
+    for part in relative_dir.split('/').filter(|p| !p.is_empty()) {
+        if !current.is_empty() {
+            current.push('/');
+        }
+        current.push_str(part);
+        let path = remote_join(remote_root, &current);
+        if provider.stat(&path).await.is_err() {
+            let _ = provider.mkdir(&path).await;
+        }
+    }
+}
+
+async fn unique_remote_archive_path<P: StorageProvider + ?Sized>(
+    provider: &mut P,
+    archive_dir: &str,
+    archive_name: &str,
+) -> String {
+    let candidate = remote_join(archive_dir, archive_name);
+    if !provider.exists(&candidate).await.unwrap_or(false) {
+        return candidate;
+    }
+    let path = Path::new(archive_name);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| archive_name.to_string());
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    for suffix in 1..=999u16 {
+        let alternative = remote_join(archive_dir, &format!("{}-{}{}", stem, suffix, ext));
+        if !provider.exists(&alternative).await.unwrap_or(false) {
+            return alternative;
+        }
+    }
+    remote_join(archive_dir, &format!("{}-overflow{}", stem, ext))
+}
+
+/// Walk `<remote_root>/.aeroversions/` and return `(relative_dir, file)` pairs,
+/// where `relative_dir` is relative to the archive folder. A missing archive
+/// folder yields an empty list.
+async fn walk_remote_versions<P: StorageProvider + ?Sized>(
+    provider: &mut P,
+    remote_root: &str,
+) -> Result<Vec<(String, crate::providers::RemoteEntry)>, String> {
+    let base = remote_join(remote_root, VERSIONS_DIR_NAME);
+    let mut files = Vec::new();
+    let mut stack = vec![String::new()];
+    while let Some(dir_rel) = stack.pop() {
+        let dir = remote_join(&base, &dir_rel);
+        let entries = match provider.list(&dir).await {
+            Ok(entries) => entries,
+            Err(_) if dir_rel.is_empty() => return Ok(files),
+            Err(e) => return Err(format!("Failed to list {}: {}", dir, e)),
+        };
+        for entry in entries {
+            if entry.name == "." || entry.name == ".." {
+                continue;
+            }
+            let child_rel = if dir_rel.is_empty() {
+                entry.name.clone()
+            } else {
+                format!("{}/{}", dir_rel, entry.name)
+            };
+            if entry.is_dir {
+                if stack.len() < 10_000 {
+                    stack.push(child_rel);
+                }
+            } else {
+                files.push((dir_rel.clone(), entry));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// One archived copy as seen by the retention policy.
+#[derive(Debug, Clone)]
+struct RetentionItem {
+    /// Versions of the same original file share a group (`None`: ungrouped)
+    group: Option<String>,
+    /// Archive file name (timestamp embedded, sorts chronologically)
+    name: String,
+    /// When the copy was archived, if known
+    archived: Option<SystemTime>,
+}
+
+/// Indices of `items` the strategy no longer wants to keep.
+fn select_expired(
+    strategy: &VersioningStrategy,
+    now: SystemTime,
+    items: &[RetentionItem],
+) -> Vec<usize> {
+    let mut expired = Vec::new();
+    match strategy {
+        VersioningStrategy::Disabled => {}
+        VersioningStrategy::TrashCan { max_age_days } => {
+            let cutoff = now - Duration::from_secs(u64::from(*max_age_days) * 86400);
+            for (index, item) in items.iter().enumerate() {
+                if item.archived.is_some_and(|archived| archived < cutoff) {
+                    expired.push(index);
+                }
+            }
+        }
+        VersioningStrategy::Simple { max_copies } => {
+            for mut indices in group_indices(items) {
+                // Timestamp is in the name: newest first
+                indices.sort_by(|a, b| items[*b].name.cmp(&items[*a].name));
+                expired.extend(indices.into_iter().skip(*max_copies as usize));
+            }
+        }
+        VersioningStrategy::Staggered => {
+            // 1/hour for 24h, 1/day for 30d, 1/week older
+            for mut indices in group_indices(items) {
+                indices.retain(|i| items[*i].archived.is_some());
+                indices.sort_by_key(|i| std::cmp::Reverse(items[*i].archived));
+                let mut kept_buckets = std::collections::HashSet::new();
+                for index in indices {
+                    let age_secs = items[index]
+                        .archived
+                        .and_then(|archived| now.duration_since(archived).ok())
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0);
+                    let bucket = if age_secs < 86400 {
+                        (0u8, age_secs / 3600)
+                    } else if age_secs < 86400 * 30 {
+                        (1, age_secs / 86400)
+                    } else {
+                        (2, age_secs / (86400 * 7))
+                    };
+                    if !kept_buckets.insert(bucket) {
+                        expired.push(index);
+                    }
+                }
+            }
+        }
+    }
+    expired.sort_unstable();
+    expired
+}
+
+fn group_indices(items: &[RetentionItem]) -> Vec<Vec<usize>> {
+    let mut groups: std::collections::HashMap<&str, Vec<usize>> = std::collections::HashMap::new();
+    for (index, item) in items.iter().enumerate() {
+        if let Some(group) = item.group.as_deref() {
+            groups.entry(group).or_default().push(index);
+        }
+    }
+    groups.into_values().collect()
 }
 
 #[cfg(test)]
@@ -579,4 +939,96 @@ mod tests {
         let versions = versioning.list_versions("docs/report.txt").unwrap();
         assert_eq!(versions.len(), 2);
     }
+
+    #[test]
+    fn cleanup_simple_keeps_newest_copies_per_file() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path();
+        let versions = root.join(".aeroversions/docs");
+        for ts in ["20260101-000000", "20260102-000000", "20260103-000000"] {
+            write_file(&versions.join(format!("report~{}.txt", ts)), "v");
+        }
+        write_file(&versions.join("other~20260101-000000.txt"), "v");
+
+        let versioning = SyncVersioning::new(root, VersioningStrategy::Simple { max_copies: 2 });
+        let stats = versioning.cleanup().unwrap();
+        assert_eq!(stats.deleted_count, 1);
+        assert!(!versions.join("report~20260101-000000.txt").exists());
+        assert!(versions.join("report~20260103-000000.txt").exists());
+        assert!(versions.join("other~20260101-000000.txt").exists());
+    }
+
+    #[test]
+    fn strategy_from_name_accepts_retention_parameter() {
+        assert_eq!(
+            VersioningStrategy::from_name("simple:10"),
+            Some(VersioningStrategy::Simple { max_copies: 10 })
+        );
+        assert_eq!(
+            VersioningStrategy::from_name("trash_can"),
+            Some(VersioningStrategy::TrashCan { max_age_days: 30 })
+        );
+        assert_eq!(
+            VersioningStrategy::from_name("disabled"),
+            Some(VersioningStrategy::Disabled)
+        );
+        assert_eq!(VersioningStrategy::from_name("staggered:3"), None);
+        assert_eq!(VersioningStrategy::from_name("simple:x"), None);
+        assert_eq!(VersioningStrategy::from_name("weekly"), None);
+    }
+
+    #[test]
+    fn parse_archive_name_round_trips_with_collision_suffix() {
+        let name = archive_file_name(Path::new("a~b.tar.gz"), "20260412-101500");
+        assert_eq!(name, "a~b.tar~20260412-101500.gz");
+        assert_eq!(
+            parse_archive_name(&name),
+            Some(("a~b.tar.gz".to_string(), "20260412-101500".to_string()))
+        );
+        assert_eq!(
+            parse_archive_name("Makefile~20260412-101500-3"),
+            Some(("Makefile".to_string(), "20260412-101500".to_string()))
+        );
+        assert_eq!(parse_archive_name("notes.txt"), None);
+        assert_eq!(parse_archive_name("x~yesterday.txt"), None);
+    }
+
+    #[test]
+    fn versions_path_detection_only_matches_archive_root() {
+        assert!(is_versions_path(".aeroversions"));
+        assert!(is_versions_path(".aeroversions/docs/a~20260101-000000.txt"));
+        assert!(!is_versions_path(".aeroversions-old/a.txt"));
+        assert!(!is_versions_path("docs/.aeroversions/a.txt"));
+    }
+
+    #[test]
+    fn select_expired_uses_archive_time_for_trash_can() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(100 * 86400);
+        let item = |days_ago: u64| RetentionItem {
+            group: Some("docs/report.txt".to_string()),
+            name: String::new(),
+            archived: Some(now - Duration::from_secs(days_ago * 86400)),
+        };
+        let items = vec![item(1), item(45), item(29)];
+        let expired = select_expired(
+            &VersioningStrategy::TrashCan { max_age_days: 30 },
+            now,
+            &items,
+        );
+        assert_eq!(expired, vec![1]);
+    }
+
+    #[test]
+    fn select_expired_staggered_keeps_one_per_hour_bucket() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(100 * 86400);
+        let item = |secs_ago: u64| RetentionItem {
+            group: Some("a.txt".to_string()),
+            name: String::new(),
+            archived: Some(now - Duration::from_secs(secs_ago)),
+        };
+        // Two copies in the first hour, one in the second
+        let items = vec![item(60), item(1800), item(4000)];
+        let expired = select_expired(&VersioningStrategy::Staggered, now, &items);
+        assert_eq!(expired, vec![1]);
+    }
 }
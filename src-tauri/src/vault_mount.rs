@@ -0,0 +1,876 @@
+//! Folder view of an AeroVault v2 container behind `aeroftp-cli vault mount`.
+//!
+//! The `aerovault` crate works on whole entries and rewrites the container
+//! on every change, so a mount cannot hand each `write()` to the vault. The
+//! tree is read from the manifest once. A file is decrypted into a private
+//! staging directory the first time it is opened, and reads and writes go
+//! to that plaintext copy. [`VaultMount::commit`] then replays the
+//! difference between the tree and the vault as a few batched operations
+//! (deletes, moves, new folders, re-encrypted files) and compacts the
+//! container so replaced data does not pile up. The CLI commits on unmount
+//! and, with `--commit-interval`, periodically.
+//!
+//! A failed commit leaves the vault consistent: every step is one atomic
+//! vault rewrite, and the tree records how far it got, so the next commit
+//! picks up from there. New content is written before anything is deleted,
+//! so even a crash mid-commit loses no data; at worst a modified file is
+//! left next to its old version under a `.aeroftp-vault-mount-*` name.
+//! Plaintext only lives in the staging directory (mode 0700);
+//! [`VaultMount::close`] zero-fills and removes it.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use crate::vault_remote::wipe_file;
+use aerovault::Vault;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Inode of the vault root.
+pub const ROOT_INO: u64 = 1;
+/// Entries are parked under this name while a commit reorders the tree.
+const PARK_PREFIX: &str = ".aeroftp-vault-mount-";
+
+/// A file or folder of the mounted vault.
+#[derive(Debug, Clone)]
+pub struct VaultNode {
+    pub name: String,
+    pub parent: u64,
+    pub is_dir: bool,
+    pub size: u64,
+    pub mtime: SystemTime,
+    children: BTreeMap<String, u64>,
+    /// Vault entry holding this file's content. `None` for new files and
+    /// once the content changed locally.
+    origin: Option<String>,
+    /// Decrypted copy in the staging directory.
+    staged: Option<PathBuf>,
+}
+
+/// What a commit wrote to the vault.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitStats {
+    pub deleted: usize,
+    pub moved: usize,
+    pub created_dirs: usize,
+    pub written: usize,
+    /// Bytes reclaimed by the closing compaction.
+    pub reclaimed: u64,
+}
+
+/// An unlocked vault presented as a directory tree.
+pub struct VaultMount {
+    vault: Vault,
+    staging: PathBuf,
+    nodes: HashMap<u64, VaultNode>,
+    next_ino: u64,
+    dirty: bool,
+    /// Makes the next commit fail right before it deletes entries.
+    #[cfg(test)]
+    fail_before_delete: bool,
+}
+
+impl VaultMount {
+    /// Build the tree from the vault manifest. `staging` receives decrypted
+    /// copies and is created with owner-only permissions.
+    pub fn open(vault: Vault, staging: &Path) -> Result<Self, String> {
+        fs::create_dir_all(staging)
+            .map_err(|e| format!("Cannot create {}: {}", staging.display(), e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(staging, fs::Permissions::from_mode(0o700))
+                .map_err(|e| format!("Cannot protect {}: {}", staging.display(), e))?;
+        }
+
+        let mut entries = vault.list().map_err(|e| e.to_string())?;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut mount = Self {
+            vault,
+            staging: staging.to_path_buf(),
+            nodes: HashMap::new(),
+            next_ino: ROOT_INO + 1,
+            dirty: false,
+            #[cfg(test)]
+            fail_before_delete: false,
+        };
+        mount
+            .nodes
+            .insert(ROOT_INO, new_node(String::new(), ROOT_INO, true));
+
+        for entry in entries {
+            let name = entry.name.trim_matches('/');
+            if name.is_empty() {
+                continue;
+            }
+            let (parent_path, leaf) = name.rsplit_once('/').unwrap_or(("", name));
+            let parent = mount.ensure_dir(parent_path);
+            let mtime = parse_modified(&entry.modified);
+            if entry.is_dir {
+                let ino = mount.ensure_dir(name);
+                if let Some(node) = mount.nodes.get_mut(&ino) {
+                    node.mtime = mtime;
+                }
+                continue;
+            }
+            let mut node = new_node(leaf.to_string(), parent, false);
+            node.size = entry.size;
+            node.mtime = mtime;
+            node.origin = Some(name.to_string());
+            mount.insert(node);
+        }
+        Ok(mount)
+    }
+
+    pub fn node(&self, ino: u64) -> Option<&VaultNode> {
+        self.nodes.get(&ino)
+    }
+
+    pub fn lookup(&self, parent: u64, name: &str) -> Option<u64> {
+        self.nodes.get(&parent)?.children.get(name).copied()
+    }
+
+    /// Children of a folder, sorted by name.
+    pub fn children(&self, ino: u64) -> Vec<u64> {
+        self.nodes
+            .get(&ino)
+            .map(|n| n.children.values().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Path of `ino` inside the vault (`""` for the root).
+    pub fn path(&self, ino: u64) -> String {
+        let mut parts = Vec::new();
+        let mut cur = ino;
+        while cur != ROOT_INO {
+            let Some(node) = self.nodes.get(&cur) else {
+                break;
+            };
+            parts.push(node.name.as_str());
+            cur = node.parent;
+        }
+        parts.reverse();
+        parts.join("/")
+    }
+
+    /// True when the tree holds changes the vault does not have yet.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Read from a file, decrypting it on first access.
+    pub fn read(&mut self, ino: u64, offset: u64, len: u32) -> io::Result<Vec<u8>> {
+        let staged = self.stage(ino)?;
+        let mut file = File::open(staged)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buf = Vec::with_capacity(len as usize);
+        file.take(len as u64).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn write(&mut self, ino: u64, offset: u64, data: &[u8]) -> io::Result<u32> {
+        let staged = self.stage(ino)?;
+        let mut file = OpenOptions::new().write(true).open(staged)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(data)?;
+        let end = offset + data.len() as u64;
+        let node = self.file_mut(ino)?;
+        node.size = node.size.max(end);
+        self.touch(ino);
+        Ok(data.len() as u32)
+    }
+
+    /// Resize a file. Truncating to zero skips decrypting the old content.
+    pub fn truncate(&mut self, ino: u64, size: u64) -> io::Result<()> {
+        if size == 0 {
+            let dir = self.staging.join(ino.to_string());
+            fs::create_dir_all(&dir)?;
+            let node = self.file_mut(ino)?;
+            let staged = node.staged.clone().unwrap_or_else(|| dir.join(&node.name));
+            File::create(&staged)?;
+            node.staged = Some(staged);
+        } else {
+            let staged = self.stage(ino)?;
+            OpenOptions::new().write(true).open(staged)?.set_len(size)?;
+        }
+        self.file_mut(ino)?.size = size;
+        self.touch(ino);
+        Ok(())
+    }
+
+    pub fn create(&mut self, parent: u64, name: &str) -> io::Result<u64> {
+        self.check_free(parent, name)?;
+        let mut node = new_node(name.to_string(), parent, false);
+        node.mtime = SystemTime::now();
+        let ino = self.insert(node);
+        let dir = self.staging.join(ino.to_string());
+        let staged = fs::create_dir_all(&dir)
+            .and_then(|_| File::create(dir.join(name)))
+            .map(|_| dir.join(name));
+        match staged {
+            Ok(staged) => {
+                self.file_mut(ino)?.staged = Some(staged);
+                self.touch(parent);
+                self.touch(ino);
+                Ok(ino)
+            }
+            Err(e) => {
+                self.detach(ino);
+                Err(e)
+            }
+        }
+    }
+
+    pub fn mkdir(&mut self, parent: u64, name: &str) -> io::Result<u64> {
+        self.check_free(parent, name)?;
+        let mut node = new_node(name.to_string(), parent, true);
+        node.mtime = SystemTime::now();
+        let ino = self.insert(node);
+        self.touch(parent);
+        Ok(ino)
+    }
+
+    pub fn unlink(&mut self, parent: u64, name: &str) -> io::Result<()> {
+        let ino = self.lookup(parent, name).ok_or(io::ErrorKind::NotFound)?;
+        self.file_mut(ino)?;
+        self.touch(parent);
+        self.detach(ino);
+        Ok(())
+    }
+
+    pub fn rmdir(&mut self, parent: u64, name: &str) -> io::Result<()> {
+        let ino = self.lookup(parent, name).ok_or(io::ErrorKind::NotFound)?;
+        let node = &self.nodes[&ino];
+        if !node.is_dir {
+            return Err(io::ErrorKind::NotADirectory.into());
+        }
+        if !node.children.is_empty() {
+            return Err(io::ErrorKind::DirectoryNotEmpty.into());
+        }
+        self.touch(parent);
+        self.detach(ino);
+        Ok(())
+    }
+
+    /// Move `parent/name` to `new_parent/new_name`, replacing a file or an
+    /// empty folder of the same kind already there.
+    pub fn rename(
+        &mut self,
+        parent: u64,
+        name: &str,
+        new_parent: u64,
+        new_name: &str,
+    ) -> io::Result<()> {
+        let ino = self.lookup(parent, name).ok_or(io::ErrorKind::NotFound)?;
+        check_name(new_name)?;
+        if !self.nodes.get(&new_parent).is_some_and(|n| n.is_dir) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        // A folder cannot move below itself
+        let mut cur = new_parent;
+        while cur != ROOT_INO {
+            if cur == ino {
+                return Err(io::ErrorKind::InvalidInput.into());
+            }
+            cur = self.nodes[&cur].parent;
+        }
+
+        let is_dir = self.nodes[&ino].is_dir;
+        if let Some(target) = self.lookup(new_parent, new_name) {
+            if target == ino {
+                return Ok(());
+            }
+            let existing = &self.nodes[&target];
+            match (is_dir, existing.is_dir) {
+                (false, true) => return Err(io::ErrorKind::IsADirectory.into()),
+                (true, false) => return Err(io::ErrorKind::NotADirectory.into()),
+                (true, true) if !existing.children.is_empty() => {
+                    return Err(io::ErrorKind::DirectoryNotEmpty.into())
+                }
+                _ => self.detach(target),
+            }
+        }
+
+        if let Some(node) = self.nodes.get_mut(&parent) {
+            node.children.remove(name);
+        }
+        if let Some(node) = self.nodes.get_mut(&new_parent) {
+            node.children.insert(new_name.to_string(), ino);
+        }
+        let node = self.nodes.get_mut(&ino).ok_or(io::ErrorKind::NotFound)?;
+        node.name = new_name.to_string();
+        node.parent = new_parent;
+        self.touch(parent);
+        self.touch(new_parent);
+        Ok(())
+    }
+
+    /// Write every local change into the vault, then compact it when
+    /// entries were dropped or replaced.
+    pub fn commit(&mut self) -> Result<CommitStats, String> {
+        let mut stats = CommitStats::default();
+        if !self.dirty {
+            return Ok(stats);
+        }
+
+        // Desired tree
+        let mut dirs = BTreeSet::new();
+        let mut files = Vec::new();
+        let mut stack = vec![ROOT_INO];
+        while let Some(ino) = stack.pop() {
+            for child in self.children(ino) {
+                let path = self.path(child);
+                if self.nodes[&child].is_dir {
+                    dirs.insert(path);
+                    stack.push(child);
+                } else {
+                    files.push((child, path));
+                }
+            }
+        }
+
+        let mut current: HashMap<String, bool> = self
+            .vault
+            .list()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|e| (e.name.trim_matches('/').to_string(), e.is_dir))
+            .collect();
+
+        // 1. New and modified content first, so no entry is deleted before
+        //    what replaces it is in the vault: straight into place when the
+        //    path is free, otherwise parked at the root and moved below
+        let mut uploads: BTreeMap<String, Vec<(u64, PathBuf, String)>> = BTreeMap::new();
+        for (ino, path) in &files {
+            if self.nodes[ino].origin.is_some() {
+                continue;
+            }
+            let (parent, leaf) = path.rsplit_once('/').unwrap_or(("", path));
+            let in_place = !current.contains_key(path)
+                && (parent.is_empty()
+                    || (current.get(parent) == Some(&true) && dirs.contains(parent)));
+            let (dir, name) = if in_place {
+                (parent, path.clone())
+            } else {
+                ("", park_name(*ino, &current))
+            };
+            let file_name = if in_place { leaf } else { name.as_str() };
+            let staged = self
+                .named_copy(*ino, file_name)
+                .map_err(|e| format!("Cannot stage {}: {}", path, e))?;
+            uploads
+                .entry(dir.to_string())
+                .or_default()
+                .push((*ino, staged, name));
+        }
+        let mut fresh = HashSet::new();
+        for (dir, batch) in uploads {
+            let paths: Vec<&PathBuf> = batch.iter().map(|(_, p, _)| p).collect();
+            let added = self
+                .vault
+                .add_files_to_dir(&paths, &dir)
+                .map_err(|e| format!("Cannot write into /{}: {}", dir, e))?;
+            if added as usize != batch.len() {
+                return Err(format!(
+                    "Only {} of {} files written into /{}",
+                    added,
+                    batch.len(),
+                    dir
+                ));
+            }
+            for (ino, _, name) in batch {
+                current.insert(name.clone(), false);
+                if let Some(node) = self.nodes.get_mut(&ino) {
+                    node.origin = Some(name);
+                }
+                fresh.insert(ino);
+            }
+            stats.written += added as usize;
+        }
+
+        // 2. Content nobody refers to any more
+        let claimed: HashSet<&str> = files
+            .iter()
+            .filter_map(|(ino, _)| self.nodes[ino].origin.as_deref())
+            .collect();
+        let stale: Vec<&str> = current
+            .iter()
+            .filter(|(name, is_dir)| !**is_dir && !claimed.contains(name.as_str()))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !stale.is_empty() {
+            #[cfg(test)]
+            if self.fail_before_delete {
+                return Err("Injected failure".to_string());
+            }
+            let deleted = self
+                .vault
+                .delete_entries(&stale, false)
+                .map_err(|e| format!("Cannot delete entries: {}", e))?;
+            stats.deleted += deleted as usize;
+            let stale: Vec<String> = stale.into_iter().map(str::to_string).collect();
+            for name in stale {
+                current.remove(&name);
+            }
+        }
+
+        // 3. Moved files go straight to a free destination, or are parked
+        //    at the root until the folders below are rebuilt
+        let mut parked = Vec::new();
+        for (ino, path) in &files {
+            let Some(origin) = self.nodes[ino].origin.clone() else {
+                continue;
+            };
+            if origin == *path {
+                continue;
+            }
+            let parent_ok = match path.rsplit_once('/') {
+                Some((parent, _)) => current.get(parent) == Some(&true) && dirs.contains(parent),
+                None => true,
+            };
+            let target = if !current.contains_key(path) && parent_ok {
+                path.clone()
+            } else if origin.starts_with(PARK_PREFIX) {
+                parked.push((*ino, path.clone()));
+                continue;
+            } else {
+                parked.push((*ino, path.clone()));
+                park_name(*ino, &current)
+            };
+            self.move_entry(*ino, &origin, &target, &mut current)?;
+            stats.moved += usize::from(target == *path && !fresh.contains(ino));
+        }
+
+        // 4. Folders that are gone
+        let stale_dirs: Vec<&str> = current
+            .iter()
+            .filter(|(name, is_dir)| **is_dir && !dirs.contains(name.as_str()))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !stale_dirs.is_empty() {
+            let deleted = self
+                .vault
+                .delete_entries(&stale_dirs, false)
+                .map_err(|e| format!("Cannot delete folders: {}", e))?;
+            stats.deleted += deleted as usize;
+            current.retain(|name, is_dir| !*is_dir || dirs.contains(name));
+        }
+
+        // 5. New folders; creating the deepest ones makes their parents too
+        let missing: Vec<&String> = dirs
+            .iter()
+            .filter(|d| current.get(d.as_str()) != Some(&true))
+            .collect();
+        for dir in &missing {
+            let prefix = format!("{}/", dir);
+            if missing.iter().any(|other| other.starts_with(&prefix)) {
+                continue;
+            }
+            let created = self
+                .vault
+                .create_directory(dir)
+                .map_err(|e| format!("Cannot create folder {}: {}", dir, e))?;
+            stats.created_dirs += created as usize;
+        }
+        for dir in missing {
+            current.insert(dir.clone(), true);
+        }
+
+        // 6. Parked files to their place
+        for (ino, path) in parked {
+            let origin = self.nodes[&ino].origin.clone().unwrap_or_default();
+            self.move_entry(ino, &origin, &path, &mut current)?;
+            stats.moved += usize::from(!fresh.contains(&ino));
+        }
+
+        // 7. Reclaim the space of deleted and replaced content
+        if stats.deleted > 0 {
+            let compacted = self
+                .vault
+                .compact()
+                .map_err(|e| format!("Cannot compact vault: {}", e))?;
+            stats.reclaimed = compacted.saved_bytes;
+        }
+
+        self.dirty = false;
+        Ok(stats)
+    }
+
+    /// Wipe the decrypted copies and the staging directory.
+    pub fn close(self) {
+        for node in self.nodes.values() {
+            if let Some(staged) = &node.staged {
+                let _ = wipe_file(staged);
+            }
+        }
+        let _ = fs::remove_dir_all(&self.staging);
+    }
+
+    fn insert(&mut self, node: VaultNode) -> u64 {
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        if let Some(parent) = self.nodes.get_mut(&node.parent) {
+            parent.children.insert(node.name.clone(), ino);
+        }
+        self.nodes.insert(ino, node);
+        ino
+    }
+
+    /// Inode of the folder at `path`, creating missing folders on the way.
+    fn ensure_dir(&mut self, path: &str) -> u64 {
+        let mut cur = ROOT_INO;
+        for part in path.split('/').filter(|p| !p.is_empty()) {
+            cur = match self.lookup(cur, part) {
+                Some(ino) => ino,
+                None => self.insert(new_node(part.to_string(), cur, true)),
+            };
+        }
+        cur
+    }
+
+    /// Drop `ino` (and anything below it) from the tree.
+    fn detach(&mut self, ino: u64) {
+        let Some(node) = self.nodes.remove(&ino) else {
+            return;
+        };
+        if let Some(parent) = self.nodes.get_mut(&node.parent) {
+            if parent.children.get(&node.name) == Some(&ino) {
+                parent.children.remove(&node.name);
+            }
+        }
+        if let Some(staged) = &node.staged {
+            let _ = wipe_file(staged);
+            if let Some(dir) = staged.parent() {
+                let _ = fs::remove_dir(dir);
+            }
+        }
+        for child in node.children.into_values() {
+            self.detach(child);
+        }
+    }
+
+    fn check_free(&self, parent: u64, name: &str) -> io::Result<()> {
+        check_name(name)?;
+        let dir = self.nodes.get(&parent).ok_or(io::ErrorKind::NotFound)?;
+        if !dir.is_dir {
+            return Err(io::ErrorKind::NotADirectory.into());
+        }
+        if dir.children.contains_key(name) {
+            return Err(io::ErrorKind::AlreadyExists.into());
+        }
+        Ok(())
+    }
+
+    fn file_mut(&mut self, ino: u64) -> io::Result<&mut VaultNode> {
+        let node = self.nodes.get_mut(&ino).ok_or(io::ErrorKind::NotFound)?;
+        if node.is_dir {
+            return Err(io::ErrorKind::IsADirectory.into());
+        }
+        Ok(node)
+    }
+
+    /// Record a local change to `ino`: its content no longer matches the
+    /// vault entry it came from.
+    fn touch(&mut self, ino: u64) {
+        if let Some(node) = self.nodes.get_mut(&ino) {
+            node.mtime = SystemTime::now();
+            if !node.is_dir {
+                node.origin = None;
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Decrypted copy of a file, extracting it on first use.
+    fn stage(&mut self, ino: u64) -> io::Result<PathBuf> {
+        let node = self.file_mut(ino)?;
+        if let Some(staged) = &node.staged {
+            return Ok(staged.clone());
+        }
+        let origin = node.origin.clone().ok_or(io::ErrorKind::NotFound)?;
+        let dir = self.staging.join(ino.to_string());
+        fs::create_dir_all(&dir)?;
+        let staged = self
+            .vault
+            .extract(&origin, &dir)
+            .map_err(io::Error::other)?;
+        self.file_mut(ino)?.staged = Some(staged.clone());
+        Ok(staged)
+    }
+
+    /// The staged copy renamed to `name`, which is what `add_files_to_dir`
+    /// stores it as.
+    fn named_copy(&mut self, ino: u64, name: &str) -> io::Result<PathBuf> {
+        let node = self.file_mut(ino)?;
+        let staged = node.staged.clone().ok_or(io::ErrorKind::NotFound)?;
+        let named = staged.with_file_name(name);
+        if named != staged {
+            fs::rename(&staged, &named)?;
+            node.staged = Some(named.clone());
+        }
+        Ok(named)
+    }
+
+    fn move_entry(
+        &mut self,
+        ino: u64,
+        from: &str,
+        to: &str,
+        current: &mut HashMap<String, bool>,
+    ) -> Result<(), String> {
+        self.vault
+            .move_entry(from, to)
+            .map_err(|e| format!("Cannot move {} to {}: {}", from, to, e))?;
+        current.remove(from);
+        current.insert(to.to_string(), false);
+        if let Some(node) = self.nodes.get_mut(&ino) {
+            node.origin = Some(to.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Root-level name to park `ino` under that is not taken in the vault, even
+/// by an entry an interrupted commit left behind.
+fn park_name(ino: u64, current: &HashMap<String, bool>) -> String {
+    let mut name = format!("{}{}", PARK_PREFIX, ino);
+    while current.contains_key(&name) {
+        name.push('_');
+    }
+    name
+}
+
+fn new_node(name: String, parent: u64, is_dir: bool) -> VaultNode {
+    VaultNode {
+        name,
+        parent,
+        is_dir,
+        size: 0,
+        mtime: UNIX_EPOCH,
+        children: BTreeMap::new(),
+        origin: None,
+        staged: None,
+    }
+}
+
+/// Names the vault accepts as a single path segment.
+fn check_name(name: &str) -> io::Result<()> {
+    if name.is_empty()
+        || name.contains(['/', '\\', '\0'])
+        || name.contains("..")
+        || name.starts_with(PARK_PREFIX)
+    {
+        return Err(io::ErrorKind::InvalidInput.into());
+    }
+    Ok(())
+}
+
+fn parse_modified(modified: &str) -> SystemTime {
+    chrono::DateTime::parse_from_rfc3339(modified)
+        .ok()
+        .and_then(|dt| u64::try_from(dt.timestamp()).ok())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+        .unwrap_or(UNIX_EPOCH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aerovault::CreateOptions;
+
+    const PASSWORD: &str = "correct horse battery";
+
+    fn vault_with(dir: &Path, files: &[(&str, &[u8])]) -> PathBuf {
+        let path = dir.join("test.aerovault");
+        let vault = Vault::create(CreateOptions::new(&path, PASSWORD)).unwrap();
+        vault.create_directory("docs").unwrap();
+        for (name, data) in files {
+            let src = dir.join("src");
+            fs::create_dir_all(&src).unwrap();
+            let (parent, leaf) = name.rsplit_once('/').unwrap_or(("", name));
+            fs::write(src.join(leaf), data).unwrap();
+            vault.add_files_to_dir(&[src.join(leaf)], parent).unwrap();
+            fs::remove_file(src.join(leaf)).unwrap();
+        }
+        path
+    }
+
+    fn contents(path: &Path) -> BTreeMap<String, Option<Vec<u8>>> {
+        let vault = Vault::open(path, PASSWORD).unwrap();
+        let out = tempfile::tempdir().unwrap();
+        vault
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|e| {
+                let data = (!e.is_dir).then(|| {
+                    let dir = out.path().join(&e.name);
+                    fs::create_dir_all(&dir).unwrap();
+                    fs::read(vault.extract(&e.name, &dir).unwrap()).unwrap()
+                });
+                (e.name, data)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn files_are_decrypted_on_first_read_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = vault_with(tmp.path(), &[("docs/a.txt", b"alpha"), ("b.txt", b"beta")]);
+        let staging = tmp.path().join("staging");
+        let mut mount = VaultMount::open(Vault::open(&path, PASSWORD).unwrap(), &staging).unwrap();
+
+        let docs = mount.lookup(ROOT_INO, "docs").unwrap();
+        let a = mount.lookup(docs, "a.txt").unwrap();
+        assert_eq!(mount.path(a), "docs/a.txt");
+        assert_eq!(mount.node(a).unwrap().size, 5);
+        assert_eq!(fs::read_dir(&staging).unwrap().count(), 0);
+
+        assert_eq!(mount.read(a, 1, 3).unwrap(), b"lph");
+        assert_eq!(fs::read_dir(&staging).unwrap().count(), 1);
+        assert!(!mount.is_dirty());
+        assert_eq!(mount.commit().unwrap(), CommitStats::default());
+
+        mount.close();
+        assert!(!staging.exists());
+    }
+
+    #[test]
+    fn commit_replays_local_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = vault_with(
+            tmp.path(),
+            &[
+                ("docs/a.txt", b"alpha"),
+                ("b.txt", b"beta"),
+                ("c.txt", b"gamma"),
+            ],
+        );
+        let mut mount = VaultMount::open(
+            Vault::open(&path, PASSWORD).unwrap(),
+            &tmp.path().join("staging"),
+        )
+        .unwrap();
+
+        let docs = mount.lookup(ROOT_INO, "docs").unwrap();
+        let a = mount.lookup(docs, "a.txt").unwrap();
+        mount.write(a, 5, b"-bet").unwrap();
+        let new = mount.create(docs, "new.txt").unwrap();
+        mount.write(new, 0, b"fresh").unwrap();
+        let sub = mount.mkdir(ROOT_INO, "archive").unwrap();
+        mount.rename(ROOT_INO, "b.txt", sub, "b.txt").unwrap();
+        mount.unlink(ROOT_INO, "c.txt").unwrap();
+        assert_eq!(
+            mount.rmdir(ROOT_INO, "docs").unwrap_err().kind(),
+            io::ErrorKind::DirectoryNotEmpty
+        );
+        assert_eq!(
+            mount.create(ROOT_INO, "a..b").unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+
+        let stats = mount.commit().unwrap();
+        assert_eq!(stats.moved, 1);
+        assert_eq!(stats.written, 2);
+        assert!(!mount.is_dirty());
+        mount.close();
+
+        let after = contents(&path);
+        let names: Vec<&str> = after.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [
+                "archive",
+                "archive/b.txt",
+                "docs",
+                "docs/a.txt",
+                "docs/new.txt"
+            ]
+        );
+        assert_eq!(after["docs/a.txt"].as_deref(), Some(&b"alpha-bet"[..]));
+        assert_eq!(after["docs/new.txt"].as_deref(), Some(&b"fresh"[..]));
+        assert_eq!(after["archive/b.txt"].as_deref(), Some(&b"beta"[..]));
+    }
+
+    #[test]
+    fn interrupted_commit_keeps_old_and_new_content() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = vault_with(tmp.path(), &[("docs/a.txt", b"alpha"), ("b.txt", b"beta")]);
+        let mut mount = VaultMount::open(
+            Vault::open(&path, PASSWORD).unwrap(),
+            &tmp.path().join("staging"),
+        )
+        .unwrap();
+
+        let docs = mount.lookup(ROOT_INO, "docs").unwrap();
+        let a = mount.lookup(docs, "a.txt").unwrap();
+        mount.write(a, 5, b"-bet").unwrap();
+        let b = mount.lookup(ROOT_INO, "b.txt").unwrap();
+        mount.write(b, 0, b"B").unwrap();
+        mount.rename(ROOT_INO, "b.txt", docs, "b.txt").unwrap();
+
+        mount.fail_before_delete = true;
+        assert!(mount.commit().is_err());
+        assert!(mount.is_dirty());
+
+        // What a crash at this point leaves on disk: every old entry, and
+        // the new content either in place or parked at the root
+        let after = contents(&path);
+        assert_eq!(after["docs/a.txt"].as_deref(), Some(&b"alpha"[..]));
+        assert_eq!(after["b.txt"].as_deref(), Some(&b"beta"[..]));
+        assert_eq!(after["docs/b.txt"].as_deref(), Some(&b"Beta"[..]));
+        let parked: Vec<&Option<Vec<u8>>> = after
+            .iter()
+            .filter(|(name, _)| name.starts_with(PARK_PREFIX))
+            .map(|(_, data)| data)
+            .collect();
+        assert_eq!(parked, [&Some(b"alpha-bet".to_vec())]);
+
+        mount.fail_before_delete = false;
+        let stats = mount.commit().unwrap();
+        assert_eq!(stats.deleted, 2);
+        mount.close();
+
+        let after = contents(&path);
+        let names: Vec<&str> = after.keys().map(String::as_str).collect();
+        assert_eq!(names, ["docs", "docs/a.txt", "docs/b.txt"]);
+        assert_eq!(after["docs/a.txt"].as_deref(), Some(&b"alpha-bet"[..]));
+        assert_eq!(after["docs/b.txt"].as_deref(), Some(&b"Beta"[..]));
+    }
+
+    #[test]
+    fn swapped_names_and_replaced_folders_commit_cleanly() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = vault_with(
+            tmp.path(),
+            &[("x.txt", b"x"), ("y.txt", b"y"), ("docs/z", b"z")],
+        );
+        let mut mount = VaultMount::open(
+            Vault::open(&path, PASSWORD).unwrap(),
+            &tmp.path().join("staging"),
+        )
+        .unwrap();
+
+        mount.rename(ROOT_INO, "x.txt", ROOT_INO, "tmp").unwrap();
+        mount.rename(ROOT_INO, "y.txt", ROOT_INO, "x.txt").unwrap();
+        mount.rename(ROOT_INO, "tmp", ROOT_INO, "y.txt").unwrap();
+        // A file takes the place of a folder that moved away
+        mount.rename(ROOT_INO, "docs", ROOT_INO, "papers").unwrap();
+        let docs = mount.create(ROOT_INO, "docs").unwrap();
+        mount.write(docs, 0, b"now a file").unwrap();
+
+        mount.commit().unwrap();
+        mount.close();
+
+        let after = contents(&path);
+        assert_eq!(after["x.txt"].as_deref(), Some(&b"y"[..]));
+        assert_eq!(after["y.txt"].as_deref(), Some(&b"x"[..]));
+        assert_eq!(after["papers/z"].as_deref(), Some(&b"z"[..]));
+        assert_eq!(after["docs"].as_deref(), Some(&b"now a file"[..]));
+        assert!(!after.keys().any(|k| k.starts_with(PARK_PREFIX)));
+    }
+}
@@ -13,7 +13,8 @@
 // Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
 
 use crate::provider_commands::ProviderState;
-use std::path::PathBuf;
+use crate::providers::StorageProvider;
+use std::path::{Path, PathBuf};
 use tauri::State;
 
 /// Validate a path has no null bytes (defense-in-depth for C FFI providers).
@@ -31,14 +32,10 @@ fn is_managed_temp_vault_filename(file_name: &str) -> bool {
     file_name.starts_with("aerovault_remote_") && file_name.ends_with(".aerovault")
 }
 
-/// Download a remote .aerovault file to a temporary local path.
-/// Returns the temporary local file path.
-#[tauri::command]
-pub async fn vault_v2_download_remote(
-    state: State<'_, ProviderState>,
-    remote_path: String,
-) -> Result<String, String> {
-    validate_no_null_bytes(&remote_path)?;
+/// Reject remote vault paths that are not `.aerovault` files or that try
+/// to escape their directory.
+fn validate_remote_vault_path(remote_path: &str) -> Result<(), String> {
+    validate_no_null_bytes(remote_path)?;
 
     if !remote_path.ends_with(".aerovault") {
         return Err("File must have .aerovault extension".into());
@@ -47,23 +44,31 @@ pub async fn vault_v2_download_remote(
     if remote_path.contains("..") {
         return Err("Path traversal not allowed".into());
     }
+    Ok(())
+}
 
-    let temp_dir = std::env::temp_dir();
+/// Fresh temp path for a downloaded vault, matching the pattern that
+/// [`cleanup_temp`] accepts.
+pub fn temp_vault_path() -> PathBuf {
     let temp_name = format!("aerovault_remote_{}.aerovault", uuid::Uuid::new_v4());
-    let local_path = temp_dir.join(&temp_name);
+    std::env::temp_dir().join(temp_name)
+}
 
-    // Get the active provider and download
-    let mut provider_guard = state.provider.lock().await;
-    let provider = provider_guard
-        .as_mut()
-        .ok_or("No active connection. Connect to a server first.")?;
+/// Download a remote .aerovault file to a new temp file only the current
+/// user can read. Used by the GUI and by `aeroftp-cli vault mount`.
+pub async fn download_to_temp(
+    provider: &mut dyn StorageProvider,
+    remote_path: &str,
+) -> Result<PathBuf, String> {
+    validate_remote_vault_path(remote_path)?;
 
+    let local_path = temp_vault_path();
     let local_str = local_path
         .to_str()
         .ok_or("Temp path contains invalid UTF-8")?;
 
     provider
-        .download(&remote_path, local_str, None)
+        .download(remote_path, local_str, None)
         .await
         .map_err(|e| format!("Download failed: {}", e))?;
 
@@ -83,18 +88,17 @@ pub async fn vault_v2_download_remote(
         return Err("Downloaded file is empty".into());
     }
 
-    Ok(local_path.to_string_lossy().to_string())
+    Ok(local_path)
 }
 
-/// Upload a local vault file back to the remote server.
-#[tauri::command]
-pub async fn vault_v2_upload_remote(
-    state: State<'_, ProviderState>,
-    local_path: String,
-    remote_path: String,
+/// Upload a local vault file to `remote_path`.
+pub async fn upload_vault(
+    provider: &mut dyn StorageProvider,
+    local_path: &str,
+    remote_path: &str,
 ) -> Result<(), String> {
-    validate_no_null_bytes(&local_path)?;
-    validate_no_null_bytes(&remote_path)?;
+    validate_no_null_bytes(local_path)?;
+    validate_no_null_bytes(remote_path)?;
 
     if !local_path.ends_with(".aerovault") || !remote_path.ends_with(".aerovault") {
         return Err("Files must have .aerovault extension".into());
@@ -105,31 +109,63 @@ pub async fn vault_v2_upload_remote(
     }
 
     // Verify local file is in temp directory (defense-in-depth for upload)
-    let path = PathBuf::from(&local_path);
+    let path = PathBuf::from(local_path);
     if !path.exists() {
         return Err("Local vault file not found".into());
     }
 
+    provider
+        .upload(local_path, remote_path, None)
+        .await
+        .map_err(|e| format!("Upload failed: {}", e))
+}
+
+/// Download a remote .aerovault file to a temporary local path.
+/// Returns the temporary local file path.
+#[tauri::command]
+pub async fn vault_v2_download_remote(
+    state: State<'_, ProviderState>,
+    remote_path: String,
+) -> Result<String, String> {
+    validate_remote_vault_path(&remote_path)?;
+
+    // Get the active provider and download
     let mut provider_guard = state.provider.lock().await;
     let provider = provider_guard
         .as_mut()
         .ok_or("No active connection. Connect to a server first.")?;
 
-    provider
-        .upload(&local_path, &remote_path, None)
-        .await
-        .map_err(|e| format!("Upload failed: {}", e))?;
+    let local_path = download_to_temp(provider.as_mut(), &remote_path).await?;
+    Ok(local_path.to_string_lossy().to_string())
+}
 
-    Ok(())
+/// Upload a local vault file back to the remote server.
+#[tauri::command]
+pub async fn vault_v2_upload_remote(
+    state: State<'_, ProviderState>,
+    local_path: String,
+    remote_path: String,
+) -> Result<(), String> {
+    let mut provider_guard = state.provider.lock().await;
+    let provider = provider_guard
+        .as_mut()
+        .ok_or("No active connection. Connect to a server first.")?;
+
+    upload_vault(provider.as_mut(), &local_path, &remote_path).await
 }
 
 /// Clean up a temporary vault file securely.
-/// Validates: temp directory confinement, filename pattern, no symlinks.
 #[tauri::command]
 pub fn vault_v2_cleanup_temp(local_path: String) -> Result<(), String> {
-    validate_no_null_bytes(&local_path)?;
+    cleanup_temp(&local_path)
+}
 
-    let path = PathBuf::from(&local_path);
+/// Securely remove a temp vault created by [`download_to_temp`].
+/// Validates: temp directory confinement, filename pattern, no symlinks.
+pub fn cleanup_temp(local_path: &str) -> Result<(), String> {
+    validate_no_null_bytes(local_path)?;
+
+    let path = PathBuf::from(local_path);
 
     // Validate filename pattern: only clean up files we created
     let file_name = path
@@ -162,16 +198,18 @@ pub fn vault_v2_cleanup_temp(local_path: String) -> Result<(), String> {
         return Err("Can only clean up files in temp directory".into());
     }
 
-    // Zero-fill before delete for security (best-effort on modern storage)
-    if let Ok(metadata) = std::fs::symlink_metadata(&canonical_path) {
+    wipe_file(&canonical_path)
+}
+
+/// Zero-fill a file (best-effort on modern storage) and delete it. Also
+/// used for the decrypted copies staged by a vault mount.
+pub fn wipe_file(path: &Path) -> Result<(), String> {
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
         let size = metadata.len();
         if size > 0 {
             let chunk_size = std::cmp::min(size as usize, 1024 * 1024); // 1MB chunks
             let zeros = vec![0u8; chunk_size];
-            if let Ok(mut file) = std::fs::OpenOptions::new()
-                .write(true)
-                .open(&canonical_path)
-            {
+            if let Ok(mut file) = std::fs::OpenOptions::new().write(true).open(path) {
                 use std::io::Write;
                 let mut remaining = size;
                 let mut write_failed = false;
@@ -188,7 +226,7 @@ pub fn vault_v2_cleanup_temp(local_path: String) -> Result<(), String> {
 
                 if write_failed {
                     // Still delete even if zero-fill failed
-                    let _ = std::fs::remove_file(&canonical_path);
+                    let _ = std::fs::remove_file(path);
                     return Err(
                         "Secure zero-fill incomplete; file deleted without full overwrite".into(),
                     );
@@ -197,8 +235,7 @@ pub fn vault_v2_cleanup_temp(local_path: String) -> Result<(), String> {
         }
     }
 
-    std::fs::remove_file(&canonical_path)
-        .map_err(|e| format!("Failed to remove temp file: {}", e))?;
+    std::fs::remove_file(path).map_err(|e| format!("Failed to remove temp file: {}", e))?;
 
     Ok(())
 }
@@ -0,0 +1,1196 @@
+//! Persistent on-disk cache behind `aeroftp-cli mount`.
+//!
+//! Without it the FUSE layer keeps only a metadata TTL cache: every read
+//! goes to the provider and writes are staged in throwaway temp files that
+//! are uploaded synchronously when the file is closed. This module gives a
+//! mount a cache directory holding one sparse data file per remote file,
+//! plus a JSON sidecar recording which chunks of the data file are filled,
+//! the remote fingerprint (size and mtime) they were fetched against, and
+//! whether the file carries local changes that are not uploaded yet.
+//!
+//! The cache modes follow rclone's `--vfs-cache-mode`:
+//! - `off`: nothing persists. Reads stream from the provider, and writes
+//!   are staged and uploaded when the file is closed.
+//! - `writes`: written files live in the cache. A background queue uploads
+//!   them `--vfs-write-back` after the last close, so closing a file no
+//!   longer waits for the network.
+//! - `full`: reads are cached too, chunk by chunk. The least recently used
+//!   entries are evicted once the cache exceeds `--vfs-cache-max-size`, and
+//!   entries unused for longer than `--vfs-cache-max-age` are dropped.
+//!
+//! The dirty flag reaches the sidecar before the first local byte reaches
+//! the data file. A crash or kill therefore leaves exactly the set of files
+//! that still have to be uploaded, and [`VfsCache::open`] queues them
+//! again. Dirty and open entries are never evicted.
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Granularity of the read cache and of provider range requests.
+pub const DEFAULT_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+/// Extra bytes fetched past a sequential read.
+pub const DEFAULT_READ_AHEAD: u64 = 1024 * 1024;
+/// Clean entries unused for this long are evicted.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(3600);
+/// Delay between the last close of a written file and its upload.
+pub const DEFAULT_WRITE_BACK: Duration = Duration::from_secs(5);
+/// Upper bound for the retry delay of a failing upload.
+const MAX_UPLOAD_BACKOFF: Duration = Duration::from_secs(300);
+
+const META_VERSION: u32 = 1;
+const DATA_DIR: &str = "data";
+const META_DIR: &str = "meta";
+/// Remote mtime of a file this cache just uploaded. The provider assigns
+/// the real value, which is adopted the next time the file is seen with the
+/// uploaded size.
+const MTIME_PENDING: i64 = i64::MIN;
+
+/// What the mount keeps on local disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VfsCacheMode {
+    /// Stream reads, upload writes on close.
+    #[default]
+    Off,
+    /// Cache written files and upload them in the background.
+    Writes,
+    /// Cache reads and writes.
+    Full,
+}
+
+impl VfsCacheMode {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "writes" => Ok(Self::Writes),
+            "full" => Ok(Self::Full),
+            other => Err(format!(
+                "Unknown VFS cache mode '{}' (expected off, writes or full)",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Writes => "writes",
+            Self::Full => "full",
+        }
+    }
+
+    /// Whether written files go through the cache and the upload queue.
+    pub fn caches_writes(self) -> bool {
+        self != Self::Off
+    }
+
+    /// Whether remote content read through the mount is kept on disk.
+    pub fn caches_reads(self) -> bool {
+        self == Self::Full
+    }
+}
+
+/// Tuning knobs of a mount's cache, mirrored by the `--vfs-*` flags.
+#[derive(Debug, Clone)]
+pub struct VfsCacheOptions {
+    pub mode: VfsCacheMode,
+    /// Evict clean entries once the cache holds more than this many bytes.
+    pub max_size: Option<u64>,
+    pub max_age: Duration,
+    pub chunk_size: u64,
+    pub read_ahead: u64,
+    pub write_back: Duration,
+}
+
+impl Default for VfsCacheOptions {
+    fn default() -> Self {
+        Self {
+            mode: VfsCacheMode::default(),
+            max_size: None,
+            max_age: DEFAULT_MAX_AGE,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            read_ahead: DEFAULT_READ_AHEAD,
+            write_back: DEFAULT_WRITE_BACK,
+        }
+    }
+}
+
+/// Default cache directory for a mount, keyed by whatever identifies the
+/// remote tree (server URL plus base path).
+pub fn default_cache_root(identity: &str) -> Option<PathBuf> {
+    let digest = hex::encode(Sha256::digest(identity.as_bytes()));
+    dirs::cache_dir().map(|dir| dir.join("aeroftp").join("vfs").join(&digest[..16]))
+}
+
+/// Sidecar of one cached file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ItemMeta {
+    version: u32,
+    path: String,
+    size: u64,
+    /// Remote mtime (Unix seconds) the cached chunks belong to.
+    mtime: i64,
+    chunk_size: u64,
+    #[serde(default)]
+    chunks: BTreeSet<u64>,
+    /// Unix seconds of the last read or write, for LRU eviction.
+    last_access: u64,
+    #[serde(default)]
+    dirty: bool,
+    /// Created through the mount and never uploaded.
+    #[serde(default)]
+    local_only: bool,
+}
+
+impl ItemMeta {
+    fn new(path: &str, size: u64, mtime: i64, chunk_size: u64) -> Self {
+        Self {
+            version: META_VERSION,
+            path: path.to_string(),
+            size,
+            mtime,
+            chunk_size,
+            chunks: BTreeSet::new(),
+            last_access: now_secs(),
+            dirty: false,
+            local_only: false,
+        }
+    }
+
+    fn chunk_count(&self) -> u64 {
+        self.size.div_ceil(self.chunk_size)
+    }
+
+    fn fill_chunks(&mut self) {
+        self.chunks = (0..self.chunk_count()).collect();
+    }
+
+    fn is_complete(&self) -> bool {
+        self.dirty || self.chunks.len() as u64 == self.chunk_count()
+    }
+
+    /// Bytes of the data file that hold cached content.
+    fn cached_bytes(&self) -> u64 {
+        if self.dirty {
+            return self.size;
+        }
+        self.chunks
+            .iter()
+            .map(|&i| {
+                let start = i * self.chunk_size;
+                self.size.saturating_sub(start).min(self.chunk_size)
+            })
+            .sum()
+    }
+
+    /// Whether every chunk overlapping `offset..end` is present.
+    fn covers(&self, offset: u64, end: u64) -> bool {
+        if self.dirty || offset >= end {
+            return true;
+        }
+        let first = offset / self.chunk_size;
+        let last = (end - 1) / self.chunk_size;
+        (first..=last).all(|i| self.chunks.contains(&i))
+    }
+}
+
+struct Item {
+    meta: ItemMeta,
+    /// Open file handles; open entries are not evicted.
+    open: u32,
+    /// Handles open for writing; a file is not uploaded while written.
+    writers: u32,
+    /// Bumped on every local change, so an upload that raced a write does
+    /// not clear the dirty flag.
+    generation: u64,
+    /// When the queued upload becomes due.
+    due: Option<Instant>,
+    attempts: u32,
+    /// The sidecar lags behind `meta`: chunks were stored since the last
+    /// save. Only clean content is deferred this way, so a crash merely
+    /// forgets chunks that are fetched again.
+    unsaved: bool,
+}
+
+impl Item {
+    fn new(meta: ItemMeta) -> Self {
+        Self {
+            meta,
+            open: 0,
+            writers: 0,
+            generation: 1,
+            due: None,
+            attempts: 0,
+            unsaved: false,
+        }
+    }
+}
+
+/// A dirty file handed to the uploader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingUpload {
+    pub path: String,
+    pub local: PathBuf,
+    /// Pass back to [`VfsCache::upload_finished`] or
+    /// [`VfsCache::upload_failed`].
+    pub generation: u64,
+}
+
+/// Cache directory of one mount. All methods take `&self` and may be called
+/// from the FUSE thread and the upload task at the same time.
+pub struct VfsCache {
+    root: PathBuf,
+    opts: VfsCacheOptions,
+    items: Mutex<HashMap<String, Item>>,
+    /// Sum of `cached_bytes` over `items`, only changed with `items` locked.
+    cached_bytes: AtomicU64,
+}
+
+impl VfsCache {
+    /// Open (or create) the cache at `root`. Dirty entries left behind by an
+    /// earlier run are queued for upload right away.
+    pub fn open(root: impl Into<PathBuf>, opts: VfsCacheOptions) -> io::Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(root.join(DATA_DIR))?;
+        fs::create_dir_all(root.join(META_DIR))?;
+        let cache = Self {
+            root,
+            opts,
+            items: Mutex::new(HashMap::new()),
+            cached_bytes: AtomicU64::new(0),
+        };
+        cache.recover()?;
+        cache.evict()?;
+        Ok(cache)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn options(&self) -> &VfsCacheOptions {
+        &self.opts
+    }
+
+    /// Local file holding the cached content of `path`.
+    pub fn data_path(&self, path: &str) -> PathBuf {
+        self.root.join(DATA_DIR).join(item_id(path))
+    }
+
+    fn meta_path(&self, path: &str) -> PathBuf {
+        self.root
+            .join(META_DIR)
+            .join(format!("{}.json", item_id(path)))
+    }
+
+    fn recover(&self) -> io::Result<()> {
+        let now = Instant::now();
+        let mut items = HashMap::new();
+        for entry in fs::read_dir(self.root.join(META_DIR))? {
+            let file = entry?.path();
+            if file.extension().and_then(|e| e.to_str()) != Some("json") {
+                // Half-written sidecar from an interrupted save.
+                let _ = fs::remove_file(&file);
+                continue;
+            }
+            let meta = fs::read(&file)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<ItemMeta>(&bytes).ok())
+                .filter(|m| m.version == META_VERSION && file == self.meta_path(&m.path));
+            let Some(mut meta) = meta else {
+                let _ = fs::remove_file(&file);
+                continue;
+            };
+            let data = self.data_path(&meta.path);
+            let Ok(data_len) = fs::metadata(&data).map(|m| m.len()) else {
+                let _ = fs::remove_file(&file);
+                continue;
+            };
+            if meta.dirty {
+                // Writes after the last sidecar save may have grown the file.
+                meta.size = data_len;
+                meta.fill_chunks();
+            }
+            if meta.chunk_size != self.opts.chunk_size {
+                // The chunk map is meaningless under another chunk size.
+                meta.chunk_size = self.opts.chunk_size;
+                if meta.dirty {
+                    meta.fill_chunks();
+                } else {
+                    meta.chunks.clear();
+                    File::create(&data)?;
+                }
+                self.save_meta(&meta)?;
+            }
+            let mut item = Item::new(meta);
+            if item.meta.dirty {
+                item.due = Some(now);
+            }
+            items.insert(item.meta.path.clone(), item);
+        }
+
+        // Data files without a sidecar are leftovers of an interrupted store.
+        let known: HashSet<String> = items.keys().map(|p| item_id(p)).collect();
+        for entry in fs::read_dir(self.root.join(DATA_DIR))? {
+            let entry = entry?;
+            if !known.contains(&*entry.file_name().to_string_lossy()) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+
+        let total = items.values().map(|i| i.meta.cached_bytes()).sum();
+        self.cached_bytes.store(total, Ordering::Relaxed);
+        *self.items.lock().unwrap() = items;
+        Ok(())
+    }
+
+    /// Write the sidecar through a temp file, so a crash leaves either the
+    /// old or the new version.
+    fn save_meta(&self, meta: &ItemMeta) -> io::Result<()> {
+        let path = self.meta_path(&meta.path);
+        let tmp = path.with_extension("json.tmp");
+        {
+            let mut file = File::create(&tmp)?;
+            file.write_all(&serde_json::to_vec(meta).map_err(io::Error::other)?)?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp, &path)
+    }
+
+    /// Save the sidecar of `item` and clear its deferred state.
+    fn save_item(&self, item: &mut Item) -> io::Result<()> {
+        self.save_meta(&item.meta)?;
+        item.unsaved = false;
+        Ok(())
+    }
+
+    /// Write every sidecar left behind by [`VfsCache::store`].
+    pub fn flush(&self) -> io::Result<()> {
+        let mut items = self.items.lock().unwrap();
+        for item in items.values_mut().filter(|i| i.unsaved) {
+            self.save_item(item)?;
+        }
+        Ok(())
+    }
+
+    /// Account for an entry whose cached content went from `before` to
+    /// `after` bytes. Call with `items` locked.
+    fn recount(&self, before: u64, after: u64) {
+        if after >= before {
+            self.cached_bytes
+                .fetch_add(after - before, Ordering::Relaxed);
+        } else {
+            self.cached_bytes
+                .fetch_sub(before - after, Ordering::Relaxed);
+        }
+    }
+
+    fn remove_files(&self, path: &str) {
+        let _ = fs::remove_file(self.meta_path(path));
+        let _ = fs::remove_file(self.data_path(path));
+    }
+
+    /// Start tracking `path` at the remote fingerprint `size`/`mtime`.
+    /// Chunks fetched against another fingerprint are dropped. A dirty entry
+    /// is left alone, since it is newer than anything on the remote.
+    pub fn prepare(&self, path: &str, size: u64, mtime: i64) -> io::Result<()> {
+        let mut items = self.items.lock().unwrap();
+        if let Some(item) = items.get_mut(path) {
+            let meta = &mut item.meta;
+            if meta.dirty {
+                return Ok(());
+            }
+            if meta.size == size && (meta.mtime == mtime || meta.mtime == MTIME_PENDING) {
+                if meta.mtime != mtime {
+                    meta.mtime = mtime;
+                    self.save_item(item)?;
+                }
+                return Ok(());
+            }
+            self.recount(meta.cached_bytes(), 0);
+            meta.size = size;
+            meta.mtime = mtime;
+            meta.chunks.clear();
+            self.save_item(item)?;
+            File::create(self.data_path(path))?;
+            return Ok(());
+        }
+
+        File::create(self.data_path(path))?;
+        let meta = ItemMeta::new(path, size, mtime, self.opts.chunk_size);
+        self.save_meta(&meta)?;
+        items.insert(path.to_string(), Item::new(meta));
+        Ok(())
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.items.lock().unwrap().contains_key(path)
+    }
+
+    /// Whether the whole file is available locally.
+    pub fn is_complete(&self, path: &str) -> bool {
+        self.items
+            .lock()
+            .unwrap()
+            .get(path)
+            .is_some_and(|i| i.meta.is_complete())
+    }
+
+    pub fn is_dirty(&self, path: &str) -> bool {
+        self.items
+            .lock()
+            .unwrap()
+            .get(path)
+            .is_some_and(|i| i.meta.dirty)
+    }
+
+    /// Created through the mount and not uploaded yet, so the remote does
+    /// not know the file.
+    pub fn is_local_only(&self, path: &str) -> bool {
+        self.items
+            .lock()
+            .unwrap()
+            .get(path)
+            .is_some_and(|i| i.meta.local_only)
+    }
+
+    /// Byte ranges of `offset..offset + len` that still have to be fetched,
+    /// rounded out to whole chunks and merged into contiguous runs.
+    pub fn missing_ranges(&self, path: &str, offset: u64, len: u64) -> Vec<(u64, u64)> {
+        let items = self.items.lock().unwrap();
+        let Some(item) = items.get(path) else {
+            return Vec::new();
+        };
+        let meta = &item.meta;
+        let end = offset.saturating_add(len).min(meta.size);
+        if meta.dirty || offset >= end {
+            return Vec::new();
+        }
+        let mut runs: Vec<(u64, u64)> = Vec::new();
+        for index in offset / meta.chunk_size..=(end - 1) / meta.chunk_size {
+            if meta.chunks.contains(&index) {
+                continue;
+            }
+            let start = index * meta.chunk_size;
+            let chunk_len = (meta.size - start).min(meta.chunk_size);
+            match runs.last_mut() {
+                Some((run_start, run_len)) if *run_start + *run_len == start => {
+                    *run_len += chunk_len
+                }
+                _ => runs.push((start, chunk_len)),
+            }
+        }
+        runs
+    }
+
+    /// Store remote content fetched at `offset`. Every chunk the data fully
+    /// covers is marked present. The sidecar is saved on the next close,
+    /// eviction or [`VfsCache::flush`], not once per chunk.
+    pub fn store(&self, path: &str, offset: u64, data: &[u8]) -> io::Result<()> {
+        let over_limit = {
+            let mut items = self.items.lock().unwrap();
+            let Some(item) = items.get_mut(path) else {
+                return Ok(());
+            };
+            let meta = &mut item.meta;
+            if meta.dirty {
+                // The local copy wins over whatever the remote returned.
+                return Ok(());
+            }
+            write_at(&self.data_path(path), offset, data)?;
+            let before = meta.cached_bytes();
+            let end = offset + data.len() as u64;
+            let mut index = offset.div_ceil(meta.chunk_size);
+            while index < meta.chunk_count() {
+                let chunk_end = ((index + 1) * meta.chunk_size).min(meta.size);
+                if chunk_end > end {
+                    break;
+                }
+                meta.chunks.insert(index);
+                index += 1;
+            }
+            meta.last_access = now_secs();
+            self.recount(before, meta.cached_bytes());
+            item.unsaved = true;
+            self.opts.max_size.is_some_and(|max| self.usage() > max)
+        };
+        if over_limit {
+            self.evict()?;
+        }
+        Ok(())
+    }
+
+    /// Read `len` bytes at `offset` if the range is cached. Reads past the
+    /// end of the file are cut short, as with a regular file.
+    pub fn read(&self, path: &str, offset: u64, len: u64) -> io::Result<Option<Vec<u8>>> {
+        let mut items = self.items.lock().unwrap();
+        let Some(item) = items.get_mut(path) else {
+            return Ok(None);
+        };
+        let end = offset.saturating_add(len).min(item.meta.size);
+        if offset >= end {
+            return Ok(Some(Vec::new()));
+        }
+        if !item.meta.covers(offset, end) {
+            return Ok(None);
+        }
+        item.meta.last_access = now_secs();
+        let mut file = File::open(self.data_path(path))?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buf = vec![0u8; (end - offset) as usize];
+        file.read_exact(&mut buf)?;
+        Ok(Some(buf))
+    }
+
+    /// Take over a complete download of `path`, for providers without range
+    /// reads.
+    pub fn adopt(&self, path: &str, downloaded: &Path) -> io::Result<()> {
+        {
+            let mut items = self.items.lock().unwrap();
+            let Some(item) = items.get_mut(path).filter(|i| !i.meta.dirty) else {
+                // Gone, or the local copy is newer.
+                return fs::remove_file(downloaded);
+            };
+            let data = self.data_path(path);
+            fs::rename(downloaded, &data)?;
+            let before = item.meta.cached_bytes();
+            item.meta.size = fs::metadata(&data)?.len();
+            item.meta.fill_chunks();
+            item.meta.last_access = now_secs();
+            self.recount(before, item.meta.cached_bytes());
+            self.save_item(item)?;
+        }
+        if self.opts.max_size.is_some_and(|max| self.usage() > max) {
+            self.evict()?;
+        }
+        Ok(())
+    }
+
+    /// Start an empty, dirty local version of `path`: a new file created
+    /// through the mount (`local_only`), or an existing one truncated to
+    /// zero, which needs nothing from the remote.
+    pub fn create(&self, path: &str, local_only: bool) -> io::Result<()> {
+        let mut items = self.items.lock().unwrap();
+        // Replacing an open entry keeps its handles.
+        let (open, writers, generation, was_local, bytes) =
+            items.get(path).map_or((0, 0, 0, false, 0), |i| {
+                (
+                    i.open,
+                    i.writers,
+                    i.generation,
+                    i.meta.local_only,
+                    i.meta.cached_bytes(),
+                )
+            });
+        File::create(self.data_path(path))?;
+        self.recount(bytes, 0);
+        let mut meta = ItemMeta::new(path, 0, now_secs() as i64, self.opts.chunk_size);
+        meta.dirty = true;
+        meta.local_only = local_only || was_local;
+        self.save_meta(&meta)?;
+        let mut item = Item::new(meta);
+        item.open = open;
+        item.writers = writers;
+        item.generation = generation + 1;
+        item.due = self.write_back_due(&item);
+        items.insert(path.to_string(), item);
+        Ok(())
+    }
+
+    /// Write local changes. The entry must be complete: the caller fetches
+    /// the missing ranges first.
+    pub fn write(&self, path: &str, offset: u64, data: &[u8]) -> io::Result<()> {
+        self.modify(path, |file, meta| {
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(data)?;
+            meta.size = meta.size.max(offset + data.len() as u64);
+            Ok(())
+        })
+    }
+
+    pub fn truncate(&self, path: &str, size: u64) -> io::Result<()> {
+        self.modify(path, |file, meta| {
+            file.set_len(size)?;
+            meta.size = size;
+            Ok(())
+        })
+    }
+
+    fn modify(
+        &self,
+        path: &str,
+        change: impl FnOnce(&mut File, &mut ItemMeta) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut items = self.items.lock().unwrap();
+        let item = items
+            .get_mut(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not in the VFS cache"))?;
+        if !item.meta.is_complete() {
+            return Err(io::Error::other("file is not fully cached"));
+        }
+        let before = item.meta.cached_bytes();
+        if !item.meta.dirty {
+            // Persist the flag first: after a crash the file must still be
+            // known as modified.
+            item.meta.dirty = true;
+            self.save_item(item)?;
+        }
+        let mut file = OpenOptions::new().write(true).open(self.data_path(path))?;
+        let changed = change(&mut file, &mut item.meta);
+        self.recount(before, item.meta.cached_bytes());
+        changed?;
+        item.meta.fill_chunks();
+        item.meta.last_access = now_secs();
+        item.generation += 1;
+        item.due = self.write_back_due(item);
+        Ok(())
+    }
+
+    /// A change made without an open handle (`truncate(2)`) is queued
+    /// right away; otherwise the last close queues it.
+    fn write_back_due(&self, item: &Item) -> Option<Instant> {
+        (item.writers == 0).then(|| Instant::now() + self.opts.write_back)
+    }
+
+    /// Register an open handle. Open entries are never evicted, and a file
+    /// open for writing is not uploaded.
+    pub fn open_file(&self, path: &str, write: bool) {
+        if let Some(item) = self.items.lock().unwrap().get_mut(path) {
+            item.open += 1;
+            if write {
+                item.writers += 1;
+            }
+        }
+    }
+
+    /// Drop a handle. Closing the last writer of a dirty file flushes it to
+    /// disk and queues its upload after the write-back delay.
+    pub fn close_file(&self, path: &str, write: bool) -> io::Result<()> {
+        let mut items = self.items.lock().unwrap();
+        let Some(item) = items.get_mut(path) else {
+            return Ok(());
+        };
+        item.open = item.open.saturating_sub(1);
+        if write {
+            item.writers = item.writers.saturating_sub(1);
+        }
+        if item.meta.dirty && item.writers == 0 {
+            File::open(self.data_path(path))?.sync_all()?;
+            item.due = Some(Instant::now() + self.opts.write_back);
+        }
+        self.save_item(item)
+    }
+
+    /// Flush local changes of `path` to disk (FUSE `flush`/`fsync`).
+    pub fn sync(&self, path: &str) -> io::Result<()> {
+        let mut items = self.items.lock().unwrap();
+        match items.get_mut(path) {
+            Some(item) if item.meta.dirty => {
+                File::open(self.data_path(path))?.sync_all()?;
+                self.save_item(item)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Uploads whose write-back delay has passed.
+    pub fn due_uploads(&self) -> Vec<PendingUpload> {
+        let now = Instant::now();
+        self.collect_uploads(|item| item.writers == 0 && item.due.is_some_and(|due| due <= now))
+    }
+
+    /// Every dirty file, due or not, for the final flush on unmount.
+    pub fn pending_uploads(&self) -> Vec<PendingUpload> {
+        self.collect_uploads(|_| true)
+    }
+
+    fn collect_uploads(&self, wanted: impl Fn(&Item) -> bool) -> Vec<PendingUpload> {
+        let items = self.items.lock().unwrap();
+        let mut uploads: Vec<PendingUpload> = items
+            .values()
+            .filter(|item| item.meta.dirty && wanted(item))
+            .map(|item| PendingUpload {
+                path: item.meta.path.clone(),
+                local: self.data_path(&item.meta.path),
+                generation: item.generation,
+            })
+            .collect();
+        uploads.sort_by(|a, b| a.path.cmp(&b.path));
+        uploads
+    }
+
+    /// Record a finished upload. Returns false if the file changed or moved
+    /// in the meantime, in which case it stays queued.
+    pub fn upload_finished(&self, path: &str, generation: u64) -> io::Result<bool> {
+        let mut items = self.items.lock().unwrap();
+        let Some(item) = items.get_mut(path) else {
+            return Ok(false);
+        };
+        if item.generation != generation || !item.meta.dirty {
+            return Ok(false);
+        }
+        item.meta.dirty = false;
+        item.meta.local_only = false;
+        item.meta.fill_chunks();
+        item.meta.mtime = MTIME_PENDING;
+        item.due = None;
+        item.attempts = 0;
+        self.save_item(item)?;
+        Ok(true)
+    }
+
+    /// Requeue a failed upload with exponential backoff. Returns the delay,
+    /// or `None` if the file changed in the meantime.
+    pub fn upload_failed(&self, path: &str, generation: u64) -> Option<Duration> {
+        let mut items = self.items.lock().unwrap();
+        let item = items.get_mut(path)?;
+        if item.generation != generation || !item.meta.dirty {
+            return None;
+        }
+        item.attempts += 1;
+        let base = self.opts.write_back.max(Duration::from_secs(1));
+        let delay = base
+            .saturating_mul(1 << item.attempts.min(16))
+            .min(MAX_UPLOAD_BACKOFF);
+        item.due = Some(Instant::now() + delay);
+        Some(delay)
+    }
+
+    /// Move `from` and, for a directory, everything below it to `to`.
+    /// Whatever was cached under the new names is replaced.
+    pub fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        if from == to {
+            return Ok(());
+        }
+        let mut items = self.items.lock().unwrap();
+        let prefix = format!("{}/", from);
+        let moved: Vec<String> = items
+            .keys()
+            .filter(|p| *p == from || p.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for old in moved {
+            let Some(mut item) = items.remove(&old) else {
+                continue;
+            };
+            let new = format!("{}{}", to, &old[from.len()..]);
+            if let Some(replaced) = items.remove(&new) {
+                self.recount(replaced.meta.cached_bytes(), 0);
+                self.remove_files(&new);
+            }
+            // Link, record, then unlink: a crash at any step leaves at
+            // least one complete pair of data file and sidecar.
+            let _ = fs::remove_file(self.data_path(&new));
+            fs::hard_link(self.data_path(&old), self.data_path(&new))?;
+            item.meta.path = new.clone();
+            item.generation += 1;
+            self.save_item(&mut item)?;
+            self.remove_files(&old);
+            if item.meta.dirty && item.writers == 0 {
+                item.due = Some(Instant::now() + self.opts.write_back);
+            }
+            items.insert(new, item);
+        }
+        Ok(())
+    }
+
+    /// Forget `path` and everything below it.
+    pub fn remove(&self, path: &str) {
+        let mut items = self.items.lock().unwrap();
+        let prefix = format!("{}/", path);
+        let gone: Vec<String> = items
+            .keys()
+            .filter(|p| *p == path || p.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for p in gone {
+            if let Some(item) = items.remove(&p) {
+                self.recount(item.meta.cached_bytes(), 0);
+            }
+            self.remove_files(&p);
+        }
+    }
+
+    /// Size and local mtime of a dirty file, which override the remote
+    /// listing until the upload lands.
+    pub fn dirty_attr(&self, path: &str) -> Option<(u64, SystemTime)> {
+        let items = self.items.lock().unwrap();
+        let item = items.get(path).filter(|i| i.meta.dirty)?;
+        Some((item.meta.size, self.local_mtime(path)))
+    }
+
+    /// Dirty files directly inside `dir`, so files not uploaded yet still
+    /// show up in listings.
+    pub fn dirty_children(&self, dir: &str) -> Vec<(String, u64, SystemTime)> {
+        let items = self.items.lock().unwrap();
+        let mut children: Vec<(String, u64, SystemTime)> = items
+            .values()
+            .filter(|i| i.meta.dirty && parent_of(&i.meta.path) == dir)
+            .map(|i| {
+                (
+                    i.meta.path.clone(),
+                    i.meta.size,
+                    self.local_mtime(&i.meta.path),
+                )
+            })
+            .collect();
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+        children
+    }
+
+    fn local_mtime(&self, path: &str) -> SystemTime {
+        fs::metadata(self.data_path(path))
+            .and_then(|m| m.modified())
+            .unwrap_or_else(|_| SystemTime::now())
+    }
+
+    /// Bytes of cached content on disk.
+    pub fn usage(&self) -> u64 {
+        self.cached_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Evict closed, clean entries: those unused for longer than `max_age`,
+    /// then the least recently used ones until the cache fits `max_size`.
+    /// The sidecars of the surviving entries are brought up to date.
+    /// Returns the number of bytes freed.
+    pub fn evict(&self) -> io::Result<u64> {
+        let mut items = self.items.lock().unwrap();
+        let now = now_secs();
+        let max_age = self.opts.max_age.as_secs();
+        let mut total = self.usage();
+        let mut candidates: Vec<(u64, String)> = items
+            .iter()
+            .filter(|(_, i)| i.open == 0 && !i.meta.dirty)
+            .map(|(p, i)| (i.meta.last_access, p.clone()))
+            .collect();
+        candidates.sort();
+
+        let mut freed = 0;
+        for (last_access, path) in candidates {
+            let expired = now.saturating_sub(last_access) > max_age;
+            let over = self.opts.max_size.is_some_and(|max| total > max);
+            if !expired && !over {
+                // Oldest first: nothing after this one is due either.
+                break;
+            }
+            if let Some(item) = items.remove(&path) {
+                let bytes = item.meta.cached_bytes();
+                self.remove_files(&path);
+                self.recount(bytes, 0);
+                total -= bytes;
+                freed += bytes;
+            }
+        }
+        for item in items.values_mut().filter(|i| i.unsaved) {
+            self.save_item(item)?;
+        }
+        Ok(freed)
+    }
+
+    /// Whether a complete download of `path` fits within `max_size`.
+    pub fn fits_whole(&self, path: &str) -> bool {
+        let items = self.items.lock().unwrap();
+        match (items.get(path), self.opts.max_size) {
+            (Some(item), Some(max)) => item.meta.size <= max,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+impl Drop for VfsCache {
+    /// Chunks stored since the last save survive a clean unmount.
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Last block fetched for a streaming reader, so the kernel's small reads
+/// are served from one larger provider request.
+#[derive(Debug, Default)]
+pub struct ReadAheadBuffer {
+    offset: u64,
+    data: Vec<u8>,
+    /// The provider returned less than asked: the buffer ends at EOF.
+    eof: bool,
+}
+
+impl ReadAheadBuffer {
+    /// The buffered bytes for `offset..offset + len`, if all are buffered.
+    /// Near the end of the file the result is cut short, as with a regular
+    /// read.
+    pub fn get(&self, offset: u64, len: u64) -> Option<&[u8]> {
+        if len == 0 || offset < self.offset {
+            return None;
+        }
+        let start = usize::try_from(offset - self.offset).ok()?;
+        let end = start.checked_add(len as usize)?;
+        match self.data.get(start..end) {
+            Some(data) => Some(data),
+            None if self.eof => self.data.get(start..),
+            None => None,
+        }
+    }
+
+    /// Remember `data`, fetched at `offset` for a request of `requested`
+    /// bytes.
+    pub fn fill(&mut self, offset: u64, data: Vec<u8>, requested: u64) {
+        self.offset = offset;
+        self.eof = (data.len() as u64) < requested;
+        self.data = data;
+    }
+}
+
+fn item_id(path: &str) -> String {
+    hex::encode(Sha256::digest(path.as_bytes()))
+}
+
+fn parent_of(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) => "/",
+        Some(i) => &path[..i],
+        None => "",
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn write_at(path: &Path, offset: u64, data: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts(mode: VfsCacheMode) -> VfsCacheOptions {
+        VfsCacheOptions {
+            mode,
+            chunk_size: 4,
+            write_back: Duration::ZERO,
+            ..VfsCacheOptions::default()
+        }
+    }
+
+    #[test]
+    fn mode_parses_and_round_trips() {
+        for mode in [VfsCacheMode::Off, VfsCacheMode::Writes, VfsCacheMode::Full] {
+            assert_eq!(VfsCacheMode::parse(mode.as_str()).unwrap(), mode);
+        }
+        assert_eq!(VfsCacheMode::parse(" FULL ").unwrap(), VfsCacheMode::Full);
+        assert!(VfsCacheMode::parse("minimal").is_err());
+        assert!(!VfsCacheMode::Off.caches_writes());
+        assert!(VfsCacheMode::Writes.caches_writes() && !VfsCacheMode::Writes.caches_reads());
+    }
+
+    #[test]
+    fn chunks_are_cached_and_refetched_after_remote_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VfsCache::open(dir.path(), opts(VfsCacheMode::Full)).unwrap();
+        cache.prepare("/f", 10, 100).unwrap();
+
+        assert_eq!(cache.missing_ranges("/f", 0, 10), vec![(0, 10)]);
+        assert_eq!(cache.read("/f", 0, 3).unwrap(), None);
+        cache.store("/f", 4, b"4567").unwrap();
+        assert_eq!(cache.missing_ranges("/f", 0, 10), vec![(0, 4), (8, 2)]);
+        assert_eq!(cache.read("/f", 5, 2).unwrap().unwrap(), b"56");
+        cache.store("/f", 8, b"89").unwrap();
+        assert_eq!(cache.read("/f", 8, 100).unwrap().unwrap(), b"89");
+        assert_eq!(cache.read("/f", 10, 4).unwrap().unwrap(), b"");
+
+        // Same fingerprint after a restart: still cached.
+        drop(cache);
+        let cache = VfsCache::open(dir.path(), opts(VfsCacheMode::Full)).unwrap();
+        cache.prepare("/f", 10, 100).unwrap();
+        assert_eq!(cache.missing_ranges("/f", 4, 6), Vec::<(u64, u64)>::new());
+
+        // Edited elsewhere: everything is fetched again.
+        cache.prepare("/f", 10, 200).unwrap();
+        assert_eq!(cache.missing_ranges("/f", 0, 10), vec![(0, 10)]);
+
+        // A provider without range reads hands over a whole download.
+        let download = dir.path().join("download");
+        fs::write(&download, b"ABCDEFGHIJK").unwrap();
+        cache.adopt("/f", &download).unwrap();
+        assert!(cache.is_complete("/f"));
+        assert_eq!(cache.read("/f", 9, 5).unwrap().unwrap(), b"JK");
+    }
+
+    #[test]
+    fn pending_uploads_survive_a_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VfsCache::open(dir.path(), opts(VfsCacheMode::Writes)).unwrap();
+        cache.create("/new.txt", true).unwrap();
+        cache.open_file("/new.txt", true);
+        cache.write("/new.txt", 0, b"hello").unwrap();
+        assert!(cache.due_uploads().is_empty(), "still open for writing");
+        assert_eq!(cache.dirty_children("/")[0].1, 5);
+        // No close: the process dies with the file open.
+        drop(cache);
+
+        let cache = VfsCache::open(dir.path(), opts(VfsCacheMode::Writes)).unwrap();
+        let due = cache.due_uploads();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].path, "/new.txt");
+        assert_eq!(fs::read(&due[0].local).unwrap(), b"hello");
+        assert!(cache.is_local_only("/new.txt"));
+
+        // A write racing the upload keeps the file queued.
+        cache.open_file("/new.txt", true);
+        cache.write("/new.txt", 5, b"!").unwrap();
+        assert!(!cache
+            .upload_finished("/new.txt", due[0].generation)
+            .unwrap());
+        cache.close_file("/new.txt", true).unwrap();
+        let again = cache.due_uploads();
+        assert_eq!(fs::read(&again[0].local).unwrap(), b"hello!");
+        assert!(cache
+            .upload_failed("/new.txt", again[0].generation)
+            .is_some());
+        assert!(cache.due_uploads().is_empty(), "backing off");
+        assert!(cache
+            .upload_finished("/new.txt", again[0].generation)
+            .unwrap());
+        assert!(cache.pending_uploads().is_empty());
+        assert!(!cache.is_local_only("/new.txt"));
+
+        // The uploaded copy stays readable until the remote reports it.
+        cache.prepare("/new.txt", 6, 1234).unwrap();
+        assert_eq!(cache.read("/new.txt", 0, 6).unwrap().unwrap(), b"hello!");
+    }
+
+    #[test]
+    fn usage_is_tracked_and_stored_chunks_are_saved_in_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VfsCache::open(dir.path(), opts(VfsCacheMode::Full)).unwrap();
+        let saved_chunks = || {
+            let bytes = fs::read(cache.meta_path("/f")).unwrap();
+            serde_json::from_slice::<ItemMeta>(&bytes)
+                .unwrap()
+                .chunks
+                .len()
+        };
+        cache.prepare("/f", 10, 100).unwrap();
+        cache.store("/f", 0, b"0123").unwrap();
+        cache.store("/f", 4, b"4567").unwrap();
+        assert_eq!(cache.usage(), 8);
+        assert_eq!(saved_chunks(), 0, "no sidecar write per chunk");
+        cache.flush().unwrap();
+        assert_eq!(saved_chunks(), 2);
+
+        cache.create("/g", true).unwrap();
+        cache.write("/g", 0, b"abc").unwrap();
+        assert_eq!(cache.usage(), 11);
+        cache.prepare("/f", 12, 200).unwrap();
+        assert_eq!(cache.usage(), 3);
+        cache.remove("/g");
+        assert_eq!(cache.usage(), 0);
+    }
+
+    #[test]
+    fn rename_moves_directory_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VfsCache::open(dir.path(), opts(VfsCacheMode::Writes)).unwrap();
+        cache.create("/a/x", true).unwrap();
+        cache.write("/a/x", 0, b"x").unwrap();
+        cache.prepare("/a/b/y", 2, 1).unwrap();
+        cache.store("/a/b/y", 0, b"yy").unwrap();
+        cache.prepare("/ab", 1, 1).unwrap();
+
+        cache.rename("/a", "/c").unwrap();
+        assert!(!cache.contains("/a/x") && !cache.contains("/a/b/y"));
+        assert!(cache.is_dirty("/c/x"));
+        assert_eq!(cache.read("/c/b/y", 0, 2).unwrap().unwrap(), b"yy");
+        assert!(cache.contains("/ab"), "sibling with a common prefix");
+        assert_eq!(cache.dirty_children("/c")[0].0, "/c/x");
+
+        drop(cache);
+        let cache = VfsCache::open(dir.path(), opts(VfsCacheMode::Writes)).unwrap();
+        assert_eq!(cache.pending_uploads()[0].path, "/c/x");
+        cache.remove("/c");
+        assert!(cache.pending_uploads().is_empty());
+        assert_eq!(fs::read_dir(dir.path().join(DATA_DIR)).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn eviction_is_lru_and_skips_open_and_dirty_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = VfsCache::open(dir.path(), opts(VfsCacheMode::Full)).unwrap();
+        for (i, name) in ["/old", "/mid", "/new"].iter().enumerate() {
+            cache.prepare(name, 4, 1).unwrap();
+            cache.store(name, 0, b"data").unwrap();
+            cache
+                .items
+                .lock()
+                .unwrap()
+                .get_mut(*name)
+                .unwrap()
+                .meta
+                .last_access = i as u64 + 1;
+        }
+        cache.open_file("/old", false);
+        cache.create("/dirty", true).unwrap();
+        cache.write("/dirty", 0, b"dirty").unwrap();
+
+        cache.opts.max_size = Some(8);
+        cache.evict().unwrap();
+        assert!(cache.contains("/old"), "open");
+        assert!(!cache.contains("/mid"));
+        assert!(!cache.contains("/new"));
+        assert!(cache.contains("/dirty"));
+        assert_eq!(cache.usage(), 9);
+
+        cache.close_file("/old", false).unwrap();
+        cache.evict().unwrap();
+        assert!(!cache.contains("/old"));
+
+        let mut o = opts(VfsCacheMode::Full);
+        o.max_age = Duration::ZERO;
+        let aged = VfsCache::open(dir.path(), o).unwrap();
+        aged.prepare("/stale", 4, 1).unwrap();
+        aged.items
+            .lock()
+            .unwrap()
+            .get_mut("/stale")
+            .unwrap()
+            .meta
+            .last_access = 0;
+        aged.evict().unwrap();
+        assert!(!aged.contains("/stale"));
+        assert!(aged.contains("/dirty"));
+    }
+
+    #[test]
+    fn read_ahead_buffer_serves_contained_ranges() {
+        let mut buf = ReadAheadBuffer::default();
+        assert!(buf.get(0, 1).is_none());
+        buf.fill(100, b"abcdef".to_vec(), 6);
+        assert_eq!(buf.get(102, 3).unwrap(), b"cde");
+        assert_eq!(buf.get(100, 6).unwrap(), b"abcdef");
+        assert!(buf.get(104, 3).is_none());
+        assert!(buf.get(99, 2).is_none());
+
+        // Short fetch: the file ends inside the buffer.
+        buf.fill(100, b"abcdef".to_vec(), 10);
+        assert_eq!(buf.get(104, 3).unwrap(), b"ef");
+        assert_eq!(buf.get(106, 3).unwrap(), b"");
+        assert!(buf.get(107, 1).is_none());
+    }
+}
@@ -0,0 +1,618 @@
+//! Peak-memory regression test for the aerorsync streaming single-file
+//! paths (`drive_upload_through_delta_streaming`,
+//! `drive_download_through_delta_streaming`).
+//!
+//! A synthetic "VM image" goes up and then comes back down through the
+//! native driver, against an in-process fake remote that generates its
+//! side of the protocol on the fly and throws away what the driver sends.
+//! A counting global allocator records the peak heap of each transfer:
+//! neither the image, its signatures nor its delta may ever be resident,
+//! so the peak must stay under `PEAK_HEAP_LIMIT` whatever the image size.
+//!
+//! The default image is 160 MiB, already larger than the limit, so the
+//! debug run stays short. Scale it to the multi-GB case with:
+//!
+//! ```bash
+//! AERORSYNC_MEMORY_TEST_BYTES=53687091200 \
+//!   cargo test --release --features aerorsync --test aerorsync_memory -- --nocapture
+//! ```
+
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (c) 2024-2026 axpnet: AI-assisted (see AI-TRANSPARENCY.md)
+
+#![cfg(feature = "aerorsync")]
+
+use async_trait::async_trait;
+use ftp_client_gui_lib::aerorsync::compression::{CompressionAlgo, LiteralEncoder};
+use ftp_client_gui_lib::aerorsync::engine_adapter::{
+    signature_block_size, BaselineSource, CurrentDeltaSyncBridge,
+};
+use ftp_client_gui_lib::aerorsync::events::EventSink;
+use ftp_client_gui_lib::aerorsync::mock::MockStream;
+use ftp_client_gui_lib::aerorsync::native_driver::AerorsyncDriver;
+use ftp_client_gui_lib::aerorsync::real_wire::{
+    encode_delta_end, encode_delta_op, encode_file_list_entry, encode_file_list_terminator,
+    encode_item_flags, encode_ndx, encode_server_preamble, encode_sum_block, encode_sum_head,
+    DeltaOp, DeltaStreamState, FileListDecodeOptions, FileListEntry, MuxHeader, MuxTag, NdxState,
+    ServerPreamble, SumBlock, SumHead,
+};
+use ftp_client_gui_lib::aerorsync::remote_command::RemoteCommandSpec;
+use ftp_client_gui_lib::aerorsync::transport::{
+    CancelHandle, RawByteStream, RawRemoteShellTransport, RemoteCommandOutput, RemoteExecRequest,
+    RemoteShellTransport, TransportProbe,
+};
+use ftp_client_gui_lib::aerorsync::types::AerorsyncError;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use xxhash_rust::xxh3::Xxh3Default;
+
+/// Heap the process may hold at any point of a transfer, test harness
+/// and fake remote included.
+const PEAK_HEAP_LIMIT: usize = 128 * 1024 * 1024;
+
+const DEFAULT_IMAGE_BYTES: u64 = 160 * 1024 * 1024;
+
+/// One block in `CHANGED_BLOCK_EVERY` differs between the two versions of
+/// the image, as if the guest had rewritten scattered sectors.
+const CHANGED_BLOCK_EVERY: u64 = 64;
+
+/// Payload size of the MSG_DATA frames the fake remote sends.
+const FAKE_FRAME_BYTES: usize = 1024 * 1024;
+
+// ---- peak-tracking allocator ----------------------------------------------
+
+struct PeakAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn grow(bytes: usize) {
+    let now = CURRENT.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    PEAK.fetch_max(now, Ordering::Relaxed);
+}
+
+fn shrink(bytes: usize) {
+    CURRENT.fetch_sub(bytes, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                grow(new_size - layout.size());
+            } else {
+                shrink(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAlloc = PeakAlloc;
+
+/// Restart peak tracking from the current heap.
+fn reset_peak() {
+    PEAK.store(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+// ---- synthetic image --------------------------------------------------------
+
+fn splitmix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Two versions of a pseudo-random image of `len` bytes, seen in blocks
+/// of `block` bytes: version 1 rewrites every `CHANGED_BLOCK_EVERY`-th
+/// block of version 0.
+#[derive(Clone, Copy)]
+struct Image {
+    len: u64,
+    block: u64,
+}
+
+impl Image {
+    fn changed(&self, block_idx: u64) -> bool {
+        block_idx % CHANGED_BLOCK_EVERY == CHANGED_BLOCK_EVERY / 2
+    }
+
+    /// Fill `buf` with the bytes of `version` starting at `offset`.
+    fn fill(&self, version: u8, mut offset: u64, buf: &mut [u8]) {
+        let mut at = 0;
+        while at < buf.len() {
+            let block_idx = offset / self.block;
+            let seed = if version == 1 && self.changed(block_idx) {
+                0xC0FF_EE00_0000_0000
+            } else {
+                0
+            };
+            let block_end = ((block_idx + 1) * self.block).min(self.len);
+            let span = ((block_end - offset) as usize).min(buf.len() - at);
+            let mut word = splitmix(seed ^ (offset / 8)).to_le_bytes();
+            for (pos, byte) in (offset..).zip(&mut buf[at..at + span]) {
+                if pos & 7 == 0 {
+                    word = splitmix(seed ^ (pos / 8)).to_le_bytes();
+                }
+                *byte = word[(pos & 7) as usize];
+            }
+            at += span;
+            offset += span as u64;
+        }
+    }
+}
+
+/// Version 1 of the image as an `AsyncRead`: the upload source.
+struct ImageReader {
+    image: Image,
+    pos: u64,
+}
+
+impl AsyncRead for ImageReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let n = (buf.remaining() as u64).min(self.image.len - self.pos) as usize;
+        let pos = self.pos;
+        self.image.fill(1, pos, buf.initialize_unfilled_to(n));
+        buf.advance(n);
+        self.pos += n as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Version 0 of the image as the download basis.
+struct ImageBaseline {
+    image: Image,
+}
+
+#[async_trait]
+impl BaselineSource for ImageBaseline {
+    fn len(&self) -> u64 {
+        self.image.len
+    }
+
+    async fn read_block(&mut self, block_idx: u32, block_size: u32) -> std::io::Result<Vec<u8>> {
+        let offset = u64::from(block_idx) * u64::from(block_size);
+        if offset > self.image.len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "block past the end of the image",
+            ));
+        }
+        let len = u64::from(block_size).min(self.image.len - offset) as usize;
+        let mut out = vec![0u8; len];
+        self.image.fill(0, offset, &mut out);
+        Ok(out)
+    }
+}
+
+/// Download sink: hashes what the driver writes and keeps nothing.
+#[derive(Default)]
+struct HashingWriter {
+    hasher: Xxh3Default,
+    written: u64,
+}
+
+impl AsyncWrite for HashingWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.hasher.update(buf);
+        self.written += buf.len() as u64;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+// ---- fake remote ----------------------------------------------------------
+
+fn mux_frame(payload: &[u8]) -> Vec<u8> {
+    let header = MuxHeader {
+        tag: MuxTag::Data,
+        length: payload.len() as u32,
+    };
+    let mut out = header.encode().to_vec();
+    out.extend_from_slice(payload);
+    out
+}
+
+fn server_preamble() -> Vec<u8> {
+    encode_server_preamble(&ServerPreamble {
+        protocol_version: 31,
+        compat_flags: 0x07,
+        checksum_algos: "xxh128 xxh3 xxh64 md5 md4 sha1 none".to_string(),
+        compression_algos: "zstd lz4 zlibx zlib none".to_string(),
+        checksum_seed: 0xDEAD_BEEF,
+        consumed: 0,
+    })
+}
+
+fn image_entry(len: u64) -> FileListEntry {
+    // XMIT_LONG_NAME | XMIT_SAME_MODE | XMIT_SAME_TIME | XMIT_SAME_UID |
+    // XMIT_SAME_GID: only the name and size go on the wire.
+    FileListEntry {
+        flags: 0x0040 | 0x0002 | 0x0080 | 0x0008 | 0x0010,
+        path: "disk.img".to_string(),
+        size: len as i64,
+        mtime: 0,
+        mtime_nsec: None,
+        mode: 0,
+        uid: None,
+        uid_name: None,
+        gid: None,
+        gid_name: None,
+        checksum: vec![0; 16],
+        link_target: None,
+        hardlink: None,
+        acl: None,
+        xattrs: None,
+    }
+}
+
+/// What the fake remote sends once the preamble is out.
+enum Script {
+    /// Upload: the generator's request, with `count` made-up sum blocks
+    /// that never match, so the whole image goes out as literals.
+    Signatures {
+        head: SumHead,
+        next: u32,
+        header_sent: bool,
+    },
+    /// Download: file list, then the delta from version 0 to version 1
+    /// of the image and the xxh128 trailer.
+    Delta {
+        image: Image,
+        file_list_sent: bool,
+        next_block: u64,
+        state: DeltaStreamState,
+        encoder: LiteralEncoder,
+        hasher: Box<Xxh3Default>,
+        digest: Arc<Mutex<Option<u128>>>,
+        finished: bool,
+    },
+}
+
+struct FakeRemote {
+    inbound: VecDeque<u8>,
+    script: Script,
+    /// What the driver sent, counted and dropped.
+    outbound_bytes: Arc<AtomicUsize>,
+}
+
+impl FakeRemote {
+    fn new(script: Script, outbound_bytes: Arc<AtomicUsize>) -> Self {
+        Self {
+            inbound: server_preamble().into(),
+            script,
+            outbound_bytes,
+        }
+    }
+
+    /// Generate about one frame more of inbound bytes, if any are left.
+    fn refill(&mut self) {
+        let mut payload = Vec::new();
+        match &mut self.script {
+            Script::Signatures {
+                head,
+                next,
+                header_sent,
+            } => {
+                if !*header_sent {
+                    payload.extend_from_slice(&encode_ndx(1, &mut NdxState::new()));
+                    payload.extend_from_slice(&encode_item_flags(0x8002));
+                    payload.extend_from_slice(&encode_sum_head(head));
+                    *header_sent = true;
+                }
+                while (*next as i32) < head.count && payload.len() < FAKE_FRAME_BYTES {
+                    let noise = splitmix(u64::from(*next) ^ 0x5EED);
+                    let block = SumBlock {
+                        rolling: noise as u32,
+                        strong: noise.to_le_bytes().repeat(3)[..head.checksum_length as usize]
+                            .to_vec(),
+                    };
+                    payload.extend_from_slice(&encode_sum_block(&block));
+                    *next += 1;
+                }
+            }
+            Script::Delta {
+                image,
+                file_list_sent,
+                next_block,
+                state,
+                encoder,
+                hasher,
+                digest,
+                finished,
+            } => {
+                if !*file_list_sent {
+                    let opts = FileListDecodeOptions {
+                        protocol: 31,
+                        xfer_flags_as_varint: true,
+                        always_checksum: true,
+                        csum_len: 16,
+                        preserve_uid: true,
+                        preserve_gid: true,
+                        previous_name: None,
+                        preserve_links: true,
+                        preserve_hard_links: false,
+                        preserve_acls: false,
+                        preserve_xattrs: false,
+                        scope: None,
+                    };
+                    self.inbound.extend(mux_frame(&encode_file_list_entry(
+                        &image_entry(image.len),
+                        &opts,
+                    )));
+                    self.inbound
+                        .extend(mux_frame(&encode_file_list_terminator(&opts)));
+                    *file_list_sent = true;
+                    return;
+                }
+                if *finished {
+                    return;
+                }
+                let blocks = image.len.div_ceil(image.block);
+                let mut data = Vec::new();
+                while *next_block < blocks && payload.len() < FAKE_FRAME_BYTES {
+                    let start = *next_block * image.block;
+                    let len = (image.block.min(image.len - start)) as usize;
+                    data.resize(len, 0);
+                    image.fill(1, start, &mut data);
+                    hasher.update(&data);
+                    if image.changed(*next_block) {
+                        for compressed_payload in encoder.encode_literal(&data, true).unwrap() {
+                            let op = DeltaOp::Literal { compressed_payload };
+                            payload.extend_from_slice(&encode_delta_op(&op, state));
+                        }
+                    } else {
+                        let op = DeltaOp::CopyRun {
+                            start_token_index: *next_block as i32,
+                            run_length: 1,
+                        };
+                        payload.extend_from_slice(&encode_delta_op(&op, state));
+                    }
+                    *next_block += 1;
+                }
+                if *next_block == blocks {
+                    let sum = hasher.digest128();
+                    *digest.lock().unwrap() = Some(sum);
+                    payload.extend_from_slice(encode_delta_end(state.format()));
+                    payload.extend_from_slice(&sum.to_le_bytes());
+                    *finished = true;
+                }
+            }
+        }
+        if !payload.is_empty() {
+            self.inbound.extend(mux_frame(&payload));
+        }
+    }
+}
+
+#[async_trait]
+impl RawByteStream for FakeRemote {
+    async fn read_bytes(&mut self, max: usize) -> Result<Vec<u8>, AerorsyncError> {
+        if self.inbound.is_empty() {
+            self.refill();
+        }
+        let n = max.min(self.inbound.len());
+        Ok(self.inbound.drain(..n).collect())
+    }
+
+    async fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), AerorsyncError> {
+        self.outbound_bytes
+            .fetch_add(bytes.len(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<(), AerorsyncError> {
+        Ok(())
+    }
+}
+
+struct FakeTransport {
+    remote: Mutex<Option<FakeRemote>>,
+}
+
+#[async_trait]
+impl RemoteShellTransport for FakeTransport {
+    type Stream = MockStream;
+
+    async fn probe(&self) -> Result<TransportProbe, AerorsyncError> {
+        Err(AerorsyncError::transport("fake remote: raw streams only"))
+    }
+
+    async fn exec(
+        &self,
+        _request: RemoteExecRequest,
+    ) -> Result<RemoteCommandOutput, AerorsyncError> {
+        Err(AerorsyncError::transport("fake remote: raw streams only"))
+    }
+
+    async fn open_stream(
+        &self,
+        _request: RemoteExecRequest,
+    ) -> Result<Self::Stream, AerorsyncError> {
+        Err(AerorsyncError::transport("fake remote: raw streams only"))
+    }
+
+    async fn cancel(&self) -> Result<(), AerorsyncError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RawRemoteShellTransport for FakeTransport {
+    type RawStream = FakeRemote;
+
+    async fn open_raw_stream(
+        &self,
+        _request: RemoteExecRequest,
+    ) -> Result<Self::RawStream, AerorsyncError> {
+        self.remote
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| AerorsyncError::transport("fake remote already opened"))
+    }
+}
+
+struct Discard;
+impl EventSink for Discard {}
+
+fn driver_for(script: Script, outbound_bytes: Arc<AtomicUsize>) -> AerorsyncDriver<FakeTransport> {
+    let transport = FakeTransport {
+        remote: Mutex::new(Some(FakeRemote::new(script, outbound_bytes))),
+    };
+    AerorsyncDriver::new(transport, CancelHandle::inert())
+}
+
+// ---- the test ----------------------------------------------------------------
+
+/// Upload, then download, a synthetic VM image under the heap limit. One
+/// test function on purpose: the allocator is process-wide, so nothing
+/// else may run while a peak is being measured.
+#[tokio::test]
+async fn streaming_transfers_of_a_large_image_stay_under_the_heap_limit() {
+    let len = std::env::var("AERORSYNC_MEMORY_TEST_BYTES")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_IMAGE_BYTES);
+    let adapter = CurrentDeltaSyncBridge::new();
+    let block = signature_block_size(&adapter, len) as u64;
+    let image = Image { len, block };
+
+    // Upload: the remote copy is version 0, signed with the block size a
+    // receiver would pick; the driver streams version 1 against it.
+    let count = len.div_ceil(block);
+    let head = SumHead {
+        count: count as i32,
+        block_length: block as i32,
+        checksum_length: 16,
+        remainder_length: (len % block) as i32,
+    };
+    let sent = Arc::new(AtomicUsize::new(0));
+    let mut driver = driver_for(
+        Script::Signatures {
+            head,
+            next: 0,
+            header_sent: false,
+        },
+        sent.clone(),
+    );
+    reset_peak();
+    driver
+        .drive_upload_through_delta_streaming(
+            RemoteCommandSpec::upload("/remote/disk.img"),
+            image_entry(len),
+            ImageReader { image, pos: 0 },
+            len,
+            &adapter,
+            &mut Discard,
+        )
+        .await
+        .expect("streaming upload completes");
+    let upload_peak = PEAK.load(Ordering::Relaxed);
+    drop(driver);
+    assert!(
+        sent.load(Ordering::Relaxed) as u64 > len,
+        "the whole image must have gone out as literals"
+    );
+
+    // Download: the local copy is version 0 and the remote sends the
+    // delta to version 1.
+    let digest = Arc::new(Mutex::new(None));
+    let mut driver = driver_for(
+        Script::Delta {
+            image,
+            file_list_sent: false,
+            next_block: 0,
+            state: DeltaStreamState::new(),
+            encoder: LiteralEncoder::new(CompressionAlgo::Zstd, None).unwrap(),
+            hasher: Box::new(Xxh3Default::new()),
+            digest: digest.clone(),
+            finished: false,
+        },
+        Arc::new(AtomicUsize::new(0)),
+    );
+    let mut baseline = ImageBaseline { image };
+    let mut writer = HashingWriter::default();
+    reset_peak();
+    driver
+        .drive_download_through_delta_streaming(
+            RemoteCommandSpec::download("/remote/disk.img"),
+            &mut baseline,
+            &mut writer,
+            &adapter,
+            &mut Discard,
+        )
+        .await
+        .expect("streaming download completes");
+    let download_peak = PEAK.load(Ordering::Relaxed);
+    let expected = digest.lock().unwrap().expect("remote sent the whole delta");
+    assert_eq!(writer.written, len);
+    assert_eq!(
+        writer.hasher.digest128(),
+        expected,
+        "reconstructed image must be version 1"
+    );
+    assert_eq!(
+        driver.sent_sum_head().map(|h| h.count as u64),
+        Some(count),
+        "the driver must have signed the whole basis"
+    );
+
+    eprintln!(
+        "aerorsync_memory: {len} byte image, peak heap upload {} MiB, download {} MiB",
+        upload_peak >> 20,
+        download_peak >> 20
+    );
+    assert!(
+        upload_peak < PEAK_HEAP_LIMIT,
+        "upload peak heap {upload_peak} bytes over the {PEAK_HEAP_LIMIT} byte limit"
+    );
+    assert!(
+        download_peak < PEAK_HEAP_LIMIT,
+        "download peak heap {download_peak} bytes over the {PEAK_HEAP_LIMIT} byte limit"
+    );
+}
@@ -30,6 +30,7 @@ use ftp_client_gui_lib::sync::{
     sync_tree_core, ConflictMode, DeltaPolicy, NoopProgressSink, SyncDirection, SyncOptions,
 };
 use ftp_client_gui_lib::sync_core::ScanOptions;
+use ftp_client_gui_lib::sync_versioning::VersioningStrategy;
 use secrecy::SecretString;
 use std::path::{Path, PathBuf};
 use std::process::Command as StdCommand;
@@ -408,6 +409,7 @@ async fn product_path_uses_delta_when_session_is_eligible() {
         delete_orphans: false,
         conflict_mode: ConflictMode::Larger,
         scan: ScanOptions::default(),
+        remote_versioning: VersioningStrategy::Disabled,
     };
     let mut sink = NoopProgressSink;
 
@@ -499,6 +501,7 @@ async fn product_path_falls_through_silently_when_session_not_eligible() {
         delete_orphans: false,
         conflict_mode: ConflictMode::Larger,
         scan: ScanOptions::default(),
+        remote_versioning: VersioningStrategy::Disabled,
     };
     let mut sink = NoopProgressSink;
 
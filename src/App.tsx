@@ -9127,6 +9127,7 @@ interface UpdateVerificationInfo {
           remotePath={currentRemotePath}
           isConnected={isConnected}
           protocol={connectionParams.protocol || sessions.find(s => s.id === activeSessionId)?.connectionParams?.protocol}
+          serverProfile={sessions.find(s => s.id === activeSessionId)?.savedServerId}
           onSyncComplete={async () => {
             await loadRemoteFiles();
             await loadLocalFiles(currentLocalPath);
@@ -37,6 +37,10 @@ interface MountConfig {
     allow_other: boolean;
     auto_start: boolean;
     created_at: string;
+    vfs_cache_mode?: 'off' | 'writes' | 'full';
+    vfs_cache_max_size?: string | null;
+    vfs_cache_max_age?: string | null;
+    cache_dir?: string | null;
 }
 
 interface MountStatus {
@@ -23,9 +23,20 @@ interface SyncTemplateDialogProps {
     remotePath: string;
     profileId: string;
     excludePatterns: string[];
+    /** Saved server profile id of the active session, if it came from one */
+    serverProfile?: string;
+    conflictStrategy?: string;
+    versioningStrategy?: string;
 }
 
-type ExportFormat = 'aerosync' | 'bash' | 'pwsh';
+type ExportFormat = 'aerosync' | 'bash' | 'pwsh' | 'job';
+
+/** Result of `export_sync_job_file_cmd`. */
+interface SyncJobExport {
+    content: string;
+    /** The panel's local versioning has no job file equivalent. */
+    versioning_left_out: boolean;
+}
 
 function detectDefaultScriptFormat(): ExportFormat {
     if (typeof navigator !== 'undefined') {
@@ -42,6 +53,9 @@ export const SyncTemplateDialog: React.FC<SyncTemplateDialogProps> = ({
     remotePath,
     profileId,
     excludePatterns,
+    serverProfile,
+    conflictStrategy,
+    versioningStrategy,
 }) => {
     const t = useTranslation();
     const [mode, setMode] = useState<'export' | 'import'>('export');
@@ -118,12 +132,47 @@ export const SyncTemplateDialog: React.FC<SyncTemplateDialogProps> = ({
         return true;
     };
 
+    const exportJobFile = async () => {
+        const filePath = await save({
+            defaultPath: 'sync-job.toml',
+            filters: [{ name: 'AeroFTP sync job', extensions: ['toml', 'yaml', 'yml'] }],
+        });
+        if (!filePath) return false;
+        const lower = filePath.toLowerCase();
+        const exported = await invoke<SyncJobExport>('export_sync_job_file_cmd', {
+            args: {
+                profile_id: profileId,
+                server_profile: serverProfile || null,
+                name: templateName,
+                description: templateDesc,
+                local_path: localPath,
+                remote_path: remotePath,
+                exclude_patterns: excludePatterns,
+                conflict_strategy: conflictStrategy || null,
+                versioning_strategy: versioningStrategy || null,
+                format: lower.endsWith('.yaml') || lower.endsWith('.yml') ? 'yaml' : 'toml',
+            },
+        });
+        await writeTextFile(filePath, exported.content);
+        setResult({
+            success: true,
+            message: t(
+                exported.versioning_left_out
+                    ? 'syncPanel.templateJobVersioningLeftOut'
+                    : 'syncPanel.templateJobExportedToast',
+            ),
+        });
+        return true;
+    };
+
     const handleExport = async () => {
         setExporting(true);
         setResult(null);
         try {
             if (exportFormat === 'aerosync') {
                 await exportTemplate();
+            } else if (exportFormat === 'job') {
+                await exportJobFile();
             } else {
                 await exportScript(exportFormat);
             }
@@ -189,13 +238,15 @@ export const SyncTemplateDialog: React.FC<SyncTemplateDialogProps> = ({
     const exportButtonLabel =
         exportFormat === 'aerosync'
             ? t('syncPanel.templateExport')
-            : exportFormat === 'bash'
-                ? t('syncPanel.templateFormatBash')
-                : t('syncPanel.templateFormatPwsh');
+            : exportFormat === 'job'
+                ? t('syncPanel.templateFormatJob')
+                : exportFormat === 'bash'
+                    ? t('syncPanel.templateFormatBash')
+                    : t('syncPanel.templateFormatPwsh');
 
     const formatRadio = (value: ExportFormat, label: string, hint?: string) => {
         const active = exportFormat === value;
-        const isDefault = value !== 'aerosync' && value === defaultScriptFormat;
+        const isDefault = (value === 'bash' || value === 'pwsh') && value === defaultScriptFormat;
         return (
             <button
                 type="button"
@@ -273,7 +324,9 @@ export const SyncTemplateDialog: React.FC<SyncTemplateDialogProps> = ({
                             <p className="text-xs text-gray-400 text-center">
                                 {exportFormat === 'aerosync'
                                     ? t('syncPanel.templateExportDesc')
-                                    : `aeroftp-cli sync wrapper (${exportFormat === 'pwsh' ? 'PowerShell' : 'bash'})`}
+                                    : exportFormat === 'job'
+                                        ? 'aeroftp-cli sync --job'
+                                        : `aeroftp-cli sync wrapper (${exportFormat === 'pwsh' ? 'PowerShell' : 'bash'})`}
                             </p>
                             <input
                                 type="text"
@@ -297,6 +350,7 @@ export const SyncTemplateDialog: React.FC<SyncTemplateDialogProps> = ({
                                     {formatRadio('aerosync', t('syncPanel.templateFormatAerosync') || '.aerosync template')}
                                     {formatRadio('bash', t('syncPanel.templateFormatBash') || 'Bash script (.sh)')}
                                     {formatRadio('pwsh', t('syncPanel.templateFormatPwsh') || 'PowerShell script (.ps1)')}
+                                    {formatRadio('job', t('syncPanel.templateFormatJob') || 'Job file (.toml / .yaml)')}
                                 </div>
                             </div>
                             <div className="text-center pt-1">
@@ -113,6 +113,8 @@ interface SyncPanelProps {
   remotePath: string;
   isConnected: boolean;
   protocol?: ProviderType;
+  /** Saved server profile id of the active session, if it came from one */
+  serverProfile?: string;
   onSyncComplete?: () => void;
 }
 
@@ -224,6 +226,7 @@ export const SyncPanel: React.FC<SyncPanelProps> = ({
   remotePath,
   isConnected,
   protocol,
+  serverProfile,
   onSyncComplete,
 }) => {
   const t = useTranslation();
@@ -3553,6 +3556,9 @@ export const SyncPanel: React.FC<SyncPanelProps> = ({
         remotePath={editRemotePath}
         profileId={activeProfileId}
         excludePatterns={options.exclude_patterns}
+        serverProfile={serverProfile}
+        conflictStrategy={options.conflict_strategy}
+        versioningStrategy={options.versioning_strategy}
       />
       <RollbackDialog
         isOpen={showRollback}
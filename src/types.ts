@@ -490,6 +490,24 @@ export interface FileInfo {
   modified: string | null;
   is_dir: boolean;
   checksum: string | null;
+  /** Mode, owner and xattrs, when the compare asked for them */
+  meta?: FileMeta;
+}
+
+/** POSIX metadata of a sync entry (xattr values are raw bytes) */
+export interface FileMeta {
+  mode?: number;
+  uid?: number;
+  gid?: number;
+  xattrs?: Record<string, number[]>;
+}
+
+/** Metadata to apply when the content already matches */
+export interface MetaDiff {
+  mode?: number;
+  uid?: number;
+  gid?: number;
+  xattrs?: Record<string, number[]>;
 }
 
 export interface FileComparison {
@@ -501,6 +519,8 @@ export interface FileComparison {
   sync_reason: string;
   /** True if this file existed in a previous sync index (for bisync delete detection) */
   previously_synced?: boolean;
+  /** Permission/owner/xattr changes for an otherwise identical entry */
+  meta_diff?: MetaDiff;
 }
 
 export type ConflictStrategy =
@@ -523,6 +543,12 @@ export interface CompareOptions {
   max_size?: number;
   min_age_secs?: number;
   max_age_secs?: number;
+  /** Preserve permissions, owner and xattrs (CompareOptions.metadata) */
+  metadata?: {
+    perms?: boolean;
+    owner?: boolean;
+    xattrs?: boolean;
+  };
   versioning_strategy?: "disabled" | "trash_can" | "simple" | "staggered";
   /** Bandwidth schedule preset: off = manual limits, office = throttle 08-18, night = throttle 18-08 */
   bw_schedule?: "off" | "office" | "night";